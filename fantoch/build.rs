@@ -0,0 +1,11 @@
+include!("build/message_fingerprint.rs");
+
+fn main() {
+    // generate the fingerprint of the `Basic` protocol messages
+    generate_message_fingerprints("src/protocol");
+
+    // generate the gRPC client protocol
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/fantoch.proto")
+        .expect("gRPC protocol definition should compile");
+}
@@ -0,0 +1,76 @@
+// Generation of the schema fingerprints of the protocols' `Message` enums.
+// This file is included by the build scripts of the crates that define
+// protocols (and so it can't depend on anything but `std`).
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// name of the file (in `OUT_DIR`) with the generated fingerprints
+const MESSAGE_FINGERPRINTS_FILE: &str = "message_fingerprints.rs";
+
+/// Generates, for each file in `protocol_dir` that defines a `Message` enum, a
+/// constant (named after the file) with the fingerprint of that definition.
+/// Comments and whitespace don't change the fingerprint.
+fn generate_message_fingerprints(protocol_dir: &str) {
+    println!("cargo:rerun-if-changed={}", protocol_dir);
+    let mut entries: Vec<_> = fs::read_dir(protocol_dir)
+        .expect("protocol directory should exist")
+        .map(|entry| entry.expect("protocol directory entry should exist"))
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+        .collect();
+    // sort entries so that the generated file doesn't depend on the order
+    // they're read
+    entries.sort();
+
+    let mut generated = String::new();
+    for path in entries {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let source = fs::read_to_string(&path)
+            .expect("protocol file should be readable");
+        if let Some(definition) = message_definition(&source) {
+            let name = path
+                .file_stem()
+                .expect("protocol file should have a name")
+                .to_string_lossy()
+                .to_uppercase();
+            generated.push_str(&format!(
+                "pub const {}: u64 = {:#018x};\n",
+                name,
+                fnv1a(definition.as_bytes())
+            ));
+        }
+    }
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR should be set");
+    fs::write(
+        Path::new(&out_dir).join(MESSAGE_FINGERPRINTS_FILE),
+        generated,
+    )
+    .expect("message fingerprints should be written");
+}
+
+// Returns the definition of the `Message` enum in `source` (if any), without
+// comments and whitespace.
+fn message_definition(source: &str) -> Option<String> {
+    let mut lines = source
+        .lines()
+        .skip_while(|line| !line.starts_with("pub enum Message {"));
+    let first = lines.next()?;
+    let definition = std::iter::once(first)
+        .chain(lines.take_while(|line| *line != "}"))
+        .flat_map(|line| {
+            let code = line.find("//").map_or(line, |comment| &line[..comment]);
+            code.chars().filter(|c| !c.is_whitespace())
+        })
+        .collect();
+    Some(definition)
+}
+
+// 64-bit FNV-1a, which is stable across builds (unlike `DefaultHasher`).
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
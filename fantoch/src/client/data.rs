@@ -1,3 +1,8 @@
+use super::skew::KeySkew;
+use crate::command::CommandTrace;
+use crate::id::{ProcessId, Rifl, ShardId};
+use crate::kvs::Key;
+use crate::time::ClockDrift;
 use crate::HashMap;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -8,6 +13,48 @@ pub struct ClientData {
     // - mapping from operation end time to all latencies registered at that
     //   end time
     data: HashMap<u64, Vec<Duration>>,
+    // mapping from number of keys accessed by a command to the number of
+    // commands issued that access that many keys (unlike the above, this is
+    // not affected by `prune`)
+    #[serde(default)]
+    key_counts: HashMap<usize, u64>,
+    // time it took each client to connect (and say hi) to all the processes it
+    // submits commands to
+    #[serde(default)]
+    connect_latencies: Vec<Duration>,
+    // time since clients started until they were all connected (when merging,
+    // the highest is kept)
+    #[serde(default)]
+    setup_time: Option<Duration>,
+    // whether clients were shut down before finishing their workload (when
+    // merging, the data is partial if any of the merged data is partial)
+    #[serde(default)]
+    partial: bool,
+    // mapping from operation end time to all latencies, corrected for
+    // coordinated omission, registered at that end time; this is only
+    // recorded by open-loop clients, where each command has an intended start
+    // time (see `Pending::start_scheduled`)
+    #[serde(default)]
+    corrected_data: HashMap<u64, Vec<Duration>>,
+    // most accessed keys, used to estimate the skew of the workload actually
+    // issued (unlike `data`, this is not affected by `prune`)
+    #[serde(default)]
+    key_skew: KeySkew,
+    // mapping from each second to the number of commands that completed in
+    // that second and the number of commands that timed out in that second
+    // (see `Pending::time_out`); commands that complete after timing out are
+    // not counted as completed
+    #[serde(default)]
+    availability: HashMap<u64, (u64, u64)>,
+    // round-trip times measured to each candidate process when clients detect
+    // the closest process on each shard (empty if the processes to connect to
+    // were given)
+    #[serde(default)]
+    probes: Vec<ProcessProbe>,
+    // drift of the wall-clock of each client machine with respect to the
+    // clock used to measure latencies (see `MonotonicTime::drift`)
+    #[serde(default)]
+    clock_drifts: Vec<ClockDrift>,
 }
 
 impl ClientData {
@@ -18,7 +65,23 @@ impl ClientData {
 
     /// Merges two histograms.
     pub fn merge(&mut self, other: &Self) {
-        data_merge(&mut self.data, &other.data)
+        data_merge(&mut self.data, &other.data);
+        for (key_count, commands) in other.key_counts.iter() {
+            *self.key_counts.entry(*key_count).or_default() += commands;
+        }
+        self.connect_latencies
+            .extend(other.connect_latencies.iter().cloned());
+        self.setup_time = self.setup_time.max(other.setup_time);
+        self.partial |= other.partial;
+        data_merge(&mut self.corrected_data, &other.corrected_data);
+        self.key_skew.merge(&other.key_skew);
+        for (second, (successes, timeouts)) in other.availability.iter() {
+            let current = self.availability.entry(*second).or_default();
+            current.0 += successes;
+            current.1 += timeouts;
+        }
+        self.probes.extend(other.probes.iter().cloned());
+        self.clock_drifts.extend(other.clock_drifts.iter().cloned());
     }
 
     /// Records a more mata.
@@ -27,10 +90,128 @@ impl ClientData {
         latencies.push(latency);
     }
 
+    /// Records the latency of an operation corrected for coordinated omission,
+    /// i.e. measured from the time the operation was intended to start.
+    pub fn record_corrected(&mut self, latency: Duration, end_time: u64) {
+        let latencies =
+            self.corrected_data.entry(end_time).or_insert_with(Vec::new);
+        latencies.push(latency);
+    }
+
+    /// Records that a command completed (without timing out) at `end_time`
+    /// (in millis).
+    pub fn record_success(&mut self, end_time: u64) {
+        self.availability.entry(end_time / 1000).or_default().0 += 1;
+    }
+
+    /// Records that `count` commands timed out at `time` (in millis).
+    pub fn record_timeouts(&mut self, count: u64, time: u64) {
+        if count > 0 {
+            self.availability.entry(time / 1000).or_default().1 += count;
+        }
+    }
+
+    /// Returns, for each second, the number of commands that completed and the
+    /// number of commands that timed out in that second.
+    pub fn availability_data(
+        &self,
+    ) -> impl Iterator<Item = (u64, (u64, u64))> + '_ {
+        self.availability
+            .iter()
+            .map(|(second, counts)| (*second, *counts))
+    }
+
+    /// Records that a command accessing `key_count` keys was issued.
+    pub fn record_key_count(&mut self, key_count: usize) {
+        *self.key_counts.entry(key_count).or_default() += 1;
+    }
+
+    /// Returns the number of commands issued per number of keys accessed.
+    pub fn key_count_data(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.key_counts
+            .iter()
+            .map(|(key_count, commands)| (*key_count, *commands))
+    }
+
+    /// Records that a command accessing `key` was issued.
+    pub fn record_key_access(&mut self, key: &Key) {
+        self.key_skew.record(key);
+    }
+
+    /// Returns the estimator of the key-access skew of the commands issued.
+    pub fn key_skew(&self) -> &KeySkew {
+        &self.key_skew
+    }
+
+    /// Records that a client took `connect_latency` to connect to all the
+    /// processes, and that it was connected `setup_time` after clients
+    /// started.
+    pub fn record_connected(
+        &mut self,
+        connect_latency: Duration,
+        setup_time: Duration,
+    ) {
+        self.connect_latencies.push(connect_latency);
+        self.setup_time = self.setup_time.max(Some(setup_time));
+    }
+
+    /// Returns the time it took each client to connect to all the processes.
+    pub fn connect_latency_data(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.connect_latencies.iter().cloned()
+    }
+
+    /// Returns the time since clients started until they were all connected
+    /// (if known).
+    pub fn setup_time(&self) -> Option<Duration> {
+        self.setup_time
+    }
+
+    /// Records the round-trip times measured to each candidate process when
+    /// detecting the closest process on each shard.
+    pub fn record_probes(&mut self, probes: Vec<ProcessProbe>) {
+        self.probes.extend(probes);
+    }
+
+    /// Returns the round-trip times measured to each candidate process when
+    /// detecting the closest process on each shard (if detected).
+    pub fn probes(&self) -> &[ProcessProbe] {
+        &self.probes
+    }
+
+    /// Records the drift of the wall-clock of a client machine (measured once
+    /// its clients ended).
+    pub fn record_clock_drift(&mut self, drift: ClockDrift) {
+        self.clock_drifts.push(drift);
+    }
+
+    /// Returns the drift of the wall-clock of each client machine.
+    pub fn clock_drifts(&self) -> &[ClockDrift] {
+        &self.clock_drifts
+    }
+
+    /// Marks this data as partial, i.e. clients were shut down before
+    /// finishing their workload.
+    pub fn set_partial(&mut self) {
+        self.partial = true;
+    }
+
+    /// Checks whether clients were shut down before finishing their workload.
+    pub fn partial(&self) -> bool {
+        self.partial
+    }
+
     pub fn latency_data(&self) -> impl Iterator<Item = Duration> + '_ {
         self.data.values().flat_map(|v| v.iter()).cloned()
     }
 
+    /// Returns the latencies corrected for coordinated omission (empty unless
+    /// recorded by open-loop clients).
+    pub fn corrected_latency_data(
+        &self,
+    ) -> impl Iterator<Item = Duration> + '_ {
+        self.corrected_data.values().flat_map(|v| v.iter()).cloned()
+    }
+
     pub fn throughput_data(&self) -> impl Iterator<Item = (u64, usize)> + '_ {
         self.data
             .iter()
@@ -70,11 +251,56 @@ impl ClientData {
 
     /// Prune events that are before `start` or after `end`.
     pub fn prune(&mut self, start: u64, end: u64) {
-        self.data.retain(|&time, _| {
-            // retain if within the given bounds
-            time >= start && time <= end
-        })
+        // retain if within the given bounds
+        let within =
+            |&time: &u64, _: &mut Vec<Duration>| time >= start && time <= end;
+        self.data.retain(within);
+        self.corrected_data.retain(within);
+        self.availability.retain(|&second, _| {
+            second >= start / 1000 && second <= end / 1000
+        });
+    }
+}
+
+/// Round-trip times measured by clients to a candidate process, and whether
+/// the process was selected as the closest on its shard.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProcessProbe {
+    pub process_id: ProcessId,
+    pub shard_id: ShardId,
+    pub rtts: Vec<Duration>,
+    pub selected: bool,
+}
+
+impl ProcessProbe {
+    pub fn new(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        rtts: Vec<Duration>,
+    ) -> Self {
+        Self {
+            process_id,
+            shard_id,
+            rtts,
+            selected: false,
+        }
     }
+
+    /// Returns the median of the round-trip times measured.
+    pub fn median_rtt(&self) -> Duration {
+        let mut rtts = self.rtts.clone();
+        rtts.sort();
+        rtts.get(rtts.len() / 2).cloned().unwrap_or_default()
+    }
+}
+
+/// Trace of a sampled command (see `Config::trace_sample_rate`), together with
+/// the latency observed by the client that issued it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClientTrace {
+    pub rifl: Rifl,
+    pub latency: Duration,
+    pub trace: CommandTrace,
 }
 
 pub fn data_merge<V>(
@@ -174,4 +400,161 @@ mod tests {
         throughput.sort();
         assert_eq!(throughput, vec![]);
     }
+
+    #[test]
+    fn key_count_test() {
+        let mut data = ClientData::new();
+        data.record_key_count(1);
+        data.record_key_count(2);
+        data.record_key_count(1);
+
+        let mut other = ClientData::new();
+        other.record_key_count(2);
+        other.record_key_count(3);
+
+        data.merge(&other);
+        // pruning doesn't affect key counts
+        data.prune(1, 1);
+        let mut key_counts: Vec<_> = data.key_count_data().collect();
+        key_counts.sort();
+        assert_eq!(key_counts, vec![(1, 2), (2, 2), (3, 1)]);
+    }
+
+    #[test]
+    fn connected_test() {
+        let mut data = ClientData::new();
+        assert_eq!(data.setup_time(), None);
+        data.record_connected(
+            Duration::from_millis(10),
+            Duration::from_millis(100),
+        );
+
+        let mut other = ClientData::new();
+        other.record_connected(
+            Duration::from_millis(20),
+            Duration::from_millis(300),
+        );
+        other.record_connected(
+            Duration::from_millis(30),
+            Duration::from_millis(200),
+        );
+
+        // the setup time is the time until the last client was connected
+        data.merge(&other);
+        assert_eq!(data.setup_time(), Some(Duration::from_millis(300)));
+        let mut connect_latencies: Vec<_> =
+            data.connect_latency_data().collect();
+        connect_latencies.sort();
+        assert_eq!(
+            connect_latencies,
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+                Duration::from_millis(30),
+            ]
+        );
+    }
+
+    #[test]
+    fn corrected_test() {
+        let mut data = ClientData::new();
+        data.record(Duration::from_millis(1), 10);
+        data.record_corrected(Duration::from_millis(3), 10);
+        assert_eq!(data.latency_data().count(), 1);
+
+        let mut other = ClientData::new();
+        other.record(Duration::from_millis(2), 20);
+        other.record_corrected(Duration::from_millis(2), 20);
+
+        // corrected latencies are merged
+        data.merge(&other);
+        let mut corrected: Vec<_> = data.corrected_latency_data().collect();
+        corrected.sort();
+        assert_eq!(
+            corrected,
+            vec![Duration::from_millis(2), Duration::from_millis(3)]
+        );
+
+        // and pruned
+        data.prune(15, 20);
+        let corrected: Vec<_> = data.corrected_latency_data().collect();
+        assert_eq!(corrected, vec![Duration::from_millis(2)]);
+    }
+
+    #[test]
+    fn availability_test() {
+        let mut data = ClientData::new();
+        // in second 1, two commands completed and one timed out
+        data.record_success(1_000);
+        data.record_success(1_999);
+        data.record_timeouts(1, 1_500);
+        // in second 2, no command timed out
+        data.record_timeouts(0, 2_000);
+
+        let mut other = ClientData::new();
+        // in second 2, two commands timed out
+        other.record_timeouts(2, 2_000);
+        // in second 3, one command completed
+        other.record_success(3_000);
+
+        data.merge(&other);
+        let mut availability: Vec<_> = data.availability_data().collect();
+        availability.sort();
+        assert_eq!(availability, vec![(1, (2, 1)), (2, (0, 2)), (3, (1, 0))]);
+
+        // prune second 1 out
+        data.prune(2_500, 3_000);
+        let mut availability: Vec<_> = data.availability_data().collect();
+        availability.sort();
+        assert_eq!(availability, vec![(2, (0, 2)), (3, (1, 0))]);
+    }
+
+    #[test]
+    fn probes_test() {
+        let rtts = vec![
+            Duration::from_millis(30),
+            Duration::from_millis(10),
+            Duration::from_millis(20),
+        ];
+        let probe = ProcessProbe::new(1, 0, rtts);
+        assert_eq!(probe.median_rtt(), Duration::from_millis(20));
+
+        // probes are merged
+        let mut data = ClientData::new();
+        data.record_probes(vec![probe.clone()]);
+        let mut other = ClientData::new();
+        other.record_probes(vec![probe.clone()]);
+        data.merge(&other);
+        assert_eq!(data.probes(), &[probe.clone(), probe][..]);
+    }
+
+    #[test]
+    fn clock_drifts_test() {
+        let drift = ClockDrift {
+            elapsed: Duration::from_secs(60),
+            drift_nanos: 120_000,
+        };
+
+        // clock drifts are merged
+        let mut data = ClientData::new();
+        data.record_clock_drift(drift);
+        let mut other = ClientData::new();
+        other.record_clock_drift(drift);
+        data.merge(&other);
+        assert_eq!(data.clock_drifts(), &[drift, drift][..]);
+    }
+
+    #[test]
+    fn partial_test() {
+        let mut data = ClientData::new();
+        assert!(!data.partial());
+
+        // merging with partial data makes the data partial
+        let mut other = ClientData::new();
+        other.set_partial();
+        data.merge(&ClientData::new());
+        assert!(!data.partial());
+        data.merge(&other);
+        assert!(data.partial());
+    }
 }
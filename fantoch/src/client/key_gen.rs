@@ -1,31 +1,51 @@
+use crate::client::ZipfSampler;
 use crate::id::ClientId;
 use crate::kvs::Key;
-use rand::distributions::Distribution;
-use rand::Rng;
-use serde::{Deserialize, Serialize};
-use zipf::ZipfDistribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::sync::Arc;
 
 pub const CONFLICT_COLOR: &str = "CONFLICT";
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum KeyGen {
+    /// Commands access, with probability `conflict_rate` (in percentage), a
+    /// random key from a pool of `pool_size` keys, and a key unique to their
+    /// client otherwise. Pools with different ids have disjoint keys, and so
+    /// clients (e.g. in a `WorkloadMix`) can use independent pools with
+    /// different conflict rates at the same time.
     ConflictPool {
         conflict_rate: usize,
         pool_size: usize,
+        // metadata of experiments prior to multiple pools has no pool id
+        #[serde(default)]
+        pool_id: usize,
     },
     Zipf {
         coefficient: f64,
         total_keys_per_shard: usize,
     },
+    Custom(CustomKeyGen),
 }
 
 impl KeyGen {
+    /// Creates a `KeyGen` from a custom key generator.
+    pub fn custom(generator: &'static dyn KeyGenerator) -> Self {
+        Self::Custom(CustomKeyGen {
+            name: generator.name(),
+            generator: Some(generator),
+        })
+    }
+
     pub fn initial_state(
         self,
         shard_count: usize,
         client_id: ClientId,
+        seed: Option<u64>,
     ) -> KeyGenState {
-        KeyGenState::new(self, shard_count, client_id)
+        KeyGenState::new(self, shard_count, client_id, seed)
     }
 }
 
@@ -35,8 +55,15 @@ impl std::fmt::Display for KeyGen {
             Self::ConflictPool {
                 conflict_rate,
                 pool_size,
+                pool_id,
             } => {
-                write!(f, "conflict_{}_{}", conflict_rate, pool_size)
+                write!(f, "conflict_{}_{}", conflict_rate, pool_size)?;
+                // the default pool is omitted, so that names are the same as
+                // before multiple pools were supported
+                if *pool_id != 0 {
+                    write!(f, "_pool{}", pool_id)?;
+                }
+                Ok(())
             }
             Self::Zipf {
                 total_keys_per_shard,
@@ -47,38 +74,137 @@ impl std::fmt::Display for KeyGen {
                 format!("zipf_{:.2}_{}", coefficient, total_keys_per_shard)
                     .replace(".", "-")
             ),
+            Self::Custom(custom) => write!(f, "custom_{}", custom.name),
         }
     }
 }
 
+/// Key generator that can be implemented outside of this crate, and used in a
+/// `Workload` through `KeyGen::custom`.
+pub trait KeyGenerator: Send + Sync {
+    /// Returns the name of the key generator, which identifies it in the
+    /// results metadata.
+    fn name(&self) -> &'static str;
+
+    /// Generates a command key for client `client_id`. All random choices
+    /// should be drawn from `rng` so that seeded workloads are reproducible.
+    fn gen_cmd_key(
+        &self,
+        client_id: ClientId,
+        shard_count: usize,
+        rng: &mut StdRng,
+    ) -> Key;
+}
+
+/// A custom key generator. Only its name is serialized, and thus a
+/// deserialized `CustomKeyGen` can't generate keys.
+#[derive(Clone, Copy)]
+pub struct CustomKeyGen {
+    name: &'static str,
+    generator: Option<&'static dyn KeyGenerator>,
+}
+
+impl CustomKeyGen {
+    /// Returns the name of the key generator.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn gen_cmd_key(
+        &self,
+        client_id: ClientId,
+        shard_count: usize,
+        rng: &mut StdRng,
+    ) -> Key {
+        self.generator
+            .unwrap_or_else(|| {
+                panic!(
+                    "custom key generator {} can't be used after deserialization",
+                    self.name
+                )
+            })
+            .gen_cmd_key(client_id, shard_count, rng)
+    }
+}
+
+impl fmt::Debug for CustomKeyGen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl PartialEq for CustomKeyGen {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Serialize for CustomKeyGen {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name)
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomKeyGen {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // the name is leaked so that `KeyGen` can remain `Copy`; this is fine
+        // as only a few custom key generators are ever deserialized
+        let name = String::deserialize(deserializer)?;
+        Ok(Self {
+            name: Box::leak(name.into_boxed_str()),
+            generator: None,
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct KeyGenState {
     key_gen: KeyGen,
+    shard_count: usize,
     client_id: ClientId,
-    zipf: Option<ZipfDistribution>,
+    // zipf sampler, shared by all clients in this process
+    zipf: Option<Arc<ZipfSampler>>,
+    // rng from which all the random choices of the client's workload are
+    // drawn
+    rng: StdRng,
 }
 
 impl KeyGenState {
-    fn new(key_gen: KeyGen, shard_count: usize, client_id: ClientId) -> Self {
+    fn new(
+        key_gen: KeyGen,
+        shard_count: usize,
+        client_id: ClientId,
+        seed: Option<u64>,
+    ) -> Self {
         let zipf = match key_gen {
-            KeyGen::ConflictPool { .. } => None,
+            KeyGen::ConflictPool { .. } | KeyGen::Custom(_) => None,
             KeyGen::Zipf {
                 coefficient,
                 total_keys_per_shard,
             } => {
                 // compute key count
                 let key_count = total_keys_per_shard * shard_count;
-                // initialize zipf distribution
-                let zipf = ZipfDistribution::new(key_count, coefficient)
-                    .expect(
-                    "it should be possible to initialize the ZipfDistribution",
-                );
-                Some(zipf)
+                // get zipf sampler
+                Some(ZipfSampler::shared(key_count, coefficient))
             }
         };
+        // if there's a seed, each client gets a different one
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(client_id)),
+            None => StdRng::from_entropy(),
+        };
         Self {
             key_gen,
+            shard_count,
             client_id,
             zipf,
+            rng,
         }
     }
 
@@ -87,22 +213,39 @@ impl KeyGenState {
             KeyGen::ConflictPool {
                 conflict_rate,
                 pool_size,
-            } => self.gen_conflict_rate(conflict_rate, pool_size),
+                pool_id,
+            } => self.gen_conflict_rate(conflict_rate, pool_size, pool_id),
             KeyGen::Zipf { .. } => self.gen_zipf(),
+            KeyGen::Custom(custom) => custom.gen_cmd_key(
+                self.client_id,
+                self.shard_count,
+                &mut self.rng,
+            ),
         }
     }
 
-    /// Generate a command key based on the conflict rate provided.
-    fn gen_conflict_rate(&self, conflict_rate: usize, pool_size: usize) -> Key {
+    /// Generate a command key based on the conflict rate provided, drawing
+    /// conflicting keys from pool `pool_id`.
+    fn gen_conflict_rate(
+        &mut self,
+        conflict_rate: usize,
+        pool_size: usize,
+        pool_id: usize,
+    ) -> Key {
         debug_assert!(conflict_rate <= 100);
         debug_assert!(pool_size >= 1);
 
         // check if we should generate a conflict
-        let should_conflict = true_if_random_is_less_than(conflict_rate);
+        let should_conflict = self.true_if_random_is_less_than(conflict_rate);
         if should_conflict {
             // if it should conflict, select a random key from the pool
-            let random_key = rand::thread_rng().gen_range(0..pool_size);
-            format!("{}{}", CONFLICT_COLOR, random_key)
+            let random_key = self.rng.gen_range(0..pool_size);
+            if pool_id == 0 {
+                format!("{}{}", CONFLICT_COLOR, random_key)
+            } else {
+                // the separator ensures keys of different pools never match
+                format!("{}{}_{}", CONFLICT_COLOR, pool_id, random_key)
+            }
         } else {
             // avoid conflict with unique client key
             self.client_id.to_string()
@@ -113,16 +256,22 @@ impl KeyGenState {
     fn gen_zipf(&mut self) -> Key {
         let zipf = self
             .zipf
-            .expect("ZipfDistribution should already be initialized");
-        let mut rng = rand::thread_rng();
-        zipf.sample(&mut rng).to_string()
+            .as_ref()
+            .expect("ZipfSampler should already be initialized");
+        zipf.sample(&mut self.rng).to_string()
+    }
+
+    pub fn true_if_random_is_less_than(&mut self, percentage: usize) -> bool {
+        match percentage {
+            0 => false,
+            100 => true,
+            _ => self.rng.gen_range(0..100) < percentage,
+        }
     }
-}
 
-pub fn true_if_random_is_less_than(percentage: usize) -> bool {
-    match percentage {
-        0 => false,
-        100 => true,
-        _ => rand::thread_rng().gen_range(0..100) < percentage,
+    /// Returns the rng from which the random choices of the client's workload
+    /// are drawn.
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
     }
 }
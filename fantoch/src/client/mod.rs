@@ -1,29 +1,59 @@
 // This module contains the definition of `Workload`
 pub mod workload;
 
+// This module contains the definition of `WorkloadMix`
+pub mod workload_mix;
+
 // This module contains the definition of `KeyGenerator` and
 // `KeyGeneratorState`.
 pub mod key_gen;
 
+// This module contains the definition of `ZipfSampler`
+pub mod zipf;
+
 // This module contains the definition of `Pending`
 pub mod pending;
 
 // This module contains the definition of `ClientData`
 pub mod data;
 
+// This module contains the definition of `KeySkew`
+pub mod skew;
+
+// This module contains the definition of `Transaction`
+pub mod transaction;
+
+// This module contains the definition of `ProcessSelection`
+pub mod selection;
+
+// This module contains the definition of `ClientStats`
+pub mod stats;
+
+// This module contains the definition of `ClientHi`, `ProcessHi` and
+// `ClientToServer`, and of how they're framed on the wire.
+pub mod wire;
+
 // Re-exports.
-pub use data::ClientData;
-pub use key_gen::KeyGen;
+pub use data::{ClientData, ClientTrace, ProcessProbe};
+pub use key_gen::{CustomKeyGen, KeyGen, KeyGenerator};
 pub use pending::Pending;
+pub use selection::ProcessSelection;
+pub use skew::KeySkew;
+pub use stats::ClientStats;
+pub use transaction::Transaction;
 pub use workload::Workload;
+pub use workload_mix::WorkloadMix;
+pub use zipf::ZipfSampler;
 
-use crate::command::Command;
+use crate::command::{Command, CommandResult, CommandTrace};
 use crate::id::{ClientId, ProcessId, Rifl, RiflGen, ShardId};
 use crate::time::SysTime;
-use crate::HashMap;
-use crate::{info, trace};
+use crate::{flow, info, trace};
+use crate::{HashMap, HashSet};
 use key_gen::KeyGenState;
+use std::time::Duration;
 
+#[derive(Clone)]
 pub struct Client {
     /// id of this client
     client_id: ClientId,
@@ -38,11 +68,20 @@ pub struct Client {
     key_gen_state: KeyGenState,
     /// map from pending command RIFL to its start time
     pending: Pending,
+    /// set of commands cancelled while pending; results for these commands
+    /// may still arrive, and should be ignored
+    cancelled: HashSet<Rifl>,
     /// mapping from
     data: ClientData,
+    /// traces of the commands sampled (if tracing is enabled)
+    traces: Vec<ClientTrace>,
     /// frequency of status messages; if set with Some(1), a status message
     /// will be shown after each command completes
     status_frequency: Option<usize>,
+    /// number of transactions that aborted
+    aborted_transactions: usize,
+    /// number of commands issued that access each shard
+    shard_commands: HashMap<ShardId, usize>,
 }
 
 impl Client {
@@ -53,9 +92,11 @@ impl Client {
         status_frequency: Option<usize>,
     ) -> Self {
         // create key gen state
-        let key_gen_state = workload
-            .key_gen()
-            .initial_state(workload.shard_count(), client_id);
+        let key_gen_state = workload.key_gen().initial_state(
+            workload.shard_count(),
+            client_id,
+            workload.seed(),
+        );
         // create client
         Self {
             client_id,
@@ -64,8 +105,12 @@ impl Client {
             workload,
             key_gen_state,
             pending: Pending::new(),
+            cancelled: HashSet::new(),
             data: ClientData::new(),
+            traces: Vec::new(),
             status_frequency,
+            aborted_transactions: 0,
+            shard_commands: HashMap::new(),
         }
     }
 
@@ -91,11 +136,32 @@ impl Client {
     pub fn cmd_send(
         &mut self,
         time: &dyn SysTime,
+    ) -> Option<(ShardId, Command)> {
+        self.do_cmd_send(None, time)
+    }
+
+    /// Generates the next command in this client's workload, given the time
+    /// (in micros) at which it was intended to be issued. This allows
+    /// open-loop clients to record latencies corrected for coordinated
+    /// omission (see `ClientData::corrected_latency_data`).
+    pub fn cmd_send_scheduled(
+        &mut self,
+        intended_start_time: u64,
+        time: &dyn SysTime,
+    ) -> Option<(ShardId, Command)> {
+        self.do_cmd_send(Some(intended_start_time), time)
+    }
+
+    fn do_cmd_send(
+        &mut self,
+        intended_start_time: Option<u64>,
+        time: &dyn SysTime,
     ) -> Option<(ShardId, Command)> {
         // generate next command in the workload if some process_id
         self.workload
             .next_cmd(&mut self.rifl_gen, &mut self.key_gen_state)
-            .map(|(target_shard, cmd)| {
+            .map(|(target_shard, mut cmd)| {
+                cmd.set_timestamp(time.millis());
                 // if a new command was generated, start it in pending
                 let rifl = cmd.rifl();
                 trace!(
@@ -104,17 +170,40 @@ impl Client {
                     rifl,
                     time.micros()
                 );
-                self.pending.start(rifl, time);
+                match intended_start_time {
+                    Some(intended_start_time) => self.pending.start_scheduled(
+                        rifl,
+                        intended_start_time,
+                        time,
+                    ),
+                    None => self.pending.start(rifl, time),
+                }
+                self.record_issued(&cmd);
                 (target_shard, cmd)
             })
     }
 
-    /// Handle executed command and return a boolean indicating whether we have
-    /// generated all commands and receive all the corresponding command
-    /// results.
-    pub fn cmd_recv(&mut self, rifl: Rifl, time: &dyn SysTime) {
+    /// Handle executed command and return its latency (or `None` if the
+    /// command was cancelled, in which case its result is ignored).
+    pub fn cmd_recv(
+        &mut self,
+        rifl: Rifl,
+        time: &dyn SysTime,
+    ) -> Option<Duration> {
+        // ignore results from cancelled commands
+        if self.cancelled.remove(&rifl) {
+            trace!(
+                "c{}: ignoring result of cancelled rifl {:?}",
+                self.client_id,
+                rifl
+            );
+            return None;
+        }
+
         // end command in pending and save command latency
-        let (latency, end_time) = self.pending.end(rifl, time);
+        let timed_out = self.pending.timed_out(&rifl);
+        let (latency, corrected_latency, end_time) =
+            self.pending.end(rifl, time);
         trace!(
             "c{}: rifl {:?} ended after {} micros at {}",
             self.client_id,
@@ -122,7 +211,22 @@ impl Client {
             latency.as_micros(),
             end_time
         );
+        flow!(
+            client = self.client_id,
+            rifl = ?rifl,
+            phase = "end",
+            latency = latency.as_micros() as u64,
+            time = end_time
+        );
         self.data.record(latency, end_time);
+        if let Some(corrected_latency) = corrected_latency {
+            self.data.record_corrected(corrected_latency, end_time);
+        }
+        // commands that complete after timing out were already counted as
+        // unavailable
+        if !timed_out {
+            self.data.record_success(end_time);
+        }
 
         if let Some(frequency) = self.status_frequency {
             if self.workload.issued_commands() % frequency == 0 {
@@ -134,12 +238,124 @@ impl Client {
                 );
             }
         }
+        Some(latency)
+    }
+
+    /// Records the trace of a sampled command, given the latency observed by
+    /// this client.
+    pub fn record_trace(
+        &mut self,
+        rifl: Rifl,
+        latency: Duration,
+        trace: CommandTrace,
+    ) {
+        self.traces.push(ClientTrace {
+            rifl,
+            latency,
+            trace,
+        });
+    }
+
+    /// Returns the traces of the commands sampled.
+    pub fn traces(&self) -> &[ClientTrace] {
+        &self.traces
+    }
+
+    /// Cancels a pending command. Returns a boolean indicating whether the
+    /// command was pending; if so, the process where the command was submitted
+    /// should be notified, so that it can commit a noop in its place.
+    /// Note that only protocols for which `Protocol::cancellable` holds
+    /// support this; others reject the cancel and still execute the command.
+    pub fn cancel(&mut self, rifl: Rifl) -> bool {
+        let cancelled = self.pending.cancel(rifl);
+        if cancelled {
+            trace!("c{}: rifl {:?} cancelled", self.client_id, rifl);
+            self.cancelled.insert(rifl);
+        }
+        cancelled
+    }
+
+    /// Records, as timed out, the commands that have been pending for at least
+    /// `timeout` (see `Pending::time_out`). This is used to measure the
+    /// availability perceived by clients (e.g. during faults), as commands
+    /// that never complete would otherwise go unnoticed.
+    pub fn check_timeouts(&mut self, timeout: Duration, time: &dyn SysTime) {
+        let timed_out = self.pending.time_out(timeout, time);
+        if timed_out > 0 {
+            trace!(
+                "c{}: {} commands timed out | time = {}",
+                self.client_id,
+                timed_out,
+                time.micros()
+            );
+        }
+        self.data.record_timeouts(timed_out, time.millis());
+    }
+
+    /// Begins an interactive transaction (see `Transaction`).
+    pub fn begin(&self) -> Transaction {
+        Transaction::begin(self.workload.shard_count())
+    }
+
+    /// Generates the command that commits the transaction provided.
+    pub fn commit_send(
+        &mut self,
+        tx: &Transaction,
+        time: &dyn SysTime,
+    ) -> (ShardId, Command) {
+        let (target_shard, mut cmd) = tx.commit(self.rifl_gen.next_id());
+        cmd.set_timestamp(time.millis());
+        let rifl = cmd.rifl();
+        trace!(
+            "c{}: new transaction commit pending {:?} | time = {}",
+            self.client_id,
+            rifl,
+            time.micros()
+        );
+        self.pending.start(rifl, time);
+        self.record_issued(&cmd);
+        (target_shard, cmd)
+    }
+
+    /// Handles the result of the command that commits the transaction
+    /// provided (with the results from all the shards accessed). Returns a
+    /// boolean indicating whether the transaction committed; if not, the
+    /// transaction is counted as aborted.
+    pub fn commit_recv(
+        &mut self,
+        tx: &Transaction,
+        cmd_result: &CommandResult,
+        time: &dyn SysTime,
+    ) -> bool {
+        self.cmd_recv(cmd_result.rifl(), time);
+        let committed = tx.validate(cmd_result.results());
+        if !committed {
+            trace!(
+                "c{}: transaction {:?} aborted",
+                self.client_id,
+                cmd_result.rifl()
+            );
+            self.aborted_transactions += 1;
+        }
+        committed
+    }
+
+    /// Returns the number of transactions that aborted.
+    pub fn aborted_transactions(&self) -> usize {
+        self.aborted_transactions
     }
 
     pub fn workload_finished(&self) -> bool {
         self.workload.finished()
     }
 
+    /// Returns the number of commands issued when the client starts, each of
+    /// which is then followed by a new command once it completes (see
+    /// `Workload::set_max_in_flight`).
+    pub fn max_in_flight(&self) -> usize {
+        self.workload.max_in_flight().unwrap_or(1)
+    }
+
     pub fn finished(&self) -> bool {
         // we're done once:
         // - the workload is finished and
@@ -147,14 +363,51 @@ impl Client {
         self.workload.finished() && self.pending.is_empty()
     }
 
+    /// Checks whether the client has no commands pending.
+    pub fn pending_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
     pub fn data(&self) -> &ClientData {
         &self.data
     }
 
+    /// Marks the data of this client as partial, i.e. the client was stopped
+    /// before finishing its workload (see `ClientData::set_partial`).
+    pub fn set_partial(&mut self) {
+        self.data.set_partial();
+    }
+
+    /// Records that this client took `connect_latency` to connect to all the
+    /// processes, and that it was connected `setup_time` after clients
+    /// started (see `ClientData::record_connected`).
+    pub fn record_connected(
+        &mut self,
+        connect_latency: Duration,
+        setup_time: Duration,
+    ) {
+        self.data.record_connected(connect_latency, setup_time);
+    }
+
     /// Returns the number of commands already issued.
     pub fn issued_commands(&self) -> usize {
         self.workload.issued_commands()
     }
+
+    /// Returns the number of commands issued that access each shard.
+    pub fn shard_commands(&self) -> &HashMap<ShardId, usize> {
+        &self.shard_commands
+    }
+
+    fn record_issued(&mut self, cmd: &Command) {
+        for shard_id in cmd.shards() {
+            *self.shard_commands.entry(*shard_id).or_default() += 1;
+        }
+        self.data.record_key_count(cmd.total_key_count());
+        for (_, key) in cmd.all_keys() {
+            self.data.record_key_access(key);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -177,6 +430,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate,
             pool_size,
+            pool_id: 0,
         };
         let payload_size = 100;
         let workload = Workload::new(
@@ -287,6 +541,16 @@ mod tests {
         // check there's no next command
         assert!(next.is_none());
 
+        // check that both commands accessed the single shard
+        assert_eq!(client.shard_commands().get(&shard_id), Some(&2));
+        // both commands access a single key
+        let key_counts: Vec<_> = client.data().key_count_data().collect();
+        assert_eq!(key_counts, vec![(1, 2)]);
+        // and, since the conflict rate is 100%, the same key
+        let key_skew = client.data().key_skew();
+        assert_eq!(key_skew.total(), 2);
+        assert!(matches!(key_skew.top_k(1).as_slice(), [(_, 2)]));
+
         // check latency
         let mut latency: Vec<_> = client.data().latency_data().collect();
         latency.sort();
@@ -300,4 +564,73 @@ mod tests {
         throughput.sort();
         assert_eq!(throughput, vec![(10, 1), (15, 1)],);
     }
+
+    #[test]
+    fn client_timeouts() {
+        let commands_per_client = 2;
+        let mut client = gen_client(commands_per_client);
+        let mut time = SimTime::new();
+        let timeout = Duration::from_millis(100);
+
+        // the first command times out at time 100, and completes afterwards
+        let (_, cmd) = client
+            .cmd_send(&time)
+            .expect("there should be a first operation");
+        time.add_millis(100);
+        client.check_timeouts(timeout, &time);
+        time.add_millis(1000);
+        client.cmd_recv(cmd.rifl(), &time);
+
+        // the second command completes before timing out
+        let (_, cmd) = client
+            .cmd_send(&time)
+            .expect("there should be a second operation");
+        time.add_millis(10);
+        client.check_timeouts(timeout, &time);
+        client.cmd_recv(cmd.rifl(), &time);
+
+        // the late command is not counted as completed
+        let mut availability: Vec<_> =
+            client.data().availability_data().collect();
+        availability.sort();
+        assert_eq!(availability, vec![(0, (0, 1)), (1, (1, 0))]);
+        // but its latency is still recorded
+        assert_eq!(client.data().latency_data().count(), 2);
+    }
+
+    #[test]
+    fn client_transaction() {
+        let key = String::from("A");
+        let value = String::from("x");
+        let commands_per_client = 0;
+        let mut client = gen_client(commands_per_client);
+        let mut time = SimTime::new();
+
+        // transaction that read no value for `A` and writes to it
+        let mut tx = client.begin();
+        tx.record_read(key.clone(), None);
+        tx.put(key.clone(), value.clone());
+
+        // first commit succeeds
+        let (_, cmd) = client.commit_send(&tx, &time);
+        time.add_millis(10);
+        let mut results = HashMap::new();
+        results.insert(key.clone(), vec![None, None]);
+        let cmd_result = CommandResult::new(cmd.rifl(), results);
+        assert!(client.commit_recv(&tx, &cmd_result, &time));
+        assert_eq!(client.aborted_transactions(), 0);
+
+        // second commit aborts, as `A` was written in the meantime
+        let (_, cmd) = client.commit_send(&tx, &time);
+        time.add_millis(10);
+        let mut results = HashMap::new();
+        results.insert(key, vec![Some(value), None]);
+        let cmd_result = CommandResult::new(cmd.rifl(), results);
+        assert!(!client.commit_recv(&tx, &cmd_result, &time));
+        assert_eq!(client.aborted_transactions(), 1);
+
+        // both commits were completed
+        assert!(client.finished());
+        assert_eq!(client.data().latency_data().count(), 2);
+    }
 }
@@ -1,12 +1,17 @@
 use crate::id::Rifl;
 use crate::time::SysTime;
-use crate::HashMap;
+use crate::{HashMap, HashSet};
 use std::time::Duration;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Pending {
-    /// mapping from Rifl to command start time (in micros)
-    pending: HashMap<Rifl, u64>,
+    /// mapping from Rifl to command start time (in micros) and, if the command
+    /// was issued on a schedule, the time (in micros) at which it was intended
+    /// to start
+    pending: HashMap<Rifl, (u64, Option<u64>)>,
+    /// set of pending commands that have already timed out (see
+    /// `Pending::time_out`)
+    timed_out: HashSet<Rifl>,
 }
 
 impl Pending {
@@ -17,31 +22,101 @@ impl Pending {
 
     /// Start a command given its rifl.
     pub fn start(&mut self, rifl: Rifl, time: &dyn SysTime) {
-        // compute start time
-        let start_time = time.micros();
-        // add to pending and check it has never been added before
-        // TODO: replace with `.expect_none` once it's stabilized
-        if self.pending.insert(rifl, start_time).is_some() {
-            panic!("the same rifl can't be inserted twice in client pending list of commands");
-        }
+        self.do_start(rifl, None, time);
     }
 
-    /// End a command returns command latency and the time it was returned.
-    pub fn end(&mut self, rifl: Rifl, time: &dyn SysTime) -> (Duration, u64) {
+    /// Start a command given its rifl and the time (in micros) at which it was
+    /// intended to start (e.g. by an open-loop client). If the command starts
+    /// late, its latency corrected for coordinated omission is computed from
+    /// the intended start time (see `Pending::end`).
+    pub fn start_scheduled(
+        &mut self,
+        rifl: Rifl,
+        intended_start_time: u64,
+        time: &dyn SysTime,
+    ) {
+        self.do_start(rifl, Some(intended_start_time), time);
+    }
+
+    /// End a command returns command latency, its latency corrected for
+    /// coordinated omission (if the command was issued on a schedule) and the
+    /// time it was returned.
+    pub fn end(
+        &mut self,
+        rifl: Rifl,
+        time: &dyn SysTime,
+    ) -> (Duration, Option<Duration>, u64) {
         // get start time
-        let start_time = self
+        let (start_time, intended_start_time) = self
             .pending
             .remove(&rifl)
             .expect("can't end a command if a command has not started");
+        self.timed_out.remove(&rifl);
         // compute end time
         let end_time = time.micros();
         // make sure time is monotonic
         assert!(start_time <= end_time);
         // compute latency
         let latency = Duration::from_micros(end_time - start_time);
+        // compute corrected latency: the command is considered to have started
+        // at the time it was intended to start (unless it started earlier)
+        let corrected_latency =
+            intended_start_time.map(|intended_start_time| {
+                let start_time = std::cmp::min(start_time, intended_start_time);
+                Duration::from_micros(end_time - start_time)
+            });
         // compute end time in milliseconds
         let end_time = end_time / 1000;
-        (latency, end_time)
+        (latency, corrected_latency, end_time)
+    }
+
+    fn do_start(
+        &mut self,
+        rifl: Rifl,
+        intended_start_time: Option<u64>,
+        time: &dyn SysTime,
+    ) {
+        // compute start time
+        let start_time = time.micros();
+        // add to pending and check it has never been added before
+        // TODO: replace with `.expect_none` once it's stabilized
+        if self
+            .pending
+            .insert(rifl, (start_time, intended_start_time))
+            .is_some()
+        {
+            panic!("the same rifl can't be inserted twice in client pending list of commands");
+        }
+    }
+
+    /// Cancel a command given its rifl. Returns a boolean indicating whether the
+    /// command was pending.
+    pub fn cancel(&mut self, rifl: Rifl) -> bool {
+        self.timed_out.remove(&rifl);
+        self.pending.remove(&rifl).is_some()
+    }
+
+    /// Marks the commands that have been pending for at least `timeout` as
+    /// timed out, and returns how many of them hadn't timed out before (i.e.
+    /// each command times out at most once). Commands that time out are kept
+    /// pending, as their result may still arrive.
+    pub fn time_out(&mut self, timeout: Duration, time: &dyn SysTime) -> u64 {
+        let now = time.micros();
+        let timeout = timeout.as_micros() as u64;
+        let mut timed_out = 0;
+        for (rifl, (start_time, _)) in self.pending.iter() {
+            if now.saturating_sub(*start_time) >= timeout
+                && self.timed_out.insert(*rifl)
+            {
+                timed_out += 1;
+            }
+        }
+        timed_out
+    }
+
+    /// Checks whether a pending command has timed out.
+    pub fn timed_out(&self, rifl: &Rifl) -> bool {
+        self.timed_out.contains(rifl)
     }
 
     /// Checks whether pending is empty.
@@ -89,8 +164,10 @@ mod tests {
 
         // end first rifl at time 11
         time.add_millis(1);
-        let (latency, return_time) = pending.end(rifl1, &time);
+        let (latency, corrected_latency, return_time) =
+            pending.end(rifl1, &time);
         assert_eq!(latency.as_millis(), 11);
+        assert_eq!(corrected_latency, None);
         assert_eq!(return_time, 11);
 
         // pending is not empty
@@ -105,8 +182,10 @@ mod tests {
 
         // end third rifl at time 16
         time.add_millis(1);
-        let (latency, return_time) = pending.end(rifl3, &time);
+        let (latency, corrected_latency, return_time) =
+            pending.end(rifl3, &time);
         assert_eq!(latency.as_millis(), 1);
+        assert_eq!(corrected_latency, None);
         assert_eq!(return_time, 16);
 
         // pending is not empty
@@ -114,14 +193,124 @@ mod tests {
 
         // end second rifl at time 20
         time.add_millis(4);
-        let (latency, return_time) = pending.end(rifl2, &time);
+        let (latency, corrected_latency, return_time) =
+            pending.end(rifl2, &time);
         assert_eq!(latency.as_millis(), 10);
+        assert_eq!(corrected_latency, None);
         assert_eq!(return_time, 20);
 
         // pending is empty now
         assert!(pending.is_empty());
     }
 
+    #[test]
+    fn cancel() {
+        // create pending
+        let mut pending = Pending::new();
+
+        // create rifl gen and 2 rifls
+        let source = 10;
+        let mut rifl_gen = RiflGen::new(source);
+        let rifl1 = rifl_gen.next_id();
+        let rifl2 = rifl_gen.next_id();
+
+        // create sys time
+        let time = SimTime::new();
+
+        // can't cancel a rifl that has not started
+        assert!(!pending.cancel(rifl1));
+
+        // start both rifls
+        pending.start(rifl1, &time);
+        pending.start(rifl2, &time);
+
+        // cancel the first one, twice
+        assert!(pending.cancel(rifl1));
+        assert!(!pending.cancel(rifl1));
+        assert!(!pending.is_empty());
+
+        // end the second one
+        pending.end(rifl2, &time);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn time_out() {
+        // create pending
+        let mut pending = Pending::new();
+
+        // create rifl gen and 2 rifls
+        let source = 10;
+        let mut rifl_gen = RiflGen::new(source);
+        let rifl1 = rifl_gen.next_id();
+        let rifl2 = rifl_gen.next_id();
+
+        // create sys time
+        let mut time = SimTime::new();
+        let timeout = Duration::from_millis(10);
+
+        // start the first rifl at time 0 and the second at time 5
+        pending.start(rifl1, &time);
+        time.add_millis(5);
+        pending.start(rifl2, &time);
+
+        // at time 9, no rifl has timed out
+        time.add_millis(4);
+        assert_eq!(pending.time_out(timeout, &time), 0);
+
+        // at time 10, the first rifl times out, but only once
+        time.add_millis(1);
+        assert_eq!(pending.time_out(timeout, &time), 1);
+        assert_eq!(pending.time_out(timeout, &time), 0);
+        assert!(pending.timed_out(&rifl1));
+        assert!(!pending.timed_out(&rifl2));
+
+        // the first rifl can still end
+        pending.end(rifl1, &time);
+        assert!(!pending.timed_out(&rifl1));
+
+        // at time 15, the second rifl times out
+        time.add_millis(5);
+        assert_eq!(pending.time_out(timeout, &time), 1);
+        assert!(pending.cancel(rifl2));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn scheduled() {
+        // create pending
+        let mut pending = Pending::new();
+
+        // create rifl gen and 2 rifls
+        let source = 10;
+        let mut rifl_gen = RiflGen::new(source);
+        let rifl1 = rifl_gen.next_id();
+        let rifl2 = rifl_gen.next_id();
+
+        // create sys time
+        let mut time = SimTime::new();
+
+        // at time 10, start the first rifl (intended to start at time 10) and
+        // the second rifl (intended to start at time 5)
+        time.add_millis(10);
+        pending.start_scheduled(rifl1, 10_000, &time);
+        pending.start_scheduled(rifl2, 5_000, &time);
+
+        // end both at time 12: only the latency of the second rifl is
+        // corrected
+        time.add_millis(2);
+        let (latency, corrected_latency, return_time) =
+            pending.end(rifl1, &time);
+        assert_eq!(latency.as_millis(), 2);
+        assert_eq!(corrected_latency, Some(Duration::from_millis(2)));
+        assert_eq!(return_time, 12);
+        let (latency, corrected_latency, return_time) =
+            pending.end(rifl2, &time);
+        assert_eq!(latency.as_millis(), 2);
+        assert_eq!(corrected_latency, Some(Duration::from_millis(7)));
+        assert_eq!(return_time, 12);
+    }
+
     #[test]
     #[should_panic]
     fn double_start() {
@@ -0,0 +1,65 @@
+use crate::id::ProcessId;
+use serde::{Deserialize, Serialize};
+
+/// Selects, on each shard, the process to which clients submit their commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProcessSelection {
+    /// Submit to the k-th closest process (`KthClosest(0)` being the closest).
+    KthClosest(usize),
+    /// Submit to the process with this id (on the first shard), or to the
+    /// process in the same position in the case of the remaining shards.
+    Fixed(ProcessId),
+}
+
+impl Default for ProcessSelection {
+    fn default() -> Self {
+        Self::KthClosest(0)
+    }
+}
+
+impl ProcessSelection {
+    /// Selects a process given all the processes of some shard sorted by
+    /// distance (closest first).
+    pub fn select(&self, sorted: &[ProcessId]) -> ProcessId {
+        match *self {
+            Self::KthClosest(k) => *sorted.get(k).unwrap_or_else(|| {
+                panic!("there should be at least {} processes per shard", k + 1)
+            }),
+            Self::Fixed(process_id) => {
+                // the ids of the processes in each shard are consecutive (see
+                // `util::process_ids`)
+                let n = sorted.len();
+                let first = sorted
+                    .iter()
+                    .min()
+                    .expect("there should be at least one process per shard");
+                let position = (process_id as usize - 1) % n;
+                first + position as ProcessId
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select() {
+        // processes of the first and second shard (n = 3)
+        let shard_0 = vec![2, 3, 1];
+        let shard_1 = vec![5, 6, 4];
+
+        let selection = ProcessSelection::default();
+        assert_eq!(selection.select(&shard_0), 2);
+        assert_eq!(selection.select(&shard_1), 5);
+
+        let selection = ProcessSelection::KthClosest(2);
+        assert_eq!(selection.select(&shard_0), 1);
+        assert_eq!(selection.select(&shard_1), 4);
+
+        let selection = ProcessSelection::Fixed(3);
+        assert_eq!(selection.select(&shard_0), 3);
+        assert_eq!(selection.select(&shard_1), 6);
+    }
+}
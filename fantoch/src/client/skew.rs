@@ -0,0 +1,184 @@
+use crate::kvs::Key;
+use crate::HashMap;
+use serde::{Deserialize, Serialize};
+
+// maximum number of keys tracked by `KeySkew`; the counts of the keys tracked
+// are overestimated by at most `total / KEY_SKEW_CAPACITY`
+const KEY_SKEW_CAPACITY: usize = 100;
+
+// number of most accessed keys considered when estimating the Zipf
+// coefficient; since only the least accessed keys tracked can have their
+// counts overestimated, these should be accurate
+const ZIPF_FIT_KEYS: usize = 10;
+
+/// Online estimator of the key-access skew of the commands issued by clients.
+/// It keeps the most accessed keys using the Space-Saving algorithm (i.e.
+/// with bounded memory), which is enough to estimate the Zipf coefficient of
+/// the workload actually issued.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KeySkew {
+    // mapping from each key tracked to its (estimated) number of accesses
+    counts: HashMap<Key, u64>,
+    // total number of accesses recorded
+    total: u64,
+}
+
+impl KeySkew {
+    /// Creates an empty `KeySkew`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an access to `key`.
+    pub fn record(&mut self, key: &Key) {
+        self.total += 1;
+        if let Some(count) = self.counts.get_mut(key) {
+            *count += 1;
+        } else if self.counts.len() < KEY_SKEW_CAPACITY {
+            self.counts.insert(key.clone(), 1);
+        } else {
+            // replace the least accessed key, inheriting its count
+            let (min_key, min_count) = self
+                .counts
+                .iter()
+                .min_by_key(|(_, count)| **count)
+                .map(|(key, count)| (key.clone(), *count))
+                .expect("there should be at least one key tracked");
+            self.counts.remove(&min_key);
+            self.counts.insert(key.clone(), min_count + 1);
+        }
+    }
+
+    /// Merges two estimators, keeping only the most accessed keys.
+    pub fn merge(&mut self, other: &Self) {
+        self.total += other.total;
+        for (key, count) in other.counts.iter() {
+            *self.counts.entry(key.clone()).or_default() += count;
+        }
+        if self.counts.len() > KEY_SKEW_CAPACITY {
+            let top = self.top_k(KEY_SKEW_CAPACITY);
+            self.counts = top.into_iter().collect();
+        }
+    }
+
+    /// Returns the total number of accesses recorded.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the (at most) `k` most accessed keys along with their
+    /// (estimated) number of accesses, sorted from the most to the least
+    /// accessed.
+    pub fn top_k(&self, k: usize) -> Vec<(Key, u64)> {
+        let mut top: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(key, count)| (key.clone(), *count))
+            .collect();
+        // break ties by key so that the result is deterministic
+        top.sort_unstable_by(|(key_a, count_a), (key_b, count_b)| {
+            count_b.cmp(count_a).then_with(|| key_a.cmp(key_b))
+        });
+        top.truncate(k);
+        top
+    }
+
+    /// Estimates the Zipf coefficient of the accesses recorded, i.e. the `s`
+    /// such that the frequency of the key with rank `r` is proportional to
+    /// `1 / r^s`, by fitting a line to the log-log rank-frequency plot of the
+    /// most accessed keys. Returns `None` if less than two keys were accessed.
+    pub fn zipf_coefficient(&self) -> Option<f64> {
+        let points: Vec<_> = self
+            .top_k(ZIPF_FIT_KEYS)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, count))| {
+                let rank = (index + 1) as f64;
+                (rank.ln(), (count as f64).ln())
+            })
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+
+        // least squares fit
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (cov, var) =
+            points.iter().fold((0f64, 0f64), |(cov, var), (x, y)| {
+                let dx = x - mean_x;
+                (cov + dx * (y - mean_y), var + dx * dx)
+            });
+        let slope = cov / var;
+        Some(-slope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_n(skew: &mut KeySkew, key: &str, n: u64) {
+        let key = Key::from(key);
+        (0..n).for_each(|_| skew.record(&key));
+    }
+
+    #[test]
+    fn key_skew_flow() {
+        let mut skew = KeySkew::new();
+        assert_eq!(skew.zipf_coefficient(), None);
+
+        // a single key tells nothing about the skew
+        record_n(&mut skew, "A", 10);
+        assert_eq!(skew.zipf_coefficient(), None);
+
+        // uniform accesses have coefficient 0
+        record_n(&mut skew, "B", 10);
+        record_n(&mut skew, "C", 10);
+        assert_eq!(skew.total(), 30);
+        assert_eq!(skew.zipf_coefficient(), Some(0f64));
+
+        // accesses that follow a zipf with coefficient 1
+        let mut skew = KeySkew::new();
+        record_n(&mut skew, "A", 120);
+        record_n(&mut skew, "B", 60);
+        record_n(&mut skew, "C", 40);
+        record_n(&mut skew, "D", 30);
+        let coefficient = skew.zipf_coefficient().unwrap();
+        assert!((coefficient - 1f64).abs() < 1e-9);
+        assert_eq!(
+            skew.top_k(2),
+            vec![(Key::from("A"), 120), (Key::from("B"), 60)]
+        );
+
+        // merging sums the accesses
+        let mut other = KeySkew::new();
+        record_n(&mut other, "D", 100);
+        skew.merge(&other);
+        assert_eq!(skew.total(), 350);
+        assert_eq!(
+            skew.top_k(2),
+            vec![(Key::from("D"), 130), (Key::from("A"), 120)]
+        );
+    }
+
+    #[test]
+    fn key_skew_bounded() {
+        let mut skew = KeySkew::new();
+        record_n(&mut skew, "hot", 1000);
+        for key in 0..(10 * KEY_SKEW_CAPACITY) {
+            skew.record(&key.to_string());
+        }
+
+        // only a bounded number of keys is tracked, but the hot key is kept
+        assert_eq!(skew.counts.len(), KEY_SKEW_CAPACITY);
+        assert_eq!(skew.top_k(1), vec![(Key::from("hot"), 1000)]);
+
+        // merging keeps it bounded
+        let other = skew.clone();
+        skew.merge(&other);
+        assert_eq!(skew.counts.len(), KEY_SKEW_CAPACITY);
+        assert_eq!(skew.top_k(1), vec![(Key::from("hot"), 2000)]);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::info;
+use std::time::Duration;
+
+// prefix of the line logged with the stats of clients (see `ClientStats::log`)
+const CLIENT_STATS: &str = "client stats";
+
+/// Summary of the progress of all clients running in some machine. These are
+/// periodically logged while clients run, so that the experiment harness can
+/// follow runs (and abort the ones where no command completes) without
+/// waiting for the client data, which is only written once clients end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientStats {
+    /// number of commands completed so far
+    pub completed: u64,
+    /// number of commands completed per second since the previous stats
+    pub throughput: u64,
+}
+
+impl ClientStats {
+    /// Computes the stats given the number of commands completed so far, and
+    /// the number of commands completed when the previous stats were computed
+    /// (`elapsed` ago).
+    pub fn new(completed: u64, previous: u64, elapsed: Duration) -> Self {
+        let millis = std::cmp::max(elapsed.as_millis() as u64, 1);
+        let throughput = completed.saturating_sub(previous) * 1000 / millis;
+        Self {
+            completed,
+            throughput,
+        }
+    }
+
+    /// Logs the stats in a line that can be parsed back by
+    /// `ClientStats::from_stats_line`.
+    pub fn log(&self) {
+        info!(
+            "{}: {} completed, {} per second",
+            CLIENT_STATS, self.completed, self.throughput
+        );
+    }
+
+    /// Parses the stats from a line logged by `ClientStats::log`. Returns
+    /// `None` if the line doesn't report stats.
+    pub fn from_stats_line(line: &str) -> Option<Self> {
+        let line = &line[line.find(CLIENT_STATS)? + CLIENT_STATS.len()..];
+        let line = line.strip_prefix(": ")?;
+        let (completed, line) = line.split_once(" completed, ")?;
+        let (throughput, _) = line.split_once(" per second")?;
+        Some(Self {
+            completed: completed.parse().ok()?,
+            throughput: throughput.parse().ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_line() {
+        let stats = ClientStats::new(1500, 500, Duration::from_secs(2));
+        assert_eq!(stats.throughput, 500);
+        let line = format!(
+            "2021-01-01T00:00:00 INFO fantoch::client::stats: {}: {} completed, {} per second",
+            CLIENT_STATS, stats.completed, stats.throughput
+        );
+        assert_eq!(ClientStats::from_stats_line(&line), Some(stats));
+
+        // other lines are ignored
+        assert_eq!(ClientStats::from_stats_line("all clients ended"), None);
+        let line = format!("{}: many completed, 0 per second", CLIENT_STATS);
+        assert_eq!(ClientStats::from_stats_line(&line), None);
+    }
+}
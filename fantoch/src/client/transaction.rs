@@ -0,0 +1,230 @@
+use crate::command::Command;
+use crate::id::{Rifl, ShardId};
+use crate::kvs::{KVOp, KVOpResult, Key, Value};
+use crate::HashMap;
+
+/// An interactive transaction with optimistic concurrency control (OCC).
+///
+/// Between `Transaction::begin` and `Transaction::commit`, the values read by
+/// the transaction (e.g. with read-only commands issued by the client) are
+/// recorded in its read set, while its writes are buffered locally. On commit,
+/// the transaction becomes a single multi-key command that:
+/// - reads again every key in the read set, so that the read set can be
+///   validated
+/// - applies all the buffered writes
+///
+/// Once the commit command is executed, `Transaction::validate` checks whether
+/// the keys in the read set still had the values read by the transaction; if
+/// not, the transaction aborts.
+///
+/// Note that the `KVStore` has no conditional operations, and thus the writes
+/// of a transaction that aborts are still applied. Aborted transactions should
+/// thus only be used to study contention (e.g. by counting and retrying them).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    shard_count: u64,
+    // values read by the transaction (`None` if a key had no value)
+    reads: HashMap<Key, KVOpResult>,
+    // writes buffered by the transaction (either a `Put` or a `Delete`)
+    writes: HashMap<Key, KVOp>,
+}
+
+impl Transaction {
+    /// Begins a new transaction in a system with `shard_count` shards.
+    pub fn begin(shard_count: usize) -> Self {
+        Self {
+            shard_count: shard_count as u64,
+            reads: HashMap::new(),
+            writes: HashMap::new(),
+        }
+    }
+
+    /// Returns the value of `key` as seen by this transaction, if the key was
+    /// written or read before by the transaction. Otherwise, `None` is
+    /// returned, and the client should read the key and record its value with
+    /// `Transaction::record_read`.
+    pub fn get(&self, key: &Key) -> Option<KVOpResult> {
+        match self.writes.get(key) {
+            Some(KVOp::Put(value)) | Some(KVOp::PutTtl(value, _)) => {
+                Some(Some(value.clone()))
+            }
+            Some(KVOp::Delete) => Some(None),
+            Some(KVOp::Get)
+            | Some(KVOp::Increment(_))
+            | Some(KVOp::SetAdd(_)) => {
+                unreachable!("only Put and Delete ops are buffered")
+            }
+            None => self.reads.get(key).cloned(),
+        }
+    }
+
+    /// Records the value read for `key`. If the key was already read by this
+    /// transaction, the first value read is kept (as that's the one the
+    /// transaction depends on).
+    pub fn record_read(&mut self, key: Key, value: KVOpResult) {
+        self.reads.entry(key).or_insert(value);
+    }
+
+    /// Buffers a write of `value` to `key`.
+    pub fn put(&mut self, key: Key, value: Value) {
+        self.writes.insert(key, KVOp::Put(value));
+    }
+
+    /// Buffers the deletion of `key`.
+    pub fn delete(&mut self, key: Key) {
+        self.writes.insert(key, KVOp::Delete);
+    }
+
+    /// Returns the keys read by this transaction.
+    pub fn read_set(&self) -> impl Iterator<Item = &Key> {
+        self.reads.keys()
+    }
+
+    /// Returns the keys written by this transaction.
+    pub fn write_set(&self) -> impl Iterator<Item = &Key> {
+        self.writes.keys()
+    }
+
+    /// Checks if the transaction is read-only.
+    pub fn read_only(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Creates the command that commits this transaction, along with the
+    /// shard to which it should be submitted (the lowest shard accessed).
+    pub fn commit(&self, rifl: Rifl) -> (ShardId, Command) {
+        let mut ops: HashMap<Key, Vec<KVOp>> = HashMap::new();
+        // validate reads before applying writes
+        for key in self.reads.keys() {
+            ops.entry(key.clone()).or_default().push(KVOp::Get);
+        }
+        for (key, op) in self.writes.iter() {
+            ops.entry(key.clone()).or_default().push(op.clone());
+        }
+        assert!(
+            !ops.is_empty(),
+            "a transaction should access at least one key"
+        );
+
+        // place each key on its shard
+        let mut shard_to_ops: HashMap<ShardId, HashMap<_, _>> = HashMap::new();
+        for (key, key_ops) in ops {
+            let shard_id = crate::util::key_hash(&key) % self.shard_count;
+            shard_to_ops
+                .entry(shard_id)
+                .or_default()
+                .insert(key, key_ops);
+        }
+        let target_shard = *shard_to_ops
+            .keys()
+            .min()
+            .expect("there should be a target shard");
+        (target_shard, Command::new(rifl, shard_to_ops))
+    }
+
+    /// Validates the read set of this transaction given the results of its
+    /// commit command (from all the shards accessed). Returns a boolean
+    /// indicating whether the transaction committed.
+    pub fn validate(&self, results: &HashMap<Key, Vec<KVOpResult>>) -> bool {
+        self.reads.iter().all(|(key, read)| {
+            // the first result on each key is the one of the validation read
+            // (there's none if the ops on the key failed to execute)
+            let key_results = results
+                .get(key)
+                .expect("commit results should contain all keys read");
+            key_results.first() == Some(read)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{CommandResult, CommandResultBuilder};
+    use crate::kvs::KVStore;
+
+    // Executes the commit of `tx` in `store`, returning its results.
+    fn execute_commit(
+        tx: &Transaction,
+        rifl: Rifl,
+        store: &mut KVStore,
+    ) -> CommandResult {
+        let (target_shard, cmd) = tx.commit(rifl);
+        assert_eq!(target_shard, 0);
+        let mut builder =
+            CommandResultBuilder::new(rifl, cmd.total_key_count());
+        for result in cmd.execute(target_shard, store) {
+            builder.add_partial(result.key, result.partial_results);
+        }
+        builder.into()
+    }
+
+    #[test]
+    fn transaction_get() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let x = String::from("x");
+        let y = String::from("y");
+
+        let shard_count = 1;
+        let mut tx = Transaction::begin(shard_count);
+        assert!(tx.read_only());
+
+        // nothing is known about keys that were not read or written
+        assert_eq!(tx.get(&key_a), None);
+
+        // only the first read is recorded
+        tx.record_read(key_a.clone(), Some(x.clone()));
+        tx.record_read(key_a.clone(), Some(y.clone()));
+        assert_eq!(tx.get(&key_a), Some(Some(x.clone())));
+
+        // writes are seen by the transaction
+        tx.put(key_a.clone(), y.clone());
+        assert_eq!(tx.get(&key_a), Some(Some(y)));
+        tx.delete(key_b.clone());
+        assert_eq!(tx.get(&key_b), Some(None));
+        assert!(!tx.read_only());
+
+        assert_eq!(tx.read_set().collect::<Vec<_>>(), vec![&key_a]);
+        let mut write_set: Vec<_> = tx.write_set().collect();
+        write_set.sort();
+        assert_eq!(write_set, vec![&key_a, &key_b]);
+    }
+
+    #[test]
+    fn transaction_commit_and_abort() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let x = String::from("x");
+        let y = String::from("y");
+
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+        let shard_count = 1;
+
+        // two transactions read `A` (which has no value) and write to `B`
+        let mut tx1 = Transaction::begin(shard_count);
+        tx1.record_read(key_a.clone(), None);
+        tx1.put(key_b.clone(), x.clone());
+        let mut tx2 = tx1.clone();
+        // `tx2` also writes to `A`
+        tx2.put(key_a.clone(), y.clone());
+
+        // `tx2` commits first
+        let result = execute_commit(&tx2, Rifl::new(1, 1), &mut store);
+        assert!(tx2.validate(result.results()));
+
+        // `tx1` aborts as `A` changed since it was read
+        let result = execute_commit(&tx1, Rifl::new(2, 1), &mut store);
+        assert!(!tx1.validate(result.results()));
+
+        // a transaction that reads the new value of `A` commits
+        let mut tx3 = Transaction::begin(shard_count);
+        tx3.record_read(key_a.clone(), Some(y));
+        tx3.delete(key_a.clone());
+        let result = execute_commit(&tx3, Rifl::new(3, 1), &mut store);
+        assert!(tx3.validate(result.results()));
+        assert_eq!(store.test_execute(&key_a, KVOp::Get), None);
+        assert_eq!(store.test_execute(&key_b, KVOp::Get), Some(x));
+    }
+}
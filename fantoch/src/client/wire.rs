@@ -0,0 +1,106 @@
+use crate::command::Command;
+use crate::id::{ClientId, Epoch, ProcessId, Rifl, ShardId};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+// size (in bytes) of the length header that precedes each frame
+const LENGTH_HEADER_SIZE: usize = 4;
+
+// A `ProcessHi` is sent by processes when a connection is set up (both to
+// other processes and to clients). The epoch identifies the incarnation of the
+// process, which allows peers to tell a restarted process apart from its
+// previous incarnation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessHi {
+    pub process_id: ProcessId,
+    pub shard_id: ShardId,
+    pub epoch: Epoch,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientHi(pub Vec<ClientId>);
+
+// If the command touches a single shard, then a `Submit` will be sent to that
+// shard. If the command touches more than on shard, a `Submit` will be sent to
+// one targetted shard and a `Register` will be sent to the remaining shards to
+// make sure that the client will eventually receive a `CommandResult` from all
+// shards.
+// A `Cancel` can be sent to the shard where the command was submitted in order
+// to abort it (if it has not yet been committed and the protocol supports it).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientToServer {
+    Submit(Command),
+    Register(Command),
+    Cancel(Rifl),
+}
+
+/// Writes `value` as a single frame: a 4-byte big-endian length header
+/// followed by the value serialized with `bincode`. This is the framing used by
+/// the runner's connections, which allows clients to talk to processes without
+/// depending on the runner.
+pub fn write_frame<W, V>(writer: &mut W, value: &V) -> io::Result<()>
+where
+    W: Write,
+    V: Serialize,
+{
+    let bytes = bincode::serialize(value).map_err(invalid_data)?;
+    let length = u32::try_from(bytes.len()).map_err(invalid_data)?;
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(&bytes)
+}
+
+/// Reads a single frame written with `write_frame` (or by the runner's
+/// connections).
+pub fn read_frame<R, V>(reader: &mut R) -> io::Result<V>
+where
+    R: Read,
+    V: DeserializeOwned,
+{
+    let mut length = [0; LENGTH_HEADER_SIZE];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    bincode::deserialize(&bytes).map_err(invalid_data)
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_roundtrip() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, &ClientHi(vec![1, 2])).expect("write");
+        write_frame(&mut buffer, &ClientToServer::Cancel(Rifl::new(1, 10)))
+            .expect("write");
+
+        // each frame starts with its length
+        let hi_size = bincode::serialized_size(&ClientHi(vec![1, 2]))
+            .expect("serialized size") as usize;
+        assert_eq!(
+            &buffer[..LENGTH_HEADER_SIZE],
+            &(hi_size as u32).to_be_bytes()
+        );
+
+        let mut reader = &buffer[..];
+        let ClientHi(client_ids) = read_frame(&mut reader).expect("read");
+        assert_eq!(client_ids, vec![1, 2]);
+        let msg: ClientToServer = read_frame(&mut reader).expect("read");
+        assert!(
+            matches!(msg, ClientToServer::Cancel(rifl) if rifl == Rifl::new(1, 10))
+        );
+
+        // reading from an exhausted reader fails
+        assert!(read_frame::<_, ClientHi>(&mut reader).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use crate::client::key_gen::{KeyGen, KeyGenState};
 use crate::command::Command;
 use crate::id::{RiflGen, ShardId};
-use crate::kvs::{KVOp, Key, Value};
+use crate::kvs::{KVOp, Key, StateMachineKind, Value};
 use crate::trace;
 use crate::HashMap;
 use rand::distributions::Alphanumeric;
@@ -9,7 +9,7 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::iter;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Workload {
     /// number of shards
     shard_count: u64,
@@ -23,6 +23,33 @@ pub struct Workload {
     read_only_percentage: usize,
     /// size of payload in command (in bytes)
     payload_size: usize,
+    /// percentage of commands whose keys are all on the same shard
+    #[serde(default)]
+    shard_locality: usize,
+    /// if set, the clients only need an ack for commands that aren't
+    /// read-only (and thus their results are not sent back)
+    #[serde(default)]
+    ack_only_writes: bool,
+    /// if set, the random choices of each client are drawn from an rng seeded
+    /// with this value (and the client identifier)
+    #[serde(default)]
+    seed: Option<u64>,
+    /// if set, values are written with this TTL (in milliseconds)
+    #[serde(default)]
+    ttl: Option<u64>,
+    /// if set, only this percentage of read-only commands gets back the values
+    /// read; the remaining ones only get back their hashes
+    #[serde(default)]
+    value_sample_percentage: Option<usize>,
+    /// if set, closed-loop clients issue up to this many commands before
+    /// waiting for any of them to complete; otherwise, they issue one command
+    /// at a time
+    #[serde(default)]
+    max_in_flight: Option<usize>,
+    /// if set, the commands that aren't read-only target this state machine
+    /// (see `Workload::set_state_machine`)
+    #[serde(default)]
+    state_machine: Option<StateMachineKind>,
     /// number of commands already issued in this workload
     command_count: usize,
 }
@@ -39,6 +66,7 @@ impl Workload {
         if let KeyGen::ConflictPool {
             pool_size,
             conflict_rate,
+            ..
         } = key_gen
         {
             assert!(
@@ -55,6 +83,20 @@ impl Workload {
         }
         // by default, the read-only percentage is 0
         let read_only_percentage = 0;
+        // by default, keys are not colocated
+        let shard_locality = 0;
+        // by default, the results of all commands are sent back
+        let ack_only_writes = false;
+        // by default, workloads are not seeded
+        let seed = None;
+        // by default, values written never expire
+        let ttl = None;
+        // by default, all read-only commands get back the values read
+        let value_sample_percentage = None;
+        // by default, clients issue one command at a time
+        let max_in_flight = None;
+        // by default, commands target the key-value store
+        let state_machine = None;
         Self {
             shard_count: shard_count as u64,
             keys_per_command,
@@ -62,6 +104,13 @@ impl Workload {
             commands_per_client,
             read_only_percentage,
             payload_size,
+            shard_locality,
+            ack_only_writes,
+            seed,
+            ttl,
+            value_sample_percentage,
+            max_in_flight,
+            state_machine,
             command_count: 0,
         }
     }
@@ -109,6 +158,131 @@ impl Workload {
         self.payload_size
     }
 
+    /// Returns the percentage of commands to be generated by this workload
+    /// whose keys are all on the same shard.
+    pub fn shard_locality(&self) -> usize {
+        self.shard_locality
+    }
+
+    /// Sets the percentage of commands to be generated by this workload whose
+    /// keys are all on the same shard. The keys of the remaining commands are
+    /// generated independently, and thus may or may not be on the same shard.
+    pub fn set_shard_locality(&mut self, shard_locality: usize) {
+        assert!(
+            shard_locality <= 100,
+            "the shard locality must be less or equal to 100"
+        );
+        if shard_locality > 0 && self.keys_per_command > 1 {
+            assert!(
+                matches!(self.key_gen, KeyGen::Zipf { .. }),
+                "invalid workload; keys can only be colocated with the zipf key generator"
+            );
+        }
+        self.shard_locality = shard_locality;
+    }
+
+    /// Returns whether the clients only need an ack for the commands generated
+    /// by this workload that aren't read-only.
+    pub fn ack_only_writes(&self) -> bool {
+        self.ack_only_writes
+    }
+
+    /// Sets whether the clients only need an ack for the commands generated by
+    /// this workload that aren't read-only.
+    pub fn set_ack_only_writes(&mut self, ack_only_writes: bool) {
+        self.ack_only_writes = ack_only_writes;
+    }
+
+    /// Returns the seed of the workload (if any).
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Sets the seed of the workload: if set, each client generates the same
+    /// commands across runs.
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    /// Returns the TTL (in milliseconds) of the values written by this
+    /// workload (if any).
+    pub fn ttl(&self) -> Option<u64> {
+        self.ttl
+    }
+
+    /// Sets the TTL (in milliseconds) of the values written by this workload:
+    /// if set, the values expire once the TTL has passed since the command
+    /// that wrote them was issued (see `KVOp::PutTtl`).
+    pub fn set_ttl(&mut self, ttl: Option<u64>) {
+        self.ttl = ttl;
+    }
+
+    /// Returns the percentage of read-only commands generated by this workload
+    /// that get back the values read (if not all).
+    pub fn value_sample_percentage(&self) -> Option<usize> {
+        self.value_sample_percentage
+    }
+
+    /// Sets the percentage of read-only commands generated by this workload
+    /// that get back the values read: if set, the remaining ones only get back
+    /// the hashes of the values, which saves bandwidth with many clients,
+    /// while the values of the sampled ones can still be checked.
+    pub fn set_value_sample_percentage(
+        &mut self,
+        value_sample_percentage: Option<usize>,
+    ) {
+        if let Some(percentage) = value_sample_percentage {
+            assert!(
+                percentage <= 100,
+                "the value sample percentage must be less or equal to 100"
+            );
+        }
+        self.value_sample_percentage = value_sample_percentage;
+    }
+
+    /// Returns the maximum number of commands that closed-loop clients have
+    /// in flight (if more than one).
+    pub fn max_in_flight(&self) -> Option<usize> {
+        self.max_in_flight
+    }
+
+    /// Sets the maximum number of commands that closed-loop clients have in
+    /// flight: if set, clients issue up to this many commands before waiting
+    /// for any of them to complete, and then issue a new command each time
+    /// one completes. This sits between closed-loop clients that issue one
+    /// command at a time and open-loop clients that issue commands at a fixed
+    /// rate, regardless of how many are in flight.
+    pub fn set_max_in_flight(&mut self, max_in_flight: Option<usize>) {
+        if let Some(max_in_flight) = max_in_flight {
+            assert!(
+                max_in_flight > 0,
+                "the maximum number of commands in flight must be at least 1"
+            );
+        }
+        self.max_in_flight = max_in_flight;
+    }
+
+    /// Returns the state machine targeted by this workload (if not the
+    /// key-value store).
+    pub fn state_machine(&self) -> Option<StateMachineKind> {
+        self.state_machine
+    }
+
+    /// Sets the state machine targeted by this workload (see `StateMachine`):
+    /// if set, the commands that aren't read-only are, for each key:
+    /// - with a `StateMachineKind::Lock`, acquires (`KVOp::Put`) or releases
+    ///   (`KVOp::Delete`) of the lock, with the same probability
+    /// - with a `StateMachineKind::Queue`, enqueues (`KVOp::Put`) or dequeues
+    ///   (`KVOp::Delete`), with the same probability
+    /// - with a `StateMachineKind::Counter`, increments (`KVOp::Increment`) of
+    ///   the counter by one
+    pub fn set_state_machine(
+        &mut self,
+        state_machine: Option<StateMachineKind>,
+    ) {
+        self.state_machine = state_machine;
+    }
+
     /// Generate the next command.
     pub fn next_cmd(
         &mut self,
@@ -155,9 +329,8 @@ impl Workload {
         // be the shard of the first key generated
         let keys = self.gen_unique_keys(key_gen_state);
         // check if the command should be read-only
-        let read_only = super::key_gen::true_if_random_is_less_than(
-            self.read_only_percentage,
-        );
+        let read_only = key_gen_state
+            .true_if_random_is_less_than(self.read_only_percentage);
         let mut target_shard = None;
 
         for key in keys {
@@ -165,11 +338,16 @@ impl Workload {
             let op = if read_only {
                 // if read-only, the op is a `Get`
                 KVOp::Get
+            } else if let Some(state_machine) = self.state_machine {
+                self.gen_state_machine_op(state_machine, key_gen_state)
             } else {
-                // if not read-only, the op is a `Put`:
+                // if not read-only, the op is a `Put` (with a TTL, if set):
                 // - generate payload for `Put` op
-                let value = self.gen_cmd_value();
-                KVOp::Put(value)
+                let value = self.gen_cmd_value(key_gen_state);
+                match self.ttl {
+                    Some(ttl) => KVOp::PutTtl(value, ttl),
+                    None => KVOp::Put(value),
+                }
             };
             // compute key's shard and save op
             let shard_id = self.shard_id(&key);
@@ -182,23 +360,59 @@ impl Workload {
             target_shard.expect("there should be a target shard");
 
         // create command
-        (target_shard, Command::new(rifl, ops))
+        let mut cmd = Command::new(rifl, ops);
+        cmd.set_ack_only(self.ack_only_writes && !read_only);
+        // if values are sampled, read-only commands not sampled only get back
+        // the hashes of the values read
+        if let Some(percentage) = self.value_sample_percentage {
+            if read_only {
+                let sampled =
+                    key_gen_state.true_if_random_is_less_than(percentage);
+                cmd.set_hash_values(!sampled);
+            }
+        }
+        (target_shard, cmd)
     }
 
     fn gen_unique_keys(&self, key_gen_state: &mut KeyGenState) -> Vec<Key> {
+        // check if all keys should be on the shard of the first key generated
+        let colocated = self.shard_count > 1
+            && key_gen_state.true_if_random_is_less_than(self.shard_locality);
         let mut keys = Vec::with_capacity(self.keys_per_command);
         while keys.len() != self.keys_per_command {
             let key = key_gen_state.gen_cmd_key();
-            if !keys.contains(&key) {
+            let same_shard = || match keys.first() {
+                Some(first) => self.shard_id(first) == self.shard_id(&key),
+                None => true,
+            };
+            if !keys.contains(&key) && (!colocated || same_shard()) {
                 keys.push(key);
             }
         }
         keys
     }
 
+    /// Generate an op (that's not read-only) targeting `state_machine`.
+    fn gen_state_machine_op(
+        &self,
+        state_machine: StateMachineKind,
+        key_gen_state: &mut KeyGenState,
+    ) -> KVOp {
+        match state_machine {
+            StateMachineKind::Lock | StateMachineKind::Queue => {
+                if key_gen_state.true_if_random_is_less_than(50) {
+                    KVOp::Put(self.gen_cmd_value(key_gen_state))
+                } else {
+                    KVOp::Delete
+                }
+            }
+            StateMachineKind::Counter => KVOp::Increment(1),
+        }
+    }
+
     /// Generate a command payload with the payload size provided.
-    fn gen_cmd_value(&self) -> Value {
-        let mut rng = rand::thread_rng();
+    fn gen_cmd_value(&self, key_gen_state: &mut KeyGenState) -> Value {
+        let rng = key_gen_state.rng();
         iter::repeat(())
             .map(|_| rng.sample(Alphanumeric) as char)
             .take(self.payload_size)
@@ -236,6 +450,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 100,
             pool_size: POOL_SIZE,
+            pool_id: 0,
         };
         let mut workload = Workload::new(
             shard_count,
@@ -245,7 +460,7 @@ mod tests {
             payload_size,
         );
         let mut key_gen_state =
-            key_gen.initial_state(workload.shard_count(), client_id);
+            key_gen.initial_state(workload.shard_count(), client_id, None);
         let (target_shard, command) =
             workload.gen_cmd(&mut rifl_gen, &mut key_gen_state);
         assert_eq!(target_shard, 0);
@@ -258,6 +473,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 0,
             pool_size: POOL_SIZE,
+            pool_id: 0,
         };
         let mut workload = Workload::new(
             shard_count,
@@ -267,7 +483,7 @@ mod tests {
             payload_size,
         );
         let mut key_gen_state =
-            key_gen.initial_state(workload.shard_count(), client_id);
+            key_gen.initial_state(workload.shard_count(), client_id, None);
         let (target_shard, command) =
             workload.gen_cmd(&mut rifl_gen, &mut key_gen_state);
         assert_eq!(target_shard, 0);
@@ -290,6 +506,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 100,
             pool_size: POOL_SIZE,
+            pool_id: 0,
         };
         let mut workload = Workload::new(
             shard_count,
@@ -299,7 +516,7 @@ mod tests {
             payload_size,
         );
         let mut key_gen_state =
-            key_gen.initial_state(workload.shard_count(), client_id);
+            key_gen.initial_state(workload.shard_count(), client_id, None);
 
         // check total and issued commands
         assert_eq!(workload.commands_per_client(), commands_per_client);
@@ -313,7 +530,7 @@ mod tests {
                 // since there's a single shard, keys should be on shard 0
                 assert_eq!(target_shard, 0);
                 let (key, ops) = cmd.into_iter(target_shard).next().unwrap();
-                let mut ops = ops.as_ref().clone();
+                let mut ops = ops.into_ops();
                 // since the conflict is 100, the key should be `CONFLICT_COLOR`
                 assert_eq!(key, CONFLICT_COLOR);
                 assert_eq!(ops.len(), 1);
@@ -347,6 +564,105 @@ mod tests {
         assert!(workload.finished());
     }
 
+    #[test]
+    fn gen_cmd_with_ttl() {
+        let client_id = 1;
+        let mut rifl_gen = RiflGen::new(client_id);
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 100,
+            pool_size: POOL_SIZE,
+            pool_id: 0,
+        };
+        let mut workload = Workload::new(1, key_gen, 1, 10, 10);
+        let mut key_gen_state =
+            key_gen.initial_state(workload.shard_count(), client_id, None);
+
+        // with a ttl, writes are issued with it
+        let ttl = 100;
+        workload.set_ttl(Some(ttl));
+        let (target_shard, cmd) =
+            workload.gen_cmd(&mut rifl_gen, &mut key_gen_state);
+        let (_, ops) = cmd.into_iter(target_shard).next().unwrap();
+        assert!(
+            matches!(ops.ops()[0], KVOp::PutTtl(_, op_ttl) if op_ttl == ttl)
+        );
+    }
+
+    #[test]
+    fn gen_cmd_with_state_machine() {
+        let client_id = 1;
+        let mut rifl_gen = RiflGen::new(client_id);
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 100,
+            pool_size: POOL_SIZE,
+            pool_id: 0,
+        };
+        let commands_per_client = 1000;
+        let mut workload =
+            Workload::new(1, key_gen, 1, commands_per_client, 10);
+        let mut key_gen_state =
+            key_gen.initial_state(workload.shard_count(), client_id, None);
+        let mut gen_op = |workload: &mut Workload| {
+            let (target_shard, cmd) =
+                workload.gen_cmd(&mut rifl_gen, &mut key_gen_state);
+            let (_, ops) = cmd.into_iter(target_shard).next().unwrap();
+            ops.ops()[0].clone()
+        };
+
+        // with a counter, writes are increments
+        workload.set_state_machine(Some(StateMachineKind::Counter));
+        assert_eq!(gen_op(&mut workload), KVOp::Increment(1));
+
+        // with a queue (or a lock), writes are either puts or deletes
+        workload.set_state_machine(Some(StateMachineKind::Queue));
+        let puts = (0..100)
+            .filter(|_| match gen_op(&mut workload) {
+                KVOp::Put(_) => true,
+                KVOp::Delete => false,
+                op => panic!("unexpected op {:?}", op),
+            })
+            .count();
+        assert!(puts > 25 && puts < 75);
+
+        // reads are still gets
+        workload.set_read_only_percentage(100);
+        assert_eq!(gen_op(&mut workload), KVOp::Get);
+    }
+
+    #[test]
+    fn gen_cmd_with_value_sample_percentage() {
+        let client_id = 1;
+        let mut rifl_gen = RiflGen::new(client_id);
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 100,
+            pool_size: POOL_SIZE,
+            pool_id: 0,
+        };
+        let commands_per_client = 1000;
+        let mut workload =
+            Workload::new(1, key_gen, 1, commands_per_client, 10);
+        workload.set_read_only_percentage(100);
+        let mut key_gen_state =
+            key_gen.initial_state(workload.shard_count(), client_id, None);
+
+        // by default, all reads get back the values read
+        let (_, cmd) = workload.gen_cmd(&mut rifl_gen, &mut key_gen_state);
+        assert!(!cmd.hash_values());
+
+        // with a sample percentage, only some of them do
+        let sample_percentage = 10;
+        workload.set_value_sample_percentage(Some(sample_percentage));
+        let sampled = (0..commands_per_client)
+            .filter(|_| {
+                let (_, cmd) =
+                    workload.gen_cmd(&mut rifl_gen, &mut key_gen_state);
+                !cmd.hash_values()
+            })
+            .count();
+        let expected = commands_per_client * sample_percentage / 100;
+        assert!(sampled > expected / 2 && sampled < expected * 2);
+    }
+
     #[test]
     fn conflict_rate() {
         for conflict_rate in vec![1, 2, 10, 50] {
@@ -364,6 +680,7 @@ mod tests {
             let key_gen = KeyGen::ConflictPool {
                 conflict_rate,
                 pool_size: POOL_SIZE,
+                pool_id: 0,
             };
             let mut workload = Workload::new(
                 shard_count,
@@ -373,7 +690,7 @@ mod tests {
                 payload_size,
             );
             let mut key_gen_state =
-                key_gen.initial_state(workload.shard_count(), client_id);
+                key_gen.initial_state(workload.shard_count(), client_id, None);
 
             // count conflicting commands
             let mut conflict_color_count = 0;
@@ -397,6 +714,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn shard_locality() {
+        // create rilf gen
+        let client_id = 1;
+        let mut rifl_gen = RiflGen::new(client_id);
+
+        // general config
+        let shard_count = 4;
+        let keys_per_command = 3;
+        let commands_per_client = 1000;
+        let payload_size = 0;
+
+        // create workload
+        let key_gen = KeyGen::Zipf {
+            coefficient: 0.1,
+            total_keys_per_shard: 1_000_000,
+        };
+        let workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        assert_eq!(workload.shard_locality(), 0);
+
+        // count the commands that access a single shard
+        let mut single_shard_commands = |shard_locality| {
+            let mut workload = workload;
+            workload.set_shard_locality(shard_locality);
+            let mut key_gen_state =
+                key_gen.initial_state(workload.shard_count(), client_id, None);
+            let mut count = 0;
+            while let Some((target_shard, cmd)) =
+                workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+            {
+                assert_eq!(cmd.total_key_count(), keys_per_command);
+                if cmd.shard_count() == 1 {
+                    assert!(cmd.replicated_by(&target_shard));
+                    count += 1;
+                }
+            }
+            count
+        };
+
+        // with full locality, all commands access a single shard
+        assert_eq!(single_shard_commands(100), commands_per_client);
+
+        // without locality, commands access a single shard only by chance
+        // (with probability 1/16 = (1/4)^2)
+        assert!(single_shard_commands(0) < commands_per_client / 4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn shard_locality_with_conflict_pool() {
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 50,
+            pool_size: 1,
+            pool_id: 0,
+        };
+        let mut workload = Workload::new(2, key_gen, 2, 1, 0);
+        workload.set_shard_locality(50);
+    }
+
     #[test]
     fn two_shards() {
         // in order for this test to pass, `check_two_shards` should generate a
@@ -428,7 +810,7 @@ mod tests {
             payload_size,
         );
         let mut key_gen_state =
-            key_gen.initial_state(workload.shard_count(), client_id);
+            key_gen.initial_state(workload.shard_count(), client_id, None);
 
         let (target_shard, cmd) = workload
             .next_cmd(&mut rifl_gen, &mut key_gen_state)
@@ -449,4 +831,111 @@ mod tests {
         // - 1 key in shard 1
         cmd.key_count(0) == 1 && cmd.key_count(1) == 1
     }
+
+    #[test]
+    fn seeded_workload() {
+        // general config
+        let shard_count = 2;
+        let keys_per_command = 2;
+        let commands_per_client = 10;
+        let payload_size = 10;
+
+        // create seeded workload
+        let key_gen = KeyGen::Zipf {
+            coefficient: 0.5,
+            total_keys_per_shard: 1_000_000,
+        };
+        let mut workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        workload.set_read_only_percentage(50);
+        workload.set_seed(Some(42));
+
+        // generates all the commands of a client; since the order in which
+        // `Command` lists its keys depends on the hasher (which is not
+        // seeded), only the rifl and the (sorted) operations are compared
+        let gen_cmds = |client_id| {
+            let mut workload = workload;
+            let mut rifl_gen = RiflGen::new(client_id);
+            let mut key_gen_state = key_gen.initial_state(
+                workload.shard_count(),
+                client_id,
+                workload.seed(),
+            );
+            std::iter::from_fn(|| {
+                workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+            })
+            .map(|(target_shard, cmd)| {
+                let mut ops: Vec<_> = cmd
+                    .shards()
+                    .flat_map(|shard_id| cmd.iter(*shard_id))
+                    .map(|(key, ops)| (key.clone(), ops.clone()))
+                    .collect();
+                ops.sort_by(|(key_a, _), (key_b, _)| key_a.cmp(key_b));
+                (target_shard, cmd.rifl(), ops)
+            })
+            .collect::<Vec<_>>()
+        };
+
+        // the same client generates the same commands across runs
+        let cmds = gen_cmds(1);
+        assert_eq!(cmds.len(), commands_per_client);
+        assert_eq!(cmds, gen_cmds(1));
+
+        // but different clients generate different commands
+        assert_ne!(cmds, gen_cmds(2));
+    }
+
+    #[test]
+    fn custom_key_gen() {
+        use crate::client::KeyGenerator;
+        use crate::id::ClientId;
+        use rand::rngs::StdRng;
+
+        // key generator in which each client accesses one of its own 10 keys
+        struct OwnKeys;
+
+        impl KeyGenerator for OwnKeys {
+            fn name(&self) -> &'static str {
+                "own_keys"
+            }
+
+            fn gen_cmd_key(
+                &self,
+                client_id: ClientId,
+                _shard_count: usize,
+                rng: &mut StdRng,
+            ) -> Key {
+                format!("{}_{}", client_id, rng.gen_range(0..10))
+            }
+        }
+
+        static OWN_KEYS: OwnKeys = OwnKeys;
+
+        // create workload with the custom key generator
+        let client_id = 1;
+        let mut rifl_gen = RiflGen::new(client_id);
+        let key_gen = KeyGen::custom(&OWN_KEYS);
+        let mut workload = Workload::new(1, key_gen, 1, 10, 10);
+        let mut key_gen_state =
+            key_gen.initial_state(workload.shard_count(), client_id, None);
+        assert_eq!(key_gen.to_string(), "custom_own_keys");
+
+        // all keys are generated by the custom key generator
+        while let Some((target_shard, cmd)) =
+            workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+        {
+            assert!(cmd.keys(target_shard).all(|key| key.starts_with("1_")));
+        }
+
+        // only the name of the key generator is serialized
+        let serialized = bincode::serialize(&workload).unwrap();
+        let deserialized: Workload = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized.key_gen(), key_gen);
+        assert_eq!(deserialized.key_gen().to_string(), "custom_own_keys");
+    }
 }
@@ -0,0 +1,282 @@
+use crate::client::Workload;
+use crate::id::ClientId;
+use crate::kvs::StateMachineKind;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of workloads in a `WorkloadMix`.
+pub const WORKLOAD_MIX_MAX_SIZE: usize = 4;
+
+/// Mixture of workloads executed concurrently by disjoint subsets of clients,
+/// where each workload is executed by a given percentage of the clients.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WorkloadMix {
+    // workloads along with the percentage of clients executing them; the
+    // capacity of the mix is fixed so that it can be `Copy` (like `Workload`)
+    workloads: [Option<(Workload, usize)>; WORKLOAD_MIX_MAX_SIZE],
+}
+
+impl WorkloadMix {
+    /// Creates a new mix given each workload and the percentage of clients
+    /// that should execute it.
+    pub fn new(workloads: Vec<(Workload, usize)>) -> Self {
+        assert!(
+            !workloads.is_empty(),
+            "a workload mix should have at least one workload"
+        );
+        assert!(
+            workloads.len() <= WORKLOAD_MIX_MAX_SIZE,
+            "a workload mix can have at most {} workloads",
+            WORKLOAD_MIX_MAX_SIZE
+        );
+        assert!(
+            workloads.iter().all(|(_, percentage)| *percentage > 0),
+            "the percentage of clients executing each workload should be positive"
+        );
+        assert_eq!(
+            workloads
+                .iter()
+                .map(|(_, percentage)| percentage)
+                .sum::<usize>(),
+            100,
+            "the percentages of clients in a workload mix should sum to 100"
+        );
+        let (first, _) = workloads[0];
+        assert!(
+            workloads.iter().all(|(workload, _)| {
+                workload.shard_count() == first.shard_count()
+                    && workload.commands_per_client()
+                        == first.commands_per_client()
+            }),
+            "all workloads in a mix should have the same number of shards and commands per client"
+        );
+
+        let mut mix = [None; WORKLOAD_MIX_MAX_SIZE];
+        for (index, workload) in workloads.into_iter().enumerate() {
+            mix[index] = Some(workload);
+        }
+        Self { workloads: mix }
+    }
+
+    /// Creates a mix with a single workload, executed by all clients.
+    pub fn single(workload: Workload) -> Self {
+        Self::new(vec![(workload, 100)])
+    }
+
+    /// Returns the workloads in the mix, along with the percentage of clients
+    /// executing each of them.
+    pub fn workloads(&self) -> impl Iterator<Item = (Workload, usize)> + '_ {
+        self.workloads.iter().filter_map(|workload| *workload)
+    }
+
+    /// Returns the first workload in the mix.
+    pub fn first(&self) -> Workload {
+        let (workload, _) =
+            self.workloads[0].expect("a workload mix can't be empty");
+        workload
+    }
+
+    /// Returns the number of workloads in the mix.
+    pub fn size(&self) -> usize {
+        self.workloads().count()
+    }
+
+    /// Returns true if the mix has a single workload.
+    pub fn is_single(&self) -> bool {
+        self.size() == 1
+    }
+
+    /// Sets the seed of all the workloads in the mix (see
+    /// `Workload::set_seed`).
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        for (workload, _) in self.workloads.iter_mut().flatten() {
+            workload.set_seed(seed);
+        }
+    }
+
+    /// Sets the TTL of the values written by all the workloads in the mix
+    /// (see `Workload::set_ttl`).
+    pub fn set_ttl(&mut self, ttl: Option<u64>) {
+        for (workload, _) in self.workloads.iter_mut().flatten() {
+            workload.set_ttl(ttl);
+        }
+    }
+
+    /// Sets the state machine targeted by all the workloads in the mix (see
+    /// `Workload::set_state_machine`).
+    pub fn set_state_machine(
+        &mut self,
+        state_machine: Option<StateMachineKind>,
+    ) {
+        for (workload, _) in self.workloads.iter_mut().flatten() {
+            workload.set_state_machine(state_machine);
+        }
+    }
+
+    /// Sets the percentage of read-only commands that get back the values read
+    /// in all the workloads in the mix (see
+    /// `Workload::set_value_sample_percentage`).
+    pub fn set_value_sample_percentage(
+        &mut self,
+        value_sample_percentage: Option<usize>,
+    ) {
+        for (workload, _) in self.workloads.iter_mut().flatten() {
+            workload.set_value_sample_percentage(value_sample_percentage);
+        }
+    }
+
+    /// Sets the maximum number of commands in flight in all the workloads in
+    /// the mix (see `Workload::set_max_in_flight`).
+    pub fn set_max_in_flight(&mut self, max_in_flight: Option<usize>) {
+        for (workload, _) in self.workloads.iter_mut().flatten() {
+            workload.set_max_in_flight(max_in_flight);
+        }
+    }
+
+    /// Assigns a workload to each client: clients are split in contiguous
+    /// (and thus disjoint) subsets, with each subset executing one of the
+    /// workloads.
+    pub fn assign(
+        &self,
+        client_ids: Vec<ClientId>,
+    ) -> Vec<(ClientId, Workload)> {
+        let client_count = client_ids.len();
+        let workload_count = self.size();
+        let mut client_ids = client_ids.into_iter();
+        let mut assigned = Vec::with_capacity(client_count);
+        for (index, (workload, percentage)) in self.workloads().enumerate() {
+            // the last workload is assigned to all the remaining clients
+            let count = if index == workload_count - 1 {
+                client_count - assigned.len()
+            } else {
+                (client_count * percentage) / 100
+            };
+            assigned.extend(
+                client_ids
+                    .by_ref()
+                    .take(count)
+                    .map(|client_id| (client_id, workload)),
+            );
+        }
+        assigned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::KeyGen;
+    use crate::id::RiflGen;
+    use crate::kvs::Key;
+    use crate::HashSet;
+
+    fn workload(key_gen: KeyGen, keys_per_command: usize) -> Workload {
+        let shard_count = 1;
+        let commands_per_client = 100;
+        let payload_size = 1;
+        Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        )
+    }
+
+    #[test]
+    fn workload_mix_assign() {
+        let zipf = workload(
+            KeyGen::Zipf {
+                coefficient: 1.0,
+                total_keys_per_shard: 1_000_000,
+            },
+            1,
+        );
+        let uniform = workload(
+            KeyGen::ConflictPool {
+                conflict_rate: 0,
+                pool_size: 1,
+                pool_id: 0,
+            },
+            2,
+        );
+
+        // a single workload is assigned to all clients
+        let mix = WorkloadMix::single(zipf);
+        assert!(mix.is_single());
+        assert_eq!(mix.first(), zipf);
+        let assigned = mix.assign((1..=10).collect());
+        assert_eq!(assigned.len(), 10);
+        assert!(assigned.iter().all(|(_, workload)| *workload == zipf));
+
+        // 80% of clients execute the zipf workload and 20% the uniform one
+        let mix = WorkloadMix::new(vec![(zipf, 80), (uniform, 20)]);
+        assert_eq!(mix.size(), 2);
+        assert_eq!(mix.first(), zipf);
+        let assigned = mix.assign((1..=10).collect());
+        let expected: Vec<_> = (1..=8)
+            .map(|client_id| (client_id, zipf))
+            .chain((9..=10).map(|client_id| (client_id, uniform)))
+            .collect();
+        assert_eq!(assigned, expected);
+
+        // the remaining clients are assigned to the last workload
+        let assigned = mix.assign((1..=3).collect());
+        let expected = vec![(1, zipf), (2, zipf), (3, uniform)];
+        assert_eq!(assigned, expected);
+    }
+
+    #[test]
+    fn workload_mix_conflict_pools() {
+        let pool = |conflict_rate, pool_id| {
+            workload(
+                KeyGen::ConflictPool {
+                    conflict_rate,
+                    pool_size: 1,
+                    pool_id,
+                },
+                1,
+            )
+        };
+
+        // 90% of clients in a low-conflict pool, and 10% in a high-conflict
+        // one
+        let mix = WorkloadMix::new(vec![(pool(2, 0), 90), (pool(100, 1), 10)]);
+        let mut keys = vec![HashSet::new(), HashSet::new()];
+        for (client_id, mut workload) in mix.assign((1..=10).collect()) {
+            let index = if client_id <= 9 { 0 } else { 1 };
+            let mut rifl_gen = RiflGen::new(client_id);
+            let mut key_gen_state = workload.key_gen().initial_state(
+                workload.shard_count(),
+                client_id,
+                Some(0),
+            );
+            while let Some((shard_id, cmd)) =
+                workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+            {
+                keys[index].extend(cmd.keys(shard_id).cloned());
+            }
+        }
+
+        // the pools don't share any key, even though both have a single key
+        let conflicting = |keys: &HashSet<Key>| {
+            keys.iter().any(|key| key.contains("CONFLICT"))
+        };
+        assert!(conflicting(&keys[0]));
+        assert_eq!(keys[1].len(), 1);
+        assert!(conflicting(&keys[1]));
+        assert!(keys[0].is_disjoint(&keys[1]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn workload_mix_invalid_percentages() {
+        let zipf = workload(
+            KeyGen::Zipf {
+                coefficient: 1.0,
+                total_keys_per_shard: 1_000_000,
+            },
+            1,
+        );
+        WorkloadMix::new(vec![(zipf, 80), (zipf, 10)]);
+    }
+}
@@ -0,0 +1,175 @@
+use parking_lot::Mutex;
+use rand::Rng;
+use std::sync::Arc;
+
+// samplers created so far (in this process), indexed by key count and
+// coefficient, so that all clients running on the same machine share them
+static SAMPLERS: Mutex<Vec<(usize, u64, Arc<ZipfSampler>)>> =
+    parking_lot::const_mutex(Vec::new());
+
+/// Samples keys `1..=key_count` following a Zipf distribution with some
+/// coefficient `s` (i.e. key `k` is sampled with probability proportional to
+/// `1 / k^s`).
+///
+/// Samples are drawn in constant time using the alias method: each key gets a
+/// slot with an acceptance probability and an alias, and so sampling requires
+/// a single random number (whose high bits pick the slot and whose low bits
+/// decide between the slot and its alias) and a single memory access.
+/// Computing the table takes `O(key_count)` time and memory, which is why
+/// samplers should be shared (see `ZipfSampler::shared`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZipfSampler {
+    // for each slot, its acceptance probability (scaled to `u32`) and the key
+    // (minus one) sampled when the slot is not accepted
+    slots: Vec<(u32, u32)>,
+}
+
+impl ZipfSampler {
+    pub fn new(key_count: usize, coefficient: f64) -> Self {
+        assert!(key_count > 0, "there should be at least one key");
+        assert!(
+            key_count <= u32::MAX as usize,
+            "too many keys for the zipf sampler"
+        );
+        assert!(coefficient >= 0.0, "zipf coefficient should be positive");
+
+        // compute the weight of each key, scaled so that the average is 1
+        let weights: Vec<_> = (1..=key_count)
+            .map(|key| 1.0 / (key as f64).powf(coefficient))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        let mut prob: Vec<_> = weights
+            .into_iter()
+            .map(|weight| weight * key_count as f64 / total)
+            .collect();
+        let mut alias: Vec<_> = (0..key_count as u32).collect();
+
+        // split slots into the ones with less and with more than the average
+        let (mut small, mut large): (Vec<_>, Vec<_>) =
+            (0..key_count).partition(|&slot| prob[slot] < 1.0);
+
+        // fill each small slot with the excess of some large slot
+        while let (Some(s), Some(&l)) = (small.pop(), large.last()) {
+            alias[s] = l as u32;
+            prob[l] -= 1.0 - prob[s];
+            if prob[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+
+        // due to rounding errors, the remaining slots may not be exactly 1
+        for slot in small.into_iter().chain(large) {
+            prob[slot] = 1.0;
+        }
+
+        // scale acceptance probabilities so that a slot is accepted if the
+        // low 32 bits of the random number are not higher than its threshold
+        let slots = prob
+            .into_iter()
+            .zip(alias)
+            .map(|(prob, alias)| {
+                let threshold = (prob * u32::MAX as f64).round() as u32;
+                (threshold, alias)
+            })
+            .collect();
+        Self { slots }
+    }
+
+    /// Returns the sampler for `key_count` keys and the given `coefficient`,
+    /// creating it if no client in this process has done it yet.
+    pub fn shared(key_count: usize, coefficient: f64) -> Arc<Self> {
+        let mut samplers = SAMPLERS.lock();
+        let found = samplers.iter().find(|(count, bits, _)| {
+            *count == key_count && *bits == coefficient.to_bits()
+        });
+        if let Some((_, _, sampler)) = found {
+            return sampler.clone();
+        }
+        let sampler = Arc::new(Self::new(key_count, coefficient));
+        samplers.push((key_count, coefficient.to_bits(), sampler.clone()));
+        sampler
+    }
+
+    /// Returns the number of keys.
+    pub fn key_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Samples a key (in `1..=key_count`).
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        let random = rng.next_u64();
+        // map the high 32 bits to a slot (the bias of this mapping is
+        // negligible given that there are at most `u32::MAX` slots)
+        let slot = ((random >> 32) * self.slots.len() as u64) >> 32;
+        let slot = slot as usize;
+        let (threshold, alias) = self.slots[slot];
+        let key = if random as u32 <= threshold {
+            slot
+        } else {
+            alias as usize
+        };
+        key + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn zipf_sampler() {
+        let key_count = 100;
+        let coefficient = 1.0;
+        let sampler = ZipfSampler::new(key_count, coefficient);
+        assert_eq!(sampler.key_count(), key_count);
+
+        // sample many keys
+        let samples = 1_000_000;
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = vec![0; key_count];
+        for _ in 0..samples {
+            let key = sampler.sample(&mut rng);
+            assert!((1..=key_count).contains(&key));
+            counts[key - 1] += 1;
+        }
+
+        // check that the frequency of each key is close to the expected one
+        let total: f64 = (1..=key_count)
+            .map(|key| 1.0 / (key as f64).powf(coefficient))
+            .sum();
+        for (index, count) in counts.into_iter().enumerate() {
+            let key = (index + 1) as f64;
+            let expected = 1.0 / key.powf(coefficient) / total;
+            let frequency = count as f64 / samples as f64;
+            assert!(
+                (frequency - expected).abs() < 0.002,
+                "key {} has frequency {} but expected {}",
+                key,
+                frequency,
+                expected
+            );
+        }
+
+        // with coefficient 0, all keys are equally likely
+        let sampler = ZipfSampler::new(key_count, 0.0);
+        assert!(sampler
+            .slots
+            .iter()
+            .all(|(threshold, _)| *threshold == u32::MAX));
+    }
+
+    #[test]
+    fn zipf_sampler_shared() {
+        let a = ZipfSampler::shared(10, 0.5);
+        let b = ZipfSampler::shared(10, 0.5);
+        let c = ZipfSampler::shared(10, 0.7);
+        let d = ZipfSampler::shared(20, 0.5);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert!(!Arc::ptr_eq(&a, &d));
+        assert_eq!(d.key_count(), 20);
+    }
+}
@@ -1,24 +1,43 @@
 use crate::executor::ExecutorResult;
-use crate::id::{Rifl, ShardId};
-use crate::kvs::{KVOp, KVOpResult, KVStore, Key};
+use crate::id::{Dot, Rifl, ShardId};
+use crate::kvs::{Commutative, KVError, KVOp, KVOpResult, KVStore, Key};
 use crate::HashMap;
-use serde::{Deserialize, Serialize};
+use bytes::Bytes;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{self, Debug};
 use std::iter::FromIterator;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 pub const DEFAULT_SHARD_ID: ShardId = 0;
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Command {
     rifl: Rifl,
-    shard_to_ops: HashMap<ShardId, HashMap<Key, Arc<Vec<KVOp>>>>,
+    shard_to_ops: HashMap<ShardId, HashMap<Key, KeyOps>>,
     // mapping from shard to the keys on that shard; this will be used by
     // `Tempo` to exchange `MStable` messages between shards
     shard_to_keys: Arc<HashMap<ShardId, Vec<Key>>>,
+    // if this command is a batch of client commands, the original commands
+    // merged into it
+    batch: Option<Arc<CommandBatch>>,
+    // whether all ops are `Get`s; this is computed upfront so that it's known
+    // without decoding the ops (see `KeyOps`)
+    read_only: bool,
+    // if all ops are in the same class of commutative ops, that class; it's
+    // computed upfront for the same reason as `read_only`
+    commutative: Option<Commutative>,
+    // if set, the client that issued this command only needs an ack, and
+    // thus its results are not sent back
+    ack_only: bool,
+    // if set, the client that issued this command only needs the hashes of
+    // the values read, and thus the values themselves are not sent back
+    hash_values: bool,
+    // time (in milliseconds) at which the command was issued; values written
+    // with a TTL expire relative to it (see `KVOp::PutTtl`)
+    timestamp: u64,
     // field used to output and empty iterator of keys when rustc can't figure
     // out what we mean
-    _empty_keys: HashMap<Key, Arc<Vec<KVOp>>>,
+    _empty_keys: HashMap<Key, KeyOps>,
 }
 
 impl Command {
@@ -28,6 +47,19 @@ impl Command {
         shard_to_ops: HashMap<ShardId, HashMap<Key, Vec<KVOp>>>,
     ) -> Self {
         let mut shard_to_keys: HashMap<ShardId, Vec<Key>> = Default::default();
+        let read_only = shard_to_ops.values().all(|shard_ops| {
+            shard_ops
+                .values()
+                .all(|ops| ops.iter().all(|op| op == &KVOp::Get))
+        });
+        let mut classes = shard_to_ops
+            .values()
+            .flat_map(|shard_ops| shard_ops.values().flatten())
+            .map(KVOp::commutative);
+        let commutative = match classes.next() {
+            Some(first) if classes.all(|class| class == first) => first,
+            _ => None,
+        };
         let shard_to_ops = shard_to_ops
             .into_iter()
             .map(|(shard_id, shard_ops)| {
@@ -42,8 +74,8 @@ impl Command {
                                 .or_default()
                                 .push(key.clone());
 
-                            // `Arc` the ops on this key
-                            (key, Arc::new(ops))
+                            // frame the ops on this key
+                            (key, KeyOps::new(ops))
                         })
                         .collect(),
                 )
@@ -53,6 +85,12 @@ impl Command {
             rifl,
             shard_to_ops,
             shard_to_keys: Arc::new(shard_to_keys),
+            batch: None,
+            read_only,
+            commutative,
+            ack_only: false,
+            hash_values: false,
+            timestamp: 0,
             _empty_keys: HashMap::new(),
         }
     }
@@ -74,11 +112,46 @@ impl Command {
     /// Checks if the command is read-only.
     pub fn read_only(&self) -> bool {
         // a command is read-only if all ops are `Get`s
-        self.shard_to_ops.values().all(|shard_ops| {
-            shard_ops
-                .values()
-                .all(|ops| ops.iter().all(|op| op == &KVOp::Get))
-        })
+        self.read_only
+    }
+
+    /// Returns the class of commutative ops all ops of this command belong to
+    /// (if any). Commands composed only of ops in the same class commute.
+    pub fn commutative(&self) -> Option<Commutative> {
+        self.commutative
+    }
+
+    /// Checks if the client that issued this command only needs an ack (i.e.
+    /// not its results).
+    pub fn ack_only(&self) -> bool {
+        self.ack_only
+    }
+
+    /// Sets whether the client that issued this command only needs an ack.
+    pub fn set_ack_only(&mut self, ack_only: bool) {
+        self.ack_only = ack_only;
+    }
+
+    /// Checks if the client that issued this command only needs the hashes of
+    /// the values read (i.e. not the values).
+    pub fn hash_values(&self) -> bool {
+        self.hash_values
+    }
+
+    /// Sets whether the client that issued this command only needs the hashes
+    /// of the values read.
+    pub fn set_hash_values(&mut self, hash_values: bool) {
+        self.hash_values = hash_values;
+    }
+
+    /// Returns the time (in milliseconds) at which the command was issued.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Sets the time (in milliseconds) at which the command was issued.
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = timestamp;
     }
 
     /// Checks if the command is replicated by `shard_id`.
@@ -91,6 +164,30 @@ impl Command {
         self.rifl
     }
 
+    /// Returns the original commands merged into this command, if this
+    /// command is a batch.
+    pub fn batch(&self) -> Option<&Arc<CommandBatch>> {
+        self.batch.as_ref()
+    }
+
+    /// Returns the identifiers of the original commands merged into this
+    /// command (if this command is not a batch, that's only its own
+    /// identifier).
+    pub fn batch_rifls(&self) -> Vec<Rifl> {
+        match self.batch.as_ref() {
+            Some(batch) => batch.rifls().to_vec(),
+            None => vec![self.rifl],
+        }
+    }
+
+    /// Returns the number of original commands merged into this command.
+    pub fn batch_size(&self) -> usize {
+        self.batch
+            .as_ref()
+            .map(|batch| batch.rifls().len())
+            .unwrap_or(1)
+    }
+
     /// Returns the number of keys accessed by this command on the shard
     /// provided.
     pub fn key_count(&self, shard_id: ShardId) -> usize {
@@ -145,14 +242,13 @@ impl Command {
         store: &'a mut KVStore,
     ) -> impl Iterator<Item = ExecutorResult> + 'a {
         let rifl = self.rifl;
+        let timestamp = self.timestamp;
         self.into_iter(shard_id).map(move |(key, ops)| {
-            // take the ops inside the arc if we're the last with a
-            // reference to it (otherwise, clone them)
-            let ops =
-                Arc::try_unwrap(ops).unwrap_or_else(|ops| ops.as_ref().clone());
+            // take the ops (decoding them, if needed)
+            let ops = ops.into_ops();
             // execute this op
-            let partial_results = store.execute(&key, ops, rifl);
-            ExecutorResult::new(rifl, key, partial_results)
+            let execution = store.execute(&key, ops, rifl, timestamp);
+            ExecutorResult::from_execution(rifl, key, execution)
         })
     }
 
@@ -160,7 +256,7 @@ impl Command {
     pub fn iter(
         &self,
         shard_id: ShardId,
-    ) -> impl Iterator<Item = (&Key, &Arc<Vec<KVOp>>)> {
+    ) -> impl Iterator<Item = (&Key, &KeyOps)> {
         self.shard_to_ops
             .get(&shard_id)
             .map(|shard_ops| shard_ops.iter())
@@ -171,15 +267,20 @@ impl Command {
     pub fn into_iter(
         mut self,
         shard_id: ShardId,
-    ) -> impl Iterator<Item = (Key, Arc<Vec<KVOp>>)> {
+    ) -> impl Iterator<Item = (Key, KeyOps)> {
         self.shard_to_ops
             .remove(&shard_id)
             .map(|shard_ops| shard_ops.into_iter())
             .unwrap_or_else(|| self._empty_keys.into_iter())
     }
 
-    /// Checks if a command conflicts with another given command.
+    /// Checks if a command conflicts with another given command. Commands
+    /// composed only of ops in the same class of commutative ops (see
+    /// `Command::commutative`) never conflict.
     pub fn conflicts(&self, other: &Command) -> bool {
+        if self.commutative.is_some() && self.commutative == other.commutative {
+            return false;
+        }
         self.shard_to_ops.iter().any(|(shard_id, shard_ops)| {
             shard_ops
                 .iter()
@@ -195,15 +296,43 @@ impl Command {
             .unwrap_or(false)
     }
 
-    /// Adds the operations in the `other` command to this command.
+    /// Adds the operations in the `other` command to this command. This
+    /// command then becomes a batch, which allows its results to be split
+    /// back into the results of the original commands.
     pub fn merge(&mut self, other: Command) {
+        assert!(
+            other.batch.is_none(),
+            "a batch should not be merged into another command"
+        );
+        // record the layout of this command, if it's not yet a batch
+        if self.batch.is_none() {
+            let mut batch = CommandBatch::default();
+            batch.push(self.rifl, &self.shard_to_ops);
+            self.batch = Some(Arc::new(batch));
+        }
+        Arc::get_mut(self.batch.as_mut().expect("batch should exist"))
+            .expect("a command should only be cloned after all merges have occurred")
+            .push(other.rifl, &other.shard_to_ops);
+        self.read_only = self.read_only && other.read_only;
+        // the batch commutes only if all its commands commute with each other
+        if self.commutative != other.commutative {
+            self.commutative = None;
+        }
+        // the results of the batch are only dropped if none of the original
+        // commands needs them
+        self.ack_only = self.ack_only && other.ack_only;
+        // and the same for the values read
+        self.hash_values = self.hash_values && other.hash_values;
+        // the batch is as recent as its most recent command
+        self.timestamp = std::cmp::max(self.timestamp, other.timestamp);
+
         for (shard_id, shard_ops) in other.shard_to_ops {
             let current_shard_ops =
                 self.shard_to_ops.entry(shard_id).or_default();
             for (key, ops) in shard_ops {
-                let ops = Arc::try_unwrap(ops).expect("a command to be merged into another command should have not been cloned");
+                let ops = Arc::try_unwrap(ops.into_decoded()).expect("a command to be merged into another command should have not been cloned");
                 let current_ops = current_shard_ops.entry(key).or_default();
-                Arc::get_mut(current_ops).expect("a command should only be cloned after all merges have occurred").extend(ops);
+                current_ops.get_mut().expect("a command should only be cloned after all merges have occurred").extend(ops);
             }
         }
     }
@@ -222,12 +351,254 @@ impl fmt::Debug for Command {
     }
 }
 
+/// Layout of a batch: the original commands merged into some command, and
+/// the ops each of them has on each key. This allows the results of a batch
+/// to be split back into the results of the original commands.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandBatch {
+    // original commands in the order they were merged
+    rifls: Vec<Rifl>,
+    // mapping from each key to the original commands that accessed it, along
+    // with the number of ops of each (in the order their ops were merged)
+    layout: HashMap<ShardId, HashMap<Key, Vec<(Rifl, usize)>>>,
+}
+
+impl CommandBatch {
+    fn push(
+        &mut self,
+        rifl: Rifl,
+        shard_to_ops: &HashMap<ShardId, HashMap<Key, KeyOps>>,
+    ) {
+        self.rifls.push(rifl);
+        for (shard_id, shard_ops) in shard_to_ops {
+            let layout = self.layout.entry(*shard_id).or_default();
+            for (key, ops) in shard_ops {
+                layout
+                    .entry(key.clone())
+                    .or_default()
+                    .push((rifl, ops.ops().len()));
+            }
+        }
+    }
+
+    /// Returns the identifiers of the original commands in this batch.
+    pub fn rifls(&self) -> &[Rifl] {
+        &self.rifls
+    }
+
+    /// Returns the identifiers of the original commands that access the shard
+    /// provided (each identifier is returned once per key accessed).
+    pub fn shard_rifls(
+        &self,
+        shard_id: ShardId,
+    ) -> impl Iterator<Item = Rifl> + '_ {
+        self.layout
+            .get(&shard_id)
+            .into_iter()
+            .flat_map(|layout| layout.values())
+            .flat_map(|key_rifls| key_rifls.iter().map(|(rifl, _)| *rifl))
+    }
+
+    /// Returns the number of keys accessed by the original command `rifl` on
+    /// the shard provided.
+    pub fn key_count(&self, rifl: Rifl, shard_id: ShardId) -> usize {
+        self.layout
+            .get(&shard_id)
+            .map(|layout| {
+                layout
+                    .values()
+                    .filter(|key_rifls| {
+                        key_rifls.iter().any(|(key_rifl, _)| *key_rifl == rifl)
+                    })
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Returns the shards accessed by the original command `rifl`.
+    pub fn shard_count(&self, rifl: Rifl) -> usize {
+        self.layout
+            .keys()
+            .filter(|shard_id| self.key_count(rifl, **shard_id) > 0)
+            .count()
+    }
+
+    /// Splits the results of the ops on `key` into the results of each of the
+    /// original commands that accessed it.
+    pub fn split(
+        &self,
+        shard_id: ShardId,
+        key: &Key,
+        mut results: Vec<KVOpResult>,
+    ) -> Vec<(Rifl, Vec<KVOpResult>)> {
+        let key_rifls = self
+            .layout
+            .get(&shard_id)
+            .and_then(|layout| layout.get(key))
+            .expect("split key should be part of the batch");
+        // if the ops on the key were not executed, there are no results
+        if results.is_empty() {
+            return key_rifls
+                .iter()
+                .map(|(rifl, _)| (*rifl, Vec::new()))
+                .collect();
+        }
+        let op_count: usize = key_rifls.iter().map(|(_, count)| count).sum();
+        assert_eq!(
+            results.len(),
+            op_count,
+            "there should be one result per op in the batch"
+        );
+        // split the results from the last original command to the first
+        let mut split: Vec<_> = key_rifls
+            .iter()
+            .rev()
+            .map(|(rifl, count)| {
+                let rifl_results = results.split_off(results.len() - count);
+                (*rifl, rifl_results)
+            })
+            .collect();
+        split.reverse();
+        split
+    }
+}
+
+/// Ops of a command on some key.
+///
+/// The ops on each key are serialized in their own frame, and once
+/// deserialized, they're kept in that frame until they're needed (which is
+/// usually only when they're executed). With large batches, this allows
+/// workers to handle the commands received from other processes, and route
+/// them key by key to executors, without decoding all their ops upfront.
+#[derive(Clone)]
+pub struct KeyOps {
+    // frame with the encoded ops; it's kept once the ops are encoded (or
+    // received already encoded), so that they're encoded at most once
+    frame: OnceLock<Bytes>,
+    // decoded ops; at least one of `frame` and `ops` is always set
+    ops: OnceLock<Arc<Vec<KVOp>>>,
+}
+
+impl KeyOps {
+    /// Creates a new `KeyOps`.
+    pub fn new(ops: Vec<KVOp>) -> Self {
+        Self {
+            frame: OnceLock::new(),
+            ops: OnceLock::from(Arc::new(ops)),
+        }
+    }
+
+    /// Returns the ops, decoding them if needed.
+    pub fn ops(&self) -> &Arc<Vec<KVOp>> {
+        self.ops
+            .get_or_init(|| Arc::new(Self::decode(self.frame())))
+    }
+
+    /// Takes the ops, decoding them if needed. The ops are only cloned if
+    /// they're still referenced elsewhere.
+    pub fn into_ops(self) -> Vec<KVOp> {
+        match self.ops.into_inner() {
+            Some(ops) => {
+                Arc::try_unwrap(ops).unwrap_or_else(|ops| ops.as_ref().clone())
+            }
+            None => Self::decode(self.frame.get().expect(
+                "[key_ops] the frame should exist if ops are not decoded",
+            )),
+        }
+    }
+
+    /// Checks whether the ops have been decoded from their frame (or were
+    /// never encoded).
+    pub fn decoded(&self) -> bool {
+        self.ops.get().is_some()
+    }
+
+    fn into_decoded(self) -> Arc<Vec<KVOp>> {
+        self.ops();
+        self.ops
+            .into_inner()
+            .expect("[key_ops] ops should be decoded")
+    }
+
+    fn get_mut(&mut self) -> Option<&mut Vec<KVOp>> {
+        self.ops();
+        // since the ops are about to change, drop their frame
+        self.frame = OnceLock::new();
+        self.ops.get_mut().and_then(Arc::get_mut)
+    }
+
+    fn frame(&self) -> &Bytes {
+        self.frame.get_or_init(|| {
+            let ops = self.ops.get().expect(
+                "[key_ops] ops should be decoded if the frame doesn't exist",
+            );
+            let frame = bincode::serialize(ops.as_ref())
+                .expect("[key_ops] serialize should work");
+            Bytes::from(frame)
+        })
+    }
+
+    fn decode(frame: &Bytes) -> Vec<KVOp> {
+        bincode::deserialize(frame).expect("[key_ops] deserialize should work")
+    }
+}
+
+impl Default for KeyOps {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl PartialEq for KeyOps {
+    fn eq(&self, other: &Self) -> bool {
+        self.ops() == other.ops()
+    }
+}
+
+impl Eq for KeyOps {}
+
+impl fmt::Debug for KeyOps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ops.get() {
+            Some(ops) => write!(f, "{:?}", ops),
+            None => write!(f, "<{} bytes>", self.frame().len()),
+        }
+    }
+}
+
+impl Serialize for KeyOps {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.frame())
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyOps {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let frame = Bytes::deserialize(deserializer)?;
+        Ok(Self {
+            frame: OnceLock::from(frame),
+            ops: OnceLock::new(),
+        })
+    }
+}
+
 /// Structure that aggregates partial results of multi-key commands.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CommandResultBuilder {
     rifl: Rifl,
     key_count: usize,
     results: HashMap<Key, Vec<KVOpResult>>,
+    errors: HashMap<Key, KVError>,
+    shed: bool,
+    executed_clock: Option<u64>,
+    ack_only: bool,
+    hash_values: bool,
 }
 
 impl CommandResultBuilder {
@@ -238,12 +609,43 @@ impl CommandResultBuilder {
             rifl,
             key_count,
             results: HashMap::new(),
+            errors: HashMap::new(),
+            shed: false,
+            executed_clock: None,
+            ack_only: false,
+            hash_values: false,
         }
     }
 
+    /// Drops the partial results added from now on (keeping only the keys
+    /// they're on), since the client only needs an ack.
+    pub fn set_ack_only(&mut self) {
+        self.ack_only = true;
+    }
+
+    /// Replaces the values in the partial results added from now on by their
+    /// hashes, since the client only needs to know which values were read.
+    pub fn set_hash_values(&mut self) {
+        self.hash_values = true;
+    }
+
     /// Adds a partial command result to the overall result.
     /// Returns a boolean indicating whether the full result is ready.
     pub fn add_partial(&mut self, key: Key, partial_results: Vec<KVOpResult>) {
+        // don't ship the results back if the client only needs an ack
+        // (or only the hashes of the values, if that's all the client needs)
+        let partial_results = if self.ack_only {
+            Vec::new()
+        } else if self.hash_values {
+            partial_results
+                .into_iter()
+                .map(|result| {
+                    result.map(|value| crate::util::value_hash(&value))
+                })
+                .collect()
+        } else {
+            partial_results
+        };
         // add op result for `key`
         let res = self.results.insert(key, partial_results);
 
@@ -251,6 +653,26 @@ impl CommandResultBuilder {
         assert!(res.is_none());
     }
 
+    /// Adds an error that prevented the ops on `key` from being executed. The
+    /// results on that key are left empty.
+    pub fn add_error(&mut self, key: Key, error: KVError) {
+        self.errors.insert(key.clone(), error);
+        self.add_partial(key, Vec::new());
+    }
+
+    /// Marks the command as shed. A command is shed if any of its partial
+    /// results was shed.
+    pub fn set_shed(&mut self) {
+        self.shed = true;
+    }
+
+    /// Records the executed clock of a stale read. The executed clock of the
+    /// command is the lowest of its stale reads.
+    pub fn set_executed_clock(&mut self, executed_clock: u64) {
+        let current = self.executed_clock.get_or_insert(executed_clock);
+        *current = std::cmp::min(*current, executed_clock);
+    }
+
     pub fn ready(&self) -> bool {
         // we're ready if the number of partial results equals `key_count`
         self.results.len() == self.key_count
@@ -262,12 +684,24 @@ impl CommandResultBuilder {
 pub struct CommandResult {
     rifl: Rifl,
     results: HashMap<Key, Vec<KVOpResult>>,
+    errors: HashMap<Key, KVError>,
+    shed: bool,
+    executed_clock: Option<u64>,
+    trace: Option<CommandTrace>,
 }
 
 impl CommandResult {
     /// Creates a new `CommandResult`.
     pub fn new(rifl: Rifl, results: HashMap<Key, Vec<KVOpResult>>) -> Self {
-        CommandResult { rifl, results }
+        let shed = false;
+        CommandResult {
+            rifl,
+            results,
+            errors: HashMap::new(),
+            shed,
+            executed_clock: None,
+            trace: None,
+        }
     }
 
     /// Returns the command identifier.
@@ -279,6 +713,36 @@ impl CommandResult {
     pub fn results(&self) -> &HashMap<Key, Vec<KVOpResult>> {
         &self.results
     }
+
+    /// Returns the errors that prevented the ops on some keys from being
+    /// executed (the results on those keys are empty).
+    pub fn errors(&self) -> &HashMap<Key, KVError> {
+        &self.errors
+    }
+
+    /// Returns true if the command was shed by some executor, in which case
+    /// its results should be ignored.
+    pub fn shed(&self) -> bool {
+        self.shed
+    }
+
+    /// Returns, if the command was served by stale reads (see
+    /// `Config::stale_reads`), the executed clock of the stores it was read
+    /// from, which bounds how stale its results are.
+    pub fn executed_clock(&self) -> Option<u64> {
+        self.executed_clock
+    }
+
+    /// Returns the trace of the command, if it was sampled (see
+    /// `Config::trace_sample_rate`).
+    pub fn trace(&self) -> Option<&CommandTrace> {
+        self.trace.as_ref()
+    }
+
+    /// Attaches a trace to the command result.
+    pub fn set_trace(&mut self, trace: CommandTrace) {
+        self.trace = Some(trace);
+    }
 }
 
 impl From<CommandResultBuilder> for CommandResult {
@@ -287,10 +751,70 @@ impl From<CommandResultBuilder> for CommandResult {
         Self {
             rifl: cmd_result_builder.rifl,
             results: cmd_result_builder.results,
+            errors: cmd_result_builder.errors,
+            shed: cmd_result_builder.shed,
+            executed_clock: cmd_result_builder.executed_clock,
+            trace: None,
         }
     }
 }
 
+/// Times (in micros) at which a sampled command went through each phase in
+/// the process where it was submitted (see `Config::trace_sample_rate`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandTrace {
+    dot: Option<Dot>,
+    received: u64,
+    executed: Option<u64>,
+    replied: Option<u64>,
+}
+
+impl CommandTrace {
+    /// Creates a new `CommandTrace` for a command received from its client at
+    /// `received`. The dot is only known if assigned when the command is
+    /// submitted (which is not the case in leader-based protocols).
+    pub fn new(dot: Option<Dot>, received: u64) -> Self {
+        Self {
+            dot,
+            received,
+            executed: None,
+            replied: None,
+        }
+    }
+
+    /// Records the time at which the results of the command were received
+    /// from all the executors.
+    pub fn set_executed(&mut self, executed: u64) {
+        self.executed = Some(executed);
+    }
+
+    /// Records the time at which the command result was sent to the client.
+    pub fn set_replied(&mut self, replied: u64) {
+        self.replied = Some(replied);
+    }
+
+    /// Returns the dot of the command (if known).
+    pub fn dot(&self) -> Option<Dot> {
+        self.dot
+    }
+
+    /// Returns the time at which the command was received from its client.
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Returns the time at which the command was executed (if it was).
+    pub fn executed(&self) -> Option<u64> {
+        self.executed
+    }
+
+    /// Returns the time at which the command result was sent to the client
+    /// (if it was).
+    pub fn replied(&self) -> Option<u64> {
+        self.replied
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -334,5 +858,138 @@ mod tests {
         assert!(cmd_ab.conflicts(&cmd_b));
         assert!(!cmd_ab.conflicts(&cmd_c));
         assert!(cmd_ab.conflicts(&cmd_ab));
+
+        // commands composed only of commutative ops of the same class don't
+        // conflict
+        let key_a = String::from("A");
+        let incr_a =
+            Command::from(rifl, vec![(key_a.clone(), KVOp::Increment(1))]);
+        let decr_a =
+            Command::from(rifl, vec![(key_a.clone(), KVOp::Increment(-1))]);
+        let set_add_a = Command::from(
+            rifl,
+            vec![(key_a.clone(), KVOp::SetAdd(key_a.clone()))],
+        );
+        assert_eq!(incr_a.commutative(), Some(Commutative::Increment));
+        assert_eq!(cmd_a.commutative(), None);
+        assert!(!incr_a.conflicts(&decr_a));
+        assert!(incr_a.conflicts(&set_add_a));
+        assert!(incr_a.conflicts(&cmd_a));
+        assert!(cmd_ab.conflicts(&incr_a));
+
+        // and a command mixing commutative and other ops is not commutative
+        let mixed = Command::from(
+            rifl,
+            vec![
+                (key_a.clone(), KVOp::Increment(1)),
+                (String::from("B"), KVOp::Get),
+            ],
+        );
+        assert_eq!(mixed.commutative(), None);
+        assert!(mixed.conflicts(&incr_a));
+
+        // the same holds for batches
+        let mut batch = incr_a;
+        batch.merge(decr_a);
+        assert_eq!(batch.commutative(), Some(Commutative::Increment));
+        batch.merge(set_add_a);
+        assert_eq!(batch.commutative(), None);
+    }
+
+    #[test]
+    fn merge_and_split() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let rifl_1 = Rifl::new(1, 1);
+        let rifl_2 = Rifl::new(2, 1);
+        let rifl_3 = Rifl::new(3, 1);
+
+        // a command that's not a batch
+        let mut cmd = multi_put(rifl_1, vec![key_a.clone(), key_b.clone()]);
+        assert!(cmd.batch().is_none());
+        assert_eq!(cmd.batch_rifls(), vec![rifl_1]);
+        assert_eq!(cmd.batch_size(), 1);
+
+        // merge two commands into it
+        cmd.merge(multi_put(rifl_2, vec![key_a.clone()]));
+        cmd.merge(Command::from(
+            rifl_3,
+            vec![(key_a.clone(), KVOp::Get), (key_b.clone(), KVOp::Get)],
+        ));
+        assert_eq!(cmd.rifl(), rifl_1);
+        assert_eq!(cmd.batch_rifls(), vec![rifl_1, rifl_2, rifl_3]);
+        assert_eq!(cmd.batch_size(), 3);
+
+        // check the layout of the batch
+        let batch = cmd.batch().expect("command should be a batch");
+        assert_eq!(batch.key_count(rifl_1, DEFAULT_SHARD_ID), 2);
+        assert_eq!(batch.key_count(rifl_2, DEFAULT_SHARD_ID), 1);
+        assert_eq!(batch.key_count(rifl_3, DEFAULT_SHARD_ID), 2);
+        assert_eq!(batch.shard_count(rifl_2), 1);
+
+        // split the results on each key
+        let x = Some(String::from("x"));
+        let y = Some(String::from("y"));
+        assert_eq!(
+            batch.split(DEFAULT_SHARD_ID, &key_a, vec![None, None, x.clone()]),
+            vec![
+                (rifl_1, vec![None]),
+                (rifl_2, vec![None]),
+                (rifl_3, vec![x])
+            ]
+        );
+        assert_eq!(
+            batch.split(DEFAULT_SHARD_ID, &key_b, vec![None, y.clone()]),
+            vec![(rifl_1, vec![None]), (rifl_3, vec![y])]
+        );
+        // keys whose ops were not executed have no results
+        assert_eq!(
+            batch.split(DEFAULT_SHARD_ID, &key_b, Vec::new()),
+            vec![(rifl_1, Vec::new()), (rifl_3, Vec::new())]
+        );
+    }
+
+    #[test]
+    fn framed_ops() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let mut batch = multi_put(Rifl::new(1, 1), vec![key_a.clone()]);
+        batch.merge(multi_put(Rifl::new(2, 1), vec![key_a.clone(), key_b]));
+        assert!(!batch.read_only());
+
+        // once deserialized, the ops on each key are kept encoded
+        let bytes = bincode::serialize(&batch).unwrap();
+        let received: Command = bincode::deserialize(&bytes).unwrap();
+        assert!(received
+            .iter(DEFAULT_SHARD_ID)
+            .all(|(_, ops)| !ops.decoded()));
+        // (and it's known whether the command is read-only without decoding
+        // them)
+        assert!(!received.read_only());
+        assert!(received
+            .iter(DEFAULT_SHARD_ID)
+            .all(|(_, ops)| !ops.decoded()));
+
+        // serializing the command again reuses the frames (the order of keys
+        // may differ, but not the size)
+        assert_eq!(bincode::serialize(&received).unwrap().len(), bytes.len());
+        assert!(received
+            .iter(DEFAULT_SHARD_ID)
+            .all(|(_, ops)| !ops.decoded()));
+
+        // ops are only decoded when needed, and only on that key
+        let (_, ops_a) = received
+            .iter(DEFAULT_SHARD_ID)
+            .find(|(key, _)| **key == key_a)
+            .unwrap();
+        assert_eq!(
+            ops_a.ops().as_ref(),
+            &vec![KVOp::Put(key_a.clone()), KVOp::Put(key_a.clone())]
+        );
+        assert!(received
+            .iter(DEFAULT_SHARD_ID)
+            .filter(|(key, _)| **key != key_a)
+            .all(|(_, ops)| !ops.decoded()));
+        assert_eq!(received, batch);
     }
 }
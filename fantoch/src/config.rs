@@ -1,4 +1,7 @@
+use crate::client::ProcessSelection;
 use crate::id::ProcessId;
+use crate::kvs::StateMachineKind;
+use crate::load_balance::WorkerAssignment;
 use crate::warn;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -15,6 +18,10 @@ pub struct Config {
     execute_at_commit: bool,
     /// defines the interval between executor cleanups
     executor_cleanup_interval: Duration,
+    /// defines whether executors should adapt the interval between cleanups
+    /// to their load (ignoring `executor_cleanup_interval`)
+    #[serde(default)]
+    executor_adaptive_cleanup: bool,
     /// defines the interval between between executed notifications sent to
     /// the local worker process
     executor_executed_notification_interval: Duration,
@@ -24,22 +31,189 @@ pub struct Config {
     /// defines whether the executor should monitor the execution order of
     /// commands
     executor_monitor_execution_order: bool,
+    /// defines whether command results should be delivered to clients in
+    /// submission order (buffering the ones that complete out of order)
+    #[serde(default)]
+    executor_ordered_delivery: bool,
+    /// defines whether commands whose commit-to-execution delay exceeds some
+    /// budget should be shed (i.e. replied to with an error), and if so, the
+    /// budget; only supported by executors that can shed commands (see
+    /// `Executor::supports_latency_budget`)
+    #[serde(default)]
+    executor_latency_budget: Option<Duration>,
+    /// defines whether the search for SCCs (in the graph executor) should be
+    /// interrupted once it visits some number of vertices, and if so, that
+    /// number; interrupted searches are resumed in the next cleanup, so that
+    /// large SCCs don't starve the remaining executor work
+    #[serde(default)]
+    executor_scc_budget: Option<usize>,
+    /// if set, keys are partitioned among this number of tenants, and clients
+    /// are only authorized to access keys of their own tenant
+    #[serde(default)]
+    executor_tenant_count: Option<usize>,
+    /// defines whether consecutive writes on the same key executed within
+    /// the same executor drain cycle should be batched (i.e. only the last
+    /// one is applied to the store)
+    #[serde(default)]
+    executor_write_batching: bool,
+    /// if set, executors apply ops to this built-in state machine instead of
+    /// the key-value store
+    #[serde(default)]
+    executor_state_machine: Option<StateMachineKind>,
+    /// defines whether executors should raise an alarm when their backlog
+    /// (i.e. the number of commands committed but not yet executed) stays
+    /// above some size for a sustained period, and if so, the size and the
+    /// period
+    #[serde(default)]
+    executor_backlog_alarm: Option<(usize, Duration)>,
+    /// defines whether processes should abort once a backlog alarm is raised
+    #[serde(default)]
+    executor_backlog_alarm_abort: bool,
+    /// defines whether the rate at which each executor produces (partial)
+    /// command results should be limited (emulating an execution-side
+    /// bottleneck, such as a slow disk or state machine), and if so, the rate
+    /// (in results per second) and the size of the bursts allowed
+    #[serde(default)]
+    executor_throttle: Option<(usize, usize)>,
+    /// defines whether read-only commands should be served directly from the
+    /// local store (skipping the protocol), which may return stale values
+    #[serde(default)]
+    stale_reads: bool,
+    /// if set, one in every `trace_sample_rate` commands is traced, i.e. the
+    /// time at which it goes through each phase is sent back to the client
+    #[serde(default)]
+    trace_sample_rate: Option<usize>,
+    /// defines the process (on each shard) to which clients submit commands
+    #[serde(default)]
+    client_process_selection: ProcessSelection,
+    /// if set, the first `preload_keys` keys of a Zipf workload (i.e. keys "1"
+    /// to "`preload_keys`") are created in each shard before the run starts,
+    /// both in the executors' store and in the protocols' per-key clocks
+    #[serde(default)]
+    preload_keys: Option<usize>,
     /// defines the interval between garbage collections
     gc_interval: Option<Duration>,
+    /// if set, defines the interval between anti-entropy rounds, in which
+    /// committed commands that other processes are missing (according to the
+    /// committed clocks exchanged for garbage collection) are re-shipped to
+    /// them; this requires garbage collection to be enabled
+    #[serde(default)]
+    anti_entropy_interval: Option<Duration>,
     // starting leader process
     leader: Option<ProcessId>,
     /// defines whether tempo should employ tiny quorums or not
     tempo_tiny_quorums: bool,
+    /// defines whether tempo should employ tiny quorums only for single-key
+    /// commands (and regular quorums otherwise)
+    #[serde(default)]
+    tempo_per_command_tiny_quorums: bool,
     /// defines the interval between clock bumps, if any
     tempo_clock_bump_interval: Option<Duration>,
     /// defines the interval the sending of `MDetached` messages in tempo, if
     /// any
     tempo_detached_send_interval: Option<Duration>,
+    /// defines whether tempo should bump the clocks of all keys (and not only
+    /// the ones accessed by the command) upon receiving an `MCommit` with a
+    /// clock higher than any previously committed
+    #[serde(default)]
+    tempo_commit_clock_bump: bool,
+    /// if set, overrides the stability threshold used by tempo's executor
+    /// (see `Config::tempo_quorum_sizes`); thresholds lower than the safe one
+    /// can only be used in the simulator
+    #[serde(default)]
+    tempo_stability_threshold: Option<usize>,
+    /// defines whether tempo should commit read-only commands accessing more
+    /// than one shard as soon as each shard reports its clock snapshot (i.e.
+    /// without ever taking the slow path), consulting again only the shards
+    /// whose snapshot is lower than the highest one
+    #[serde(default)]
+    tempo_multi_shard_reads: bool,
     /// defines whether caesar should employ the wait condition
     caesar_wait_condition: bool,
     /// defines whether protocols should try to bypass the fast quorum process
     /// ack (which is only possible if the fast quorum size is 2)
     skip_fast_ack: bool,
+    /// defines whether the acks sent to the same coordinator should be
+    /// coalesced into a single message, and if so, the interval between the
+    /// sending of coalesced acks
+    #[serde(default)]
+    ack_coalescing_interval: Option<Duration>,
+    /// defines the time without heartbeats after which a process is suspected
+    /// by a `FailureDetector` (if set)
+    #[serde(default)]
+    failure_detector_timeout: Option<Duration>,
+    /// defines the size of the channels between clients and workers, if
+    /// different from the one of the client channels
+    #[serde(default)]
+    client_to_workers_buffer_size: Option<usize>,
+    /// defines the size of the channels between workers and executors, if
+    /// different from the one of the process channels
+    #[serde(default)]
+    to_executors_buffer_size: Option<usize>,
+    /// defines the size of the channels between the periodic task and
+    /// workers, if different from the one of the process channels
+    #[serde(default)]
+    periodic_to_workers_buffer_size: Option<usize>,
+    /// defines how messages are assigned to workers given their index (e.g.
+    /// the sequence of the dot they refer to); see `WorkerAssignment`
+    #[serde(default)]
+    worker_assignment: WorkerAssignment,
+    /// defines the number of tasks accepting client connections (sharing the
+    /// client port with `SO_REUSEPORT`), if more than one
+    #[serde(default)]
+    client_acceptors: Option<usize>,
+    /// defines whether the command results sent to the same client
+    /// connection should be written in batches (i.e. a single network write
+    /// for all the results available), and if so, the interval between
+    /// flushes
+    #[serde(default)]
+    client_results_flush_delay: Option<Duration>,
+    /// defines the number of tasks tracking the progress of the commands
+    /// submitted on each client connection, if more than one; each client
+    /// multiplexed on the connection is assigned to one of the tasks (based on
+    /// its identifier), so that the results of commands from different
+    /// clients are aggregated in parallel
+    #[serde(default)]
+    client_pending_shards: Option<usize>,
+    /// defines whether the processing of some commands should be delayed at
+    /// the process they're submitted to (emulating server-side hiccups, such
+    /// as GC pauses), and if so, the rate (one in every `rate` commands,
+    /// chosen at random, is delayed) and the delay
+    #[serde(default)]
+    server_delay_injection: Option<(usize, Duration)>,
+    /// defines whether messages between processes should be compressed, and
+    /// if so, the minimum size (in bytes) of the messages compressed; the
+    /// threshold actually used starts at this value and is then tuned by each
+    /// connection given the compression ratios observed and the time spent
+    /// compressing
+    #[serde(default)]
+    message_compression: Option<usize>,
+    /// defines whether messages between processes are delivered at least
+    /// once, and if so, the maximum number of messages sent on each
+    /// connection that can be awaiting acknowledgment by the peer; messages
+    /// are sequenced per connection, kept until acknowledged (so that they can
+    /// be retransmitted), and duplicates are dropped before reaching the
+    /// protocol, whose handlers are not necessarily idempotent
+    #[serde(default)]
+    delivery_window: Option<usize>,
+    /// defines whether processes should be kept alive when a protocol (or
+    /// executor) reports a violation of one of its invariants; if not, the
+    /// process panics once the violation is recorded
+    #[serde(default)]
+    keep_alive_on_violation: bool,
+    /// defines whether dependency-based protocols (i.e. `Atlas` and `EPaxos`)
+    /// should bound how old a dependency can be, and if so, the maximum
+    /// number of sequences a pending dependency can be behind the stable
+    /// frontier (i.e. the highest stable sequence of any process); commands
+    /// with older dependencies take the slow path, even if the fast path
+    /// condition holds
+    #[serde(default)]
+    dependency_horizon: Option<u64>,
+    /// defines whether messages should be sent to the farthest processes
+    /// first, so that the slowest members of a quorum receive them (e.g. an
+    /// `MCommit`) as early as possible
+    #[serde(default)]
+    farthest_first: bool,
 }
 
 impl Config {
@@ -57,42 +231,141 @@ impl Config {
         let execute_at_commit = false;
         // by default, executor cleanups happen every 5ms
         let executor_cleanup_interval = Duration::from_millis(5);
+        // by default, the executor cleanup interval is not adapted
+        let executor_adaptive_cleanup = false;
         // by default, executed notifications happen every 50ms
         let executor_executed_notification_interval = Duration::from_millis(50);
         // by default, pending commnads are not monitored
         let executor_monitor_pending_interval = None;
         // by default, executors do not monitor execution order
         let executor_monitor_execution_order = false;
+        // by default, results are delivered as soon as they're ready
+        let executor_ordered_delivery = false;
+        // by default, commands are never shed
+        let executor_latency_budget = None;
+        // by default, the search for SCCs is never interrupted
+        let executor_scc_budget = None;
+        // by default, there's no access control
+        let executor_tenant_count = None;
+        // by default, every write is applied to the store
+        let executor_write_batching = false;
+        // by default, ops are applied to the key-value store
+        let executor_state_machine = None;
+        // by default, there are no backlog alarms
+        let executor_backlog_alarm = None;
+        // by default, processes don't abort on backlog alarms
+        let executor_backlog_alarm_abort = false;
+        // by default, execution is not throttled
+        let executor_throttle = None;
+        // by default, read-only commands go through the protocol
+        let stale_reads = false;
+        // by default, commands are not traced
+        let trace_sample_rate = None;
+        // by default, clients submit commands to the closest process
+        let client_process_selection = ProcessSelection::default();
+        // by default, keys are only created once accessed
+        let preload_keys = None;
         // by default, commands are deleted at commit time
         let gc_interval = None;
+        // by default, there's no anti-entropy
+        let anti_entropy_interval = None;
         // by default, there's no leader
         let leader = None;
         // by default, `tempo_tiny_quorums = false`
         let tempo_tiny_quorums = false;
+        // by default, `tempo_per_command_tiny_quorums = false`
+        let tempo_per_command_tiny_quorums = false;
         // by default, clocks are not bumped periodically
         let tempo_clock_bump_interval = None;
         // by default, `MDetached` messages are not sent
         let tempo_detached_send_interval = None;
+        // by default, `tempo_commit_clock_bump = false`
+        let tempo_commit_clock_bump = false;
+        // by default, the stability threshold is the safe one
+        let tempo_stability_threshold = None;
+        // by default, `tempo_multi_shard_reads = false`
+        let tempo_multi_shard_reads = false;
         // by default, `caesar_wait_condition = true`
         let caesar_wait_condition = true;
         // by default `skip_fast_ack = false;
         let skip_fast_ack = false;
+        // by default, acks are sent as soon as they're created
+        let ack_coalescing_interval = None;
+        // by default, there's no failure detector
+        let failure_detector_timeout = None;
+        // by default, channel buffer sizes are set by the run layer
+        let client_to_workers_buffer_size = None;
+        let to_executors_buffer_size = None;
+        let periodic_to_workers_buffer_size = None;
+        // by default, consecutive dots are assigned to consecutive workers
+        let worker_assignment = WorkerAssignment::default();
+        // by default, client connections are accepted by a single task
+        let client_acceptors = None;
+        // by default, command results are flushed as soon as they're ready
+        let client_results_flush_delay = None;
+        let client_pending_shards = None;
+        // by default, no delay is injected
+        let server_delay_injection = None;
+        // by default, messages are not compressed
+        let message_compression = None;
+        // by default, messages are delivered at most once
+        let delivery_window = None;
+        // by default, processes panic on violations
+        let keep_alive_on_violation = false;
+        // by default, dependencies can be arbitrarily old
+        let dependency_horizon = None;
+        // by default, messages are sent to processes in no particular order
+        let farthest_first = false;
         Self {
             n,
             f,
             shard_count,
             execute_at_commit,
             executor_cleanup_interval,
+            executor_adaptive_cleanup,
             executor_executed_notification_interval,
             executor_monitor_pending_interval,
             executor_monitor_execution_order,
+            executor_ordered_delivery,
+            executor_latency_budget,
+            executor_scc_budget,
+            executor_tenant_count,
+            executor_write_batching,
+            executor_state_machine,
+            executor_backlog_alarm,
+            executor_backlog_alarm_abort,
+            executor_throttle,
+            stale_reads,
+            trace_sample_rate,
+            client_process_selection,
+            preload_keys,
             gc_interval,
+            anti_entropy_interval,
             leader,
             tempo_tiny_quorums,
+            tempo_per_command_tiny_quorums,
             tempo_clock_bump_interval,
             tempo_detached_send_interval,
+            tempo_commit_clock_bump,
+            tempo_stability_threshold,
+            tempo_multi_shard_reads,
             caesar_wait_condition,
             skip_fast_ack,
+            ack_coalescing_interval,
+            failure_detector_timeout,
+            client_to_workers_buffer_size,
+            to_executors_buffer_size,
+            periodic_to_workers_buffer_size,
+            worker_assignment,
+            client_acceptors,
+            client_results_flush_delay,
+            client_pending_shards,
+            server_delay_injection,
+            message_compression,
+            delivery_window,
+            keep_alive_on_violation,
+            dependency_horizon,
+            farthest_first,
         }
     }
 
@@ -137,6 +410,19 @@ impl Config {
         self.executor_cleanup_interval = interval;
     }
 
+    /// Checks whether the executor cleanup interval is adaptive.
+    pub fn executor_adaptive_cleanup(&self) -> bool {
+        self.executor_adaptive_cleanup
+    }
+
+    /// Changes the value of `executor_adaptive_cleanup`.
+    pub fn set_executor_adaptive_cleanup(
+        &mut self,
+        executor_adaptive_cleanup: bool,
+    ) {
+        self.executor_adaptive_cleanup = executor_adaptive_cleanup;
+    }
+
     /// Checks the executor monitor pending interval.
     pub fn executor_monitor_pending_interval(&self) -> Option<Duration> {
         self.executor_monitor_pending_interval
@@ -164,6 +450,177 @@ impl Config {
             executor_monitor_execution_order;
     }
 
+    /// Checks whether results are delivered in submission order.
+    pub fn executor_ordered_delivery(&self) -> bool {
+        self.executor_ordered_delivery
+    }
+
+    /// Changes the value of `executor_ordered_delivery`.
+    pub fn set_executor_ordered_delivery(
+        &mut self,
+        executor_ordered_delivery: bool,
+    ) {
+        self.executor_ordered_delivery = executor_ordered_delivery;
+    }
+
+    /// Checks the executor latency budget.
+    pub fn executor_latency_budget(&self) -> Option<Duration> {
+        self.executor_latency_budget
+    }
+
+    /// Sets the executor latency budget.
+    pub fn set_executor_latency_budget<I>(&mut self, budget: I)
+    where
+        I: Into<Option<Duration>>,
+    {
+        self.executor_latency_budget = budget.into();
+    }
+
+    /// Checks the maximum number of vertices visited by each search for SCCs.
+    pub fn executor_scc_budget(&self) -> Option<usize> {
+        self.executor_scc_budget
+    }
+
+    /// Sets the maximum number of vertices visited by each search for SCCs.
+    pub fn set_executor_scc_budget<I>(&mut self, budget: I)
+    where
+        I: Into<Option<usize>>,
+    {
+        self.executor_scc_budget = budget.into();
+    }
+
+    /// Checks the number of tenants among which keys are partitioned.
+    pub fn executor_tenant_count(&self) -> Option<usize> {
+        self.executor_tenant_count
+    }
+
+    /// Sets the number of tenants among which keys are partitioned.
+    pub fn set_executor_tenant_count<I>(&mut self, tenant_count: I)
+    where
+        I: Into<Option<usize>>,
+    {
+        self.executor_tenant_count = tenant_count.into();
+    }
+
+    /// Checks whether writes are batched by executors.
+    pub fn executor_write_batching(&self) -> bool {
+        self.executor_write_batching
+    }
+
+    /// Changes the value of `executor_write_batching`.
+    pub fn set_executor_write_batching(
+        &mut self,
+        executor_write_batching: bool,
+    ) {
+        self.executor_write_batching = executor_write_batching;
+    }
+
+    /// Checks the state machine to which executors apply ops (if not the
+    /// key-value store).
+    pub fn executor_state_machine(&self) -> Option<StateMachineKind> {
+        self.executor_state_machine
+    }
+
+    /// Sets the state machine to which executors apply ops.
+    pub fn set_executor_state_machine<S>(&mut self, state_machine: S)
+    where
+        S: Into<Option<StateMachineKind>>,
+    {
+        self.executor_state_machine = state_machine.into();
+    }
+
+    /// Checks the executor backlog alarm, i.e. the backlog size and the
+    /// period during which the backlog must stay above it for an alarm to be
+    /// raised.
+    pub fn executor_backlog_alarm(&self) -> Option<(usize, Duration)> {
+        self.executor_backlog_alarm
+    }
+
+    /// Sets the executor backlog alarm.
+    pub fn set_executor_backlog_alarm<A>(&mut self, alarm: A)
+    where
+        A: Into<Option<(usize, Duration)>>,
+    {
+        self.executor_backlog_alarm = alarm.into();
+    }
+
+    /// Checks whether processes abort once a backlog alarm is raised.
+    pub fn executor_backlog_alarm_abort(&self) -> bool {
+        self.executor_backlog_alarm_abort
+    }
+
+    /// Changes the value of `executor_backlog_alarm_abort`.
+    pub fn set_executor_backlog_alarm_abort(
+        &mut self,
+        executor_backlog_alarm_abort: bool,
+    ) {
+        self.executor_backlog_alarm_abort = executor_backlog_alarm_abort;
+    }
+
+    /// Checks the executor throttle, i.e. the rate (in results per second) at
+    /// which each executor produces command results and the size of the
+    /// bursts allowed.
+    pub fn executor_throttle(&self) -> Option<(usize, usize)> {
+        self.executor_throttle
+    }
+
+    /// Sets the executor throttle.
+    pub fn set_executor_throttle<T>(&mut self, throttle: T)
+    where
+        T: Into<Option<(usize, usize)>>,
+    {
+        self.executor_throttle = throttle.into();
+    }
+
+    /// Checks whether read-only commands are served from the local store.
+    pub fn stale_reads(&self) -> bool {
+        self.stale_reads
+    }
+
+    /// Changes the value of `stale_reads`.
+    pub fn set_stale_reads(&mut self, stale_reads: bool) {
+        self.stale_reads = stale_reads;
+    }
+
+    /// Checks the rate at which commands are traced.
+    pub fn trace_sample_rate(&self) -> Option<usize> {
+        self.trace_sample_rate
+    }
+
+    /// Changes the rate at which commands are traced.
+    pub fn set_trace_sample_rate<I>(&mut self, sample_rate: I)
+    where
+        I: Into<Option<usize>>,
+    {
+        self.trace_sample_rate = sample_rate.into();
+    }
+
+    /// Checks the process (on each shard) to which clients submit commands.
+    pub fn client_process_selection(&self) -> ProcessSelection {
+        self.client_process_selection
+    }
+
+    /// Changes the process (on each shard) to which clients submit commands.
+    pub fn set_client_process_selection(
+        &mut self,
+        selection: ProcessSelection,
+    ) {
+        self.client_process_selection = selection;
+    }
+
+    /// Checks the number of keys preloaded in each shard.
+    pub fn preload_keys(&self) -> Option<usize> {
+        self.preload_keys
+    }
+
+    /// Changes the number of keys preloaded in each shard.
+    pub fn set_preload_keys<I>(&mut self, preload_keys: I)
+    where
+        I: Into<Option<usize>>,
+    {
+        self.preload_keys = preload_keys.into();
+    }
+
     /// Checks the executed notification interval.
     pub fn executor_executed_notification_interval(&self) -> Duration {
         self.executor_executed_notification_interval
@@ -190,6 +647,19 @@ impl Config {
         self.gc_interval = interval.into();
     }
 
+    /// Checks the anti-entropy interval.
+    pub fn anti_entropy_interval(&self) -> Option<Duration> {
+        self.anti_entropy_interval
+    }
+
+    /// Sets the anti-entropy interval.
+    pub fn set_anti_entropy_interval<I>(&mut self, interval: I)
+    where
+        I: Into<Option<Duration>>,
+    {
+        self.anti_entropy_interval = interval.into();
+    }
+
     /// Checks whether a starting leader has been defined.
     pub fn leader(&self) -> Option<ProcessId> {
         self.leader
@@ -213,6 +683,21 @@ impl Config {
         self.tempo_tiny_quorums = tempo_tiny_quorums;
     }
 
+    /// Checks whether tempo per-command tiny quorums is enabled or not.
+    /// This has no effect if `tempo_tiny_quorums` is enabled (as in that case
+    /// all commands use tiny quorums).
+    pub fn tempo_per_command_tiny_quorums(&self) -> bool {
+        self.tempo_per_command_tiny_quorums
+    }
+
+    /// Changes the value of `tempo_per_command_tiny_quorums`.
+    pub fn set_tempo_per_command_tiny_quorums(
+        &mut self,
+        tempo_per_command_tiny_quorums: bool,
+    ) {
+        self.tempo_per_command_tiny_quorums = tempo_per_command_tiny_quorums;
+    }
+
     /// Checks tempo clock bump interval.
     pub fn tempo_clock_bump_interval(&self) -> Option<Duration> {
         self.tempo_clock_bump_interval
@@ -239,6 +724,66 @@ impl Config {
         self.tempo_detached_send_interval = interval.into();
     }
 
+    /// Checks whether tempo commit clock bumps are enabled or not.
+    pub fn tempo_commit_clock_bump(&self) -> bool {
+        self.tempo_commit_clock_bump
+    }
+
+    /// Changes the value of `tempo_commit_clock_bump`.
+    pub fn set_tempo_commit_clock_bump(
+        &mut self,
+        tempo_commit_clock_bump: bool,
+    ) {
+        self.tempo_commit_clock_bump = tempo_commit_clock_bump;
+    }
+
+    /// Checks the stability threshold overriding tempo's safe one (if any).
+    pub fn tempo_stability_threshold(&self) -> Option<usize> {
+        self.tempo_stability_threshold
+    }
+
+    /// Overrides tempo's stability threshold. The threshold must be between 1
+    /// and `n`, but it can be lower than the safe one (see
+    /// `Config::tempo_stability_threshold_is_safe`).
+    pub fn set_tempo_stability_threshold<I>(&mut self, threshold: I)
+    where
+        I: Into<Option<usize>>,
+    {
+        let threshold = threshold.into();
+        if let Some(threshold) = threshold {
+            assert!(
+                threshold >= 1 && threshold <= self.n,
+                "the stability threshold should be between 1 and n"
+            );
+        }
+        self.tempo_stability_threshold = threshold;
+    }
+
+    /// Checks whether tempo's stability threshold is safe, i.e. whether it's
+    /// not overridden by one lower than the safe one. Lower thresholds make
+    /// commands stable (and thus executed) sooner, but they no longer ensure
+    /// that all the processes where a command's clock was computed are among
+    /// the ones consulted for its stability.
+    pub fn tempo_stability_threshold_is_safe(&self) -> bool {
+        let (_, _, safe_threshold) = self.tempo_safe_quorum_sizes();
+        self.tempo_stability_threshold
+            .map(|threshold| threshold >= safe_threshold)
+            .unwrap_or(true)
+    }
+
+    /// Checks whether tempo's multi-shard reads are enabled or not.
+    pub fn tempo_multi_shard_reads(&self) -> bool {
+        self.tempo_multi_shard_reads
+    }
+
+    /// Changes the value of `tempo_multi_shard_reads`.
+    pub fn set_tempo_multi_shard_reads(
+        &mut self,
+        tempo_multi_shard_reads: bool,
+    ) {
+        self.tempo_multi_shard_reads = tempo_multi_shard_reads;
+    }
+
     /// Checks whether caesar's wait condition is enabled or not.
     pub fn caesar_wait_condition(&self) -> bool {
         self.caesar_wait_condition
@@ -258,6 +803,280 @@ impl Config {
     pub fn set_skip_fast_ack(&mut self, skip_fast_ack: bool) {
         self.skip_fast_ack = skip_fast_ack;
     }
+
+    /// Checks the ack coalescing interval.
+    pub fn ack_coalescing_interval(&self) -> Option<Duration> {
+        self.ack_coalescing_interval
+    }
+
+    /// Sets the ack coalescing interval.
+    pub fn set_ack_coalescing_interval<I>(&mut self, interval: I)
+    where
+        I: Into<Option<Duration>>,
+    {
+        self.ack_coalescing_interval = interval.into();
+    }
+
+    /// Checks the failure detector timeout.
+    pub fn failure_detector_timeout(&self) -> Option<Duration> {
+        self.failure_detector_timeout
+    }
+
+    /// Sets the failure detector timeout.
+    pub fn set_failure_detector_timeout<T>(&mut self, timeout: T)
+    where
+        T: Into<Option<Duration>>,
+    {
+        self.failure_detector_timeout = timeout.into();
+    }
+
+    /// Checks the size of the channels between clients and workers.
+    pub fn client_to_workers_buffer_size(&self) -> Option<usize> {
+        self.client_to_workers_buffer_size
+    }
+
+    /// Sets the size of the channels between clients and workers.
+    pub fn set_client_to_workers_buffer_size<S>(&mut self, buffer_size: S)
+    where
+        S: Into<Option<usize>>,
+    {
+        self.client_to_workers_buffer_size = buffer_size.into();
+    }
+
+    /// Checks the size of the channels between workers and executors.
+    pub fn to_executors_buffer_size(&self) -> Option<usize> {
+        self.to_executors_buffer_size
+    }
+
+    /// Sets the size of the channels between workers and executors.
+    pub fn set_to_executors_buffer_size<S>(&mut self, buffer_size: S)
+    where
+        S: Into<Option<usize>>,
+    {
+        self.to_executors_buffer_size = buffer_size.into();
+    }
+
+    /// Checks the size of the channels between the periodic task and workers.
+    pub fn periodic_to_workers_buffer_size(&self) -> Option<usize> {
+        self.periodic_to_workers_buffer_size
+    }
+
+    /// Sets the size of the channels between the periodic task and workers.
+    pub fn set_periodic_to_workers_buffer_size<S>(&mut self, buffer_size: S)
+    where
+        S: Into<Option<usize>>,
+    {
+        self.periodic_to_workers_buffer_size = buffer_size.into();
+    }
+
+    /// Checks how messages are assigned to workers.
+    pub fn worker_assignment(&self) -> WorkerAssignment {
+        self.worker_assignment
+    }
+
+    /// Changes how messages are assigned to workers.
+    pub fn set_worker_assignment(&mut self, assignment: WorkerAssignment) {
+        self.worker_assignment = assignment;
+    }
+
+    /// Checks the number of tasks accepting client connections.
+    pub fn client_acceptors(&self) -> Option<usize> {
+        self.client_acceptors
+    }
+
+    /// Sets the number of tasks accepting client connections.
+    pub fn set_client_acceptors<A>(&mut self, acceptors: A)
+    where
+        A: Into<Option<usize>>,
+    {
+        self.client_acceptors = acceptors.into();
+    }
+
+    /// Checks the delay between flushes of command results to clients.
+    pub fn client_results_flush_delay(&self) -> Option<Duration> {
+        self.client_results_flush_delay
+    }
+
+    /// Sets the delay between flushes of command results to clients.
+    pub fn set_client_results_flush_delay<D>(&mut self, delay: D)
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.client_results_flush_delay = delay.into();
+    }
+
+    /// Checks the number of tasks tracking the progress of the commands
+    /// submitted on each client connection.
+    pub fn client_pending_shards(&self) -> Option<usize> {
+        self.client_pending_shards
+    }
+
+    /// Sets the number of tasks tracking the progress of the commands
+    /// submitted on each client connection.
+    pub fn set_client_pending_shards<S>(&mut self, shards: S)
+    where
+        S: Into<Option<usize>>,
+    {
+        self.client_pending_shards = shards.into();
+    }
+
+    /// Checks the delay injected at the process commands are submitted to,
+    /// i.e. the rate of commands delayed and the delay.
+    pub fn server_delay_injection(&self) -> Option<(usize, Duration)> {
+        self.server_delay_injection
+    }
+
+    /// Sets the delay injected at the process commands are submitted to.
+    pub fn set_server_delay_injection<I>(&mut self, injection: I)
+    where
+        I: Into<Option<(usize, Duration)>>,
+    {
+        self.server_delay_injection = injection.into();
+    }
+
+    /// Checks the minimum size of the messages compressed (if messages are
+    /// compressed).
+    pub fn message_compression(&self) -> Option<usize> {
+        self.message_compression
+    }
+
+    /// Sets the minimum size of the messages compressed.
+    pub fn set_message_compression<C>(&mut self, compression: C)
+    where
+        C: Into<Option<usize>>,
+    {
+        self.message_compression = compression.into();
+    }
+
+    /// Checks the maximum number of unacknowledged messages per connection
+    /// (if messages are delivered at least once).
+    pub fn delivery_window(&self) -> Option<usize> {
+        self.delivery_window
+    }
+
+    /// Sets the maximum number of unacknowledged messages per connection.
+    pub fn set_delivery_window<W>(&mut self, window: W)
+    where
+        W: Into<Option<usize>>,
+    {
+        self.delivery_window = window.into();
+    }
+
+    /// Checks whether processes are kept alive on violations.
+    pub fn keep_alive_on_violation(&self) -> bool {
+        self.keep_alive_on_violation
+    }
+
+    /// Changes the value of `keep_alive_on_violation`.
+    pub fn set_keep_alive_on_violation(
+        &mut self,
+        keep_alive_on_violation: bool,
+    ) {
+        self.keep_alive_on_violation = keep_alive_on_violation;
+    }
+
+    /// Checks the dependency horizon.
+    pub fn dependency_horizon(&self) -> Option<u64> {
+        self.dependency_horizon
+    }
+
+    /// Sets the dependency horizon.
+    pub fn set_dependency_horizon<H>(&mut self, horizon: H)
+    where
+        H: Into<Option<u64>>,
+    {
+        self.dependency_horizon = horizon.into();
+    }
+
+    /// Checks whether messages are sent to the farthest processes first.
+    pub fn farthest_first(&self) -> bool {
+        self.farthest_first
+    }
+
+    /// Changes the value of `farthest_first`.
+    pub fn set_farthest_first(&mut self, farthest_first: bool) {
+        self.farthest_first = farthest_first;
+    }
+}
+
+/// Named combinations of the configuration knobs commonly used together, so
+/// that experiments can be reproduced by naming the profile they were run
+/// with. Profiles only set knobs that are not protocol-specific: `n`, `f`,
+/// the number of shards and the `tempo_*` quorum knobs are still set by each
+/// experiment, while the knobs not set by a profile keep the defaults of
+/// `Config::new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConfigProfile {
+    /// Knobs used by default in the wide-area experiments: executors adapt
+    /// their cleanup interval to their load, commands are garbage collected
+    /// every 50ms and `MDetached` messages are sent every 5ms.
+    WanDefault,
+    /// Same as `WanDefault`, but with less frequent executed notifications and
+    /// garbage collections, trading memory for throughput when the network is
+    /// not the bottleneck.
+    LanHighThroughput,
+    /// Knobs used in the fairness and tail latency experiments of the EuroSys
+    /// paper (figure 6). These match `WanDefault` at the time the experiments
+    /// were run, and are kept separately so that they don't change with it.
+    PaperEurosysFig6,
+}
+
+impl ConfigProfile {
+    /// Returns all the profiles.
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::WanDefault,
+            Self::LanHighThroughput,
+            Self::PaperEurosysFig6,
+        ]
+    }
+
+    /// Returns the name of the profile.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::WanDefault => "wan-default",
+            Self::LanHighThroughput => "lan-high-throughput",
+            Self::PaperEurosysFig6 => "paper-eurosys-fig6",
+        }
+    }
+
+    /// Returns the profile with the name provided, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|profile| profile.name() == name)
+    }
+
+    /// Applies the profile to `config`.
+    pub fn apply(&self, config: &mut Config) {
+        match self {
+            Self::WanDefault | Self::PaperEurosysFig6 => {
+                config.set_executor_adaptive_cleanup(true);
+                config.set_gc_interval(Duration::from_millis(50));
+                config
+                    .set_tempo_detached_send_interval(Duration::from_millis(5));
+            }
+            Self::LanHighThroughput => {
+                config.set_executor_adaptive_cleanup(true);
+                config.set_executor_executed_notification_interval(
+                    Duration::from_millis(100),
+                );
+                config.set_gc_interval(Duration::from_millis(100));
+                config
+                    .set_tempo_detached_send_interval(Duration::from_millis(5));
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Create a new `Config` with the knobs of the profile provided (see
+    /// `ConfigProfile`).
+    pub fn from_profile(profile: ConfigProfile, n: usize, f: usize) -> Self {
+        let mut config = Self::new(n, f);
+        profile.apply(&mut config);
+        config
+    }
 }
 
 impl Config {
@@ -314,19 +1133,45 @@ impl Config {
     /// - this ensures that the stability threshold plus the minimum number of
     ///   processes where clocks are computed (i.e. fast_quorum_size - f + 1) is
     ///   greater than n
+    ///
+    /// With per-command tiny quorums, the fast quorum size returned is the one
+    /// of regular quorums (see `Config::tempo_tiny_fast_quorum_size` for the
+    /// one of tiny quorums), while the stability threshold is the one of tiny
+    /// quorums, since some commands may have their clocks computed by only f +
+    /// 1 processes.
+    ///
+    /// If the stability threshold is overridden (see
+    /// `Config::set_tempo_stability_threshold`), the override is returned
+    /// instead.
     pub fn tempo_quorum_sizes(&self) -> (usize, usize, usize) {
+        let (fast_quorum_size, write_quorum_size, stability_threshold) =
+            self.tempo_safe_quorum_sizes();
+        let stability_threshold = self
+            .tempo_stability_threshold
+            .unwrap_or(stability_threshold);
+        (fast_quorum_size, write_quorum_size, stability_threshold)
+    }
+
+    fn tempo_safe_quorum_sizes(&self) -> (usize, usize, usize) {
         let n = self.n;
         let f = self.f;
         let minority = n / 2;
         let (fast_quorum_size, stability_threshold) = if self.tempo_tiny_quorums
         {
             (2 * f, n - f)
+        } else if self.tempo_per_command_tiny_quorums {
+            (minority + f, n - f)
         } else {
             (minority + f, minority + 1)
         };
         let write_quorum_size = f + 1;
         (fast_quorum_size, write_quorum_size, stability_threshold)
     }
+
+    /// Computes `Tempo` fast quorum size for commands using tiny quorums.
+    pub fn tempo_tiny_fast_quorum_size(&self) -> usize {
+        2 * self.f
+    }
 }
 
 #[cfg(test)]
@@ -370,6 +1215,12 @@ mod tests {
         config.set_executor_cleanup_interval(interval);
         assert_eq!(config.executor_cleanup_interval(), interval);
 
+        // by default, the executor cleanup interval is not adaptive
+        assert!(!config.executor_adaptive_cleanup());
+        // but that can change
+        config.set_executor_adaptive_cleanup(true);
+        assert!(config.executor_adaptive_cleanup());
+
         // by default, the executor executed notification interval is 50ms
         assert_eq!(
             config.executor_executed_notification_interval(),
@@ -395,6 +1246,92 @@ mod tests {
         config.set_executor_monitor_execution_order(true);
         assert_eq!(config.executor_monitor_execution_order(), true);
 
+        // by default, results are not delivered in submission order
+        assert!(!config.executor_ordered_delivery());
+        // but that can change
+        config.set_executor_ordered_delivery(true);
+        assert!(config.executor_ordered_delivery());
+
+        // by default, there's no executor latency budget
+        assert_eq!(config.executor_latency_budget(), None);
+        // but that can change
+        let budget = Duration::from_millis(100);
+        config.set_executor_latency_budget(budget);
+        assert_eq!(config.executor_latency_budget(), Some(budget));
+
+        // by default, there's no executor SCC budget
+        assert_eq!(config.executor_scc_budget(), None);
+        // but that can change
+        config.set_executor_scc_budget(1000);
+        assert_eq!(config.executor_scc_budget(), Some(1000));
+
+        // by default, there's no access control
+        assert_eq!(config.executor_tenant_count(), None);
+        // but that can change
+        config.set_executor_tenant_count(4);
+        assert_eq!(config.executor_tenant_count(), Some(4));
+
+        // by default, writes are not batched
+        assert!(!config.executor_write_batching());
+        // but that can change
+        config.set_executor_write_batching(true);
+        assert!(config.executor_write_batching());
+
+        // by default, ops are applied to the key-value store
+        assert_eq!(config.executor_state_machine(), None);
+        // but that can change
+        config.set_executor_state_machine(StateMachineKind::Lock);
+        assert_eq!(
+            config.executor_state_machine(),
+            Some(StateMachineKind::Lock)
+        );
+
+        // by default, there are no backlog alarms
+        assert_eq!(config.executor_backlog_alarm(), None);
+        assert!(!config.executor_backlog_alarm_abort());
+        // but that can change
+        let alarm = (1000, Duration::from_secs(10));
+        config.set_executor_backlog_alarm(alarm);
+        config.set_executor_backlog_alarm_abort(true);
+        assert_eq!(config.executor_backlog_alarm(), Some(alarm));
+        assert!(config.executor_backlog_alarm_abort());
+
+        // by default, execution is not throttled
+        assert_eq!(config.executor_throttle(), None);
+        // but that can change
+        config.set_executor_throttle((1000, 100));
+        assert_eq!(config.executor_throttle(), Some((1000, 100)));
+
+        // by default, there are no stale reads
+        assert!(!config.stale_reads());
+        // but that can change
+        config.set_stale_reads(true);
+        assert!(config.stale_reads());
+
+        // by default, commands are not traced
+        assert_eq!(config.trace_sample_rate(), None);
+        // but that can change
+        config.set_trace_sample_rate(1000);
+        assert_eq!(config.trace_sample_rate(), Some(1000));
+
+        // by default, clients submit commands to the closest process
+        assert_eq!(
+            config.client_process_selection(),
+            ProcessSelection::KthClosest(0)
+        );
+        // but that can change
+        config.set_client_process_selection(ProcessSelection::Fixed(2));
+        assert_eq!(
+            config.client_process_selection(),
+            ProcessSelection::Fixed(2)
+        );
+
+        // by default, keys are not preloaded
+        assert_eq!(config.preload_keys(), None);
+        // but that can change
+        config.set_preload_keys(1_000_000);
+        assert_eq!(config.preload_keys(), Some(1_000_000));
+
         // by default, there's no garbage collection interval
         assert_eq!(config.gc_interval(), None);
 
@@ -403,6 +1340,13 @@ mod tests {
         config.set_gc_interval(interval);
         assert_eq!(config.gc_interval(), Some(interval));
 
+        // by default, there's no anti-entropy
+        assert_eq!(config.anti_entropy_interval(), None);
+        // but that can change
+        let interval = Duration::from_millis(500);
+        config.set_anti_entropy_interval(interval);
+        assert_eq!(config.anti_entropy_interval(), Some(interval));
+
         // by default, there's no leader
         assert!(config.leader().is_none());
         // but that can change
@@ -421,6 +1365,13 @@ mod tests {
         config.set_tempo_tiny_quorums(true);
         assert!(config.tempo_tiny_quorums());
 
+        // by default, tempo per-command tiny quorums is false
+        assert!(!config.tempo_per_command_tiny_quorums());
+
+        // but that can change
+        config.set_tempo_per_command_tiny_quorums(true);
+        assert!(config.tempo_per_command_tiny_quorums());
+
         // by default, there's no clock bump interval
         assert!(config.tempo_clock_bump_interval().is_none());
         // but that can change
@@ -435,6 +1386,20 @@ mod tests {
         config.set_tempo_detached_send_interval(interval);
         assert_eq!(config.tempo_detached_send_interval(), Some(interval));
 
+        // by default, tempo commit clock bumps are disabled
+        assert!(!config.tempo_commit_clock_bump());
+
+        // but that can change
+        config.set_tempo_commit_clock_bump(true);
+        assert!(config.tempo_commit_clock_bump());
+
+        // by default, tempo multi-shard reads are disabled
+        assert!(!config.tempo_multi_shard_reads());
+
+        // but that can change
+        config.set_tempo_multi_shard_reads(true);
+        assert!(config.tempo_multi_shard_reads());
+
         // by default, caesar wait condition is true
         assert!(config.caesar_wait_condition());
 
@@ -456,6 +1421,96 @@ mod tests {
         // if we change it to true, it becomes true
         config.set_skip_fast_ack(true);
         assert!(config.skip_fast_ack());
+
+        // by default, acks are not coalesced
+        assert_eq!(config.ack_coalescing_interval(), None);
+        // but that can change
+        let interval = Duration::from_micros(500);
+        config.set_ack_coalescing_interval(interval);
+        assert_eq!(config.ack_coalescing_interval(), Some(interval));
+
+        // by default, there's no failure detector
+        assert_eq!(config.failure_detector_timeout(), None);
+        // but that can change
+        let timeout = Duration::from_millis(300);
+        config.set_failure_detector_timeout(timeout);
+        assert_eq!(config.failure_detector_timeout(), Some(timeout));
+
+        // by default, channel buffer sizes are not set
+        assert!(config.client_to_workers_buffer_size().is_none());
+        assert!(config.to_executors_buffer_size().is_none());
+        assert!(config.periodic_to_workers_buffer_size().is_none());
+
+        // but that can change
+        config.set_client_to_workers_buffer_size(100);
+        config.set_to_executors_buffer_size(200);
+        config.set_periodic_to_workers_buffer_size(300);
+        assert_eq!(config.client_to_workers_buffer_size(), Some(100));
+        assert_eq!(config.to_executors_buffer_size(), Some(200));
+        assert_eq!(config.periodic_to_workers_buffer_size(), Some(300));
+
+        // by default, dots are assigned to workers contiguously
+        assert_eq!(config.worker_assignment(), WorkerAssignment::Contiguous);
+        // but that can change
+        config.set_worker_assignment(WorkerAssignment::Hashed);
+        assert_eq!(config.worker_assignment(), WorkerAssignment::Hashed);
+
+        // by default, there's a single client acceptor
+        assert!(config.client_acceptors().is_none());
+        // but that can change
+        config.set_client_acceptors(4);
+        assert_eq!(config.client_acceptors(), Some(4));
+
+        // by default, command results are not written in batches
+        assert_eq!(config.client_results_flush_delay(), None);
+        // but that can change
+        let delay = Duration::from_micros(200);
+        config.set_client_results_flush_delay(delay);
+        assert_eq!(config.client_results_flush_delay(), Some(delay));
+
+        // by default, commands submitted on each client connection are
+        // tracked by a single task
+        assert_eq!(config.client_pending_shards(), None);
+        // but that can change
+        config.set_client_pending_shards(4);
+        assert_eq!(config.client_pending_shards(), Some(4));
+
+        // by default, no delay is injected
+        assert_eq!(config.server_delay_injection(), None);
+        // but that can change
+        let injection = (100, Duration::from_millis(50));
+        config.set_server_delay_injection(injection);
+        assert_eq!(config.server_delay_injection(), Some(injection));
+
+        // by default, messages are not compressed
+        assert_eq!(config.message_compression(), None);
+        // but that can change
+        config.set_message_compression(1024);
+        assert_eq!(config.message_compression(), Some(1024));
+
+        // by default, messages are delivered at most once
+        assert_eq!(config.delivery_window(), None);
+        // but that can change
+        config.set_delivery_window(64);
+        assert_eq!(config.delivery_window(), Some(64));
+
+        // by default, processes are not kept alive on violations
+        assert!(!config.keep_alive_on_violation());
+        // but that can change
+        config.set_keep_alive_on_violation(true);
+        assert!(config.keep_alive_on_violation());
+
+        // by default, dependencies can be arbitrarily old
+        assert_eq!(config.dependency_horizon(), None);
+        // but that can change
+        config.set_dependency_horizon(1000);
+        assert_eq!(config.dependency_horizon(), Some(1000));
+
+        // by default, messages are not sent to the farthest processes first
+        assert!(!config.farthest_first());
+        // but that can change
+        config.set_farthest_first(true);
+        assert!(config.farthest_first());
     }
 
     #[test]
@@ -546,5 +1601,77 @@ mod tests {
         let mut config = Config::new(7, 2);
         config.set_tempo_tiny_quorums(true);
         assert_eq!(config.tempo_quorum_sizes(), (4, 3, 5));
+
+        // per-command tiny quorums = true
+        let mut config = Config::new(7, 1);
+        config.set_tempo_per_command_tiny_quorums(true);
+        assert_eq!(config.tempo_quorum_sizes(), (4, 2, 6));
+        assert_eq!(config.tempo_tiny_fast_quorum_size(), 2);
+
+        let mut config = Config::new(7, 2);
+        config.set_tempo_per_command_tiny_quorums(true);
+        assert_eq!(config.tempo_quorum_sizes(), (5, 3, 5));
+        assert_eq!(config.tempo_tiny_fast_quorum_size(), 4);
+
+        // stability threshold override
+        let mut config = Config::new(7, 1);
+        assert_eq!(config.tempo_stability_threshold(), None);
+        assert!(config.tempo_stability_threshold_is_safe());
+        // higher thresholds are safe
+        config.set_tempo_stability_threshold(5);
+        assert_eq!(config.tempo_quorum_sizes(), (4, 2, 5));
+        assert!(config.tempo_stability_threshold_is_safe());
+        // but lower ones are not
+        config.set_tempo_stability_threshold(3);
+        assert_eq!(config.tempo_quorum_sizes(), (4, 2, 3));
+        assert!(!config.tempo_stability_threshold_is_safe());
+        // and what's safe depends on the quorums used
+        config.set_tempo_tiny_quorums(true);
+        config.set_tempo_stability_threshold(5);
+        assert_eq!(config.tempo_quorum_sizes(), (2, 2, 5));
+        assert!(!config.tempo_stability_threshold_is_safe());
+        config.set_tempo_stability_threshold(None);
+        assert_eq!(config.tempo_quorum_sizes(), (2, 2, 6));
+    }
+
+    #[test]
+    #[should_panic]
+    fn tempo_stability_threshold_higher_than_n() {
+        let mut config = Config::new(5, 1);
+        config.set_tempo_stability_threshold(6);
+    }
+
+    #[test]
+    fn config_profiles() {
+        // names are unique and map back to the profile
+        for profile in ConfigProfile::all() {
+            assert_eq!(ConfigProfile::from_name(profile.name()), Some(profile));
+        }
+        assert_eq!(ConfigProfile::from_name("unknown"), None);
+
+        // profiles keep `n` and `f` and set their knobs
+        let config = Config::from_profile(ConfigProfile::WanDefault, 5, 2);
+        assert_eq!(config.n(), 5);
+        assert_eq!(config.f(), 2);
+        assert!(config.executor_adaptive_cleanup());
+        assert_eq!(config.gc_interval(), Some(Duration::from_millis(50)));
+        assert_eq!(
+            config.tempo_detached_send_interval(),
+            Some(Duration::from_millis(5))
+        );
+
+        let config =
+            Config::from_profile(ConfigProfile::LanHighThroughput, 3, 1);
+        assert_eq!(
+            config.executor_executed_notification_interval(),
+            Duration::from_millis(100)
+        );
+        assert_eq!(config.gc_interval(), Some(Duration::from_millis(100)));
+
+        // the paper profile matches the one it was taken from
+        assert_eq!(
+            Config::from_profile(ConfigProfile::PaperEurosysFig6, 5, 1),
+            Config::from_profile(ConfigProfile::WanDefault, 5, 1)
+        );
     }
 }
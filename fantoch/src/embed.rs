@@ -0,0 +1,381 @@
+use crate::command::{Command, CommandResult};
+use crate::config::Config;
+use crate::executor::{AggregatePending, Executor};
+use crate::id::{ClientId, ProcessId, Rifl, RiflGen, ShardId};
+use crate::kvs::{KVOp, Key};
+use crate::protocol::{Action, Protocol};
+use crate::time::RunTime;
+use crate::HashMap;
+use color_eyre::eyre::{eyre, Report};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+// embedded clusters have a single shard
+const SHARD_ID: ShardId = 0;
+
+// Inputs of the task running each process.
+enum ToProcess<P: Protocol> {
+    Submit(Command, oneshot::Sender<CommandResult>),
+    Message(ProcessId, ShardId, P::Message),
+    PeriodicEvent(P::PeriodicEvent),
+    ExecutedNotification,
+    ExecutorCleanup,
+}
+
+type ProcessSender<P> = mpsc::UnboundedSender<ToProcess<P>>;
+
+/// An in-process cluster running protocol `P`: each process runs as a tokio
+/// task, and processes exchange messages through channels (instead of TCP).
+/// This allows fantoch protocols to be used as an embedded replication layer,
+/// e.g. in the tests of downstream crates.
+///
+/// Only clusters with a single shard are supported. The cluster is stopped
+/// once dropped.
+pub struct Cluster<P: Protocol> {
+    // channels to each process, sorted by process identifier
+    processes: Vec<(ProcessId, ProcessSender<P>)>,
+    // identifier of the last client created
+    client_id: Arc<AtomicU64>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl<P> Cluster<P>
+where
+    P: Protocol + Send + 'static,
+{
+    /// Starts a cluster with the processes defined by `config`. It should be
+    /// called within a tokio runtime.
+    pub fn start(config: Config) -> Result<Self, Report> {
+        if config.shard_count() != 1 {
+            return Err(eyre!(
+                "embedded clusters only support a single shard; found {} shards",
+                config.shard_count()
+            ));
+        }
+
+        // create the channels of all processes before spawning them, as each
+        // process sends messages to all the others
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            crate::util::process_ids(SHARD_ID, config.n())
+                .map(|process_id| {
+                    let (tx, rx) = mpsc::unbounded_channel();
+                    ((process_id, tx), rx)
+                })
+                .unzip();
+        let peers: HashMap<_, _> = senders.iter().cloned().collect();
+
+        let mut handles = Vec::new();
+        for ((process_id, tx), rx) in senders.iter().zip(receivers) {
+            let (mut process, periodic_events) =
+                P::new(*process_id, SHARD_ID, config);
+
+            // processes are sorted by identifier, but with self first
+            let sorted = std::iter::once(*process_id)
+                .chain(
+                    senders
+                        .iter()
+                        .map(|(peer_id, _)| *peer_id)
+                        .filter(|peer_id| peer_id != process_id),
+                )
+                .map(|peer_id| (peer_id, SHARD_ID))
+                .collect();
+            let (connect_ok, _) = process.discover(sorted);
+            if !connect_ok {
+                return Err(eyre!("process {} couldn't connect", process_id));
+            }
+            let executor =
+                <P::Executor as Executor>::new(*process_id, SHARD_ID, config);
+
+            // periodic events are delivered to the process as any other input
+            for (event, interval) in periodic_events {
+                handles.push(ticker(tx.clone(), interval, move || {
+                    ToProcess::PeriodicEvent(event.clone())
+                }));
+            }
+            handles.push(ticker(
+                tx.clone(),
+                config.executor_executed_notification_interval(),
+                || ToProcess::ExecutedNotification,
+            ));
+            handles.push(ticker(
+                tx.clone(),
+                config.executor_cleanup_interval(),
+                || ToProcess::ExecutorCleanup,
+            ));
+
+            handles.push(tokio::spawn(process_task(
+                process,
+                executor,
+                rx,
+                peers.clone(),
+            )));
+        }
+
+        Ok(Self {
+            processes: senders,
+            client_id: Arc::new(AtomicU64::new(0)),
+            handles,
+        })
+    }
+
+    /// Returns the identifiers of the processes in the cluster.
+    pub fn process_ids(&self) -> impl Iterator<Item = ProcessId> + '_ {
+        self.processes.iter().map(|(process_id, _)| *process_id)
+    }
+
+    /// Creates a new client, which submits its commands to one of the
+    /// processes (clients are assigned to processes in a round-robin
+    /// fashion).
+    pub fn client(&self) -> ClusterClient<P> {
+        let client_id = self.client_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let index = (client_id as usize - 1) % self.processes.len();
+        let (process_id, process) = self.processes[index].clone();
+        ClusterClient {
+            client_id,
+            process_id,
+            rifl_gen: RiflGen::new(client_id),
+            process,
+        }
+    }
+}
+
+impl<P: Protocol> Drop for Cluster<P> {
+    fn drop(&mut self) {
+        for handle in self.handles.iter() {
+            handle.abort();
+        }
+    }
+}
+
+/// A client of a `Cluster`.
+pub struct ClusterClient<P: Protocol> {
+    client_id: ClientId,
+    process_id: ProcessId,
+    rifl_gen: RiflGen,
+    process: ProcessSender<P>,
+}
+
+impl<P: Protocol> ClusterClient<P> {
+    /// Returns the identifier of this client.
+    pub fn id(&self) -> ClientId {
+        self.client_id
+    }
+
+    /// Returns the identifier of the process this client submits commands to.
+    pub fn process_id(&self) -> ProcessId {
+        self.process_id
+    }
+
+    /// Submits a command with the given ops, waiting for its result.
+    pub async fn submit(
+        &mut self,
+        ops: impl IntoIterator<Item = (Key, Vec<KVOp>)>,
+    ) -> Result<CommandResult, Report> {
+        let rifl = self.rifl_gen.next_id();
+        let mut shard_to_ops = HashMap::new();
+        shard_to_ops.insert(SHARD_ID, ops.into_iter().collect());
+        let cmd = Command::new(rifl, shard_to_ops);
+
+        let (tx, rx) = oneshot::channel();
+        if self.process.send(ToProcess::Submit(cmd, tx)).is_err() {
+            return Err(eyre!("process {} has stopped", self.process_id));
+        }
+        rx.await.map_err(|_| {
+            eyre!("process {} dropped command {:?}", self.process_id, rifl)
+        })
+    }
+
+    /// Executes `op` on `key`, returning its result.
+    pub async fn execute(
+        &mut self,
+        key: Key,
+        op: KVOp,
+    ) -> Result<Option<String>, Report> {
+        let cmd_result = self.submit(vec![(key.clone(), vec![op])]).await?;
+        if let Some(error) = cmd_result.errors().get(&key) {
+            return Err(eyre!("op on key {:?} failed: {:?}", key, error));
+        }
+        let mut results = cmd_result
+            .results()
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| eyre!("missing result of key {:?}", key))?;
+        Ok(results.pop().flatten())
+    }
+}
+
+// Spawns a task that sends the input created by `input` to a process every
+// `interval`.
+fn ticker<P, F>(
+    tx: ProcessSender<P>,
+    interval: Duration,
+    input: F,
+) -> JoinHandle<()>
+where
+    P: Protocol + Send + 'static,
+    F: Fn() -> ToProcess<P> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        // the first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if tx.send(input()).is_err() {
+                return;
+            }
+        }
+    })
+}
+
+async fn process_task<P>(
+    mut process: P,
+    mut executor: P::Executor,
+    mut rx: mpsc::UnboundedReceiver<ToProcess<P>>,
+    peers: HashMap<ProcessId, ProcessSender<P>>,
+) where
+    P: Protocol + Send + 'static,
+{
+    let time = RunTime;
+    let process_id = process.id();
+    let shard_id = process.shard_id();
+    let mut pending = AggregatePending::new(process_id, shard_id);
+    let mut waiting: HashMap<Rifl, oneshot::Sender<CommandResult>> =
+        HashMap::new();
+
+    while let Some(input) = rx.recv().await {
+        match input {
+            ToProcess::Submit(cmd, tx) => {
+                waiting.insert(cmd.rifl(), tx);
+                pending.wait_for(&cmd);
+                process.submit(None, cmd, &time);
+            }
+            ToProcess::Message(from, from_shard_id, msg) => {
+                process.handle(from, from_shard_id, msg, &time);
+            }
+            ToProcess::PeriodicEvent(event) => {
+                process.handle_event(event, &time);
+            }
+            ToProcess::ExecutedNotification => {
+                if let Some(executed) = executor.executed(&time) {
+                    process.handle_executed(executed, &time);
+                }
+            }
+            ToProcess::ExecutorCleanup => {
+                executor.cleanup(&time);
+            }
+        }
+
+        // handle the actions of the process until there are no more (messages
+        // to self are handled right away)
+        loop {
+            let actions: Vec<_> = process.to_processes_iter().collect();
+            if actions.is_empty() {
+                break;
+            }
+            for action in actions {
+                match action {
+                    Action::ToSend { target, msg } => {
+                        for to in target {
+                            if to == process_id {
+                                process.handle(
+                                    process_id,
+                                    shard_id,
+                                    msg.clone(),
+                                    &time,
+                                );
+                            } else if let Some(peer) = peers.get(&to) {
+                                // peers only stop once the cluster is dropped
+                                let _ = peer.send(ToProcess::Message(
+                                    process_id,
+                                    shard_id,
+                                    msg.clone(),
+                                ));
+                            }
+                        }
+                    }
+                    Action::ToForward { msg } => {
+                        process.handle(process_id, shard_id, msg, &time);
+                    }
+                }
+            }
+        }
+
+        // handle new execution info in the executor
+        for info in process.to_executors_iter().collect::<Vec<_>>() {
+            executor.handle(info, &time);
+        }
+        loop {
+            let mut infos: Vec<_> =
+                executor.to_executors_iter().map(|(_, info)| info).collect();
+            infos.extend(
+                executor.to_process_executors_iter().map(|(_, info)| info),
+            );
+            if infos.is_empty() {
+                break;
+            }
+            for info in infos {
+                executor.handle(info, &time);
+            }
+        }
+
+        // violations are never expected
+        if let Some(violation) =
+            process.to_violations().or_else(|| executor.to_violations())
+        {
+            panic!("violation {}", violation);
+        }
+
+        // send the results of the commands that are ready
+        for executor_result in executor.to_clients_iter().collect::<Vec<_>>() {
+            for cmd_result in pending.add_executor_result(executor_result) {
+                if let Some(tx) = waiting.remove(&cmd_result.rifl()) {
+                    // the client may have given up on the command
+                    let _ = tx.send(cmd_result);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Basic;
+
+    #[tokio::test]
+    async fn embedded_cluster() {
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+        let cluster = Cluster::<Basic>::start(config).expect("cluster starts");
+        assert_eq!(cluster.process_ids().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // clients are assigned to processes in a round-robin fashion
+        let mut client_a = cluster.client();
+        let mut client_b = cluster.client();
+        assert_eq!(client_a.process_id(), 1);
+        assert_eq!(client_b.process_id(), 2);
+
+        // clients read their own writes
+        for (client, key) in vec![(&mut client_a, "A"), (&mut client_b, "B")] {
+            let key = String::from(key);
+            let value = String::from("x");
+            let result = client
+                .execute(key.clone(), KVOp::Put(value.clone()))
+                .await
+                .unwrap();
+            assert_eq!(result, None);
+            let result = client.execute(key, KVOp::Get).await.unwrap();
+            assert_eq!(result, Some(value));
+        }
+
+        // only a single shard is supported
+        let mut config = Config::new(n, f);
+        config.set_shard_count(2);
+        assert!(Cluster::<Basic>::start(config).is_err());
+    }
+}
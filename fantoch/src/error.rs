@@ -0,0 +1,133 @@
+use crate::warn;
+use color_eyre::Report;
+use std::fmt;
+
+// prefix of the line logged when a run fails (see `RunError::log_failure`)
+const RUN_FAILED: &str = "run failed";
+
+/// Errors that make a run fail. These are categorized so that failed runs can
+/// be told apart, e.g. by the experiment harness, which retries runs that
+/// failed due to transient errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError {
+    /// connecting to (or listening for) other processes failed, or some
+    /// connection was lost
+    Connection(String),
+    /// some value couldn't be serialized or deserialized
+    Serialization(String),
+    /// the other end of a channel between tasks is gone
+    Channel(String),
+    /// a protocol (or executor) violated one of its invariants
+    ProtocolViolation(String),
+}
+
+impl RunError {
+    /// Returns the name of the category of the error.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Connection(_) => "connection",
+            Self::Serialization(_) => "serialization",
+            Self::Channel(_) => "channel",
+            Self::ProtocolViolation(_) => "protocol_violation",
+        }
+    }
+
+    /// Checks whether the error is transient, i.e. whether the run may succeed
+    /// if retried.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Connection(_) | Self::Channel(_))
+    }
+
+    /// Finds the first `RunError` in the chain of errors of `report` (if
+    /// any).
+    pub fn find(report: &Report) -> Option<&Self> {
+        report
+            .chain()
+            .find_map(|error| error.downcast_ref::<Self>())
+    }
+
+    /// Logs that the run failed with `report`, along with the category of the
+    /// error (if known), and returns it.
+    pub fn log_failure(report: Report) -> Report {
+        if let Some(error) = Self::find(&report) {
+            warn!("{} ({}): {:?}", RUN_FAILED, error.category(), report);
+        } else {
+            warn!("{}: {:?}", RUN_FAILED, report);
+        }
+        report
+    }
+
+    /// Parses the error from a line logged by `RunError::log_failure`. Returns
+    /// `None` if the line doesn't report a failure, or if the category of the
+    /// error is unknown.
+    pub fn from_failure_line(line: &str) -> Option<Self> {
+        let line = &line[line.find(RUN_FAILED)? + RUN_FAILED.len()..];
+        let line = line.strip_prefix(" (")?;
+        let end = line.find("): ")?;
+        let msg = line[end + 3..].to_string();
+        let error = match &line[..end] {
+            "connection" => Self::Connection(msg),
+            "serialization" => Self::Serialization(msg),
+            "channel" => Self::Channel(msg),
+            "protocol_violation" => Self::ProtocolViolation(msg),
+            _ => return None,
+        };
+        Some(error)
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connection(msg)
+            | Self::Serialization(msg)
+            | Self::Channel(msg)
+            | Self::ProtocolViolation(msg) => {
+                write!(f, "{} error: {}", self.category(), msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::WrapErr;
+
+    #[test]
+    fn find() {
+        // errors are found even if wrapped
+        let error = RunError::Channel(String::from("receiver dropped"));
+        let report: Result<(), Report> = Err(error.clone().into());
+        let report = report
+            .wrap_err("forward to worker")
+            .wrap_err("handle message")
+            .unwrap_err();
+        assert_eq!(RunError::find(&report), Some(&error));
+        assert!(error.is_transient());
+
+        // but not all errors are run errors
+        let report = Report::msg("unknown");
+        assert_eq!(RunError::find(&report), None);
+    }
+
+    #[test]
+    fn failure_line() {
+        let error = RunError::ProtocolViolation(String::from("p1 committed"));
+        let line = format!(
+            "2021-01-01T00:00:00 WARN fantoch::error: {} ({}): {}",
+            RUN_FAILED,
+            error.category(),
+            "p1 committed"
+        );
+        assert_eq!(RunError::from_failure_line(&line), Some(error));
+        assert!(!RunError::ProtocolViolation(String::new()).is_transient());
+
+        // failures without a category (or other lines) are ignored
+        let line = format!("{}: unknown", RUN_FAILED);
+        assert_eq!(RunError::from_failure_line(&line), None);
+        assert_eq!(RunError::from_failure_line("process 1 started"), None);
+    }
+}
@@ -1,8 +1,12 @@
-use crate::command::{Command, CommandResult, CommandResultBuilder};
-use crate::executor::ExecutorResult;
+use crate::command::{
+    Command, CommandBatch, CommandResult, CommandResultBuilder,
+};
+use crate::executor::{ExecutorResult, RiflSet};
 use crate::id::{ProcessId, Rifl, ShardId};
+use crate::kvs::{KVError, KVOpResult, Key};
 use crate::trace;
 use crate::HashMap;
+use std::sync::Arc;
 
 /// Structure that tracks the progress of pending commands.
 #[derive(Clone)]
@@ -10,6 +14,54 @@ pub struct AggregatePending {
     process_id: ProcessId,
     shard_id: ShardId,
     pending: HashMap<Rifl, CommandResultBuilder>,
+    // original commands of pending batches for which no partial result has
+    // been received yet; their `CommandResultBuilder` is only created (and
+    // added to `pending`) once the first one is received, which, with large
+    // batches, keeps `pending` small
+    unmaterialized: RiflSet,
+    // mapping from the identifier of each pending batch to its progress
+    batches: HashMap<Rifl, PendingBatch>,
+}
+
+/// What `AggregatePending` needs to know about a command in order to track it.
+/// This allows commands to be tracked by an `AggregatePending` owned by some
+/// other task without sending it the whole command.
+#[derive(Debug, Clone)]
+pub struct PendingCommand {
+    rifl: Rifl,
+    key_count: usize,
+    batch: Option<Arc<CommandBatch>>,
+    ack_only: bool,
+    hash_values: bool,
+}
+
+impl PendingCommand {
+    /// Creates a new `PendingCommand` for a command tracked at shard
+    /// `shard_id`.
+    pub fn new(cmd: &Command, shard_id: ShardId) -> Self {
+        Self {
+            rifl: cmd.rifl(),
+            key_count: cmd.key_count(shard_id),
+            batch: cmd.batch().cloned(),
+            ack_only: cmd.ack_only(),
+            hash_values: cmd.hash_values(),
+        }
+    }
+
+    /// Returns the command identifier.
+    pub fn rifl(&self) -> Rifl {
+        self.rifl
+    }
+}
+
+#[derive(Clone)]
+struct PendingBatch {
+    // layout of the batch
+    batch: Arc<CommandBatch>,
+    // number of original commands still pending
+    pending: usize,
+    ack_only: bool,
+    hash_values: bool,
 }
 
 impl AggregatePending {
@@ -23,14 +75,27 @@ impl AggregatePending {
             process_id,
             shard_id,
             pending: HashMap::new(),
+            unmaterialized: RiflSet::new(),
+            batches: HashMap::new(),
         }
     }
 
-    /// Starts tracking a command submitted by some client.
+    /// Starts tracking a command submitted by some client. If the command is a
+    /// batch, each of the original commands in it is tracked separately, so
+    /// that its result is returned as soon as its own ops are executed.
     pub fn wait_for(&mut self, cmd: &Command) -> bool {
-        // get command rifl and key count
-        let rifl = cmd.rifl();
-        let key_count = cmd.key_count(self.shard_id);
+        self.wait_for_pending(PendingCommand::new(cmd, self.shard_id))
+    }
+
+    /// Same as `wait_for`, but given only what's needed to track the command.
+    pub fn wait_for_pending(&mut self, cmd: PendingCommand) -> bool {
+        let PendingCommand {
+            rifl,
+            key_count,
+            batch,
+            ack_only,
+            hash_values,
+        } = cmd;
         trace!(
             "p{}: AggregatePending::wait_for {:?} | count = {}",
             self.process_id,
@@ -38,22 +103,155 @@ impl AggregatePending {
             key_count
         );
 
-        // create `CommandResult`
-        let cmd_result = CommandResultBuilder::new(rifl, key_count);
-        // add it to pending
-        self.pending.insert(rifl, cmd_result).is_none()
+        if let Some(batch) = batch {
+            if self.batches.contains_key(&rifl) {
+                return false;
+            }
+            // track the original commands that access this shard (their
+            // `CommandResultBuilder` is only created once needed)
+            let mut batch_pending = 0;
+            for batch_rifl in batch.shard_rifls(self.shard_id) {
+                if self.unmaterialized.insert(batch_rifl) {
+                    batch_pending += 1;
+                }
+            }
+            let pending_batch = PendingBatch {
+                batch,
+                pending: batch_pending,
+                ack_only,
+                hash_values,
+            };
+            self.batches.insert(rifl, pending_batch);
+            true
+        } else {
+            // create `CommandResult`
+            let cmd_result =
+                Self::result_builder(rifl, key_count, ack_only, hash_values);
+            // add it to pending
+            self.pending.insert(rifl, cmd_result).is_none()
+        }
+    }
+
+    fn result_builder(
+        rifl: Rifl,
+        key_count: usize,
+        ack_only: bool,
+        hash_values: bool,
+    ) -> CommandResultBuilder {
+        let mut cmd_result = CommandResultBuilder::new(rifl, key_count);
+        if ack_only {
+            cmd_result.set_ack_only();
+        }
+        if hash_values {
+            cmd_result.set_hash_values();
+        }
+        cmd_result
     }
 
-    /// Adds a new partial command result.
+    /// Stops tracking a command (e.g. because it was cancelled by the client
+    /// that submitted it). Returns whether the command was being tracked.
+    pub fn cancel(&mut self, rifl: Rifl) -> bool {
+        trace!("p{}: AggregatePending::cancel {:?}", self.process_id, rifl);
+        if let Some(pending_batch) = self.batches.remove(&rifl) {
+            // stop tracking all the original commands in the batch
+            for batch_rifl in pending_batch.batch.rifls() {
+                if !self.unmaterialized.remove(*batch_rifl) {
+                    self.pending.remove(batch_rifl);
+                }
+            }
+            true
+        } else {
+            self.pending.remove(&rifl).is_some()
+        }
+    }
+
+    /// Adds a new partial command result. Returns the results of the commands
+    /// that became ready (there can be more than one if the partial result is
+    /// from a batch).
     pub fn add_executor_result(
         &mut self,
         executor_result: ExecutorResult,
-    ) -> Option<CommandResult> {
+    ) -> Vec<CommandResult> {
         let ExecutorResult {
             rifl,
             key,
             partial_results,
+            shed,
+            error,
+            executed_clock,
         } = executor_result;
+
+        let batch = self.batches.get(&rifl).map(|pending_batch| {
+            (
+                pending_batch.batch.clone(),
+                pending_batch.ack_only,
+                pending_batch.hash_values,
+            )
+        });
+        match batch {
+            Some((batch, ack_only, hash_values)) => {
+                // split the results of the batch into the results of the
+                // original commands
+                let ready: Vec<_> = batch
+                    .split(self.shard_id, &key, partial_results)
+                    .into_iter()
+                    .filter_map(|(batch_rifl, partial_results)| {
+                        // create the `CommandResultBuilder` of this command if
+                        // this is its first partial result
+                        if self.unmaterialized.remove(batch_rifl) {
+                            let key_count =
+                                batch.key_count(batch_rifl, self.shard_id);
+                            let cmd_result = Self::result_builder(
+                                batch_rifl,
+                                key_count,
+                                ack_only,
+                                hash_values,
+                            );
+                            self.pending.insert(batch_rifl, cmd_result);
+                        }
+                        self.add_partial(
+                            batch_rifl,
+                            key.clone(),
+                            partial_results,
+                            shed,
+                            error,
+                            executed_clock,
+                        )
+                    })
+                    .collect();
+
+                // stop tracking the batch once all its commands are ready
+                let pending_batch =
+                    self.batches.get_mut(&rifl).expect("batch should exist");
+                pending_batch.pending -= ready.len();
+                if pending_batch.pending == 0 {
+                    self.batches.remove(&rifl);
+                }
+                ready
+            }
+            None => self
+                .add_partial(
+                    rifl,
+                    key,
+                    partial_results,
+                    shed,
+                    error,
+                    executed_clock,
+                )
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn add_partial(
+        &mut self,
+        rifl: Rifl,
+        key: Key,
+        partial_results: Vec<KVOpResult>,
+        shed: bool,
+        error: Option<KVError>,
+        executed_clock: Option<u64>,
+    ) -> Option<CommandResult> {
         // get current value:
         // - if it's not part of pending, then ignore it
         // (if it's not part of pending, it means that it is from a client from
@@ -62,7 +260,16 @@ impl AggregatePending {
         let cmd_result_builder = self.pending.get_mut(&rifl)?;
 
         // add partial result and check if it's ready
-        cmd_result_builder.add_partial(key, partial_results);
+        match error {
+            Some(error) => cmd_result_builder.add_error(key, error),
+            None => cmd_result_builder.add_partial(key, partial_results),
+        }
+        if shed {
+            cmd_result_builder.set_shed();
+        }
+        if let Some(executed_clock) = executed_clock {
+            cmd_result_builder.set_executed_clock(executed_clock);
+        }
         if cmd_result_builder.ready() {
             trace!(
                 "p{}: AggregatePending::add_partial {:?} is ready",
@@ -143,7 +350,7 @@ mod tests {
             key_b.clone(),
             vec![get_b_res],
         ));
-        assert!(res.is_none());
+        assert!(res.is_empty());
 
         // add the result of put a before being waited for
         let put_a_res = store.test_execute(&key_a, KVOp::Put(foo.clone()));
@@ -152,22 +359,22 @@ mod tests {
             key_a.clone(),
             vec![put_a_res.clone()],
         ));
-        assert!(res.is_none());
+        assert!(res.is_empty());
 
         // wait for `put_a`
         pending.wait_for(&put_a);
 
         // add the result of put a and assert that the command is ready
-        let res = pending.add_executor_result(ExecutorResult::new(
+        let mut res = pending.add_executor_result(ExecutorResult::new(
             put_a_rifl,
             key_a.clone(),
             vec![put_a_res.clone()],
         ));
-        assert!(res.is_some());
+        assert_eq!(res.len(), 1);
 
         // check that there's only one result (since the command accessed a
         // single key)
-        let res = res.unwrap();
+        let res = res.pop().unwrap();
         assert_eq!(res.results().len(), 1);
 
         // check that there was nothing in the kvs before
@@ -175,7 +382,7 @@ mod tests {
 
         // add the result of put b and assert that the command is ready
         let put_b_res = store.test_execute(&key_b, KVOp::Put(bar.clone()));
-        let res = pending.add_executor_result(ExecutorResult::new(
+        let mut res = pending.add_executor_result(ExecutorResult::new(
             put_b_rifl,
             key_b.clone(),
             vec![put_b_res],
@@ -183,7 +390,7 @@ mod tests {
 
         // check that there's only one result (since the command accessed a
         // single key)
-        let res = res.unwrap();
+        let res = res.pop().unwrap();
         assert_eq!(res.results().len(), 1);
 
         // check that there was nothing in the kvs before
@@ -191,20 +398,192 @@ mod tests {
 
         // add the result of get a and assert that the command is ready
         let get_a_res = store.test_execute(&key_a, KVOp::Get);
-        let res = pending.add_executor_result(ExecutorResult::new(
+        let mut res = pending.add_executor_result(ExecutorResult::new(
             get_ab_rifl,
             key_a.clone(),
             vec![get_a_res],
         ));
-        assert!(res.is_some());
+        assert_eq!(res.len(), 1);
 
         // check that there are two results (since the command accessed two
         // keys)
-        let res = res.unwrap();
+        let res = res.pop().unwrap();
         assert_eq!(res.results().len(), 2);
 
         // check that `get_ab` saw `put_a` but not `put_b`
         assert_eq!(res.results().get(&key_a).unwrap(), &vec![Some(foo)]);
         assert_eq!(res.results().get(&key_b).unwrap(), &vec![None]);
     }
+
+    #[test]
+    fn pending_batch_flow() {
+        // create pending and store
+        let process_id = 1;
+        let shard_id = 0;
+        let mut pending = AggregatePending::new(process_id, shard_id);
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+
+        // keys and values
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let foo = String::from("foo");
+
+        // batch with put a, get a and get b
+        let put_a_rifl = Rifl::new(1, 1);
+        let get_a_rifl = Rifl::new(2, 1);
+        let get_b_rifl = Rifl::new(3, 1);
+        let mut batch = Command::from(
+            put_a_rifl,
+            vec![(key_a.clone(), KVOp::Put(foo.clone()))],
+        );
+        batch
+            .merge(Command::from(get_a_rifl, vec![(key_a.clone(), KVOp::Get)]));
+        batch
+            .merge(Command::from(get_b_rifl, vec![(key_b.clone(), KVOp::Get)]));
+        assert_eq!(batch.rifl(), put_a_rifl);
+        assert_eq!(
+            batch.batch_rifls(),
+            vec![put_a_rifl, get_a_rifl, get_b_rifl]
+        );
+        assert!(pending.wait_for(&batch));
+
+        // no `CommandResultBuilder` is created until the first partial result
+        // of each command is received
+        assert!(pending.pending.is_empty());
+        assert_eq!(pending.unmaterialized.len(), 3);
+
+        // execute the batch one key at a time
+        let mut results: HashMap<_, _> = batch
+            .execute(shard_id, &mut store)
+            .map(|result| (result.key.clone(), result))
+            .collect();
+
+        // the ops on key b complete get b
+        let res = pending.add_executor_result(results.remove(&key_b).unwrap());
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].rifl(), get_b_rifl);
+        assert_eq!(res[0].results().get(&key_b).unwrap(), &vec![None]);
+
+        // the ops on key a complete both put a and get a
+        let res = pending.add_executor_result(results.remove(&key_a).unwrap());
+        let res: HashMap<_, _> = res
+            .into_iter()
+            .map(|cmd_result| (cmd_result.rifl(), cmd_result))
+            .collect();
+        assert_eq!(res.len(), 2);
+        assert_eq!(
+            res.get(&put_a_rifl).unwrap().results().get(&key_a).unwrap(),
+            &vec![None]
+        );
+        assert_eq!(
+            res.get(&get_a_rifl).unwrap().results().get(&key_a).unwrap(),
+            &vec![Some(foo)]
+        );
+
+        // the batch is no longer being tracked
+        assert!(pending.batches.is_empty());
+        assert!(pending.pending.is_empty());
+        assert!(pending.unmaterialized.is_empty());
+    }
+
+    #[test]
+    fn pending_ack_only() {
+        // create pending and store
+        let process_id = 1;
+        let shard_id = 0;
+        let mut pending = AggregatePending::new(process_id, shard_id);
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+
+        // keys and values
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let foo = String::from("foo");
+        store.test_execute(&key_a, KVOp::Put(foo.clone()));
+
+        // ack-only command that deletes a and gets b
+        let rifl = Rifl::new(1, 1);
+        let mut cmd = Command::from(
+            rifl,
+            vec![(key_a.clone(), KVOp::Delete), (key_b.clone(), KVOp::Get)],
+        );
+        cmd.set_ack_only(true);
+        assert!(pending.wait_for(&cmd));
+
+        // the command is ready once both keys are executed
+        let results: Vec<_> = cmd.execute(shard_id, &mut store).collect();
+        let res: Vec<_> = results
+            .into_iter()
+            .flat_map(|result| pending.add_executor_result(result))
+            .collect();
+        assert_eq!(res.len(), 1);
+
+        // there's a result on each key, but no values
+        let res = &res[0];
+        assert_eq!(res.results().len(), 2);
+        assert_eq!(res.results().get(&key_a).unwrap(), &vec![]);
+        assert_eq!(res.results().get(&key_b).unwrap(), &vec![]);
+
+        // the delete was executed anyway
+        assert_eq!(store.test_execute(&key_a, KVOp::Get), None);
+
+        // merging an ack-only command with one that isn't ack-only results in
+        // a command that isn't ack-only
+        let mut batch = Command::from(Rifl::new(2, 1), vec![]);
+        batch.set_ack_only(true);
+        let mut other = Command::from(Rifl::new(3, 1), vec![]);
+        other.set_ack_only(true);
+        batch.merge(other);
+        assert!(batch.ack_only());
+        batch.merge(Command::from(Rifl::new(4, 1), vec![]));
+        assert!(!batch.ack_only());
+    }
+
+    #[test]
+    fn pending_hash_values() {
+        // create pending and store
+        let process_id = 1;
+        let shard_id = 0;
+        let mut pending = AggregatePending::new(process_id, shard_id);
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+
+        // keys and values
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let foo = String::from("foo");
+        store.test_execute(&key_a, KVOp::Put(foo.clone()));
+
+        // command that gets a and b, and only needs the hashes of the values
+        let rifl = Rifl::new(1, 1);
+        let mut cmd = Command::from(
+            rifl,
+            vec![(key_a.clone(), KVOp::Get), (key_b.clone(), KVOp::Get)],
+        );
+        cmd.set_hash_values(true);
+        assert!(pending.wait_for(&cmd));
+
+        let results: Vec<_> = cmd.execute(shard_id, &mut store).collect();
+        let res: Vec<_> = results
+            .into_iter()
+            .flat_map(|result| pending.add_executor_result(result))
+            .collect();
+        assert_eq!(res.len(), 1);
+
+        // the value read is replaced by its hash, and missing values remain
+        // missing
+        let res = &res[0];
+        let hash = crate::util::value_hash(&foo);
+        assert_ne!(hash, foo);
+        assert_eq!(res.results().get(&key_a).unwrap(), &vec![Some(hash)]);
+        assert_eq!(res.results().get(&key_b).unwrap(), &vec![None]);
+
+        // merging a command that only needs hashes with one that needs values
+        // results in a command that needs values
+        let mut batch = Command::from(Rifl::new(2, 1), vec![]);
+        batch.set_hash_values(true);
+        batch.merge(Command::from(Rifl::new(3, 1), vec![]));
+        assert!(!batch.hash_values());
+    }
 }
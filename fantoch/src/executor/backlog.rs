@@ -0,0 +1,154 @@
+use crate::config::Config;
+use crate::id::ProcessId;
+use crate::time::SysTime;
+use std::fmt;
+use std::time::Duration;
+
+/// Alarm raised by a `BacklogWatchdog` once the backlog of an executor (i.e.
+/// the number of commands committed but not yet executed) stayed above the
+/// configured size for the configured period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BacklogAlarm {
+    pub process_id: ProcessId,
+    pub executor_index: usize,
+    pub backlog: usize,
+    pub threshold: usize,
+    // for how long the backlog has been above the threshold
+    pub duration: Duration,
+}
+
+impl fmt::Display for BacklogAlarm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p{} @ executor {}: backlog of {} commands (above {}) for {:?}",
+            self.process_id,
+            self.executor_index,
+            self.backlog,
+            self.threshold,
+            self.duration
+        )
+    }
+}
+
+/// Watchdog that raises a `BacklogAlarm` when the backlog of an executor stays
+/// above `Config::executor_backlog_alarm` for a sustained period, which
+/// usually means that the executor is livelocked. Once raised, the alarm is
+/// only raised again after the backlog drops below the threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BacklogWatchdog {
+    process_id: ProcessId,
+    executor_index: usize,
+    // backlog size and period (in milliseconds), if alarms are enabled
+    alarm: Option<(usize, u64)>,
+    // time (in milliseconds) since which the backlog is above the threshold
+    above_since: Option<u64>,
+    raised: bool,
+    alarms: u64,
+}
+
+impl BacklogWatchdog {
+    /// Creates a new `BacklogWatchdog`. Alarms are only raised if
+    /// `executor_backlog_alarm` is set.
+    pub fn new(
+        process_id: ProcessId,
+        executor_index: usize,
+        config: &Config,
+    ) -> Self {
+        Self {
+            process_id,
+            executor_index,
+            alarm: config
+                .executor_backlog_alarm()
+                .map(|(size, period)| (size, period.as_millis() as u64)),
+            above_since: None,
+            raised: false,
+            alarms: 0,
+        }
+    }
+
+    /// Checks the current `backlog` of the executor, returning an alarm if
+    /// it's been above the threshold for the configured period.
+    #[must_use]
+    pub fn check(
+        &mut self,
+        backlog: usize,
+        time: &dyn SysTime,
+    ) -> Option<BacklogAlarm> {
+        let (threshold, period) = self.alarm?;
+        if backlog <= threshold {
+            // re-arm the alarm
+            self.above_since = None;
+            self.raised = false;
+            return None;
+        }
+
+        let now = time.millis();
+        let above_since = *self.above_since.get_or_insert(now);
+        let elapsed = now.saturating_sub(above_since);
+        if elapsed >= period && !self.raised {
+            self.raised = true;
+            self.alarms += 1;
+            Some(BacklogAlarm {
+                process_id: self.process_id,
+                executor_index: self.executor_index,
+                backlog,
+                threshold,
+                duration: Duration::from_millis(elapsed),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of alarms raised so far.
+    pub fn alarms(&self) -> u64 {
+        self.alarms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimTime;
+
+    #[test]
+    fn backlog_watchdog_flow() {
+        let mut config = Config::new(3, 1);
+        config.set_executor_backlog_alarm((10, Duration::from_millis(300)));
+        let mut watchdog = BacklogWatchdog::new(1, 0, &config);
+        let mut time = SimTime::new();
+
+        // a backlog at the threshold is fine
+        assert_eq!(watchdog.check(10, &time), None);
+
+        // a backlog above it is only an alarm if sustained
+        assert_eq!(watchdog.check(11, &time), None);
+        time.add_millis(200);
+        assert_eq!(watchdog.check(20, &time), None);
+        time.add_millis(100);
+        let alarm = watchdog.check(30, &time).expect("alarm should be raised");
+        assert_eq!(alarm.backlog, 30);
+        assert_eq!(alarm.threshold, 10);
+        assert_eq!(alarm.duration, Duration::from_millis(300));
+
+        // the alarm is raised only once
+        time.add_millis(1000);
+        assert_eq!(watchdog.check(30, &time), None);
+        assert_eq!(watchdog.alarms(), 1);
+
+        // a drop in the backlog re-arms the alarm and restarts the period
+        assert_eq!(watchdog.check(5, &time), None);
+        assert_eq!(watchdog.check(30, &time), None);
+        time.add_millis(300);
+        assert!(watchdog.check(30, &time).is_some());
+        assert_eq!(watchdog.alarms(), 2);
+
+        // if alarms are not enabled, they're never raised
+        let config = Config::new(3, 1);
+        let mut watchdog = BacklogWatchdog::new(1, 0, &config);
+        assert_eq!(watchdog.check(1000, &time), None);
+        time.add_millis(1000);
+        assert_eq!(watchdog.check(1000, &time), None);
+    }
+}
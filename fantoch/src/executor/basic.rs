@@ -1,13 +1,13 @@
+use crate::command::KeyOps;
 use crate::config::Config;
 use crate::executor::{
-    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
-    MessageKey,
+    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorMetricsKind,
+    ExecutorResult, MessageKey,
 };
 use crate::id::{ProcessId, Rifl, ShardId};
-use crate::kvs::{KVOp, KVStore, Key};
+use crate::kvs::{KVStore, Key, KeyRange};
 use crate::time::SysTime;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct BasicExecutor {
@@ -19,13 +19,8 @@ pub struct BasicExecutor {
 impl Executor for BasicExecutor {
     type ExecutionInfo = BasicExecutionInfo;
 
-    fn new(
-        _process_id: ProcessId,
-        _shard_id: ShardId,
-        _config: Config,
-    ) -> Self {
-        let monitor = false;
-        let store = KVStore::new(monitor);
+    fn new(_process_id: ProcessId, shard_id: ShardId, config: Config) -> Self {
+        let store = KVStore::from_config(&config, shard_id);
         let metrics = ExecutorMetrics::new();
         let to_clients = Vec::new();
 
@@ -36,16 +31,36 @@ impl Executor for BasicExecutor {
         }
     }
 
+    fn set_key_range(&mut self, key_range: KeyRange) {
+        self.store.set_key_range(key_range);
+    }
+
     fn handle(&mut self, info: Self::ExecutionInfo, _time: &dyn SysTime) {
-        let BasicExecutionInfo { rifl, key, ops } = info;
-        // take the ops inside the arc if we're the last with a
-        // reference to it (otherwise, clone them)
-        let ops =
-            Arc::try_unwrap(ops).unwrap_or_else(|ops| ops.as_ref().clone());
+        let BasicExecutionInfo {
+            rifl,
+            key,
+            ops,
+            timestamp,
+        } = info;
+        // take the ops (decoding them, if needed)
+        let ops = ops.into_ops();
         // execute op in the `KVStore`
-        let partial_results = self.store.execute(&key, ops, rifl);
+        let execution = self.store.execute(&key, ops, rifl, timestamp);
         self.to_clients
-            .push(ExecutorResult::new(rifl, key, partial_results));
+            .push(ExecutorResult::from_execution(rifl, key, execution));
+
+        // record the writes elided
+        let elided = self.store.flush_writes();
+        if elided > 0 {
+            self.metrics
+                .aggregate(ExecutorMetricsKind::ElidedWrites, elided);
+        }
+        // record the values expired
+        let expired = self.store.take_expired();
+        if expired > 0 {
+            self.metrics
+                .aggregate(ExecutorMetricsKind::ExpiredValues, expired);
+        }
     }
 
     fn to_clients(&mut self) -> Option<ExecutorResult> {
@@ -63,18 +78,28 @@ impl Executor for BasicExecutor {
     fn monitor(&self) -> Option<ExecutionOrderMonitor> {
         None
     }
+
+    fn store(&self) -> &KVStore {
+        &self.store
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BasicExecutionInfo {
     rifl: Rifl,
     key: Key,
-    ops: Arc<Vec<KVOp>>,
+    ops: KeyOps,
+    timestamp: u64,
 }
 
 impl BasicExecutionInfo {
-    pub fn new(rifl: Rifl, key: Key, ops: Arc<Vec<KVOp>>) -> Self {
-        Self { rifl, key, ops }
+    pub fn new(rifl: Rifl, key: Key, ops: KeyOps, timestamp: u64) -> Self {
+        Self {
+            rifl,
+            key,
+            ops,
+            timestamp,
+        }
     }
 }
 
@@ -0,0 +1,261 @@
+use crate::config::Config;
+use crate::executor::{Executor, ExecutorResult};
+use crate::id::{ProcessId, Rifl, ShardId};
+use crate::kvs::{Key, KeyRange};
+use crate::time::SysTime;
+use crate::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Message mirrored by the executors of a primary process to its canary (see
+/// `Canary`). Each message carries the index of the executor that sent it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CanaryMessage<I> {
+    // execution info handled by the executor
+    Info(usize, I),
+    // result produced by the executor
+    Result(usize, ExecutorResult),
+}
+
+/// Result of a command on some key that differs between the primary process
+/// and its canary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub primary: ExecutorResult,
+    pub canary: ExecutorResult,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CanaryStats {
+    /// number of results compared
+    pub compared: u64,
+    /// number of results compared that differ
+    pub diverged: u64,
+    /// number of results produced so far only by the primary or the canary
+    pub pending: usize,
+}
+
+/// Executors of a canary process, i.e. a process that doesn't participate in
+/// quorums but receives a mirrored copy of the execution infos handled by the
+/// executors of a primary process, along with the results they produced. The
+/// canary may run a newer build than the primary: each execution info is
+/// handled by the canary's own executors, whose results are compared with the
+/// ones of the primary, and the results that differ are reported as
+/// `Divergence`s.
+///
+/// Results of commands shed by either (see `ExecutorResult::shed`) are not
+/// compared, since shedding depends on timing.
+pub struct Canary<E> {
+    // one executor per executor of the primary
+    executors: Vec<E>,
+    // mapping from a result (of a command on some key) produced by only one
+    // of the processes to whether it was produced by the primary
+    pending: HashMap<(Rifl, Key), (bool, ExecutorResult)>,
+    divergences: VecDeque<Divergence>,
+    compared: u64,
+    diverged: u64,
+}
+
+impl<E> Canary<E>
+where
+    E: Executor,
+{
+    /// Creates the canary of a primary process with `executors` executors.
+    pub fn new(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: Config,
+        executors: usize,
+    ) -> Self {
+        let executors = (0..executors)
+            .map(|index| {
+                let mut executor = E::new(process_id, shard_id, config);
+                executor.set_executor_index(index);
+                executor.set_key_range(KeyRange::worker(index, executors));
+                executor
+            })
+            .collect();
+        Self {
+            executors,
+            pending: HashMap::new(),
+            divergences: VecDeque::new(),
+            compared: 0,
+            diverged: 0,
+        }
+    }
+
+    pub fn handle(
+        &mut self,
+        msg: CanaryMessage<E::ExecutionInfo>,
+        time: &dyn SysTime,
+    ) {
+        match msg {
+            CanaryMessage::Info(index, info) => {
+                self.executor(index).handle(info, time);
+                self.fetch_results(index, time);
+            }
+            CanaryMessage::Result(_, result) => self.compare(true, result),
+        }
+    }
+
+    /// Runs the periodic cleanup of the canary's executors.
+    pub fn cleanup(&mut self, time: &dyn SysTime) {
+        for index in 0..self.executors.len() {
+            self.executors[index].cleanup(time);
+            self.fetch_results(index, time);
+        }
+    }
+
+    #[must_use]
+    pub fn to_divergences(&mut self) -> Option<Divergence> {
+        self.divergences.pop_front()
+    }
+
+    pub fn stats(&self) -> CanaryStats {
+        CanaryStats {
+            compared: self.compared,
+            diverged: self.diverged,
+            pending: self.pending.len(),
+        }
+    }
+
+    fn executor(&mut self, index: usize) -> &mut E {
+        let executors = self.executors.len();
+        self.executors.get_mut(index).unwrap_or_else(|| {
+            panic!(
+                "canary has {} executors but got a message from executor {}",
+                executors, index
+            )
+        })
+    }
+
+    fn fetch_results(&mut self, index: usize, time: &dyn SysTime) {
+        let executor = &mut self.executors[index];
+        // messages to other executors (and executed notifications) are
+        // dropped: the infos the primary's executors got from other executors
+        // are mirrored as well
+        executor.to_executors_iter().for_each(drop);
+        executor.to_process_executors_iter().for_each(drop);
+        let _ = executor.executed(time);
+        let results: Vec<_> = executor.to_clients_iter().collect();
+        for result in results {
+            self.compare(false, result);
+        }
+    }
+
+    fn compare(&mut self, primary: bool, result: ExecutorResult) {
+        let id = (result.rifl, result.key.clone());
+        let (other_primary, other) = match self.pending.remove(&id) {
+            Some(other) => other,
+            None => {
+                self.pending.insert(id, (primary, result));
+                return;
+            }
+        };
+        assert_ne!(
+            primary, other_primary,
+            "{:?} should be executed only once on key {} by each process",
+            id.0, id.1
+        );
+        let (primary, canary) = if primary {
+            (result, other)
+        } else {
+            (other, result)
+        };
+        if primary.shed || canary.shed {
+            return;
+        }
+        self.compared += 1;
+        if primary.partial_results != canary.partial_results
+            || primary.error != canary.error
+        {
+            self.diverged += 1;
+            self.divergences.push_back(Divergence { primary, canary });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::KeyOps;
+    use crate::executor::{BasicExecutionInfo, BasicExecutor};
+    use crate::kvs::KVOp;
+    use crate::time::SimTime;
+
+    #[test]
+    fn canary() {
+        let config = Config::new(3, 1);
+        let time = SimTime::new();
+        let executors = 1;
+        let mut canary = Canary::<BasicExecutor>::new(1, 0, config, executors);
+
+        let key = String::from("A");
+        let info = |rifl, op| {
+            let ops = KeyOps::new(vec![op]);
+            BasicExecutionInfo::new(rifl, key.clone(), ops, 0)
+        };
+        let result = |rifl, partial_results| {
+            ExecutorResult::new(rifl, key.clone(), partial_results)
+        };
+        let put_rifl = Rifl::new(1, 1);
+        let get_rifl = Rifl::new(1, 2);
+        let value = String::from("v");
+
+        // the primary's result may be mirrored before the execution info
+        canary.handle(
+            CanaryMessage::Result(0, result(put_rifl, vec![None])),
+            &time,
+        );
+        assert_eq!(canary.stats().pending, 1);
+        canary.handle(
+            CanaryMessage::Info(0, info(put_rifl, KVOp::Put(value.clone()))),
+            &time,
+        );
+        assert_eq!(
+            canary.stats(),
+            CanaryStats {
+                compared: 1,
+                diverged: 0,
+                pending: 0
+            }
+        );
+
+        // the canary reads the value put, while the primary (say, due to a
+        // bug) reads nothing
+        canary.handle(CanaryMessage::Info(0, info(get_rifl, KVOp::Get)), &time);
+        canary.handle(
+            CanaryMessage::Result(0, result(get_rifl, vec![None])),
+            &time,
+        );
+        assert_eq!(canary.stats().diverged, 1);
+        assert_eq!(
+            canary.to_divergences(),
+            Some(Divergence {
+                primary: result(get_rifl, vec![None]),
+                canary: result(get_rifl, vec![Some(value)]),
+            })
+        );
+        assert_eq!(canary.to_divergences(), None);
+
+        // shed results are not compared
+        let shed_rifl = Rifl::new(1, 3);
+        canary
+            .handle(CanaryMessage::Info(0, info(shed_rifl, KVOp::Get)), &time);
+        canary.handle(
+            CanaryMessage::Result(
+                0,
+                ExecutorResult::shed(shed_rifl, key.clone()),
+            ),
+            &time,
+        );
+        assert_eq!(
+            canary.stats(),
+            CanaryStats {
+                compared: 2,
+                diverged: 1,
+                pending: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+// by default, adaptive cleanups happen at most every 1ms and at least every
+// 10ms
+const DEFAULT_MIN_INTERVAL: Duration = Duration::from_millis(1);
+const DEFAULT_MAX_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Interval between executor cleanups that adapts to the executor's load:
+/// - the interval is reset to its minimum when new missing dependencies are
+///   observed
+/// - the interval is halved while there are pending dots
+/// - the interval is doubled when the executor is idle
+#[derive(Debug, Clone)]
+pub struct AdaptiveCleanupInterval {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl AdaptiveCleanupInterval {
+    /// Creates a new `AdaptiveCleanupInterval` bounded by `min` and `max`.
+    /// It starts at `max`.
+    pub fn new(min: Duration, max: Duration) -> Self {
+        assert!(min > Duration::from_millis(0));
+        assert!(min <= max);
+        Self {
+            min,
+            max,
+            current: max,
+        }
+    }
+
+    /// Returns the current interval.
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Computes the next interval given the number of pending dots and the
+    /// number of missing dependencies observed since the previous cleanup.
+    pub fn next(&mut self, pending: usize, missing_deps: usize) -> Duration {
+        self.current = if missing_deps > 0 {
+            self.min
+        } else if pending > 0 {
+            std::cmp::max(self.current / 2, self.min)
+        } else {
+            std::cmp::min(self.current * 2, self.max)
+        };
+        self.current
+    }
+}
+
+impl Default for AdaptiveCleanupInterval {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_INTERVAL, DEFAULT_MAX_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adaptive_cleanup_interval() {
+        let ms = Duration::from_millis;
+        let mut interval = AdaptiveCleanupInterval::new(ms(1), ms(8));
+
+        // starts at the max
+        assert_eq!(interval.current(), ms(8));
+
+        // pending dots halve the interval until the min
+        assert_eq!(interval.next(10, 0), ms(4));
+        assert_eq!(interval.next(10, 0), ms(2));
+        assert_eq!(interval.next(10, 0), ms(1));
+        assert_eq!(interval.next(10, 0), ms(1));
+
+        // being idle doubles the interval until the max
+        assert_eq!(interval.next(0, 0), ms(2));
+        assert_eq!(interval.next(0, 0), ms(4));
+        assert_eq!(interval.next(0, 0), ms(8));
+        assert_eq!(interval.next(0, 0), ms(8));
+
+        // missing dependencies reset the interval to the min
+        assert_eq!(interval.next(0, 1), ms(1));
+    }
+}
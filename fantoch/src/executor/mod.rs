@@ -1,28 +1,61 @@
 // This module contains the definition of `Pending`.
 mod aggregate;
 
+// This module contains the definition of `BacklogWatchdog`.
+mod backlog;
+
 // This module contains the implementation of a basic executor that executes
 // operations as soon as it receives them.
 mod basic;
 
+// This module contains the definition of `Canary`.
+mod canary;
+
+// This module contains the definition of `AdaptiveCleanupInterval`.
+mod cleanup;
+
 // This module contains the definition of `ExecutionOrderMonitor`.
 mod monitor;
 
+// This module contains the definition of `OrderedDelivery`.
+mod ordered;
+
+// This module contains the definition of `RiflSet`.
+mod rifls;
+
+// This module contains the definition of `ShadowExecutor`.
+mod shadow;
+
+// This module contains the definition of `GraphSnapshot`.
+mod snapshot;
+
+// This module contains the definition of `ExecutionThrottle`.
+mod throttle;
+
 // Re-exports.
-pub use aggregate::AggregatePending;
+pub use aggregate::{AggregatePending, PendingCommand};
+pub use backlog::{BacklogAlarm, BacklogWatchdog};
 pub use basic::{BasicExecutionInfo, BasicExecutor};
-pub use monitor::ExecutionOrderMonitor;
+pub use canary::{Canary, CanaryMessage, CanaryStats, Divergence};
+pub use cleanup::AdaptiveCleanupInterval;
+pub use monitor::{ExecutionOrderMonitor, OrderDivergence};
+pub use ordered::OrderedDelivery;
+pub use rifls::RiflSet;
+pub use shadow::{ShadowExecutionInfo, ShadowExecutor};
+pub use snapshot::GraphSnapshot;
+pub use throttle::ExecutionThrottle;
 
 use crate::config::Config;
 use crate::id::{ProcessId, Rifl, ShardId};
-use crate::kvs::{KVOpResult, Key};
+use crate::kvs::{KVError, KVOp, KVOpResult, KVStore, Key, KeyRange};
 use crate::metrics::Metrics;
-use crate::protocol::{CommittedAndExecuted, MessageIndex};
+use crate::protocol::{CommittedAndExecuted, MessageIndex, Violation};
 use crate::time::SysTime;
 use crate::util;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug};
+use std::time::Duration;
 
 pub trait Executor: Clone {
     // TODO why is Send needed?
@@ -40,15 +73,48 @@ pub trait Executor: Clone {
         // executors interested in the index should overwrite this
     }
 
+    fn set_key_range(&mut self, _key_range: KeyRange) {
+        // executors whose workers partition the keys by their hash (see
+        // `MessageKey`) should overwrite this, so that the store of each
+        // worker only holds the keys it owns
+    }
+
     fn cleanup(&mut self, _time: &dyn SysTime) {
         // executors interested in a periodic cleanup should overwrite this
     }
 
+    fn cleanup_interval(&mut self) -> Option<Duration> {
+        // executors that adapt the interval between cleanups should overwrite
+        // this; otherwise, the configured interval is used
+        None
+    }
+
     fn monitor_pending(&mut self, _time: &dyn SysTime) {
         // executors interested in a periodic check of pending commands should
         // overwrite this
     }
 
+    fn sample_memory(&mut self) {
+        // executors that track the (approximate) memory used by their data
+        // structures should overwrite this
+    }
+
+    /// Returns the number of commands committed but not yet executed, if the
+    /// executor tracks it.
+    fn backlog(&self) -> Option<usize> {
+        // executors that buffer committed commands until they can be executed
+        // should overwrite this
+        None
+    }
+
+    /// Returns a snapshot of the dependency graph of the executor, if it has
+    /// one.
+    fn graph_snapshot(&self) -> Option<GraphSnapshot> {
+        // executors that order commands using a dependency graph should
+        // overwrite this
+        None
+    }
+
     fn handle(&mut self, infos: Self::ExecutionInfo, time: &dyn SysTime);
 
     #[must_use]
@@ -70,6 +136,22 @@ pub trait Executor: Clone {
         ToExecutorsIter { executor: self }
     }
 
+    #[must_use]
+    fn to_process_executors(
+        &mut self,
+    ) -> Option<(ProcessId, Self::ExecutionInfo)> {
+        // executors that need to target the executors of a specific process
+        // (instead of the closest process of a shard) should overwrite this
+        None
+    }
+
+    #[must_use]
+    fn to_process_executors_iter(
+        &mut self,
+    ) -> ToProcessExecutorsIter<'_, Self> {
+        ToProcessExecutorsIter { executor: self }
+    }
+
     #[must_use]
     fn executed(
         &mut self,
@@ -81,11 +163,39 @@ pub trait Executor: Clone {
         None
     }
 
+    #[must_use]
+    fn to_violations(&mut self) -> Option<Violation> {
+        // executors that record violations of their invariants (instead of
+        // panicking) should overwrite this
+        None
+    }
+
     fn parallel() -> bool;
 
+    fn supports_latency_budget() -> bool {
+        // executors that shed commands whose commit-to-execution delay exceeds
+        // the executor latency budget (see `Config::executor_latency_budget`)
+        // should overwrite this
+        false
+    }
+
     fn metrics(&self) -> &ExecutorMetrics;
 
     fn monitor(&self) -> Option<ExecutionOrderMonitor>;
+
+    /// Returns the store in which commands are executed. Read-only commands
+    /// are served directly from it if stale reads are enabled (see
+    /// `Config::stale_reads`).
+    fn store(&self) -> &KVStore;
+
+    /// Returns the index of the executor whose store serves the stale reads
+    /// on `key`.
+    #[allow(clippy::ptr_arg)]
+    fn stale_read_index(key: &Key) -> Option<(usize, usize)> {
+        // executors that don't partition keys by their hash (see
+        // `MessageKey`) should overwrite this
+        Some(key_index(key))
+    }
 }
 
 pub struct ToClientsIter<'a, E> {
@@ -118,15 +228,65 @@ where
     }
 }
 
+pub struct ToProcessExecutorsIter<'a, E> {
+    executor: &'a mut E,
+}
+
+impl<'a, E> Iterator for ToProcessExecutorsIter<'a, E>
+where
+    E: Executor,
+{
+    type Item = (ProcessId, E::ExecutionInfo);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.executor.to_process_executors()
+    }
+}
+
 pub type ExecutorMetrics = Metrics<ExecutorMetricsKind>;
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExecutorMetricsKind {
     ExecutionDelay,
+    // number of writes not applied to the store since they were overwritten
+    // by a later write on the same key (only if write batching is enabled)
+    ElidedWrites,
     ChainSize,
     OutRequests,
     InRequests,
     InRequestReplies,
+    CleanupInterval,
+    RequestRtt,
+    RequestRetries,
+    // number of times SCC formation was stalled due to missing dependencies,
+    // and how many of those stalls had at least one dependency from another
+    // shard (partial replication)
+    DependencyStalls,
+    RemoteDependencyStalls,
+    // number of times SCC formation was interrupted since it visited more
+    // vertices than the budget allows (see `Config::executor_scc_budget`)
+    SccBudgetExhaustions,
+    StabilityDelay,
+    ShedCommands,
+    ShadowLag,
+    ShadowLead,
+    ShadowOrderMismatches,
+    // approximate memory (in bytes) used by the major executor data
+    // structures, sampled periodically
+    VertexIndexMemory,
+    PendingIndexMemory,
+    VotesTableMemory,
+    PendingMemory,
+    // number of violations of the executor's invariants (see `Violation`)
+    Violations,
+    // number of alarms raised due to a sustained backlog (see
+    // `BacklogWatchdog`)
+    BacklogAlarms,
+    // number of values expired in the store (see `KVOp::PutTtl`)
+    ExpiredValues,
+    // total time (in milliseconds) the executor waited due to its throttle
+    // (see `ExecutionThrottle`)
+    ThrottleDelay,
 }
 
 impl Debug for ExecutorMetricsKind {
@@ -134,6 +294,7 @@ impl Debug for ExecutorMetricsKind {
         match self {
             // general metric
             ExecutorMetricsKind::ExecutionDelay => write!(f, "execution_delay"),
+            ExecutorMetricsKind::ElidedWrites => write!(f, "elided_writes"),
             // graph executor specific
             ExecutorMetricsKind::ChainSize => write!(f, "chain_size"),
             ExecutorMetricsKind::OutRequests => write!(f, "out_requests"),
@@ -141,6 +302,49 @@ impl Debug for ExecutorMetricsKind {
             ExecutorMetricsKind::InRequestReplies => {
                 write!(f, "in_request_replies")
             }
+            ExecutorMetricsKind::CleanupInterval => {
+                write!(f, "cleanup_interval")
+            }
+            ExecutorMetricsKind::RequestRtt => write!(f, "request_rtt"),
+            ExecutorMetricsKind::RequestRetries => {
+                write!(f, "request_retries")
+            }
+            ExecutorMetricsKind::DependencyStalls => {
+                write!(f, "dependency_stalls")
+            }
+            ExecutorMetricsKind::RemoteDependencyStalls => {
+                write!(f, "remote_dependency_stalls")
+            }
+            ExecutorMetricsKind::SccBudgetExhaustions => {
+                write!(f, "scc_budget_exhaustions")
+            }
+            // table executor specific
+            ExecutorMetricsKind::StabilityDelay => {
+                write!(f, "stability_delay")
+            }
+            ExecutorMetricsKind::ShedCommands => write!(f, "shed_commands"),
+            // shadow executor specific
+            ExecutorMetricsKind::ShadowLag => write!(f, "shadow_lag"),
+            ExecutorMetricsKind::ShadowLead => write!(f, "shadow_lead"),
+            ExecutorMetricsKind::ShadowOrderMismatches => {
+                write!(f, "shadow_order_mismatches")
+            }
+            // memory usage (graph and pred executors)
+            ExecutorMetricsKind::VertexIndexMemory => {
+                write!(f, "vertex_index_memory")
+            }
+            ExecutorMetricsKind::PendingIndexMemory => {
+                write!(f, "pending_index_memory")
+            }
+            // memory usage (table executor)
+            ExecutorMetricsKind::VotesTableMemory => {
+                write!(f, "votes_table_memory")
+            }
+            ExecutorMetricsKind::PendingMemory => write!(f, "pending_memory"),
+            ExecutorMetricsKind::Violations => write!(f, "violations"),
+            ExecutorMetricsKind::BacklogAlarms => write!(f, "backlog_alarms"),
+            ExecutorMetricsKind::ExpiredValues => write!(f, "expired_values"),
+            ExecutorMetricsKind::ThrottleDelay => write!(f, "throttle_delay"),
         }
     }
 }
@@ -166,11 +370,19 @@ fn key_index(key: &Key) -> (usize, usize) {
     (0, index)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExecutorResult {
     pub rifl: Rifl,
     pub key: Key,
     pub partial_results: Vec<KVOpResult>,
+    // whether the command was shed (as its execution exceeded the executor
+    // latency budget)
+    pub shed: bool,
+    // error that prevented the ops on this key from being executed, if any
+    pub error: Option<KVError>,
+    // if the ops were served by a stale read (see `Config::stale_reads`), the
+    // executed clock of the store they were read from
+    pub executed_clock: Option<u64>,
 }
 
 impl ExecutorResult {
@@ -179,6 +391,63 @@ impl ExecutorResult {
             rifl,
             key,
             partial_results,
+            shed: false,
+            error: None,
+            executed_clock: None,
+        }
+    }
+
+    /// Creates the result of executing the ops of a command on some key,
+    /// given the outcome of their execution in the `KVStore`.
+    pub fn from_execution(
+        rifl: Rifl,
+        key: Key,
+        execution: Result<Vec<KVOpResult>, KVError>,
+    ) -> Self {
+        match execution {
+            Ok(partial_results) => Self::new(rifl, key, partial_results),
+            Err(error) => Self::error(rifl, key, error),
         }
     }
+
+    /// Creates the result of a command whose ops on some key were not
+    /// executed due to `error`.
+    pub fn error(rifl: Rifl, key: Key, error: KVError) -> Self {
+        ExecutorResult {
+            rifl,
+            key,
+            partial_results: Vec::new(),
+            shed: false,
+            error: Some(error),
+            executed_clock: None,
+        }
+    }
+
+    /// Creates the result of a command that was shed, i.e. a command that
+    /// was executed (so that replicas remain consistent) but whose results
+    /// are replaced by an error as they exceeded the executor latency budget.
+    pub fn shed(rifl: Rifl, key: Key) -> Self {
+        ExecutorResult {
+            rifl,
+            key,
+            partial_results: Vec::new(),
+            shed: true,
+            error: None,
+            executed_clock: None,
+        }
+    }
+
+    /// Creates the result of a stale read of the ops of a command on some key,
+    /// i.e. ops that were read directly from `store` (skipping the protocol).
+    pub fn stale_read(
+        rifl: Rifl,
+        key: Key,
+        ops: Vec<KVOp>,
+        store: &KVStore,
+    ) -> Self {
+        let execution = store.read(&key, ops, rifl);
+        let mut result = Self::from_execution(rifl, key, execution);
+        result.executed_clock = Some(store.executed_clock());
+        result
+    }
 }
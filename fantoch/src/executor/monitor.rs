@@ -47,4 +47,75 @@ impl ExecutionOrderMonitor {
     pub fn len(&self) -> usize {
         self.order_per_key.len()
     }
+
+    /// Returns the first position at which the execution order of `key` in
+    /// this monitor and in `other` diverge, if any. Since processes may have
+    /// executed different numbers of commands (e.g. if a run is stopped while
+    /// some processes are behind), the orders only diverge if neither is a
+    /// prefix of the other.
+    pub fn divergence(
+        &self,
+        key: &Key,
+        other: &Self,
+    ) -> Option<OrderDivergence> {
+        let empty = Vec::new();
+        let left = self.get_order(key).unwrap_or(&empty);
+        let right = other.get_order(key).unwrap_or(&empty);
+        left.iter()
+            .zip(right.iter())
+            .position(|(left, right)| left != right)
+            .map(|index| OrderDivergence {
+                key: key.clone(),
+                index,
+                left: left[index],
+                right: right[index],
+            })
+    }
+}
+
+/// First position at which the execution order of a key differs between two
+/// processes, along with the command executed at that position by each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderDivergence {
+    pub key: Key,
+    pub index: usize,
+    pub left: Rifl,
+    pub right: Rifl,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn divergence() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let rifl = |seq| Rifl::new(1, seq);
+
+        let mut left = ExecutionOrderMonitor::new();
+        let mut right = ExecutionOrderMonitor::new();
+        for seq in 1..=3 {
+            left.add(&key_a, rifl(seq));
+        }
+        // a prefix is not a divergence
+        for seq in 1..=2 {
+            right.add(&key_a, rifl(seq));
+        }
+        assert_eq!(left.divergence(&key_a, &right), None);
+        assert_eq!(right.divergence(&key_a, &left), None);
+        // neither is a key missing in one of the monitors
+        left.add(&key_b, rifl(4));
+        assert_eq!(left.divergence(&key_b, &right), None);
+
+        // but a different command at the same position is
+        right.add(&key_a, rifl(4));
+        let divergence = OrderDivergence {
+            key: key_a.clone(),
+            index: 2,
+            left: rifl(3),
+            right: rifl(4),
+        };
+        assert_eq!(left.divergence(&key_a, &right), Some(divergence));
+    }
 }
@@ -0,0 +1,176 @@
+use crate::command::CommandResult;
+use crate::id::{ClientId, Rifl};
+use crate::metrics::Histogram;
+use crate::time::SysTime;
+use crate::trace;
+use crate::HashMap;
+use std::collections::VecDeque;
+
+/// Delivers command results in per-client submission order: results of
+/// commands that complete before some command previously submitted by the
+/// same client are buffered until that command completes (or is cancelled).
+#[derive(Debug, Clone, Default)]
+pub struct OrderedDelivery {
+    // mapping from client to the commands it submitted (in submission order)
+    // that haven't been delivered yet
+    submitted: HashMap<ClientId, VecDeque<Rifl>>,
+    // results waiting for the delivery of previously submitted commands,
+    // along with the time (in micros) at which they became ready
+    buffered: HashMap<Rifl, (CommandResult, u64)>,
+    // time (in micros) each result spent buffered
+    delay: Histogram,
+}
+
+impl OrderedDelivery {
+    /// Creates a new `OrderedDelivery` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new command submitted by some client.
+    pub fn submitted(&mut self, rifl: Rifl) {
+        trace!("OrderedDelivery::submitted {:?}", rifl);
+        self.submitted
+            .entry(rifl.source())
+            .or_default()
+            .push_back(rifl);
+    }
+
+    /// Adds a new command result, returning all the results that can be
+    /// delivered (in submission order).
+    pub fn add(
+        &mut self,
+        cmd_result: CommandResult,
+        time: &dyn SysTime,
+    ) -> Vec<CommandResult> {
+        let rifl = cmd_result.rifl();
+        trace!("OrderedDelivery::add {:?}", rifl);
+        // results of commands that haven't been registered are delivered
+        // right away
+        if !self.is_submitted(&rifl) {
+            return vec![cmd_result];
+        }
+        self.buffered.insert(rifl, (cmd_result, time.micros()));
+        self.deliver(rifl.source(), time)
+    }
+
+    /// Stops tracking a command (e.g. because it was cancelled by the client
+    /// that submitted it), returning all the results that can be delivered
+    /// after it.
+    pub fn cancel(
+        &mut self,
+        rifl: Rifl,
+        time: &dyn SysTime,
+    ) -> Vec<CommandResult> {
+        trace!("OrderedDelivery::cancel {:?}", rifl);
+        let client_id = rifl.source();
+        if let Some(submitted) = self.submitted.get_mut(&client_id) {
+            submitted.retain(|submitted_rifl| *submitted_rifl != rifl);
+        }
+        self.buffered.remove(&rifl);
+        self.deliver(client_id, time)
+    }
+
+    /// Returns the number of results currently buffered.
+    pub fn buffered(&self) -> usize {
+        self.buffered.len()
+    }
+
+    /// Returns the histogram with the time (in micros) each result spent
+    /// buffered.
+    pub fn delay(&self) -> &Histogram {
+        &self.delay
+    }
+
+    fn is_submitted(&self, rifl: &Rifl) -> bool {
+        self.submitted
+            .get(&rifl.source())
+            .map(|submitted| submitted.contains(rifl))
+            .unwrap_or(false)
+    }
+
+    fn deliver(
+        &mut self,
+        client_id: ClientId,
+        time: &dyn SysTime,
+    ) -> Vec<CommandResult> {
+        let now = time.micros();
+        let mut ready = Vec::new();
+        if let Some(submitted) = self.submitted.get_mut(&client_id) {
+            // deliver results while the oldest submitted command is ready
+            while let Some(rifl) = submitted.front() {
+                if let Some((cmd_result, ready_time)) =
+                    self.buffered.remove(rifl)
+                {
+                    self.delay.increment(now - ready_time);
+                    ready.push(cmd_result);
+                    submitted.pop_front();
+                } else {
+                    break;
+                }
+            }
+            if submitted.is_empty() {
+                self.submitted.remove(&client_id);
+            }
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimTime;
+
+    fn result(rifl: Rifl) -> CommandResult {
+        CommandResult::new(rifl, HashMap::new())
+    }
+
+    fn rifls(results: Vec<CommandResult>) -> Vec<Rifl> {
+        results.into_iter().map(|result| result.rifl()).collect()
+    }
+
+    #[test]
+    fn ordered_delivery_flow() {
+        let mut time = SimTime::new();
+        let mut ordered = OrderedDelivery::new();
+
+        // client 1 submits three commands; client 2 submits one
+        let a1 = Rifl::new(1, 1);
+        let a2 = Rifl::new(1, 2);
+        let a3 = Rifl::new(1, 3);
+        let b1 = Rifl::new(2, 1);
+        ordered.submitted(a1);
+        ordered.submitted(a2);
+        ordered.submitted(a3);
+        ordered.submitted(b1);
+
+        // the second command of client 1 completes first and it's buffered
+        assert!(ordered.add(result(a2), &time).is_empty());
+        assert_eq!(ordered.buffered(), 1);
+
+        // commands of other clients are not affected
+        assert_eq!(rifls(ordered.add(result(b1), &time)), vec![b1]);
+
+        // once the first command completes, both are delivered
+        time.add_millis(10);
+        assert_eq!(rifls(ordered.add(result(a1), &time)), vec![a1, a2]);
+        assert_eq!(ordered.buffered(), 0);
+
+        // the buffered result waited for 10ms, while the others didn't wait
+        assert_eq!(ordered.delay().count(), 3);
+        assert_eq!(ordered.delay().inner().get(&10_000), Some(&1));
+        assert_eq!(ordered.delay().inner().get(&0), Some(&2));
+
+        // results of unknown commands are delivered right away
+        let c1 = Rifl::new(3, 1);
+        assert_eq!(rifls(ordered.add(result(c1), &time)), vec![c1]);
+
+        // cancelling a command unblocks the ones submitted after it
+        let a4 = Rifl::new(1, 4);
+        ordered.submitted(a4);
+        assert!(ordered.add(result(a4), &time).is_empty());
+        assert_eq!(rifls(ordered.cancel(a3, &time)), vec![a4]);
+        assert_eq!(ordered.buffered(), 0);
+    }
+}
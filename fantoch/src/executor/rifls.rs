@@ -0,0 +1,167 @@
+use crate::id::{ClientId, Rifl};
+use crate::HashMap;
+
+/// Compact set of `Rifl`s. Since clients assign consecutive sequence numbers to
+/// their commands, the `Rifl`s of each client are kept as a sorted list of
+/// disjoint sequence ranges; with batching, where each client has many
+/// commands pending at once, this is much smaller than tracking each `Rifl`
+/// on its own.
+#[derive(Debug, Clone, Default)]
+pub struct RiflSet {
+    // mapping from each client to its sorted and disjoint ranges of sequences
+    // (with exclusive ends); adjacent ranges are always merged
+    ranges: HashMap<ClientId, Vec<(u64, u64)>>,
+    len: usize,
+}
+
+impl RiflSet {
+    /// Creates a new, empty, `RiflSet`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the number of `Rifl`s in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Checks whether `rifl` is in the set.
+    pub fn contains(&self, rifl: Rifl) -> bool {
+        self.ranges
+            .get(&rifl.source())
+            .map(|ranges| Self::find(ranges, rifl.sequence()).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Adds `rifl` to the set. Returns whether it was not in the set yet.
+    pub fn insert(&mut self, rifl: Rifl) -> bool {
+        let seq = rifl.sequence();
+        let ranges = self.ranges.entry(rifl.source()).or_default();
+        // index of the first range starting after `seq`
+        let index = ranges.partition_point(|(start, _)| *start <= seq);
+        let extends_prev = match index.checked_sub(1).map(|i| ranges[i]) {
+            Some((_, end)) if seq < end => return false,
+            Some((_, end)) => end == seq,
+            None => false,
+        };
+        let extends_next = ranges
+            .get(index)
+            .map(|(start, _)| *start == seq + 1)
+            .unwrap_or(false);
+        match (extends_prev, extends_next) {
+            (true, true) => {
+                // `seq` fills the gap between two ranges, so merge them
+                let (_, end) = ranges.remove(index);
+                ranges[index - 1].1 = end;
+            }
+            (true, false) => ranges[index - 1].1 += 1,
+            (false, true) => ranges[index].0 = seq,
+            (false, false) => ranges.insert(index, (seq, seq + 1)),
+        }
+        self.len += 1;
+        true
+    }
+
+    /// Removes `rifl` from the set. Returns whether it was in the set.
+    pub fn remove(&mut self, rifl: Rifl) -> bool {
+        let seq = rifl.sequence();
+        let ranges = if let Some(ranges) = self.ranges.get_mut(&rifl.source()) {
+            ranges
+        } else {
+            return false;
+        };
+        let index = if let Some(index) = Self::find(ranges, seq) {
+            index
+        } else {
+            return false;
+        };
+        let (start, end) = ranges[index];
+        if start == seq && end == seq + 1 {
+            ranges.remove(index);
+        } else if start == seq {
+            ranges[index].0 += 1;
+        } else if end == seq + 1 {
+            ranges[index].1 -= 1;
+        } else {
+            // `seq` is in the middle of the range, so split it
+            ranges[index].1 = seq;
+            ranges.insert(index + 1, (seq + 1, end));
+        }
+        if ranges.is_empty() {
+            self.ranges.remove(&rifl.source());
+        }
+        self.len -= 1;
+        true
+    }
+
+    // Returns the index of the range containing `seq` (if any).
+    fn find(ranges: &[(u64, u64)], seq: u64) -> Option<usize> {
+        let index = ranges.partition_point(|(start, _)| *start <= seq);
+        let index = index.checked_sub(1)?;
+        let (_, end) = ranges[index];
+        if seq < end {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rifl_set() {
+        let mut set = RiflSet::new();
+        assert!(set.is_empty());
+
+        // consecutive sequences of the same client are kept in a single range
+        for seq in 1..=10 {
+            assert!(set.insert(Rifl::new(1, seq)));
+        }
+        assert!(!set.insert(Rifl::new(1, 5)));
+        assert!(set.insert(Rifl::new(2, 1)));
+        assert_eq!(set.len(), 11);
+        assert_eq!(set.ranges.get(&1), Some(&vec![(1, 11)]));
+        assert!(set.contains(Rifl::new(1, 1)));
+        assert!(set.contains(Rifl::new(1, 10)));
+        assert!(!set.contains(Rifl::new(1, 11)));
+        assert!(!set.contains(Rifl::new(3, 1)));
+
+        // removing from the middle splits the range
+        assert!(set.remove(Rifl::new(1, 5)));
+        assert!(!set.remove(Rifl::new(1, 5)));
+        assert!(!set.contains(Rifl::new(1, 5)));
+        assert_eq!(set.ranges.get(&1), Some(&vec![(1, 5), (6, 11)]));
+
+        // removing from the ends shrinks it
+        assert!(set.remove(Rifl::new(1, 1)));
+        assert!(set.remove(Rifl::new(1, 10)));
+        assert_eq!(set.ranges.get(&1), Some(&vec![(2, 5), (6, 10)]));
+        assert_eq!(set.len(), 8);
+
+        // filling the gap merges both ranges again, and extending a range on
+        // either side doesn't create new ranges
+        assert!(set.insert(Rifl::new(1, 5)));
+        assert!(set.insert(Rifl::new(1, 1)));
+        assert!(set.insert(Rifl::new(1, 10)));
+        assert_eq!(set.ranges.get(&1), Some(&vec![(1, 11)]));
+
+        // non-adjacent sequences create new ranges
+        assert!(set.insert(Rifl::new(1, 20)));
+        assert!(set.insert(Rifl::new(1, 0)));
+        assert_eq!(set.ranges.get(&1), Some(&vec![(0, 11), (20, 21)]));
+
+        // clients without sequences are dropped
+        assert!(set.remove(Rifl::new(2, 1)));
+        assert!(!set.ranges.contains_key(&2));
+        assert!(!set.remove(Rifl::new(2, 1)));
+        assert_eq!(set.len(), 12);
+    }
+}
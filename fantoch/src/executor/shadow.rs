@@ -0,0 +1,262 @@
+use crate::config::Config;
+use crate::executor::{
+    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorMetricsKind,
+    ExecutorResult,
+};
+use crate::id::{ProcessId, Rifl, ShardId};
+use crate::kvs::{KVStore, Key, KeyRange};
+use crate::protocol::{CommittedAndExecuted, MessageIndex, Violation};
+use crate::time::SysTime;
+use crate::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Executor that runs the executor of a primary protocol along with the
+/// executor of a shadow protocol. Only the results of the primary executor are
+/// delivered to clients; the results of the shadow executor are compared with
+/// the ones of the primary executor, recording:
+/// - `ShadowLag`: how much later (in millis) the shadow executor produced a
+///   result
+/// - `ShadowLead`: how much earlier (in millis) the shadow executor produced a
+///   result
+/// - `ShadowOrderMismatches`: number of positions at which the execution
+///   order on some key differs between the two executors
+#[derive(Clone)]
+pub struct ShadowExecutor<PE, SE> {
+    primary: PE,
+    shadow: SE,
+    metrics: ExecutorMetrics,
+    to_clients: VecDeque<ExecutorResult>,
+    // mapping from a partial result (of a command on some key) produced by
+    // only one of the executors to whether it was produced by the primary,
+    // along with the time (in millis) at which it was produced
+    finished: HashMap<(Rifl, Key), (bool, u64)>,
+    // per-key execution order of each executor that is yet to be compared
+    // with the other executor
+    orders: HashMap<Key, (VecDeque<Rifl>, VecDeque<Rifl>)>,
+}
+
+impl<PE, SE> Executor for ShadowExecutor<PE, SE>
+where
+    PE: Executor,
+    SE: Executor,
+{
+    type ExecutionInfo =
+        ShadowExecutionInfo<PE::ExecutionInfo, SE::ExecutionInfo>;
+
+    fn new(process_id: ProcessId, shard_id: ShardId, config: Config) -> Self {
+        Self {
+            primary: PE::new(process_id, shard_id, config),
+            shadow: SE::new(process_id, shard_id, config),
+            metrics: ExecutorMetrics::new(),
+            to_clients: VecDeque::new(),
+            finished: HashMap::new(),
+            orders: HashMap::new(),
+        }
+    }
+
+    fn set_executor_index(&mut self, index: usize) {
+        self.primary.set_executor_index(index);
+        self.shadow.set_executor_index(index);
+    }
+
+    fn set_key_range(&mut self, key_range: KeyRange) {
+        self.primary.set_key_range(key_range);
+        self.shadow.set_key_range(key_range);
+    }
+
+    fn cleanup(&mut self, time: &dyn SysTime) {
+        self.primary.cleanup(time);
+        self.shadow.cleanup(time);
+        self.fetch_results(time);
+    }
+
+    fn cleanup_interval(&mut self) -> Option<Duration> {
+        // use the smallest of the intervals, if any
+        match (
+            self.primary.cleanup_interval(),
+            self.shadow.cleanup_interval(),
+        ) {
+            (Some(primary), Some(shadow)) => Some(primary.min(shadow)),
+            (primary, shadow) => primary.or(shadow),
+        }
+    }
+
+    fn monitor_pending(&mut self, time: &dyn SysTime) {
+        self.primary.monitor_pending(time);
+        self.shadow.monitor_pending(time);
+    }
+
+    fn backlog(&self) -> Option<usize> {
+        // the backlog of the shadow executor doesn't delay clients
+        self.primary.backlog()
+    }
+
+    fn handle(&mut self, info: Self::ExecutionInfo, time: &dyn SysTime) {
+        match info {
+            ShadowExecutionInfo::Primary(info) => {
+                self.primary.handle(info, time)
+            }
+            ShadowExecutionInfo::Shadow(info) => self.shadow.handle(info, time),
+        }
+        self.fetch_results(time);
+    }
+
+    fn to_clients(&mut self) -> Option<ExecutorResult> {
+        self.to_clients.pop_front()
+    }
+
+    fn to_executors(&mut self) -> Option<(ShardId, Self::ExecutionInfo)> {
+        if let Some((shard_id, info)) = self.primary.to_executors() {
+            Some((shard_id, ShadowExecutionInfo::Primary(info)))
+        } else {
+            self.shadow.to_executors().map(|(shard_id, info)| {
+                (shard_id, ShadowExecutionInfo::Shadow(info))
+            })
+        }
+    }
+
+    fn to_process_executors(
+        &mut self,
+    ) -> Option<(ProcessId, Self::ExecutionInfo)> {
+        if let Some((process_id, info)) = self.primary.to_process_executors() {
+            Some((process_id, ShadowExecutionInfo::Primary(info)))
+        } else {
+            self.shadow
+                .to_process_executors()
+                .map(|(process_id, info)| {
+                    (process_id, ShadowExecutionInfo::Shadow(info))
+                })
+        }
+    }
+
+    fn executed(&mut self, time: &dyn SysTime) -> Option<CommittedAndExecuted> {
+        // executed notifications can't tell which protocol they're about, and
+        // thus only the ones of the primary protocol are delivered
+        self.primary.executed(time)
+    }
+
+    fn to_violations(&mut self) -> Option<Violation> {
+        self.primary
+            .to_violations()
+            .or_else(|| self.shadow.to_violations())
+    }
+
+    fn parallel() -> bool {
+        // results from both executors can only be compared if they're produced
+        // by the same executor
+        false
+    }
+
+    fn metrics(&self) -> &ExecutorMetrics {
+        &self.metrics
+    }
+
+    fn monitor(&self) -> Option<ExecutionOrderMonitor> {
+        self.primary.monitor()
+    }
+
+    fn store(&self) -> &KVStore {
+        // stale reads are served by the primary executor
+        self.primary.store()
+    }
+
+    fn stale_read_index(key: &Key) -> Option<(usize, usize)> {
+        PE::stale_read_index(key)
+    }
+}
+
+impl<PE, SE> ShadowExecutor<PE, SE>
+where
+    PE: Executor,
+    SE: Executor,
+{
+    /// Returns the executor of the primary protocol.
+    pub fn primary(&self) -> &PE {
+        &self.primary
+    }
+
+    /// Returns the executor of the shadow protocol.
+    pub fn shadow(&self) -> &SE {
+        &self.shadow
+    }
+
+    fn fetch_results(&mut self, time: &dyn SysTime) {
+        let now = time.millis();
+        while let Some(result) = self.primary.to_clients() {
+            self.compare(true, result.rifl, &result.key, now);
+            self.to_clients.push_back(result);
+        }
+        while let Some(result) = self.shadow.to_clients() {
+            self.compare(false, result.rifl, &result.key, now);
+        }
+    }
+
+    fn compare(&mut self, primary: bool, rifl: Rifl, key: &Key, now: u64) {
+        // compare the time at which both executors produced this result
+        if let Some((other_primary, other_time)) =
+            self.finished.remove(&(rifl, key.clone()))
+        {
+            assert_ne!(
+                primary, other_primary,
+                "{:?} should be executed only once on key {} by each executor",
+                rifl, key
+            );
+            let (primary_time, shadow_time) = if primary {
+                (now, other_time)
+            } else {
+                (other_time, now)
+            };
+            if shadow_time >= primary_time {
+                self.metrics.collect(
+                    ExecutorMetricsKind::ShadowLag,
+                    shadow_time - primary_time,
+                );
+            } else {
+                self.metrics.collect(
+                    ExecutorMetricsKind::ShadowLead,
+                    primary_time - shadow_time,
+                );
+            }
+        } else {
+            self.finished.insert((rifl, key.clone()), (primary, now));
+        }
+
+        // compare the execution order on this key
+        let (primary_order, shadow_order) =
+            self.orders.entry(key.clone()).or_default();
+        if primary {
+            primary_order.push_back(rifl);
+        } else {
+            shadow_order.push_back(rifl);
+        }
+        while !primary_order.is_empty() && !shadow_order.is_empty() {
+            let primary_rifl = primary_order.pop_front();
+            let shadow_rifl = shadow_order.pop_front();
+            if primary_rifl != shadow_rifl {
+                self.metrics
+                    .aggregate(ExecutorMetricsKind::ShadowOrderMismatches, 1);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShadowExecutionInfo<PI, SI> {
+    Primary(PI),
+    Shadow(SI),
+}
+
+impl<PI, SI> MessageIndex for ShadowExecutionInfo<PI, SI>
+where
+    PI: MessageIndex,
+    SI: MessageIndex,
+{
+    fn index(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::Primary(info) => info.index(),
+            Self::Shadow(info) => info.index(),
+        }
+    }
+}
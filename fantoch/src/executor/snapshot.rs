@@ -0,0 +1,206 @@
+use crate::id::{Dot, ProcessId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Snapshot of the dependency graph of an executor: the vertices that are
+/// pending (i.e. committed but not yet executed), the edges from each of them
+/// to their dependencies (that haven't been executed) and the stability
+/// frontier (i.e. the highest sequence from each process such that all
+/// commands up to it have been executed). Dependencies that are neither
+/// pending nor executed are missing. The snapshot can be exported in DOT and
+/// in GraphML so that pathological dependency structures can be visualized.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphSnapshot {
+    pending: BTreeSet<Dot>,
+    missing: BTreeSet<Dot>,
+    edges: BTreeSet<(Dot, Dot)>,
+    frontier: BTreeMap<ProcessId, u64>,
+}
+
+impl GraphSnapshot {
+    /// Creates an empty `GraphSnapshot` with the stability `frontier`
+    /// provided.
+    pub fn new(frontier: impl IntoIterator<Item = (ProcessId, u64)>) -> Self {
+        Self {
+            frontier: frontier.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a pending vertex along with its dependencies that haven't been
+    /// executed.
+    pub fn add_pending(
+        &mut self,
+        dot: Dot,
+        deps: impl IntoIterator<Item = Dot>,
+    ) {
+        self.pending.insert(dot);
+        self.missing.remove(&dot);
+        for dep in deps {
+            if !self.pending.contains(&dep) {
+                self.missing.insert(dep);
+            }
+            self.edges.insert((dot, dep));
+        }
+    }
+
+    /// Returns the pending vertices.
+    pub fn pending(&self) -> &BTreeSet<Dot> {
+        &self.pending
+    }
+
+    /// Returns the dependencies that are neither pending nor executed.
+    pub fn missing(&self) -> &BTreeSet<Dot> {
+        &self.missing
+    }
+
+    /// Returns the edges from each pending vertex to its dependencies.
+    pub fn edges(&self) -> &BTreeSet<(Dot, Dot)> {
+        &self.edges
+    }
+
+    /// Returns the stability frontier.
+    pub fn frontier(&self) -> &BTreeMap<ProcessId, u64> {
+        &self.frontier
+    }
+
+    /// Exports the snapshot in DOT. Missing dependencies are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph dependencies {{").unwrap();
+        writeln!(dot, "  label=\"frontier: {}\";", self.frontier_label())
+            .unwrap();
+        for vertex in self.pending.iter() {
+            writeln!(dot, "  \"{}\";", Self::vertex_id(vertex)).unwrap();
+        }
+        for vertex in self.missing.iter() {
+            writeln!(dot, "  \"{}\" [style=dashed];", Self::vertex_id(vertex))
+                .unwrap();
+        }
+        for (from, to) in self.edges.iter() {
+            writeln!(
+                dot,
+                "  \"{}\" -> \"{}\";",
+                Self::vertex_id(from),
+                Self::vertex_id(to)
+            )
+            .unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    /// Exports the snapshot in GraphML. Each vertex has a `status` (either
+    /// "pending" or "missing"), and the graph has the stability `frontier`.
+    pub fn to_graphml(&self) -> String {
+        let mut graphml = String::new();
+        writeln!(graphml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")
+            .unwrap();
+        writeln!(
+            graphml,
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+        )
+        .unwrap();
+        writeln!(
+            graphml,
+            "  <key id=\"frontier\" for=\"graph\" attr.name=\"frontier\" attr.type=\"string\"/>"
+        )
+        .unwrap();
+        writeln!(
+            graphml,
+            "  <key id=\"status\" for=\"node\" attr.name=\"status\" attr.type=\"string\"/>"
+        )
+        .unwrap();
+        writeln!(
+            graphml,
+            "  <graph id=\"dependencies\" edgedefault=\"directed\">"
+        )
+        .unwrap();
+        writeln!(
+            graphml,
+            "    <data key=\"frontier\">{}</data>",
+            self.frontier_label()
+        )
+        .unwrap();
+        let vertices = self
+            .pending
+            .iter()
+            .map(|vertex| (vertex, "pending"))
+            .chain(self.missing.iter().map(|vertex| (vertex, "missing")));
+        for (vertex, status) in vertices {
+            writeln!(
+                graphml,
+                "    <node id=\"{}\"><data key=\"status\">{}</data></node>",
+                Self::vertex_id(vertex),
+                status
+            )
+            .unwrap();
+        }
+        for (from, to) in self.edges.iter() {
+            writeln!(
+                graphml,
+                "    <edge source=\"{}\" target=\"{}\"/>",
+                Self::vertex_id(from),
+                Self::vertex_id(to)
+            )
+            .unwrap();
+        }
+        writeln!(graphml, "  </graph>").unwrap();
+        writeln!(graphml, "</graphml>").unwrap();
+        graphml
+    }
+
+    fn vertex_id(dot: &Dot) -> String {
+        format!("{}.{}", dot.source(), dot.sequence())
+    }
+
+    fn frontier_label(&self) -> String {
+        self.frontier
+            .iter()
+            .map(|(process_id, sequence)| {
+                format!("p{}={}", process_id, sequence)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn graph_snapshot_flow() {
+        let dot_1_3 = Dot::new(1, 3);
+        let dot_2_1 = Dot::new(2, 1);
+        let dot_2_2 = Dot::new(2, 2);
+        let mut snapshot = GraphSnapshot::new(vec![(1, 2), (2, 0)]);
+
+        // a cycle between 1.3 and 2.1, where 2.1 also depends on 2.2, which
+        // hasn't been committed yet
+        snapshot.add_pending(dot_1_3, vec![dot_2_1]);
+        assert!(snapshot.missing().contains(&dot_2_1));
+        snapshot.add_pending(dot_2_1, vec![dot_1_3, dot_2_2]);
+        assert_eq!(
+            snapshot.pending(),
+            &vec![dot_1_3, dot_2_1].into_iter().collect()
+        );
+        assert_eq!(snapshot.missing(), &vec![dot_2_2].into_iter().collect());
+        assert_eq!(snapshot.edges().len(), 3);
+
+        let dot = snapshot.to_dot();
+        assert!(dot.contains("label=\"frontier: p1=2 p2=0\";"));
+        assert!(dot.contains("  \"1.3\";"));
+        assert!(dot.contains("  \"2.2\" [style=dashed];"));
+        assert!(dot.contains("  \"1.3\" -> \"2.1\";"));
+        assert!(dot.contains("  \"2.1\" -> \"1.3\";"));
+        assert!(dot.contains("  \"2.1\" -> \"2.2\";"));
+
+        let graphml = snapshot.to_graphml();
+        assert!(graphml.contains("<data key=\"frontier\">p1=2 p2=0</data>"));
+        assert!(graphml.contains(
+            "<node id=\"2.2\"><data key=\"status\">missing</data></node>"
+        ));
+        assert!(graphml.contains("<edge source=\"2.1\" target=\"2.2\"/>"));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::config::Config;
+use crate::time::SysTime;
+use std::time::Duration;
+
+/// Token bucket that limits the rate at which an executor produces (partial)
+/// command results to `Config::executor_throttle`, emulating an execution-side
+/// bottleneck (e.g. a slow disk or state machine). Each result consumes one
+/// token; once the bucket is empty, the executor should wait for the delay
+/// returned by `ExecutionThrottle::consume` before executing more commands,
+/// which builds up a backlog (and backpressure) in the commit pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionThrottle {
+    // rate (in results per second) and bucket size, if throttling is enabled
+    throttle: Option<(u64, u64)>,
+    // tokens available at `last_refill` (negative if the executor consumed
+    // more tokens than available, in which case it should wait for the debt
+    // to be paid)
+    tokens: f64,
+    // time (in microseconds) of the last refill
+    last_refill: Option<u64>,
+    // total delay (in microseconds) imposed so far
+    delayed: u64,
+}
+
+impl ExecutionThrottle {
+    /// Creates a new `ExecutionThrottle`. Results are only throttled if
+    /// `executor_throttle` is set, in which case the bucket starts full.
+    pub fn new(config: &Config) -> Self {
+        let throttle = config
+            .executor_throttle()
+            .map(|(rate, burst)| (rate as u64, burst as u64));
+        let tokens = throttle.map(|(_, burst)| burst as f64).unwrap_or(0.0);
+        Self {
+            throttle,
+            tokens,
+            last_refill: None,
+            delayed: 0,
+        }
+    }
+
+    /// Consumes a token for each of the `results` produced by the executor,
+    /// returning for how long it should wait before executing more commands
+    /// (if at all).
+    #[must_use]
+    pub fn consume(
+        &mut self,
+        results: usize,
+        time: &dyn SysTime,
+    ) -> Option<Duration> {
+        let (rate, burst) = self.throttle?;
+
+        // refill the bucket with the tokens accumulated since the last refill
+        let now = time.micros();
+        let last_refill = self.last_refill.replace(now).unwrap_or(now);
+        let elapsed = now.saturating_sub(last_refill) as f64 / 1_000_000.0;
+        self.tokens = (self.tokens + elapsed * rate as f64).min(burst as f64);
+
+        self.tokens -= results as f64;
+        if self.tokens >= 0.0 {
+            return None;
+        }
+        // wait until the debt is paid
+        let delay = (-self.tokens / rate as f64 * 1_000_000.0).ceil() as u64;
+        self.delayed += delay;
+        Some(Duration::from_micros(delay))
+    }
+
+    /// Returns the total delay imposed so far.
+    pub fn delayed(&self) -> Duration {
+        Duration::from_micros(self.delayed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimTime;
+
+    #[test]
+    fn execution_throttle_flow() {
+        // 1000 results per second, with bursts of up to 10 results
+        let mut config = Config::new(3, 1);
+        config.set_executor_throttle((1000, 10));
+        let mut throttle = ExecutionThrottle::new(&config);
+        let mut time = SimTime::new();
+
+        // bursts up to the bucket size are not delayed
+        assert_eq!(throttle.consume(4, &time), None);
+        assert_eq!(throttle.consume(6, &time), None);
+
+        // once the bucket is empty, each result takes 1ms
+        assert_eq!(throttle.consume(2, &time), Some(Duration::from_millis(2)));
+
+        // after waiting, the debt is paid
+        time.add_millis(2);
+        assert_eq!(throttle.consume(0, &time), None);
+
+        // the bucket never holds more than its size
+        time.add_millis(1000);
+        assert_eq!(throttle.consume(10, &time), None);
+        assert_eq!(throttle.consume(1, &time), Some(Duration::from_millis(1)));
+        assert_eq!(throttle.delayed(), Duration::from_millis(3));
+
+        // if throttling is not enabled, results are never delayed
+        let config = Config::new(3, 1);
+        let mut throttle = ExecutionThrottle::new(&config);
+        assert_eq!(throttle.consume(1_000_000, &time), None);
+        assert_eq!(throttle.delayed(), Duration::ZERO);
+    }
+}
@@ -18,6 +18,10 @@ pub type RiflGen = IdGen<ClientId>;
 // shard ids
 pub type ShardId = u64;
 
+// process epochs: each incarnation of a process (i.e. each time it's
+// restarted) has a higher epoch than the previous ones
+pub type Epoch = u64;
+
 #[derive(
     Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
 )]
@@ -1,7 +1,11 @@
+use crate::config::Config;
 use crate::executor::ExecutionOrderMonitor;
-use crate::id::Rifl;
+use crate::id::{ClientId, Rifl, ShardId};
 use crate::HashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
 
 // Definition of `Key` and `Value` types.
 pub type Key = String;
@@ -13,15 +17,411 @@ pub type Value = String;
 pub enum KVOp {
     Get,
     Put(Value),
+    // `Put` whose value expires once the given TTL (in milliseconds) has
+    // passed since the timestamp of the command that wrote it
+    PutTtl(Value, u64),
     Delete,
+    // adds the given amount to the value, interpreted as an integer (that's
+    // zero if the key has no value, or if the value is not an integer)
+    Increment(i64),
+    // adds the given element to the value, interpreted as a set of elements
+    // separated by `SET_SEPARATOR`; elements are kept sorted, so that the
+    // value doesn't depend on the order in which elements are added
+    SetAdd(Value),
+}
+
+/// Separator of the elements in a value updated with `KVOp::SetAdd`.
+pub const SET_SEPARATOR: char = ',';
+
+/// Classes of commutative `KVOp`s: ops in the same class commute with each
+/// other, and so commands composed only of ops in the same class don't
+/// conflict (see `Command::conflicts`). Commutative ops don't return the
+/// previous value, as it depends on the order in which they're executed.
+///
+/// Values written with a TTL should not be updated with commutative ops, as
+/// whether they have expired depends on the order in which ops are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Commutative {
+    Increment,
+    SetAdd,
+}
+
+impl KVOp {
+    /// Returns the class of commutative ops this op belongs to (if any).
+    pub fn commutative(&self) -> Option<Commutative> {
+        match self {
+            Self::Increment(_) => Some(Commutative::Increment),
+            Self::SetAdd(_) => Some(Commutative::SetAdd),
+            _ => None,
+        }
+    }
 }
 
 pub type KVOpResult = Option<Value>;
 
+/// Errors that may occur when executing operations on some key. If so, none
+/// of the operations on that key are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KVError {
+    /// the client is not authorized to execute some of the operations
+    Unauthorized,
+    /// the key is not in the `KeyRange` owned by the store
+    NotOwned,
+}
+
+/// Authorization hook invoked before each operation on some key is executed.
+/// Since it's invoked by the executors of all replicas, it should be
+/// deterministic.
+pub trait AccessControl: Debug + Send + Sync {
+    /// Checks whether `client_id` is authorized to execute `op` on `key`.
+    fn authorized(&self, client_id: ClientId, key: &Key, op: &KVOp) -> bool;
+}
+
+/// `AccessControl` in which keys are partitioned among `tenant_count`
+/// tenants, and clients are only authorized to access the keys of their own
+/// tenant (client `c` belongs to tenant `c % tenant_count`, while key `k`
+/// belongs to tenant `hash(k) % tenant_count`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TenantAccessControl {
+    tenant_count: u64,
+}
+
+impl TenantAccessControl {
+    pub fn new(tenant_count: usize) -> Self {
+        assert!(tenant_count > 0, "there should be at least one tenant");
+        Self {
+            tenant_count: tenant_count as u64,
+        }
+    }
+
+    /// Returns the tenant `key` belongs to.
+    pub fn key_tenant(&self, key: &Key) -> u64 {
+        crate::util::key_hash(key) % self.tenant_count
+    }
+
+    /// Returns the tenant `client_id` belongs to.
+    pub fn client_tenant(&self, client_id: ClientId) -> u64 {
+        client_id % self.tenant_count
+    }
+}
+
+impl AccessControl for TenantAccessControl {
+    fn authorized(&self, client_id: ClientId, key: &Key, _op: &KVOp) -> bool {
+        self.client_tenant(client_id) == self.key_tenant(key)
+    }
+}
+
+/// State machine to which executors apply the ops of the commands they
+/// execute. By default, the `KVStore` applies ops to its own map from keys to
+/// values; if a `StateMachine` is set (see `KVStore::set_state_machine`), ops
+/// are applied to it instead, which allows benchmarking applications other
+/// than a key-value store (e.g. locks or queues) with the existing executors.
+/// Since it's applied by the executors of all replicas, it should be
+/// deterministic.
+///
+/// The `KVStore` still checks that keys are owned and that ops are authorized
+/// (and monitors the execution order, if enabled) before applying them, but
+/// features of its own map (i.e. write batching and TTLs) don't apply.
+pub trait StateMachine: Debug + Send + Sync {
+    /// Applies `op` on `key`, issued by the client in `rifl`. The `timestamp`
+    /// is the one of the command `op` belongs to.
+    fn apply(
+        &mut self,
+        key: &Key,
+        op: KVOp,
+        rifl: Rifl,
+        timestamp: u64,
+    ) -> KVOpResult;
+
+    /// Returns the result of `op` on `key` without changing the state. This
+    /// is only called with read-only ops (i.e. `KVOp::Get`).
+    fn read(&self, key: &Key, op: &KVOp) -> KVOpResult;
+
+    /// Clones the state machine.
+    fn box_clone(&self) -> Box<dyn StateMachine>;
+}
+
+impl Clone for Box<dyn StateMachine> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Built-in `StateMachine`s, which can be selected in the `Config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StateMachineKind {
+    Lock,
+    Queue,
+    Counter,
+}
+
+impl StateMachineKind {
+    /// Creates a new instance of this state machine.
+    pub fn create(&self) -> Box<dyn StateMachine> {
+        match self {
+            Self::Lock => Box::new(LockStateMachine::default()),
+            Self::Queue => Box::new(QueueStateMachine::default()),
+            Self::Counter => Box::new(CounterStateMachine::default()),
+        }
+    }
+}
+
+impl fmt::Display for StateMachineKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lock => write!(f, "lock"),
+            Self::Queue => write!(f, "queue"),
+            Self::Counter => write!(f, "counter"),
+        }
+    }
+}
+
+/// `StateMachine` in which each key is a lock that can be held by a single
+/// client:
+/// - `KVOp::Put` acquires the lock for the client that issued it, returning
+///   the client holding it if it couldn't be acquired
+/// - `KVOp::Delete` releases the lock if held by the client that issued it,
+///   returning the client that held it
+/// - `KVOp::Get` returns the client holding the lock
+///
+/// Other ops are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct LockStateMachine {
+    owners: HashMap<Key, ClientId>,
+}
+
+impl StateMachine for LockStateMachine {
+    fn apply(
+        &mut self,
+        key: &Key,
+        op: KVOp,
+        rifl: Rifl,
+        _timestamp: u64,
+    ) -> KVOpResult {
+        let client_id = rifl.source();
+        match op {
+            KVOp::Put(_) => {
+                let owner = self.owners.entry(key.clone()).or_insert(client_id);
+                if *owner == client_id {
+                    None
+                } else {
+                    Some(owner.to_string())
+                }
+            }
+            KVOp::Delete => match self.owners.get(key) {
+                Some(owner) if *owner == client_id => {
+                    self.owners.remove(key).map(|owner| owner.to_string())
+                }
+                _ => None,
+            },
+            op => self.read(key, &op),
+        }
+    }
+
+    fn read(&self, key: &Key, op: &KVOp) -> KVOpResult {
+        match op {
+            KVOp::Get => self.owners.get(key).map(|owner| owner.to_string()),
+            _ => None,
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn StateMachine> {
+        Box::new(self.clone())
+    }
+}
+
+/// `StateMachine` in which each key is a FIFO queue of values:
+/// - `KVOp::Put` enqueues the value
+/// - `KVOp::Delete` dequeues the value at the front, returning it
+/// - `KVOp::Get` returns the value at the front
+///
+/// Other ops are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct QueueStateMachine {
+    queues: HashMap<Key, VecDeque<Value>>,
+}
+
+impl StateMachine for QueueStateMachine {
+    fn apply(
+        &mut self,
+        key: &Key,
+        op: KVOp,
+        _rifl: Rifl,
+        _timestamp: u64,
+    ) -> KVOpResult {
+        match op {
+            KVOp::Put(value) => {
+                self.queues.entry(key.clone()).or_default().push_back(value);
+                None
+            }
+            KVOp::Delete => {
+                self.queues.get_mut(key).and_then(|queue| queue.pop_front())
+            }
+            op => self.read(key, &op),
+        }
+    }
+
+    fn read(&self, key: &Key, op: &KVOp) -> KVOpResult {
+        match op {
+            KVOp::Get => self
+                .queues
+                .get(key)
+                .and_then(|queue| queue.front())
+                .cloned(),
+            _ => None,
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn StateMachine> {
+        Box::new(self.clone())
+    }
+}
+
+/// `StateMachine` in which each key is an integer counter (that starts at
+/// zero):
+/// - `KVOp::Increment` adds the given amount to the counter
+/// - `KVOp::Delete` resets the counter, returning its value
+/// - `KVOp::Get` returns the value of the counter
+///
+/// Other ops are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct CounterStateMachine {
+    counters: HashMap<Key, i64>,
+}
+
+impl StateMachine for CounterStateMachine {
+    fn apply(
+        &mut self,
+        key: &Key,
+        op: KVOp,
+        _rifl: Rifl,
+        _timestamp: u64,
+    ) -> KVOpResult {
+        match op {
+            KVOp::Increment(delta) => {
+                let counter = self.counters.entry(key.clone()).or_default();
+                *counter = counter.wrapping_add(delta);
+                None
+            }
+            KVOp::Delete => {
+                self.counters.remove(key).map(|value| value.to_string())
+            }
+            op => self.read(key, &op),
+        }
+    }
+
+    fn read(&self, key: &Key, op: &KVOp) -> KVOpResult {
+        match op {
+            KVOp::Get => {
+                let value = self.counters.get(key).cloned().unwrap_or(0);
+                Some(value.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn StateMachine> {
+        Box::new(self.clone())
+    }
+}
+
+/// Range of keys owned by a `KVStore`. Keys are assigned to `slot_count` slots
+/// by their hash (in the same way executor workers are assigned the keys they
+/// execute, see `MessageKey`), and the range owns the slots in `[start, end)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRange {
+    start: usize,
+    end: usize,
+    slot_count: usize,
+}
+
+impl KeyRange {
+    pub fn new(start: usize, end: usize, slot_count: usize) -> Self {
+        assert!(
+            start <= end && end <= slot_count,
+            "invalid key range [{}, {}) with {} slots",
+            start,
+            end,
+            slot_count
+        );
+        Self {
+            start,
+            end,
+            slot_count,
+        }
+    }
+
+    /// Returns the range owned by executor worker `index` (out of `executors`)
+    /// when each worker starts with the keys it executes.
+    pub fn worker(index: usize, executors: usize) -> Self {
+        Self::new(index, index + 1, executors)
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.slot_count
+    }
+
+    /// Returns the slot `key` is assigned to.
+    pub fn slot(&self, key: &Key) -> usize {
+        (crate::util::key_hash(key) % self.slot_count as u64) as usize
+    }
+
+    /// Checks whether `key` belongs to the range.
+    pub fn contains(&self, key: &Key) -> bool {
+        let slot = self.slot(key);
+        self.start <= slot && slot < self.end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Keys (and their values) in a `KeyRange` released by a `KVStore` (see
+/// `KVStore::release`), to be acquired by another one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRangeTransfer {
+    pub range: KeyRange,
+    pub entries: Vec<(Key, Value)>,
+    pub expirations: Vec<(Key, u64)>,
+}
+
 #[derive(Default, Clone)]
 pub struct KVStore {
     store: HashMap<Key, Value>,
     monitor: Option<ExecutionOrderMonitor>,
+    access_control: Option<Arc<dyn AccessControl>>,
+    // number of executions in the store; it's returned with stale reads as a
+    // bound on how stale they are
+    executed_clock: u64,
+    // if write batching is enabled, the last value written to each key since
+    // the last flush (but not yet applied to `store`), and the number of
+    // writes overwritten while in this buffer
+    write_batching: bool,
+    pending_writes: HashMap<Key, Value>,
+    elided_writes: u64,
+    // if set, only the keys in this range are held (and can be accessed)
+    key_range: Option<KeyRange>,
+    // mapping from each key written with a TTL to the timestamp at which its
+    // value expires; expiration is applied when the key is next accessed,
+    // given the timestamp of the command accessing it, and thus it's
+    // deterministic across replicas
+    expirations: HashMap<Key, u64>,
+    // highest timestamp of the commands executed; stale reads are served as
+    // of this timestamp
+    timestamp: u64,
+    // number of values expired since the last time this was taken
+    expired: u64,
+    // if set, ops are applied to this state machine instead of `store`
+    state_machine: Option<Box<dyn StateMachine>>,
 }
 
 impl KVStore {
@@ -35,42 +435,353 @@ impl KVStore {
         Self {
             store: Default::default(),
             monitor,
+            access_control: None,
+            executed_clock: 0,
+            write_batching: false,
+            pending_writes: Default::default(),
+            elided_writes: 0,
+            key_range: None,
+            expirations: Default::default(),
+            timestamp: 0,
+            expired: 0,
+            state_machine: None,
+        }
+    }
+
+    /// Creates a new `KVStore` instance for shard `shard_id` as defined by
+    /// `config`.
+    pub fn from_config(config: &Config, shard_id: ShardId) -> Self {
+        let mut store = Self::new(config.executor_monitor_execution_order());
+        if let Some(tenant_count) = config.executor_tenant_count() {
+            store.set_access_control(TenantAccessControl::new(tenant_count));
+        }
+        store.set_write_batching(config.executor_write_batching());
+        if let Some(kind) = config.executor_state_machine() {
+            store.set_state_machine(kind.create());
         }
+        store.preload(crate::util::preloaded_keys(config, shard_id));
+        store
+    }
+
+    /// Creates the keys provided (with an empty value), so that the first
+    /// access to each of them doesn't pay for its allocation.
+    /// Keys not owned by the store are ignored.
+    pub fn preload(&mut self, keys: impl Iterator<Item = Key>) {
+        let key_range = self.key_range;
+        let keys = keys
+            .filter(|key| Self::in_range(key_range.as_ref(), key))
+            .map(|key| (key, Value::new()));
+        self.store.extend(keys);
+    }
+
+    /// Restricts the store to the keys in `key_range` (the keys not in it are
+    /// dropped). This allows the executor workers that partition the keys of
+    /// a shard to each hold only the keys they own.
+    pub fn set_key_range(&mut self, key_range: KeyRange) {
+        self.flush_writes();
+        self.store.retain(|key, _| key_range.contains(key));
+        self.key_range = Some(key_range);
+    }
+
+    /// Returns the range of keys owned by the store, if it's not the whole
+    /// key space.
+    pub fn key_range(&self) -> Option<&KeyRange> {
+        self.key_range.as_ref()
+    }
+
+    /// Checks whether `key` is owned by the store.
+    pub fn owns(&self, key: &Key) -> bool {
+        Self::in_range(self.key_range.as_ref(), key)
+    }
+
+    /// Releases the ownership of the keys in `range`, returning them (along
+    /// with their values) so that they can be acquired by another store. The
+    /// range released must be at the start or at the end of the range owned,
+    /// so that the one left is still a range.
+    pub fn release(&mut self, range: KeyRange) -> KeyRangeTransfer {
+        assert!(
+            self.state_machine.is_none(),
+            "can't release keys from a store with a state machine"
+        );
+        let owned = self
+            .key_range
+            .expect("can't release keys from a store that owns all keys");
+        assert_eq!(range.slot_count, owned.slot_count);
+        let remaining = if range.start == owned.start && range.end <= owned.end
+        {
+            KeyRange::new(range.end, owned.end, owned.slot_count)
+        } else if range.end == owned.end && range.start >= owned.start {
+            KeyRange::new(owned.start, range.start, owned.slot_count)
+        } else {
+            panic!("can't release {:?} from owned {:?}", range, owned)
+        };
+
+        // apply batched writes before extracting the keys released
+        self.flush_writes();
+        let released: Vec<_> = self
+            .store
+            .keys()
+            .filter(|key| range.contains(key))
+            .cloned()
+            .collect();
+        let expirations = released
+            .iter()
+            .filter_map(|key| {
+                self.expirations
+                    .remove(key)
+                    .map(|expires_at| (key.clone(), expires_at))
+            })
+            .collect();
+        let entries = released
+            .into_iter()
+            .map(|key| {
+                let value = self.store.remove(&key).unwrap();
+                (key, value)
+            })
+            .collect();
+        self.key_range = Some(remaining);
+        KeyRangeTransfer {
+            range,
+            entries,
+            expirations,
+        }
+    }
+
+    /// Acquires the ownership of the keys released by another store. The
+    /// range acquired must be adjacent to the range owned.
+    pub fn acquire(&mut self, transfer: KeyRangeTransfer) {
+        let KeyRangeTransfer {
+            range,
+            entries,
+            expirations,
+        } = transfer;
+        let owned = self
+            .key_range
+            .expect("can't acquire keys in a store that owns all keys");
+        assert_eq!(range.slot_count, owned.slot_count);
+        let extended = if owned.is_empty() {
+            range
+        } else if range.end == owned.start {
+            KeyRange::new(range.start, owned.end, owned.slot_count)
+        } else if range.start == owned.end {
+            KeyRange::new(owned.start, range.end, owned.slot_count)
+        } else {
+            panic!("can't acquire {:?} with owned {:?}", range, owned)
+        };
+        self.store.extend(entries);
+        self.expirations.extend(expirations);
+        self.key_range = Some(extended);
+    }
+
+    /// Sets the hook that authorizes operations before they're executed.
+    pub fn set_access_control<A>(&mut self, access_control: A)
+    where
+        A: AccessControl + 'static,
+    {
+        self.access_control = Some(Arc::new(access_control));
+    }
+
+    /// Sets the state machine to which ops are applied (instead of the map
+    /// from keys to values of the store).
+    pub fn set_state_machine(&mut self, state_machine: Box<dyn StateMachine>) {
+        self.state_machine = Some(state_machine);
+    }
+
+    /// Returns the state machine to which ops are applied, if any.
+    pub fn state_machine(&self) -> Option<&dyn StateMachine> {
+        self.state_machine.as_deref()
+    }
+
+    /// Sets whether consecutive `KVOp::Put`s on the same key should be
+    /// batched until the next `KVStore::flush_writes`.
+    pub fn set_write_batching(&mut self, write_batching: bool) {
+        self.write_batching = write_batching;
+    }
+
+    /// Applies the writes batched since the last flush, returning how many
+    /// writes were elided (i.e. overwritten by a later write on the same key
+    /// before being applied). Executors should call this at the end of each
+    /// drain cycle.
+    pub fn flush_writes(&mut self) -> u64 {
+        self.store.extend(self.pending_writes.drain());
+        std::mem::take(&mut self.elided_writes)
     }
 
     pub fn monitor(&self) -> Option<&ExecutionOrderMonitor> {
         self.monitor.as_ref()
     }
 
+    /// Returns the number of executions in the `KVStore`.
+    pub fn executed_clock(&self) -> u64 {
+        self.executed_clock
+    }
+
+    /// Returns the number of values expired (see `KVOp::PutTtl`) since the
+    /// last time this was called.
+    pub fn take_expired(&mut self) -> u64 {
+        std::mem::take(&mut self.expired)
+    }
+
     /// Executes `KVOp`s in the `KVStore`.
     #[cfg(test)]
     pub fn test_execute(&mut self, key: &Key, op: KVOp) -> KVOpResult {
-        let mut results = self.do_execute(key, vec![op]);
+        let mut results = self.do_execute(key, vec![op], self.timestamp);
         assert_eq!(results.len(), 1);
         results.pop().unwrap()
     }
 
+    /// Executes `KVOp`s in the `KVStore`, unless the client that issued them
+    /// is not authorized to execute some of them. The `timestamp` is the one
+    /// of the command the ops belong to (see `Command::timestamp`), and it's
+    /// used to expire values written with a TTL.
     pub fn execute(
         &mut self,
         key: &Key,
         ops: Vec<KVOp>,
         rifl: Rifl,
-    ) -> Vec<KVOpResult> {
+        timestamp: u64,
+    ) -> Result<Vec<KVOpResult>, KVError> {
+        self.check_owned(key)?;
+        self.authorize(key, &ops, rifl)?;
+
         // update monitor, if we're monitoring
         if let Some(monitor) = self.monitor.as_mut() {
             monitor.add(&key, rifl);
         }
-        self.do_execute(key, ops)
+        self.executed_clock += 1;
+        self.timestamp = std::cmp::max(self.timestamp, timestamp);
+        if let Some(state_machine) = self.state_machine.as_mut() {
+            let results = ops
+                .into_iter()
+                .map(|op| state_machine.apply(key, op, rifl, timestamp))
+                .collect();
+            return Ok(results);
+        }
+        Ok(self.do_execute(key, ops, timestamp))
+    }
+
+    /// Executes read-only `KVOp`s in the `KVStore`, possibly returning stale
+    /// values (as these ops are not ordered by any protocol). For the same
+    /// reason, the execution order monitor is not updated, and values are
+    /// expired given the highest timestamp executed.
+    pub fn read(
+        &self,
+        key: &Key,
+        ops: Vec<KVOp>,
+        rifl: Rifl,
+    ) -> Result<Vec<KVOpResult>, KVError> {
+        self.check_owned(key)?;
+        self.authorize(key, &ops, rifl)?;
+        if let Some(state_machine) = self.state_machine.as_ref() {
+            let results =
+                ops.iter().map(|op| state_machine.read(key, op)).collect();
+            return Ok(results);
+        }
+        let results = ops
+            .into_iter()
+            .map(|op| match op {
+                KVOp::Get => self
+                    .pending_writes
+                    .get(key)
+                    .or_else(|| {
+                        if self.expired(key, self.timestamp) {
+                            None
+                        } else {
+                            self.store.get(key)
+                        }
+                    })
+                    .cloned(),
+                op => panic!("can't read with non-read-only op {:?}", op),
+            })
+            .collect();
+        Ok(results)
+    }
+
+    // Checks that the key is owned by the store.
+    fn check_owned(&self, key: &Key) -> Result<(), KVError> {
+        if self.owns(key) {
+            Ok(())
+        } else {
+            Err(KVError::NotOwned)
+        }
+    }
+
+    fn in_range(key_range: Option<&KeyRange>, key: &Key) -> bool {
+        match key_range {
+            Some(key_range) => key_range.contains(key),
+            None => true,
+        }
+    }
+
+    // Checks that all ops are authorized, if there's access control.
+    fn authorize(
+        &self,
+        key: &Key,
+        ops: &[KVOp],
+        rifl: Rifl,
+    ) -> Result<(), KVError> {
+        if let Some(access_control) = self.access_control.as_ref() {
+            let client_id = rifl.source();
+            if !ops
+                .iter()
+                .all(|op| access_control.authorized(client_id, key, op))
+            {
+                return Err(KVError::Unauthorized);
+            }
+        }
+        Ok(())
+    }
+
+    // Checks whether the value of `key` has expired at `timestamp`.
+    fn expired(&self, key: &Key, timestamp: u64) -> bool {
+        self.expirations
+            .get(key)
+            .map(|expires_at| *expires_at <= timestamp)
+            .unwrap_or(false)
     }
 
     #[allow(clippy::ptr_arg)]
-    fn do_execute(&mut self, key: &Key, ops: Vec<KVOp>) -> Vec<KVOpResult> {
+    fn do_execute(
+        &mut self,
+        key: &Key,
+        ops: Vec<KVOp>,
+        timestamp: u64,
+    ) -> Vec<KVOpResult> {
+        // remove the value of the key if it has expired
+        if self.expired(key, timestamp) {
+            self.expirations.remove(key);
+            self.store.remove(key);
+            self.expired += 1;
+        }
         ops.into_iter()
-            .map(|op| self.do_execute_op(key, op))
+            .map(|op| self.do_execute_op(key, op, timestamp))
             .collect()
     }
 
-    fn do_execute_op(&mut self, key: &Key, op: KVOp) -> KVOpResult {
+    fn do_execute_op(
+        &mut self,
+        key: &Key,
+        op: KVOp,
+        timestamp: u64,
+    ) -> KVOpResult {
+        // any op other than a `KVOp::Get` overwrites the expiration of the key
+        // (if any)
+        if op != KVOp::Get {
+            self.expirations.remove(key);
+        }
+        if self.write_batching {
+            if let KVOp::Put(value) = op {
+                // buffer the write, eliding the previous buffered one (if any)
+                if self.pending_writes.insert(key.clone(), value).is_some() {
+                    self.elided_writes += 1;
+                }
+                return None;
+            }
+            // any other op ends the batch of writes on this key
+            if let Some(value) = self.pending_writes.remove(key) {
+                self.store.insert(key.clone(), value);
+            }
+        }
         match op {
             KVOp::Get => self.store.get(key).cloned(),
             KVOp::Put(value) => {
@@ -78,7 +789,31 @@ impl KVStore {
                 self.store.insert(key.clone(), value);
                 None
             }
+            KVOp::PutTtl(value, ttl) => {
+                self.store.insert(key.clone(), value);
+                self.expirations
+                    .insert(key.clone(), timestamp.saturating_add(ttl));
+                None
+            }
             KVOp::Delete => self.store.remove(key),
+            KVOp::Increment(delta) => {
+                let value = self.store.entry(key.clone()).or_default();
+                let current = value.parse::<i64>().unwrap_or(0);
+                *value = current.wrapping_add(delta).to_string();
+                None
+            }
+            KVOp::SetAdd(element) => {
+                let value = self.store.entry(key.clone()).or_default();
+                let mut elements: Vec<_> = value
+                    .split(SET_SEPARATOR)
+                    .filter(|element| !element.is_empty())
+                    .collect();
+                if let Err(index) = elements.binary_search(&element.as_str()) {
+                    elements.insert(index, &element);
+                    *value = elements.join(&SET_SEPARATOR.to_string());
+                }
+                None
+            }
         }
     }
 }
@@ -86,6 +821,7 @@ impl KVStore {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::HashSet;
 
     #[test]
     fn store_flow() {
@@ -155,4 +891,386 @@ mod tests {
         // get key_a    -> none
         assert_eq!(store.test_execute(&key_a, KVOp::Get), None);
     }
+
+    #[test]
+    fn store_commutative() {
+        let key = String::from("A");
+        let ops =
+            vec![KVOp::Increment(3), KVOp::Increment(-1), KVOp::Increment(10)];
+        let elements = vec!["c", "a", "b", "a"];
+
+        // executing commutative ops in any order results in the same value
+        let monitor = false;
+        let mut values = HashSet::new();
+        for rotation in 0..ops.len() {
+            let mut store = KVStore::new(monitor);
+            let mut ops = ops.clone();
+            ops.rotate_left(rotation);
+            for op in ops.into_iter().rev() {
+                assert_eq!(op.commutative(), Some(Commutative::Increment));
+                assert_eq!(store.test_execute(&key, op), None);
+            }
+            values.insert(store.test_execute(&key, KVOp::Get));
+        }
+        assert_eq!(values.len(), 1);
+        assert!(values.contains(&Some(String::from("12"))));
+
+        let mut values = HashSet::new();
+        for rotation in 0..elements.len() {
+            let mut store = KVStore::new(monitor);
+            let mut elements = elements.clone();
+            elements.rotate_left(rotation);
+            for element in elements {
+                let op = KVOp::SetAdd(element.to_string());
+                assert_eq!(store.test_execute(&key, op), None);
+            }
+            values.insert(store.test_execute(&key, KVOp::Get));
+        }
+        assert_eq!(values.len(), 1);
+        assert!(values.contains(&Some(String::from("a,b,c"))));
+
+        // values that are not integers are incremented from zero
+        let mut store = KVStore::new(monitor);
+        store.test_execute(&key, KVOp::Put(String::from("x")));
+        store.test_execute(&key, KVOp::Increment(2));
+        assert_eq!(
+            store.test_execute(&key, KVOp::Get),
+            Some(String::from("2"))
+        );
+        assert_eq!(KVOp::Put(key).commutative(), None);
+    }
+
+    #[test]
+    fn store_access_control() {
+        // with two tenants, find a key of each tenant
+        let tenant_count = 2;
+        let access_control = TenantAccessControl::new(tenant_count);
+        let keys: Vec<_> = (0..)
+            .map(|i| format!("{}", i))
+            .filter(|key| access_control.key_tenant(key) == 0)
+            .take(1)
+            .chain(
+                (0..)
+                    .map(|i| format!("{}", i))
+                    .filter(|key| access_control.key_tenant(key) == 1)
+                    .take(1),
+            )
+            .collect();
+        let (key_0, key_1) = (&keys[0], &keys[1]);
+
+        // store
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+        store.set_access_control(access_control);
+
+        // clients 2 and 1 belong to tenants 0 and 1, respectively
+        let x = String::from("x");
+        let rifl_0 = Rifl::new(2, 1);
+        let rifl_1 = Rifl::new(1, 1);
+
+        // clients can access keys of their tenant
+        assert_eq!(
+            store.execute(
+                key_0,
+                vec![KVOp::Put(x.clone()), KVOp::Get],
+                rifl_0,
+                0
+            ),
+            Ok(vec![None, Some(x.clone())])
+        );
+        assert_eq!(
+            store.execute(key_1, vec![KVOp::Get], rifl_1, 0),
+            Ok(vec![None])
+        );
+
+        // but not keys of other tenants
+        assert_eq!(
+            store.execute(key_0, vec![KVOp::Delete], rifl_1, 0),
+            Err(KVError::Unauthorized)
+        );
+        assert_eq!(
+            store.execute(key_1, vec![KVOp::Get], rifl_0, 0),
+            Err(KVError::Unauthorized)
+        );
+
+        // unauthorized ops are not executed
+        assert_eq!(store.test_execute(key_0, KVOp::Get), Some(x));
+    }
+
+    #[test]
+    fn store_read() {
+        let key = String::from("A");
+        let x = String::from("x");
+        let rifl = Rifl::new(1, 1);
+
+        // store
+        let monitor = true;
+        let mut store = KVStore::new(monitor);
+        assert_eq!(store.executed_clock(), 0);
+
+        // reads don't change the executed clock
+        assert_eq!(store.read(&key, vec![KVOp::Get], rifl), Ok(vec![None]));
+        assert_eq!(store.executed_clock(), 0);
+
+        // executions do
+        assert_eq!(
+            store.execute(&key, vec![KVOp::Put(x.clone())], rifl, 0),
+            Ok(vec![None])
+        );
+        assert_eq!(store.executed_clock(), 1);
+
+        // reads see the last value executed and are not monitored
+        let rifl = Rifl::new(1, 2);
+        assert_eq!(
+            store.read(&key, vec![KVOp::Get, KVOp::Get], rifl),
+            Ok(vec![Some(x.clone()), Some(x)])
+        );
+        assert_eq!(store.executed_clock(), 1);
+        let monitor = store.monitor().expect("store should have a monitor");
+        assert_eq!(monitor.get_order(&key), Some(&vec![Rifl::new(1, 1)]));
+    }
+
+    #[test]
+    fn store_write_batching() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let x = String::from("x");
+        let y = String::from("y");
+        let z = String::from("z");
+
+        // store
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+        store.set_write_batching(true);
+
+        // all writes produce a result
+        assert_eq!(store.test_execute(&key_a, KVOp::Put(x.clone())), None);
+        assert_eq!(store.test_execute(&key_a, KVOp::Put(y.clone())), None);
+        assert_eq!(store.test_execute(&key_b, KVOp::Put(x.clone())), None);
+        assert_eq!(store.test_execute(&key_a, KVOp::Put(z.clone())), None);
+
+        // buffered writes are visible to ops and reads
+        let rifl = Rifl::new(1, 1);
+        assert_eq!(
+            store.read(&key_a, vec![KVOp::Get], rifl),
+            Ok(vec![Some(z.clone())])
+        );
+        assert_eq!(store.test_execute(&key_b, KVOp::Get), Some(x.clone()));
+
+        // the `KVOp::Get` on `B` ended its batch, so only the two writes on
+        // `A` overwritten were elided
+        assert_eq!(store.flush_writes(), 2);
+        assert_eq!(store.flush_writes(), 0);
+
+        // after the flush, writes were applied
+        assert_eq!(store.test_execute(&key_a, KVOp::Get), Some(z.clone()));
+        assert_eq!(store.test_execute(&key_b, KVOp::Get), Some(x));
+
+        // a delete ends the batch, and thus it returns the last value written
+        assert_eq!(store.test_execute(&key_a, KVOp::Put(y.clone())), None);
+        assert_eq!(store.test_execute(&key_a, KVOp::Delete), Some(y));
+        assert_eq!(store.test_execute(&key_a, KVOp::Get), None);
+        assert_eq!(store.flush_writes(), 0);
+    }
+
+    #[test]
+    fn store_ttl() {
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let x = String::from("x");
+        let y = String::from("y");
+        let rifl = Rifl::new(1, 1);
+        let get = |store: &mut KVStore, key: &Key, timestamp: u64| {
+            store
+                .execute(key, vec![KVOp::Get], rifl, timestamp)
+                .unwrap()
+                .pop()
+                .unwrap()
+        };
+
+        // store
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+
+        // put key_a x with a ttl of 10 at timestamp 100 (and key_b y with no
+        // ttl)
+        let put_ttl = vec![KVOp::PutTtl(x.clone(), 10)];
+        assert_eq!(store.execute(&key_a, put_ttl, rifl, 100), Ok(vec![None]));
+        let put = vec![KVOp::Put(y.clone())];
+        assert_eq!(store.execute(&key_b, put, rifl, 100), Ok(vec![None]));
+
+        // before timestamp 110, key_a is still there
+        assert_eq!(get(&mut store, &key_a, 109), Some(x.clone()));
+        assert_eq!(store.take_expired(), 0);
+
+        // and after it, it has expired (even for stale reads)
+        assert_eq!(
+            store.read(&key_a, vec![KVOp::Get], rifl),
+            Ok(vec![Some(x.clone())])
+        );
+        assert_eq!(get(&mut store, &key_b, 110), Some(y.clone()));
+        assert_eq!(store.read(&key_a, vec![KVOp::Get], rifl), Ok(vec![None]));
+        assert_eq!(get(&mut store, &key_a, 110), None);
+        assert_eq!(store.take_expired(), 1);
+
+        // a later put without ttl overwrites the expiration
+        let put_ttl = vec![KVOp::PutTtl(x.clone(), 10)];
+        assert_eq!(store.execute(&key_a, put_ttl, rifl, 120), Ok(vec![None]));
+        let put = vec![KVOp::Put(y.clone())];
+        assert_eq!(store.execute(&key_a, put, rifl, 125), Ok(vec![None]));
+        assert_eq!(get(&mut store, &key_a, 200), Some(y.clone()));
+
+        // expirations are kept when keys are transferred
+        let slot_count = 1;
+        store.set_key_range(KeyRange::new(0, slot_count, slot_count));
+        let put_ttl = vec![KVOp::PutTtl(x, 10)];
+        assert_eq!(store.execute(&key_a, put_ttl, rifl, 200), Ok(vec![None]));
+        let transfer = store.release(KeyRange::new(0, slot_count, slot_count));
+        assert_eq!(transfer.expirations, vec![(key_a.clone(), 210)]);
+        let mut other = KVStore::new(monitor);
+        other.set_key_range(KeyRange::new(0, 0, slot_count));
+        other.acquire(transfer);
+        assert_eq!(get(&mut other, &key_b, 205), Some(y));
+        assert_eq!(get(&mut other, &key_a, 210), None);
+        assert_eq!(other.take_expired(), 1);
+    }
+
+    #[test]
+    fn preload() {
+        let mut config = Config::new(3, 1);
+        config.set_preload_keys(10);
+        let mut store = KVStore::from_config(&config, 0);
+
+        // preloaded keys have an empty value
+        let key = String::from("10");
+        assert_eq!(store.test_execute(&key, KVOp::Get), Some(String::new()));
+        let key = String::from("11");
+        assert_eq!(store.test_execute(&key, KVOp::Get), None);
+    }
+
+    #[test]
+    fn key_range_transfer() {
+        // find a key in each of the 3 slots
+        let slot_count = 3;
+        let all = KeyRange::new(0, slot_count, slot_count);
+        let keys: Vec<_> = (0..slot_count)
+            .map(|slot| {
+                (0..)
+                    .map(|i| format!("{}", i))
+                    .find(|key| all.slot(key) == slot)
+                    .unwrap()
+            })
+            .collect();
+        let x = String::from("x");
+        let rifl = Rifl::new(1, 1);
+
+        // two workers: the first owns slots 0 and 1, the second slot 2
+        let monitor = false;
+        let mut store_a = KVStore::new(monitor);
+        store_a.set_key_range(KeyRange::new(0, 2, slot_count));
+        let mut store_b = KVStore::new(monitor);
+        store_b.set_key_range(KeyRange::worker(2, slot_count));
+        assert!(store_a.owns(&keys[1]));
+        assert!(!store_a.owns(&keys[2]));
+        assert!(store_b.owns(&keys[2]));
+
+        // keys can only be accessed in the store that owns them
+        store_a.set_write_batching(true);
+        assert_eq!(
+            store_a.execute(&keys[1], vec![KVOp::Put(x.clone())], rifl, 0),
+            Ok(vec![None])
+        );
+        assert_eq!(
+            store_b.execute(&keys[1], vec![KVOp::Get], rifl, 0),
+            Err(KVError::NotOwned)
+        );
+        assert_eq!(
+            store_a.read(&keys[2], vec![KVOp::Get], rifl),
+            Err(KVError::NotOwned)
+        );
+        assert_eq!(store_b.executed_clock(), 0);
+
+        // transfer slot 1 (including the write not yet flushed)
+        let transfer = store_a.release(KeyRange::worker(1, slot_count));
+        assert_eq!(transfer.entries, vec![(keys[1].clone(), x.clone())]);
+        store_b.acquire(transfer);
+        assert_eq!(store_a.key_range(), Some(&KeyRange::worker(0, slot_count)));
+        assert_eq!(store_b.key_range(), Some(&KeyRange::new(1, 3, slot_count)));
+        assert_eq!(
+            store_a.read(&keys[1], vec![KVOp::Get], rifl),
+            Err(KVError::NotOwned)
+        );
+        assert_eq!(
+            store_b.read(&keys[1], vec![KVOp::Get], rifl),
+            Ok(vec![Some(x)])
+        );
+
+        // preloading ignores the keys not owned
+        store_a.preload(keys.clone().into_iter());
+        assert_eq!(
+            store_a.test_execute(&keys[0], KVOp::Get),
+            Some(Value::new())
+        );
+        assert_eq!(store_a.store.len(), 1);
+    }
+
+    #[test]
+    fn state_machines() {
+        let key = String::from("A");
+        let x = String::from("x");
+        let y = String::from("y");
+        let rifl_1 = Rifl::new(1, 1);
+        let rifl_2 = Rifl::new(2, 1);
+
+        // lock: client 1 acquires it, so client 2 can't acquire nor release it
+        let monitor = false;
+        let mut store = KVStore::new(monitor);
+        store.set_state_machine(StateMachineKind::Lock.create());
+        let mut execute = |op, rifl| {
+            let mut results = store.execute(&key, vec![op], rifl, 0).unwrap();
+            results.pop().unwrap()
+        };
+        assert_eq!(execute(KVOp::Put(x.clone()), rifl_1), None);
+        assert_eq!(execute(KVOp::Put(x.clone()), rifl_1), None);
+        assert_eq!(execute(KVOp::Put(x.clone()), rifl_2), Some("1".into()));
+        assert_eq!(execute(KVOp::Delete, rifl_2), None);
+        assert_eq!(execute(KVOp::Get, rifl_2), Some("1".into()));
+        assert_eq!(execute(KVOp::Delete, rifl_1), Some("1".into()));
+        assert_eq!(execute(KVOp::Put(x.clone()), rifl_2), None);
+        assert_eq!(
+            store.read(&key, vec![KVOp::Get], rifl_1),
+            Ok(vec![Some("2".into())])
+        );
+        assert_eq!(store.executed_clock(), 7);
+
+        // queue: values are dequeued in the order they were enqueued
+        let mut store = KVStore::new(monitor);
+        store.set_state_machine(StateMachineKind::Queue.create());
+        let mut execute = |op| {
+            let mut results = store.execute(&key, vec![op], rifl_1, 0).unwrap();
+            results.pop().unwrap()
+        };
+        assert_eq!(execute(KVOp::Put(x.clone())), None);
+        assert_eq!(execute(KVOp::Put(y.clone())), None);
+        assert_eq!(execute(KVOp::Get), Some(x.clone()));
+        assert_eq!(execute(KVOp::Delete), Some(x));
+        assert_eq!(execute(KVOp::Delete), Some(y));
+        assert_eq!(execute(KVOp::Delete), None);
+        // the map of the store is not used
+        assert!(store.store.is_empty());
+
+        // counter: increments are added up
+        let mut store = KVStore::new(monitor);
+        store.set_state_machine(StateMachineKind::Counter.create());
+        let mut execute = |op| {
+            let mut results = store.execute(&key, vec![op], rifl_1, 0).unwrap();
+            results.pop().unwrap()
+        };
+        assert_eq!(execute(KVOp::Get), Some("0".into()));
+        assert_eq!(execute(KVOp::Increment(5)), None);
+        assert_eq!(execute(KVOp::Increment(-2)), None);
+        assert_eq!(execute(KVOp::Get), Some("3".into()));
+        assert_eq!(execute(KVOp::Delete), Some("3".into()));
+        assert_eq!(execute(KVOp::Get), Some("0".into()));
+    }
 }
@@ -26,6 +26,9 @@ pub mod command;
 // This module contains the definition of `Config`.
 pub mod config;
 
+// This module contains the definition of `RunError`.
+pub mod error;
+
 // This module contains the definition of `ToSend`, `Process` and `BaseProcess`
 // and implementations of all protocols supported.
 pub mod protocol;
@@ -45,8 +48,14 @@ pub mod sim;
 #[cfg(feature = "run")]
 pub mod run;
 
+// This module contains the definition of `Cluster` (that runs a given
+// `Protocol` in-process, as an embedded replication layer)
+#[cfg(feature = "run")]
+pub mod embed;
+
 pub mod load_balance {
     use crate::id::Dot;
+    use serde::{Deserialize, Serialize};
 
     // the worker index that should be used by leader-based protocols
     pub const LEADER_WORKER_INDEX: usize = 0;
@@ -73,6 +82,104 @@ pub mod load_balance {
     pub fn worker_dot_index_shift(dot: &Dot) -> Option<(usize, usize)> {
         worker_index_shift(dot.sequence() as usize)
     }
+
+    /// Defines how shifted indexes (e.g. the sequence of a dot) are assigned to
+    /// the workers that are not reserved. Indexes with no shift are always
+    /// assigned to the reserved worker they name.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+    )]
+    pub enum WorkerAssignment {
+        /// Consecutive indexes are assigned to consecutive workers (i.e. index
+        /// `i` to worker `reserved + i % (workers - reserved)`), preserving
+        /// the contiguity of the dots of each process.
+        Contiguous,
+        /// Indexes are first hashed with SplitMix64, which, unlike the hasher
+        /// of the standard library, doesn't depend on the run, the platform or
+        /// the version of the compiler. This breaks any correlation between
+        /// the sequence of dots (or slots) and the worker handling them.
+        Hashed,
+    }
+
+    impl Default for WorkerAssignment {
+        fn default() -> Self {
+            Self::Contiguous
+        }
+    }
+
+    /// Returns the worker (out of `workers`) to which `index` (as returned by
+    /// e.g. `worker_dot_index_shift`) is assigned.
+    pub fn worker_index(
+        (reserved, index): (usize, usize),
+        workers: usize,
+        assignment: WorkerAssignment,
+    ) -> usize {
+        if reserved < workers {
+            // only shifted indexes are hashed
+            let index = match assignment {
+                WorkerAssignment::Contiguous => index as u64,
+                WorkerAssignment::Hashed if reserved > 0 => {
+                    splitmix64(index as u64)
+                }
+                WorkerAssignment::Hashed => index as u64,
+            };
+            // compute the actual index only in the remaining indexes
+            let remaining = (workers - reserved) as u64;
+            reserved + (index % remaining) as usize
+        } else {
+            // if there's as many reserved (or more) as workers in the
+            // pool, then ignore reservation
+            index % workers
+        }
+    }
+
+    // Finalizer of SplitMix64 (see e.g. https://prng.di.unimi.it/splitmix64.c).
+    fn splitmix64(x: u64) -> u64 {
+        let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn hashed_worker_index() {
+            // the hash function should never change, as otherwise runs with
+            // the same number of workers would route dots differently
+            assert_eq!(splitmix64(0), 0xe220a8397b1dcdaf);
+
+            let workers = 6;
+            let index =
+                |index| worker_index(index, workers, WorkerAssignment::Hashed);
+            // indexes with no shift are not hashed
+            assert_eq!(index(worker_index_no_shift(0).unwrap()), 0);
+            assert_eq!(index(worker_index_no_shift(1).unwrap()), 1);
+            // shifted indexes are never assigned to reserved workers
+            for sequence in 0..1000 {
+                let index = index(worker_index_shift(sequence).unwrap());
+                assert!(index >= WORKERS_INDEXES_RESERVED && index < workers);
+            }
+            // and, unlike with contiguous assignment, consecutive sequences
+            // are not assigned to consecutive workers
+            let contiguous = (0..8)
+                .map(|sequence| {
+                    worker_index(
+                        worker_index_shift(sequence).unwrap(),
+                        workers,
+                        WorkerAssignment::Contiguous,
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(contiguous, vec![2, 3, 4, 5, 2, 3, 4, 5]);
+            let hashed = (0..8)
+                .map(|sequence| index(worker_index_shift(sequence).unwrap()))
+                .collect::<Vec<_>>();
+            assert_ne!(hashed, contiguous);
+        }
+    }
 }
 
 // This module contains some utilitary functions.
@@ -30,6 +30,14 @@ impl Histogram {
         stats
     }
 
+    /// Creates an histogram from a mapping from values to their number of
+    /// occurrences (values with no occurrences are ignored).
+    pub fn from_occurrences(values: BTreeMap<u64, usize>) -> Self {
+        let values =
+            values.into_iter().filter(|(_, count)| *count > 0).collect();
+        Self { values }
+    }
+
     /// Returns the number of occurrences.
     pub fn count(&self) -> usize {
         self.values.iter().map(|(_, count)| count).sum::<usize>()
@@ -409,6 +417,18 @@ mod tests {
         assert_eq!(stats.max(), F64::new(20.0));
     }
 
+    #[test]
+    fn from_occurrences() {
+        let mut values = BTreeMap::new();
+        values.insert(10, 2);
+        values.insert(20, 0);
+        values.insert(40, 1);
+        let stats = Histogram::from_occurrences(values);
+        assert_eq!(stats, Histogram::from(vec![10, 10, 40]));
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.max(), F64::new(40.0));
+    }
+
     #[test]
     fn stats_show() {
         let stats = Histogram::from(vec![1, 1, 1]);
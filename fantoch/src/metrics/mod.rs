@@ -55,6 +55,14 @@ where
         self.aggregated.get(&kind)
     }
 
+    pub fn collected(&self) -> impl Iterator<Item = (&K, &Histogram)> {
+        self.collected.iter()
+    }
+
+    pub fn aggregated(&self) -> impl Iterator<Item = (&K, &u64)> {
+        self.aggregated.iter()
+    }
+
     pub fn merge(&mut self, other: &Self) {
         for (k, hist) in other.collected.iter() {
             let current = self.collected.entry(*k).or_default();
@@ -0,0 +1,120 @@
+use crate::planet::Region;
+use crate::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// AWS and GCP regions located in the same city, as (AWS, GCP) pairs.
+pub const AWS_GCP_REGIONS: &[(&str, &str)] = &[
+    ("us-east-1", "us-east4"),
+    ("us-west-2", "us-west1"),
+    ("ca-central-1", "northamerica-northeast1"),
+    ("sa-east-1", "southamerica-east1"),
+    ("eu-west-2", "europe-west2"),
+    ("eu-central-1", "europe-west3"),
+    ("ap-east-1", "asia-east2"),
+    ("ap-northeast-1", "asia-northeast1"),
+    ("ap-northeast-3", "asia-northeast2"),
+    ("ap-south-1", "asia-south1"),
+    ("ap-southeast-1", "asia-southeast1"),
+    ("ap-southeast-2", "australia-southeast1"),
+];
+
+/// Mapping from region names (e.g. a GCP region or a baremetal site) to the
+/// canonical region name used in the latency matrix of a `Planet`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionAliases {
+    /// mapping from alias to canonical region
+    aliases: HashMap<Region, Region>,
+}
+
+impl RegionAliases {
+    /// Creates an empty set of aliases.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates aliases from GCP region names to the AWS regions located in
+    /// the same city (see `AWS_GCP_REGIONS`).
+    pub fn gcp_to_aws() -> Self {
+        let mut aliases = Self::new();
+        for (aws, gcp) in AWS_GCP_REGIONS {
+            aliases.add(Region::new(*gcp), Region::new(*aws));
+        }
+        aliases
+    }
+
+    /// Creates aliases from AWS region names to the GCP regions located in
+    /// the same city (see `AWS_GCP_REGIONS`).
+    pub fn aws_to_gcp() -> Self {
+        let mut aliases = Self::new();
+        for (aws, gcp) in AWS_GCP_REGIONS {
+            aliases.add(Region::new(*aws), Region::new(*gcp));
+        }
+        aliases
+    }
+
+    /// Makes `alias` an alias of `region`. If `region` is itself an alias, the
+    /// new alias points to its canonical region.
+    pub fn add(&mut self, alias: Region, region: Region) {
+        let canonical = self.canonical(&region);
+        assert_ne!(
+            alias, canonical,
+            "a region can't be an alias of itself: {:?}",
+            alias
+        );
+        assert!(
+            !self.aliases.values().any(|region| region == &alias),
+            "region {:?} is already the canonical region of some alias",
+            alias
+        );
+        self.aliases.insert(alias, canonical);
+    }
+
+    /// Returns the canonical region of `region`, or `region` itself if it's
+    /// not an alias.
+    pub fn canonical(&self, region: &Region) -> Region {
+        self.aliases
+            .get(region)
+            .cloned()
+            .unwrap_or_else(|| region.clone())
+    }
+
+    /// Returns all the aliases of `region` (sorted by name).
+    pub fn aliases_of(&self, region: &Region) -> Vec<Region> {
+        let mut aliases: Vec<_> = self
+            .aliases
+            .iter()
+            .filter(|(_, canonical)| *canonical == region)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        aliases.sort();
+        aliases
+    }
+
+    /// Returns true if there are no aliases.
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_aliases() {
+        let mut aliases = RegionAliases::gcp_to_aws();
+        let us_e1 = Region::new("us-east-1");
+        let us_e4 = Region::new("us-east4");
+        let site = Region::new("lisbon-site");
+
+        // canonical regions are the AWS ones
+        assert_eq!(aliases.canonical(&us_e4), us_e1);
+        assert_eq!(aliases.canonical(&us_e1), us_e1);
+        assert_eq!(aliases.canonical(&site), site);
+
+        // an alias of an alias points to the canonical region
+        aliases.add(site.clone(), us_e4.clone());
+        assert_eq!(aliases.canonical(&site), us_e1);
+        assert_eq!(aliases.aliases_of(&us_e1), vec![site, us_e4]);
+    }
+}
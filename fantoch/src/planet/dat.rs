@@ -1,4 +1,4 @@
-use crate::planet::Region;
+use crate::planet::{LatencyPercentile, LatencyPercentiles, Region};
 use crate::HashMap;
 use std::str::FromStr;
 
@@ -27,10 +27,13 @@ impl Dat {
         Region::new(region)
     }
 
-    /// Computes, based on the `Dat` file, the latency from this region to all
-    /// other regions.
+    /// Computes, based on the `Dat` file, the latency (at the percentile
+    /// provided) from this region to all other regions.
     /// The local latency (within the same region) will always be 1.
-    pub fn latencies(&self) -> HashMap<Region, u64> {
+    pub fn latencies(
+        &self,
+        percentile: LatencyPercentile,
+    ) -> HashMap<Region, u64> {
         // open the file in read-only mode (ignoring errors)
         let file = std::fs::File::open(self.filename.clone()).unwrap();
 
@@ -42,7 +45,7 @@ impl Dat {
         BufReader::new(file)
             .lines()
             .map(|line| line.unwrap())
-            .map(Dat::latency)
+            .map(|line| Dat::latency(line, percentile))
             .map(|(region, latency)| {
                 if region == this_region {
                     (region, super::INTRA_REGION_LATENCY)
@@ -53,25 +56,98 @@ impl Dat {
             .collect()
     }
 
+    /// Computes, based on the `Dat` file, the RTT percentiles from this region
+    /// to all other regions. Regions whose entry doesn't carry percentiles are
+    /// not included.
+    pub fn percentiles(&self) -> HashMap<Region, LatencyPercentiles> {
+        // open the file in read-only mode (ignoring errors)
+        let file = std::fs::File::open(self.filename.clone()).unwrap();
+
+        // get this region
+        let this_region = self.region();
+
+        // for each line in the file with percentiles, compute a pair (region,
+        // percentiles)
+        // - intra-region latency is assumed to be 0
+        BufReader::new(file)
+            .lines()
+            .map(|line| line.unwrap())
+            .filter_map(Dat::percentiles_from_line)
+            .map(|(region, percentiles)| {
+                if region == this_region {
+                    let latency = super::INTRA_REGION_LATENCY;
+                    let percentiles = LatencyPercentiles {
+                        p50: latency,
+                        p95: latency,
+                        p99: latency,
+                    };
+                    (region, percentiles)
+                } else {
+                    (region, percentiles)
+                }
+            })
+            .collect()
+    }
+
     /// Extracts from a line of the `Dat` file, the region's name and the
-    /// average latency to it.
-    fn latency(line: String) -> (Region, u64) {
-        let mut iter = line.split(|c| c == '/' || c == ':');
-
-        // latency is in the second entry
-        let latency = iter.nth(1).unwrap();
-        // convert it to f64
-        let latency = f64::from_str(latency).unwrap();
+    /// latency to it at the percentile provided.
+    ///
+    /// Each line looks like "min/avg/max/mdev:region", optionally followed by
+    /// the p50, p95 and p99 RTTs, as in "min/avg/max/mdev/p50/p95/p99:region".
+    /// If the line doesn't carry percentiles, the average is used.
+    fn latency(line: String, percentile: LatencyPercentile) -> (Region, u64) {
+        let (region, stats) = Dat::stats(line);
+
+        // get the latency at the percentile, falling back to the average
+        let latency = stats
+            .get(percentile.stat_index())
+            .unwrap_or_else(|| &stats[LatencyPercentile::Avg.stat_index()]);
         // convert it to u64 (it always rounds down)
-        let latency = latency as u64;
+        let latency = *latency as u64;
+
+        // return both
+        (region, latency)
+    }
+
+    /// Extracts from a line of the `Dat` file, the region's name and the RTT
+    /// percentiles to it, if the line carries them.
+    fn percentiles_from_line(
+        line: String,
+    ) -> Option<(Region, LatencyPercentiles)> {
+        let (region, stats) = Dat::stats(line);
+
+        // convert each percentile to u64 (it always rounds down)
+        let stat = |percentile: LatencyPercentile| {
+            stats.get(percentile.stat_index()).map(|stat| *stat as u64)
+        };
+        let percentiles = LatencyPercentiles {
+            p50: stat(LatencyPercentile::P50)?,
+            p95: stat(LatencyPercentile::P95)?,
+            p99: stat(LatencyPercentile::P99)?,
+        };
+        Some((region, percentiles))
+    }
+
+    /// Extracts from a line of the `Dat` file, the region's name and all the
+    /// stats in it.
+    fn stats(line: String) -> (Region, Vec<f64>) {
+        let mut parts = line.split(':');
+
+        // stats are in the first entry
+        let stats: Vec<_> = parts
+            .next()
+            .unwrap()
+            .split('/')
+            .map(|stat| f64::from_str(stat).unwrap())
+            .collect();
 
         // region is the last entry
-        let region = iter.last().unwrap();
+        let region = parts.last().unwrap();
         // convert it to Region
         let region = Region::new(region);
 
         // return both
-        (region, latency)
+        (region, stats)
     }
 
     /// Gets the list of all `Dat`'s present in `LAT_DIR`.
@@ -150,6 +226,35 @@ mod tests {
         expected.insert(Region::new("asia-southeast1"), 289);
         expected.insert(Region::new("asia-south1"), 352);
 
-        assert_eq!(dat.latencies(), expected);
+        assert_eq!(dat.latencies(LatencyPercentile::Avg), expected);
+    }
+
+    #[test]
+    fn latency_percentiles() {
+        let region = Region::new("eu-west-1");
+
+        // lines with percentiles
+        let line = "170.214/175.729/180.045/2.742/174.1/178.9/179.8:eu-west-1";
+        let latency = |percentile| Dat::latency(line.to_string(), percentile);
+        assert_eq!(latency(LatencyPercentile::Avg), (region.clone(), 175));
+        assert_eq!(latency(LatencyPercentile::P50), (region.clone(), 174));
+        assert_eq!(latency(LatencyPercentile::P95), (region.clone(), 178));
+        assert_eq!(latency(LatencyPercentile::P99), (region.clone(), 179));
+        let percentiles = LatencyPercentiles {
+            p50: 174,
+            p95: 178,
+            p99: 179,
+        };
+        assert_eq!(
+            Dat::percentiles_from_line(line.to_string()),
+            Some((region.clone(), percentiles))
+        );
+
+        // lines without percentiles fall back to the average
+        let line = "170.214/175.729/180.045/2.742:eu-west-1";
+        let latency = |percentile| Dat::latency(line.to_string(), percentile);
+        assert_eq!(latency(LatencyPercentile::Avg), (region.clone(), 175));
+        assert_eq!(latency(LatencyPercentile::P99), (region, 175));
+        assert_eq!(Dat::percentiles_from_line(line.to_string()), None);
     }
 }
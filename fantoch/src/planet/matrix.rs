@@ -0,0 +1,170 @@
+use crate::planet::{LatencyPercentiles, Planet, Region};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+
+/// Latency from region `from` to region `to`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistanceMatrixEntry {
+    pub from: Region,
+    pub to: Region,
+    pub latency: u64,
+    /// RTT percentiles (only if the `Planet` has them for this pair)
+    pub percentiles: Option<LatencyPercentiles>,
+}
+
+/// Distance matrix between a set of regions. Besides being serializable (e.g.
+/// to JSON), it can be rendered as CSV or as a markdown table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistanceMatrix {
+    pub regions: Vec<Region>,
+    /// one entry per pair of regions, ordered by `from` and then by `to` (in
+    /// the order of `regions`)
+    pub entries: Vec<DistanceMatrixEntry>,
+}
+
+impl DistanceMatrix {
+    /// Creates the distance matrix between `regions` in `planet`.
+    ///
+    /// # Panics
+    /// Panics if the latency between some pair of regions is unknown.
+    pub fn new(planet: &Planet, regions: Vec<Region>) -> Self {
+        let mut entries = Vec::with_capacity(regions.len() * regions.len());
+        for from in regions.iter() {
+            for to in regions.iter() {
+                let latency =
+                    planet.ping_latency(from, to).unwrap_or_else(|| {
+                        panic!("latency from {:?} to {:?} is unknown", from, to)
+                    });
+                let percentiles = planet.ping_latency_percentiles(from, to);
+                entries.push(DistanceMatrixEntry {
+                    from: from.clone(),
+                    to: to.clone(),
+                    latency,
+                    percentiles,
+                });
+            }
+        }
+        Self { regions, entries }
+    }
+
+    /// Returns true if the percentiles are known for all pairs of regions.
+    pub fn has_percentiles(&self) -> bool {
+        self.entries.iter().all(|entry| entry.percentiles.is_some())
+    }
+
+    /// Renders the matrix as CSV, with one row per pair of regions. Percentile
+    /// columns are only included if the percentiles are known for all pairs.
+    pub fn to_csv(&self) -> Result<String, fmt::Error> {
+        let has_percentiles = self.has_percentiles();
+        let mut output = String::new();
+
+        // header
+        write!(&mut output, "from,to,latency")?;
+        if has_percentiles {
+            write!(&mut output, ",p50,p95,p99")?;
+        }
+        writeln!(&mut output)?;
+
+        for entry in self.entries.iter() {
+            write!(
+                &mut output,
+                "{:?},{:?},{}",
+                entry.from, entry.to, entry.latency
+            )?;
+            if has_percentiles {
+                let percentiles = entry.percentiles.unwrap();
+                write!(
+                    &mut output,
+                    ",{},{},{}",
+                    percentiles.p50, percentiles.p95, percentiles.p99
+                )?;
+            }
+            writeln!(&mut output)?;
+        }
+
+        Ok(output)
+    }
+
+    /// Renders the matrix as a markdown table with the latency between each
+    /// pair of regions.
+    pub fn to_markdown(&self) -> Result<String, fmt::Error> {
+        let mut output = String::new();
+
+        // start header
+        write!(&mut output, "| |")?;
+        for r in self.regions.iter() {
+            write!(&mut output, " {:?} |", r)?;
+        }
+        writeln!(&mut output)?;
+
+        // end header
+        write!(&mut output, "|:---:|")?;
+        for _ in self.regions.iter() {
+            write!(&mut output, ":---:|")?;
+        }
+        writeln!(&mut output)?;
+
+        // for each region a (entries are ordered by `from`)
+        let rows = self.entries.chunks(self.regions.len().max(1));
+        for (a, row) in self.regions.iter().zip(rows) {
+            write!(&mut output, "| __{:?}__ |", a)?;
+
+            // latency from a to every other region b
+            for entry in row {
+                write!(&mut output, " {} |", entry.latency)?;
+            }
+            writeln!(&mut output)?;
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashMap;
+
+    #[test]
+    fn distance_matrix() {
+        let (regions, planet) = Planet::equidistant(10, 2);
+        let matrix = planet.to_distance_matrix(regions);
+        assert!(!matrix.has_percentiles());
+        assert_eq!(
+            matrix.to_csv().unwrap(),
+            "from,to,latency\nr_0,r_0,0\nr_0,r_1,10\nr_1,r_0,10\nr_1,r_1,0\n"
+        );
+        assert_eq!(
+            matrix.to_markdown().unwrap(),
+            "| | r_0 | r_1 |\n|:---:|:---:|:---:|\n| __r_0__ | 0 | 10 |\n| __r_1__ | 10 | 0 |\n"
+        );
+    }
+
+    #[test]
+    fn distance_matrix_percentiles() {
+        // planet from a `Dat` file with percentiles
+        let region = Region::new("eu-west-1");
+        let percentiles = LatencyPercentiles {
+            p50: 174,
+            p95: 178,
+            p99: 179,
+        };
+        let mut latencies = HashMap::new();
+        latencies.insert(region.clone(), 175);
+        let mut planet = Planet::from_latencies(
+            vec![(region.clone(), latencies)].into_iter().collect(),
+        );
+        let mut region_percentiles = HashMap::new();
+        region_percentiles.insert(region.clone(), percentiles);
+        planet.percentiles = vec![(region.clone(), region_percentiles)]
+            .into_iter()
+            .collect();
+
+        let matrix = planet.to_distance_matrix(vec![region]);
+        assert!(matrix.has_percentiles());
+        assert_eq!(
+            matrix.to_csv().unwrap(),
+            "from,to,latency,p50,p95,p99\neu-west-1,eu-west-1,175,174,178,179\n"
+        );
+    }
+}
@@ -4,13 +4,25 @@ mod dat;
 // This module contains the definition of `Region`.
 pub mod region;
 
+// This module contains the definition of `RegionAliases`.
+pub mod alias;
+
+// This module contains the definition of `DistanceMatrix`.
+pub mod matrix;
+
+// This module contains the validation (and cleaning) of latency datasets.
+pub mod validation;
+
 // Re-exports.
+pub use alias::RegionAliases;
+pub use matrix::{DistanceMatrix, DistanceMatrixEntry};
 pub use region::Region;
+pub use validation::{Asymmetry, TriangleViolation, ValidationReport};
 
 use crate::planet::dat::Dat;
 use crate::HashMap;
 use serde::{Deserialize, Serialize};
-use std::fmt::{self, Write};
+use std::fmt;
 
 // directory that contains all dat files for GCP
 const GCP_LAT_DIR: &str = "../latency_gcp/";
@@ -18,6 +30,43 @@ const GCP_LAT_DIR: &str = "../latency_gcp/";
 // assume that intra region latency is 0
 const INTRA_REGION_LATENCY: u64 = 0;
 
+/// Which RTT statistic of the `Dat` files should be used as the latency between
+/// two regions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LatencyPercentile {
+    Avg,
+    P50,
+    P95,
+    P99,
+}
+
+impl LatencyPercentile {
+    /// Position of this statistic in the "min/avg/max/mdev/p50/p95/p99" entry
+    /// of a `Dat` file.
+    fn stat_index(&self) -> usize {
+        match self {
+            Self::Avg => 1,
+            Self::P50 => 4,
+            Self::P95 => 5,
+            Self::P99 => 6,
+        }
+    }
+}
+
+impl Default for LatencyPercentile {
+    fn default() -> Self {
+        Self::Avg
+    }
+}
+
+/// RTT percentiles between two regions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Planet {
     /// mapping from region A to a mapping from region B to the latency between
@@ -25,6 +74,13 @@ pub struct Planet {
     latencies: HashMap<Region, HashMap<Region, u64>>,
     /// mapping from each region to the regions sorted by distance
     sorted: HashMap<Region, Vec<(u64, Region)>>,
+    /// mapping from region aliases to the regions in `latencies`
+    #[serde(default)]
+    aliases: RegionAliases,
+    /// mapping from region A to a mapping from region B to the RTT
+    /// percentiles between A and B (only if the `Dat` files carry them)
+    #[serde(default)]
+    percentiles: HashMap<Region, HashMap<Region, LatencyPercentiles>>,
 }
 
 impl Planet {
@@ -34,14 +90,31 @@ impl Planet {
         Self::from(GCP_LAT_DIR)
     }
 
-    /// Creates a new `Planet` instance.
+    /// Creates a new `Planet` instance using average latencies.
     pub fn from(lat_dir: &str) -> Self {
+        Self::from_percentile(lat_dir, LatencyPercentile::Avg)
+    }
+
+    /// Creates a new `Planet` instance using the latencies at the percentile
+    /// provided. `Dat` files without percentiles fall back to averages.
+    pub fn from_percentile(
+        lat_dir: &str,
+        percentile: LatencyPercentile,
+    ) -> Self {
+        let dats = Dat::all_dats(lat_dir);
         // create latencies
-        let latencies: HashMap<_, _> = Dat::all_dats(lat_dir)
+        let latencies: HashMap<_, _> = dats
             .iter()
-            .map(|dat| (dat.region(), dat.latencies()))
+            .map(|dat| (dat.region(), dat.latencies(percentile)))
             .collect();
-        Self::from_latencies(latencies)
+        // create percentiles
+        let percentiles = dats
+            .iter()
+            .map(|dat| (dat.region(), dat.percentiles()))
+            .collect();
+        let mut planet = Self::from_latencies(latencies);
+        planet.percentiles = percentiles;
+        planet
     }
 
     /// Creates a new `Planet` instance from the latencies provided.
@@ -50,7 +123,14 @@ impl Planet {
     ) -> Self {
         // create sorted and and planet
         let sorted = Self::sort_by_distance(latencies.clone());
-        Planet { latencies, sorted }
+        let aliases = RegionAliases::new();
+        let percentiles = HashMap::new();
+        Planet {
+            latencies,
+            sorted,
+            aliases,
+            percentiles,
+        }
     }
 
     /// Creates a equidistant `Planet`.
@@ -94,28 +174,72 @@ impl Planet {
         let sorted = Self::sort_by_distance(latencies.clone());
 
         // create single-region planet
-        let planet = Planet { latencies, sorted };
+        let aliases = RegionAliases::new();
+        let percentiles = HashMap::new();
+        let planet = Planet {
+            latencies,
+            sorted,
+            aliases,
+            percentiles,
+        };
         (regions, planet)
     }
 
+    /// Sets the region aliases of this `Planet`. This allows e.g. a
+    /// deployment in GCP (or in some baremetal sites) to use the latencies
+    /// between AWS regions.
+    pub fn with_aliases(mut self, aliases: RegionAliases) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Makes `alias` an alias of `region`.
+    pub fn add_alias(&mut self, alias: Region, region: Region) {
+        self.aliases.add(alias, region);
+    }
+
+    /// Returns the canonical name of `region`, i.e. the name used for it in
+    /// the latency matrix.
+    pub fn canonical(&self, region: &Region) -> Region {
+        self.aliases.canonical(region)
+    }
+
     /// Retrieves a list with all regions.
     pub fn regions(&self) -> Vec<Region> {
         self.latencies.keys().cloned().collect()
     }
 
     /// Retrieves the distance between the two regions passed as argument.
+    /// Region aliases are resolved to their canonical region.
     pub fn ping_latency(&self, from: &Region, to: &Region) -> Option<u64> {
         // get from's entries
-        let entries = self.latencies.get(from)?;
+        let entries = self.latencies.get(&self.canonical(from))?;
 
         // get to's entry in from's entries
-        entries.get(to).cloned()
+        entries.get(&self.canonical(to)).cloned()
+    }
+
+    /// Retrieves the RTT percentiles between the two regions passed as
+    /// argument, if the `Dat` files used to create this `Planet` carry them.
+    /// Region aliases are resolved to their canonical region.
+    pub fn ping_latency_percentiles(
+        &self,
+        from: &Region,
+        to: &Region,
+    ) -> Option<LatencyPercentiles> {
+        // get from's entries
+        let entries = self.percentiles.get(&self.canonical(from))?;
+
+        // get to's entry in from's entries
+        entries.get(&self.canonical(to)).cloned()
     }
 
     /// Returns a list of `Region`s sorted by the distance to the `Region`
     /// passed as argument. The distance to each region is also returned.
+    /// Region aliases are resolved to their canonical region, and the regions
+    /// returned are always canonical.
     pub fn sorted(&self, from: &Region) -> Option<&Vec<(u64, Region)>> {
-        self.sorted.get(from)
+        self.sorted.get(&self.canonical(from))
     }
 
     /// Returns a mapping from region to regions sorted by distance (ASC).
@@ -141,39 +265,21 @@ impl Planet {
 }
 
 impl Planet {
+    /// Returns the distance matrix between the regions passed as argument.
+    ///
+    /// # Panics
+    /// Panics if the latency between some pair of regions is unknown.
+    pub fn to_distance_matrix(&self, regions: Vec<Region>) -> DistanceMatrix {
+        DistanceMatrix::new(self, regions)
+    }
+
+    /// Returns the distance matrix between the regions passed as argument as a
+    /// markdown table.
     pub fn distance_matrix(
         &self,
         regions: Vec<Region>,
     ) -> Result<String, fmt::Error> {
-        let mut output = String::new();
-
-        // start header
-        write!(&mut output, "| |")?;
-        for r in regions.iter() {
-            write!(&mut output, " {:?} |", r)?;
-        }
-        writeln!(&mut output)?;
-
-        // end header
-        write!(&mut output, "|:---:|")?;
-        for _ in regions.iter() {
-            write!(&mut output, ":---:|")?;
-        }
-        writeln!(&mut output)?;
-
-        // for each region a
-        for a in regions.iter() {
-            write!(&mut output, "| __{:?}__ |", a)?;
-
-            // compute latency from a to every other region b
-            for b in regions.iter() {
-                let lat = self.ping_latency(a, b).unwrap();
-                write!(&mut output, " {} |", lat)?;
-            }
-            writeln!(&mut output)?;
-        }
-
-        Ok(output)
+        self.to_distance_matrix(regions).to_markdown()
     }
 }
 
@@ -253,6 +359,29 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn aliases() {
+        // GCP planet where AWS region names can also be used
+        let planet = Planet::new().with_aliases(RegionAliases::aws_to_gcp());
+
+        // regions
+        let eu_w3 = Region::new("europe-west3");
+        let us_e4 = Region::new("us-east4");
+        let eu_c1 = Region::new("eu-central-1");
+        let us_e1 = Region::new("us-east-1");
+
+        assert_eq!(planet.canonical(&eu_c1), eu_w3);
+        assert_eq!(
+            planet.ping_latency(&eu_c1, &us_e1),
+            planet.ping_latency(&eu_w3, &us_e4)
+        );
+        assert_eq!(planet.sorted(&eu_c1), planet.sorted(&eu_w3));
+
+        // regions without an alias are unknown
+        let eu_w1 = Region::new("eu-west-1");
+        assert_eq!(planet.ping_latency(&eu_w1, &us_e1), None);
+    }
+
     #[test]
     fn equidistant() {
         let planet_distance = 10;
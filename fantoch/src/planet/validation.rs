@@ -0,0 +1,283 @@
+use crate::planet::{Planet, Region};
+use crate::HashMap;
+
+/// Pair of regions whose latencies in each direction differ by more than the
+/// tolerance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asymmetry {
+    pub a: Region,
+    pub b: Region,
+    pub a_to_b: u64,
+    pub b_to_a: u64,
+}
+
+/// Pair of regions whose latency is higher than the latency of going through a
+/// third region, i.e. `from -> via -> to` is faster than `from -> to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriangleViolation {
+    pub from: Region,
+    pub to: Region,
+    pub via: Region,
+    pub direct: u64,
+    pub indirect: u64,
+}
+
+/// Problems found in a latency dataset by `Planet::validate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// pairs of regions `(from, to)` whose latency is unknown
+    pub missing: Vec<(Region, Region)>,
+    pub asymmetries: Vec<Asymmetry>,
+    /// triangle-inequality violations are reported but not considered errors,
+    /// since routing between regions doesn't always follow the shortest path
+    pub triangle_violations: Vec<TriangleViolation>,
+}
+
+impl ValidationReport {
+    /// Returns true if no pair is missing and all pairs are symmetric (within
+    /// the tolerance used).
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.asymmetries.is_empty()
+    }
+}
+
+impl Planet {
+    /// Validates the latency dataset of this `Planet`, reporting:
+    /// - the pairs of regions whose latency is missing
+    /// - the pairs of regions whose latencies in each direction differ by more
+    ///   than `tolerance` (as a fraction of the highest of the two)
+    /// - the triangle-inequality violations
+    ///
+    /// Findings are sorted by region name, so that reports of different
+    /// snapshots can be compared.
+    pub fn validate(&self, tolerance: f64) -> ValidationReport {
+        assert!(tolerance >= 0.0, "tolerance should be positive");
+        let regions = self.sorted_regions();
+        let mut report = ValidationReport::default();
+
+        for (i, a) in regions.iter().enumerate() {
+            for b in regions.iter() {
+                if self.latency(a, b).is_none() {
+                    report.missing.push((a.clone(), b.clone()));
+                }
+            }
+
+            // check each pair of (different) regions only once
+            for b in regions.iter().skip(i + 1) {
+                if let (Some(a_to_b), Some(b_to_a)) =
+                    (self.latency(a, b), self.latency(b, a))
+                {
+                    if !Self::symmetric(a_to_b, b_to_a, tolerance) {
+                        report.asymmetries.push(Asymmetry {
+                            a: a.clone(),
+                            b: b.clone(),
+                            a_to_b,
+                            b_to_a,
+                        });
+                    }
+                }
+            }
+        }
+
+        for from in regions.iter() {
+            for to in regions.iter().filter(|to| *to != from) {
+                let direct = if let Some(direct) = self.latency(from, to) {
+                    direct
+                } else {
+                    continue;
+                };
+                for via in
+                    regions.iter().filter(|via| ![from, to].contains(via))
+                {
+                    let indirect = self
+                        .latency(from, via)
+                        .zip(self.latency(via, to))
+                        .map(|(first, second)| first + second);
+                    match indirect {
+                        Some(indirect) if indirect < direct => {
+                            report.triangle_violations.push(
+                                TriangleViolation {
+                                    from: from.clone(),
+                                    to: to.clone(),
+                                    via: via.clone(),
+                                    direct,
+                                    indirect,
+                                },
+                            );
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+        report
+    }
+
+    /// Returns a cleaned version of this `Planet`, where:
+    /// - missing latencies are filled with the latency in the opposite
+    ///   direction (if known), or with 0 within the same region
+    /// - pairs of regions that are not symmetric within `tolerance` (see
+    ///   `Planet::validate`) get the lowest of the two latencies in both
+    ///   directions, since collection artifacts (e.g. a congested probe) only
+    ///   make latencies higher
+    ///
+    /// Triangle-inequality violations are left untouched. RTT percentiles are
+    /// dropped, as they may no longer match the cleaned latencies, while
+    /// region aliases are kept.
+    pub fn cleaned(&self, tolerance: f64) -> Self {
+        assert!(tolerance >= 0.0, "tolerance should be positive");
+        let regions = self.sorted_regions();
+        let mut latencies: HashMap<Region, HashMap<Region, u64>> =
+            HashMap::new();
+        for a in regions.iter() {
+            for b in regions.iter() {
+                let latency = match (self.latency(a, b), self.latency(b, a)) {
+                    (Some(a_to_b), Some(b_to_a)) => {
+                        if Self::symmetric(a_to_b, b_to_a, tolerance) {
+                            a_to_b
+                        } else {
+                            std::cmp::min(a_to_b, b_to_a)
+                        }
+                    }
+                    (Some(latency), None) | (None, Some(latency)) => latency,
+                    (None, None) if a == b => super::INTRA_REGION_LATENCY,
+                    (None, None) => continue,
+                };
+                latencies
+                    .entry(a.clone())
+                    .or_default()
+                    .insert(b.clone(), latency);
+            }
+        }
+        Self::from_latencies(latencies).with_aliases(self.aliases.clone())
+    }
+
+    // Returns all regions (that have latencies from them), sorted by name.
+    fn sorted_regions(&self) -> Vec<Region> {
+        let mut regions = self.regions();
+        regions.sort();
+        regions
+    }
+
+    // Returns the latency between two (canonical) regions.
+    fn latency(&self, from: &Region, to: &Region) -> Option<u64> {
+        self.latencies.get(from)?.get(to).cloned()
+    }
+
+    fn symmetric(a_to_b: u64, b_to_a: u64, tolerance: f64) -> bool {
+        let diff = (a_to_b as f64 - b_to_a as f64).abs();
+        diff <= tolerance * std::cmp::max(a_to_b, b_to_a) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planet_from(latencies: Vec<(&str, &str, u64)>) -> Planet {
+        let mut all: HashMap<Region, HashMap<Region, u64>> = HashMap::new();
+        for (from, to, latency) in latencies {
+            all.entry(Region::new(from))
+                .or_default()
+                .insert(Region::new(to), latency);
+        }
+        Planet::from_latencies(all)
+    }
+
+    #[test]
+    fn validate() {
+        let (a, b, c) = (Region::new("a"), Region::new("b"), Region::new("c"));
+        let planet = planet_from(vec![
+            ("a", "a", 0),
+            ("a", "b", 100),
+            ("a", "c", 10),
+            ("b", "a", 105),
+            ("b", "b", 0),
+            ("b", "c", 20),
+            ("c", "a", 10),
+            ("c", "b", 40),
+        ]);
+
+        let report = planet.validate(0.1);
+        assert!(!report.is_valid());
+        // latency from c to c is missing
+        assert_eq!(report.missing, vec![(c.clone(), c.clone())]);
+        // b and c are not symmetric, but a and b are (within 10%)
+        assert_eq!(
+            report.asymmetries,
+            vec![Asymmetry {
+                a: b.clone(),
+                b: c.clone(),
+                a_to_b: 20,
+                b_to_a: 40,
+            }]
+        );
+        // going through c is faster than going directly between a and b
+        let via_c =
+            |from: &Region, to: &Region, direct, indirect| TriangleViolation {
+                from: from.clone(),
+                to: to.clone(),
+                via: c.clone(),
+                direct,
+                indirect,
+            };
+        assert_eq!(
+            report.triangle_violations,
+            vec![via_c(&a, &b, 100, 50), via_c(&b, &a, 105, 30)]
+        );
+
+        // with a lower tolerance, a and b are no longer symmetric
+        let report = planet.validate(0.01);
+        assert_eq!(report.asymmetries.len(), 2);
+    }
+
+    #[test]
+    fn cleaned() {
+        let (a, b, c) = (Region::new("a"), Region::new("b"), Region::new("c"));
+        let planet = planet_from(vec![
+            ("a", "a", 0),
+            ("a", "b", 100),
+            ("a", "c", 10),
+            ("b", "a", 105),
+            ("b", "b", 0),
+            ("b", "c", 20),
+            ("c", "a", 10),
+            ("c", "b", 40),
+        ]);
+
+        let cleaned = planet.cleaned(0.1);
+        // missing latencies within the same region are filled with 0
+        assert_eq!(cleaned.ping_latency(&c, &c), Some(0));
+        // pairs within the tolerance are left untouched
+        assert_eq!(cleaned.ping_latency(&a, &b), Some(100));
+        assert_eq!(cleaned.ping_latency(&b, &a), Some(105));
+        // while the others get the lowest latency
+        assert_eq!(cleaned.ping_latency(&b, &c), Some(20));
+        assert_eq!(cleaned.ping_latency(&c, &b), Some(20));
+
+        // the cleaned dataset is valid, but triangle-inequality violations
+        // are kept
+        let report = cleaned.validate(0.1);
+        assert!(report.is_valid());
+        assert_eq!(report.triangle_violations.len(), 2);
+        assert_eq!(cleaned.sorted(&c).unwrap()[0], (0, c.clone()));
+
+        // missing latencies are filled with the opposite direction
+        let planet =
+            planet_from(vec![("a", "a", 0), ("a", "b", 50), ("b", "b", 0)]);
+        let cleaned = planet.cleaned(0.1);
+        assert!(cleaned.validate(0.1).missing.is_empty());
+        assert_eq!(cleaned.ping_latency(&b, &a), Some(50));
+    }
+
+    #[test]
+    fn aws_snapshots() {
+        // snapshots have no missing pairs, and cleaning them makes them valid
+        for snapshot in vec!["2020_06_05", "2021_02_13"] {
+            let planet = Planet::from(&format!("../latency_aws/{}", snapshot));
+            let report = planet.validate(0.1);
+            assert!(report.missing.is_empty(), "{}: {:?}", snapshot, report);
+            assert!(planet.cleaned(0.1).validate(0.1).is_valid());
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::config::Config;
 use crate::id::{Dot, DotGen, ProcessId, ShardId};
-use crate::protocol::{ProtocolMetrics, ProtocolMetricsKind};
+use crate::protocol::{ProtocolMetrics, ProtocolMetricsKind, Violation};
+use crate::time::SysTime;
 use crate::trace;
 use crate::{HashMap, HashSet};
 use std::iter::FromIterator;
@@ -22,6 +23,8 @@ pub struct BaseProcess {
     write_quorum_size: usize,
     dot_gen: DotGen,
     metrics: ProtocolMetrics,
+    // violations recorded but not yet reported to the run layer
+    violations: Vec<Violation>,
 }
 
 impl BaseProcess {
@@ -51,6 +54,7 @@ impl BaseProcess {
             write_quorum_size,
             dot_gen: DotGen::new(process_id),
             metrics: ProtocolMetrics::new(),
+            violations: Vec::new(),
         }
     }
 
@@ -181,14 +185,27 @@ impl BaseProcess {
         &self.metrics
     }
 
-    // Increment fast path count.
+    // Increment fast path count (and committed count).
     pub fn fast_path(&mut self) {
         self.metrics.aggregate(ProtocolMetricsKind::FastPath, 1);
+        self.committed();
     }
 
-    // Increment slow path count.
+    // Increment slow path count (and committed count).
     pub fn slow_path(&mut self) {
         self.metrics.aggregate(ProtocolMetricsKind::SlowPath, 1);
+        self.committed();
+    }
+
+    // Increment committed count.
+    pub fn committed(&mut self) {
+        self.metrics.aggregate(ProtocolMetricsKind::Committed, 1);
+    }
+
+    // Increment stale messages count.
+    pub fn stale_message(&mut self) {
+        self.metrics
+            .aggregate(ProtocolMetricsKind::StaleMessages, 1);
     }
 
     // Accumulate more stable commands.
@@ -201,6 +218,37 @@ impl BaseProcess {
     pub fn collect_metric(&mut self, kind: ProtocolMetricsKind, value: u64) {
         self.metrics.collect(kind, value);
     }
+
+    // Aggregate a new metric.
+    pub fn aggregate_metric(&mut self, kind: ProtocolMetricsKind, value: u64) {
+        self.metrics.aggregate(kind, value);
+    }
+
+    // Collect a new sample of the stability lag (i.e. the number of commands
+    // committed locally but not yet known to be stable).
+    pub fn stability_lag(&mut self, lag: u64, time: &dyn SysTime) {
+        self.metrics.collect(ProtocolMetricsKind::StabilityLag, lag);
+        let second = time.millis() / 1000;
+        self.metrics
+            .collect(ProtocolMetricsKind::StabilityLagAt(second), lag);
+    }
+
+    // Records a violation of one of the protocol's invariants, along with the
+    // state needed to diagnose it (see `Violation`).
+    pub fn violation<R, S>(&mut self, reason: R, state: S)
+    where
+        R: Into<String>,
+        S: Into<String>,
+    {
+        let violation = Violation::new(self.process_id, reason, state);
+        self.metrics.aggregate(ProtocolMetricsKind::Violations, 1);
+        self.violations.push(violation);
+    }
+
+    // Returns the next violation to be reported (if any).
+    pub fn to_violations(&mut self) -> Option<Violation> {
+        self.violations.pop()
+    }
 }
 
 #[cfg(test)]
@@ -3,12 +3,13 @@ use crate::config::Config;
 use crate::executor::{BasicExecutionInfo, BasicExecutor, Executor};
 use crate::id::{Dot, ProcessId, ShardId};
 use crate::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    AckCoalescer, Action, BaseProcess, Info, MessageIndex, MessagePriority,
+    Protocol, ProtocolMetrics, SequentialCommandsInfo, StateSizes,
+    VClockGCTrack,
 };
 use crate::singleton;
 use crate::time::SysTime;
-use crate::trace;
+use crate::{flow, trace};
 use crate::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -21,9 +22,11 @@ pub struct Basic {
     bp: BaseProcess,
     cmds: SequentialCommandsInfo<BasicInfo>,
     gc_track: VClockGCTrack,
+    acks: AckCoalescer<Message>,
     to_processes: Vec<Action<Self>>,
     to_executors: Vec<ExecutionInfo>,
     buffered_mcommits: HashSet<Dot>,
+    buffered_mcommit_noops: HashSet<Dot>,
 }
 
 impl Protocol for Basic {
@@ -58,27 +61,36 @@ impl Protocol for Basic {
             write_quorum_size,
         );
         let gc_track = VClockGCTrack::new(process_id, shard_id, config.n());
+        let acks = AckCoalescer::new(process_id, &config);
         let to_processes = Vec::new();
         let to_executors = Vec::new();
         let buffered_mcommits = HashSet::new();
+        let buffered_mcommit_noops = HashSet::new();
 
         // create `Basic`
         let protocol = Self {
             bp,
             cmds,
             gc_track,
+            acks,
             to_processes,
             to_executors,
             buffered_mcommits,
+            buffered_mcommit_noops,
         };
 
         // create periodic events
-        let events = if let Some(interval) = config.gc_interval() {
+        let mut events = if let Some(interval) = config.gc_interval() {
             vec![(PeriodicEvent::GarbageCollection, interval)]
         } else {
             vec![]
         };
 
+        // maybe create flush acks periodic event
+        if let Some(interval) = config.ack_coalescing_interval() {
+            events.push((PeriodicEvent::FlushAcks, interval));
+        }
+
         // return both
         (protocol, events)
     }
@@ -121,7 +133,14 @@ impl Protocol for Basic {
                 self.handle_mstore(from, dot, cmd, quorum)
             }
             Message::MStoreAck { dot } => self.handle_mstoreack(from, dot),
+            Message::MStoreAckBatch { acks } => {
+                for ack in acks {
+                    self.handle(from, _from_shard_id, ack, _time);
+                }
+            }
             Message::MCommit { dot } => self.handle_mcommit(dot),
+            Message::MCommitNoop { dot } => self.handle_mcommit_noop(dot),
+            Message::MForwardCancel { dot } => self.handle_cancel(dot, false),
             Message::MCommitDot { dot } => self.handle_mcommit_dot(from, dot),
             Message::MGarbageCollection { committed } => {
                 self.handle_mgc(from, committed)
@@ -131,18 +150,20 @@ impl Protocol for Basic {
     }
 
     /// Handles periodic local events.
-    fn handle_event(
-        &mut self,
-        event: Self::PeriodicEvent,
-        _time: &dyn SysTime,
-    ) {
+    fn handle_event(&mut self, event: Self::PeriodicEvent, time: &dyn SysTime) {
         match event {
             PeriodicEvent::GarbageCollection => {
-                self.handle_event_garbage_collection()
+                self.handle_event_garbage_collection(time)
             }
+            PeriodicEvent::FlushAcks => self.handle_event_flush_acks(time),
         }
     }
 
+    /// Cancels a command previously submitted by some client.
+    fn cancel(&mut self, dot: Dot, _time: &dyn SysTime) {
+        self.handle_cancel(dot, true);
+    }
+
     /// Returns a new action to be sent to other processes.
     fn to_processes(&mut self) -> Option<Action<Self>> {
         self.to_processes.pop()
@@ -161,9 +182,29 @@ impl Protocol for Basic {
         true
     }
 
+    fn cancellable() -> bool {
+        true
+    }
+
+    fn message_fingerprint() -> u64 {
+        super::message_fingerprints::BASIC
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
+
+    fn state_sizes(&self) -> StateSizes {
+        let mut sizes = StateSizes::new();
+        sizes.insert("dots_in_flight", self.cmds.hot_len());
+        sizes.insert("dots_committed", self.cmds.cold_len());
+        sizes.insert("buffered_mcommits", self.buffered_mcommits.len());
+        sizes.insert(
+            "buffered_mcommit_noops",
+            self.buffered_mcommit_noops.len(),
+        );
+        sizes
+    }
 }
 
 impl Basic {
@@ -184,6 +225,55 @@ impl Basic {
         })
     }
 
+    /// Handles a cancel operation by a client (if `target_shard`), or
+    /// forwarded by the shard where the client cancelled the command.
+    fn handle_cancel(&mut self, dot: Dot, target_shard: bool) {
+        trace!("p{}: Cancel({:?}, {})", self.id(), dot, target_shard);
+
+        // get cmd info; since the `MStore` sent to self is handled before
+        // any cancel, if there's no info, the command has already been
+        // committed (and removed) or garbage collected; in this case, do
+        // nothing (and don't re-create its info, as it would never be
+        // garbage collected)
+        let info = match self.cmds.find_mut(&dot) {
+            Some(info) => info,
+            None => return,
+        };
+
+        // do nothing if the command has already been committed
+        if info.committed {
+            return;
+        }
+        info.committed = true;
+
+        // if we're the target shard and the command touches other shards, the
+        // command is also cancelled there
+        if target_shard {
+            if let Some(cmd) = info.cmd.as_ref() {
+                let my_shard_id = self.bp.shard_id;
+                for shard_id in
+                    cmd.shards().filter(|shard_id| **shard_id != my_shard_id)
+                {
+                    let target = singleton![self.bp.closest_process(shard_id)];
+                    self.to_processes.push(Action::ToSend {
+                        target,
+                        msg: Message::MForwardCancel { dot },
+                    });
+                }
+            }
+        }
+
+        // create `MCommitNoop` and target
+        let mcommit_noop = Message::MCommitNoop { dot };
+        let target = self.bp.all();
+
+        // save new action
+        self.to_processes.push(Action::ToSend {
+            target,
+            msg: mcommit_noop,
+        });
+    }
+
     fn handle_mstore(
         &mut self,
         from: ProcessId,
@@ -208,15 +298,17 @@ impl Basic {
 
         // reply if we're part of the quorum
         if quorum.contains(&self.id()) {
-            // create `MStoreAck` and target
+            // create `MStoreAck` and maybe coalesce it with other acks
             let mstoreack = Message::MStoreAck { dot };
-            let target = singleton![from];
-
-            // save new action
-            self.to_processes.push(Action::ToSend {
-                target,
-                msg: mstoreack,
-            })
+            if let Some(mstoreack) = self.acks.coalesce(from, mstoreack) {
+                let target = singleton![from];
+
+                // save new action
+                self.to_processes.push(Action::ToSend {
+                    target,
+                    msg: mstoreack,
+                })
+            }
         }
 
         // check if there's a buffered commit notification; if yes, handle
@@ -224,6 +316,11 @@ impl Basic {
         if self.buffered_mcommits.remove(&dot) {
             self.handle_mcommit(dot);
         }
+
+        // same as above, but for commit notifications of noops
+        if self.buffered_mcommit_noops.remove(&dot) {
+            self.handle_mcommit_noop(dot);
+        }
     }
 
     fn handle_mstoreack(&mut self, from: ProcessId, dot: Dot) {
@@ -232,11 +329,19 @@ impl Basic {
         // get cmd info
         let info = self.cmds.get(dot);
 
+        // do nothing if the command has already been committed (which can
+        // happen if it was cancelled by the client)
+        if info.committed {
+            return;
+        }
+
         // update quorum clocks
         info.acks.insert(from);
 
         // check if we have all necessary replies
         if info.acks.len() == self.bp.config.basic_quorum_size() {
+            info.committed = true;
+            self.bp.committed();
             let mcommit = Message::MCommit { dot };
             let target = self.bp.all();
 
@@ -256,13 +361,21 @@ impl Basic {
 
         // check if we have received the initial `MStore`
         if let Some(cmd) = info.cmd.as_ref() {
+            flow!(process = self.bp.process_id, dot = ?dot, phase = "commit");
+
             // if so, create execution info:
             // - one entry per key being accessed will be created, which allows
             //   the basic executor to run in parallel
             let rifl = cmd.rifl();
+            let timestamp = cmd.timestamp();
             let execution_info =
                 cmd.iter(self.bp.shard_id).map(|(key, ops)| {
-                    BasicExecutionInfo::new(rifl, key.clone(), ops.clone())
+                    BasicExecutionInfo::new(
+                        rifl,
+                        key.clone(),
+                        ops.clone(),
+                        timestamp,
+                    )
                 });
             self.to_executors.extend(execution_info);
 
@@ -281,6 +394,35 @@ impl Basic {
         }
     }
 
+    fn handle_mcommit_noop(&mut self, dot: Dot) {
+        trace!("p{}: MCommitNoop({:?})", self.id(), dot);
+
+        // get cmd info
+        let info = self.cmds.get(dot);
+
+        // check if we have received the initial `MStore`; although the payload
+        // is not needed to commit a noop, waiting for it ensures that a late
+        // `MStore` doesn't recreate the command info after it's been removed
+        if info.cmd.is_some() {
+            flow!(process = self.bp.process_id, dot = ?dot, phase = "noop");
+
+            // the command is not executed, so there's nothing to be sent to
+            // executors
+            if self.gc_running() {
+                // notify self with the committed dot
+                self.to_processes.push(Action::ToForward {
+                    msg: Message::MCommitDot { dot },
+                });
+            } else {
+                // if we're not running gc, remove the dot info now
+                self.cmds.gc_single(dot);
+            }
+        } else {
+            // if not, buffer this `MCommitNoop` notification
+            self.buffered_mcommit_noops.insert(dot);
+        }
+    }
+
     fn handle_mcommit_dot(&mut self, from: ProcessId, dot: Dot) {
         trace!("p{}: MCommitDot({:?})", self.id(), dot);
         assert_eq!(from, self.bp.process_id);
@@ -316,11 +458,18 @@ impl Basic {
         self.bp.stable(stable_count);
     }
 
-    fn handle_event_garbage_collection(&mut self) {
+    fn handle_event_garbage_collection(&mut self, time: &dyn SysTime) {
         trace!("p{}: PeriodicEvent::GarbageCollection", self.id());
 
-        // retrieve the committed clock
-        let committed = self.gc_track.clock().frontier();
+        // sample the stability lag
+        let lag = self.gc_track.stability_lag();
+        self.bp.stability_lag(lag, time);
+
+        // retrieve the entries of the committed clock that changed since the
+        // last garbage collection; this message is sent even if no entry
+        // changed, as receiving it is what triggers the computation of new
+        // stable dots
+        let committed = self.gc_track.committed_delta();
 
         // save new action
         self.to_processes.push(Action::ToSend {
@@ -329,6 +478,23 @@ impl Basic {
         });
     }
 
+    fn handle_event_flush_acks(&mut self, _time: &dyn SysTime) {
+        trace!("p{}: PeriodicEvent::FlushAcks", self.id());
+
+        for (target, mut acks) in self.acks.flush() {
+            // only create a batch if there's more than one ack
+            let msg = if acks.len() == 1 {
+                acks.pop().expect("there should be one ack")
+            } else {
+                Message::MStoreAckBatch { acks }
+            };
+            self.to_processes.push(Action::ToSend {
+                target: singleton![target],
+                msg,
+            });
+        }
+    }
+
     fn gc_running(&self) -> bool {
         self.bp.config.gc_interval().is_some()
     }
@@ -340,6 +506,9 @@ impl Basic {
 struct BasicInfo {
     cmd: Option<Command>,
     acks: HashSet<ProcessId>,
+    // only used by the coordinator to track whether it has decided to commit
+    // the command (or a noop, if the command was cancelled)
+    committed: bool,
 }
 
 impl Info for BasicInfo {
@@ -355,6 +524,7 @@ impl Info for BasicInfo {
         Self {
             cmd: None,
             acks: HashSet::with_capacity(fast_quorum_size),
+            committed: false,
         }
     }
 }
@@ -370,9 +540,18 @@ pub enum Message {
     MStoreAck {
         dot: Dot,
     },
+    MStoreAckBatch {
+        acks: Vec<Message>,
+    },
     MCommit {
         dot: Dot,
     },
+    MCommitNoop {
+        dot: Dot,
+    },
+    MForwardCancel {
+        dot: Dot,
+    },
     MCommitDot {
         dot: Dot,
     },
@@ -393,7 +572,14 @@ impl MessageIndex for Message {
             // Protocol messages
             Self::MStore { dot, .. } => worker_dot_index_shift(&dot),
             Self::MStoreAck { dot, .. } => worker_dot_index_shift(&dot),
+            // all acks in a batch were coalesced by the same worker, and thus
+            // they're all handled by the same worker
+            Self::MStoreAckBatch { acks } => {
+                acks.first().and_then(MessageIndex::index)
+            }
             Self::MCommit { dot, .. } => worker_dot_index_shift(&dot),
+            Self::MCommitNoop { dot, .. } => worker_dot_index_shift(&dot),
+            Self::MForwardCancel { dot, .. } => worker_dot_index_shift(&dot),
             // GC messages
             Self::MCommitDot { .. } => worker_index_no_shift(GC_WORKER_INDEX),
             Self::MGarbageCollection { .. } => {
@@ -404,9 +590,18 @@ impl MessageIndex for Message {
     }
 }
 
+impl MessagePriority for Message {
+    fn high_priority(&self) -> bool {
+        // only `MStore` carries the command payload; all other messages are
+        // small control messages
+        !matches!(self, Self::MStore { .. })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodicEvent {
     GarbageCollection,
+    FlushAcks,
 }
 
 impl MessageIndex for PeriodicEvent {
@@ -414,6 +609,10 @@ impl MessageIndex for PeriodicEvent {
         use crate::load_balance::{worker_index_no_shift, GC_WORKER_INDEX};
         match self {
             Self::GarbageCollection => worker_index_no_shift(GC_WORKER_INDEX),
+            Self::FlushAcks => {
+                // should be sent to all workers
+                None
+            }
         }
     }
 }
@@ -506,6 +705,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate,
             pool_size,
+            pool_id: 0,
         };
         let commands_per_client = 10;
         let payload_size = 100;
@@ -615,6 +815,7 @@ mod tests {
             ready.pop().expect("there should an executor result");
         let cmd_result = pending
             .add_executor_result(executor_result)
+            .pop()
             .expect("there should be a command result");
 
         // handle the previous command result
@@ -631,4 +832,231 @@ mod tests {
         let check_msg = |msg: &Message| matches!(msg, Message::MStore {dot, ..} if dot == &Dot::new(process_id_1, 2));
         assert!(matches!(mstore, Action::ToSend {msg, ..} if check_msg(&msg)));
     }
+
+    #[test]
+    fn basic_message_priority() {
+        use crate::protocol::MessagePriority;
+
+        let dot = Dot::new(1, 1);
+        let cmd = Command::from(
+            crate::id::Rifl::new(1, 1),
+            vec![(String::from("A"), crate::kvs::KVOp::Get)],
+        );
+        let mstore = Message::MStore {
+            dot,
+            cmd,
+            quorum: HashSet::new(),
+        };
+        assert!(!mstore.high_priority());
+        assert!(Message::MStoreAck { dot }.high_priority());
+        assert!(Message::MCommit { dot }.high_priority());
+    }
+
+    #[test]
+    fn basic_cancel() {
+        // processes ids
+        let process_id_1 = 1;
+        let process_id_2 = 2;
+        let process_id_3 = 3;
+
+        // there's a single shard
+        let shard_id = 0;
+
+        // processes
+        let region = Region::new("europe-west2");
+        let processes = vec![
+            (process_id_1, shard_id, region.clone()),
+            (process_id_2, shard_id, region.clone()),
+            (process_id_3, shard_id, region.clone()),
+        ];
+
+        // planet and system time
+        let planet = Planet::new();
+        let time = SimTime::new();
+
+        // n and f
+        let n = 3;
+        let f = 1;
+        let config = Config::new(n, f);
+
+        // create basic 1 and discover processes
+        let (mut basic_1, _) = Basic::new(process_id_1, shard_id, config);
+        let sorted =
+            util::sort_processes_by_distance(&region, &planet, processes);
+        basic_1.discover(sorted);
+
+        // submit a command
+        let rifl = crate::id::Rifl::new(1, 1);
+        let cmd = Command::from(
+            rifl,
+            vec![(String::from("A"), crate::kvs::KVOp::Get)],
+        );
+        basic_1.submit(None, cmd, &time);
+        let mut actions: Vec<_> = basic_1.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        let mstore = match actions.pop().unwrap() {
+            Action::ToSend { msg, .. } => msg,
+            action => panic!("unexpected action: {:?}", action),
+        };
+        let dot = match &mstore {
+            Message::MStore { dot, .. } => *dot,
+            msg => panic!("unexpected message: {:?}", msg),
+        };
+
+        // cancelling a command not yet stored has no effect
+        basic_1.cancel(dot, &time);
+        assert_eq!(basic_1.to_processes_iter().count(), 0);
+        assert!(basic_1.cmds.find(&dot).is_none());
+
+        // handle the `MStore` sent to self (as the run layer does before
+        // handling any cancel) and ignore the `MStoreAck` sent back
+        basic_1.handle(process_id_1, shard_id, mstore, &time);
+        assert_eq!(basic_1.to_processes_iter().count(), 1);
+
+        // cancel the command: this should commit a noop
+        basic_1.cancel(dot, &time);
+        let mut actions: Vec<_> = basic_1.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        let check_msg = |msg: &Message| matches!(msg, Message::MCommitNoop { dot: d } if d == &dot);
+        assert!(
+            matches!(actions.pop().unwrap(), Action::ToSend { msg, target } if check_msg(&msg) && target.len() == n)
+        );
+
+        // cancelling again has no effect
+        basic_1.cancel(dot, &time);
+        assert_eq!(basic_1.to_processes_iter().count(), 0);
+
+        // acks received after the cancel don't lead to a commit
+        let msg = Message::MStoreAck { dot };
+        basic_1.handle(process_id_2, shard_id, msg.clone(), &time);
+        basic_1.handle(process_id_3, shard_id, msg, &time);
+        assert_eq!(basic_1.to_processes_iter().count(), 0);
+
+        // nothing is sent to executors, even after the noop is committed
+        basic_1.handle(
+            process_id_1,
+            shard_id,
+            Message::MCommitNoop { dot },
+            &time,
+        );
+        assert_eq!(basic_1.to_executors_iter().count(), 0);
+
+        // since gc is not running, the command info has been removed, and
+        // cancelling the command doesn't re-create it
+        assert!(basic_1.cmds.find(&dot).is_none());
+        basic_1.cancel(dot, &time);
+        assert_eq!(basic_1.to_processes_iter().count(), 0);
+        assert!(basic_1.cmds.find(&dot).is_none());
+    }
+
+    #[test]
+    fn basic_ack_coalescing() {
+        // processes ids
+        let process_id_1 = 1;
+        let process_id_2 = 2;
+        let process_id_3 = 3;
+
+        // there's a single shard
+        let shard_id = 0;
+
+        // processes
+        let region = Region::new("europe-west2");
+        let processes = vec![
+            (process_id_1, shard_id, region.clone()),
+            (process_id_2, shard_id, region.clone()),
+            (process_id_3, shard_id, region.clone()),
+        ];
+
+        // planet and system time
+        let planet = Planet::new();
+        let time = SimTime::new();
+
+        // n and f
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_ack_coalescing_interval(Duration::from_millis(1));
+
+        // create basic 1 and basic 2, and discover processes
+        let (mut basic_1, _) = Basic::new(process_id_1, shard_id, config);
+        let (mut basic_2, events) = Basic::new(process_id_2, shard_id, config);
+        assert!(events
+            .contains(&(PeriodicEvent::FlushAcks, Duration::from_millis(1))));
+        let sorted = util::sort_processes_by_distance(
+            &region,
+            &planet,
+            processes.clone(),
+        );
+        basic_1.discover(sorted);
+        let sorted =
+            util::sort_processes_by_distance(&region, &planet, processes);
+        basic_2.discover(sorted);
+
+        // submit two commands in basic 1
+        let mut mstores = Vec::new();
+        for seq in 1..=2 {
+            let rifl = crate::id::Rifl::new(1, seq);
+            let cmd = Command::from(
+                rifl,
+                vec![(String::from("A"), crate::kvs::KVOp::Get)],
+            );
+            basic_1.submit(None, cmd, &time);
+            match basic_1.to_processes_iter().next() {
+                Some(Action::ToSend { msg, .. }) => mstores.push(msg),
+                action => panic!("unexpected action: {:?}", action),
+            }
+        }
+
+        // the acks from basic 1 to itself are not coalesced
+        for mstore in mstores.clone() {
+            basic_1.handle(process_id_1, shard_id, mstore, &time);
+        }
+        let self_acks: Vec<_> = basic_1.to_processes_iter().collect();
+        assert_eq!(self_acks.len(), 2);
+
+        // make sure basic 2 is part of the quorum, and then check that its
+        // acks are coalesced
+        for mut mstore in mstores {
+            if let Message::MStore { quorum, .. } = &mut mstore {
+                quorum.insert(process_id_2);
+            }
+            basic_2.handle(process_id_1, shard_id, mstore, &time);
+        }
+        assert_eq!(basic_2.to_processes_iter().count(), 0);
+
+        // once the acks are flushed, there's a single batch with both acks
+        basic_2.handle_event(PeriodicEvent::FlushAcks, &time);
+        let mut actions: Vec<_> = basic_2.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        let batch = match actions.pop().unwrap() {
+            Action::ToSend {
+                target,
+                msg: msg @ Message::MStoreAckBatch { .. },
+            } => {
+                assert_eq!(target, singleton![process_id_1]);
+                msg
+            }
+            action => panic!("unexpected action: {:?}", action),
+        };
+
+        // flushing again has no effect
+        basic_2.handle_event(PeriodicEvent::FlushAcks, &time);
+        assert_eq!(basic_2.to_processes_iter().count(), 0);
+
+        // handling the acks leads to both commands being committed
+        for action in self_acks {
+            if let Action::ToSend { msg, .. } = action {
+                basic_1.handle(process_id_1, shard_id, msg, &time);
+            }
+        }
+        basic_1.handle(process_id_2, shard_id, batch, &time);
+        let check_msg = |msg: &Message| matches!(msg, Message::MCommit { .. });
+        let mcommits = basic_1
+            .to_processes_iter()
+            .filter(|action| {
+                matches!(action, Action::ToSend { msg, .. } if check_msg(msg))
+            })
+            .count();
+        assert_eq!(mcommits, 2);
+    }
 }
@@ -0,0 +1,75 @@
+use crate::config::Config;
+use crate::id::ProcessId;
+use crate::HashMap;
+
+/// Buffers the acks sent to each coordinator, so that all the acks buffered
+/// for the same coordinator can be sent in a single message when the
+/// `ack_coalescing_interval` periodic event fires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AckCoalescer<M> {
+    process_id: ProcessId,
+    enabled: bool,
+    pending: HashMap<ProcessId, Vec<M>>,
+}
+
+impl<M> AckCoalescer<M> {
+    /// Creates a new `AckCoalescer`. Acks are only buffered if
+    /// `ack_coalescing_interval` is set.
+    pub fn new(process_id: ProcessId, config: &Config) -> Self {
+        Self {
+            process_id,
+            enabled: config.ack_coalescing_interval().is_some(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Buffers an ack to be sent to `target`. If acks are not being coalesced
+    /// or `target` is this process (in which case the ack doesn't go through
+    /// the network), the ack is returned so that it's sent right away.
+    pub fn coalesce(&mut self, target: ProcessId, ack: M) -> Option<M> {
+        if self.enabled && target != self.process_id {
+            self.pending.entry(target).or_default().push(ack);
+            None
+        } else {
+            Some(ack)
+        }
+    }
+
+    /// Returns all the acks buffered, grouped by target.
+    pub fn flush(&mut self) -> impl Iterator<Item = (ProcessId, Vec<M>)> + '_ {
+        self.pending.drain()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn ack_coalescer_flow() {
+        let process_id = 1;
+        let mut config = Config::new(3, 1);
+
+        // if not enabled, acks are returned right away
+        let mut coalescer = AckCoalescer::new(process_id, &config);
+        assert_eq!(coalescer.coalesce(2, "a"), Some("a"));
+        assert_eq!(coalescer.flush().count(), 0);
+
+        // if enabled, only acks to self are returned right away
+        config.set_ack_coalescing_interval(Duration::from_millis(1));
+        let mut coalescer = AckCoalescer::new(process_id, &config);
+        assert_eq!(coalescer.coalesce(1, "a"), Some("a"));
+        assert_eq!(coalescer.coalesce(2, "b"), None);
+        assert_eq!(coalescer.coalesce(3, "c"), None);
+        assert_eq!(coalescer.coalesce(2, "d"), None);
+
+        // acks are grouped by target
+        let mut flushed: Vec<_> = coalescer.flush().collect();
+        flushed.sort();
+        assert_eq!(flushed, vec![(2, vec!["b", "d"]), (3, vec!["c"])]);
+
+        // nothing is left after a flush
+        assert_eq!(coalescer.flush().count(), 0);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::config::Config;
+use crate::id::ProcessId;
+use crate::protocol::{BaseProcess, ProtocolMetricsKind};
+use crate::time::SysTime;
+use crate::{HashMap, HashSet};
+use std::time::Duration;
+
+// number of heartbeats sent per `failure_detector_timeout`; this allows a few
+// heartbeats to be delayed before a process is suspected
+const HEARTBEATS_PER_TIMEOUT: u32 = 3;
+
+/// Heartbeat-based failure detector: a process is suspected once no heartbeat
+/// was received from it during `failure_detector_timeout`. Protocols should
+/// send a heartbeat to all processes (and check for new suspicions) every
+/// `FailureDetector::interval`, and report the heartbeats received with
+/// `FailureDetector::heartbeat`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureDetector {
+    // timeout in milliseconds (if the failure detector is enabled)
+    timeout: Option<u64>,
+    // time (in milliseconds) at which each process was last heard from
+    last_heard: HashMap<ProcessId, u64>,
+    suspected: HashSet<ProcessId>,
+}
+
+impl FailureDetector {
+    /// Creates a new `FailureDetector`. Processes are only suspected if
+    /// `failure_detector_timeout` is set.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            timeout: config
+                .failure_detector_timeout()
+                .map(|timeout| timeout.as_millis() as u64),
+            last_heard: HashMap::new(),
+            suspected: HashSet::new(),
+        }
+    }
+
+    /// Returns the interval between heartbeats, if the failure detector is
+    /// enabled.
+    pub fn interval(config: &Config) -> Option<Duration> {
+        config
+            .failure_detector_timeout()
+            .map(|timeout| timeout / HEARTBEATS_PER_TIMEOUT)
+    }
+
+    /// Records a heartbeat from `from`. If `from` was suspected, the suspicion
+    /// is revoked and accounted as a false suspicion.
+    pub fn heartbeat(
+        &mut self,
+        from: ProcessId,
+        bp: &mut BaseProcess,
+        time: &dyn SysTime,
+    ) {
+        self.last_heard.insert(from, time.millis());
+        if self.suspected.remove(&from) {
+            bp.aggregate_metric(ProtocolMetricsKind::FalseSuspicions, 1);
+        }
+    }
+
+    /// Checks which of the processes in the shard (other than self) haven't
+    /// been heard from during the timeout, returning the ones that became
+    /// suspected. The detection latency of each (i.e. the time since it was
+    /// last heard from, in milliseconds) is recorded.
+    #[must_use]
+    pub fn check(
+        &mut self,
+        bp: &mut BaseProcess,
+        time: &dyn SysTime,
+    ) -> Vec<ProcessId> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return Vec::new(),
+        };
+        let now = time.millis();
+        let mut suspected = Vec::new();
+        for process_id in bp.all_but_me() {
+            // processes start being monitored the first time we check them
+            let last_heard = *self.last_heard.entry(process_id).or_insert(now);
+            let elapsed = now.saturating_sub(last_heard);
+            if elapsed >= timeout && self.suspected.insert(process_id) {
+                bp.collect_metric(
+                    ProtocolMetricsKind::FailureDetectionLatency,
+                    elapsed,
+                );
+                suspected.push(process_id);
+            }
+        }
+        suspected
+    }
+
+    /// Checks whether `process_id` is currently suspected.
+    pub fn suspected(&self, process_id: ProcessId) -> bool {
+        self.suspected.contains(&process_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimTime;
+
+    #[test]
+    fn failure_detector_flow() {
+        // processes
+        let process_id = 1;
+        let shard_id = 0;
+        let mut config = Config::new(3, 1);
+        config.set_failure_detector_timeout(Duration::from_millis(300));
+        let mut bp = BaseProcess::new(process_id, shard_id, config, 2, 2);
+        bp.discover(vec![(1, shard_id), (2, shard_id), (3, shard_id)]);
+
+        // heartbeats are sent 3 times per timeout
+        assert_eq!(
+            FailureDetector::interval(&config),
+            Some(Duration::from_millis(100))
+        );
+
+        let mut time = SimTime::new();
+        let mut detector = FailureDetector::new(&config);
+
+        // processes start being monitored on the first check
+        assert!(detector.check(&mut bp, &time).is_empty());
+
+        // only process 2 sends heartbeats
+        time.add_millis(200);
+        detector.heartbeat(2, &mut bp, &time);
+        assert!(detector.check(&mut bp, &time).is_empty());
+
+        // after the timeout, process 3 is suspected (only once)
+        time.add_millis(100);
+        assert_eq!(detector.check(&mut bp, &time), vec![3]);
+        assert!(detector.suspected(3));
+        assert!(!detector.suspected(2));
+        time.add_millis(100);
+        assert!(detector.check(&mut bp, &time).is_empty());
+
+        // and so is process 2
+        time.add_millis(100);
+        assert_eq!(detector.check(&mut bp, &time), vec![2]);
+
+        // a heartbeat from process 3 revokes its suspicion
+        detector.heartbeat(3, &mut bp, &time);
+        assert!(!detector.suspected(3));
+
+        // check the metrics recorded
+        let metrics = bp.metrics();
+        let latency = metrics
+            .get_collected(ProtocolMetricsKind::FailureDetectionLatency)
+            .expect("detection latencies should have been collected");
+        assert_eq!(latency.count(), 2);
+        assert_eq!(
+            metrics.get_aggregated(ProtocolMetricsKind::FalseSuspicions),
+            Some(&1)
+        );
+
+        // if the failure detector is not enabled, there are no suspicions
+        let config = Config::new(3, 1);
+        assert_eq!(FailureDetector::interval(&config), None);
+        let mut detector = FailureDetector::new(&config);
+        assert!(detector.check(&mut bp, &time).is_empty());
+        time.add_millis(1000);
+        assert!(detector.check(&mut bp, &time).is_empty());
+    }
+}
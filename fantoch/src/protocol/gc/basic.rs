@@ -27,4 +27,9 @@ impl BasicGCTrack {
             false
         }
     }
+
+    /// Returns the number of commands recorded that are not yet stable.
+    pub fn stability_lag(&self) -> u64 {
+        self.dot_to_count.len() as u64
+    }
 }
@@ -11,6 +11,10 @@ pub struct ClockGCTrack<E: EventSet> {
     process_id: ProcessId,
     shard_id: ShardId,
     n: usize,
+    // committed clock sent in the last call to `committed_delta`
+    previous_sent: VClock<ProcessId>,
+    // committed clock in the last call to `missing`
+    previous_missing: VClock<ProcessId>,
     // the next 3 variables will be updated by the single process responsible
     // for GC
     my_clock: AEClock<ProcessId>,
@@ -27,6 +31,8 @@ impl<E: EventSet> ClockGCTrack<E> {
             process_id,
             shard_id,
             n,
+            previous_sent: VClock::with(util::process_ids(shard_id, n)),
+            previous_missing: VClock::with(util::process_ids(shard_id, n)),
             my_clock: Self::bottom_aeclock(shard_id, n),
             all_but_me,
             previous_stable: Self::bottom_clock(shard_id, n),
@@ -47,19 +53,95 @@ impl<E: EventSet> ClockGCTrack<E> {
         debug_assert_eq!(self.my_clock.len(), self.n);
     }
 
-    /// Records the set of commands by process `from`.
+    /// Returns the entries of the committed clock (i.e. the frontier of the
+    /// local clock) that changed since the last call to this method. Since
+    /// `update_clock_of` accumulates knowledge, these entries are enough for
+    /// other processes to keep track of our committed clock.
+    pub fn committed_delta(&mut self) -> VClock<ProcessId> {
+        let committed = self.my_clock.frontier();
+        let delta: Vec<_> = committed
+            .iter()
+            .filter(|(process_id, current)| {
+                self.previous_sent
+                    .get(process_id)
+                    .map(|previous| previous.frontier() < current.frontier())
+                    .unwrap_or(true)
+            })
+            .map(|(process_id, current)| (*process_id, current.clone()))
+            .collect();
+        self.previous_sent = committed;
+        VClock::from(delta)
+    }
+
+    /// Records the set of commands by process `from`. The clock received may
+    /// only contain the entries that changed (see `committed_delta`).
     pub fn update_clock_of(
         &mut self,
         from: ProcessId,
         clock: Clock<ProcessId, E>,
     ) {
-        if let Some(current) = self.all_but_me.get_mut(&from) {
-            // accumulate new knowledge; simply replacing it doesn't work since
-            // messages can be reordered
-            current.join(&clock);
-        } else {
-            self.all_but_me.insert(from, clock);
-        }
+        let (shard_id, n) = (self.shard_id, self.n);
+        // accumulate new knowledge; simply replacing it doesn't work since
+        // messages can be reordered and since missing entries are unchanged
+        self.all_but_me
+            .entry(from)
+            .or_insert_with(|| Self::bottom_clock(shard_id, n))
+            .join(&clock);
+    }
+
+    /// Returns, for each process that has sent us its committed clock, the
+    /// commands it is missing, i.e. the ones that were already below our
+    /// committed clock in the previous call to this method but are not yet
+    /// below its committed clock. Only commands committed since the previous
+    /// call are ignored, so that commands are not reported as missing simply
+    /// because the clocks of other processes are not up-to-date. At most
+    /// `max_per_process` commands are returned for each process.
+    pub fn missing(
+        &mut self,
+        max_per_process: usize,
+    ) -> HashMap<ProcessId, Vec<Dot>> {
+        let previous = std::mem::replace(
+            &mut self.previous_missing,
+            self.my_clock.frontier(),
+        );
+        self.all_but_me
+            .iter()
+            .filter_map(|(peer_id, clock)| {
+                let dots: Vec<_> = previous
+                    .iter()
+                    .flat_map(|(process_id, committed)| {
+                        let peer_committed = clock
+                            .get(process_id)
+                            .map(|peer_committed| peer_committed.frontier())
+                            .unwrap_or_default();
+                        (peer_committed + 1..=committed.frontier())
+                            .map(move |seq| Dot::new(*process_id, seq))
+                    })
+                    .take(max_per_process)
+                    .collect();
+                if dots.is_empty() {
+                    None
+                } else {
+                    Some((*peer_id, dots))
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the number of commands committed locally (i.e. below the
+    /// frontier of the local clock) that are not yet known to be stable.
+    pub fn stability_lag(&self) -> u64 {
+        self.previous_stable
+            .iter()
+            .map(|(process_id, stable)| {
+                let committed = self
+                    .my_clock
+                    .get(process_id)
+                    .map(|committed| committed.frontier())
+                    .unwrap_or_default();
+                committed.saturating_sub(stable.frontier())
+            })
+            .sum()
     }
 
     fn bottom_aeclock(shard_id: ShardId, n: usize) -> AEClock<ProcessId> {
@@ -198,6 +280,7 @@ mod tests {
         assert_eq!(gc.clock().frontier(), vclock(0, 0));
         assert_eq!(gc.stable_clock(), vclock(0, 0));
         assert_eq!(stable_dots(gc.stable()), vec![]);
+        assert_eq!(gc.stability_lag(), 0);
 
         // let's create a bunch of dots
         let dot11 = Dot::new(1, 1);
@@ -217,6 +300,7 @@ mod tests {
         assert_eq!(gc.clock().frontier(), vclock(2, 0));
         assert_eq!(gc.stable_clock(), vclock(0, 0));
         assert_eq!(stable_dots(gc.stable()), vec![]);
+        assert_eq!(gc.stability_lag(), 2);
 
         // if we update with the committed clock from process 2 nothing changes
         gc.update_clock_of(2, gc2.clock().frontier());
@@ -233,6 +317,7 @@ mod tests {
         assert_eq!(gc.clock().frontier(), vclock(2, 0));
         assert_eq!(gc.stable_clock(), vclock(1, 0));
         assert_eq!(stable_dots(gc.stable()), vec![dot11]);
+        assert_eq!(gc.stability_lag(), 1);
 
         // if we call stable again, no new dot is returned
         assert_eq!(gc.stable_clock(), vclock(1, 0));
@@ -248,5 +333,73 @@ mod tests {
         assert_eq!(gc.stable_clock(), vclock(3, 0));
         assert_eq!(stable_dots(gc.stable()), vec![dot12, dot13]);
         assert_eq!(stable_dots(gc.stable()), vec![]);
+        assert_eq!(gc.stability_lag(), 0);
+    }
+
+    #[test]
+    fn gc_committed_delta() {
+        let n = 2;
+        let shard_id = 0;
+        let mut gc = VClockGCTrack::new(1, shard_id, n);
+        let mut gc2 = VClockGCTrack::new(2, shard_id, n);
+
+        // nothing committed, so there's nothing to send
+        assert_eq!(gc.committed_delta(), VClock::from(vec![]));
+
+        // commit dot11 and dot21: both entries changed
+        gc.add_to_clock(&Dot::new(1, 1));
+        gc.add_to_clock(&Dot::new(2, 1));
+        let delta = gc.committed_delta();
+        assert_eq!(delta, vclock(1, 1));
+        gc2.update_clock_of(1, delta);
+
+        // nothing changed since the last delta
+        assert_eq!(gc.committed_delta(), VClock::from(vec![]));
+
+        // commit dot22: only the entry of process 2 changed
+        gc.add_to_clock(&Dot::new(2, 2));
+        let delta = gc.committed_delta();
+        assert_eq!(delta, VClock::from(vec![(2, MaxSet::from(2))]));
+
+        // process 2 still has the full view of the committed clock
+        gc2.update_clock_of(1, delta);
+        gc2.add_to_clock(&Dot::new(1, 1));
+        gc2.add_to_clock(&Dot::new(2, 1));
+        gc2.add_to_clock(&Dot::new(2, 2));
+        assert_eq!(gc2.stable_clock(), vclock(1, 2));
+    }
+
+    #[test]
+    fn gc_missing() {
+        let n = 2;
+        let shard_id = 0;
+        let mut gc = VClockGCTrack::new(1, shard_id, n);
+
+        // nothing is missing if we haven't heard from process 2
+        gc.add_to_clock(&Dot::new(1, 1));
+        gc.add_to_clock(&Dot::new(1, 2));
+        assert!(gc.missing(10).is_empty());
+
+        // commands committed since the previous call are not yet missing
+        gc.update_clock_of(2, vclock(0, 0));
+        gc.add_to_clock(&Dot::new(2, 1));
+        let missing = gc.missing(10);
+        assert_eq!(missing.len(), 1);
+        let mut dots =
+            missing.get(&2).expect("process 2 is missing dots").clone();
+        dots.sort();
+        assert_eq!(dots, vec![Dot::new(1, 1), Dot::new(1, 2)]);
+
+        // now dot21 is also missing, but at most 2 dots are returned
+        gc.update_clock_of(2, vclock(1, 0));
+        let missing = gc.missing(2);
+        let mut dots =
+            missing.get(&2).expect("process 2 is missing dots").clone();
+        dots.sort();
+        assert_eq!(dots, vec![Dot::new(1, 2), Dot::new(2, 1)]);
+
+        // once process 2 commits them, nothing is missing
+        gc.update_clock_of(2, vclock(2, 1));
+        assert!(gc.missing(10).is_empty());
     }
 }
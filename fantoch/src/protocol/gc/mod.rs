@@ -4,3 +4,7 @@ mod clock;
 // Re-exports.
 pub use basic::BasicGCTrack;
 pub use clock::{ClockGCTrack, VClockGCTrack};
+
+/// Maximum number of commands re-shipped to each process in each anti-entropy
+/// round (see `Config::anti_entropy_interval`).
+pub const ANTI_ENTROPY_MAX_REPAIRS: usize = 1000;
@@ -2,6 +2,7 @@ use super::Info;
 use crate::id::{Dot, ProcessId, ShardId};
 use crate::util;
 use crate::HashMap;
+use std::collections::BTreeSet;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SequentialCommandsInfo<I> {
@@ -11,7 +12,16 @@ pub struct SequentialCommandsInfo<I> {
     f: usize,
     fast_quorum_size: usize,
     write_quorum_size: usize,
-    dot_to_info: HashMap<Dot, I>,
+    // info of in-flight dots
+    hot: HashMap<Dot, I>,
+    // sequences of committed dots (from each process) that have not yet been
+    // garbage collected; nothing else is needed to garbage collect them, and
+    // keeping their whole info around for tens of millions of dots is too
+    // costly
+    cold: HashMap<ProcessId, BTreeSet<u64>>,
+    // highest stable sequence of each process, i.e. all dots from that process
+    // up to that sequence have been garbage collected
+    stable: HashMap<ProcessId, u64>,
 }
 
 impl<I> SequentialCommandsInfo<I>
@@ -33,7 +43,9 @@ where
             f,
             fast_quorum_size,
             write_quorum_size,
-            dot_to_info: HashMap::new(),
+            hot: HashMap::new(),
+            cold: HashMap::new(),
+            stable: HashMap::new(),
         }
     }
 
@@ -48,7 +60,7 @@ where
         let f = self.f;
         let fast_quorum_size = self.fast_quorum_size;
         let write_quorum_size = self.write_quorum_size;
-        self.dot_to_info.entry(dot).or_insert_with(|| {
+        self.hot.entry(dot).or_insert_with(|| {
             I::new(
                 process_id,
                 shard_id,
@@ -60,21 +72,177 @@ where
         })
     }
 
+    /// Returns the `Info` associated with `Dot`, unless `Dot` has already been
+    /// moved to the cold storage (see `commit`) or garbage collected, in which
+    /// case `None` is returned. Unlike `get`, this never re-creates the `Info`
+    /// of such dots (which would never be collected again).
+    pub fn get_in_flight(&mut self, dot: Dot) -> Option<&mut I> {
+        if self.is_stable(&dot) || self.is_cold(&dot) {
+            None
+        } else {
+            Some(self.get(dot))
+        }
+    }
+
+    /// Returns the `Info` associated with `Dot`, if any.
+    pub fn find(&self, dot: &Dot) -> Option<&I> {
+        self.hot.get(dot)
+    }
+
+    /// Same as `find`, but returns a mutable reference.
+    pub fn find_mut(&mut self, dot: &Dot) -> Option<&mut I> {
+        self.hot.get_mut(dot)
+    }
+
+    /// Moves `Dot`, which has been committed, to the cold storage, dropping
+    /// its `Info`. From then on, only what's needed to garbage collect it is
+    /// kept.
+    pub fn commit(&mut self, dot: Dot) {
+        assert!(self.hot.remove(&dot).is_some());
+        self.cold
+            .entry(dot.source())
+            .or_default()
+            .insert(dot.sequence());
+    }
+
+    /// Checks whether `Dot` is in the cold storage, i.e. whether it has been
+    /// committed (see `commit`) but not yet garbage collected.
+    pub fn is_cold(&self, dot: &Dot) -> bool {
+        self.cold
+            .get(&dot.source())
+            .map(|cold| cold.contains(&dot.sequence()))
+            .unwrap_or(false)
+    }
+
+    /// Returns the number of in-flight dots, i.e. dots whose `Info` is kept.
+    pub fn hot_len(&self) -> usize {
+        self.hot.len()
+    }
+
+    /// Returns the number of dots in the cold storage.
+    pub fn cold_len(&self) -> usize {
+        self.cold.values().map(|cold| cold.len()).sum()
+    }
+
     /// Performs garbage collection of stable dots.
     /// Returns how many stable does were removed.
     pub fn gc(&mut self, stable: Vec<(ProcessId, u64, u64)>) -> usize {
-        util::dots(stable)
-            .filter(|dot| {
-                // remove dot:
-                // - the dot may not exist locally if there are multiple workers
-                //   and this worker is not responsible for such dot
-                self.dot_to_info.remove(&dot).is_some()
-            })
-            .count()
+        let mut removed = 0;
+        for (process_id, _start, end) in stable.iter() {
+            let current = self.stable.entry(*process_id).or_default();
+            *current = std::cmp::max(*current, *end);
+
+            // since all dots up to `end` are now stable, drop from the cold
+            // storage all sequences up to `end`
+            if let Some(cold) = self.cold.get_mut(process_id) {
+                let remaining = cold.split_off(&(end + 1));
+                removed += std::mem::replace(cold, remaining).len();
+            }
+        }
+        removed
+            + util::dots(stable)
+                .filter(|dot| {
+                    // remove dot:
+                    // - the dot may not exist locally if there are multiple
+                    //   workers and this worker is not responsible for such
+                    //   dot
+                    self.hot.remove(&dot).is_some()
+                })
+                .count()
+    }
+
+    /// Checks whether `Dot` has been garbage collected by `gc`, i.e. whether
+    /// it's stable.
+    pub fn is_stable(&self, dot: &Dot) -> bool {
+        self.stable
+            .get(&dot.source())
+            .map(|stable| dot.sequence() <= *stable)
+            .unwrap_or(false)
+    }
+
+    /// Returns the stable frontier, i.e. the highest stable sequence of any
+    /// process.
+    pub fn stable_frontier(&self) -> u64 {
+        self.stable.values().copied().max().unwrap_or(0)
+    }
+
+    /// Checks whether `Dot` is beyond the `horizon`, i.e. whether it's not yet
+    /// stable and more than `horizon` sequences behind the stable frontier.
+    /// Dots from processes in other shards are never beyond the horizon, as
+    /// their stability is not tracked here.
+    pub fn beyond_horizon(&self, dot: &Dot, horizon: u64) -> bool {
+        let same_shard = util::process_ids(self.shard_id, self.n)
+            .any(|process_id| process_id == dot.source());
+        same_shard
+            && !self.is_stable(dot)
+            && self.stable_frontier().saturating_sub(dot.sequence()) > horizon
     }
 
     /// Removes a command has been committed.
     pub fn gc_single(&mut self, dot: Dot) {
-        assert!(self.dot_to_info.remove(&dot).is_some());
+        assert!(self.hot.remove(&dot).is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct TestInfo;
+
+    impl Info for TestInfo {
+        fn new(
+            _process_id: ProcessId,
+            _shard_id: ShardId,
+            _n: usize,
+            _f: usize,
+            _fast_quorum_size: usize,
+            _write_quorum_size: usize,
+        ) -> Self {
+            Self
+        }
+    }
+
+    #[test]
+    fn hot_cold_gc() {
+        let (n, f) = (3, 1);
+        let mut cmds =
+            SequentialCommandsInfo::<TestInfo>::new(1, 0, n, f, 2, 2);
+
+        // create info for dots 1..=4 of process 1
+        let dots: Vec<_> = (1..=4).map(|seq| Dot::new(1, seq)).collect();
+        for dot in &dots {
+            cmds.get(*dot);
+        }
+
+        // commit dots 1 and 3
+        cmds.commit(dots[0]);
+        cmds.commit(dots[2]);
+        assert!(cmds.find(&dots[0]).is_none());
+        assert!(cmds.is_cold(&dots[0]));
+        assert!(!cmds.is_cold(&dots[1]));
+        assert!(cmds.is_cold(&dots[2]));
+        assert_eq!(cmds.hot_len(), 2);
+        assert_eq!(cmds.cold_len(), 2);
+
+        // committed dots are no longer in-flight
+        assert!(cmds.get_in_flight(dots[0]).is_none());
+        assert!(cmds.get_in_flight(dots[1]).is_some());
+
+        // gc dots 1..=3: the two committed and the one still in-flight
+        assert_eq!(cmds.gc(vec![(1, 1, 3)]), 3);
+        assert!(!cmds.is_cold(&dots[0]));
+        assert!(!cmds.is_cold(&dots[2]));
+        assert!(cmds.is_stable(&dots[2]));
+        assert!(cmds.get_in_flight(dots[2]).is_none());
+        assert_eq!(cmds.hot_len(), 1);
+        assert_eq!(cmds.cold_len(), 0);
+
+        // commit and gc the last dot
+        cmds.commit(dots[3]);
+        assert_eq!(cmds.gc(vec![(1, 4, 4)]), 1);
+        assert!(!cmds.is_cold(&dots[3]));
+        assert!(cmds.is_stable(&dots[3]));
     }
 }
@@ -13,15 +13,50 @@ mod base;
 // protocol fault-tolerant (but still inconsistent).
 mod basic;
 
+// This module contains the definition of `Shadow`, which runs a primary and a
+// shadow protocol side by side.
+mod shadow;
+
 // This module contains common functionality from tracking when it's safe to
 // garbage-collect a command, i.e., when it's been committed at all processes.
 mod gc;
 
+// This module contains the definition of `AckCoalescer`, which buffers the
+// acks sent to the same coordinator so that they're sent in a single message.
+mod coalesce;
+
+// This module contains the fingerprints of the protocol messages, generated
+// at build time (see `build/message_fingerprint.rs`).
+mod message_fingerprints {
+    include!(concat!(env!("OUT_DIR"), "/message_fingerprints.rs"));
+}
+
+// This module contains the registry of all the protocols that can be run.
+mod registry;
+
+// This module contains the definition of `FailureDetector`, which suspects the
+// processes that stop sending heartbeats.
+mod failure;
+
+// This module contains the definition of `Violation`, which protocols and
+// executors report instead of panicking on unexpected states.
+mod violation;
+
 // Re-exports.
 pub use base::BaseProcess;
 pub use basic::Basic;
-pub use gc::{BasicGCTrack, ClockGCTrack, VClockGCTrack};
+pub use coalesce::AckCoalescer;
+pub use failure::FailureDetector;
+pub use gc::{
+    BasicGCTrack, ClockGCTrack, VClockGCTrack, ANTI_ENTROPY_MAX_REPAIRS,
+};
 pub use info::{Info, LockedCommandsInfo, SequentialCommandsInfo};
+pub use registry::{
+    registration, FaultTolerance, ProtocolCapabilities, ProtocolRegistration,
+    ReadOnlySupport, REGISTERED_PROTOCOLS,
+};
+pub use shadow::{Shadow, ShadowEvent, ShadowMessage};
+pub use violation::Violation;
 
 use crate::command::Command;
 use crate::config::Config;
@@ -32,12 +67,17 @@ use crate::time::SysTime;
 use crate::{HashMap, HashSet};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::{self, Debug};
 use std::time::Duration;
 
 // Compact representation of which `Dot`s have been committed and executed.
 pub type CommittedAndExecuted = (u64, Vec<Dot>);
 
+// Number of entries in each of the internal structures of a protocol (e.g. the
+// number of dots tracked, or the number of keys with a clock).
+pub type StateSizes = BTreeMap<&'static str, usize>;
+
 pub trait Protocol: Debug + Clone {
     type Message: Debug
         + Clone
@@ -47,7 +87,8 @@ pub trait Protocol: Debug + Clone {
         + DeserializeOwned
         + Send
         + Sync
-        + MessageIndex; // TODO why is Sync needed??
+        + MessageIndex
+        + MessagePriority; // TODO why is Sync needed??
     type PeriodicEvent: Debug + Clone + Send + Sync + MessageIndex + Eq;
     type Executor: Executor + Send;
 
@@ -79,6 +120,13 @@ pub trait Protocol: Debug + Clone {
 
     fn handle_event(&mut self, event: Self::PeriodicEvent, time: &dyn SysTime);
 
+    fn cancel(&mut self, _dot: Dot, _time: &dyn SysTime) {
+        // protocols that support aborting in-flight commands should overwrite
+        // this (and `cancellable`); the expected behavior is that the
+        // coordinator of `dot` commits a noop for it if the command has not
+        // yet been committed
+    }
+
     fn handle_executed(
         &mut self,
         _committed_and_executed: CommittedAndExecuted,
@@ -107,11 +155,36 @@ pub trait Protocol: Debug + Clone {
         ToExecutorsIter { process: self }
     }
 
+    #[must_use]
+    fn to_violations(&mut self) -> Option<Violation> {
+        // protocols that record violations of their invariants (instead of
+        // panicking) should overwrite this
+        None
+    }
+
     fn parallel() -> bool;
 
     fn leaderless() -> bool;
 
+    fn cancellable() -> bool {
+        // by default, protocols don't support aborting in-flight commands, and
+        // so cancels are rejected by the run layer
+        false
+    }
+
+    /// Returns the fingerprint of the schema of the protocol messages. It's
+    /// exchanged when processes connect, so that processes built from
+    /// different versions of the protocol refuse to talk to each other.
+    fn message_fingerprint() -> u64;
+
     fn metrics(&self) -> &ProtocolMetrics;
+
+    fn state_sizes(&self) -> StateSizes {
+        // protocols should overwrite this so that the size of their internal
+        // state can be queried in live experiments (see the `state_sizes`
+        // control query in fantoch::run)
+        StateSizes::new()
+    }
 }
 
 pub struct ToProcessesIter<'a, P> {
@@ -155,6 +228,56 @@ pub enum ProtocolMetricsKind {
     WaitConditionDelay,
     CommittedDepsLen,
     CommandKeyCount,
+    // fast and slow paths taken by commands using tiny quorums (only tracked
+    // by `Tempo` with per-command tiny quorums); these are also accounted in
+    // `FastPath` and `SlowPath`
+    TinyQuorumFastPath,
+    TinyQuorumSlowPath,
+    // number of commands committed locally but not yet known to be stable,
+    // sampled on each garbage collection event
+    StabilityLag,
+    // same samples as `StabilityLag`, but grouped by the (unix epoch) second
+    // in which they were taken, so that the lag can be plotted over time
+    StabilityLagAt(u64),
+    // number of times tempo bumped the clocks of all keys upon receiving an
+    // `MCommit` (only tracked if `tempo_commit_clock_bump` is enabled)
+    CommitClockBump,
+    // number of read-only commands accessing more than one shard served by
+    // tempo's multi-shard reads, and the number of shards that had to be
+    // consulted again because their clock snapshot was lower than the highest
+    // one (only tracked if `tempo_multi_shard_reads` is enabled)
+    MultiShardReads,
+    MultiShardReadRetries,
+    // number of key-lock acquisitions, the wait time (in micros) of the ones
+    // that had to wait, and the total wait time on each key (identified by
+    // its hash, see `util::key_hash`); only tracked by the `Locked` variants
+    KeyLockAcquisitions,
+    KeyLockWait,
+    KeyLockWaitOn(u64),
+    // time (in milliseconds) between the last heartbeat received from a
+    // process and the moment it was suspected by the `FailureDetector`, and
+    // the number of suspicions later revoked by a heartbeat
+    FailureDetectionLatency,
+    FalseSuspicions,
+    // number of violations of the protocol's invariants (see `Violation`)
+    Violations,
+    // number of committed commands re-shipped to other processes that were
+    // missing them (only tracked if `anti_entropy_interval` is set)
+    Repairs,
+    // number of commands that took the slow path (even though the fast path
+    // condition held) because some dependency was beyond the dependency
+    // horizon (only tracked if `dependency_horizon` is set)
+    DependencyHorizonSlowPath,
+    // number of messages dropped because they referred to commands no longer
+    // in-flight, i.e. already committed (and moved to the cold storage) or
+    // garbage collected
+    StaleMessages,
+    // number of dependencies not added between commands that commute (see
+    // `kvs::Commutative`); only tracked by dependency-based protocols
+    CommutedDeps,
+    // number of commands committed by the process that coordinated them
+    // (in leaderless protocols, this is the sum of `FastPath` and `SlowPath`)
+    Committed,
 }
 
 impl Debug for ProtocolMetricsKind {
@@ -175,6 +298,46 @@ impl Debug for ProtocolMetricsKind {
             ProtocolMetricsKind::CommandKeyCount => {
                 write!(f, "command_key_count")
             }
+            ProtocolMetricsKind::TinyQuorumFastPath => {
+                write!(f, "tiny_quorum_fast_path")
+            }
+            ProtocolMetricsKind::TinyQuorumSlowPath => {
+                write!(f, "tiny_quorum_slow_path")
+            }
+            ProtocolMetricsKind::StabilityLag => write!(f, "stability_lag"),
+            ProtocolMetricsKind::StabilityLagAt(second) => {
+                write!(f, "stability_lag_at_{}", second)
+            }
+            ProtocolMetricsKind::CommitClockBump => {
+                write!(f, "commit_clock_bump")
+            }
+            ProtocolMetricsKind::MultiShardReads => {
+                write!(f, "multi_shard_reads")
+            }
+            ProtocolMetricsKind::MultiShardReadRetries => {
+                write!(f, "multi_shard_read_retries")
+            }
+            ProtocolMetricsKind::KeyLockAcquisitions => {
+                write!(f, "key_lock_acquisitions")
+            }
+            ProtocolMetricsKind::KeyLockWait => write!(f, "key_lock_wait"),
+            ProtocolMetricsKind::KeyLockWaitOn(key_hash) => {
+                write!(f, "key_lock_wait_on_{}", key_hash)
+            }
+            ProtocolMetricsKind::FailureDetectionLatency => {
+                write!(f, "failure_detection_latency")
+            }
+            ProtocolMetricsKind::FalseSuspicions => {
+                write!(f, "false_suspicions")
+            }
+            ProtocolMetricsKind::Violations => write!(f, "violations"),
+            ProtocolMetricsKind::Repairs => write!(f, "repairs"),
+            ProtocolMetricsKind::DependencyHorizonSlowPath => {
+                write!(f, "dependency_horizon_slow_path")
+            }
+            ProtocolMetricsKind::StaleMessages => write!(f, "stale_messages"),
+            ProtocolMetricsKind::CommutedDeps => write!(f, "commuted_deps"),
+            ProtocolMetricsKind::Committed => write!(f, "committed"),
         }
     }
 }
@@ -193,6 +356,31 @@ pub trait MessageIndex {
     fn index(&self) -> Option<(usize, usize)>;
 }
 
+/// Messages with a serialized size (in bytes) up to this value are, by
+/// default, sent with high priority.
+pub const HIGH_PRIORITY_MAX_MESSAGE_SIZE: u64 = 1024; // 1KB
+
+pub trait MessagePriority: Serialize {
+    /// This trait is used to decide the priority with which messages are sent
+    /// to other processes: high priority messages are never queued behind low
+    /// priority messages (e.g. large batches). By default, a message is high
+    /// priority if its serialized size is at most
+    /// `HIGH_PRIORITY_MAX_MESSAGE_SIZE`; protocols that know which messages
+    /// are small control messages (e.g. acks and commits) should overwrite
+    /// this to avoid computing the serialized size.
+    fn high_priority(&self) -> bool {
+        high_priority_by_size(self)
+    }
+}
+
+/// Classifies a message as high priority if its serialized size is at most
+/// `HIGH_PRIORITY_MAX_MESSAGE_SIZE`.
+pub fn high_priority_by_size<T: Serialize + ?Sized>(msg: &T) -> bool {
+    bincode::serialized_size(msg)
+        .map(|size| size <= HIGH_PRIORITY_MAX_MESSAGE_SIZE)
+        .unwrap_or(false)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Action<P: Protocol> {
     ToSend {
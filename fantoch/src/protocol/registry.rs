@@ -0,0 +1,303 @@
+/// Metadata of a protocol registered with `register_protocol!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolRegistration {
+    /// name of the variant that identifies the protocol in experiments
+    pub variant: &'static str,
+    /// name with which the protocol is selected (e.g. `fantoch-server
+    /// --protocol NAME`)
+    pub name: &'static str,
+    /// name of the protocol in plots
+    pub plot_name: &'static str,
+    /// default color of the protocol in plots
+    pub plot_color: &'static str,
+    /// what the protocol supports
+    pub capabilities: ProtocolCapabilities,
+}
+
+/// What a protocol supports, so that experiments (and plots) can be checked
+/// (and configured) without running the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolCapabilities {
+    /// whether commands can access more than one shard
+    pub partial_replication: bool,
+    /// how read-only commands are handled
+    pub read_only: ReadOnlySupport,
+    /// how many failures are tolerated
+    pub fault_tolerance: FaultTolerance,
+    /// whether commands are ordered by a leader
+    pub leader: bool,
+}
+
+impl ProtocolCapabilities {
+    /// Returns the maximum value of `f` supported with `n` processes.
+    pub fn max_f(&self, n: usize) -> usize {
+        match self.fault_tolerance {
+            FaultTolerance::Minority | FaultTolerance::FixedMinority => n / 2,
+            FaultTolerance::AllButOne => n - 1,
+        }
+    }
+
+    /// Returns whether the protocol depends on the configured `f`.
+    pub fn configurable_f(&self) -> bool {
+        self.fault_tolerance != FaultTolerance::FixedMinority
+    }
+}
+
+/// How a protocol handles read-only commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadOnlySupport {
+    /// read-only commands can't be submitted
+    Unsupported,
+    /// read-only commands are ordered as any other command
+    Supported,
+    /// read-only commands are only ordered with respect to writes
+    Optimized,
+}
+
+/// How many failures a protocol tolerates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultTolerance {
+    /// `f` is configured, and can be at most a minority of the processes
+    Minority,
+    /// `f` is ignored, as a minority of failures is always tolerated
+    FixedMinority,
+    /// `f` is configured, and can be up to all processes but one
+    AllButOne,
+}
+
+/// Creates the `ProtocolRegistration` of a protocol. The path to the type
+/// implementing `Protocol` is only resolved by crates that depend on the
+/// crate defining it (e.g. to dispatch to the protocol at run time).
+#[macro_export]
+macro_rules! register_protocol {
+    (
+        $variant:ident {
+            name: $name:literal,
+            protocol: $protocol:path,
+            plot_name: $plot_name:literal,
+            plot_color: $plot_color:literal,
+            capabilities: {
+                partial_replication: $partial_replication:literal,
+                read_only: $read_only:ident,
+                fault_tolerance: $fault_tolerance:ident,
+                leader: $leader:literal $(,)?
+            } $(,)?
+        }
+    ) => {
+        $crate::protocol::ProtocolRegistration {
+            variant: stringify!($variant),
+            name: $name,
+            plot_name: $plot_name,
+            plot_color: $plot_color,
+            capabilities: $crate::protocol::ProtocolCapabilities {
+                partial_replication: $partial_replication,
+                read_only: $crate::protocol::ReadOnlySupport::$read_only,
+                fault_tolerance:
+                    $crate::protocol::FaultTolerance::$fault_tolerance,
+                leader: $leader,
+            },
+        }
+    };
+}
+
+/// Registry of all the protocols that can be run. Adding a protocol only
+/// requires adding a `register_protocol!` entry here: this macro invokes
+/// `$callback!` with all the entries, which allows each crate to generate the
+/// glue it needs from them (the dispatch in the `fantoch-server` binary, the
+/// `Protocol` enum in `fantoch_exp`, and the defaults in `fantoch_plot`).
+#[macro_export]
+macro_rules! registered_protocols {
+    ($callback:ident) => {
+        $callback! {
+            register_protocol!(AtlasLocked {
+                name: "atlas_locked",
+                protocol: fantoch_ps::protocol::AtlasLocked,
+                plot_name: "Atlas",
+                plot_color: "#27ae60",
+                capabilities: {
+                    partial_replication: true,
+                    read_only: Optimized,
+                    fault_tolerance: Minority,
+                    leader: false,
+                },
+            });
+            register_protocol!(EPaxosLocked {
+                name: "epaxos_locked",
+                protocol: fantoch_ps::protocol::EPaxosLocked,
+                plot_name: "EPaxos",
+                plot_color: "#444444",
+                capabilities: {
+                    partial_replication: false,
+                    read_only: Optimized,
+                    fault_tolerance: FixedMinority,
+                    leader: false,
+                },
+            });
+            register_protocol!(CaesarLocked {
+                name: "caesar_locked",
+                protocol: fantoch_ps::protocol::CaesarLocked,
+                plot_name: "Caesar",
+                plot_color: "#bdc3c7",
+                capabilities: {
+                    partial_replication: false,
+                    read_only: Supported,
+                    fault_tolerance: FixedMinority,
+                    leader: false,
+                },
+            });
+            register_protocol!(FPaxos {
+                name: "fpaxos",
+                protocol: fantoch_ps::protocol::FPaxos,
+                plot_name: "FPaxos",
+                plot_color: "#2980b9",
+                capabilities: {
+                    partial_replication: false,
+                    read_only: Supported,
+                    fault_tolerance: Minority,
+                    leader: true,
+                },
+            });
+            register_protocol!(TempoAtomic {
+                name: "tempo_atomic",
+                protocol: fantoch_ps::protocol::TempoAtomic,
+                plot_name: "Tempo",
+                plot_color: "#f1c40f",
+                capabilities: {
+                    partial_replication: true,
+                    read_only: Unsupported,
+                    fault_tolerance: Minority,
+                    leader: false,
+                },
+            });
+            register_protocol!(TempoLocked {
+                name: "tempo_locked",
+                protocol: fantoch_ps::protocol::TempoLocked,
+                plot_name: "Tempo-L",
+                plot_color: "#2980b9",
+                capabilities: {
+                    partial_replication: true,
+                    read_only: Optimized,
+                    fault_tolerance: Minority,
+                    leader: false,
+                },
+            });
+            register_protocol!(Basic {
+                name: "basic",
+                protocol: fantoch::protocol::Basic,
+                plot_name: "Inconsistent",
+                plot_color: "#576574",
+                capabilities: {
+                    partial_replication: false,
+                    read_only: Supported,
+                    fault_tolerance: AllButOne,
+                    leader: false,
+                },
+            });
+            register_protocol!(AtlasSequential {
+                name: "atlas",
+                protocol: fantoch_ps::protocol::AtlasSequential,
+                plot_name: "Atlas-S",
+                plot_color: "#2ecc71",
+                capabilities: {
+                    partial_replication: true,
+                    read_only: Supported,
+                    fault_tolerance: Minority,
+                    leader: false,
+                },
+            });
+            register_protocol!(EPaxosSequential {
+                name: "epaxos",
+                protocol: fantoch_ps::protocol::EPaxosSequential,
+                plot_name: "EPaxos-S",
+                plot_color: "#7f8c8d",
+                capabilities: {
+                    partial_replication: false,
+                    read_only: Supported,
+                    fault_tolerance: FixedMinority,
+                    leader: false,
+                },
+            });
+            register_protocol!(TempoSequential {
+                name: "tempo",
+                protocol: fantoch_ps::protocol::TempoSequential,
+                plot_name: "Tempo-S",
+                plot_color: "#f39c12",
+                capabilities: {
+                    partial_replication: true,
+                    read_only: Supported,
+                    fault_tolerance: Minority,
+                    leader: false,
+                },
+            });
+        }
+    };
+}
+
+macro_rules! registrations {
+    ($(register_protocol!($variant:ident $fields:tt);)*) => {
+        /// Registrations of all the protocols in `registered_protocols!`.
+        pub const REGISTERED_PROTOCOLS: &[ProtocolRegistration] =
+            &[$(register_protocol!($variant $fields)),*];
+    };
+}
+
+registered_protocols!(registrations);
+
+/// Returns the registration of the protocol with the name provided.
+pub fn registration(name: &str) -> Option<&'static ProtocolRegistration> {
+    REGISTERED_PROTOCOLS
+        .iter()
+        .find(|registration| registration.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashSet;
+
+    #[test]
+    fn registry() {
+        // names and variants are unique
+        let names: HashSet<_> = REGISTERED_PROTOCOLS
+            .iter()
+            .map(|registration| registration.name)
+            .collect();
+        let variants: HashSet<_> = REGISTERED_PROTOCOLS
+            .iter()
+            .map(|registration| registration.variant)
+            .collect();
+        assert_eq!(names.len(), REGISTERED_PROTOCOLS.len());
+        assert_eq!(variants.len(), REGISTERED_PROTOCOLS.len());
+
+        let basic = registration("basic").expect("basic should be registered");
+        assert_eq!(basic.variant, "Basic");
+        assert_eq!(basic.plot_name, "Inconsistent");
+        assert!(registration("unknown").is_none());
+
+        // capabilities
+        let fpaxos = registration("fpaxos").unwrap().capabilities;
+        assert!(fpaxos.leader);
+        assert!(!fpaxos.partial_replication);
+        assert!(fpaxos.configurable_f());
+        assert_eq!(fpaxos.max_f(5), 2);
+
+        let epaxos = registration("epaxos_locked").unwrap().capabilities;
+        assert!(!epaxos.leader);
+        assert!(!epaxos.configurable_f());
+        assert_eq!(epaxos.max_f(5), 2);
+
+        let tempo = registration("tempo_atomic").unwrap().capabilities;
+        assert!(tempo.partial_replication);
+        assert_eq!(tempo.read_only, ReadOnlySupport::Unsupported);
+
+        assert_eq!(basic.capabilities.max_f(5), 4);
+
+        // only FPaxos has a leader
+        let leaders: Vec<_> = REGISTERED_PROTOCOLS
+            .iter()
+            .filter(|registration| registration.capabilities.leader)
+            .map(|registration| registration.name)
+            .collect();
+        assert_eq!(leaders, vec!["fpaxos"]);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::command::Command;
+use crate::config::Config;
+use crate::executor::{Executor, ShadowExecutionInfo, ShadowExecutor};
+use crate::id::{Dot, ProcessId, ShardId};
+use crate::protocol::{
+    Action, CommittedAndExecuted, MessageIndex, MessagePriority, Protocol,
+    ProtocolMetrics, StateSizes, Violation,
+};
+use crate::time::SysTime;
+use crate::HashMap;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Protocol that runs a primary protocol `P` along with a shadow protocol `S`,
+/// feeding both with the same submissions. Clients only get the results of the
+/// primary protocol, while the results of the shadow protocol are compared
+/// with the ones of the primary protocol by the `ShadowExecutor`. This can be
+/// used to validate a new protocol implementation against a trusted one.
+///
+/// Note that:
+/// - the metrics reported are the ones of the primary protocol (see
+///   `Shadow::shadow` for the ones of the shadow protocol)
+/// - executed notifications are only delivered to the primary protocol
+#[derive(Debug, Clone)]
+pub struct Shadow<P, S> {
+    primary: P,
+    shadow: S,
+}
+
+impl<P, S> Protocol for Shadow<P, S>
+where
+    P: Protocol,
+    S: Protocol,
+{
+    type Message = ShadowMessage<P::Message, S::Message>;
+    type PeriodicEvent = ShadowEvent<P::PeriodicEvent, S::PeriodicEvent>;
+    type Executor = ShadowExecutor<P::Executor, S::Executor>;
+
+    /// Creates a new `Shadow` process.
+    fn new(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        config: Config,
+    ) -> (Self, Vec<(Self::PeriodicEvent, Duration)>) {
+        let (primary, primary_events) = P::new(process_id, shard_id, config);
+        let (shadow, shadow_events) = S::new(process_id, shard_id, config);
+
+        // create periodic events of both protocols
+        let events = primary_events
+            .into_iter()
+            .map(|(event, delay)| (ShadowEvent::Primary(event), delay))
+            .chain(
+                shadow_events
+                    .into_iter()
+                    .map(|(event, delay)| (ShadowEvent::Shadow(event), delay)),
+            )
+            .collect();
+
+        (Self { primary, shadow }, events)
+    }
+
+    /// Returns the process identifier.
+    fn id(&self) -> ProcessId {
+        self.primary.id()
+    }
+
+    /// Returns the shard identifier.
+    fn shard_id(&self) -> ShardId {
+        self.primary.shard_id()
+    }
+
+    /// Updates the processes known by this process.
+    /// The set of processes provided is already sorted by distance.
+    fn discover(
+        &mut self,
+        processes: Vec<(ProcessId, ShardId)>,
+    ) -> (bool, HashMap<ShardId, ProcessId>) {
+        let (shadow_connected, _) = self.shadow.discover(processes.clone());
+        let (primary_connected, closest_shard_process) =
+            self.primary.discover(processes);
+        (primary_connected && shadow_connected, closest_shard_process)
+    }
+
+    /// Submits a command issued by some client to both protocols.
+    fn submit(&mut self, dot: Option<Dot>, cmd: Command, time: &dyn SysTime) {
+        self.shadow.submit(dot, cmd.clone(), time);
+        self.primary.submit(dot, cmd, time);
+    }
+
+    /// Handles protocol messages.
+    fn handle(
+        &mut self,
+        from: ProcessId,
+        from_shard_id: ShardId,
+        msg: Self::Message,
+        time: &dyn SysTime,
+    ) {
+        match msg {
+            ShadowMessage::Primary(msg) => {
+                self.primary.handle(from, from_shard_id, msg, time)
+            }
+            ShadowMessage::Shadow(msg) => {
+                self.shadow.handle(from, from_shard_id, msg, time)
+            }
+        }
+    }
+
+    /// Handles periodic local events.
+    fn handle_event(&mut self, event: Self::PeriodicEvent, time: &dyn SysTime) {
+        match event {
+            ShadowEvent::Primary(event) => {
+                self.primary.handle_event(event, time)
+            }
+            ShadowEvent::Shadow(event) => self.shadow.handle_event(event, time),
+        }
+    }
+
+    /// Cancels a command previously submitted by some client.
+    fn cancel(&mut self, dot: Dot, time: &dyn SysTime) {
+        self.primary.cancel(dot, time);
+        self.shadow.cancel(dot, time);
+    }
+
+    /// Handles executed notifications (of the primary protocol).
+    fn handle_executed(
+        &mut self,
+        committed_and_executed: CommittedAndExecuted,
+        time: &dyn SysTime,
+    ) {
+        self.primary.handle_executed(committed_and_executed, time);
+    }
+
+    /// Returns a new action to be sent to other processes.
+    fn to_processes(&mut self) -> Option<Action<Self>> {
+        if let Some(action) = self.primary.to_processes() {
+            Some(match action {
+                Action::ToSend { target, msg } => Action::ToSend {
+                    target,
+                    msg: ShadowMessage::Primary(msg),
+                },
+                Action::ToForward { msg } => Action::ToForward {
+                    msg: ShadowMessage::Primary(msg),
+                },
+            })
+        } else {
+            self.shadow.to_processes().map(|action| match action {
+                Action::ToSend { target, msg } => Action::ToSend {
+                    target,
+                    msg: ShadowMessage::Shadow(msg),
+                },
+                Action::ToForward { msg } => Action::ToForward {
+                    msg: ShadowMessage::Shadow(msg),
+                },
+            })
+        }
+    }
+
+    /// Returns new execution info for executors.
+    fn to_executors(
+        &mut self,
+    ) -> Option<<Self::Executor as Executor>::ExecutionInfo> {
+        if let Some(info) = self.primary.to_executors() {
+            Some(ShadowExecutionInfo::Primary(info))
+        } else {
+            self.shadow.to_executors().map(ShadowExecutionInfo::Shadow)
+        }
+    }
+
+    fn to_violations(&mut self) -> Option<Violation> {
+        self.primary
+            .to_violations()
+            .or_else(|| self.shadow.to_violations())
+    }
+
+    fn parallel() -> bool {
+        P::parallel() && S::parallel()
+    }
+
+    fn leaderless() -> bool {
+        // clients submit commands as expected by the primary protocol
+        P::leaderless()
+    }
+
+    fn cancellable() -> bool {
+        // cancels are forwarded to both protocols
+        P::cancellable() && S::cancellable()
+    }
+
+    fn message_fingerprint() -> u64 {
+        // messages of both protocols are exchanged
+        P::message_fingerprint().rotate_left(1) ^ S::message_fingerprint()
+    }
+
+    fn metrics(&self) -> &ProtocolMetrics {
+        self.primary.metrics()
+    }
+
+    fn state_sizes(&self) -> StateSizes {
+        self.primary.state_sizes()
+    }
+}
+
+impl<P, S> Shadow<P, S>
+where
+    P: Protocol,
+    S: Protocol,
+{
+    /// Returns the primary protocol.
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    /// Returns the shadow protocol.
+    pub fn shadow(&self) -> &S {
+        &self.shadow
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShadowMessage<PM, SM> {
+    Primary(PM),
+    Shadow(SM),
+}
+
+impl<PM, SM> MessageIndex for ShadowMessage<PM, SM>
+where
+    PM: MessageIndex,
+    SM: MessageIndex,
+{
+    fn index(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::Primary(msg) => msg.index(),
+            Self::Shadow(msg) => msg.index(),
+        }
+    }
+}
+
+impl<PM, SM> MessagePriority for ShadowMessage<PM, SM>
+where
+    PM: MessagePriority,
+    SM: MessagePriority,
+{
+    fn high_priority(&self) -> bool {
+        match self {
+            Self::Primary(msg) => msg.high_priority(),
+            Self::Shadow(msg) => msg.high_priority(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShadowEvent<PE, SE> {
+    Primary(PE),
+    Shadow(SE),
+}
+
+impl<PE, SE> MessageIndex for ShadowEvent<PE, SE>
+where
+    PE: MessageIndex,
+    SE: MessageIndex,
+{
+    fn index(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::Primary(event) => event.index(),
+            Self::Shadow(event) => event.index(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{KeyGen, Workload};
+    use crate::executor::ExecutorMetricsKind;
+    use crate::planet::{Planet, Region};
+    use crate::protocol::Basic;
+    use crate::sim::Runner;
+
+    #[test]
+    fn shadow_basic() {
+        let planet = Planet::new();
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_gc_interval(Duration::from_millis(100));
+
+        // clients workload
+        let shard_count = 1;
+        let key_gen = KeyGen::ConflictPool {
+            pool_size: 1,
+            conflict_rate: 100,
+            pool_id: 0,
+        };
+        let keys_per_command = 1;
+        let commands_per_client = 100;
+        let payload_size = 1;
+        let workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            payload_size,
+        );
+        let clients_per_process = 2;
+
+        let process_regions = vec![
+            Region::new("asia-east1"),
+            Region::new("us-central1"),
+            Region::new("us-west1"),
+        ];
+        let client_regions = process_regions.clone();
+
+        // run `Basic` shadowed by itself
+        let mut runner: Runner<Shadow<Basic, Basic>> = Runner::new(
+            planet,
+            config,
+            workload,
+            clients_per_process,
+            process_regions,
+            client_regions,
+        );
+        let (metrics, _, clients_latencies) =
+            runner.run(Some(Duration::from_secs(1)));
+
+        // clients got the results of all commands
+        let issued: usize =
+            clients_latencies.values().map(|(issued, _)| issued).sum();
+        let total_commands = commands_per_client * clients_per_process * n;
+        assert_eq!(issued, total_commands);
+
+        // each process compared the results of all commands
+        for (_, executor_metrics) in metrics.values() {
+            let compared: usize = vec![
+                ExecutorMetricsKind::ShadowLag,
+                ExecutorMetricsKind::ShadowLead,
+            ]
+            .into_iter()
+            .filter_map(|kind| executor_metrics.get_collected(kind))
+            .map(|histogram| histogram.count())
+            .sum();
+            assert_eq!(compared, total_commands);
+        }
+    }
+}
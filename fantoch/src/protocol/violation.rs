@@ -0,0 +1,41 @@
+use crate::id::ProcessId;
+use std::fmt;
+
+/// Violation of an invariant of a protocol (or of its executor), e.g. a
+/// command being indexed twice by the executor. Instead of panicking, the
+/// handlers that detect a violation record it (see `Protocol::to_violations`
+/// and `Executor::to_violations`) together with a dump of the state needed to
+/// diagnose it, and skip the offending message. The run layer then decides
+/// whether the process should be kept alive (see
+/// `Config::keep_alive_on_violation`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub process_id: ProcessId,
+    pub reason: String,
+    pub state: String,
+}
+
+impl Violation {
+    /// Creates a new `Violation`.
+    pub fn new<R, S>(process_id: ProcessId, reason: R, state: S) -> Self
+    where
+        R: Into<String>,
+        S: Into<String>,
+    {
+        Self {
+            process_id,
+            reason: reason.into(),
+            state: state.into(),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p{}: {}\n   state: {}",
+            self.process_id, self.reason, self.state
+        )
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::RunError;
 use crate::warn;
 use color_eyre::Report;
 use std::fmt::Debug;
@@ -25,6 +26,38 @@ pub fn channel<M>(
     )
 }
 
+/// A channel with two priority classes: messages sent with high priority are
+/// always received before any pending message sent with low priority.
+#[derive(Debug)]
+pub struct PriorityChannelSender<M> {
+    high: ChannelSender<M>,
+    low: ChannelSender<M>,
+}
+
+#[derive(Debug)]
+pub struct PriorityChannelReceiver<M> {
+    high: ChannelReceiver<M>,
+    low: ChannelReceiver<M>,
+    high_closed: bool,
+    low_closed: bool,
+}
+
+pub fn priority_channel<M>(
+    channel_buffer_size: usize,
+) -> (PriorityChannelSender<M>, PriorityChannelReceiver<M>) {
+    let (high_tx, high_rx) = channel(channel_buffer_size);
+    let (low_tx, low_rx) = channel(channel_buffer_size);
+    (
+        PriorityChannelSender::from_senders(high_tx, low_tx),
+        PriorityChannelReceiver {
+            high: high_rx,
+            low: low_rx,
+            high_closed: false,
+            low_closed: false,
+        },
+    )
+}
+
 impl<M> ChannelSender<M>
 where
     M: Debug + 'static,
@@ -48,11 +81,11 @@ where
                 self.sender
                     .send(value)
                     .await
-                    .map_err(|e| Report::msg(e.to_string()))
+                    .map_err(|e| RunError::Channel(e.to_string()).into())
             }
             Err(e) => {
                 // otherwise, upstream the error
-                Err(Report::msg(e.to_string()))
+                Err(RunError::Channel(e.to_string()).into())
             }
         }
     }
@@ -65,6 +98,66 @@ impl<M> ChannelSender<M> {
     }
 }
 
+impl<M> PriorityChannelSender<M> {
+    pub fn from_senders(high: ChannelSender<M>, low: ChannelSender<M>) -> Self {
+        Self { high, low }
+    }
+
+    pub fn into_senders(self) -> (ChannelSender<M>, ChannelSender<M>) {
+        (self.high, self.low)
+    }
+}
+
+impl<M> PriorityChannelSender<M>
+where
+    M: Debug + 'static,
+{
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        let name = name.into();
+        self.high.set_name(format!("{}_high", name));
+        self.low.set_name(format!("{}_low", name));
+    }
+
+    pub async fn send(
+        &mut self,
+        value: M,
+        high_priority: bool,
+    ) -> Result<(), Report> {
+        if high_priority {
+            self.high.send(value).await
+        } else {
+            self.low.send(value).await
+        }
+    }
+}
+
+impl<M> PriorityChannelReceiver<M> {
+    pub fn into_receivers(self) -> (ChannelReceiver<M>, ChannelReceiver<M>) {
+        (self.high, self.low)
+    }
+
+    pub async fn recv(&mut self) -> Option<M> {
+        // since `biased` is used, the high priority channel is always polled
+        // first; even though both sender-ends are dropped together, messages
+        // may still be queued in one of the channels once the other one is
+        // closed, and so `None` is only returned once both are closed
+        loop {
+            tokio::select! {
+                biased;
+                msg = self.high.recv(), if !self.high_closed => match msg {
+                    Some(msg) => return Some(msg),
+                    None => self.high_closed = true,
+                },
+                msg = self.low.recv(), if !self.low_closed => match msg {
+                    Some(msg) => return Some(msg),
+                    None => self.low_closed = true,
+                },
+                else => return None,
+            }
+        }
+    }
+}
+
 impl<M> ChannelReceiver<M> {
     pub async fn recv(&mut self) -> Option<M> {
         self.receiver.recv().await
@@ -79,3 +172,54 @@ impl<T> Clone for ChannelSender<T> {
         }
     }
 }
+
+impl<T> Clone for PriorityChannelSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            high: self.high.clone(),
+            low: self.low.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn priority_channel() {
+        let (mut tx, mut rx) = super::priority_channel::<u64>(10);
+
+        // send low priority messages first, then high priority ones
+        tx.send(1, false).await.expect("message sent");
+        tx.send(2, false).await.expect("message sent");
+        tx.send(3, true).await.expect("message sent");
+        tx.send(4, true).await.expect("message sent");
+
+        // high priority messages are received first; order within each class
+        // is preserved
+        for expected in vec![3, 4, 1, 2] {
+            assert_eq!(rx.recv().await, Some(expected));
+        }
+
+        // once the sender is dropped, the receiver sees the channel closed
+        drop(tx);
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn priority_channel_closed() {
+        let (mut tx, mut rx) = super::priority_channel::<u64>(10);
+
+        // send messages of both classes and drop the sender
+        tx.send(1, false).await.expect("message sent");
+        tx.send(2, false).await.expect("message sent");
+        tx.send(3, true).await.expect("message sent");
+        drop(tx);
+
+        // low priority messages are still received after the high priority
+        // channel is closed
+        for expected in vec![3, 1, 2] {
+            assert_eq!(rx.recv().await, Some(expected));
+        }
+        assert_eq!(rx.recv().await, None);
+    }
+}
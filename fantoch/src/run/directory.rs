@@ -0,0 +1,259 @@
+use crate::id::{ProcessId, ShardId};
+use color_eyre::eyre::{eyre, Report, WrapErr};
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+const HTTP_PREFIX: &str = "http://";
+const COMMENT_PREFIX: char = '#';
+
+/// An entry in a `Directory`: where a process is (and in which region).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    pub process_id: ProcessId,
+    pub shard_id: ShardId,
+    pub region: String,
+    pub address: String,
+    pub client_address: String,
+}
+
+impl DirectoryEntry {
+    /// Returns the port in the process address.
+    pub fn port(&self) -> Option<u16> {
+        port(&self.address)
+    }
+
+    /// Returns the port in the client address.
+    pub fn client_port(&self) -> Option<u16> {
+        port(&self.client_address)
+    }
+}
+
+fn port(address: &str) -> Option<u16> {
+    address.rsplit(':').next()?.parse().ok()
+}
+
+/// A directory with the addresses of all processes in a deployment. Each line
+/// of its textual representation describes a process and has the form
+/// `PROCESS_ID SHARD_ID REGION ADDRESS CLIENT_ADDRESS`; empty lines and lines
+/// starting with '#' are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Directory {
+    entries: Vec<DirectoryEntry>,
+}
+
+impl Directory {
+    /// Creates a directory from a list of entries.
+    pub fn new(mut entries: Vec<DirectoryEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.process_id);
+        Self { entries }
+    }
+
+    /// Fetches the directory from `source`, which is either an HTTP endpoint
+    /// (e.g. `http://10.0.0.1:8000/directory`) or the path of a static file.
+    pub fn fetch(source: &str) -> Result<Self, Report> {
+        let contents = if source.starts_with(HTTP_PREFIX) {
+            http_get(source)?
+        } else {
+            std::fs::read_to_string(source).wrap_err_with(|| {
+                format!("reading directory file {:?}", source)
+            })?
+        };
+        Self::parse(&contents)
+    }
+
+    /// Parses the textual representation of a directory.
+    pub fn parse(contents: &str) -> Result<Self, Report> {
+        let mut entries = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(COMMENT_PREFIX) {
+                continue;
+            }
+            let parts: Vec<_> = line.split_whitespace().collect();
+            if parts.len() != 5 {
+                return Err(eyre!(
+                    "directory lines should have the form 'PROCESS_ID SHARD_ID REGION ADDRESS CLIENT_ADDRESS'; found {:?}",
+                    line
+                ));
+            }
+            let process_id =
+                parts[0].parse::<ProcessId>().wrap_err_with(|| {
+                    format!("invalid process id in {:?}", line)
+                })?;
+            let shard_id = parts[1]
+                .parse::<ShardId>()
+                .wrap_err_with(|| format!("invalid shard id in {:?}", line))?;
+            if entries
+                .iter()
+                .any(|entry: &DirectoryEntry| entry.process_id == process_id)
+            {
+                return Err(eyre!(
+                    "duplicate process {} in directory",
+                    process_id
+                ));
+            }
+            entries.push(DirectoryEntry {
+                process_id,
+                shard_id,
+                region: parts[2].to_string(),
+                address: parts[3].to_string(),
+                client_address: parts[4].to_string(),
+            });
+        }
+        Ok(Self::new(entries))
+    }
+
+    /// Returns all entries, sorted by process identifier.
+    pub fn entries(&self) -> &[DirectoryEntry] {
+        &self.entries
+    }
+
+    /// Returns the entry of `process_id`, if any.
+    pub fn entry(&self, process_id: ProcessId) -> Option<&DirectoryEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.process_id == process_id)
+    }
+
+    /// Returns the addresses `process_id` should connect to: the ones of all
+    /// other processes in its shard, and, in each other shard, the one of the
+    /// process in the same region.
+    pub fn peers(&self, process_id: ProcessId) -> Result<Vec<String>, Report> {
+        let me = self.entry(process_id).ok_or_else(|| {
+            eyre!("process {} not found in directory", process_id)
+        })?;
+        let peers = self
+            .entries
+            .iter()
+            .filter(|entry| entry.process_id != process_id)
+            .filter(|entry| {
+                entry.shard_id == me.shard_id || entry.region == me.region
+            })
+            .map(|entry| entry.address.clone())
+            .collect();
+        Ok(peers)
+    }
+
+    /// Returns the client addresses of all processes, which can be used by
+    /// clients as candidates to connect to.
+    pub fn client_addresses(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| entry.client_address.clone())
+            .collect()
+    }
+}
+
+impl fmt::Display for Directory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "{} {} {} {} {}",
+                entry.process_id,
+                entry.shard_id,
+                entry.region,
+                entry.address,
+                entry.client_address
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Performs a (blocking) HTTP GET, returning the response body. This is only
+// used once at startup, so a minimal HTTP/1.0 client is enough.
+fn http_get(url: &str) -> Result<String, Report> {
+    let rest = url.trim_start_matches(HTTP_PREFIX);
+    let (host, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let mut stream = TcpStream::connect(host)
+        .wrap_err_with(|| format!("connecting to directory at {:?}", url))?;
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\n\r\n", path, host)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(|| {
+        eyre!("malformed response from directory at {:?}", url)
+    })?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(eyre!("directory at {:?} replied with {:?}", url, status));
+    }
+    Ok(body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    const DIRECTORY: &str = "
+        # two shards, three regions
+        1 0 eu 10.0.0.1:3000 10.0.0.1:4000
+        2 0 us 10.0.0.2:3000 10.0.0.2:4000
+        3 0 ap 10.0.0.3:3000 10.0.0.3:4000
+        4 1 eu 10.0.0.1:3001 10.0.0.1:4001
+        5 1 us 10.0.0.2:3001 10.0.0.2:4001
+        6 1 ap 10.0.0.3:3001 10.0.0.3:4001
+    ";
+
+    #[test]
+    fn directory_peers() {
+        let directory = Directory::parse(DIRECTORY).expect("valid directory");
+        assert_eq!(directory.entries().len(), 6);
+
+        // peers in the same shard, plus the one in the same region
+        assert_eq!(
+            directory.peers(1).unwrap(),
+            vec!["10.0.0.2:3000", "10.0.0.3:3000", "10.0.0.1:3001"]
+        );
+        assert_eq!(
+            directory.peers(5).unwrap(),
+            vec!["10.0.0.2:3000", "10.0.0.1:3001", "10.0.0.3:3001"]
+        );
+        assert!(directory.peers(7).is_err());
+        let entry = directory.entry(4).expect("process 4 exists");
+        assert_eq!(entry.port(), Some(3001));
+        assert_eq!(entry.client_port(), Some(4001));
+        assert_eq!(directory.client_addresses().len(), 6);
+
+        // parsing what's displayed yields the same directory
+        let displayed = directory.to_string();
+        assert_eq!(Directory::parse(&displayed).unwrap(), directory);
+    }
+
+    #[test]
+    fn directory_invalid() {
+        assert!(Directory::parse("1 0 eu 10.0.0.1:3000").is_err());
+        assert!(Directory::parse("a 0 eu 10.0.0.1:3000 10.0.0.1:4000").is_err());
+        let duplicate = "1 0 eu 10.0.0.1:3000 10.0.0.1:4000\n1 0 us 10.0.0.2:3000 10.0.0.2:4000";
+        assert!(Directory::parse(duplicate).is_err());
+    }
+
+    #[test]
+    fn directory_fetch() {
+        // serve the directory over HTTP once
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            write!(
+                stream,
+                "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                DIRECTORY.len(),
+                DIRECTORY
+            )
+            .unwrap();
+        });
+        let url = format!("http://{}/directory", address);
+        let directory = Directory::fetch(&url).expect("directory fetched");
+        server.join().unwrap();
+        assert_eq!(directory, Directory::parse(DIRECTORY).unwrap());
+    }
+}
@@ -60,6 +60,10 @@
 /// Other notes:
 /// - the runner allows `Protocol` workers to share state; however, it assumes
 ///   that `Executor` workers never do
+/// - if a polling seed is set, `Protocol` workers poll their incoming channels
+///   in an order determined by that seed (instead of tokio's random order);
+///   along with a single-threaded runtime, this makes runs easier to reproduce
+///   when debugging
 // This module contains the "runner" prelude.
 mod prelude;
 
@@ -75,24 +79,33 @@ pub mod chan;
 // This module contains the implementaion on client-side and server-side logic.
 pub mod task;
 
+// This module contains the definition of `Directory`, used by processes and
+// clients to discover the addresses of their peers.
+pub mod directory;
+
 const CONNECT_RETRIES: usize = 100;
 
-use crate::client::Workload;
+use crate::client::{Workload, WorkloadMix};
 use crate::config::Config;
+use crate::error::RunError;
 use crate::executor::Executor;
 use crate::hash_map::HashMap;
+use crate::hash_set::HashSet;
 use crate::id::{AtomicDotGen, ClientId, ProcessId, ShardId};
 use crate::info;
-use crate::protocol::Protocol;
-use color_eyre::Report;
+use crate::protocol::{Protocol, StateSizes};
+use color_eyre::eyre::{eyre, Report};
 use futures::stream::{FuturesUnordered, StreamExt};
 use prelude::*;
+use rw::TcpOptions;
 use std::fmt::Debug;
-use std::net::IpAddr;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
+use task::server::metrics_logger::MetricsReporter;
 use tokio::net::ToSocketAddrs;
-use tokio::sync::Semaphore;
+use tokio::sync::{watch, Semaphore};
 
 pub async fn process<P, A>(
     process_id: ProcessId,
@@ -103,7 +116,7 @@ pub async fn process<P, A>(
     client_port: u16,
     addresses: Vec<(A, Option<Duration>)>,
     config: Config,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     tcp_buffer_size: usize,
     tcp_flush_interval: Option<Duration>,
     process_channel_buffer_size: usize,
@@ -112,17 +125,41 @@ pub async fn process<P, A>(
     executors: usize,
     multiplexing: usize,
     execution_log: Option<String>,
+    execution_order_log: Option<String>,
     ping_interval: Option<Duration>,
     metrics_file: Option<String>,
+    graph_snapshot_dir: Option<String>,
+    polling_seed: Option<u64>,
+    epoch_file: Option<String>,
+    canary: Option<String>,
+    control_port: Option<u16>,
 ) -> Result<(), Report>
 where
     P: Protocol + Send + 'static, // TODO what does this 'static do?
     A: ToSocketAddrs + Debug + Clone,
 {
+    // maybe start the control listener, whose queries are answered by the
+    // workers through the inspect channel
+    let inspect_chan = match control_port {
+        Some(control_port) => {
+            let inspect_chan = task::server::control::start_listener::<P>(
+                process_id,
+                shard_id,
+                ip,
+                control_port,
+                workers,
+            )
+            .await
+            .map_err(RunError::log_failure)?;
+            Some(inspect_chan)
+        }
+        None => None,
+    };
+
     // create semaphore for callers that don't care about the connected
     // notification
     let semaphore = Arc::new(Semaphore::new(0));
-    process_with_notify_and_inspect::<P, A, ()>(
+    process_with_notify_and_inspect::<P, A, StateSizes>(
         process_id,
         shard_id,
         sorted_processes,
@@ -131,7 +168,7 @@ where
         client_port,
         addresses,
         config,
-        tcp_nodelay,
+        tcp_options,
         tcp_buffer_size,
         tcp_flush_interval,
         process_channel_buffer_size,
@@ -140,12 +177,32 @@ where
         executors,
         multiplexing,
         execution_log,
+        execution_order_log,
         ping_interval,
         metrics_file,
+        graph_snapshot_dir,
+        polling_seed,
+        epoch_file,
+        canary,
         semaphore,
-        None,
+        inspect_chan,
     )
     .await
+    .map_err(RunError::log_failure)
+}
+
+/// Runs several independent processes (e.g. of different shards, each with its
+/// own ports) in the same runtime, returning as soon as one of them fails.
+pub async fn processes<F>(processes: Vec<F>) -> Result<(), Report>
+where
+    F: Future<Output = Result<(), Report>> + Send + 'static,
+{
+    let mut processes: FuturesUnordered<_> =
+        processes.into_iter().map(task::spawn).collect();
+    while let Some(result) = processes.next().await {
+        result??;
+    }
+    Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -158,7 +215,7 @@ async fn process_with_notify_and_inspect<P, A, R>(
     client_port: u16,
     addresses: Vec<(A, Option<Duration>)>,
     config: Config,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     tcp_buffer_size: usize,
     tcp_flush_interval: Option<Duration>,
     process_channel_buffer_size: usize,
@@ -167,8 +224,13 @@ async fn process_with_notify_and_inspect<P, A, R>(
     executors: usize,
     multiplexing: usize,
     execution_log: Option<String>,
+    execution_order_log: Option<String>,
     ping_interval: Option<Duration>,
     metrics_file: Option<String>,
+    graph_snapshot_dir: Option<String>,
+    polling_seed: Option<u64>,
+    epoch_file: Option<String>,
+    canary: Option<String>,
     connected: Arc<Semaphore>,
     inspect_chan: Option<InspectReceiver<P, R>>,
 ) -> Result<(), Report>
@@ -187,6 +249,21 @@ where
         panic!("running non-parallel executor with {} executors", executors)
     }
 
+    // panic if there's a latency budget that the executor can't enforce
+    if config.executor_latency_budget().is_some()
+        && !P::Executor::supports_latency_budget()
+    {
+        panic!(
+            "running executor that doesn't support latency budgets with one"
+        );
+    }
+
+    // panic if the stability threshold is unsafe (such thresholds can only be
+    // explored in the simulator)
+    if !config.tempo_stability_threshold_is_safe() {
+        panic!("running with a stability threshold lower than the safe one");
+    }
+
     // panic if protocol is leaderless and there's a leader
     if P::leaderless() && config.leader().is_some() {
         panic!("running leaderless protocol with a leader");
@@ -200,42 +277,104 @@ where
     // check ports are different
     assert!(port != client_port);
 
+    // compute the epoch of this incarnation of the process
+    let epoch = task::server::epoch::next_epoch(epoch_file.as_ref())?;
+    info!("p{}: epoch {}", process_id, epoch);
+
     // ---------------------
     // start process listener
     let listener = task::listen((ip, port)).await?;
 
     // create forward channels: reader -> workers
-    let (reader_to_workers, reader_to_workers_rxs) = ReaderToWorkers::<P>::new(
-        "reader_to_workers",
-        process_channel_buffer_size,
-        workers,
-    );
+    let (mut reader_to_workers, reader_to_workers_rxs) =
+        ReaderToWorkers::<P>::new(
+            "reader_to_workers",
+            process_channel_buffer_size,
+            workers,
+        );
+    reader_to_workers.set_assignment(config.worker_assignment());
 
     // create forward channels: worker /readers -> executors
+    let to_executors_buffer_size = config
+        .to_executors_buffer_size()
+        .unwrap_or(process_channel_buffer_size);
     let (to_executors, to_executors_rxs) = ToExecutors::<P>::new(
         "to_executors",
-        process_channel_buffer_size,
+        to_executors_buffer_size,
         executors,
     );
 
+    // maybe create metrics logger
+    let (
+        worker_to_metrics_logger,
+        executor_to_metrics_logger,
+        writer_to_metrics_logger,
+        acceptor_to_metrics_logger,
+    ) = if let Some(metrics_file) = metrics_file {
+        let (worker_to_metrics_logger, from_workers) =
+            chan::channel(process_channel_buffer_size);
+        let (executor_to_metrics_logger, from_executors) =
+            chan::channel(process_channel_buffer_size);
+        let (writer_to_metrics_logger, from_writers) =
+            chan::channel(process_channel_buffer_size);
+        let (acceptor_to_metrics_logger, from_acceptors) =
+            chan::channel(process_channel_buffer_size);
+        // workers and executors report their metrics whenever the metrics
+        // logger requests a new snapshot
+        let (snapshot_requests_tx, snapshot_requests) = watch::channel(0);
+        let worker_to_metrics_logger = MetricsReporter::new(
+            worker_to_metrics_logger,
+            snapshot_requests.clone(),
+        );
+        let executor_to_metrics_logger =
+            MetricsReporter::new(executor_to_metrics_logger, snapshot_requests);
+        task::spawn(task::server::metrics_logger::metrics_logger_task(
+            metrics_file,
+            workers,
+            executors,
+            snapshot_requests_tx,
+            from_workers,
+            from_executors,
+            from_writers,
+            from_acceptors,
+        ));
+        (
+            Some(worker_to_metrics_logger),
+            Some(executor_to_metrics_logger),
+            Some(writer_to_metrics_logger),
+            Some(acceptor_to_metrics_logger),
+        )
+    } else {
+        (None, None, None, None)
+    };
+
     // connect to all processes
     let (ips, to_writers) = task::server::connect_to_all::<A, P>(
         process_id,
         shard_id,
+        epoch,
         config,
         listener,
         addresses,
         reader_to_workers.clone(),
         to_executors.clone(),
         CONNECT_RETRIES,
-        tcp_nodelay,
+        tcp_options,
         tcp_buffer_size,
         tcp_flush_interval,
         process_channel_buffer_size,
         multiplexing,
+        writer_to_metrics_logger,
     )
     .await?;
 
+    // save the shard of each connected process (`ips` are moved to the ping
+    // task) so that the sorted processes can be validated
+    let connected_shards: HashMap<_, _> = ips
+        .iter()
+        .map(|(peer_id, (peer_shard_id, _, _))| (*peer_id, *peer_shard_id))
+        .collect();
+
     // get sorted processes (maybe from ping task)
     let sorted_processes = if let Some(sorted_processes) = sorted_processes {
         // in this case, we already have the sorted processes, so simply span
@@ -264,17 +403,23 @@ where
         ask_ping_task(to_ping).await
     };
 
-    // check that we have n processes (all in my shard), plus one connection to
-    // each other shard
-    assert_eq!(
-        sorted_processes.len(),
-        config.n() + config.shard_count() - 1,
-        "sorted processes count should be n + shards - 1"
-    );
+    // fail fast if the sorted processes are inconsistent with the processes
+    // we're connected to
+    check_sorted_processes(
+        process_id,
+        shard_id,
+        &config,
+        &connected_shards,
+        &sorted_processes,
+    )?;
 
     // ---------------------
-    // start client listener
-    let client_listener = task::listen((ip, client_port)).await?;
+    // start client listeners (one per acceptor)
+    let client_acceptors = config.client_acceptors().unwrap_or(1);
+    let client_listeners = task::listen_reuseport(
+        SocketAddr::new(ip, client_port),
+        client_acceptors,
+    )?;
 
     // create atomic dot generator to be used by clients in case the protocol is
     // leaderless:
@@ -290,26 +435,36 @@ where
     };
 
     // create forward channels: periodic task -> workers
-    let (periodic_to_workers, periodic_to_workers_rxs) = PeriodicToWorkers::new(
-        "periodic_to_workers",
-        process_channel_buffer_size,
-        workers,
-    );
+    let periodic_to_workers_buffer_size = config
+        .periodic_to_workers_buffer_size()
+        .unwrap_or(process_channel_buffer_size);
+    let (mut periodic_to_workers, periodic_to_workers_rxs) =
+        PeriodicToWorkers::new(
+            "periodic_to_workers",
+            periodic_to_workers_buffer_size,
+            workers,
+        );
+    periodic_to_workers.set_assignment(config.worker_assignment());
 
     // create forward channels: executors -> workers
-    let (executors_to_workers, executors_to_workers_rxs) =
+    let (mut executors_to_workers, executors_to_workers_rxs) =
         ExecutorsToWorkers::new(
             "executors_to_workers",
             process_channel_buffer_size,
             workers,
         );
+    executors_to_workers.set_assignment(config.worker_assignment());
 
     // create forward channels: client -> workers
-    let (client_to_workers, client_to_workers_rxs) = ClientToWorkers::new(
+    let client_to_workers_buffer_size = config
+        .client_to_workers_buffer_size()
+        .unwrap_or(client_channel_buffer_size);
+    let (mut client_to_workers, client_to_workers_rxs) = ClientToWorkers::new(
         "client_to_workers",
-        client_channel_buffer_size,
+        client_to_workers_buffer_size,
         workers,
     );
+    client_to_workers.set_assignment(config.worker_assignment());
 
     // create forward channels: client -> executors
     let (client_to_executors, client_to_executors_rxs) = ClientToExecutors::new(
@@ -318,44 +473,41 @@ where
         executors,
     );
 
+    // if stale reads are enabled, read-only commands are served by the
+    // executors
+    let stale_read_index = if config.stale_reads() {
+        Some(P::Executor::stale_read_index as StaleReadIndex)
+    } else {
+        None
+    };
+
     // start client listener
     task::server::client::start_listener(
         process_id,
         shard_id,
-        client_listener,
+        epoch,
+        client_listeners,
         atomic_dot_gen,
         client_to_workers,
         client_to_executors,
-        tcp_nodelay,
+        tcp_options,
         client_channel_buffer_size,
+        config.executor_ordered_delivery(),
+        stale_read_index,
+        P::cancellable(),
+        config.trace_sample_rate(),
+        config.client_results_flush_delay(),
+        config.client_pending_shards().unwrap_or(1),
+        config.server_delay_injection(),
+        acceptor_to_metrics_logger,
     );
 
-    // maybe create metrics logger
-    let (worker_to_metrics_logger, executor_to_metrics_logger) =
-        if let Some(metrics_file) = metrics_file {
-            let (worker_to_metrics_logger, from_workers) =
-                chan::channel(process_channel_buffer_size);
-            let (executor_to_metrics_logger, from_executors) =
-                chan::channel(process_channel_buffer_size);
-            task::spawn(task::server::metrics_logger::metrics_logger_task(
-                metrics_file,
-                from_workers,
-                from_executors,
-            ));
-            (
-                Some(worker_to_metrics_logger),
-                Some(executor_to_metrics_logger),
-            )
-        } else {
-            (None, None)
-        };
-
     // create process
     let (mut process, process_events) = P::new(process_id, shard_id, config);
 
     // discover processes
     let (connect_ok, closest_shard_process) =
-        process.discover(sorted_processes);
+        process.discover(sorted_processes.clone());
     assert!(connect_ok, "process should have discovered successfully");
 
     // spawn periodic task
@@ -375,6 +527,21 @@ where
         shard_writers.insert(shard_id, writers);
     }
 
+    // maybe start mirroring executors to a canary
+    let to_canary = canary.map(|canary| {
+        let mut tx = task::spawn_consumer(process_channel_buffer_size, |rx| {
+            task::server::canary::mirror_task::<P>(
+                canary,
+                tcp_options,
+                tcp_buffer_size,
+                CONNECT_RETRIES,
+                rx,
+            )
+        });
+        tx.set_name("to_canary");
+        tx
+    });
+
     // start executors
     task::server::executor::start_executors::<P>(
         process_id,
@@ -384,8 +551,13 @@ where
         client_to_executors_rxs,
         executors_to_workers,
         shard_writers,
+        to_writers.clone(),
         to_executors.clone(),
         executor_to_metrics_logger,
+        graph_snapshot_dir,
+        to_canary,
+        execution_order_log,
+        process_channel_buffer_size,
     );
 
     // start process workers
@@ -396,11 +568,15 @@ where
         periodic_to_workers_rxs,
         executors_to_workers_rxs,
         to_writers,
+        sorted_processes,
+        config.farthest_first(),
         reader_to_workers,
         to_executors,
         process_channel_buffer_size,
         execution_log,
         worker_to_metrics_logger,
+        polling_seed,
+        config.keep_alive_on_violation(),
     );
     info!("process {} started", process_id);
 
@@ -415,17 +591,55 @@ where
     Ok(())
 }
 
+/// Runs the canary of process `process_id`, which receives (on `port`) a
+/// mirrored copy of the execution infos handled by the executors of that
+/// process (see the `canary` argument of `process`). The canary doesn't
+/// participate in quorums: it only executes the infos received and compares
+/// its results with the ones of the process, logging the ones that diverge.
+pub async fn canary<P>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    ip: IpAddr,
+    port: u16,
+    config: Config,
+    tcp_options: TcpOptions,
+    tcp_buffer_size: usize,
+    executors: usize,
+) -> Result<(), Report>
+where
+    P: Protocol + Send + 'static,
+{
+    task::server::canary::canary_task::<P>(
+        process_id,
+        shard_id,
+        ip,
+        port,
+        config,
+        tcp_options,
+        tcp_buffer_size,
+        executors,
+    )
+    .await
+    .map_err(RunError::log_failure)
+}
+
 pub async fn client<A>(
     ids: Vec<ClientId>,
-    addresses: Vec<A>,
+    addresses: Vec<(A, Option<Duration>)>,
+    detect_closest: bool,
     interval: Option<Duration>,
-    workload: Workload,
+    workload_mix: WorkloadMix,
     batch_max_size: usize,
     batch_max_delay: Duration,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     channel_buffer_size: usize,
     status_frequency: Option<usize>,
+    stats_interval: Option<Duration>,
+    max_run_time: Option<Duration>,
+    shutdown_timeout: Duration,
+    command_timeout: Option<Duration>,
     metrics_file: Option<String>,
+    traces_file: Option<String>,
 ) -> Result<(), Report>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
@@ -433,17 +647,116 @@ where
     task::client::client(
         ids,
         addresses,
+        detect_closest,
         interval,
-        workload,
+        workload_mix,
         batch_max_size,
         batch_max_delay,
         CONNECT_RETRIES,
-        tcp_nodelay,
+        tcp_options,
         channel_buffer_size,
         status_frequency,
+        stats_interval,
+        max_run_time,
+        shutdown_timeout,
+        command_timeout,
         metrics_file,
+        traces_file,
     )
     .await
+    .map_err(RunError::log_failure)
+}
+
+pub async fn gateway<A>(
+    client_id: ClientId,
+    addresses: Vec<A>,
+    ip: IpAddr,
+    port: u16,
+    grpc_port: Option<u16>,
+    tcp_options: TcpOptions,
+    channel_buffer_size: usize,
+) -> Result<(), Report>
+where
+    A: ToSocketAddrs + Clone + Debug,
+{
+    task::client::gateway(
+        client_id,
+        addresses,
+        ip,
+        port,
+        grpc_port,
+        CONNECT_RETRIES,
+        tcp_options,
+        channel_buffer_size,
+    )
+    .await
+}
+
+// Checks that `sorted_processes` start with this process, contain all `n`
+// processes in its shard plus one process of each other shard, and that each
+// of them (other than this process) is one we're connected to.
+fn check_sorted_processes(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    config: &Config,
+    connected_shards: &HashMap<ProcessId, ShardId>,
+    sorted_processes: &[(ProcessId, ShardId)],
+) -> Result<(), Report> {
+    let expected = config.n() + config.shard_count() - 1;
+    if sorted_processes.len() != expected {
+        return Err(eyre!(
+            "sorted processes {:?} should have n + shards - 1 = {} processes",
+            sorted_processes,
+            expected
+        ));
+    }
+    if sorted_processes.first() != Some(&(process_id, shard_id)) {
+        return Err(eyre!(
+            "sorted processes {:?} should start with process {} of shard {}",
+            sorted_processes,
+            process_id,
+            shard_id
+        ));
+    }
+
+    let mut seen = HashSet::with_capacity(sorted_processes.len());
+    let mut other_shards = HashSet::with_capacity(config.shard_count());
+    for (peer_id, peer_shard_id) in sorted_processes.iter().skip(1) {
+        if !seen.insert(*peer_id) || *peer_id == process_id {
+            return Err(eyre!(
+                "process {} appears more than once in sorted processes {:?}",
+                peer_id,
+                sorted_processes
+            ));
+        }
+        match connected_shards.get(peer_id) {
+            Some(connected_shard_id) if connected_shard_id == peer_shard_id => {
+            }
+            Some(connected_shard_id) => {
+                return Err(eyre!(
+                    "process {} is in shard {} in sorted processes but connected as part of shard {}",
+                    peer_id,
+                    peer_shard_id,
+                    connected_shard_id
+                ));
+            }
+            None => {
+                return Err(eyre!(
+                    "process {} in sorted processes is not one of the processes connected to: {:?}",
+                    peer_id,
+                    connected_shards
+                ));
+            }
+        }
+        if *peer_shard_id != shard_id && !other_shards.insert(*peer_shard_id) {
+            return Err(eyre!(
+                "sorted processes {:?} should have a single process of shard {}",
+                sorted_processes,
+                peer_shard_id
+            ));
+        }
+    }
+    Ok(())
 }
 
 async fn ask_ping_task(
@@ -498,8 +811,218 @@ pub mod tests {
             .unwrap_or_default() as usize
     }
 
+    #[test]
+    fn check_sorted_processes_test() {
+        // process 4 of shard 1, connected to 5 and 6 (shard 1) and 1 (shard 0)
+        let mut config = Config::new(3, 1);
+        config.set_shard_count(2);
+        let connected: HashMap<_, _> =
+            vec![(5, 1), (6, 1), (1, 0)].into_iter().collect();
+        let check = |sorted: Vec<(ProcessId, ShardId)>| {
+            check_sorted_processes(4, 1, &config, &connected, &sorted)
+        };
+
+        assert!(check(vec![(4, 1), (1, 0), (5, 1), (6, 1)]).is_ok());
+        // missing process
+        assert!(check(vec![(4, 1), (1, 0), (5, 1)]).is_err());
+        // not starting with self
+        assert!(check(vec![(1, 0), (4, 1), (5, 1), (6, 1)]).is_err());
+        // not connected to process 2
+        assert!(check(vec![(4, 1), (2, 0), (5, 1), (6, 1)]).is_err());
+        // wrong shard
+        assert!(check(vec![(4, 1), (1, 1), (5, 1), (6, 1)]).is_err());
+        // duplicate process
+        assert!(check(vec![(4, 1), (1, 0), (5, 1), (5, 1)]).is_err());
+    }
+
     #[test]
     fn run_basic_test() {
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            None,
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn run_basic_client_acceptors_test() {
+        let client_acceptors = Some(4);
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            client_acceptors,
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn run_basic_stale_reads_test() {
+        let stale_reads = true;
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            None,
+            stale_reads,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn run_basic_trace_test() {
+        let trace_sample_rate = Some(10);
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            None,
+            false,
+            trace_sample_rate,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn run_basic_results_flush_delay_test() {
+        let results_flush_delay = Some(Duration::from_micros(500));
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            None,
+            false,
+            None,
+            results_flush_delay,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn run_basic_pending_shards_test() {
+        let pending_shards = Some(2);
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            None,
+            false,
+            None,
+            None,
+            pending_shards,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn run_basic_delay_injection_test() {
+        let delay_injection = Some((10, Duration::from_millis(20)));
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            None,
+            false,
+            None,
+            None,
+            None,
+            delay_injection,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn run_basic_message_compression_test() {
+        let message_compression = Some(64);
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            None,
+            false,
+            None,
+            None,
+            None,
+            None,
+            message_compression,
+            None,
+        );
+    }
+
+    #[test]
+    fn run_basic_delivery_window_test() {
+        // a small window so that writers wait for acks
+        let delivery_window = Some(4);
+        run_basic(
+            tokio_test_runtime(),
+            None,
+            None,
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            delivery_window,
+        );
+    }
+
+    #[test]
+    fn run_basic_deterministic_test() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .expect("tokio runtime build should work");
+        let polling_seed = Some(42);
+        run_basic(
+            runtime,
+            polling_seed,
+            None,
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[allow(dead_code)]
+    fn run_basic(
+        runtime: tokio::runtime::Runtime,
+        polling_seed: Option<u64>,
+        client_acceptors: Option<usize>,
+        stale_reads: bool,
+        trace_sample_rate: Option<usize>,
+        results_flush_delay: Option<Duration>,
+        pending_shards: Option<usize>,
+        delay_injection: Option<(usize, Duration)>,
+        message_compression: Option<usize>,
+        delivery_window: Option<usize>,
+    ) {
         use crate::client::KeyGen;
 
         // config
@@ -513,6 +1036,30 @@ pub mod tests {
         // there's a single shard
         config.set_shard_count(1);
 
+        // set the number of client acceptors
+        config.set_client_acceptors(client_acceptors);
+
+        // set whether read-only commands are served from the local store
+        config.set_stale_reads(stale_reads);
+
+        // set the rate at which commands are traced
+        config.set_trace_sample_rate(trace_sample_rate);
+
+        // set the delay between flushes of command results
+        config.set_client_results_flush_delay(results_flush_delay);
+
+        // set the number of tasks tracking the commands of each connection
+        config.set_client_pending_shards(pending_shards);
+
+        // set the delay injected at processes
+        config.set_server_delay_injection(delay_injection);
+
+        // set whether messages between processes are compressed
+        config.set_message_compression(message_compression);
+
+        // set whether messages between processes are delivered at least once
+        config.set_delivery_window(delivery_window);
+
         // create workload
         let keys_per_command = 1;
         let shard_count = 1;
@@ -521,16 +1068,22 @@ pub mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate,
             pool_size,
+            pool_id: 0,
         };
         let commands_per_client = 100;
         let payload_size = 1;
-        let workload = Workload::new(
+        let mut workload = Workload::new(
             shard_count,
             key_gen,
             keys_per_command,
             commands_per_client,
             payload_size,
         );
+        if stale_reads {
+            // with stale reads, only commands with writes go through the
+            // protocol
+            workload.set_read_only_percentage(50);
+        }
 
         let clients_per_process = 3;
         let workers = 2;
@@ -538,7 +1091,7 @@ pub mod tests {
         let extra_run_time = Some(Duration::from_secs(5));
 
         // run test and get total stable commands
-        let total_stable_count = tokio_test_runtime()
+        let total_stable_count = runtime
             .block_on(
                 run_test_with_inspect_fun::<crate::protocol::Basic, usize>(
                     config,
@@ -548,6 +1101,7 @@ pub mod tests {
                     executors,
                     Some(inspect_stable_commands),
                     extra_run_time,
+                    polling_seed,
                 ),
             )
             .expect("run should complete successfully")
@@ -557,7 +1111,12 @@ pub mod tests {
 
         // get that all commands stablized at all processes
         let total_commands = n * clients_per_process * commands_per_client;
-        assert!(total_stable_count == total_commands * n);
+        if stale_reads {
+            // read-only commands are never stable
+            assert!(total_stable_count < total_commands * n);
+        } else {
+            assert!(total_stable_count == total_commands * n);
+        }
     }
 
     pub fn tokio_test_runtime() -> tokio::runtime::Runtime {
@@ -580,6 +1139,7 @@ pub mod tests {
         executors: usize,
         inspect_fun: Option<fn(&P) -> R>,
         extra_run_time: Option<Duration>,
+        polling_seed: Option<u64>,
     ) -> Result<HashMap<ProcessId, Vec<R>>, Report>
     where
         P: Protocol + Send + 'static,
@@ -591,7 +1151,7 @@ pub mod tests {
         let localhost = "127.0.0.1"
             .parse::<IpAddr>()
             .expect("127.0.0.1 should be a valid ip");
-        let tcp_nodelay = true;
+        let tcp_options = TcpOptions::new(true);
         let tcp_buffer_size = 1024;
         let tcp_flush_interval = Some(Duration::from_millis(1));
         let process_channel_buffer_size = 10000;
@@ -738,7 +1298,7 @@ pub mod tests {
                     client_port,
                     addresses,
                     config,
-                    tcp_nodelay,
+                    tcp_options,
                     tcp_buffer_size,
                     tcp_flush_interval,
                     process_channel_buffer_size,
@@ -747,8 +1307,13 @@ pub mod tests {
                     executors,
                     multiplexing,
                     execution_log,
+                    None,
                     ping_interval,
                     Some(metrics_file),
+                    None,
+                    polling_seed,
+                    None,
+                    None,
                     semaphore.clone(),
                     Some(inspect),
                 ),
@@ -781,9 +1346,12 @@ pub mod tests {
                     .chain(std::iter::once(process_id))
                     .map(|peer_id| {
                         let client_port = *client_ports.get(&peer_id).unwrap();
-                        format!("localhost:{}", client_port)
+                        let address = format!("localhost:{}", client_port);
+                        let delay = None;
+                        (address, delay)
                     })
                     .collect();
+                let detect_closest = false;
 
                 // compute interval:
                 // - if the process id is even, then issue a command every 2ms
@@ -800,19 +1368,32 @@ pub mod tests {
 
                 // spawn client
                 let status_frequency = None;
+                let stats_interval = None;
+                let max_run_time = None;
+                let shutdown_timeout = Duration::from_secs(1);
+                let command_timeout = None;
                 let metrics_file =
                     Some(format!(".metrics_client_{}", process_id));
+                let traces_file = config
+                    .trace_sample_rate()
+                    .map(|_| format!(".traces_client_{}", process_id));
                 tokio::task::spawn(client(
                     client_ids,
                     addresses,
+                    detect_closest,
                     interval,
-                    workload,
+                    WorkloadMix::single(workload),
                     batch_max_size,
                     batch_max_delay,
-                    tcp_nodelay,
+                    tcp_options,
                     client_channel_buffer_size,
                     status_frequency,
+                    stats_interval,
+                    max_run_time,
+                    shutdown_timeout,
+                    command_timeout,
                     metrics_file,
+                    traces_file,
                 ))
             })
             .collect();
@@ -850,11 +1431,11 @@ pub mod tests {
 
     async fn gather_workers_replies<R>(
         workers: usize,
-        reply_chan: &mut chan::ChannelReceiver<R>,
+        reply_chan: &mut chan::ChannelReceiver<(usize, R)>,
     ) -> Vec<R> {
         let mut replies = Vec::with_capacity(workers);
         for _ in 0..workers {
-            let reply = reply_chan
+            let (_worker_index, reply) = reply_chan
                 .recv()
                 .await
                 .expect("reply from process 1 should work");
@@ -1,3 +1,4 @@
+use crate::load_balance::{self, WorkerAssignment};
 use crate::run::chan::{self, ChannelReceiver, ChannelSender};
 use color_eyre::Report;
 use std::fmt::Debug;
@@ -10,6 +11,7 @@ pub trait PoolIndex {
 pub struct ToPool<M> {
     name: String,
     pool: Vec<ChannelSender<M>>,
+    assignment: WorkerAssignment,
 }
 
 impl<M> ToPool<M>
@@ -42,10 +44,16 @@ where
         let to_pool = Self {
             name: name.into(),
             pool,
+            assignment: WorkerAssignment::default(),
         };
         (to_pool, rxs)
     }
 
+    /// Changes how shifted indexes are assigned to the pool workers.
+    pub fn set_assignment(&mut self, assignment: WorkerAssignment) {
+        self.assignment = assignment;
+    }
+
     /// Returns the size of the pool.
     pub fn pool_size(&self) -> usize {
         self.pool.len()
@@ -106,22 +114,11 @@ where
     where
         T: PoolIndex,
     {
-        msg.index().map(|(reserved, index)| {
-            Self::do_index(reserved, index, self.pool_size())
+        msg.index().map(|index| {
+            load_balance::worker_index(index, self.pool_size(), self.assignment)
         })
     }
 
-    fn do_index(reserved: usize, index: usize, pool_size: usize) -> usize {
-        if reserved < pool_size {
-            // compute the actual index only in the remaining indexes
-            reserved + (index % (pool_size - reserved))
-        } else {
-            // if there's as many reserved (or more) as workers in the
-            // pool, then ignore reservation
-            index % pool_size
-        }
-    }
-
     async fn do_forward(
         &mut self,
         index: Option<usize>,
@@ -141,7 +138,11 @@ mod tests {
     use super::*;
 
     fn do_index(reserved: usize, index: usize, pool_size: usize) -> usize {
-        ToPool::<()>::do_index(reserved, index, pool_size)
+        load_balance::worker_index(
+            (reserved, index),
+            pool_size,
+            WorkerAssignment::Contiguous,
+        )
     }
 
     #[test]
@@ -1,34 +1,35 @@
-use super::chan::{ChannelReceiver, ChannelSender};
+use super::chan::{
+    ChannelReceiver, ChannelSender, PriorityChannelReceiver,
+    PriorityChannelSender,
+};
 use super::pool;
+use super::rw::{MessageType, RwMetrics};
+use super::task::server::client::AcceptorMetrics;
+use super::task::server::metrics_logger::MetricsReporter;
 use crate::command::{Command, CommandResult};
-use crate::executor::{Executor, ExecutorMetrics, ExecutorResult};
-use crate::id::{ClientId, Dot, ProcessId, ShardId};
+use crate::executor::{
+    CanaryMessage, Executor, ExecutorMetrics, ExecutorResult,
+};
+use crate::id::{ClientId, Dot, ProcessId, Rifl, ShardId};
+use crate::kvs::{KVOp, Key};
 use crate::load_balance::*;
 use crate::protocol::{
-    CommittedAndExecuted, MessageIndex, Protocol, ProtocolMetrics,
+    high_priority_by_size, CommittedAndExecuted, MessageIndex, MessagePriority,
+    Protocol, ProtocolMetrics,
 };
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProcessHi {
-    pub process_id: ProcessId,
-    pub shard_id: ShardId,
-}
+// Re-exports.
+pub use crate::client::wire::{ClientHi, ClientToServer, ProcessHi};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ClientHi(pub Vec<ClientId>);
-
-// If the command touches a single shard, then a `Submit` will be sent to that
-// shard. If the command touches more than on shard, a `Submit` will be sent to
-// one targetted shard and a `Register` will be sent to the remaining shards to
-// make sure that the client will eventually receive a `CommandResult` from all
-// shards.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ClientToServer {
-    Submit(Command),
-    Register(Command),
+#[derive(Debug, Clone)]
+pub enum ClientToWorker {
+    // new commands submitted by clients
+    Submit(Option<Dot>, Command),
+    // commands cancelled by clients
+    Cancel(Dot),
 }
 
 #[derive(Debug, Clone)]
@@ -37,8 +38,14 @@ pub enum ClientToExecutor {
     Register(Vec<ClientId>, ExecutorResultSender),
     // unregister
     Unregister(Vec<ClientId>),
+    // read-only ops on some key to be served directly from the store of the
+    // executor with the index provided (see `Executor::stale_read_index`)
+    StaleRead(Option<(usize, usize)>, Rifl, Key, Vec<KVOp>),
 }
 
+// Computes the index of the executor that serves stale reads on some key.
+pub type StaleReadIndex = fn(&Key) -> Option<(usize, usize)>;
+
 #[derive(Debug, Serialize, Deserialize)]
 // these bounds are explained here: https://github.com/serde-rs/serde/issues/1503#issuecomment-475059482
 #[serde(bound(
@@ -57,50 +64,86 @@ impl<P: Protocol> POEMessage<P> {
             Self::Executor(_) => true,
         }
     }
+
+    pub fn high_priority(&self) -> bool {
+        match self {
+            Self::Protocol(msg) => msg.high_priority(),
+            Self::Executor(execution_info) => {
+                high_priority_by_size(execution_info)
+            }
+        }
+    }
+
+    /// Returns the type of the message, i.e. the variant of the protocol
+    /// message (if it's one).
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            Self::Protocol(msg) => MessageType::of(msg),
+            Self::Executor(_) => MessageType::from_name("ExecutionInfo"),
+        }
+    }
 }
 
 // list of channels used to communicate between tasks
 // TODO: remove most of these as it doesn't really help reading the code
 pub type ReaderReceiver<P> =
     ChannelReceiver<(ProcessId, ShardId, <P as Protocol>::Message)>;
-pub type WriterReceiver<P> = ChannelReceiver<Arc<POEMessage<P>>>;
-pub type WriterSender<P> = ChannelSender<Arc<POEMessage<P>>>;
+pub type WriterReceiver<P> = PriorityChannelReceiver<Arc<POEMessage<P>>>;
+pub type WriterSender<P> = PriorityChannelSender<Arc<POEMessage<P>>>;
 pub type ClientToExecutorReceiver = ChannelReceiver<ClientToExecutor>;
 pub type ClientToServerReceiver = ChannelReceiver<ClientToServer>;
 pub type ServerToClientSender = ChannelSender<CommandResult>;
 pub type ExecutorResultReceiver = ChannelReceiver<ExecutorResult>;
 pub type ExecutorResultSender = ChannelSender<ExecutorResult>;
 pub type ExecutedReceiver = ChannelReceiver<CommittedAndExecuted>;
-pub type SubmitReceiver = ChannelReceiver<(Option<Dot>, Command)>;
+pub type SubmitReceiver = ChannelReceiver<ClientToWorker>;
 pub type ExecutionInfoReceiver<P> =
     ChannelReceiver<<<P as Protocol>::Executor as Executor>::ExecutionInfo>;
 pub type ExecutionInfoSender<P> =
     ChannelSender<<<P as Protocol>::Executor as Executor>::ExecutionInfo>;
+pub type CanaryMessageReceiver<P> = ChannelReceiver<
+    CanaryMessage<<<P as Protocol>::Executor as Executor>::ExecutionInfo>,
+>;
+pub type CanaryMessageSender<P> = ChannelSender<
+    CanaryMessage<<<P as Protocol>::Executor as Executor>::ExecutionInfo>,
+>;
+pub type ExecutionOrderReceiver = ChannelReceiver<(Key, Rifl)>;
+pub type ExecutionOrderSender = ChannelSender<(Key, Rifl)>;
 pub type PeriodicEventReceiver<P, R> =
     ChannelReceiver<FromPeriodicMessage<P, R>>;
-pub type InspectFun<P, R> = (fn(&P) -> R, ChannelSender<R>);
+pub type InspectFun<P, R> = (fn(&P) -> R, ChannelSender<(usize, R)>);
 pub type InspectReceiver<P, R> = ChannelReceiver<InspectFun<P, R>>;
 pub type SortedProcessesSender =
     ChannelSender<ChannelSender<Vec<(ProcessId, ShardId)>>>;
 pub type SortedProcessesReceiver =
     ChannelReceiver<ChannelSender<Vec<(ProcessId, ShardId)>>>;
-pub type ProtocolMetricsReceiver = ChannelReceiver<(usize, ProtocolMetrics)>;
-pub type ProtocolMetricsSender = ChannelSender<(usize, ProtocolMetrics)>;
-pub type ExecutorMetricsReceiver = ChannelReceiver<(usize, ExecutorMetrics)>;
-pub type ExecutorMetricsSender = ChannelSender<(usize, ExecutorMetrics)>;
+pub type ProtocolMetricsReceiver =
+    ChannelReceiver<(usize, u64, ProtocolMetrics)>;
+pub type ProtocolMetricsReporter = MetricsReporter<ProtocolMetrics>;
+pub type ExecutorMetricsReceiver =
+    ChannelReceiver<(usize, u64, ExecutorMetrics)>;
+pub type ExecutorMetricsReporter = MetricsReporter<ExecutorMetrics>;
+pub type RwMetricsReceiver = ChannelReceiver<(usize, RwMetrics)>;
+pub type RwMetricsSender = ChannelSender<(usize, RwMetrics)>;
+pub type AcceptorMetricsReceiver = ChannelReceiver<(usize, AcceptorMetrics)>;
+pub type AcceptorMetricsSender = ChannelSender<(usize, AcceptorMetrics)>;
 
 // 1. workers receive messages from clients
-pub type ClientToWorkers = pool::ToPool<(Option<Dot>, Command)>;
-impl pool::PoolIndex for (Option<Dot>, Command) {
+pub type ClientToWorkers = pool::ToPool<ClientToWorker>;
+impl pool::PoolIndex for ClientToWorker {
     fn index(&self) -> Option<(usize, usize)> {
-        // if there's a `Dot`, then the protocol is leaderless; otherwise, it is
-        // leader-based and the command should always be forwarded to the leader
-        // worker
-        self.0
-            .as_ref()
-            .map(worker_dot_index_shift)
-            // no necessary reserve if there's a leader
-            .unwrap_or_else(|| worker_index_no_shift(LEADER_WORKER_INDEX))
+        match self {
+            // if there's a `Dot`, then the protocol is leaderless; otherwise,
+            // it is leader-based and the command should always be forwarded to
+            // the leader worker
+            Self::Submit(dot, _) => dot
+                .as_ref()
+                .map(worker_dot_index_shift)
+                // no necessary reserve if there's a leader
+                .unwrap_or_else(|| worker_index_no_shift(LEADER_WORKER_INDEX)),
+            // cancels are forwarded to the worker that handled the submit
+            Self::Cancel(dot) => worker_dot_index_shift(dot),
+        }
     }
 }
 
@@ -121,12 +164,13 @@ where
 // 3. workers receive messages from the periodic-events task
 // - this message can either be a periodic event or
 // - an inspect function that takes a reference to the protocol state and
-//   returns a boolean; this boolean is then sent through the `ChannelSender`
-//   (this is useful for e.g. testing)
+//   returns some value; this value is then sent (along with the index of the
+//   worker) through the `ChannelSender` (this is useful for e.g. testing and
+//   for the control queries, see `task::server::control`)
 #[derive(Clone)]
 pub enum FromPeriodicMessage<P: Protocol, R> {
     Event(P::PeriodicEvent),
-    Inspect(fn(&P) -> R, ChannelSender<R>),
+    Inspect(fn(&P) -> R, ChannelSender<(usize, R)>),
 }
 
 impl<P, R> fmt::Debug for FromPeriodicMessage<P, R>
@@ -166,6 +210,16 @@ impl pool::PoolIndex for CommittedAndExecuted {
 
 // 5. executors receive messages from clients
 pub type ClientToExecutors = pool::ToPool<ClientToExecutor>;
+impl pool::PoolIndex for ClientToExecutor {
+    fn index(&self) -> Option<(usize, usize)> {
+        match self {
+            // stale reads are forwarded to the executor that owns the key
+            Self::StaleRead(index, ..) => *index,
+            // all other messages are sent to all executors
+            _ => None,
+        }
+    }
+}
 
 // 6. executors receive messages from workers and reader tasks
 pub type ToExecutors<P> =
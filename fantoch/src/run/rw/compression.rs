@@ -0,0 +1,328 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write as _};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+// maximum length of the names kept by `MessageType`; the capacity is fixed so
+// that `MessageType` can be `Copy` (and thus used as a metric kind)
+const MESSAGE_TYPE_MAX_LEN: usize = 24;
+
+// interval between consecutive tunings of the compression threshold
+const TUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+// the compression threshold is never raised above this value
+const MAX_THRESHOLD: usize = 1024 * 1024; // 1MB
+
+// compressing is only worth it if it saves at least this fraction of bytes;
+// otherwise, the threshold is raised
+const MIN_SAVINGS: f64 = 0.1;
+
+// if compressing saves at least this fraction of bytes (and there's CPU
+// headroom), the threshold is lowered
+const GOOD_SAVINGS: f64 = 0.5;
+
+// maximum fraction of time that should be spent compressing; above it, the
+// threshold is raised
+const MAX_BUSY: f64 = 0.05;
+
+// number of consecutive tunings without compressing any message after which
+// the threshold is lowered, probing whether smaller messages compress well
+const IDLE_TUNINGS_BEFORE_PROBE: usize = 10;
+
+/// Name of the type of some message (e.g. the variant of the enum of protocol
+/// messages), used to report compression metrics per message type.
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct MessageType {
+    name: [u8; MESSAGE_TYPE_MAX_LEN],
+    len: usize,
+}
+
+impl MessageType {
+    /// Creates a `MessageType` with the name provided (truncated if longer
+    /// than the capacity).
+    pub fn from_name(name: &str) -> Self {
+        let mut msg_type = Self {
+            name: [0; MESSAGE_TYPE_MAX_LEN],
+            len: 0,
+        };
+        for byte in name.bytes().take(MESSAGE_TYPE_MAX_LEN) {
+            msg_type.push(byte);
+        }
+        msg_type
+    }
+
+    /// Creates a `MessageType` whose name is the one of the variant (or of the
+    /// type) of `msg`, as printed by its `Debug` implementation. Formatting is
+    /// stopped once the name is written, and so this is cheap even for large
+    /// messages.
+    pub fn of<T: fmt::Debug + ?Sized>(msg: &T) -> Self {
+        let mut writer = NameWriter {
+            msg_type: Self::from_name(""),
+        };
+        // formatting fails once something other than the name is written
+        let _ = write!(writer, "{:?}", msg);
+        writer.msg_type
+    }
+
+    /// Returns the name of the message type.
+    pub fn name(&self) -> &str {
+        std::str::from_utf8(&self.name[..self.len])
+            .expect("message type name should be valid UTF-8")
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.len < MESSAGE_TYPE_MAX_LEN {
+            self.name[self.len] = byte;
+            self.len += 1;
+        }
+    }
+}
+
+impl fmt::Debug for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Keeps the leading identifier written to it, failing on anything else.
+struct NameWriter {
+    msg_type: MessageType,
+}
+
+impl fmt::Write for NameWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'_' {
+                self.msg_type.push(byte);
+            } else {
+                return Err(fmt::Error);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compression of the messages sent on a connection. Only messages whose
+/// serialized size is at least the current threshold are compressed. The
+/// threshold starts at the minimum one configured (see
+/// `Config::message_compression`) and is periodically tuned given the
+/// compression ratios observed and the fraction of time spent compressing:
+/// - it's raised if compressing doesn't save enough bytes or if it uses too
+///   much CPU
+/// - it's lowered if compressing saves many bytes and there's CPU headroom,
+///   or if no message has been compressed for a while
+#[derive(Debug)]
+pub struct AdaptiveCompression {
+    min_threshold: usize,
+    threshold: usize,
+    // bytes before and after compression, and time spent compressing, since
+    // the threshold was last tuned
+    bytes_in: u64,
+    bytes_out: u64,
+    busy: Duration,
+    last_tuned: Instant,
+    // number of consecutive tunings without compressing any message
+    idle_tunings: usize,
+    // buffer to which messages are compressed; it's reused across messages
+    buffer: Vec<u8>,
+}
+
+impl AdaptiveCompression {
+    /// Creates a new `AdaptiveCompression` given the minimum threshold.
+    pub fn new(min_threshold: usize) -> Self {
+        Self {
+            min_threshold,
+            threshold: min_threshold,
+            bytes_in: 0,
+            bytes_out: 0,
+            busy: Duration::default(),
+            last_tuned: Instant::now(),
+            idle_tunings: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the current threshold.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Compresses `bytes`, returning the compressed bytes if they're smaller
+    /// than the original ones.
+    pub fn compress(&mut self, bytes: &[u8]) -> Option<&[u8]> {
+        let start = Instant::now();
+        self.buffer.clear();
+        let mut encoder =
+            DeflateEncoder::new(&mut self.buffer, flate2::Compression::fast());
+        encoder
+            .write_all(bytes)
+            .and_then(|_| encoder.try_finish())
+            .expect("[rw] compress should work");
+        drop(encoder);
+        let compressed = self.buffer.len() < bytes.len();
+        let bytes_out = if compressed {
+            self.buffer.len()
+        } else {
+            bytes.len()
+        };
+        self.record(bytes.len(), bytes_out, start.elapsed());
+        if compressed {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Tunes the threshold if `TUNE_INTERVAL` has passed since the last time
+    /// it was tuned. Returns the new threshold in that case.
+    pub fn maybe_tune(&mut self) -> Option<usize> {
+        let elapsed = self.last_tuned.elapsed();
+        if elapsed >= TUNE_INTERVAL {
+            self.tune(elapsed);
+            self.last_tuned = Instant::now();
+            Some(self.threshold)
+        } else {
+            None
+        }
+    }
+
+    fn record(&mut self, bytes_in: usize, bytes_out: usize, took: Duration) {
+        self.bytes_in += bytes_in as u64;
+        self.bytes_out += bytes_out as u64;
+        self.busy += took;
+    }
+
+    fn tune(&mut self, elapsed: Duration) {
+        if self.bytes_in == 0 {
+            self.idle_tunings += 1;
+            if self.idle_tunings >= IDLE_TUNINGS_BEFORE_PROBE {
+                self.idle_tunings = 0;
+                self.lower();
+            }
+        } else {
+            self.idle_tunings = 0;
+            let savings = 1f64 - self.bytes_out as f64 / self.bytes_in as f64;
+            let busy = self.busy.as_secs_f64() / elapsed.as_secs_f64();
+            if savings < MIN_SAVINGS || busy > MAX_BUSY {
+                self.raise();
+            } else if savings >= GOOD_SAVINGS && busy <= MAX_BUSY / 2f64 {
+                self.lower();
+            }
+        }
+        self.bytes_in = 0;
+        self.bytes_out = 0;
+        self.busy = Duration::default();
+    }
+
+    fn raise(&mut self) {
+        self.threshold = (self.threshold * 2).min(MAX_THRESHOLD);
+    }
+
+    fn lower(&mut self) {
+        self.threshold = (self.threshold / 2).max(self.min_threshold);
+    }
+}
+
+/// Returns a reader of the decompressed `bytes`.
+pub fn decompress(bytes: &[u8]) -> impl std::io::Read + '_ {
+    DeflateDecoder::new(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    enum Message {
+        MCollect { payload: Vec<u8> },
+        MCommit(u64),
+        Ping,
+    }
+
+    #[test]
+    fn message_type() {
+        let msg = Message::MCollect {
+            payload: vec![0; 1024],
+        };
+        assert_eq!(MessageType::of(&msg).name(), "MCollect");
+        assert_eq!(MessageType::of(&Message::MCommit(10)).name(), "MCommit");
+        assert_eq!(MessageType::of(&Message::Ping).name(), "Ping");
+        // long names are truncated
+        let name = "a".repeat(2 * MESSAGE_TYPE_MAX_LEN);
+        assert_eq!(
+            MessageType::from_name(&name).name(),
+            &name[..MESSAGE_TYPE_MAX_LEN]
+        );
+    }
+
+    #[test]
+    fn adaptive_compression_flow() {
+        let min_threshold = 1024;
+        let mut compression = AdaptiveCompression::new(min_threshold);
+        let second = Duration::from_secs(1);
+        assert_eq!(compression.threshold(), min_threshold);
+
+        // messages that compress well keep the threshold at the minimum
+        compression.record(1000, 100, Duration::from_millis(1));
+        compression.tune(second);
+        assert_eq!(compression.threshold(), min_threshold);
+
+        // messages that don't compress well raise the threshold
+        compression.record(1000, 950, Duration::from_millis(1));
+        compression.tune(second);
+        assert_eq!(compression.threshold(), 2 * min_threshold);
+
+        // and so does spending too much time compressing
+        compression.record(1000, 100, Duration::from_millis(100));
+        compression.tune(second);
+        assert_eq!(compression.threshold(), 4 * min_threshold);
+
+        // messages that compress well with CPU headroom lower it
+        compression.record(1000, 100, Duration::from_millis(1));
+        compression.tune(second);
+        assert_eq!(compression.threshold(), 2 * min_threshold);
+
+        // if nothing is compressed for a while, the threshold is lowered
+        for _ in 0..(IDLE_TUNINGS_BEFORE_PROBE - 1) {
+            compression.tune(second);
+            assert_eq!(compression.threshold(), 2 * min_threshold);
+        }
+        compression.tune(second);
+        assert_eq!(compression.threshold(), min_threshold);
+
+        // the threshold is bounded
+        for _ in 0..100 {
+            compression.record(1000, 1000, Duration::from_millis(1));
+            compression.tune(second);
+        }
+        assert_eq!(compression.threshold(), MAX_THRESHOLD);
+    }
+
+    #[test]
+    fn compress_decompress() {
+        use std::io::Read;
+
+        let mut compression = AdaptiveCompression::new(0);
+
+        // repetitive bytes are compressed
+        let bytes = vec![7u8; 4096];
+        let compressed = compression
+            .compress(&bytes)
+            .expect("bytes should be compressed")
+            .to_vec();
+        assert!(compressed.len() < bytes.len());
+        let mut decompressed = Vec::new();
+        decompress(&compressed)
+            .read_to_end(&mut decompressed)
+            .expect("decompress should work");
+        assert_eq!(decompressed, bytes);
+
+        // while tiny ones are not
+        assert!(compression.compress(&[1, 2, 3]).is_none());
+    }
+}
@@ -1,31 +1,92 @@
 use super::Rw;
+use socket2::{SockRef, TcpKeepalive};
 use std::net::IpAddr;
 use std::ops::{Deref, DerefMut};
 use tokio::net::TcpStream;
 use tokio::time::Duration;
 
+/// Options of the TCP socket underlying each `Connection`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpOptions {
+    /// whether TCP_NODELAY is set
+    pub nodelay: bool,
+    /// if set, the size of the socket send buffer (SO_SNDBUF)
+    pub send_buffer_size: Option<usize>,
+    /// if set, the size of the socket receive buffer (SO_RCVBUF)
+    pub recv_buffer_size: Option<usize>,
+    /// if set, keepalive probes are sent once the connection has been idle
+    /// for this long
+    pub keepalive: Option<Duration>,
+    /// if set, the maximum segment size (TCP_MAXSEG) of each connection; this
+    /// is only a hint, as the effective one (see `Connection::mss`) is
+    /// bounded by the MTU of the path to the peer (e.g. larger segments
+    /// require jumbo frames)
+    pub max_segment_size: Option<u32>,
+    /// if set, a shared secret sent first on each connection established; the
+    /// connections accepted that don't present it are dropped (see
+    /// `task::authenticate`), so that stray connections (e.g. from previous
+    /// experiments or port scanners) can't inject garbage into a running
+    /// benchmark
+    pub auth_token: Option<u64>,
+}
+
+impl TcpOptions {
+    /// Creates `TcpOptions` that only set TCP_NODELAY, leaving the remaining
+    /// options with the defaults of the OS.
+    pub fn new(nodelay: bool) -> Self {
+        Self {
+            nodelay,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            keepalive: None,
+            max_segment_size: None,
+            auth_token: None,
+        }
+    }
+
+    /// Sets the maximum segment size (TCP_MAXSEG), if any, in a socket. Since
+    /// the MSS is advertised when a connection is established, this should be
+    /// done before connecting (or, for the connections accepted, in the
+    /// listener, from which they inherit it).
+    pub fn set_max_segment_size<'s, S>(&self, socket: S)
+    where
+        S: Into<SockRef<'s>>,
+    {
+        if let Some(max_segment_size) = self.max_segment_size {
+            socket
+                .into()
+                .set_mss(max_segment_size)
+                .expect("setting TCP_MAXSEG should work");
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Connection {
     ip_addr: Option<IpAddr>,
     delay: Option<Duration>,
+    mss: Option<u32>,
     rw: Rw<TcpStream>,
 }
 
 impl Connection {
     pub fn new(
         stream: TcpStream,
-        tcp_nodelay: bool,
+        tcp_options: TcpOptions,
         tcp_buffer_size: usize,
     ) -> Self {
         // get ip addr
         let ip_addr = stream.peer_addr().ok().map(|peer_addr| peer_addr.ip());
         // configure stream
-        configure(&stream, tcp_nodelay);
+        configure(&stream, tcp_options);
+        // probe the effective MSS
+        let mss = SockRef::from(&stream).mss().ok();
         // create rw
         let rw = Rw::from(tcp_buffer_size, tcp_buffer_size, stream);
         Self {
             ip_addr,
             delay: None,
+            mss,
             rw,
         }
     }
@@ -41,34 +102,42 @@ impl Connection {
     pub fn set_delay(&mut self, delay: Duration) {
         self.delay = Some(delay)
     }
+
+    /// Returns the effective maximum segment size of the connection, as
+    /// reported by the kernel once the connection was established.
+    pub fn mss(&self) -> Option<u32> {
+        self.mss
+    }
 }
 
-fn configure(stream: &TcpStream, tcp_nodelay: bool) {
+fn configure(stream: &TcpStream, tcp_options: TcpOptions) {
     // set TCP_NODELAY
     stream
-        .set_nodelay(tcp_nodelay)
+        .set_nodelay(tcp_options.nodelay)
         .expect("setting TCP_NODELAY should work");
 
-    // // maybe adapt SO_RCVBUF and SO_SNDBUF and compute buffer capacity
-    // // change SO_RCVBUF if lower than `tcp_buffer_size`
-    // if let Ok(so_rcvbuf) = stream.recv_buffer_size() {
-    //     if so_rcvbuf < tcp_buffer_size {
-    //         stream
-    //             .set_recv_buffer_size(tcp_buffer_size)
-    //             .expect("setting tcp recv buffer should work");
-    //     }
-    // }
-    // log!("SO_RCVBUF: {:?}", stream.recv_buffer_size());
-
-    // // change SO_SNFBUF if lower than `tcp_buffer_size`
-    // if let Ok(so_sndbuf) = stream.send_buffer_size() {
-    //     if so_sndbuf < tcp_buffer_size {
-    //         stream
-    //             .set_send_buffer_size(tcp_buffer_size)
-    //             .expect("setting tcp send buffer should work");
-    //     }
-    // }
-    // log!("SO_SNDBUF: {:?}", stream.send_buffer_size());
+    // the remaining options are not exposed by tokio's `TcpStream`
+    let socket = SockRef::from(stream);
+
+    // set SO_SNDBUF and SO_RCVBUF
+    if let Some(send_buffer_size) = tcp_options.send_buffer_size {
+        socket
+            .set_send_buffer_size(send_buffer_size)
+            .expect("setting SO_SNDBUF should work");
+    }
+    if let Some(recv_buffer_size) = tcp_options.recv_buffer_size {
+        socket
+            .set_recv_buffer_size(recv_buffer_size)
+            .expect("setting SO_RCVBUF should work");
+    }
+
+    // set SO_KEEPALIVE
+    if let Some(keepalive) = tcp_options.keepalive {
+        let keepalive = TcpKeepalive::new().with_time(keepalive);
+        socket
+            .set_tcp_keepalive(&keepalive)
+            .expect("setting SO_KEEPALIVE should work");
+    }
 }
 
 impl Deref for Connection {
@@ -84,3 +153,135 @@ impl DerefMut for Connection {
         &mut self.rw
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::{TcpListener, TcpSocket};
+
+    #[tokio::test]
+    async fn configure_tcp_options() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind should work");
+        let address = listener.local_addr().expect("local addr should work");
+        let stream = TcpStream::connect(address)
+            .await
+            .expect("connect should work");
+
+        // by default, only TCP_NODELAY is set
+        configure(&stream, TcpOptions::new(true));
+        let socket = SockRef::from(&stream);
+        assert!(stream.nodelay().unwrap());
+        assert!(!socket.keepalive().unwrap());
+
+        // set all options
+        let buffer_size = 64 * 1024;
+        let tcp_options = TcpOptions {
+            nodelay: false,
+            send_buffer_size: Some(buffer_size),
+            recv_buffer_size: Some(buffer_size),
+            keepalive: Some(Duration::from_secs(10)),
+            max_segment_size: Some(1000),
+            auth_token: None,
+        };
+        configure(&stream, tcp_options);
+        assert!(!stream.nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        // the OS may adjust the buffer sizes set (e.g. Linux doubles them)
+        assert!(socket.send_buffer_size().unwrap() >= buffer_size);
+        assert!(socket.recv_buffer_size().unwrap() >= buffer_size);
+    }
+
+    #[tokio::test]
+    async fn max_segment_size() {
+        let mut tcp_options = TcpOptions::new(true);
+        tcp_options.max_segment_size = Some(1000);
+
+        // set the MSS in both the listener and the connecting socket
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind should work");
+        tcp_options.set_max_segment_size(&listener);
+        let address = listener.local_addr().expect("local addr should work");
+        let socket = TcpSocket::new_v4().expect("socket should work");
+        tcp_options.set_max_segment_size(&socket);
+        let stream =
+            socket.connect(address).await.expect("connect should work");
+        let (accepted, _) =
+            listener.accept().await.expect("accept should work");
+
+        // the effective MSS of both ends is bounded by the one set (even
+        // though the loopback MTU allows much larger segments)
+        let tcp_buffer_size = 0;
+        for stream in [stream, accepted] {
+            let connection =
+                Connection::new(stream, tcp_options, tcp_buffer_size);
+            let mss = connection.mss().expect("MSS should be reported");
+            assert!(mss > 0 && mss <= 1000);
+        }
+    }
+
+    #[tokio::test]
+    async fn auth_token() {
+        use crate::run::task;
+
+        let mut tcp_options = TcpOptions::new(true);
+        tcp_options.auth_token = Some(42);
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind should work");
+        let address = listener.local_addr().expect("local addr should work");
+        let tcp_buffer_size = 0;
+        let connect_retries = 1;
+
+        // connections presenting the token are kept
+        let _connected = task::connect(
+            address,
+            tcp_options,
+            tcp_buffer_size,
+            connect_retries,
+        )
+        .await
+        .expect("connect should work");
+        let (accepted, _) =
+            listener.accept().await.expect("accept should work");
+        let mut accepted =
+            Connection::new(accepted, tcp_options, tcp_buffer_size);
+        assert!(task::authenticate(&mut accepted, tcp_options).await);
+
+        // connections presenting a different token are dropped
+        let mut other_tcp_options = tcp_options;
+        other_tcp_options.auth_token = Some(43);
+        let _connected = task::connect(
+            address,
+            other_tcp_options,
+            tcp_buffer_size,
+            connect_retries,
+        )
+        .await
+        .expect("connect should work");
+        let (accepted, _) =
+            listener.accept().await.expect("accept should work");
+        let mut accepted =
+            Connection::new(accepted, tcp_options, tcp_buffer_size);
+        assert!(!task::authenticate(&mut accepted, tcp_options).await);
+
+        // and so are connections that don't present any token
+        let mut stream = TcpStream::connect(address)
+            .await
+            .expect("connect should work");
+        let (accepted, _) =
+            listener.accept().await.expect("accept should work");
+        let mut accepted =
+            Connection::new(accepted, tcp_options, tcp_buffer_size);
+        // (garbage is sent instead, which isn't a valid frame)
+        use tokio::io::AsyncWriteExt;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\n\r\n")
+            .await
+            .expect("write should work");
+        drop(stream);
+        assert!(!task::authenticate(&mut accepted, tcp_options).await);
+    }
+}
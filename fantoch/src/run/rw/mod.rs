@@ -1,16 +1,23 @@
+// This module contains the definition of `AdaptiveCompression`.
+mod compression;
+
 // This module contains the definition of `Connection`.
 mod connection;
 
 // Re-exports.
-pub use connection::Connection;
+pub use compression::{AdaptiveCompression, MessageType};
+pub use connection::{Connection, TcpOptions};
 
+use crate::error::RunError;
+use crate::metrics::Metrics;
 use crate::warn;
-use bytes::{Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use color_eyre::eyre::{Report, WrapErr};
 use futures::sink::{Sink, SinkExt};
 use futures::stream::StreamExt;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::pin::Pin;
 use tokio::io::{AsyncRead, AsyncWrite, BufStream};
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
@@ -20,8 +27,21 @@ use tokio_util::codec::{Framed, LengthDelimitedCodec};
 #[derive(Debug)]
 pub struct Rw<S> {
     rw: Framed<BufStream<S>, LengthDelimitedCodec>,
+    // buffer to which messages are serialized; it's reused across messages,
+    // which avoids an allocation per message sent
+    buffer: BytesMut,
+    // start of the allocation currently backing `buffer`
+    buffer_start: usize,
+    // if set, each frame starts with a flag indicating whether it's
+    // compressed (see `Rw::set_compression`)
+    compression: Option<AdaptiveCompression>,
+    metrics: RwMetrics,
 }
 
+// flags that start each frame if compression is enabled
+const UNCOMPRESSED: u8 = 0;
+const COMPRESSED: u8 = 1;
+
 impl<S> Rw<S>
 where
     S: AsyncWrite + AsyncRead + Unpin,
@@ -31,7 +51,30 @@ where
         let rw = BufStream::with_capacity(reader_capacity, writer_capacity, rw);
         // frame rw
         let rw = Framed::new(rw, LengthDelimitedCodec::new());
-        Self { rw }
+        Self {
+            rw,
+            buffer: BytesMut::new(),
+            buffer_start: 0,
+            compression: None,
+            metrics: RwMetrics::new(),
+        }
+    }
+
+    pub fn metrics(&self) -> &RwMetrics {
+        &self.metrics
+    }
+
+    /// Enables the compression of the messages sent whose serialized size is
+    /// at least `min_threshold` bytes (see `AdaptiveCompression`). Since frames
+    /// then carry a compression flag, this should be enabled at both ends of
+    /// the connection (at the same point in the stream of messages).
+    pub fn set_compression(&mut self, min_threshold: usize) {
+        self.compression = Some(AdaptiveCompression::new(min_threshold));
+    }
+
+    /// Checks whether compression is enabled.
+    pub fn compression(&self) -> bool {
+        self.compression.is_some()
     }
 
     pub async fn recv<V>(&mut self) -> Option<V>
@@ -41,7 +84,11 @@ where
         match self.rw.next().await {
             Some(Ok(bytes)) => {
                 // if it is, and not an error, deserialize it
-                let value = deserialize(bytes);
+                let value = if self.compression.is_some() {
+                    deserialize_flagged(bytes)
+                } else {
+                    deserialize(bytes)
+                };
                 Some(value)
             }
             Some(Err(e)) => {
@@ -56,10 +103,24 @@ where
     where
         V: Serialize,
     {
-        let bytes = serialize(value);
+        self.send_typed(value, None).await
+    }
+
+    /// Sends a message of type `msg_type`, which is used to report
+    /// compression metrics per message type.
+    pub async fn send_typed<V>(
+        &mut self,
+        value: &V,
+        msg_type: Option<MessageType>,
+    ) -> Result<(), Report>
+    where
+        V: Serialize,
+    {
+        let bytes = self.serialize(value, msg_type);
         self.rw
             .send(bytes)
             .await
+            .map_err(|e| RunError::Connection(e.to_string()))
             .wrap_err("error while sending to sink")
     }
 
@@ -67,20 +128,133 @@ where
     where
         V: Serialize,
     {
-        let bytes = serialize(value);
+        self.write_typed(value, None).await
+    }
+
+    /// Writes a message of type `msg_type`, which is used to report
+    /// compression metrics per message type.
+    pub async fn write_typed<V>(
+        &mut self,
+        value: &V,
+        msg_type: Option<MessageType>,
+    ) -> Result<(), Report>
+    where
+        V: Serialize,
+    {
+        let bytes = self.serialize(value, msg_type);
         futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_ready(cx))
             .await
+            .map_err(|e| RunError::Connection(e.to_string()))
             .wrap_err("error while polling sink ready")?;
         Pin::new(&mut self.rw)
             .start_send(bytes)
+            .map_err(|e| RunError::Connection(e.to_string()))
             .wrap_err("error while starting send to sink")
     }
 
     pub async fn flush(&mut self) -> Result<(), Report> {
         futures::future::poll_fn(|cx| Pin::new(&mut self.rw).poll_flush(cx))
             .await
+            .map_err(|e| RunError::Connection(e.to_string()))
             .wrap_err("error while flushing sink")
     }
+
+    fn serialize<V>(
+        &mut self,
+        value: &V,
+        msg_type: Option<MessageType>,
+    ) -> Bytes
+    where
+        V: Serialize,
+    {
+        let size = bincode::serialized_size(value)
+            .expect("[rw] serialized size should work")
+            as usize;
+        // account for the compression flag, if any
+        let size = if self.compression.is_some() {
+            size + 1
+        } else {
+            size
+        };
+
+        // the bytes returned by this function are copied to the write buffer
+        // of the framed sink (and then dropped) before this function is called
+        // again; for this reason, when there's not enough capacity left in the
+        // buffer, `reserve` is able to reclaim the whole allocation and only
+        // allocates if the message doesn't fit in it
+        if self.buffer.capacity() < size {
+            self.buffer.reserve(size);
+            let buffer_start = self.buffer.as_ptr() as usize;
+            if buffer_start != self.buffer_start {
+                self.buffer_start = buffer_start;
+                self.metrics.aggregate(RwMetricsKind::BufferAllocations, 1);
+            }
+        }
+        self.metrics.aggregate(RwMetricsKind::SerializedMessages, 1);
+
+        if self.compression.is_some() {
+            self.buffer.put_u8(UNCOMPRESSED);
+        }
+        bincode::serialize_into((&mut self.buffer).writer(), value)
+            .expect("[rw] serialize should work");
+        if let Some(compression) = self.compression.as_mut() {
+            Self::maybe_compress(
+                compression,
+                &mut self.buffer,
+                msg_type,
+                &mut self.metrics,
+            );
+        }
+        self.buffer.split().freeze()
+    }
+
+    // Compresses the message serialized in `buffer` (right after its
+    // compression flag) if it's at least as large as the current threshold.
+    fn maybe_compress(
+        compression: &mut AdaptiveCompression,
+        buffer: &mut BytesMut,
+        msg_type: Option<MessageType>,
+        metrics: &mut RwMetrics,
+    ) {
+        // tune the threshold on every message (and not only on the ones
+        // compressed), so that it can be lowered after a while without
+        // compressing any message
+        if let Some(threshold) = compression.maybe_tune() {
+            metrics
+                .collect(RwMetricsKind::CompressionThreshold, threshold as u64);
+        }
+
+        let size = buffer.len() - 1;
+        if size < compression.threshold() {
+            metrics.aggregate(RwMetricsKind::UncompressedMessages, 1);
+            return;
+        }
+
+        let msg_type =
+            msg_type.unwrap_or_else(|| MessageType::from_name("untyped"));
+        let sent = match compression.compress(&buffer[1..]) {
+            Some(compressed) => {
+                // replace the serialized message by the compressed one (which
+                // is smaller, and thus fits in the capacity reserved)
+                buffer.clear();
+                buffer.put_u8(COMPRESSED);
+                buffer.extend_from_slice(compressed);
+                compressed.len()
+            }
+            // the message is sent uncompressed if compressing it didn't
+            // make it smaller
+            None => size,
+        };
+        metrics.aggregate(RwMetricsKind::CompressedMessages(msg_type), 1);
+        metrics.aggregate(
+            RwMetricsKind::CompressionBytesIn(msg_type),
+            size as u64,
+        );
+        metrics.aggregate(
+            RwMetricsKind::CompressionBytesOut(msg_type),
+            sent as u64,
+        );
+    }
 }
 
 fn deserialize<V>(bytes: BytesMut) -> V
@@ -90,11 +264,178 @@ where
     bincode::deserialize(&bytes).expect("[rw] deserialize should work")
 }
 
-fn serialize<V>(value: &V) -> Bytes
+// Deserializes a frame that starts with a compression flag.
+fn deserialize_flagged<V>(bytes: BytesMut) -> V
 where
-    V: Serialize,
+    V: DeserializeOwned,
 {
-    // TODO can we avoid `Bytes`?
-    let bytes = bincode::serialize(value).expect("[rw] serialize should work");
-    Bytes::from(bytes)
+    match bytes.first() {
+        Some(&UNCOMPRESSED) => bincode::deserialize(&bytes[1..])
+            .expect("[rw] deserialize should work"),
+        Some(&COMPRESSED) => {
+            bincode::deserialize_from(compression::decompress(&bytes[1..]))
+                .expect("[rw] deserialize compressed should work")
+        }
+        flag => panic!("[rw] invalid compression flag: {:?}", flag),
+    }
+}
+
+pub type RwMetrics = Metrics<RwMetricsKind>;
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RwMetricsKind {
+    SerializedMessages,
+    BufferAllocations,
+    // messages not compressed as they were below the compression threshold
+    UncompressedMessages,
+    // messages (of some type) above the compression threshold, along with
+    // their size before and after compression
+    CompressedMessages(MessageType),
+    CompressionBytesIn(MessageType),
+    CompressionBytesOut(MessageType),
+    CompressionThreshold,
+}
+
+impl fmt::Debug for RwMetricsKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RwMetricsKind::SerializedMessages => {
+                write!(f, "serialized_messages")
+            }
+            RwMetricsKind::BufferAllocations => write!(f, "buffer_allocations"),
+            RwMetricsKind::UncompressedMessages => {
+                write!(f, "uncompressed_messages")
+            }
+            RwMetricsKind::CompressedMessages(msg_type) => {
+                write!(f, "compressed_messages_{:?}", msg_type)
+            }
+            RwMetricsKind::CompressionBytesIn(msg_type) => {
+                write!(f, "compression_bytes_in_{:?}", msg_type)
+            }
+            RwMetricsKind::CompressionBytesOut(msg_type) => {
+                write!(f, "compression_bytes_out_{:?}", msg_type)
+            }
+            RwMetricsKind::CompressionThreshold => {
+                write!(f, "compression_threshold")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn buffer_reuse() {
+        let (a, b) = tokio::io::duplex(1024 * 1024);
+        let mut a = Rw::from(1024, 1024, a);
+        let mut b = Rw::from(1024, 1024, b);
+
+        // send messages of the same size
+        let messages = 100;
+        for i in 0..messages {
+            a.send(&(i as u64, String::from("hello")))
+                .await
+                .expect("send should work");
+            let received: (u64, String) = b.recv().await.expect("recv");
+            assert_eq!(received, (i as u64, String::from("hello")));
+        }
+
+        // a single allocation was needed
+        let metrics = a.metrics();
+        assert_eq!(
+            metrics.get_aggregated(RwMetricsKind::SerializedMessages),
+            Some(&messages)
+        );
+        assert_eq!(
+            metrics.get_aggregated(RwMetricsKind::BufferAllocations),
+            Some(&1)
+        );
+
+        // a larger message requires a new allocation
+        a.send(&vec![0u8; 10 * 1024])
+            .await
+            .expect("send should work");
+        let received: Vec<u8> = b.recv().await.expect("recv");
+        assert_eq!(received.len(), 10 * 1024);
+        assert_eq!(
+            a.metrics().get_aggregated(RwMetricsKind::BufferAllocations),
+            Some(&2)
+        );
+    }
+
+    #[tokio::test]
+    async fn compression() {
+        let (a, b) = tokio::io::duplex(1024 * 1024);
+        let mut a = Rw::from(1024, 1024, a);
+        let mut b = Rw::from(1024, 1024, b);
+        a.set_compression(1024);
+        b.set_compression(1024);
+        let small = MessageType::from_name("small");
+        let large = MessageType::from_name("large");
+
+        // small messages are not compressed
+        a.send_typed(&(1u64, String::from("hello")), Some(small))
+            .await
+            .expect("send should work");
+        let received: (u64, String) = b.recv().await.expect("recv");
+        assert_eq!(received, (1, String::from("hello")));
+
+        // large ones are
+        let payload = vec![7u8; 10 * 1024];
+        a.send_typed(&payload, Some(large))
+            .await
+            .expect("send should work");
+        let received: Vec<u8> = b.recv().await.expect("recv");
+        assert_eq!(received, payload);
+
+        let metrics = a.metrics();
+        assert_eq!(
+            metrics.get_aggregated(RwMetricsKind::UncompressedMessages),
+            Some(&1)
+        );
+        assert_eq!(
+            metrics.get_aggregated(RwMetricsKind::CompressedMessages(small)),
+            None
+        );
+        assert_eq!(
+            metrics.get_aggregated(RwMetricsKind::CompressedMessages(large)),
+            Some(&1)
+        );
+        let bytes_in = *metrics
+            .get_aggregated(RwMetricsKind::CompressionBytesIn(large))
+            .expect("bytes in should be set");
+        let bytes_out = *metrics
+            .get_aggregated(RwMetricsKind::CompressionBytesOut(large))
+            .expect("bytes out should be set");
+        assert!(bytes_out < bytes_in / 10);
+    }
+
+    #[tokio::test]
+    async fn wire_frames() {
+        use crate::client::wire;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut raw, rw) = tokio::io::duplex(1024);
+        let mut rw = Rw::from(1024, 1024, rw);
+
+        // frames written with `wire::write_frame` can be received by `Rw`
+        let mut bytes = Vec::new();
+        wire::write_frame(&mut bytes, &(1u64, String::from("hello")))
+            .expect("write frame should work");
+        raw.write_all(&bytes).await.expect("write should work");
+        let received: (u64, String) = rw.recv().await.expect("recv");
+        assert_eq!(received, (1, String::from("hello")));
+
+        // frames sent by `Rw` can be read with `wire::read_frame`
+        rw.send(&(2u64, String::from("world")))
+            .await
+            .expect("send should work");
+        let mut bytes = vec![0; bytes.len()];
+        raw.read_exact(&mut bytes).await.expect("read should work");
+        let received: (u64, String) =
+            wire::read_frame(&mut &bytes[..]).expect("read frame should work");
+        assert_eq!(received, (2, String::from("world")));
+    }
 }
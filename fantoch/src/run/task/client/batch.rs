@@ -1,5 +1,5 @@
 use crate::command::Command;
-use crate::id::{Rifl, ShardId};
+use crate::id::ShardId;
 use crate::HashMap;
 use std::iter::FromIterator;
 use tokio::time::Instant;
@@ -7,7 +7,6 @@ use tokio::time::Instant;
 #[derive(Debug)]
 pub struct Batch {
     cmd: Command,
-    rifls: Vec<Rifl>,
     deadline: Instant,
     // mapping from shard id to the number of times it was selected as the
     // target for the commands in this batch
@@ -16,10 +15,8 @@ pub struct Batch {
 
 impl Batch {
     pub fn new(target_shard: ShardId, cmd: Command, deadline: Instant) -> Self {
-        let rifl = cmd.rifl();
         Self {
             cmd,
-            rifls: vec![rifl],
             deadline,
             target_shards: HashMap::from_iter(vec![(target_shard, 1)]),
         }
@@ -30,10 +27,8 @@ impl Batch {
         // command
         assert!(other.shards().any(|shard_id| shard_id == &target_shard));
 
-        let rifl = other.rifl();
+        // the command keeps track of the rifls merged into it
         self.cmd.merge(other);
-        // add this command's rifl to the list of rifls in this batch
-        self.rifls.push(rifl);
         // update target shard counts
         let current_count = self.target_shards.entry(target_shard).or_default();
         *current_count += 1;
@@ -44,17 +39,17 @@ impl Batch {
     }
 
     #[cfg(test)]
-    pub fn rifls(&self) -> &Vec<Rifl> {
-        &self.rifls
+    pub fn rifls(&self) -> Vec<crate::id::Rifl> {
+        self.cmd.batch_rifls()
     }
 
     pub fn size(&self) -> usize {
-        self.rifls.len()
+        self.cmd.batch_size()
     }
 
-    pub fn unpack(self) -> (ShardId, Command, Vec<Rifl>) {
+    pub fn unpack(self) -> (ShardId, Command) {
         let target_shard = self.target_shard();
-        (target_shard, self.cmd, self.rifls)
+        (target_shard, self.cmd)
     }
 
     /// Computes the target shard as the shard most selected as the target
@@ -76,6 +71,7 @@ impl Batch {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::id::Rifl;
     use crate::kvs::{KVOp, Key};
 
     #[test]
@@ -111,22 +107,22 @@ mod tests {
         let cmd5 = create_command(rifl5, shard2, key_c.clone());
 
         let mut batch = Batch::new(shard1, cmd1, Instant::now());
-        assert_eq!(batch.rifls(), &vec![rifl1]);
+        assert_eq!(batch.rifls(), vec![rifl1]);
         assert_eq!(batch.size(), 1);
         assert_eq!(batch.target_shard(), shard1);
 
         batch.merge(shard1, cmd2);
-        assert_eq!(batch.rifls(), &vec![rifl1, rifl2]);
+        assert_eq!(batch.rifls(), vec![rifl1, rifl2]);
         assert_eq!(batch.size(), 2);
         assert_eq!(batch.target_shard(), shard1);
 
         batch.merge(shard2, cmd3);
-        assert_eq!(batch.rifls(), &vec![rifl1, rifl2, rifl3]);
+        assert_eq!(batch.rifls(), vec![rifl1, rifl2, rifl3]);
         assert_eq!(batch.size(), 3);
         assert_eq!(batch.target_shard(), shard1);
 
         batch.merge(shard2, cmd4);
-        assert_eq!(batch.rifls(), &vec![rifl1, rifl2, rifl3, rifl4]);
+        assert_eq!(batch.rifls(), vec![rifl1, rifl2, rifl3, rifl4]);
         assert_eq!(batch.size(), 4);
         // at this point the target shard can be either as both have the same
         // count
@@ -135,7 +131,7 @@ mod tests {
         );
 
         batch.merge(shard2, cmd5);
-        assert_eq!(batch.rifls(), &vec![rifl1, rifl2, rifl3, rifl4, rifl5]);
+        assert_eq!(batch.rifls(), vec![rifl1, rifl2, rifl3, rifl4, rifl5]);
         assert_eq!(batch.size(), 5);
         assert_eq!(batch.target_shard(), shard2);
 
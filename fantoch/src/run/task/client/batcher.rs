@@ -1,10 +1,11 @@
 use super::batch::Batch;
 use crate::command::Command;
+use crate::error::RunError;
 use crate::id::ShardId;
 use crate::run::chan::{ChannelReceiver, ChannelSender};
 use crate::run::task;
 use crate::warn;
-use color_eyre::eyre::{eyre, Report};
+use color_eyre::Report;
 use tokio::time::{self, Duration};
 
 struct BatchingConfig {
@@ -88,7 +89,8 @@ async fn add_to_batch(
         }
         Ok(())
     } else {
-        Err(eyre!("error receiving message from parent"))
+        let msg = String::from("error receiving message from parent");
+        Err(RunError::Channel(msg).into())
     }
 }
 
@@ -0,0 +1,139 @@
+use super::client_say_hi;
+use crate::client::ProcessProbe;
+use crate::error::RunError;
+use crate::run::rw::TcpOptions;
+use crate::run::task;
+use crate::{info, warn};
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use std::fmt::Debug;
+use std::time::Instant;
+use tokio::net::ToSocketAddrs;
+
+// number of times the round-trip time to each candidate process is measured
+const PROBES: usize = 5;
+
+/// Detects the closest process on each shard (i.e. the one with the lowest
+/// median round-trip time) among the `candidates`. This allows clients to run
+/// without knowing their region (e.g. in clusters without region labels):
+/// - first, each candidate is probed `PROBES` times, measuring how long it
+///   takes to establish a connection with it, and is asked for its identifier
+///   and the shard it belongs to
+/// - then, the closest candidate on each shard is selected
+///
+/// Returns the addresses of the selected candidates (one per shard) along with
+/// the measurements of all candidates.
+pub async fn closest_processes<A>(
+    candidates: Vec<A>,
+    tcp_options: TcpOptions,
+    connect_retries: usize,
+) -> Result<(Vec<A>, Vec<ProcessProbe>), Report>
+where
+    A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
+{
+    let tcp_buffer_size = 0;
+    let mut probes = Vec::with_capacity(candidates.len());
+    for candidate in candidates.iter() {
+        let mut rtts = Vec::with_capacity(PROBES);
+        let mut hi = None;
+        for probe in 0..PROBES {
+            // only the first probe retries, as the candidate may still be
+            // starting
+            let retries = if probe == 0 { connect_retries } else { 1 };
+            let start = Instant::now();
+            let mut connection = task::connect(
+                candidate.clone(),
+                tcp_options,
+                tcp_buffer_size,
+                retries,
+            )
+            .await
+            .wrap_err_with(|| format!("probe candidate {:?}", candidate))?;
+            rtts.push(start.elapsed());
+
+            // say hi (without any client) so that the candidate doesn't
+            // complain about the connection, and to find out its identifier
+            hi = client_say_hi(Vec::new(), &mut connection).await;
+        }
+        let (process_id, shard_id) = hi.ok_or_else(|| {
+            let msg = format!("candidate {:?} didn't say hi back", candidate);
+            RunError::Connection(msg)
+        })?;
+        probes.push(ProcessProbe::new(process_id, shard_id, rtts));
+    }
+
+    // select the closest candidate on each shard
+    select(&mut probes);
+    let selected = candidates
+        .into_iter()
+        .zip(probes.iter())
+        .filter(|(_, probe)| probe.selected)
+        .map(|(candidate, probe)| {
+            info!(
+                "[client] selected {:?} (process {}) on shard {} with median rtt {:?}",
+                candidate,
+                probe.process_id,
+                probe.shard_id,
+                probe.median_rtt()
+            );
+            candidate
+        })
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        warn!("[client] no candidate process selected");
+    }
+    Ok((selected, probes))
+}
+
+// Marks, on each shard, the probe with the lowest median round-trip time as
+// selected (if there's a tie, the process with the lowest identifier is
+// selected).
+fn select(probes: &mut [ProcessProbe]) {
+    let mut closest = crate::HashMap::new();
+    for (index, probe) in probes.iter().enumerate() {
+        let key = (probe.median_rtt(), probe.process_id);
+        let current = closest.entry(probe.shard_id).or_insert((key, index));
+        if key < current.0 {
+            *current = (key, index);
+        }
+    }
+    for (_, index) in closest.values() {
+        probes[*index].selected = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn probe(
+        process_id: crate::id::ProcessId,
+        shard_id: crate::id::ShardId,
+        rtts: Vec<u64>,
+    ) -> ProcessProbe {
+        let rtts = rtts.into_iter().map(Duration::from_millis).collect();
+        ProcessProbe::new(process_id, shard_id, rtts)
+    }
+
+    #[test]
+    fn select_closest() {
+        let mut probes = vec![
+            // shard 0: process 2 has the lowest median, even though process 1
+            // had the lowest round-trip time
+            probe(1, 0, vec![1, 50, 60]),
+            probe(2, 0, vec![10, 10, 30]),
+            probe(3, 0, vec![20, 20, 20]),
+            // shard 1: processes 4 and 5 tie, and so the lowest is selected
+            probe(5, 1, vec![15, 5, 10]),
+            probe(4, 1, vec![10, 10, 10]),
+        ];
+        select(&mut probes);
+        let selected: Vec<_> = probes
+            .iter()
+            .filter(|probe| probe.selected)
+            .map(|probe| probe.process_id)
+            .collect();
+        assert_eq!(selected, vec![2, 4]);
+    }
+}
@@ -0,0 +1,455 @@
+use super::rw;
+use crate::command::{Command, CommandResult};
+use crate::error::RunError;
+use crate::hash_map::HashMap;
+use crate::id::{ClientId, Rifl, RiflGen, ShardId};
+use crate::kvs::{KVError, KVOp, KVOpResult, Key};
+use crate::run::chan::{self, ChannelSender};
+use crate::run::prelude::*;
+use crate::run::rw::TcpOptions;
+use crate::run::task;
+use crate::time::{RunTime, SysTime};
+use crate::{info, trace, warn};
+use color_eyre::eyre::{eyre, Report};
+use std::fmt::Debug;
+use std::net::IpAddr;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+// Request sent by a frontend (HTTP or gRPC) task to the gateway: the
+// operations to be executed on each key along with the channel where their
+// results should be sent.
+pub type GatewayRequest =
+    (HashMap<Key, Vec<KVOp>>, ChannelSender<GatewayResult>);
+
+// Result of a gateway request: the identifier assigned to the command
+// submitted along with the results of the operations on each key, and the
+// errors on the keys whose operations failed to execute.
+pub type GatewayResult =
+    (Rifl, HashMap<Key, Vec<KVOpResult>>, HashMap<Key, KVError>);
+
+// Results of a command that are yet to be received from some shards.
+struct Pending {
+    missing_shards: usize,
+    results: HashMap<Key, Vec<KVOpResult>>,
+    errors: HashMap<Key, KVError>,
+    reply: ChannelSender<GatewayResult>,
+}
+
+/// Starts an HTTP gateway that converts REST requests into single-key commands
+/// submitted (as client `client_id`) to the processes in `addresses` (one per
+/// shard). The following requests are supported:
+/// - `GET /KEY`: reads the value of `KEY`
+/// - `PUT /KEY` (with the value as the request body): writes to `KEY`
+/// - `DELETE /KEY`: deletes `KEY`
+///
+/// Each request gets a JSON response of the form `{"key": KEY, "result":
+/// RESULT}`, where `RESULT` is the result of the operation (`null` if none).
+///
+/// If `grpc_port` is set, the gateway also serves the `Fantoch` gRPC service
+/// (defined in `proto/fantoch.proto`) on that port, which supports multi-key
+/// commands; this requires the `grpc` feature.
+pub async fn gateway<A>(
+    client_id: ClientId,
+    addresses: Vec<A>,
+    ip: IpAddr,
+    port: u16,
+    grpc_port: Option<u16>,
+    connect_retries: usize,
+    tcp_options: TcpOptions,
+    channel_buffer_size: usize,
+) -> Result<(), Report>
+where
+    A: ToSocketAddrs + Clone + Debug,
+{
+    let client_ids = vec![client_id];
+    let shard_count = addresses.len() as u64;
+    let mut shard_to_process = HashMap::with_capacity(addresses.len());
+    let mut connections = Vec::with_capacity(addresses.len());
+
+    // connect to each address (one per shard)
+    let tcp_buffer_size = 0;
+    for address in addresses {
+        let mut connection = task::connect(
+            address,
+            tcp_options,
+            tcp_buffer_size,
+            connect_retries,
+        )
+        .await?;
+
+        // say hi
+        let (process_id, shard_id) =
+            super::client_say_hi(client_ids.clone(), &mut connection)
+                .await
+                .ok_or_else(|| {
+                    let msg = String::from("couldn't say hi to process");
+                    RunError::Connection(msg)
+                })?;
+        assert!(
+            shard_to_process.insert(shard_id, process_id).is_none(),
+            "gateway shouldn't connect to the same shard more than once"
+        );
+        // no delay is injected between the gateway and the processes
+        connections.push((process_id, connection, None));
+    }
+    assert!(
+        (0..shard_count)
+            .all(|shard_id| shard_to_process.contains_key(&shard_id)),
+        "gateway should be connected to all shards"
+    );
+
+    // start client read-write tasks
+    let (mut from_server, mut process_to_writer) = rw::start_client_rw_tasks(
+        &client_ids,
+        channel_buffer_size,
+        connections,
+    );
+    let mut shard_to_writer: HashMap<_, _> = shard_to_process
+        .into_iter()
+        .map(|(shard_id, process_id)| {
+            let writer = process_to_writer
+                .remove(&process_id)
+                .expect("a rw-task should exist for each process id");
+            (shard_id, writer)
+        })
+        .collect();
+
+    // start HTTP listener
+    let listener = task::listen((ip, port)).await?;
+    let (to_gateway, mut from_frontends) = chan::channel(channel_buffer_size);
+    task::spawn(http_listener_task(listener, to_gateway.clone()));
+    info!("gateway listening on {}:{}", ip, port);
+
+    // start gRPC server
+    if let Some(grpc_port) = grpc_port {
+        start_grpc_server(ip, grpc_port, to_gateway)?;
+        info!("gateway serving gRPC on {}:{}", ip, grpc_port);
+    }
+
+    // mapping from each pending command to its results so far
+    let mut pending = HashMap::new();
+    let mut rifl_gen = RiflGen::new(client_id);
+
+    loop {
+        tokio::select! {
+            request = from_frontends.recv() => {
+                let (ops, reply) = request.ok_or_else(|| {
+                    let msg = "error receiving request from gateway frontends";
+                    RunError::Channel(msg.to_string())
+                })?;
+                let rifl = rifl_gen.next_id();
+                let cmd = gateway_cmd(rifl, ops, shard_count);
+                let shard_ids: Vec<_> = cmd.shards().cloned().collect();
+                let cmd_pending = Pending {
+                    missing_shards: shard_ids.len(),
+                    results: HashMap::with_capacity(cmd.total_key_count()),
+                    errors: HashMap::new(),
+                    reply,
+                };
+                pending.insert(rifl, cmd_pending);
+
+                // submit the command to all the shards it accesses
+                for shard_id in shard_ids {
+                    let writer = shard_to_writer
+                        .get_mut(&shard_id)
+                        .expect("[gateway] there should be a writer for each shard");
+                    let msg = ClientToServer::Submit(cmd.clone());
+                    if let Err(e) = writer.send(msg).await {
+                        warn!("[gateway] error while submitting command: {:?}", e);
+                    }
+                }
+            }
+            cmd_result = from_server.recv() => {
+                let cmd_result = cmd_result.ok_or_else(|| {
+                    let msg = "error receiving command result from server";
+                    RunError::Channel(msg.to_string())
+                })?;
+                handle_cmd_result(cmd_result, &mut pending).await;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn start_grpc_server(
+    ip: IpAddr,
+    grpc_port: u16,
+    to_gateway: ChannelSender<GatewayRequest>,
+) -> Result<(), Report> {
+    let address = std::net::SocketAddr::new(ip, grpc_port);
+    task::spawn(super::grpc::grpc_server_task(address, to_gateway));
+    Ok(())
+}
+
+#[cfg(not(feature = "grpc"))]
+fn start_grpc_server(
+    _ip: IpAddr,
+    _grpc_port: u16,
+    _to_gateway: ChannelSender<GatewayRequest>,
+) -> Result<(), Report> {
+    Err(eyre!("serving gRPC requires the grpc feature"))
+}
+
+// Creates a command with the operations provided, placing each key on the
+// shard that replicates it. The command is timestamped with the current time.
+fn gateway_cmd(
+    rifl: Rifl,
+    ops: HashMap<Key, Vec<KVOp>>,
+    shard_count: u64,
+) -> Command {
+    let mut shard_to_ops: HashMap<ShardId, HashMap<_, _>> = HashMap::new();
+    for (key, key_ops) in ops {
+        let shard_id = crate::util::key_hash(&key) % shard_count;
+        shard_to_ops
+            .entry(shard_id)
+            .or_default()
+            .insert(key, key_ops);
+    }
+    let mut cmd = Command::new(rifl, shard_to_ops);
+    cmd.set_timestamp(RunTime.millis());
+    cmd
+}
+
+async fn handle_cmd_result(
+    cmd_result: CommandResult,
+    pending: &mut HashMap<Rifl, Pending>,
+) {
+    let rifl = cmd_result.rifl();
+    let cmd_pending = pending
+        .get_mut(&rifl)
+        .expect("[gateway] command result should belong to a pending command");
+    // add the results of this shard
+    cmd_pending.results.extend(
+        cmd_result
+            .results()
+            .iter()
+            .map(|(key, results)| (key.clone(), results.clone())),
+    );
+    cmd_pending.errors.extend(
+        cmd_result
+            .errors()
+            .iter()
+            .map(|(key, error)| (key.clone(), *error)),
+    );
+    cmd_pending.missing_shards -= 1;
+
+    // reply once the results from all shards were received
+    if cmd_pending.missing_shards == 0 {
+        let Pending {
+            results,
+            errors,
+            mut reply,
+            ..
+        } = pending.remove(&rifl).expect("pending command should exist");
+        if let Err(e) = reply.send((rifl, results, errors)).await {
+            warn!("[gateway] error while sending result to frontend: {:?}", e);
+        }
+    }
+}
+
+async fn http_listener_task(
+    listener: TcpListener,
+    to_gateway: ChannelSender<GatewayRequest>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _addr)) => {
+                trace!("[gateway] new http connection: {:?}", _addr);
+                task::spawn(http_connection_task(stream, to_gateway.clone()));
+            }
+            Err(e) => {
+                warn!("[gateway] couldn't accept new connection: {:?}", e)
+            }
+        }
+    }
+}
+
+// Handles a single HTTP request, closing the connection afterwards.
+async fn http_connection_task(
+    mut stream: TcpStream,
+    to_gateway: ChannelSender<GatewayRequest>,
+) {
+    let (read, mut write) = stream.split();
+    let mut reader = BufReader::new(read);
+    let response = match read_request(&mut reader).await {
+        Ok((method, path, body)) => match parse_request(&method, &path, body) {
+            Ok((key, op)) => http_execute(to_gateway, key, op).await,
+            Err((status, error)) => http_error(status, error),
+        },
+        Err(e) => http_error("400 Bad Request", &e.to_string()),
+    };
+    if let Err(e) = write.write_all(response.as_bytes()).await {
+        warn!("[gateway] error while writing http response: {:?}", e);
+    }
+}
+
+// Executes a single operation on a single key, returning the HTTP response.
+async fn http_execute(
+    mut to_gateway: ChannelSender<GatewayRequest>,
+    key: Key,
+    op: KVOp,
+) -> String {
+    let (reply_tx, mut reply_rx) = chan::channel(1);
+    let mut ops = HashMap::new();
+    ops.insert(key.clone(), vec![op]);
+    if let Err(e) = to_gateway.send((ops, reply_tx)).await {
+        warn!("[gateway] error while sending request: {:?}", e);
+    }
+    match reply_rx.recv().await {
+        Some((_rifl, results, errors)) => {
+            if let Some(error) = errors.get(&key) {
+                let body = serde_json::json!({
+                    "key": key,
+                    "error": error,
+                });
+                return http_response("403 Forbidden", body);
+            }
+            let result = results
+                .get(&key)
+                .and_then(|results| results.first())
+                .cloned()
+                .expect("there should be a result for the operation");
+            let body = serde_json::json!({
+                "key": key,
+                "result": result,
+            });
+            http_response("200 OK", body)
+        }
+        None => {
+            http_error("503 Service Unavailable", "gateway is not available")
+        }
+    }
+}
+
+// Reads an HTTP request, returning its method, path and body.
+async fn read_request<R>(
+    reader: &mut R,
+) -> Result<(String, String, String), Report>
+where
+    R: AsyncBufRead + Unpin,
+{
+    // read request line
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Err(eyre!("connection closed before request"));
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or_else(|| eyre!("missing method"))?;
+    let path = parts.next().ok_or_else(|| eyre!("missing path"))?;
+    let (method, path) = (method.to_string(), path.to_string());
+
+    // read headers (only the content length matters)
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            return Err(eyre!("connection closed before end of headers"));
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let mut parts = header.splitn(2, ':');
+        let name = parts.next().unwrap_or_default();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length =
+                parts.next().unwrap_or_default().trim().parse::<usize>()?;
+        }
+    }
+
+    // read body
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    let body = String::from_utf8(body)?;
+    Ok((method, path, body))
+}
+
+// Converts an HTTP request into the operation to be executed on some key.
+fn parse_request(
+    method: &str,
+    path: &str,
+    body: String,
+) -> Result<(Key, KVOp), (&'static str, &'static str)> {
+    let key = path.trim_start_matches('/');
+    if key.is_empty() || key.contains('/') {
+        return Err(("404 Not Found", "path should be of the form /KEY"));
+    }
+    let op = match method {
+        "GET" => KVOp::Get,
+        "PUT" => KVOp::Put(body),
+        "DELETE" => KVOp::Delete,
+        _ => {
+            return Err((
+                "405 Method Not Allowed",
+                "method should be GET, PUT or DELETE",
+            ))
+        }
+    };
+    Ok((key.to_string(), op))
+}
+
+fn http_error(status: &str, error: &str) -> String {
+    http_response(status, serde_json::json!({ "error": error }))
+}
+
+fn http_response(status: &str, body: serde_json::Value) -> String {
+    let body = body.to_string();
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn gateway_read_request() {
+        let request = "PUT /a HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello";
+        let mut reader = BufReader::new(request.as_bytes());
+        let (method, path, body) = read_request(&mut reader).await.unwrap();
+        assert_eq!(method, "PUT");
+        assert_eq!(path, "/a");
+        assert_eq!(body, "hello");
+
+        // requests with no body
+        let request = "GET /a HTTP/1.1\r\n\r\n";
+        let mut reader = BufReader::new(request.as_bytes());
+        let (method, path, body) = read_request(&mut reader).await.unwrap();
+        assert_eq!(method, "GET");
+        assert_eq!(path, "/a");
+        assert_eq!(body, "");
+
+        // incomplete requests
+        let request = "GET /a HTTP/1.1\r\n";
+        let mut reader = BufReader::new(request.as_bytes());
+        assert!(read_request(&mut reader).await.is_err());
+    }
+
+    #[test]
+    fn gateway_parse_request() {
+        let key = String::from("a");
+        let value = String::from("x");
+        assert_eq!(
+            parse_request("GET", "/a", String::new()),
+            Ok((key.clone(), KVOp::Get))
+        );
+        assert_eq!(
+            parse_request("PUT", "/a", value.clone()),
+            Ok((key.clone(), KVOp::Put(value)))
+        );
+        assert_eq!(
+            parse_request("DELETE", "/a", String::new()),
+            Ok((key, KVOp::Delete))
+        );
+        assert!(parse_request("GET", "/", String::new()).is_err());
+        assert!(parse_request("GET", "/a/b", String::new()).is_err());
+        assert!(parse_request("POST", "/a", String::new()).is_err());
+    }
+}
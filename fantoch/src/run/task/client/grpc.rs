@@ -0,0 +1,184 @@
+use super::gateway::GatewayRequest;
+use crate::hash_map::HashMap;
+use crate::id::Rifl;
+use crate::kvs::{KVError, KVOp, KVOpResult, Key};
+use crate::run::chan::{self, ChannelSender};
+use crate::run::task;
+use crate::warn;
+use futures::stream::Stream;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+// Code generated from `proto/fantoch.proto`.
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("fantoch");
+}
+
+use proto::fantoch_server::{Fantoch, FantochServer};
+
+// Number of results that can be buffered in each stream of results.
+const STREAM_BUFFER_SIZE: usize = 1000;
+
+pub async fn grpc_server_task(
+    address: SocketAddr,
+    to_gateway: ChannelSender<GatewayRequest>,
+) {
+    let service = FantochServer::new(GrpcService { to_gateway });
+    if let Err(e) = tonic::transport::Server::builder()
+        .add_service(service)
+        .serve(address)
+        .await
+    {
+        warn!("[grpc] error while serving gRPC requests: {:?}", e);
+    }
+}
+
+struct GrpcService {
+    to_gateway: ChannelSender<GatewayRequest>,
+}
+
+#[tonic::async_trait]
+impl Fantoch for GrpcService {
+    async fn submit(
+        &self,
+        request: Request<proto::Command>,
+    ) -> Result<Response<proto::CommandResult>, Status> {
+        let result =
+            execute(self.to_gateway.clone(), request.into_inner()).await?;
+        Ok(Response::new(result))
+    }
+
+    type SubmitStreamStream = Pin<
+        Box<
+            dyn Stream<Item = Result<proto::CommandResult, Status>>
+                + Send
+                + Sync
+                + 'static,
+        >,
+    >;
+
+    async fn submit_stream(
+        &self,
+        request: Request<Streaming<proto::Command>>,
+    ) -> Result<Response<Self::SubmitStreamStream>, Status> {
+        let mut commands = request.into_inner();
+        let to_gateway = self.to_gateway.clone();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+
+        task::spawn(async move {
+            loop {
+                match commands.message().await {
+                    Ok(Some(cmd)) => {
+                        // execute each command in its own task so that
+                        // commands in the same stream are executed
+                        // concurrently
+                        let to_gateway = to_gateway.clone();
+                        let tx = tx.clone();
+                        task::spawn(async move {
+                            let result = execute(to_gateway, cmd).await;
+                            if tx.send(result).await.is_err() {
+                                warn!("[grpc] stream of results was closed");
+                            }
+                        });
+                    }
+                    Ok(None) => {
+                        // the stream of commands has ended
+                        break;
+                    }
+                    Err(status) => {
+                        warn!(
+                            "[grpc] error in stream of commands: {:?}",
+                            status
+                        );
+                        let _ = tx.send(Err(status)).await;
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+// Submits a command to the gateway, waiting for its result.
+async fn execute(
+    mut to_gateway: ChannelSender<GatewayRequest>,
+    cmd: proto::Command,
+) -> Result<proto::CommandResult, Status> {
+    let id = cmd.id;
+    let ops = from_proto(cmd)?;
+    let (reply_tx, mut reply_rx) = chan::channel(1);
+    if let Err(e) = to_gateway.send((ops, reply_tx)).await {
+        warn!("[grpc] error while sending request to gateway: {:?}", e);
+    }
+    let (rifl, results, errors) = reply_rx
+        .recv()
+        .await
+        .ok_or_else(|| Status::unavailable("gateway is not available"))?;
+    Ok(to_proto(id, rifl, results, errors))
+}
+
+fn from_proto(cmd: proto::Command) -> Result<HashMap<Key, Vec<KVOp>>, Status> {
+    if cmd.ops.is_empty() {
+        return Err(Status::invalid_argument(
+            "command should access at least one key",
+        ));
+    }
+    cmd.ops
+        .into_iter()
+        .map(|(key, ops)| {
+            let ops = ops
+                .ops
+                .into_iter()
+                .map(|op| match op.kind {
+                    Some(proto::op::Kind::Get(_)) => Ok(KVOp::Get),
+                    Some(proto::op::Kind::Put(put)) => Ok(KVOp::Put(put.value)),
+                    Some(proto::op::Kind::Delete(_)) => Ok(KVOp::Delete),
+                    None => Err(Status::invalid_argument(
+                        "operation kind should be set",
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((key, ops))
+        })
+        .collect()
+}
+
+fn to_proto(
+    id: u64,
+    rifl: Rifl,
+    results: HashMap<Key, Vec<KVOpResult>>,
+    errors: HashMap<Key, KVError>,
+) -> proto::CommandResult {
+    let results = results
+        .into_iter()
+        .map(|(key, results)| {
+            let results = results.into_iter().map(op_result_to_proto).collect();
+            (key, proto::OpResults { results })
+        })
+        .collect();
+    let errors = errors
+        .into_iter()
+        .map(|(key, error)| (key, format!("{:?}", error)))
+        .collect();
+    proto::CommandResult {
+        id,
+        rifl: Some(proto::Rifl {
+            source: rifl.source(),
+            sequence: rifl.sequence(),
+        }),
+        results,
+        errors,
+    }
+}
+
+fn op_result_to_proto(result: KVOpResult) -> proto::OpResult {
+    proto::OpResult {
+        kind: result.map(proto::op_result::Kind::Value),
+    }
+}
@@ -13,82 +13,165 @@ mod batcher;
 // Implementation of an unbatcher.
 mod unbatcher;
 
-use crate::client::{Client, ClientData, Workload};
-use crate::command::{Command, CommandResult};
+// Implementation of the detection of the closest process on each shard.
+mod detect;
+
+// Implementation of an HTTP gateway.
+mod gateway;
+
+// Implementation of the gRPC frontend of the gateway.
+#[cfg(feature = "grpc")]
+mod grpc;
+
+// Re-exports.
+pub use gateway::gateway;
+
+use crate::client::{Client, ClientData, ClientStats, Workload, WorkloadMix};
+use crate::command::{Command, CommandResult, CommandTrace};
 use crate::hash_map::HashMap;
 use crate::id::{ClientId, ProcessId, Rifl, ShardId};
 use crate::run::chan::{self, ChannelReceiver, ChannelSender};
 use crate::run::prelude::*;
-use crate::run::rw::Connection;
+use crate::run::rw::{Connection, TcpOptions};
 use crate::run::task;
-use crate::time::{RunTime, SysTime};
+use crate::time::{MonotonicTime, SysTime};
 use crate::HashSet;
 use crate::{info, trace, warn};
 use color_eyre::Report;
 use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::ToSocketAddrs;
+use tokio::sync::watch;
 
 const MAX_CLIENT_CONNECTIONS: usize = 32;
+// how often clients check whether their pending commands have timed out (if a
+// command timeout is set)
+const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
 
 pub async fn client<A>(
     ids: Vec<ClientId>,
-    addresses: Vec<A>,
+    addresses: Vec<(A, Option<Duration>)>,
+    detect_closest: bool,
     interval: Option<Duration>,
-    workload: Workload,
+    workload_mix: WorkloadMix,
     batch_max_size: usize,
     batch_max_delay: Duration,
     connect_retries: usize,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     channel_buffer_size: usize,
     status_frequency: Option<usize>,
+    stats_interval: Option<Duration>,
+    max_run_time: Option<Duration>,
+    shutdown_timeout: Duration,
+    command_timeout: Option<Duration>,
     metrics_file: Option<String>,
+    traces_file: Option<String>,
 ) -> Result<(), Report>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
 {
+    // clients' setup time is measured from here
+    let start = Instant::now();
+
+    // latencies are measured with a raw monotonic clock (calibrated against
+    // the wall-clock), shared by all client workers
+    let time = MonotonicTime::new();
+
+    // on SIGINT, clients stop issuing new commands and wait for the pending
+    // ones, so that the data collected so far is not lost
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    task::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("[client] SIGINT received; shutting down clients");
+            let _ = shutdown_tx.send(true);
+        }
+    });
+
+    // if the closest processes are to be detected, the addresses given are the
+    // candidates; otherwise, they're the ones to connect to (one per shard)
+    let (addresses, probes) = if detect_closest {
+        // no delays are injected when connecting to the closest processes, as
+        // these are supposed to reflect the actual distance to them
+        let candidates =
+            addresses.into_iter().map(|(address, _)| address).collect();
+        let (addresses, probes) =
+            detect::closest_processes(candidates, tcp_options, connect_retries)
+                .await?;
+        let addresses = addresses
+            .into_iter()
+            .map(|address| (address, None))
+            .collect();
+        (addresses, probes)
+    } else {
+        (addresses, Vec::new())
+    };
+
+    // number of commands completed by all clients so far; if a stats interval
+    // is set, their stats are periodically logged
+    let completed = Arc::new(AtomicU64::new(0));
+    let stats_handle = stats_interval.map(|stats_interval| {
+        task::spawn(log_stats(stats_interval, completed.clone()))
+    });
+
     // create client pool
     let mut pool = Vec::with_capacity(MAX_CLIENT_CONNECTIONS);
     // init each entry
     pool.resize_with(MAX_CLIENT_CONNECTIONS, Vec::new);
 
-    // assign each client to a client worker
-    ids.into_iter().enumerate().for_each(|(index, client_id)| {
-        let index = index % MAX_CLIENT_CONNECTIONS;
-        pool[index].push(client_id);
-    });
+    // assign a workload to each client, and each client to a client worker
+    workload_mix.assign(ids).into_iter().enumerate().for_each(
+        |(index, client)| {
+            let index = index % MAX_CLIENT_CONNECTIONS;
+            pool[index].push(client);
+        },
+    );
 
     // start each client worker in pool
-    let handles = pool.into_iter().filter_map(|client_ids| {
+    let handles = pool.into_iter().filter_map(|clients| {
         // only start a client for this pool index if any client id was assigned
         // to it
-        if !client_ids.is_empty() {
+        if !clients.is_empty() {
             // start the open loop client if some interval was provided
             let handle = if let Some(interval) = interval {
                 task::spawn(open_loop_client::<A>(
-                    client_ids,
+                    clients,
+                    start,
+                    time,
                     addresses.clone(),
                     interval,
-                    workload,
                     batch_max_size,
                     batch_max_delay,
                     connect_retries,
-                    tcp_nodelay,
+                    tcp_options,
                     channel_buffer_size,
                     status_frequency,
+                    completed.clone(),
+                    max_run_time,
+                    shutdown_rx.clone(),
+                    shutdown_timeout,
+                    command_timeout,
                 ))
             } else {
                 task::spawn(closed_loop_client::<A>(
-                    client_ids,
+                    clients,
+                    start,
+                    time,
                     addresses.clone(),
-                    workload,
                     batch_max_size,
                     batch_max_delay,
                     connect_retries,
-                    tcp_nodelay,
+                    tcp_options,
                     channel_buffer_size,
                     status_frequency,
+                    completed.clone(),
+                    max_run_time,
+                    shutdown_rx.clone(),
+                    shutdown_timeout,
+                    command_timeout,
                 ))
             };
             Some(handle)
@@ -97,8 +180,12 @@ where
         }
     });
 
-    // wait for all clients to complete and aggregate their metrics
+    // wait for all clients to complete and aggregate their metrics (and the
+    // number of commands issued to each shard)
     let mut data = ClientData::new();
+    data.record_probes(probes);
+    let mut traces = Vec::new();
+    let mut shard_commands = BTreeMap::new();
 
     let mut handles = handles.collect::<FuturesUnordered<_>>();
     while let Some(join_result) = handles.next().await {
@@ -106,45 +193,100 @@ where
         for client in clients {
             info!("client {} ended", client.id());
             data.merge(client.data());
+            traces.extend(client.traces().iter().cloned());
+            for (shard_id, commands) in client.shard_commands() {
+                *shard_commands.entry(*shard_id).or_insert(0) += commands;
+            }
             info!("metrics from {} collected", client.id());
         }
     }
 
+    // stop logging stats (if they were being logged)
+    if let Some(handle) = stats_handle {
+        handle.abort();
+    }
+
+    if data.partial() {
+        warn!("[client] clients were shut down before finishing their workload; their data is partial");
+    }
+
+    // record how much the wall-clock drifted while clients ran
+    let drift = time.drift();
+    info!(
+        "clock drift of {}ns after {:?} ({:.3}ppm)",
+        drift.drift_nanos,
+        drift.elapsed,
+        drift.ppm()
+    );
+    data.record_clock_drift(drift);
+
+    if let Some(setup_time) = data.setup_time() {
+        info!("all clients connected after {:?}", setup_time);
+    }
+
+    // the experiment harness checks, for each shard, that these match the
+    // number of commands executed by servers
+    for (shard_id, commands) in shard_commands {
+        info!("shard {} commands issued: {}", shard_id, commands);
+    }
+
     if let Some(file) = metrics_file {
         info!("will write client data to {}", file);
         task::util::serialize_and_compress(&data, &file)?;
     }
 
+    if let Some(file) = traces_file {
+        info!("will write {} client traces to {}", traces.len(), file);
+        task::util::serialize_and_compress(&traces, &file)?;
+    }
+
     info!("all clients ended");
     Ok(())
 }
 
 async fn closed_loop_client<A>(
-    client_ids: Vec<ClientId>,
-    addresses: Vec<A>,
-    workload: Workload,
+    clients: Vec<(ClientId, Workload)>,
+    start: Instant,
+    time: MonotonicTime,
+    addresses: Vec<(A, Option<Duration>)>,
     batch_max_size: usize,
     batch_max_delay: Duration,
     connect_retries: usize,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     channel_buffer_size: usize,
     status_frequency: Option<usize>,
+    completed: Arc<AtomicU64>,
+    max_run_time: Option<Duration>,
+    mut shutdown: watch::Receiver<bool>,
+    shutdown_timeout: Duration,
+    command_timeout: Option<Duration>,
 ) -> Option<Vec<Client>>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
 {
-    // create system time
-    let time = RunTime;
+    // clients are stopped once `max_run_time` is reached (if set)
+    let max_run_time_reached = max_run_time_reached(max_run_time);
+    tokio::pin!(max_run_time_reached);
+
+    // once a shutdown is requested, clients wait at most `shutdown_timeout`
+    // for their pending commands
+    let mut shutting_down = false;
+    let shutdown_deadline = tokio::time::sleep(shutdown_timeout);
+    tokio::pin!(shutdown_deadline);
+
+    // pending commands are periodically checked for timeouts (if a command
+    // timeout is set)
+    let mut timeout_check = tokio::time::interval(TIMEOUT_CHECK_INTERVAL);
 
     // setup client
     let (mut clients, mut unbatcher_rx, mut batcher_tx) = client_setup(
-        client_ids,
+        clients,
+        start,
         addresses,
-        workload,
         batch_max_size,
         batch_max_delay,
         connect_retries,
-        tcp_nodelay,
+        tcp_options,
         channel_buffer_size,
         status_frequency,
     )
@@ -155,65 +297,122 @@ where
     // track which clients are workload finished
     let mut workload_finished = HashSet::with_capacity(clients.len());
 
-    // generate the first message of each client
+    // generate the first messages of each client (as many as it can have in
+    // flight)
     for client in clients.values_mut() {
-        cmd_send(client, &time, &mut batcher_tx, &mut workload_finished).await;
+        for _ in 0..client.max_in_flight() {
+            if !workload_finished.contains(&client.id()) {
+                cmd_send(
+                    client,
+                    None,
+                    &time,
+                    &mut batcher_tx,
+                    &mut workload_finished,
+                )
+                .await;
+            }
+        }
     }
 
     // wait for results and generate/submit new commands while there are
     // commands to be generated
-    while finished.len() < clients.len() {
-        // and wait for next result
-        let from_unbatcher = unbatcher_rx.recv().await;
-        let ready_clients =
-            cmd_recv(&mut clients, &time, from_unbatcher, &mut finished);
-        for client_id in ready_clients {
-            let client = clients
-                .get_mut(&client_id)
-                .expect("[client] ready client should exist");
-            // if client hasn't finished, issue a new command
-            cmd_send(client, &time, &mut batcher_tx, &mut workload_finished)
-                .await;
+    let mut stopped = false;
+    while !stopped && finished.len() < clients.len() {
+        tokio::select! {
+            from_unbatcher = unbatcher_rx.recv() => {
+                // and wait for next result
+                let ready_clients = cmd_recv(
+                    &mut clients,
+                    &time,
+                    from_unbatcher,
+                    &mut finished,
+                    &completed,
+                );
+                if shutting_down {
+                    // no new commands are issued while shutting down
+                    stopped = pending_empty(&clients);
+                } else {
+                    for client_id in ready_clients {
+                        let client = clients
+                            .get_mut(&client_id)
+                            .expect("[client] ready client should exist");
+                        // if client hasn't finished, issue a new command
+                        // (with more than one command in flight, the workload
+                        // may be finished while some are still pending)
+                        if !workload_finished.contains(&client_id) {
+                            cmd_send(client, None, &time, &mut batcher_tx, &mut workload_finished)
+                                .await;
+                        }
+                    }
+                }
+            }
+            _ = timeout_check.tick(), if command_timeout.is_some() => {
+                check_timeouts(&mut clients, command_timeout, &time);
+            }
+            _ = &mut max_run_time_reached => {
+                stopped = true;
+            }
+            _ = shutdown_requested(&mut shutdown), if !shutting_down => {
+                shutting_down = true;
+                shutdown_deadline.as_mut().reset(tokio::time::Instant::now() + shutdown_timeout);
+                stopped = pending_empty(&clients);
+            }
+            _ = &mut shutdown_deadline, if shutting_down => {
+                warn!("[client] shutdown timeout of {:?} reached; stopping clients", shutdown_timeout);
+                stopped = true;
+            }
         }
     }
-    assert_eq!(workload_finished.len(), finished.len());
+    assert!(stopped || workload_finished.len() == finished.len());
 
     // return clients
-    Some(
-        clients
-            .into_iter()
-            .map(|(_client_id, client)| client)
-            .collect(),
-    )
+    Some(stopped_clients(clients, shutting_down))
 }
 
 async fn open_loop_client<A>(
-    client_ids: Vec<ClientId>,
-    addresses: Vec<A>,
+    clients: Vec<(ClientId, Workload)>,
+    start: Instant,
+    time: MonotonicTime,
+    addresses: Vec<(A, Option<Duration>)>,
     interval: Duration,
-    workload: Workload,
     batch_max_size: usize,
     batch_max_delay: Duration,
     connect_retries: usize,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     channel_buffer_size: usize,
     status_frequency: Option<usize>,
+    completed: Arc<AtomicU64>,
+    max_run_time: Option<Duration>,
+    mut shutdown: watch::Receiver<bool>,
+    shutdown_timeout: Duration,
+    command_timeout: Option<Duration>,
 ) -> Option<Vec<Client>>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
 {
-    // create system time
-    let time = RunTime;
+    // clients are stopped once `max_run_time` is reached (if set)
+    let max_run_time_reached = max_run_time_reached(max_run_time);
+    tokio::pin!(max_run_time_reached);
+
+    // once a shutdown is requested, clients wait at most `shutdown_timeout`
+    // for their pending commands
+    let mut shutting_down = false;
+    let shutdown_deadline = tokio::time::sleep(shutdown_timeout);
+    tokio::pin!(shutdown_deadline);
+
+    // pending commands are periodically checked for timeouts (if a command
+    // timeout is set)
+    let mut timeout_check = tokio::time::interval(TIMEOUT_CHECK_INTERVAL);
 
     // setup client
     let (mut clients, mut unbatcher_rx, mut batcher_tx) = client_setup(
-        client_ids,
+        clients,
+        start,
         addresses,
-        workload,
         batch_max_size,
         batch_max_delay,
         connect_retries,
-        tcp_nodelay,
+        tcp_options,
         channel_buffer_size,
         status_frequency,
     )
@@ -227,7 +426,8 @@ where
     // track which clients are workload finished
     let mut workload_finished = HashSet::with_capacity(clients.len());
 
-    while finished.len() < clients.len() {
+    let mut stopped = false;
+    while !stopped && finished.len() < clients.len() {
         tokio::select! {
             from_unbatcher = unbatcher_rx.recv() => {
                 cmd_recv(
@@ -235,58 +435,156 @@ where
                     &time,
                     from_unbatcher,
                     &mut finished,
+                    &completed,
                 );
+                if shutting_down {
+                    stopped = pending_empty(&clients);
+                }
             }
-            _ = interval.tick() => {
+            tick = interval.tick(), if !shutting_down => {
+                // compute the time at which commands were intended to be
+                // issued on this tick; this is earlier than the current time
+                // if the tick is late, in which case the latency of these
+                // commands is corrected for coordinated omission
+                let intended_start_time = time.micros().saturating_sub(tick.elapsed().as_micros() as u64);
                 // submit new command on every tick for each connected client
                 // (if there are still commands to be generated)
                 for (client_id, client) in clients.iter_mut(){
                     // if the client hasn't finished, try to issue a new command
                     if !workload_finished.contains(client_id) {
-                        cmd_send(client, &time, &mut batcher_tx, &mut workload_finished).await;
+                        cmd_send(client, Some(intended_start_time), &time, &mut batcher_tx, &mut workload_finished).await;
                     }
                 }
             }
+            _ = timeout_check.tick(), if command_timeout.is_some() => {
+                check_timeouts(&mut clients, command_timeout, &time);
+            }
+            _ = &mut max_run_time_reached => {
+                stopped = true;
+            }
+            _ = shutdown_requested(&mut shutdown), if !shutting_down => {
+                shutting_down = true;
+                shutdown_deadline.as_mut().reset(tokio::time::Instant::now() + shutdown_timeout);
+                stopped = pending_empty(&clients);
+            }
+            _ = &mut shutdown_deadline, if shutting_down => {
+                warn!("[client] shutdown timeout of {:?} reached; stopping clients", shutdown_timeout);
+                stopped = true;
+            }
         }
     }
-    assert_eq!(workload_finished.len(), finished.len());
+    assert!(stopped || workload_finished.len() == finished.len());
 
     // return clients
-    Some(
-        clients
-            .into_iter()
-            .map(|(_client_id, client)| client)
-            .collect(),
-    )
+    Some(stopped_clients(clients, shutting_down))
+}
+
+// Logs the stats of all clients every `stats_interval`, given the number of
+// commands they have completed so far.
+async fn log_stats(stats_interval: Duration, completed: Arc<AtomicU64>) {
+    let mut interval = tokio::time::interval(stats_interval);
+    // the first tick completes immediately
+    interval.tick().await;
+    let mut previous = 0;
+    let mut previous_time = Instant::now();
+    loop {
+        interval.tick().await;
+        let now = Instant::now();
+        let current = completed.load(Ordering::Relaxed);
+        ClientStats::new(current, previous, now - previous_time).log();
+        previous = current;
+        previous_time = now;
+    }
+}
+
+// Completes once `max_run_time` has passed, or never if it's not set.
+async fn max_run_time_reached(max_run_time: Option<Duration>) {
+    if let Some(max_run_time) = max_run_time {
+        tokio::time::sleep(max_run_time).await;
+        warn!(
+            "[client] max run time of {:?} reached; stopping clients",
+            max_run_time
+        );
+    } else {
+        futures::future::pending::<()>().await;
+    }
+}
+
+// Completes once a shutdown is requested, or never if the sender is dropped
+// without requesting it.
+async fn shutdown_requested(shutdown: &mut watch::Receiver<bool>) {
+    while !*shutdown.borrow() {
+        if shutdown.changed().await.is_err() {
+            futures::future::pending::<()>().await;
+        }
+    }
+}
+
+// Records, as timed out, the commands pending for at least `command_timeout`
+// (if set).
+fn check_timeouts(
+    clients: &mut HashMap<ClientId, Client>,
+    command_timeout: Option<Duration>,
+    time: &dyn SysTime,
+) {
+    if let Some(command_timeout) = command_timeout {
+        for client in clients.values_mut() {
+            client.check_timeouts(command_timeout, time);
+        }
+    }
+}
+
+// Checks whether none of the clients has commands pending.
+fn pending_empty(clients: &HashMap<ClientId, Client>) -> bool {
+    clients.values().all(Client::pending_empty)
+}
+
+// Returns the clients, marking their data as partial if they were shut down.
+fn stopped_clients(
+    clients: HashMap<ClientId, Client>,
+    shut_down: bool,
+) -> Vec<Client> {
+    clients
+        .into_iter()
+        .map(|(_client_id, mut client)| {
+            if shut_down {
+                client.set_partial();
+            }
+            client
+        })
+        .collect()
 }
 
 async fn client_setup<A>(
-    client_ids: Vec<ClientId>,
-    addresses: Vec<A>,
-    workload: Workload,
+    clients: Vec<(ClientId, Workload)>,
+    start: Instant,
+    addresses: Vec<(A, Option<Duration>)>,
     batch_max_size: usize,
     batch_max_delay: Duration,
     client_retries: usize,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     channel_buffer_size: usize,
     status_frequency: Option<usize>,
 ) -> Option<(
     HashMap<ClientId, Client>,
-    ChannelReceiver<Vec<Rifl>>,
+    ChannelReceiver<Vec<(Rifl, Option<CommandTrace>)>>,
     ChannelSender<(ShardId, Command)>,
 )>
 where
     A: ToSocketAddrs + Clone + Debug + Send + 'static + Sync,
 {
+    let client_ids: Vec<_> =
+        clients.iter().map(|(client_id, _)| *client_id).collect();
     let mut shard_to_process = HashMap::with_capacity(addresses.len());
     let mut connections = Vec::with_capacity(addresses.len());
 
     // connect to each address (one per shard)
+    let connect_start = Instant::now();
     let tcp_buffer_size = 0;
-    for address in addresses {
+    for (address, delay) in addresses {
         let connect = task::connect(
             address,
-            tcp_nodelay,
+            tcp_options,
             tcp_buffer_size,
             client_retries,
         );
@@ -311,8 +609,10 @@ where
         assert!(shard_to_process.insert(shard_id, process_id).is_none(), "client shouldn't try to connect to the same shard more than once, only to the closest one");
 
         // update list of connected processes
-        connections.push((process_id, connection));
+        connections.push((process_id, connection, delay));
     }
+    let connect_latency = connect_start.elapsed();
+    let setup_time = start.elapsed();
 
     // start client read-write task
     let (read, mut process_to_writer) = rw::start_client_rw_tasks(
@@ -337,12 +637,13 @@ where
     );
 
     // create clients
-    let clients = client_ids
-        .iter()
-        .map(|&client_id| {
-            let client = Client::new(client_id, workload, status_frequency);
+    let clients = clients
+        .into_iter()
+        .map(|(client_id, workload)| {
+            let mut client = Client::new(client_id, workload, status_frequency);
             // no need to discover as the `unbatcher` will do the job of
             // selecting the closest process
+            client.record_connected(connect_latency, setup_time);
             (client_id, client)
         })
         .collect();
@@ -369,7 +670,7 @@ async fn spawn_batcher_and_unbatcher(
     shard_to_writer: HashMap<ShardId, ChannelSender<ClientToServer>>,
 ) -> Option<(
     HashMap<ClientId, Client>,
-    ChannelReceiver<Vec<Rifl>>,
+    ChannelReceiver<Vec<(Rifl, Option<CommandTrace>)>>,
     ChannelSender<(ShardId, Command)>,
 )> {
     let (mut batcher_tx, batcher_rx) = chan::channel(channel_buffer_size);
@@ -406,14 +707,22 @@ async fn spawn_batcher_and_unbatcher(
 }
 
 /// Generate the next command, returning a boolean representing whether a new
-/// command was generated or not.
+/// command was generated or not. If the command was intended to be issued at
+/// some earlier time (in micros), that time should be provided.
 async fn cmd_send(
     client: &mut Client,
+    intended_start_time: Option<u64>,
     time: &dyn SysTime,
     to_batcher: &mut ChannelSender<(ShardId, Command)>,
     workload_finished: &mut HashSet<ClientId>,
 ) {
-    if let Some(next) = client.cmd_send(time) {
+    let next = match intended_start_time {
+        Some(intended_start_time) => {
+            client.cmd_send_scheduled(intended_start_time, time)
+        }
+        None => client.cmd_send(time),
+    };
+    if let Some(next) = next {
         if let Err(e) = to_batcher.send(next).await {
             warn!("[client] error forwarding batch: {:?}", e);
         }
@@ -424,16 +733,17 @@ async fn cmd_send(
     }
 }
 
-/// Handles new ready rifls. Returns the client ids of clients with a new
-/// command finished.
+/// Handles new ready rifls (and their traces, if sampled). Returns the client
+/// ids of clients with a new command finished.
 fn cmd_recv(
     clients: &mut HashMap<ClientId, Client>,
     time: &dyn SysTime,
-    from_unbatcher: Option<Vec<Rifl>>,
+    from_unbatcher: Option<Vec<(Rifl, Option<CommandTrace>)>>,
     finished: &mut HashSet<ClientId>,
+    completed: &AtomicU64,
 ) -> Vec<ClientId> {
     if let Some(rifls) = from_unbatcher {
-        do_cmd_recv(clients, time, rifls, finished)
+        do_cmd_recv(clients, time, rifls, finished, completed)
     } else {
         panic!("[client] error while receiving message from client read-write task");
     }
@@ -442,12 +752,13 @@ fn cmd_recv(
 fn do_cmd_recv(
     clients: &mut HashMap<ClientId, Client>,
     time: &dyn SysTime,
-    rifls: Vec<Rifl>,
+    rifls: Vec<(Rifl, Option<CommandTrace>)>,
     finished: &mut HashSet<ClientId>,
+    completed: &AtomicU64,
 ) -> Vec<ClientId> {
     rifls
         .into_iter()
-        .map(move |rifl| {
+        .map(move |(rifl, trace)| {
             // find client that sent this command
             let client_id = rifl.source();
             let client = clients
@@ -455,7 +766,15 @@ fn do_cmd_recv(
                 .expect("[client] command result should belong to a client");
 
             // handle command results
-            client.cmd_recv(rifl, time);
+            let latency = client.cmd_recv(rifl, time);
+            if latency.is_some() {
+                completed.fetch_add(1, Ordering::Relaxed);
+            }
+
+            // save the trace (if any) of commands that were not cancelled
+            if let (Some(latency), Some(trace)) = (latency, trace) {
+                client.record_trace(rifl, latency, trace);
+            }
 
             // check if client is finished
             if client.finished() {
@@ -483,13 +802,15 @@ async fn client_say_hi(
     if let Some(ProcessHi {
         process_id,
         shard_id,
+        epoch: _epoch,
     }) = connection.recv().await
     {
         trace!(
-            "[client] clients {:?} received hi from process {} with shard id {}",
+            "[client] clients {:?} received hi from process {} with shard id {} (epoch {})",
             client_ids,
             process_id,
-            shard_id
+            shard_id,
+            _epoch
         );
         Some((process_id, shard_id))
     } else {
@@ -1,4 +1,4 @@
-use crate::command::{Command, CommandResult};
+use crate::command::{Command, CommandResult, CommandTrace};
 use crate::hash_map::{Entry, HashMap};
 use crate::id::Rifl;
 use crate::trace;
@@ -10,39 +10,58 @@ struct Expected {
 
 pub struct ShardsPending {
     pending: HashMap<Rifl, (Expected, Vec<CommandResult>)>,
-    rifl_to_batch_rifls: HashMap<Rifl, Vec<Rifl>>,
 }
 
 impl ShardsPending {
     pub fn new() -> Self {
         Self {
             pending: Default::default(),
-            rifl_to_batch_rifls: Default::default(),
         }
     }
 
-    pub fn register(&mut self, cmd: &Command, batch_rifls: Vec<Rifl>) {
-        let rifl = cmd.rifl();
-        trace!("c{}: register {:?}", rifl.source(), rifl);
+    pub fn register(&mut self, cmd: &Command) {
+        trace!("c{}: register {:?}", cmd.rifl().source(), cmd.rifl());
 
+        // if the command is a batch, then each of the original commands in it
+        // is registered separately (as the servers return their results
+        // separately)
+        match cmd.batch() {
+            Some(batch) => {
+                for rifl in batch.rifls() {
+                    let expected = Expected {
+                        shard_count: batch.shard_count(*rifl),
+                        total_key_count: cmd
+                            .shards()
+                            .map(|shard_id| batch.key_count(*rifl, *shard_id))
+                            .sum(),
+                    };
+                    self.register_expected(*rifl, expected);
+                }
+            }
+            None => {
+                let expected = Expected {
+                    shard_count: cmd.shard_count(),
+                    total_key_count: cmd.total_key_count(),
+                };
+                self.register_expected(cmd.rifl(), expected);
+            }
+        }
+    }
+
+    fn register_expected(&mut self, rifl: Rifl, expected: Expected) {
         // add command to pending
-        let expected = Expected {
-            shard_count: cmd.shard_count(),
-            total_key_count: cmd.total_key_count(),
-        };
         let results = Vec::with_capacity(expected.shard_count);
         let res = self.pending.insert(rifl, (expected, results));
         assert!(res.is_none());
-
-        // update mapping rifl -> batch rifls
-        let res = self.rifl_to_batch_rifls.insert(rifl, batch_rifls);
-        assert!(res.is_none());
     }
 
     // Add new `CommandResult`.
     // If some command got the `CommandResult`s from each of the shards
-    // accessed, then return all the `Rifl`s in that batch.
-    pub fn add(&mut self, result: CommandResult) -> Option<Vec<Rifl>> {
+    // accessed, then return its `Rifl` (and its trace, if it was sampled).
+    pub fn add(
+        &mut self,
+        result: CommandResult,
+    ) -> Option<(Rifl, Option<CommandTrace>)> {
         let rifl = result.rifl();
         trace!("c{}: received {:?}", rifl.source(), rifl);
 
@@ -73,15 +92,16 @@ impl ShardsPending {
                         .sum();
                     assert_eq!(results_key_count, expected.total_key_count);
 
+                    // only the shard where the command was submitted traces
+                    // it
+                    let trace = results
+                        .iter()
+                        .find_map(|cmd_result| cmd_result.trace().copied());
+
                     // remove command from pending
                     entry.remove();
 
-                    // return batch rifls associated with this rifl
-                    let batch_rifls =
-                        self.rifl_to_batch_rifls.remove(&rifl).expect(
-                            "each rifl should be mapped to their batch rifls",
-                        );
-                    Some(batch_rifls)
+                    Some((rifl, trace))
                 } else {
                     None
                 }
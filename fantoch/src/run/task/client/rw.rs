@@ -5,12 +5,14 @@ use crate::run::chan::{self, ChannelReceiver, ChannelSender};
 use crate::run::prelude::*;
 use crate::run::rw::Connection;
 use crate::run::task;
+use crate::run::task::server::delay;
 use crate::{trace, warn};
+use std::time::Duration;
 
 pub fn start_client_rw_tasks(
     client_ids: &Vec<ClientId>,
     channel_buffer_size: usize,
-    connections: Vec<(ProcessId, Connection)>,
+    connections: Vec<(ProcessId, Connection, Option<Duration>)>,
 ) -> (
     ChannelReceiver<CommandResult>,
     HashMap<ProcessId, ChannelSender<ClientToServer>>,
@@ -26,7 +28,7 @@ pub fn start_client_rw_tasks(
     ));
 
     let mut process_to_tx = HashMap::with_capacity(connections.len());
-    for (process_id, connection) in connections {
+    for (process_id, connection, delay) in connections {
         // create client-to-server channels: since clients may send operations
         // to different shards, we create one client-to-rw channel per rw task
         let (mut c2s_tx, c2s_rx) = chan::channel(channel_buffer_size);
@@ -36,8 +38,36 @@ pub fn start_client_rw_tasks(
             task::util::ids_repr(&client_ids)
         ));
 
+        let (to_parent, from_parent, c2s_tx) = if let Some(delay) = delay {
+            // if there's a connection delay, messages to and from the server
+            // go through a delay task in each direction
+            let (mut to_delay_s2c_tx, to_delay_s2c_rx) =
+                chan::channel(channel_buffer_size);
+            to_delay_s2c_tx.set_name(format!(
+                "to_delay_server_to_client_{}_{}",
+                process_id,
+                task::util::ids_repr(&client_ids)
+            ));
+            let (mut to_delay_c2s_tx, to_delay_c2s_rx) =
+                chan::channel(channel_buffer_size);
+            to_delay_c2s_tx.set_name(format!(
+                "to_delay_client_to_server_{}_{}",
+                process_id,
+                task::util::ids_repr(&client_ids)
+            ));
+            task::spawn(delay::delay_task(
+                to_delay_s2c_rx,
+                s2c_tx.clone(),
+                delay,
+            ));
+            task::spawn(delay::delay_task(to_delay_c2s_rx, c2s_tx, delay));
+            (to_delay_s2c_tx, c2s_rx, to_delay_c2s_tx)
+        } else {
+            (s2c_tx.clone(), c2s_rx, c2s_tx)
+        };
+
         // spawn rw task
-        task::spawn(client_rw_task(connection, s2c_tx.clone(), c2s_rx));
+        task::spawn(client_rw_task(connection, to_parent, from_parent));
         process_to_tx.insert(process_id, c2s_tx);
     }
     (s2c_rx, process_to_tx)
@@ -1,16 +1,17 @@
 use super::batch::Batch;
 use super::pending::ShardsPending;
-use crate::command::CommandResult;
+use crate::command::{CommandResult, CommandTrace};
+use crate::error::RunError;
 use crate::id::{Rifl, ShardId};
 use crate::run::chan::{ChannelReceiver, ChannelSender};
 use crate::run::prelude::ClientToServer;
 use crate::warn;
 use crate::HashMap;
-use color_eyre::eyre::{eyre, Report};
+use color_eyre::Report;
 
 pub async fn unbatcher(
     mut from: ChannelReceiver<Batch>,
-    mut to: ChannelSender<Vec<Rifl>>,
+    mut to: ChannelSender<Vec<(Rifl, Option<CommandTrace>)>>,
     mut read: ChannelReceiver<CommandResult>,
     mut shard_to_writer: HashMap<ShardId, ChannelSender<ClientToServer>>,
 ) {
@@ -46,7 +47,8 @@ async fn handle_from_batcher(
         handle_batch(batch, shard_to_writer, pending).await;
         Ok(())
     } else {
-        Err(eyre!("error receiving message from parent"))
+        let msg = String::from("error receiving message from parent");
+        Err(RunError::Channel(msg).into())
     }
 }
 
@@ -56,11 +58,12 @@ async fn handle_batch(
     pending: &mut ShardsPending,
 ) {
     // extract info from batch
-    let (target_shard, cmd, rifls) = batch.unpack();
+    let (target_shard, cmd) = batch.unpack();
 
     // register command in pending (which will aggregate several
-    // `CommandResult`s if the command acesses more than one shard)
-    pending.register(&cmd, rifls);
+    // `CommandResult`s if the command acesses more than one shard, and split
+    // the batch into its original commands)
+    pending.register(&cmd);
 
     // 1. register the command in all shards but the target shard
     for shard in cmd.shards().filter(|shard| **shard != target_shard) {
@@ -92,24 +95,27 @@ async fn send_to_shard(
 
 async fn handle_from_server(
     cmd_result: Option<CommandResult>,
-    to: &mut ChannelSender<Vec<Rifl>>,
+    to: &mut ChannelSender<Vec<(Rifl, Option<CommandTrace>)>>,
     pending: &mut ShardsPending,
 ) -> Result<(), Report> {
     if let Some(cmd_result) = cmd_result {
         handle_cmd_result(cmd_result, to, pending).await;
         Ok(())
     } else {
-        Err(eyre!("error receiving message from parent"))
+        let msg = String::from("error receiving message from parent");
+        Err(RunError::Channel(msg).into())
     }
 }
 
 async fn handle_cmd_result(
     cmd_result: CommandResult,
-    to: &mut ChannelSender<Vec<Rifl>>,
+    to: &mut ChannelSender<Vec<(Rifl, Option<CommandTrace>)>>,
     pending: &mut ShardsPending,
 ) {
-    if let Some(rifls) = pending.add(cmd_result) {
-        if let Err(e) = to.send(rifls).await {
+    // each original command in a batch is returned as soon as it completes,
+    // so that its latency is not affected by the rest of the batch
+    if let Some(ready) = pending.add(cmd_result) {
+        if let Err(e) = to.send(vec![ready]).await {
             warn!("[unbatcher] error while sending message to client: {:?}", e);
         }
     }
@@ -1,4 +1,5 @@
-mod util;
+// This module contains utilities shared by server's and client's tasks.
+pub mod util;
 
 // This module contains server's side logic.
 pub mod server;
@@ -6,16 +7,25 @@ pub mod server;
 // This module contains client's side logic.
 pub mod client;
 
+use crate::error::RunError;
 use crate::run::chan::{self, ChannelReceiver, ChannelSender};
-use crate::run::rw::Connection;
+use crate::run::rw::{Connection, TcpOptions};
 use crate::{info, trace, warn};
 use color_eyre::Report;
 use std::fmt::Debug;
 use std::future::Future;
-use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpSocket, TcpStream, ToSocketAddrs};
 use tokio::task::JoinHandle;
 use tokio::time::Duration;
 
+// maximum number of pending connections in each listener socket
+const LISTEN_BACKLOG: u32 = 1024;
+
+// time given to each connection accepted to present the authentication token
+// (if any)
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Just a wrapper around tokio::spawn.
 pub fn spawn<F>(task: F) -> JoinHandle<F::Output>
 where
@@ -104,7 +114,7 @@ where
 /// Connect to some address.
 pub async fn connect<A>(
     address: A,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     tcp_buffer_size: usize,
     connect_retries: usize,
 ) -> Result<Connection, Report>
@@ -113,10 +123,21 @@ where
 {
     let mut tries = 0;
     loop {
-        match TcpStream::connect(address.clone()).await {
+        match connect_socket(address.clone(), tcp_options).await {
             Ok(stream) => {
-                let connection =
-                    Connection::new(stream, tcp_nodelay, tcp_buffer_size);
+                let mut connection =
+                    Connection::new(stream, tcp_options, tcp_buffer_size);
+                // present the authentication token (if any) before anything
+                // else is sent
+                if let Some(auth_token) = tcp_options.auth_token {
+                    connection.send(&auth_token).await.map_err(|e| {
+                        let msg = format!(
+                            "send auth token to {:?}: {:?}",
+                            address, e
+                        );
+                        RunError::Connection(msg)
+                    })?;
+                }
                 return Ok(connection);
             }
             Err(e) => {
@@ -131,40 +152,149 @@ where
                     );
                     tokio::time::sleep(Duration::from_secs(1)).await;
                 } else {
-                    return Err(e.into());
+                    let msg = format!("connect to {:?}: {}", address, e);
+                    return Err(RunError::Connection(msg).into());
                 }
             }
         }
     }
 }
 
+// Connects to the first address `address` resolves to, setting the maximum
+// segment size (if any) before the connection is established.
+async fn connect_socket<A>(
+    address: A,
+    tcp_options: TcpOptions,
+) -> std::io::Result<TcpStream>
+where
+    A: ToSocketAddrs,
+{
+    let address =
+        tokio::net::lookup_host(address)
+            .await?
+            .next()
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "address didn't resolve",
+                )
+            })?;
+    let socket = match address {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    tcp_options.set_max_segment_size(&socket);
+    socket.connect(address).await
+}
+
 /// Listen on some address.
 pub async fn listen<A>(address: A) -> Result<TcpListener, Report>
 where
     A: ToSocketAddrs,
 {
-    Ok(TcpListener::bind(address).await?)
+    TcpListener::bind(address)
+        .await
+        .map_err(|e| RunError::Connection(format!("listen: {}", e)).into())
 }
 
-/// Listen on new connections and send them to parent process.
+/// Listen on some address with `listeners` sockets. If more than one, the
+/// sockets are bound with `SO_REUSEPORT`, which makes the kernel shard new
+/// connections among them.
+pub fn listen_reuseport(
+    address: SocketAddr,
+    listeners: usize,
+) -> Result<Vec<TcpListener>, Report> {
+    assert!(listeners > 0, "there should be at least one listener");
+    (0..listeners)
+        .map(|_| -> std::io::Result<TcpListener> {
+            let socket = match address {
+                SocketAddr::V4(_) => TcpSocket::new_v4()?,
+                SocketAddr::V6(_) => TcpSocket::new_v6()?,
+            };
+            socket.set_reuseaddr(true)?;
+            if listeners > 1 {
+                socket.set_reuseport(true)?;
+            }
+            socket.bind(address)?;
+            socket.listen(LISTEN_BACKLOG)
+        })
+        .collect::<Result<_, _>>()
+        .map_err(|e| {
+            let msg = format!("listen on {:?}: {}", address, e);
+            RunError::Connection(msg).into()
+        })
+}
+
+/// Checks that a connection just accepted presents the authentication token
+/// set in `tcp_options` (if any) within `AUTH_TIMEOUT`. Returns whether the
+/// connection should be kept.
+pub async fn authenticate(
+    connection: &mut Connection,
+    tcp_options: TcpOptions,
+) -> bool {
+    let auth_token = if let Some(auth_token) = tcp_options.auth_token {
+        auth_token
+    } else {
+        return true;
+    };
+    match tokio::time::timeout(AUTH_TIMEOUT, connection.recv::<u64>()).await {
+        Ok(Some(token)) if token == auth_token => true,
+        Ok(Some(_)) => {
+            warn!(
+                "[auth] rejecting connection from {:?}: wrong token",
+                connection.ip_addr()
+            );
+            false
+        }
+        Ok(None) => {
+            warn!(
+                "[auth] rejecting connection from {:?}: no token received",
+                connection.ip_addr()
+            );
+            false
+        }
+        Err(_) => {
+            warn!(
+                "[auth] rejecting connection from {:?}: no token received in {:?}",
+                connection.ip_addr(),
+                AUTH_TIMEOUT
+            );
+            false
+        }
+    }
+}
+
+/// Listen on new connections and send them to parent process (once they're
+/// authenticated).
 async fn listener_task(
     listener: TcpListener,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     tcp_buffer_size: usize,
-    mut parent: ChannelSender<Connection>,
+    parent: ChannelSender<Connection>,
 ) {
+    // connections accepted inherit the maximum segment size of the listener
+    tcp_options.set_max_segment_size(&listener);
     loop {
         match listener.accept().await {
             Ok((stream, _addr)) => {
                 trace!("[listener] new connection: {:?}", _addr);
 
                 // create connection
-                let connection =
-                    Connection::new(stream, tcp_nodelay, tcp_buffer_size);
+                let mut connection =
+                    Connection::new(stream, tcp_options, tcp_buffer_size);
 
-                if let Err(e) = parent.send(connection).await {
-                    warn!("[listener] error sending stream to parent process: {:?}", e);
-                }
+                // authenticate the connection in a separate task, so that
+                // connections that never present a token don't delay the
+                // ones accepted next
+                let mut parent = parent.clone();
+                spawn(async move {
+                    if !authenticate(&mut connection, tcp_options).await {
+                        return;
+                    }
+                    if let Err(e) = parent.send(connection).await {
+                        warn!("[listener] error sending stream to parent process: {:?}", e);
+                    }
+                });
             }
             Err(e) => {
                 warn!("[listener] couldn't accept new connection: {:?}", e)
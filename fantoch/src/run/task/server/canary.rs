@@ -0,0 +1,202 @@
+use crate::config::Config;
+use crate::error::RunError;
+use crate::executor::{Canary, CanaryMessage, Executor, ExecutorResult};
+use crate::id::{ProcessId, ShardId};
+use crate::protocol::Protocol;
+use crate::run::prelude::*;
+use crate::run::rw::{Connection, TcpOptions};
+use crate::run::task;
+use crate::time::RunTime;
+use crate::{info, trace, warn};
+use color_eyre::Report;
+use std::net::IpAddr;
+use tokio::time::{self, Duration};
+
+const CANARY_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+const CANARY_STATS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Mirrors the execution infos handled (and the results produced) by an
+/// executor to the canary (see `mirror_task`).
+pub struct ToCanary<P: Protocol> {
+    executor_index: usize,
+    tx: CanaryMessageSender<P>,
+}
+
+impl<P> ToCanary<P>
+where
+    P: Protocol + 'static,
+{
+    pub fn new(executor_index: usize, tx: CanaryMessageSender<P>) -> Self {
+        Self { executor_index, tx }
+    }
+
+    pub async fn info(
+        &mut self,
+        info: &<P::Executor as Executor>::ExecutionInfo,
+    ) {
+        let msg = CanaryMessage::Info(self.executor_index, info.clone());
+        self.send(msg).await
+    }
+
+    pub async fn result(&mut self, result: &ExecutorResult) {
+        let msg = CanaryMessage::Result(self.executor_index, result.clone());
+        self.send(msg).await
+    }
+
+    async fn send(
+        &mut self,
+        msg: CanaryMessage<<P::Executor as Executor>::ExecutionInfo>,
+    ) {
+        if let Err(e) = self.tx.send(msg).await {
+            warn!("[executor] error while sending to canary: {:?}", e);
+        }
+    }
+}
+
+/// Forwards the messages mirrored by the executors to the canary at
+/// `address`. If the canary can't be reached (or the connection fails), the
+/// messages are dropped, so that the canary never stops the primary.
+pub async fn mirror_task<P>(
+    address: String,
+    tcp_options: TcpOptions,
+    tcp_buffer_size: usize,
+    connect_retries: usize,
+    mut from_executors: CanaryMessageReceiver<P>,
+) where
+    P: Protocol + 'static,
+{
+    info!("[canary_mirror] started with canary {}", address);
+    let mut connection = match task::connect(
+        &address,
+        tcp_options,
+        tcp_buffer_size,
+        connect_retries,
+    )
+    .await
+    {
+        Ok(connection) => Some(connection),
+        Err(e) => {
+            warn!("[canary_mirror] error connecting to canary: {:?}", e);
+            None
+        }
+    };
+
+    // create interval
+    let mut interval = time::interval(CANARY_FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            msg = from_executors.recv() => {
+                trace!("[canary_mirror] from executors: {:?}", msg);
+                if let Some(msg) = msg {
+                    if let Some(canary) = connection.as_mut() {
+                        if let Err(e) = canary.write(&msg).await {
+                            warn!("[canary_mirror] error when writing to canary; stopped mirroring: {:?}", e);
+                            connection = None;
+                        }
+                    }
+                } else {
+                    warn!("[canary_mirror] error while receiving message from executors");
+                }
+            }
+            _ = interval.tick() => {
+                if let Some(canary) = connection.as_mut() {
+                    if let Err(e) = canary.flush().await {
+                        warn!("[canary_mirror] error when flushing to canary; stopped mirroring: {:?}", e);
+                        connection = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs the canary of process `process_id` (with `executors` executors):
+/// waits for the connection from that process and handles the messages it
+/// mirrors (see `Canary`) until the connection ends. Divergences are logged
+/// as they're found.
+pub async fn canary_task<P>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    ip: IpAddr,
+    port: u16,
+    config: Config,
+    tcp_options: TcpOptions,
+    tcp_buffer_size: usize,
+    executors: usize,
+) -> Result<(), Report>
+where
+    P: Protocol + 'static,
+{
+    let listener = task::listen((ip, port)).await?;
+    tcp_options.set_max_segment_size(&listener);
+    info!(
+        "[canary] p{}: waiting for the primary on port {}",
+        process_id, port
+    );
+    // connections that don't authenticate are dropped
+    let mut connection = loop {
+        let (stream, addr) = listener.accept().await.map_err(|e| {
+            RunError::Connection(format!("canary accept: {}", e))
+        })?;
+        let mut connection =
+            Connection::new(stream, tcp_options, tcp_buffer_size);
+        if task::authenticate(&mut connection, tcp_options).await {
+            info!(
+                "[canary] p{}: primary connected from {:?}",
+                process_id, addr
+            );
+            break connection;
+        }
+    };
+
+    let mut canary =
+        Canary::<P::Executor>::new(process_id, shard_id, config, executors);
+    let time = RunTime;
+
+    // create intervals
+    let mut cleanup_interval =
+        time::interval(config.executor_cleanup_interval());
+    let mut stats_interval = time::interval(CANARY_STATS_INTERVAL);
+
+    loop {
+        tokio::select! {
+            msg = connection.recv() => {
+                if let Some(msg) = msg {
+                    canary.handle(msg, &time);
+                } else {
+                    info!("[canary] primary disconnected");
+                    break;
+                }
+            }
+            _ = cleanup_interval.tick() => {
+                canary.cleanup(&time);
+            }
+            _ = stats_interval.tick() => {
+                log_stats(&canary);
+            }
+        }
+        while let Some(divergence) = canary.to_divergences() {
+            warn!(
+                "[canary] divergence on {:?} on key {}: primary {:?} | canary {:?}",
+                divergence.primary.rifl,
+                divergence.primary.key,
+                divergence.primary,
+                divergence.canary
+            );
+        }
+    }
+    log_stats(&canary);
+    Ok(())
+}
+
+fn log_stats<E>(canary: &Canary<E>)
+where
+    E: Executor,
+{
+    let stats = canary.stats();
+    info!(
+        "[canary] {} compared | {} diverged | {} pending",
+        stats.compared, stats.diverged, stats.pending
+    );
+}
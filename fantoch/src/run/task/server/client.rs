@@ -1,75 +1,198 @@
-use crate::command::Command;
-use crate::executor::{AggregatePending, ExecutorResult};
-use crate::id::{AtomicDotGen, ClientId, ProcessId, ShardId};
-use crate::run::chan;
+use crate::command::{Command, CommandResult, CommandTrace};
+use crate::executor::{AggregatePending, OrderedDelivery, PendingCommand};
+use crate::id::{AtomicDotGen, ClientId, Dot, Epoch, ProcessId, Rifl, ShardId};
+use crate::metrics::Metrics;
+use crate::run::chan::{self, ChannelReceiver, ChannelSender};
 use crate::run::prelude::*;
-use crate::run::rw::Connection;
+use crate::run::rw::{Connection, TcpOptions};
 use crate::run::task;
+use crate::run::task::server::metrics_logger;
+use crate::time::{RunTime, SysTime};
+use crate::HashMap;
 use crate::{info, trace, warn};
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
 use tokio::net::TcpListener;
+use tokio::time::{self, Duration, Instant};
+
+pub type AcceptorMetrics = Metrics<AcceptorMetricsKind>;
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AcceptorMetricsKind {
+    AcceptedConnections,
+    // time (in milliseconds) between the first client connection accepted by
+    // the process and each connection accepted by the acceptor; its max is the
+    // duration of the accept phase
+    AcceptPhase,
+    // number of command results written to clients per network write (only
+    // if command results are written in batches)
+    ResultsPerWrite,
+    // number of commands whose processing was delayed (only if delays are
+    // injected)
+    InjectedDelays,
+    // number of command results aggregated by each pending task (only if the
+    // commands submitted on each connection are tracked by several tasks)
+    PendingShardResults(usize),
+}
+
+impl fmt::Debug for AcceptorMetricsKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcceptorMetricsKind::AcceptedConnections => {
+                write!(f, "accepted_connections")
+            }
+            AcceptorMetricsKind::AcceptPhase => write!(f, "accept_phase"),
+            AcceptorMetricsKind::ResultsPerWrite => {
+                write!(f, "results_per_write")
+            }
+            AcceptorMetricsKind::InjectedDelays => {
+                write!(f, "injected_delays")
+            }
+            AcceptorMetricsKind::PendingShardResults(shard_index) => {
+                write!(f, "pending_shard_results_{}", shard_index)
+            }
+        }
+    }
+}
 
 pub fn start_listener(
     process_id: ProcessId,
     shard_id: ShardId,
-    listener: TcpListener,
+    epoch: Epoch,
+    listeners: Vec<TcpListener>,
     atomic_dot_gen: Option<AtomicDotGen>,
     client_to_workers: ClientToWorkers,
     client_to_executors: ClientToExecutors,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     client_channel_buffer_size: usize,
+    ordered_delivery: bool,
+    stale_read_index: Option<StaleReadIndex>,
+    cancellable: bool,
+    trace_sample_rate: Option<usize>,
+    results_flush_delay: Option<Duration>,
+    pending_shards: usize,
+    delay_injection: Option<(usize, Duration)>,
+    to_metrics_logger: Option<AcceptorMetricsSender>,
 ) {
-    task::spawn(client_listener_task(
-        process_id,
-        shard_id,
-        listener,
-        atomic_dot_gen,
-        client_to_workers,
-        client_to_executors,
-        tcp_nodelay,
-        client_channel_buffer_size,
-    ));
+    // time at which the first client connection was accepted (by any of the
+    // acceptors)
+    let first_accept = Arc::new(Mutex::new(None));
+    for (acceptor_index, listener) in listeners.into_iter().enumerate() {
+        task::spawn(client_acceptor_task(
+            acceptor_index,
+            process_id,
+            shard_id,
+            epoch,
+            listener,
+            first_accept.clone(),
+            atomic_dot_gen.clone(),
+            client_to_workers.clone(),
+            client_to_executors.clone(),
+            tcp_options,
+            client_channel_buffer_size,
+            ordered_delivery,
+            stale_read_index,
+            cancellable,
+            trace_sample_rate,
+            results_flush_delay,
+            pending_shards,
+            delay_injection,
+            to_metrics_logger.clone(),
+        ));
+    }
 }
 
-/// Listen on new client connections and spawn a client task for each new
+/// Accepts new client connections and spawns a client task for each new
 /// connection.
-async fn client_listener_task(
+async fn client_acceptor_task(
+    acceptor_index: usize,
     process_id: ProcessId,
     shard_id: ShardId,
+    epoch: Epoch,
     listener: TcpListener,
+    first_accept: Arc<Mutex<Option<Instant>>>,
     atomic_dot_gen: Option<AtomicDotGen>,
     client_to_workers: ClientToWorkers,
     client_to_executors: ClientToExecutors,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     client_channel_buffer_size: usize,
+    ordered_delivery: bool,
+    stale_read_index: Option<StaleReadIndex>,
+    cancellable: bool,
+    trace_sample_rate: Option<usize>,
+    results_flush_delay: Option<Duration>,
+    pending_shards: usize,
+    delay_injection: Option<(usize, Duration)>,
+    mut to_metrics_logger: Option<AcceptorMetricsSender>,
 ) {
-    // start listener task
     let tcp_buffer_size = 0;
-    let mut rx = task::spawn_producer(client_channel_buffer_size, |tx| {
-        task::listener_task(listener, tcp_nodelay, tcp_buffer_size, tx)
-    });
+
+    // connections accepted inherit the maximum segment size of the listener
+    tcp_options.set_max_segment_size(&listener);
+
+    // create metrics (shared with the client tasks spawned, which record how
+    // many results they write per network write) and keep the last ones sent
+    let metrics = Arc::new(Mutex::new(AcceptorMetrics::new()));
+    let mut metrics_sent = AcceptorMetrics::new();
+    let mut interval = time::interval(metrics_logger::METRICS_INTERVAL);
 
     loop {
-        // handle new client connections
-        match rx.recv().await {
-            Some(connection) => {
-                trace!("[client_listener] new connection");
-                // start client server task and give it the producer-end of the
-                // channel in order for this client to notify
-                // parent
-                task::spawn(client_server_task(
-                    process_id,
-                    shard_id,
-                    atomic_dot_gen.clone(),
-                    client_to_workers.clone(),
-                    client_to_executors.clone(),
-                    client_channel_buffer_size,
-                    connection,
-                ));
+        tokio::select! {
+            accepted = listener.accept() => {
+                match accepted {
+                    Ok((stream, _addr)) => {
+                        trace!("[client_acceptor] new connection: {:?}", _addr);
+                        let connection = Connection::new(stream, tcp_options, tcp_buffer_size);
+                        // start client server task
+                        task::spawn(client_server_task(
+                            tcp_options,
+                            process_id,
+                            shard_id,
+                            epoch,
+                            atomic_dot_gen.clone(),
+                            client_to_workers.clone(),
+                            client_to_executors.clone(),
+                            client_channel_buffer_size,
+                            ordered_delivery,
+                            stale_read_index,
+                            cancellable,
+                            trace_sample_rate,
+                            results_flush_delay,
+                            pending_shards,
+                            delay_injection,
+                            metrics.clone(),
+                            connection,
+                        ));
+
+                        // record how long after the first connection this one
+                        // was accepted
+                        let since_first_accept = first_accept
+                            .lock()
+                            .get_or_insert_with(Instant::now)
+                            .elapsed();
+                        let mut metrics = metrics.lock();
+                        metrics.aggregate(AcceptorMetricsKind::AcceptedConnections, 1);
+                        metrics.collect(AcceptorMetricsKind::AcceptPhase, since_first_accept.as_millis() as u64);
+                    }
+                    Err(e) => {
+                        warn!("[client_acceptor] couldn't accept new connection: {:?}", e)
+                    }
+                }
             }
-            None => {
-                warn!(
-                    "[client_listener] error receiving message from listener"
-                );
+            _ = interval.tick(), if to_metrics_logger.is_some() => {
+                let metrics = metrics.lock().clone();
+                if metrics != metrics_sent {
+                    metrics_sent = metrics.clone();
+                    if let Some(to_metrics_logger) = to_metrics_logger.as_mut() {
+                        if let Err(e) = to_metrics_logger.send((acceptor_index, metrics)).await {
+                            warn!("[client_acceptor] error sending metrics to logger: {:?}", e);
+                        }
+                    }
+                }
             }
         }
     }
@@ -78,18 +201,34 @@ async fn client_listener_task(
 /// Client server-side task. Checks messages both from the client connection
 /// (new commands) and parent (new command results).
 async fn client_server_task(
+    tcp_options: TcpOptions,
     process_id: ProcessId,
     shard_id: ShardId,
+    epoch: Epoch,
     atomic_dot_gen: Option<AtomicDotGen>,
     mut client_to_workers: ClientToWorkers,
     mut client_to_executors: ClientToExecutors,
     client_channel_buffer_size: usize,
+    ordered_delivery: bool,
+    stale_read_index: Option<StaleReadIndex>,
+    cancellable: bool,
+    trace_sample_rate: Option<usize>,
+    results_flush_delay: Option<Duration>,
+    pending_shards: usize,
+    delay_injection: Option<(usize, Duration)>,
+    metrics: Arc<Mutex<AcceptorMetrics>>,
     mut connection: Connection,
 ) {
+    if !task::authenticate(&mut connection, tcp_options).await {
+        return;
+    }
     let client = server_receive_hi(
         process_id,
         shard_id,
+        epoch,
         client_channel_buffer_size,
+        pending_shards,
+        &metrics,
         &mut connection,
         &mut client_to_executors,
     )
@@ -98,23 +237,49 @@ async fn client_server_task(
         warn!("[client_server] giving up on new client {:?} since handshake failed:", connection);
         return;
     }
-    let (client_ids, mut executor_results) = client.unwrap();
+    let (client_ids, mut pending) = client.unwrap();
+
+    // mapping from the rifl of each command submitted to its dot (so that
+    // commands can be cancelled)
+    let mut submitted = HashMap::new();
+
+    // if enabled, command results are delivered in submission order
+    let mut ordered = if ordered_delivery {
+        Some(OrderedDelivery::new())
+    } else {
+        None
+    };
+
+    // number of commands whose results were sent to the client
+    let mut executed = 0;
+
+    // traces of the commands sampled (if tracing is enabled)
+    let mut tracer = CommandTracer::new(trace_sample_rate);
 
-    // create pending
-    let mut pending = AggregatePending::new(process_id, shard_id);
+    // if enabled, command results are written in batches
+    let mut batcher = ResultsBatcher::new(results_flush_delay, metrics.clone());
+
+    // if enabled, the submission of some commands to the workers is delayed
+    let mut delayer = SubmitDelayer::new(delay_injection, metrics);
 
     loop {
         tokio::select! {
-            executor_result = executor_results.recv() => {
-                trace!("[client_server] new executor result: {:?}", executor_result);
-                client_server_task_handle_executor_result(executor_result, &mut connection, &mut pending).await;
+            cmd_results = pending.next() => {
+                trace!("[client_server] new command results: {:?}", cmd_results);
+                client_server_task_handle_cmd_results(cmd_results, &mut connection, &mut ordered, &mut submitted, &mut executed, &mut tracer, &mut batcher).await;
             }
             from_client = connection.recv() => {
                 trace!("[client_server] from client: {:?}", from_client);
-                if !client_server_task_handle_from_client(from_client, &client_ids, &atomic_dot_gen, &mut client_to_workers, &mut client_to_executors, &mut pending).await {
+                if !client_server_task_handle_from_client(from_client, shard_id, &client_ids, &atomic_dot_gen, stale_read_index, cancellable, &mut client_to_workers, &mut client_to_executors, &mut connection, &mut pending, &mut ordered, &mut submitted, &mut executed, &mut tracer, &mut batcher, &mut delayer).await {
                     return;
                 }
             }
+            submit = delayer.next() => {
+                client_server_task_forward_submit(submit, &mut client_to_workers).await;
+            }
+            _ = batcher.tick() => {
+                batcher.flush(&mut connection).await;
+            }
         }
     }
 }
@@ -122,10 +287,13 @@ async fn client_server_task(
 async fn server_receive_hi(
     process_id: ProcessId,
     shard_id: ShardId,
+    epoch: Epoch,
     client_channel_buffer_size: usize,
+    pending_shards: usize,
+    metrics: &Arc<Mutex<AcceptorMetrics>>,
     connection: &mut Connection,
     client_to_executors: &mut ClientToExecutors,
-) -> Option<(Vec<ClientId>, ExecutorResultReceiver)> {
+) -> Option<(Vec<ClientId>, ClientPending)> {
     // receive hi from client
     let client_ids = if let Some(ClientHi(client_ids)) = connection.recv().await
     {
@@ -138,125 +306,718 @@ async fn server_receive_hi(
         return None;
     };
 
-    // create channel where the executors will write executor results
-    let (mut executor_results_tx, executor_results_rx) =
-        chan::channel(client_channel_buffer_size);
+    // assign each client to a pending shard (there's no point in having more
+    // shards than clients)
+    let pending_shards =
+        std::cmp::max(1, std::cmp::min(pending_shards, client_ids.len()));
+    let mut shard_client_ids = vec![Vec::new(); pending_shards];
+    for client_id in client_ids.iter() {
+        let shard_index =
+            ClientPending::shard_index(*client_id, pending_shards);
+        shard_client_ids[shard_index].push(*client_id);
+    }
 
-    // set channels name
-    let ids_repr = task::util::ids_repr(&client_ids);
-    executor_results_tx
-        .set_name(format!("client_server_executor_results_{}", ids_repr));
+    let mut executor_results = Vec::with_capacity(pending_shards);
+    for shard_client_ids in shard_client_ids {
+        // create channel where the executors will write executor results
+        let (mut executor_results_tx, executor_results_rx) =
+            chan::channel(client_channel_buffer_size);
 
-    // register clients in all executors
-    let register =
-        ClientToExecutor::Register(client_ids.clone(), executor_results_tx);
-    if let Err(e) = client_to_executors.broadcast(register).await {
-        warn!(
-            "[client_server] error while registering clients in executors: {:?}",
-            e
-        );
+        // set channels name
+        let ids_repr = task::util::ids_repr(&shard_client_ids);
+        executor_results_tx
+            .set_name(format!("client_server_executor_results_{}", ids_repr));
+
+        // register clients in all executors (unless there are none, which is
+        // the case when clients are only probing this process)
+        if !shard_client_ids.is_empty() {
+            let register = ClientToExecutor::Register(
+                shard_client_ids,
+                executor_results_tx,
+            );
+            if let Err(e) = client_to_executors.broadcast(register).await {
+                warn!(
+                    "[client_server] error while registering clients in executors: {:?}",
+                    e
+                );
+            }
+        }
+        executor_results.push(executor_results_rx);
     }
 
     // say hi back
     let hi = ProcessHi {
         process_id,
         shard_id,
+        epoch,
     };
     if let Err(e) = connection.send(&hi).await {
         warn!("[client_server] error while sending hi: {:?}", e);
     }
 
-    // return client id and channel where client should read executor results
-    Some((client_ids, executor_results_rx))
+    // return client ids and where command results should be read from
+    let pending = ClientPending::new(
+        process_id,
+        shard_id,
+        client_channel_buffer_size,
+        executor_results,
+        metrics,
+    );
+    Some((client_ids, pending))
 }
 
 async fn client_server_task_handle_from_client(
     from_client: Option<ClientToServer>,
+    shard_id: ShardId,
     client_ids: &Vec<ClientId>,
     atomic_dot_gen: &Option<AtomicDotGen>,
+    stale_read_index: Option<StaleReadIndex>,
+    cancellable: bool,
     client_to_workers: &mut ClientToWorkers,
     client_to_executors: &mut ClientToExecutors,
-    pending: &mut AggregatePending,
+    connection: &mut Connection,
+    pending: &mut ClientPending,
+    ordered: &mut Option<OrderedDelivery>,
+    submitted: &mut HashMap<Rifl, Dot>,
+    executed: &mut usize,
+    tracer: &mut CommandTracer,
+    batcher: &mut ResultsBatcher,
+    delayer: &mut SubmitDelayer,
 ) -> bool {
     if let Some(from_client) = from_client {
         client_server_task_handle_cmd(
             from_client,
+            shard_id,
             atomic_dot_gen,
+            stale_read_index,
+            cancellable,
             client_to_workers,
+            client_to_executors,
+            connection,
             pending,
+            ordered,
+            submitted,
+            executed,
+            tracer,
+            batcher,
+            delayer,
         )
         .await;
         true
     } else {
         info!("[client_server] client disconnected.");
-        // unregister client in all executors
-        if let Err(e) = client_to_executors
-            .broadcast(ClientToExecutor::Unregister(client_ids.clone()))
-            .await
-        {
-            warn!(
-                "[client_server] error while unregistering client in executors: {:?}",
-                e
+        // the experiment harness checks, for each shard, that these add up to
+        // the number of commands issued by clients
+        info!(
+            "[client_server] shard {} commands executed: {}",
+            shard_id, executed
+        );
+        if let Some(ordered) = ordered {
+            info!(
+                "[client_server] ordered delivery delay (in micros): {:?}",
+                ordered.delay()
             );
         }
+        // unregister client in all executors (if registered)
+        if !client_ids.is_empty() {
+            if let Err(e) = client_to_executors
+                .broadcast(ClientToExecutor::Unregister(client_ids.clone()))
+                .await
+            {
+                warn!(
+                    "[client_server] error while unregistering client in executors: {:?}",
+                    e
+                );
+            }
+        }
         false
     }
 }
 
 async fn client_server_task_handle_cmd(
     from_client: ClientToServer,
+    shard_id: ShardId,
     atomic_dot_gen: &Option<AtomicDotGen>,
+    stale_read_index: Option<StaleReadIndex>,
+    cancellable: bool,
     client_to_workers: &mut ClientToWorkers,
-    pending: &mut AggregatePending,
+    client_to_executors: &mut ClientToExecutors,
+    connection: &mut Connection,
+    pending: &mut ClientPending,
+    ordered: &mut Option<OrderedDelivery>,
+    submitted: &mut HashMap<Rifl, Dot>,
+    executed: &mut usize,
+    tracer: &mut CommandTracer,
+    batcher: &mut ResultsBatcher,
+    delayer: &mut SubmitDelayer,
 ) {
     match from_client {
         ClientToServer::Register(cmd) => {
             // only register the command
-            client_server_task_register_cmd(&cmd, pending).await;
+            client_server_task_register_cmd(&cmd, pending, ordered).await;
         }
         ClientToServer::Submit(cmd) => {
             // register the command and submit it
-            client_server_task_register_cmd(&cmd, pending).await;
+            client_server_task_register_cmd(&cmd, pending, ordered).await;
+
+            // if stale reads are enabled, read-only commands on this shard
+            // are served directly by the executors
+            if let Some(stale_read_index) = stale_read_index {
+                if cmd.read_only() && cmd.shard_count() == 1 {
+                    tracer.received(&cmd, None);
+                    client_server_task_stale_read(
+                        cmd,
+                        shard_id,
+                        stale_read_index,
+                        client_to_executors,
+                    )
+                    .await;
+                    return;
+                }
+            }
 
             // create dot for this command (if we have a dot gen)
             let dot = atomic_dot_gen
                 .as_ref()
                 .map(|atomic_dot_gen| atomic_dot_gen.next_id());
-            // forward command to worker process
-            if let Err(e) = client_to_workers.forward((dot, cmd)).await {
+            // save the dot (if any) so that the command can be cancelled
+            if let Some(dot) = dot {
+                submitted.insert(cmd.rifl(), dot);
+            }
+            // maybe start tracing the command
+            tracer.received(&cmd, dot);
+            // forward command to worker process (unless it's delayed)
+            let submit = ClientToWorker::Submit(dot, cmd);
+            if let Some(submit) = delayer.maybe_delay(submit) {
+                client_server_task_forward_submit(submit, client_to_workers)
+                    .await;
+            }
+        }
+        ClientToServer::Cancel(rifl) => {
+            // reject the cancel if the protocol can't abort in-flight
+            // commands; the command is still executed, and so its results are
+            // still delivered to the client
+            if !cancellable {
                 warn!(
-                    "[client_server] error while sending new command to protocol worker: {:?}",
-                    e
+                    "[client_server] rejecting cancel of command {:?} as the protocol doesn't support it",
+                    rifl
+                );
+                return;
+            }
+
+            // stop waiting for the command results
+            pending.cancel(rifl).await;
+            tracer.cancel(rifl);
+
+            // results waiting for this command can now be delivered
+            if let Some(ordered) = ordered {
+                let cmd_results = ordered.cancel(rifl, &RunTime);
+                client_server_task_send_results(
+                    cmd_results,
+                    connection,
+                    executed,
+                    tracer,
+                    batcher,
+                )
+                .await;
+            }
+
+            // only commands with a dot can be cancelled
+            if let Some(dot) = submitted.remove(&rifl) {
+                // forward cancel to worker process
+                let cancel = ClientToWorker::Cancel(dot);
+                if let Err(e) = client_to_workers.forward(cancel).await {
+                    warn!(
+                        "[client_server] error while sending command cancel to protocol worker: {:?}",
+                        e
+                    );
+                }
+            } else {
+                warn!(
+                    "[client_server] can't cancel command {:?} without a dot",
+                    rifl
                 );
             }
         }
     }
 }
 
+async fn client_server_task_stale_read(
+    cmd: Command,
+    shard_id: ShardId,
+    stale_read_index: StaleReadIndex,
+    client_to_executors: &mut ClientToExecutors,
+) {
+    let rifl = cmd.rifl();
+    for (key, ops) in cmd.into_iter(shard_id) {
+        // take the ops (decoding them, if needed)
+        let ops = ops.into_ops();
+        let index = stale_read_index(&key);
+        let stale_read = ClientToExecutor::StaleRead(index, rifl, key, ops);
+        if let Err(e) = client_to_executors.forward(stale_read).await {
+            warn!(
+                "[client_server] error while sending stale read to executor: {:?}",
+                e
+            );
+        }
+    }
+}
+
 async fn client_server_task_register_cmd(
     cmd: &Command,
-    pending: &mut AggregatePending,
+    pending: &mut ClientPending,
+    ordered: &mut Option<OrderedDelivery>,
 ) {
     // we'll receive partial
     // results from the executor, thus  register command in pending
-    pending.wait_for(&cmd);
+    pending.wait_for(&cmd).await;
+    // if results are delivered in submission order, also register it there
+    // (in the case of batches, results are delivered per original command)
+    if let Some(ordered) = ordered {
+        for rifl in cmd.batch_rifls() {
+            ordered.submitted(rifl);
+        }
+    }
 }
 
-async fn client_server_task_handle_executor_result(
-    executor_result: Option<ExecutorResult>,
+async fn client_server_task_handle_cmd_results(
+    cmd_results: Option<Vec<CommandResult>>,
     connection: &mut Connection,
-    pending: &mut AggregatePending,
+    ordered: &mut Option<OrderedDelivery>,
+    submitted: &mut HashMap<Rifl, Dot>,
+    executed: &mut usize,
+    tracer: &mut CommandTracer,
+    batcher: &mut ResultsBatcher,
 ) {
-    if let Some(executor_result) = executor_result {
-        if let Some(cmd_result) = pending.add_executor_result(executor_result) {
-            if let Err(e) = connection.send(&cmd_result).await {
-                warn!(
-                    "[client_server] error while sending command results: {:?}",
-                    e
+    if let Some(cmd_results) = cmd_results {
+        // if the executor result is from a batch, it may complete several
+        // commands
+        for cmd_result in cmd_results {
+            // the command can no longer be cancelled
+            submitted.remove(&cmd_result.rifl());
+            tracer.executed(cmd_result.rifl());
+            // if results are delivered in submission order, the result may
+            // have to wait for previously submitted commands
+            let cmd_results = if let Some(ordered) = ordered {
+                ordered.add(cmd_result, &RunTime)
+            } else {
+                vec![cmd_result]
+            };
+            client_server_task_send_results(
+                cmd_results,
+                connection,
+                executed,
+                tracer,
+                batcher,
+            )
+            .await;
+        }
+    } else {
+        warn!("[client_server] error while receiving new command results");
+    }
+}
+
+async fn client_server_task_send_results(
+    cmd_results: Vec<CommandResult>,
+    connection: &mut Connection,
+    executed: &mut usize,
+    tracer: &mut CommandTracer,
+    batcher: &mut ResultsBatcher,
+) {
+    *executed += cmd_results.len();
+    for mut cmd_result in cmd_results {
+        tracer.replied(&mut cmd_result);
+        batcher.send(cmd_result, connection).await;
+    }
+}
+
+async fn client_server_task_forward_submit(
+    submit: ClientToWorker,
+    client_to_workers: &mut ClientToWorkers,
+) {
+    if let Err(e) = client_to_workers.forward(submit).await {
+        warn!(
+            "[client_server] error while sending new command to protocol worker: {:?}",
+            e
+        );
+    }
+}
+
+// Tracks the progress of the commands submitted on a client connection. If
+// several pending shards are set (see `Config::client_pending_shards`), each
+// client multiplexed on the connection is assigned to one of them, and the
+// results of its commands are aggregated by the task owning that shard.
+enum ClientPending {
+    Local {
+        pending: AggregatePending,
+        executor_results: ExecutorResultReceiver,
+    },
+    Sharded {
+        shard_id: ShardId,
+        to_shards: Vec<ChannelSender<ToPendingShard>>,
+        cmd_results: ChannelReceiver<Vec<CommandResult>>,
+    },
+}
+
+#[derive(Debug)]
+enum ToPendingShard {
+    WaitFor(PendingCommand),
+    Cancel(Rifl),
+}
+
+impl ClientPending {
+    // Creates a pending shard for each of the channels where executor results
+    // are written (if there's a single channel, commands are tracked by the
+    // client task itself).
+    fn new(
+        process_id: ProcessId,
+        shard_id: ShardId,
+        channel_buffer_size: usize,
+        mut executor_results: Vec<ExecutorResultReceiver>,
+        metrics: &Arc<Mutex<AcceptorMetrics>>,
+    ) -> Self {
+        if executor_results.len() == 1 {
+            let executor_results = executor_results
+                .pop()
+                .expect("there should be a channel of executor results");
+            return Self::Local {
+                pending: AggregatePending::new(process_id, shard_id),
+                executor_results,
+            };
+        }
+
+        let (cmd_results_tx, cmd_results) = chan::channel(channel_buffer_size);
+        let to_shards = executor_results
+            .into_iter()
+            .enumerate()
+            .map(|(shard_index, executor_results)| {
+                let (mut to_shard, from_client_server) =
+                    chan::channel(channel_buffer_size);
+                to_shard.set_name(format!("to_pending_shard_{}", shard_index));
+                task::spawn(pending_shard_task(
+                    shard_index,
+                    AggregatePending::new(process_id, shard_id),
+                    from_client_server,
+                    executor_results,
+                    cmd_results_tx.clone(),
+                    metrics.clone(),
+                ));
+                to_shard
+            })
+            .collect();
+        Self::Sharded {
+            shard_id,
+            to_shards,
+            cmd_results,
+        }
+    }
+
+    // Clients are assigned to pending shards based on their identifier; since
+    // the clients multiplexed on the same connection usually have consecutive
+    // identifiers, they're spread evenly across shards.
+    fn shard_index(client_id: ClientId, shards: usize) -> usize {
+        (client_id % shards as u64) as usize
+    }
+
+    async fn wait_for(&mut self, cmd: &Command) {
+        match self {
+            Self::Local { pending, .. } => {
+                pending.wait_for(cmd);
+            }
+            Self::Sharded {
+                shard_id,
+                to_shards,
+                ..
+            } => {
+                let cmd = PendingCommand::new(cmd, *shard_id);
+                let rifl = cmd.rifl();
+                Self::send_to_shard(
+                    to_shards,
+                    rifl,
+                    ToPendingShard::WaitFor(cmd),
+                )
+                .await;
+            }
+        }
+    }
+
+    async fn cancel(&mut self, rifl: Rifl) {
+        match self {
+            Self::Local { pending, .. } => {
+                pending.cancel(rifl);
+            }
+            Self::Sharded { to_shards, .. } => {
+                Self::send_to_shard(
+                    to_shards,
+                    rifl,
+                    ToPendingShard::Cancel(rifl),
+                )
+                .await;
+            }
+        }
+    }
+
+    async fn send_to_shard(
+        to_shards: &mut [ChannelSender<ToPendingShard>],
+        rifl: Rifl,
+        msg: ToPendingShard,
+    ) {
+        let shard_index = Self::shard_index(rifl.source(), to_shards.len());
+        if let Err(e) = to_shards[shard_index].send(msg).await {
+            warn!(
+                "[client_server] error while sending to pending shard {}: {:?}",
+                shard_index, e
+            );
+        }
+    }
+
+    // Completes with the results of the commands that became ready (there can
+    // be none if the executor result received only completed part of a
+    // command).
+    async fn next(&mut self) -> Option<Vec<CommandResult>> {
+        match self {
+            Self::Local {
+                pending,
+                executor_results,
+            } => executor_results.recv().await.map(|executor_result| {
+                pending.add_executor_result(executor_result)
+            }),
+            Self::Sharded { cmd_results, .. } => cmd_results.recv().await,
+        }
+    }
+}
+
+async fn pending_shard_task(
+    shard_index: usize,
+    mut pending: AggregatePending,
+    mut from_client_server: ChannelReceiver<ToPendingShard>,
+    mut executor_results: ExecutorResultReceiver,
+    mut to_client_server: ChannelSender<Vec<CommandResult>>,
+    metrics: Arc<Mutex<AcceptorMetrics>>,
+) {
+    loop {
+        // commands are registered before being submitted, and thus before
+        // any of their results can be written by the executors; since `biased`
+        // is used, a command is always registered before its results are
+        // added (which would otherwise be ignored)
+        tokio::select! {
+            biased;
+            msg = from_client_server.recv() => match msg {
+                Some(ToPendingShard::WaitFor(cmd)) => {
+                    pending.wait_for_pending(cmd);
+                }
+                Some(ToPendingShard::Cancel(rifl)) => {
+                    pending.cancel(rifl);
+                }
+                None => {
+                    trace!("[pending_shard] client task is gone");
+                    return;
+                }
+            },
+            executor_result = executor_results.recv() => {
+                let executor_result = if let Some(executor_result) = executor_result {
+                    executor_result
+                } else {
+                    warn!("[pending_shard] error while receiving new executor result from executor");
+                    return;
+                };
+                let cmd_results = pending.add_executor_result(executor_result);
+                if cmd_results.is_empty() {
+                    continue;
+                }
+                metrics.lock().aggregate(
+                    AcceptorMetricsKind::PendingShardResults(shard_index),
+                    cmd_results.len() as u64,
                 );
+                if let Err(e) = to_client_server.send(cmd_results).await {
+                    warn!(
+                        "[pending_shard] error while sending command results: {:?}",
+                        e
+                    );
+                }
             }
         }
-    } else {
-        warn!("[client_server] error while receiving new executor result from executor");
+    }
+}
+
+// Delays the submission of one in every `rate` commands (chosen at random) to
+// the workers (see `Config::server_delay_injection`). Since all delayed
+// commands are delayed by the same amount, they're submitted in the order they
+// were delayed.
+struct SubmitDelayer {
+    injection: Option<(usize, Duration)>,
+    delayed: VecDeque<(Instant, ClientToWorker)>,
+    metrics: Arc<Mutex<AcceptorMetrics>>,
+}
+
+impl SubmitDelayer {
+    fn new(
+        injection: Option<(usize, Duration)>,
+        metrics: Arc<Mutex<AcceptorMetrics>>,
+    ) -> Self {
+        Self {
+            injection,
+            delayed: VecDeque::new(),
+            metrics,
+        }
+    }
+
+    // Returns the submit back if it's not to be delayed.
+    fn maybe_delay(
+        &mut self,
+        submit: ClientToWorker,
+    ) -> Option<ClientToWorker> {
+        match self.injection {
+            Some((rate, delay))
+                if rand::thread_rng().gen_range(0..rate) == 0 =>
+            {
+                self.metrics
+                    .lock()
+                    .aggregate(AcceptorMetricsKind::InjectedDelays, 1);
+                self.delayed
+                    .push_back((task::util::deadline(delay), submit));
+                None
+            }
+            _ => Some(submit),
+        }
+    }
+
+    // Completes with the next delayed submit once its delay expires (never, if
+    // there's none).
+    async fn next(&mut self) -> ClientToWorker {
+        match self.delayed.front() {
+            Some((deadline, _)) => {
+                time::sleep_until(*deadline).await;
+                let (_, submit) = self
+                    .delayed
+                    .pop_front()
+                    .expect("a delayed submit should exist");
+                submit
+            }
+            None => futures::future::pending().await,
+        }
+    }
+}
+
+// Writes command results to the client connection, either flushing each of
+// them right away or, if a flush delay is set, only flushing the ones written
+// in the meantime once the delay expires.
+struct ResultsBatcher {
+    flush_interval: Option<time::Interval>,
+    // number of results written since the last flush
+    unflushed: u64,
+    metrics: Arc<Mutex<AcceptorMetrics>>,
+}
+
+impl ResultsBatcher {
+    fn new(
+        flush_delay: Option<Duration>,
+        metrics: Arc<Mutex<AcceptorMetrics>>,
+    ) -> Self {
+        Self {
+            flush_interval: flush_delay.map(time::interval),
+            unflushed: 0,
+            metrics,
+        }
+    }
+
+    async fn send(
+        &mut self,
+        cmd_result: CommandResult,
+        connection: &mut Connection,
+    ) {
+        let result = if self.flush_interval.is_some() {
+            // connection write *doesn't* flush
+            self.unflushed += 1;
+            connection.write(&cmd_result).await
+        } else {
+            // connection send *does* flush
+            connection.send(&cmd_result).await
+        };
+        if let Err(e) = result {
+            warn!(
+                "[client_server] error while sending command results: {:?}",
+                e
+            );
+        }
+    }
+
+    // Completes when the flush delay expires (never, if there's none).
+    async fn tick(&mut self) {
+        match self.flush_interval.as_mut() {
+            Some(interval) => {
+                interval.tick().await;
+            }
+            None => futures::future::pending().await,
+        }
+    }
+
+    async fn flush(&mut self, connection: &mut Connection) {
+        if self.unflushed == 0 {
+            return;
+        }
+        self.metrics
+            .lock()
+            .collect(AcceptorMetricsKind::ResultsPerWrite, self.unflushed);
+        self.unflushed = 0;
+        if let Err(e) = connection.flush().await {
+            warn!(
+                "[client_server] error while flushing command results: {:?}",
+                e
+            );
+        }
+    }
+}
+
+// Traces the commands sampled (see `Config::trace_sample_rate`) until their
+// results are sent back to the client.
+struct CommandTracer {
+    sample_rate: Option<usize>,
+    traces: HashMap<Rifl, CommandTrace>,
+}
+
+impl CommandTracer {
+    fn new(sample_rate: Option<usize>) -> Self {
+        Self {
+            sample_rate,
+            traces: HashMap::new(),
+        }
+    }
+
+    // Starts tracing the command (if sampled). Commands are sampled based on
+    // their dot, or, if the dot is only assigned later by the protocol (as in
+    // leader-based protocols), based on their rifl.
+    fn received(&mut self, cmd: &Command, dot: Option<Dot>) {
+        if let Some(sample_rate) = self.sample_rate {
+            let sequence = dot
+                .map(|dot| dot.sequence())
+                .unwrap_or_else(|| cmd.rifl().sequence());
+            if sequence % sample_rate as u64 == 0 {
+                let trace = CommandTrace::new(dot, RunTime.micros());
+                // in the case of batches, results are sent per original
+                // command
+                for rifl in cmd.batch_rifls() {
+                    self.traces.insert(rifl, trace);
+                }
+            }
+        }
+    }
+
+    fn executed(&mut self, rifl: Rifl) {
+        if let Some(trace) = self.traces.get_mut(&rifl) {
+            trace.set_executed(RunTime.micros());
+        }
+    }
+
+    // Attaches the trace of the command (if any) to its result.
+    fn replied(&mut self, cmd_result: &mut CommandResult) {
+        if let Some(mut trace) = self.traces.remove(&cmd_result.rifl()) {
+            trace.set_replied(RunTime.micros());
+            cmd_result.set_trace(trace);
+        }
+    }
+
+    fn cancel(&mut self, rifl: Rifl) {
+        self.traces.remove(&rifl);
     }
 }
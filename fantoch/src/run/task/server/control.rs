@@ -0,0 +1,200 @@
+use crate::id::{ProcessId, ShardId};
+use crate::protocol::{Protocol, StateSizes};
+use crate::run::chan::{self, ChannelSender};
+use crate::run::prelude::*;
+use crate::run::task;
+use crate::{info, trace, warn};
+use color_eyre::eyre::Report;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+// Query that returns the sizes of the internal state of each worker (see
+// `Protocol::state_sizes`).
+pub const STATE_SIZES_QUERY: &str = "state_sizes";
+
+/// Starts a control listener on `control_port`. Queries are sent as lines of
+/// text, and each gets a JSON line as a response. The following queries are
+/// supported:
+/// - `state_sizes`: returns the sizes of the internal state of each worker, in
+///   the form `{"process_id": ID, "shard_id": ID, "workers": {INDEX: SIZES}}`
+///
+/// The queries are forwarded to the workers through the returned inspect
+/// channel, which should be given to the periodic task.
+pub async fn start_listener<P>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    ip: IpAddr,
+    control_port: u16,
+    workers: usize,
+) -> Result<InspectReceiver<P, StateSizes>, Report>
+where
+    P: Protocol + 'static,
+{
+    let listener = task::listen((ip, control_port)).await?;
+    let (to_workers, inspect_chan) = chan::channel(1);
+    task::spawn(control_listener_task(
+        listener, process_id, shard_id, workers, to_workers,
+    ));
+    info!(
+        "p{}: control listening on {}:{}",
+        process_id, ip, control_port
+    );
+    Ok(inspect_chan)
+}
+
+async fn control_listener_task<P>(
+    listener: TcpListener,
+    process_id: ProcessId,
+    shard_id: ShardId,
+    workers: usize,
+    to_workers: ChannelSender<InspectFun<P, StateSizes>>,
+) where
+    P: Protocol + 'static,
+{
+    loop {
+        match listener.accept().await {
+            Ok((stream, _addr)) => {
+                trace!("[control] new connection: {:?}", _addr);
+                task::spawn(control_connection_task(
+                    stream,
+                    process_id,
+                    shard_id,
+                    workers,
+                    to_workers.clone(),
+                ));
+            }
+            Err(e) => {
+                warn!("[control] couldn't accept new connection: {:?}", e)
+            }
+        }
+    }
+}
+
+// Answers the queries received on a connection until it is closed.
+async fn control_connection_task<P>(
+    mut stream: TcpStream,
+    process_id: ProcessId,
+    shard_id: ShardId,
+    workers: usize,
+    mut to_workers: ChannelSender<InspectFun<P, StateSizes>>,
+) where
+    P: Protocol + 'static,
+{
+    let (read, mut write) = stream.split();
+    let mut lines = BufReader::new(read).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let response = match line.trim() {
+            STATE_SIZES_QUERY => {
+                state_sizes(process_id, shard_id, workers, &mut to_workers)
+                    .await
+            }
+            query => serde_json::json!({
+                "error": format!("unknown query: {:?}", query),
+            }),
+        };
+        let response = format!("{}\n", response);
+        if let Err(e) = write.write_all(response.as_bytes()).await {
+            warn!("[control] error while writing response: {:?}", e);
+            return;
+        }
+    }
+}
+
+async fn state_sizes<P>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    workers: usize,
+    to_workers: &mut ChannelSender<InspectFun<P, StateSizes>>,
+) -> serde_json::Value
+where
+    P: Protocol + 'static,
+{
+    let (reply_tx, mut reply_rx) = chan::channel(workers);
+    let inspect_fun = P::state_sizes as fn(&P) -> StateSizes;
+    if let Err(e) = to_workers.send((inspect_fun, reply_tx)).await {
+        warn!("[control] error while sending query to workers: {:?}", e);
+    }
+
+    // gather the sizes reported by each worker
+    let mut sizes = BTreeMap::new();
+    for _ in 0..workers {
+        match reply_rx.recv().await {
+            Some((worker_index, worker_sizes)) => {
+                sizes.insert(worker_index, worker_sizes);
+            }
+            None => {
+                return serde_json::json!({
+                    "error": "workers are not available",
+                });
+            }
+        }
+    }
+    serde_json::json!({
+        "process_id": process_id,
+        "shard_id": shard_id,
+        "workers": sizes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::protocol::Basic;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn control_state_sizes() {
+        let (process_id, shard_id) = (1, 0);
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let control_port = 7301;
+        let workers = 2;
+        let mut inspect_chan = start_listener::<Basic>(
+            process_id,
+            shard_id,
+            ip,
+            control_port,
+            workers,
+        )
+        .await
+        .expect("control listener should start");
+
+        // answer the queries as the workers would (with the same state)
+        tokio::spawn(async move {
+            let (process, _) =
+                Basic::new(process_id, shard_id, Config::new(3, 1));
+            while let Some((inspect_fun, mut reply_tx)) =
+                inspect_chan.recv().await
+            {
+                for worker_index in 0..workers {
+                    let sizes = inspect_fun(&process);
+                    reply_tx.blind_send((worker_index, sizes)).await;
+                }
+            }
+        });
+
+        let mut stream = TcpStream::connect((ip, control_port))
+            .await
+            .expect("should connect to the control listener");
+        let (read, mut write) = stream.split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"state_sizes\n").await.unwrap();
+        let response = lines.next_line().await.unwrap().unwrap();
+        let response: serde_json::Value =
+            serde_json::from_str(&response).unwrap();
+        assert_eq!(response["process_id"], 1);
+        assert_eq!(response["shard_id"], 0);
+        for worker_index in &["0", "1"] {
+            assert_eq!(response["workers"][worker_index]["dots_in_flight"], 0);
+        }
+
+        // unknown queries get an error
+        write.write_all(b"heap\n").await.unwrap();
+        let response = lines.next_line().await.unwrap().unwrap();
+        let response: serde_json::Value =
+            serde_json::from_str(&response).unwrap();
+        assert!(response["error"].is_string());
+    }
+}
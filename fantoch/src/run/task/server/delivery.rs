@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+
+/// Sequence number of a message sent on a connection (the first message has
+/// sequence number 1).
+pub type Seq = u64;
+
+/// Sender side of the at-least-once delivery of messages on a connection (see
+/// `Config::delivery_window`). Each message sent is assigned the next sequence
+/// number and kept until the peer acknowledges it, so that it can be
+/// retransmitted (e.g. once the connection is re-established).
+pub struct Outbox<M> {
+    window: usize,
+    next_seq: Seq,
+    unacked: VecDeque<(Seq, M)>,
+}
+
+impl<M> Outbox<M> {
+    /// Creates a new `Outbox` with at most `window` unacknowledged messages.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "the delivery window should be positive");
+        Self {
+            window,
+            next_seq: 1,
+            unacked: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Checks whether no more messages can be sent until some are
+    /// acknowledged.
+    pub fn is_full(&self) -> bool {
+        self.unacked.len() >= self.window
+    }
+
+    /// Assigns the next sequence number to `msg`, keeping it until it's
+    /// acknowledged.
+    pub fn push(&mut self, msg: M) -> Seq {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.unacked.push_back((seq, msg));
+        seq
+    }
+
+    /// Handles a cumulative acknowledgment, i.e. of all messages with a
+    /// sequence number up to `seq`.
+    pub fn ack(&mut self, seq: Seq) {
+        while let Some((front, _)) = self.unacked.front() {
+            if *front > seq {
+                break;
+            }
+            self.unacked.pop_front();
+        }
+    }
+
+    /// Returns the messages not yet acknowledged (sorted by sequence number),
+    /// i.e. the ones to be retransmitted.
+    pub fn unacked(&self) -> impl Iterator<Item = &(Seq, M)> {
+        self.unacked.iter()
+    }
+}
+
+/// Receiver side of the at-least-once delivery of messages on a connection.
+/// Since messages are retransmitted in order, starting from the oldest
+/// unacknowledged one, a message is a duplicate if its sequence number is not
+/// higher than the one of the last message delivered. Acknowledgments are
+/// cumulative and sent every half window, so that the sender can keep sending
+/// while acknowledgments are in transit.
+pub struct Inbox {
+    ack_interval: Seq,
+    delivered: Seq,
+    acked: Seq,
+}
+
+impl Inbox {
+    /// Creates a new `Inbox` for a sender with at most `window`
+    /// unacknowledged messages.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "the delivery window should be positive");
+        Self {
+            ack_interval: std::cmp::max(1, window as Seq / 2),
+            delivered: 0,
+            acked: 0,
+        }
+    }
+
+    /// Checks whether the message with sequence number `seq` should be
+    /// delivered, i.e. whether it's not a duplicate.
+    pub fn receive(&mut self, seq: Seq) -> bool {
+        if seq <= self.delivered {
+            return false;
+        }
+        self.delivered = seq;
+        true
+    }
+
+    /// Returns the acknowledgment to be sent to the sender, if enough
+    /// messages have been delivered since the last one.
+    pub fn to_ack(&mut self) -> Option<Seq> {
+        if self.delivered - self.acked >= self.ack_interval {
+            self.acked = self.delivered;
+            Some(self.acked)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outbox_flow() {
+        let mut outbox = Outbox::new(2);
+        assert!(!outbox.is_full());
+        assert_eq!(outbox.push("a"), 1);
+        assert_eq!(outbox.push("b"), 2);
+        assert!(outbox.is_full());
+
+        // acks are cumulative
+        outbox.ack(1);
+        assert!(!outbox.is_full());
+        assert_eq!(outbox.push("c"), 3);
+        let unacked: Vec<_> = outbox.unacked().cloned().collect();
+        assert_eq!(unacked, vec![(2, "b"), (3, "c")]);
+        outbox.ack(3);
+        assert_eq!(outbox.unacked().count(), 0);
+
+        // old acks are ignored
+        outbox.ack(2);
+        assert_eq!(outbox.push("d"), 4);
+        assert_eq!(outbox.unacked().count(), 1);
+    }
+
+    #[test]
+    fn inbox_dedup_and_ack() {
+        let mut inbox = Inbox::new(4);
+        assert!(inbox.receive(1));
+        assert_eq!(inbox.to_ack(), None);
+        assert!(inbox.receive(2));
+        assert_eq!(inbox.to_ack(), Some(2));
+        assert_eq!(inbox.to_ack(), None);
+
+        // retransmissions of messages already delivered are dropped
+        assert!(!inbox.receive(1));
+        assert!(!inbox.receive(2));
+        assert!(inbox.receive(3));
+        assert!(!inbox.receive(3));
+        assert_eq!(inbox.to_ack(), None);
+        assert!(inbox.receive(4));
+        assert_eq!(inbox.to_ack(), Some(4));
+
+        // with a window of 1, every message is acked
+        let mut inbox = Inbox::new(1);
+        assert!(inbox.receive(1));
+        assert_eq!(inbox.to_ack(), Some(1));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::id::{Epoch, ProcessId};
+use crate::HashMap;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Returns the epoch of this incarnation of the process. If an epoch file is
+/// set, the epoch persisted there (if any) is incremented, and the new epoch
+/// is persisted before being returned; this ensures that a restarted process
+/// has a higher epoch than all its previous incarnations. Without an epoch
+/// file, the epoch is always 0 (and thus restarts can't be detected).
+pub fn next_epoch(epoch_file: Option<&String>) -> Result<Epoch, Report> {
+    let epoch_file = if let Some(epoch_file) = epoch_file {
+        epoch_file
+    } else {
+        return Ok(0);
+    };
+    let epoch = match std::fs::read_to_string(epoch_file) {
+        Ok(contents) => {
+            let previous = contents
+                .trim()
+                .parse::<Epoch>()
+                .wrap_err_with(|| format!("parse epoch file {}", epoch_file))?;
+            previous + 1
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(e) => {
+            return Err(e)
+                .wrap_err_with(|| format!("read epoch file {}", epoch_file))
+        }
+    };
+    // write to a temporary file and then rename it, so that the epoch file is
+    // never left half-written
+    let tmp = format!("{}.tmp", epoch_file);
+    std::fs::write(&tmp, epoch.to_string())
+        .and_then(|_| std::fs::rename(&tmp, epoch_file))
+        .wrap_err_with(|| format!("write epoch file {}", epoch_file))?;
+    Ok(epoch)
+}
+
+/// How the epoch announced by a peer compares with the latest one known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpochChange {
+    /// the epoch is older than the latest one known, i.e. it belongs to an
+    /// incarnation of the peer that has since restarted
+    Stale { current: Epoch },
+    /// the epoch is the latest one known
+    Current,
+    /// the epoch is newer than the latest one known, i.e. the peer restarted
+    Restarted { previous: Epoch },
+}
+
+/// Tracks the latest epoch of each peer. Readers use it to stop forwarding
+/// messages sent by old incarnations of a peer as soon as a newer incarnation
+/// shows up.
+#[derive(Debug, Clone)]
+pub struct PeerEpochs {
+    epochs: Arc<HashMap<ProcessId, AtomicU64>>,
+}
+
+impl PeerEpochs {
+    /// Creates a new `PeerEpochs` given the epoch announced by each peer
+    /// during the handshake (possibly more than once per peer).
+    pub fn new<I>(peers: I) -> Self
+    where
+        I: IntoIterator<Item = (ProcessId, Epoch)>,
+    {
+        let mut epochs = HashMap::new();
+        for (process_id, epoch) in peers {
+            let current = epochs.entry(process_id).or_insert(epoch);
+            *current = std::cmp::max(*current, epoch);
+        }
+        let epochs = epochs
+            .into_iter()
+            .map(|(process_id, epoch)| (process_id, AtomicU64::new(epoch)))
+            .collect();
+        Self {
+            epochs: Arc::new(epochs),
+        }
+    }
+
+    /// Returns the latest epoch known of some peer (if the peer is known).
+    pub fn current(&self, process_id: ProcessId) -> Option<Epoch> {
+        self.epochs
+            .get(&process_id)
+            .map(|epoch| epoch.load(Ordering::Acquire))
+    }
+
+    /// Checks whether `epoch` is older than the latest epoch known of some
+    /// peer. Unknown peers are never stale.
+    pub fn is_stale(&self, process_id: ProcessId, epoch: Epoch) -> bool {
+        self.current(process_id)
+            .map(|current| epoch < current)
+            .unwrap_or(false)
+    }
+
+    /// Records that some peer announced `epoch`, returning how it compares
+    /// with the latest epoch known. Returns `None` if the peer is unknown.
+    pub fn observe(
+        &self,
+        process_id: ProcessId,
+        epoch: Epoch,
+    ) -> Option<EpochChange> {
+        let current = self.epochs.get(&process_id)?;
+        let previous = current.fetch_max(epoch, Ordering::AcqRel);
+        let change = if epoch < previous {
+            EpochChange::Stale { current: previous }
+        } else if epoch == previous {
+            EpochChange::Current
+        } else {
+            EpochChange::Restarted { previous }
+        };
+        Some(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_epoch() {
+        // without an epoch file, the epoch is always 0
+        assert_eq!(super::next_epoch(None).unwrap(), 0);
+
+        // with one, the epoch is incremented on every call
+        let epoch_file = std::env::temp_dir()
+            .join(format!("fantoch_epoch_{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let _ = std::fs::remove_file(&epoch_file);
+        assert_eq!(super::next_epoch(Some(&epoch_file)).unwrap(), 0);
+        assert_eq!(super::next_epoch(Some(&epoch_file)).unwrap(), 1);
+        assert_eq!(super::next_epoch(Some(&epoch_file)).unwrap(), 2);
+
+        // an epoch file with garbage is an error
+        std::fs::write(&epoch_file, "garbage").unwrap();
+        assert!(super::next_epoch(Some(&epoch_file)).is_err());
+        std::fs::remove_file(&epoch_file).unwrap();
+    }
+
+    #[test]
+    fn peer_epochs() {
+        // peer 1 announced epoch 0 on a connection and 2 on another
+        let epochs = PeerEpochs::new(vec![(1, 0), (1, 2), (2, 0)]);
+        assert_eq!(epochs.current(1), Some(2));
+        assert_eq!(epochs.current(2), Some(0));
+        assert_eq!(epochs.current(3), None);
+        assert!(epochs.is_stale(1, 0));
+        assert!(!epochs.is_stale(1, 2));
+        assert!(!epochs.is_stale(3, 0));
+
+        // old incarnations are stale
+        assert_eq!(
+            epochs.observe(1, 1),
+            Some(EpochChange::Stale { current: 2 })
+        );
+        assert_eq!(epochs.observe(1, 2), Some(EpochChange::Current));
+        assert_eq!(epochs.current(1), Some(2));
+
+        // peer 2 restarts, and so connections from epoch 0 become stale
+        assert_eq!(
+            epochs.observe(2, 1),
+            Some(EpochChange::Restarted { previous: 0 })
+        );
+        assert_eq!(epochs.current(2), Some(1));
+        assert!(epochs.is_stale(2, 0));
+
+        // unknown peers are ignored
+        assert_eq!(epochs.observe(3, 0), None);
+    }
+}
@@ -8,6 +8,8 @@ use tokio::time::{self, Duration};
 const EXECUTION_LOGGER_FLUSH_INTERVAL: Duration = Duration::from_secs(1); // flush every second
 const EXECUTION_LOGGER_BUFFER_SIZE: usize = 8 * 1024; // 8KB
 
+/// Logs the execution infos received from the workers, which can then be
+/// replayed (see the `graph_executor_replay` binary).
 pub async fn execution_logger_task<P>(
     execution_log: String,
     mut from_executors: ExecutionInfoReceiver<P>,
@@ -53,3 +55,54 @@ pub async fn execution_logger_task<P>(
         }
     }
 }
+
+/// Logs the order in which commands are executed on each key (as pairs of key
+/// and command identifier, in execution order), so that the orders of all
+/// processes can be compared once the run is over (see the
+/// `execution_order_audit` binary).
+pub async fn execution_order_logger_task(
+    execution_order_log: String,
+    mut from_executors: ExecutionOrderReceiver,
+) {
+    info!(
+        "[execution_order_logger] started with log {}",
+        execution_order_log
+    );
+
+    // create execution order log file (truncating it if already exists)
+    let file = File::create(execution_order_log)
+        .await
+        .expect("it should be possible to create execution order log file");
+
+    // create file logger
+    let mut logger = Rw::from(
+        EXECUTION_LOGGER_BUFFER_SIZE,
+        EXECUTION_LOGGER_BUFFER_SIZE,
+        file,
+    );
+
+    // create interval
+    let mut interval = time::interval(EXECUTION_LOGGER_FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            order = from_executors.recv() => {
+                trace!("[execution_order_logger] from executors: {:?}", order);
+                if let Some(order) = order {
+                    // write execution order to file
+                    if let Err(e) = logger.write(&order).await {
+                        warn!("[execution_order_logger] error when writing to the logger file: {:?}", e);
+                    }
+                } else {
+                    warn!("[execution_order_logger] error while receiving execution order from executors");
+                }
+            }
+            _ = interval.tick()  => {
+                // flush
+                if let Err(e) = logger.flush().await {
+                    warn!("[execution_order_logger] error when flushing to the logger file: {:?}", e);
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,19 @@
+use super::execution_logger;
+use super::metrics_logger;
 use crate::config::Config;
-use crate::executor::Executor;
+use crate::executor::{
+    BacklogAlarm, BacklogWatchdog, ExecutionThrottle, Executor,
+    ExecutorMetricsKind, ExecutorResult, GraphSnapshot,
+};
 use crate::id::{ClientId, ProcessId, ShardId};
+use crate::kvs::KeyRange;
 use crate::protocol::Protocol;
 use crate::run::prelude::*;
 use crate::run::task;
-use crate::time::RunTime;
+use crate::run::task::server::canary::ToCanary;
+use crate::time::{RunTime, SysTime};
 use crate::HashMap;
-use crate::{debug, trace, warn};
+use crate::{debug, info, trace, warn};
 use std::sync::Arc;
 use tokio::time;
 
@@ -19,12 +26,32 @@ pub fn start_executors<P>(
     client_to_executors_rxs: Vec<ClientToExecutorReceiver>,
     executors_to_workers: ExecutorsToWorkers,
     shard_writers: HashMap<ShardId, Vec<WriterSender<P>>>,
+    process_writers: HashMap<ProcessId, Vec<WriterSender<P>>>,
     to_executors: ToExecutors<P>,
-    to_metrics_logger: Option<ExecutorMetricsSender>,
+    to_metrics_logger: Option<ExecutorMetricsReporter>,
+    graph_snapshot_dir: Option<String>,
+    to_canary: Option<CanaryMessageSender<P>>,
+    execution_order_log: Option<String>,
+    process_channel_buffer_size: usize,
 ) where
     P: Protocol + 'static,
 {
+    let to_execution_order_logger =
+        execution_order_log.map(|execution_order_log| {
+            // if the execution order log was set, then start its logger
+            let mut tx =
+                task::spawn_consumer(process_channel_buffer_size, |rx| {
+                    execution_logger::execution_order_logger_task(
+                        execution_order_log,
+                        rx,
+                    )
+                });
+            tx.set_name("to_execution_order_logger");
+            tx
+        });
+
     // zip rxs'
+    let executors = to_executors_rxs.len();
     let incoming = to_executors_rxs
         .into_iter()
         .zip(client_to_executors_rxs.into_iter());
@@ -36,57 +63,91 @@ pub fn start_executors<P>(
     for (executor_index, (from_workers, from_clients)) in incoming.enumerate() {
         task::spawn(executor_task::<P>(
             executor_index,
+            executors,
             executor.clone(),
+            process_id,
             shard_id,
             config,
             from_workers,
             from_clients,
             executors_to_workers.clone(),
             shard_writers.clone(),
+            process_writers.clone(),
             to_executors.clone(),
             to_metrics_logger.clone(),
+            graph_snapshot_dir.clone(),
+            to_canary
+                .clone()
+                .map(|tx| ToCanary::new(executor_index, tx)),
+            to_execution_order_logger.clone(),
         ));
     }
 }
 
 async fn executor_task<P>(
     executor_index: usize,
+    executors: usize,
     mut executor: P::Executor,
+    process_id: ProcessId,
     shard_id: ShardId,
     config: Config,
     mut from_workers: ExecutionInfoReceiver<P>,
     mut from_clients: ClientToExecutorReceiver,
     mut executors_to_workers: ExecutorsToWorkers,
     mut shard_writers: HashMap<ShardId, Vec<WriterSender<P>>>,
+    mut process_writers: HashMap<ProcessId, Vec<WriterSender<P>>>,
     mut to_executors: ToExecutors<P>,
-    mut to_metrics_logger: Option<ExecutorMetricsSender>,
+    mut to_metrics_logger: Option<ExecutorMetricsReporter>,
+    graph_snapshot_dir: Option<String>,
+    mut to_canary: Option<ToCanary<P>>,
+    mut to_execution_order_logger: Option<ExecutionOrderSender>,
 ) where
     P: Protocol + 'static,
 {
     // set executor index
     executor.set_executor_index(executor_index);
 
+    // each executor worker owns the keys it executes
+    executor.set_key_range(KeyRange::worker(executor_index, executors));
+
     // create time
     let time = RunTime;
 
     // holder of all client info
     let mut to_clients = ToClients::new();
 
+    // create the watchdog of the executor's backlog
+    let mut backlog_watchdog =
+        BacklogWatchdog::new(process_id, executor_index, &config);
+
+    // create the throttle of the executor's results (if any)
+    let mut throttle = ExecutionThrottle::new(&config);
+
+    // create the writer of graph snapshots (if a directory is set)
+    let mut graph_snapshots = graph_snapshot_dir
+        .map(|dir| GraphSnapshotWriter::new(process_id, executor_index, dir));
+    let mut graph_snapshot_requests =
+        GraphSnapshotRequests::new(graph_snapshots.is_some());
+
     // create a tokio sleep
     let sleep = |interval| Box::pin(time::sleep(interval));
 
-    // create executors cleanup interval
-    let gen_cleanup_delay = || sleep(config.executor_cleanup_interval());
-    let mut cleanup_delay = gen_cleanup_delay();
+    // create executors cleanup interval (which executors may adapt)
+    let cleanup_interval = |executor: &mut P::Executor| {
+        executor
+            .cleanup_interval()
+            .unwrap_or_else(|| config.executor_cleanup_interval())
+    };
+    let mut cleanup_delay = sleep(cleanup_interval(&mut executor));
 
     // create executors executed notification delay
     let gen_executed_notification_delay =
         || sleep(config.executor_executed_notification_interval());
     let mut executed_notification_delay = gen_executed_notification_delay();
 
-    // create metrics interval
-    let gen_metrics_delay = || sleep(super::metrics_logger::METRICS_INTERVAL);
-    let mut metrics_delay = gen_metrics_delay();
+    // create backlog check interval
+    let gen_backlog_delay = || sleep(metrics_logger::METRICS_INTERVAL);
+    let mut backlog_delay = gen_backlog_delay();
 
     // check if executors monitor pending interval is set
     if let Some(monitor_pending_interval) =
@@ -103,72 +164,254 @@ async fn executor_task<P>(
                     executor.monitor_pending(&time);
                     monitor_pending_delay = gen_monitor_pending_delay();
                 }
+                _ = graph_snapshot_requests.recv() => {
+                    save_graph_snapshot::<P>(&executor, &mut graph_snapshots, "requested", &time);
+                }
                 execution_info = from_workers.recv() => {
-                    handle_execution_info(execution_info, &mut executor, shard_id, &mut shard_writers, &mut to_executors, &mut to_clients, &time).await;
+                    handle_execution_info(execution_info, &mut executor, process_id, shard_id, &mut shard_writers, &mut process_writers, &mut to_executors, &mut to_clients, &mut to_canary, &mut to_execution_order_logger, &mut throttle, &time).await;
                 }
                 from_client = from_clients.recv() => {
-                    handle_from_client::<P>(from_client, &mut to_clients).await;
+                    handle_from_client::<P>(from_client, &mut executor, &mut to_clients).await;
                 }
                 _ = &mut cleanup_delay => {
-                    cleanup_tick(&mut executor, shard_id, &mut shard_writers, &mut to_executors, &mut to_clients, &time).await;
-                    cleanup_delay = gen_cleanup_delay();
+                    cleanup_tick(&mut executor, process_id, shard_id, &mut shard_writers, &mut process_writers, &mut to_executors, &mut to_clients, &mut to_canary, &mut to_execution_order_logger, &mut throttle, &time).await;
+                    cleanup_delay = sleep(cleanup_interval(&mut executor));
                 }
                 _ = &mut executed_notification_delay => {
                     executed_notification_tick::<P>(&mut executor, &mut executors_to_workers, &time).await;
                     executed_notification_delay = gen_executed_notification_delay();
                 }
-                _ = &mut metrics_delay => {
-                    metrics_tick::<P>(executor_index, &mut executor, &mut to_metrics_logger).await;
-                    metrics_delay = gen_metrics_delay();
+                _ = &mut backlog_delay => {
+                    backlog_tick::<P>(&mut executor, &mut backlog_watchdog, &mut graph_snapshots, &config, &time);
+                    backlog_delay = gen_backlog_delay();
+                }
+                version = metrics_logger::snapshot_requested(&mut to_metrics_logger) => {
+                    report_metrics::<P>(executor_index, version, &mut executor, &backlog_watchdog, &throttle, &mut to_metrics_logger).await;
                 }
             }
+            report_violations::<P>(
+                &mut executor,
+                &mut graph_snapshots,
+                &config,
+                &time,
+            );
         }
     } else {
         loop {
             tokio::select! {
+                _ = graph_snapshot_requests.recv() => {
+                    save_graph_snapshot::<P>(&executor, &mut graph_snapshots, "requested", &time);
+                }
                 execution_info = from_workers.recv() => {
-                    handle_execution_info(execution_info, &mut executor, shard_id, &mut shard_writers, &mut to_executors, &mut to_clients, &time).await;
+                    handle_execution_info(execution_info, &mut executor, process_id, shard_id, &mut shard_writers, &mut process_writers, &mut to_executors, &mut to_clients, &mut to_canary, &mut to_execution_order_logger, &mut throttle, &time).await;
                 }
                 from_client = from_clients.recv() => {
-                    handle_from_client::<P>(from_client, &mut to_clients).await;
+                    handle_from_client::<P>(from_client, &mut executor, &mut to_clients).await;
                 }
                 _ = &mut cleanup_delay => {
-                    cleanup_tick(&mut executor, shard_id, &mut shard_writers, &mut to_executors, &mut to_clients, &time).await;
-                    cleanup_delay = gen_cleanup_delay();
+                    cleanup_tick(&mut executor, process_id, shard_id, &mut shard_writers, &mut process_writers, &mut to_executors, &mut to_clients, &mut to_canary, &mut to_execution_order_logger, &mut throttle, &time).await;
+                    cleanup_delay = sleep(cleanup_interval(&mut executor));
                 }
                 _ = &mut executed_notification_delay => {
                     executed_notification_tick::<P>(&mut executor, &mut executors_to_workers, &time).await;
                     executed_notification_delay = gen_executed_notification_delay();
                 }
-                _ = &mut metrics_delay  => {
-                    metrics_tick::<P>(executor_index, &mut executor, &mut to_metrics_logger).await;
-                    metrics_delay = gen_metrics_delay();
+                _ = &mut backlog_delay => {
+                    backlog_tick::<P>(&mut executor, &mut backlog_watchdog, &mut graph_snapshots, &config, &time);
+                    backlog_delay = gen_backlog_delay();
+                }
+                version = metrics_logger::snapshot_requested(&mut to_metrics_logger) => {
+                    report_metrics::<P>(executor_index, version, &mut executor, &backlog_watchdog, &throttle, &mut to_metrics_logger).await;
+                }
+            }
+            report_violations::<P>(
+                &mut executor,
+                &mut graph_snapshots,
+                &config,
+                &time,
+            );
+        }
+    }
+}
+
+// Reports the violations recorded by the executor (if any), saving a snapshot
+// of its dependency graph first (as reporting may panic).
+fn report_violations<P>(
+    executor: &mut P::Executor,
+    graph_snapshots: &mut Option<GraphSnapshotWriter>,
+    config: &Config,
+    time: &RunTime,
+) where
+    P: Protocol,
+{
+    while let Some(violation) = executor.to_violations() {
+        save_graph_snapshot::<P>(executor, graph_snapshots, "violation", time);
+        super::report_violation(
+            "executor",
+            violation,
+            config.keep_alive_on_violation(),
+        );
+    }
+}
+
+// Logs a backlog alarm. If processes should abort on backlog alarms (see
+// `Config::executor_backlog_alarm_abort`), this panics.
+fn report_backlog_alarm(alarm: BacklogAlarm, abort: bool) {
+    warn!("[executor] backlog alarm {}", alarm);
+    if abort {
+        panic!("[executor] backlog alarm {}", alarm);
+    }
+}
+
+// Saves a snapshot of the executor's dependency graph (if it has one and if a
+// directory for snapshots is set).
+fn save_graph_snapshot<P>(
+    executor: &P::Executor,
+    graph_snapshots: &mut Option<GraphSnapshotWriter>,
+    reason: &str,
+    time: &RunTime,
+) where
+    P: Protocol,
+{
+    if let Some(graph_snapshots) = graph_snapshots.as_mut() {
+        if let Some(snapshot) = executor.graph_snapshot() {
+            graph_snapshots.write(snapshot, reason, time);
+        }
+    }
+}
+
+// Writes snapshots of the dependency graph of an executor to a directory, both
+// in DOT and in GraphML.
+struct GraphSnapshotWriter {
+    process_id: ProcessId,
+    executor_index: usize,
+    dir: String,
+    // number of snapshots written so far
+    count: usize,
+}
+
+impl GraphSnapshotWriter {
+    fn new(process_id: ProcessId, executor_index: usize, dir: String) -> Self {
+        Self {
+            process_id,
+            executor_index,
+            dir,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, snapshot: GraphSnapshot, reason: &str, time: &RunTime) {
+        self.count += 1;
+        let prefix = format!(
+            "{}/graph_p{}_e{}_{}_{}_{}",
+            self.dir,
+            self.process_id,
+            self.executor_index,
+            self.count,
+            time.millis(),
+            reason
+        );
+        let files = vec![
+            (format!("{}.dot", prefix), snapshot.to_dot()),
+            (format!("{}.graphml", prefix), snapshot.to_graphml()),
+        ];
+        for (file, contents) in files {
+            match std::fs::write(&file, contents) {
+                Ok(()) => info!("[executor] graph snapshot saved in {}", file),
+                Err(e) => warn!(
+                    "[executor] error while saving graph snapshot in {}: {:?}",
+                    file, e
+                ),
+            }
+        }
+    }
+}
+
+// On-demand requests for graph snapshots, which are made by sending SIGUSR1
+// to the process (only supported on unix).
+struct GraphSnapshotRequests {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl GraphSnapshotRequests {
+    fn new(enabled: bool) -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let signal = if enabled {
+                signal(SignalKind::user_defined1())
+                    .map_err(|e| {
+                        warn!(
+                            "[executor] error while listening to SIGUSR1: {:?}",
+                            e
+                        )
+                    })
+                    .ok()
+            } else {
+                None
+            };
+            Self { signal }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = enabled;
+            Self {}
+        }
+    }
+
+    // Resolves once a new snapshot is requested. If requests are not enabled,
+    // this never resolves.
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        {
+            if let Some(signal) = self.signal.as_mut() {
+                if signal.recv().await.is_some() {
+                    return;
                 }
+                // stop listening if the signal stream ended
+                self.signal = None;
             }
         }
+        futures::future::pending().await
     }
 }
 
 async fn handle_execution_info<P>(
     execution_info: Option<<P::Executor as Executor>::ExecutionInfo>,
     executor: &mut P::Executor,
+    process_id: ProcessId,
     shard_id: ShardId,
     shard_writers: &mut HashMap<ShardId, Vec<WriterSender<P>>>,
+    process_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     to_executors: &mut ToExecutors<P>,
     to_clients: &mut ToClients,
+    to_canary: &mut Option<ToCanary<P>>,
+    to_execution_order_logger: &mut Option<ExecutionOrderSender>,
+    throttle: &mut ExecutionThrottle,
     time: &RunTime,
 ) where
     P: Protocol + 'static,
 {
     trace!("[executor] from workers: {:?}", execution_info);
     if let Some(execution_info) = execution_info {
+        // mirror the execution info to the canary (if any)
+        if let Some(to_canary) = to_canary.as_mut() {
+            to_canary.info(&execution_info).await;
+        }
         executor.handle(execution_info, time);
         fetch_results(
             executor,
+            process_id,
             shard_id,
             shard_writers,
+            process_writers,
             to_executors,
             to_clients,
+            to_canary,
+            to_execution_order_logger,
+            throttle,
+            time,
         )
         .await;
     } else {
@@ -178,32 +421,77 @@ async fn handle_execution_info<P>(
 
 async fn fetch_results<P>(
     executor: &mut P::Executor,
+    process_id: ProcessId,
     shard_id: ShardId,
     shard_writers: &mut HashMap<ShardId, Vec<WriterSender<P>>>,
+    process_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     to_executors: &mut ToExecutors<P>,
     to_clients: &mut ToClients,
+    to_canary: &mut Option<ToCanary<P>>,
+    to_execution_order_logger: &mut Option<ExecutionOrderSender>,
+    throttle: &mut ExecutionThrottle,
+    time: &RunTime,
 ) where
     P: Protocol + 'static,
 {
-    fetch_new_command_results::<P>(executor, to_clients).await;
+    let results = fetch_new_command_results::<P>(
+        executor,
+        to_clients,
+        to_canary,
+        to_execution_order_logger,
+    )
+    .await;
     fetch_info_to_executors::<P>(
         executor,
+        process_id,
         shard_id,
         shard_writers,
+        process_writers,
         to_executors,
     )
     .await;
+
+    // if execution is throttled, wait before executing more commands; in the
+    // meantime, execution info accumulates in the channels from workers
+    if let Some(delay) = throttle.consume(results, time) {
+        tokio::time::sleep(delay).await;
+    }
 }
 
 async fn fetch_new_command_results<P>(
     executor: &mut P::Executor,
     to_clients: &mut ToClients,
-) where
-    P: Protocol,
+    to_canary: &mut Option<ToCanary<P>>,
+    to_execution_order_logger: &mut Option<ExecutionOrderSender>,
+) -> usize
+where
+    P: Protocol + 'static,
 {
     // forward executor results (commands or partial commands) to clients that
     // are waiting for them
+    let mut results = 0;
     for executor_result in executor.to_clients_iter() {
+        results += 1;
+        // mirror the result to the canary (if any)
+        if let Some(to_canary) = to_canary.as_mut() {
+            to_canary.result(&executor_result).await;
+        }
+
+        // record the execution order (if enabled); as in
+        // `ExecutionOrderMonitor`, only the ops that were executed in the
+        // order defined by the protocol are recorded
+        if let Some(to_logger) = to_execution_order_logger.as_mut() {
+            let executed = executor_result.executed_clock.is_none()
+                && !executor_result.shed
+                && executor_result.error.is_none();
+            if executed {
+                let order = (executor_result.key.clone(), executor_result.rifl);
+                if let Err(e) = to_logger.send(order).await {
+                    warn!("[executor] error while sending execution order to logger: {:?}", e);
+                }
+            }
+        }
+
         // get client id
         let client_id = executor_result.rifl.source();
 
@@ -217,12 +505,15 @@ async fn fetch_new_command_results<P>(
             }
         }
     }
+    results
 }
 
 async fn fetch_info_to_executors<P>(
     executor: &mut P::Executor,
+    process_id: ProcessId,
     shard_id: ShardId,
     shard_writers: &mut HashMap<ShardId, Vec<WriterSender<P>>>,
+    process_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     to_executors: &mut ToExecutors<P>,
 ) where
     P: Protocol + 'static,
@@ -241,10 +532,12 @@ async fn fetch_info_to_executors<P>(
             }
         } else {
             let msg_to_send = Arc::new(POEMessage::Executor(execution_info));
+            let high_priority = msg_to_send.high_priority();
             if let Some(channels) = shard_writers.get_mut(&target_shard) {
                 crate::run::task::server::process::send_to_one_writer::<P>(
                     "executor",
                     msg_to_send,
+                    high_priority,
                     channels,
                 )
                 .await
@@ -255,10 +548,42 @@ async fn fetch_info_to_executors<P>(
             }
         }
     }
+
+    // forward execution info to specific processes
+    for (target, execution_info) in executor.to_process_executors_iter() {
+        debug!(
+            "[executor] to executors in process {}: {:?}",
+            target, execution_info
+        );
+        // check if it's a message to self
+        if process_id == target {
+            // notify executor
+            if let Err(e) = to_executors.forward(execution_info).await {
+                warn!("[executor] error while notifying other executors with new execution info: {:?}", e);
+            }
+        } else {
+            let msg_to_send = Arc::new(POEMessage::Executor(execution_info));
+            let high_priority = msg_to_send.high_priority();
+            if let Some(channels) = process_writers.get_mut(&target) {
+                crate::run::task::server::process::send_to_one_writer::<P>(
+                    "executor",
+                    msg_to_send,
+                    high_priority,
+                    channels,
+                )
+                .await
+            } else {
+                panic!(
+                    "[executor] tried to send a message to a non-connected process"
+                );
+            }
+        }
+    }
 }
 
 async fn handle_from_client<P>(
     from_client: Option<ClientToExecutor>,
+    executor: &mut P::Executor,
     to_clients: &mut ToClients,
 ) where
     P: Protocol,
@@ -272,6 +597,28 @@ async fn handle_from_client<P>(
             ClientToExecutor::Unregister(client_ids) => {
                 to_clients.unregister(client_ids);
             }
+            ClientToExecutor::StaleRead(_, rifl, key, ops) => {
+                // serve the read directly from the store
+                let executor_result = ExecutorResult::stale_read(
+                    rifl,
+                    key,
+                    ops,
+                    executor.store(),
+                );
+                let client_id = rifl.source();
+                if let Some(executor_results_tx) =
+                    to_clients.to_client(&client_id)
+                {
+                    if let Err(e) =
+                        executor_results_tx.send(executor_result).await
+                    {
+                        warn!(
+                            "[executor] error while sending stale read result to client {}: {:?}",
+                            client_id, e
+                        );
+                    }
+                }
+            }
         }
     } else {
         warn!("[executor] error while receiving new command from clients");
@@ -280,18 +627,35 @@ async fn handle_from_client<P>(
 
 async fn cleanup_tick<P>(
     executor: &mut P::Executor,
+    process_id: ProcessId,
     shard_id: ShardId,
     shard_writers: &mut HashMap<ShardId, Vec<WriterSender<P>>>,
+    process_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
     to_executors: &mut ToExecutors<P>,
     to_clients: &mut ToClients,
+    to_canary: &mut Option<ToCanary<P>>,
+    to_execution_order_logger: &mut Option<ExecutionOrderSender>,
+    throttle: &mut ExecutionThrottle,
     time: &RunTime,
 ) where
     P: Protocol + 'static,
 {
     trace!("[executor] cleanup");
     executor.cleanup(time);
-    fetch_results(executor, shard_id, shard_writers, to_executors, to_clients)
-        .await;
+    fetch_results(
+        executor,
+        process_id,
+        shard_id,
+        shard_writers,
+        process_writers,
+        to_executors,
+        to_clients,
+        to_canary,
+        to_execution_order_logger,
+        throttle,
+        time,
+    )
+    .await;
 }
 
 async fn executed_notification_tick<P>(
@@ -312,22 +676,56 @@ async fn executed_notification_tick<P>(
     }
 }
 
-async fn metrics_tick<P>(
+fn backlog_tick<P>(
+    executor: &mut P::Executor,
+    backlog_watchdog: &mut BacklogWatchdog,
+    graph_snapshots: &mut Option<GraphSnapshotWriter>,
+    config: &Config,
+    time: &RunTime,
+) where
+    P: Protocol + 'static,
+{
+    // check the backlog (if the executor tracks it)
+    if let Some(backlog) = executor.backlog() {
+        if let Some(alarm) = backlog_watchdog.check(backlog, time) {
+            save_graph_snapshot::<P>(
+                executor,
+                graph_snapshots,
+                "backlog_alarm",
+                time,
+            );
+            report_backlog_alarm(alarm, config.executor_backlog_alarm_abort());
+        }
+    }
+}
+
+async fn report_metrics<P>(
     executor_index: usize,
+    version: u64,
     executor: &mut P::Executor,
-    to_metrics_logger: &mut Option<ExecutorMetricsSender>,
+    backlog_watchdog: &BacklogWatchdog,
+    throttle: &ExecutionThrottle,
+    to_metrics_logger: &mut Option<ExecutorMetricsReporter>,
 ) where
     P: Protocol + 'static,
 {
     if let Some(to_metrics_logger) = to_metrics_logger.as_mut() {
-        // send metrics to logger (in case there's one)
-        let executor_metrics = executor.metrics().clone();
-        if let Err(e) = to_metrics_logger
-            .send((executor_index, executor_metrics))
-            .await
-        {
-            warn!("[executor] error while sending metrics to metrics logger: {:?}", e);
+        // sample memory usage and send metrics to logger (in case there's one)
+        executor.sample_memory();
+        let mut executor_metrics = executor.metrics().clone();
+        let alarms = backlog_watchdog.alarms();
+        if alarms > 0 {
+            executor_metrics
+                .aggregate(ExecutorMetricsKind::BacklogAlarms, alarms);
+        }
+        let throttle_delay = throttle.delayed().as_millis() as u64;
+        if throttle_delay > 0 {
+            executor_metrics
+                .aggregate(ExecutorMetricsKind::ThrottleDelay, throttle_delay);
         }
+        to_metrics_logger
+            .report(executor_index, version, executor_metrics)
+            .await;
     }
 }
 
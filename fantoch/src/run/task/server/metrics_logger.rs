@@ -1,18 +1,212 @@
-use crate::executor::ExecutorMetrics;
-use crate::protocol::ProtocolMetrics;
+use crate::executor::{ExecutorMetrics, ExecutorMetricsKind};
+use crate::metrics::Metrics;
+use crate::protocol::{ProtocolMetrics, ProtocolMetricsKind};
+use crate::run::chan::ChannelSender;
 use crate::run::prelude::*;
+use crate::run::rw::RwMetrics;
 use crate::run::task;
+use crate::run::task::server::client::AcceptorMetrics;
+use crate::time::{RunTime, SysTime};
 use crate::HashMap;
 use crate::{info, trace, warn};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use tokio::sync::watch;
 use tokio::time::{self, Duration};
 
 pub const METRICS_INTERVAL: Duration = Duration::from_secs(5); // notify/flush every 5 seconds
 
+// prefix of the line logged every `METRICS_INTERVAL` with the number of
+// commands committed so far (see `committed_from_log_line`)
+const COMMITTED_COMMANDS: &str = "committed commands";
+
+pub type RuntimeMetrics = Metrics<RuntimeMetricsKind>;
+
+// Internals of the tokio runtime in which the process runs, sampled every
+// `METRICS_INTERVAL` (only if the `runtime-metrics` feature is enabled).
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuntimeMetricsKind {
+    // number of tasks alive in the runtime
+    AliveTasks,
+    // number of tasks waiting in the global queue of the runtime, and in the
+    // local queue of each worker
+    GlobalQueueDepth,
+    LocalQueueDepth,
+    // number of tasks polled, and the mean time (in micros) each worker takes
+    // to poll a task
+    Polls,
+    MeanPollTime,
+    // percentage of time each worker was busy since the previous sample; if
+    // workers are always busy, the run layer (and not the protocol) is the
+    // bottleneck
+    WorkerBusy,
+}
+
+impl fmt::Debug for RuntimeMetricsKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeMetricsKind::AliveTasks => write!(f, "alive_tasks"),
+            RuntimeMetricsKind::GlobalQueueDepth => {
+                write!(f, "global_queue_depth")
+            }
+            RuntimeMetricsKind::LocalQueueDepth => {
+                write!(f, "local_queue_depth")
+            }
+            RuntimeMetricsKind::Polls => write!(f, "polls"),
+            RuntimeMetricsKind::MeanPollTime => write!(f, "mean_poll_time"),
+            RuntimeMetricsKind::WorkerBusy => write!(f, "worker_busy"),
+        }
+    }
+}
+
+/// Handle with which protocol workers and executors report their metrics: the
+/// metrics logger requests a new snapshot every `METRICS_INTERVAL`, and each
+/// of them replies with its metrics tagged with the version of the snapshot.
+/// This ensures that all metrics in a snapshot are taken (roughly) at the same
+/// time, independently of when each worker or executor was started.
+#[derive(Debug)]
+pub struct MetricsReporter<M> {
+    to_logger: ChannelSender<(usize, u64, M)>,
+    requests: watch::Receiver<u64>,
+}
+
+impl<M> Clone for MetricsReporter<M> {
+    fn clone(&self) -> Self {
+        Self {
+            to_logger: self.to_logger.clone(),
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+impl<M> MetricsReporter<M>
+where
+    M: fmt::Debug + 'static,
+{
+    pub fn new(
+        to_logger: ChannelSender<(usize, u64, M)>,
+        requests: watch::Receiver<u64>,
+    ) -> Self {
+        Self {
+            to_logger,
+            requests,
+        }
+    }
+
+    /// Completes with the version of the next snapshot requested (never, if
+    /// the metrics logger is gone).
+    pub async fn requested(&mut self) -> u64 {
+        if self.requests.changed().await.is_err() {
+            futures::future::pending::<()>().await;
+        }
+        *self.requests.borrow()
+    }
+
+    /// Reports the metrics of worker/executor `index` for snapshot `version`.
+    pub async fn report(&mut self, index: usize, version: u64, metrics: M) {
+        if let Err(e) = self.to_logger.send((index, version, metrics)).await {
+            warn!(
+                "[metrics_logger] error while reporting metrics of {}: {:?}",
+                index, e
+            );
+        }
+    }
+}
+
+/// Completes with the version of the next snapshot requested, or never if
+/// there's no metrics logger.
+pub async fn snapshot_requested<M>(
+    reporter: &mut Option<MetricsReporter<M>>,
+) -> u64
+where
+    M: fmt::Debug + 'static,
+{
+    match reporter {
+        Some(reporter) => reporter.requested().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Metrics of all protocol workers and executors of a process, as reported
+/// for the same snapshot request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MetricsSnapshot {
+    version: u64,
+    // time (in milliseconds) at which the snapshot was requested
+    time: u64,
+    protocol: ProtocolMetrics,
+    executor: ExecutorMetrics,
+}
+
+impl MetricsSnapshot {
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    pub fn protocol_metrics(&self) -> &ProtocolMetrics {
+        &self.protocol
+    }
+
+    pub fn executor_metrics(&self) -> &ExecutorMetrics {
+        &self.executor
+    }
+}
+
+// Snapshot for which some protocol workers or executors haven't yet reported
+// their metrics.
+struct PendingSnapshot {
+    version: u64,
+    time: u64,
+    workers: HashMap<usize, ProtocolMetrics>,
+    executors: HashMap<usize, ExecutorMetrics>,
+}
+
+impl PendingSnapshot {
+    fn new(version: u64, time: u64) -> Self {
+        Self {
+            version,
+            time,
+            workers: HashMap::new(),
+            executors: HashMap::new(),
+        }
+    }
+
+    fn is_complete(&self, workers: usize, executors: usize) -> bool {
+        self.workers.len() == workers && self.executors.len() == executors
+    }
+
+    fn into_snapshot(self) -> MetricsSnapshot {
+        let mut protocol = ProtocolMetrics::new();
+        for worker_metrics in self.workers.values() {
+            protocol.merge(worker_metrics);
+        }
+        let mut executor = ExecutorMetrics::new();
+        for executor_metrics in self.executors.values() {
+            executor.merge(executor_metrics);
+        }
+        MetricsSnapshot {
+            version: self.version,
+            time: self.time,
+            protocol,
+            executor,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ProcessMetrics {
     workers: HashMap<usize, ProtocolMetrics>,
     executors: HashMap<usize, ExecutorMetrics>,
+    writers: HashMap<usize, RwMetrics>,
+    acceptors: HashMap<usize, AcceptorMetrics>,
+    runtime: RuntimeMetrics,
+    // snapshots of the protocol and executor metrics, ordered by version
+    #[serde(default)]
+    snapshots: Vec<MetricsSnapshot>,
 }
 
 impl ProcessMetrics {
@@ -20,9 +214,33 @@ impl ProcessMetrics {
         Self {
             workers: HashMap::new(),
             executors: HashMap::new(),
+            writers: HashMap::new(),
+            acceptors: HashMap::new(),
+            runtime: RuntimeMetrics::new(),
+            snapshots: Vec::new(),
         }
     }
 
+    pub fn snapshots(&self) -> &[MetricsSnapshot] {
+        &self.snapshots
+    }
+
+    /// Returns, for each interval between consecutive snapshots, the time (in
+    /// milliseconds) at which the interval ended and the rate (per second) at
+    /// which the aggregated protocol metric `kind` grew during it.
+    pub fn protocol_rates(&self, kind: ProtocolMetricsKind) -> Vec<(u64, f64)> {
+        rates(&self.snapshots, |snapshot| {
+            snapshot.protocol.get_aggregated(kind).copied()
+        })
+    }
+
+    /// Same as `ProcessMetrics::protocol_rates` but for executor metrics.
+    pub fn executor_rates(&self, kind: ExecutorMetricsKind) -> Vec<(u64, f64)> {
+        rates(&self.snapshots, |snapshot| {
+            snapshot.executor.get_aggregated(kind).copied()
+        })
+    }
+
     pub fn protocol_metrics(&self) -> ProtocolMetrics {
         let mut metrics = ProtocolMetrics::new();
         for worker_metrics in self.workers.values() {
@@ -38,54 +256,418 @@ impl ProcessMetrics {
         }
         metrics
     }
+
+    pub fn writer_metrics(&self) -> RwMetrics {
+        let mut metrics = RwMetrics::new();
+        for writer_metrics in self.writers.values() {
+            metrics.merge(writer_metrics);
+        }
+        metrics
+    }
+
+    pub fn acceptor_metrics(&self) -> AcceptorMetrics {
+        let mut metrics = AcceptorMetrics::new();
+        for acceptor_metrics in self.acceptors.values() {
+            metrics.merge(acceptor_metrics);
+        }
+        metrics
+    }
+
+    pub fn runtime_metrics(&self) -> RuntimeMetrics {
+        self.runtime.clone()
+    }
+
+    /// Returns the number of commands committed so far by the protocol
+    /// workers (see `ProtocolMetricsKind::Committed`).
+    pub fn committed(&self) -> u64 {
+        self.workers
+            .values()
+            .filter_map(|metrics| {
+                metrics.get_aggregated(ProtocolMetricsKind::Committed)
+            })
+            .sum()
+    }
+}
+
+/// Parses the number of committed commands from a line logged by the metrics
+/// logger, which harnesses can follow to detect runs that stopped making
+/// progress. Returns `None` if the line doesn't report it.
+pub fn committed_from_log_line(line: &str) -> Option<u64> {
+    let line =
+        &line[line.find(COMMITTED_COMMANDS)? + COMMITTED_COMMANDS.len()..];
+    line.strip_prefix(": ")?.trim().parse().ok()
+}
+
+fn rates(
+    snapshots: &[MetricsSnapshot],
+    value: impl Fn(&MetricsSnapshot) -> Option<u64>,
+) -> Vec<(u64, f64)> {
+    snapshots
+        .windows(2)
+        .filter_map(|window| {
+            let (previous, current) = (&window[0], &window[1]);
+            let elapsed = current.time.saturating_sub(previous.time);
+            if elapsed == 0 {
+                return None;
+            }
+            let delta = value(current)
+                .unwrap_or_default()
+                .saturating_sub(value(previous).unwrap_or_default());
+            let rate = delta as f64 * 1000.0 / elapsed as f64;
+            Some((current.time, rate))
+        })
+        .collect()
+}
+
+#[cfg(feature = "runtime-metrics")]
+struct RuntimeSampler {
+    // total busy duration of each worker in the previous sample, and when it
+    // was taken
+    busy: Vec<Duration>,
+    sampled_at: std::time::Instant,
+    // total number of tasks polled in the previous sample
+    polls: u64,
+}
+
+#[cfg(feature = "runtime-metrics")]
+impl RuntimeSampler {
+    fn new() -> Self {
+        Self {
+            busy: Vec::new(),
+            sampled_at: std::time::Instant::now(),
+            polls: 0,
+        }
+    }
+
+    fn sample(&mut self, metrics: &mut RuntimeMetrics) {
+        let runtime = tokio::runtime::Handle::current().metrics();
+        let workers = runtime.num_workers();
+        let elapsed = self.sampled_at.elapsed();
+        self.sampled_at = std::time::Instant::now();
+        self.busy.resize(workers, Duration::from_secs(0));
+
+        metrics.collect(
+            RuntimeMetricsKind::AliveTasks,
+            runtime.num_alive_tasks() as u64,
+        );
+        metrics.collect(
+            RuntimeMetricsKind::GlobalQueueDepth,
+            runtime.global_queue_depth() as u64,
+        );
+
+        let mut polls = 0;
+        for worker in 0..workers {
+            metrics.collect(
+                RuntimeMetricsKind::LocalQueueDepth,
+                runtime.worker_local_queue_depth(worker) as u64,
+            );
+            metrics.collect(
+                RuntimeMetricsKind::MeanPollTime,
+                runtime.worker_mean_poll_time(worker).as_micros() as u64,
+            );
+            polls += runtime.worker_poll_count(worker);
+
+            // compute how busy the worker was since the previous sample
+            let busy = runtime.worker_total_busy_duration(worker);
+            let busy_since = busy.saturating_sub(self.busy[worker]);
+            self.busy[worker] = busy;
+            if !elapsed.is_zero() {
+                let percentage =
+                    busy_since.as_secs_f64() * 100.0 / elapsed.as_secs_f64();
+                metrics.collect(
+                    RuntimeMetricsKind::WorkerBusy,
+                    percentage.round() as u64,
+                );
+            }
+        }
+        metrics.aggregate(
+            RuntimeMetricsKind::Polls,
+            polls.saturating_sub(self.polls),
+        );
+        self.polls = polls;
+    }
+}
+
+// Without the `runtime-metrics` feature, the runtime is not sampled.
+#[cfg(not(feature = "runtime-metrics"))]
+struct RuntimeSampler;
+
+#[cfg(not(feature = "runtime-metrics"))]
+impl RuntimeSampler {
+    fn new() -> Self {
+        Self
+    }
+
+    fn sample(&mut self, _metrics: &mut RuntimeMetrics) {}
 }
 
 pub async fn metrics_logger_task(
     metrics_file: String,
+    workers: usize,
+    executors: usize,
+    snapshot_requests: watch::Sender<u64>,
     mut from_workers: ProtocolMetricsReceiver,
     mut from_executors: ExecutorMetricsReceiver,
+    mut from_writers: RwMetricsReceiver,
+    mut from_acceptors: AcceptorMetricsReceiver,
 ) {
     info!("[metrics_logger] started with log {}", metrics_file);
 
     // create metrics
     let mut global_metrics = ProcessMetrics::new();
 
+    // snapshots are timestamped with the wall-clock time
+    let time = RunTime;
+    let mut version = 0;
+    let mut pending: Option<PendingSnapshot> = None;
+
     // create interval
     let mut interval = time::interval(METRICS_INTERVAL);
 
+    // create runtime sampler
+    let mut runtime_sampler = RuntimeSampler::new();
+
+    // writers are only created if this process is connected to some other
+    // process; in case there's none, this channel is closed from the start
+    let mut writers_closed = false;
+
+    // SIGQUIT makes the metrics logger dump the status of the process
+    let mut status_requests = StatusRequests::new();
+
     loop {
         tokio::select! {
             metrics = from_workers.recv() => {
                 trace!("[metrics_logger] from protocol worker: {:?}", metrics);
-                if let Some((index, protocol_metrics)) = metrics  {
-                    // update metrics for this worker
+                if let Some((index, version, protocol_metrics)) = metrics  {
+                    // update metrics for this worker, and the snapshot they
+                    // were reported for
+                    if let Some(snapshot) = pending.as_mut().filter(|snapshot| snapshot.version == version) {
+                        snapshot.workers.insert(index, protocol_metrics.clone());
+                    }
                     global_metrics.workers.insert(index, protocol_metrics);
+                    maybe_complete_snapshot(&mut pending, workers, executors, &mut global_metrics);
                 } else {
                     warn!("[metrics_logger] error while receiving metrics from protocol worker");
                 }
             }
             metrics = from_executors.recv() => {
                 trace!("[metrics_logger] from executor: {:?}", metrics);
-                if let Some((index, executor_metrics)) = metrics  {
-                    // update metrics for this executor
+                if let Some((index, version, executor_metrics)) = metrics  {
+                    // update metrics for this executor, and the snapshot they
+                    // were reported for
+                    if let Some(snapshot) = pending.as_mut().filter(|snapshot| snapshot.version == version) {
+                        snapshot.executors.insert(index, executor_metrics.clone());
+                    }
                     global_metrics.executors.insert(index, executor_metrics);
+                    maybe_complete_snapshot(&mut pending, workers, executors, &mut global_metrics);
                 } else {
                     warn!("[metrics_logger] error while receiving metrics from executor");
                 }
             }
+            metrics = from_writers.recv(), if !writers_closed => {
+                trace!("[metrics_logger] from writer: {:?}", metrics);
+                if let Some((index, writer_metrics)) = metrics  {
+                    // update metrics for this writer
+                    global_metrics.writers.insert(index, writer_metrics);
+                } else {
+                    writers_closed = true;
+                }
+            }
+            metrics = from_acceptors.recv() => {
+                trace!("[metrics_logger] from client acceptor: {:?}", metrics);
+                if let Some((index, acceptor_metrics)) = metrics  {
+                    // update metrics for this acceptor
+                    global_metrics.acceptors.insert(index, acceptor_metrics);
+                } else {
+                    warn!("[metrics_logger] error while receiving metrics from client acceptor");
+                }
+            }
             _ = interval.tick()  => {
-                // First serialize to a temporary file, and then rename it. This makes it more
-                // likely we won't end up with a corrupted file if we're shutdown in the middle
-                // of this.
-                let tmp = format!("{}_tmp", metrics_file);
-                if let Err(e) = task::util::serialize_and_compress(&global_metrics, &tmp) {
+                // request a new snapshot; if some worker or executor didn't
+                // report its metrics for the previous one in the meantime,
+                // that one is discarded
+                if let Some(snapshot) = pending.take() {
+                    warn!("[metrics_logger] snapshot {} incomplete: {} of {} workers and {} of {} executors reported", snapshot.version, snapshot.workers.len(), workers, snapshot.executors.len(), executors);
+                }
+                version += 1;
+                pending = Some(PendingSnapshot::new(version, time.millis()));
+                // this only fails if there's no worker or executor left
+                let _ = snapshot_requests.send(version);
+
+                // sample the runtime
+                runtime_sampler.sample(&mut global_metrics.runtime);
+
+                // metrics are written atomically, and so we won't end up with
+                // a corrupted file if we're shutdown in the middle of this
+                if let Err(e) = task::util::serialize_and_compress(&global_metrics, &metrics_file) {
                     panic!("[metrics_logger] couldn't serialize metrics: {:?}", e);
                 }
-                // rename file
-                if let Err(e) = std::fs::rename(&tmp, &metrics_file) {
-                    warn!("[metrics_logger] coudn't rename temporary metrics file: {:?}", e);
+
+                // report progress
+                info!("[metrics_logger] {}: {}", COMMITTED_COMMANDS, global_metrics.committed());
+            }
+            _ = status_requests.recv() => {
+                log_status(&global_metrics, &pending, workers, executors);
+            }
+        }
+    }
+}
+
+// Logs the latest metrics reported by each worker and executor, along with
+// the ones that haven't reported metrics for the pending snapshot (if any),
+// which can help to find which of them is stuck.
+fn log_status(
+    global_metrics: &ProcessMetrics,
+    pending: &Option<PendingSnapshot>,
+    workers: usize,
+    executors: usize,
+) {
+    info!(
+        "[metrics_logger] status: {}: {}",
+        COMMITTED_COMMANDS,
+        global_metrics.committed()
+    );
+    if let Some(snapshot) = pending {
+        let missing_workers: Vec<_> = (0..workers)
+            .filter(|index| !snapshot.workers.contains_key(index))
+            .collect();
+        let missing_executors: Vec<_> = (0..executors)
+            .filter(|index| !snapshot.executors.contains_key(index))
+            .collect();
+        info!(
+            "[metrics_logger] status: snapshot {} missing workers {:?} and executors {:?}",
+            snapshot.version, missing_workers, missing_executors
+        );
+    }
+    let mut workers: Vec<_> = global_metrics.workers.iter().collect();
+    workers.sort_by_key(|(index, _)| *index);
+    for (index, metrics) in workers {
+        info!("[metrics_logger] status: worker {}: {:?}", index, metrics);
+    }
+    let mut executors: Vec<_> = global_metrics.executors.iter().collect();
+    executors.sort_by_key(|(index, _)| *index);
+    for (index, metrics) in executors {
+        info!("[metrics_logger] status: executor {}: {:?}", index, metrics);
+    }
+}
+
+// On-demand requests for a dump of the status of the process, which are made
+// by sending SIGQUIT to the process (only supported on unix). Since it's
+// handled, SIGQUIT no longer terminates the process.
+struct StatusRequests {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl StatusRequests {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let signal = signal(SignalKind::quit())
+                .map_err(|e| {
+                    warn!(
+                        "[metrics_logger] error while listening to SIGQUIT: {:?}",
+                        e
+                    )
+                })
+                .ok();
+            Self { signal }
+        }
+        #[cfg(not(unix))]
+        {
+            Self {}
+        }
+    }
+
+    // Resolves once a new status dump is requested. If requests are not
+    // supported, this never resolves.
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        {
+            if let Some(signal) = self.signal.as_mut() {
+                if signal.recv().await.is_some() {
+                    return;
                 }
+                // stop listening if the signal stream ended
+                self.signal = None;
             }
         }
+        futures::future::pending().await
+    }
+}
+
+// Moves the pending snapshot to the ones recorded once all workers and
+// executors have reported their metrics for it.
+fn maybe_complete_snapshot(
+    pending: &mut Option<PendingSnapshot>,
+    workers: usize,
+    executors: usize,
+    global_metrics: &mut ProcessMetrics,
+) {
+    if pending
+        .as_ref()
+        .map(|snapshot| snapshot.is_complete(workers, executors))
+        .unwrap_or_default()
+    {
+        let snapshot = pending.take().expect("pending snapshot should exist");
+        global_metrics.snapshots.push(snapshot.into_snapshot());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(version: u64, time: u64, commands: u64) -> MetricsSnapshot {
+        let mut pending = PendingSnapshot::new(version, time);
+        // split the commands across two workers
+        for (index, commands) in [(0, commands / 2), (1, commands / 2)] {
+            let mut metrics = ProtocolMetrics::new();
+            metrics.aggregate(ProtocolMetricsKind::Stable, commands);
+            pending.workers.insert(index, metrics);
+        }
+        pending.executors.insert(0, ExecutorMetrics::new());
+        assert!(pending.is_complete(2, 1));
+        pending.into_snapshot()
+    }
+
+    #[test]
+    fn committed_line() {
+        let mut metrics = ProcessMetrics::new();
+        for (index, committed) in [(0, 10), (1, 32)] {
+            let mut worker_metrics = ProtocolMetrics::new();
+            worker_metrics.aggregate(ProtocolMetricsKind::Committed, committed);
+            metrics.workers.insert(index, worker_metrics);
+        }
+        assert_eq!(metrics.committed(), 42);
+
+        let line = format!(
+            "2021-01-01T00:00:00.000000Z  INFO fantoch::run: [metrics_logger] {}: {}",
+            COMMITTED_COMMANDS,
+            metrics.committed()
+        );
+        assert_eq!(committed_from_log_line(&line), Some(42));
+        assert_eq!(committed_from_log_line("process 1 started"), None);
+    }
+
+    #[test]
+    fn snapshot_rates() {
+        let mut metrics = ProcessMetrics::new();
+        metrics.snapshots = vec![
+            snapshot(1, 0, 0),
+            snapshot(2, 5000, 1000),
+            snapshot(3, 10000, 1000),
+            snapshot(4, 12000, 2000),
+        ];
+        assert_eq!(
+            metrics.protocol_rates(ProtocolMetricsKind::Stable),
+            vec![(5000, 200.0), (10000, 0.0), (12000, 500.0)]
+        );
+        // metrics never aggregated have a rate of zero
+        assert_eq!(
+            metrics.executor_rates(ExecutorMetricsKind::ChainSize),
+            vec![(5000, 0.0), (10000, 0.0), (12000, 0.0)]
+        );
     }
 }
@@ -4,6 +4,9 @@ pub mod executor;
 // This module contains execution logger's implementation.
 mod execution_logger;
 
+// This module contains canary's implementation.
+pub mod canary;
+
 // This module contains process's implementation.
 pub mod process;
 
@@ -22,35 +25,50 @@ pub mod delay;
 // This module contains periodic metrics's implementation.
 pub mod metrics_logger;
 
+// This module contains the implementation of process epochs.
+pub mod epoch;
+
+// This module contains the implementation of the control listener.
+pub mod control;
+
+// This module contains the implementation of at-least-once delivery.
+pub mod delivery;
+
 use crate::config::Config;
-use crate::id::{ProcessId, ShardId};
-use crate::protocol::Protocol;
-use crate::run::chan;
+use crate::error::RunError;
+use crate::id::{Epoch, ProcessId, ShardId};
+use crate::protocol::{Protocol, Violation};
+use crate::run::chan::{self, ChannelReceiver};
 use crate::run::prelude::*;
-use crate::run::rw::Connection;
+use crate::run::rw::{Connection, MessageType, TcpOptions};
 use crate::run::task;
 use crate::HashMap;
-use crate::{trace, warn};
+use crate::{info, trace, warn};
 use color_eyre::Report;
+use delivery::{Inbox, Outbox, Seq};
+use epoch::{EpochChange, PeerEpochs};
 use std::fmt::Debug;
 use std::net::IpAddr;
+use std::sync::Arc;
 use tokio::net::{TcpListener, ToSocketAddrs};
 use tokio::time::{self, Duration};
 
 pub async fn connect_to_all<A, P>(
     process_id: ProcessId,
     shard_id: ShardId,
+    epoch: Epoch,
     config: Config,
     listener: TcpListener,
     addresses: Vec<(A, Option<Duration>)>,
     to_workers: ReaderToWorkers<P>,
     to_executors: ToExecutors<P>,
     connect_retries: usize,
-    tcp_nodelay: bool,
+    tcp_options: TcpOptions,
     tcp_buffer_size: usize,
     tcp_flush_interval: Option<Duration>,
     channel_buffer_size: usize,
     multiplexing: usize,
+    to_metrics_logger: Option<RwMetricsSender>,
 ) -> Result<
     (
         HashMap<ProcessId, (ShardId, IpAddr, Option<Duration>)>,
@@ -75,7 +93,7 @@ where
 
     // spawn listener
     let mut from_listener = task::spawn_producer(channel_buffer_size, |tx| {
-        task::listener_task(listener, tcp_nodelay, tcp_buffer_size, tx)
+        task::listener_task(listener, tcp_options, tcp_buffer_size, tx)
     });
 
     // create list of in and out connections:
@@ -92,7 +110,7 @@ where
         for _ in 0..multiplexing {
             let mut connection = task::connect(
                 &address,
-                tcp_nodelay,
+                tcp_options,
                 tcp_buffer_size,
                 connect_retries,
             )
@@ -115,91 +133,228 @@ where
         incoming.push(connection);
     }
 
-    let res = handshake::<P>(
+    // log the smallest effective MSS among all connections, which may be
+    // smaller than the one configured (e.g. if not all machines support
+    // jumbo frames)
+    let mss = outgoing
+        .iter()
+        .chain(incoming.iter())
+        .filter_map(Connection::mss)
+        .min();
+    if let Some(mss) = mss {
+        info!("p{}: effective MSS: {}", process_id, mss);
+    }
+
+    let (peer_epochs, res) = handshake::<P>(
         process_id,
         shard_id,
-        to_workers,
-        to_executors,
+        epoch,
+        to_workers.clone(),
+        to_executors.clone(),
+        config.message_compression(),
+        config.delivery_window(),
         tcp_flush_interval,
         channel_buffer_size,
         incoming,
         outgoing,
+        to_metrics_logger,
     )
-    .await;
+    .await?;
+
+    // from now on, new connections can only come from restarted peers
+    task::spawn(rejoin_task::<P>(
+        process_id,
+        shard_id,
+        epoch,
+        from_listener,
+        peer_epochs,
+        to_workers,
+        to_executors,
+        config.message_compression(),
+        config.delivery_window(),
+    ));
     Ok(res)
 }
 
 async fn handshake<P>(
     process_id: ProcessId,
     shard_id: ShardId,
+    epoch: Epoch,
     to_workers: ReaderToWorkers<P>,
     to_executors: ToExecutors<P>,
+    message_compression: Option<usize>,
+    delivery_window: Option<usize>,
     tcp_flush_interval: Option<Duration>,
     channel_buffer_size: usize,
     mut connections_0: Vec<Connection>,
     mut connections_1: Vec<Connection>,
-) -> (
-    HashMap<ProcessId, (ShardId, IpAddr, Option<Duration>)>,
-    HashMap<ProcessId, Vec<WriterSender<P>>>,
-)
+    to_metrics_logger: Option<RwMetricsSender>,
+) -> Result<
+    (
+        PeerEpochs,
+        (
+            HashMap<ProcessId, (ShardId, IpAddr, Option<Duration>)>,
+            HashMap<ProcessId, Vec<WriterSender<P>>>,
+        ),
+    ),
+    Report,
+>
 where
     P: Protocol + 'static,
 {
     // say hi to all on both connections
-    say_hi(process_id, shard_id, &mut connections_0).await;
-    say_hi(process_id, shard_id, &mut connections_1).await;
+    let message_fingerprint = P::message_fingerprint();
+    say_hi(
+        process_id,
+        shard_id,
+        epoch,
+        message_fingerprint,
+        &mut connections_0,
+    )
+    .await;
+    say_hi(
+        process_id,
+        shard_id,
+        epoch,
+        message_fingerprint,
+        &mut connections_1,
+    )
+    .await;
     trace!("said hi to all processes");
 
     // receive hi from all on both connections
-    let id_to_connection_0 = receive_hi(connections_0).await;
-    let id_to_connection_1 = receive_hi(connections_1).await;
+    let mut id_to_connection_0 =
+        receive_hi(process_id, message_fingerprint, connections_0).await?;
+    let mut id_to_connection_1 =
+        receive_hi(process_id, message_fingerprint, connections_1).await?;
+
+    // maybe compress messages from now on; since all processes share the same
+    // config, compression is enabled at both ends of each connection
+    if let Some(min_threshold) = message_compression {
+        id_to_connection_0
+            .iter_mut()
+            .chain(id_to_connection_1.iter_mut())
+            .for_each(|(_, _, _, connection)| {
+                connection.set_compression(min_threshold)
+            });
+    }
+
+    // track the epoch announced by each peer
+    let peer_epochs = PeerEpochs::new(
+        id_to_connection_0
+            .iter()
+            .chain(id_to_connection_1.iter())
+            .map(|(peer_id, _, peer_epoch, _)| (*peer_id, *peer_epoch)),
+    );
 
     // start readers and writers
-    start_readers::<P>(to_workers, to_executors, id_to_connection_0);
-    start_writers::<P>(
+    start_readers::<P>(
+        to_workers,
+        to_executors,
+        &peer_epochs,
+        delivery_window,
+        id_to_connection_0,
+    );
+    let id_to_connection_1 = id_to_connection_1
+        .into_iter()
+        .map(|(peer_id, peer_shard_id, _, connection)| {
+            (peer_id, peer_shard_id, connection)
+        })
+        .collect();
+    let res = start_writers::<P>(
         shard_id,
+        delivery_window,
         tcp_flush_interval,
         channel_buffer_size,
         id_to_connection_1,
+        to_metrics_logger,
     )
-    .await
+    .await;
+    Ok((peer_epochs, res))
 }
 
+// The hi sent to other processes is followed by the fingerprint of the
+// protocol messages (which is not sent to clients, as they don't exchange
+// protocol messages).
 async fn say_hi(
     process_id: ProcessId,
     shard_id: ShardId,
-    connections: &mut Vec<Connection>,
+    epoch: Epoch,
+    message_fingerprint: u64,
+    connections: &mut [Connection],
 ) {
     let hi = ProcessHi {
         process_id,
         shard_id,
+        epoch,
     };
     // send hi on each connection
     for connection in connections.iter_mut() {
         if let Err(e) = connection.send(&hi).await {
             warn!("error while sending hi to connection: {:?}", e)
         }
+        if let Err(e) = connection.send(&message_fingerprint).await {
+            warn!(
+                "error while sending message fingerprint to connection: {:?}",
+                e
+            )
+        }
     }
 }
 
 async fn receive_hi(
+    process_id: ProcessId,
+    message_fingerprint: u64,
     connections: Vec<Connection>,
-) -> Vec<(ProcessId, ShardId, Connection)> {
+) -> Result<Vec<(ProcessId, ShardId, Epoch, Connection)>, RunError> {
     let mut id_to_connection = Vec::with_capacity(connections.len());
 
     // receive hi from each connection
     for mut connection in connections {
         if let Some(ProcessHi {
-            process_id,
+            process_id: peer_id,
             shard_id,
+            epoch,
         }) = connection.recv().await
         {
-            id_to_connection.push((process_id, shard_id, connection));
+            check_message_fingerprint(
+                process_id,
+                peer_id,
+                message_fingerprint,
+                &mut connection,
+            )
+            .await?;
+            id_to_connection.push((peer_id, shard_id, epoch, connection));
         } else {
             panic!("error receiving hi");
         }
     }
-    id_to_connection
+    Ok(id_to_connection)
+}
+
+// Receives the fingerprint of the protocol messages sent by `peer_id` after
+// its hi, failing if it differs from ours: if the processes were built from
+// different versions of the protocol, the messages of one may not be properly
+// deserialized by the other.
+async fn check_message_fingerprint(
+    process_id: ProcessId,
+    peer_id: ProcessId,
+    message_fingerprint: u64,
+    connection: &mut Connection,
+) -> Result<(), RunError> {
+    match connection.recv::<u64>().await {
+        Some(peer_fingerprint) if peer_fingerprint == message_fingerprint => {
+            Ok(())
+        }
+        Some(peer_fingerprint) => Err(RunError::Serialization(format!(
+            "p{} has message fingerprint {:#018x} but p{} has {:#018x}; refusing to connect",
+            process_id, message_fingerprint, peer_id, peer_fingerprint
+        ))),
+        None => Err(RunError::Connection(format!(
+            "p{}: error receiving message fingerprint from p{}",
+            process_id, peer_id
+        ))),
+    }
 }
 
 /// Starts a reader task per connection received. A `ReaderToWorkers` is passed
@@ -208,16 +363,119 @@ async fn receive_hi(
 fn start_readers<P>(
     to_workers: ReaderToWorkers<P>,
     to_executors: ToExecutors<P>,
-    connections: Vec<(ProcessId, ShardId, Connection)>,
+    peer_epochs: &PeerEpochs,
+    delivery_window: Option<usize>,
+    connections: Vec<(ProcessId, ShardId, Epoch, Connection)>,
 ) where
     P: Protocol + 'static,
 {
-    for (process_id, shard_id, connection) in connections {
+    for (process_id, shard_id, epoch, connection) in connections {
         task::spawn(reader_task::<P>(
             to_workers.clone(),
             to_executors.clone(),
+            peer_epochs.clone(),
+            delivery_window,
+            process_id,
+            shard_id,
+            epoch,
+            connection,
+        ));
+    }
+}
+
+/// Handles the connections accepted after the handshake, which can only come
+/// from restarted peers. Connections announcing the latest epoch of a known
+/// peer are accepted (and, once a newer epoch is seen, messages from the
+/// previous incarnation are dropped by its readers); all other connections
+/// are rejected.
+async fn rejoin_task<P>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    epoch: Epoch,
+    mut from_listener: ChannelReceiver<Connection>,
+    peer_epochs: PeerEpochs,
+    to_workers: ReaderToWorkers<P>,
+    to_executors: ToExecutors<P>,
+    message_compression: Option<usize>,
+    delivery_window: Option<usize>,
+) where
+    P: Protocol + 'static,
+{
+    while let Some(mut connection) = from_listener.recv().await {
+        let (peer_id, peer_shard_id, peer_epoch) = if let Some(ProcessHi {
+            process_id,
+            shard_id,
+            epoch,
+        }) =
+            connection.recv().await
+        {
+            (process_id, shard_id, epoch)
+        } else {
+            warn!("p{}: error receiving hi from new connection", process_id);
+            continue;
+        };
+        if let Err(e) = check_message_fingerprint(
+            process_id,
+            peer_id,
+            P::message_fingerprint(),
+            &mut connection,
+        )
+        .await
+        {
+            warn!("p{}: rejecting connection: {}", process_id, e);
+            continue;
+        }
+
+        match peer_epochs.observe(peer_id, peer_epoch) {
+            Some(EpochChange::Restarted { previous }) => {
+                // TODO the restarted peer should be brought up to date with a
+                // state transfer, and new outgoing connections to it should
+                // replace the current ones
+                warn!(
+                    "p{}: p{} restarted (epoch {} -> {}); state transfer is not supported yet",
+                    process_id, peer_id, previous, peer_epoch
+                );
+            }
+            Some(EpochChange::Current) => {
+                // another connection from the latest incarnation (when
+                // multiplexing)
+            }
+            Some(EpochChange::Stale { current }) => {
+                warn!(
+                    "p{}: rejecting connection from p{} with stale epoch {} (current is {})",
+                    process_id, peer_id, peer_epoch, current
+                );
+                continue;
+            }
+            None => {
+                warn!(
+                    "p{}: rejecting connection from unknown process p{}",
+                    process_id, peer_id
+                );
+                continue;
+            }
+        }
+
+        // say hi back and start reading from the new connection
+        say_hi(
             process_id,
             shard_id,
+            epoch,
+            P::message_fingerprint(),
+            std::slice::from_mut(&mut connection),
+        )
+        .await;
+        if let Some(min_threshold) = message_compression {
+            connection.set_compression(min_threshold);
+        }
+        task::spawn(reader_task::<P>(
+            to_workers.clone(),
+            to_executors.clone(),
+            peer_epochs.clone(),
+            delivery_window,
+            peer_id,
+            peer_shard_id,
+            peer_epoch,
             connection,
         ));
     }
@@ -225,9 +483,11 @@ fn start_readers<P>(
 
 async fn start_writers<P>(
     shard_id: ShardId,
+    delivery_window: Option<usize>,
     tcp_flush_interval: Option<Duration>,
     channel_buffer_size: usize,
     connections: Vec<(ProcessId, ShardId, Connection)>,
+    to_metrics_logger: Option<RwMetricsSender>,
 ) -> (
     HashMap<ProcessId, (ShardId, IpAddr, Option<Duration>)>,
     HashMap<ProcessId, Vec<WriterSender<P>>>,
@@ -240,7 +500,9 @@ where
     let mut writers = HashMap::with_capacity(connections.len());
 
     // start on writer task per connection
-    for (peer_id, peer_shard_id, connection) in connections {
+    for (writer_index, (peer_id, peer_shard_id, connection)) in
+        connections.into_iter().enumerate()
+    {
         // save shard id, ip and connection delay
         let ip = connection
             .ip_addr()
@@ -253,7 +515,8 @@ where
 
         // get list set of writers to this process and create writer channels
         let txs = writers.entry(peer_id).or_insert_with(Vec::new);
-        let (mut writer_tx, writer_rx) = chan::channel(channel_buffer_size);
+        let (mut writer_tx, writer_rx) =
+            chan::priority_channel(channel_buffer_size);
 
         // name the channel accordingly
         writer_tx.set_name(format!(
@@ -272,14 +535,20 @@ where
 
         // spawn the writer task
         task::spawn(writer_task::<P>(
+            writer_index,
+            delivery_window,
             tcp_flush_interval,
             connection,
             writer_rx,
+            to_metrics_logger.clone(),
         ));
 
         let tx = if let Some(delay) = connection_delay {
-            // if connection has a delay, spawn a delay task for this writer
-            let (mut delay_tx, delay_rx) = chan::channel(channel_buffer_size);
+            // if connection has a delay, spawn a delay task for this writer;
+            // there's one delay task per priority class so that high priority
+            // messages are not delayed behind low priority ones
+            let (mut delay_tx, delay_rx) =
+                chan::priority_channel(channel_buffer_size);
 
             // name the channel accordingly
             delay_tx.set_name(format!(
@@ -288,8 +557,15 @@ where
                 peer_id
             ));
 
-            // spawn delay task
-            task::spawn(delay::delay_task(delay_rx, writer_tx, delay));
+            // spawn delay tasks
+            let (writer_high_tx, writer_low_tx) = writer_tx.into_senders();
+            let (delay_high_rx, delay_low_rx) = delay_rx.into_receivers();
+            task::spawn(delay::delay_task(
+                delay_high_rx,
+                writer_high_tx,
+                delay,
+            ));
+            task::spawn(delay::delay_task(delay_low_rx, writer_low_tx, delay));
 
             // in this case, messages are first forward to the delay task, which
             // then forwards them to the writer task
@@ -311,14 +587,32 @@ where
 async fn reader_task<P>(
     mut reader_to_workers: ReaderToWorkers<P>,
     mut to_executors: ToExecutors<P>,
+    peer_epochs: PeerEpochs,
+    delivery_window: Option<usize>,
     process_id: ProcessId,
     shard_id: ShardId,
+    epoch: Epoch,
     mut connection: Connection,
 ) where
     P: Protocol + 'static,
 {
+    let mut inbox = delivery_window.map(Inbox::new);
     loop {
-        match connection.recv::<POEMessage<P>>().await {
+        let msg = match recv_msg::<P>(&mut connection, &mut inbox).await {
+            Some(Some(msg)) => Some(msg),
+            // skip duplicates
+            Some(None) => continue,
+            None => None,
+        };
+        // drop messages from old incarnations of the peer
+        if peer_epochs.is_stale(process_id, epoch) {
+            warn!(
+                "[reader] closing connection from p{} with stale epoch {}",
+                process_id, epoch
+            );
+            break;
+        }
+        match msg {
             Some(msg) => match msg {
                 POEMessage::Protocol(msg) => {
                     let forward = reader_to_workers
@@ -346,12 +640,18 @@ async fn reader_task<P>(
 
 /// Writer task.
 async fn writer_task<P>(
+    writer_index: usize,
+    delivery_window: Option<usize>,
     tcp_flush_interval: Option<Duration>,
     mut connection: Connection,
     mut parent: WriterReceiver<P>,
+    mut to_metrics_logger: Option<RwMetricsSender>,
 ) where
     P: Protocol + 'static,
 {
+    let mut outbox = delivery_window.map(Outbox::new);
+    // track when metrics were last sent to the metrics logger
+    let mut metrics_sent = time::Instant::now();
     // track whether there's been a flush error on this connection
     let mut flush_error = false;
     // if flush interval higher than 0, then flush periodically; otherwise,
@@ -364,9 +664,10 @@ async fn writer_task<P>(
                 msg = parent.recv() => {
                     if let Some(msg) = msg {
                         // connection write *doesn't* flush
-                        if let Err(e) = connection.write(&*msg).await {
+                        if let Err(e) = write_msg(&mut connection, &mut outbox, msg, false).await {
                             warn!("[writer] error writing message in connection: {:?}", e);
                         }
+                        maybe_send_metrics(writer_index, &connection, &mut to_metrics_logger, &mut metrics_sent).await;
                     } else {
                         warn!("[writer] error receiving message from parent");
                         break;
@@ -388,12 +689,21 @@ async fn writer_task<P>(
         loop {
             if let Some(msg) = parent.recv().await {
                 // connection write *does* flush
-                if let Err(e) = connection.send(&*msg).await {
+                if let Err(e) =
+                    write_msg(&mut connection, &mut outbox, msg, true).await
+                {
                     warn!(
                         "[writer] error sending message to connection: {:?}",
                         e
                     );
                 }
+                maybe_send_metrics(
+                    writer_index,
+                    &connection,
+                    &mut to_metrics_logger,
+                    &mut metrics_sent,
+                )
+                .await;
             } else {
                 warn!("[writer] error receiving message from parent");
                 break;
@@ -402,3 +712,121 @@ async fn writer_task<P>(
     }
     warn!("[writer] exiting after failure");
 }
+
+/// Receives a message from the connection. If messages are delivered at least
+/// once, duplicates are returned as `Some(None)`, and acknowledgments are sent
+/// back to the peer as messages are delivered.
+async fn recv_msg<P>(
+    connection: &mut Connection,
+    inbox: &mut Option<Inbox>,
+) -> Option<Option<POEMessage<P>>>
+where
+    P: Protocol,
+{
+    let inbox = if let Some(inbox) = inbox.as_mut() {
+        inbox
+    } else {
+        return connection.recv::<POEMessage<P>>().await.map(Some);
+    };
+    let (seq, msg) = connection.recv::<(Seq, POEMessage<P>)>().await?;
+    if !inbox.receive(seq) {
+        trace!("[reader] dropping duplicate message {}", seq);
+        return Some(None);
+    }
+    if let Some(ack) = inbox.to_ack() {
+        if let Err(e) = connection.send(&ack).await {
+            warn!("[reader] error sending ack to connection: {:?}", e);
+        }
+    }
+    Some(Some(msg))
+}
+
+/// Writes a message to the connection, flushing it if `flush` is set. If
+/// messages are delivered at least once, the message is sequenced and kept
+/// until acknowledged; if the delivery window is full, this waits for an
+/// acknowledgment from the peer (after flushing the messages written so far,
+/// as otherwise the peer may never acknowledge them).
+async fn write_msg<P>(
+    connection: &mut Connection,
+    outbox: &mut Option<Outbox<Arc<POEMessage<P>>>>,
+    msg: Arc<POEMessage<P>>,
+    flush: bool,
+) -> Result<(), Report>
+where
+    P: Protocol,
+{
+    let msg_type = message_type(connection, &msg);
+    let outbox = if let Some(outbox) = outbox.as_mut() {
+        outbox
+    } else if flush {
+        return connection.send_typed(&*msg, msg_type).await;
+    } else {
+        return connection.write_typed(&*msg, msg_type).await;
+    };
+    if outbox.is_full() {
+        connection.flush().await?;
+        let ack = connection.recv::<Seq>().await.ok_or_else(|| {
+            RunError::Connection(String::from(
+                "error receiving ack from connection",
+            ))
+        })?;
+        outbox.ack(ack);
+    }
+    let seq = outbox.push(msg.clone());
+    if flush {
+        connection.send_typed(&(seq, &*msg), msg_type).await
+    } else {
+        connection.write_typed(&(seq, &*msg), msg_type).await
+    }
+}
+
+/// Returns the type of a message, if it's needed to report compression
+/// metrics.
+fn message_type<P>(
+    connection: &Connection,
+    msg: &POEMessage<P>,
+) -> Option<MessageType>
+where
+    P: Protocol,
+{
+    if connection.compression() {
+        Some(msg.message_type())
+    } else {
+        None
+    }
+}
+
+/// Sends the connection metrics to the metrics logger (if any), at most once
+/// every `METRICS_INTERVAL`.
+async fn maybe_send_metrics(
+    writer_index: usize,
+    connection: &Connection,
+    to_metrics_logger: &mut Option<RwMetricsSender>,
+    metrics_sent: &mut time::Instant,
+) {
+    if let Some(to_metrics_logger) = to_metrics_logger.as_mut() {
+        if metrics_sent.elapsed() >= metrics_logger::METRICS_INTERVAL {
+            *metrics_sent = time::Instant::now();
+            let metrics = connection.metrics().clone();
+            if let Err(e) =
+                to_metrics_logger.send((writer_index, metrics)).await
+            {
+                warn!("[writer] error sending metrics to logger: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Logs a violation reported by a protocol (or executor), along with the state
+/// dumped by it. Unless processes should be kept alive on violations (see
+/// `Config::keep_alive_on_violation`), this reports the run as failed and
+/// panics.
+fn report_violation(tag: &'static str, violation: Violation, keep_alive: bool) {
+    warn!("[{}] violation {}", tag, violation);
+    if !keep_alive {
+        let msg = format!("[{}] violation {}", tag, violation);
+        let report =
+            RunError::log_failure(RunError::ProtocolViolation(msg).into());
+        panic!("{}", report);
+    }
+}
@@ -1,17 +1,22 @@
 use super::execution_logger;
-use crate::command::Command;
-use crate::id::{Dot, ProcessId, ShardId};
+use super::metrics_logger;
+use crate::id::{ProcessId, ShardId};
 use crate::protocol::{Action, CommittedAndExecuted, Protocol};
 use crate::run::prelude::*;
 use crate::run::task;
 use crate::time::RunTime;
 use crate::HashMap;
 use crate::{trace, warn};
-use rand::Rng;
+use futures::FutureExt;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 use std::fmt::Debug;
 use std::sync::Arc;
 use tokio::task::JoinHandle;
-use tokio::time;
+
+// Writers of each process, in the order in which messages are sent to them.
+type ProcessWriters<P> = Vec<(ProcessId, Vec<WriterSender<P>>)>;
 
 /// Starts process workers.
 pub fn start_processes<P, R>(
@@ -21,11 +26,15 @@ pub fn start_processes<P, R>(
     periodic_to_workers_rxs: Vec<PeriodicEventReceiver<P, R>>,
     executors_to_workers_rxs: Vec<ExecutedReceiver>,
     to_writers: HashMap<ProcessId, Vec<WriterSender<P>>>,
+    sorted_processes: Vec<(ProcessId, ShardId)>,
+    farthest_first: bool,
     reader_to_workers: ReaderToWorkers<P>,
     to_executors: ToExecutors<P>,
     process_channel_buffer_size: usize,
     execution_log: Option<String>,
-    to_metrics_logger: Option<ProtocolMetricsSender>,
+    to_metrics_logger: Option<ProtocolMetricsReporter>,
+    polling_seed: Option<u64>,
+    keep_alive_on_violation: bool,
 ) -> Vec<JoinHandle<()>>
 where
     P: Protocol + Send + 'static,
@@ -40,6 +49,9 @@ where
         tx
     });
 
+    let to_writers =
+        order_writers(to_writers, &sorted_processes, farthest_first);
+
     // zip rxs'
     let incoming = reader_to_workers_rxs
         .into_iter()
@@ -68,6 +80,8 @@ where
                     to_executors.clone(),
                     to_execution_logger.clone(),
                     to_metrics_logger.clone(),
+                    polling_seed,
+                    keep_alive_on_violation,
                 );
                 task::spawn(task)
                 // // if this is a reserved worker, run it on its own runtime
@@ -93,6 +107,39 @@ where
         .collect()
 }
 
+// Orders the writers of each process. If `farthest_first` is set, writers are
+// ordered from the farthest to the closest process (given that
+// `sorted_processes` are sorted by distance), so that messages sent to a quorum
+// reach its slowest members as early as possible.
+fn order_writers<P>(
+    to_writers: HashMap<ProcessId, Vec<WriterSender<P>>>,
+    sorted_processes: &[(ProcessId, ShardId)],
+    farthest_first: bool,
+) -> ProcessWriters<P>
+where
+    P: Protocol,
+{
+    let mut to_writers: Vec<_> = to_writers.into_iter().collect();
+    if farthest_first {
+        to_writers.sort_by_key(|(to, _)| {
+            let distance = sorted_processes
+                .iter()
+                .position(|(process_id, _)| process_id == to);
+            std::cmp::Reverse(distance)
+        });
+    }
+    to_writers
+}
+
+// Message received by a process worker from one of its incoming channels.
+enum Incoming<P: Protocol, R> {
+    FromProcesses(Option<(ProcessId, ShardId, P::Message)>),
+    FromPeriodic(Option<FromPeriodicMessage<P, R>>),
+    FromExecutors(Option<CommittedAndExecuted>),
+    FromClients(Option<ClientToWorker>),
+    MetricsSnapshot(u64),
+}
+
 async fn process_task<P, R>(
     worker_index: usize,
     mut process: P,
@@ -100,11 +147,13 @@ async fn process_task<P, R>(
     mut from_clients: SubmitReceiver,
     mut from_periodic: PeriodicEventReceiver<P, R>,
     mut from_executors: ExecutedReceiver,
-    mut to_writers: HashMap<ProcessId, Vec<WriterSender<P>>>,
+    mut to_writers: ProcessWriters<P>,
     mut reader_to_workers: ReaderToWorkers<P>,
     mut to_executors: ToExecutors<P>,
     mut to_execution_logger: Option<ExecutionInfoSender<P>>,
-    mut to_metrics_logger: Option<ProtocolMetricsSender>,
+    mut to_metrics_logger: Option<ProtocolMetricsReporter>,
+    polling_seed: Option<u64>,
+    keep_alive_on_violation: bool,
 ) where
     P: Protocol + 'static,
     R: Debug + 'static,
@@ -112,42 +161,150 @@ async fn process_task<P, R>(
     // create time
     let time = RunTime;
 
-    // create interval (for metrics notification)
-    let mut interval = time::interval(super::metrics_logger::METRICS_INTERVAL);
+    // create rng that decides the polling order (in case there's a seed);
+    // each worker gets a different seed
+    let mut polling_rng = polling_seed
+        .map(|seed| StdRng::seed_from_u64(seed + worker_index as u64));
 
     loop {
-        // TODO maybe used select_biased
-        tokio::select! {
-            msg = from_readers.recv() => {
-                selected_from_processes(worker_index, msg, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await
+        // if there's a polling rng, first try to receive a message without
+        // waiting; only if there's none we wait for the first one to arrive
+        let seeded = polling_rng.as_mut().and_then(|rng| {
+            seeded_recv(
+                rng,
+                &mut from_readers,
+                &mut from_clients,
+                &mut from_periodic,
+                &mut from_executors,
+            )
+        });
+        let incoming = match seeded {
+            Some(incoming) => incoming,
+            // TODO maybe used select_biased
+            None => tokio::select! {
+                msg = from_readers.recv() => Incoming::FromProcesses(msg),
+                event = from_periodic.recv() => Incoming::FromPeriodic(event),
+                executed = from_executors.recv() => Incoming::FromExecutors(executed),
+                cmd = from_clients.recv() => Incoming::FromClients(cmd),
+                version = metrics_logger::snapshot_requested(&mut to_metrics_logger) => Incoming::MetricsSnapshot(version),
+            },
+        };
+
+        match incoming {
+            Incoming::FromProcesses(msg) => {
+                selected_from_processes(
+                    worker_index,
+                    msg,
+                    &mut process,
+                    &mut to_writers,
+                    &mut reader_to_workers,
+                    &mut to_executors,
+                    &mut to_execution_logger,
+                    &time,
+                )
+                .await
             }
-            event = from_periodic.recv() => {
-                selected_from_periodic_task(worker_index, event, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await
+            Incoming::FromPeriodic(event) => {
+                selected_from_periodic_task(
+                    worker_index,
+                    event,
+                    &mut process,
+                    &mut to_writers,
+                    &mut reader_to_workers,
+                    &mut to_executors,
+                    &mut to_execution_logger,
+                    &time,
+                )
+                .await
             }
-            executed = from_executors.recv() => {
-                selected_from_executors(worker_index, executed, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await
+            Incoming::FromExecutors(executed) => {
+                selected_from_executors(
+                    worker_index,
+                    executed,
+                    &mut process,
+                    &mut to_writers,
+                    &mut reader_to_workers,
+                    &mut to_executors,
+                    &mut to_execution_logger,
+                    &time,
+                )
+                .await
             }
-            cmd = from_clients.recv() => {
-                selected_from_clients(worker_index, cmd, &mut process, &mut to_writers, &mut reader_to_workers, &mut to_executors, &mut to_execution_logger, &time).await
+            Incoming::FromClients(cmd) => {
+                selected_from_clients(
+                    worker_index,
+                    cmd,
+                    &mut process,
+                    &mut to_writers,
+                    &mut reader_to_workers,
+                    &mut to_executors,
+                    &mut to_execution_logger,
+                    &time,
+                )
+                .await
             }
-            _ = interval.tick()  => {
+            Incoming::MetricsSnapshot(version) => {
                 if let Some(to_metrics_logger) = to_metrics_logger.as_mut() {
                     // send metrics to logger (in case there's one)
                     let protocol_metrics = process.metrics().clone();
-                    if let Err(e) = to_metrics_logger.send((worker_index, protocol_metrics)).await {
-                        warn!("[server] error while sending metrics to metrics logger: {:?}", e);
-                    }
+                    to_metrics_logger
+                        .report(worker_index, version, protocol_metrics)
+                        .await;
                 }
             }
         }
+
+        // report the violations recorded while handling the message (if any)
+        while let Some(violation) = process.to_violations() {
+            super::report_violation(
+                "server",
+                violation,
+                keep_alive_on_violation,
+            );
+        }
     }
 }
 
+// Tries to receive a message (without waiting) from one of the incoming
+// channels, polling them in an order picked by `rng`.
+fn seeded_recv<P, R>(
+    rng: &mut StdRng,
+    from_readers: &mut ReaderReceiver<P>,
+    from_clients: &mut SubmitReceiver,
+    from_periodic: &mut PeriodicEventReceiver<P, R>,
+    from_executors: &mut ExecutedReceiver,
+) -> Option<Incoming<P, R>>
+where
+    P: Protocol + 'static,
+    R: Debug + 'static,
+{
+    let mut channels = [0, 1, 2, 3];
+    channels.shuffle(rng);
+    channels.iter().find_map(|channel| match channel {
+        0 => from_readers
+            .recv()
+            .now_or_never()
+            .map(Incoming::FromProcesses),
+        1 => from_periodic
+            .recv()
+            .now_or_never()
+            .map(Incoming::FromPeriodic),
+        2 => from_executors
+            .recv()
+            .now_or_never()
+            .map(Incoming::FromExecutors),
+        _ => from_clients
+            .recv()
+            .now_or_never()
+            .map(Incoming::FromClients),
+    })
+}
+
 async fn selected_from_processes<P>(
     worker_index: usize,
     msg: Option<(ProcessId, ShardId, P::Message)>,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -183,7 +340,7 @@ async fn handle_from_processes<P>(
     from_shard_id: ShardId,
     msg: P::Message,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -209,7 +366,7 @@ async fn handle_from_processes<P>(
 async fn send_to_processes_and_executors<P>(
     worker_index: usize,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -236,6 +393,8 @@ async fn send_to_processes_and_executors<P>(
                 // prevent unnecessary cloning of messages, since send only
                 // requires a reference to the message
                 let msg_to_send = Arc::new(POEMessage::Protocol(msg));
+                // compute message priority only once
+                let high_priority = msg_to_send.high_priority();
 
                 // send message to writers in target
                 for (to, channels) in to_writers.iter_mut() {
@@ -243,6 +402,7 @@ async fn send_to_processes_and_executors<P>(
                         send_to_one_writer::<P>(
                             "server",
                             msg_to_send.clone(),
+                            high_priority,
                             channels,
                         )
                         .await
@@ -309,6 +469,7 @@ async fn handle_message_from_self<P>(
 pub async fn send_to_one_writer<P>(
     tag: &'static str,
     msg: Arc<POEMessage<P>>,
+    high_priority: bool,
     writers: &mut Vec<WriterSender<P>>,
 ) where
     P: Protocol + 'static,
@@ -316,7 +477,7 @@ pub async fn send_to_one_writer<P>(
     // pick a random one
     let writer_index = rand::thread_rng().gen_range(0..writers.len());
 
-    if let Err(e) = writers[writer_index].send(msg).await {
+    if let Err(e) = writers[writer_index].send(msg, high_priority).await {
         warn!(
             "[{}] error while sending to writer {}: {:?}",
             tag, writer_index, e
@@ -326,9 +487,9 @@ pub async fn send_to_one_writer<P>(
 
 async fn selected_from_clients<P>(
     worker_index: usize,
-    cmd: Option<(Option<Dot>, Command)>,
+    cmd: Option<ClientToWorker>,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -337,10 +498,9 @@ async fn selected_from_clients<P>(
     P: Protocol + 'static,
 {
     trace!("[server] from clients: {:?}", cmd);
-    if let Some((dot, cmd)) = cmd {
+    if let Some(cmd) = cmd {
         handle_from_clients(
             worker_index,
-            dot,
             cmd,
             process,
             to_writers,
@@ -357,10 +517,9 @@ async fn selected_from_clients<P>(
 
 async fn handle_from_clients<P>(
     worker_index: usize,
-    dot: Option<Dot>,
-    cmd: Command,
+    cmd: ClientToWorker,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -368,8 +527,16 @@ async fn handle_from_clients<P>(
 ) where
     P: Protocol + 'static,
 {
-    // submit command in process
-    process.submit(dot, cmd, time);
+    match cmd {
+        ClientToWorker::Submit(dot, cmd) => {
+            // submit command in process
+            process.submit(dot, cmd, time);
+        }
+        ClientToWorker::Cancel(dot) => {
+            // cancel command in process
+            process.cancel(dot, time);
+        }
+    }
     send_to_processes_and_executors(
         worker_index,
         process,
@@ -386,7 +553,7 @@ async fn selected_from_periodic_task<P, R>(
     worker_index: usize,
     event: Option<FromPeriodicMessage<P, R>>,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -417,7 +584,7 @@ async fn handle_from_periodic_task<P, R>(
     worker_index: usize,
     msg: FromPeriodicMessage<P, R>,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -443,7 +610,7 @@ async fn handle_from_periodic_task<P, R>(
         }
         FromPeriodicMessage::Inspect(f, mut tx) => {
             let outcome = f(&process);
-            if let Err(e) = tx.send(outcome).await {
+            if let Err(e) = tx.send((worker_index, outcome)).await {
                 warn!("[server] error while sending inspect result: {:?}", e);
             }
         }
@@ -454,7 +621,7 @@ async fn selected_from_executors<P>(
     worker_index: usize,
     committed_and_executed: Option<CommittedAndExecuted>,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -484,7 +651,7 @@ async fn handle_from_executors<P>(
     worker_index: usize,
     committed_and_executed: CommittedAndExecuted,
     process: &mut P,
-    to_writers: &mut HashMap<ProcessId, Vec<WriterSender<P>>>,
+    to_writers: &mut ProcessWriters<P>,
     reader_to_workers: &mut ReaderToWorkers<P>,
     to_executors: &mut ToExecutors<P>,
     to_execution_logger: &mut Option<ExecutionInfoSender<P>>,
@@ -1,8 +1,14 @@
+use crate::error::RunError;
 use crate::id::ClientId;
 use color_eyre::Report;
+use serde::de::DeserializeOwned;
 use serde::Serialize;
 use tokio::time::{Duration, Instant};
 
+// suffix of the temporary files where data is serialized to before being
+// renamed
+const TMP_FILE_SUFFIX: &str = "_tmp";
+
 pub fn deadline(delay: Duration) -> Instant {
     Instant::now()
         .checked_add(delay)
@@ -21,23 +27,53 @@ pub fn serialize_and_compress<T: Serialize>(
     data: &T,
     file: &str,
 ) -> Result<(), Report> {
+    let error = |e: &dyn std::fmt::Display| {
+        let msg = format!("save serialized data in file {:?}: {}", file, e);
+        RunError::Serialization(msg)
+    };
+    // first serialize to a temporary file, and then rename it: since renames
+    // are atomic, a process killed in the middle of this never leaves a
+    // half-written file behind
+    let tmp = format!("{}{}", file, TMP_FILE_SUFFIX);
     // if the file does not exist it will be created, otherwise truncated
-    std::fs::File::create(file)
-        .ok()
-        // create a buf writer
-        .map(std::io::BufWriter::new)
-        // compress using gzip
-        .map(|buffer| {
-            flate2::write::GzEncoder::new(buffer, flate2::Compression::best())
-        })
-        // and try to serialize
-        .map(|writer| {
-            bincode::serialize_into(writer, data)
-                .expect("error serializing data")
-        })
-        .unwrap_or_else(|| {
-            panic!("couldn't save serialized data in file {:?}", file)
-        });
-
+    let writer = std::fs::File::create(&tmp).map_err(|e| error(&e))?;
+    // create a buf writer
+    let buffer = std::io::BufWriter::new(writer);
+    // compress using gzip
+    let mut encoder =
+        flate2::write::GzEncoder::new(buffer, flate2::Compression::best());
+    // and try to serialize
+    bincode::serialize_into(&mut encoder, data).map_err(|e| error(&e))?;
+    // make sure everything reaches the disk before renaming
+    let buffer = encoder.finish().map_err(|e| error(&e))?;
+    let writer = buffer.into_inner().map_err(|e| error(&e))?;
+    writer.sync_all().map_err(|e| error(&e))?;
+    std::fs::rename(&tmp, file).map_err(|e| error(&e))?;
+    sync_parent_dir(file).map_err(|e| error(&e))?;
     Ok(())
 }
+
+// Syncs the directory containing `file`, so that its renaming is persisted.
+fn sync_parent_dir(file: &str) -> std::io::Result<()> {
+    let parent = std::path::Path::new(file)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| std::path::Path::new("."));
+    std::fs::File::open(parent)?.sync_all()
+}
+
+pub fn decompress_and_deserialize<T: DeserializeOwned>(
+    file: &str,
+) -> Result<T, Report> {
+    let error = |e: &dyn std::fmt::Display| {
+        let msg = format!("load serialized data from file {:?}: {}", file, e);
+        RunError::Serialization(msg)
+    };
+    let file = std::fs::File::open(file).map_err(|e| error(&e))?;
+    // create a buf reader
+    let buffer = std::io::BufReader::new(file);
+    // decompress using gzip and try to deserialize
+    let reader = flate2::read::GzDecoder::new(buffer);
+    let data = bincode::deserialize_from(reader).map_err(|e| error(&e))?;
+    Ok(data)
+}
@@ -0,0 +1,147 @@
+use crate::client::Workload;
+use crate::command::Command;
+use crate::id::{ClientId, Rifl, RiflGen, ShardId};
+use crate::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// A command submitted by a client at some point of the simulation (see
+/// `Runner::replay`).
+#[derive(Debug, Clone)]
+pub struct Submission {
+    /// simulation time at which the command is submitted by its client
+    pub at: Duration,
+    pub client_id: ClientId,
+    /// shard to which the command is submitted
+    pub target_shard: ShardId,
+    pub cmd: Command,
+}
+
+/// Generates the submissions of `client_count` clients running `workload` in
+/// open loop, i.e. each client submits a new command every `interval`,
+/// regardless of whether previous commands have completed. Clients are
+/// identified from 1 to `client_count`, as in `Runner::new`.
+///
+/// Since these submissions don't depend on the protocol being simulated, they
+/// can be replayed with several protocols in order to compare them command by
+/// command (see `LatencyDifferences`).
+pub fn open_loop_submissions(
+    workload: Workload,
+    client_count: usize,
+    interval: Duration,
+) -> Vec<Submission> {
+    let mut submissions = Vec::new();
+    for client_id in 1..=client_count as ClientId {
+        let mut workload = workload;
+        let mut rifl_gen = RiflGen::new(client_id);
+        let mut key_gen_state = workload.key_gen().initial_state(
+            workload.shard_count(),
+            client_id,
+            workload.seed(),
+        );
+        let mut at = Duration::from_secs(0);
+        while let Some((target_shard, mut cmd)) =
+            workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
+        {
+            cmd.set_timestamp(at.as_millis() as u64);
+            submissions.push(Submission {
+                at,
+                client_id,
+                target_shard,
+                cmd,
+            });
+            at += interval;
+        }
+    }
+    // sort submissions by time; since the sort is stable, submissions at the
+    // same time are sorted by client
+    submissions.sort_by_key(|submission| submission.at);
+    submissions
+}
+
+/// Distribution of the per-command latency differences between two
+/// simulations in which the same submissions were replayed (e.g. with
+/// different protocols).
+#[derive(Clone, PartialEq, Eq)]
+pub struct LatencyDifferences {
+    // for each command completed in both simulations, its latency in the
+    // second simulation minus its latency in the first one (in millis); these
+    // are kept sorted
+    diffs: Vec<i64>,
+}
+
+impl LatencyDifferences {
+    /// Computes the latency differences between the latencies in `other` and
+    /// the ones in `baseline` (as returned by `Runner::replay`). Commands that
+    /// only completed in one of the simulations are ignored.
+    pub fn new(
+        baseline: &HashMap<Rifl, Duration>,
+        other: &HashMap<Rifl, Duration>,
+    ) -> Self {
+        let mut diffs: Vec<_> = baseline
+            .iter()
+            .filter_map(|(rifl, baseline_latency)| {
+                other.get(rifl).map(|other_latency| {
+                    other_latency.as_millis() as i64
+                        - baseline_latency.as_millis() as i64
+                })
+            })
+            .collect();
+        diffs.sort_unstable();
+        Self { diffs }
+    }
+
+    /// Returns the number of commands compared.
+    pub fn count(&self) -> usize {
+        self.diffs.len()
+    }
+
+    /// Returns the number of commands that were faster in the second
+    /// simulation.
+    pub fn faster(&self) -> usize {
+        self.diffs.iter().filter(|diff| **diff < 0).count()
+    }
+
+    /// Returns the number of commands that were slower in the second
+    /// simulation.
+    pub fn slower(&self) -> usize {
+        self.diffs.iter().filter(|diff| **diff > 0).count()
+    }
+
+    /// Returns the mean latency difference (in millis).
+    pub fn mean(&self) -> f64 {
+        if self.diffs.is_empty() {
+            return 0.0;
+        }
+        self.diffs.iter().sum::<i64>() as f64 / self.diffs.len() as f64
+    }
+
+    /// Returns the latency difference (in millis) at some percentile, with
+    /// `percentile` between 0 and 1.
+    /// It panics if no command was compared.
+    pub fn percentile(&self, percentile: f64) -> i64 {
+        assert!((0.0..=1.0).contains(&percentile));
+        assert!(!self.diffs.is_empty(), "no command was compared");
+        let index = ((self.diffs.len() - 1) as f64 * percentile).round();
+        self.diffs[index as usize]
+    }
+}
+
+impl fmt::Debug for LatencyDifferences {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.diffs.is_empty() {
+            return write!(f, "count=0");
+        }
+        write!(
+            f,
+            "count={} faster={} slower={} avg={:.1} p1={} p50={} p99={}",
+            self.count(),
+            self.faster(),
+            self.slower(),
+            self.mean(),
+            self.percentile(0.01),
+            self.percentile(0.5),
+            self.percentile(0.99)
+        )
+    }
+}
@@ -0,0 +1,148 @@
+use crate::id::{Dot, ProcessId, ShardId};
+use crate::HashMap;
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+// Processes in a simulation "gossip" what they have committed through a
+// registry shared by the simulation, which allows us to check that they all
+// agree on the committed values. Since processes can be handled by different
+// threads (see `Runner::step_parallel`), each thread keeps track of the
+// registry of the simulation it's currently handling.
+thread_local! {
+    static CURRENT: RefCell<Option<Registry>> = RefCell::new(None);
+}
+
+// mapping from each dot (in some shard) to the first process that committed it
+// along with the value committed
+type Committed = HashMap<(ShardId, Dot), (ProcessId, Box<dyn Any + Send>)>;
+
+/// Registry of the values committed by the processes of a simulation.
+#[derive(Clone, Default)]
+pub struct Registry {
+    committed: Arc<Mutex<Committed>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+/// Starts recording committed values in the current thread into a new
+/// registry, discarding the ones recorded until now. The registry is returned
+/// so that it can be shared with other threads (see `with`).
+pub fn start() -> Registry {
+    let registry = Registry::new();
+    CURRENT.with(|current| {
+        *current.borrow_mut() = Some(registry.clone());
+    });
+    registry
+}
+
+/// Runs `f` recording committed values into `registry` in the current thread.
+/// Once `f` returns, the registry used before (if any) is restored.
+pub fn with<F, R>(registry: &Registry, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let previous =
+        CURRENT.with(|current| current.replace(Some(registry.clone())));
+    let result = f();
+    CURRENT.with(|current| {
+        *current.borrow_mut() = previous;
+    });
+    result
+}
+
+/// Records that `process_id` committed `value` (e.g. a clock or a set of
+/// dependencies) for `dot` in `shard_id`, and panics if some other process
+/// committed a different value for the same dot. If recording hasn't been
+/// started in the current thread (which is the case outside the simulator),
+/// nothing is done.
+pub fn committed<V>(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    dot: Dot,
+    value: &V,
+) where
+    V: PartialEq + Debug + Clone + Send + 'static,
+{
+    let registry = CURRENT.with(|current| current.borrow().clone());
+    if let Some(registry) = registry {
+        let mut committed = registry
+            .committed
+            .lock()
+            .expect("locking the committed registry should work");
+        let (first_process_id, first_value) = committed
+            .entry((shard_id, dot))
+            .or_insert_with(|| (process_id, Box::new(value.clone())));
+        let first_value = first_value
+            .downcast_ref::<V>()
+            .expect("all values committed should have the same type");
+        assert_eq!(
+            first_value, value,
+            "agreement violation on {:?} at shard {}: p{} committed {:?} but p{} committed {:?}",
+            dot, shard_id, first_process_id, first_value, process_id, value
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_agreement() {
+        let dot_a = Dot::new(1, 1);
+        let dot_b = Dot::new(1, 2);
+
+        // nothing is checked before recording starts
+        committed(1, 0, dot_a, &10u64);
+        committed(2, 0, dot_a, &11u64);
+
+        start();
+        committed(1, 0, dot_a, &10u64);
+        committed(2, 0, dot_a, &10u64);
+        // the same dot can have different values at different shards
+        committed(4, 1, dot_a, &11u64);
+        committed(3, 0, dot_b, &12u64);
+    }
+
+    #[test]
+    #[should_panic]
+    fn committed_agreement_violation() {
+        let dot = Dot::new(1, 1);
+        start();
+        committed(1, 0, dot, &10u64);
+        committed(2, 0, dot, &11u64);
+    }
+
+    #[test]
+    fn committed_agreement_violation_across_threads() {
+        let dot = Dot::new(1, 1);
+        let registry = start();
+        committed(1, 0, dot, &10u64);
+
+        // the value committed by another thread with the same registry is
+        // also checked
+        let handle = std::thread::spawn(move || {
+            with(&registry, || committed(2, 0, dot, &11u64))
+        });
+        assert!(handle.join().is_err());
+    }
+
+    #[test]
+    fn with_restores_registry() {
+        let dot = Dot::new(1, 1);
+        start();
+        committed(1, 0, dot, &10u64);
+
+        // values committed within `with` are recorded in the given registry
+        with(&Registry::new(), || committed(2, 0, dot, &11u64));
+
+        // and the previous registry is restored afterwards
+        committed(3, 0, dot, &10u64);
+    }
+}
@@ -7,7 +7,17 @@ pub mod schedule;
 // This module contains the definition of `Runner`.
 pub mod runner;
 
+// This module contains the definitions of `Submission` and
+// `LatencyDifferences`, used to compare protocols command by command.
+pub mod comparison;
+
+// This module contains the runtime checks of invariants that span several
+// processes, enabled with the `strict-invariants` feature.
+#[cfg(feature = "strict-invariants")]
+pub mod invariants;
+
 // Re-exports.
+pub use comparison::{open_loop_submissions, LatencyDifferences, Submission};
 pub use runner::Runner;
 pub use schedule::Schedule;
-pub use simulation::Simulation;
+pub use simulation::{Simulation, SimulationSnapshot};
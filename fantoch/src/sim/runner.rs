@@ -1,21 +1,27 @@
 use crate::client::{Client, Workload};
 use crate::command::{Command, CommandResult, DEFAULT_SHARD_ID};
 use crate::config::Config;
-use crate::executor::{ExecutionOrderMonitor, Executor, ExecutorMetrics};
-use crate::id::{ClientId, ProcessId, ShardId};
+use crate::executor::{
+    AggregatePending, ExecutionOrderMonitor, Executor, ExecutorMetrics,
+};
+use crate::id::{ClientId, ProcessId, Rifl, ShardId};
 use crate::metrics::Histogram;
 use crate::planet::{Planet, Region};
 use crate::protocol::{Action, Protocol, ProtocolMetrics};
-use crate::sim::{Schedule, Simulation};
-use crate::time::SysTime;
+use crate::sim::{Schedule, Simulation, SimulationSnapshot, Submission};
+use crate::time::{SimTime, SysTime};
 use crate::util;
 use crate::HashMap;
 use rand::Rng;
+#[cfg(feature = "parallel-sim")]
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::fmt;
 use std::fmt::Debug;
 use std::time::Duration;
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 enum ScheduleAction<Message, PeriodicEvent> {
     SubmitToProc(ProcessId, Command),
     SendToProc(ProcessId, ShardId, ProcessId, Message),
@@ -23,18 +29,69 @@ enum ScheduleAction<Message, PeriodicEvent> {
     PeriodicProcessEvent(ProcessId, PeriodicEvent, Duration),
     PeriodicExecutedNotification(ProcessId, Duration),
 }
+
+impl<Message, PeriodicEvent> ScheduleAction<Message, PeriodicEvent> {
+    // Returns the process this action is addressed to, if any.
+    fn target_process(&self) -> Option<ProcessId> {
+        match self {
+            Self::SubmitToProc(process_id, _)
+            | Self::SendToProc(_, _, process_id, _)
+            | Self::PeriodicProcessEvent(process_id, _, _)
+            | Self::PeriodicExecutedNotification(process_id, _) => {
+                Some(*process_id)
+            }
+            Self::SendToClient(_, _) => None,
+        }
+    }
+}
+
+impl<Message, PeriodicEvent> ScheduleAction<Message, PeriodicEvent>
+where
+    Message: Serialize,
+{
+    // Returns the size (in bytes) of the message sent in this action (0 if no
+    // message is sent).
+    fn size(&self) -> u64 {
+        let size = match self {
+            Self::SubmitToProc(_, cmd) => bincode::serialized_size(cmd),
+            Self::SendToProc(_, _, _, msg) => bincode::serialized_size(msg),
+            Self::SendToClient(_, cmd_result) => {
+                bincode::serialized_size(cmd_result)
+            }
+            Self::PeriodicProcessEvent(_, _, _)
+            | Self::PeriodicExecutedNotification(_, _) => return 0,
+        };
+        size.expect("message should be serializable")
+    }
+}
+
 #[derive(Clone)]
 enum MessageRegion {
     Process(ProcessId),
     Client(ClientId),
 }
 
+// A new action that results from handling some action in a process.
+enum ToSchedule<P: Protocol> {
+    // to be delivered after the distance between two regions
+    Message(
+        MessageRegion,
+        MessageRegion,
+        ScheduleAction<P::Message, P::PeriodicEvent>,
+    ),
+    // to be delivered after some delay
+    Periodic(Duration, ScheduleAction<P::Message, P::PeriodicEvent>),
+}
+
 pub struct Runner<P: Protocol> {
     planet: Planet,
+    config: Config,
     simulation: Simulation<P>,
     schedule: Schedule<ScheduleAction<P::Message, P::PeriodicEvent>>,
     // mapping from process identifier to its region
     process_to_region: HashMap<ProcessId, Region>,
+    // processes to be discovered by clients (when they're created or migrated)
+    to_discover: Vec<(ProcessId, ShardId, Region)>,
     // mapping from client identifier to its region
     client_to_region: HashMap<ClientId, Region>,
     // total number of clients
@@ -44,10 +101,57 @@ pub struct Runner<P: Protocol> {
     make_distances_symmetric: bool,
     // boolean indicating whether the runner should reoder messages
     reorder_messages: bool,
+    // bandwidth (in bytes per second) of the links between two regions with a
+    // bandwidth cap (see `Runner::set_link_bandwidth`)
+    link_bandwidths: HashMap<(Region, Region), u64>,
+    // time (in nanos) until which each link with a bandwidth cap is busy
+    // transmitting the messages already sent over it
+    link_busy_until: HashMap<(Region, Region), u64>,
+    // state of the simulation loop
+    status: SimulationStatus,
+    clients_done: usize,
+    extra_sim_time: Option<Duration>,
+    simulation_final_time: u64,
+    // if set, a snapshot is taken every `snapshot_interval` of simulation time
+    snapshot_interval: Option<Duration>,
+    // maximum number of snapshots kept (older ones are discarded first)
+    max_snapshots: usize,
+    // snapshots taken, from the oldest to the newest
+    snapshots: VecDeque<RunnerSnapshot<P>>,
+    // set if submissions are being replayed (see `Runner::replay`)
+    replay: Option<Replay>,
+    // registry of the values committed by processes, used to check that they
+    // all agree on them
+    #[cfg(feature = "strict-invariants")]
+    invariants: super::invariants::Registry,
+}
+
+// State of the submissions being replayed.
+#[derive(Clone)]
+struct Replay {
+    // time (in micros) at which each pending command was submitted
+    submitted: HashMap<Rifl, u64>,
+    // latency of each completed command
+    latencies: HashMap<Rifl, Duration>,
+}
+
+// A snapshot of the simulation along with everything needed to resume the
+// simulation loop from it.
+#[derive(Clone)]
+struct RunnerSnapshot<P: Protocol> {
+    simulation: SimulationSnapshot<P>,
+    schedule: Schedule<ScheduleAction<P::Message, P::PeriodicEvent>>,
+    client_to_region: HashMap<ClientId, Region>,
+    link_busy_until: HashMap<(Region, Region), u64>,
+    status: SimulationStatus,
+    clients_done: usize,
+    simulation_final_time: u64,
+    replay: Option<Replay>,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum SimulationStatus {
+    NotStarted,
     ClientsRunning,
     ExtraSimulationTime,
     Done,
@@ -72,6 +176,23 @@ where
         // check that we have the correct number of `process_regions`
         assert_eq!(process_regions.len(), config.n());
         assert!(config.gc_interval().is_some());
+        // check that the executor can enforce the latency budget (if any)
+        assert!(
+            config.executor_latency_budget().is_none()
+                || <P::Executor as Executor>::supports_latency_budget(),
+            "executor doesn't support latency budgets"
+        );
+        // check that there's no SCC budget, as the searches interrupted by it
+        // are only resumed in executor cleanups, which the simulator doesn't
+        // run
+        assert!(
+            config.executor_scc_budget().is_none(),
+            "simulator doesn't support SCC budgets"
+        );
+
+        // start checking that processes agree on what they commit
+        #[cfg(feature = "strict-invariants")]
+        let invariants = super::invariants::start();
 
         // create simulation
         let mut simulation = Simulation::new();
@@ -150,12 +271,13 @@ where
                 let mut client =
                     Client::new(client_id, workload, status_frequency);
                 // discover
-                let closest = util::closest_process_per_shard(
+                let processes = util::select_process_per_shard(
                     &region,
                     &planet,
                     to_discover.clone(),
+                    config.client_process_selection(),
                 );
-                client.connect(closest);
+                client.connect(processes);
                 // and register it
                 simulation.register_client(client);
                 client_to_region.insert(client_id, region.clone());
@@ -165,15 +287,29 @@ where
         // create runner
         let mut runner = Self {
             planet,
+            config,
             simulation,
             schedule: Schedule::new(),
             process_to_region,
+            to_discover,
             client_to_region,
             // since we start ids in 1, the last id is the same as the number of
             // clients
             client_count: client_id as usize,
             make_distances_symmetric: false,
             reorder_messages: false,
+            link_bandwidths: HashMap::new(),
+            link_busy_until: HashMap::new(),
+            status: SimulationStatus::NotStarted,
+            clients_done: 0,
+            extra_sim_time: None,
+            simulation_final_time: 0,
+            snapshot_interval: None,
+            max_snapshots: 0,
+            snapshots: VecDeque::new(),
+            replay: None,
+            #[cfg(feature = "strict-invariants")]
+            invariants,
         };
 
         // schedule periodic process events
@@ -197,6 +333,38 @@ where
         self.reorder_messages = true;
     }
 
+    /// Caps the bandwidth of the link from region `from` to region `to` at
+    /// `bytes_per_sec`. Messages sent over this link are transmitted one at a
+    /// time, each taking a time proportional to its size, and so they're
+    /// delayed by their transmission time (and by the time waiting for the
+    /// messages sent before them) on top of the distance between the regions.
+    /// Links are directional, and messages sent over links without a cap are
+    /// only delayed by the distance between the regions.
+    ///
+    /// Note that submissions replayed with `Runner::replay` are not affected.
+    pub fn set_link_bandwidth(
+        &mut self,
+        from: Region,
+        to: Region,
+        bytes_per_sec: u64,
+    ) {
+        assert!(bytes_per_sec > 0, "link bandwidth should be positive");
+        self.link_bandwidths.insert((from, to), bytes_per_sec);
+    }
+
+    /// Takes a snapshot of the simulation every `interval` of simulation time,
+    /// keeping at most the last `max_snapshots` snapshots. These can be used
+    /// to rewind the simulation with `Runner::rollback`.
+    pub fn enable_snapshots(
+        &mut self,
+        interval: Duration,
+        max_snapshots: usize,
+    ) {
+        assert!(max_snapshots > 0);
+        self.snapshot_interval = Some(interval);
+        self.max_snapshots = max_snapshots;
+    }
+
     /// Run the simulation. `extra_sim_time` indicates how much longer should
     /// the simulation run after clients are finished.
     pub fn run(
@@ -207,6 +375,95 @@ where
         HashMap<ProcessId, Option<ExecutionOrderMonitor>>,
         HashMap<Region, (usize, Histogram)>,
     ) {
+        // start clients
+        self.start(extra_sim_time);
+
+        // run simulation loop
+        while self.step() {}
+
+        // return metrics and client latencies
+        self.results()
+    }
+
+    /// Runs the simulation by replaying `submissions` instead of running the
+    /// clients' workloads: each command is submitted at the time specified,
+    /// regardless of when previous commands complete. Returns the latency of
+    /// each command, as observed by its client. `extra_sim_time` indicates how
+    /// much longer should the simulation run after all commands complete.
+    ///
+    /// Replaying the same submissions in runners of different protocols (with
+    /// the same regions) makes each process see the same commands in the same
+    /// order and at the same time (and thus assign them the same dots), which
+    /// allows protocols to be compared command by command (see
+    /// `LatencyDifferences`). For this, messages should not be reordered (see
+    /// `Runner::reorder_messages`).
+    pub fn replay(
+        &mut self,
+        submissions: Vec<Submission>,
+        extra_sim_time: Option<Duration>,
+    ) -> HashMap<Rifl, Duration> {
+        assert!(
+            self.status == SimulationStatus::NotStarted,
+            "simulation has already started"
+        );
+        assert!(!submissions.is_empty(), "there should be submissions");
+        self.status = SimulationStatus::ClientsRunning;
+        self.extra_sim_time = extra_sim_time;
+
+        // schedule all submissions
+        let mut submitted = HashMap::new();
+        for submission in submissions {
+            let Submission {
+                at,
+                client_id,
+                target_shard,
+                cmd,
+            } = submission;
+            let (client, _) = self.simulation.get_client(client_id);
+            let process_id = client.shard_process(&target_shard);
+
+            // the command reaches the process after the distance between the
+            // client and the process
+            let from = self.compute_region(MessageRegion::Client(client_id));
+            let to = self.compute_region(MessageRegion::Process(process_id));
+            let delay = at + Duration::from_millis(self.distance(from, to));
+
+            let res = submitted.insert(cmd.rifl(), at.as_micros() as u64);
+            assert!(res.is_none(), "commands should be submitted only once");
+            let action = ScheduleAction::SubmitToProc(process_id, cmd);
+            self.schedule
+                .schedule(self.simulation.time(), delay, action);
+        }
+        self.replay = Some(Replay {
+            submitted,
+            latencies: HashMap::new(),
+        });
+
+        // take the first snapshot right away
+        if self.snapshot_interval.is_some() {
+            self.take_snapshot();
+        }
+
+        // run simulation loop
+        while self.step() {}
+
+        self.replay
+            .take()
+            .expect("submissions should be being replayed")
+            .latencies
+    }
+
+    /// Starts the simulation without running it; the simulation can then be
+    /// run one step at a time with `Runner::step`. `extra_sim_time` indicates
+    /// how much longer should the simulation run after clients are finished.
+    pub fn start(&mut self, extra_sim_time: Option<Duration>) {
+        assert!(
+            self.status == SimulationStatus::NotStarted,
+            "simulation has already started"
+        );
+        self.status = SimulationStatus::ClientsRunning;
+        self.extra_sim_time = extra_sim_time;
+
         // start clients
         self.simulation.start_clients().into_iter().for_each(
             |(client_id, process_id, cmd)| {
@@ -219,161 +476,373 @@ where
             },
         );
 
-        // run simulation loop
-        self.simulation_loop(extra_sim_time);
-
-        // return metrics and client latencies
-        (
-            self.metrics(),
-            self.executors_monitors(),
-            self.clients_latencies(),
-        )
+        // take the first snapshot right away
+        if self.snapshot_interval.is_some() {
+            self.take_snapshot();
+        }
     }
 
-    fn simulation_loop(&mut self, extra_sim_time: Option<Duration>) {
-        let mut simulation_status = SimulationStatus::ClientsRunning;
-        let mut clients_done = 0;
-        let mut simulation_final_time = 0;
+    /// Handles the next scheduled action. Returns `false` if the simulation
+    /// is done, and `true` otherwise.
+    pub fn step(&mut self) -> bool {
+        assert!(
+            self.status != SimulationStatus::NotStarted,
+            "simulation should be started before stepping"
+        );
+        if self.status == SimulationStatus::Done {
+            return false;
+        }
 
-        while simulation_status != SimulationStatus::Done {
-            let action = self.schedule
-                .next_action(self.simulation.time())
-                .expect("there should be a new action since stability is always running");
+        self.maybe_take_snapshot();
 
-            match action {
-                ScheduleAction::PeriodicProcessEvent(
-                    process_id,
-                    event,
-                    delay,
-                ) => {
-                    self.handle_periodic_process_event(process_id, event, delay)
-                }
-                ScheduleAction::PeriodicExecutedNotification(
-                    process_id,
-                    delay,
-                ) => self
-                    .handle_periodic_executed_notification(process_id, delay),
-                ScheduleAction::SubmitToProc(process_id, cmd) => {
-                    self.handle_submit_to_proc(process_id, cmd);
-                }
-                ScheduleAction::SendToProc(
-                    from,
-                    from_shard_id,
-                    process_id,
-                    msg,
-                ) => {
-                    self.handle_send_to_proc(
-                        from,
-                        from_shard_id,
-                        process_id,
-                        msg,
-                    );
-                }
-                ScheduleAction::SendToClient(client_id, cmd_result) => {
-                    // handle new command result in client
-                    let submit = self.simulation.forward_to_client(cmd_result);
-                    if let Some((process_id, cmd)) = submit {
-                        self.schedule_submit(
-                            MessageRegion::Client(client_id),
-                            process_id,
-                            cmd,
-                        );
-                    } else {
-                        clients_done += 1;
-                        // if all clients are done, enter the next phase
-                        if clients_done == self.client_count {
-                            simulation_status = match extra_sim_time {
-                                Some(extra) => {
-                                    // if there's extra time, compute the
-                                    // final simulation time
-                                    simulation_final_time =
-                                        self.simulation.time().millis()
-                                            + extra.as_millis() as u64;
-                                    SimulationStatus::ExtraSimulationTime
-                                }
-                                None => {
-                                    // otherwise, end the simulation
-                                    SimulationStatus::Done
-                                }
-                            }
-                        }
-                    }
-                }
+        let action = self.schedule.next_action(self.simulation.time()).expect(
+            "there should be a new action since stability is always running",
+        );
+
+        match action {
+            ScheduleAction::SendToClient(client_id, cmd_result) => {
+                self.handle_send_to_client(client_id, cmd_result);
+            }
+            action => {
+                let process_id = action
+                    .target_process()
+                    .expect("action should be addressed to some process");
+                let (process, executor, pending, time) =
+                    self.simulation.get_process(process_id);
+
+                // handle action and schedule new actions
+                let mut to_schedule = Vec::new();
+                Self::handle_process_action(
+                    process,
+                    executor,
+                    pending,
+                    time,
+                    action,
+                    &mut to_schedule,
+                );
+                self.schedule_all(to_schedule);
             }
+        }
 
-            // check if we're in extra simulation time; if yes, finish the
-            // simulation if we're past the final simulation time
-            let should_end_sim = simulation_status
-                == SimulationStatus::ExtraSimulationTime
-                && self.simulation.time().millis() > simulation_final_time;
-            if should_end_sim {
-                simulation_status = SimulationStatus::Done;
+        self.check_extra_sim_time();
+        self.status != SimulationStatus::Done
+    }
+
+    /// Runs the simulation until simulation time reaches `millis` (or the
+    /// simulation is done). Returns `false` if the simulation is done, and
+    /// `true` otherwise.
+    pub fn step_until(&mut self, millis: u64) -> bool {
+        while self.now() < millis {
+            if !self.step() {
+                return false;
             }
         }
+        true
+    }
+
+    /// Returns the current simulation time (in millis).
+    pub fn now(&mut self) -> u64 {
+        self.simulation.time().millis()
+    }
+
+    /// Returns the simulation times (in millis) at which the snapshots
+    /// currently kept were taken, from the oldest to the newest.
+    pub fn snapshot_times(&self) -> Vec<u64> {
+        self.snapshots
+            .iter()
+            .map(|snapshot| snapshot.simulation.millis())
+            .collect()
+    }
+
+    /// Rewinds the simulation to the newest snapshot taken at or before
+    /// `millis`, discarding all the snapshots taken after it. Returns the
+    /// simulation time (in millis) the simulation was rewound to, or `None` if
+    /// there's no such snapshot.
+    ///
+    /// Note that if messages are being reordered (see
+    /// `Runner::reorder_messages`), the simulation may not behave as before
+    /// after being rewound.
+    pub fn rollback(&mut self, millis: u64) -> Option<u64> {
+        let index = self
+            .snapshots
+            .iter()
+            .rposition(|snapshot| snapshot.simulation.millis() <= millis)?;
+        self.snapshots.truncate(index + 1);
+
+        // the snapshot is kept, so that we can rewind to it again
+        let snapshot = self.snapshots[index].clone();
+        let time = snapshot.simulation.millis();
+        self.simulation.restore(snapshot.simulation);
+        self.schedule = snapshot.schedule;
+        self.client_to_region = snapshot.client_to_region;
+        self.link_busy_until = snapshot.link_busy_until;
+        self.status = snapshot.status;
+        self.clients_done = snapshot.clients_done;
+        self.simulation_final_time = snapshot.simulation_final_time;
+        self.replay = snapshot.replay;
+        Some(time)
+    }
+
+    /// Moves the client with this identifier to `region`. The client discovers
+    /// the processes again (from its new region), and its next commands are
+    /// submitted to the process selected then. Commands (and their results)
+    /// already in flight are not affected.
+    ///
+    /// Note that the client's latencies are reported under its region at the
+    /// end of the simulation (see `Runner::results`).
+    /// It panics if the client is not registered.
+    pub fn migrate_client(&mut self, client_id: ClientId, region: Region) {
+        assert!(
+            self.client_to_region.contains_key(&client_id),
+            "client {} should have been registered before",
+            client_id
+        );
+
+        // discover again
+        let processes = util::select_process_per_shard(
+            &region,
+            &self.planet,
+            self.to_discover.clone(),
+            self.config.client_process_selection(),
+        );
+        let (client, _) = self.simulation.get_client(client_id);
+        client.connect(processes);
+
+        // update client to region mapping
+        self.client_to_region.insert(client_id, region);
     }
 
-    fn handle_periodic_process_event(
+    /// Returns the process (and its executor) with this identifier, so that
+    /// its state can be inspected.
+    /// It panics if the process is not registered.
+    pub fn process(&mut self, process_id: ProcessId) -> (&P, &P::Executor) {
+        let (process, executor, _, _) = self.simulation.get_process(process_id);
+        (process, executor)
+    }
+
+    /// Returns the processes' metrics, executors' monitors and clients'
+    /// latencies at this point of the simulation.
+    pub fn results(
         &mut self,
-        process_id: ProcessId,
-        event: P::PeriodicEvent,
-        delay: Duration,
+    ) -> (
+        HashMap<ProcessId, (ProtocolMetrics, ExecutorMetrics)>,
+        HashMap<ProcessId, Option<ExecutionOrderMonitor>>,
+        HashMap<Region, (usize, Histogram)>,
     ) {
-        // get process
-        let (process, _, _, time) = self.simulation.get_process(process_id);
+        (
+            self.metrics(),
+            self.executors_monitors(),
+            self.clients_latencies(),
+        )
+    }
 
-        // handle event adn schedule new actions
-        process.handle_event(event.clone(), time);
-        self.send_to_processes_and_executors(process_id);
+    // Takes a snapshot if snapshots are enabled and enough simulation time has
+    // passed since the last one.
+    fn maybe_take_snapshot(&mut self) {
+        if let Some(interval) = self.snapshot_interval {
+            let last_snapshot = self
+                .snapshots
+                .back()
+                .map(|snapshot| snapshot.simulation.millis())
+                .unwrap_or_default();
+            if self.now() >= last_snapshot + interval.as_millis() as u64 {
+                self.take_snapshot();
+            }
+        }
+    }
 
-        // schedule the next periodic event
-        self.schedule_periodic_process_event(process_id, event, delay);
+    fn take_snapshot(&mut self) {
+        let snapshot = RunnerSnapshot {
+            simulation: self.simulation.snapshot(),
+            schedule: self.schedule.clone(),
+            client_to_region: self.client_to_region.clone(),
+            link_busy_until: self.link_busy_until.clone(),
+            status: self.status,
+            clients_done: self.clients_done,
+            simulation_final_time: self.simulation_final_time,
+            replay: self.replay.clone(),
+        };
+        self.snapshots.push_back(snapshot);
+        // discard the oldest snapshot if we have too many
+        if self.snapshots.len() > self.max_snapshots {
+            self.snapshots.pop_front();
+        }
     }
 
-    fn handle_periodic_executed_notification(
+    fn handle_send_to_client(
         &mut self,
-        process_id: ProcessId,
-        delay: Duration,
+        client_id: ClientId,
+        cmd_result: CommandResult,
     ) {
-        // get process and executor
-        let (process, executor, _, time) =
-            self.simulation.get_process(process_id);
-
-        // handle executed and schedule new actions
-        if let Some(executed) = executor.executed(time) {
-            process.handle_executed(executed, time);
-            self.send_to_processes_and_executors(process_id);
+        // if submissions are being replayed, simply record the latency of the
+        // command
+        if let Some(replay) = self.replay.as_mut() {
+            let rifl = cmd_result.rifl();
+            let submitted = replay
+                .submitted
+                .remove(&rifl)
+                .expect("command should have been submitted");
+            let now = self.simulation.time().micros();
+            replay
+                .latencies
+                .insert(rifl, Duration::from_micros(now - submitted));
+            if replay.submitted.is_empty() {
+                self.clients_finished();
+            }
+            return;
         }
 
-        // schedule the next periodic event
-        self.schedule_periodic_executed_notification(process_id, delay);
+        // handle new command result in client
+        let submit = self.simulation.forward_to_client(cmd_result);
+        if let Some((process_id, cmd)) = submit {
+            self.schedule_submit(
+                MessageRegion::Client(client_id),
+                process_id,
+                cmd,
+            );
+        } else if self.simulation.get_client(client_id).0.finished() {
+            // the client is done once it has no more commands to issue and
+            // none pending (with more than one command in flight, the last
+            // commands issued may still be pending)
+            self.clients_done += 1;
+            // if all clients are done, enter the next phase
+            if self.clients_done == self.client_count {
+                self.clients_finished();
+            }
+        }
     }
 
-    fn handle_submit_to_proc(&mut self, process_id: ProcessId, cmd: Command) {
-        // get process and executor
-        let (process, _executor, pending, time) =
-            self.simulation.get_process(process_id);
+    fn clients_finished(&mut self) {
+        self.status = match self.extra_sim_time {
+            Some(extra) => {
+                // if there's extra time, compute the final simulation time
+                self.simulation_final_time =
+                    self.simulation.time().millis() + extra.as_millis() as u64;
+                SimulationStatus::ExtraSimulationTime
+            }
+            None => {
+                // otherwise, end the simulation
+                SimulationStatus::Done
+            }
+        }
+    }
 
-        // register command in pending
-        pending.wait_for(&cmd);
+    fn check_extra_sim_time(&mut self) {
+        // check if we're in extra simulation time; if yes, finish the
+        // simulation if we're past the final simulation time
+        let should_end_sim = self.status
+            == SimulationStatus::ExtraSimulationTime
+            && self.simulation.time().millis() > self.simulation_final_time;
+        if should_end_sim {
+            self.status = SimulationStatus::Done;
+        }
+    }
 
-        // submit to process and schedule new actions
-        process.submit(None, cmd, time);
-        self.send_to_processes_and_executors(process_id);
+    // Handles an action addressed to some process. Since this only needs the
+    // state of that process, actions addressed to different processes can be
+    // handled in parallel; the new actions that result from it are pushed to
+    // `to_schedule`, and should be scheduled afterwards.
+    fn handle_process_action(
+        process: &mut P,
+        executor: &mut P::Executor,
+        pending: &mut AggregatePending,
+        time: &SimTime,
+        action: ScheduleAction<P::Message, P::PeriodicEvent>,
+        to_schedule: &mut Vec<ToSchedule<P>>,
+    ) {
+        match action {
+            ScheduleAction::PeriodicProcessEvent(process_id, event, delay) => {
+                // handle event and schedule new actions
+                process.handle_event(event.clone(), time);
+                Self::send_to_processes_and_executors(
+                    process,
+                    executor,
+                    pending,
+                    time,
+                    to_schedule,
+                );
+
+                // schedule the next periodic event
+                let action = ScheduleAction::PeriodicProcessEvent(
+                    process_id, event, delay,
+                );
+                to_schedule.push(ToSchedule::Periodic(delay, action));
+            }
+            ScheduleAction::PeriodicExecutedNotification(process_id, delay) => {
+                // handle executed and schedule new actions
+                if let Some(executed) = executor.executed(time) {
+                    process.handle_executed(executed, time);
+                    Self::send_to_processes_and_executors(
+                        process,
+                        executor,
+                        pending,
+                        time,
+                        to_schedule,
+                    );
+                }
+
+                // schedule the next periodic event
+                let action = ScheduleAction::PeriodicExecutedNotification(
+                    process_id, delay,
+                );
+                to_schedule.push(ToSchedule::Periodic(delay, action));
+            }
+            ScheduleAction::SubmitToProc(_, cmd) => {
+                // register command in pending
+                pending.wait_for(&cmd);
+
+                // submit to process and schedule new actions
+                process.submit(None, cmd, time);
+                Self::send_to_processes_and_executors(
+                    process,
+                    executor,
+                    pending,
+                    time,
+                    to_schedule,
+                );
+            }
+            ScheduleAction::SendToProc(from, from_shard_id, _, msg) => {
+                Self::handle_send_to_proc(
+                    process,
+                    executor,
+                    pending,
+                    time,
+                    from,
+                    from_shard_id,
+                    msg,
+                    to_schedule,
+                );
+            }
+            ScheduleAction::SendToClient(client_id, _) => {
+                panic!(
+                    "action addressed to client {} can't be handled by process {}",
+                    client_id,
+                    process.id()
+                );
+            }
+        }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn handle_send_to_proc(
-        &mut self,
+        process: &mut P,
+        executor: &mut P::Executor,
+        pending: &mut AggregatePending,
+        time: &SimTime,
         from: ProcessId,
         from_shard_id: ShardId,
-        process_id: ProcessId,
         msg: P::Message,
+        to_schedule: &mut Vec<ToSchedule<P>>,
     ) {
-        // get process and executor
-        let (process, _, _, time) = self.simulation.get_process(process_id);
-
         // handle message and schedule new actions
         process.handle(from, from_shard_id, msg, time);
-        self.send_to_processes_and_executors(process_id);
+        Self::send_to_processes_and_executors(
+            process,
+            executor,
+            pending,
+            time,
+            to_schedule,
+        );
     }
 
     // (maybe) Schedules a new submit from a client.
@@ -392,15 +861,18 @@ where
         );
     }
 
-    fn send_to_processes_and_executors(&mut self, process_id: ProcessId) {
-        // get process and executor
-        let (process, executor, pending, time) =
-            self.simulation.get_process(process_id);
-        assert_eq!(process.id(), process_id);
+    fn send_to_processes_and_executors(
+        process: &mut P,
+        executor: &mut P::Executor,
+        pending: &mut AggregatePending,
+        time: &SimTime,
+        to_schedule: &mut Vec<ToSchedule<P>>,
+    ) {
+        let process_id = process.id();
         let shard_id = process.shard_id();
 
         // get ready commands
-        let protocol_actions = process.to_processes_iter().collect();
+        let protocol_actions: Vec<_> = process.to_processes_iter().collect();
 
         // handle new execution info in the executor
         let ready: Vec<_> = process
@@ -414,40 +886,29 @@ where
                     assert_eq!(shard_id, DEFAULT_SHARD_ID);
                     executor.handle(info, time);
                 }
+                let to_process_executors =
+                    executor.to_process_executors_iter().collect::<Vec<_>>();
+                for (target, info) in to_process_executors {
+                    assert_eq!(target, process_id);
+                    executor.handle(info, time);
+                }
                 // TODO remove collect
                 executor.to_clients_iter().collect::<Vec<_>>()
             })
             // handle all partial results in pending
-            .filter_map(|executor_result| {
+            .flat_map(|executor_result| {
                 pending.add_executor_result(executor_result)
             })
             .collect();
 
-        // schedule new messages
-        self.schedule_protocol_actions(
-            process_id,
-            shard_id,
-            MessageRegion::Process(process_id),
-            protocol_actions,
-        );
-
-        // schedule new command results
-        ready.into_iter().for_each(|cmd_result| {
-            self.schedule_to_client(
-                MessageRegion::Process(process_id),
-                cmd_result,
-            )
-        });
-    }
+        // violations are never expected in the simulator
+        if let Some(violation) =
+            process.to_violations().or_else(|| executor.to_violations())
+        {
+            panic!("violation {}", violation);
+        }
 
-    /// (maybe) Schedules a new send from some process.
-    fn schedule_protocol_actions(
-        &mut self,
-        process_id: ProcessId,
-        shard_id: ShardId,
-        from_region: MessageRegion,
-        protocol_actions: Vec<Action<P>>,
-    ) {
+        // schedule new messages
         for protocol_action in protocol_actions {
             match protocol_action {
                 Action::ToSend { target, msg } => {
@@ -455,11 +916,15 @@ where
                     target.into_iter().for_each(|to| {
                         // if message to self, deliver immediately
                         if to == process_id {
-                            self.handle_send_to_proc(
+                            Self::handle_send_to_proc(
+                                process,
+                                executor,
+                                pending,
+                                time,
                                 process_id,
                                 shard_id,
-                                process_id,
                                 msg.clone(),
+                                to_schedule,
                             )
                         } else {
                             // otherwise, create action and schedule it
@@ -469,38 +934,57 @@ where
                                 to,
                                 msg.clone(),
                             );
-                            self.schedule_message(
-                                from_region.clone(),
+                            to_schedule.push(ToSchedule::Message(
+                                MessageRegion::Process(process_id),
                                 MessageRegion::Process(to),
                                 action,
-                            );
+                            ));
                         }
                     });
                 }
                 Action::ToForward { msg } => {
                     // deliver to-forward messages immediately
-                    self.handle_send_to_proc(
-                        process_id, shard_id, process_id, msg,
+                    Self::handle_send_to_proc(
+                        process,
+                        executor,
+                        pending,
+                        time,
+                        process_id,
+                        shard_id,
+                        msg,
+                        to_schedule,
                     );
                 }
             }
         }
+
+        // schedule new command results
+        ready.into_iter().for_each(|cmd_result| {
+            let client_id = cmd_result.rifl().source();
+            let action = ScheduleAction::SendToClient(client_id, cmd_result);
+            to_schedule.push(ToSchedule::Message(
+                MessageRegion::Process(process_id),
+                MessageRegion::Client(client_id),
+                action,
+            ));
+        });
     }
 
-    /// Schedules a new command result.
-    fn schedule_to_client(
-        &mut self,
-        from_region: MessageRegion,
-        cmd_result: CommandResult,
-    ) {
-        // create action and schedule it
-        let client_id = cmd_result.rifl().source();
-        let action = ScheduleAction::SendToClient(client_id, cmd_result);
-        self.schedule_message(
-            from_region,
-            MessageRegion::Client(client_id),
-            action,
-        );
+    /// Schedules the new actions that resulted from handling some action in a
+    /// process.
+    fn schedule_all(&mut self, to_schedule: Vec<ToSchedule<P>>) {
+        for to_schedule in to_schedule {
+            match to_schedule {
+                ToSchedule::Message(from_region, to_region, action) => {
+                    self.schedule_message(from_region, to_region, action)
+                }
+                ToSchedule::Periodic(delay, action) => self.schedule.schedule(
+                    self.simulation.time(),
+                    delay,
+                    action,
+                ),
+            }
+        }
     }
 
     /// Schedules a message.
@@ -523,10 +1007,38 @@ where
             distance = (distance as f64 * multiplier) as u64;
         }
 
+        // compute the time the message takes to be transmitted (if the link
+        // has a bandwidth cap)
+        let transmission = if self.link_bandwidths.is_empty() {
+            Duration::from_nanos(0)
+        } else {
+            let link = (from.clone(), to.clone());
+            self.transmission(link, action.size())
+        };
+
         // schedule action
-        let distance = Duration::from_millis(distance);
+        let delay = Duration::from_millis(distance) + transmission;
         self.schedule
-            .schedule(self.simulation.time(), distance, action);
+            .schedule(self.simulation.time(), delay, action);
+    }
+
+    /// Computes the time until a message with `size` bytes sent now over
+    /// `link` is fully transmitted, given the bandwidth of the link and the
+    /// messages still being transmitted over it. Returns zero if the link has
+    /// no bandwidth cap.
+    fn transmission(&mut self, link: (Region, Region), size: u64) -> Duration {
+        let bytes_per_sec = match self.link_bandwidths.get(&link) {
+            Some(bytes_per_sec) => *bytes_per_sec as u128,
+            None => return Duration::from_nanos(0),
+        };
+        let now = self.simulation.time().nanos();
+        let transmission =
+            (size as u128 * 1_000_000_000 / bytes_per_sec) as u64;
+
+        // the message can only start being transmitted once the link is free
+        let busy_until = self.link_busy_until.entry(link).or_default();
+        *busy_until = std::cmp::max(*busy_until, now) + transmission;
+        Duration::from_nanos(*busy_until - now)
     }
 
     /// Schedules the next periodic process event.
@@ -681,6 +1193,126 @@ where
     }
 }
 
+#[cfg(feature = "parallel-sim")]
+impl<P> Runner<P>
+where
+    P: Protocol + Send,
+{
+    /// Same as `Runner::run`, but the simulation is run with
+    /// `Runner::step_parallel`.
+    pub fn run_parallel(
+        &mut self,
+        extra_sim_time: Option<Duration>,
+    ) -> (
+        HashMap<ProcessId, (ProtocolMetrics, ExecutorMetrics)>,
+        HashMap<ProcessId, Option<ExecutionOrderMonitor>>,
+        HashMap<Region, (usize, Histogram)>,
+    ) {
+        // start clients
+        self.start(extra_sim_time);
+
+        // run simulation loop
+        while self.step_parallel() {}
+
+        // return metrics and client latencies
+        self.results()
+    }
+
+    /// Handles all the actions scheduled for the next simulation time (instead
+    /// of a single one, as in `Runner::step`). The actions addressed to
+    /// different processes are handled in parallel, and the new actions that
+    /// result from them are only scheduled once all processes are done.
+    /// Returns `false` if the simulation is done, and `true` otherwise.
+    pub fn step_parallel(&mut self) -> bool {
+        assert!(
+            self.status != SimulationStatus::NotStarted,
+            "simulation should be started before stepping"
+        );
+        if self.status == SimulationStatus::Done {
+            return false;
+        }
+
+        self.maybe_take_snapshot();
+
+        let actions =
+            self.schedule.next_actions(self.simulation.time()).expect(
+                "there should be new actions since stability is always running",
+            );
+
+        // split actions by the process they're addressed to
+        let mut process_to_actions: HashMap<_, Vec<_>> = HashMap::new();
+        let mut client_actions = Vec::new();
+        for action in actions {
+            match action.target_process() {
+                Some(process_id) => process_to_actions
+                    .entry(process_id)
+                    .or_default()
+                    .push(action),
+                None => client_actions.push(action),
+            }
+        }
+
+        // handle the actions of each process in parallel
+        let (processes, time) = self.simulation.get_all_processes();
+        let mut processes: Vec<_> = processes
+            .filter_map(|(process_id, process, executor, pending)| {
+                process_to_actions.remove(&process_id).map(|actions| {
+                    (process_id, process, executor, pending, actions)
+                })
+            })
+            .collect();
+        assert!(
+            process_to_actions.is_empty(),
+            "all actions should be addressed to registered processes"
+        );
+        // sort processes so that new actions are always scheduled in the same
+        // order
+        processes.sort_by_key(|(process_id, _, _, _, _)| *process_id);
+        #[cfg(feature = "strict-invariants")]
+        let invariants = &self.invariants;
+        let to_schedule: Vec<_> = processes
+            .into_par_iter()
+            .map(|(_, process, executor, pending, actions)| {
+                let handle = || {
+                    let mut to_schedule = Vec::new();
+                    for action in actions {
+                        Self::handle_process_action(
+                            process,
+                            executor,
+                            pending,
+                            time,
+                            action,
+                            &mut to_schedule,
+                        );
+                    }
+                    to_schedule
+                };
+                // processes handled in other threads record what they commit
+                // in the registry of this simulation
+                #[cfg(feature = "strict-invariants")]
+                let handle = || super::invariants::with(invariants, handle);
+                handle()
+            })
+            .collect();
+
+        // schedule new actions
+        for to_schedule in to_schedule {
+            self.schedule_all(to_schedule);
+        }
+
+        // handle the actions addressed to clients
+        for action in client_actions {
+            if let ScheduleAction::SendToClient(client_id, cmd_result) = action
+            {
+                self.handle_send_to_client(client_id, cmd_result);
+            }
+        }
+
+        self.check_extra_sim_time();
+        self.status != SimulationStatus::Done
+    }
+}
+
 impl<Message: Debug, PeriodicEvent: Debug> fmt::Debug
     for ScheduleAction<Message, PeriodicEvent>
 {
@@ -727,7 +1359,15 @@ mod tests {
     use crate::metrics::F64;
     use crate::protocol::{Basic, ProtocolMetricsKind};
 
-    fn run(f: usize, clients_per_process: usize) -> (Histogram, Histogram) {
+    fn runner(f: usize, clients_per_process: usize) -> Runner<Basic> {
+        runner_with_max_in_flight(f, clients_per_process, None)
+    }
+
+    fn runner_with_max_in_flight(
+        f: usize,
+        clients_per_process: usize,
+        max_in_flight: Option<usize>,
+    ) -> Runner<Basic> {
         // planet
         let planet = Planet::new();
 
@@ -746,16 +1386,18 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             pool_size,
             conflict_rate,
+            pool_id: 0,
         };
         let commands_per_client = 1000;
         let payload_size = 100;
-        let workload = Workload::new(
+        let mut workload = Workload::new(
             shard_count,
             key_gen,
             keys_per_command,
             commands_per_client,
             payload_size,
         );
+        workload.set_max_in_flight(max_in_flight);
 
         // process regions
         let process_regions = vec![
@@ -769,15 +1411,34 @@ mod tests {
             vec![Region::new("us-west1"), Region::new("us-west2")];
 
         // create runner
-        let mut runner: Runner<Basic> = Runner::new(
+        Runner::new(
             planet,
             config,
             workload,
             clients_per_process,
             process_regions,
             client_regions,
-        );
+        )
+    }
+
+    fn run(f: usize, clients_per_process: usize) -> (Histogram, Histogram) {
+        run_with_max_in_flight(f, clients_per_process, None)
+    }
+
+    fn run_with_max_in_flight(
+        f: usize,
+        clients_per_process: usize,
+        max_in_flight: Option<usize>,
+    ) -> (Histogram, Histogram) {
+        let runner =
+            runner_with_max_in_flight(f, clients_per_process, max_in_flight);
+        run_with(runner, clients_per_process)
+    }
 
+    fn run_with(
+        mut runner: Runner<Basic>,
+        clients_per_process: usize,
+    ) -> (Histogram, Histogram) {
         // run simulation until the clients end + another second second
         let (metrics, _executors_monitors, mut clients_latencies) =
             runner.run(Some(Duration::from_secs(1)));
@@ -791,6 +1452,7 @@ mod tests {
             .expect("there should stats from us-west2 region");
 
         // check the number of issued commands
+        let commands_per_client = 1000;
         let expected = commands_per_client * clients_per_process;
         assert_eq!(us_west1_issued, expected);
         assert_eq!(us_west2_issued, expected);
@@ -868,4 +1530,199 @@ mod tests {
         assert_eq!(us_west2_with_one.mean(), us_west2_with_ten.mean());
         assert_eq!(us_west2_with_one.cov(), us_west2_with_ten.cov());
     }
+
+    #[test]
+    fn runner_max_in_flight() {
+        // one command in flight per client
+        let f = 1;
+        let clients_per_process = 1;
+        let (us_west1_with_one, us_west2_with_one) =
+            run(f, clients_per_process);
+
+        // 4 commands in flight per client: all commands are still issued
+        // (which is checked by `run`) and, since commands don't interfere with
+        // each other in the basic protocol, their latency is the same
+        let max_in_flight = Some(4);
+        let (us_west1_with_four, us_west2_with_four) =
+            run_with_max_in_flight(f, clients_per_process, max_in_flight);
+        assert_eq!(us_west1_with_one.mean(), us_west1_with_four.mean());
+        assert_eq!(us_west2_with_one.mean(), us_west2_with_four.mean());
+    }
+
+    #[test]
+    fn runner_link_bandwidth() {
+        let f = 0;
+        let clients_per_process = 1;
+        let (us_west1, us_west2) = run(f, clients_per_process);
+        assert_eq!(us_west1.mean(), F64::new(0.0));
+        assert_eq!(us_west2.mean(), F64::new(24.0));
+
+        // cap the link used by the client in us-west2 to submit commands to
+        // us-west1 at 1KB/s: since commands carry a payload of 100 bytes, each
+        // takes over 100ms to be transmitted
+        let mut capped = runner(f, clients_per_process);
+        capped.set_link_bandwidth(
+            Region::new("us-west2"),
+            Region::new("us-west1"),
+            1000,
+        );
+        let (us_west1_capped, us_west2_capped) =
+            run_with(capped, clients_per_process);
+        assert_eq!(us_west1_capped.mean(), F64::new(0.0));
+        assert!(us_west2_capped.mean() > F64::new(124.0));
+
+        // with more clients, commands also wait for the ones sent before them
+        // to be transmitted
+        let clients_per_process = 4;
+        let mut capped = runner(f, clients_per_process);
+        capped.set_link_bandwidth(
+            Region::new("us-west2"),
+            Region::new("us-west1"),
+            1000,
+        );
+        let (us_west1_queued, us_west2_queued) =
+            run_with(capped, clients_per_process);
+        assert_eq!(us_west1_queued.mean(), F64::new(0.0));
+        assert!(us_west2_queued.mean() > us_west2_capped.mean());
+    }
+
+    #[test]
+    fn runner_rollback() {
+        let f = 1;
+        let clients_per_process = 2;
+        let extra_sim_time = Some(Duration::from_secs(1));
+
+        // run simulation from start to end
+        let (_, _, expected) =
+            runner(f, clients_per_process).run(extra_sim_time);
+
+        // run simulation with snapshots every 100ms, keeping the last 5
+        let mut runner = runner(f, clients_per_process);
+        let max_snapshots = 5;
+        runner.enable_snapshots(Duration::from_millis(100), max_snapshots);
+        runner.start(extra_sim_time);
+        assert_eq!(runner.snapshot_times(), vec![0]);
+
+        // run for a while
+        assert!(runner.step_until(2000));
+        assert!(runner.now() >= 2000);
+        let snapshot_times = runner.snapshot_times();
+        assert_eq!(snapshot_times.len(), max_snapshots);
+        assert!(snapshot_times.windows(2).all(|w| w[0] + 100 <= w[1]));
+
+        // there's no snapshot this old
+        assert_eq!(runner.rollback(1000), None);
+
+        // rewind to the second snapshot, which drops the ones after it
+        let time = runner.rollback(snapshot_times[1] + 1);
+        assert_eq!(time, Some(snapshot_times[1]));
+        assert_eq!(runner.now(), snapshot_times[1]);
+        assert_eq!(runner.snapshot_times(), snapshot_times[..2].to_vec());
+
+        // finish the simulation: the results should be the same as if the
+        // simulation was never rewound
+        while runner.step() {}
+        let (_, _, clients_latencies) = runner.results();
+        assert_eq!(clients_latencies, expected);
+    }
+
+    #[test]
+    fn runner_migrate_client() {
+        let f = 1;
+        let clients_per_process = 1;
+        let extra_sim_time = Some(Duration::from_secs(1));
+        let client_id = 2;
+        let shard_id = 0;
+
+        let mut runner = runner(f, clients_per_process);
+        runner.start(extra_sim_time);
+
+        // the client in us-west2 submits to the process in us-west1
+        let (client, _) = runner.simulation.get_client(client_id);
+        assert_eq!(client.shard_process(&shard_id), 3);
+
+        // run for a while and then move the client to asia-east1
+        assert!(runner.step_until(2000));
+        runner.migrate_client(client_id, Region::new("asia-east1"));
+
+        // the client now submits to the process in asia-east1
+        let (client, _) = runner.simulation.get_client(client_id);
+        assert_eq!(client.shard_process(&shard_id), 1);
+
+        // finish the simulation: all commands are completed and the
+        // client's latencies are reported under its new region
+        while runner.step() {}
+        let (_, _, mut clients_latencies) = runner.results();
+        assert!(!clients_latencies.contains_key(&Region::new("us-west2")));
+        let (issued, histogram) = clients_latencies
+            .remove(&Region::new("asia-east1"))
+            .expect("there should stats from asia-east1 region");
+        assert_eq!(issued, 1000);
+        assert_eq!(histogram.count(), 1000);
+    }
+
+    #[test]
+    fn runner_replay() {
+        use crate::sim::{open_loop_submissions, LatencyDifferences};
+
+        let clients_per_process = 1;
+        let extra_sim_time = Some(Duration::from_secs(1));
+
+        // each of the two clients submits a command every 10ms
+        let key_gen = KeyGen::ConflictPool {
+            pool_size: 1,
+            conflict_rate: 100,
+            pool_id: 0,
+        };
+        let workload = Workload::new(1, key_gen, 1, 100, 100);
+        let submissions =
+            open_loop_submissions(workload, 2, Duration::from_millis(10));
+        assert_eq!(submissions.len(), 200);
+
+        // replaying the same submissions leads to the same latencies
+        let f1 = runner(1, clients_per_process)
+            .replay(submissions.clone(), extra_sim_time);
+        assert_eq!(f1.len(), 200);
+        let same = runner(1, clients_per_process)
+            .replay(submissions.clone(), extra_sim_time);
+        let diffs = LatencyDifferences::new(&f1, &same);
+        assert_eq!(diffs.count(), 200);
+        assert_eq!((diffs.faster(), diffs.slower()), (0, 0));
+
+        // with f = 2, all commands are slower: each command takes 84ms more,
+        // as in `runner_single_client_per_process`
+        let f2 =
+            runner(2, clients_per_process).replay(submissions, extra_sim_time);
+        let diffs = LatencyDifferences::new(&f1, &f2);
+        assert_eq!(diffs.count(), 200);
+        assert_eq!(diffs.slower(), 200);
+        assert_eq!(diffs.percentile(0.0), 84);
+        assert_eq!(diffs.percentile(1.0), 84);
+    }
+
+    #[cfg(feature = "parallel-sim")]
+    #[test]
+    fn runner_parallel() {
+        let f = 1;
+        let clients_per_process = 10;
+        let extra_sim_time = Some(Duration::from_secs(1));
+
+        // run simulation sequentially and in parallel
+        let (_, _, expected) =
+            runner(f, clients_per_process).run(extra_sim_time);
+        let (metrics, _, clients_latencies) =
+            runner(f, clients_per_process).run_parallel(extra_sim_time);
+
+        // clients should observe the same latencies
+        assert_eq!(clients_latencies, expected);
+
+        // check that all commands were gc-ed
+        let total_commands = (1000 * clients_per_process * 2) as u64;
+        metrics.values().for_each(|(process_metrics, _)| {
+            let stable_count = process_metrics
+                .get_aggregated(ProtocolMetricsKind::Stable)
+                .expect("stability should have happened");
+            assert_eq!(*stable_count, total_commands);
+        });
+    }
 }
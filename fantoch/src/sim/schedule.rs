@@ -3,20 +3,29 @@ use std::cmp::{Ordering, Reverse};
 use std::collections::BinaryHeap;
 use std::time::Duration;
 
+#[derive(Clone)]
 pub struct Schedule<A> {
     queue: BinaryHeap<Reverse<QueueEntry<A>>>,
+    // number of actions scheduled so far
+    scheduled: u64,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 struct QueueEntry<A> {
+    // schedule time in nanoseconds
     schedule_time: u64,
+    // order in which the action was scheduled
+    order: u64,
     action: A,
 }
 
 impl<A: Eq> Ord for QueueEntry<A> {
     fn cmp(&self, other: &Self) -> Ordering {
-        // simply compare their schedule time
-        self.schedule_time.cmp(&other.schedule_time)
+        // compare their schedule time; actions scheduled for the same time
+        // are ordered by the order in which they were scheduled, which makes
+        // simulations deterministic
+        (self.schedule_time, self.order)
+            .cmp(&(other.schedule_time, other.order))
     }
 }
 
@@ -31,19 +40,25 @@ impl<A: Eq> Schedule<A> {
     pub fn new() -> Self {
         Self {
             queue: BinaryHeap::new(),
+            scheduled: 0,
         }
     }
 
     /// Schedule a new `ScheduleAction` at a certain `time`.
     pub fn schedule(&mut self, time: &SimTime, delay: Duration, action: A) {
         // compute schedule time
-        let schedule_time = time.millis() + delay.as_millis() as u64;
+        let schedule_time = time
+            .nanos()
+            .checked_add(SimTime::duration_to_nanos(delay))
+            .expect("simulation time overflow");
 
         // create new queue entry
         let entry = QueueEntry {
             schedule_time,
+            order: self.scheduled,
             action,
         };
+        self.scheduled += 1;
         // push new entry to the queue
         self.queue.push(Reverse(entry));
     }
@@ -53,11 +68,31 @@ impl<A: Eq> Schedule<A> {
         // get the next actions
         self.queue.pop().map(|entry| {
             // advance simulation time
-            time.set_millis(entry.0.schedule_time);
+            time.set_nanos(entry.0.schedule_time);
             // return only the action
             entry.0.action
         })
     }
+
+    /// Retrieve all the actions scheduled for the next schedule time.
+    pub fn next_actions(&mut self, time: &mut SimTime) -> Option<Vec<A>> {
+        // get the next action
+        let action = self.next_action(time)?;
+        let mut actions = vec![action];
+
+        // get all other actions scheduled for the same time
+        let now = time.nanos();
+        while self
+            .queue
+            .peek()
+            .map(|entry| entry.0.schedule_time == now)
+            .unwrap_or_default()
+        {
+            let entry = self.queue.pop().expect("there should be an entry");
+            actions.push(entry.0.action);
+        }
+        Some(actions)
+    }
 }
 
 #[cfg(test)]
@@ -118,4 +153,83 @@ mod tests {
         assert!(next == String::from("b") || next == String::from("e"));
         assert_eq!(time.millis(), 17);
     }
+
+    #[test]
+    fn schedule_next_actions() {
+        // create simulation time and schedule
+        let mut time = SimTime::new();
+        let mut schedule: Schedule<String> = Schedule::new();
+        assert!(schedule.next_actions(&mut time).is_none());
+
+        // schedule "a" and "b" with delay 10, and "c" with delay 12
+        schedule.schedule(&time, Duration::from_millis(10), String::from("a"));
+        schedule.schedule(&time, Duration::from_millis(12), String::from("c"));
+        schedule.schedule(&time, Duration::from_millis(10), String::from("b"));
+
+        // check "a" and "b" are the next actions, simulation time is now 10
+        let mut next = schedule
+            .next_actions(&mut time)
+            .expect("there should be next actions");
+        next.sort();
+        assert_eq!(next, vec![String::from("a"), String::from("b")]);
+        assert_eq!(time.millis(), 10);
+
+        // check "c" is the next action, simulation time is now 12
+        let next = schedule
+            .next_actions(&mut time)
+            .expect("there should be next actions");
+        assert_eq!(next, vec![String::from("c")]);
+        assert_eq!(time.millis(), 12);
+        assert!(schedule.next_actions(&mut time).is_none());
+    }
+
+    #[test]
+    fn schedule_same_time_in_order() {
+        // create simulation time and schedule
+        let mut time = SimTime::new();
+        let mut schedule: Schedule<String> = Schedule::new();
+
+        // schedule "c", "a" and "b" with delay 10
+        for action in vec!["c", "a", "b"] {
+            schedule.schedule(
+                &time,
+                Duration::from_millis(10),
+                String::from(action),
+            );
+        }
+
+        // check actions are handled in the order they were scheduled
+        for expected in vec!["c", "a", "b"] {
+            let next = schedule
+                .next_action(&mut time)
+                .expect("there should be a next action");
+            assert_eq!(next, String::from(expected));
+        }
+    }
+
+    #[test]
+    fn schedule_sub_millis() {
+        // create simulation time and schedule
+        let mut time = SimTime::new();
+        let mut schedule: Schedule<String> = Schedule::new();
+
+        // schedule "a" with a delay of 300us and "b" with a delay of 200us
+        schedule.schedule(&time, Duration::from_micros(300), String::from("a"));
+        schedule.schedule(&time, Duration::from_micros(200), String::from("b"));
+
+        // check "b" is the next action, simulation time is now 200us
+        let next = schedule
+            .next_action(&mut time)
+            .expect("there should be a next action");
+        assert_eq!(next, String::from("b"));
+        assert_eq!(time.micros(), 200);
+        assert_eq!(time.millis(), 0);
+
+        // check "a" is the next action, simulation time is now 300us
+        let next = schedule
+            .next_action(&mut time)
+            .expect("there should be a next action");
+        assert_eq!(next, String::from("a"));
+        assert_eq!(time.micros(), 300);
+    }
 }
@@ -3,7 +3,7 @@ use crate::command::{Command, CommandResult};
 use crate::executor::AggregatePending;
 use crate::id::{ClientId, ProcessId};
 use crate::protocol::{Action, Protocol};
-use crate::time::SimTime;
+use crate::time::{SimTime, SysTime};
 use crate::HashMap;
 use std::cell::Cell;
 
@@ -13,6 +13,24 @@ pub struct Simulation<P: Protocol> {
     clients: HashMap<ClientId, Cell<Client>>,
 }
 
+/// A copy of the state of all processes, executors and clients in a
+/// `Simulation`, along with the simulation time, that can be later restored
+/// with `Simulation::restore`.
+#[derive(Clone)]
+pub struct SimulationSnapshot<P: Protocol> {
+    time: SimTime,
+    processes: HashMap<ProcessId, (P, P::Executor, AggregatePending)>,
+    clients: HashMap<ClientId, Client>,
+}
+
+impl<P: Protocol> SimulationSnapshot<P> {
+    /// Returns the simulation time (in millis) at which the snapshot was
+    /// taken.
+    pub fn millis(&self) -> u64 {
+        self.time.millis()
+    }
+}
+
 impl<P> Simulation<P>
 where
     P: Protocol,
@@ -32,6 +50,41 @@ where
         &mut self.time
     }
 
+    /// Takes a snapshot of the current state of the `Simulation`.
+    pub fn snapshot(&mut self) -> SimulationSnapshot<P> {
+        let processes = self
+            .processes
+            .iter_mut()
+            .map(|(process_id, cell)| (*process_id, cell.get_mut().clone()))
+            .collect();
+        let clients = self
+            .clients
+            .iter_mut()
+            .map(|(client_id, cell)| (*client_id, cell.get_mut().clone()))
+            .collect();
+        SimulationSnapshot {
+            time: self.time.clone(),
+            processes,
+            clients,
+        }
+    }
+
+    /// Restores the state of the `Simulation` from a snapshot. Note that this
+    /// may move the simulation time backwards.
+    pub fn restore(&mut self, snapshot: SimulationSnapshot<P>) {
+        self.time = snapshot.time;
+        self.processes = snapshot
+            .processes
+            .into_iter()
+            .map(|(process_id, state)| (process_id, Cell::new(state)))
+            .collect();
+        self.clients = snapshot
+            .clients
+            .into_iter()
+            .map(|(client_id, client)| (client_id, Cell::new(client)))
+            .collect();
+    }
+
     /// Registers a `Process` in the `Simulation` by storing it in a `Cell`.
     pub fn register_process(&mut self, process: P, executor: P::Executor) {
         // get identifier
@@ -58,21 +111,30 @@ where
         assert!(res.is_none());
     }
 
-    /// Starts all clients registered in the router.
+    /// Starts all clients registered in the router (each client submits as
+    /// many commands as it can have in flight).
     pub fn start_clients(&mut self) -> Vec<(ClientId, ProcessId, Command)> {
         let time = &self.time;
-        self.clients
-            .iter_mut()
-            .map(|(_, client)| {
-                let client = client.get_mut();
-                // start client
-                let (target_shard, cmd) = client
-                    .cmd_send(time)
-                    .expect("clients should submit at least one command");
-                let process_id = client.shard_process(&target_shard);
-                (client.id(), process_id, cmd)
-            })
-            .collect()
+        let mut submits = Vec::with_capacity(self.clients.len());
+        for client in self.clients.values_mut() {
+            let client = client.get_mut();
+            // start client
+            let (target_shard, cmd) = client
+                .cmd_send(time)
+                .expect("clients should submit at least one command");
+            let process_id = client.shard_process(&target_shard);
+            submits.push((client.id(), process_id, cmd));
+
+            // submit the remaining commands that can be in flight (if the
+            // workload has that many)
+            for _ in 1..client.max_in_flight() {
+                if let Some((target_shard, cmd)) = client.cmd_send(time) {
+                    let process_id = client.shard_process(&target_shard);
+                    submits.push((client.id(), process_id, cmd));
+                }
+            }
+        }
+        submits
     }
 
     /// Forward a `ToSend`.
@@ -167,6 +229,23 @@ where
         (process, executor, pending, &self.time)
     }
 
+    /// Returns all processes registered along with the simulation time. This
+    /// allows processes to be handled in parallel.
+    pub fn get_all_processes(
+        &mut self,
+    ) -> (
+        impl Iterator<
+            Item = (ProcessId, &mut P, &mut P::Executor, &mut AggregatePending),
+        >,
+        &SimTime,
+    ) {
+        let processes = self.processes.iter_mut().map(|(process_id, cell)| {
+            let (process, executor, pending) = cell.get_mut();
+            (*process_id, process, executor, pending)
+        });
+        (processes, &self.time)
+    }
+
     /// Returns the client registered with this identifier.
     /// It panics if the client is not registered.
     pub fn get_client(
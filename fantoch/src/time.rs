@@ -1,8 +1,15 @@
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_os = "linux"))]
+use std::time::Instant;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+// number of samples taken when calibrating a `MonotonicTime`
+const CALIBRATION_SAMPLES: usize = 16;
+
 pub trait SysTime: Send + 'static + Sync /* TODO why is Sync needed here */ {
     fn millis(&self) -> u64;
     fn micros(&self) -> u64;
+    fn nanos(&self) -> u64;
 }
 
 // TODO find a better name
@@ -24,48 +31,209 @@ impl SysTime for RunTime {
     fn micros(&self) -> u64 {
         self.duration_since_unix_epoch().as_micros() as u64
     }
+
+    fn nanos(&self) -> u64 {
+        self.duration_since_unix_epoch().as_nanos() as u64
+    }
+}
+
+/// Time read from a raw monotonic clock (`CLOCK_MONOTONIC_RAW` on Linux),
+/// which, unlike the wall-clock, is neither stepped nor slewed by NTP. Since
+/// the raw clock has an arbitrary origin, it is calibrated against the
+/// wall-clock on creation, so that the times returned can still be compared
+/// with the ones returned by `RunTime` (e.g. in other machines).
+///
+/// This is used to measure latencies in run mode, where the wall-clock being
+/// adjusted during an experiment would distort sub-millisecond latencies.
+#[derive(Clone, Copy)]
+pub struct MonotonicTime {
+    // wall-clock (in nanos since UNIX EPOCH) at calibration
+    wall_start: u64,
+    // raw clock (in nanos) at calibration
+    raw_start: u64,
+    // used as the raw clock where `CLOCK_MONOTONIC_RAW` is not available
+    #[cfg(not(target_os = "linux"))]
+    origin: Instant,
+}
+
+impl MonotonicTime {
+    /// Creates a new `MonotonicTime`, calibrated against the wall-clock.
+    pub fn new() -> Self {
+        let mut time = Self {
+            wall_start: 0,
+            raw_start: 0,
+            #[cfg(not(target_os = "linux"))]
+            origin: Instant::now(),
+        };
+        // read the raw clock between two wall-clock reads several times, and
+        // keep the sample where these reads were the closest, as that's the
+        // one where the raw clock read is the most likely to correspond to
+        // the midpoint of the wall-clock reads
+        let mut best_gap = u64::MAX;
+        for _ in 0..CALIBRATION_SAMPLES {
+            let before = RunTime.nanos();
+            let raw = time.raw_nanos();
+            let after = RunTime.nanos();
+            let gap = after.saturating_sub(before);
+            if gap < best_gap {
+                best_gap = gap;
+                time.wall_start = before + gap / 2;
+                time.raw_start = raw;
+            }
+        }
+        time
+    }
+
+    /// Returns the drift of the wall-clock with respect to the raw clock since
+    /// calibration.
+    pub fn drift(&self) -> ClockDrift {
+        let wall = RunTime.nanos() as i64;
+        let raw = self.nanos() as i64;
+        ClockDrift {
+            elapsed: Duration::from_nanos(self.nanos() - self.wall_start),
+            drift_nanos: wall - raw,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn raw_nanos(&self) -> u64 {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // safety: `ts` is a valid timespec, and `CLOCK_MONOTONIC_RAW` is
+        // supported since Linux 2.6.28
+        let result =
+            unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC_RAW, &mut ts) };
+        assert_eq!(result, 0, "CLOCK_MONOTONIC_RAW should be available");
+        ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn raw_nanos(&self) -> u64 {
+        self.origin.elapsed().as_nanos() as u64
+    }
+}
+
+impl Default for MonotonicTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SysTime for MonotonicTime {
+    fn millis(&self) -> u64 {
+        self.nanos() / 1_000_000
+    }
+
+    fn micros(&self) -> u64 {
+        self.nanos() / 1_000
+    }
+
+    fn nanos(&self) -> u64 {
+        self.wall_start + (self.raw_nanos() - self.raw_start)
+    }
+}
+
+/// Drift of the wall-clock of a machine with respect to its raw monotonic
+/// clock (see `MonotonicTime::drift`): after `elapsed`, the wall-clock was
+/// `drift_nanos` ahead of (or behind, if negative) the raw clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClockDrift {
+    pub elapsed: Duration,
+    pub drift_nanos: i64,
 }
 
-#[derive(Default)]
+impl ClockDrift {
+    /// Returns the drift rate in parts per million.
+    pub fn ppm(&self) -> f64 {
+        let elapsed = self.elapsed.as_nanos() as f64;
+        if elapsed == 0.0 {
+            0.0
+        } else {
+            self.drift_nanos as f64 * 1_000_000.0 / elapsed
+        }
+    }
+}
+
+/// Simulation time, kept with nanosecond resolution so that sub-millisecond
+/// latencies (e.g. in LAN deployments) can be simulated.
+#[derive(Default, Clone)]
 pub struct SimTime {
-    micros: u64,
+    nanos: u64,
 }
 
 impl SimTime {
     /// Creates a new simulation time.
     pub fn new() -> Self {
-        Self { micros: 0 }
+        Self { nanos: 0 }
     }
 
     // Increases simulation time by `millis`.
     pub fn add_millis(&mut self, millis: u64) {
-        self.micros += Self::millis_to_micros(millis);
+        self.add_nanos(Self::millis_to_nanos(millis));
+    }
+
+    // Increases simulation time by `micros`.
+    pub fn add_micros(&mut self, micros: u64) {
+        self.add_nanos(Self::micros_to_nanos(micros));
+    }
+
+    // Increases simulation time by `nanos`.
+    pub fn add_nanos(&mut self, nanos: u64) {
+        self.nanos = self
+            .nanos
+            .checked_add(nanos)
+            .expect("simulation time overflow");
+    }
+
+    // Increases simulation time by `duration`.
+    pub fn add_duration(&mut self, duration: Duration) {
+        self.add_nanos(Self::duration_to_nanos(duration));
     }
 
     /// Sets simulation time.
     pub fn set_millis(&mut self, new_time_millis: u64) {
-        let new_time_micros = Self::millis_to_micros(new_time_millis);
+        self.set_nanos(Self::millis_to_nanos(new_time_millis));
+    }
+
+    /// Sets simulation time (in nanoseconds).
+    pub fn set_nanos(&mut self, new_time_nanos: u64) {
         // make sure time is monotonic
-        assert!(self.micros <= new_time_micros);
-        self.micros = new_time_micros;
+        assert!(self.nanos <= new_time_nanos);
+        self.nanos = new_time_nanos;
     }
 
-    fn millis_to_micros(millis: u64) -> u64 {
-        millis * 1000
+    /// Converts a duration to nanoseconds, panicking if it can't be
+    /// represented in the simulation time.
+    pub fn duration_to_nanos(duration: Duration) -> u64 {
+        let nanos = duration.as_nanos();
+        assert!(nanos <= u64::MAX as u128, "simulation time overflow");
+        nanos as u64
     }
 
-    fn micros_to_millis(micros: u64) -> u64 {
-        micros / 1000
+    fn millis_to_nanos(millis: u64) -> u64 {
+        millis
+            .checked_mul(1_000_000)
+            .expect("simulation time overflow")
+    }
+
+    fn micros_to_nanos(micros: u64) -> u64 {
+        micros.checked_mul(1_000).expect("simulation time overflow")
     }
 }
 
 impl SysTime for SimTime {
+    fn nanos(&self) -> u64 {
+        self.nanos
+    }
+
     fn micros(&self) -> u64 {
-        self.micros
+        self.nanos / 1_000
     }
 
     fn millis(&self) -> u64 {
-        Self::micros_to_millis(self.micros)
+        self.nanos / 1_000_000
     }
 }
 
@@ -92,6 +260,50 @@ mod tests {
         // set time at 20
         time.set_millis(20);
         assert_eq!(time.millis(), 20);
+
+        // sub-millisecond ticks
+        time.add_micros(500);
+        assert_eq!(time.millis(), 20);
+        assert_eq!(time.micros(), 20_500);
+        time.add_nanos(500_001);
+        assert_eq!(time.millis(), 21);
+        assert_eq!(time.micros(), 21_000);
+        assert_eq!(time.nanos(), 21_000_001);
+        time.add_duration(Duration::from_micros(1));
+        assert_eq!(time.nanos(), 21_001_001);
+    }
+
+    #[test]
+    fn monotonic_now() {
+        let time = MonotonicTime::new();
+        // after calibration, it's close to the wall-clock
+        let wall = RunTime.millis();
+        let monotonic = time.millis();
+        assert!((wall as i64 - monotonic as i64).abs() <= 10);
+
+        // and it's monotonic
+        let mut previous = time.nanos();
+        for _ in 0..1000 {
+            let now = time.nanos();
+            assert!(previous <= now);
+            previous = now;
+        }
+
+        let drift = time.drift();
+        assert!(drift.drift_nanos.abs() < 10_000_000);
+
+        let drift = ClockDrift {
+            elapsed: Duration::from_secs(10),
+            drift_nanos: -50_000,
+        };
+        assert_eq!(drift.ppm(), -5.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sim_time_should_not_overflow() {
+        let mut time = SimTime::new();
+        time.add_millis(u64::MAX);
     }
 
     #[test]
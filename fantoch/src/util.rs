@@ -1,7 +1,9 @@
+use crate::client::ProcessSelection;
+use crate::config::Config;
 use crate::id::{Dot, ProcessId, ShardId};
-use crate::kvs::Key;
+use crate::kvs::{Key, Value};
 use crate::planet::{Planet, Region};
-use crate::HashMap;
+use crate::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 
 // warn and info logs are always enabled!
@@ -48,6 +50,25 @@ macro_rules! trace {
     };
 }
 
+// flow events are structured (i.e. with fields such as `dot`, `phase` and
+// `latency`) and logged with target `flow`, which makes it easier to query them
+// when logs are in JSON; as with `debug`, they are enabled if
+// `max_level_debug` or `max_level_trace`
+
+#[macro_export]
+#[cfg(any(feature = "max_level_debug", feature = "max_level_trace"))]
+macro_rules! flow {
+    ($( $args:tt )*) => { tracing::debug!(target: "flow", $( $args )*); }
+}
+
+#[macro_export]
+#[cfg(not(any(feature = "max_level_debug", feature = "max_level_trace")))]
+macro_rules! flow {
+    ($( $args:tt )*) => {
+        ()
+    };
+}
+
 /// create a singleton hash set
 #[macro_export]
 macro_rules! singleton {
@@ -73,6 +94,7 @@ macro_rules! elapsed {
 pub fn init_tracing_subscriber(
     log_file: Option<impl AsRef<std::path::Path> + std::fmt::Debug>,
     tracing_directives: Option<&'static str>,
+    log_json: bool,
 ) -> tracing_appender::non_blocking::WorkerGuard {
     // create log format
     let format = tracing_subscriber::fmt::format()
@@ -91,6 +113,7 @@ pub fn init_tracing_subscriber(
 
     println!("log_file: {:?}", log_file);
     println!("env_filter: {}", env_filter);
+    println!("log_json: {}", log_json);
 
     // create writer
     let builder = tracing_appender::non_blocking::NonBlockingBuilder::default()
@@ -102,11 +125,25 @@ pub fn init_tracing_subscriber(
         None => builder.finish(std::io::stdout()),
     };
 
-    tracing_subscriber::fmt()
-        .event_format(format)
+    let subscriber = tracing_subscriber::fmt()
         .with_writer(non_blocking_appender)
-        .with_env_filter(env_filter)
-        .init();
+        .with_env_filter(env_filter);
+    if log_json {
+        // in JSON mode, each event is written as a single line with its fields
+        // at the top level, and with time, level and target included so that
+        // logs can be ingested and queried without any custom parsing
+        let format = format
+            .with_timer(tracing_subscriber::fmt::time::SystemTime)
+            .with_target(true)
+            .with_level(true)
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(false);
+        subscriber.event_format(format).init();
+    } else {
+        subscriber.event_format(format).init();
+    }
 
     guard
 }
@@ -121,6 +158,29 @@ pub fn key_hash(key: &Key) -> u64 {
     hasher.finish()
 }
 
+/// Compute the hash of a value (as an hexadecimal string), which can be sent
+/// back to clients in its place.
+#[allow(clippy::ptr_arg)]
+pub fn value_hash(value: &Value) -> Value {
+    let mut hasher = DefaultHasher::default();
+    value.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Returns the keys to be preloaded in shard `shard_id` (see
+/// `Config::preload_keys`), i.e. the ones in "1" to "`preload_keys`" that
+/// belong to this shard.
+pub fn preloaded_keys(
+    config: &Config,
+    shard_id: ShardId,
+) -> impl Iterator<Item = Key> {
+    let shard_count = config.shard_count() as u64;
+    let preload_keys = config.preload_keys().unwrap_or_default();
+    (1..=preload_keys)
+        .map(|key| key.to_string())
+        .filter(move |key| key_hash(key) % shard_count == shard_id)
+}
+
 /// Returns an iterator with all process identifiers in this shard in a system
 /// with `n` processes.
 pub fn process_ids(
@@ -184,6 +244,46 @@ pub fn sort_processes_by_distance(
         .collect()
 }
 
+/// Derives the sorted processes of `process_id` (i.e. the ones it should be
+/// connected to, sorted by distance) given the region of each process: all
+/// processes in its shard, and the closest process of each other shard.
+/// `process_id` is always the first process returned.
+pub fn derive_sorted_processes(
+    process_id: ProcessId,
+    shard_id: ShardId,
+    planet: &Planet,
+    processes: Vec<(ProcessId, ShardId, Region)>,
+) -> Result<Vec<(ProcessId, ShardId)>, String> {
+    let region = processes
+        .iter()
+        .find(|(id, _, _)| *id == process_id)
+        .map(|(_, _, region)| region.clone())
+        .ok_or_else(|| format!("no region set for process {}", process_id))?;
+    let regions = planet.regions();
+    for (id, _, region) in processes.iter() {
+        if !regions.contains(&planet.canonical(region)) {
+            return Err(format!(
+                "region {:?} of process {} is not part of the planet",
+                region, id
+            ));
+        }
+    }
+
+    let mut shards_seen = HashSet::new();
+    shards_seen.insert(shard_id);
+    let sorted = sort_processes_by_distance(&region, planet, processes)
+        .into_iter()
+        .filter(|(id, peer_shard_id)| {
+            // keep all processes in my shard, and the first in each other
+            *id != process_id
+                && (*peer_shard_id == shard_id
+                    || shards_seen.insert(*peer_shard_id))
+        });
+    Ok(std::iter::once((process_id, shard_id))
+        .chain(sorted)
+        .collect())
+}
+
 /// Returns a mapping from shard id to the closest process on that shard.
 pub fn closest_process_per_shard(
     region: &Region,
@@ -200,6 +300,28 @@ pub fn closest_process_per_shard(
     processes
 }
 
+/// Returns a mapping from shard id to the process on that shard selected by
+/// `selection`.
+pub fn select_process_per_shard(
+    region: &Region,
+    planet: &Planet,
+    processes: Vec<(ProcessId, ShardId, Region)>,
+    selection: ProcessSelection,
+) -> HashMap<ShardId, ProcessId> {
+    let sorted = sort_processes_by_distance(region, planet, processes);
+    let mut shard_to_sorted = HashMap::new();
+    for (process_id, shard_id) in sorted {
+        shard_to_sorted
+            .entry(shard_id)
+            .or_insert_with(Vec::new)
+            .push(process_id);
+    }
+    shard_to_sorted
+        .into_iter()
+        .map(|(shard_id, sorted)| (shard_id, selection.select(&sorted)))
+        .collect()
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -264,4 +386,102 @@ pub mod tests {
 
         assert_eq!(expected, sorted);
     }
+
+    #[test]
+    fn derive_sorted_processes_test() {
+        // processes of two shards (n = 3)
+        let regions = [
+            Region::new("europe-west3"),
+            Region::new("us-east1"),
+            Region::new("asia-east1"),
+        ];
+        let processes: Vec<_> = all_process_ids(2, 3)
+            .map(|(process_id, shard_id)| {
+                let region = regions[(process_id as usize - 1) % 3].clone();
+                (process_id, shard_id, region)
+            })
+            .collect();
+        let planet = Planet::new();
+
+        // process 4 is first, even though process 1 is in the same region
+        let sorted =
+            derive_sorted_processes(4, 1, &planet, processes.clone()).unwrap();
+        assert_eq!(sorted, vec![(4, 1), (1, 0), (5, 1), (6, 1)]);
+        let sorted =
+            derive_sorted_processes(2, 0, &planet, processes.clone()).unwrap();
+        assert_eq!(sorted, vec![(2, 0), (5, 1), (1, 0), (3, 0)]);
+
+        // unknown processes and regions are reported
+        assert!(
+            derive_sorted_processes(7, 0, &planet, processes.clone()).is_err()
+        );
+        let mut processes = processes;
+        processes[0].2 = Region::new("mars-west1");
+        assert!(derive_sorted_processes(2, 0, &planet, processes).is_err());
+    }
+
+    #[test]
+    fn select_process_per_shard_test() {
+        // processes of two shards (n = 3)
+        let regions = [
+            Region::new("europe-west3"),
+            Region::new("us-east1"),
+            Region::new("asia-east1"),
+        ];
+        let processes: Vec<_> = all_process_ids(2, 3)
+            .map(|(process_id, shard_id)| {
+                let region = regions[(process_id as usize - 1) % 3].clone();
+                (process_id, shard_id, region)
+            })
+            .collect();
+
+        let region = Region::new("europe-west3");
+        let planet = Planet::new();
+        let select = |selection| {
+            let mut selected: Vec<_> = select_process_per_shard(
+                &region,
+                &planet,
+                processes.clone(),
+                selection,
+            )
+            .into_iter()
+            .collect();
+            selected.sort();
+            selected
+        };
+
+        // the default is the closest process
+        assert_eq!(select(ProcessSelection::default()), vec![(0, 1), (1, 4)]);
+        assert_eq!(
+            select(ProcessSelection::KthClosest(0)),
+            vec![(0, 1), (1, 4)]
+        );
+        assert_eq!(
+            select(ProcessSelection::KthClosest(1)),
+            vec![(0, 2), (1, 5)]
+        );
+        assert_eq!(
+            select(ProcessSelection::KthClosest(2)),
+            vec![(0, 3), (1, 6)]
+        );
+        assert_eq!(select(ProcessSelection::Fixed(3)), vec![(0, 3), (1, 6)]);
+    }
+
+    #[test]
+    fn preloaded_keys_test() {
+        let mut config = Config::new(3, 1);
+        config.set_shard_count(2);
+
+        // by default, no key is preloaded
+        assert_eq!(preloaded_keys(&config, 0).count(), 0);
+
+        // otherwise, each key is preloaded in a single shard
+        config.set_preload_keys(100);
+        let mut keys: Vec<_> = preloaded_keys(&config, 0)
+            .chain(preloaded_keys(&config, 1))
+            .map(|key| key.parse::<usize>().unwrap())
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(keys, (1..=100).collect::<Vec<_>>());
+    }
 }
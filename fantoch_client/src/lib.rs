@@ -0,0 +1,185 @@
+#![deny(rust_2018_idioms)]
+
+// Re-exports.
+pub use fantoch::command::{Command, CommandResult};
+pub use fantoch::id::{ClientId, ProcessId, Rifl, ShardId};
+pub use fantoch::kvs::{KVOp, KVOpResult, Key};
+
+use color_eyre::eyre::{Report, WrapErr};
+use fantoch::client::wire::{self, ClientHi, ClientToServer, ProcessHi};
+use fantoch::id::RiflGen;
+use serde::Serialize;
+use std::io::{BufReader, BufWriter, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+/// Blocking client that submits commands to a single `fantoch` process (and
+/// thus to a single shard). Unlike the clients in `fantoch::run`, it doesn't
+/// depend on tokio, and so it can be embedded in any application.
+#[derive(Debug)]
+pub struct Client {
+    rifl_gen: RiflGen,
+    process_id: ProcessId,
+    shard_id: ShardId,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl Client {
+    /// Connects to the process listening for clients at `address`.
+    pub fn connect<A>(address: A, client_id: ClientId) -> Result<Self, Report>
+    where
+        A: ToSocketAddrs,
+    {
+        let stream =
+            TcpStream::connect(address).wrap_err("connect to process")?;
+        stream.set_nodelay(true).wrap_err("set nodelay")?;
+        let reader =
+            BufReader::new(stream.try_clone().wrap_err("clone stream")?);
+        let writer = BufWriter::new(stream);
+
+        let mut client = Self {
+            rifl_gen: RiflGen::new(client_id),
+            process_id: 0,
+            shard_id: 0,
+            reader,
+            writer,
+        };
+
+        // say hi and wait for the hi back
+        client.send(&ClientHi(vec![client_id]))?;
+        let ProcessHi {
+            process_id,
+            shard_id,
+            ..
+        } = wire::read_frame(&mut client.reader)
+            .wrap_err("receive hi from process")?;
+        client.process_id = process_id;
+        client.shard_id = shard_id;
+        Ok(client)
+    }
+
+    /// Returns the client identifier.
+    pub fn id(&self) -> ClientId {
+        self.rifl_gen.source()
+    }
+
+    /// Returns the identifier of the process the client is connected to.
+    pub fn process_id(&self) -> ProcessId {
+        self.process_id
+    }
+
+    /// Returns the shard of the process the client is connected to.
+    pub fn shard_id(&self) -> ShardId {
+        self.shard_id
+    }
+
+    /// Generates the `Rifl` of the next command.
+    pub fn next_rifl(&mut self) -> Rifl {
+        self.rifl_gen.next_id()
+    }
+
+    /// Submits a command, without waiting for its result.
+    pub fn submit(&mut self, cmd: Command) -> Result<(), Report> {
+        self.send(&ClientToServer::Submit(cmd))
+    }
+
+    /// Registers a command submitted at another shard, so that its result in
+    /// this shard is sent to the client.
+    pub fn register(&mut self, cmd: Command) -> Result<(), Report> {
+        self.send(&ClientToServer::Register(cmd))
+    }
+
+    /// Cancels a command (if it has not yet been committed).
+    pub fn cancel(&mut self, rifl: Rifl) -> Result<(), Report> {
+        self.send(&ClientToServer::Cancel(rifl))
+    }
+
+    /// Waits for the next command result.
+    pub fn recv(&mut self) -> Result<CommandResult, Report> {
+        wire::read_frame(&mut self.reader).wrap_err("receive command result")
+    }
+
+    /// Submits a command with the operations provided and waits for its
+    /// result. The results of other commands received in the meantime are
+    /// discarded.
+    pub fn execute<I>(&mut self, ops: I) -> Result<CommandResult, Report>
+    where
+        I: IntoIterator<Item = (Key, KVOp)>,
+    {
+        let rifl = self.next_rifl();
+        self.submit(Command::from(rifl, ops))?;
+        loop {
+            let cmd_result = self.recv()?;
+            if cmd_result.rifl() == rifl {
+                return Ok(cmd_result);
+            }
+        }
+    }
+
+    fn send<V>(&mut self, value: &V) -> Result<(), Report>
+    where
+        V: Serialize,
+    {
+        wire::write_frame(&mut self.writer, value)
+            .and_then(|_| self.writer.flush())
+            .wrap_err("send to process")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::HashMap;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn client_flow() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let address = listener.local_addr().expect("local addr");
+
+        // process that replies to each command with its rifl
+        let process = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("accept");
+            let ClientHi(client_ids) =
+                wire::read_frame(&mut stream).expect("hi");
+            assert_eq!(client_ids, vec![10]);
+            let hi = ProcessHi {
+                process_id: 2,
+                shard_id: 1,
+                epoch: 0,
+            };
+            wire::write_frame(&mut stream, &hi).expect("hi back");
+
+            let msg: ClientToServer =
+                wire::read_frame(&mut stream).expect("submit");
+            let cmd = match msg {
+                ClientToServer::Submit(cmd) => cmd,
+                msg => panic!("unexpected message: {:?}", msg),
+            };
+            let rifl = cmd.rifl();
+            let results = cmd
+                .into_iter(0)
+                .map(|(key, ops)| (key, vec![None; ops.ops().len()]))
+                .collect();
+            let cmd_result = CommandResult::new(rifl, results);
+            wire::write_frame(&mut stream, &cmd_result).expect("result");
+        });
+
+        let mut client = Client::connect(address, 10).expect("connect");
+        assert_eq!(client.id(), 10);
+        assert_eq!(client.process_id(), 2);
+        assert_eq!(client.shard_id(), 1);
+
+        let key = String::from("A");
+        let cmd_result = client
+            .execute(vec![(key.clone(), KVOp::Put(String::from("a")))])
+            .expect("execute");
+        assert_eq!(cmd_result.rifl(), Rifl::new(10, 1));
+        let mut expected = HashMap::new();
+        expected.insert(key, vec![None]);
+        assert_eq!(cmd_result.results(), &expected);
+
+        process.join().expect("process");
+    }
+}
@@ -119,8 +119,9 @@ setup() {
     # reload system configuration so that previous changes  take place
     sudo sysctl --system
 
-    # install htop, dstat and lsof
-    sudo apt-get install -y htop dstat lsof
+    # install htop, dstat, lsof and gdb (used to dump the stacks of processes
+    # in runs that stall)
+    sudo apt-get install -y htop dstat lsof gdb
     dstat --help
     lsof -h
 
@@ -152,38 +153,56 @@ build_fantoch() {
 
     # build all the binaries in release mode for maximum performance:
     # - build if features enabled if any features were defined
+    # - tokio's runtime metrics are only available with `tokio_unstable`
     cd "${FANTOCH_PACKAGE}"
+    local rustflags="-C target-cpu=native"
+    if [[ "${features}" == *"runtime-metrics"* ]]; then
+        rustflags="${rustflags} --cfg tokio_unstable"
+    fi
     if [ "${features}" == "" ]; then
-        RUSTFLAGS="-C target-cpu=native" cargo build --release --bins
+        RUSTFLAGS="${rustflags}" cargo build --release --bins
     else
-        RUSTFLAGS="-C target-cpu=native" cargo build --release --bins --features ${features}
+        RUSTFLAGS="${rustflags}" cargo build --release --bins --features ${features}
     fi
 }
 
-if [[ $# < 3 || $# > 4 ]]; then
-    echo "usage: setup.sh testbed mode branch (features)"
+if [[ $# < 4 || $# > 5 ]]; then
+    echo "usage: setup.sh testbed mode branch steps (features)"
     exit 1
 fi
 
 testbed=$1
 mode=$2
 branch=$3
-features=$4
+steps=$4 # possible values: all, setup, build
+features=$5
 
-case ${testbed} in
-"aws")
-    setup "true" ${mode}
-    ;;
-"baremetal")
-    setup "false" ${mode}
-    ;;
-"local")
-    # nothing to setup
+case ${steps} in
+"all" | "setup" | "build")
     ;;
 *)
-    echo "invalid testbed: ${testbed}"
+    echo "invalid steps: ${steps}"
     exit 1
 esac
 
-# in all cases, build fantoch
-build_fantoch ${branch} ${features}
+if [ "${steps}" != "build" ]; then
+    case ${testbed} in
+    "aws")
+        setup "true" ${mode}
+        ;;
+    "baremetal")
+        setup "false" ${mode}
+        ;;
+    "local")
+        # nothing to setup
+        ;;
+    *)
+        echo "invalid testbed: ${testbed}"
+        exit 1
+    esac
+fi
+
+# build fantoch unless its binaries are going to be copied to this machine
+if [ "${steps}" != "setup" ]; then
+    build_fantoch ${branch} ${features}
+fi
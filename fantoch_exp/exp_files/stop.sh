@@ -50,7 +50,7 @@ stop_fantoch() {
     ssh "${SSH_ARGS}" ${machine} "${cmd}" </dev/null
 
     # remove files
-    cmd="rm -f *.metrics *.log *.err *.dstat.csv heaptrack.*.gz *perf.data*"
+    cmd="rm -f *.metrics *.log *.err *.dstat.csv *.stacks heaptrack.*.gz *perf.data*"
     ssh "${SSH_ARGS}" ${machine} "${cmd}" </dev/null
 
     cmd="ls -ltrah | grep G"
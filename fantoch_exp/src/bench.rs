@@ -1,26 +1,53 @@
 use crate::config::{
-    self, ClientConfig, ExperimentConfig, ProcessType, ProtocolConfig,
-    RegionIndex,
+    self, ClientConfig, ClientMachineIndex, ClientsPerRegion, Colocation,
+    DeploymentIndex, ExperimentConfig, ProcessType, ProtocolConfig,
+    RegionFailure, RegionIndex, TcpConfig,
 };
+use crate::dashboard::Dashboard;
+use crate::journal::Journal;
+use crate::lint;
 use crate::machine::{Machine, Machines};
+use crate::metadata::{
+    self, ExperimentMetadata, MachineMetadata, MachinePlacement,
+};
 use crate::progress::TracingProgressBar;
+use crate::summary::Summary;
+use crate::sweep;
 use crate::{FantochFeature, Protocol, RunMode, SerializationFormat, Testbed};
 use color_eyre::eyre::{self, WrapErr};
 use color_eyre::Report;
-use fantoch::client::{KeyGen, Workload};
+use fantoch::client::{
+    ClientData, ClientStats, KeyGen, ProcessSelection, WorkloadMix,
+};
 use fantoch::config::Config;
-use fantoch::id::ProcessId;
+use fantoch::error::RunError;
+use fantoch::id::{ProcessId, ShardId};
 use fantoch::planet::{Planet, Region};
-use std::collections::HashMap;
+use fantoch::protocol::ReadOnlySupport;
+use fantoch::run::task::server::metrics_logger;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::Path;
 use tokio::time::Duration;
 
 type Ips = HashMap<ProcessId, String>;
+type Processes = HashMap<ProcessId, (Region, tokio::process::Child)>;
 
 const LOG_FILE_EXT: &str = "log";
 const ERR_FILE_EXT: &str = "err";
 const DSTAT_FILE_EXT: &str = "dstat.csv";
 const METRICS_FILE_EXT: &str = "metrics";
+const PING_FILE_EXT: &str = "ping";
+const TRACES_FILE_EXT: &str = "traces";
+const STACKS_FILE_EXT: &str = "stacks";
+// suffix of the directory (next to the experiment directory) where the
+// diagnostics of a run that stalled are saved
+const DIAGNOSTICS_DIR_SUFFIX: &str = "diagnostics";
+// interval between checks of the health of machines (only if there's a
+// dashboard)
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// interval between checks of the number of commands committed by processes
+// (only if `ExperimentTimeouts::stalled` is set)
+const PROGRESS_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 pub(crate) const FLAMEGRAPH_FILE_EXT: &str = "flamegraph.svg";
 
 #[derive(Clone, Copy)]
@@ -28,6 +55,36 @@ pub struct ExperimentTimeouts {
     pub start: Option<Duration>,
     pub run: Option<Duration>,
     pub stop: Option<Duration>,
+    // runs where clients complete no command for this long are aborted, and
+    // the combination being run is skipped (not retried)
+    pub zero_throughput: Option<Duration>,
+    // runs where processes commit no command for this long are aborted (and
+    // retried), after collecting diagnostics from all machines (see
+    // `collect_diagnostics`)
+    pub stalled: Option<Duration>,
+}
+
+// Sanity checks performed on the results of each experiment: experiments whose
+// results fail them are rerun up to `max_reruns` times; if they still fail, the
+// checks that failed are recorded in `ExperimentConfig::sanity_failures`.
+#[derive(Clone, Copy)]
+pub struct SanityChecks {
+    pub max_reruns: usize,
+    // max number of errors logged by clients
+    pub max_client_errors: usize,
+}
+
+// Checks, performed before any experiment starts, that the latencies between
+// processes match the ones in the planet (when delays are injected): the RTT
+// between each pair of server machines is measured with `pings` pings, and the
+// injected delay is added to it; if the result differs from the planet's
+// latency by more than `tolerance`, experiments are aborted (if `abort` is set)
+// or a warning is logged.
+#[derive(Clone, Copy)]
+pub struct LatencyChecks {
+    pub pings: usize,
+    pub tolerance: Duration,
+    pub abort: bool,
 }
 
 #[derive(Debug)]
@@ -40,30 +97,89 @@ impl std::fmt::Display for TimeoutError {
 }
 impl std::error::Error for TimeoutError {}
 
+#[derive(Debug)]
+struct ZeroThroughputError(Region);
+
+impl std::fmt::Display for ZeroThroughputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for ZeroThroughputError {}
+
+// Error returned when processes stop committing commands, with the number of
+// commands committed until then.
+#[derive(Debug)]
+struct StalledError(u64);
+
+impl std::fmt::Display for StalledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl std::error::Error for StalledError {}
+
 pub async fn bench_experiment(
-    machines: Machines<'_>,
+    mut machines: Machines<'_>,
     run_mode: RunMode,
     max_log_level: &tracing::Level,
     features: Vec<FantochFeature>,
+    branch: String,
     testbed: Testbed,
     planet: Option<Planet>,
     configs: Vec<(Protocol, Config)>,
-    clients_per_region: Vec<usize>,
-    workloads: Vec<Workload>,
+    co_deployed: Option<Protocol>,
+    clients_per_region: Vec<ClientsPerRegion>,
+    colocation: Option<Colocation>,
+    workloads: Vec<WorkloadMix>,
     batch_max_sizes: Vec<usize>,
     batch_max_delay: Duration,
     cpus: usize,
-    skip: impl Fn(Protocol, Config, usize) -> bool,
+    process_tcp_config: TcpConfig,
+    client_tcp_config: TcpConfig,
+    ping_interval: Option<Duration>,
+    region_failure: Option<RegionFailure>,
+    run_duration: Option<Duration>,
+    skip: impl Fn(Protocol, Config, ClientsPerRegion) -> bool,
+    run_order_seed: Option<u64>,
+    experiment_seed: Option<u64>,
     experiment_timeouts: ExperimentTimeouts,
-    protocols_to_cleanup: Vec<Protocol>,
+    sanity_checks: Option<SanityChecks>,
+    latency_checks: Option<LatencyChecks>,
     progress: TracingProgressBar,
+    dashboard: Option<Dashboard>,
     results_dir: impl AsRef<Path>,
 ) -> Result<(), Report> {
+    // flamegraph and heaptrack files are not per deployment
+    assert!(
+        co_deployed.is_none() || run_mode == RunMode::Release,
+        "co-deployments are only supported in release mode"
+    );
+
+    // check the spec before running anything
+    lint::lint(&configs, &batch_max_sizes).wrap_err("lint")?;
+
+    // record all commands executed on the machines, so that they can be
+    // audited (and replayed) if something fails
+    let journal = Journal::create(&results_dir).wrap_err("create journal")?;
+    machines.set_journal(journal);
+
+    // connections to processes must present a token unique to this bench, so
+    // that stray connections (e.g. from processes or clients of previous
+    // experiments that were left running) are dropped
+    let auth_token = Some(random_auth_token());
+    let process_tcp_config = TcpConfig {
+        auth_token,
+        ..process_tcp_config
+    };
+    let client_tcp_config = TcpConfig {
+        auth_token,
+        ..client_tcp_config
+    };
+
     match testbed {
         Testbed::Local | Testbed::Baremetal => {
-            cleanup(&machines, protocols_to_cleanup)
-                .await
-                .wrap_err("initial cleanup")?;
+            cleanup(&machines).await.wrap_err("initial cleanup")?;
             tracing::info!("initial cleanup completed");
         }
         Testbed::Aws => {
@@ -71,114 +187,325 @@ pub async fn bench_experiment(
         }
     }
 
+    // check that the latencies to be injected are the ones observed (only if
+    // delays are injected)
+    if let Some(latency_checks) = latency_checks {
+        let mismatches = check_latencies(&machines, &planet, latency_checks)
+            .await
+            .wrap_err("check_latencies")?;
+        if !mismatches.is_empty() {
+            if latency_checks.abort {
+                eyre::bail!("latency mismatches: {:?}", mismatches);
+            }
+            tracing::warn!("latency mismatches: {:?}", mismatches);
+        }
+    }
+
+    // record the environment in which experiments run, so that it can be
+    // compared across experiments (and results of different commits aren't
+    // mixed by accident)
+    let commit = match crate::machine::branch_commit(&branch).await {
+        Ok(commit) => Some(commit),
+        Err(e) => {
+            tracing::warn!("commit of branch {} not found: {:?}", branch, e);
+            None
+        }
+    };
+    let exp_metadata = ExperimentMetadata {
+        branch,
+        commit,
+        machines: machines_metadata(&machines, testbed)
+            .await
+            .wrap_err("machines_metadata")?,
+    };
+
+    if let Some(dashboard) = &dashboard {
+        dashboard.set_total(
+            batch_max_sizes.len()
+                * configs.len()
+                * workloads.len()
+                * clients_per_region.len(),
+        );
+    }
+
+    // compute all runs (in a deterministic order) and maybe shuffle them (so
+    // that time-of-day effects are spread across them)
+    let mut runs = Vec::with_capacity(
+        batch_max_sizes.len()
+            * configs.len()
+            * workloads.len()
+            * clients_per_region.len(),
+    );
     for batch_max_size in &batch_max_sizes {
         for &(protocol, config) in &configs {
-            for workload in &workloads {
+            for workload_mix in &workloads {
                 for &clients in &clients_per_region {
-                    // check that we have the correct number of server machines
-                    assert_eq!(
-                        machines.server_count(),
-                        config.n() * config.shard_count(),
-                        "not enough server machines"
-                    );
+                    runs.push((
+                        batch_max_size,
+                        protocol,
+                        config,
+                        workload_mix,
+                        clients,
+                    ));
+                }
+            }
+        }
+    }
+    if let Some(seed) = run_order_seed {
+        tracing::info!("shuffling runs with seed {}", seed);
+        sweep::shuffle(&mut runs, seed);
+    }
 
-                    // check that we have the correct number of client machines
-                    assert_eq!(
-                        machines.client_count(),
-                        config.n(),
-                        "not enough client machines"
-                    );
+    for (batch_max_size, protocol, config, workload_mix, clients) in runs {
+        // seed the workload of clients (if there's an experiment seed), so
+        // that they generate the same commands across runs
+        let mut workload_mix = *workload_mix;
+        workload_mix.set_seed(experiment_seed);
+
+        // check that we have the correct number of server machines
+        assert_eq!(
+            machines.server_count(),
+            config.n() * config.shard_count(),
+            "not enough server machines"
+        );
 
-                    // maybe skip configuration
-                    if skip(protocol, config, clients) {
-                        progress.inc();
-                        continue;
+        // check that we have the correct number of client machines
+        assert_eq!(
+            machines.client_count(),
+            config.n() * machines.client_machines_per_region(),
+            "not enough client machines"
+        );
+
+        // maybe skip configuration
+        if skip(protocol, config, clients) {
+            progress.inc();
+            if let Some(dashboard) = &dashboard {
+                dashboard.inc();
+            }
+            continue;
+        }
+
+        // compute the protocol of each deployment: the
+        // co-deployed protocol (if any) runs side-by-side with
+        // `protocol`, using the same config and a mirrored
+        // workload
+        let protocols: Vec<_> =
+            std::iter::once(protocol).chain(co_deployed).collect();
+
+        // check each workload in the mix
+        for (workload, _) in workload_mix.workloads() {
+            if workload.read_only_percentage() > 0 {
+                if let Some(protocol) = protocols.iter().find(|protocol| {
+                    protocol.capabilities().read_only
+                        == ReadOnlySupport::Unsupported
+                }) {
+                    panic!("{:?} doesn't support read-only commands", protocol)
+                }
+            }
+
+            if let KeyGen::ConflictPool { .. } = workload.key_gen() {
+                if workload.shard_count() > 1 {
+                    // the conflict rate key gen is weird in partial
+                    // replication; for example, consider the case where
+                    // commands access two shards (so,
+                    // `shards_per_command = 2`) and the conflict rate
+                    // is 0; further, assume that client A issued
+                    // command first a command X on shards 0 and 1 and
+                    // then a command Y on shards 1 and 2; even though
+                    // the conflict rate is 0, since we use the client
+                    // identifier to make the command doesn't conflict
+                    // with commands from another clients, commands from
+                    // the same client conflict with itself; thus,
+                    // command Y will depend on command X; this means
+                    // that shard 2 needs to learn about command X in
+                    // order to be able to execute command Y. overall,
+                    // we have a non-conflicting workload that's
+                    // non-genuine, and that doesn't seem right. for
+                    // this reason, we simply don't allow it
+                    // panic!("invalid workload; conflict rate key gen
+                    // is inappropriate for partial replication
+                    // scenarios");
+                    panic!("conflict rate key generator is not suitable for partial replication");
+                }
+            }
+        }
+
+        if let Some(dashboard) = &dashboard {
+            dashboard.start_combination(format!(
+                "{:?} | n = {} | f = {} | shards = {} | clients per region = {} | batch max size = {} | {:?}",
+                protocols,
+                config.n(),
+                config.f(),
+                config.shard_count(),
+                clients,
+                batch_max_size,
+                workload_mix
+            ));
+        }
+
+        // number of times the experiment was rerun because its
+        // results failed the sanity checks
+        let mut reruns = 0;
+        loop {
+            // create one experiment directory per deployment
+            let mut exp_dirs = Vec::with_capacity(protocols.len());
+            for protocol in &protocols {
+                let exp_dir = create_exp_dir(&results_dir)
+                    .await
+                    .wrap_err("create_exp_dir")?;
+                crate::serialize(
+                    &exp_metadata,
+                    format!("{}/{}", exp_dir, metadata::METADATA_FILE),
+                    SerializationFormat::Json,
+                )
+                .wrap_err("save_exp_metadata")?;
+                tracing::info!(
+                    "experiment metrics of {:?} will be saved in {}",
+                    protocol,
+                    exp_dir
+                );
+                exp_dirs.push(exp_dir);
+            }
+            let run = run_experiment(
+                &machines,
+                run_mode,
+                max_log_level,
+                &features,
+                testbed,
+                &planet,
+                &protocols,
+                config,
+                clients,
+                colocation,
+                workload_mix,
+                *batch_max_size,
+                batch_max_delay,
+                cpus,
+                process_tcp_config,
+                client_tcp_config,
+                ping_interval,
+                &region_failure,
+                run_duration,
+                experiment_timeouts,
+                dashboard.as_ref(),
+                &exp_metadata,
+                &exp_dirs,
+            );
+            if let Err(e) = run.await {
+                // if some client had zero throughput, the combination is
+                // clearly failing, so skip it instead of retrying it
+                let zero_throughput = e.chain().find_map(|error| {
+                    error.downcast_ref::<ZeroThroughputError>()
+                });
+                if let Some(ZeroThroughputError(region)) = zero_throughput {
+                    tracing::warn!(
+                        "zero throughput in region {:?}; will cleanup and skip this combination",
+                        region
+                    );
+                    for exp_dir in exp_dirs {
+                        tokio::fs::remove_dir_all(exp_dir)
+                            .await
+                            .wrap_err("remove exp dir")?;
+                    }
+                    cleanup(&machines).await?;
+                    progress.inc();
+                    if let Some(dashboard) = &dashboard {
+                        dashboard.inc();
                     }
+                    break;
+                }
 
-                    if protocol == Protocol::TempoAtomic
-                        && workload.read_only_percentage() > 0
-                    {
-                        panic!("TempoAtomic doesn't support read-only commands")
+                // check if it's a timeout error, a stalled run or a transient
+                // run error
+                let stalled = e
+                    .chain()
+                    .find_map(|error| error.downcast_ref::<StalledError>());
+                let retry = match (e.downcast_ref::<TimeoutError>(), stalled) {
+                    (Some(TimeoutError(source)), _) => {
+                        tracing::warn!(
+                            "timeout in {:?}; will cleanup and try again",
+                            source
+                        );
+                        true
+                    }
+                    (None, Some(StalledError(committed))) => {
+                        tracing::warn!(
+                            "run stalled after {} committed commands; will cleanup and try again",
+                            committed
+                        );
+                        true
+                    }
+                    (None, None) => match RunError::find(&e) {
+                        Some(error) if error.is_transient() => {
+                            tracing::warn!(
+                                "run failed ({}); will cleanup and try again",
+                                error.category()
+                            );
+                            true
+                        }
+                        _ => false,
+                    },
+                };
+                if retry {
+                    // if so, cleanup and restart the experiment
+                    for exp_dir in exp_dirs {
+                        tokio::fs::remove_dir_all(exp_dir)
+                            .await
+                            .wrap_err("remove exp dir")?;
+                    }
+                    cleanup(&machines).await?;
+                } else {
+                    // if not, quit
+                    return Err(e);
+                }
+            } else {
+                // if there's no error, maybe check the results;
+                // results of experiments with a region failure
+                // are not expected to pass the sanity checks
+                let sanity_checks =
+                    sanity_checks.filter(|_| region_failure.is_none());
+                if let Some(sanity_checks) = sanity_checks {
+                    let mut failed = false;
+                    let mut failures = Vec::with_capacity(exp_dirs.len());
+                    for exp_dir in &exp_dirs {
+                        let exp_failures =
+                            check_results(&machines, sanity_checks, exp_dir)
+                                .wrap_err("check_results")?;
+                        failed |= !exp_failures.is_empty();
+                        failures.push(exp_failures);
                     }
 
-                    if let KeyGen::ConflictPool { .. } = workload.key_gen() {
-                        if workload.shard_count() > 1 {
-                            // the conflict rate key gen is weird in partial
-                            // replication; for example, consider the case where
-                            // commands access two shards (so,
-                            // `shards_per_command = 2`) and the conflict rate
-                            // is 0; further, assume that client A issued
-                            // command first a command X on shards 0 and 1 and
-                            // then a command Y on shards 1 and 2; even though
-                            // the conflict rate is 0, since we use the client
-                            // identifier to make the command doesn't conflict
-                            // with commands from another clients, commands from
-                            // the same client conflict with itself; thus,
-                            // command Y will depend on command X; this means
-                            // that shard 2 needs to learn about command X in
-                            // order to be able to execute command Y. overall,
-                            // we have a non-conflicting workload that's
-                            // non-genuine, and that doesn't seem right. for
-                            // this reason, we simply don't allow it
-                            // panic!("invalid workload; conflict rate key gen
-                            // is inappropriate for partial replication
-                            // scenarios");
-                            panic!("conflict rate key generator is not suitable for partial replication");
+                    if failed && reruns < sanity_checks.max_reruns {
+                        // if the results failed the sanity checks,
+                        // remove them and rerun the experiment
+                        tracing::warn!("sanity checks failed: {:?}; will run the experiment again", failures);
+                        for exp_dir in exp_dirs {
+                            tokio::fs::remove_dir_all(exp_dir)
+                                .await
+                                .wrap_err("remove exp dir")?;
                         }
+                        reruns += 1;
+                        continue;
                     }
 
-                    loop {
-                        let exp_dir = create_exp_dir(&results_dir)
-                            .await
-                            .wrap_err("create_exp_dir")?;
-                        tracing::info!(
-                            "experiment metrics will be saved in {}",
-                            exp_dir
-                        );
-                        let run = run_experiment(
-                            &machines,
-                            run_mode,
-                            max_log_level,
-                            &features,
-                            testbed,
-                            &planet,
-                            protocol,
-                            config,
-                            clients,
-                            *workload,
-                            *batch_max_size,
-                            batch_max_delay,
-                            cpus,
-                            experiment_timeouts,
-                            &exp_dir,
-                        );
-                        if let Err(e) = run.await {
-                            // check if it's a timeout error
-                            match e.downcast_ref::<TimeoutError>() {
-                                Some(TimeoutError(source)) => {
-                                    // if it's a timeout error, cleanup and
-                                    // restart
-                                    // the experiment
-                                    tracing::warn!("timeout in {:?}; will cleanup and try again", source);
-                                    tokio::fs::remove_dir_all(exp_dir)
-                                        .await
-                                        .wrap_err("remove exp dir")?;
-                                    cleanup(&machines, vec![protocol]).await?;
-                                }
-                                None => {
-                                    // if not, quit
-                                    return Err(e);
-                                }
-                            }
-                        } else {
-                            // if there's no error, then exit the loop and run
-                            // the next experiment
-                            // (if any)
-                            progress.inc();
-                            break;
+                    // if they still fail, flag them
+                    for (exp_dir, exp_failures) in exp_dirs.iter().zip(failures)
+                    {
+                        if !exp_failures.is_empty() {
+                            tracing::warn!("sanity checks failed in {} after {} reruns: {:?}", exp_dir, reruns, exp_failures);
+                            flag_results(exp_dir, exp_failures)
+                                .wrap_err("flag_results")?;
                         }
                     }
                 }
+
+                // exit the loop and run the next experiment (if
+                // any)
+                progress.inc();
+                if let Some(dashboard) = &dashboard {
+                    dashboard.inc();
+                }
+                break;
             }
         }
     }
@@ -193,31 +520,56 @@ async fn run_experiment(
     features: &Vec<FantochFeature>,
     testbed: Testbed,
     planet: &Option<Planet>,
-    protocol: Protocol,
+    protocols: &[Protocol],
     config: Config,
-    clients_per_region: usize,
-    workload: Workload,
+    clients_per_region: ClientsPerRegion,
+    colocation: Option<Colocation>,
+    workload_mix: WorkloadMix,
     batch_max_size: usize,
     batch_max_delay: Duration,
     cpus: usize,
+    process_tcp_config: TcpConfig,
+    client_tcp_config: TcpConfig,
+    ping_interval: Option<Duration>,
+    region_failure: &Option<RegionFailure>,
+    run_duration: Option<Duration>,
     experiment_timeouts: ExperimentTimeouts,
-    exp_dir: &str,
+    dashboard: Option<&Dashboard>,
+    exp_metadata: &ExperimentMetadata,
+    exp_dirs: &[String],
 ) -> Result<(), Report> {
     // holder of dstat processes to be launched in all machines
     let mut dstats = Vec::with_capacity(machines.vm_count());
 
-    // start processes
-    let start = start_processes(
-        machines,
-        run_mode,
-        max_log_level,
-        testbed,
-        planet,
-        protocol,
-        config,
-        cpus,
-        &mut dstats,
-    );
+    // maybe start the background ping mesh between all machines
+    let pings = if let Some(interval) = ping_interval {
+        start_pings(machines, interval).wrap_err("start_pings")?
+    } else {
+        Vec::new()
+    };
+
+    // start processes of all deployments
+    let start = async {
+        let mut deployments = Vec::with_capacity(protocols.len());
+        for (deployment, protocol) in protocols.iter().enumerate() {
+            let started = start_processes(
+                machines,
+                run_mode,
+                max_log_level,
+                testbed,
+                planet,
+                deployment,
+                *protocol,
+                config,
+                cpus,
+                process_tcp_config,
+                &mut dstats,
+            )
+            .await?;
+            deployments.push(started);
+        }
+        Ok::<_, Report>(deployments)
+    };
     // check if a start timeout was set
     let start_result = if let Some(timeout) = experiment_timeouts.start {
         // if yes, abort experiment if timeout triggers
@@ -231,18 +583,100 @@ async fn run_experiment(
         // if no, simply wait for start to finish
         start.await
     };
-    let (process_ips, processes) = start_result.wrap_err("start_processes")?;
+    let (process_ips, processes): (Vec<_>, Vec<_>) = start_result
+        .wrap_err("start_processes")?
+        .into_iter()
+        .unzip();
+
+    // now that processes are connected, record the effective MSS of their
+    // connections in the metadata of each deployment
+    for (deployment, exp_dir) in exp_dirs.iter().enumerate() {
+        let mut exp_metadata = exp_metadata.clone();
+        record_effective_mss(machines, deployment, &mut exp_metadata)
+            .await
+            .wrap_err("record_effective_mss")?;
+        crate::serialize(
+            &exp_metadata,
+            format!("{}/{}", exp_dir, metadata::METADATA_FILE),
+            SerializationFormat::Json,
+        )
+        .wrap_err("save_exp_metadata")?;
+    }
 
-    // run clients
+    // run clients (of all deployments at the same time)
+    // only ask clients for traces if commands are being traced
+    let traces = config.trace_sample_rate().is_some();
     let run_clients = run_clients(
+        testbed,
+        planet,
+        config.n(),
         clients_per_region,
-        workload,
+        colocation,
+        config.client_process_selection(),
+        workload_mix,
         batch_max_size,
         batch_max_delay,
+        client_tcp_config,
+        traces,
+        region_failure,
+        run_duration,
+        // clients may not complete commands for a while after a region fails
+        experiment_timeouts
+            .zero_throughput
+            .filter(|_| region_failure.is_none()),
         machines,
         process_ips,
         &mut dstats,
+        dashboard,
     );
+    // maybe inject a region failure while clients run
+    let run_clients = async {
+        match region_failure {
+            Some(region_failure) => {
+                let (result, failure_time) = tokio::join!(
+                    run_clients,
+                    inject_region_failure(
+                        machines,
+                        protocols.len(),
+                        config.n(),
+                        clients_per_region,
+                        colocation,
+                        region_failure,
+                    )
+                );
+                result?;
+                failure_time.wrap_err("inject_region_failure").map(Some)
+            }
+            None => run_clients.await.map(|()| None),
+        }
+    };
+    // if processes are expected to commit commands throughout the run, abort
+    // it as soon as they stop doing so (processes may not commit commands for
+    // a while after a region fails)
+    let stalled = experiment_timeouts
+        .stalled
+        .filter(|_| region_failure.is_none());
+    let run_clients = async {
+        match stalled {
+            Some(stalled) => tokio::select! {
+                result = run_clients => result,
+                error = watch_progress(machines, exp_dirs, stalled) => Err(error),
+            },
+            None => run_clients.await,
+        }
+    };
+    // if there's a dashboard, check the health of machines while clients run
+    let run_clients = async {
+        match dashboard {
+            Some(dashboard) => tokio::select! {
+                result = run_clients => result,
+                _ = check_health(machines, dashboard) => {
+                    unreachable!("health checks should run forever")
+                }
+            },
+            None => run_clients.await,
+        }
+    };
     // check if a run timeout was set
     let run_clients_result = if let Some(timeout) = experiment_timeouts.run {
         // if yes, abort experiment if timeout triggers
@@ -256,37 +690,69 @@ async fn run_experiment(
         // if not, simply wait for run to finish
         run_clients.await
     };
-    run_clients_result.wrap_err("run_clients")?;
+    let region_failure_time = run_clients_result.wrap_err("run_clients")?;
 
     // stop dstat
     stop_dstats(machines, dstats).await.wrap_err("stop_dstat")?;
 
-    // create experiment config and pull metrics
-    let exp_config = ExperimentConfig::new(
-        machines.placement().clone(),
-        planet.clone(),
-        run_mode,
-        features.clone(),
-        testbed,
-        protocol,
-        config,
-        clients_per_region,
-        workload,
-        batch_max_size,
-        batch_max_delay,
-        cpus,
-    );
+    // stop the ping mesh (if it was started)
+    if ping_interval.is_some() {
+        stop_pings(machines, pings).await.wrap_err("stop_pings")?;
+    }
 
     let pull_metrics_and_stop = async {
-        pull_metrics(machines, exp_config, &exp_dir)
-            .await
-            .wrap_err("pull_metrics")?;
+        for (deployment, (protocol, processes)) in
+            protocols.iter().zip(processes).enumerate()
+        {
+            let exp_dir = &exp_dirs[deployment];
+
+            // compute the protocol deployed alongside this one (if any)
+            let co_deployed_with = protocols
+                .iter()
+                .enumerate()
+                .find(|(other, _)| *other != deployment)
+                .map(|(_, protocol)| *protocol);
+
+            // create experiment config and pull metrics
+            let mut exp_config = ExperimentConfig::new(
+                machines.placement().clone(),
+                planet.clone(),
+                run_mode,
+                features.clone(),
+                testbed,
+                *protocol,
+                config,
+                clients_per_region,
+                machines.client_machines_per_region(),
+                colocation,
+                workload_mix,
+                batch_max_size,
+                batch_max_delay,
+                cpus,
+                ping_interval,
+                co_deployed_with,
+                region_failure.clone(),
+                run_duration,
+                process_tcp_config,
+                client_tcp_config,
+            );
+            exp_config.region_failure_time = region_failure_time;
+            pull_metrics(machines, deployment, exp_config, exp_dir)
+                .await
+                .wrap_err("pull_metrics")?;
+
+            // stop processes: should only be stopped after copying all the
+            // metrics to avoid unnecessary noise in the logs
+            stop_processes(machines, run_mode, deployment, exp_dir, processes)
+                .await
+                .wrap_err("stop_processes")?;
+        }
 
-        // stop processes: should only be stopped after copying all the metrics
-        // to avoid unnecessary noise in the logs
-        stop_processes(machines, run_mode, protocol, &exp_dir, processes)
+        // files shared by all deployments can only be removed once metrics
+        // have been pulled for all of them
+        remove_shared_files(machines)
             .await
-            .wrap_err("stop_processes")?;
+            .wrap_err("remove_shared_files")?;
 
         Ok(())
     };
@@ -312,12 +778,13 @@ async fn start_processes(
     max_log_level: &tracing::Level,
     testbed: Testbed,
     planet: &Option<Planet>,
+    deployment: DeploymentIndex,
     protocol: Protocol,
     config: Config,
     cpus: usize,
+    tcp_config: TcpConfig,
     dstats: &mut Vec<tokio::process::Child>,
-) -> Result<(Ips, HashMap<ProcessId, (Region, tokio::process::Child)>), Report>
-{
+) -> Result<(Ips, Processes), Report> {
     let ips: Ips = machines
         .servers()
         .map(|(process_id, vm)| (*process_id, vm.ip()))
@@ -367,23 +834,34 @@ async fn start_processes(
         let process_type = ProcessType::Server(*process_id);
 
         // compute files to be generated during this run
-        let log_file = config::run_file(process_type, LOG_FILE_EXT);
-        let err_file = config::run_file(process_type, ERR_FILE_EXT);
-        let dstat_file = config::run_file(process_type, DSTAT_FILE_EXT);
-        let metrics_file = config::run_file(process_type, METRICS_FILE_EXT);
+        let log_file =
+            config::deployment_run_file(deployment, process_type, LOG_FILE_EXT);
+        let err_file =
+            config::deployment_run_file(deployment, process_type, ERR_FILE_EXT);
+        let metrics_file = config::deployment_run_file(
+            deployment,
+            process_type,
+            METRICS_FILE_EXT,
+        );
 
-        // start dstat and save it
-        let dstat = start_dstat(dstat_file, vm).await?;
-        dstats.push(dstat);
+        // start dstat and save it (only once per machine, as it's shared by
+        // all deployments)
+        if deployment == 0 {
+            let dstat_file = config::run_file(process_type, DSTAT_FILE_EXT);
+            let dstat = start_dstat(dstat_file, vm).await?;
+            dstats.push(dstat);
+        }
 
         // create protocol config and generate args
         let protocol_config = ProtocolConfig::new(
             protocol,
+            deployment,
             *process_id,
             *shard_id,
             config,
             sorted,
             ips,
+            tcp_config,
             metrics_file,
             cpus,
             log_file,
@@ -392,19 +870,16 @@ async fn start_processes(
 
         let command = crate::machine::fantoch_bin_script(
             process_type,
-            protocol.binary(),
+            crate::SERVER_BINARY,
             args,
             run_mode,
             max_log_level,
             err_file,
         );
-        let process = vm
-            .prepare_exec(command)
-            .spawn()
-            .wrap_err("failed to start process")?;
+        let process = vm.spawn(command).wrap_err("failed to start process")?;
         processes.insert(*process_id, (from_region.clone(), process));
 
-        wait_processes.push(wait_process_started(process_id, &vm));
+        wait_processes.push(wait_process_started(deployment, process_id, &vm));
     }
 
     // wait all processse started
@@ -431,85 +906,271 @@ fn maybe_inject_delay(
     })
 }
 
+// Returns the pairs of server machines whose latency (i.e. the RTT measured
+// between them plus the delay injected) doesn't match the one in `planet`. If
+// there's no planet, there's nothing to check as no delay is injected.
+async fn check_latencies(
+    machines: &Machines<'_>,
+    planet: &Option<Planet>,
+    latency_checks: LatencyChecks,
+) -> Result<Vec<String>, Report> {
+    if planet.is_none() {
+        return Ok(Vec::new());
+    }
+
+    let mut checks = Vec::with_capacity(machines.server_count().pow(2));
+    for (from_id, from_vm) in machines.servers() {
+        for (to_id, to_vm) in machines.servers() {
+            if from_id == to_id {
+                continue;
+            }
+            let from = machines.process_region(from_id);
+            let to = machines.process_region(to_id);
+            checks.push(async move {
+                let command = format!(
+                    "ping -c {} -q {} | tail -n 1",
+                    latency_checks.pings,
+                    to_vm.ip()
+                );
+                let stdout = from_vm.exec(command).await.wrap_err("ping")?;
+                Ok::<_, Report>((from, to, parse_ping_rtt(&stdout)))
+            });
+        }
+    }
+
+    let mut mismatches = Vec::new();
+    for result in futures::future::join_all(checks).await {
+        let (from, to, measured) = result?;
+        // the delay injected is half the expected latency (in each direction)
+        let delay = maybe_inject_delay(from, to, planet)
+            .expect("delay should be injected");
+        let injected = 2 * delay;
+        let expected = planet
+            .as_ref()
+            .and_then(|planet| planet.ping_latency(from, to))
+            .expect("both regions should be part of the planet");
+        match measured {
+            Some(measured) => {
+                let effective = measured + injected as f64;
+                let tolerance = latency_checks.tolerance.as_secs_f64() * 1000.0;
+                if (effective - expected as f64).abs() > tolerance {
+                    mismatches.push(format!(
+                        "{:?} -> {:?}: expected {}ms, but measured {:.1}ms (+ {}ms injected)",
+                        from, to, expected, measured, injected
+                    ));
+                }
+            }
+            None => {
+                mismatches.push(format!("{:?} -> {:?}: unreachable", from, to))
+            }
+        }
+    }
+    mismatches.sort();
+    Ok(mismatches)
+}
+
+// Parses the average RTT (in millis) from the summary line of `ping -q`, e.g.
+// "rtt min/avg/max/mdev = 0.041/0.052/0.063/0.010 ms". Returns `None` if there
+// was no reply (in which case there's no such line).
+fn parse_ping_rtt(summary: &str) -> Option<f64> {
+    let (_, stats) = summary.split_once(" = ")?;
+    stats.split('/').nth(1)?.parse().ok()
+}
+
 async fn run_clients(
-    clients_per_region: usize,
-    workload: Workload,
+    testbed: Testbed,
+    planet: &Option<Planet>,
+    n: usize,
+    clients_per_region: ClientsPerRegion,
+    colocation: Option<Colocation>,
+    process_selection: ProcessSelection,
+    workload_mix: WorkloadMix,
     batch_max_size: usize,
     batch_max_delay: Duration,
+    tcp_config: TcpConfig,
+    traces: bool,
+    region_failure: &Option<RegionFailure>,
+    run_duration: Option<Duration>,
+    zero_throughput: Option<Duration>,
     machines: &Machines<'_>,
-    process_ips: Ips,
+    process_ips: Vec<Ips>,
     dstats: &mut Vec<tokio::process::Child>,
+    dashboard: Option<&Dashboard>,
 ) -> Result<(), Report> {
-    let mut clients = HashMap::with_capacity(machines.client_count());
-    let mut wait_clients = Vec::with_capacity(machines.client_count());
-
-    for (region, vm) in machines.clients() {
-        // find all processes in this region (we have more than one there's more
-        // than one shard)
-        let (processes_in_region, region_index) =
-            machines.processes_in_region(region);
-
-        // compute id start and id end:
-        // - first compute the id end
-        // - and then compute id start: subtract `clients_per_machine` and add 1
-        let id_end = region_index as usize * clients_per_region;
-        let id_start = id_end - clients_per_region + 1;
-
-        // get ips of all processes in this region
-        let ips = processes_in_region
-            .iter()
-            .map(|process_id| {
-                let ip = process_ips
-                    .get(process_id)
-                    .expect("process should have ip")
-                    .clone();
-                (*process_id, ip)
-            })
-            .collect();
+    // if some region fails, clients are stopped some time after the failure;
+    // in time-bounded runs, clients are stopped once the run is over
+    let max_run_time = region_failure
+        .as_ref()
+        .map(|region_failure| region_failure.max_run_time())
+        .into_iter()
+        .chain(run_duration)
+        .min();
+    // commands only time out at clients if some region fails
+    let command_timeout = region_failure
+        .as_ref()
+        .and_then(|region_failure| region_failure.command_timeout);
+
+    // compute the clients in each client machine (and in each server machine,
+    // if clients are colocated with servers)
+    let mut client_groups = Vec::with_capacity(machines.vm_count());
+    for (region, client_index, vm) in machines.clients() {
+        // compute id start and id end of the clients in this machine
+        let region_index = machines.region_index(region);
+        let (id_start, id_end) = client_ids(
+            region_index,
+            clients_per_region,
+            machines.client_machines_per_region(),
+            client_index,
+        );
 
         // compute process type
-        let process_type = ProcessType::Client(region_index);
+        let process_type = ProcessType::Client(region_index, client_index);
 
-        // compute files to be generated during this run
-        let log_file = config::run_file(process_type, LOG_FILE_EXT);
-        let err_file = config::run_file(process_type, ERR_FILE_EXT);
+        // start dstat and save it (only once per machine, as it's shared by
+        // all deployments)
         let dstat_file = config::run_file(process_type, DSTAT_FILE_EXT);
-        let metrics_file = config::run_file(process_type, METRICS_FILE_EXT);
-
-        // start dstat and save it
         let dstat = start_dstat(dstat_file, vm).await?;
         dstats.push(dstat);
 
-        // create client config and generate args
-        let client_config = ClientConfig::new(
-            id_start,
-            id_end,
-            ips,
-            workload,
-            batch_max_size,
-            batch_max_delay,
-            metrics_file,
-            log_file,
-        );
-        let args = client_config.to_args();
+        client_groups.push((region, process_type, id_start, id_end, vm, None));
+    }
+    if let Some(colocation) = colocation {
+        for (process_id, vm) in machines.servers() {
+            // colocated clients share the dstat of their server
+            let region = machines.process_region(process_id);
+            let (id_start, id_end) = colocated_client_ids(
+                n,
+                clients_per_region,
+                colocation,
+                *process_id,
+            );
+            let process_type = ProcessType::ColocatedClient(*process_id);
+            client_groups.push((
+                region,
+                process_type,
+                id_start,
+                id_end,
+                vm,
+                colocation.cpu_shares,
+            ));
+        }
+    }
 
-        let command = crate::machine::fantoch_bin_script(
-            process_type,
-            "client",
-            args,
-            // always run clients on release mode
-            RunMode::Release,
-            // always run clients on info level
-            &tracing::Level::INFO,
-            err_file,
-        );
-        let client = vm
-            .prepare_exec(command)
-            .spawn()
-            .wrap_err("failed to start client")?;
-        clients.insert(region_index, client);
+    // in local runs, all machines are the same one, and so the latency between
+    // clients and the processes they submit commands to is injected as well
+    // (otherwise, only the latency between processes would be)
+    let client_planet = if testbed == Testbed::Local {
+        planet
+    } else {
+        &None
+    };
+
+    let client_count = client_groups.len() * process_ips.len();
+    let mut clients = HashMap::with_capacity(client_count);
+    let mut wait_clients = Vec::with_capacity(client_count);
+
+    for (region, process_type, id_start, id_end, vm, cpu_shares) in
+        client_groups
+    {
+        // find the process to which clients submit commands on each shard (by
+        // default, the one in this region)
+        let region_index = machines.region_index(region);
+        let selected_processes =
+            machines.selected_processes(region_index, process_selection);
+
+        // start one client per deployment, all of them with the same workload
+        // mix
+        for (deployment, process_ips) in process_ips.iter().enumerate() {
+            // get ips of all processes selected
+            let ips = selected_processes
+                .iter()
+                .map(|process_id| {
+                    let ip = process_ips
+                        .get(process_id)
+                        .expect("process should have ip")
+                        .clone();
+                    // compute delay to be injected (if there's a planet)
+                    let to_region = machines.process_region(process_id);
+                    let delay =
+                        maybe_inject_delay(region, to_region, client_planet);
+                    (*process_id, ip, delay)
+                })
+                .collect();
+
+            // compute files to be generated during this run
+            let log_file = config::deployment_run_file(
+                deployment,
+                process_type,
+                LOG_FILE_EXT,
+            );
+            let err_file = config::deployment_run_file(
+                deployment,
+                process_type,
+                ERR_FILE_EXT,
+            );
+            let metrics_file = config::deployment_run_file(
+                deployment,
+                process_type,
+                METRICS_FILE_EXT,
+            );
+            let traces_file = if traces {
+                Some(config::deployment_run_file(
+                    deployment,
+                    process_type,
+                    TRACES_FILE_EXT,
+                ))
+            } else {
+                None
+            };
+
+            // create client config and generate args
+            let client_config = ClientConfig::new(
+                deployment,
+                id_start,
+                id_end,
+                ips,
+                workload_mix,
+                batch_max_size,
+                batch_max_delay,
+                tcp_config,
+                max_run_time,
+                command_timeout,
+                metrics_file,
+                traces_file,
+                log_file,
+            );
+            let args = client_config.to_args();
+
+            let mut command = crate::machine::fantoch_bin_script(
+                process_type,
+                "client",
+                args,
+                // always run clients on release mode
+                RunMode::Release,
+                // always run clients on info level
+                &tracing::Level::INFO,
+                err_file,
+            );
+            if let Some(cpu_shares) = cpu_shares {
+                command = with_cpu_shares(command, cpu_shares);
+            }
+            let client =
+                vm.spawn(command).wrap_err("failed to start client")?;
+            clients.insert((deployment, process_type), client);
 
-        wait_clients.push(wait_client_ended(region_index, region.clone(), &vm));
+            // clients in the region that fails are killed, and thus never end
+            if failed(region_failure, region) {
+                continue;
+            }
+            wait_clients.push(wait_client_ended(
+                deployment,
+                process_type,
+                region.clone(),
+                &vm,
+                zero_throughput,
+                dashboard,
+            ));
+        }
     }
 
     // wait all clients ended
@@ -519,12 +1180,153 @@ async fn run_clients(
     Ok(())
 }
 
+// Runs `command` in its own scope (i.e. cgroup) with `cpu_shares` as its CPU
+// weight, so that colocated clients get a controlled share of the CPU of the
+// server machine.
+fn with_cpu_shares(command: String, cpu_shares: u64) -> String {
+    format!(
+        "systemd-run --user --scope --quiet -p CPUWeight={} bash -c '{}'",
+        cpu_shares, command
+    )
+}
+
+// Waits until it's time for the region in `region_failure` to fail, and then
+// kills all its processes (of all deployments) and clients. Returns the unix
+// time (in millis) at which the failure was injected.
+async fn inject_region_failure(
+    machines: &Machines<'_>,
+    deployments: usize,
+    n: usize,
+    clients_per_region: ClientsPerRegion,
+    colocation: Option<Colocation>,
+    region_failure: &RegionFailure,
+) -> Result<u64, Report> {
+    tokio::time::sleep(region_failure.after).await;
+    let failure_time = exp_timestamp() as u64 / 1000;
+    let region = &region_failure.region;
+    tracing::info!("injecting failure of region {:?}", region);
+
+    // kill processes
+    for (process_id, vm) in machines.servers() {
+        if machines.process_region(process_id) == region {
+            for deployment in 0..deployments {
+                stop_process(vm, deployment, *process_id, region)
+                    .await
+                    .wrap_err("stop_process")?;
+            }
+        }
+    }
+
+    // kill clients: clients are found by their ids (and not by the binary
+    // name) as, in local experiments, all clients run in the same machine
+    for (client_region, client_index, vm) in machines.clients() {
+        if client_region == region {
+            let region_index = machines.region_index(region);
+            let (id_start, id_end) = client_ids(
+                region_index,
+                clients_per_region,
+                machines.client_machines_per_region(),
+                client_index,
+            );
+            kill_clients(vm, id_start, id_end).await?;
+        }
+    }
+
+    // kill colocated clients (if any)
+    if let Some(colocation) = colocation {
+        for (process_id, vm) in machines.servers() {
+            if machines.process_region(process_id) == region {
+                let (id_start, id_end) = colocated_client_ids(
+                    n,
+                    clients_per_region,
+                    colocation,
+                    *process_id,
+                );
+                kill_clients(vm, id_start, id_end).await?;
+            }
+        }
+    }
+    Ok(failure_time)
+}
+
+// Kills the clients with identifiers between `id_start` and `id_end` running
+// in `vm`.
+async fn kill_clients(
+    vm: &Machine<'_>,
+    id_start: usize,
+    id_end: usize,
+) -> Result<(), Report> {
+    // the trailing `[ ]` prevents `pkill` from matching the shell that runs it
+    let command = format!("pkill -f -- '--ids {}-{}[ ]'", id_start, id_end);
+    vm.exec(command).await.wrap_err("pkill client")?;
+    Ok(())
+}
+
+// Checks whether `region` is the one that fails in `region_failure`.
+fn failed(region_failure: &Option<RegionFailure>, region: &Region) -> bool {
+    region_failure
+        .as_ref()
+        .map(|region_failure| &region_failure.region == region)
+        .unwrap_or(false)
+}
+
+// Computes the identifiers of the clients running in some client machine of a
+// region: the clients of that region are split (as evenly as possible) among
+// its `client_machines_per_region` client machines. Each region gets a range
+// of identifiers as large as the highest number of clients in a region, so
+// that the ranges of different regions don't overlap.
+fn client_ids(
+    region_index: RegionIndex,
+    clients_per_region: ClientsPerRegion,
+    client_machines_per_region: usize,
+    client_index: ClientMachineIndex,
+) -> (usize, usize) {
+    let region_clients = clients_per_region.count(region_index);
+    assert!(
+        region_clients >= client_machines_per_region,
+        "there should be at least one client per client machine"
+    );
+
+    // compute the id start of this region:
+    // - first compute the id end of the range of this region
+    // - and then compute id start: subtract the range size and add 1
+    let range = clients_per_region.max();
+    let region_id_end = region_index as usize * range;
+    let region_id_start = region_id_end - range + 1;
+
+    // the first `extra` machines run one more client than the others
+    let base = region_clients / client_machines_per_region;
+    let extra = region_clients % client_machines_per_region;
+    let count = base + if client_index < extra { 1 } else { 0 };
+    let offset = client_index * base + client_index.min(extra);
+
+    let id_start = region_id_start + offset;
+    let id_end = id_start + count - 1;
+    (id_start, id_end)
+}
+
+// Computes the identifiers of the clients colocated with process `process_id`:
+// these come after the identifiers of the clients in all `n` regions (see
+// `client_ids`), and each server machine gets `colocation.clients` of them.
+fn colocated_client_ids(
+    n: usize,
+    clients_per_region: ClientsPerRegion,
+    colocation: Colocation,
+    process_id: ProcessId,
+) -> (usize, usize) {
+    let regions_id_end = n * clients_per_region.max();
+    let id_start =
+        regions_id_end + (process_id as usize - 1) * colocation.clients + 1;
+    let id_end = id_start + colocation.clients - 1;
+    (id_start, id_end)
+}
+
 async fn stop_processes(
     machines: &Machines<'_>,
     run_mode: RunMode,
-    protocol: Protocol,
+    deployment: DeploymentIndex,
     exp_dir: &str,
-    processes: HashMap<ProcessId, (Region, tokio::process::Child)>,
+    processes: Processes,
 ) -> Result<(), Report> {
     let mut wait_processes = Vec::with_capacity(machines.server_count());
     for (process_id, (region, mut pchild)) in processes {
@@ -571,12 +1373,12 @@ async fn stop_processes(
         }
 
         // stop process
-        stop_process(vm, process_id, &region)
+        stop_process(vm, deployment, process_id, &region)
             .await
             .wrap_err("stop_process")?;
 
         wait_processes.push(wait_process_ended(
-            protocol,
+            deployment,
             heaptrack_pid,
             process_id,
             region,
@@ -595,24 +1397,15 @@ async fn stop_processes(
 
 async fn stop_process(
     vm: &Machine<'_>,
+    deployment: DeploymentIndex,
     process_id: ProcessId,
     region: &Region,
 ) -> Result<(), Report> {
     // find process pid in remote vm
     // TODO: this should equivalent to `pkill PROTOCOL_BINARY`
-    let command = format!(
-        "lsof -i :{} -i :{} -sTCP:LISTEN | grep -v PID",
-        config::port(process_id),
-        config::client_port(process_id)
-    );
-    let output = vm.exec(command).await.wrap_err("lsof | grep")?;
-    let mut pids: Vec<_> = output
-        .lines()
-        // take the second column (which contains the PID)
-        .map(|line| line.split_whitespace().collect::<Vec<_>>()[1])
-        .collect();
-    pids.sort();
-    pids.dedup();
+    let pids = process_pids(vm, deployment, process_id)
+        .await
+        .wrap_err("process_pids")?;
 
     // there should be at most one pid
     match pids.len() {
@@ -634,20 +1427,46 @@ async fn stop_process(
     Ok(())
 }
 
-async fn wait_process_started(
-    process_id: &ProcessId,
+// Finds the pids of the processes listening on the ports of process
+// `process_id` of some deployment.
+async fn process_pids(
     vm: &Machine<'_>,
-) -> Result<(), Report> {
-    // small delay between calls
-    let duration = tokio::time::Duration::from_secs(2);
+    deployment: DeploymentIndex,
+    process_id: ProcessId,
+) -> Result<Vec<String>, Report> {
+    let command = format!(
+        "lsof -i :{} -i :{} -sTCP:LISTEN | grep -v PID",
+        config::port(deployment, process_id),
+        config::client_port(deployment, process_id)
+    );
+    let output = vm.exec(command).await.wrap_err("lsof | grep")?;
+    let mut pids: Vec<_> = output
+        .lines()
+        // take the second column (which contains the PID)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>()[1].to_string())
+        .collect();
+    pids.sort();
+    pids.dedup();
+    Ok(pids)
+}
+
+async fn wait_process_started(
+    deployment: DeploymentIndex,
+    process_id: &ProcessId,
+    vm: &Machine<'_>,
+) -> Result<(), Report> {
+    // small delay between calls
+    let duration = tokio::time::Duration::from_secs(2);
 
     // compute process type and log file
     let process_type = ProcessType::Server(*process_id);
-    let log_file = config::run_file(process_type, LOG_FILE_EXT);
+    let log_file =
+        config::deployment_run_file(deployment, process_type, LOG_FILE_EXT);
 
     let mut count = 0;
     while count != 1 {
         tokio::time::sleep(duration).await;
+        check_run_failed(&log_file, vm).await?;
         let command =
             format!("grep -c 'process {} started' {}", process_id, log_file);
         let stdout = vm.exec(&command).await.wrap_err("grep -c")?;
@@ -660,8 +1479,22 @@ async fn wait_process_started(
     Ok(())
 }
 
+// Fails with the error reported in `log_file` if the run failed (see
+// `RunError::log_failure`).
+async fn check_run_failed(
+    log_file: &str,
+    vm: &Machine<'_>,
+) -> Result<(), Report> {
+    let command = format!("grep -m 1 'run failed (' {}", log_file);
+    let stdout = vm.exec(&command).await.wrap_err("grep -m 1")?;
+    match RunError::from_failure_line(&stdout) {
+        Some(error) => Err(Report::new(error)),
+        None => Ok(()),
+    }
+}
+
 async fn wait_process_ended(
-    protocol: Protocol,
+    deployment: DeploymentIndex,
     heaptrack_pid: Option<u32>,
     process_id: ProcessId,
     region: Region,
@@ -677,8 +1510,8 @@ async fn wait_process_ended(
         tokio::time::sleep(duration).await;
         let command = format!(
             "lsof -i :{} -i :{} -sTCP:LISTEN | wc -l",
-            config::port(process_id),
-            config::client_port(process_id)
+            config::port(deployment, process_id),
+            config::client_port(deployment, process_id)
         );
         let stdout = vm.exec(&command).await.wrap_err("lsof | wc")?;
         if stdout.is_empty() {
@@ -730,7 +1563,6 @@ async fn wait_process_ended(
             let heaptrack_pid =
                 heaptrack_pid.expect("heaptrack pid should be set");
             pull_heaptrack_file(
-                protocol,
                 heaptrack_pid,
                 process_type,
                 &region,
@@ -745,20 +1577,63 @@ async fn wait_process_ended(
 }
 
 async fn wait_client_ended(
-    region_index: RegionIndex,
+    deployment: DeploymentIndex,
+    process_type: ProcessType,
     region: Region,
     vm: &Machine<'_>,
+    zero_throughput: Option<Duration>,
+    dashboard: Option<&Dashboard>,
 ) -> Result<(), Report> {
     // small delay between calls
     let duration = tokio::time::Duration::from_secs(10);
 
-    // compute process type and log file
-    let process_type = ProcessType::Client(region_index);
-    let log_file = config::run_file(process_type, LOG_FILE_EXT);
+    // compute log file
+    let log_file =
+        config::deployment_run_file(deployment, process_type, LOG_FILE_EXT);
+
+    // track when clients last completed some command
+    let mut completed = 0;
+    let mut last_progress = tokio::time::Instant::now();
 
     let mut count = 0;
     while count != 1 {
         tokio::time::sleep(duration).await;
+        if let Some(zero_throughput) = zero_throughput {
+            // check the last stats logged by clients
+            let command =
+                format!("grep 'client stats: ' {} | tail -n 1", log_file);
+            let stdout = vm.exec(&command).await.wrap_err("client stats")?;
+            if let Some(stats) = ClientStats::from_stats_line(&stdout) {
+                if stats.completed > completed {
+                    completed = stats.completed;
+                    last_progress = tokio::time::Instant::now();
+                }
+            }
+            if last_progress.elapsed() > zero_throughput {
+                return Err(Report::new(ZeroThroughputError(region)));
+            }
+        }
+        if let Some(dashboard) = dashboard {
+            // sum the last status reported by each client in this machine
+            let command = format!(
+                "grep -o 'c[0-9]*: [0-9]* of' {} | awk '{{ issued[$1] = $2 }} END {{ total = 0; for (c in issued) total += issued[c]; print total }}'",
+                log_file
+            );
+            let stdout = vm.exec(&command).await.wrap_err("client status")?;
+            match stdout.parse::<usize>() {
+                Ok(issued) => dashboard.client_progress(
+                    format!(
+                        "deployment {} | {:?} | {}",
+                        deployment,
+                        region,
+                        process_type.name()
+                    ),
+                    issued,
+                ),
+                Err(_) => tracing::warn!("unexpected output from: {}", command),
+            }
+        }
+        check_run_failed(&log_file, vm).await?;
         let command = format!("grep -c 'all clients ended' {}", log_file);
         let stdout = vm.exec(&command).await.wrap_err("grep -c")?;
         if stdout.is_empty() {
@@ -769,14 +1644,184 @@ async fn wait_client_ended(
     }
 
     tracing::info!(
-        "client {} in region {:?} terminated successfully",
-        region_index,
+        "{} in region {:?} terminated successfully",
+        process_type.name(),
         region
     );
 
     Ok(())
 }
 
+// Follows the number of commands committed by the processes of all
+// deployments (as logged by their metrics logger), and fails once it hasn't
+// increased for `stalled`, after collecting diagnostics from all machines.
+async fn watch_progress(
+    machines: &Machines<'_>,
+    exp_dirs: &[String],
+    stalled: Duration,
+) -> Report {
+    let mut committed = 0;
+    let mut last_progress = tokio::time::Instant::now();
+    loop {
+        tokio::time::sleep(PROGRESS_CHECK_INTERVAL).await;
+        let total = match committed_commands(machines, exp_dirs.len()).await {
+            Ok(total) => total,
+            Err(e) => return e.wrap_err("committed_commands"),
+        };
+        if total > committed {
+            committed = total;
+            last_progress = tokio::time::Instant::now();
+        } else if last_progress.elapsed() > stalled {
+            tracing::warn!(
+                "no command committed in the last {:?}; collecting diagnostics",
+                stalled
+            );
+            if let Err(e) = collect_diagnostics(machines, exp_dirs).await {
+                return e.wrap_err("collect_diagnostics");
+            }
+            return Report::new(StalledError(committed));
+        }
+    }
+}
+
+// Sums the last number of committed commands logged by each process of each
+// deployment.
+async fn committed_commands(
+    machines: &Machines<'_>,
+    deployments: usize,
+) -> Result<u64, Report> {
+    let mut total = 0;
+    for deployment in 0..deployments {
+        for (process_id, vm) in machines.servers() {
+            let process_type = ProcessType::Server(*process_id);
+            let log_file = config::deployment_run_file(
+                deployment,
+                process_type,
+                LOG_FILE_EXT,
+            );
+            let command =
+                format!("grep 'committed commands: ' {} | tail -n 1", log_file);
+            let stdout =
+                vm.exec(&command).await.wrap_err("committed commands")?;
+            total += metrics_logger::committed_from_log_line(&stdout)
+                .unwrap_or_default();
+        }
+    }
+    Ok(total)
+}
+
+// Collects diagnostics from the processes of all deployments: each process is
+// asked to log its internal status (by sending it SIGQUIT), and the stack of
+// each of its threads is dumped with gdb. These are then pulled, along with
+// the logs of processes, to a directory next to the experiment directory of
+// each deployment (as the latter is removed before the run is retried).
+async fn collect_diagnostics(
+    machines: &Machines<'_>,
+    exp_dirs: &[String],
+) -> Result<(), Report> {
+    for (deployment, exp_dir) in exp_dirs.iter().enumerate() {
+        let diagnostics_dir = format!("{}_{}", exp_dir, DIAGNOSTICS_DIR_SUFFIX);
+        tokio::fs::create_dir_all(&diagnostics_dir)
+            .await
+            .wrap_err("create_dir_all")?;
+
+        let mut diagnostics = Vec::with_capacity(machines.server_count());
+        for (process_id, vm) in machines.servers() {
+            diagnostics.push(collect_process_diagnostics(
+                deployment,
+                *process_id,
+                machines.process_region(process_id),
+                vm,
+                &diagnostics_dir,
+            ));
+        }
+        for result in futures::future::join_all(diagnostics).await {
+            let () = result?;
+        }
+        tracing::info!("diagnostics saved in {}", diagnostics_dir);
+    }
+    Ok(())
+}
+
+async fn collect_process_diagnostics(
+    deployment: DeploymentIndex,
+    process_id: ProcessId,
+    region: &Region,
+    vm: &Machine<'_>,
+    diagnostics_dir: &str,
+) -> Result<(), Report> {
+    let process_type = ProcessType::Server(process_id);
+    let log_file =
+        config::deployment_run_file(deployment, process_type, LOG_FILE_EXT);
+    let stacks_file =
+        config::deployment_run_file(deployment, process_type, STACKS_FILE_EXT);
+
+    let pids = process_pids(vm, deployment, process_id)
+        .await
+        .wrap_err("process_pids")?;
+    for pid in &pids {
+        // the status is logged by the metrics logger, so, if the runtime of
+        // the process is stuck, only the stack dump will be available
+        vm.exec(format!("kill -QUIT {}", pid))
+            .await
+            .wrap_err("kill -QUIT")?;
+        let command = format!(
+            "sudo -n gdb -p {} -batch -ex 'thread apply all bt' >> {} 2>&1",
+            pid, stacks_file
+        );
+        vm.exec(command).await.wrap_err("gdb")?;
+    }
+    if pids.is_empty() {
+        tracing::warn!(
+            "process {} not running in region {:?}; no stacks to dump",
+            process_id,
+            region
+        );
+    }
+
+    // pull the log (which now includes the status) and the stacks
+    let prefix = config::file_prefix(process_type, region);
+    vm.copy_from(&log_file, format!("{}/{}.log", diagnostics_dir, prefix))
+        .await
+        .wrap_err("copy log")?;
+    if !pids.is_empty() {
+        vm.copy_from(
+            &stacks_file,
+            format!("{}/{}.{}", diagnostics_dir, prefix, STACKS_FILE_EXT),
+        )
+        .await
+        .wrap_err("copy stacks")?;
+    }
+    Ok(())
+}
+
+async fn check_health(machines: &Machines<'_>, dashboard: &Dashboard) {
+    // report the load averages and the memory available in each machine
+    let command =
+        "cut -d ' ' -f 1-3 /proc/loadavg && grep MemAvailable /proc/meminfo";
+    let servers = machines
+        .servers()
+        .map(|(process_id, vm)| (format!("server {}", process_id), vm));
+    let clients = machines.clients().map(|(region, client_index, vm)| {
+        (
+            format!("client {:?} | machine {}", region, client_index),
+            vm,
+        )
+    });
+    let vms: Vec<_> = servers.chain(clients).collect();
+    loop {
+        let checks = vms.iter().map(|(name, vm)| async move {
+            let health = match vm.exec(command).await {
+                Ok(stdout) => format!("load {}", stdout.replace('\n', " | ")),
+                Err(e) => format!("unreachable: {}", e),
+            };
+            dashboard.machine_health(name.clone(), health);
+        });
+        futures::future::join_all(checks).await;
+        tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+    }
+}
+
 async fn start_dstat(
     dstat_file: String,
     vm: &Machine<'_>,
@@ -785,9 +1830,7 @@ async fn start_dstat(
         "dstat -t -T -cdnm --io --output {} 1 > /dev/null",
         dstat_file
     );
-    vm.prepare_exec(command)
-        .spawn()
-        .wrap_err("failed to start dstat")
+    vm.spawn(command).wrap_err("failed to start dstat")
 }
 
 async fn stop_dstats(
@@ -871,18 +1914,95 @@ async fn check_no_dstat(vm: &Machine<'_>) -> Result<(), Report> {
     }
 }
 
+fn start_pings(
+    machines: &Machines<'_>,
+    interval: Duration,
+) -> Result<Vec<tokio::process::Child>, Report> {
+    // compute the ips of all machines
+    let mut ips: Vec<_> = machines.vms().map(|vm| vm.ip()).collect();
+    ips.sort();
+    ips.dedup();
+
+    // compute the process type of each machine
+    let servers = machines
+        .servers()
+        .map(|(process_id, vm)| (ProcessType::Server(*process_id), vm));
+    let clients = machines.clients().map(|(region, client_index, vm)| {
+        let region_index = machines.region_index(region);
+        (ProcessType::Client(region_index, client_index), vm)
+    });
+
+    let mut pings = Vec::with_capacity(machines.vm_count());
+    for (process_type, vm) in servers.chain(clients) {
+        let ping_file = config::run_file(process_type, PING_FILE_EXT);
+        let ping = start_ping(ping_file, &ips, interval, vm)?;
+        pings.push(ping);
+    }
+    Ok(pings)
+}
+
+fn start_ping(
+    ping_file: String,
+    ips: &[String],
+    interval: Duration,
+    vm: &Machine<'_>,
+) -> Result<tokio::process::Child, Report> {
+    // ping all other machines; each line in the output file is prefixed with
+    // the ip being pinged and contains the timestamp (`-D`) of each reply
+    let targets = ips
+        .iter()
+        .filter(|ip| **ip != vm.ip())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let command = format!(
+        "(for ip in {}; do ping -D -i {} $ip | sed -u \"s/^/$ip /\" & done; wait) > {}",
+        targets,
+        interval.as_secs_f64(),
+        ping_file
+    );
+    vm.spawn(command).wrap_err("failed to start ping")
+}
+
+async fn stop_pings(
+    machines: &Machines<'_>,
+    pings: Vec<tokio::process::Child>,
+) -> Result<(), Report> {
+    for mut ping in pings {
+        // kill ssh process
+        if let Err(e) = ping.kill().await {
+            tracing::warn!(
+                "error trying to kill ssh ping {:?}: {:?}",
+                ping.id(),
+                e
+            );
+        }
+    }
+
+    // stop pings in parallel
+    let mut stops = Vec::new();
+    for vm in machines.vms() {
+        stops.push(vm.exec("pkill ping"));
+    }
+    for result in futures::future::join_all(stops).await {
+        let _ = result.wrap_err("pkill ping")?;
+    }
+    Ok(())
+}
+
 async fn pull_metrics(
     machines: &Machines<'_>,
-    exp_config: ExperimentConfig,
+    deployment: DeploymentIndex,
+    mut exp_config: ExperimentConfig,
     exp_dir: &str,
 ) -> Result<(), Report> {
-    // save experiment config
-    crate::serialize(
-        exp_config,
-        format!("{}/exp_config.json", exp_dir),
-        SerializationFormat::Json,
-    )
-    .wrap_err("save_exp_config")?;
+    // only pull ping files if the ping mesh was started
+    let pull_pings = exp_config.ping_interval.is_some();
+    // only pull traces files if commands were traced
+    let pull_traces = exp_config.config.trace_sample_rate().is_some();
+    // clients in the region that failed (if any) were killed, and thus have no
+    // metrics files
+    let region_failure = exp_config.region_failure.clone();
 
     let mut pulls = Vec::with_capacity(machines.vm_count());
     // prepare server metrics pull
@@ -890,14 +2010,52 @@ async fn pull_metrics(
         // compute region and process type
         let region = machines.process_region(process_id);
         let process_type = ProcessType::Server(*process_id);
-        pulls.push(pull_metrics_files(process_type, region, vm, &exp_dir));
+        pulls.push(pull_metrics_files(
+            deployment,
+            process_type,
+            region,
+            vm,
+            true,
+            pull_pings,
+            false,
+            &exp_dir,
+        ));
     }
     // prepare client metrics pull
-    for (region, vm) in machines.clients() {
+    for (region, client_index, vm) in machines.clients() {
         // compute region index and process type
         let region_index = machines.region_index(region);
-        let process_type = ProcessType::Client(region_index);
-        pulls.push(pull_metrics_files(process_type, region, vm, &exp_dir));
+        let process_type = ProcessType::Client(region_index, client_index);
+        let pull_metrics = !failed(&region_failure, region);
+        pulls.push(pull_metrics_files(
+            deployment,
+            process_type,
+            region,
+            vm,
+            pull_metrics,
+            pull_pings,
+            pull_traces && pull_metrics,
+            &exp_dir,
+        ));
+    }
+    // prepare colocated client metrics pull (if any); these clients are not
+    // part of the ping mesh
+    if exp_config.colocation.is_some() {
+        for (process_id, vm) in machines.servers() {
+            let region = machines.process_region(process_id);
+            let process_type = ProcessType::ColocatedClient(*process_id);
+            let pull_metrics = !failed(&region_failure, region);
+            pulls.push(pull_metrics_files(
+                deployment,
+                process_type,
+                region,
+                vm,
+                pull_metrics,
+                false,
+                pull_traces && pull_metrics,
+                &exp_dir,
+            ));
+        }
     }
 
     // pull all metrics in parallel
@@ -905,9 +2063,357 @@ async fn pull_metrics(
         let _ = result.wrap_err("pull_metrics")?;
     }
 
+    // check that no command was lost (unless some region failed or the run was
+    // time-bounded, in which case some commands are lost by design)
+    if exp_config.region_failure.is_none() && exp_config.run_duration.is_none()
+    {
+        exp_config.integrity_discrepancies =
+            check_integrity(machines, exp_config.colocation, exp_dir)
+                .wrap_err("check_integrity")?;
+    }
+    if !exp_config.integrity_discrepancies.is_empty() {
+        tracing::warn!(
+            "commands lost in {}: {:?}",
+            exp_dir,
+            exp_config.integrity_discrepancies
+        );
+    }
+
+    // save experiment summary; failing to do so doesn't fail the experiment,
+    // as the summary can always be recomputed from the metrics pulled
+    if let Err(e) = Summary::save(&exp_config, exp_dir) {
+        tracing::warn!("error saving summary in {}: {:?}", exp_dir, e);
+    }
+
+    // save experiment config
+    crate::serialize(
+        exp_config,
+        format!("{}/exp_config.json", exp_dir),
+        SerializationFormat::Json,
+    )
+    .wrap_err("save_exp_config")?;
+
+    // all files are in place, and so the results can be marked as complete
+    crate::mark_complete(exp_dir).wrap_err("mark_complete")?;
+
     Ok(())
 }
 
+// Checks, for each shard, that the number of commands executed by servers (i.e.
+// whose results were sent to clients) matches the number of commands issued by
+// clients, using the counts logged by both in `exp_dir`. Returns the
+// discrepancies found.
+fn check_integrity(
+    machines: &Machines<'_>,
+    colocation: Option<Colocation>,
+    exp_dir: &str,
+) -> Result<Vec<String>, Report> {
+    let mut executed = BTreeMap::new();
+    for (process_id, _) in machines.servers() {
+        let region = machines.process_region(process_id);
+        let prefix =
+            config::file_prefix(ProcessType::Server(*process_id), region);
+        let log =
+            std::fs::read_to_string(format!("{}/{}.log", exp_dir, prefix))
+                .wrap_err("read server log")?;
+        sum_shard_counts(&log, "executed", &mut executed);
+    }
+
+    let mut issued = BTreeMap::new();
+    for (region, process_type) in client_process_types(machines, colocation) {
+        let prefix = config::file_prefix(process_type, region);
+        let log =
+            std::fs::read_to_string(format!("{}/{}.log", exp_dir, prefix))
+                .wrap_err("read client log")?;
+        sum_shard_counts(&log, "issued", &mut issued);
+    }
+
+    let shards: BTreeSet<_> = executed.keys().chain(issued.keys()).collect();
+    let discrepancies = shards
+        .into_iter()
+        .filter_map(|shard_id| {
+            let executed = executed.get(shard_id).copied().unwrap_or_default();
+            let issued = issued.get(shard_id).copied().unwrap_or_default();
+            if executed != issued {
+                Some(format!(
+                    "shard {}: {} commands executed out of {} issued",
+                    shard_id, executed, issued
+                ))
+            } else {
+                None
+            }
+        })
+        .collect();
+    Ok(discrepancies)
+}
+
+// Returns the process type (and region) of the clients in all client machines,
+// and of the clients colocated with servers (if any).
+fn client_process_types<'a>(
+    machines: &'a Machines<'_>,
+    colocation: Option<Colocation>,
+) -> Vec<(&'a Region, ProcessType)> {
+    let mut process_types: Vec<_> = machines
+        .clients()
+        .map(|(region, client_index, _)| {
+            let region_index = machines.region_index(region);
+            (region, ProcessType::Client(region_index, client_index))
+        })
+        .collect();
+    if colocation.is_some() {
+        process_types.extend(machines.servers().map(|(process_id, _)| {
+            let region = machines.process_region(process_id);
+            (region, ProcessType::ColocatedClient(*process_id))
+        }));
+    }
+    process_types
+}
+
+// Sums, per shard, the counts in the log lines of the form
+// "shard SHARD_ID commands WHAT: COUNT".
+fn sum_shard_counts(
+    log: &str,
+    what: &str,
+    counts: &mut BTreeMap<ShardId, usize>,
+) {
+    let what = format!("{}:", what);
+    for line in log.lines() {
+        let tokens: Vec<_> = line.split_whitespace().collect();
+        for window in tokens.windows(5) {
+            if let ["shard", shard_id, "commands", found, count] = window {
+                if *found == what {
+                    if let (Ok(shard_id), Ok(count)) =
+                        (shard_id.parse(), count.parse::<usize>())
+                    {
+                        *counts.entry(shard_id).or_default() += count;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Returns the sanity checks failed by the results in `exp_dir`.
+fn check_results(
+    machines: &Machines<'_>,
+    sanity_checks: SanityChecks,
+    exp_dir: &str,
+) -> Result<Vec<String>, Report> {
+    let exp_config: ExperimentConfig = crate::deserialize(
+        format!("{}/exp_config.json", exp_dir),
+        SerializationFormat::Json,
+    )
+    .wrap_err("deserialize exp config")?;
+
+    let mut client_data = ClientData::new();
+    let mut client_errors = 0;
+    for (region, process_type) in
+        client_process_types(machines, exp_config.colocation)
+    {
+        let prefix = config::file_prefix(process_type, region);
+
+        // merge the metrics of all clients
+        let data: ClientData = crate::deserialize(
+            format!("{}/{}_metrics.bincode.gz", exp_dir, prefix),
+            SerializationFormat::BincodeGz,
+        )
+        .wrap_err("deserialize client metrics")?;
+        client_data.merge(&data);
+
+        // count the errors logged and anything written to stderr; logs don't
+        // include the level, so errors are detected by their message
+        let log =
+            std::fs::read_to_string(format!("{}/{}.log", exp_dir, prefix))
+                .wrap_err("read client log")?;
+        let err =
+            std::fs::read_to_string(format!("{}/{}.err", exp_dir, prefix))
+                .wrap_err("read client err")?;
+        client_errors +=
+            log.lines().filter(|line| line.contains("] error")).count()
+                + err.lines().filter(|line| !line.trim().is_empty()).count();
+    }
+
+    let mut failures = Vec::new();
+    // all commands issued should have completed (in time-bounded runs, clients
+    // are stopped before issuing all their commands)
+    if exp_config.run_duration.is_none() {
+        let colocated_clients = exp_config
+            .colocation
+            .map(|colocation| colocation.clients * machines.server_count())
+            .unwrap_or_default();
+        let issued =
+            (exp_config.clients_per_region.total(exp_config.config.n())
+                + colocated_clients)
+                * exp_config.workload.commands_per_client();
+        let completed = client_data.latency_data().count();
+        if completed != issued {
+            failures.push(format!(
+                "{} commands completed out of {} issued",
+                completed, issued
+            ));
+        }
+    }
+    if client_errors > sanity_checks.max_client_errors {
+        failures.push(format!(
+            "{} client errors (max is {})",
+            client_errors, sanity_checks.max_client_errors
+        ));
+    }
+    if client_data.throughput() == 0.0 {
+        failures.push(String::from("throughput is 0"));
+    }
+    Ok(failures)
+}
+
+// Records in the experiment config the sanity checks failed by the results in
+// `exp_dir`.
+fn flag_results(exp_dir: &str, failures: Vec<String>) -> Result<(), Report> {
+    let path = format!("{}/exp_config.json", exp_dir);
+    let mut exp_config: ExperimentConfig =
+        crate::deserialize(&path, SerializationFormat::Json)
+            .wrap_err("deserialize exp config")?;
+    exp_config.sanity_failures = failures;
+    crate::serialize(exp_config, &path, SerializationFormat::Json)
+        .wrap_err("serialize exp config")
+}
+
+async fn remove_shared_files(machines: &Machines<'_>) -> Result<(), Report> {
+    let command = format!("rm -f *.{} *.{}", DSTAT_FILE_EXT, PING_FILE_EXT);
+    let mut removes = Vec::with_capacity(machines.vm_count());
+    for vm in machines.vms() {
+        removes.push(vm.exec(command.clone()));
+    }
+    for result in futures::future::join_all(removes).await {
+        let _ = result.wrap_err("remove shared files")?;
+    }
+    Ok(())
+}
+
+// Returns the metadata of all machines, indexed by the prefix of the files
+// of the process running in each machine.
+async fn machines_metadata(
+    machines: &Machines<'_>,
+    testbed: Testbed,
+) -> Result<BTreeMap<String, MachineMetadata>, Report> {
+    let mut names = Vec::with_capacity(machines.vm_count());
+    let mut vms = Vec::with_capacity(machines.vm_count());
+    for (process_id, vm) in machines.servers() {
+        let region = machines.process_region(process_id);
+        names.push(config::file_prefix(
+            ProcessType::Server(*process_id),
+            region,
+        ));
+        vms.push(vm);
+    }
+    for (region, client_index, vm) in machines.clients() {
+        let region_index = machines.region_index(region);
+        let process_type = ProcessType::Client(region_index, client_index);
+        names.push(config::file_prefix(process_type, region));
+        vms.push(vm);
+    }
+
+    // find the kernel of all machines in parallel
+    let kernels =
+        futures::future::join_all(vms.iter().map(|vm| vm.exec("uname -r")))
+            .await;
+    // on AWS, also find where each machine was placed
+    let placements =
+        futures::future::join_all(vms.iter().map(|vm| async move {
+            match testbed {
+                Testbed::Aws => machine_placement(vm).await.map(Some),
+                Testbed::Local | Testbed::Baremetal => Ok(None),
+            }
+        }))
+        .await;
+    let mut metadata = BTreeMap::new();
+    for (((name, vm), kernel), placement) in
+        names.into_iter().zip(vms).zip(kernels).zip(placements)
+    {
+        let kernel = kernel.wrap_err("uname -r")?.trim().to_string();
+        let placement = placement.wrap_err("machine_placement")?;
+        metadata.insert(
+            name,
+            MachineMetadata {
+                ip: vm.ip(),
+                kernel,
+                mss: None,
+                placement,
+            },
+        );
+    }
+    Ok(metadata)
+}
+
+// Returns the availability zone and the id of the instance of an AWS machine,
+// as reported by the instance metadata service.
+async fn machine_placement(
+    vm: &Machine<'_>,
+) -> Result<MachinePlacement, Report> {
+    let query = |path| {
+        format!(
+            "curl -s -m 5 http://169.254.169.254/latest/meta-data/{}",
+            path
+        )
+    };
+    let availability_zone = vm
+        .exec(query("placement/availability-zone"))
+        .await
+        .wrap_err("query availability zone")?
+        .trim()
+        .to_string();
+    let instance_id = vm
+        .exec(query("instance-id"))
+        .await
+        .wrap_err("query instance id")?
+        .trim()
+        .to_string();
+    Ok(MachinePlacement {
+        availability_zone,
+        instance_id,
+    })
+}
+
+// Records the effective MSS logged by each server of deployment `deployment`
+// in the metadata of its machine.
+async fn record_effective_mss(
+    machines: &Machines<'_>,
+    deployment: DeploymentIndex,
+    exp_metadata: &mut ExperimentMetadata,
+) -> Result<(), Report> {
+    for (process_id, vm) in machines.servers() {
+        let process_type = ProcessType::Server(*process_id);
+        let log_file =
+            config::deployment_run_file(deployment, process_type, LOG_FILE_EXT);
+        let command = format!(
+            "grep -o 'effective MSS: [0-9]*' {} | tail -n 1 | cut -d' ' -f3",
+            log_file
+        );
+        let stdout = vm.exec(&command).await.wrap_err("grep effective MSS")?;
+        // the MSS is not logged if it can't be probed
+        let mss = if stdout.is_empty() {
+            None
+        } else {
+            Some(stdout.trim().parse::<u32>().wrap_err("parse MSS")?)
+        };
+        let region = machines.process_region(process_id);
+        let name = config::file_prefix(process_type, region);
+        if let Some(machine) = exp_metadata.machines.get_mut(&name) {
+            machine.mss = mss;
+        }
+    }
+    Ok(())
+}
+
+// Returns a random token (`RandomState` is seeded randomly by the OS, which
+// saves a dependency on `rand`).
+fn random_auth_token() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher =
+        std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(exp_timestamp());
+    hasher.finish()
+}
+
 async fn create_exp_dir(
     results_dir: impl AsRef<Path>,
 ) -> Result<String, Report> {
@@ -927,19 +2433,27 @@ fn exp_timestamp() -> u128 {
 }
 
 async fn pull_metrics_files(
+    deployment: DeploymentIndex,
     process_type: ProcessType,
     region: &Region,
     vm: &Machine<'_>,
+    pull_metrics: bool,
+    pull_pings: bool,
+    pull_traces: bool,
     exp_dir: &str,
 ) -> Result<(), Report> {
     // compute filename prefix
     let prefix = config::file_prefix(process_type, region);
 
-    // compute files to be pulled
-    let log_file = config::run_file(process_type, LOG_FILE_EXT);
-    let err_file = config::run_file(process_type, ERR_FILE_EXT);
+    // compute files to be pulled:
+    // - dstat and ping files are shared by all deployments
+    let log_file =
+        config::deployment_run_file(deployment, process_type, LOG_FILE_EXT);
+    let err_file =
+        config::deployment_run_file(deployment, process_type, ERR_FILE_EXT);
     let dstat_file = config::run_file(process_type, DSTAT_FILE_EXT);
-    let metrics_file = config::run_file(process_type, METRICS_FILE_EXT);
+    let metrics_file =
+        config::deployment_run_file(deployment, process_type, METRICS_FILE_EXT);
 
     // pull log file
     let local_path = format!("{}/{}.log", exp_dir, prefix);
@@ -953,22 +2467,47 @@ async fn pull_metrics_files(
         .await
         .wrap_err("copy err")?;
 
-    // pull dstat
-    let local_path = format!("{}/{}_dstat.csv", exp_dir, prefix);
-    vm.copy_from(&dstat_file, local_path)
-        .await
-        .wrap_err("copy dstat")?;
+    // pull dstat (colocated clients share the dstat of their server)
+    if !matches!(process_type, ProcessType::ColocatedClient(_)) {
+        let local_path = format!("{}/{}_dstat.csv", exp_dir, prefix);
+        vm.copy_from(&dstat_file, local_path)
+            .await
+            .wrap_err("copy dstat")?;
+    }
 
-    // pull metrics file
-    let local_path = format!("{}/{}_metrics.bincode.gz", exp_dir, prefix);
-    vm.copy_from(&metrics_file, local_path)
-        .await
-        .wrap_err("copy metrics")?;
+    // maybe pull metrics file
+    if pull_metrics {
+        let local_path = format!("{}/{}_metrics.bincode.gz", exp_dir, prefix);
+        vm.copy_from(&metrics_file, local_path)
+            .await
+            .wrap_err("copy metrics")?;
+    }
+
+    // maybe pull ping file
+    if pull_pings {
+        let ping_file = config::run_file(process_type, PING_FILE_EXT);
+        let local_path = format!("{}/{}_ping.log", exp_dir, prefix);
+        vm.copy_from(&ping_file, local_path)
+            .await
+            .wrap_err("copy ping")?;
+    }
+
+    // maybe pull traces file (only generated by clients)
+    let traces_file =
+        config::deployment_run_file(deployment, process_type, TRACES_FILE_EXT);
+    if pull_traces {
+        let local_path = format!("{}/{}_traces.bincode.gz", exp_dir, prefix);
+        vm.copy_from(&traces_file, local_path)
+            .await
+            .wrap_err("copy traces")?;
+    }
 
     // remove metric files:
     // - note that in the case of `Process::Server`, the metrics file is
     //   generated periodic, and thus, remove it makes little sense
-    let to_remove = format!("rm {} {} {}", log_file, dstat_file, metrics_file);
+    // - dstat and ping files are removed in `remove_shared_files`
+    let to_remove =
+        format!("rm -f {} {} {}", log_file, metrics_file, traces_file);
     vm.exec(to_remove).await.wrap_err("remove files")?;
 
     match process_type {
@@ -979,8 +2518,19 @@ async fn pull_metrics_files(
                 region
             );
         }
-        ProcessType::Client(_) => {
-            tracing::info!("client metric files pulled in region {:?}", region);
+        ProcessType::Client(_, client_index) => {
+            tracing::info!(
+                "client (machine {}) metric files pulled in region {:?}",
+                client_index,
+                region
+            );
+        }
+        ProcessType::ColocatedClient(process_id) => {
+            tracing::info!(
+                "client (colocated with process {:?}) metric files pulled in region {:?}",
+                process_id,
+                region
+            );
         }
     }
 
@@ -1010,7 +2560,6 @@ async fn pull_flamegraph_file(
 }
 
 async fn pull_heaptrack_file(
-    protocol: Protocol,
     heaptrack_pid: u32,
     process_type: ProcessType,
     region: &Region,
@@ -1019,7 +2568,7 @@ async fn pull_heaptrack_file(
 ) -> Result<(), Report> {
     // compute heaptrack filename: heaptrack.BINARY.PID.gz
     let heaptrack =
-        format!("heaptrack.{}.{}.gz", protocol.binary(), heaptrack_pid);
+        format!("heaptrack.{}.{}.gz", crate::SERVER_BINARY, heaptrack_pid);
 
     // compute filename prefix
     let prefix = config::file_prefix(process_type, region);
@@ -1034,10 +2583,7 @@ async fn pull_heaptrack_file(
     Ok(())
 }
 
-pub async fn cleanup(
-    machines: &Machines<'_>,
-    protocols: Vec<Protocol>,
-) -> Result<(), Report> {
+pub async fn cleanup(machines: &Machines<'_>) -> Result<(), Report> {
     // stop dstats in all machines
     stop_dstats(machines, Vec::new())
         .await
@@ -1045,12 +2591,11 @@ pub async fn cleanup(
 
     // do the rest of the cleanup
     let mut cleanups = Vec::new();
-    for protocol in protocols {
-        for (_, vm) in machines.servers() {
-            cleanups.push(cleanup_machine(vm, protocol.binary()));
-        }
+    // all protocols run as the same binary
+    for (_, vm) in machines.servers() {
+        cleanups.push(cleanup_machine(vm, crate::SERVER_BINARY));
     }
-    for (_, vm) in machines.clients() {
+    for (_, _, vm) in machines.clients() {
         cleanups.push(cleanup_machine(vm, "client"));
     }
 
@@ -1072,6 +2617,7 @@ async fn cleanup_machine(
     vm.exec("pkill heaptrack")
         .await
         .wrap_err("pkill heaptrack")?;
+    vm.exec("pkill ping").await.wrap_err("pkill ping")?;
 
     // kill the binary
     let command = format!("pkill {}", binary);
@@ -1094,11 +2640,14 @@ async fn cleanup_machine(
 
     // remove files
     let command = format!(
-        "rm -f *.{} *.{} *.{} *.{} *.{} heaptrack.*.gz *perf.data*",
+        "rm -f *.{} *.{} *.{} *.{} *.{} *.{} *.{} *.{} heaptrack.*.gz *perf.data*",
         LOG_FILE_EXT,
         ERR_FILE_EXT,
         DSTAT_FILE_EXT,
         METRICS_FILE_EXT,
+        PING_FILE_EXT,
+        TRACES_FILE_EXT,
+        STACKS_FILE_EXT,
         FLAMEGRAPH_FILE_EXT
     );
     vm.exec(command).await.wrap_err("rm files")?;
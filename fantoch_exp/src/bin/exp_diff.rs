@@ -0,0 +1,20 @@
+use color_eyre::Report;
+
+/// Prints the differences between the recorded metadata of two experiment
+/// folders. It should be called like: $ exp_diff exp_dir exp_dir
+fn main() -> Result<(), Report> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() != 2 {
+        eprintln!("usage: exp_diff EXP_DIR EXP_DIR");
+        std::process::exit(1);
+    }
+
+    let differences = fantoch_exp::metadata::diff(&args[0], &args[1])?;
+    if differences.is_empty() {
+        println!("no differences");
+    }
+    for difference in differences {
+        println!("{}", difference);
+    }
+    Ok(())
+}
@@ -1,17 +1,25 @@
 use color_eyre::eyre::WrapErr;
 use color_eyre::Report;
-use fantoch::client::{KeyGen, Workload};
-use fantoch::config::Config;
-use fantoch::planet::Planet;
-use fantoch_exp::bench::ExperimentTimeouts;
-use fantoch_exp::machine::Machines;
+use fantoch::client::{KeyGen, Workload, WorkloadMix};
+use fantoch::config::{Config, ConfigProfile};
+use fantoch::planet::{LatencyPercentile, Planet};
+use fantoch_exp::bench::{ExperimentTimeouts, LatencyChecks, SanityChecks};
+use fantoch_exp::dashboard::Dashboard;
+use fantoch_exp::machine::{BinaryCache, Machines};
 use fantoch_exp::progress::TracingProgressBar;
-use fantoch_exp::{FantochFeature, Protocol, RunMode, Testbed};
+use fantoch_exp::sweep::Sweep;
+use fantoch_exp::testbed::aws::{AwsProvider, ServerPlacement};
+use fantoch_exp::testbed::baremetal::BaremetalProvider;
+use fantoch_exp::testbed::local::LocalProvider;
+use fantoch_exp::testbed::MachineProvider;
+use fantoch_exp::{
+    ClientsPerRegion, Colocation, FantochFeature, Protocol, RegionFailure,
+    RunMode, TcpConfig, Testbed,
+};
 use rusoto_core::Region;
 use std::path::Path;
 use std::time::Duration;
 use tsunami::providers::aws::LaunchMode;
-use tsunami::Tsunami;
 
 // timeouts
 const fn minutes(minutes: u64) -> Duration {
@@ -22,35 +30,104 @@ const EXPERIMENT_TIMEOUTS: ExperimentTimeouts = ExperimentTimeouts {
     start: Some(minutes(20)),
     run: Some(minutes(20)),
     stop: Some(minutes(20)),
+    zero_throughput: Some(minutes(5)),
+    stalled: Some(minutes(2)),
 };
 
 // latency dir
 const LATENCY_AWS: &str = "../latency_aws/2020_06_05";
 // const LATENCY_AWS: &str = "../latency_aws/2021_02_13";
+// RTT statistic used when injecting delays
+const LATENCY_PERCENTILE: LatencyPercentile = LatencyPercentile::Avg;
 
 // aws experiment config
 const LAUCH_MODE: LaunchMode = LaunchMode::OnDemand;
 // const SERVER_INSTANCE_TYPE: &str = "m5.4xlarge";
 const SERVER_INSTANCE_TYPE: &str = "c5.2xlarge";
 const CLIENT_INSTANCE_TYPE: &str = "m5.2xlarge";
+// - with `ServerPlacement::DistinctZones`, the servers in each region are placed
+//   in distinct availability zones (and thus hosts), so that noisy-neighbor
+//   effects between them are ruled out
+const SERVER_PLACEMENT: ServerPlacement = ServerPlacement::Any;
 const MAX_SPOT_INSTANCE_REQUEST_WAIT_SECS: u64 = 5 * 60; // 5 minutes
 
-// processes config
-const EXECUTE_AT_COMMIT: bool = false;
-const EXECUTOR_CLEANUP_INTERVAL: Duration = Duration::from_millis(10);
-const EXECUTOR_MONITOR_PENDING_INTERVAL: Option<Duration> = None;
-const GC_INTERVAL: Option<Duration> = Some(Duration::from_millis(50));
-const SEND_DETACHED_INTERVAL: Duration = Duration::from_millis(5);
+// processes config: knobs not set by each experiment come from this profile
+const CONFIG_PROFILE: ConfigProfile = ConfigProfile::WanDefault;
 
 // clients config
+// - clients in each region are split among these many client machines
+const CLIENT_MACHINES_PER_REGION: usize = 1;
+// - if set, each server machine also runs some clients (e.g. `Some(Colocation
+//   { clients: 8, cpu_shares: Some(50) })`), in addition to the ones in the
+//   client machines
+const COLOCATION: Option<Colocation> = None;
 const COMMANDS_PER_CLIENT_WAN: usize = 500;
 const COMMANDS_PER_CLIENT_LAN: usize = 5_000;
+// - if set, each combination runs for this long, and clients stop even if they
+//   haven't issued all their commands (so the commands per client above should
+//   be high enough for clients not to finish earlier)
+const RUN_DURATION: Option<Duration> = None;
+
+// experiments whose results fail the sanity checks are rerun (disabled if
+// `None`)
+const SANITY_CHECKS: Option<SanityChecks> = Some(SanityChecks {
+    max_reruns: 2,
+    max_client_errors: 0,
+});
+
+// when delays are injected, the latencies between processes are checked before
+// experiments start (disabled if `None`)
+const LATENCY_CHECKS: Option<LatencyChecks> = Some(LatencyChecks {
+    pings: 10,
+    tolerance: Duration::from_millis(5),
+    abort: true,
+});
+
+// tcp config of the connections between processes (and of the server-side of
+// client connections) and of the client-side of client connections
+const PROCESS_TCP_CONFIG: TcpConfig = TcpConfig {
+    nodelay: true,
+    send_buffer_size: None,
+    recv_buffer_size: None,
+    keepalive: None,
+    max_segment_size: None,
+    auth_token: None,
+};
+const CLIENT_TCP_CONFIG: TcpConfig = TcpConfig {
+    nodelay: true,
+    send_buffer_size: None,
+    recv_buffer_size: None,
+    keepalive: None,
+    max_segment_size: None,
+    auth_token: None,
+};
 
 // batching config
 const BATCH_MAX_DELAY: Duration = Duration::from_millis(5);
 
+// background ping mesh between all machines (disabled if `None`)
+const PING_INTERVAL: Option<Duration> = None;
+
+// port of the local web page showing the progress of experiments (disabled if
+// `None`)
+const DASHBOARD_PORT: Option<u16> = Some(8080);
+
+// if set, the runs of each experiment are shuffled (deterministically) using
+// this seed, so that time-of-day effects are spread across runs
+const RUN_ORDER_SEED: Option<u64> = None;
+
+// if set, clients' workloads are seeded with this seed, so that experiments
+// issue the same commands when repeated
+const EXPERIMENT_SEED: Option<u64> = None;
+
+// protocol to be deployed alongside each of the protocols being benchmarked
+// (if any); both run on the same machines with mirrored workloads
+const CO_DEPLOYED: Option<Protocol> = None;
+
 // fantoch run config
 const BRANCH: &str = "master";
+// binaries are built once and then reused across experiment runs
+const BINARY_CACHE_DIR: &str = "binary_cache";
 
 // tracing max log level: compile-time level should be <= run-time level
 const MAX_LEVEL_COMPILE_TIME: tracing::Level = tracing::Level::INFO;
@@ -68,44 +145,27 @@ const RUN_MODE: RunMode = RunMode::Release;
 // const FEATURES: &[FantochFeature] = &[FantochFeature::Jemalloc];
 // const RUN_MODE: RunMode = RunMode::Flamegraph;
 
-// list of protocol binaries to cleanup before running the experiment
-const PROTOCOLS_TO_CLEANUP: &[Protocol] = &[
-    Protocol::TempoAtomic,
-    /*
-    Protocol::Basic,
-    Protocol::AtlasLocked,
-    Protocol::EPaxosLocked,
-    Protocol::CaesarLocked,
-    Protocol::FPaxos,
-    Protocol::CaesarLocked,
-    */
-];
-
 macro_rules! config {
     ($n:expr, $f:expr, $tiny_quorums:expr, $clock_bump_interval:expr, $skip_fast_ack:expr) => {{
-        let mut config = Config::new($n, $f);
+        config!(CONFIG_PROFILE; $n, $f, $tiny_quorums, $clock_bump_interval, $skip_fast_ack)
+    }};
+    ($profile:expr; $n:expr, $f:expr, $tiny_quorums:expr, $clock_bump_interval:expr, $skip_fast_ack:expr) => {{
+        let mut config = Config::from_profile($profile, $n, $f);
         config.set_tempo_tiny_quorums($tiny_quorums);
         if let Some(interval) = $clock_bump_interval {
             config.set_tempo_clock_bump_interval::<Option<Duration>>(interval);
         }
         config.set_skip_fast_ack($skip_fast_ack);
-        config.set_execute_at_commit(EXECUTE_AT_COMMIT);
-        config.set_executor_cleanup_interval(EXECUTOR_CLEANUP_INTERVAL);
-        if let Some(interval) = EXECUTOR_MONITOR_PENDING_INTERVAL {
-            config.set_executor_monitor_pending_interval(interval);
-        }
-        if let Some(interval) = GC_INTERVAL {
-            config.set_gc_interval(interval);
-        }
-        config.set_tempo_detached_send_interval(SEND_DETACHED_INTERVAL);
         config
     }};
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Report> {
+    // failover_plot().await
     // fairness_and_tail_latency_plot().await
     // increasing_load_plot().await
+    // load_sweep_plot().await
     // batching_plot().await
     partial_replication_plot().await
 }
@@ -183,7 +243,7 @@ async fn partial_replication_plot() -> Result<(), Report> {
                 payload_size,
             );
             workload.set_read_only_percentage(read_only_percentage);
-            workloads.push(workload);
+            workloads.push(WorkloadMix::single(workload));
         }
     }
 
@@ -204,7 +264,7 @@ async fn partial_replication_plot() -> Result<(), Report> {
     );
 
     // create AWS planet
-    let planet = Some(Planet::from(LATENCY_AWS));
+    let planet = Some(Planet::from_percentile(LATENCY_AWS, LATENCY_PERCENTILE));
 
     baremetal_bench(
         regions,
@@ -215,6 +275,7 @@ async fn partial_replication_plot() -> Result<(), Report> {
         workloads,
         batch_max_sizes,
         cpus,
+        None,
         skip,
         progress,
         results_dir,
@@ -269,6 +330,7 @@ async fn batching_plot() -> Result<(), Report> {
     let key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
 
     let mut workloads = Vec::new();
@@ -280,7 +342,7 @@ async fn batching_plot() -> Result<(), Report> {
             COMMANDS_PER_CLIENT_WAN,
             payload_size,
         );
-        workloads.push(workload);
+        workloads.push(WorkloadMix::single(workload));
     }
 
     let skip = |_, _, _| false;
@@ -299,7 +361,7 @@ async fn batching_plot() -> Result<(), Report> {
     );
 
     // create AWS planet
-    let planet = Some(Planet::from(LATENCY_AWS));
+    let planet = Some(Planet::from_percentile(LATENCY_AWS, LATENCY_PERCENTILE));
     baremetal_bench(
         regions.clone(),
         shard_count,
@@ -309,6 +371,7 @@ async fn batching_plot() -> Result<(), Report> {
         workloads.clone(),
         batch_max_sizes,
         cpus,
+        None,
         skip,
         progress.clone(),
         results_dir,
@@ -366,10 +429,12 @@ async fn increasing_load_plot() -> Result<(), Report> {
         KeyGen::ConflictPool {
             conflict_rate: 2,
             pool_size: 1,
+            pool_id: 0,
         },
         KeyGen::ConflictPool {
             conflict_rate: 10,
             pool_size: 1,
+            pool_id: 0,
         },
     ];
 
@@ -382,12 +447,12 @@ async fn increasing_load_plot() -> Result<(), Report> {
             COMMANDS_PER_CLIENT_WAN,
             payload_size,
         );
-        workloads.push(workload);
+        workloads.push(WorkloadMix::single(workload));
     }
 
-    let skip = |protocol, _, clients| {
+    let skip = |protocol, _, clients: ClientsPerRegion| {
         // skip Atlas with more than 4096 clients
-        protocol == Protocol::AtlasLocked && clients > 1024 * 20
+        protocol == Protocol::AtlasLocked && clients.max() > 1024 * 20
     };
 
     // set shards in each config
@@ -404,7 +469,7 @@ async fn increasing_load_plot() -> Result<(), Report> {
     );
 
     // create AWS planet
-    let planet = Some(Planet::from(LATENCY_AWS));
+    let planet = Some(Planet::from_percentile(LATENCY_AWS, LATENCY_PERCENTILE));
 
     baremetal_bench(
         regions,
@@ -415,6 +480,7 @@ async fn increasing_load_plot() -> Result<(), Report> {
         workloads,
         batch_max_sizes,
         cpus,
+        None,
         skip,
         progress,
         results_dir,
@@ -423,8 +489,9 @@ async fn increasing_load_plot() -> Result<(), Report> {
 }
 
 #[allow(dead_code)]
-async fn fairness_and_tail_latency_plot() -> Result<(), Report> {
-    let results_dir = "../results_fairness_and_tail_latency";
+async fn failover_plot() -> Result<(), Report> {
+    // folder where all results will be stored
+    let results_dir = "../results_failover";
     let regions = vec![
         Region::EuWest1,
         Region::UsWest1,
@@ -434,16 +501,220 @@ async fn fairness_and_tail_latency_plot() -> Result<(), Report> {
     ];
     let n = regions.len();
 
+    // after 60 seconds of steady-state load, all processes (and clients) in
+    // `Region::ApSoutheast1` are killed; clients in the remaining regions keep
+    // running for another 60 seconds, and their commands time out after 1
+    // second (so that their availability can be measured)
+    let region_failure = RegionFailure::new(
+        fantoch::planet::Region::new(Region::ApSoutheast1.name()),
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+        Some(Duration::from_secs(1)),
+    );
+
     let mut configs = vec![
         // (protocol, (n, f, tiny quorums, clock bump interval, skip fast ack))
-        (Protocol::FPaxos, config!(n, 1, false, None, false)),
-        (Protocol::FPaxos, config!(n, 2, false, None, false)),
         (Protocol::TempoAtomic, config!(n, 1, false, None, false)),
         (Protocol::TempoAtomic, config!(n, 2, false, None, false)),
+        (Protocol::FPaxos, config!(n, 1, false, None, false)),
+        (Protocol::FPaxos, config!(n, 2, false, None, false)),
         (Protocol::AtlasLocked, config!(n, 1, false, None, false)),
         (Protocol::AtlasLocked, config!(n, 2, false, None, false)),
-        (Protocol::EPaxosLocked, config!(n, 2, false, None, false)),
-        (Protocol::CaesarLocked, config!(n, 2, false, None, false)),
+    ];
+
+    let clients_per_region = vec![512];
+    let batch_max_sizes = vec![1];
+
+    let shard_count = 1;
+    let keys_per_command = 1;
+    let payload_size = 100;
+    let cpus = 12;
+
+    let key_gen = KeyGen::ConflictPool {
+        conflict_rate: 2,
+        pool_size: 1,
+        pool_id: 0,
+    };
+    // clients are stopped by the region failure, so the workload should be
+    // long enough for clients to still be running by then
+    let commands_per_client = 1_000_000;
+    let workload = Workload::new(
+        shard_count,
+        key_gen,
+        keys_per_command,
+        commands_per_client,
+        payload_size,
+    );
+    let workloads = vec![WorkloadMix::single(workload)];
+
+    let skip = |_, _, _| false;
+
+    // set shards in each config
+    configs
+        .iter_mut()
+        .for_each(|(_protocol, config)| config.set_shard_count(shard_count));
+
+    // init logging
+    let progress = TracingProgressBar::init(
+        (workloads.len()
+            * clients_per_region.len()
+            * configs.len()
+            * batch_max_sizes.len()) as u64,
+    );
+
+    // create AWS planet
+    let planet = Some(Planet::from_percentile(LATENCY_AWS, LATENCY_PERCENTILE));
+
+    baremetal_bench(
+        regions,
+        shard_count,
+        planet,
+        configs,
+        clients_per_region,
+        workloads,
+        batch_max_sizes,
+        cpus,
+        Some(region_failure),
+        skip,
+        progress,
+        results_dir,
+    )
+    .await
+}
+
+#[allow(dead_code)]
+async fn load_sweep_plot() -> Result<(), Report> {
+    // folder where all results will be stored
+    let results_dir = "../results_load_sweep";
+
+    // parameters swept
+    let sweep = Sweep::parse(
+        "
+        clients = [32..65536 step x2]
+        conflict ∈ {2, 10, 30}
+        ",
+    )?;
+    let clients_per_region: Vec<usize> = sweep.values("clients")?;
+    let conflict_rates = sweep.values("conflict")?;
+
+    let regions = vec![
+        Region::EuWest1,
+        Region::UsWest1,
+        Region::ApSoutheast1,
+        Region::CaCentral1,
+        Region::SaEast1,
+    ];
+    let n = regions.len();
+
+    let mut configs = vec![
+        // (protocol, (n, f, tiny quorums, clock bump interval, skip fast ack))
+        (Protocol::TempoAtomic, config!(n, 1, false, None, false)),
+        (Protocol::FPaxos, config!(n, 1, false, None, false)),
+        (Protocol::AtlasLocked, config!(n, 1, false, None, false)),
+    ];
+    let batch_max_sizes = vec![1];
+
+    let shard_count = 1;
+    let keys_per_command = 1;
+    let payload_size = 100;
+    let cpus = 12;
+
+    let workloads: Vec<_> = conflict_rates
+        .into_iter()
+        .map(|conflict_rate| {
+            let key_gen = KeyGen::ConflictPool {
+                conflict_rate,
+                pool_size: 1,
+                pool_id: 0,
+            };
+            let workload = Workload::new(
+                shard_count,
+                key_gen,
+                keys_per_command,
+                COMMANDS_PER_CLIENT_WAN,
+                payload_size,
+            );
+            WorkloadMix::single(workload)
+        })
+        .collect();
+
+    let skip = |_, _, _| false;
+
+    // set shards in each config
+    configs
+        .iter_mut()
+        .for_each(|(_protocol, config)| config.set_shard_count(shard_count));
+
+    // init logging
+    let progress = TracingProgressBar::init(
+        (workloads.len()
+            * clients_per_region.len()
+            * configs.len()
+            * batch_max_sizes.len()) as u64,
+    );
+
+    // create AWS planet
+    let planet = Some(Planet::from_percentile(LATENCY_AWS, LATENCY_PERCENTILE));
+
+    baremetal_bench(
+        regions,
+        shard_count,
+        planet,
+        configs,
+        clients_per_region,
+        workloads,
+        batch_max_sizes,
+        cpus,
+        None,
+        skip,
+        progress,
+        results_dir,
+    )
+    .await
+}
+
+#[allow(dead_code)]
+async fn fairness_and_tail_latency_plot() -> Result<(), Report> {
+    let results_dir = "../results_fairness_and_tail_latency";
+    let regions = vec![
+        Region::EuWest1,
+        Region::UsWest1,
+        Region::ApSoutheast1,
+        Region::CaCentral1,
+        Region::SaEast1,
+    ];
+    let n = regions.len();
+
+    // knobs used in the paper
+    let profile = ConfigProfile::PaperEurosysFig6;
+    let mut configs = vec![
+        // (protocol, (n, f, tiny quorums, clock bump interval, skip fast ack))
+        (Protocol::FPaxos, config!(profile; n, 1, false, None, false)),
+        (Protocol::FPaxos, config!(profile; n, 2, false, None, false)),
+        (
+            Protocol::TempoAtomic,
+            config!(profile; n, 1, false, None, false),
+        ),
+        (
+            Protocol::TempoAtomic,
+            config!(profile; n, 2, false, None, false),
+        ),
+        (
+            Protocol::AtlasLocked,
+            config!(profile; n, 1, false, None, false),
+        ),
+        (
+            Protocol::AtlasLocked,
+            config!(profile; n, 2, false, None, false),
+        ),
+        (
+            Protocol::EPaxosLocked,
+            config!(profile; n, 2, false, None, false),
+        ),
+        (
+            Protocol::CaesarLocked,
+            config!(profile; n, 2, false, None, false),
+        ),
     ];
 
     let clients_per_region = vec![256, 512];
@@ -457,6 +728,7 @@ async fn fairness_and_tail_latency_plot() -> Result<(), Report> {
     let key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
     let key_gens = vec![key_gen];
 
@@ -469,12 +741,12 @@ async fn fairness_and_tail_latency_plot() -> Result<(), Report> {
             COMMANDS_PER_CLIENT_WAN,
             payload_size,
         );
-        workloads.push(workload);
+        workloads.push(WorkloadMix::single(workload));
     }
 
-    let skip = |protocol, _, clients| {
+    let skip = |protocol, _, clients: ClientsPerRegion| {
         // only run FPaxos with 512 clients
-        protocol == Protocol::FPaxos && clients != 512
+        protocol == Protocol::FPaxos && clients != 512.into()
     };
 
     // set shards in each config
@@ -503,6 +775,7 @@ async fn fairness_and_tail_latency_plot() -> Result<(), Report> {
         workloads,
         batch_max_sizes,
         cpus,
+        None,
         skip,
         progress,
         results_dir,
@@ -564,6 +837,12 @@ async fn whatever_plot() -> Result<(), Report> {
     let payload_size = 100;
     let cpus = 2;
 
+    // compare tracking the commands of each client connection in a single task
+    // with spreading them across as many tasks as cpus
+    let mut sharded_pending = config!(n, 1, false, None, false);
+    sharded_pending.set_client_pending_shards(cpus);
+    configs.push((Protocol::TempoAtomic, sharded_pending));
+
     let coefficients = vec![
         // 0.5, 0.75, 1.0,
         // 1.25, 1.5, 1.75,
@@ -583,12 +862,12 @@ async fn whatever_plot() -> Result<(), Report> {
             COMMANDS_PER_CLIENT_LAN,
             payload_size,
         );
-        workloads.push(workload);
+        workloads.push(WorkloadMix::single(workload));
     }
 
-    let skip = |protocol, _, clients| {
+    let skip = |protocol, _, clients: ClientsPerRegion| {
         // skip Atlas with more than 4096 clients
-        protocol == Protocol::AtlasLocked && clients > 1024 * 20
+        protocol == Protocol::AtlasLocked && clients.max() > 1024 * 20
     };
 
     /*
@@ -686,7 +965,7 @@ async fn whatever_plot() -> Result<(), Report> {
         COMMANDS_PER_CLIENT,
         payload_size,
     );
-    workloads.push(workload);
+    workloads.push(WorkloadMix::single(workload));
 
     let skip = |_, _, _| false;
     */
@@ -717,6 +996,7 @@ async fn whatever_plot() -> Result<(), Report> {
         workloads,
         batch_max_sizes,
         cpus,
+        None,
         skip,
         progress,
         results_dir,
@@ -754,23 +1034,28 @@ async fn local_bench(
     shard_count: usize,
     planet: Option<Planet>,
     configs: Vec<(Protocol, Config)>,
-    clients_per_region: Vec<usize>,
-    workloads: Vec<Workload>,
+    clients_per_region: Vec<impl Into<ClientsPerRegion>>,
+    workloads: Vec<WorkloadMix>,
     batch_max_sizes: Vec<usize>,
     cpus: usize,
-    skip: impl Fn(Protocol, Config, usize) -> bool,
+    region_failure: Option<RegionFailure>,
+    skip: impl Fn(Protocol, Config, ClientsPerRegion) -> bool,
     progress: TracingProgressBar,
     results_dir: impl AsRef<Path>,
 ) -> Result<(), Report>
 where
 {
-    // setup baremetal machines
-    let machines = fantoch_exp::testbed::local::setup(
+    // fail before launching any machine if the spec is known to be invalid
+    fantoch_exp::lint::lint(&configs, &batch_max_sizes).wrap_err("lint")?;
+
+    // setup local machines
+    let mut provider =
+        LocalProvider::new(BRANCH.to_string(), RUN_MODE, all_features());
+    let machines = fantoch_exp::testbed::setup(
+        &mut provider,
         regions,
         shard_count,
-        BRANCH.to_string(),
-        RUN_MODE,
-        all_features(),
+        CLIENT_MACHINES_PER_REGION,
     )
     .await
     .wrap_err("local spawn")?;
@@ -785,6 +1070,7 @@ where
         workloads,
         batch_max_sizes,
         cpus,
+        region_failure,
         skip,
         progress,
         results_dir,
@@ -801,30 +1087,32 @@ async fn baremetal_bench(
     shard_count: usize,
     planet: Option<Planet>,
     configs: Vec<(Protocol, Config)>,
-    clients_per_region: Vec<usize>,
-    workloads: Vec<Workload>,
+    clients_per_region: Vec<impl Into<ClientsPerRegion>>,
+    workloads: Vec<WorkloadMix>,
     batch_max_sizes: Vec<usize>,
     cpus: usize,
-    skip: impl Fn(Protocol, Config, usize) -> bool,
+    region_failure: Option<RegionFailure>,
+    skip: impl Fn(Protocol, Config, ClientsPerRegion) -> bool,
     progress: TracingProgressBar,
     results_dir: impl AsRef<Path>,
 ) -> Result<(), Report>
 where
 {
-    // create launcher
-    let mut launchers = fantoch_exp::testbed::baremetal::create_launchers(
-        &regions,
-        shard_count,
-    );
+    // fail before launching any machine if the spec is known to be invalid
+    fantoch_exp::lint::lint(&configs, &batch_max_sizes).wrap_err("lint")?;
 
     // setup baremetal machines
-    let machines = fantoch_exp::testbed::baremetal::setup(
-        &mut launchers,
-        regions,
-        shard_count,
+    let mut provider = BaremetalProvider::new(
         BRANCH.to_string(),
         RUN_MODE,
         all_features(),
+        BinaryCache::new(BINARY_CACHE_DIR),
+    );
+    let machines = fantoch_exp::testbed::setup(
+        &mut provider,
+        regions,
+        shard_count,
+        CLIENT_MACHINES_PER_REGION,
     )
     .await
     .wrap_err("baremetal spawn")?;
@@ -839,6 +1127,7 @@ where
         workloads,
         batch_max_sizes,
         cpus,
+        region_failure,
         skip,
         progress,
         results_dir,
@@ -854,17 +1143,31 @@ async fn aws_bench(
     regions: Vec<Region>,
     shard_count: usize,
     configs: Vec<(Protocol, Config)>,
-    clients_per_region: Vec<usize>,
-    workloads: Vec<Workload>,
+    clients_per_region: Vec<impl Into<ClientsPerRegion>>,
+    workloads: Vec<WorkloadMix>,
     batch_max_sizes: Vec<usize>,
     cpus: usize,
-    skip: impl Fn(Protocol, Config, usize) -> bool,
+    region_failure: Option<RegionFailure>,
+    skip: impl Fn(Protocol, Config, ClientsPerRegion) -> bool,
     progress: TracingProgressBar,
     results_dir: impl AsRef<Path>,
 ) -> Result<(), Report> {
-    let mut launcher: tsunami::providers::aws::Launcher<_> = Default::default();
+    // fail before launching any machine if the spec is known to be invalid
+    fantoch_exp::lint::lint(&configs, &batch_max_sizes).wrap_err("lint")?;
+
+    let mut provider = AwsProvider::new(
+        LAUCH_MODE,
+        SERVER_INSTANCE_TYPE.to_string(),
+        CLIENT_INSTANCE_TYPE.to_string(),
+        SERVER_PLACEMENT,
+        MAX_SPOT_INSTANCE_REQUEST_WAIT_SECS,
+        BRANCH.to_string(),
+        RUN_MODE,
+        all_features(),
+        BinaryCache::new(BINARY_CACHE_DIR),
+    );
     let res = do_aws_bench(
-        &mut launcher,
+        &mut provider,
         regions,
         shard_count,
         configs,
@@ -872,6 +1175,7 @@ async fn aws_bench(
         workloads,
         batch_max_sizes,
         cpus,
+        region_failure,
         skip,
         progress,
         results_dir,
@@ -885,37 +1189,30 @@ async fn aws_bench(
     tracing::info!("will wait 5 minutes before terminating spot instances");
     tokio::time::sleep(tokio::time::Duration::from_secs(60 * 5)).await;
 
-    launcher.terminate_all().await?;
+    provider.terminate().await?;
     Ok(())
 }
 
 async fn do_aws_bench(
-    launcher: &mut tsunami::providers::aws::Launcher<
-        rusoto_credential::DefaultCredentialsProvider,
-    >,
+    provider: &mut AwsProvider,
     regions: Vec<Region>,
     shard_count: usize,
     configs: Vec<(Protocol, Config)>,
-    clients_per_region: Vec<usize>,
-    workloads: Vec<Workload>,
+    clients_per_region: Vec<impl Into<ClientsPerRegion>>,
+    workloads: Vec<WorkloadMix>,
     batch_max_sizes: Vec<usize>,
     cpus: usize,
-    skip: impl Fn(Protocol, Config, usize) -> bool,
+    region_failure: Option<RegionFailure>,
+    skip: impl Fn(Protocol, Config, ClientsPerRegion) -> bool,
     progress: TracingProgressBar,
     results_dir: impl AsRef<Path>,
 ) -> Result<(), Report> {
     // setup aws machines
-    let machines = fantoch_exp::testbed::aws::setup(
-        launcher,
-        LAUCH_MODE,
+    let machines = fantoch_exp::testbed::setup(
+        provider,
         regions,
         shard_count,
-        SERVER_INSTANCE_TYPE.to_string(),
-        CLIENT_INSTANCE_TYPE.to_string(),
-        MAX_SPOT_INSTANCE_REQUEST_WAIT_SECS,
-        BRANCH.to_string(),
-        RUN_MODE,
-        all_features(),
+        CLIENT_MACHINES_PER_REGION,
     )
     .await
     .wrap_err("aws spawn")?;
@@ -933,6 +1230,7 @@ async fn do_aws_bench(
         workloads,
         batch_max_sizes,
         cpus,
+        region_failure,
         skip,
         progress,
         results_dir,
@@ -948,31 +1246,52 @@ async fn run_bench(
     testbed: Testbed,
     planet: Option<Planet>,
     configs: Vec<(Protocol, Config)>,
-    clients_per_region: Vec<usize>,
-    workloads: Vec<Workload>,
+    clients_per_region: Vec<impl Into<ClientsPerRegion>>,
+    workloads: Vec<WorkloadMix>,
     batch_max_sizes: Vec<usize>,
     cpus: usize,
-    skip: impl Fn(Protocol, Config, usize) -> bool,
+    region_failure: Option<RegionFailure>,
+    skip: impl Fn(Protocol, Config, ClientsPerRegion) -> bool,
     progress: TracingProgressBar,
     results_dir: impl AsRef<Path>,
 ) -> Result<(), Report> {
+    // maybe start the dashboard
+    let dashboard = if let Some(port) = DASHBOARD_PORT {
+        Some(Dashboard::serve(port).await.wrap_err("dashboard")?)
+    } else {
+        None
+    };
+    let clients_per_region =
+        clients_per_region.into_iter().map(Into::into).collect();
     fantoch_exp::bench::bench_experiment(
         machines,
         RUN_MODE,
         &MAX_LEVEL_RUN_TIME,
         all_features(),
+        BRANCH.to_string(),
         testbed,
         planet,
         configs,
+        CO_DEPLOYED,
         clients_per_region,
+        COLOCATION,
         workloads,
         batch_max_sizes,
         BATCH_MAX_DELAY,
         cpus,
+        PROCESS_TCP_CONFIG,
+        CLIENT_TCP_CONFIG,
+        PING_INTERVAL,
+        region_failure,
+        RUN_DURATION,
         skip,
+        RUN_ORDER_SEED,
+        EXPERIMENT_SEED,
         EXPERIMENT_TIMEOUTS,
-        PROTOCOLS_TO_CLEANUP.to_vec(),
+        SANITY_CHECKS,
+        LATENCY_CHECKS,
         progress,
+        dashboard,
         results_dir,
     )
     .await
@@ -189,7 +189,7 @@ async fn ping(
     let hosts_file = "hosts";
     let output_file = format!("{}.dat", vm.nickname);
 
-    let vm = Machine::Tsunami(vm);
+    let vm = Machine::new(vm);
 
     // first copy both SCRIPT and HOSTS files to the machine
     vm.copy_to(SCRIPT, script_file)
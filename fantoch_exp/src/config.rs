@@ -1,9 +1,11 @@
 #[cfg(feature = "exp")]
 use crate::args;
 use crate::{FantochFeature, Protocol, RunMode, Testbed};
-use fantoch::client::Workload;
+use fantoch::client::{Workload, WorkloadMix};
 use fantoch::config::Config;
 use fantoch::id::{ProcessId, ShardId};
+#[cfg(feature = "exp")]
+use fantoch::load_balance::WorkerAssignment;
 use fantoch::planet::{Planet, Region};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -11,9 +13,16 @@ use std::fmt;
 use std::time::Duration;
 
 pub type RegionIndex = usize;
+pub type DeploymentIndex = usize;
+pub type ClientMachineIndex = usize;
 pub type Placement = HashMap<(Region, ShardId), (ProcessId, RegionIndex)>;
 pub type PlacementFlat = Vec<(Region, ShardId, ProcessId, RegionIndex)>;
 
+// schema version of `ExperimentConfig`: it should be bumped whenever
+// `ExperimentConfig` changes, and a migration shim from the previous version
+// should be added to `fantoch_plot`'s `ResultsDB`
+pub const EXPERIMENT_CONFIG_SCHEMA_VERSION: u32 = 10;
+
 // FIXED
 #[cfg(feature = "exp")]
 const IP: &str = "0.0.0.0";
@@ -24,7 +33,6 @@ const EXECUTORS: usize = 16;
 const MULTIPLEXING: usize = 16;
 
 // process tcp config
-const PROCESS_TCP_NODELAY: bool = true;
 // by default, each socket stream is buffered (with a buffer of size 8KBs),
 // which should greatly reduce the number of syscalls for small-sized messages
 const PROCESS_TCP_BUFFER_SIZE: usize = 16 * 1024 * 1024; // 16MB
@@ -57,20 +65,65 @@ const PING_INTERVAL: Option<Duration> = Some(Duration::from_millis(500));
 // const STATUS_FREQUENCY: Option<usize> = None;
 const STATUS_FREQUENCY: Option<usize> = Some(10);
 
+// clients log their stats every 10 seconds, so that the harness can follow
+// runs (see `bench::ExperimentTimeouts::zero_throughput`)
+#[cfg(feature = "exp")]
+const STATS_INTERVAL: Option<Duration> = Some(Duration::from_secs(10));
+
 // if paxos, set process 1 as the leader
 const LEADER: ProcessId = 1;
 
-// client tcp config
-const CLIENT_TCP_NODELAY: bool = true;
+/// Options of the TCP connections of processes or clients (which are passed to
+/// `fantoch::run::rw::TcpOptions` by the binaries).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpConfig {
+    pub nodelay: bool,
+    // size of the socket send buffer (SO_SNDBUF), if not the OS default
+    pub send_buffer_size: Option<usize>,
+    // size of the socket receive buffer (SO_RCVBUF), if not the OS default
+    pub recv_buffer_size: Option<usize>,
+    // idle time after which keepalive probes are sent, if enabled
+    pub keepalive: Option<Duration>,
+    // maximum segment size (TCP_MAXSEG), if not derived from the MTU
+    pub max_segment_size: Option<u32>,
+    // shared secret presented on each connection to processes, if connections
+    // are authenticated (set by `bench::bench_experiment`)
+    pub auth_token: Option<u64>,
+}
+
+#[cfg(feature = "exp")]
+impl TcpConfig {
+    fn to_args(&self) -> Vec<String> {
+        let mut args = args!["--tcp_nodelay", self.nodelay];
+        if let Some(buffer_size) = self.send_buffer_size {
+            args.extend(args!["--tcp_send_buffer_size", buffer_size]);
+        }
+        if let Some(buffer_size) = self.recv_buffer_size {
+            args.extend(args!["--tcp_recv_buffer_size", buffer_size]);
+        }
+        if let Some(keepalive) = self.keepalive {
+            args.extend(args!["--tcp_keepalive", keepalive.as_millis()]);
+        }
+        if let Some(mss) = self.max_segment_size {
+            args.extend(args!["--tcp_max_segment_size", mss]);
+        }
+        if let Some(auth_token) = self.auth_token {
+            args.extend(args!["--auth_token", auth_token]);
+        }
+        args
+    }
+}
 
 #[cfg(feature = "exp")]
 pub struct ProtocolConfig {
+    protocol: Protocol,
+    deployment: DeploymentIndex,
     process_id: ProcessId,
     shard_id: ShardId,
     sorted: Option<Vec<(ProcessId, ShardId)>>,
     ips: Vec<(ProcessId, String, Option<usize>)>,
     config: Config,
-    tcp_nodelay: bool,
+    tcp_config: TcpConfig,
     tcp_buffer_size: usize,
     tcp_flush_interval: Option<Duration>,
     process_channel_buffer_size: usize,
@@ -90,11 +143,13 @@ pub struct ProtocolConfig {
 impl ProtocolConfig {
     pub fn new(
         protocol: Protocol,
+        deployment: DeploymentIndex,
         process_id: ProcessId,
         shard_id: ShardId,
         mut config: Config,
         sorted: Option<Vec<(ProcessId, ShardId)>>,
         ips: Vec<(ProcessId, String, Option<usize>)>,
+        tcp_config: TcpConfig,
         metrics_file: String,
         cpus: usize,
         log_file: String,
@@ -103,12 +158,14 @@ impl ProtocolConfig {
             workers_executors_and_leader(protocol, &mut config);
 
         Self {
+            protocol,
+            deployment,
             process_id,
             shard_id,
             sorted,
             ips,
             config,
-            tcp_nodelay: PROCESS_TCP_NODELAY,
+            tcp_config,
             tcp_buffer_size: PROCESS_TCP_BUFFER_SIZE,
             tcp_flush_interval: PROCESS_TCP_FLUSH_INTERVAL,
             process_channel_buffer_size: PROCESS_CHANNEL_BUFFER_SIZE,
@@ -127,6 +184,8 @@ impl ProtocolConfig {
 
     pub fn to_args(&self) -> Vec<String> {
         let mut args = args![
+            "--protocol",
+            self.protocol.name(),
             "--id",
             self.process_id,
             "--shard_id",
@@ -134,9 +193,9 @@ impl ProtocolConfig {
             "--ip",
             IP,
             "--port",
-            port(self.process_id),
+            port(self.deployment, self.process_id),
             "--client_port",
-            client_port(self.process_id),
+            client_port(self.deployment, self.process_id),
             "--addresses",
             self.ips_to_addresses(),
             "--processes",
@@ -161,8 +220,50 @@ impl ProtocolConfig {
         }
         args.extend(args![
             "--executor_cleanup_interval",
-            self.config.executor_cleanup_interval().as_millis()
+            self.config.executor_cleanup_interval().as_millis(),
+            "--executor_adaptive_cleanup",
+            self.config.executor_adaptive_cleanup(),
+            "--executor_ordered_delivery",
+            self.config.executor_ordered_delivery()
         ]);
+        if let Some(budget) = self.config.executor_latency_budget() {
+            args.extend(args!["--executor_latency_budget", budget.as_millis()]);
+        }
+        if let Some(budget) = self.config.executor_scc_budget() {
+            args.extend(args!["--executor_scc_budget", budget]);
+        }
+        if let Some(tenant_count) = self.config.executor_tenant_count() {
+            args.extend(args!["--executor_tenant_count", tenant_count]);
+        }
+        if self.config.executor_write_batching() {
+            args.extend(args!["--executor_write_batching", true]);
+        }
+        if let Some(state_machine) = self.config.executor_state_machine() {
+            args.extend(args!["--executor_state_machine", state_machine]);
+        }
+        if let Some((size, period)) = self.config.executor_backlog_alarm() {
+            args.extend(args!["--executor_backlog_alarm_size", size]);
+            args.extend(args![
+                "--executor_backlog_alarm_period",
+                period.as_millis()
+            ]);
+        }
+        if self.config.executor_backlog_alarm_abort() {
+            args.extend(args!["--executor_backlog_alarm_abort", true]);
+        }
+        if let Some((rate, burst)) = self.config.executor_throttle() {
+            args.extend(args!["--executor_throttle_rate", rate]);
+            args.extend(args!["--executor_throttle_burst", burst]);
+        }
+        if self.config.stale_reads() {
+            args.extend(args!["--stale_reads", true]);
+        }
+        if let Some(sample_rate) = self.config.trace_sample_rate() {
+            args.extend(args!["--trace_sample_rate", sample_rate]);
+        }
+        if let Some(preload_keys) = self.config.preload_keys() {
+            args.extend(args!["--preload_keys", preload_keys]);
+        }
         if let Some(interval) = self.config.executor_monitor_pending_interval()
         {
             args.extend(args![
@@ -173,6 +274,12 @@ impl ProtocolConfig {
         if let Some(interval) = self.config.gc_interval() {
             args.extend(args!["--gc_interval", interval.as_millis()]);
         }
+        if let Some(interval) = self.config.anti_entropy_interval() {
+            args.extend(args!["--anti_entropy_interval", interval.as_millis()]);
+        }
+        if let Some(horizon) = self.config.dependency_horizon() {
+            args.extend(args!["--dependency_horizon", horizon]);
+        }
         if let Some(leader) = self.config.leader() {
             args.extend(args!["--leader", leader]);
         }
@@ -180,6 +287,10 @@ impl ProtocolConfig {
             "--tempo_tiny_quorums",
             self.config.tempo_tiny_quorums()
         ]);
+        args.extend(args![
+            "--tempo_per_command_tiny_quorums",
+            self.config.tempo_per_command_tiny_quorums()
+        ]);
         if let Some(interval) = self.config.tempo_clock_bump_interval() {
             args.extend(args![
                 "--tempo_clock_bump_interval",
@@ -192,14 +303,77 @@ impl ProtocolConfig {
                 interval.as_millis()
             ]);
         }
-        args.extend(args!["--skip_fast_ack", self.config.skip_fast_ack()]);
-
         args.extend(args![
-            "--tcp_nodelay",
-            self.tcp_nodelay,
-            "--tcp_buffer_size",
-            self.tcp_buffer_size
+            "--tempo_commit_clock_bump",
+            self.config.tempo_commit_clock_bump()
         ]);
+        if let Some(threshold) = self.config.tempo_stability_threshold() {
+            args.extend(args!["--tempo_stability_threshold", threshold]);
+        }
+        args.extend(args![
+            "--tempo_multi_shard_reads",
+            self.config.tempo_multi_shard_reads()
+        ]);
+        args.extend(args!["--skip_fast_ack", self.config.skip_fast_ack()]);
+        if let Some(interval) = self.config.ack_coalescing_interval() {
+            args.extend(args![
+                "--ack_coalescing_interval",
+                interval.as_millis()
+            ]);
+        }
+        if let Some(buffer_size) = self.config.client_to_workers_buffer_size() {
+            args.extend(args!["--client_to_workers_buffer_size", buffer_size]);
+        }
+        if let Some(buffer_size) = self.config.to_executors_buffer_size() {
+            args.extend(args!["--to_executors_buffer_size", buffer_size]);
+        }
+        if let Some(buffer_size) = self.config.periodic_to_workers_buffer_size()
+        {
+            args.extend(args![
+                "--periodic_to_workers_buffer_size",
+                buffer_size
+            ]);
+        }
+        match self.config.worker_assignment() {
+            WorkerAssignment::Contiguous => {}
+            WorkerAssignment::Hashed => {
+                args.extend(args!["--worker_assignment", "hashed"]);
+            }
+        }
+        if let Some(acceptors) = self.config.client_acceptors() {
+            args.extend(args!["--client_acceptors", acceptors]);
+        }
+        if let Some(delay) = self.config.client_results_flush_delay() {
+            args.extend(args![
+                "--client_results_flush_delay",
+                delay.as_micros()
+            ]);
+        }
+        if let Some(shards) = self.config.client_pending_shards() {
+            args.extend(args!["--client_pending_shards", shards]);
+        }
+        if let Some((rate, delay)) = self.config.server_delay_injection() {
+            args.extend(args!["--server_delay_injection_rate", rate]);
+            args.extend(args![
+                "--server_delay_injection_delay",
+                delay.as_millis()
+            ]);
+        }
+        if let Some(threshold) = self.config.message_compression() {
+            args.extend(args!["--message_compression", threshold]);
+        }
+        if let Some(window) = self.config.delivery_window() {
+            args.extend(args!["--delivery_window", window]);
+        }
+        if self.config.keep_alive_on_violation() {
+            args.extend(args!["--keep_alive_on_violation", true]);
+        }
+        if self.config.farthest_first() {
+            args.extend(args!["--farthest_first", true]);
+        }
+
+        args.extend(self.tcp_config.to_args());
+        args.extend(args!["--tcp_buffer_size", self.tcp_buffer_size]);
         if let Some(interval) = self.tcp_flush_interval {
             args.extend(args!["--tcp_flush_interval", interval.as_millis()]);
         }
@@ -233,7 +407,8 @@ impl ProtocolConfig {
         self.ips
             .iter()
             .map(|(peer_id, ip, delay)| {
-                let address = format!("{}:{}", ip, port(*peer_id));
+                let address =
+                    format!("{}:{}", ip, port(self.deployment, *peer_id));
                 if let Some(delay) = delay {
                     format!("{}-{}", address, delay)
                 } else {
@@ -263,24 +438,32 @@ fn workers_executors_and_leader(
             config.set_leader(LEADER);
             f(1)
         }
-        Protocol::TempoAtomic => f(EXECUTORS),
-        Protocol::TempoLocked => f(EXECUTORS),
-        Protocol::Basic => f(EXECUTORS),
+        // sequential protocols only support a single worker and executor
+        Protocol::AtlasSequential
+        | Protocol::EPaxosSequential
+        | Protocol::TempoSequential => (1, 1),
+        // by default, use all executors
+        _ => f(EXECUTORS),
     }
 }
 
 #[cfg(feature = "exp")]
 pub struct ClientConfig {
+    deployment: DeploymentIndex,
     id_start: usize,
     id_end: usize,
-    ips: Vec<(ProcessId, String)>,
-    workload: Workload,
+    ips: Vec<(ProcessId, String, Option<usize>)>,
+    workload_mix: WorkloadMix,
     batch_max_size: usize,
     batch_max_delay: Duration,
-    tcp_nodelay: bool,
+    tcp_config: TcpConfig,
     channel_buffer_size: usize,
     status_frequency: Option<usize>,
+    stats_interval: Option<Duration>,
+    max_run_time: Option<Duration>,
+    command_timeout: Option<Duration>,
     metrics_file: String,
+    traces_file: Option<String>,
     stack_size: Option<usize>,
     cpus: Option<usize>,
     log_file: String,
@@ -289,26 +472,36 @@ pub struct ClientConfig {
 #[cfg(feature = "exp")]
 impl ClientConfig {
     pub fn new(
+        deployment: DeploymentIndex,
         id_start: usize,
         id_end: usize,
-        ips: Vec<(ProcessId, String)>,
-        workload: Workload,
+        ips: Vec<(ProcessId, String, Option<usize>)>,
+        workload_mix: WorkloadMix,
         batch_max_size: usize,
         batch_max_delay: Duration,
+        tcp_config: TcpConfig,
+        max_run_time: Option<Duration>,
+        command_timeout: Option<Duration>,
         metrics_file: String,
+        traces_file: Option<String>,
         log_file: String,
     ) -> Self {
         Self {
+            deployment,
             id_start,
             id_end,
             ips,
-            workload,
+            workload_mix,
             batch_max_size,
             batch_max_delay,
-            tcp_nodelay: CLIENT_TCP_NODELAY,
+            tcp_config,
             channel_buffer_size: CLIENT_CHANNEL_BUFFER_SIZE,
             status_frequency: STATUS_FREQUENCY,
+            stats_interval: STATS_INTERVAL,
+            max_run_time,
+            command_timeout,
             metrics_file,
+            traces_file,
             stack_size: CLIENT_STACK_SIZE,
             cpus: None,
             log_file,
@@ -316,50 +509,75 @@ impl ClientConfig {
     }
 
     pub fn to_args(&self) -> Vec<String> {
-        use fantoch::client::KeyGen;
-        let key_gen = match self.workload.key_gen() {
-            KeyGen::ConflictPool {
-                conflict_rate,
-                pool_size,
-            } => {
-                format!("conflict_pool,{},{}", conflict_rate, pool_size)
-            }
-            KeyGen::Zipf {
-                coefficient,
-                total_keys_per_shard,
-            } => format!("zipf,{},{}", coefficient, total_keys_per_shard),
-        };
+        let workload = self.workload_mix.first();
         let mut args = args![
             "--ids",
             format!("{}-{}", self.id_start, self.id_end),
             "--addresses",
             self.ips_to_addresses(),
             "--shard_count",
-            self.workload.shard_count(),
+            workload.shard_count(),
             "--key_gen",
-            key_gen,
+            Self::key_gen_to_arg(&workload),
             "--keys_per_command",
-            self.workload.keys_per_command(),
+            workload.keys_per_command(),
             "--commands_per_client",
-            self.workload.commands_per_client(),
+            workload.commands_per_client(),
             "--payload_size",
-            self.workload.payload_size(),
+            workload.payload_size(),
             "--read_only_percentage",
-            self.workload.read_only_percentage(),
+            workload.read_only_percentage(),
+            "--shard_locality",
+            workload.shard_locality(),
             "--batch_max_size",
             self.batch_max_size,
             "--batch_max_delay",
             self.batch_max_delay.as_millis(),
-            "--tcp_nodelay",
-            self.tcp_nodelay,
             "--channel_buffer_size",
             self.channel_buffer_size,
             "--metrics_file",
             self.metrics_file,
         ];
+        args.extend(self.tcp_config.to_args());
+        if workload.ack_only_writes() {
+            args.extend(args!["--ack_only_writes", true]);
+        }
+        if let Some(seed) = workload.seed() {
+            args.extend(args!["--seed", seed]);
+        }
+        if let Some(ttl) = workload.ttl() {
+            args.extend(args!["--ttl", ttl]);
+        }
+        if let Some(state_machine) = workload.state_machine() {
+            args.extend(args!["--state_machine", state_machine]);
+        }
+        if let Some(percentage) = workload.value_sample_percentage() {
+            args.extend(args!["--value_sample_percentage", percentage]);
+        }
+        if let Some(max_in_flight) = workload.max_in_flight() {
+            args.extend(args!["--max_in_flight", max_in_flight]);
+        }
+        if !self.workload_mix.is_single() {
+            args.extend(args!["--workload_mix", self.workload_mix_to_arg()]);
+        }
         if let Some(status_frequency) = self.status_frequency {
             args.extend(args!["--status_frequency", status_frequency]);
         }
+        if let Some(stats_interval) = self.stats_interval {
+            args.extend(args!["--stats_interval", stats_interval.as_secs()]);
+        }
+        if let Some(max_run_time) = self.max_run_time {
+            args.extend(args!["--max_run_time", max_run_time.as_secs()]);
+        }
+        if let Some(command_timeout) = self.command_timeout {
+            args.extend(args![
+                "--command_timeout",
+                command_timeout.as_millis()
+            ]);
+        }
+        if let Some(traces_file) = &self.traces_file {
+            args.extend(args!["--traces_file", traces_file]);
+        }
         if let Some(stack_size) = self.stack_size {
             args.extend(args!["--stack_size", stack_size]);
         }
@@ -370,19 +588,286 @@ impl ClientConfig {
         args
     }
 
+    fn key_gen_to_arg(workload: &Workload) -> String {
+        use fantoch::client::KeyGen;
+        match workload.key_gen() {
+            KeyGen::ConflictPool {
+                conflict_rate,
+                pool_size,
+                pool_id,
+            } => {
+                format!(
+                    "conflict_pool,{},{},{}",
+                    conflict_rate, pool_size, pool_id
+                )
+            }
+            KeyGen::Zipf {
+                coefficient,
+                total_keys_per_shard,
+            } => format!("zipf,{},{}", coefficient, total_keys_per_shard),
+            KeyGen::Custom(custom) => panic!(
+                "custom key generator {} can't be used in experiments",
+                custom.name()
+            ),
+        }
+    }
+
+    fn workload_mix_to_arg(&self) -> String {
+        self.workload_mix
+            .workloads()
+            .map(|(workload, percentage)| {
+                format!(
+                    "{}/{}/{}/{}/{}",
+                    percentage,
+                    Self::key_gen_to_arg(&workload),
+                    workload.keys_per_command(),
+                    workload.read_only_percentage(),
+                    workload.payload_size()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
     fn ips_to_addresses(&self) -> String {
         self.ips
             .iter()
-            .map(|(process_id, ip)| {
-                format!("{}:{}", ip, client_port(*process_id))
+            .map(|(process_id, ip, delay)| {
+                let port = client_port(self.deployment, *process_id);
+                let address = format!("{}:{}", ip, port);
+                if let Some(delay) = delay {
+                    format!("{}-{}", address, delay)
+                } else {
+                    address
+                }
             })
             .collect::<Vec<_>>()
             .join(",")
     }
 }
 
+// Failure of all the processes (and clients) of a region, injected while the
+// experiment runs. Since clients in other regions may never complete their
+// workload (e.g. if the protocol can't make progress without the failed
+// processes), clients are stopped `observe` after the failure.
+// TODO warm standby processes (i.e. learners in each region that are promoted
+// to replace the failed ones) would allow comparing the time to fail over with
+// the one of a cold restart; however, processes can't be added to (or promoted
+// within) a running configuration, as there's no reconfiguration nor state
+// transfer yet (see `rejoin_task` in `fantoch::run`)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RegionFailure {
+    pub region: Region,
+    // time (since clients started) after which the region fails
+    pub after: Duration,
+    // time during which clients keep running after the failure
+    pub observe: Duration,
+    // time after which commands pending at clients are considered to have
+    // timed out, so that the availability perceived by clients can be
+    // measured
+    pub command_timeout: Option<Duration>,
+}
+
+impl RegionFailure {
+    pub fn new(
+        region: Region,
+        after: Duration,
+        observe: Duration,
+        command_timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            region,
+            after,
+            observe,
+            command_timeout,
+        }
+    }
+
+    // time after which clients are stopped
+    pub fn max_run_time(&self) -> Duration {
+        self.after + self.observe
+    }
+}
+
+// Clients running in the server machines (alongside each server), in addition
+// to the ones in the client machines, which reflects deployments where
+// application servers and replicas share hosts. Colocated clients submit their
+// commands to the processes selected for the region of their server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Colocation {
+    // number of clients in each server machine
+    pub clients: usize,
+    // CPU weight (between 1 and 10000, where 100 is the default of every
+    // process) given to the colocated clients; if not set, they compete for
+    // CPU with the server as any other process
+    pub cpu_shares: Option<u64>,
+}
+
+impl Colocation {
+    pub fn new(clients: usize, cpu_shares: Option<u64>) -> Self {
+        assert!(clients > 0, "there should be at least one colocated client");
+        Self {
+            clients,
+            cpu_shares,
+        }
+    }
+}
+
+// maximum number of regions with a different number of clients; the capacity
+// is fixed so that `ClientsPerRegion` can be `Copy` (like `WorkloadMix`)
+pub const CLIENTS_PER_REGION_MAX_REGIONS: usize = 16;
+
+// Number of clients in each region: either the same number in every region,
+// or a different number in each region (indexed by `RegionIndex`), which
+// allows asymmetric demand. The former is serialized as a single number (as
+// before), and so older results remain valid.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "ClientCounts", into = "ClientCounts")]
+pub struct ClientsPerRegion {
+    // number of clients in the region with index `i + 1`; if there's a single
+    // count, it's the number of clients in every region
+    counts: [usize; CLIENTS_PER_REGION_MAX_REGIONS],
+    len: usize,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum ClientCounts {
+    Uniform(usize),
+    PerRegion(Vec<usize>),
+}
+
+impl ClientsPerRegion {
+    /// Creates a `ClientsPerRegion` with the same number of clients in every
+    /// region.
+    pub fn uniform(clients: usize) -> Self {
+        Self::new(vec![clients], false)
+    }
+
+    /// Creates a `ClientsPerRegion` given the number of clients in each
+    /// region, ordered by `RegionIndex` (i.e. in the order regions were
+    /// provided to the testbed).
+    pub fn per_region(counts: Vec<usize>) -> Self {
+        Self::new(counts, true)
+    }
+
+    /// Creates a `ClientsPerRegion` given the number of clients in each of
+    /// the experiment `regions`.
+    pub fn from_map(
+        regions: &[Region],
+        counts: &HashMap<Region, usize>,
+    ) -> Self {
+        let counts = regions
+            .iter()
+            .map(|region| {
+                *counts.get(region).unwrap_or_else(|| {
+                    panic!("missing number of clients in {:?}", region)
+                })
+            })
+            .collect();
+        Self::per_region(counts)
+    }
+
+    fn new(clients: Vec<usize>, per_region: bool) -> Self {
+        assert!(
+            !clients.is_empty(),
+            "there should be clients in at least one region"
+        );
+        assert!(
+            clients.len() <= CLIENTS_PER_REGION_MAX_REGIONS,
+            "the number of clients can only be set for up to {} regions",
+            CLIENTS_PER_REGION_MAX_REGIONS
+        );
+        let mut counts = [0; CLIENTS_PER_REGION_MAX_REGIONS];
+        counts[..clients.len()].copy_from_slice(&clients);
+        // a single count is only per region if explicitly requested
+        let len = if per_region { clients.len() } else { 0 };
+        Self { counts, len }
+    }
+
+    /// Returns the number of clients in every region, if it's the same in
+    /// all of them.
+    pub fn uniform_count(&self) -> Option<usize> {
+        if self.len == 0 {
+            Some(self.counts[0])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of clients in the region with index
+    /// `region_index`.
+    pub fn count(&self, region_index: RegionIndex) -> usize {
+        match self.uniform_count() {
+            Some(clients) => clients,
+            None => {
+                assert!(
+                    region_index >= 1 && region_index <= self.len,
+                    "missing number of clients for region index {}",
+                    region_index
+                );
+                self.counts[region_index - 1]
+            }
+        }
+    }
+
+    /// Returns the highest number of clients in a region.
+    pub fn max(&self) -> usize {
+        self.counts.iter().copied().max().unwrap_or_default()
+    }
+
+    /// Returns the total number of clients in `regions` regions.
+    pub fn total(&self, regions: usize) -> usize {
+        (1..=regions)
+            .map(|region_index| self.count(region_index))
+            .sum()
+    }
+}
+
+impl From<usize> for ClientsPerRegion {
+    fn from(clients: usize) -> Self {
+        Self::uniform(clients)
+    }
+}
+
+impl From<ClientCounts> for ClientsPerRegion {
+    fn from(counts: ClientCounts) -> Self {
+        match counts {
+            ClientCounts::Uniform(clients) => Self::uniform(clients),
+            ClientCounts::PerRegion(counts) => Self::per_region(counts),
+        }
+    }
+}
+
+impl From<ClientsPerRegion> for ClientCounts {
+    fn from(clients: ClientsPerRegion) -> Self {
+        match clients.uniform_count() {
+            Some(clients) => Self::Uniform(clients),
+            None => Self::PerRegion(clients.counts[..clients.len].to_vec()),
+        }
+    }
+}
+
+impl fmt::Display for ClientsPerRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.uniform_count() {
+            Some(clients) => write!(f, "{}", clients),
+            None => write!(f, "{:?}", &self.counts[..self.len]),
+        }
+    }
+}
+
+impl fmt::Debug for ClientsPerRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct ExperimentConfig {
+    // older results have no schema version (which is the same as version 0)
+    #[serde(default)]
+    pub schema_version: u32,
     pub placement: PlacementFlat,
     pub planet: Option<Planet>,
     pub run_mode: RunMode,
@@ -390,19 +875,63 @@ pub struct ExperimentConfig {
     pub testbed: Testbed,
     pub protocol: Protocol,
     pub config: Config,
-    pub clients_per_region: usize,
+    pub clients_per_region: ClientsPerRegion,
+    // clients in each region are split among these many client machines
+    pub client_machines_per_region: usize,
+    // clients running in the server machines (if any); older results have no
+    // colocated clients
+    #[serde(default)]
+    pub colocation: Option<Colocation>,
+    // first workload in the mix executed by clients
     pub workload: Workload,
+    // mix of workloads executed by clients (if there was more than one);
+    // older results have no workload mix
+    #[serde(default)]
+    pub workload_mix: Option<WorkloadMix>,
     pub batch_max_size: usize,
     pub batch_max_delay: Duration,
     pub process_tcp_nodelay: bool,
+    pub process_tcp_send_buffer_size: Option<usize>,
+    pub process_tcp_recv_buffer_size: Option<usize>,
+    pub process_tcp_keepalive: Option<Duration>,
+    pub process_tcp_max_segment_size: Option<u32>,
     pub tcp_buffer_size: usize,
     pub tcp_flush_interval: Option<Duration>,
     pub process_channel_buffer_size: usize,
     pub cpus: usize,
+    // older results have no ping mesh
+    #[serde(default)]
+    pub ping_interval: Option<Duration>,
+    // protocol deployed in the same machines during the experiment (if any);
+    // older results have no co-deployment
+    #[serde(default)]
+    pub co_deployed_with: Option<Protocol>,
+    // sanity checks that failed in every run of the experiment (if any); older
+    // results were not checked
+    #[serde(default)]
+    pub sanity_failures: Vec<String>,
+    // shards where the number of commands executed by servers didn't match
+    // the number of commands issued by clients (if any); older results were
+    // not checked
+    #[serde(default)]
+    pub integrity_discrepancies: Vec<String>,
+    // region that failed during the experiment (if any), and the unix time (in
+    // millis) at which the failure was injected
+    pub region_failure: Option<RegionFailure>,
+    pub region_failure_time: Option<u64>,
+    // if set, clients ran for this long (or until they issued all their
+    // commands, if earlier); otherwise, until they issued all their commands
+    pub run_duration: Option<Duration>,
+    // seed of the clients' workloads (if any)
+    pub experiment_seed: Option<u64>,
     pub workers: usize,
     pub executors: usize,
     pub multiplexing: usize,
     pub client_tcp_nodelay: bool,
+    pub client_tcp_send_buffer_size: Option<usize>,
+    pub client_tcp_recv_buffer_size: Option<usize>,
+    pub client_tcp_keepalive: Option<Duration>,
+    pub client_tcp_max_segment_size: Option<u32>,
     pub client_channel_buffer_size: usize,
 }
 
@@ -415,11 +944,19 @@ impl ExperimentConfig {
         testbed: Testbed,
         protocol: Protocol,
         mut config: Config,
-        clients_per_region: usize,
-        workload: Workload,
+        clients_per_region: ClientsPerRegion,
+        client_machines_per_region: usize,
+        colocation: Option<Colocation>,
+        workload_mix: WorkloadMix,
         batch_max_size: usize,
         batch_max_delay: Duration,
         cpus: usize,
+        ping_interval: Option<Duration>,
+        co_deployed_with: Option<Protocol>,
+        region_failure: Option<RegionFailure>,
+        run_duration: Option<Duration>,
+        process_tcp_config: TcpConfig,
+        client_tcp_config: TcpConfig,
     ) -> Self {
         let (workers, executors) =
             workers_executors_and_leader(protocol, &mut config);
@@ -431,6 +968,7 @@ impl ExperimentConfig {
             .map(|((a, b), (c, d))| (a, b, c, d))
             .collect();
         Self {
+            schema_version: EXPERIMENT_CONFIG_SCHEMA_VERSION,
             placement,
             planet,
             run_mode,
@@ -439,18 +977,41 @@ impl ExperimentConfig {
             protocol,
             config,
             clients_per_region,
-            process_tcp_nodelay: PROCESS_TCP_NODELAY,
+            client_machines_per_region,
+            colocation,
+            process_tcp_nodelay: process_tcp_config.nodelay,
+            process_tcp_send_buffer_size: process_tcp_config.send_buffer_size,
+            process_tcp_recv_buffer_size: process_tcp_config.recv_buffer_size,
+            process_tcp_keepalive: process_tcp_config.keepalive,
+            process_tcp_max_segment_size: process_tcp_config.max_segment_size,
             tcp_buffer_size: PROCESS_TCP_BUFFER_SIZE,
             tcp_flush_interval: PROCESS_TCP_FLUSH_INTERVAL,
             process_channel_buffer_size: PROCESS_CHANNEL_BUFFER_SIZE,
             cpus,
+            ping_interval,
+            co_deployed_with,
+            sanity_failures: Vec::new(),
+            integrity_discrepancies: Vec::new(),
+            region_failure,
+            region_failure_time: None,
+            run_duration,
+            experiment_seed: workload_mix.first().seed(),
             workers,
             executors,
             multiplexing: MULTIPLEXING,
-            workload,
+            workload: workload_mix.first(),
+            workload_mix: if workload_mix.is_single() {
+                None
+            } else {
+                Some(workload_mix)
+            },
             batch_max_size,
             batch_max_delay,
-            client_tcp_nodelay: CLIENT_TCP_NODELAY,
+            client_tcp_nodelay: client_tcp_config.nodelay,
+            client_tcp_send_buffer_size: client_tcp_config.send_buffer_size,
+            client_tcp_recv_buffer_size: client_tcp_config.recv_buffer_size,
+            client_tcp_keepalive: client_tcp_config.keepalive,
+            client_tcp_max_segment_size: client_tcp_config.max_segment_size,
             client_channel_buffer_size: CLIENT_CHANNEL_BUFFER_SIZE,
         }
     }
@@ -461,21 +1022,38 @@ impl fmt::Debug for ExperimentConfig {
         writeln!(f, "config = {:?}", self.config)?;
         writeln!(f, "protocol = {:?}", self.protocol)?;
         writeln!(f, "clients_per_region = {:?}", self.clients_per_region)?;
-        writeln!(f, "workload = {:?}", self.workload)
+        writeln!(
+            f,
+            "client_machines_per_region = {:?}",
+            self.client_machines_per_region
+        )?;
+        writeln!(f, "colocation = {:?}", self.colocation)?;
+        writeln!(f, "workload = {:?}", self.workload)?;
+        writeln!(f, "workload_mix = {:?}", self.workload_mix)
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ProcessType {
     Server(ProcessId),
-    Client(usize),
+    Client(RegionIndex, ClientMachineIndex),
+    // clients running in the machine of some server
+    ColocatedClient(ProcessId),
 }
 
 impl ProcessType {
     pub fn name(&self) -> String {
         match self {
             Self::Server(process_id) => format!("server_{}", process_id),
-            Self::Client(region_index) => format!("client_{}", region_index),
+            // the first client machine in each region keeps the name used
+            // when there was a single client machine per region
+            Self::Client(region_index, 0) => format!("client_{}", region_index),
+            Self::Client(region_index, client_index) => {
+                format!("client_{}_m{}", region_index, client_index)
+            }
+            Self::ColocatedClient(process_id) => {
+                format!("client_s{}", process_id)
+            }
         }
     }
 }
@@ -486,6 +1064,20 @@ pub fn run_file(process_type: ProcessType, file_ext: &str) -> String {
     format!("{}.{}", process_type.name(), file_ext)
 }
 
+// create filename for a run file of some deployment; files of the first
+// deployment keep the same name as in `run_file`
+pub fn deployment_run_file(
+    deployment: DeploymentIndex,
+    process_type: ProcessType,
+    file_ext: &str,
+) -> String {
+    if deployment == 0 {
+        run_file(process_type, file_ext)
+    } else {
+        format!("{}_{}.{}", process_type.name(), deployment, file_ext)
+    }
+}
+
 // create filename prefix
 pub fn file_prefix(process_type: ProcessType, region: &Region) -> String {
     format!("{:?}_{}", region, process_type.name())
@@ -493,11 +1085,16 @@ pub fn file_prefix(process_type: ProcessType, region: &Region) -> String {
 
 const PORT: usize = 3000;
 const CLIENT_PORT: usize = 4000;
+// each deployment uses a disjoint port range
+const DEPLOYMENT_PORT_OFFSET: usize = 500;
 
-pub fn port(process_id: ProcessId) -> usize {
-    process_id as usize + PORT
+pub fn port(deployment: DeploymentIndex, process_id: ProcessId) -> usize {
+    process_id as usize + PORT + deployment * DEPLOYMENT_PORT_OFFSET
 }
 
-pub fn client_port(process_id: ProcessId) -> usize {
-    process_id as usize + CLIENT_PORT
+pub fn client_port(
+    deployment: DeploymentIndex,
+    process_id: ProcessId,
+) -> usize {
+    process_id as usize + CLIENT_PORT + deployment * DEPLOYMENT_PORT_OFFSET
 }
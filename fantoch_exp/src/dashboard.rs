@@ -0,0 +1,223 @@
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// the page served reloads itself with this frequency
+const REFRESH_INTERVAL_SECS: u64 = 5;
+// max size of the requests read (the request itself is ignored)
+const REQUEST_BUFFER_SIZE: usize = 1024;
+
+/// Local web page that shows the progress of `bench_experiment`, so that long
+/// campaigns can be monitored without tailing logs over SSH.
+#[derive(Clone)]
+pub struct Dashboard {
+    state: Arc<Mutex<DashboardState>>,
+}
+
+struct DashboardState {
+    start: Instant,
+    total: usize,
+    done: usize,
+    combination: Option<Combination>,
+    machines: BTreeMap<String, String>,
+}
+
+struct Combination {
+    description: String,
+    start: Instant,
+    // mapping from each client to the number of commands issued by it and to
+    // the time at which that number was observed
+    issued: BTreeMap<String, (usize, Instant)>,
+    // mapping from each client to its throughput in the last observation
+    throughput: BTreeMap<String, f64>,
+}
+
+impl Dashboard {
+    /// Starts serving the dashboard on `localhost:port`.
+    pub async fn serve(port: u16) -> Result<Self, Report> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .await
+            .wrap_err("dashboard bind")?;
+        tracing::info!("dashboard available at http://127.0.0.1:{}", port);
+
+        let dashboard = Self {
+            state: Arc::new(Mutex::new(DashboardState {
+                start: Instant::now(),
+                total: 0,
+                done: 0,
+                combination: None,
+                machines: BTreeMap::new(),
+            })),
+        };
+
+        let server = dashboard.clone();
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(server.clone().respond(stream));
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "error accepting dashboard connection: {:?}",
+                            e
+                        );
+                    }
+                }
+            }
+        });
+        Ok(dashboard)
+    }
+
+    /// Sets the number of combinations to be run.
+    pub fn set_total(&self, total: usize) {
+        self.state.lock().unwrap().total = total;
+    }
+
+    /// Records that one more combination was run (or skipped).
+    pub fn inc(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.done += 1;
+        state.combination = None;
+    }
+
+    /// Records that the combination with the description provided started.
+    pub fn start_combination(&self, description: String) {
+        self.state.lock().unwrap().combination = Some(Combination {
+            description,
+            start: Instant::now(),
+            issued: BTreeMap::new(),
+            throughput: BTreeMap::new(),
+        });
+    }
+
+    /// Records the number of commands issued so far by some client.
+    pub fn client_progress(&self, client: String, issued: usize) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(combination) = state.combination.as_mut() {
+            let now = Instant::now();
+            if let Some((previous, at)) = combination.issued.get(&client) {
+                let elapsed = now.duration_since(*at).as_secs_f64();
+                if elapsed > 0.0 {
+                    let delta = issued.saturating_sub(*previous) as f64;
+                    combination
+                        .throughput
+                        .insert(client.clone(), delta / elapsed);
+                }
+            }
+            combination.issued.insert(client, (issued, now));
+        }
+    }
+
+    /// Records the health of some machine.
+    pub fn machine_health(&self, machine: String, health: String) {
+        self.state.lock().unwrap().machines.insert(machine, health);
+    }
+
+    async fn respond(self, mut stream: TcpStream) {
+        // the request is ignored: all paths serve the same page
+        let mut request = [0; REQUEST_BUFFER_SIZE];
+        if let Err(e) = stream.read(&mut request).await {
+            tracing::warn!("error reading dashboard request: {:?}", e);
+            return;
+        }
+        let body = self.render();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        if let Err(e) = stream.write_all(response.as_bytes()).await {
+            tracing::warn!("error writing dashboard response: {:?}", e);
+        }
+    }
+
+    fn render(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut html = String::new();
+        writeln!(
+            html,
+            "<!DOCTYPE html><html><head><meta http-equiv=\"refresh\" content=\"{}\"><title>fantoch_exp</title></head><body>",
+            REFRESH_INTERVAL_SECS
+        )
+        .unwrap();
+
+        writeln!(html, "<h2>Progress</h2>").unwrap();
+        writeln!(
+            html,
+            "<p>{} of {} combinations done (running for {})</p>",
+            state.done,
+            state.total,
+            fmt_duration(state.start.elapsed())
+        )
+        .unwrap();
+
+        writeln!(html, "<h2>Current combination</h2>").unwrap();
+        match &state.combination {
+            Some(combination) => {
+                writeln!(
+                    html,
+                    "<p>{} (running for {})</p>",
+                    escape(&combination.description),
+                    fmt_duration(combination.start.elapsed())
+                )
+                .unwrap();
+                let total: f64 = combination.throughput.values().sum();
+                writeln!(
+                    html,
+                    "<p>client throughput: {:.0} commands/s</p><table border=\"1\"><tr><th>client</th><th>issued</th><th>commands/s</th></tr>",
+                    total
+                )
+                .unwrap();
+                for (client, (issued, _)) in &combination.issued {
+                    let throughput = combination
+                        .throughput
+                        .get(client)
+                        .map(|throughput| format!("{:.0}", throughput))
+                        .unwrap_or_else(|| String::from("-"));
+                    writeln!(
+                        html,
+                        "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        escape(client),
+                        issued,
+                        throughput
+                    )
+                    .unwrap();
+                }
+                writeln!(html, "</table>").unwrap();
+            }
+            None => {
+                writeln!(html, "<p>none</p>").unwrap();
+            }
+        }
+
+        writeln!(html, "<h2>Machines</h2><table border=\"1\"><tr><th>machine</th><th>health</th></tr>").unwrap();
+        for (machine, health) in &state.machines {
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape(machine),
+                escape(health)
+            )
+            .unwrap();
+        }
+        writeln!(html, "</table></body></html>").unwrap();
+        html
+    }
+}
+
+fn fmt_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}h{:02}m{:02}s", secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,76 @@
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Name of the file (in the results dir) where all commands executed on the
+/// experiment machines are recorded, one JSON entry per line.
+pub const JOURNAL_FILE: &str = "journal.jsonl";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    // ip of the machine in which the command was executed
+    pub machine: String,
+    // command as executed locally (e.g. wrapped in `ssh`)
+    pub command: String,
+    // unix time (in millis) at which the command started and ended; commands
+    // spawned in the background are recorded as soon as they start
+    pub start: u64,
+    pub end: Option<u64>,
+    // exit code of the command; not set if the command was spawned in the
+    // background, failed to start, or was terminated by a signal
+    pub exit_code: Option<i32>,
+}
+
+/// Journal of the commands executed on the experiment machines, so that failed
+/// setups can be audited and replayed manually.
+#[derive(Clone)]
+pub struct Journal {
+    file: Arc<Mutex<File>>,
+}
+
+impl Journal {
+    /// Creates a new journal in `results_dir`. If a journal already exists
+    /// there, new entries are appended to it.
+    pub fn create(results_dir: impl AsRef<Path>) -> Result<Self, Report> {
+        std::fs::create_dir_all(&results_dir).wrap_err("create results dir")?;
+        let path = results_dir.as_ref().join(JOURNAL_FILE);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err("open journal file")?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Records a new entry. Failing to record an entry doesn't fail the
+    /// experiment; it's only logged.
+    pub fn record(&self, entry: JournalEntry) {
+        let mut line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("error serializing journal entry: {:?}", e);
+                return;
+            }
+        };
+        line.push('\n');
+        let mut file = self.file.lock().expect("journal lock should work");
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            tracing::warn!("error writing journal entry: {:?}", e);
+        }
+    }
+
+    /// Returns the current unix time (in millis).
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("we're way past epoch")
+            .as_millis() as u64
+    }
+}
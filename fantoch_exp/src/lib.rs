@@ -3,21 +3,34 @@
 #[cfg(feature = "exp")]
 pub mod bench;
 #[cfg(feature = "exp")]
+pub mod dashboard;
+#[cfg(feature = "exp")]
+pub mod journal;
+#[cfg(feature = "exp")]
 pub mod machine;
 #[cfg(feature = "exp")]
 pub mod progress;
 #[cfg(feature = "exp")]
+pub mod summary;
+#[cfg(feature = "exp")]
 pub mod testbed;
 #[cfg(feature = "exp")]
 pub mod util;
 
 pub mod config;
+pub mod lint;
+pub mod metadata;
+pub mod sweep;
 
 // Re-exports.
-pub use config::{ExperimentConfig, PlacementFlat, ProcessType};
+pub use config::{
+    ClientsPerRegion, Colocation, ExperimentConfig, PlacementFlat, ProcessType,
+    RegionFailure, TcpConfig,
+};
 
 use color_eyre::eyre::WrapErr;
 use color_eyre::Report;
+use fantoch::protocol::{ProtocolCapabilities, ProtocolRegistration};
 use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 use std::path::Path;
@@ -75,6 +88,7 @@ pub enum FantochFeature {
     Jemalloc,
     MaxLevelDebug,
     MaxLevelTrace,
+    RuntimeMetrics,
 }
 
 impl FantochFeature {
@@ -83,6 +97,7 @@ impl FantochFeature {
             Self::Jemalloc => "jemalloc",
             Self::MaxLevelDebug => "max_level_debug",
             Self::MaxLevelTrace => "max_level_trace",
+            Self::RuntimeMetrics => "runtime-metrics",
         }
         .to_string()
     }
@@ -98,39 +113,67 @@ impl FantochFeature {
     }
 }
 
-#[derive(
-    Debug,
-    Clone,
-    Copy,
-    PartialEq,
-    Eq,
-    PartialOrd,
-    Ord,
-    Deserialize,
-    Serialize,
-    Hash,
-)]
-pub enum Protocol {
-    AtlasLocked,
-    EPaxosLocked,
-    CaesarLocked,
-    FPaxos,
-    TempoAtomic,
-    TempoLocked,
-    Basic,
+// Binary that runs all protocols (the protocol to be run is selected with
+// `--protocol`).
+pub const SERVER_BINARY: &str = "fantoch-server";
+
+// Generates, from the protocols in `fantoch::registered_protocols!`, the
+// `Protocol` enum with one variant per protocol.
+macro_rules! protocol_enum {
+    ($(
+        register_protocol!($variant:ident {
+            name: $name:literal,
+            protocol: $protocol:path,
+            plot_name: $plot_name:literal,
+            plot_color: $plot_color:literal,
+            capabilities: $capabilities:tt $(,)?
+        });
+    )*) => {
+        #[derive(
+            Debug,
+            Clone,
+            Copy,
+            PartialEq,
+            Eq,
+            PartialOrd,
+            Ord,
+            Deserialize,
+            Serialize,
+            Hash,
+        )]
+        pub enum Protocol {
+            $($variant,)*
+        }
+
+        impl Protocol {
+            /// Returns all the protocols.
+            pub fn all() -> Vec<Protocol> {
+                vec![$(Protocol::$variant),*]
+            }
+
+            /// Returns the name with which the protocol is selected in the
+            /// server binary (see `SERVER_BINARY`).
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Protocol::$variant => $name,)*
+                }
+            }
+        }
+    };
 }
 
+fantoch::registered_protocols!(protocol_enum);
+
 impl Protocol {
-    pub fn binary(&self) -> &'static str {
-        match self {
-            Protocol::AtlasLocked => "atlas_locked",
-            Protocol::EPaxosLocked => "epaxos_locked",
-            Protocol::CaesarLocked => "caesar_locked",
-            Protocol::FPaxos => "fpaxos",
-            Protocol::TempoAtomic => "tempo_atomic",
-            Protocol::TempoLocked => "tempo_locked",
-            Protocol::Basic => "basic",
-        }
+    /// Returns the registration of the protocol.
+    pub fn registration(&self) -> &'static ProtocolRegistration {
+        fantoch::protocol::registration(self.name())
+            .expect("all protocols should be registered")
+    }
+
+    /// Returns what the protocol supports.
+    pub fn capabilities(&self) -> ProtocolCapabilities {
+        self.registration().capabilities
     }
 }
 
@@ -152,6 +195,12 @@ impl Testbed {
     }
 }
 
+// suffix of the temporary files where data is serialized to before being
+// renamed
+const TMP_FILE_SUFFIX: &str = "_tmp";
+// name of the file that marks the results in an experiment dir as complete
+const COMPLETE_MARKER_FILE: &str = "complete";
+
 #[derive(Debug)]
 pub enum SerializationFormat {
     BincodeGz,
@@ -167,24 +216,66 @@ pub fn serialize<T>(
 where
     T: serde::Serialize,
 {
+    // first serialize to a temporary file, and then rename it: since renames
+    // are atomic, a run killed in the middle of this never leaves a
+    // half-written file behind
+    let file = file.as_ref();
+    let mut tmp = file.as_os_str().to_owned();
+    tmp.push(TMP_FILE_SUFFIX);
     // if the file does not exist it will be created, otherwise truncated
-    let file = std::fs::File::create(file).wrap_err("serialize create file")?;
+    let writer =
+        std::fs::File::create(&tmp).wrap_err("serialize create file")?;
     // create a buf writer
-    let buf = std::io::BufWriter::new(file);
+    let mut buf = std::io::BufWriter::new(writer);
     // and try to serialize
     match format {
         SerializationFormat::BincodeGz => {
-            let buf =
-                flate2::write::GzEncoder::new(buf, flate2::Compression::best());
-            bincode::serialize_into(buf, &data).wrap_err("serialize")?
+            let mut encoder = flate2::write::GzEncoder::new(
+                &mut buf,
+                flate2::Compression::best(),
+            );
+            bincode::serialize_into(&mut encoder, &data)
+                .wrap_err("serialize")?;
+            encoder.finish().wrap_err("serialize finish")?;
         }
         SerializationFormat::Json => {
-            serde_json::to_writer(buf, &data).wrap_err("serialize")?
+            serde_json::to_writer(&mut buf, &data).wrap_err("serialize")?
         }
     }
+    // make sure everything reaches the disk before renaming
+    let writer = buf.into_inner().wrap_err("serialize flush")?;
+    writer.sync_all().wrap_err("serialize sync")?;
+    std::fs::rename(&tmp, file).wrap_err("serialize rename")?;
+    sync_parent_dir(file).wrap_err("serialize sync dir")?;
     Ok(())
 }
 
+/// Marks the results in `exp_dir` as complete, i.e. all their files were
+/// written. Results without this marker may have been left half-written by a
+/// run that was killed.
+pub fn mark_complete(exp_dir: impl AsRef<Path>) -> Result<(), Report> {
+    let marker = exp_dir.as_ref().join(COMPLETE_MARKER_FILE);
+    std::fs::File::create(&marker)
+        .and_then(|file| file.sync_all())
+        .wrap_err("create complete marker")?;
+    sync_parent_dir(&marker).wrap_err("sync complete marker")
+}
+
+/// Checks whether the results in `exp_dir` were marked as complete.
+pub fn is_complete(exp_dir: impl AsRef<Path>) -> bool {
+    exp_dir.as_ref().join(COMPLETE_MARKER_FILE).exists()
+}
+
+// Syncs the directory containing `file`, so that its creation (or renaming)
+// is persisted.
+fn sync_parent_dir(file: &Path) -> std::io::Result<()> {
+    let parent = file
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    std::fs::File::open(parent)?.sync_all()
+}
+
 // TODO maybe make this async
 pub fn deserialize<T>(
     file: impl AsRef<Path>,
@@ -0,0 +1,82 @@
+use crate::Protocol;
+use color_eyre::eyre;
+use color_eyre::Report;
+use fantoch::config::Config;
+
+/// Checks the combinations of an experiment spec for ones that are known to be
+/// unsupported or meaningless (e.g. FPaxos with more than one shard), so that
+/// experiments fail before any machine is launched instead of wasting a run.
+/// All the problems found are reported at once.
+pub fn lint(
+    configs: &[(Protocol, Config)],
+    batch_max_sizes: &[usize],
+) -> Result<(), Report> {
+    let mut problems = Vec::new();
+    for (protocol, config) in configs {
+        for problem in lint_config(*protocol, config, batch_max_sizes) {
+            problems.push(format!(
+                "{:?} with n = {}, f = {}: {}",
+                protocol,
+                config.n(),
+                config.f(),
+                problem
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        eyre::bail!("invalid experiment spec:\n- {}", problems.join("\n- "))
+    }
+}
+
+fn lint_config(
+    protocol: Protocol,
+    config: &Config,
+    batch_max_sizes: &[usize],
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let capabilities = protocol.capabilities();
+
+    let max_f = capabilities.max_f(config.n());
+    if config.f() == 0 || config.f() > max_f {
+        problems.push(format!("f should be between 1 and {}", max_f));
+    }
+
+    if config.shard_count() > 1 && !capabilities.partial_replication {
+        problems.push(format!(
+            "partial replication is not supported (shard_count = {})",
+            config.shard_count()
+        ));
+    }
+
+    // Caesar ignores `f`, and its fast quorum (3n/4 + 1) contains all
+    // processes with n = 3, in which case no process can fail (or be slow)
+    // without all commands taking the slow path
+    if matches!(protocol, Protocol::CaesarLocked) {
+        let (fast_quorum_size, _) = config.caesar_quorum_sizes();
+        if fast_quorum_size >= config.n() {
+            problems.push(format!(
+                "the fast quorum (of size {}) contains all processes",
+                fast_quorum_size
+            ));
+        }
+    }
+
+    // batching amortizes the cost of ordering commands, which is skipped
+    // altogether when commands are executed at commit time
+    if config.execute_at_commit() {
+        let batch_max_sizes: Vec<_> =
+            batch_max_sizes.iter().filter(|&&size| size > 1).collect();
+        if !batch_max_sizes.is_empty() {
+            problems.push(format!(
+                "batching (batch_max_size = {:?}) is meaningless with execute_at_commit",
+                batch_max_sizes
+            ));
+        }
+    }
+
+    problems
+}
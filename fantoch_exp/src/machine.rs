@@ -1,51 +1,210 @@
 use crate::args;
-use crate::config::{Placement, RegionIndex};
+use crate::config::{ClientMachineIndex, Placement, RegionIndex};
+use crate::journal::{Journal, JournalEntry};
 use crate::{FantochFeature, ProcessType, RunMode, Testbed};
 use color_eyre::eyre::WrapErr;
 use color_eyre::Report;
+use fantoch::client::ProcessSelection;
 use fantoch::id::{ProcessId, ShardId};
 use fantoch::planet::Region;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::future::Future;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::Arc;
 
 const SETUP_SCRIPT: &str = "exp_files/setup.sh";
+const FANTOCH_REPOSITORY: &str = "https://github.com/vitorenesduarte/fantoch";
+const REMOTE_BINARIES_DIR: &str = "fantoch/target/release";
+
+/// Operations that each provider of machines (e.g. AWS or baremetal machines
+/// launched with tsunami, or the local machine) implements, so that the rest
+/// of the experiment code, which only deals with `Machine`s, is independent of
+/// where the machines come from. Commands are only prepared here; running
+/// them is done by `Machine`.
+pub trait RemoteMachine: Send + Sync {
+    /// Returns the ip of the machine.
+    fn ip(&self) -> String;
+
+    /// Prepares a command that runs `command` on the machine.
+    fn prepare_exec(&self, command: String) -> tokio::process::Command;
+
+    /// Prepares a command that copies the local file `local_path` to
+    /// `remote_path` on the machine.
+    fn prepare_copy_to(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+    ) -> tokio::process::Command;
+
+    /// Prepares a command that copies the file `remote_path` on the machine
+    /// to the local file `local_path`.
+    fn prepare_copy_from(
+        &self,
+        remote_path: &Path,
+        local_path: &Path,
+    ) -> tokio::process::Command;
+}
+
+impl<T: RemoteMachine + ?Sized> RemoteMachine for &T {
+    fn ip(&self) -> String {
+        (**self).ip()
+    }
+
+    fn prepare_exec(&self, command: String) -> tokio::process::Command {
+        (**self).prepare_exec(command)
+    }
+
+    fn prepare_copy_to(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+    ) -> tokio::process::Command {
+        (**self).prepare_copy_to(local_path, remote_path)
+    }
 
-pub enum Machine<'a> {
-    Tsunami(tsunami::Machine<'a>),
-    TsunamiRef(&'a tsunami::Machine<'a>),
-    Local,
+    fn prepare_copy_from(
+        &self,
+        remote_path: &Path,
+        local_path: &Path,
+    ) -> tokio::process::Command {
+        (**self).prepare_copy_from(remote_path, local_path)
+    }
+}
+
+// machines launched with tsunami are accessed through ssh/scp
+impl RemoteMachine for tsunami::Machine<'_> {
+    fn ip(&self) -> String {
+        self.public_ip.clone()
+    }
+
+    fn prepare_exec(&self, command: String) -> tokio::process::Command {
+        Machine::prepare_ssh_exec(
+            self.username.as_ref(),
+            self.public_ip.as_ref(),
+            self.private_key
+                .as_ref()
+                .expect("private key should be set"),
+            command,
+        )
+    }
+
+    fn prepare_copy_to(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+    ) -> tokio::process::Command {
+        let from = local_path.display().to_string();
+        let to = format!(
+            "{}@{}:{}",
+            self.username,
+            self.public_ip,
+            remote_path.display()
+        );
+        Machine::prepare_scp(self, from, to)
+    }
+
+    fn prepare_copy_from(
+        &self,
+        remote_path: &Path,
+        local_path: &Path,
+    ) -> tokio::process::Command {
+        let from = format!(
+            "{}@{}:{}",
+            self.username,
+            self.public_ip,
+            remote_path.display()
+        );
+        let to = local_path.display().to_string();
+        Machine::prepare_scp(self, from, to)
+    }
+}
+
+/// The machine in which experiments are launched.
+pub struct LocalMachine;
+
+impl RemoteMachine for LocalMachine {
+    fn ip(&self) -> String {
+        String::from("127.0.0.1")
+    }
+
+    fn prepare_exec(&self, command: String) -> tokio::process::Command {
+        Machine::create_command(command)
+    }
+
+    fn prepare_copy_to(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+    ) -> tokio::process::Command {
+        Machine::prepare_cp(local_path, remote_path)
+    }
+
+    fn prepare_copy_from(
+        &self,
+        remote_path: &Path,
+        local_path: &Path,
+    ) -> tokio::process::Command {
+        Machine::prepare_cp(remote_path, local_path)
+    }
+}
+
+pub struct Machine<'a> {
+    inner: Box<dyn RemoteMachine + 'a>,
+    // if set, all commands executed on this machine are recorded here
+    journal: Option<Journal>,
 }
 
 impl<'a> Machine<'a> {
-    pub fn ip(&self) -> String {
-        match self {
-            Self::Tsunami(vm) => vm.public_ip.clone(),
-            Self::TsunamiRef(vm) => vm.public_ip.clone(),
-            Self::Local => String::from("127.0.0.1"),
+    /// Creates a new `Machine` from any `RemoteMachine`.
+    pub fn new<M>(machine: M) -> Self
+    where
+        M: RemoteMachine + 'a,
+    {
+        Self {
+            inner: Box::new(machine),
+            journal: None,
         }
     }
 
+    /// Creates a new `Machine` that represents the local machine.
+    pub fn local() -> Self {
+        Self::new(LocalMachine)
+    }
+
+    pub fn ip(&self) -> String {
+        self.inner.ip()
+    }
+
+    /// Sets the journal where all commands executed on this machine are
+    /// recorded.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = Some(journal);
+    }
+
     pub async fn exec(&self, command: impl ToString) -> Result<String, Report> {
-        match self {
-            Self::Tsunami(vm) => Self::tsunami_exec(vm, command).await,
-            Self::TsunamiRef(vm) => Self::tsunami_exec(vm, command).await,
-            Self::Local => {
-                Self::exec_command(Self::create_command(command)).await
-            }
-        }
+        let out = self.run(self.prepare_exec(command)).await?;
+        Self::stdout(out)
+    }
+
+    /// Spawns `command` in the background.
+    pub fn spawn(
+        &self,
+        command: impl ToString,
+    ) -> Result<tokio::process::Child, Report> {
+        let mut command = self.prepare_exec(command);
+        let start = Journal::now();
+        let child = command.spawn();
+        // the exit code of background commands is never known
+        self.record(&command, start, None, None);
+        child.wrap_err("spawn command")
     }
 
     pub fn prepare_exec(
         &self,
         command: impl ToString,
     ) -> tokio::process::Command {
-        match &self {
-            Self::Tsunami(vm) => Self::tsunami_prepare_exec(vm, command),
-            Self::TsunamiRef(vm) => Self::tsunami_prepare_exec(vm, command),
-            Self::Local => Self::create_command(command),
-        }
+        self.inner.prepare_exec(command.to_string())
     }
 
     pub async fn script_exec(
@@ -63,15 +222,12 @@ impl<'a> Machine<'a> {
         local_path: impl AsRef<Path>,
         remote_path: impl AsRef<Path>,
     ) -> Result<(), Report> {
-        match self {
-            Self::Tsunami(vm) => {
-                Self::tsunami_copy_to(vm, local_path, remote_path).await
-            }
-            Self::TsunamiRef(vm) => {
-                Self::tsunami_copy_to(vm, local_path, remote_path).await
-            }
-            Self::Local => Self::local_copy(local_path, remote_path).await,
-        }
+        self.run(
+            self.inner
+                .prepare_copy_to(local_path.as_ref(), remote_path.as_ref()),
+        )
+        .await?;
+        Ok(())
     }
 
     pub async fn copy_from(
@@ -79,80 +235,19 @@ impl<'a> Machine<'a> {
         remote_path: impl AsRef<Path>,
         local_path: impl AsRef<Path>,
     ) -> Result<(), Report> {
-        match self {
-            Self::Tsunami(vm) => {
-                Self::tsunami_copy_from(vm, remote_path, local_path).await
-            }
-            Self::TsunamiRef(vm) => {
-                Self::tsunami_copy_from(vm, remote_path, local_path).await
-            }
-            Self::Local => Self::local_copy(remote_path, local_path).await,
-        }
-    }
-
-    async fn tsunami_exec(
-        vm: &tsunami::Machine<'_>,
-        command: impl ToString,
-    ) -> Result<String, Report> {
-        Self::ssh_exec(
-            vm.username.as_ref(),
-            vm.public_ip.as_ref(),
-            vm.private_key.as_ref().expect("private key should be set"),
-            command,
+        self.run(
+            self.inner
+                .prepare_copy_from(remote_path.as_ref(), local_path.as_ref()),
         )
-        .await
-    }
-
-    fn tsunami_prepare_exec(
-        vm: &tsunami::Machine<'_>,
-        command: impl ToString,
-    ) -> tokio::process::Command {
-        Self::prepare_ssh_exec(
-            vm.username.as_ref(),
-            vm.public_ip.as_ref(),
-            vm.private_key.as_ref().expect("private key should be set"),
-            command,
-        )
-    }
-
-    async fn tsunami_copy_to(
-        vm: &tsunami::Machine<'_>,
-        local_path: impl AsRef<Path>,
-        remote_path: impl AsRef<Path>,
-    ) -> Result<(), Report> {
-        let from = local_path.as_ref().display();
-        let to = format!(
-            "{}@{}:{}",
-            vm.username,
-            vm.public_ip,
-            remote_path.as_ref().display()
-        );
-        let scp_command = format!(
-            "scp -o StrictHostKeyChecking=no -i {} {} {}",
-            vm.private_key
-                .as_ref()
-                .expect("private key should be set")
-                .as_path()
-                .display(),
-            from,
-            to,
-        );
-        Self::create_command(scp_command).output().await?;
+        .await?;
         Ok(())
     }
 
-    async fn tsunami_copy_from(
+    fn prepare_scp(
         vm: &tsunami::Machine<'_>,
-        remote_path: impl AsRef<Path>,
-        local_path: impl AsRef<Path>,
-    ) -> Result<(), Report> {
-        let from = format!(
-            "{}@{}:{}",
-            vm.username,
-            vm.public_ip,
-            remote_path.as_ref().display()
-        );
-        let to = local_path.as_ref().display();
+        from: String,
+        to: String,
+    ) -> tokio::process::Command {
         let scp_command = format!(
             "scp -o StrictHostKeyChecking=no -i {} {} {}",
             vm.private_key
@@ -163,18 +258,12 @@ impl<'a> Machine<'a> {
             from,
             to,
         );
-        Self::create_command(scp_command).output().await?;
-        Ok(())
+        Self::create_command(scp_command)
     }
 
-    async fn local_copy(
-        from: impl AsRef<Path>,
-        to: impl AsRef<Path>,
-    ) -> Result<(), Report> {
-        let cp_command =
-            format!("cp {} {}", from.as_ref().display(), to.as_ref().display());
-        Self::create_command(cp_command).output().await?;
-        Ok(())
+    fn prepare_cp(from: &Path, to: &Path) -> tokio::process::Command {
+        let cp_command = format!("cp {} {}", from.display(), to.display());
+        Self::create_command(cp_command)
     }
 
     pub async fn ssh_exec(
@@ -217,10 +306,51 @@ impl<'a> Machine<'a> {
         command
     }
 
+    // Runs `command` until it ends, recording it in the journal (if any).
+    async fn run(
+        &self,
+        mut command: tokio::process::Command,
+    ) -> Result<std::process::Output, Report> {
+        let start = Journal::now();
+        let out = command.output().await;
+        let exit_code = out.as_ref().ok().and_then(|out| out.status.code());
+        self.record(&command, start, Some(Journal::now()), exit_code);
+        out.wrap_err("ssh command")
+    }
+
+    fn record(
+        &self,
+        command: &tokio::process::Command,
+        start: u64,
+        end: Option<u64>,
+        exit_code: Option<i32>,
+    ) {
+        if let Some(journal) = &self.journal {
+            // commands are always created with `bash -c <command>`
+            let command = command
+                .as_std()
+                .get_args()
+                .last()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .unwrap_or_default();
+            journal.record(JournalEntry {
+                machine: self.ip(),
+                command,
+                start,
+                end,
+                exit_code,
+            });
+        }
+    }
+
     async fn exec_command(
         mut command: tokio::process::Command,
     ) -> Result<String, Report> {
         let out = command.output().await.wrap_err("ssh command")?;
+        Self::stdout(out)
+    }
+
+    fn stdout(out: std::process::Output) -> Result<String, Report> {
         let out = String::from_utf8(out.stdout)
             .wrap_err("output conversion to utf8")?
             .trim()
@@ -238,25 +368,53 @@ pub struct Machines<'a> {
     // potentially more than one process machine per region (if partial
     // replication)
     servers: HashMap<ProcessId, Machine<'a>>,
-    // only one client machine per region
-    clients: HashMap<Region, Machine<'a>>,
+    // the same number of client machines per region (among which the clients
+    // of that region are split)
+    clients: HashMap<(Region, ClientMachineIndex), Machine<'a>>,
+    client_machines_per_region: usize,
 }
 
 impl<'a> Machines<'a> {
     pub fn new(
         placement: Placement,
         servers: HashMap<ProcessId, Machine<'a>>,
-        clients: HashMap<Region, Machine<'a>>,
+        clients: HashMap<(Region, ClientMachineIndex), Machine<'a>>,
     ) -> Self {
         assert_eq!(
             placement.len(),
             servers.len(),
             "placement and servers should have the same cardinality"
         );
+
+        // compute the number of client machines per region and check that
+        // it's the same in all regions
+        let mut regions: Vec<_> =
+            clients.keys().map(|(region, _)| region).collect();
+        regions.sort();
+        regions.dedup();
+        assert!(!regions.is_empty(), "there should be client machines");
+        assert_eq!(
+            clients.len() % regions.len(),
+            0,
+            "all regions should have the same number of client machines"
+        );
+        let client_machines_per_region = clients.len() / regions.len();
+        for region in regions {
+            for client_index in 0..client_machines_per_region {
+                assert!(
+                    clients.contains_key(&(region.clone(), client_index)),
+                    "client machine {} should exist in region {:?}",
+                    client_index,
+                    region
+                );
+            }
+        }
+
         Self {
             placement,
             servers,
             clients,
+            client_machines_per_region,
         }
     }
 
@@ -264,6 +422,15 @@ impl<'a> Machines<'a> {
         &self.placement
     }
 
+    /// Sets the journal where all commands executed on these machines are
+    /// recorded.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.servers
+            .values_mut()
+            .chain(self.clients.values_mut())
+            .for_each(|vm| vm.set_journal(journal.clone()));
+    }
+
     pub fn servers(&self) -> impl Iterator<Item = (&ProcessId, &Machine<'_>)> {
         self.servers.iter()
     }
@@ -274,8 +441,12 @@ impl<'a> Machines<'a> {
             .expect("server vm should exist")
     }
 
-    pub fn clients(&self) -> impl Iterator<Item = (&Region, &Machine<'_>)> {
-        self.clients.iter()
+    pub fn clients(
+        &self,
+    ) -> impl Iterator<Item = (&Region, ClientMachineIndex, &Machine<'_>)> {
+        self.clients
+            .iter()
+            .map(|((region, client_index), vm)| (region, *client_index, vm))
     }
 
     pub fn vms(&self) -> impl Iterator<Item = &Machine<'_>> {
@@ -293,6 +464,10 @@ impl<'a> Machines<'a> {
         self.clients.len()
     }
 
+    pub fn client_machines_per_region(&self) -> usize {
+        self.client_machines_per_region
+    }
+
     pub fn vm_count(&self) -> usize {
         self.server_count() + self.client_count()
     }
@@ -349,6 +524,42 @@ impl<'a> Machines<'a> {
         (ids, region_index)
     }
 
+    /// Returns the process, on each shard, to which the clients in region
+    /// index `region_index` should submit their commands. Processes are
+    /// sorted by distance in the same way as in `sorted_processes`.
+    pub fn selected_processes(
+        &self,
+        region_index: RegionIndex,
+        selection: ProcessSelection,
+    ) -> Vec<ProcessId> {
+        // mapping from shard id to the process in each region index
+        let mut shards = BTreeMap::new();
+        for ((_, shard_id), (process_id, peer_region_index)) in
+            self.placement.iter()
+        {
+            shards
+                .entry(*shard_id)
+                .or_insert_with(BTreeMap::new)
+                .insert(*peer_region_index, *process_id);
+        }
+
+        shards
+            .into_iter()
+            .map(|(_, processes)| {
+                let n = processes.len();
+                let sorted: Vec<_> = (region_index..=n)
+                    .chain(1..region_index)
+                    .map(|index| {
+                        *processes
+                            .get(&index)
+                            .expect("region index should be in placement")
+                    })
+                    .collect();
+                selection.select(&sorted)
+            })
+            .collect()
+    }
+
     pub fn sorted_processes(
         &self,
         shard_count: usize,
@@ -408,11 +619,148 @@ impl<'a> Machines<'a> {
     }
 }
 
+/// Cache of fantoch binaries, kept locally (in `dir`) across experiment
+/// runs. Binaries are keyed by the commit they were built from, the
+/// features and run mode used, and the cpu model of the machine that built
+/// them (since binaries are compiled with `target-cpu=native`). This allows
+/// binaries to be built only once and then copied to all other machines.
+#[derive(Clone)]
+pub struct BinaryCache {
+    dir: PathBuf,
+    // makes sure that only one machine builds the binaries missing in cache
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl BinaryCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    // Makes sure that the binaries are in `REMOTE_BINARIES_DIR` on the `vm`:
+    // - if the binaries are not in cache, they're built on the `vm` and then
+    //   copied to the cache
+    // - otherwise, they're copied from the cache to the `vm`
+    async fn setup(
+        &self,
+        vm: &Machine<'_>,
+        script_file: &str,
+        testbed: &str,
+        mode: &str,
+        branch: &str,
+        features: &str,
+    ) -> Result<(), Report> {
+        let key_dir = self.key_dir(vm, mode, branch, features).await?;
+
+        let guard = self.lock.lock().await;
+        if !key_dir.exists() {
+            tracing::info!("building binaries for {:?}", key_dir);
+            let stdout = vm
+                .script_exec(
+                    script_file,
+                    args![testbed, mode, branch, "build", features, "2>&1"],
+                )
+                .await?;
+            tracing::trace!("full output:\n{}", stdout);
+
+            // copy binaries to a temporary dir first, so that a cache entry
+            // only exists if all binaries were successfully copied
+            let tmp_dir = key_dir.with_extension("tmp");
+            tokio::fs::create_dir_all(&tmp_dir)
+                .await
+                .wrap_err("create tmp binary cache dir")?;
+            for binary in Self::remote_binaries(vm).await? {
+                vm.copy_from(
+                    format!("{}/{}", REMOTE_BINARIES_DIR, binary),
+                    tmp_dir.join(&binary),
+                )
+                .await
+                .wrap_err("copy_from binary")?;
+            }
+            tokio::fs::rename(&tmp_dir, &key_dir)
+                .await
+                .wrap_err("rename tmp binary cache dir")?;
+            // the binaries are already in the vm
+            return Ok(());
+        }
+        drop(guard);
+
+        tracing::debug!("copying binaries from {:?}", key_dir);
+        vm.exec(format!("mkdir -p {}", REMOTE_BINARIES_DIR))
+            .await
+            .wrap_err("mkdir binaries dir")?;
+        let mut entries = tokio::fs::read_dir(&key_dir)
+            .await
+            .wrap_err("read binary cache dir")?;
+        while let Some(entry) = entries.next_entry().await? {
+            let binary = entry.file_name();
+            let remote_path =
+                format!("{}/{}", REMOTE_BINARIES_DIR, binary.to_string_lossy());
+            vm.copy_to(entry.path(), &remote_path)
+                .await
+                .wrap_err("copy_to binary")?;
+            vm.exec(format!("chmod u+x {}", remote_path))
+                .await
+                .wrap_err("chmod binary")?;
+        }
+        Ok(())
+    }
+
+    async fn key_dir(
+        &self,
+        vm: &Machine<'_>,
+        mode: &str,
+        branch: &str,
+        features: &str,
+    ) -> Result<PathBuf, Report> {
+        // find the commit the branch currently points to
+        let commit = branch_commit(branch).await?;
+
+        // find the cpu model of the vm
+        let command = "grep -m1 'model name' /proc/cpuinfo";
+        let stdout = vm.exec(command).await.wrap_err("cpu model")?;
+        let cpu = stdout
+            .split(':')
+            .nth(1)
+            .unwrap_or_default()
+            .trim()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>();
+
+        let features = if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.replace(',', "-")
+        };
+        let key = format!("{}_{}_{}_{}", commit, features, mode, cpu);
+        Ok(self.dir.join(key))
+    }
+
+    // Returns the name of all binaries built on the `vm`.
+    async fn remote_binaries(vm: &Machine<'_>) -> Result<Vec<String>, Report> {
+        let command = format!(
+            "find {} -maxdepth 1 -type f -executable -printf '%f\\n'",
+            REMOTE_BINARIES_DIR
+        );
+        let stdout = vm.exec(command).await.wrap_err("find binaries")?;
+        let binaries: Vec<_> =
+            stdout.lines().map(|binary| binary.to_string()).collect();
+        if binaries.is_empty() {
+            color_eyre::eyre::bail!("no binaries found after build");
+        }
+        Ok(binaries)
+    }
+}
+
 pub fn fantoch_setup(
     branch: String,
     run_mode: RunMode,
     features: Vec<FantochFeature>,
     testbed: Testbed,
+    binary_cache: BinaryCache,
 ) -> Box<
     dyn for<'r> Fn(
             &'r tsunami::Machine<'_>,
@@ -428,11 +776,12 @@ pub fn fantoch_setup(
     );
 
     Box::new(move |vm| {
-        let vm = Machine::TsunamiRef(vm);
+        let vm = Machine::new(vm);
         let testbed = testbed.name();
         let mode = run_mode.name();
         let branch = branch.clone();
         let features = fantoch_features_as_arg(&features);
+        let binary_cache = binary_cache.clone();
         Box::pin(async move {
             // files
             let script_file = "setup.sh";
@@ -442,13 +791,13 @@ pub fn fantoch_setup(
                 .await
                 .wrap_err("copy_to setup script")?;
 
-            // execute setup script
+            // execute setup script (without building fantoch)
             let mut done = false;
             while !done {
                 let stdout = vm
                     .script_exec(
                         script_file,
-                        args![testbed, mode, branch, features, "2>&1"],
+                        args![testbed, mode, branch, "setup", features, "2>&1"],
                     )
                     .await?;
                 tracing::trace!("full output:\n{}", stdout);
@@ -464,6 +813,7 @@ pub fn fantoch_setup(
                     "htop",
                     "dstat",
                     "lsof",
+                    "gdb",
                 ]
                 .into_iter()
                 .all(|package| {
@@ -489,7 +839,12 @@ pub fn fantoch_setup(
                     );
                 }
             }
-            Ok(())
+
+            // make sure the binaries are in the machine
+            binary_cache
+                .setup(&vm, script_file, &testbed, &mode, &branch, &features)
+                .await
+                .wrap_err("binary cache setup")
         })
     })
 }
@@ -523,13 +878,13 @@ pub async fn local_fantoch_setup(
     let testbed = testbed.name();
     let mode = run_mode.name();
     let features = fantoch_features_as_arg(&features);
-    let vm = Machine::Local;
+    let vm = Machine::local();
 
     // execute setup script
     let stdout = vm
         .script_exec(
             SETUP_SCRIPT,
-            args![testbed, mode, branch, features, "2>&1"],
+            args![testbed, mode, branch, "all", features, "2>&1"],
         )
         .await?;
     tracing::trace!("full output:\n{}", stdout);
@@ -552,12 +907,29 @@ pub fn fantoch_bin_script(
     max_log_level: &tracing::Level,
     err_file: impl ToString,
 ) -> String {
-    // binary=info makes sure that we also capture any logs in there
+    // binary=info makes sure that we also capture any logs in there (the log
+    // target of a binary is its name with dashes replaced by underscores)
     let env_vars = format!(
         "RUST_LOG={}={},fantoch={},fantoch_ps={}",
-        binary, max_log_level, max_log_level, max_log_level,
+        binary.replace('-', "_"),
+        max_log_level,
+        max_log_level,
+        max_log_level,
     );
     let run_command = run_mode.run_command(process_type, &env_vars, binary);
     let args = args.join(" ");
     format!("{} {} > {} 2>&1", run_command, args, err_file.to_string())
 }
+
+/// Returns the commit `branch` currently points to in the fantoch repository.
+pub async fn branch_commit(branch: &str) -> Result<String, Report> {
+    let command =
+        format!("git ls-remote {} refs/heads/{}", FANTOCH_REPOSITORY, branch);
+    let stdout = Machine::local().exec(command).await?;
+    let commit = stdout
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| color_eyre::eyre::eyre!("branch {} not found", branch))?
+        .to_string();
+    Ok(commit)
+}
@@ -0,0 +1,147 @@
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+const EXP_CONFIG_FILE: &str = "exp_config.json";
+pub const METADATA_FILE: &str = "metadata.json";
+
+/// Metadata about the environment in which an experiment was run, recorded in
+/// each experiment folder next to the experiment config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentMetadata {
+    pub branch: String,
+    // commit the branch pointed to when the experiment was run; older results
+    // have no commit recorded
+    #[serde(default)]
+    pub commit: Option<String>,
+    // mapping from the name of each machine to its metadata
+    pub machines: BTreeMap<String, MachineMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineMetadata {
+    pub ip: String,
+    pub kernel: String,
+    // smallest effective MSS of the connections of the server running in the
+    // machine (which depends on the MTU of the machines, e.g. whether they
+    // support jumbo frames); older results (and clients) have no MSS recorded
+    #[serde(default)]
+    pub mss: Option<u32>,
+    // placement achieved by the machine (only on AWS), so that machines
+    // sharing a host or zone can be identified; older results have no
+    // placement recorded
+    #[serde(default)]
+    pub placement: Option<MachinePlacement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachinePlacement {
+    pub availability_zone: String,
+    pub instance_id: String,
+}
+
+/// A field whose value differs between two experiment folders. The value is
+/// `None` if the field was not recorded in that folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataDifference {
+    pub field: String,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+impl fmt::Display for MetadataDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<String>| {
+            value
+                .clone()
+                .unwrap_or_else(|| String::from("<not recorded>"))
+        };
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.field,
+            show(&self.left),
+            show(&self.right)
+        )
+    }
+}
+
+/// Returns the differences between the recorded metadata (i.e. the experiment
+/// config and the environment metadata) of the experiment folders
+/// `left_exp_dir` and `right_exp_dir`.
+pub fn diff(
+    left_exp_dir: impl AsRef<Path>,
+    right_exp_dir: impl AsRef<Path>,
+) -> Result<Vec<MetadataDifference>, Report> {
+    let left = flat_metadata(left_exp_dir)?;
+    let mut right = flat_metadata(right_exp_dir)?;
+
+    let mut differences = Vec::new();
+    for (field, left) in left {
+        let right = right.remove(&field);
+        if Some(&left) != right.as_ref() {
+            differences.push(MetadataDifference {
+                field,
+                left: Some(left),
+                right,
+            });
+        }
+    }
+    // the fields left in `right` were not recorded in `left`
+    differences.extend(right.into_iter().map(|(field, right)| {
+        MetadataDifference {
+            field,
+            left: None,
+            right: Some(right),
+        }
+    }));
+    differences.sort_by(|a, b| a.field.cmp(&b.field));
+    Ok(differences)
+}
+
+// Loads the metadata recorded in `exp_dir` as a mapping from each field to its
+// value. The files are loaded as plain json (and not as `ExperimentConfig`) so
+// that results with different schema versions can be compared; older results
+// have no environment metadata.
+fn flat_metadata(
+    exp_dir: impl AsRef<Path>,
+) -> Result<BTreeMap<String, String>, Report> {
+    let exp_dir = exp_dir.as_ref();
+    let mut fields = BTreeMap::new();
+    for (file, prefix) in
+        vec![(EXP_CONFIG_FILE, "exp_config"), (METADATA_FILE, "metadata")]
+    {
+        let path = exp_dir.join(file);
+        if path.exists() {
+            let value: Value =
+                crate::deserialize(&path, crate::SerializationFormat::Json)
+                    .wrap_err_with(|| format!("deserialize {:?}", path))?;
+            flatten(prefix.to_string(), value, &mut fields);
+        } else if file == EXP_CONFIG_FILE {
+            color_eyre::eyre::bail!("{:?} not found", path);
+        }
+    }
+    Ok(fields)
+}
+
+fn flatten(field: String, value: Value, fields: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(format!("{}.{}", field, key), value, fields);
+            }
+        }
+        Value::Array(values) => {
+            for (index, value) in values.into_iter().enumerate() {
+                flatten(format!("{}[{}]", field, index), value, fields);
+            }
+        }
+        value => {
+            fields.insert(field, value.to_string());
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use crate::config::{self, ClientsPerRegion, ExperimentConfig, ProcessType};
+use crate::{Protocol, SerializationFormat};
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use fantoch::client::{ClientData, Workload};
+use fantoch::metrics::Histogram;
+use fantoch::planet::Region;
+use fantoch::protocol::ProtocolMetricsKind;
+use fantoch::run::task::server::metrics_logger::ProcessMetrics;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name of the file (in each experiment dir) with the summary of the
+/// experiment.
+pub const SUMMARY_FILE: &str = "summary.json";
+
+/// Summary of an experiment, i.e. of a (config, workload, clients) combination,
+/// written as a single JSON file so that quick comparisons can be made (e.g.
+/// with `jq`) without loading all metrics.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Summary {
+    pub protocol: Protocol,
+    pub n: usize,
+    pub f: usize,
+    pub shard_count: usize,
+    pub clients_per_region: ClientsPerRegion,
+    pub workload: Workload,
+    // ops/s completed by all clients
+    pub throughput: f64,
+    pub latency: LatencySummary,
+    pub latency_per_region: BTreeMap<String, LatencySummary>,
+    // percentage of commands that took the fast path (if the protocol has one)
+    pub fast_path_ratio: Option<f64>,
+    // resource usage of the server machines (if their dstats were pulled)
+    pub resources: Option<ResourcesSummary>,
+    pub integrity_discrepancies: Vec<String>,
+}
+
+/// Latency percentiles (in milliseconds).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub p99_9: f64,
+}
+
+/// Resource usage of the server machines while clients were running.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ResourcesSummary {
+    // cpu usage (usr + sys, in percentage) averaged over all machines, and of
+    // the busiest machine
+    pub cpu_mean: f64,
+    pub cpu_max: f64,
+    // highest memory used (in MB) by any machine
+    pub mem_used_max: f64,
+    // network usage (in MB/s) averaged over all machines
+    pub net_recv_mean: f64,
+    pub net_send_mean: f64,
+}
+
+impl Summary {
+    /// Computes the summary of the experiment in `exp_dir`, given the metrics
+    /// files pulled to it.
+    pub fn compute(
+        exp_config: &ExperimentConfig,
+        exp_dir: &str,
+    ) -> Result<Self, Report> {
+        // load the metrics of all clients (per region)
+        let mut client_data = BTreeMap::new();
+        for (region, process_type) in client_process_types(exp_config) {
+            let prefix = config::file_prefix(process_type, &region);
+            let data: ClientData = crate::deserialize(
+                format!("{}/{}_metrics.bincode.gz", exp_dir, prefix),
+                SerializationFormat::BincodeGz,
+            )
+            .wrap_err("deserialize client metrics")?;
+            client_data
+                .entry(region.name().clone())
+                .or_insert_with(ClientData::new)
+                .merge(&data);
+        }
+        let mut global = ClientData::new();
+        for data in client_data.values() {
+            global.merge(data);
+        }
+        let latency_per_region = client_data
+            .iter()
+            .map(|(region, data)| (region.clone(), LatencySummary::from(data)))
+            .collect();
+
+        // compute the fast path ratio from the metrics of all servers
+        let mut fast_path = 0;
+        let mut slow_path = 0;
+        for (region, _, process_id, _) in exp_config.placement.iter() {
+            let prefix =
+                config::file_prefix(ProcessType::Server(*process_id), region);
+            let metrics: ProcessMetrics = crate::deserialize(
+                format!("{}/{}_metrics.bincode.gz", exp_dir, prefix),
+                SerializationFormat::BincodeGz,
+            )
+            .wrap_err("deserialize process metrics")?;
+            let metrics = metrics.protocol_metrics();
+            let aggregated = |kind| {
+                metrics.get_aggregated(kind).cloned().unwrap_or_default()
+            };
+            fast_path += aggregated(ProtocolMetricsKind::FastPath);
+            slow_path += aggregated(ProtocolMetricsKind::SlowPath);
+        }
+        let fast_path_ratio = if fast_path + slow_path > 0 {
+            Some((fast_path * 100) as f64 / (fast_path + slow_path) as f64)
+        } else {
+            None
+        };
+
+        // only consider resource usage while clients were running
+        let resources = global.start_and_end().and_then(|(start, end)| {
+            ResourcesSummary::compute(exp_config, exp_dir, start, end)
+        });
+
+        Ok(Self {
+            protocol: exp_config.protocol,
+            n: exp_config.config.n(),
+            f: exp_config.config.f(),
+            shard_count: exp_config.config.shard_count(),
+            clients_per_region: exp_config.clients_per_region,
+            workload: exp_config.workload,
+            throughput: global.throughput(),
+            latency: LatencySummary::from(&global),
+            latency_per_region,
+            fast_path_ratio,
+            resources,
+            integrity_discrepancies: exp_config.integrity_discrepancies.clone(),
+        })
+    }
+
+    /// Computes the summary of the experiment in `exp_dir` and saves it there.
+    pub fn save(
+        exp_config: &ExperimentConfig,
+        exp_dir: &str,
+    ) -> Result<(), Report> {
+        let summary = Self::compute(exp_config, exp_dir)?;
+        crate::serialize(
+            summary,
+            format!("{}/{}", exp_dir, SUMMARY_FILE),
+            SerializationFormat::Json,
+        )
+        .wrap_err("serialize summary")
+    }
+}
+
+impl LatencySummary {
+    fn from(data: &ClientData) -> Self {
+        let histogram = Histogram::from(
+            data.latency_data()
+                .map(|latency| latency.as_micros() as u64),
+        );
+        // convert micros to millis
+        let millis = |micros: f64| micros / 1000.0;
+        Self {
+            count: histogram.count(),
+            mean: millis(histogram.mean().value()),
+            p50: millis(histogram.percentile(0.5).value()),
+            p95: millis(histogram.percentile(0.95).value()),
+            p99: millis(histogram.percentile(0.99).value()),
+            p99_9: millis(histogram.percentile(0.999).value()),
+        }
+    }
+}
+
+impl ResourcesSummary {
+    fn compute(
+        exp_config: &ExperimentConfig,
+        exp_dir: &str,
+        start: u64,
+        end: u64,
+    ) -> Option<Self> {
+        let mut cpu = Vec::new();
+        let mut mem_used_max = 0f64;
+        let mut net_recv = Vec::new();
+        let mut net_send = Vec::new();
+        for (region, _, process_id, _) in exp_config.placement.iter() {
+            let prefix =
+                config::file_prefix(ProcessType::Server(*process_id), region);
+            let path = format!("{}/{}_dstat.csv", exp_dir, prefix);
+            let rows = match dstat_rows(&path, start, end) {
+                Some(rows) if !rows.is_empty() => rows,
+                _ => {
+                    tracing::warn!("no dstat rows in {:?}", path);
+                    continue;
+                }
+            };
+            cpu.push(mean(rows.iter().map(|row| row.cpu_usr + row.cpu_sys)));
+            net_recv.push(mean(rows.iter().map(|row| row.net_recv)));
+            net_send.push(mean(rows.iter().map(|row| row.net_send)));
+            for row in rows {
+                mem_used_max = mem_used_max.max(row.mem_used);
+            }
+        }
+        if cpu.is_empty() {
+            return None;
+        }
+        // convert bytes to MB
+        let mb = |bytes: f64| bytes / 1_000_000.0;
+        Some(Self {
+            cpu_mean: mean(cpu.iter().cloned()),
+            cpu_max: cpu.iter().cloned().fold(0f64, f64::max),
+            mem_used_max: mb(mem_used_max),
+            net_recv_mean: mb(mean(net_recv.into_iter())),
+            net_send_mean: mb(mean(net_send.into_iter())),
+        })
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0f64, 0usize), |(sum, count), value| {
+        (sum + value, count + 1)
+    });
+    if count == 0 {
+        0f64
+    } else {
+        sum / count as f64
+    }
+}
+
+// Returns the region and the process type of all clients whose metrics were
+// pulled (clients in the region that failed, if any, have no metrics).
+fn client_process_types(
+    exp_config: &ExperimentConfig,
+) -> Vec<(Region, ProcessType)> {
+    let failed = exp_config
+        .region_failure
+        .as_ref()
+        .map(|region_failure| &region_failure.region);
+    let mut process_types = Vec::new();
+    let mut regions = BTreeMap::new();
+    for (region, _, process_id, region_index) in exp_config.placement.iter() {
+        if Some(region) == failed {
+            continue;
+        }
+        regions.insert(*region_index, region.clone());
+        if exp_config.colocation.is_some() {
+            let process_type = ProcessType::ColocatedClient(*process_id);
+            process_types.push((region.clone(), process_type));
+        }
+    }
+    for (region_index, region) in regions {
+        for client_index in 0..exp_config.client_machines_per_region {
+            let process_type = ProcessType::Client(region_index, client_index);
+            process_types.push((region.clone(), process_type));
+        }
+    }
+    process_types
+}
+
+// Fields of a dstat row that are summarized.
+struct DstatRow {
+    cpu_usr: f64,
+    cpu_sys: f64,
+    net_recv: f64,
+    net_send: f64,
+    mem_used: f64,
+}
+
+// Parses the rows of the dstat file in `path` that were recorded between
+// `start` and `end` (in millis). Returns `None` if the file can't be read or
+// doesn't have the expected columns.
+fn dstat_rows(path: &str, start: u64, end: u64) -> Option<Vec<DstatRow>> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let parse_line = |line: &str| -> Vec<String> {
+        line.split(',')
+            .map(|field| field.trim().trim_matches('"').to_string())
+            .collect()
+    };
+    let mut lines = contents.lines();
+
+    // find the header line (the ones before it describe the run)
+    let header = lines
+        .by_ref()
+        .map(parse_line)
+        .find(|fields| fields.iter().any(|field| field == "epoch"))?;
+    let column = |name: &str| header.iter().position(|field| field == name);
+    let epoch = column("epoch")?;
+    let usr = column("usr")?;
+    let sys = column("sys")?;
+    let recv = column("recv")?;
+    let send = column("send")?;
+    let used = column("used")?;
+
+    let rows = lines
+        .map(parse_line)
+        .filter(|fields| fields.len() >= header.len())
+        .filter_map(|fields| {
+            let value = |index: usize| fields[index].parse::<f64>().ok();
+            // convert epoch to milliseconds
+            let epoch = (value(epoch)? * 1000f64).round() as u64;
+            if epoch < start || epoch > end {
+                return None;
+            }
+            Some(DstatRow {
+                cpu_usr: value(usr)?,
+                cpu_sys: value(sys)?,
+                net_recv: value(recv)?,
+                net_send: value(send)?,
+                mem_used: value(used)?,
+            })
+        })
+        .collect();
+    Some(rows)
+}
@@ -0,0 +1,209 @@
+use color_eyre::eyre::{self, WrapErr};
+use color_eyre::Report;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+/// A parameter sweep: an ordered list of parameters, each with the list of
+/// values it should take. Sweeps are parsed from a spec with one parameter
+/// per line (or separated by `;`), where each parameter is one of:
+/// - `name = value`: a single value
+/// - `name = {v1, v2, v3}` (or `name ∈ {v1, v2, v3}`): a set of values
+/// - `name = [start..end]`: all integers from `start` to `end` (inclusive)
+/// - `name = [start..end step +k]` (or `step k`): every `k`-th integer from
+///   `start` to `end`
+/// - `name = [start..end step xk]`: `start`, `start * k`, `start * k^2`, ...
+///   up to `end`
+///
+/// For example, `clients = [32..65536 step x2]; conflict ∈ {2, 10, 30}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sweep {
+    params: Vec<(String, Vec<String>)>,
+}
+
+impl Sweep {
+    pub fn parse(spec: &str) -> Result<Self, Report> {
+        let mut params: Vec<(String, Vec<String>)> = Vec::new();
+        let lines = spec
+            .split(|c| c == '\n' || c == ';')
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for line in lines {
+            let (name, values) =
+                parse_param(line).wrap_err_with(|| format!("{:?}", line))?;
+            if params.iter().any(|(param, _)| *param == name) {
+                eyre::bail!("parameter {:?} defined more than once", name);
+            }
+            params.push((name, values));
+        }
+        Ok(Self { params })
+    }
+
+    /// Returns the names of the parameters, in the order they were defined.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.params.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the values of parameter `name`, parsed as `T`.
+    pub fn values<T>(&self, name: &str) -> Result<Vec<T>, Report>
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        let values = match self.params.iter().find(|(param, _)| param == name) {
+            Some((_, values)) => values,
+            None => eyre::bail!("parameter {:?} not found", name),
+        };
+        values
+            .iter()
+            .map(|value| {
+                value.parse().map_err(|e| {
+                    eyre::eyre!(
+                        "invalid value {:?} for parameter {:?}: {:?}",
+                        value,
+                        name,
+                        e
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Returns all the combinations of parameter values, each with the value
+    /// of each parameter (in the order they were defined). Combinations are
+    /// generated in a deterministic order: the first parameter changes the
+    /// slowest and the last parameter changes the fastest.
+    pub fn points(&self) -> Vec<Vec<(&str, &str)>> {
+        let mut points = vec![Vec::with_capacity(self.params.len())];
+        for (name, values) in self.params.iter() {
+            points = points
+                .into_iter()
+                .flat_map(|point| {
+                    values.iter().map(move |value| {
+                        let mut point = point.clone();
+                        point.push((name.as_str(), value.as_str()));
+                        point
+                    })
+                })
+                .collect();
+        }
+        points
+    }
+}
+
+fn parse_param(line: &str) -> Result<(String, Vec<String>), Report> {
+    let (name, expr) = if let Some(index) = line.find('=') {
+        (&line[..index], &line[index + '='.len_utf8()..])
+    } else if let Some(index) = line.find('∈') {
+        (&line[..index], &line[index + '∈'.len_utf8()..])
+    } else {
+        eyre::bail!("expected `name = values` or `name ∈ values`");
+    };
+    let name = name.trim();
+    let expr = expr.trim();
+    if name.is_empty() {
+        eyre::bail!("missing parameter name");
+    }
+
+    let values = if let Some(set) = strip_delimiters(expr, '{', '}') {
+        let values: Vec<_> = set
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+            .collect();
+        if values.is_empty() {
+            eyre::bail!("empty set of values");
+        }
+        values
+    } else if let Some(range) = strip_delimiters(expr, '[', ']') {
+        parse_range(range)?
+            .into_iter()
+            .map(|value| value.to_string())
+            .collect()
+    } else if expr.is_empty() {
+        eyre::bail!("missing value");
+    } else {
+        vec![expr.to_string()]
+    };
+    Ok((name.to_string(), values))
+}
+
+fn strip_delimiters(expr: &str, open: char, close: char) -> Option<&str> {
+    if expr.starts_with(open) && expr.ends_with(close) {
+        Some(&expr[open.len_utf8()..expr.len() - close.len_utf8()])
+    } else {
+        None
+    }
+}
+
+fn parse_range(range: &str) -> Result<Vec<u64>, Report> {
+    let (bounds, step) = match range.find("step") {
+        Some(index) => (&range[..index], Some(range[index + 4..].trim())),
+        None => (range, None),
+    };
+    let mut bounds = bounds.splitn(2, "..");
+    let start = parse_number(bounds.next().unwrap_or_default())?;
+    let end = parse_number(
+        bounds
+            .next()
+            .ok_or_else(|| eyre::eyre!("expected `start..end`"))?,
+    )?;
+    if start > end {
+        eyre::bail!("range start {} is higher than its end {}", start, end);
+    }
+
+    let mut values = Vec::new();
+    let mut value = start;
+    match step {
+        Some(step) if step.starts_with('x') => {
+            let factor = parse_number(&step[1..])?;
+            if factor < 2 || start == 0 {
+                eyre::bail!("geometric ranges should start above 0 and have a step of at least x2");
+            }
+            while value <= end {
+                values.push(value);
+                value *= factor;
+            }
+        }
+        step => {
+            let step = match step {
+                Some(step) => parse_number(step.trim_start_matches('+'))?,
+                None => 1,
+            };
+            if step == 0 {
+                eyre::bail!("range step should be above 0");
+            }
+            while value <= end {
+                values.push(value);
+                value += step;
+            }
+        }
+    }
+    Ok(values)
+}
+
+fn parse_number(number: &str) -> Result<u64, Report> {
+    let number = number.trim();
+    number
+        .parse()
+        .wrap_err_with(|| format!("invalid number {:?}", number))
+}
+
+/// Shuffles `items` deterministically given `seed`, i.e. the same seed always
+/// results in the same order.
+pub fn shuffle<T>(items: &mut [T], seed: u64) {
+    // Fisher-Yates shuffle with a splitmix64 generator, so that the order
+    // only depends on the seed
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
@@ -1,67 +1,110 @@
-use super::Nickname;
-use crate::machine::{Machine, Machines};
+use super::{LaunchFuture, MachineProvider, Nickname, TerminateFuture};
+use crate::machine::{BinaryCache, Machine};
 use crate::{FantochFeature, RunMode, Testbed};
 use color_eyre::Report;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use tsunami::providers::aws::AvailabilityZoneSpec;
 use tsunami::Tsunami;
 
-pub async fn setup(
-    launcher: &mut tsunami::providers::aws::Launcher<
+// availability zones (appended to the name of each region) across which servers
+// in the same region are spread, if `ServerPlacement::DistinctZones` is used
+const AVAILABILITY_ZONES: [char; 3] = ['a', 'b', 'c'];
+
+/// How the server instances in the same region (i.e. of different shards) are
+/// placed with respect to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServerPlacement {
+    /// Instances are placed wherever EC2 has capacity, and so servers may end
+    /// up on the same host (and affect each other).
+    Any,
+    /// Each instance is placed in a different availability zone of the region
+    /// (and thus on a distinct host); if there are more servers in the region
+    /// than zones, zones are reused. Note that tsunami only supports cluster
+    /// placement groups, and so spread placement groups can't be used instead.
+    DistinctZones,
+}
+
+/// Provider of AWS spot (or on-demand) instances, launched with tsunami.
+pub struct AwsProvider {
+    launcher: tsunami::providers::aws::Launcher<
         rusoto_credential::DefaultCredentialsProvider,
     >,
     launch_mode: tsunami::providers::aws::LaunchMode,
-    regions: Vec<rusoto_core::Region>,
-    shard_count: usize,
     server_instance_type: String,
     client_instance_type: String,
+    server_placement: ServerPlacement,
     max_spot_instance_request_wait_secs: u64,
     branch: String,
     run_mode: RunMode,
     features: Vec<FantochFeature>,
-) -> Result<Machines<'_>, Report> {
-    // create nicknames for all machines
-    let nicknames = super::create_nicknames(shard_count, &regions);
+    binary_cache: BinaryCache,
+}
 
-    // setup machines
-    let vms = spawn_and_setup(
-        launcher,
-        launch_mode,
-        nicknames,
-        server_instance_type,
-        client_instance_type,
-        max_spot_instance_request_wait_secs,
-        branch,
-        run_mode,
-        features,
-    )
-    .await?;
+impl AwsProvider {
+    pub fn new(
+        launch_mode: tsunami::providers::aws::LaunchMode,
+        server_instance_type: String,
+        client_instance_type: String,
+        server_placement: ServerPlacement,
+        max_spot_instance_request_wait_secs: u64,
+        branch: String,
+        run_mode: RunMode,
+        features: Vec<FantochFeature>,
+        binary_cache: BinaryCache,
+    ) -> Self {
+        Self {
+            launcher: Default::default(),
+            launch_mode,
+            server_instance_type,
+            client_instance_type,
+            server_placement,
+            max_spot_instance_request_wait_secs,
+            branch,
+            run_mode,
+            features,
+            binary_cache,
+        }
+    }
+}
 
-    // create placement, servers, and clients
-    let region_count = regions.len();
-    let process_count = region_count * shard_count;
-    let client_count = region_count;
-    let placement = super::create_placement(shard_count, regions);
-    let mut servers = HashMap::with_capacity(process_count);
-    let mut clients = HashMap::with_capacity(client_count);
+impl MachineProvider for AwsProvider {
+    fn testbed(&self) -> Testbed {
+        Testbed::Aws
+    }
 
-    for (Nickname { shard_id, region }, vm) in vms {
-        let vm = Machine::Tsunami(vm);
-        match shard_id {
-            Some(shard_id) => {
-                let (process_id, _region_index) = placement
-                    .get(&(region, shard_id))
-                    .expect("region and shard id should exist in placement");
-                assert!(servers.insert(*process_id, vm).is_none());
-            }
-            None => {
-                // add to clients
-                assert!(clients.insert(region, vm).is_none());
-            }
-        }
+    fn launch(&mut self, nicknames: Vec<Nickname>) -> LaunchFuture<'_> {
+        Box::pin(async move {
+            let vms = spawn_and_setup(
+                &mut self.launcher,
+                self.launch_mode.clone(),
+                nicknames,
+                self.server_instance_type.clone(),
+                self.client_instance_type.clone(),
+                self.server_placement,
+                self.max_spot_instance_request_wait_secs,
+                self.branch.clone(),
+                self.run_mode,
+                self.features.clone(),
+                self.binary_cache.clone(),
+            )
+            .await?;
+            let vms = vms
+                .into_iter()
+                .map(|(nickname, vm)| (nickname, Machine::new(vm)))
+                .collect();
+            Ok(vms)
+        })
+    }
+
+    fn terminate(&mut self) -> TerminateFuture<'_> {
+        // take the launcher, as terminating it consumes it
+        let launcher = std::mem::take(&mut self.launcher);
+        Box::pin(async move {
+            launcher.terminate_all().await?;
+            Ok(())
+        })
     }
-    let machines = Machines::new(placement, servers, clients);
-    Ok(machines)
 }
 
 async fn spawn_and_setup<'a>(
@@ -72,10 +115,12 @@ async fn spawn_and_setup<'a>(
     nicknames: Vec<Nickname>,
     server_instance_type: String,
     client_instance_type: String,
+    server_placement: ServerPlacement,
     max_spot_instance_request_wait_secs: u64,
     branch: String,
     run_mode: RunMode,
     features: Vec<FantochFeature>,
+    binary_cache: BinaryCache,
 ) -> Result<Vec<(Nickname, tsunami::Machine<'a>)>, Report> {
     // create machine descriptors
     let mut descriptors = Vec::with_capacity(nicknames.len());
@@ -97,16 +142,33 @@ async fn spawn_and_setup<'a>(
             .parse::<rusoto_core::Region>()
             .expect("creating a rusoto_core::Region should work");
 
+        // get availability zone: since there's a single server per shard in
+        // each region, servers are spread across zones by their shard id
+        let availability_zone = match (server_placement, nickname.shard_id) {
+            (ServerPlacement::DistinctZones, Some(shard_id)) => {
+                let zone = AVAILABILITY_ZONES
+                    [shard_id as usize % AVAILABILITY_ZONES.len()];
+                AvailabilityZoneSpec::Specify(format!(
+                    "{}{}",
+                    region.name(),
+                    zone
+                ))
+            }
+            _ => AvailabilityZoneSpec::Any,
+        };
+
         // create setup
         let setup = tsunami::providers::aws::Setup::default()
             .instance_type(instance_type)
             .region_with_ubuntu_ami(region)
             .await?
+            .availability_zone(availability_zone)
             .setup(crate::machine::fantoch_setup(
                 branch.clone(),
                 run_mode,
                 features.clone(),
                 Testbed::Aws,
+                binary_cache.clone(),
             ));
 
         // save setup
@@ -1,114 +1,97 @@
-use super::Nickname;
-use crate::machine::{Machine, Machines};
+use super::{LaunchFuture, MachineProvider, Nickname, TerminateFuture};
+use crate::machine::{BinaryCache, Machine};
 use crate::{FantochFeature, RunMode, Testbed};
 use color_eyre::eyre::WrapErr;
 use color_eyre::Report;
-use std::collections::HashMap;
 
 const MACHINES: &str = "exp_files/machines";
 const PRIVATE_KEY: &str = "~/.ssh/id_rsa";
 
-pub fn create_launchers(
-    regions: &Vec<rusoto_core::Region>,
-    shard_count: usize,
-) -> Vec<tsunami::providers::baremetal::Machine> {
-    let server_count = regions.len();
-    let client_count = regions.len();
-    let machine_count = server_count * shard_count + client_count;
-    // create one launcher per machine
-    (0..machine_count)
-        .map(|_| tsunami::providers::baremetal::Machine::default())
-        .collect()
-}
-
-pub async fn setup<'a>(
-    launcher_per_machine: &'a mut Vec<tsunami::providers::baremetal::Machine>,
-    regions: Vec<rusoto_core::Region>,
-    shard_count: usize,
+/// Provider of the baremetal machines listed in `MACHINES` (one
+/// `username@addr` per line), launched with tsunami.
+pub struct BaremetalProvider {
+    // one launcher per machine
+    launchers: Vec<tsunami::providers::baremetal::Machine>,
     branch: String,
     run_mode: RunMode,
     features: Vec<FantochFeature>,
-) -> Result<Machines<'a>, Report> {
-    let server_count = regions.len();
-    let client_count = regions.len();
-    let machine_count = server_count * shard_count + client_count;
-    assert_eq!(
-        launcher_per_machine.len(),
-        machine_count,
-        "not enough launchers"
-    );
+    binary_cache: BinaryCache,
+}
 
-    // get ips and check that we have enough of them
-    let content = tokio::fs::read_to_string(MACHINES).await?;
-    let machines: Vec<_> = content.lines().take(machine_count).collect();
-    assert_eq!(machines.len(), machine_count, "not enough machines");
-
-    // create nicknames for all machines
-    let nicknames = super::create_nicknames(shard_count, &regions);
-
-    // get machine and launcher iterators
-    let mut machines_iter = machines.into_iter();
-    let mut launcher_iter = launcher_per_machine.iter_mut();
-
-    // setup machines
-    let mut launches = Vec::with_capacity(machine_count);
-    for nickname in nicknames {
-        // find one machine and a launcher for this machine
-        let machine = machines_iter.next().unwrap();
-        let launcher = launcher_iter.next().unwrap();
-
-        // create baremetal setup
-        let setup = baremetal_setup(
-            machine,
-            branch.clone(),
+impl BaremetalProvider {
+    pub fn new(
+        branch: String,
+        run_mode: RunMode,
+        features: Vec<FantochFeature>,
+        binary_cache: BinaryCache,
+    ) -> Self {
+        Self {
+            launchers: Vec::new(),
+            branch,
             run_mode,
-            features.clone(),
-        )
-        .await
-        .wrap_err("baremetal setup")?;
-
-        // save baremetal launch
-        let launch = baremetal_launch(launcher, nickname, setup);
-        launches.push(launch);
+            features,
+            binary_cache,
+        }
     }
+}
 
-    // create placement, servers, and clients
-    let placement = super::create_placement(shard_count, regions);
-    let mut servers = HashMap::with_capacity(server_count);
-    let mut clients = HashMap::with_capacity(client_count);
-
-    for result in futures::future::join_all(launches).await {
-        let vm = result.wrap_err("baremetal launch")?;
-        let Nickname { region, shard_id } = Nickname::from_string(&vm.nickname);
-        let vm = Machine::Tsunami(vm);
-
-        let unique_insert = match shard_id {
-            Some(shard_id) => {
-                // it's a server; find it's process id
-                let (process_id, _region_index) =
-                    placement.get(&(region, shard_id)).expect(
-                        "pair region and shard id should exist in placement",
-                    );
-                servers.insert(*process_id, vm).is_none()
+impl MachineProvider for BaremetalProvider {
+    fn testbed(&self) -> Testbed {
+        Testbed::Baremetal
+    }
+
+    fn launch(&mut self, nicknames: Vec<Nickname>) -> LaunchFuture<'_> {
+        Box::pin(async move {
+            let machine_count = nicknames.len();
+
+            // get ips and check that we have enough of them
+            let content = tokio::fs::read_to_string(MACHINES).await?;
+            let machines: Vec<_> =
+                content.lines().take(machine_count).collect();
+            assert_eq!(machines.len(), machine_count, "not enough machines");
+
+            // create one launcher per machine
+            self.launchers = (0..machine_count)
+                .map(|_| tsunami::providers::baremetal::Machine::default())
+                .collect();
+
+            // setup machines
+            let mut launches = Vec::with_capacity(machine_count);
+            for ((nickname, machine), launcher) in nicknames
+                .into_iter()
+                .zip(machines)
+                .zip(self.launchers.iter_mut())
+            {
+                // create baremetal setup
+                let setup = baremetal_setup(
+                    machine,
+                    self.branch.clone(),
+                    self.run_mode,
+                    self.features.clone(),
+                    self.binary_cache.clone(),
+                )
+                .await
+                .wrap_err("baremetal setup")?;
+
+                // save baremetal launch
+                let launch = baremetal_launch(launcher, nickname, setup);
+                launches.push(launch);
             }
-            None => {
-                // it's a client
-                clients.insert(region, vm).is_none()
+
+            let mut vms = Vec::with_capacity(machine_count);
+            for result in futures::future::join_all(launches).await {
+                let vm = result.wrap_err("baremetal launch")?;
+                let nickname = Nickname::from_string(&vm.nickname);
+                vms.push((nickname, Machine::new(vm)));
             }
-        };
-        assert!(unique_insert);
+            Ok(vms)
+        })
     }
 
-    // check that we have enough machines
-    assert_eq!(
-        servers.len(),
-        server_count * shard_count,
-        "not enough server vms"
-    );
-    assert_eq!(clients.len(), client_count, "not enough client vms");
-
-    let machines = Machines::new(placement, servers, clients);
-    Ok(machines)
+    fn terminate(&mut self) -> TerminateFuture<'_> {
+        // baremetal machines are not terminated
+        Box::pin(async { Ok(()) })
+    }
 }
 
 async fn baremetal_setup(
@@ -116,6 +99,7 @@ async fn baremetal_setup(
     branch: String,
     run_mode: RunMode,
     features: Vec<FantochFeature>,
+    binary_cache: BinaryCache,
 ) -> Result<tsunami::providers::baremetal::Setup, Report> {
     let parts: Vec<_> = machine.split('@').collect();
     assert_eq!(parts.len(), 2, "machine should have the form username@addr");
@@ -163,6 +147,7 @@ async fn baremetal_setup(
                 run_mode,
                 features,
                 Testbed::Baremetal,
+                binary_cache,
             )
         };
 
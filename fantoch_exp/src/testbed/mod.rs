@@ -2,10 +2,16 @@ pub mod aws;
 pub mod baremetal;
 pub mod local;
 
-use crate::config::Placement;
+use crate::config::{ClientMachineIndex, Placement};
+use crate::machine::{Machine, Machines};
+use crate::Testbed;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
 use fantoch::id::{ProcessId, ShardId};
 use fantoch::planet::Region;
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 
 const NICKNAME_SEP: &str = "_";
 const SERVER_TAG: &str = "server";
@@ -13,25 +19,49 @@ const CLIENT_TAG: &str = "client";
 
 pub struct Nickname {
     pub region: Region,
+    // set if it's a server
     pub shard_id: Option<ShardId>,
+    // set if it's a client
+    pub client_index: Option<ClientMachineIndex>,
 }
 
 impl Nickname {
-    pub fn new<S: Into<String>>(region: S, shard_id: Option<ShardId>) -> Self {
+    pub fn server<S: Into<String>>(region: S, shard_id: ShardId) -> Self {
         let region = Region::new(region);
-        Self { region, shard_id }
+        Self {
+            region,
+            shard_id: Some(shard_id),
+            client_index: None,
+        }
+    }
+
+    pub fn client<S: Into<String>>(
+        region: S,
+        client_index: ClientMachineIndex,
+    ) -> Self {
+        let region = Region::new(region);
+        Self {
+            region,
+            shard_id: None,
+            client_index: Some(client_index),
+        }
     }
 
     pub fn to_string(&self) -> String {
-        if let Some(shard_id) = self.shard_id {
-            // if there's a shard, then it's server
-            format!(
+        match (self.shard_id, self.client_index) {
+            (Some(shard_id), None) => format!(
                 "{}{}{:?}{}{}",
                 SERVER_TAG, NICKNAME_SEP, self.region, NICKNAME_SEP, shard_id
-            )
-        } else {
-            // otherwise it is a client
-            format!("{}{}{:?}", CLIENT_TAG, NICKNAME_SEP, self.region)
+            ),
+            (None, Some(client_index)) => format!(
+                "{}{}{:?}{}{}",
+                CLIENT_TAG,
+                NICKNAME_SEP,
+                self.region,
+                NICKNAME_SEP,
+                client_index
+            ),
+            _ => panic!("nickname should either be a server or a client"),
         }
     }
 
@@ -45,12 +75,15 @@ impl Nickname {
                 let shard_id = parts[2]
                     .parse::<ShardId>()
                     .expect("shard id should be a number");
-                Self::new(region, Some(shard_id))
+                Self::server(region, shard_id)
             }
             CLIENT_TAG => {
-                assert_eq!(parts.len(), 2);
+                assert_eq!(parts.len(), 3);
                 let region = parts[1];
-                Self::new(region, None)
+                let client_index = parts[2]
+                    .parse::<ClientMachineIndex>()
+                    .expect("client index should be a number");
+                Self::client(region, client_index)
             }
             tag => {
                 panic!("found unexpected tag {} in nickname", tag);
@@ -59,8 +92,98 @@ impl Nickname {
     }
 }
 
+/// Future returned by `MachineProvider::launch` with the machines launched.
+pub type LaunchFuture<'a> = Pin<
+    Box<dyn Future<Output = Result<Vec<(Nickname, Machine<'a>)>, Report>> + 'a>,
+>;
+
+/// Future returned by `MachineProvider::terminate`.
+pub type TerminateFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(), Report>> + 'a>>;
+
+/// A testbed from which machines can be launched. New testbeds only need to
+/// implement this trait (and `RemoteMachine` for their machines): `setup`
+/// then creates the `Machines` on which experiments are run.
+pub trait MachineProvider {
+    /// Returns the testbed of the machines launched by this provider.
+    fn testbed(&self) -> Testbed;
+
+    /// Launches (and sets up) one machine for each of the `nicknames`.
+    fn launch(&mut self, nicknames: Vec<Nickname>) -> LaunchFuture<'_>;
+
+    /// Terminates all machines launched by this provider.
+    fn terminate(&mut self) -> TerminateFuture<'_>;
+}
+
+/// Launches, using the `provider`, the machines needed to run experiments with
+/// `shard_count` shards in each of the `regions`.
+pub async fn setup<P>(
+    provider: &mut P,
+    regions: Vec<rusoto_core::Region>,
+    shard_count: usize,
+    client_machines_per_region: usize,
+) -> Result<Machines<'_>, Report>
+where
+    P: MachineProvider,
+{
+    let testbed = provider.testbed().name();
+
+    // create nicknames for all machines
+    let nicknames =
+        create_nicknames(shard_count, client_machines_per_region, &regions);
+
+    // launch machines
+    let vms = provider
+        .launch(nicknames)
+        .await
+        .wrap_err_with(|| format!("{} launch", testbed))?;
+
+    // create placement, servers, and clients
+    let region_count = regions.len();
+    let process_count = region_count * shard_count;
+    let client_count = region_count * client_machines_per_region;
+    let placement = create_placement(shard_count, regions);
+    let mut servers = HashMap::with_capacity(process_count);
+    let mut clients = HashMap::with_capacity(client_count);
+
+    for (
+        Nickname {
+            region,
+            shard_id,
+            client_index,
+        },
+        vm,
+    ) in vms
+    {
+        let unique_insert = match (shard_id, client_index) {
+            (Some(shard_id), None) => {
+                // it's a server; find it's process id
+                let (process_id, _region_index) =
+                    placement.get(&(region, shard_id)).expect(
+                        "pair region and shard id should exist in placement",
+                    );
+                servers.insert(*process_id, vm).is_none()
+            }
+            (None, Some(client_index)) => {
+                // it's a client
+                clients.insert((region, client_index), vm).is_none()
+            }
+            _ => panic!("nickname should either be a server or a client"),
+        };
+        assert!(unique_insert);
+    }
+
+    // check that we have enough machines
+    assert_eq!(servers.len(), process_count, "not enough server vms");
+    assert_eq!(clients.len(), client_count, "not enough client vms");
+
+    let machines = Machines::new(placement, servers, clients);
+    Ok(machines)
+}
+
 pub fn create_nicknames(
     shard_count: usize,
+    client_machines_per_region: usize,
     regions: &Vec<rusoto_core::Region>,
 ) -> Vec<Nickname> {
     // create nicknames for all machines
@@ -68,11 +191,13 @@ pub fn create_nicknames(
     for region in regions.iter() {
         // create servers for this region
         for shard_id in 0..shard_count as ShardId {
-            nicknames.push(Nickname::new(region.name(), Some(shard_id)));
+            nicknames.push(Nickname::server(region.name(), shard_id));
         }
 
-        // create client for this region
-        nicknames.push(Nickname::new(region.name(), None));
+        // create clients for this region
+        for client_index in 0..client_machines_per_region {
+            nicknames.push(Nickname::client(region.name(), client_index));
+        }
     }
     nicknames
 }
@@ -0,0 +1,142 @@
+use clap::{App, Arg};
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use fantoch_exp::{ClientsPerRegion, Protocol};
+use fantoch_plot::{ArtifactBundle, ResultsDB, Search};
+
+fn main() -> Result<(), Report> {
+    let (results_dir, searches, plot_dir, specs, output) = parse_args();
+
+    // load results
+    let db = ResultsDB::load(&results_dir).wrap_err("load results")?;
+
+    // add the raw data of all searches, the plots and the specs
+    let mut bundle = ArtifactBundle::new();
+    for search in searches {
+        let matched = bundle.add_search(&db, search)?;
+        println!("{} results matched {:?}", matched, search);
+    }
+    if let Some(plot_dir) = plot_dir {
+        bundle.add_plots(plot_dir).wrap_err("add plots")?;
+    }
+    for spec in specs {
+        bundle.add_spec(spec).wrap_err("add spec")?;
+    }
+
+    // and write it
+    let manifest = bundle.write(&output).wrap_err("write bundle")?;
+    println!("bundled {} files into {}", manifest.files.len(), output);
+    Ok(())
+}
+
+fn parse_args() -> (String, Vec<Search>, Option<String>, Vec<String>, String) {
+    let matches = App::new("bundle")
+        .version("0.1")
+        .author("Vitor Enes <vitorenesduarte@gmail.com>")
+        .about("Bundles the raw data of some searches, the plots generated from them, and the experiment spec into a single archive with a manifest, ready for artifact evaluation.")
+        .arg(
+            Arg::with_name("results_dir")
+                .long("results_dir")
+                .value_name("RESULTS_DIR")
+                .help("directory with the results of the experiments")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("search")
+                .long("search")
+                .value_name("SEARCH")
+                .help("search whose results should be bundled (can be repeated); e.g. 'tempo_atomic,5,1' where 5 is n and 1 is f, optionally followed by parameters such as 'shard_count=2', 'clients_per_region=512', 'keys_per_command=2', 'read_only_percentage=50', 'payload_size=100' and 'batch_max_size=1'")
+                .required(true)
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("plot_dir")
+                .long("plot_dir")
+                .value_name("PLOT_DIR")
+                .help("directory with the plots to be bundled")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("spec")
+                .long("spec")
+                .value_name("SPEC")
+                .help("file with the spec of the experiments to be bundled (can be repeated)")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .value_name("OUTPUT")
+                .help("path of the archive to be created; default: artifact.tar.gz")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let results_dir = matches
+        .value_of("results_dir")
+        .expect("results dir should be set")
+        .to_string();
+    let searches = matches
+        .values_of("search")
+        .expect("search should be set")
+        .map(parse_search)
+        .collect();
+    let plot_dir = matches.value_of("plot_dir").map(String::from);
+    let specs = matches
+        .values_of("spec")
+        .map(|specs| specs.map(String::from).collect())
+        .unwrap_or_default();
+    let output = matches
+        .value_of("output")
+        .unwrap_or("artifact.tar.gz")
+        .to_string();
+    (results_dir, searches, plot_dir, specs, output)
+}
+
+fn parse_search(search: &str) -> Search {
+    let mut parts = search.split(',');
+    let protocol = parse_protocol(parts.next());
+    let n = parse_usize(parts.next(), "n").expect("n should be set");
+    let f = parse_usize(parts.next(), "f").expect("f should be set");
+
+    let mut search = Search::new(n, f, protocol);
+    for parameter in parts {
+        let mut parameter = parameter.splitn(2, '=');
+        let name = parameter.next().expect("parameter should have a name");
+        let value = parse_usize(parameter.next(), name)
+            .unwrap_or_else(|| panic!("{} should have a value", name));
+        match name {
+            "shard_count" => search.shard_count(value),
+            "clients_per_region" => {
+                search.clients_per_region(ClientsPerRegion::uniform(value))
+            }
+            "keys_per_command" => search.keys_per_command(value),
+            "read_only_percentage" => search.read_only_percentage(value),
+            "payload_size" => search.payload_size(value),
+            "batch_max_size" => search.batch_max_size(value),
+            _ => panic!("invalid search parameter: {}", name),
+        };
+    }
+    search
+}
+
+fn parse_usize(value: Option<&str>, name: &str) -> Option<usize> {
+    value.map(|value| {
+        value
+            .parse::<usize>()
+            .unwrap_or_else(|_| panic!("{} should be a number", name))
+    })
+}
+
+fn parse_protocol(protocol: Option<&str>) -> Protocol {
+    let protocol = protocol.expect("protocol should be set");
+    Protocol::all()
+        .into_iter()
+        .find(|candidate| candidate.name() == protocol)
+        .unwrap_or_else(|| panic!("invalid protocol: {}", protocol))
+}
@@ -2,12 +2,14 @@ use color_eyre::eyre::WrapErr;
 use color_eyre::Report;
 use fantoch::client::KeyGen;
 use fantoch::planet::{Planet, Region};
-use fantoch_exp::Protocol;
+use fantoch_exp::{Protocol, SerializationFormat};
 use fantoch_plot::{
-    ErrorBar, ExperimentData, HeatmapMetric, LatencyMetric, LatencyPrecision,
-    MetricsType, PlotFmt, ResultsDB, Search, Style, ThroughputYAxis,
+    ErrorBar, ExperimentData, HeatmapMetric, LatencyAggregation, LatencyMetric,
+    LatencyPrecision, MetricsType, PlotFmt, ResultsDB, Search, Style,
+    ThroughputYAxis,
 };
 use std::collections::HashMap;
+use std::time::Duration;
 
 // latency dir
 // const LATENCY_AWS: &str = "../latency_aws/2021_02_13";
@@ -16,6 +18,16 @@ const LATENCY_AWS: &str = "../latency_aws/2020_06_05";
 // folder where all plots will be stored
 const PLOT_DIR: Option<&str> = Some("plots");
 
+// p99 latency SLO under which the max throughput of each protocol is computed
+const LATENCY_SLO: Duration = Duration::from_millis(500);
+
+// a throughput-latency curve saturates once latency exceeds this many times
+// the latency at the lowest load
+const KNEE_FACTOR: f64 = 2.0;
+
+// how the latencies of each region are aggregated into the global latency
+const LATENCY_AGGREGATION: LatencyAggregation = LatencyAggregation::Commands;
+
 // if true, dstats per process will be generated
 const ALL_DSTATS: bool = true;
 
@@ -24,9 +36,15 @@ fn main() -> Result<(), Report> {
     fantoch_plot::set_global_style()?;
 
     // partial_replication_all()?;
+    // failover_plot()?;
+    // availability_plot()?;
+    // ping_plot()?;
+    // inter_shard_dependencies_plot()?;
+    // key_lock_table()?;
+    // conflict_rate_plot()?;
     // multi_key()?;
     // single_key_all()?;
-    show_distance_matrix();
+    show_distance_matrix()?;
     eurosys()?;
     Ok(())
 }
@@ -50,6 +68,7 @@ fn fairness_plot() -> Result<(), Report> {
     let key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
     let payload_size = 100;
     let protocols = vec![
@@ -101,10 +120,11 @@ fn fairness_plot() -> Result<(), Report> {
     let style_fun = None;
     let latency_precision = LatencyPrecision::Millis;
     let results = fantoch_plot::latency_plot(
-        searches,
+        searches.clone(),
         Some(legend_order),
         style_fun,
         latency_precision,
+        LATENCY_AGGREGATION,
         n,
         error_bar,
         PLOT_DIR,
@@ -120,6 +140,30 @@ fn fairness_plot() -> Result<(), Report> {
             histogram_fmt,
         );
     }
+
+    // generate speedup plot over FPaxos f = 1
+    let baseline = searches
+        .iter()
+        .find(|search| search.protocol == Protocol::FPaxos && search.f == 1)
+        .copied()
+        .expect("there should be a search for FPaxos f = 1");
+    let searches = searches
+        .into_iter()
+        .filter(|search| search.protocol != Protocol::FPaxos)
+        .collect();
+    let path = String::from("plot_fairness_speedup.pdf");
+    let style_fun = None;
+    fantoch_plot::latency_speedup_plot(
+        baseline,
+        searches,
+        None,
+        style_fun,
+        LATENCY_AGGREGATION,
+        n,
+        PLOT_DIR,
+        &path,
+        &db,
+    )?;
     Ok(())
 }
 
@@ -132,6 +176,7 @@ fn tail_latency_plot() -> Result<(), Report> {
     let key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
     let payload_size = 100;
     let protocols = vec![
@@ -197,10 +242,12 @@ fn increasing_load_plot() -> Result<(), Report> {
     let top_key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
     let bottom_key_gen = KeyGen::ConflictPool {
         conflict_rate: 10,
         pool_size: 1,
+        pool_id: 0,
     };
     let payload_size = 4096;
     let batch_max_size = 1;
@@ -319,6 +366,7 @@ fn increasing_load_plot() -> Result<(), Report> {
         search_refine,
         Some(Box::new(style_fun)),
         latency_precision,
+        LATENCY_AGGREGATION,
         x_range,
         y_range,
         y_log_scale,
@@ -343,10 +391,12 @@ fn batching_plot() -> Result<(), Report> {
     let key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
     let empty_key_gen = KeyGen::ConflictPool {
         conflict_rate: 0,
         pool_size: 1,
+        pool_id: 0,
     };
 
     let n = 5;
@@ -439,7 +489,7 @@ fn batching_plot() -> Result<(), Report> {
         let left_margin = None;
         let width_reduction = None;
         let path = format!("plot_batching_{}_{}.pdf", batching, payload_size);
-        let (max_throughputs, _) = fantoch_plot::throughput_latency_plot_split(
+        fantoch_plot::throughput_latency_plot_split(
             n,
             protocols.clone(),
             search_gen,
@@ -449,6 +499,7 @@ fn batching_plot() -> Result<(), Report> {
             search_refine,
             style_fun,
             latency_precision,
+            LATENCY_AGGREGATION,
             x_range,
             y_range,
             y_log_scale,
@@ -460,21 +511,29 @@ fn batching_plot() -> Result<(), Report> {
             &path,
             &db,
         )?;
-        for (search, max_throughput) in max_throughputs {
-            let name = match search.protocol {
-                Protocol::FPaxos => "fpaxos",
-                Protocol::TempoAtomic => "tempo ",
-                _ => unreachable!(),
-            };
-            println!(
-                "R {} f = {} bms = {:<5} ps = {:<4}: {}",
-                name,
-                search.f,
-                search.batch_max_size.unwrap(),
-                search.payload_size.unwrap(),
-                max_throughput
-            );
-        }
+
+        // generate max useful throughput table
+        let path = format!("knee_batching_{}_{}.pdf", batching, payload_size);
+        let searches = protocols
+            .clone()
+            .into_iter()
+            .map(|protocol| {
+                let mut search = search_gen(protocol);
+                search_refine(&mut search, key_gen);
+                search
+            })
+            .collect();
+        fantoch_plot::knee_table(
+            searches,
+            clients_per_region.clone(),
+            LatencyMetric::Average,
+            KNEE_FACTOR,
+            latency_precision,
+            LATENCY_AGGREGATION,
+            PLOT_DIR,
+            &path,
+            &db,
+        )?;
     }
 
     // create searches
@@ -540,6 +599,292 @@ fn scalability_plot() -> Result<(), Report> {
     Ok(())
 }
 
+#[allow(dead_code)]
+fn failover_plot() -> Result<(), Report> {
+    println!(">>>>>>>> FAILOVER <<<<<<<<");
+    let results_dir = "../results_failover";
+    // fixed parameters
+    let key_gen = KeyGen::ConflictPool {
+        conflict_rate: 2,
+        pool_size: 1,
+        pool_id: 0,
+    };
+    let payload_size = 100;
+    let protocols = vec![
+        (Protocol::TempoAtomic, 1),
+        (Protocol::AtlasLocked, 1),
+        (Protocol::FPaxos, 1),
+        (Protocol::TempoAtomic, 2),
+        (Protocol::AtlasLocked, 2),
+        (Protocol::FPaxos, 2),
+    ];
+    let n = 5;
+    let clients_per_region = 512;
+
+    // load results
+    let db = ResultsDB::load(results_dir).wrap_err("load results")?;
+
+    // create searches
+    let searches: Vec<_> = protocols
+        .into_iter()
+        .map(|(protocol, f)| {
+            let mut search = Search::new(n, f, protocol);
+            search
+                .key_gen(key_gen)
+                .clients_per_region(clients_per_region)
+                .payload_size(payload_size);
+            search
+        })
+        .collect();
+
+    // generate failover plot
+    let path = String::from("plot_failover.pdf");
+    let style_fun = None;
+    let results =
+        fantoch_plot::failover_plot(searches, style_fun, PLOT_DIR, &path, &db)?;
+    for (search, result) in results {
+        println!(
+            "{:<7} f = {} | {}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f,
+            result,
+        );
+    }
+    Ok(())
+}
+
+#[allow(dead_code)]
+fn availability_plot() -> Result<(), Report> {
+    println!(">>>>>>>> AVAILABILITY <<<<<<<<");
+    let results_dir = "../results_failover";
+    // fixed parameters
+    let key_gen = KeyGen::ConflictPool {
+        conflict_rate: 2,
+        pool_size: 1,
+        pool_id: 0,
+    };
+    let payload_size = 100;
+    let protocols = vec![
+        (Protocol::TempoAtomic, 1),
+        (Protocol::AtlasLocked, 1),
+        (Protocol::FPaxos, 1),
+        (Protocol::TempoAtomic, 2),
+        (Protocol::AtlasLocked, 2),
+        (Protocol::FPaxos, 2),
+    ];
+    let n = 5;
+    let clients_per_region = 512;
+
+    // load results
+    let db = ResultsDB::load(results_dir).wrap_err("load results")?;
+
+    // create searches
+    let searches: Vec<_> = protocols
+        .into_iter()
+        .map(|(protocol, f)| {
+            let mut search = Search::new(n, f, protocol);
+            search
+                .key_gen(key_gen)
+                .clients_per_region(clients_per_region)
+                .payload_size(payload_size);
+            search
+        })
+        .collect();
+
+    // generate availability plot
+    let path = String::from("plot_availability.pdf");
+    let style_fun = None;
+    let results = fantoch_plot::availability_plot(
+        searches, style_fun, PLOT_DIR, &path, &db,
+    )?;
+    for (search, result) in results {
+        println!(
+            "{:<7} f = {} | {}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f,
+            result,
+        );
+    }
+    Ok(())
+}
+
+#[allow(dead_code)]
+fn ping_plot() -> Result<(), Report> {
+    println!(">>>>>>>> PING <<<<<<<<");
+    let results_dir = "../results_ping";
+    // fixed parameters
+    let key_gen = KeyGen::ConflictPool {
+        conflict_rate: 2,
+        pool_size: 1,
+        pool_id: 0,
+    };
+    let payload_size = 100;
+    let protocols = vec![Protocol::TempoAtomic, Protocol::FPaxos];
+    let n = 5;
+    let f = 1;
+    let clients_per_region = 512;
+
+    // load results
+    let db = ResultsDB::load(results_dir).wrap_err("load results")?;
+
+    // create searches
+    let searches: Vec<_> = protocols
+        .into_iter()
+        .map(|protocol| {
+            let mut search = Search::new(n, f, protocol);
+            search
+                .key_gen(key_gen)
+                .clients_per_region(clients_per_region)
+                .payload_size(payload_size);
+            search
+        })
+        .collect();
+
+    // generate ping plot
+    let path = String::from("plot_ping.pdf");
+    let style_fun = None;
+    fantoch_plot::ping_plot(searches, style_fun, PLOT_DIR, &path, &db)?;
+    Ok(())
+}
+
+#[allow(dead_code)]
+fn inter_shard_dependencies_plot() -> Result<(), Report> {
+    println!(">>>>>>>> INTER-SHARD DEPENDENCIES <<<<<<<<");
+    let results_dir = "../results_partial_replication";
+    // fixed parameters
+    let key_gen = KeyGen::Zipf {
+        coefficient: 0.7,
+        total_keys_per_shard: 1_000_000,
+    };
+    let keys_per_command = 2;
+    let payload_size = 100;
+    let clients_per_region = 1024 * 8;
+    let n = 3;
+    let f = 1;
+    let protocols = vec![Protocol::TempoAtomic, Protocol::AtlasLocked];
+    let shard_counts = vec![1, 2, 4, 6];
+
+    // load results
+    let db = ResultsDB::load(results_dir).wrap_err("load results")?;
+
+    // create searches
+    let searches: Vec<_> = protocols
+        .into_iter()
+        .map(|protocol| {
+            let mut search = Search::new(n, f, protocol);
+            search
+                .key_gen(key_gen)
+                .keys_per_command(keys_per_command)
+                .clients_per_region(clients_per_region)
+                .payload_size(payload_size);
+            search
+        })
+        .collect();
+
+    // generate inter-shard dependencies plots
+    let rtt_path = String::from("plot_inter_shard_request_rtt.pdf");
+    let stalls_path = String::from("plot_inter_shard_stalls.pdf");
+    let style_fun = None;
+    fantoch_plot::inter_shard_dependencies_plot(
+        searches,
+        shard_counts,
+        style_fun,
+        PLOT_DIR,
+        &rtt_path,
+        &stalls_path,
+        &db,
+    )?;
+    Ok(())
+}
+
+#[allow(dead_code)]
+fn key_lock_table() -> Result<(), Report> {
+    println!(">>>>>>>> KEY LOCKS <<<<<<<<");
+    let results_dir = "../results_key_locks";
+    // fixed parameters
+    let key_gen = KeyGen::Zipf {
+        coefficient: 0.7,
+        total_keys_per_shard: 1_000_000,
+    };
+    let payload_size = 100;
+    let clients_per_region = 1024 * 8;
+    let n = 5;
+    let f = 1;
+    let hot_keys = 10;
+    let protocols = vec![
+        Protocol::TempoAtomic,
+        Protocol::TempoLocked,
+        Protocol::AtlasLocked,
+        Protocol::EPaxosLocked,
+    ];
+
+    // load results
+    let db = ResultsDB::load(results_dir).wrap_err("load results")?;
+
+    // create searches
+    let searches: Vec<_> = protocols
+        .into_iter()
+        .map(|protocol| {
+            let mut search = Search::new(n, f, protocol);
+            search
+                .key_gen(key_gen)
+                .clients_per_region(clients_per_region)
+                .payload_size(payload_size);
+            search
+        })
+        .collect();
+
+    // generate key-lock contention table
+    let path = String::from("table_key_locks.pdf");
+    fantoch_plot::key_lock_table(searches, hot_keys, PLOT_DIR, &path, &db)?;
+    Ok(())
+}
+
+#[allow(dead_code)]
+fn conflict_rate_plot() -> Result<(), Report> {
+    println!(">>>>>>>> CONFLICT RATE <<<<<<<<");
+    let results_dir = "../results_conflict_rate";
+    // fixed parameters
+    let n = 5;
+    let f = 1;
+    let payload_size = 100;
+    let conflict_rates = vec![0, 2, 5, 10, 20, 50, 100];
+    let clients_per_region = vec![32, 64, 128, 256, 512, 1024, 1024 * 2];
+    let protocols = vec![
+        Protocol::TempoAtomic,
+        Protocol::AtlasLocked,
+        Protocol::EPaxosLocked,
+    ];
+    let latency_precision = LatencyPrecision::Millis;
+
+    // load results
+    let db = ResultsDB::load(results_dir).wrap_err("load results")?;
+
+    // create searches (without a key generator nor the number of clients, as
+    // the plot aggregates results over both)
+    let searches: Vec<_> = protocols
+        .into_iter()
+        .map(|protocol| {
+            let mut search = Search::new(n, f, protocol);
+            search.payload_size(payload_size);
+            search
+        })
+        .collect();
+
+    // generate latency-vs-conflict-rate plot
+    let path = String::from("plot_conflict_rate_latency.pdf");
+    fantoch_plot::conflict_rate_latency_plot(
+        searches,
+        conflict_rates,
+        clients_per_region,
+        latency_precision,
+        PLOT_DIR,
+        &path,
+        &db,
+    )?;
+    Ok(())
+}
+
 #[allow(dead_code)]
 fn partial_replication_plot() -> Result<(), Report> {
     println!(">>>>>>>> PARTIAL REPLICATION <<<<<<<<");
@@ -674,6 +1019,7 @@ fn partial_replication_plot() -> Result<(), Report> {
             search_refine,
             style_fun,
             latency_precision,
+            LATENCY_AGGREGATION,
             x_range,
             y_range,
             y_log_scale,
@@ -884,6 +1230,7 @@ fn partial_replication_all() -> Result<(), Report> {
                     searches,
                     style_fun,
                     latency_precision,
+                    LATENCY_AGGREGATION,
                     n,
                     clients_per_region.clone(),
                     x_range,
@@ -927,31 +1274,87 @@ fn partial_replication_all() -> Result<(), Report> {
                         })
                         .collect();
                     let style_fun = None;
-                    let max_throughputs =
-                        fantoch_plot::throughput_something_plot(
-                            searches,
-                            style_fun,
-                            latency_precision,
-                            n,
-                            clients_per_region.clone(),
-                            x_range,
-                            y_range,
-                            y_axis,
-                            PLOT_DIR,
-                            &path,
-                            &db,
-                        )?;
-                    println!(
-                        "n = {} | s = {} | k = {} | {} | read_only = {} | max. tputs: {:?}",
+                    fantoch_plot::throughput_something_plot(
+                        searches,
+                        style_fun,
+                        latency_precision,
+                        LATENCY_AGGREGATION,
                         n,
-                        shard_count,
-                        keys_per_command,
-                        key_gen,
-                        read_only_percentage,
-                        max_throughputs
-                    );
+                        clients_per_region.clone(),
+                        x_range,
+                        y_range,
+                        y_axis,
+                        PLOT_DIR,
+                        &path,
+                        &db,
+                    )?;
                 }
 
+                // generate max useful throughput table
+                let path = format!(
+                    "knee_n{}_s{}_k{}_{}_r{}.pdf",
+                    n,
+                    shard_count,
+                    keys_per_command,
+                    key_gen,
+                    read_only_percentage,
+                );
+                let searches = protocol_combinations(n, protocols.clone())
+                    .into_iter()
+                    .map(|(protocol, f)| {
+                        let mut search = Search::new(n, f, protocol);
+                        search
+                            .shard_count(shard_count)
+                            .key_gen(key_gen)
+                            .keys_per_command(keys_per_command)
+                            .payload_size(payload_size);
+                        search_refine(&mut search, read_only_percentage);
+                        search
+                    })
+                    .collect();
+                fantoch_plot::knee_table(
+                    searches,
+                    clients_per_region.clone(),
+                    LatencyMetric::Percentile(0.99),
+                    KNEE_FACTOR,
+                    latency_precision,
+                    LATENCY_AGGREGATION,
+                    PLOT_DIR,
+                    &path,
+                    &db,
+                )?;
+
+                // generate max throughput under latency slo table
+                let path = format!(
+                    "slo_throughput_n{}_s{}_k{}_{}_r{}.pdf",
+                    n,
+                    shard_count,
+                    keys_per_command,
+                    key_gen,
+                    read_only_percentage,
+                );
+                let searches = protocol_combinations(n, protocols.clone())
+                    .into_iter()
+                    .map(|(protocol, f)| {
+                        let mut search = Search::new(n, f, protocol);
+                        search
+                            .shard_count(shard_count)
+                            .key_gen(key_gen)
+                            .keys_per_command(keys_per_command)
+                            .payload_size(payload_size);
+                        search_refine(&mut search, read_only_percentage);
+                        search
+                    })
+                    .collect();
+                fantoch_plot::slo_throughput_table(
+                    searches,
+                    clients_per_region.clone(),
+                    LATENCY_SLO,
+                    PLOT_DIR,
+                    &path,
+                    &db,
+                )?;
+
                 // generate dstat, latency and cdf plots
                 for clients_per_region in clients_per_region.clone() {
                     println!(
@@ -1028,6 +1431,24 @@ fn partial_replication_all() -> Result<(), Report> {
                         )?;
                     }
 
+                    // generate stability lag plots
+                    for search in searches.iter() {
+                        let path = format!(
+                            "stability_lag_{}_f{}_n{}_s{}_k{}_{}_r{}_c{}.pdf",
+                            search.protocol.name(),
+                            search.f,
+                            n,
+                            shard_count,
+                            keys_per_command,
+                            key_gen,
+                            read_only_percentage,
+                            clients_per_region,
+                        );
+                        fantoch_plot::stability_lag_plot(
+                            *search, PLOT_DIR, &path, &db,
+                        )?;
+                    }
+
                     // generate latency plot
                     let mut shown = false;
                     for error_bar in vec![
@@ -1052,6 +1473,7 @@ fn partial_replication_all() -> Result<(), Report> {
                             legend_order,
                             style_fun,
                             latency_precision,
+                            LATENCY_AGGREGATION,
                             n,
                             error_bar,
                             PLOT_DIR,
@@ -1085,10 +1507,12 @@ fn partial_replication_all() -> Result<(), Report> {
                         clients_per_region
                     );
                     let style_fun = None;
+                    let per_region = false;
                     fantoch_plot::cdf_plot(
                         searches.clone(),
                         style_fun,
                         latency_precision,
+                        per_region,
                         PLOT_DIR,
                         &path,
                         &db,
@@ -1166,6 +1590,7 @@ fn multi_key_all() -> Result<(), Report> {
                         searches,
                         style_fun,
                         latency_precision,
+                        LATENCY_AGGREGATION,
                         n,
                         clients_per_region.clone(),
                         x_range,
@@ -1265,6 +1690,7 @@ fn multi_key_all() -> Result<(), Report> {
                             legend_order,
                             style_fun,
                             latency_precision,
+                            LATENCY_AGGREGATION,
                             n,
                             error_bar,
                             PLOT_DIR,
@@ -1293,10 +1719,12 @@ fn multi_key_all() -> Result<(), Report> {
                         cpus, n, keys_per_shard, key_gen, clients_per_region
                     );
                     let style_fun = None;
+                    let per_region = false;
                     fantoch_plot::cdf_plot(
                         searches.clone(),
                         style_fun,
                         latency_precision,
+                        per_region,
                         PLOT_DIR,
                         &path,
                         &db,
@@ -1346,10 +1774,12 @@ fn single_key_all() -> Result<(), Report> {
         KeyGen::ConflictPool {
             conflict_rate: 2,
             pool_size: 1,
+            pool_id: 0,
         },
         KeyGen::ConflictPool {
             conflict_rate: 10,
             pool_size: 1,
+            pool_id: 0,
         },
     ];
     let batch_max_sizes = vec![1, 10000];
@@ -1467,6 +1897,7 @@ fn single_key_all() -> Result<(), Report> {
                             searches.clone(),
                             style_fun,
                             latency_precision,
+                            LATENCY_AGGREGATION,
                             n,
                             clients_per_region.clone(),
                             x_range,
@@ -1482,6 +1913,7 @@ fn single_key_all() -> Result<(), Report> {
                         HeatmapMetric::CPU,
                         HeatmapMetric::NetSend,
                         HeatmapMetric::NetRecv,
+                        HeatmapMetric::SlowPath,
                     ] {
                         let path = format!(
                             "heatmap_{}_n{}_{}_b{}_p{}.pdf",
@@ -1621,6 +2053,7 @@ fn single_key_all() -> Result<(), Report> {
                                 legend_order,
                                 style_fun,
                                 latency_precision,
+                                LATENCY_AGGREGATION,
                                 n,
                                 error_bar,
                                 PLOT_DIR,
@@ -1675,10 +2108,12 @@ fn single_key_all() -> Result<(), Report> {
                             clients_per_region
                         );
                         let style_fun = None;
+                        let per_region = false;
                         fantoch_plot::cdf_plot(
                             searches.clone(),
                             style_fun,
                             latency_precision,
+                            per_region,
                             PLOT_DIR,
                             &path,
                             &db,
@@ -1723,7 +2158,7 @@ fn single_key_all() -> Result<(), Report> {
 }
 
 #[allow(dead_code)]
-fn show_distance_matrix() {
+fn show_distance_matrix() -> Result<(), Report> {
     // show distance matrix
     let planet = Planet::from(LATENCY_AWS);
     let regions = vec![
@@ -1733,7 +2168,23 @@ fn show_distance_matrix() {
         Region::new("ca-central-1"),
         Region::new("sa-east-1"),
     ];
-    println!("{}", planet.distance_matrix(regions).unwrap());
+    let matrix = planet.to_distance_matrix(regions);
+    println!("{}", matrix.to_markdown()?);
+
+    // also save it as json and csv
+    if let Some(plot_dir) = PLOT_DIR {
+        std::fs::create_dir_all(plot_dir).wrap_err("create plot dir")?;
+        let csv = matrix.to_csv()?;
+        fantoch_exp::serialize(
+            matrix,
+            format!("{}/distance_matrix.json", plot_dir),
+            SerializationFormat::Json,
+        )
+        .wrap_err("save distance matrix as json")?;
+        std::fs::write(format!("{}/distance_matrix.csv", plot_dir), csv)
+            .wrap_err("save distance matrix as csv")?;
+    }
+    Ok(())
 }
 
 fn protocol_combinations(
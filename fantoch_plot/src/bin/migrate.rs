@@ -143,6 +143,7 @@ fn main() -> Result<(), Report> {
                         } => KeyGen::ConflictPool {
                             conflict_rate,
                             pool_size,
+                            pool_id: 0,
                         },
                         PreviousKeyGen::Zipf {
                             coefficient,
@@ -201,6 +202,7 @@ fn main() -> Result<(), Report> {
                     config.set_skip_fast_ack(previous.config.skip_fast_ack);
 
                     let exp_config = ExperimentConfig {
+                        schema_version: 0,
                         placement: previous.placement,
                         planet: previous.planet,
                         run_mode: previous.run_mode,
@@ -208,20 +210,39 @@ fn main() -> Result<(), Report> {
                         testbed: previous.testbed,
                         protocol: previous.protocol,
                         config,
-                        clients_per_region: previous.clients_per_region,
+                        clients_per_region: previous.clients_per_region.into(),
+                        client_machines_per_region: 1,
+                        colocation: None,
                         process_tcp_nodelay: previous.process_tcp_nodelay,
+                        process_tcp_send_buffer_size: None,
+                        process_tcp_recv_buffer_size: None,
+                        process_tcp_keepalive: None,
+                        process_tcp_max_segment_size: None,
                         tcp_buffer_size: previous.tcp_buffer_size,
                         tcp_flush_interval: previous.tcp_flush_interval,
                         process_channel_buffer_size: previous
                             .process_channel_buffer_size,
                         cpus: previous.cpus,
+                        ping_interval: None,
+                        co_deployed_with: None,
+                        sanity_failures: Vec::new(),
+                        integrity_discrepancies: Vec::new(),
+                        region_failure: None,
+                        experiment_seed: None,
+                        region_failure_time: None,
+                        run_duration: None,
                         workers: previous.workers,
                         executors: previous.executors,
                         multiplexing: previous.multiplexing,
                         workload,
+                        workload_mix: None,
                         batch_max_size: previous.batch_max_size,
                         batch_max_delay: previous.batch_max_delay,
                         client_tcp_nodelay: previous.client_tcp_nodelay,
+                        client_tcp_send_buffer_size: None,
+                        client_tcp_recv_buffer_size: None,
+                        client_tcp_keepalive: None,
+                        client_tcp_max_segment_size: None,
                         client_channel_buffer_size: previous
                             .client_channel_buffer_size,
                     };
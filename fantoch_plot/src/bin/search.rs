@@ -0,0 +1,274 @@
+use clap::{App, Arg};
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use fantoch::client::KeyGen;
+use fantoch_exp::{ClientsPerRegion, Protocol};
+use fantoch_plot::{ResultsDB, Search};
+use std::collections::BTreeMap;
+
+fn main() -> Result<(), Report> {
+    let (results_dir, search) = parse_args();
+
+    // load results
+    let db = ResultsDB::load(&results_dir).wrap_err("load results")?;
+
+    // list all matching results, with their config and summary stats
+    let matched = db.find(search)?;
+    for (timestamp, exp_config, exp_data) in matched.iter() {
+        println!(">>>>>>>> {} <<<<<<<<", timestamp.path().display());
+        let exp_config = serde_json::to_string_pretty(exp_config)
+            .wrap_err("serialize experiment config")?;
+        println!("{}", exp_config);
+        println!("latency (ms): {:?}", exp_data.global_client_latency);
+        println!(
+            "throughput (ops/s): {:.0}",
+            exp_data.global_client_throughput
+        );
+    }
+    println!("{} results matched {:?}", matched.len(), search);
+
+    // if nothing matched, show why: for each search parameter, the number of
+    // results that were filtered out by it
+    if matched.is_empty() {
+        let mut mismatches = BTreeMap::new();
+        for (_, parameter) in db.find_mismatches(search) {
+            *mismatches.entry(parameter).or_insert(0) += 1;
+        }
+        for (parameter, count) in mismatches {
+            println!("{} results filtered out by {}", count, parameter);
+        }
+    }
+    Ok(())
+}
+
+fn parse_args() -> (String, Search) {
+    let matches = App::new("search")
+        .version("0.1")
+        .author("Vitor Enes <vitorenesduarte@gmail.com>")
+        .about("Lists all experiments in a results directory that match some search parameters.")
+        .arg(
+            Arg::with_name("results_dir")
+                .long("results_dir")
+                .value_name("RESULTS_DIR")
+                .help("directory with the results of the experiments")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("n")
+                .long("n")
+                .value_name("N")
+                .help("number of processes")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("f")
+                .long("f")
+                .value_name("F")
+                .help("number of tolerated faults")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("protocol")
+                .long("protocol")
+                .value_name("PROTOCOL")
+                .help("protocol binary name, e.g. tempo_atomic")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("shard_count")
+                .long("shard_count")
+                .value_name("SHARD_COUNT")
+                .help("number of shards")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("cpus")
+                .long("cpus")
+                .value_name("CPUS")
+                .help("number of cpus used by each process")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("workers")
+                .long("workers")
+                .value_name("WORKERS")
+                .help("number of protocol workers")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("clients_per_region")
+                .long("clients_per_region")
+                .value_name("CLIENTS_PER_REGION")
+                .help("number of clients per region; either a single number (the same in every region) or a comma-separated list with the number of clients in each region (ordered by region index)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("key_gen")
+                .long("key_gen")
+                .value_name("KEY_GEN")
+                .help("representation of a key generator; possible values 'conflict_pool,100,1' where 100 is the conflict rate and 1 the pool size (optionally followed by the id of the pool, e.g. 'conflict_pool,100,1,1'), or 'zipf,1.0,1000000' where 1.0 is the zipf coefficient and 1000000 the number of keys (per shard)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("keys_per_command")
+                .long("keys_per_command")
+                .value_name("KEYS_PER_COMMAND")
+                .help("number of keys accessed by each command")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("read_only_percentage")
+                .long("read_only_percentage")
+                .value_name("READ_ONLY_PERCENTAGE")
+                .help("percentage of read-only commands")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("payload_size")
+                .long("payload_size")
+                .value_name("PAYLOAD_SIZE")
+                .help("size of the command payload")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("batch_max_size")
+                .long("batch_max_size")
+                .value_name("BATCH_MAX_SIZE")
+                .help("maximum size of batches")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let results_dir = matches
+        .value_of("results_dir")
+        .expect("results dir should be set")
+        .to_string();
+    let n = parse_usize(matches.value_of("n"), "n").expect("n should be set");
+    let f = parse_usize(matches.value_of("f"), "f").expect("f should be set");
+    let protocol = parse_protocol(matches.value_of("protocol"));
+
+    // create search
+    let mut search = Search::new(n, f, protocol);
+    if let Some(shard_count) =
+        parse_usize(matches.value_of("shard_count"), "shard_count")
+    {
+        search.shard_count(shard_count);
+    }
+    if let Some(cpus) = parse_usize(matches.value_of("cpus"), "cpus") {
+        search.cpus(cpus);
+    }
+    if let Some(workers) = parse_usize(matches.value_of("workers"), "workers") {
+        search.workers(workers);
+    }
+    if let Some(clients_per_region) =
+        parse_clients_per_region(matches.value_of("clients_per_region"))
+    {
+        search.clients_per_region(clients_per_region);
+    }
+    if let Some(key_gen) = parse_key_gen(matches.value_of("key_gen")) {
+        search.key_gen(key_gen);
+    }
+    if let Some(keys_per_command) =
+        parse_usize(matches.value_of("keys_per_command"), "keys_per_command")
+    {
+        search.keys_per_command(keys_per_command);
+    }
+    if let Some(read_only_percentage) = parse_usize(
+        matches.value_of("read_only_percentage"),
+        "read_only_percentage",
+    ) {
+        search.read_only_percentage(read_only_percentage);
+    }
+    if let Some(payload_size) =
+        parse_usize(matches.value_of("payload_size"), "payload_size")
+    {
+        search.payload_size(payload_size);
+    }
+    if let Some(batch_max_size) =
+        parse_usize(matches.value_of("batch_max_size"), "batch_max_size")
+    {
+        search.batch_max_size(batch_max_size);
+    }
+
+    (results_dir, search)
+}
+
+fn parse_usize(value: Option<&str>, name: &str) -> Option<usize> {
+    value.map(|value| {
+        value
+            .parse::<usize>()
+            .unwrap_or_else(|_| panic!("{} should be a number", name))
+    })
+}
+
+fn parse_clients_per_region(
+    clients_per_region: Option<&str>,
+) -> Option<ClientsPerRegion> {
+    clients_per_region.map(|clients_per_region| {
+        let counts: Vec<_> = clients_per_region
+            .split(',')
+            .map(|count| {
+                parse_usize(Some(count), "clients_per_region")
+                    .expect("clients_per_region should be set")
+            })
+            .collect();
+        if counts.len() == 1 {
+            ClientsPerRegion::uniform(counts[0])
+        } else {
+            ClientsPerRegion::per_region(counts)
+        }
+    })
+}
+
+fn parse_protocol(protocol: Option<&str>) -> Protocol {
+    let protocol = protocol.expect("protocol should be set");
+    Protocol::all()
+        .into_iter()
+        .find(|candidate| candidate.name() == protocol)
+        .unwrap_or_else(|| panic!("invalid protocol: {}", protocol))
+}
+
+fn parse_key_gen(key_gen: Option<&str>) -> Option<KeyGen> {
+    key_gen.map(|key_gen| {
+        let parts: Vec<_> = key_gen.split(',').collect();
+        if parts.len() != 3 && !(parts.len() == 4 && parts[0] == "conflict_pool")
+        {
+            panic!("invalid specification of key generator: {:?}", key_gen);
+        }
+        match parts[0] {
+            "conflict_pool" => {
+                let conflict_rate = parts[1]
+                    .parse::<usize>()
+                    .expect("conflict rate should be a number");
+                let pool_size = parts[2]
+                    .parse::<usize>()
+                    .expect("pool size should be a number");
+                let pool_id = parts.get(3).map_or(0, |pool_id| {
+                    pool_id.parse::<usize>().expect("pool id should be a number")
+                });
+                KeyGen::ConflictPool {
+                    conflict_rate,
+                    pool_size,
+                    pool_id,
+                }
+            }
+            "zipf" => {
+                let coefficient = parts[1]
+                    .parse::<f64>()
+                    .expect("zipf coefficient should be a float");
+                let total_keys_per_shard = parts[2]
+                    .parse::<usize>()
+                    .expect("number of keys (per shard) in the zipf distribution should be a number");
+                KeyGen::Zipf {
+                    coefficient,
+                    total_keys_per_shard,
+                }
+            }
+            kgen => panic!("invalid key generator type: {}", kgen),
+        }
+    })
+}
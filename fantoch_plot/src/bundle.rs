@@ -0,0 +1,311 @@
+use crate::{ResultsDB, Search};
+use color_eyre::eyre::{self, WrapErr};
+use color_eyre::Report;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// name of the manifest inside the archive
+const MANIFEST: &str = "MANIFEST.json";
+
+// folders (inside the archive) with the raw results of each experiment, the
+// plots and the experiment specs
+const RESULTS_DIR: &str = "results";
+const PLOTS_DIR: &str = "plots";
+const SPEC_DIR: &str = "spec";
+
+/// Description of the contents of an artifact bundle, stored in it as
+/// `MANIFEST.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub environment: Environment,
+    pub searches: Vec<BundledSearch>,
+    pub files: Vec<BundledFile>,
+}
+
+/// Metadata about the environment in which the bundle was created.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    /// time at which the bundle was created (unix time, in seconds)
+    pub created_at: u64,
+    pub fantoch_version: String,
+    /// commit of the repository from where the bundle was created (if it was
+    /// created inside one)
+    pub git_commit: Option<String>,
+    pub os: String,
+    pub arch: String,
+}
+
+/// A search included in the bundle, along with the experiments it matched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledSearch {
+    pub search: String,
+    pub experiments: Vec<String>,
+}
+
+/// A file included in the bundle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledFile {
+    /// path of the file inside the archive
+    pub path: String,
+    /// size of the file (in bytes)
+    pub size: u64,
+    /// hex-encoded SHA-256 digest of the file
+    pub sha256: String,
+}
+
+/// Bundles the raw data of some searches, the plots generated from them and
+/// the spec of the experiments into a single archive (a gzipped tarball) with
+/// a manifest, ready to be uploaded for artifact evaluation.
+#[derive(Default)]
+pub struct ArtifactBundle {
+    searches: Vec<BundledSearch>,
+    // path of each file inside the archive, along with its path on disk
+    files: Vec<(String, PathBuf)>,
+}
+
+impl ArtifactBundle {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds the raw data of all experiments in `db` matching `search`,
+    /// returning the number of experiments matched.
+    pub fn add_search(
+        &mut self,
+        db: &ResultsDB,
+        search: Search,
+    ) -> Result<usize, Report> {
+        let mut experiments = Vec::new();
+        for (timestamp, _, _) in db.find(search)? {
+            let name = timestamp.file_name().to_string_lossy().to_string();
+            // the same experiment may be matched by more than one search
+            if !self.contains_experiment(&name) {
+                let prefix = format!("{}/{}", RESULTS_DIR, name);
+                self.add_dir(&prefix, &timestamp.path())?;
+            }
+            experiments.push(name);
+        }
+        let matched = experiments.len();
+        self.searches.push(BundledSearch {
+            search: format!("{:?}", search),
+            experiments,
+        });
+        Ok(matched)
+    }
+
+    /// Adds all the plots in `plot_dir`.
+    pub fn add_plots(
+        &mut self,
+        plot_dir: impl AsRef<Path>,
+    ) -> Result<(), Report> {
+        self.add_dir(PLOTS_DIR, plot_dir.as_ref())
+    }
+
+    /// Adds the spec of the experiments, i.e. the file describing how they
+    /// were run (e.g. `fantoch_exp`'s main).
+    pub fn add_spec(&mut self, spec: impl AsRef<Path>) -> Result<(), Report> {
+        let spec = spec.as_ref();
+        let name = spec
+            .file_name()
+            .ok_or_else(|| eyre::eyre!("invalid spec file: {:?}", spec))?
+            .to_string_lossy();
+        self.add_file(format!("{}/{}", SPEC_DIR, name), spec.to_path_buf())
+    }
+
+    /// Writes the bundle to `output`, returning its manifest.
+    pub fn write(self, output: impl AsRef<Path>) -> Result<Manifest, Report> {
+        // compute the manifest
+        let mut files = Vec::with_capacity(self.files.len());
+        for (path, source) in self.files.iter() {
+            let (size, sha256) = digest(source)?;
+            files.push(BundledFile {
+                path: path.clone(),
+                size,
+                sha256,
+            });
+        }
+        let manifest = Manifest {
+            environment: Environment::current(),
+            searches: self.searches,
+            files,
+        };
+        let manifest_json = serde_json::to_vec_pretty(&manifest)
+            .wrap_err("serialize manifest")?;
+
+        // create the archive, starting with the manifest
+        let output = output.as_ref();
+        let file = File::create(output)
+            .wrap_err_with(|| format!("create bundle {:?}", output))?;
+        let encoder =
+            flate2::write::GzEncoder::new(file, flate2::Compression::best());
+        let mut archive = tar::Builder::new(encoder);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_json.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.environment.created_at);
+        header.set_cksum();
+        archive
+            .append_data(&mut header, MANIFEST, manifest_json.as_slice())
+            .wrap_err("add manifest to bundle")?;
+        for (path, source) in self.files.iter() {
+            archive
+                .append_path_with_name(source, path)
+                .wrap_err_with(|| format!("add {:?} to bundle", source))?;
+        }
+        archive
+            .into_inner()
+            .wrap_err("finish bundle")?
+            .finish()
+            .wrap_err("compress bundle")?;
+        Ok(manifest)
+    }
+
+    fn contains_experiment(&self, name: &str) -> bool {
+        self.searches
+            .iter()
+            .any(|search| search.experiments.iter().any(|exp| exp == name))
+    }
+
+    // Adds all files in `dir` (recursively), under `prefix`.
+    fn add_dir(&mut self, prefix: &str, dir: &Path) -> Result<(), Report> {
+        let mut entries = std::fs::read_dir(dir)
+            .wrap_err_with(|| format!("read directory {:?}", dir))?
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err_with(|| format!("read directory {:?}", dir))?;
+        // sort entries so that bundles are deterministic
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path =
+                format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+            if entry.path().is_dir() {
+                self.add_dir(&path, &entry.path())?;
+            } else {
+                self.add_file(path, entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_file(
+        &mut self,
+        path: String,
+        source: PathBuf,
+    ) -> Result<(), Report> {
+        if self.files.iter().any(|(existing, _)| *existing == path) {
+            eyre::bail!("{:?} added to bundle more than once", path);
+        }
+        self.files.push((path, source));
+        Ok(())
+    }
+}
+
+impl Environment {
+    fn current() -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("we're way past epoch")
+            .as_secs();
+        let git_commit = std::process::Command::new("git")
+            .args(&["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout).trim().to_string()
+            });
+        Self {
+            created_at,
+            fantoch_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit,
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+        }
+    }
+}
+
+// Computes the size and the hex-encoded SHA-256 digest of some file.
+fn digest(path: &Path) -> Result<(u64, String), Report> {
+    let file = File::open(path).wrap_err_with(|| format!("open {:?}", path))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 8192];
+    let mut size = 0;
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .wrap_err_with(|| format!("read {:?}", path))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn bundle() {
+        // create some plots and a spec
+        let dir = std::env::temp_dir()
+            .join(format!("fantoch_plot_bundle_{}", std::process::id()));
+        let plot_dir = dir.join("plots");
+        std::fs::create_dir_all(plot_dir.join("dstat")).unwrap();
+        std::fs::write(plot_dir.join("latency.pdf"), "latency").unwrap();
+        std::fs::write(plot_dir.join("dstat").join("cpu.pdf"), "cpu").unwrap();
+        let spec = dir.join("main.rs");
+        std::fs::write(&spec, "fn main() {}").unwrap();
+
+        let mut bundle = ArtifactBundle::new();
+        bundle.add_plots(&plot_dir).unwrap();
+        bundle.add_spec(&spec).unwrap();
+        // files can't be added twice
+        assert!(bundle.add_spec(&spec).is_err());
+        let output = dir.join("bundle.tar.gz");
+        let manifest = bundle.write(&output).unwrap();
+
+        // check the manifest
+        let paths: Vec<_> = manifest
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["plots/dstat/cpu.pdf", "plots/latency.pdf", "spec/main.rs"]
+        );
+        assert_eq!(manifest.files[0].size, 3);
+        assert_eq!(
+            manifest.files[2].sha256,
+            format!("{:x}", Sha256::digest(b"fn main() {}"))
+        );
+
+        // check the archive: it has the manifest and all files
+        let file = File::open(&output).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        let mut contents = HashMap::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().display().to_string();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            contents.insert(path, content);
+        }
+        assert_eq!(contents.len(), 4);
+        let archived: Manifest =
+            serde_json::from_str(&contents[MANIFEST]).unwrap();
+        assert_eq!(archived, manifest);
+        assert_eq!(contents["plots/dstat/cpu.pdf"], "cpu");
+        assert_eq!(contents["spec/main.rs"], "fn main() {}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,15 +1,68 @@
-use crate::db::{Dstat, DstatCompress, MicrosHistogramCompress};
+use crate::db::{Dstat, DstatCompress, MicrosHistogramCompress, Ping};
 use fantoch::client::ClientData;
 use fantoch::executor::ExecutorMetrics;
 use fantoch::id::ProcessId;
+use fantoch::kvs::Key;
 use fantoch::metrics::Histogram;
 use fantoch::planet::Region;
 use fantoch::protocol::ProtocolMetrics;
 use fantoch::run::task::server::metrics_logger::ProcessMetrics;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
+// a client machine is considered saturated if its average CPU utilization (%)
+// is above `CLIENT_CPU_SATURATION` or if its average network traffic (B/s) is
+// above `CLIENT_NET_SATURATION` (90% of 10GBit)
+const CLIENT_CPU_SATURATION: f64 = 90f64;
+const CLIENT_NET_SATURATION: f64 = 0.9 * 10_000_000_000f64 / 8f64;
+
+// number of most accessed keys kept from the clients' data
+const CLIENT_TOP_KEYS: usize = 10;
+
+// total number of occurrences in the histograms created by weighting the
+// latencies of each region (see `ExperimentData::weighted_merge`); it's large
+// enough so that rounding the occurrences of each latency is negligible
+const WEIGHTED_HISTOGRAM_COUNT: f64 = 1_000_000_000f64;
+
+/// How the latencies observed by the clients in each region are aggregated
+/// into a global latency.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+pub enum LatencyAggregation {
+    // each command weighs the same, and thus regions that complete more
+    // commands weigh more
+    Commands,
+    // each region weighs proportionally to its number of clients
+    Clients,
+    // each region weighs the same (i.e. the fairness view)
+    Regions,
+}
+
+impl LatencyAggregation {
+    pub fn all() -> impl Iterator<Item = Self> {
+        vec![Self::Commands, Self::Clients, Self::Regions].into_iter()
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Commands => String::from("per-command"),
+            Self::Clients => String::from("per-client"),
+            Self::Regions => String::from("per-region"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExperimentData {
     pub process_metrics: HashMap<ProcessId, (Region, ProcessMetrics)>,
@@ -19,9 +72,50 @@ pub struct ExperimentData {
     pub global_process_dstats: DstatCompress,
     pub global_client_dstats: DstatCompress,
     pub client_latency: HashMap<Region, MicrosHistogramCompress>,
+    // number of clients and of commands completed in each region
+    pub client_count: HashMap<Region, usize>,
+    pub client_commands: HashMap<Region, usize>,
+    // global latency aggregated with `LatencyAggregation::Commands`
     pub global_client_latency: MicrosHistogramCompress,
+    // latency corrected for coordinated omission, i.e. measured from the time
+    // each command was intended to start (only if clients are open-loop)
+    pub global_client_corrected_latency: Option<MicrosHistogramCompress>,
+    // global latency (and the one corrected for coordinated omission)
+    // aggregated with the remaining aggregations; `LatencyAggregation::Clients`
+    // is missing if the number of clients in some region is unknown
+    pub global_client_weighted_latency: HashMap<
+        LatencyAggregation,
+        (MicrosHistogramCompress, Option<MicrosHistogramCompress>),
+    >,
     pub client_throughput: HashMap<Region, f64>,
     pub global_client_throughput: f64,
+    // mapping from each second (unix time, sorted) to the number of commands
+    // completed by all clients in that second
+    pub global_client_throughput_timeline: Vec<(u64, usize)>,
+    // mapping from each second (unix time, sorted) to the fraction of the
+    // commands that completed (and didn't time out) in that second, among the
+    // ones that completed or timed out (commands only time out at clients if a
+    // command timeout is set; see `RegionFailure::command_timeout`)
+    pub global_client_availability_timeline: Vec<(u64, f64)>,
+    // mapping from number of keys accessed by a command to the number of
+    // commands issued (by all clients) that access that many keys
+    pub global_client_keys_per_command: BTreeMap<usize, u64>,
+    // most accessed keys (sorted) along with the number of times they were
+    // accessed by the commands issued (by all clients), and the Zipf
+    // coefficient estimated from them
+    pub global_client_top_keys: Vec<(Key, u64)>,
+    pub global_client_zipf_coefficient: Option<f64>,
+    // time it took each client to connect to all the processes
+    pub global_client_connect_latency: MicrosHistogramCompress,
+    // mapping from each region to the time it took until all its clients were
+    // connected (if known)
+    pub client_setup_time: HashMap<Region, Duration>,
+    // regions (sorted by name) whose client machine was saturated
+    pub saturated_client_regions: Vec<Region>,
+    // mapping from each second (unix time, sorted) to the average RTT (in
+    // millis) of the pings between all machines in that second (empty if the
+    // ping mesh was not run)
+    pub global_ping_timeline: Vec<(u64, f64)>,
 }
 
 impl ExperimentData {
@@ -31,6 +125,7 @@ impl ExperimentData {
         client_metrics: HashMap<Region, ClientData>,
         client_dstats: HashMap<Region, Dstat>,
         global_client_metrics: ClientData,
+        global_ping: Ping,
     ) -> Self {
         // create global protocol and executor metrics
         let mut global_protocol_metrics = ProtocolMetrics::new();
@@ -55,17 +150,29 @@ impl ExperimentData {
         // compress global process dstat
         let global_process_dstats = DstatCompress::from(&global_process_dstats);
 
-        // merge all client dstats
+        // merge all client dstats (and also detect saturated client machines)
         let mut global_client_dstats = Dstat::new();
-        for (_, client_dstat) in client_dstats {
+        let mut saturated_client_regions = Vec::new();
+        for (region, client_dstat) in client_dstats {
+            if Self::saturated(&client_dstat) {
+                saturated_client_regions.push(region);
+            }
             global_client_dstats.merge(&client_dstat);
         }
+        saturated_client_regions.sort();
         // compress global client dstat
         let global_client_dstats = DstatCompress::from(&global_client_dstats);
 
-        // create latency histogram per region (and also compute throughput)
+        // create latency histogram per region (and also compute throughput,
+        // setup time and the number of clients and commands); the histograms
+        // are kept so that they can be weighted into global histograms
         let mut client_throughput =
             HashMap::with_capacity(client_metrics.len());
+        let mut client_setup_time =
+            HashMap::with_capacity(client_metrics.len());
+        let mut client_count = HashMap::with_capacity(client_metrics.len());
+        let mut client_commands = HashMap::with_capacity(client_metrics.len());
+        let mut region_histograms = Vec::with_capacity(client_metrics.len());
         let client_latency = client_metrics
             .into_iter()
             .map(|(region, client_data)| {
@@ -73,23 +180,93 @@ impl ExperimentData {
                 let throughput = client_data.throughput();
                 client_throughput.insert(region.clone(), throughput);
 
-                // create latency histogram
+                // save setup time
+                if let Some(setup_time) = client_data.setup_time() {
+                    client_setup_time.insert(region.clone(), setup_time);
+                }
+
+                // each client records its connection once
+                client_count.insert(
+                    region.clone(),
+                    client_data.connect_latency_data().count(),
+                );
+
+                // create latency histograms
                 let latency = Self::extract_micros(client_data.latency_data());
                 let histogram = Histogram::from(latency);
+                let corrected = Histogram::from(Self::extract_micros(
+                    client_data.corrected_latency_data(),
+                ));
+                client_commands.insert(region.clone(), histogram.count());
                 // compress client histogram
-                let histogram = MicrosHistogramCompress::from(&histogram);
-                (region, histogram)
+                let compressed = MicrosHistogramCompress::from(&histogram);
+                region_histograms.push((region.clone(), histogram, corrected));
+                (region, compressed)
+            })
+            .collect();
+
+        // create the global latency histograms of the remaining aggregations
+        let global_client_weighted_latency = LatencyAggregation::all()
+            .filter(|aggregation| *aggregation != LatencyAggregation::Commands)
+            .filter_map(|aggregation| {
+                let mut weights = Vec::with_capacity(region_histograms.len());
+                for (region, _, _) in region_histograms.iter() {
+                    let weight = match aggregation {
+                        LatencyAggregation::Commands => unreachable!(),
+                        LatencyAggregation::Clients => client_count[region],
+                        LatencyAggregation::Regions => 1,
+                    };
+                    if weight == 0 {
+                        // the number of clients is unknown
+                        return None;
+                    }
+                    weights.push(weight as f64);
+                }
+                let latency = Self::weighted_merge(
+                    region_histograms.iter().map(|(_, histogram, _)| histogram),
+                    &weights,
+                );
+                let latency = MicrosHistogramCompress::from(&latency);
+                let corrected = Self::weighted_merge(
+                    region_histograms.iter().map(|(_, _, corrected)| corrected),
+                    &weights,
+                );
+                let corrected = if corrected.count() > 0 {
+                    Some(MicrosHistogramCompress::from(&corrected))
+                } else {
+                    None
+                };
+                Some((aggregation, (latency, corrected)))
             })
             .collect();
 
         // create global latency histogram (and also compute throughput)
         let global_client_throughput = global_client_metrics.throughput();
+        let global_client_throughput_timeline =
+            Self::throughput_timeline(&global_client_metrics);
+        let global_client_availability_timeline =
+            Self::availability_timeline(&global_client_metrics);
+        let global_client_keys_per_command =
+            global_client_metrics.key_count_data().collect();
+        let global_client_top_keys =
+            global_client_metrics.key_skew().top_k(CLIENT_TOP_KEYS);
+        let global_client_zipf_coefficient =
+            global_client_metrics.key_skew().zipf_coefficient();
+        let connect_latency =
+            Self::extract_micros(global_client_metrics.connect_latency_data());
+        let global_client_connect_latency =
+            MicrosHistogramCompress::from(&Histogram::from(connect_latency));
         let latency =
             Self::extract_micros(global_client_metrics.latency_data());
         let global_client_latency = Histogram::from(latency);
         // compress global client histogram
         let global_client_latency =
             MicrosHistogramCompress::from(&global_client_latency);
+        let global_client_corrected_latency =
+            Self::corrected_latency(&global_client_metrics);
+
+        // compute the ping timeline
+        let global_ping_timeline = global_ping.timeline();
 
         Self {
             process_metrics,
@@ -98,13 +275,183 @@ impl ExperimentData {
             process_dstats,
             global_process_dstats,
             client_latency,
+            client_count,
+            client_commands,
             global_client_dstats,
             global_client_latency,
+            global_client_corrected_latency,
+            global_client_weighted_latency,
             client_throughput,
             global_client_throughput,
+            global_client_throughput_timeline,
+            global_client_availability_timeline,
+            global_client_keys_per_command,
+            global_client_top_keys,
+            global_client_zipf_coefficient,
+            global_client_connect_latency,
+            client_setup_time,
+            saturated_client_regions,
+            global_ping_timeline,
         }
     }
 
+    /// Returns true if some client machine was saturated, in which case the
+    /// throughput and latency observed by clients is not to be trusted.
+    pub fn clients_saturated(&self) -> bool {
+        !self.saturated_client_regions.is_empty()
+    }
+
+    /// Returns the number of keys accessed by the commands issued by clients
+    /// that differ from `keys_per_command`, i.e. the ones not intended by the
+    /// workload.
+    pub fn unexpected_keys_per_command(
+        &self,
+        keys_per_command: usize,
+    ) -> Vec<usize> {
+        self.global_client_keys_per_command
+            .keys()
+            .filter(|key_count| **key_count != keys_per_command)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the Zipf coefficient estimated from the keys accessed by the
+    /// commands issued by clients if it differs from `coefficient` (i.e. the
+    /// one intended by the workload) by more than `tolerance`.
+    pub fn unexpected_zipf_coefficient(
+        &self,
+        coefficient: f64,
+        tolerance: f64,
+    ) -> Option<f64> {
+        self.global_client_zipf_coefficient
+            .filter(|estimated| (estimated - coefficient).abs() > tolerance)
+    }
+
+    /// Returns the client latency corrected for coordinated omission if
+    /// available (i.e. if clients are open-loop), or the latency observed by
+    /// clients otherwise. Tail latencies should be taken from this histogram,
+    /// as open-loop clients that fall behind their schedule otherwise hide
+    /// the delays that caused them to fall behind.
+    pub fn global_client_tail_latency(&self) -> &MicrosHistogramCompress {
+        self.global_client_corrected_latency
+            .as_ref()
+            .unwrap_or(&self.global_client_latency)
+    }
+
+    /// Returns the global client latency aggregated with `aggregation`.
+    pub fn global_client_latency_with(
+        &self,
+        aggregation: LatencyAggregation,
+    ) -> &MicrosHistogramCompress {
+        match aggregation {
+            LatencyAggregation::Commands => &self.global_client_latency,
+            _ => &self.weighted_latency(aggregation).0,
+        }
+    }
+
+    /// Same as `global_client_tail_latency`, but with the latency aggregated
+    /// with `aggregation`.
+    pub fn global_client_tail_latency_with(
+        &self,
+        aggregation: LatencyAggregation,
+    ) -> &MicrosHistogramCompress {
+        match aggregation {
+            LatencyAggregation::Commands => self.global_client_tail_latency(),
+            _ => {
+                let (latency, corrected) = self.weighted_latency(aggregation);
+                corrected.as_ref().unwrap_or(latency)
+            }
+        }
+    }
+
+    fn weighted_latency(
+        &self,
+        aggregation: LatencyAggregation,
+    ) -> &(MicrosHistogramCompress, Option<MicrosHistogramCompress>) {
+        self.global_client_weighted_latency
+            .get(&aggregation)
+            .unwrap_or_else(|| {
+                panic!(
+                    "{} latency is missing as the number of clients in some region is unknown",
+                    aggregation.name()
+                )
+            })
+    }
+
+    // Merges the histograms of each region so that the occurrences of each
+    // region in the resulting histogram are proportional to its weight.
+    fn weighted_merge<'a>(
+        histograms: impl Iterator<Item = &'a Histogram>,
+        weights: &[f64],
+    ) -> Histogram {
+        let histograms: Vec<_> = histograms.collect();
+        // only the regions with some latency count
+        let total_weight: f64 = histograms
+            .iter()
+            .zip(weights)
+            .filter(|(histogram, _)| histogram.count() > 0)
+            .map(|(_, weight)| weight)
+            .sum();
+        let mut merged = BTreeMap::new();
+        for (histogram, weight) in histograms.into_iter().zip(weights) {
+            let count = histogram.count();
+            if count == 0 {
+                continue;
+            }
+            let scale =
+                WEIGHTED_HISTOGRAM_COUNT * weight / total_weight / count as f64;
+            for (value, occurrences) in histogram.inner() {
+                let occurrences =
+                    (*occurrences as f64 * scale).round() as usize;
+                *merged.entry(*value).or_default() += occurrences;
+            }
+        }
+        Histogram::from_occurrences(merged)
+    }
+
+    // Computes the histogram of latencies corrected for coordinated omission,
+    // if any were recorded.
+    fn corrected_latency(
+        client_data: &ClientData,
+    ) -> Option<MicrosHistogramCompress> {
+        let mut latency =
+            Self::extract_micros(client_data.corrected_latency_data())
+                .peekable();
+        latency.peek()?;
+        let histogram = Histogram::from(latency);
+        Some(MicrosHistogramCompress::from(&histogram))
+    }
+
+    fn saturated(dstat: &Dstat) -> bool {
+        let cpu = dstat.cpu_usr.mean().value() + dstat.cpu_sys.mean().value();
+        let net_recv = dstat.net_recv.mean().value();
+        let net_send = dstat.net_send.mean().value();
+        cpu > CLIENT_CPU_SATURATION
+            || net_recv > CLIENT_NET_SATURATION
+            || net_send > CLIENT_NET_SATURATION
+    }
+
+    fn throughput_timeline(client_data: &ClientData) -> Vec<(u64, usize)> {
+        let mut seconds_to_ops = BTreeMap::new();
+        for (time_millis, ops) in client_data.throughput_data() {
+            *seconds_to_ops.entry(time_millis / 1000).or_default() += ops;
+        }
+        seconds_to_ops.into_iter().collect()
+    }
+
+    fn availability_timeline(client_data: &ClientData) -> Vec<(u64, f64)> {
+        let availability: BTreeMap<_, _> =
+            client_data.availability_data().collect();
+        availability
+            .into_iter()
+            .map(|(second, (successes, timeouts))| {
+                let availability =
+                    successes as f64 / (successes + timeouts) as f64;
+                (second, availability)
+            })
+            .collect()
+    }
+
     fn extract_micros(
         latency_data: impl Iterator<Item = Duration>,
     ) -> impl Iterator<Item = u64> {
@@ -0,0 +1,337 @@
+use color_eyre::eyre::{self, WrapErr};
+use color_eyre::Report;
+use fantoch_exp::config::EXPERIMENT_CONFIG_SCHEMA_VERSION;
+use fantoch_exp::ExperimentConfig;
+use serde_json::{Map, Value};
+
+const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+// a migration shim takes an experiment config (as json) with schema version
+// `v` and updates it to schema version `v + 1`
+type Migration = fn(&mut Map<String, Value>) -> Result<(), Report>;
+
+// migration shims indexed by the schema version they migrate from
+const MIGRATIONS: &[Migration] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+    migrate_v8_to_v9,
+    migrate_v9_to_v10,
+];
+
+/// Migrates an experiment config (as json) to the current schema version.
+/// Returns the migrated experiment config along with the schema version it was
+/// stored with.
+pub fn migrate_exp_config(
+    exp_config: Value,
+) -> Result<(ExperimentConfig, u32), Report> {
+    let mut exp_config = match exp_config {
+        Value::Object(exp_config) => exp_config,
+        _ => eyre::bail!("experiment config should be a json object"),
+    };
+
+    // find the schema version of this experiment config
+    let version = match exp_config.get(SCHEMA_VERSION_FIELD) {
+        // results from before versioning have no version
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| eyre::eyre!("invalid schema version {}", version))?
+            as u32,
+    };
+    if version > EXPERIMENT_CONFIG_SCHEMA_VERSION {
+        eyre::bail!(
+            "schema version {} is newer than the supported schema version {}",
+            version,
+            EXPERIMENT_CONFIG_SCHEMA_VERSION
+        );
+    }
+
+    // apply all migrations from this schema version to the current one
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut exp_config)?;
+    }
+    exp_config.insert(
+        SCHEMA_VERSION_FIELD.to_string(),
+        EXPERIMENT_CONFIG_SCHEMA_VERSION.into(),
+    );
+
+    let exp_config = serde_json::from_value(Value::Object(exp_config))
+        .wrap_err("deserialize migrated experiment config")?;
+    Ok((exp_config, version))
+}
+
+// Results from before versioning (e.g. the eurosys results) have no ping mesh,
+// no co-deployments, and executors that neither adapt their cleanup interval
+// nor order the delivery of results.
+fn migrate_v0_to_v1(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    set_default(exp_config, "ping_interval", Value::Null);
+    set_default(exp_config, "co_deployed_with", Value::Null);
+
+    let config = match exp_config.get_mut("config") {
+        Some(Value::Object(config)) => config,
+        _ => eyre::bail!("experiment config should have a config object"),
+    };
+    set_default(config, "executor_adaptive_cleanup", false.into());
+    set_default(config, "executor_ordered_delivery", false.into());
+    Ok(())
+}
+
+// Results from before workload mixes have clients executing a single workload.
+fn migrate_v1_to_v2(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    set_default(exp_config, "workload_mix", Value::Null);
+    Ok(())
+}
+
+// Results from before multiple client machines per region have a single client
+// machine per region.
+fn migrate_v2_to_v3(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    set_default(exp_config, "client_machines_per_region", 1.into());
+    Ok(())
+}
+
+// Results from before TCP tuning options were recorded used the OS defaults for
+// socket buffer sizes and no keepalive.
+fn migrate_v3_to_v4(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    for field in &[
+        "process_tcp_send_buffer_size",
+        "process_tcp_recv_buffer_size",
+        "process_tcp_keepalive",
+        "client_tcp_send_buffer_size",
+        "client_tcp_recv_buffer_size",
+        "client_tcp_keepalive",
+    ] {
+        set_default(exp_config, field, Value::Null);
+    }
+    Ok(())
+}
+
+// Results from before region failures could be injected had no failures.
+fn migrate_v4_to_v5(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    set_default(exp_config, "region_failure", Value::Null);
+    set_default(exp_config, "region_failure_time", Value::Null);
+    Ok(())
+}
+
+// Results from before experiment seeds had clients that weren't seeded.
+fn migrate_v5_to_v6(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    set_default(exp_config, "experiment_seed", Value::Null);
+    Ok(())
+}
+
+// Results from before the maximum segment size could be configured used the
+// one derived from the MTU.
+fn migrate_v6_to_v7(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    set_default(exp_config, "process_tcp_max_segment_size", Value::Null);
+    set_default(exp_config, "client_tcp_max_segment_size", Value::Null);
+    Ok(())
+}
+
+// Results from before clients could be colocated with servers only had clients
+// in the client machines.
+fn migrate_v7_to_v8(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    set_default(exp_config, "colocation", Value::Null);
+    Ok(())
+}
+
+// Results from before time-bounded runs had clients issuing all their commands.
+fn migrate_v8_to_v9(exp_config: &mut Map<String, Value>) -> Result<(), Report> {
+    set_default(exp_config, "run_duration", Value::Null);
+    Ok(())
+}
+
+// Results from before commands could time out at clients had region failures
+// without a command timeout.
+fn migrate_v9_to_v10(
+    exp_config: &mut Map<String, Value>,
+) -> Result<(), Report> {
+    if let Some(Value::Object(region_failure)) =
+        exp_config.get_mut("region_failure")
+    {
+        set_default(region_failure, "command_timeout", Value::Null);
+    }
+    Ok(())
+}
+
+fn set_default(object: &mut Map<String, Value>, field: &str, value: Value) {
+    object.entry(field).or_insert(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::client::{KeyGen, Workload, WorkloadMix};
+    use fantoch::config::Config;
+    use fantoch::planet::Region;
+    use fantoch_exp::{
+        Colocation, Protocol, RegionFailure, RunMode, TcpConfig, Testbed,
+    };
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn exp_config() -> ExperimentConfig {
+        let key_gen = KeyGen::ConflictPool {
+            conflict_rate: 10,
+            pool_size: 1,
+            pool_id: 0,
+        };
+        let mut workload = Workload::new(1, key_gen, 1, 100, 0);
+        workload.set_seed(Some(42));
+        let tcp_config = TcpConfig {
+            nodelay: true,
+            send_buffer_size: Some(4 * 1024 * 1024),
+            recv_buffer_size: None,
+            keepalive: Some(Duration::from_secs(10)),
+            max_segment_size: Some(8960),
+            auth_token: None,
+        };
+        ExperimentConfig::new(
+            HashMap::new(),
+            None,
+            RunMode::Release,
+            Vec::new(),
+            Testbed::Local,
+            Protocol::TempoAtomic,
+            Config::new(3, 1),
+            8.into(),
+            2,
+            Some(Colocation::new(4, Some(50))),
+            WorkloadMix::single(workload),
+            1,
+            Duration::from_millis(5),
+            12,
+            Some(Duration::from_millis(500)),
+            Some(Protocol::AtlasLocked),
+            Some(RegionFailure::new(
+                Region::new("us-east-1"),
+                Duration::from_secs(60),
+                Duration::from_secs(30),
+                Some(Duration::from_secs(1)),
+            )),
+            Some(Duration::from_secs(120)),
+            tcp_config,
+            tcp_config,
+        )
+    }
+
+    #[test]
+    fn migrations_reach_current_version() {
+        assert_eq!(MIGRATIONS.len() as u32, EXPERIMENT_CONFIG_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn migrate_unversioned_exp_config() {
+        // create an experiment config without the fields added after
+        // versioning was introduced
+        let mut exp_config = serde_json::to_value(exp_config()).unwrap();
+        let object = exp_config.as_object_mut().unwrap();
+        object.remove(SCHEMA_VERSION_FIELD);
+        object.remove("ping_interval");
+        object.remove("co_deployed_with");
+        object.remove("workload_mix");
+        object.remove("client_machines_per_region");
+        object.remove("process_tcp_send_buffer_size");
+        object.remove("client_tcp_keepalive");
+        object.remove("region_failure");
+        object.remove("region_failure_time");
+        object.remove("experiment_seed");
+        object.remove("process_tcp_max_segment_size");
+        object.remove("colocation");
+        object.remove("run_duration");
+        let config = object.get_mut("config").unwrap();
+        let config = config.as_object_mut().unwrap();
+        config.remove("executor_adaptive_cleanup");
+        config.remove("executor_ordered_delivery");
+
+        let (exp_config, version) = migrate_exp_config(exp_config).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(exp_config.schema_version, EXPERIMENT_CONFIG_SCHEMA_VERSION);
+        assert_eq!(exp_config.ping_interval, None);
+        assert_eq!(exp_config.co_deployed_with, None);
+        assert_eq!(exp_config.workload_mix, None);
+        assert_eq!(exp_config.client_machines_per_region, 1);
+        assert_eq!(exp_config.process_tcp_send_buffer_size, None);
+        assert_eq!(exp_config.client_tcp_keepalive, None);
+        assert_eq!(exp_config.region_failure, None);
+        assert_eq!(exp_config.region_failure_time, None);
+        assert_eq!(exp_config.experiment_seed, None);
+        assert_eq!(exp_config.process_tcp_max_segment_size, None);
+        assert_eq!(exp_config.colocation, None);
+        assert_eq!(exp_config.run_duration, None);
+        assert!(!exp_config.config.executor_adaptive_cleanup());
+        assert!(!exp_config.config.executor_ordered_delivery());
+        // fields that existed are kept
+        assert_eq!(exp_config.protocol, Protocol::TempoAtomic);
+        assert_eq!(exp_config.clients_per_region, 8.into());
+    }
+
+    #[test]
+    fn migrate_v9_exp_config() {
+        // create an experiment config whose region failure has no command
+        // timeout
+        let mut exp_config = serde_json::to_value(exp_config()).unwrap();
+        let object = exp_config.as_object_mut().unwrap();
+        object.insert(SCHEMA_VERSION_FIELD.to_string(), 9.into());
+        let region_failure = object.get_mut("region_failure").unwrap();
+        region_failure
+            .as_object_mut()
+            .unwrap()
+            .remove("command_timeout");
+
+        let (exp_config, version) = migrate_exp_config(exp_config).unwrap();
+        assert_eq!(version, 9);
+        let region_failure = exp_config.region_failure.unwrap();
+        assert_eq!(region_failure.command_timeout, None);
+        assert_eq!(region_failure.max_run_time(), Duration::from_secs(90));
+    }
+
+    #[test]
+    fn migrate_current_exp_config() {
+        let exp_config = serde_json::to_value(exp_config()).unwrap();
+        let (exp_config, version) = migrate_exp_config(exp_config).unwrap();
+        assert_eq!(version, EXPERIMENT_CONFIG_SCHEMA_VERSION);
+        assert_eq!(exp_config.ping_interval, Some(Duration::from_millis(500)));
+        assert_eq!(exp_config.co_deployed_with, Some(Protocol::AtlasLocked));
+        assert_eq!(exp_config.client_machines_per_region, 2);
+        assert_eq!(
+            exp_config.process_tcp_send_buffer_size,
+            Some(4 * 1024 * 1024)
+        );
+        assert_eq!(
+            exp_config.client_tcp_keepalive,
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            exp_config
+                .region_failure
+                .as_ref()
+                .and_then(|failure| failure.command_timeout),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            exp_config
+                .region_failure
+                .map(|failure| failure.max_run_time()),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(exp_config.experiment_seed, Some(42));
+        assert_eq!(exp_config.client_tcp_max_segment_size, Some(8960));
+        assert_eq!(exp_config.colocation, Some(Colocation::new(4, Some(50))));
+        assert_eq!(exp_config.run_duration, Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn reject_newer_exp_config() {
+        let mut exp_config = serde_json::to_value(exp_config()).unwrap();
+        exp_config.as_object_mut().unwrap().insert(
+            SCHEMA_VERSION_FIELD.to_string(),
+            (EXPERIMENT_CONFIG_SCHEMA_VERSION + 1).into(),
+        );
+        assert!(migrate_exp_config(exp_config).is_err());
+    }
+}
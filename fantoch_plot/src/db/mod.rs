@@ -1,16 +1,22 @@
 mod compress;
 mod dstat;
 mod exp_data;
+mod migration;
+mod ping;
 mod results_db;
 
 // Re-exports.
-pub use compress::{DstatCompress, LatencyPrecision, MicrosHistogramCompress};
+pub use compress::{
+    DstatCompress, HistogramCompress, LatencyPrecision, MicrosHistogramCompress,
+};
 pub use dstat::Dstat;
-pub use exp_data::ExperimentData;
-pub use results_db::ResultsDB;
+pub use exp_data::{ExperimentData, LatencyAggregation};
+pub use ping::Ping;
+pub use results_db::{CodeVersion, MixedVersions, ResultsDB};
 
-use fantoch::client::KeyGen;
-use fantoch_exp::Protocol;
+use fantoch::client::{KeyGen, ProcessSelection, WorkloadMix};
+use fantoch_exp::{ClientsPerRegion, Colocation, Protocol};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Search {
@@ -20,12 +26,19 @@ pub struct Search {
     pub shard_count: Option<usize>,
     pub cpus: Option<usize>,
     pub workers: Option<usize>,
-    pub clients_per_region: Option<usize>,
+    pub clients_per_region: Option<ClientsPerRegion>,
     pub key_gen: Option<KeyGen>,
     pub keys_per_command: Option<usize>,
     pub read_only_percentage: Option<usize>,
+    pub shard_locality: Option<usize>,
     pub payload_size: Option<usize>,
     pub batch_max_size: Option<usize>,
+    pub max_in_flight: Option<usize>,
+    pub workload_mix: Option<WorkloadMix>,
+    pub process_selection: Option<ProcessSelection>,
+    pub colocation: Option<Colocation>,
+    pub run_duration: Option<Duration>,
+    pub experiment_seed: Option<u64>,
 }
 
 impl Search {
@@ -41,8 +54,15 @@ impl Search {
             key_gen: None,
             keys_per_command: None,
             read_only_percentage: None,
+            shard_locality: None,
             payload_size: None,
             batch_max_size: None,
+            max_in_flight: None,
+            workload_mix: None,
+            process_selection: None,
+            colocation: None,
+            run_duration: None,
+            experiment_seed: None,
         }
     }
 
@@ -61,11 +81,11 @@ impl Search {
         self
     }
 
-    pub fn clients_per_region(
-        &mut self,
-        clients_per_region: usize,
-    ) -> &mut Self {
-        self.clients_per_region = Some(clients_per_region);
+    pub fn clients_per_region<C>(&mut self, clients_per_region: C) -> &mut Self
+    where
+        C: Into<ClientsPerRegion>,
+    {
+        self.clients_per_region = Some(clients_per_region.into());
         self
     }
 
@@ -87,6 +107,11 @@ impl Search {
         self
     }
 
+    pub fn shard_locality(&mut self, shard_locality: usize) -> &mut Self {
+        self.shard_locality = Some(shard_locality);
+        self
+    }
+
     pub fn payload_size(&mut self, payload_size: usize) -> &mut Self {
         self.payload_size = Some(payload_size);
         self
@@ -96,4 +121,37 @@ impl Search {
         self.batch_max_size = Some(batch_max_size);
         self
     }
+
+    pub fn max_in_flight(&mut self, max_in_flight: usize) -> &mut Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    pub fn workload_mix(&mut self, workload_mix: WorkloadMix) -> &mut Self {
+        self.workload_mix = Some(workload_mix);
+        self
+    }
+
+    pub fn process_selection(
+        &mut self,
+        process_selection: ProcessSelection,
+    ) -> &mut Self {
+        self.process_selection = Some(process_selection);
+        self
+    }
+
+    pub fn colocation(&mut self, colocation: Colocation) -> &mut Self {
+        self.colocation = Some(colocation);
+        self
+    }
+
+    pub fn run_duration(&mut self, run_duration: Duration) -> &mut Self {
+        self.run_duration = Some(run_duration);
+        self
+    }
+
+    pub fn experiment_seed(&mut self, experiment_seed: u64) -> &mut Self {
+        self.experiment_seed = Some(experiment_seed);
+        self
+    }
 }
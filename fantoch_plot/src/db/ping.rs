@@ -0,0 +1,101 @@
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+#[derive(Default, Clone)]
+pub struct Ping {
+    // mapping from each second (unix time) to the sum of the RTTs (in millis)
+    // of the ping replies received in that second, and how many there were
+    rtts: BTreeMap<u64, (f64, usize)>,
+}
+
+impl Ping {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        for (second, (sum, count)) in other.rtts.iter() {
+            let current = self.rtts.entry(*second).or_default();
+            current.0 += sum;
+            current.1 += count;
+        }
+    }
+
+    /// Returns, for each second (unix time, sorted), the average RTT (in
+    /// millis) of the ping replies received in that second.
+    pub fn timeline(&self) -> Vec<(u64, f64)> {
+        self.rtts
+            .iter()
+            .map(|(second, (sum, count))| (*second, sum / *count as f64))
+            .collect()
+    }
+
+    pub fn from(start: u64, end: u64, path: &str) -> Result<Self, Report> {
+        let mut ping = Self::new();
+
+        // open ping file
+        if let Ok(file) = File::open(path) {
+            for line in BufReader::new(file).lines() {
+                let line = line.wrap_err("ping line")?;
+
+                // skip lines that are not replies (e.g. the header of each
+                // ping or unreachable targets)
+                let (epoch, rtt) = match parse_reply(&line) {
+                    Some(reply) => reply,
+                    None => continue,
+                };
+
+                // only consider the reply if within bounds
+                if epoch >= start && epoch <= end {
+                    let current = ping.rtts.entry(epoch / 1000).or_default();
+                    current.0 += rtt;
+                    current.1 += 1;
+                }
+            }
+        } else {
+            println!("missing ping file: {:?}", path);
+        }
+        Ok(ping)
+    }
+}
+
+// Parses the timestamp (in millis) and the RTT (in millis) of a ping reply,
+// prefixed by the target ip, e.g.
+// "10.0.0.2 [1600000000.123456] 64 bytes from 10.0.0.2: icmp_seq=1 ttl=64
+// time=0.123 ms"
+fn parse_reply(line: &str) -> Option<(u64, f64)> {
+    let mut parts = line.split_whitespace().skip(1);
+    let epoch = parts
+        .next()?
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .parse::<f64>()
+        .ok()?;
+    // convert epoch to milliseconds
+    let epoch = (epoch * 1000f64).round() as u64;
+    let rtt = parts
+        .find_map(|part| part.strip_prefix("time="))?
+        .parse::<f64>()
+        .ok()?;
+    Some((epoch, rtt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reply_test() {
+        let reply = "10.0.0.2 [1600000000.123456] 64 bytes from 10.0.0.2: icmp_seq=1 ttl=64 time=0.123 ms";
+        assert_eq!(parse_reply(reply), Some((1600000000123, 0.123)));
+
+        // lines that are not replies are ignored
+        let header = "10.0.0.2 PING 10.0.0.2 (10.0.0.2) 56(84) bytes of data.";
+        assert_eq!(parse_reply(header), None);
+        let unreachable = "10.0.0.2 [1600000000.123456] From 10.0.0.1 icmp_seq=1 Destination Host Unreachable";
+        assert_eq!(parse_reply(unreachable), None);
+    }
+}
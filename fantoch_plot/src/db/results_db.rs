@@ -1,23 +1,92 @@
 use crate::db::dstat::Dstat;
 use crate::db::exp_data::ExperimentData;
+use crate::db::migration;
+use crate::db::ping::Ping;
 use crate::Search;
 use color_eyre::eyre::{self, WrapErr};
 use color_eyre::Report;
-use fantoch::client::ClientData;
+use fantoch::client::{ClientData, KeyGen};
 use fantoch::planet::Region;
 use fantoch::run::task::server::metrics_logger::ProcessMetrics;
+use fantoch_exp::metadata::{self, ExperimentMetadata};
 use fantoch_exp::{ExperimentConfig, ProcessType, SerializationFormat};
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs::DirEntry;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-const SNAPSHOT_SUFFIX: &str = "_experiment_data_snapshot.bincode.gz";
+// snapshots of `ExperimentData` are versioned so that, whenever
+// `ExperimentData` changes, stale snapshots are ignored (and new ones are
+// created from the raw results)
+const SNAPSHOT_MARKER: &str = "_experiment_data_snapshot";
+const SNAPSHOT_VERSION: u32 = 10;
+
+// maximum difference between the Zipf coefficient estimated from the keys
+// accessed by clients and the one configured before warning about it
+const ZIPF_COEFFICIENT_TOLERANCE: f64 = 0.1;
+
+/// The version of the code that produced some results, as recorded in their
+/// metadata.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CodeVersion {
+    pub branch: String,
+    pub commit: Option<String>,
+}
+
+impl fmt::Display for CodeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.commit {
+            Some(commit) => write!(f, "{}@{}", self.branch, commit),
+            None => write!(f, "{}@<unknown commit>", self.branch),
+        }
+    }
+}
+
+/// Results matched by a `Search` that were produced by different versions of
+/// the code (and thus shouldn't be aggregated).
+#[derive(Debug, Clone)]
+pub struct MixedVersions {
+    pub search: Search,
+    // mapping from each version to the results produced by it
+    pub versions: BTreeMap<CodeVersion, Vec<PathBuf>>,
+}
+
+impl fmt::Display for MixedVersions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "search for {:?} with n = {} and f = {} matches results of {} code versions:",
+            self.search.protocol,
+            self.search.n,
+            self.search.f,
+            self.versions.len()
+        )?;
+        for (version, paths) in &self.versions {
+            write!(
+                f,
+                " {} ({} results, e.g. {:?})",
+                version,
+                paths.len(),
+                paths[0]
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MixedVersions {}
 
 #[derive(Debug)]
 pub struct ResultsDB {
     results: Vec<(DirEntry, ExperimentConfig, ExperimentData)>,
+    // version of the code that produced each result (older results have no
+    // version recorded)
+    versions: HashMap<PathBuf, CodeVersion>,
+    // whether searches matching results of different versions should fail
+    // (instead of only emitting a warning)
+    refuse_mixed_versions: bool,
 }
 
 impl ResultsDB {
@@ -28,12 +97,12 @@ impl ResultsDB {
         let mut timestamps = Vec::new();
         for timestamp in read_dir {
             let timestamp = timestamp.wrap_err("incorrect directory entry")?;
-            // ignore snapshot files
+            // ignore snapshot files (of any version)
             if !timestamp
                 .path()
                 .display()
                 .to_string()
-                .ends_with(SNAPSHOT_SUFFIX)
+                .contains(SNAPSHOT_MARKER)
             {
                 timestamps.push(timestamp);
             }
@@ -55,15 +124,21 @@ impl ResultsDB {
             .into_par_iter()
             .map(|timestamp| {
                 let loaded_entries = loaded_entries.clone();
-                Self::load_entry(timestamp, loaded_entries, total_entries)
+                // results not marked as complete may have been left
+                // half-written by a run that was killed (or may be older than
+                // the marker)
+                let complete = fantoch_exp::is_complete(timestamp.path());
+                let entry =
+                    Self::load_entry(timestamp, loaded_entries, total_entries);
+                (complete, entry)
             })
-            .inspect(|entry| {
+            .inspect(|(_, entry)| {
                 if let Err(e) = entry {
                     println!("error: {:?}", e);
                 }
             })
             .collect();
-        for entry in loads {
+        for (complete, entry) in loads {
             let entry = entry.wrap_err("load entry");
             match entry {
                 Ok(entry) => {
@@ -72,7 +147,11 @@ impl ResultsDB {
                 Err(e) => {
                     let missing_file =
                         String::from("No such file or directory (os error 2)");
-                    if e.root_cause().to_string() == missing_file {
+                    if !complete {
+                        // if the results are not complete, some file may be
+                        // half-written; in this case, ignore the error
+                        println!("incomplete entry ignored...");
+                    } else if e.root_cause().to_string() == missing_file {
                         // if some file was not found, it may be because the
                         // experiment is still running; in this case, ignore the
                         // error
@@ -85,7 +164,46 @@ impl ResultsDB {
             }
         }
 
-        Ok(Self { results })
+        // load the version of the code that produced each result
+        let versions = results
+            .iter()
+            .filter_map(|(timestamp, _, _)| {
+                Self::load_version(timestamp)
+                    .map(|version| (timestamp.path(), version))
+            })
+            .collect();
+
+        Ok(Self {
+            results,
+            versions,
+            refuse_mixed_versions: false,
+        })
+    }
+
+    /// Sets whether searches matching results produced by different versions
+    /// of the code should fail. By default, only a warning is emitted.
+    pub fn refuse_mixed_versions(&mut self, refuse: bool) {
+        self.refuse_mixed_versions = refuse;
+    }
+
+    fn load_version(timestamp: &DirEntry) -> Option<CodeVersion> {
+        let path = timestamp.path().join(metadata::METADATA_FILE);
+        if !path.exists() {
+            return None;
+        }
+        match fantoch_exp::deserialize::<ExperimentMetadata>(
+            &path,
+            SerializationFormat::Json,
+        ) {
+            Ok(metadata) => Some(CodeVersion {
+                branch: metadata.branch,
+                commit: metadata.commit,
+            }),
+            Err(e) => {
+                eprintln!("warning: couldn't load {:?}: {:?}", path, e);
+                None
+            }
+        }
     }
 
     fn load_entry(
@@ -99,7 +217,7 @@ impl ResultsDB {
         // read the configuration of this experiment
         let exp_config_path =
             format!("{}/exp_config.json", timestamp.path().display());
-        let exp_config: ExperimentConfig = fantoch_exp::deserialize(
+        let exp_config: serde_json::Value = fantoch_exp::deserialize(
             exp_config_path,
             SerializationFormat::Json,
         )
@@ -110,9 +228,30 @@ impl ResultsDB {
             )
         })?;
 
+        // migrate it to the current schema version (if needed)
+        let (exp_config, version) = migration::migrate_exp_config(exp_config)
+            .wrap_err_with(|| {
+            format!(
+                "migrate experiment config of {:?}",
+                timestamp.path().display()
+            )
+        })?;
+        if version != exp_config.schema_version {
+            println!(
+                "migrated {:?} from schema version {} to {}",
+                timestamp.path().display(),
+                version,
+                exp_config.schema_version
+            );
+        }
+
         // check if there's snapshot of experiment data
-        let snapshot =
-            format!("{}{}", timestamp.path().display(), SNAPSHOT_SUFFIX);
+        let snapshot = format!(
+            "{}{}_v{}.bincode.gz",
+            timestamp.path().display(),
+            SNAPSHOT_MARKER,
+            SNAPSHOT_VERSION
+        );
         let exp_data = if Path::new(&snapshot).exists() {
             // if there is, simply load it
             fantoch_exp::deserialize(&snapshot, SerializationFormat::BincodeGz)
@@ -155,137 +294,357 @@ impl ResultsDB {
         search: Search,
     ) -> Result<Vec<&(DirEntry, ExperimentConfig, ExperimentData)>, Report>
     {
-        let filtered = self
+        let filtered: Vec<_> = self
             .results
             .iter()
             .filter(move |(_, exp_config, _)| {
-                // filter out configurations with different n
-                if exp_config.config.n() != search.n {
-                    return false;
-                }
+                Self::mismatch(&search, exp_config).is_none()
+            })
+            .inspect(|(timestamp, exp_config, exp_data)| {
+                Self::validate_workload(timestamp, exp_config, exp_data)
+            })
+            .collect();
+        if let Some(mixed) = self.mixed_versions(search, &filtered) {
+            if self.refuse_mixed_versions {
+                return Err(Report::new(mixed));
+            }
+            eprintln!("warning: {}", mixed);
+        }
+        Ok(filtered)
+    }
 
-                // filter out configurations with different f
-                if exp_config.config.f() != search.f {
-                    return false;
-                }
+    // Returns the versions of the code that produced the results matched by
+    // `search`, if there's more than one. Results without a recorded version
+    // are ignored.
+    fn mixed_versions(
+        &self,
+        search: Search,
+        results: &[&(DirEntry, ExperimentConfig, ExperimentData)],
+    ) -> Option<MixedVersions> {
+        let mut versions = BTreeMap::new();
+        for (timestamp, _, _) in results {
+            let path = timestamp.path();
+            if let Some(version) = self.versions.get(&path) {
+                versions
+                    .entry(version.clone())
+                    .or_insert_with(Vec::new)
+                    .push(path);
+            }
+        }
+        if versions.len() > 1 {
+            Some(MixedVersions { search, versions })
+        } else {
+            None
+        }
+    }
 
-                // filter out configurations with different protocol
-                if exp_config.protocol != search.protocol {
-                    return false;
-                }
+    /// Groups the results that match `search` by the key `key` extracts from
+    /// their configuration, skipping the results for which it returns `None`.
+    /// Unlike `find`, this allows aggregating results over a range of some
+    /// parameter (e.g. the conflict rate of a `KeyGen::ConflictPool`) in a
+    /// single pass.
+    pub fn group_by<K, F>(
+        &self,
+        search: Search,
+        key: F,
+    ) -> Result<BTreeMap<K, Vec<&ExperimentData>>, Report>
+    where
+        K: Ord,
+        F: Fn(&ExperimentConfig) -> Option<K>,
+    {
+        let mut groups = BTreeMap::new();
+        for (timestamp, exp_config, exp_data) in self.find(search)? {
+            if let Some(key) = key(exp_config) {
+                groups.entry(key).or_insert_with(Vec::new).push(exp_data);
+            } else {
+                eprintln!(
+                    "warning: ignoring {:?} as it can't be grouped",
+                    timestamp.path().display()
+                );
+            }
+        }
+        Ok(groups)
+    }
 
-                // filter out configurations with different shard_count (if set)
-                if let Some(shard_count) = search.shard_count {
-                    if exp_config.config.shard_count() != shard_count {
-                        return false;
-                    }
-                }
+    // Warns if the workload actually issued by clients differs from the one in
+    // `exp_config`, in which case the experiment may match searches it
+    // shouldn't.
+    fn validate_workload(
+        timestamp: &DirEntry,
+        exp_config: &ExperimentConfig,
+        exp_data: &ExperimentData,
+    ) {
+        // with a workload mix, commands access different numbers of keys
+        if exp_config.workload_mix.is_some() {
+            return;
+        }
+        let keys_per_command = exp_config.workload.keys_per_command();
+        let unexpected = exp_data.unexpected_keys_per_command(keys_per_command);
+        if !unexpected.is_empty() {
+            eprintln!(
+                "warning: clients in {:?} issued commands with {:?} keys (expected {})",
+                timestamp.path().display(),
+                unexpected,
+                keys_per_command
+            );
+        }
 
-                // filter out configurations with different cpus (if set)
-                if let Some(cpus) = search.cpus {
-                    if exp_config.cpus != cpus {
-                        return false;
-                    }
-                }
+        if let KeyGen::Zipf { coefficient, .. } = exp_config.workload.key_gen()
+        {
+            if let Some(estimated) = exp_data.unexpected_zipf_coefficient(
+                coefficient,
+                ZIPF_COEFFICIENT_TOLERANCE,
+            ) {
+                eprintln!(
+                    "warning: clients in {:?} issued commands with an estimated zipf coefficient of {:.2} (expected {:.2})",
+                    timestamp.path().display(),
+                    estimated,
+                    coefficient
+                );
+            }
+        }
+    }
 
-                // filter out configurations with different workers (if set)
-                if let Some(workers) = search.workers {
-                    if exp_config.workers != workers {
-                        return false;
-                    }
-                }
+    /// Returns all the results that do not match `search`, along with the
+    /// first search parameter that each of them does not match.
+    pub fn find_mismatches(
+        &self,
+        search: Search,
+    ) -> Vec<(&(DirEntry, ExperimentConfig, ExperimentData), &'static str)>
+    {
+        self.results
+            .iter()
+            .filter_map(move |result| {
+                let (_, exp_config, _) = result;
+                Self::mismatch(&search, exp_config)
+                    .map(|parameter| (result, parameter))
+            })
+            .collect()
+    }
 
-                // filter out configurations with different clients_per_region
-                // (if set)
-                if let Some(clients_per_region) = search.clients_per_region {
-                    if exp_config.clients_per_region != clients_per_region {
-                        return false;
-                    }
-                }
+    // Returns the first search parameter not matched by `exp_config` (if any).
+    fn mismatch(
+        search: &Search,
+        exp_config: &ExperimentConfig,
+    ) -> Option<&'static str> {
+        // filter out configurations with different n
+        if exp_config.config.n() != search.n {
+            return Some("n");
+        }
 
-                // filter out configurations with different key generator (if
-                // set)
-                if let Some(key_gen) = search.key_gen {
-                    if exp_config.workload.key_gen() != key_gen {
-                        return false;
-                    }
-                }
+        // filter out configurations with different f
+        if exp_config.config.f() != search.f {
+            return Some("f");
+        }
 
-                // filter out configuration with different keys_per_command (if
-                // set)
-                if let Some(keys_per_command) = search.keys_per_command {
-                    if exp_config.workload.keys_per_command()
-                        != keys_per_command
-                    {
-                        return false;
-                    }
-                }
+        // filter out configurations with different protocol
+        if exp_config.protocol != search.protocol {
+            return Some("protocol");
+        }
 
-                // filter out configurations with different read_only_percentage
-                // (if set)
-                if let Some(read_only_percentage) = search.read_only_percentage
-                {
-                    if exp_config.workload.read_only_percentage()
-                        != read_only_percentage
-                    {
-                        return false;
-                    }
-                }
+        // filter out configurations with different shard_count (if set)
+        if let Some(shard_count) = search.shard_count {
+            if exp_config.config.shard_count() != shard_count {
+                return Some("shard_count");
+            }
+        }
 
-                // filter out configurations with different payload_size (if
-                // set)
-                if let Some(payload_size) = search.payload_size {
-                    if exp_config.workload.payload_size() != payload_size {
-                        return false;
-                    }
-                }
+        // filter out configurations with different cpus (if set)
+        if let Some(cpus) = search.cpus {
+            if exp_config.cpus != cpus {
+                return Some("cpus");
+            }
+        }
 
-                // filter out configurations with different batch_max_size (if
-                // set)
-                if let Some(batch_max_size) = search.batch_max_size {
-                    if exp_config.batch_max_size != batch_max_size {
-                        return false;
-                    }
-                }
+        // filter out configurations with different workers (if set)
+        if let Some(workers) = search.workers {
+            if exp_config.workers != workers {
+                return Some("workers");
+            }
+        }
 
-                // if this exp config was not filtered-out until now, then
-                // return it
-                true
-            })
-            .collect();
-        Ok(filtered)
+        // filter out configurations with different clients_per_region
+        // (if set)
+        if let Some(clients_per_region) = search.clients_per_region {
+            if exp_config.clients_per_region != clients_per_region {
+                return Some("clients_per_region");
+            }
+        }
+
+        // filter out configurations with different key generator (if
+        // set)
+        if let Some(key_gen) = search.key_gen {
+            if exp_config.workload.key_gen() != key_gen {
+                return Some("key_gen");
+            }
+        }
+
+        // filter out configuration with different keys_per_command (if
+        // set)
+        if let Some(keys_per_command) = search.keys_per_command {
+            if exp_config.workload.keys_per_command() != keys_per_command {
+                return Some("keys_per_command");
+            }
+        }
+
+        // filter out configurations with different read_only_percentage
+        // (if set)
+        if let Some(read_only_percentage) = search.read_only_percentage {
+            if exp_config.workload.read_only_percentage()
+                != read_only_percentage
+            {
+                return Some("read_only_percentage");
+            }
+        }
+
+        // filter out configurations with different shard_locality (if set)
+        if let Some(shard_locality) = search.shard_locality {
+            if exp_config.workload.shard_locality() != shard_locality {
+                return Some("shard_locality");
+            }
+        }
+
+        // filter out configurations with different payload_size (if
+        // set)
+        if let Some(payload_size) = search.payload_size {
+            if exp_config.workload.payload_size() != payload_size {
+                return Some("payload_size");
+            }
+        }
+
+        // filter out configurations with different batch_max_size (if
+        // set)
+        if let Some(batch_max_size) = search.batch_max_size {
+            if exp_config.batch_max_size != batch_max_size {
+                return Some("batch_max_size");
+            }
+        }
+
+        // filter out configurations with a different max_in_flight (if set);
+        // experiments without it had clients issuing one command at a time
+        if let Some(max_in_flight) = search.max_in_flight {
+            if exp_config.workload.max_in_flight().unwrap_or(1) != max_in_flight
+            {
+                return Some("max_in_flight");
+            }
+        }
+
+        // filter out configurations with a different workload mix; unlike
+        // the above, this filter is always applied so that searches without
+        // a workload mix don't match experiments with one; the seed and the
+        // max in flight of the workloads are ignored, as they're filtered
+        // separately
+        let workload_mix = exp_config.workload_mix.map(|mut workload_mix| {
+            workload_mix.set_seed(None);
+            workload_mix.set_max_in_flight(None);
+            workload_mix
+        });
+        if workload_mix != search.workload_mix {
+            return Some("workload_mix");
+        }
+
+        // filter out configurations where clients submit to a different
+        // process; searches without a process selection only match
+        // experiments where clients submit to the closest process
+        let process_selection = search.process_selection.unwrap_or_default();
+        if exp_config.config.client_process_selection() != process_selection {
+            return Some("process_selection");
+        }
+
+        // filter out configurations with clients colocated differently with
+        // servers; searches without a colocation only match experiments where
+        // all clients run in the client machines
+        if exp_config.colocation != search.colocation {
+            return Some("colocation");
+        }
+
+        // filter out configurations with a different run duration (if set)
+        if let Some(run_duration) = search.run_duration {
+            if exp_config.run_duration != Some(run_duration) {
+                return Some("run_duration");
+            }
+        }
+
+        // filter out configurations with a different experiment seed (if set)
+        if let Some(experiment_seed) = search.experiment_seed {
+            if exp_config.experiment_seed != Some(experiment_seed) {
+                return Some("experiment_seed");
+            }
+        }
+
+        // if this exp config was not filtered-out until now, then it
+        // matches the search
+        None
     }
 
     fn load_experiment_data(
         timestamp: &DirEntry,
         exp_config: &ExperimentConfig,
     ) -> Result<ExperimentData, Report> {
-        // client metrics
-        let mut client_metrics = HashMap::new();
+        // client metrics of each client machine (and of the clients colocated
+        // with each server)
+        let mut machine_client_metrics = HashMap::new();
 
         for (region, _, _, region_index) in exp_config.placement.iter() {
-            // only load client metrics for this region if we haven't already
-            if !client_metrics.contains_key(region) {
-                // create client file prefix
-                let process_type = ProcessType::Client(*region_index);
+            // clients in the region that failed (if any) were killed, and thus
+            // have no metrics
+            if let Some(region_failure) = &exp_config.region_failure {
+                if &region_failure.region == region {
+                    continue;
+                }
+            }
+            for client_index in 0..exp_config.client_machines_per_region {
+                // only load client metrics for this machine if we haven't
+                // already
+                let process_type =
+                    ProcessType::Client(*region_index, client_index);
+                let key = (region.clone(), process_type);
+                if !machine_client_metrics.contains_key(&key) {
+                    // create client file prefix
+                    let prefix =
+                        fantoch_exp::config::file_prefix(process_type, region);
+
+                    // load this machine's client metrics
+                    let client: ClientData =
+                        Self::load_metrics(&timestamp, prefix)?;
+                    machine_client_metrics.insert(key, client);
+                }
+            }
+        }
+
+        // client metrics of the clients colocated with each server (if any);
+        // these are merged with the ones of the clients in the same region
+        if exp_config.colocation.is_some() {
+            for (region, _, process_id, _) in exp_config.placement.iter() {
+                if let Some(region_failure) = &exp_config.region_failure {
+                    if &region_failure.region == region {
+                        continue;
+                    }
+                }
+                let process_type = ProcessType::ColocatedClient(*process_id);
                 let prefix =
                     fantoch_exp::config::file_prefix(process_type, region);
-
-                // load this region's client metrics (there's a single client
-                // machine per region)
                 let client: ClientData =
                     Self::load_metrics(&timestamp, prefix)?;
-                assert!(client_metrics
-                    .insert(region.clone(), client)
-                    .is_none());
+                machine_client_metrics
+                    .insert((region.clone(), process_type), client);
             }
         }
 
         // clean-up client data
         let (start, end) = Self::prune_before_last_start_and_after_first_end(
-            &mut client_metrics,
+            &mut machine_client_metrics,
         )?;
 
+        // merge the client data of all client machines in each region
+        let mut client_metrics = HashMap::new();
+        for ((region, _), client) in machine_client_metrics {
+            client_metrics
+                .entry(region)
+                .or_insert_with(ClientData::new)
+                .merge(&client);
+        }
+
         // create global client data (from cleaned-up client data)
         let global_client_metrics =
             Self::global_client_metrics(&client_metrics);
@@ -297,14 +656,21 @@ impl ResultsDB {
         for (region, _, _, region_index) in exp_config.placement.iter() {
             // only load client dstats for this region if we haven't already
             if !client_dstats.contains_key(region) {
-                // create client file prefix
-                let process_type = ProcessType::Client(*region_index);
-                let prefix =
-                    fantoch_exp::config::file_prefix(process_type, region);
-
-                // load this region's client dstat
-                let client = Self::load_dstat(&timestamp, prefix, start, end)?;
-                assert!(client_dstats.insert(region.clone(), client).is_none());
+                // load the dstats of all client machines in this region
+                let mut region_dstat = Dstat::new();
+                for client_index in 0..exp_config.client_machines_per_region {
+                    // create client file prefix
+                    let process_type =
+                        ProcessType::Client(*region_index, client_index);
+                    let prefix =
+                        fantoch_exp::config::file_prefix(process_type, region);
+
+                    // load this machine's client dstat
+                    let client =
+                        Self::load_dstat(&timestamp, prefix, start, end)?;
+                    region_dstat.merge(&client);
+                }
+                client_dstats.insert(region.clone(), region_dstat);
             }
         }
 
@@ -329,6 +695,31 @@ impl ResultsDB {
             process_dstats.insert(process_id, process);
         }
 
+        // ping files of all machines (only if the ping mesh was run)
+        let mut global_ping = Ping::new();
+        if exp_config.ping_interval.is_some() {
+            let mut process_types = HashSet::new();
+            for (region, _, process_id, region_index) in
+                exp_config.placement.iter()
+            {
+                process_types
+                    .insert((region, ProcessType::Server(*process_id)));
+                // there may be more than one process per region with partial
+                // replication, but client machines are only loaded once
+                for client_index in 0..exp_config.client_machines_per_region {
+                    let process_type =
+                        ProcessType::Client(*region_index, client_index);
+                    process_types.insert((region, process_type));
+                }
+            }
+            for (region, process_type) in process_types {
+                let prefix =
+                    fantoch_exp::config::file_prefix(process_type, region);
+                let ping = Self::load_ping(&timestamp, prefix, start, end)?;
+                global_ping.merge(&ping);
+            }
+        }
+
         // return experiment data
         Ok(ExperimentData::new(
             process_metrics,
@@ -336,6 +727,7 @@ impl ResultsDB {
             client_metrics,
             client_dstats,
             global_client_metrics,
+            global_ping,
         ))
     }
 
@@ -369,12 +761,24 @@ impl ResultsDB {
             .wrap_err_with(|| format!("deserialize dstat {}", path))
     }
 
+    fn load_ping(
+        timestamp: &DirEntry,
+        prefix: String,
+        start: u64,
+        end: u64,
+    ) -> Result<Ping, Report> {
+        let path =
+            format!("{}/{}_ping.log", timestamp.path().display(), prefix);
+        Ping::from(start, end, &path)
+            .wrap_err_with(|| format!("deserialize ping {}", path))
+    }
+
     // Here we make sure that we will only consider that points in which all the
     // clients are running, i.e. we prune data points that are from
     // - before the last client starting (i.e. the max of all start times)
     // - after the first client ending (i.e. the min of all end times)
-    fn prune_before_last_start_and_after_first_end(
-        client_metrics: &mut HashMap<Region, ClientData>,
+    fn prune_before_last_start_and_after_first_end<K>(
+        client_metrics: &mut HashMap<K, ClientData>,
     ) -> Result<(u64, u64), Report> {
         // compute start and end times for all clients
         let mut starts = Vec::with_capacity(client_metrics.len());
@@ -0,0 +1,136 @@
+use crate::db::{DstatCompress, ExperimentData, HistogramCompress};
+use fantoch::protocol::ProtocolMetricsKind;
+
+#[derive(Clone, Copy, Debug)]
+pub enum DstatMetric {
+    CpuUsr,
+    CpuSys,
+    CpuWait,
+    NetRecv,
+    NetSend,
+    MemUsed,
+}
+
+impl DstatMetric {
+    pub fn name(&self) -> String {
+        match self {
+            Self::CpuUsr => String::from("cpu_usr"),
+            Self::CpuSys => String::from("cpu_sys"),
+            Self::CpuWait => String::from("cpu_wait"),
+            Self::NetRecv => String::from("net_in"),
+            Self::NetSend => String::from("net_out"),
+            Self::MemUsed => String::from("mem_used"),
+        }
+    }
+
+    fn histogram<'a>(
+        &self,
+        dstats: &'a DstatCompress,
+    ) -> &'a HistogramCompress {
+        match self {
+            Self::CpuUsr => &dstats.cpu_usr,
+            Self::CpuSys => &dstats.cpu_sys,
+            Self::CpuWait => &dstats.cpu_wait,
+            Self::NetRecv => &dstats.net_recv,
+            Self::NetSend => &dstats.net_send,
+            Self::MemUsed => &dstats.mem_used,
+        }
+    }
+}
+
+/// Expression over the dstat and process metrics of an experiment, e.g.
+/// `Div(DstatSum(NetSend), Committed)` is the sum of network traffic sent by
+/// each process divided by the number of committed commands.
+#[derive(Clone, Debug)]
+pub enum MetricExpr {
+    Const(f64),
+    // mean of a dstat metric in the dstats selected by the plot (e.g. the
+    // leader's dstats in leader-based protocols)
+    Dstat(DstatMetric),
+    // sum across all processes of the mean of a dstat metric
+    DstatSum(DstatMetric),
+    // protocol metric aggregated across all processes
+    ProtocolAggregated(ProtocolMetricsKind),
+    // mean of a protocol metric collected across all processes
+    ProtocolCollected(ProtocolMetricsKind),
+    // number of commands committed across all processes
+    Committed,
+    Add(Box<MetricExpr>, Box<MetricExpr>),
+    Sub(Box<MetricExpr>, Box<MetricExpr>),
+    Mul(Box<MetricExpr>, Box<MetricExpr>),
+    // dividing by zero evaluates to zero
+    Div(Box<MetricExpr>, Box<MetricExpr>),
+}
+
+impl MetricExpr {
+    pub fn add(lhs: Self, rhs: Self) -> Self {
+        Self::Add(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn sub(lhs: Self, rhs: Self) -> Self {
+        Self::Sub(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn mul(lhs: Self, rhs: Self) -> Self {
+        Self::Mul(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn div(lhs: Self, rhs: Self) -> Self {
+        Self::Div(Box::new(lhs), Box::new(rhs))
+    }
+
+    /// Evaluates the expression given the data of an experiment and the
+    /// dstats to be used by `MetricExpr::Dstat`.
+    pub fn eval(
+        &self,
+        exp_data: &ExperimentData,
+        dstats: &DstatCompress,
+    ) -> f64 {
+        match self {
+            Self::Const(value) => *value,
+            Self::Dstat(metric) => metric.histogram(dstats).mean(),
+            Self::DstatSum(metric) => exp_data
+                .process_dstats
+                .values()
+                .map(|dstats| metric.histogram(dstats).mean())
+                .sum(),
+            Self::ProtocolAggregated(kind) => exp_data
+                .global_protocol_metrics
+                .get_aggregated(*kind)
+                .cloned()
+                .unwrap_or_default()
+                as f64,
+            Self::ProtocolCollected(kind) => exp_data
+                .global_protocol_metrics
+                .get_collected(*kind)
+                .map(|histogram| histogram.mean().value())
+                .unwrap_or_default(),
+            Self::Committed => {
+                let fast_path =
+                    Self::ProtocolAggregated(ProtocolMetricsKind::FastPath)
+                        .eval(exp_data, dstats);
+                let slow_path =
+                    Self::ProtocolAggregated(ProtocolMetricsKind::SlowPath)
+                        .eval(exp_data, dstats);
+                fast_path + slow_path
+            }
+            Self::Add(lhs, rhs) => {
+                lhs.eval(exp_data, dstats) + rhs.eval(exp_data, dstats)
+            }
+            Self::Sub(lhs, rhs) => {
+                lhs.eval(exp_data, dstats) - rhs.eval(exp_data, dstats)
+            }
+            Self::Mul(lhs, rhs) => {
+                lhs.eval(exp_data, dstats) * rhs.eval(exp_data, dstats)
+            }
+            Self::Div(lhs, rhs) => {
+                let rhs = rhs.eval(exp_data, dstats);
+                if rhs == 0f64 {
+                    0f64
+                } else {
+                    lhs.eval(exp_data, dstats) / rhs
+                }
+            }
+        }
+    }
+}
@@ -18,26 +18,42 @@ impl PlotFmt {
     }
 
     pub fn protocol_name(protocol: Protocol) -> &'static str {
-        match protocol {
-            Protocol::AtlasLocked => "Atlas",
-            Protocol::EPaxosLocked => "EPaxos",
-            Protocol::CaesarLocked => "Caesar",
-            Protocol::FPaxos => "FPaxos",
-            Protocol::TempoAtomic => "Tempo",
-            Protocol::TempoLocked => "Tempo-L",
-            Protocol::Basic => "Inconsistent",
-        }
+        protocol.registration().plot_name
     }
 
     pub fn label(protocol: Protocol, f: usize) -> String {
-        match protocol {
-            Protocol::EPaxosLocked | Protocol::CaesarLocked => {
-                Self::protocol_name(protocol).to_string()
-            }
-            _ => format!("{} f = {}", Self::protocol_name(protocol), f),
+        // `f` is only shown for protocols that depend on it
+        if protocol.capabilities().configurable_f() {
+            format!("{} f = {}", Self::protocol_name(protocol), f)
+        } else {
+            Self::protocol_name(protocol).to_string()
         }
     }
 
+    /// Returns the default legend order of each `(protocol, f)` pair:
+    /// leader-based protocols come first (as they're usually the baseline),
+    /// followed by leaderless ones; within each group, protocols are sorted by
+    /// their registration order and then by `f`.
+    pub fn legend_order(protocols: &[(Protocol, usize)]) -> Vec<usize> {
+        let all = Protocol::all();
+        let key = |&(protocol, f): &(Protocol, usize)| {
+            let index = all
+                .iter()
+                .position(|registered| *registered == protocol)
+                .expect("all protocols should be registered");
+            (!protocol.capabilities().leader, index, f)
+        };
+        let mut sorted: Vec<_> = (0..protocols.len()).collect();
+        sorted.sort_by_key(|&position| key(&protocols[position]));
+
+        // `legend_order[i]` is the position of `protocols[i]` in the legend
+        let mut legend_order = vec![0; protocols.len()];
+        for (order, position) in sorted.into_iter().enumerate() {
+            legend_order[position] = order;
+        }
+        legend_order
+    }
+
     pub fn color(protocol: Protocol, f: usize) -> String {
         match (protocol, f) {
             (Protocol::AtlasLocked, 1) => "#27ae60",
@@ -52,10 +68,8 @@ impl PlotFmt {
             (Protocol::TempoLocked, 1) => "#2980b9", // "#111111"
             (Protocol::TempoLocked, 2) => "#c23616", // "#333333"
             (Protocol::Basic, _) => "#576574",
-            _ => panic!(
-                "PlotFmt::color: protocol = {:?} and f = {} combination not supported!",
-                protocol, f
-            ),
+            // otherwise, use the color the protocol was registered with
+            _ => protocol.registration().plot_color,
         }.to_string()
     }
 
@@ -125,6 +139,7 @@ impl PlotFmt {
             (Protocol::TempoAtomic, _) => "-",
             (Protocol::TempoLocked, _) => "-",
             (Protocol::Basic, _) => ":",
+            _ => "-",
         }
         .to_string()
     }
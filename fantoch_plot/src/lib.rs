@@ -1,18 +1,32 @@
 #![deny(rust_2018_idioms)]
 
+mod bundle;
 mod db;
+mod expr;
 mod fmt;
+mod metrics_diff;
 pub mod plot;
+mod stats;
 
 // Re-exports.
-pub use db::{ExperimentData, LatencyPrecision, ResultsDB, Search};
+pub use bundle::{
+    ArtifactBundle, BundledFile, BundledSearch, Environment, Manifest,
+};
+pub use db::{
+    CodeVersion, ExperimentData, LatencyAggregation, LatencyPrecision,
+    MixedVersions, ResultsDB, Search,
+};
+pub use expr::{DstatMetric, MetricExpr};
 pub use fmt::PlotFmt;
+pub use metrics_diff::{metrics_diff, MetricDiff};
+pub use stats::{compare_runs, Knee, PairedTTest, RunsComparison};
 
 use color_eyre::eyre::WrapErr;
 use color_eyre::Report;
 use fantoch::client::KeyGen;
-// use fantoch::executor::ExecutorMetricsKind;
+use fantoch::executor::{ExecutorMetrics, ExecutorMetricsKind};
 use fantoch::id::ProcessId;
+use fantoch::planet::Region;
 use fantoch::protocol::ProtocolMetricsKind;
 use fantoch_exp::Protocol;
 use plot::axes::Axes;
@@ -22,6 +36,7 @@ use plot::Matplotlib;
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::Duration;
 
 // defaults: [6.4, 4.8]
 // copied from: https://github.com/jonhoo/thesis/blob/master/graphs/common.py
@@ -67,11 +82,26 @@ impl LatencyMetric {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum HeatmapMetric {
     CPU,
     NetRecv,
     NetSend,
+    // percentage of the commands committed that took the slow path
+    SlowPath,
+    // mean of a protocol-phase metric collected across all processes (e.g.
+    // `ProtocolMetricsKind::WaitConditionDelay`), where `max` is the value
+    // that corresponds to 100%
+    ProtocolPhase {
+        kind: ProtocolMetricsKind,
+        max: f64,
+    },
+    // arbitrary expression, where `max` is the value that corresponds to 100%
+    Expr {
+        name: String,
+        expr: MetricExpr,
+        max: f64,
+    },
 }
 
 impl HeatmapMetric {
@@ -80,16 +110,55 @@ impl HeatmapMetric {
             Self::CPU => String::from("cpu"),
             Self::NetRecv => String::from("net_in"),
             Self::NetSend => String::from("net_out"),
+            Self::SlowPath => String::from("slow_path"),
+            Self::ProtocolPhase { kind, .. } => format!("{:?}", kind),
+            Self::Expr { name, .. } => name.clone(),
+        }
+    }
+
+    /// Returns the label of the colorbar.
+    pub fn colorbar_label(&self) -> String {
+        match self {
+            Self::CPU | Self::NetRecv | Self::NetSend | Self::Expr { .. } => {
+                String::from("utilization (%)")
+            }
+            Self::SlowPath => String::from("slow path (%)"),
+            Self::ProtocolPhase { .. } => format!("{} (% of max)", self.name()),
+        }
+    }
+
+    pub fn expr(&self) -> MetricExpr {
+        match self {
+            Self::CPU => MetricExpr::add(
+                MetricExpr::Dstat(DstatMetric::CpuUsr),
+                MetricExpr::Dstat(DstatMetric::CpuSys),
+            ),
+            Self::NetRecv => MetricExpr::Dstat(DstatMetric::NetRecv),
+            Self::NetSend => MetricExpr::Dstat(DstatMetric::NetSend),
+            Self::SlowPath => MetricExpr::div(
+                MetricExpr::mul(
+                    MetricExpr::ProtocolAggregated(
+                        ProtocolMetricsKind::SlowPath,
+                    ),
+                    MetricExpr::Const(100f64),
+                ),
+                MetricExpr::Committed,
+            ),
+            Self::ProtocolPhase { kind, .. } => {
+                MetricExpr::ProtocolCollected(*kind)
+            }
+            Self::Expr { expr, .. } => expr.clone(),
         }
     }
 
     pub fn utilization(&self, value: f64) -> usize {
         let max = match self {
-            Self::CPU => 100f64,
+            Self::CPU | Self::SlowPath => 100f64,
             Self::NetSend | Self::NetRecv => {
                 // 10GBit to B
                 10_000_000_000f64 / 8f64
             }
+            Self::ProtocolPhase { max, .. } | Self::Expr { max, .. } => *max,
         };
         (value * 100f64 / max) as usize
     }
@@ -109,11 +178,17 @@ impl ThroughputYAxis {
         }
     }
 
-    fn y_label(&self, latency_precision: LatencyPrecision) -> String {
+    fn y_label(
+        &self,
+        latency_precision: LatencyPrecision,
+        latency_aggregation: LatencyAggregation,
+    ) -> String {
         match self {
-            Self::Latency(_) => {
-                format!("latency ({})", latency_precision.name())
-            }
+            Self::Latency(_) => format!(
+                "latency ({}, {})",
+                latency_precision.name(),
+                latency_aggregation.name()
+            ),
             Self::CPU => String::from("CPU utilization (%)"),
         }
     }
@@ -186,6 +261,7 @@ pub fn latency_plot<R>(
     legend_order: Option<Vec<usize>>,
     style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
     latency_precision: LatencyPrecision,
+    latency_aggregation: LatencyAggregation,
     n: usize,
     error_bar: ErrorBar,
     output_dir: Option<&str>,
@@ -203,6 +279,11 @@ pub fn latency_plot<R>(
         "latency_plot: expected less searches than the max number of combinations"
     );
 
+    // compute legend order: if not defined, then it's the default order of
+    // the protocols searched (see `PlotFmt::legend_order`)
+    let legend_order =
+        legend_order.unwrap_or_else(|| default_legend_order(&searches));
+
     // compute x: one per region
     // - the +1 is for the 'average' group
     let x: Vec<_> = (0..n + 1).map(|i| i as f64 * FULL_REGION_WIDTH).collect();
@@ -249,10 +330,6 @@ pub fn latency_plot<R>(
     // keep track of the number of plotted instances
     let mut plotted = 0;
 
-    // compute legend order: if not defined, then it's the order given by
-    // `searches`
-    let legend_order =
-        legend_order.unwrap_or_else(|| (0..searches.len()).collect::<Vec<_>>());
     assert_eq!(
         legend_order.len(),
         searches.len(),
@@ -268,28 +345,16 @@ pub fn latency_plot<R>(
             search.n, n,
             "latency_plot: value of n in search doesn't match the provided"
         );
-        let mut exp_data = db.find(search)?;
-        match exp_data.len() {
-            0 => {
-                eprintln!(
-                    "missing data for {} f = {}",
-                    PlotFmt::protocol_name(search.protocol),
-                    search.f
-                );
-                continue;
-            }
-            1 => (),
-            _ => {
-                let matches: Vec<_> = exp_data
-                    .into_iter()
-                    .map(|(timestamp, _, _)| {
-                        timestamp.path().display().to_string()
-                    })
-                    .collect();
-                panic!("found more than 1 matching experiment for this search criteria: search {:?} | matches {:?}", search, matches);
-            }
+        let exp_data = if let Some(exp_data) = find_single(db, search)? {
+            exp_data
+        } else {
+            eprintln!(
+                "missing data for {} f = {}",
+                PlotFmt::protocol_name(search.protocol),
+                search.f
+            );
+            continue;
         };
-        let (_, _, exp_data) = exp_data.pop().unwrap();
 
         // compute y: avg latencies sorted by region name
         let mut from_err = Vec::new();
@@ -336,7 +401,7 @@ pub fn latency_plot<R>(
         // add global client latency to the 'average' group
         y.push(
             exp_data
-                .global_client_latency
+                .global_client_latency_with(latency_aggregation)
                 .mean(latency_precision)
                 .round() as u64,
         );
@@ -392,7 +457,8 @@ pub fn latency_plot<R>(
     // map regions to their pretty name
     let mut labels: Vec<_> =
         regions.into_iter().map(PlotFmt::region_name).collect();
-    labels.push("average");
+    let average_label = format!("average\n({})", latency_aggregation.name());
+    labels.push(&average_label);
     ax.set_xticklabels(labels, None)?;
 
     // set labels
@@ -419,15 +485,233 @@ pub fn latency_plot<R>(
     Ok(results)
 }
 
+/// Plots, for each region, the speedup of each search over `baseline`, i.e.
+/// the average latency of `baseline` divided by the average latency of the
+/// search. Returns the speedups of each search (sorted by region name, with
+/// the speedup of the global client latency last).
+pub fn latency_speedup_plot(
+    baseline: Search,
+    searches: Vec<Search>,
+    legend_order: Option<Vec<usize>>,
+    style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
+    latency_aggregation: LatencyAggregation,
+    n: usize,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<Vec<(Search, Vec<f64>)>, Report> {
+    const FULL_REGION_WIDTH: f64 = 10f64;
+    const MAX_COMBINATIONS: usize = 7;
+    // 80% of `FULL_REGION_WIDTH` when `MAX_COMBINATIONS` is reached
+    const BAR_WIDTH: f64 = FULL_REGION_WIDTH * 0.8 / MAX_COMBINATIONS as f64;
+    // speedups are ratios, so the precision used doesn't matter
+    let latency_precision = LatencyPrecision::Micros;
+
+    assert!(
+        searches.len() <= MAX_COMBINATIONS,
+        "latency_speedup_plot: expected less searches than the max number of combinations"
+    );
+    assert_eq!(
+        baseline.n, n,
+        "latency_speedup_plot: value of n in baseline doesn't match the provided"
+    );
+
+    // compute the average latency of the baseline in each region
+    let baseline_data = find_single(db, baseline)?.ok_or_else(|| {
+        color_eyre::eyre::eyre!(
+            "missing data for baseline {} f = {}",
+            PlotFmt::protocol_name(baseline.protocol),
+            baseline.f
+        )
+    })?;
+    let baseline_latency: BTreeMap<_, _> = baseline_data
+        .client_latency
+        .iter()
+        .map(|(region, histogram)| {
+            (region.clone(), histogram.mean(latency_precision))
+        })
+        .collect();
+    let baseline_global_latency = baseline_data
+        .global_client_latency_with(latency_aggregation)
+        .mean(latency_precision);
+    assert_eq!(
+        baseline_latency.len(),
+        n,
+        "latency_speedup_plot: the number of regions doesn't match the n provided"
+    );
+
+    // compute x: one per region
+    // - the +1 is for the 'average' group
+    let x: Vec<_> = (0..n + 1).map(|i| i as f64 * FULL_REGION_WIDTH).collect();
+
+    // we need to shift all to the left by half of the number of combinations
+    let search_count = searches.len();
+    let shift_left = search_count as f64 / 2f64;
+    // we also need to shift half bar to the right
+    let shift_right = 0.5;
+
+    // aggregate the speedups of each search
+    let mut results = Vec::new();
+
+    // start python
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let plt = PyPlot::new(py)?;
+
+    // start plot
+    let (fig, ax) = start_plot(py, &plt, None)?;
+
+    // keep track of the number of plotted instances
+    let mut plotted = 0;
+
+    // compute legend order: if not defined, then it's the default order of
+    // the protocols searched (see `PlotFmt::legend_order`)
+    let legend_order =
+        legend_order.unwrap_or_else(|| default_legend_order(&searches));
+    assert_eq!(
+        legend_order.len(),
+        searches.len(),
+        "legend order should contain the same number of searches"
+    );
+    let mut legends = BTreeMap::new();
+
+    for (index, (search, legend_order)) in
+        searches.into_iter().zip(legend_order).enumerate()
+    {
+        // check `n`
+        assert_eq!(
+            search.n, n,
+            "latency_speedup_plot: value of n in search doesn't match the provided"
+        );
+        let exp_data = if let Some(exp_data) = find_single(db, search)? {
+            exp_data
+        } else {
+            eprintln!(
+                "missing data for {} f = {}",
+                PlotFmt::protocol_name(search.protocol),
+                search.f
+            );
+            continue;
+        };
+
+        // compute y: speedups sorted by region name (`baseline_latency` is
+        // a `BTreeMap`, and thus already sorted)
+        let mut y: Vec<_> = baseline_latency
+            .iter()
+            .map(|(region, baseline_latency)| {
+                let histogram =
+                    exp_data.client_latency.get(region).unwrap_or_else(|| {
+                        panic!(
+                            "latency_speedup_plot: region {:?} missing in search {:?}",
+                            region, search
+                        )
+                    });
+                baseline_latency / histogram.mean(latency_precision)
+            })
+            .collect();
+
+        // add speedup of global client latency to the 'average' group
+        y.push(
+            baseline_global_latency
+                / exp_data
+                    .global_client_latency_with(latency_aggregation)
+                    .mean(latency_precision),
+        );
+        println!(
+            "{:<7} f = {} | speedup over {} f = {} | {:.2?}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f,
+            PlotFmt::protocol_name(baseline.protocol),
+            baseline.f,
+            y,
+        );
+
+        // compute x: shift all values by the combination's shift
+        let shift = (index as f64 - shift_left + shift_right) * BAR_WIDTH;
+        let x: Vec<_> = x.iter().map(|&x| x + shift).collect();
+
+        let kwargs = bar_style(py, search, &style_fun, BAR_WIDTH)?;
+        let line = ax.bar(x, y.clone(), Some(kwargs))?;
+        plotted += 1;
+
+        // save line with its legend order
+        legends.insert(
+            legend_order,
+            (line, PlotFmt::label(search.protocol, search.f)),
+        );
+
+        // save new result
+        results.push((search, y));
+    }
+
+    // draw a line at speedup 1, i.e. the latency of the baseline
+    let baseline_x = vec![
+        -FULL_REGION_WIDTH / 2f64,
+        n as f64 * FULL_REGION_WIDTH + FULL_REGION_WIDTH / 2f64,
+    ];
+    let kwargs = pydict!(py, ("color", "black"), ("linewidth", 1));
+    ax.plot(baseline_x, vec![1f64, 1f64], Some("--"), Some(kwargs))?;
+
+    // set xticks
+    ax.set_xticks(x, None)?;
+
+    // map regions to their pretty name
+    let mut labels: Vec<_> = baseline_latency
+        .keys()
+        .cloned()
+        .map(PlotFmt::region_name)
+        .collect();
+    let average_label = format!("average\n({})", latency_aggregation.name());
+    labels.push(&average_label);
+    ax.set_xticklabels(labels, None)?;
+
+    // set labels
+    let ylabel = format!(
+        "speedup over {}",
+        PlotFmt::label(baseline.protocol, baseline.f)
+    );
+    ax.set_ylabel(&ylabel, None)?;
+
+    // legend
+    let x_bbox_to_anchor = Some(0.46);
+    add_legend(
+        plotted,
+        Some(legends),
+        x_bbox_to_anchor,
+        None,
+        None,
+        py,
+        &ax,
+    )?;
+
+    // end plot
+    end_plot(plotted > 0, output_dir, output_file, py, &plt, Some(fig))?;
+    Ok(results)
+}
+
 // based on: https://github.com/jonhoo/thesis/blob/master/graphs/vote-memlimit-cdf.py
+// If `per_region` is set, there's one subplot per client region (instead of a
+// single plot with the latency of all regions aggregated).
 pub fn cdf_plot(
     searches: Vec<Search>,
     style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
     latency_precision: LatencyPrecision,
+    per_region: bool,
     output_dir: Option<&str>,
     output_file: &str,
     db: &ResultsDB,
 ) -> Result<(), Report> {
+    if per_region {
+        return cdf_plot_per_region(
+            searches,
+            style_fun,
+            latency_precision,
+            output_dir,
+            output_file,
+            db,
+        );
+    }
+
     // start python
     let gil = Python::acquire_gil();
     let py = gil.python();
@@ -446,6 +730,7 @@ pub fn cdf_plot(
             search,
             &style_fun,
             latency_precision,
+            None,
             db,
             &mut plotted,
         )?;
@@ -463,6 +748,87 @@ pub fn cdf_plot(
     Ok(())
 }
 
+fn cdf_plot_per_region(
+    searches: Vec<Search>,
+    style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
+    latency_precision: LatencyPrecision,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<(), Report> {
+    // find all client regions (sorted by name)
+    let mut regions = BTreeSet::new();
+    for search in searches.iter() {
+        if let Some(exp_data) = find_single(db, *search)? {
+            regions.extend(exp_data.client_latency.keys().cloned());
+        }
+    }
+
+    // start python
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let plt = PyPlot::new(py)?;
+
+    // start plot:
+    // - adjust horizontal space between subplots
+    let kwargs = pydict!(py, ("wspace", 0.1));
+    let (fig, _) = start_plot(py, &plt, Some(kwargs))?;
+
+    let mut previous_axis: Option<Axes<'_>> = None;
+    let mut plotted = 0;
+
+    for (index, region) in regions.iter().enumerate() {
+        // create subplot (shared axis with the previous subplot (if any))
+        let kwargs = match previous_axis {
+            None => None,
+            Some(previous_axis) => Some(pydict!(
+                py,
+                ("sharex", previous_axis.ax()),
+                ("sharey", previous_axis.ax())
+            )),
+        };
+        let ax = plt.subplot(1, regions.len(), index + 1, kwargs)?;
+
+        // keep track of the number of plotted instances
+        let mut subfigure_plotted = 0;
+
+        for search in searches.iter() {
+            inner_cdf_plot(
+                py,
+                &ax,
+                *search,
+                &style_fun,
+                latency_precision,
+                Some(region),
+                db,
+                &mut subfigure_plotted,
+            )?;
+        }
+
+        // set cdf plot style
+        inner_cdf_plot_style(py, &ax, None, latency_precision)?;
+        ax.set_title(PlotFmt::region_name(region.clone()))?;
+
+        // only the first subplot has y label and legend
+        if index == 0 {
+            add_legend(subfigure_plotted, None, None, None, None, py, &ax)?;
+        } else {
+            ax.set_ylabel("", None)?;
+        }
+
+        // save axis
+        previous_axis = Some(ax);
+
+        // track global number of plotted
+        plotted += subfigure_plotted;
+    }
+
+    // end plot
+    end_plot(plotted > 0, output_dir, output_file, py, &plt, Some(fig))?;
+
+    Ok(())
+}
+
 pub fn cdf_plot_split(
     top_searches: Vec<Search>,
     bottom_searches: Vec<Search>,
@@ -514,6 +880,7 @@ pub fn cdf_plot_split(
                 *search,
                 &style_fun,
                 latency_precision,
+                None,
                 db,
                 &mut subfigure_plotted,
             )?;
@@ -598,6 +965,7 @@ fn inner_cdf_plot(
     search: Search,
     style_fun: &Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
     latency_precision: LatencyPrecision,
+    region: Option<&Region>,
     db: &ResultsDB,
     plotted: &mut usize,
 ) -> Result<(), Report> {
@@ -622,13 +990,27 @@ fn inner_cdf_plot(
     };
     let (_, _, exp_data) = exp_data.pop().unwrap();
 
-    // compute x: all values in the global histogram
+    // select the histogram of the region (if any) or the global one
+    let histogram = match region {
+        Some(region) => match exp_data.client_latency.get(region) {
+            Some(histogram) => histogram,
+            None => {
+                eprintln!(
+                    "missing data for {} f = {} in region {:?}",
+                    PlotFmt::protocol_name(search.protocol),
+                    search.f,
+                    region
+                );
+                return Ok(());
+            }
+        },
+        None => exp_data.global_client_tail_latency(),
+    };
+
+    // compute x: all values in the histogram
     let x: Vec<_> = percentiles()
         .map(|percentile| {
-            exp_data
-                .global_client_latency
-                .percentile(percentile, latency_precision)
-                .round() as u64
+            histogram.percentile(percentile, latency_precision).round() as u64
         })
         .collect();
 
@@ -637,12 +1019,13 @@ fn inner_cdf_plot(
         percentiles().map(|percentile| percentile * 100.0).collect();
 
     println!(
-        "{:<7} f = {} | c = {} | {:?}",
+        "{:<7} f = {} | c = {} | region = {:?} | {:?}",
         PlotFmt::protocol_name(search.protocol),
         search.f,
         search
             .clients_per_region
             .expect("clients per region should be set"),
+        region,
         x.iter()
             .zip(y.iter())
             .filter(|(_, percentile)| vec![95.0, 98.8, 99.9, 99.99]
@@ -662,6 +1045,7 @@ pub fn throughput_something_plot(
     searches: Vec<Search>,
     style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
     latency_precision: LatencyPrecision,
+    latency_aggregation: LatencyAggregation,
     n: usize,
     clients_per_region: Vec<usize>,
     x_range: Option<(f64, f64)>,
@@ -688,6 +1072,7 @@ pub fn throughput_something_plot(
         searches,
         &style_fun,
         latency_precision,
+        latency_aggregation,
         n,
         clients_per_region,
         x_range,
@@ -708,7 +1093,7 @@ pub fn throughput_something_plot(
 
     // set labels
     ax.set_xlabel("throughput (K ops/s)", None)?;
-    let mut y_label = y_axis.y_label(latency_precision);
+    let mut y_label = y_axis.y_label(latency_precision, latency_aggregation);
     if log_scale {
         y_label = format!("{} [log-scale]", y_label);
     }
@@ -729,6 +1114,7 @@ pub fn inner_throughput_something_plot(
     searches: Vec<Search>,
     style_fun: &Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
     latency_precision: LatencyPrecision,
+    latency_aggregation: LatencyAggregation,
     n: usize,
     clients_per_region: Vec<usize>,
     x_range: Option<(f64, f64)>,
@@ -757,7 +1143,7 @@ pub fn inner_throughput_something_plot(
                         PlotFmt::protocol_name(search.protocol),
                         search.f
                     );
-                    values.push((0f64, 0f64));
+                    values.push((0f64, 0f64, false));
                     continue;
                 }
                 1 => (),
@@ -780,11 +1166,11 @@ pub fn inner_throughput_something_plot(
             let y_value = match y_axis {
                 ThroughputYAxis::Latency(latency) => match latency {
                     LatencyMetric::Average => exp_data
-                        .global_client_latency
+                        .global_client_latency_with(latency_aggregation)
                         .mean(latency_precision)
                         .round(),
                     LatencyMetric::Percentile(percentile) => exp_data
-                        .global_client_latency
+                        .global_client_tail_latency_with(latency_aggregation)
                         .percentile(percentile, latency_precision)
                         .round(),
                 },
@@ -797,15 +1183,30 @@ pub fn inner_throughput_something_plot(
                 }
             };
 
-            values.push((x_value, y_value));
+            // check whether client machines were saturated
+            let saturated = exp_data.clients_saturated();
+            if saturated {
+                eprintln!(
+                    "saturated clients for {} f = {} c = {} in {:?}",
+                    PlotFmt::protocol_name(search.protocol),
+                    search.f,
+                    clients,
+                    exp_data.saturated_client_regions
+                );
+            }
+
+            values.push((x_value, y_value, saturated));
         }
 
         // compute x: compute throughput given average latency and number of
         // clients
         let mut max_throughput = 0;
+        // also keep track of points where clients were saturated
+        let mut saturated_x = Vec::new();
+        let mut saturated_y = Vec::new();
         let (x, y): (Vec<_>, Vec<_>) = values
             .into_iter()
-            .filter_map(|(throughput, avg_latency)| {
+            .filter_map(|(throughput, avg_latency, saturated)| {
                 if throughput == 0f64 {
                     assert_eq!(avg_latency, 0f64);
                     None
@@ -818,6 +1219,11 @@ pub fn inner_throughput_something_plot(
 
                     // round y
                     let y = avg_latency.round() as usize;
+
+                    if saturated {
+                        saturated_x.push(x);
+                        saturated_y.push(y);
+                    }
                     Some((x, y))
                 }
             })
@@ -843,6 +1249,18 @@ pub fn inner_throughput_something_plot(
             ax.plot(x, y, None, Some(kwargs))?;
             *plotted += 1;
         }
+
+        // mark points where clients were saturated
+        if !saturated_x.is_empty() {
+            let kwargs = pydict!(
+                py,
+                ("color", "red"),
+                ("marker", "x"),
+                ("markersize", 8),
+                ("linestyle", "None")
+            );
+            ax.plot(saturated_x, saturated_y, None, Some(kwargs))?;
+        }
     }
 
     // maybe set x limits
@@ -1236,6 +1654,7 @@ pub fn throughput_latency_plot_split<GInput, G, RInput, R>(
     search_refine: R,
     style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
     latency_precision: LatencyPrecision,
+    latency_aggregation: LatencyAggregation,
     x_range: Option<(f64, f64)>,
     y_range: Option<(f64, f64)>,
     y_log_scale: bool,
@@ -1297,6 +1716,7 @@ where
             searches,
             &style_fun,
             latency_precision,
+            latency_aggregation,
             n,
             clients_per_region.clone(),
             x_range,
@@ -1340,9 +1760,17 @@ where
         let ylabel = if y_log_scale {
             // set log scale on y axis
             set_log_scale(py, &ax, AxisToScale::Y)?;
-            format!("latency ({}) [log-scale]", latency_precision.name())
+            format!(
+                "latency ({}, {}) [log-scale]",
+                latency_precision.name(),
+                latency_aggregation.name()
+            )
         } else {
-            format!("latency ({})", latency_precision.name())
+            format!(
+                "latency ({}, {})",
+                latency_precision.name(),
+                latency_aggregation.name()
+            )
         };
         ax.set_ylabel(&ylabel, None)?;
     }
@@ -1396,6 +1824,7 @@ where
         (3, false, false, true, HeatmapMetric::NetSend),
     ] {
         let ax = plt.subplot(1, 3, subplot, None)?;
+        let title = heatmap_metric.name();
         inner_heatmap_plot(
             py,
             &fig,
@@ -1414,7 +1843,7 @@ where
             db,
         )?;
 
-        ax.set_title(&heatmap_metric.name())?;
+        ax.set_title(&title)?;
     }
 
     // end plot
@@ -1444,6 +1873,10 @@ pub fn inner_heatmap_plot<F>(
 where
     F: Fn(&mut Search, KeyGen),
 {
+    // expression to be computed in each cell (protocol metrics are always
+    // computed from the global protocol metrics, while dstat metrics are
+    // computed from the dstats selected below)
+    let expr = heatmap_metric.expr();
     // data for all rows
     let mut rows = Vec::with_capacity(protocols.len());
     // all labels
@@ -1502,13 +1935,7 @@ where
             };
 
             // get data
-            let value = match heatmap_metric {
-                HeatmapMetric::NetSend => dstats.net_send.mean(),
-                HeatmapMetric::NetRecv => dstats.net_recv.mean(),
-                HeatmapMetric::CPU => {
-                    dstats.cpu_usr.mean() + dstats.cpu_sys.mean()
-                }
-            };
+            let value = expr.eval(exp_data, dstats);
             let utilization = heatmap_metric.utilization(value);
             row_data.push(utilization);
         }
@@ -1548,7 +1975,7 @@ where
         let kwargs =
             pydict!(py, ("cax", cbar_ax.ax()), ("orientation", "horizontal"),);
         let cbar = fig.colorbar(im.im(), Some(kwargs))?;
-        cbar.set_label("utilization (%)", None)?;
+        cbar.set_label(&heatmap_metric.colorbar_label(), None)?;
         cbar.set_ticks(vec![0, 25, 50, 75, 100], None)?;
         cbar.set_ticklabels(vec![0, 25, 50, 75, 100], None)?;
     }
@@ -1616,10 +2043,172 @@ where
     Ok(())
 }
 
-pub fn dstat_table(
+/// Plots, for each search, a heatmap of the p99 latency with the conflict rate
+/// on one axis and the number of clients per region on the other, with
+/// contour lines on top. Searches shouldn't set a key generator nor the number
+/// of clients per region: the p99 latency in each cell is the average across
+/// all the runs (with a `KeyGen::ConflictPool`) matching the search with that
+/// conflict rate and number of clients.
+pub fn conflict_rate_latency_plot(
     searches: Vec<Search>,
-    metrics_type: MetricsType,
-    output_dir: Option<&str>,
+    conflict_rates: Vec<usize>,
+    clients_per_region: Vec<usize>,
+    latency_precision: LatencyPrecision,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<(), Report> {
+    // compute the p99 latency in each cell of each search; missing cells are
+    // set to NaN, which matplotlib leaves blank
+    let mut all_rows = Vec::with_capacity(searches.len());
+    for search in searches.iter() {
+        let groups = db.group_by(*search, |exp_config| {
+            let clients = exp_config.clients_per_region.uniform_count()?;
+            match exp_config.workload.key_gen() {
+                KeyGen::ConflictPool { conflict_rate, .. } => {
+                    Some((conflict_rate, clients))
+                }
+                _ => None,
+            }
+        })?;
+
+        let mut rows = Vec::with_capacity(conflict_rates.len());
+        for &conflict_rate in conflict_rates.iter() {
+            let mut row = Vec::with_capacity(clients_per_region.len());
+            for &clients in clients_per_region.iter() {
+                let p99 = match groups.get(&(conflict_rate, clients)) {
+                    Some(runs) => {
+                        let sum: f64 = runs
+                            .iter()
+                            .map(|exp_data| {
+                                exp_data
+                                    .global_client_tail_latency()
+                                    .percentile(0.99, latency_precision)
+                            })
+                            .sum();
+                        sum / runs.len() as f64
+                    }
+                    None => {
+                        eprintln!(
+                            "missing data for {} f = {} conflict = {} c = {}",
+                            PlotFmt::protocol_name(search.protocol),
+                            search.f,
+                            conflict_rate,
+                            clients
+                        );
+                        f64::NAN
+                    }
+                };
+                row.push(p99);
+            }
+            println!(
+                "{:<7} f = {} | conflict = {} | p99 -> {:?}",
+                PlotFmt::protocol_name(search.protocol),
+                search.f,
+                conflict_rate,
+                row
+            );
+            rows.push(row);
+        }
+        all_rows.push(rows);
+    }
+
+    // all heatmaps share the same color scale
+    let values = all_rows.iter().flatten().flatten().copied();
+    let values: Vec<_> = values.filter(|value| !value.is_nan()).collect();
+    if values.is_empty() {
+        eprintln!("no data to plot in {}", output_file);
+        return Ok(());
+    }
+    let vmin = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let vmax = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    // start python
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let plt = PyPlot::new(py)?;
+
+    // start plot:
+    // - increase bottom margin to make room for the colorbar
+    // - adjust horizontal space between the plots
+    let kwargs = pydict!(py, ("bottom", 0.38), ("wspace", 0.1));
+    let (fig, _) = start_plot(py, &plt, Some(kwargs))?;
+
+    for (index, (search, rows)) in searches.iter().zip(all_rows).enumerate() {
+        let ax = plt.subplot(1, searches.len(), index + 1, None)?;
+        ax.set_title(&PlotFmt::label(search.protocol, search.f))?;
+
+        // list of colormaps: https://matplotlib.org/tutorials/colors/colormaps.html
+        let kwargs = pydict!(
+            py,
+            ("cmap", "afmhot_r"),
+            ("vmin", vmin),
+            ("vmax", vmax),
+            ("origin", "lower"),
+            ("aspect", "auto")
+        );
+        let im = ax.imshow(rows.clone(), Some(kwargs))?;
+
+        // create colorbar (once, as all heatmaps share the same color scale)
+        if index == 0 {
+            let cbar_ax = fig.add_axes(vec![0.25, 0.08, 0.5, 0.04])?;
+            let kwargs = pydict!(
+                py,
+                ("cax", cbar_ax.ax()),
+                ("orientation", "horizontal")
+            );
+            let cbar = fig.colorbar(im.im(), Some(kwargs))?;
+            let label = format!("p99 latency ({})", latency_precision.name());
+            cbar.set_label(&label, None)?;
+        }
+
+        // contour lines need at least two rows and two columns
+        if conflict_rates.len() > 1 && clients_per_region.len() > 1 {
+            let kwargs = pydict!(
+                py,
+                ("colors", "black"),
+                ("linewidths", 0.8),
+                ("levels", 4)
+            );
+            let contour_set = ax.contour(rows, Some(kwargs))?;
+            let kwargs = pydict!(py, ("fmt", "%.0f"), ("fontsize", 7));
+            ax.clabel(contour_set, Some(kwargs))?;
+        }
+
+        // set xticks and xlabels
+        let xticks: Vec<_> = (0..clients_per_region.len()).collect();
+        ax.set_xticks(xticks, None)?;
+        let kwargs = pydict!(
+            py,
+            ("rotation", 50),
+            ("horizontalalignment", "right"),
+            ("rotation_mode", "anchor")
+        );
+        ax.set_xticklabels(clients_per_region.clone(), Some(kwargs))?;
+        ax.set_xlabel("clients per region", None)?;
+
+        // set yticks and ylabels (only in the first plot)
+        let yticks: Vec<_> = (0..conflict_rates.len()).collect();
+        ax.set_yticks(yticks, None)?;
+        if index == 0 {
+            ax.set_yticklabels(conflict_rates.clone(), None)?;
+            ax.set_ylabel("conflict rate (%)", None)?;
+        } else {
+            let kwargs = pydict!(py, ("labelleft", false));
+            ax.tick_params(Some(kwargs))?;
+        }
+    }
+
+    // end plot
+    end_plot(true, output_dir, output_file, py, &plt, Some(fig))?;
+
+    Ok(())
+}
+
+pub fn dstat_table(
+    searches: Vec<Search>,
+    metrics_type: MetricsType,
+    output_dir: Option<&str>,
     output_file: &str,
     db: &ResultsDB,
 ) -> Result<(), Report> {
@@ -1713,6 +2302,7 @@ pub fn dstat_table(
         col_widths,
         row_labels,
         cells,
+        None,
         output_dir,
         output_file,
     )
@@ -1736,13 +2326,18 @@ pub fn process_metrics_table(
         "deps size",
         // TEMPO/EPAXOS:
         "command key count",
+        // TEMPO:
+        "read retry (%)",
+        // ATLAS/EPAXOS:
+        "commuted deps",
         /* ATLAS/EPAXOS/JANUS:
          * "chains",
          * "out",
          * "in", */
     ];
     let col_labels = col_labels.into_iter().map(String::from).collect();
-    let col_widths = vec![0.09, 0.09, 0.07, 0.10, 0.27, 0.23, 0.23];
+    let col_widths =
+        vec![0.09, 0.09, 0.07, 0.10, 0.21, 0.20, 0.20, 0.12, 0.12];
     // let col_widths = vec![0.11, 0.11, 0.07, 0.11, 0.68];
 
     // actual data
@@ -1869,6 +2464,23 @@ pub fn process_metrics_table(
                     command_key_count.max().value().round()
                 )
             });
+        // number of shards consulted again per 100 multi-shard reads (only
+        // available if tempo's multi-shard reads were enabled)
+        let read_retry_rate = protocol_metrics
+            .get_aggregated(ProtocolMetricsKind::MultiShardReads)
+            .filter(|reads| **reads > 0)
+            .map(|reads| {
+                let retries = protocol_metrics
+                    .get_aggregated(ProtocolMetricsKind::MultiShardReadRetries)
+                    .cloned()
+                    .unwrap_or_default();
+                format!("{:.1}", (retries * 100) as f64 / *reads as f64)
+            });
+        // number of dependencies avoided because commands commuted (only
+        // available with commutative ops)
+        let commuted_deps = protocol_metrics
+            .get_aggregated(ProtocolMetricsKind::CommutedDeps)
+            .map(|commuted| fmt(*commuted));
         // create cell
         let cell = vec![
             fast_path,
@@ -1879,6 +2491,8 @@ pub fn process_metrics_table(
             // execution_delay,
             deps_size,
             command_key_count,
+            read_retry_rate,
+            commuted_deps,
             /*
             chain_size,
             out_requests,
@@ -1911,169 +2525,1287 @@ pub fn process_metrics_table(
         col_widths,
         row_labels,
         cells,
+        None,
         output_dir,
         output_file,
     )
 }
 
-fn table(
-    plotted: usize,
-    col_labels: Vec<String>,
-    col_widths: Vec<f64>,
-    row_labels: Vec<String>,
-    cells: Vec<Vec<String>>,
+/// Plots, for each process in the experiment matching `search`, the average
+/// stability lag (i.e. the number of commands committed but not yet known to
+/// be stable) in each second of the experiment. This helps choosing the
+/// garbage collection interval.
+pub fn stability_lag_plot(
+    search: Search,
     output_dir: Option<&str>,
     output_file: &str,
+    db: &ResultsDB,
 ) -> Result<(), Report> {
-    if plotted > 0 {
-        // start python
-        let gil = Python::acquire_gil();
-        let py = gil.python();
-        let plt = PyPlot::new(py)?;
+    // max number of processes for which a legend is shown
+    const MAX_LEGEND_ENTRIES: usize = 12;
 
-        // create table arguments
-        let kwargs = pydict!(
-            py,
-            ("colLabels", col_labels),
-            ("colWidths", col_widths),
-            ("rowLabels", row_labels),
-            ("cellText", cells),
-            ("colLoc", "center"),
-            ("cellLoc", "center"),
-            ("rowLoc", "right"),
-            ("loc", "center"),
+    let exp_data = if let Some(exp_data) = find_single(db, search)? {
+        exp_data
+    } else {
+        eprintln!(
+            "missing data for {} f = {}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f
         );
+        return Ok(());
+    };
 
-        let table = plt.table(Some(kwargs))?;
-        plt.axis("off")?;
+    // compute, for each process, the average lag in each second
+    let mut timelines = BTreeMap::new();
+    for (process_id, (_, process_metrics)) in exp_data.process_metrics.iter() {
+        let timeline: BTreeMap<_, _> = process_metrics
+            .protocol_metrics()
+            .collected()
+            .filter_map(|(kind, lag)| match kind {
+                ProtocolMetricsKind::StabilityLagAt(second) => {
+                    Some((*second, lag.mean().value()))
+                }
+                _ => None,
+            })
+            .collect();
+        if !timeline.is_empty() {
+            timelines.insert(*process_id, timeline);
+        }
+    }
 
-        // create font size font
-        table.auto_set_font_size(false)?;
-        table.set_fontsize(6.5)?;
+    // seconds are shown relative to the first sample in the experiment
+    let start = timelines
+        .values()
+        .filter_map(|timeline| timeline.keys().next())
+        .min()
+        .cloned()
+        .unwrap_or_default();
 
-        // row labels are too wide without this
-        plt.tight_layout()?;
+    // start python
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let plt = PyPlot::new(py)?;
 
-        // end plot
-        end_plot(plotted > 0, output_dir, output_file, py, &plt, None)?;
-    }
-    Ok(())
-}
+    // start plot
+    let (fig, ax) = start_plot(py, &plt, None)?;
 
-// percentiles of interest
-fn percentiles() -> impl Iterator<Item = f64> {
-    (10..=60)
-        .step_by(10)
-        .chain((65..=95).step_by(5))
-        .map(|percentile| percentile as f64 / 100f64)
-        .chain(vec![
-            0.96, 0.97, 0.98, 0.984, 0.988, 0.992, 0.996, 0.999, 0.9999,
-        ])
-}
+    // keep track of the number of plotted instances
+    let mut plotted = 0;
 
-// https://matplotlib.org/3.3.1/api/_as_gen/matplotlib.pyplot.subplots_adjust.html?highlight=subplots_adjust#matplotlib.pyplot.subplots_adjust
-pub fn start_plot<'a>(
-    py: Python<'a>,
-    plt: &'a PyPlot<'a>,
-    kwargs: Option<&PyDict>,
-) -> Result<(Figure<'a>, Axes<'a>), Report> {
-    let (fig, ax) = plt.subplots(None)?;
-    // create empty arguments if no arguments were provided
-    let kwargs = if let Some(kwargs) = kwargs {
-        kwargs
-    } else {
-        pyo3::types::PyDict::new(py)
-    };
-    // set adjust top and adjust bottom defaults if not set
-    if !pytry!(py, kwargs.contains("top")) {
-        pytry!(py, kwargs.set_item("top", ADJUST_TOP));
+    for (process_id, timeline) in timelines {
+        let (x, y): (Vec<_>, Vec<_>) = timeline
+            .into_iter()
+            .map(|(second, lag)| (second - start, lag))
+            .unzip();
+        let label = format!("p{}", process_id);
+        let kwargs = pydict!(py, ("label", label), ("linewidth", 1));
+        ax.plot(x, y, None, Some(kwargs))?;
+        plotted += 1;
     }
-    if !pytry!(py, kwargs.contains("bottom")) {
-        pytry!(py, kwargs.set_item("bottom", ADJUST_BOTTOM));
+
+    // set labels
+    ax.set_xlabel("time (s)", None)?;
+    ax.set_ylabel("stability lag (commands)", None)?;
+
+    // legend
+    if plotted <= MAX_LEGEND_ENTRIES {
+        add_legend(plotted, None, None, None, None, py, &ax)?;
     }
-    fig.subplots_adjust(Some(kwargs))?;
 
-    Ok((fig, ax))
+    // end plot
+    end_plot(plotted > 0, output_dir, output_file, py, &plt, Some(fig))?;
+    Ok(())
 }
 
-pub fn end_plot(
-    something_plotted: bool,
+/// Creates a table with all the protocol and executor metrics of the
+/// experiments matching `left` and `right` side-by-side, sorted by their
+/// relative difference. The `highlight` metrics with the largest relative
+/// difference are highlighted.
+pub fn metrics_diff_table(
+    left: Search,
+    right: Search,
+    highlight: usize,
     output_dir: Option<&str>,
     output_file: &str,
-    py: Python<'_>,
-    plt: &PyPlot<'_>,
-    fig: Option<Figure<'_>>,
+    db: &ResultsDB,
 ) -> Result<(), Report> {
-    if !something_plotted {
-        // if nothing was plotted, just close the current figure
-        return plt.close(None);
-    };
+    let (left_data, right_data) =
+        match (find_single(db, left)?, find_single(db, right)?) {
+            (Some(left_data), Some(right_data)) => (left_data, right_data),
+            _ => {
+                eprintln!(
+                    "missing data for metrics diff: left {:?} | right {:?}",
+                    left, right
+                );
+                return Ok(());
+            }
+        };
 
-    // maybe save `output_file` in `output_dir` (if one was set)
-    let output_file = if let Some(output_dir) = output_dir {
-        // make sure `output_dir` exists
-        std::fs::create_dir_all(&output_dir).wrap_err("create plot dir")?;
-        format!("{}/{}", output_dir, output_file)
-    } else {
-        output_file.to_string()
-    };
+    let col_labels = vec![
+        format!("{} f = {}", PlotFmt::protocol_name(left.protocol), left.f),
+        format!("{} f = {}", PlotFmt::protocol_name(right.protocol), right.f),
+        String::from("diff (%)"),
+    ];
+    let col_widths = vec![0.25, 0.25, 0.15];
 
-    // reduce right margin
-    let kwargs = pydict!(py, ("right", 0.95));
-    plt.subplots_adjust(Some(kwargs))?;
+    // actual data
+    let mut cells = Vec::new();
+    let mut cell_colors = Vec::new();
 
-    // save figure
-    let kwargs = pydict!(py, ("format", "pdf"));
-    plt.savefig(&output_file, Some(kwargs))?;
+    // metric labels
+    let mut row_labels = Vec::new();
 
-    let kwargs = if let Some(fig) = fig {
-        // close the figure passed as argument
-        Some(pydict!(py, ("fig", fig.fig())))
-    } else {
-        // close the current figure
-        None
+    let fmt_value = |value: Option<f64>| {
+        value
+            .map(|value| format!("{:.1}", value))
+            .unwrap_or_else(|| String::from("NA"))
     };
-    plt.close(kwargs)?;
-    Ok(())
+    let diffs = metrics_diff(left_data, right_data);
+    for (index, diff) in diffs.iter().enumerate() {
+        let relative_diff = diff.relative_diff();
+        let relative_diff = if relative_diff.is_finite() {
+            format!("{:+.1}", relative_diff * 100f64)
+        } else {
+            String::from("NA")
+        };
+        println!(
+            "{:<32} | {:>14} | {:>14} | {:>8}",
+            diff.name,
+            fmt_value(diff.left),
+            fmt_value(diff.right),
+            relative_diff
+        );
+
+        let color = if index < highlight { "orange" } else { "white" };
+        cells.push(vec![
+            fmt_value(diff.left),
+            fmt_value(diff.right),
+            relative_diff,
+        ]);
+        cell_colors.push(vec![color; 3]);
+        row_labels.push(diff.name.clone());
+    }
+
+    let plotted = diffs.len();
+    table(
+        plotted,
+        col_labels,
+        col_widths,
+        row_labels,
+        cells,
+        Some(cell_colors),
+        output_dir,
+        output_file,
+    )
 }
 
-/// `legends` is a mapping from legend order to a pair of the matplotlib object
-/// and its legend.
-pub fn add_legend(
-    plotted: usize,
-    legends: Option<BTreeMap<usize, (&PyAny, String)>>,
-    x_bbox_to_anchor: Option<f64>,
-    y_bbox_to_anchor: Option<f64>,
-    column_spacing: Option<f64>,
-    py: Python<'_>,
-    ax: &Axes<'_>,
+/// Creates a table that compares the throughput, the average latency and the
+/// latency `percentiles` observed by clients in the repeated runs of two
+/// experiments. Runs are paired by their experiment seed (and then by their
+/// timestamp), and the differences with a p-value (of a paired t-test) below
+/// `alpha` are highlighted.
+pub fn significance_table(
+    left: Search,
+    right: Search,
+    percentiles: Vec<f64>,
+    alpha: f64,
+    latency_precision: LatencyPrecision,
+    latency_aggregation: LatencyAggregation,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
 ) -> Result<(), Report> {
-    if plotted == 0 {
+    let left_runs = find_runs(db, left)?;
+    let right_runs = find_runs(db, right)?;
+    if left_runs.len() < 2 || left_runs.len() != right_runs.len() {
+        eprintln!(
+            "can't pair runs for significance tests: left {:?} ({} runs) | right {:?} ({} runs)",
+            left,
+            left_runs.len(),
+            right,
+            right_runs.len()
+        );
         return Ok(());
     }
-    // default values for `y_bbox_to_anchor`
-    let one_row = 1.19;
-    let two_rows = 1.3;
-
-    let (legend_ncol, y_bbox_to_anchor_default) = match plotted {
-        0 => (0, 0.0),
-        1 => (1, one_row),
-        2 => (2, one_row),
-        3 => (3, one_row),
-        4 => (2, two_rows),
-        5 => (3, two_rows),
-        6 => (3, two_rows),
-        7 => (4, two_rows),
-        8 => (4, two_rows),
-        9 => (5, two_rows),
-        10 => (5, two_rows),
-        11 => (6, two_rows),
-        12 => (6, two_rows),
-        _ => panic!(
-            "add_legend: unsupported number of plotted instances: {}",
-            plotted
-        ),
-    };
+
+    let col_labels = vec![
+        format!("{} f = {}", PlotFmt::protocol_name(left.protocol), left.f),
+        format!("{} f = {}", PlotFmt::protocol_name(right.protocol), right.f),
+        String::from("diff (%)"),
+        String::from("p-value"),
+    ];
+    let col_widths = vec![0.2, 0.2, 0.15, 0.15];
+
+    // actual data
+    let mut cells = Vec::new();
+    let mut cell_colors = Vec::new();
+
+    // metric labels
+    let mut row_labels = Vec::new();
+
+    let comparisons = compare_runs(
+        &left_runs,
+        &right_runs,
+        &percentiles,
+        latency_precision,
+        latency_aggregation,
+    );
+    for comparison in comparisons.iter() {
+        let left_mean = comparison.left_mean();
+        let right_mean = comparison.right_mean();
+        let relative_diff = if left_mean != 0f64 {
+            format!("{:+.1}", (right_mean - left_mean) / left_mean * 100f64)
+        } else {
+            String::from("NA")
+        };
+        let (p_value, significant) = match comparison.test {
+            Some(test) => {
+                (format!("{:.3}", test.p_value), test.significant(alpha))
+            }
+            None => (String::from("NA"), false),
+        };
+        println!(
+            "{:<24} | {:>14.1} | {:>14.1} | {:>8} | {:>8}{}",
+            comparison.name,
+            left_mean,
+            right_mean,
+            relative_diff,
+            p_value,
+            if significant { " *" } else { "" }
+        );
+
+        let color = if significant { "orange" } else { "white" };
+        cells.push(vec![
+            format!("{:.1}", left_mean),
+            format!("{:.1}", right_mean),
+            relative_diff,
+            p_value,
+        ]);
+        cell_colors.push(vec![color; 4]);
+        row_labels.push(comparison.name.clone());
+    }
+
+    let plotted = comparisons.len();
+    table(
+        plotted,
+        col_labels,
+        col_widths,
+        row_labels,
+        cells,
+        Some(cell_colors),
+        output_dir,
+        output_file,
+    )
+}
+
+/// Creates a table with, for each search, the maximum throughput sustained
+/// (across `clients_per_region`) while keeping the p99 latency under `slo`.
+pub fn slo_throughput_table(
+    searches: Vec<Search>,
+    clients_per_region: Vec<usize>,
+    slo: Duration,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<(), Report> {
+    let col_labels = vec![
+        "max tput (K ops/s)",
+        "clients per region",
+        "p99 latency (ms)",
+    ];
+    let col_labels = col_labels.into_iter().map(String::from).collect();
+    let col_widths = vec![0.25, 0.25, 0.25];
+
+    // actual data
+    let mut cells = Vec::with_capacity(searches.len());
+
+    // protocol labels
+    let mut row_labels = Vec::with_capacity(searches.len());
+
+    let slo_micros = slo.as_micros() as f64;
+    let mut plotted = 0;
+    for mut search in searches {
+        // find the number of clients with the highest throughput among the
+        // ones with p99 latency under the slo
+        let mut best: Option<(f64, usize, f64)> = None;
+        for &clients in clients_per_region.iter() {
+            search.clients_per_region(clients);
+            let exp_data = match find_single(db, search)? {
+                Some(exp_data) => exp_data,
+                None => {
+                    eprintln!(
+                        "missing data for {} f = {} c = {}",
+                        PlotFmt::protocol_name(search.protocol),
+                        search.f,
+                        clients
+                    );
+                    continue;
+                }
+            };
+            let throughput = exp_data.global_client_throughput;
+            let p99 = exp_data
+                .global_client_tail_latency()
+                .percentile(0.99, LatencyPrecision::Micros);
+            let better = best
+                .map(|(best_throughput, _, _)| throughput > best_throughput)
+                .unwrap_or(true);
+            if p99 <= slo_micros && better {
+                best = Some((throughput, clients, p99));
+            }
+        }
+
+        let cell = match best {
+            Some((throughput, clients, p99)) => vec![
+                format!("{:.1}", throughput / 1000f64),
+                clients.to_string(),
+                format!("{:.1}", p99 / 1000f64),
+            ],
+            None => vec![String::from("NA"); 3],
+        };
+        println!(
+            "{:<7} f = {} | slo = {:?} | {:?}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f,
+            slo,
+            cell
+        );
+
+        // save cell
+        cells.push(cell);
+
+        // create row label
+        let row_label = format!(
+            "{} f = {}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f
+        );
+        row_labels.push(row_label);
+
+        // mark that there's data to be plotted
+        plotted += 1;
+    }
+
+    table(
+        plotted,
+        col_labels,
+        col_widths,
+        row_labels,
+        cells,
+        None,
+        output_dir,
+        output_file,
+    )
+}
+
+/// Creates a table with, for each search, the saturation point (knee) of its
+/// throughput-latency curve across `clients_per_region` (see `Knee`): the
+/// maximum useful throughput, i.e. the highest one sustained before
+/// `latency_metric` exceeds `knee_factor` times its value at the lowest load.
+pub fn knee_table(
+    searches: Vec<Search>,
+    clients_per_region: Vec<usize>,
+    latency_metric: LatencyMetric,
+    knee_factor: f64,
+    latency_precision: LatencyPrecision,
+    latency_aggregation: LatencyAggregation,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<Vec<(Search, Knee)>, Report> {
+    let latency_name = match latency_metric {
+        LatencyMetric::Average => String::from("avg"),
+        LatencyMetric::Percentile(percentile) => {
+            format!("p{}", percentile * 100f64)
+        }
+    };
+    let col_labels = vec![
+        String::from("max useful tput (K ops/s)"),
+        String::from("clients per region"),
+        format!(
+            "{} latency ({}, {})",
+            latency_name,
+            latency_precision.name(),
+            latency_aggregation.name()
+        ),
+        format!("baseline ({})", latency_precision.name()),
+    ];
+    let col_widths = vec![0.25, 0.2, 0.2, 0.2];
+
+    // actual data
+    let mut cells = Vec::with_capacity(searches.len());
+
+    // protocol labels
+    let mut row_labels = Vec::with_capacity(searches.len());
+
+    let mut knees = Vec::with_capacity(searches.len());
+    for mut search in searches {
+        // gather the curve's points
+        let mut points = Vec::with_capacity(clients_per_region.len());
+        for &clients in clients_per_region.iter() {
+            search.clients_per_region(clients);
+            let exp_data = match find_single(db, search)? {
+                Some(exp_data) => exp_data,
+                None => {
+                    eprintln!(
+                        "missing data for {} f = {} c = {}",
+                        PlotFmt::protocol_name(search.protocol),
+                        search.f,
+                        clients
+                    );
+                    continue;
+                }
+            };
+            let latency = match latency_metric {
+                LatencyMetric::Average => exp_data
+                    .global_client_latency_with(latency_aggregation)
+                    .mean(latency_precision),
+                LatencyMetric::Percentile(percentile) => exp_data
+                    .global_client_tail_latency_with(latency_aggregation)
+                    .percentile(percentile, latency_precision),
+            };
+            points.push((clients, exp_data.global_client_throughput, latency));
+        }
+
+        let knee = Knee::detect(&points, knee_factor);
+        let cell = match knee {
+            Some(knee) => {
+                // if the curve never saturates, the max useful throughput is
+                // just a lower bound
+                let bound = if knee.saturated { "" } else { ">= " };
+                vec![
+                    format!(
+                        "{}{:.1}",
+                        bound,
+                        knee.max_useful_throughput / 1000f64
+                    ),
+                    knee.clients.to_string(),
+                    format!("{:.1}", knee.latency),
+                    format!("{:.1}", knee.baseline_latency),
+                ]
+            }
+            None => vec![String::from("NA"); 4],
+        };
+        println!(
+            "{:<7} f = {} | knee = {}x | {:?}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f,
+            knee_factor,
+            cell
+        );
+
+        // save cell
+        cells.push(cell);
+
+        // create row label
+        let row_label = format!(
+            "{} f = {}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f
+        );
+        row_labels.push(row_label);
+
+        if let Some(knee) = knee {
+            search.clients_per_region(knee.clients);
+            knees.push((search, knee));
+        }
+    }
+
+    table(
+        cells.len(),
+        col_labels,
+        col_widths,
+        row_labels,
+        cells,
+        None,
+        output_dir,
+        output_file,
+    )?;
+    Ok(knees)
+}
+
+/// Creates a table with, for each search, the key-lock contention observed by
+/// processes (only tracked by the `Locked` protocol variants): the number of
+/// key-lock acquisitions, the percentage of them that had to wait, the wait
+/// time percentiles, and the share of the total wait time spent on the
+/// `hot_keys` keys with the highest wait time.
+pub fn key_lock_table(
+    searches: Vec<Search>,
+    hot_keys: usize,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<(), Report> {
+    let col_labels = vec![
+        String::from("acquisitions"),
+        String::from("contended (%)"),
+        String::from("p50 wait (us)"),
+        String::from("p99 wait (us)"),
+        format!("top-{} keys wait (%)", hot_keys),
+    ];
+    let col_widths = vec![0.2, 0.2, 0.2, 0.2, 0.2];
+
+    // actual data
+    let mut cells = Vec::with_capacity(searches.len());
+
+    // protocol labels
+    let mut row_labels = Vec::with_capacity(searches.len());
+
+    let mut plotted = 0;
+    for search in searches {
+        let exp_data = match find_single(db, search)? {
+            Some(exp_data) => exp_data,
+            None => {
+                eprintln!(
+                    "missing data for {} f = {}",
+                    PlotFmt::protocol_name(search.protocol),
+                    search.f
+                );
+                continue;
+            }
+        };
+        let metrics = &exp_data.global_protocol_metrics;
+
+        let acquisitions = metrics
+            .get_aggregated(ProtocolMetricsKind::KeyLockAcquisitions)
+            .cloned()
+            .unwrap_or_default();
+        let cell = if acquisitions == 0 {
+            vec![String::from("NA"); 5]
+        } else {
+            let (contended, p50, p99) = metrics
+                .get_collected(ProtocolMetricsKind::KeyLockWait)
+                .map(|waits| {
+                    (
+                        waits.count(),
+                        waits.percentile(0.5).value(),
+                        waits.percentile(0.99).value(),
+                    )
+                })
+                .unwrap_or_default();
+
+            // total wait time on each key, sorted from highest to lowest
+            let mut key_waits: Vec<_> = metrics
+                .aggregated()
+                .filter_map(|(kind, wait)| match kind {
+                    ProtocolMetricsKind::KeyLockWaitOn(_) => Some(*wait),
+                    _ => None,
+                })
+                .collect();
+            key_waits.sort_unstable_by(|a, b| b.cmp(a));
+            let total_wait = key_waits.iter().sum::<u64>();
+            let hot_keys_wait = key_waits.iter().take(hot_keys).sum::<u64>();
+            let hot_keys_share = if total_wait > 0 {
+                hot_keys_wait as f64 * 100f64 / total_wait as f64
+            } else {
+                0f64
+            };
+
+            vec![
+                acquisitions.to_string(),
+                format!(
+                    "{:.1}",
+                    contended as f64 * 100f64 / acquisitions as f64
+                ),
+                format!("{:.1}", p50),
+                format!("{:.1}", p99),
+                format!("{:.1}", hot_keys_share),
+            ]
+        };
+        println!(
+            "{:<7} f = {} | {:?}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f,
+            cell
+        );
+
+        // save cell
+        cells.push(cell);
+
+        // create row label
+        let row_label = format!(
+            "{} f = {}",
+            PlotFmt::protocol_name(search.protocol),
+            search.f
+        );
+        row_labels.push(row_label);
+
+        // mark that there's data to be plotted
+        plotted += 1;
+    }
+
+    table(
+        plotted,
+        col_labels,
+        col_widths,
+        row_labels,
+        cells,
+        None,
+        output_dir,
+        output_file,
+    )
+}
+
+/// Plots, for each search, the throughput observed by clients over time
+/// (relative to the time at which a region was failed) in an experiment with a
+/// region failure. Returns, for each search, the throughput dip and the
+/// time-to-recovery.
+pub fn failover_plot(
+    searches: Vec<Search>,
+    style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<Vec<(Search, String)>, Report> {
+    // throughput is considered recovered once it's above this fraction of the
+    // throughput before the failure
+    const RECOVERY_THRESHOLD: f64 = 0.9;
+
+    // start python
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let plt = PyPlot::new(py)?;
+
+    // start plot
+    let (fig, ax) = start_plot(py, &plt, None)?;
+
+    // keep track of the number of plotted instances
+    let mut plotted = 0;
+
+    let mut results = Vec::with_capacity(searches.len());
+    for search in searches {
+        let mut matches = db.find(search)?;
+        assert!(
+            matches.len() <= 1,
+            "found more than 1 matching experiment for this search criteria: search {:?}",
+            search
+        );
+        let (exp_config, exp_data) = match matches.pop() {
+            Some((_, exp_config, exp_data)) => (exp_config, exp_data),
+            None => {
+                eprintln!(
+                    "missing data for {} f = {}",
+                    PlotFmt::protocol_name(search.protocol),
+                    search.f
+                );
+                continue;
+            }
+        };
+        let failure_time =
+            if let Some(failure_time) = exp_config.region_failure_time {
+                // failure time is in millis
+                failure_time / 1000
+            } else {
+                eprintln!(
+                    "no region failure in {} f = {}",
+                    PlotFmt::protocol_name(search.protocol),
+                    search.f
+                );
+                continue;
+            };
+
+        // seconds are shown relative to the failure
+        let timeline = &exp_data.global_client_throughput_timeline;
+        let (x, y): (Vec<_>, Vec<_>) = timeline
+            .iter()
+            .map(|(second, ops)| {
+                (*second as i64 - failure_time as i64, *ops as f64 / 1000f64)
+            })
+            .unzip();
+
+        // compute the throughput before the failure, the lowest throughput
+        // after the failure, and the first second after the failure in which
+        // throughput recovered
+        let (before, after): (Vec<_>, Vec<_>) =
+            x.iter().zip(y.iter()).partition(|(second, _)| **second < 0);
+        let before_count = before.len() as f64;
+        let before = before.into_iter().map(|(_, tput)| tput).sum::<f64>()
+            / before_count;
+        let dip = after
+            .iter()
+            .map(|(_, tput)| **tput)
+            .fold(f64::INFINITY, f64::min);
+        let recovery = after
+            .iter()
+            .find(|(_, tput)| **tput >= before * RECOVERY_THRESHOLD)
+            .map(|(second, _)| format!("{}s", second))
+            .unwrap_or_else(|| String::from("never"));
+        let result = format!(
+            "before = {:.1}K | dip = {:.1}K ({:.1}%) | recovery = {}",
+            before,
+            dip,
+            (1f64 - dip / before) * 100f64,
+            recovery
+        );
+        results.push((search, result));
+
+        let kwargs = line_style(py, search, &style_fun)?;
+        pytry!(py, kwargs.set_item("marker", "None"));
+        ax.plot(x, y, None, Some(kwargs))?;
+        plotted += 1;
+    }
+
+    // mark the failure
+    let kwargs = pydict!(py, ("color", "black"), ("linestyle", "dashed"));
+    ax.axvline(0.0, Some(kwargs))?;
+
+    // set labels
+    ax.set_xlabel("time since failure (s)", None)?;
+    ax.set_ylabel("throughput (K ops/s)", None)?;
+
+    // legend
+    add_legend(plotted, None, None, None, None, py, &ax)?;
+
+    // end plot
+    end_plot(plotted > 0, output_dir, output_file, py, &plt, Some(fig))?;
+    Ok(results)
+}
+
+/// Plots, for each search, the fraction of the commands issued by clients that
+/// completed (i.e. didn't time out) in each second since the region failure.
+/// Returns, for each search, the lowest availability after the failure and for
+/// how long availability was below `AVAILABILITY_THRESHOLD`.
+pub fn availability_plot(
+    searches: Vec<Search>,
+    style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<Vec<(Search, String)>, Report> {
+    // clients are considered unavailable in the seconds in which less than
+    // this fraction of their commands completed
+    const AVAILABILITY_THRESHOLD: f64 = 0.99;
+
+    // start python
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let plt = PyPlot::new(py)?;
+
+    // start plot
+    let (fig, ax) = start_plot(py, &plt, None)?;
+
+    // keep track of the number of plotted instances
+    let mut plotted = 0;
+
+    let mut results = Vec::with_capacity(searches.len());
+    for search in searches {
+        let mut matches = db.find(search)?;
+        assert!(
+            matches.len() <= 1,
+            "found more than 1 matching experiment for this search criteria: search {:?}",
+            search
+        );
+        let (exp_config, exp_data) = match matches.pop() {
+            Some((_, exp_config, exp_data)) => (exp_config, exp_data),
+            None => {
+                eprintln!(
+                    "missing data for {} f = {}",
+                    PlotFmt::protocol_name(search.protocol),
+                    search.f
+                );
+                continue;
+            }
+        };
+        // without a command timeout, commands that never complete go
+        // unnoticed, and thus availability can't be computed
+        let command_timeout = exp_config
+            .region_failure
+            .as_ref()
+            .and_then(|region_failure| region_failure.command_timeout);
+        let failure_time = match (exp_config.region_failure_time, command_timeout)
+        {
+            // failure time is in millis
+            (Some(failure_time), Some(_)) => failure_time / 1000,
+            _ => {
+                eprintln!(
+                    "no region failure with command timeout in {} f = {}",
+                    PlotFmt::protocol_name(search.protocol),
+                    search.f
+                );
+                continue;
+            }
+        };
+
+        // seconds are shown relative to the failure
+        let timeline = &exp_data.global_client_availability_timeline;
+        let (x, y): (Vec<_>, Vec<_>) = timeline
+            .iter()
+            .map(|(second, availability)| {
+                (*second as i64 - failure_time as i64, *availability * 100f64)
+            })
+            .unzip();
+
+        // compute the lowest availability after the failure, and the number
+        // of seconds in which clients were unavailable
+        let after: Vec<_> = x
+            .iter()
+            .zip(y.iter())
+            .filter(|(second, _)| **second >= 0)
+            .map(|(_, availability)| *availability)
+            .collect();
+        let lowest = after.iter().cloned().fold(f64::INFINITY, f64::min);
+        let unavailable = after
+            .iter()
+            .filter(|availability| {
+                **availability < AVAILABILITY_THRESHOLD * 100f64
+            })
+            .count();
+        let result = format!(
+            "lowest = {:.1}% | unavailable = {}s",
+            lowest, unavailable
+        );
+        results.push((search, result));
+
+        let kwargs = line_style(py, search, &style_fun)?;
+        pytry!(py, kwargs.set_item("marker", "None"));
+        ax.plot(x, y, None, Some(kwargs))?;
+        plotted += 1;
+    }
+
+    // mark the failure
+    let kwargs = pydict!(py, ("color", "black"), ("linestyle", "dashed"));
+    ax.axvline(0.0, Some(kwargs))?;
+
+    // set y limits
+    let kwargs = pydict!(py, ("ymin", 0.0), ("ymax", 100.0));
+    ax.set_ylim(Some(kwargs))?;
+
+    // set labels
+    ax.set_xlabel("time since failure (s)", None)?;
+    ax.set_ylabel("availability (%)", None)?;
+
+    // legend
+    add_legend(plotted, None, None, None, None, py, &ax)?;
+
+    // end plot
+    end_plot(plotted > 0, output_dir, output_file, py, &plt, Some(fig))?;
+    Ok(results)
+}
+
+/// Plots, for each search, the throughput observed by clients (top) and the
+/// average RTT of the background ping mesh between all machines (bottom) over
+/// time, so that latency anomalies can be correlated with throughput dips.
+pub fn ping_plot(
+    searches: Vec<Search>,
+    style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<(), Report> {
+    // start python
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let plt = PyPlot::new(py)?;
+
+    // start plot:
+    // - adjust vertical space between the two plots
+    let kwargs = pydict!(py, ("hspace", 0.2));
+    let (fig, _) = start_plot(py, &plt, Some(kwargs))?;
+
+    // find the data of each search
+    let mut all_exp_data = Vec::with_capacity(searches.len());
+    for search in searches {
+        let mut matches = db.find(search)?;
+        assert!(
+            matches.len() <= 1,
+            "found more than 1 matching experiment for this search criteria: search {:?}",
+            search
+        );
+        match matches.pop() {
+            Some((_, exp_config, exp_data)) => {
+                if exp_config.ping_interval.is_none() {
+                    eprintln!(
+                        "no ping mesh in {} f = {}",
+                        PlotFmt::protocol_name(search.protocol),
+                        search.f
+                    );
+                    continue;
+                }
+                all_exp_data.push((search, exp_data));
+            }
+            None => {
+                eprintln!(
+                    "missing data for {} f = {}",
+                    PlotFmt::protocol_name(search.protocol),
+                    search.f
+                );
+            }
+        }
+    }
+
+    // keep track of the number of plotted instances
+    let mut plotted = 0;
+
+    // the throughput subplot is on top and shares the x-axis with the ping
+    // subplot
+    let bottom = plt.subplot(2, 1, 2, None)?;
+    let top =
+        plt.subplot(2, 1, 1, Some(pydict!(py, ("sharex", bottom.ax()))))?;
+
+    for (search, exp_data) in all_exp_data {
+        // seconds are shown relative to the start of the run
+        let timeline = &exp_data.global_client_throughput_timeline;
+        let start = match timeline.first() {
+            Some((second, _)) => *second as i64,
+            None => continue,
+        };
+        let (x, y): (Vec<_>, Vec<_>) = timeline
+            .iter()
+            .map(|(second, ops)| {
+                (*second as i64 - start, *ops as f64 / 1000f64)
+            })
+            .unzip();
+        let kwargs = line_style(py, search, &style_fun)?;
+        pytry!(py, kwargs.set_item("marker", "None"));
+        top.plot(x, y, None, Some(kwargs))?;
+
+        let (x, y): (Vec<_>, Vec<_>) = exp_data
+            .global_ping_timeline
+            .iter()
+            .map(|(second, rtt)| (*second as i64 - start, *rtt))
+            .unzip();
+        let kwargs = line_style(py, search, &style_fun)?;
+        pytry!(py, kwargs.set_item("marker", "None"));
+        bottom.plot(x, y, None, Some(kwargs))?;
+        plotted += 1;
+    }
+
+    // set labels (the x-axis is only shown on the bottom)
+    top.xaxis.set_visible(false)?;
+    top.set_ylabel("throughput (K ops/s)", None)?;
+    bottom.set_xlabel("time (s)", None)?;
+    bottom.set_ylabel("ping RTT (ms)", None)?;
+
+    // legend
+    add_legend(plotted, None, None, None, None, py, &top)?;
+
+    // end plot
+    end_plot(plotted > 0, output_dir, output_file, py, &plt, Some(fig))?;
+    Ok(())
+}
+
+/// Generates two plots with the cost of inter-shard dependencies (in the graph
+/// executor) as the number of shards increases:
+/// - the average latency of requests for dependencies to other shards
+/// - the percentage of stalls in SCC formation caused by (at least one)
+///   missing dependency from another shard
+pub fn inter_shard_dependencies_plot(
+    searches: Vec<Search>,
+    shard_counts: Vec<usize>,
+    style_fun: Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
+    output_dir: Option<&str>,
+    rtt_output_file: &str,
+    stalls_output_file: &str,
+    db: &ResultsDB,
+) -> Result<(), Report> {
+    let request_rtt = |metrics: &ExecutorMetrics| {
+        metrics
+            .get_collected(ExecutorMetricsKind::RequestRtt)
+            .map(|rtt| rtt.mean().value())
+    };
+    inner_inter_shard_dependencies_plot(
+        &searches,
+        &shard_counts,
+        &style_fun,
+        request_rtt,
+        "request latency (ms)",
+        output_dir,
+        rtt_output_file,
+        db,
+    )?;
+
+    let remote_stalls = |metrics: &ExecutorMetrics| {
+        let stalls = metrics
+            .get_aggregated(ExecutorMetricsKind::DependencyStalls)
+            .cloned()
+            .unwrap_or_default();
+        let remote_stalls = metrics
+            .get_aggregated(ExecutorMetricsKind::RemoteDependencyStalls)
+            .cloned()
+            .unwrap_or_default();
+        if stalls > 0 {
+            Some(remote_stalls as f64 * 100f64 / stalls as f64)
+        } else {
+            None
+        }
+    };
+    inner_inter_shard_dependencies_plot(
+        &searches,
+        &shard_counts,
+        &style_fun,
+        remote_stalls,
+        "remote stalls (%)",
+        output_dir,
+        stalls_output_file,
+        db,
+    )
+}
+
+fn inner_inter_shard_dependencies_plot<F>(
+    searches: &[Search],
+    shard_counts: &[usize],
+    style_fun: &Option<Box<dyn Fn(&Search) -> HashMap<Style, String>>>,
+    metric: F,
+    y_label: &str,
+    output_dir: Option<&str>,
+    output_file: &str,
+    db: &ResultsDB,
+) -> Result<(), Report>
+where
+    F: Fn(&ExecutorMetrics) -> Option<f64>,
+{
+    // start python
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let plt = PyPlot::new(py)?;
+
+    // start plot
+    let (fig, ax) = start_plot(py, &plt, None)?;
+
+    // keep track of the number of plotted instances
+    let mut plotted = 0;
+
+    for search in searches {
+        let mut search = *search;
+        let mut x = Vec::with_capacity(shard_counts.len());
+        let mut y = Vec::with_capacity(shard_counts.len());
+        for shard_count in shard_counts {
+            search.shard_count(*shard_count);
+            let exp_data = match find_single(db, search)? {
+                Some(exp_data) => exp_data,
+                None => {
+                    eprintln!(
+                        "missing data for {} f = {} shard_count = {}",
+                        PlotFmt::protocol_name(search.protocol),
+                        search.f,
+                        shard_count
+                    );
+                    continue;
+                }
+            };
+            if let Some(value) = metric(&exp_data.global_executor_metrics) {
+                x.push(*shard_count);
+                y.push(value);
+            }
+        }
+
+        // only plot if there's data
+        if !x.is_empty() {
+            let kwargs = line_style(py, search, style_fun)?;
+            ax.plot(x, y, None, Some(kwargs))?;
+            plotted += 1;
+        }
+    }
+
+    // set x ticks and labels
+    ax.set_xticks(shard_counts.to_vec(), None)?;
+    ax.set_xlabel("#shards", None)?;
+    ax.set_ylabel(y_label, None)?;
+
+    // legend
+    add_legend(plotted, None, None, None, None, py, &ax)?;
+
+    // end plot
+    end_plot(plotted > 0, output_dir, output_file, py, &plt, Some(fig))?;
+    Ok(())
+}
+
+fn table(
+    plotted: usize,
+    col_labels: Vec<String>,
+    col_widths: Vec<f64>,
+    row_labels: Vec<String>,
+    cells: Vec<Vec<String>>,
+    cell_colors: Option<Vec<Vec<&str>>>,
+    output_dir: Option<&str>,
+    output_file: &str,
+) -> Result<(), Report> {
+    if plotted > 0 {
+        // start python
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let plt = PyPlot::new(py)?;
+
+        // create table arguments
+        let kwargs = pydict!(
+            py,
+            ("colLabels", col_labels),
+            ("colWidths", col_widths),
+            ("rowLabels", row_labels),
+            ("cellText", cells),
+            ("colLoc", "center"),
+            ("cellLoc", "center"),
+            ("rowLoc", "right"),
+            ("loc", "center"),
+        );
+        if let Some(cell_colors) = cell_colors {
+            pytry!(py, kwargs.set_item("cellColours", cell_colors));
+        }
+
+        let table = plt.table(Some(kwargs))?;
+        plt.axis("off")?;
+
+        // create font size font
+        table.auto_set_font_size(false)?;
+        table.set_fontsize(6.5)?;
+
+        // row labels are too wide without this
+        plt.tight_layout()?;
+
+        // end plot
+        end_plot(plotted > 0, output_dir, output_file, py, &plt, None)?;
+    }
+    Ok(())
+}
+
+// percentiles of interest
+fn percentiles() -> impl Iterator<Item = f64> {
+    (10..=60)
+        .step_by(10)
+        .chain((65..=95).step_by(5))
+        .map(|percentile| percentile as f64 / 100f64)
+        .chain(vec![
+            0.96, 0.97, 0.98, 0.984, 0.988, 0.992, 0.996, 0.999, 0.9999,
+        ])
+}
+
+// https://matplotlib.org/3.3.1/api/_as_gen/matplotlib.pyplot.subplots_adjust.html?highlight=subplots_adjust#matplotlib.pyplot.subplots_adjust
+// Returns the data of the single experiment matching `search` (if any).
+fn find_single(
+    db: &ResultsDB,
+    search: Search,
+) -> Result<Option<&ExperimentData>, Report> {
+    let mut exp_data = db.find(search)?;
+    match exp_data.len() {
+        0 => Ok(None),
+        1 => {
+            let (_, _, exp_data) = exp_data.pop().unwrap();
+            Ok(Some(exp_data))
+        }
+        _ => {
+            let matches: Vec<_> = exp_data
+                .into_iter()
+                .map(|(timestamp, _, _)| timestamp.path().display().to_string())
+                .collect();
+            panic!("found more than 1 matching experiment for this search criteria: search {:?} | matches {:?}", search, matches);
+        }
+    }
+}
+
+// Returns all the runs matching `search`, sorted by their experiment seed and
+// then by their timestamp, so that the runs of two searches can be paired.
+fn find_runs(
+    db: &ResultsDB,
+    search: Search,
+) -> Result<Vec<&ExperimentData>, Report> {
+    let mut runs = db.find(search)?;
+    runs.sort_by_key(|(timestamp, exp_config, _)| {
+        (exp_config.experiment_seed, timestamp.file_name())
+    });
+    Ok(runs.into_iter().map(|(_, _, exp_data)| exp_data).collect())
+}
+
+pub fn start_plot<'a>(
+    py: Python<'a>,
+    plt: &'a PyPlot<'a>,
+    kwargs: Option<&PyDict>,
+) -> Result<(Figure<'a>, Axes<'a>), Report> {
+    let (fig, ax) = plt.subplots(None)?;
+    // create empty arguments if no arguments were provided
+    let kwargs = if let Some(kwargs) = kwargs {
+        kwargs
+    } else {
+        pyo3::types::PyDict::new(py)
+    };
+    // set adjust top and adjust bottom defaults if not set
+    if !pytry!(py, kwargs.contains("top")) {
+        pytry!(py, kwargs.set_item("top", ADJUST_TOP));
+    }
+    if !pytry!(py, kwargs.contains("bottom")) {
+        pytry!(py, kwargs.set_item("bottom", ADJUST_BOTTOM));
+    }
+    fig.subplots_adjust(Some(kwargs))?;
+
+    Ok((fig, ax))
+}
+
+pub fn end_plot(
+    something_plotted: bool,
+    output_dir: Option<&str>,
+    output_file: &str,
+    py: Python<'_>,
+    plt: &PyPlot<'_>,
+    fig: Option<Figure<'_>>,
+) -> Result<(), Report> {
+    if !something_plotted {
+        // if nothing was plotted, just close the current figure
+        return plt.close(None);
+    };
+
+    // maybe save `output_file` in `output_dir` (if one was set)
+    let output_file = if let Some(output_dir) = output_dir {
+        // make sure `output_dir` exists
+        std::fs::create_dir_all(&output_dir).wrap_err("create plot dir")?;
+        format!("{}/{}", output_dir, output_file)
+    } else {
+        output_file.to_string()
+    };
+
+    // reduce right margin
+    let kwargs = pydict!(py, ("right", 0.95));
+    plt.subplots_adjust(Some(kwargs))?;
+
+    // save figure
+    let kwargs = pydict!(py, ("format", "pdf"));
+    plt.savefig(&output_file, Some(kwargs))?;
+
+    let kwargs = if let Some(fig) = fig {
+        // close the figure passed as argument
+        Some(pydict!(py, ("fig", fig.fig())))
+    } else {
+        // close the current figure
+        None
+    };
+    plt.close(kwargs)?;
+    Ok(())
+}
+
+/// `legends` is a mapping from legend order to a pair of the matplotlib object
+/// and its legend.
+pub fn add_legend(
+    plotted: usize,
+    legends: Option<BTreeMap<usize, (&PyAny, String)>>,
+    x_bbox_to_anchor: Option<f64>,
+    y_bbox_to_anchor: Option<f64>,
+    column_spacing: Option<f64>,
+    py: Python<'_>,
+    ax: &Axes<'_>,
+) -> Result<(), Report> {
+    if plotted == 0 {
+        return Ok(());
+    }
+    // default values for `y_bbox_to_anchor`
+    let one_row = 1.19;
+    let two_rows = 1.3;
+
+    let (legend_ncol, y_bbox_to_anchor_default) = match plotted {
+        0 => (0, 0.0),
+        1 => (1, one_row),
+        2 => (2, one_row),
+        3 => (3, one_row),
+        4 => (2, two_rows),
+        5 => (3, two_rows),
+        6 => (3, two_rows),
+        7 => (4, two_rows),
+        8 => (4, two_rows),
+        9 => (5, two_rows),
+        10 => (5, two_rows),
+        11 => (6, two_rows),
+        12 => (6, two_rows),
+        _ => panic!(
+            "add_legend: unsupported number of plotted instances: {}",
+            plotted
+        ),
+    };
 
     // legend position: use default values if x or y not set
     let x_bbox_to_anchor_default = 0.5;
@@ -2169,6 +3901,14 @@ fn set_log_scale(
     Ok(())
 }
 
+fn default_legend_order(searches: &[Search]) -> Vec<usize> {
+    let protocols: Vec<_> = searches
+        .iter()
+        .map(|search| (search.protocol, search.f))
+        .collect();
+    PlotFmt::legend_order(&protocols)
+}
+
 fn bar_style<'a>(
     py: Python<'a>,
     search: Search,
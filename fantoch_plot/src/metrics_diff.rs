@@ -0,0 +1,143 @@
+use crate::db::ExperimentData;
+use fantoch::metrics::Metrics;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Value of some metric in two experiments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDiff {
+    pub name: String,
+    pub left: Option<f64>,
+    pub right: Option<f64>,
+}
+
+impl MetricDiff {
+    /// Returns the difference of the right value relative to the left value.
+    /// If the metric is missing in one of the experiments, or if the left
+    /// value is zero (but not the right one), the relative difference is
+    /// infinite.
+    pub fn relative_diff(&self) -> f64 {
+        match (self.left, self.right) {
+            (Some(left), Some(right)) => {
+                if left == right {
+                    0f64
+                } else if left == 0f64 {
+                    f64::INFINITY
+                } else {
+                    (right - left) / left
+                }
+            }
+            _ => f64::INFINITY,
+        }
+    }
+}
+
+/// Computes the difference between all the protocol and executor metrics of
+/// two experiments, sorted from the largest to the smallest (absolute)
+/// relative difference. Aggregated metrics are compared by their value, while
+/// collected metrics are compared by their average.
+pub fn metrics_diff(
+    left: &ExperimentData,
+    right: &ExperimentData,
+) -> Vec<MetricDiff> {
+    let mut values = BTreeMap::new();
+    add_metrics(&mut values, &left.global_protocol_metrics, true);
+    add_metrics(&mut values, &right.global_protocol_metrics, false);
+    add_metrics(&mut values, &left.global_executor_metrics, true);
+    add_metrics(&mut values, &right.global_executor_metrics, false);
+    sorted_diffs(values)
+}
+
+// Adds the aggregated and collected metrics in `metrics` to `values`, as the
+// left or the right value of each metric.
+fn add_metrics<K>(
+    values: &mut BTreeMap<String, (Option<f64>, Option<f64>)>,
+    metrics: &Metrics<K>,
+    left: bool,
+) where
+    K: Eq + Hash + Copy + Debug,
+{
+    let aggregated = metrics
+        .aggregated()
+        .map(|(kind, value)| (format!("{:?}", kind), *value as f64));
+    let collected = metrics.collected().map(|(kind, histogram)| {
+        (format!("{:?} (avg)", kind), histogram.mean().value())
+    });
+    for (name, value) in aggregated.chain(collected) {
+        let (left_value, right_value) = values.entry(name).or_default();
+        if left {
+            *left_value = Some(value);
+        } else {
+            *right_value = Some(value);
+        }
+    }
+}
+
+fn sorted_diffs(
+    values: BTreeMap<String, (Option<f64>, Option<f64>)>,
+) -> Vec<MetricDiff> {
+    let mut diffs: Vec<_> = values
+        .into_iter()
+        .map(|(name, (left, right))| MetricDiff { name, left, right })
+        .collect();
+    // sort by decreasing absolute relative difference (ties are kept sorted by
+    // name, since `values` is a `BTreeMap` and the sort is stable)
+    diffs.sort_by(|a, b| {
+        let a = a.relative_diff().abs();
+        let b = b.relative_diff().abs();
+        b.partial_cmp(&a)
+            .expect("relative differences should be comparable")
+    });
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::protocol::{ProtocolMetrics, ProtocolMetricsKind};
+
+    #[test]
+    fn metrics_diff_sorted() {
+        let mut left = ProtocolMetrics::new();
+        left.aggregate(ProtocolMetricsKind::FastPath, 100);
+        left.aggregate(ProtocolMetricsKind::SlowPath, 10);
+        left.aggregate(ProtocolMetricsKind::Stable, 50);
+        left.collect(ProtocolMetricsKind::CommittedDepsLen, 2);
+
+        let mut right = ProtocolMetrics::new();
+        right.aggregate(ProtocolMetricsKind::FastPath, 110);
+        right.aggregate(ProtocolMetricsKind::SlowPath, 0);
+        right.aggregate(ProtocolMetricsKind::Stable, 50);
+        right.collect(ProtocolMetricsKind::CommittedDepsLen, 3);
+        right.collect(ProtocolMetricsKind::CommandKeyCount, 1);
+
+        let mut values = BTreeMap::new();
+        add_metrics(&mut values, &left, true);
+        add_metrics(&mut values, &right, false);
+        let diffs = sorted_diffs(values);
+
+        let names: Vec<_> =
+            diffs.iter().map(|diff| diff.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                // missing on the left
+                "command_key_count (avg)",
+                // -100%
+                "slow_path",
+                // +50%
+                "committed_deps_len (avg)",
+                // +10%
+                "fast_path",
+                // no change
+                "stable",
+            ]
+        );
+        assert_eq!(diffs[0].left, None);
+        assert_eq!(diffs[0].right, Some(1f64));
+        assert_eq!(diffs[1].relative_diff(), -1f64);
+        assert_eq!(diffs[2].relative_diff(), 0.5);
+        assert_eq!(diffs[4].relative_diff(), 0f64);
+    }
+}
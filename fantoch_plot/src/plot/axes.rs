@@ -172,6 +172,15 @@ impl<'a> Axes<'a> {
         Ok(())
     }
 
+    pub fn axvline(
+        &self,
+        x: f64,
+        kwargs: Option<&PyDict>,
+    ) -> Result<(), Report> {
+        pytry!(self.py(), self.ax.call_method("axvline", (x,), kwargs));
+        Ok(())
+    }
+
     // any questions about legend positioning should be answered here: https://stackoverflow.com/a/43439132/4262469
     // - that's how great the answer is!
     pub fn legend(
@@ -244,6 +253,31 @@ impl<'a> Axes<'a> {
         Ok(im)
     }
 
+    pub fn contour<D>(
+        &self,
+        data: Vec<D>,
+        kwargs: Option<&PyDict>,
+    ) -> Result<&PyAny, Report>
+    where
+        D: IntoPy<PyObject>,
+    {
+        let result =
+            pytry!(self.py(), self.ax.call_method("contour", (data,), kwargs));
+        Ok(result)
+    }
+
+    pub fn clabel(
+        &self,
+        contour_set: &PyAny,
+        kwargs: Option<&PyDict>,
+    ) -> Result<(), Report> {
+        pytry!(
+            self.py(),
+            self.ax.call_method("clabel", (contour_set,), kwargs)
+        );
+        Ok(())
+    }
+
     fn py(&self) -> Python<'_> {
         self.ax.py()
     }
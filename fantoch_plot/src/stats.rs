@@ -0,0 +1,368 @@
+use crate::db::{ExperimentData, LatencyAggregation, LatencyPrecision};
+
+/// Result of a paired t-test between the values of some metric in repeated
+/// runs of two experiments, where the i-th left run is paired with the i-th
+/// right run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PairedTTest {
+    // number of pairs
+    pub samples: usize,
+    // average difference between the right and the left values
+    pub mean_diff: f64,
+    pub t: f64,
+    // two-sided p-value
+    pub p_value: f64,
+}
+
+impl PairedTTest {
+    /// Runs a paired t-test on the values provided. Returns `None` if there
+    /// are less than two pairs (or if the number of values differs).
+    pub fn new(left: &[f64], right: &[f64]) -> Option<Self> {
+        let samples = left.len();
+        if samples < 2 || samples != right.len() {
+            return None;
+        }
+        let diffs: Vec<_> = left
+            .iter()
+            .zip(right)
+            .map(|(left, right)| right - left)
+            .collect();
+        let n = samples as f64;
+        let mean_diff = diffs.iter().sum::<f64>() / n;
+        let variance = diffs
+            .iter()
+            .map(|diff| (diff - mean_diff).powi(2))
+            .sum::<f64>()
+            / (n - 1f64);
+
+        let (t, p_value) = if variance == 0f64 {
+            // all differences are the same: either there's no difference at
+            // all, or there's always the same one
+            if mean_diff == 0f64 {
+                (0f64, 1f64)
+            } else {
+                (mean_diff.signum() * f64::INFINITY, 0f64)
+            }
+        } else {
+            let t = mean_diff / (variance / n).sqrt();
+            (t, student_t_two_sided(t, n - 1f64))
+        };
+        Some(Self {
+            samples,
+            mean_diff,
+            t,
+            p_value,
+        })
+    }
+
+    /// Checks whether the difference is significant at level `alpha`.
+    pub fn significant(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+/// Values of some client metric in the repeated runs of two experiments,
+/// along with the paired t-test on them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunsComparison {
+    pub name: String,
+    pub left: Vec<f64>,
+    pub right: Vec<f64>,
+    pub test: Option<PairedTTest>,
+}
+
+impl RunsComparison {
+    fn new(name: String, left: Vec<f64>, right: Vec<f64>) -> Self {
+        let test = PairedTTest::new(&left, &right);
+        Self {
+            name,
+            left,
+            right,
+            test,
+        }
+    }
+
+    /// Returns the average value across the left runs.
+    pub fn left_mean(&self) -> f64 {
+        mean(&self.left)
+    }
+
+    /// Returns the average value across the right runs.
+    pub fn right_mean(&self) -> f64 {
+        mean(&self.right)
+    }
+}
+
+/// Compares the throughput, the average latency and the latency
+/// `percentiles` observed by clients in the repeated runs of two experiments
+/// (paired by their order in `left` and `right`). Percentiles are taken from
+/// the latency corrected for coordinated omission, if available.
+pub fn compare_runs(
+    left: &[&ExperimentData],
+    right: &[&ExperimentData],
+    percentiles: &[f64],
+    latency_precision: LatencyPrecision,
+    latency_aggregation: LatencyAggregation,
+) -> Vec<RunsComparison> {
+    let values = |runs: &[&ExperimentData],
+                  metric: &dyn Fn(&ExperimentData) -> f64| {
+        runs.iter()
+            .map(|exp_data| metric(exp_data))
+            .collect::<Vec<_>>()
+    };
+    let compare = |name: String, metric: &dyn Fn(&ExperimentData) -> f64| {
+        RunsComparison::new(name, values(left, metric), values(right, metric))
+    };
+
+    let mut comparisons = vec![
+        compare(String::from("throughput"), &|exp_data| {
+            exp_data.global_client_throughput
+        }),
+        compare(
+            format!(
+                "latency avg ({}, {})",
+                latency_precision.name(),
+                latency_aggregation.name()
+            ),
+            &|exp_data| {
+                exp_data
+                    .global_client_latency_with(latency_aggregation)
+                    .mean(latency_precision)
+            },
+        ),
+    ];
+    for percentile in percentiles {
+        comparisons.push(compare(
+            format!(
+                "latency p{} ({}, {})",
+                percentile * 100f64,
+                latency_precision.name(),
+                latency_aggregation.name()
+            ),
+            &|exp_data| {
+                exp_data
+                    .global_client_tail_latency_with(latency_aggregation)
+                    .percentile(*percentile, latency_precision)
+            },
+        ));
+    }
+    comparisons
+}
+
+/// Saturation point (knee) of a throughput-latency curve, i.e. the last point
+/// of the curve before latency exceeds `factor` times the latency at the
+/// lowest load (the baseline).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Knee {
+    // latency at the lowest load
+    pub baseline_latency: f64,
+    // number of clients (per region) at the knee
+    pub clients: usize,
+    // highest throughput up to (and including) the knee
+    pub max_useful_throughput: f64,
+    // latency at the knee
+    pub latency: f64,
+    // whether latency exceeds the threshold at some point of the curve; if
+    // not, the knee is the last point measured and the curve may not be
+    // saturated yet
+    pub saturated: bool,
+}
+
+impl Knee {
+    /// Detects the knee of the curve with the given `(clients, throughput,
+    /// latency)` points, sorted by increasing load. Returns `None` if there
+    /// are no points.
+    pub fn detect(points: &[(usize, f64, f64)], factor: f64) -> Option<Self> {
+        let (clients, throughput, baseline_latency) = *points.first()?;
+        let mut knee = Self {
+            baseline_latency,
+            clients,
+            max_useful_throughput: throughput,
+            latency: baseline_latency,
+            saturated: false,
+        };
+        let threshold = baseline_latency * factor;
+        for &(clients, throughput, latency) in points.iter().skip(1) {
+            if latency > threshold {
+                knee.saturated = true;
+                break;
+            }
+            knee.clients = clients;
+            knee.latency = latency;
+            knee.max_useful_throughput =
+                knee.max_useful_throughput.max(throughput);
+        }
+        Some(knee)
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Computes the two-sided p-value of `t` in a Student's t-distribution with
+// `df` degrees of freedom, i.e. `P(|T| >= |t|)`.
+fn student_t_two_sided(t: f64, df: f64) -> f64 {
+    let x = df / (df + t * t);
+    regularized_incomplete_beta(x, df / 2f64, 0.5)
+}
+
+// Computes the regularized incomplete beta function `I_x(a, b)` using its
+// continued fraction representation (see Numerical Recipes, 6.4).
+fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0f64 {
+        return 0f64;
+    }
+    if x >= 1f64 {
+        return 1f64;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b)
+        + a * x.ln()
+        + b * (1f64 - x).ln())
+    .exp();
+    // the continued fraction converges quickly if
+    // `x < (a + 1) / (a + b + 2)`; otherwise, use the symmetry
+    // `I_x(a, b) = 1 - I_{1-x}(b, a)`
+    if x < (a + 1f64) / (a + b + 2f64) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1f64 - front * beta_continued_fraction(1f64 - x, b, a) / b
+    }
+}
+
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 200;
+    const EPSILON: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+
+    // modified Lentz's method
+    let clamp = |value: f64| if value.abs() < TINY { TINY } else { value };
+    let mut c = 1f64;
+    let mut d = 1f64 / clamp(1f64 - (a + b) * x / (a + 1f64));
+    let mut result = d;
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        // even step
+        let numerator =
+            m * (b - m) * x / ((a + 2f64 * m - 1f64) * (a + 2f64 * m));
+        d = 1f64 / clamp(1f64 + numerator * d);
+        c = clamp(1f64 + numerator / c);
+        result *= d * c;
+        // odd step
+        let numerator = -(a + m) * (a + b + m) * x
+            / ((a + 2f64 * m) * (a + 2f64 * m + 1f64));
+        d = 1f64 / clamp(1f64 + numerator * d);
+        c = clamp(1f64 + numerator / c);
+        let delta = d * c;
+        result *= delta;
+        if (delta - 1f64).abs() < EPSILON {
+            break;
+        }
+    }
+    result
+}
+
+// Computes `ln(Γ(x))` for `x > 0` using the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS.iter().enumerate().fold(
+        1.000000000190015,
+        |series, (index, coefficient)| {
+            series + coefficient / (x + 1f64 + index as f64)
+        },
+    );
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!(
+            (value - expected).abs() < 1e-4,
+            "{} should be close to {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn knee() {
+        assert_eq!(Knee::detect(&[], 2f64), None);
+
+        // latency doubles after 3 points; the max useful throughput is the
+        // highest one before that, even if it's not the one at the knee
+        let points = [
+            (32, 10f64, 100f64),
+            (64, 20f64, 110f64),
+            (128, 40f64, 190f64),
+            (256, 35f64, 200f64),
+            (512, 45f64, 400f64),
+            (1024, 30f64, 900f64),
+        ];
+        assert_eq!(
+            Knee::detect(&points, 2f64),
+            Some(Knee {
+                baseline_latency: 100f64,
+                clients: 256,
+                max_useful_throughput: 40f64,
+                latency: 200f64,
+                saturated: true,
+            })
+        );
+
+        // with a higher factor, the curve never saturates
+        let knee = Knee::detect(&points, 10f64).unwrap();
+        assert_eq!(knee.clients, 1024);
+        assert_eq!(knee.max_useful_throughput, 45f64);
+        assert!(!knee.saturated);
+    }
+
+    #[test]
+    fn student_t() {
+        // with 1 degree of freedom, the t-distribution is a Cauchy
+        assert_close(student_t_two_sided(1f64, 1f64), 0.5);
+        // critical values for a 5% significance level
+        assert_close(student_t_two_sided(2.776445, 4f64), 0.05);
+        assert_close(student_t_two_sided(-2.262157, 9f64), 0.05);
+        assert_close(student_t_two_sided(0f64, 9f64), 1f64);
+    }
+
+    #[test]
+    fn paired_t_test() {
+        // not enough samples
+        assert_eq!(PairedTTest::new(&[1f64], &[2f64]), None);
+        assert_eq!(PairedTTest::new(&[1f64, 2f64], &[2f64]), None);
+
+        // the right is consistently (but not always equally) higher
+        let left = [10f64, 12f64, 11f64, 13f64, 10f64];
+        let right = [12f64, 13f64, 13f64, 14f64, 13f64];
+        let test = PairedTTest::new(&left, &right).unwrap();
+        assert_eq!(test.samples, 5);
+        assert_close(test.mean_diff, 1.8);
+        assert_close(test.t, 4.810702);
+        assert!(test.significant(0.01));
+
+        // the right is higher and lower at random
+        let right = [11f64, 11f64, 12f64, 12f64, 11f64];
+        let test = PairedTTest::new(&left, &right).unwrap();
+        assert!(!test.significant(0.05));
+
+        // the same difference in all pairs
+        let right: Vec<_> = left.iter().map(|value| value + 1f64).collect();
+        let test = PairedTTest::new(&left, &right).unwrap();
+        assert_eq!(test.p_value, 0f64);
+        let test = PairedTTest::new(&left, &left).unwrap();
+        assert_eq!(test.p_value, 1f64);
+    }
+}
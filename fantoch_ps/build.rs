@@ -0,0 +1,6 @@
+include!("../fantoch/build/message_fingerprint.rs");
+
+fn main() {
+    // generate the fingerprints of the protocols' messages
+    generate_message_fingerprints("src/protocol");
+}
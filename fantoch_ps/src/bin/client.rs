@@ -2,34 +2,49 @@ mod common;
 
 use clap::{App, Arg};
 use color_eyre::Report;
-use fantoch::client::{KeyGen, Workload};
+use fantoch::client::{KeyGen, Workload, WorkloadMix};
 use fantoch::id::ClientId;
 use fantoch::info;
+use fantoch::kvs::StateMachineKind;
+use fantoch::run::directory::Directory;
+use fantoch::run::rw::TcpOptions;
 use std::time::Duration;
 
 const RANGE_SEP: &str = "-";
+const WORKLOAD_MIX_SEP: &str = ";";
+const WORKLOAD_MIX_PART_SEP: &str = "/";
 const DEFAULT_KEYS_PER_COMMAND: usize = 1;
 const DEFAULT_SHARD_COUNT: usize = 1;
 const DEFAULT_KEY_GEN: KeyGen = KeyGen::ConflictPool {
     conflict_rate: 100,
     pool_size: 1,
+    pool_id: 0,
 };
 const DEFAULT_COMMANDS_PER_CLIENT: usize = 1000;
 const DEFAULT_READ_ONLY_PERCENTAGE: usize = 0;
+const DEFAULT_SHARD_LOCALITY: usize = 0;
+const DEFAULT_ACK_ONLY_WRITES: bool = false;
 const DEFAULT_PAYLOAD_SIZE: usize = 100;
 const DEFAULT_BATCH_MAX_SIZE: usize = 1;
 const DEFAULT_BATCH_MAX_DELAY: Duration = Duration::from_millis(5);
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
 type ClientArgs = (
     Vec<ClientId>,
-    Vec<String>,
+    Vec<(String, Option<Duration>)>,
+    bool,
     Option<Duration>,
-    Workload,
+    WorkloadMix,
     usize,
     Duration,
-    bool,
+    TcpOptions,
     usize,
     Option<usize>,
+    Option<Duration>,
+    Option<Duration>,
+    Duration,
+    Option<Duration>,
+    Option<String>,
     Option<String>,
     usize,
     Option<usize>,
@@ -40,30 +55,44 @@ fn main() -> Result<(), Report> {
     let (
         ids,
         addresses,
+        detect_closest,
         interval,
-        workload,
+        workload_mix,
         batch_max_size,
         batch_max_delay,
-        tcp_nodelay,
+        tcp_options,
         channel_buffer_size,
         status_frequency,
+        stats_interval,
+        max_run_time,
+        shutdown_timeout,
+        command_timeout,
         metrics_file,
+        traces_file,
         stack_size,
         cpus,
     ) = args;
 
-    common::tokio_runtime(stack_size, cpus).block_on(fantoch::run::client(
-        ids,
-        addresses,
-        interval,
-        workload,
-        batch_max_size,
-        batch_max_delay,
-        tcp_nodelay,
-        channel_buffer_size,
-        status_frequency,
-        metrics_file,
-    ))
+    common::tokio_runtime(stack_size, cpus, false).block_on(
+        fantoch::run::client(
+            ids,
+            addresses,
+            detect_closest,
+            interval,
+            workload_mix,
+            batch_max_size,
+            batch_max_delay,
+            tcp_options,
+            channel_buffer_size,
+            status_frequency,
+            stats_interval,
+            max_run_time,
+            shutdown_timeout,
+            command_timeout,
+            metrics_file,
+            traces_file,
+        ),
+    )
 }
 
 fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
@@ -83,8 +112,24 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
             Arg::with_name("addresses")
                 .long("addresses")
                 .value_name("ADDRESSES")
-                .help("comma-separated list of addresses to connect to (in the form IP:PORT e.g. 127.0.0.1:3000)")
-                .required(true)
+                .help("comma-separated list of addresses to connect to (in the form IP:PORT e.g. 127.0.0.1:3000), one per shard; if a delay (in milliseconds) is to be injected in the messages to and from a process (e.g. to emulate the latency between the client and that process), the address should be of the form IP:PORT-DELAY; either this, --candidates or --directory should be set")
+                .required_unless_one(&["candidates", "directory"])
+                .conflicts_with_all(&["candidates", "directory"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("candidates")
+                .long("candidates")
+                .value_name("CANDIDATES")
+                .help("comma-separated list of addresses of candidate processes (in the form IP:PORT e.g. 127.0.0.1:3000), possibly several per shard; if set, clients connect to the closest candidate on each shard, i.e. the one with the lowest median round-trip time measured at startup (which is stored in the metrics), which allows running clients without knowing their region")
+                .conflicts_with("directory")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("directory")
+                .long("directory")
+                .value_name("DIRECTORY")
+                .help("HTTP endpoint (e.g. http://10.0.0.1:8000/directory) or file from which the addresses of all processes are fetched; each line should have the form 'PROCESS_ID SHARD_ID REGION ADDRESS CLIENT_ADDRESS'; if set, all client addresses in the directory are used as --candidates")
                 .takes_value(true),
         )
         .arg(
@@ -105,7 +150,7 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
             Arg::with_name("key_gen")
                 .long("key_gen")
                 .value_name("KEY_GEN")
-                .help("representation of a key generator; possible values 'conflict_pool,100,1' where 100 is the conflict rate and 1 the pool size, or 'zipf,1.3,10000' where 1.3 is the zipf coefficient (which should be non-zero) and 10000 the number of keys (per shard) in the distribution; default: 'conflict_rate,100,1'")
+                .help("representation of a key generator; possible values 'conflict_pool,100,1' where 100 is the conflict rate and 1 the pool size (optionally followed by the id of the pool, e.g. 'conflict_pool,100,1,1', as pools with different ids have disjoint keys), or 'zipf,1.3,10000' where 1.3 is the zipf coefficient (which should be non-zero) and 10000 the number of keys (per shard) in the distribution; default: 'conflict_rate,100,1'")
                 .takes_value(true),
         )
         .arg(
@@ -129,6 +174,55 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("percentage of read-only commands; default: 0")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("shard_locality")
+                .long("shard_locality")
+                .value_name("SHARD_LOCALITY")
+                .help("percentage of commands whose keys are all on the same shard (applied to every workload, even if a workload mix is set); only supported with the zipf key generator; default: 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ack_only_writes")
+                .long("ack_only_writes")
+                .value_name("ACK_ONLY_WRITES")
+                .help("boolean indicating whether clients only need an ack for commands that aren't read-only, in which case their results are not sent back (applied to every workload, even if a workload mix is set); default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("SEED")
+                .help("if set, the random choices of each client (e.g. which keys are accessed) are drawn from an rng seeded with this value and the client identifier, and thus each client generates the same commands across runs (applied to every workload, even if a workload mix is set); by default clients are not seeded")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ttl")
+                .long("ttl")
+                .value_name("TTL")
+                .help("if set, values are written with this TTL (in milliseconds), after which they expire (applied to every workload, even if a workload mix is set); by default values never expire")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("state_machine")
+                .long("state_machine")
+                .value_name("STATE_MACHINE")
+                .help("if set, the commands that aren't read-only target this state machine, which should match the one set in the servers: 'lock' (each write acquires or releases the lock on a key), 'queue' (each write enqueues a value on, or dequeues a value from, a key) or 'counter' (each write increments the counter of a key) (applied to every workload, even if a workload mix is set); by default commands target the key-value store")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("value_sample_percentage")
+                .long("value_sample_percentage")
+                .value_name("VALUE_SAMPLE_PERCENTAGE")
+                .help("if set, only this percentage of read-only commands gets back the values read, while the remaining ones only get back their hashes (applied to every workload, even if a workload mix is set); by default all read-only commands get back the values read")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_in_flight")
+                .long("max_in_flight")
+                .value_name("MAX_IN_FLIGHT")
+                .help("if set, closed-loop clients issue up to this many commands before waiting for any of them to complete, issuing a new one each time one completes (applied to every workload, even if a workload mix is set; ignored if an interval is set); by default clients issue one command at a time")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("payload_size")
                 .long("payload_size")
@@ -136,6 +230,13 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("size of the command payload; default: 100 (bytes)")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("workload_mix")
+                .long("workload_mix")
+                .value_name("WORKLOAD_MIX")
+                .help("semicolon-separated list of workloads executed by disjoint subsets of clients, each one represented as PERCENTAGE/KEY_GEN/KEYS_PER_COMMAND/READ_ONLY_PERCENTAGE/PAYLOAD_SIZE, e.g. '80/zipf,1.0,1000000/1/0/100;20/conflict_pool,0,1/2/0/100' where 80% of clients access a single key with a zipf distribution and the remaining 20% access two keys without conflicts, or '90/conflict_pool,2,1/1/0/100;10/conflict_pool,50,1,1/1/0/100' where 90% of clients are in a low-conflict pool and 10% in an independent high-conflict pool; if set, it overrides the key generator, keys per command, read-only percentage and payload size (the shard count and commands per client are shared by all workloads)")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("batch_max_size")
                 .long("batch_max_size")
@@ -157,6 +258,41 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("set TCP_NODELAY; default: true")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("tcp_send_buffer_size")
+                .long("tcp_send_buffer_size")
+                .value_name("TCP_SEND_BUFFER_SIZE")
+                .help("size of the socket send buffer (SO_SNDBUF); by default the OS default is used")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tcp_recv_buffer_size")
+                .long("tcp_recv_buffer_size")
+                .value_name("TCP_RECV_BUFFER_SIZE")
+                .help("size of the socket receive buffer (SO_RCVBUF); by default the OS default is used")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tcp_keepalive")
+                .long("tcp_keepalive")
+                .value_name("TCP_KEEPALIVE")
+                .help("idle time (in milliseconds) after which TCP keepalive probes are sent; by default keepalive is not enabled")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tcp_max_segment_size")
+                .long("tcp_max_segment_size")
+                .value_name("TCP_MAX_SEGMENT_SIZE")
+                .help("maximum segment size (TCP_MAXSEG), e.g. to make use of jumbo frames; by default the MSS is derived from the MTU")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("auth_token")
+                .long("auth_token")
+                .value_name("AUTH_TOKEN")
+                .help("shared secret (a number) presented on each connection to processes, which drop the connections that don't present the one they're configured with; by default connections are not authenticated")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("channel_buffer_size")
                 .long("channel_buffer_size")
@@ -171,6 +307,34 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("frequency of status messages; if set with 1, a status message will be shown for each completed command; default: no status messages are shown")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("stats_interval")
+                .long("stats_interval")
+                .value_name("STATS_INTERVAL")
+                .help("interval (in seconds) at which the number of commands completed by all clients (and their throughput) is logged; default: no stats are logged")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_run_time")
+                .long("max_run_time")
+                .value_name("MAX_RUN_TIME")
+                .help("time (in seconds) after which clients stop, even if their workload hasn't finished; by default clients only stop once their workload finishes")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("shutdown_timeout")
+                .long("shutdown_timeout")
+                .value_name("SHUTDOWN_TIMEOUT")
+                .help("on SIGINT, clients stop issuing commands and wait at most this time (in milliseconds) for the pending ones before writing their (partial) metrics; default: 5000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("command_timeout")
+                .long("command_timeout")
+                .value_name("COMMAND_TIMEOUT")
+                .help("time (in milliseconds) after which pending commands are considered to have timed out; commands that time out are counted as unavailable in the metrics (and not as completed if their result arrives later), which allows measuring the availability perceived by clients during faults; by default commands never time out")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("metrics_file")
                 .long("metrics_file")
@@ -178,6 +342,13 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("file in which metrics are written to; by default metrics are not logged")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("traces_file")
+                .long("traces_file")
+                .value_name("TRACES_FILE")
+                .help("file in which the traces of the commands sampled by the servers (see the server's --trace_sample_rate) are written to; by default traces are not logged")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("stack_size")
                 .long("stack_size")
@@ -199,66 +370,119 @@ fn parse_args() -> (ClientArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("file to which logs will be written to; if not set, logs will be redirect to the stdout")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("log_json")
+                .long("log_json")
+                .value_name("LOG_JSON")
+                .help("boolean indicating whether logs should be written as JSON lines (one JSON object per event, with its fields at the top level); default: false")
+                .takes_value(true),
+        )
         .get_matches();
 
     let tracing_directives = None;
+    let log_json = common::parse_log_json(matches.value_of("log_json"));
     let guard = fantoch::util::init_tracing_subscriber(
         matches.value_of("log_file"),
         tracing_directives,
+        log_json,
     );
 
     // parse arguments
     let ids = parse_id_range(matches.value_of("ids"));
-    let addresses = parse_addresses(matches.value_of("addresses"));
+    let (addresses, detect_closest) = parse_addresses(
+        matches.value_of("addresses"),
+        matches.value_of("candidates"),
+        matches.value_of("directory"),
+    );
     let interval = parse_interval(matches.value_of("interval"));
-    let workload = parse_workload(
+    let mut workload_mix = parse_workload_mix(
         matches.value_of("shard_count"),
         matches.value_of("key_gen"),
         matches.value_of("keys_per_command"),
         matches.value_of("commands_per_client"),
         matches.value_of("read_only_percentage"),
         matches.value_of("payload_size"),
+        matches.value_of("shard_locality"),
+        matches.value_of("ack_only_writes"),
+        matches.value_of("workload_mix"),
     );
+    workload_mix.set_seed(parse_seed(matches.value_of("seed")));
+    workload_mix.set_ttl(parse_ttl(matches.value_of("ttl")));
+    workload_mix.set_state_machine(parse_state_machine(
+        matches.value_of("state_machine"),
+    ));
+    workload_mix.set_value_sample_percentage(parse_value_sample_percentage(
+        matches.value_of("value_sample_percentage"),
+    ));
+    workload_mix.set_max_in_flight(parse_max_in_flight(
+        matches.value_of("max_in_flight"),
+    ));
 
     let batch_max_size =
         parse_batch_max_size(matches.value_of("batch_max_size"));
     let batch_max_delay =
         parse_batch_max_delay(matches.value_of("batch_max_delay"));
 
-    let tcp_nodelay =
-        common::parse_tcp_nodelay(matches.value_of("tcp_nodelay"));
+    let tcp_options = common::parse_tcp_options(
+        matches.value_of("tcp_nodelay"),
+        matches.value_of("tcp_send_buffer_size"),
+        matches.value_of("tcp_recv_buffer_size"),
+        matches.value_of("tcp_keepalive"),
+        matches.value_of("tcp_max_segment_size"),
+        matches.value_of("auth_token"),
+    );
     let channel_buffer_size = common::parse_channel_buffer_size(
         matches.value_of("channel_buffer_size"),
     );
     let status_frequency =
         parse_status_frequency(matches.value_of("status_frequency"));
+    let stats_interval =
+        parse_stats_interval(matches.value_of("stats_interval"));
+    let max_run_time = parse_max_run_time(matches.value_of("max_run_time"));
+    let shutdown_timeout =
+        parse_shutdown_timeout(matches.value_of("shutdown_timeout"));
+    let command_timeout =
+        parse_command_timeout(matches.value_of("command_timeout"));
     let metrics_file = parse_metrics_file(matches.value_of("metrics_file"));
+    let traces_file = parse_traces_file(matches.value_of("traces_file"));
     let stack_size = common::parse_stack_size(matches.value_of("stack_size"));
     let cpus = common::parse_cpus(matches.value_of("cpus"));
 
     info!("ids: {}-{}", ids.first().unwrap(), ids.last().unwrap());
     info!("client number: {}", ids.len());
     info!("addresses: {:?}", addresses);
-    info!("workload: {:?}", workload);
+    info!("detect closest: {:?}", detect_closest);
+    info!("workload mix: {:?}", workload_mix);
     info!("batch_max_size: {:?}", batch_max_size);
     info!("batch_max_delay: {:?}", batch_max_delay);
-    info!("tcp_nodelay: {:?}", tcp_nodelay);
+    info!("tcp options: {:?}", tcp_options);
     info!("channel buffer size: {:?}", channel_buffer_size);
     info!("status frequency: {:?}", status_frequency);
+    info!("stats interval: {:?}", stats_interval);
+    info!("max run time: {:?}", max_run_time);
+    info!("shutdown timeout: {:?}", shutdown_timeout);
+    info!("command timeout: {:?}", command_timeout);
     info!("metrics file: {:?}", metrics_file);
+    info!("traces file: {:?}", traces_file);
     info!("stack size: {:?}", stack_size);
 
     let args = (
         ids,
         addresses,
+        detect_closest,
         interval,
-        workload,
+        workload_mix,
         batch_max_size,
         batch_max_delay,
-        tcp_nodelay,
+        tcp_options,
         channel_buffer_size,
         status_frequency,
+        stats_interval,
+        max_run_time,
+        shutdown_timeout,
+        command_timeout,
         metrics_file,
+        traces_file,
         stack_size,
         cpus,
     );
@@ -285,12 +509,34 @@ fn parse_id_range(id_range: Option<&str>) -> Vec<ClientId> {
     }
 }
 
-fn parse_addresses(addresses: Option<&str>) -> Vec<String> {
-    addresses
-        .expect("addresses should be set")
+fn parse_addresses(
+    addresses: Option<&str>,
+    candidates: Option<&str>,
+    directory: Option<&str>,
+) -> (Vec<(String, Option<Duration>)>, bool) {
+    // all processes in a directory are candidates
+    if let Some(directory) = directory {
+        let directory =
+            Directory::fetch(directory).expect("directory should be fetched");
+        let addresses = directory
+            .client_addresses()
+            .into_iter()
+            .map(|address| (address, None))
+            .collect();
+        return (addresses, true);
+    }
+
+    // if candidates are set, the closest ones should be detected
+    let (addresses, detect_closest) = match (addresses, candidates) {
+        (Some(addresses), None) => (addresses, false),
+        (None, Some(candidates)) => (candidates, true),
+        _ => panic!("either addresses, candidates or directory should be set"),
+    };
+    let addresses = addresses
         .split(common::protocol::LIST_SEP)
-        .map(|address| address.to_string())
-        .collect()
+        .map(common::protocol::parse_address)
+        .collect();
+    (addresses, detect_closest)
 }
 
 fn parse_millis_duration(millis: Option<&str>) -> Option<Duration> {
@@ -311,6 +557,8 @@ fn parse_workload(
     commands_per_client: Option<&str>,
     read_only_percentage: Option<&str>,
     payload_size: Option<&str>,
+    shard_locality: Option<&str>,
+    ack_only_writes: Option<&str>,
 ) -> Workload {
     let shard_count = parse_shard_count(shard_count);
     let key_gen = parse_key_gen(key_gen);
@@ -318,6 +566,8 @@ fn parse_workload(
     let commands_per_client = parse_commands_per_client(commands_per_client);
     let read_only_percentage = parse_read_only_percentage(read_only_percentage);
     let payload_size = parse_payload_size(payload_size);
+    let shard_locality = parse_shard_locality(shard_locality);
+    let ack_only_writes = parse_ack_only_writes(ack_only_writes);
     let mut workload = Workload::new(
         shard_count,
         key_gen,
@@ -326,9 +576,65 @@ fn parse_workload(
         payload_size,
     );
     workload.set_read_only_percentage(read_only_percentage);
+    workload.set_shard_locality(shard_locality);
+    workload.set_ack_only_writes(ack_only_writes);
     workload
 }
 
+fn parse_workload_mix(
+    shard_count: Option<&str>,
+    key_gen: Option<&str>,
+    keys_per_command: Option<&str>,
+    commands_per_client: Option<&str>,
+    read_only_percentage: Option<&str>,
+    payload_size: Option<&str>,
+    shard_locality: Option<&str>,
+    ack_only_writes: Option<&str>,
+    workload_mix: Option<&str>,
+) -> WorkloadMix {
+    if let Some(workload_mix) = workload_mix {
+        let workloads = workload_mix
+            .split(WORKLOAD_MIX_SEP)
+            .map(|workload| {
+                let parts: Vec<_> =
+                    workload.split(WORKLOAD_MIX_PART_SEP).collect();
+                if parts.len() != 5 {
+                    panic!(
+                        "invalid specification of workload in mix: {:?}",
+                        workload
+                    );
+                }
+                let percentage = parts[0]
+                    .parse::<usize>()
+                    .expect("workload percentage should be a number");
+                let workload = parse_workload(
+                    shard_count,
+                    Some(parts[1]),
+                    Some(parts[2]),
+                    commands_per_client,
+                    Some(parts[3]),
+                    Some(parts[4]),
+                    shard_locality,
+                    ack_only_writes,
+                );
+                (workload, percentage)
+            })
+            .collect();
+        WorkloadMix::new(workloads)
+    } else {
+        WorkloadMix::single(parse_workload(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            read_only_percentage,
+            payload_size,
+            shard_locality,
+            ack_only_writes,
+        ))
+    }
+}
+
 fn parse_keys_per_command(number: Option<&str>) -> usize {
     number
         .map(|number| {
@@ -354,13 +660,13 @@ fn parse_key_gen(key_gen: Option<&str>) -> KeyGen {
         .map(|key_gen| {
             let parts: Vec<_>= key_gen.split(',').collect();
             match parts.len() {
-                2 | 3 => (),
+                2 | 3 | 4 => (),
                 _ => panic!("invalid specification of key generator: {:?}", key_gen)
             };
             match parts[0] {
                 "conflict_pool" => {
-                    if parts.len() != 3 {
-                        panic!("conflict_pool key generator takes two arguments");
+                    if parts.len() != 3 && parts.len() != 4 {
+                        panic!("conflict_pool key generator takes two or three arguments");
                     }
                     let conflict_rate = parts[1]
                         .parse::<usize>()
@@ -368,7 +674,12 @@ fn parse_key_gen(key_gen: Option<&str>) -> KeyGen {
                     let pool_size = parts[2]
                         .parse::<usize>()
                         .expect("pool size should be a number");
-                    KeyGen::ConflictPool { conflict_rate, pool_size }
+                    let pool_id = parts.get(3).map(|pool_id| {
+                        pool_id
+                            .parse::<usize>()
+                            .expect("pool id should be a number")
+                    }).unwrap_or(0);
+                    KeyGen::ConflictPool { conflict_rate, pool_size, pool_id }
                 }
                 "zipf" => {
                     if parts.len() != 3 {
@@ -410,6 +721,61 @@ fn parse_read_only_percentage(number: Option<&str>) -> usize {
         .unwrap_or(DEFAULT_READ_ONLY_PERCENTAGE)
 }
 
+fn parse_shard_locality(number: Option<&str>) -> usize {
+    number
+        .map(|number| {
+            number
+                .parse::<usize>()
+                .expect("shard locality should be a number")
+        })
+        .unwrap_or(DEFAULT_SHARD_LOCALITY)
+}
+
+fn parse_ack_only_writes(ack_only_writes: Option<&str>) -> bool {
+    ack_only_writes
+        .map(|ack_only_writes| {
+            ack_only_writes
+                .parse::<bool>()
+                .expect("ack only writes should be a boolean")
+        })
+        .unwrap_or(DEFAULT_ACK_ONLY_WRITES)
+}
+
+fn parse_seed(seed: Option<&str>) -> Option<u64> {
+    seed.map(|seed| seed.parse::<u64>().expect("seed should be a number"))
+}
+
+fn parse_ttl(ttl: Option<&str>) -> Option<u64> {
+    ttl.map(|ttl| ttl.parse::<u64>().expect("ttl should be a number"))
+}
+
+fn parse_state_machine(
+    state_machine: Option<&str>,
+) -> Option<StateMachineKind> {
+    state_machine.map(|state_machine| match state_machine {
+        "lock" => StateMachineKind::Lock,
+        "queue" => StateMachineKind::Queue,
+        "counter" => StateMachineKind::Counter,
+        state_machine => panic!("invalid state machine: {}", state_machine),
+    })
+}
+
+fn parse_value_sample_percentage(percentage: Option<&str>) -> Option<usize> {
+    percentage.map(|percentage| {
+        percentage
+            .parse::<usize>()
+            .expect("value sample percentage should be a number")
+    })
+}
+
+fn parse_max_in_flight(max_in_flight: Option<&str>) -> Option<usize> {
+    max_in_flight.map(|max_in_flight| {
+        max_in_flight
+            .parse::<usize>()
+            .expect("max in flight should be a number")
+    })
+}
+
 fn parse_payload_size(number: Option<&str>) -> usize {
     number
         .map(|number| {
@@ -442,6 +808,36 @@ fn parse_status_frequency(status_frequency: Option<&str>) -> Option<usize> {
     })
 }
 
+fn parse_stats_interval(stats_interval: Option<&str>) -> Option<Duration> {
+    stats_interval.map(|stats_interval| {
+        let secs = stats_interval
+            .parse::<u64>()
+            .expect("stats interval should be a number");
+        Duration::from_secs(secs)
+    })
+}
+
+fn parse_max_run_time(max_run_time: Option<&str>) -> Option<Duration> {
+    max_run_time.map(|max_run_time| {
+        let secs = max_run_time
+            .parse::<u64>()
+            .expect("max run time should be a number");
+        Duration::from_secs(secs)
+    })
+}
+
+fn parse_shutdown_timeout(duration: Option<&str>) -> Duration {
+    parse_millis_duration(duration).unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT)
+}
+
+fn parse_command_timeout(duration: Option<&str>) -> Option<Duration> {
+    parse_millis_duration(duration)
+}
+
 pub fn parse_metrics_file(metrics_file: Option<&str>) -> Option<String> {
     metrics_file.map(String::from)
 }
+
+pub fn parse_traces_file(traces_file: Option<&str>) -> Option<String> {
+    traces_file.map(String::from)
+}
@@ -3,18 +3,31 @@
 pub mod protocol;
 
 use fantoch::info;
+use fantoch::run::rw::TcpOptions;
 use std::time::Duration;
 
 const DEFAULT_TCP_NODELAY: bool = true;
 const DEFAULT_TCP_BUFFER_SIZE: usize = 8 * 1024; // 8 KBs
 const DEFAULT_CHANNEL_BUFFER_SIZE: usize = 10000;
 const DEFAULT_STACK_SIZE: usize = 8 * 1024 * 1024; // 8MBs
+const DEFAULT_LOG_JSON: bool = false;
 
 #[allow(dead_code)]
 pub fn tokio_runtime(
     stack_size: usize,
     cpus: Option<usize>,
+    single_threaded: bool,
 ) -> tokio::runtime::Runtime {
+    if single_threaded {
+        // all tasks run in the thread that blocks on the runtime
+        info!("cpus: 1 (single-threaded runtime)");
+        return tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .expect("tokio runtime build should work");
+    }
+
     // get number of cpus
     let available = num_cpus::get();
     let cpus = cpus.unwrap_or(available);
@@ -41,6 +54,43 @@ pub fn parse_tcp_nodelay(tcp_nodelay: Option<&str>) -> bool {
         .unwrap_or(DEFAULT_TCP_NODELAY)
 }
 
+pub fn parse_tcp_options(
+    tcp_nodelay: Option<&str>,
+    tcp_send_buffer_size: Option<&str>,
+    tcp_recv_buffer_size: Option<&str>,
+    tcp_keepalive: Option<&str>,
+    tcp_max_segment_size: Option<&str>,
+    auth_token: Option<&str>,
+) -> TcpOptions {
+    let mut tcp_options = TcpOptions::new(parse_tcp_nodelay(tcp_nodelay));
+    tcp_options.send_buffer_size = tcp_send_buffer_size.map(|buffer_size| {
+        buffer_size
+            .parse::<usize>()
+            .expect("tcp_send_buffer_size should be a number")
+    });
+    tcp_options.recv_buffer_size = tcp_recv_buffer_size.map(|buffer_size| {
+        buffer_size
+            .parse::<usize>()
+            .expect("tcp_recv_buffer_size should be a number")
+    });
+    tcp_options.keepalive = tcp_keepalive.map(|keepalive| {
+        let millis = keepalive
+            .parse::<u64>()
+            .expect("tcp_keepalive should be a number");
+        Duration::from_millis(millis)
+    });
+    tcp_options.max_segment_size = tcp_max_segment_size.map(|mss| {
+        mss.parse::<u32>()
+            .expect("tcp_max_segment_size should be a number")
+    });
+    tcp_options.auth_token = auth_token.map(|auth_token| {
+        auth_token
+            .parse::<u64>()
+            .expect("auth_token should be a number")
+    });
+    tcp_options
+}
+
 pub fn parse_tcp_buffer_size(buffer_size: Option<&str>) -> usize {
     parse_buffer_size(buffer_size, DEFAULT_TCP_BUFFER_SIZE)
 }
@@ -81,3 +131,13 @@ pub fn parse_stack_size(stack_size: Option<&str>) -> usize {
 pub fn parse_cpus(cpus: Option<&str>) -> Option<usize> {
     cpus.map(|cpus| cpus.parse::<usize>().expect("cpus should be a number"))
 }
+
+pub fn parse_log_json(log_json: Option<&str>) -> bool {
+    log_json
+        .map(|log_json| {
+            log_json
+                .parse::<bool>()
+                .expect("log_json should be a boolean")
+        })
+        .unwrap_or(DEFAULT_LOG_JSON)
+}
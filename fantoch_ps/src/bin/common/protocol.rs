@@ -1,9 +1,14 @@
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 use color_eyre::Report;
 use fantoch::config::Config;
 use fantoch::id::{ProcessId, ShardId};
 use fantoch::info;
+use fantoch::kvs::StateMachineKind;
+use fantoch::load_balance::WorkerAssignment;
+use fantoch::planet::{Planet, Region};
 use fantoch::protocol::Protocol;
+use fantoch::run::directory::Directory;
+use fantoch::run::rw::TcpOptions;
 use std::net::IpAddr;
 use std::time::Duration;
 
@@ -18,6 +23,12 @@ const DEFAULT_CLIENT_PORT: u16 = 4000;
 
 const DEFAULT_EXECUTE_AT_COMMIT: bool = false;
 const DEFAULT_EXECUTOR_CLEANUP_INTERVAL: Duration = Duration::from_millis(5);
+const DEFAULT_EXECUTOR_ADAPTIVE_CLEANUP: bool = false;
+const DEFAULT_EXECUTOR_ORDERED_DELIVERY: bool = false;
+const DEFAULT_EXECUTOR_WRITE_BATCHING: bool = false;
+const DEFAULT_EXECUTOR_BACKLOG_ALARM_PERIOD: Duration = Duration::from_secs(10);
+const DEFAULT_EXECUTOR_BACKLOG_ALARM_ABORT: bool = false;
+const DEFAULT_EXECUTOR_THROTTLE_BURST: usize = 1;
 
 const DEFAULT_WORKERS: usize = 1;
 const DEFAULT_EXECUTORS: usize = 1;
@@ -25,10 +36,19 @@ const DEFAULT_MULTIPLEXING: usize = 1;
 
 // tempo's config
 const DEFAULT_TEMPO_TINY_QUORUMS: bool = false;
+const DEFAULT_TEMPO_PER_COMMAND_TINY_QUORUMS: bool = false;
 const DEFAULT_TEMPO_DETACHED_SEND_INTERVAL: Duration = Duration::from_millis(5);
+const DEFAULT_TEMPO_COMMIT_CLOCK_BUMP: bool = false;
+const DEFAULT_TEMPO_MULTI_SHARD_READS: bool = false;
 
 // protocol's config
 const DEFAULT_SKIP_FAST_ACK: bool = false;
+const DEFAULT_STALE_READS: bool = false;
+const DEFAULT_KEEP_ALIVE_ON_VIOLATION: bool = false;
+const DEFAULT_FARTHEST_FIRST: bool = false;
+const DEFAULT_CANARY_MODE: bool = false;
+const DEFAULT_SERVER_DELAY_INJECTION_DELAY: Duration =
+    Duration::from_millis(50);
 
 #[global_allocator]
 #[cfg(feature = "jemalloc")]
@@ -41,9 +61,10 @@ type ProtocolArgs = (
     IpAddr,
     u16,
     u16,
+    Option<u16>,
     Vec<(String, Option<Duration>)>,
     Config,
-    bool,
+    TcpOptions,
     usize,
     Option<Duration>,
     usize,
@@ -52,10 +73,16 @@ type ProtocolArgs = (
     usize,
     usize,
     Option<String>,
+    Option<String>,
     Option<Duration>,
     Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    bool,
     usize,
     Option<usize>,
+    Option<u64>,
 );
 
 #[allow(dead_code)]
@@ -63,7 +90,17 @@ pub fn run<P>() -> Result<(), Report>
 where
     P: Protocol + Send + 'static,
 {
-    let (args, _guard) = parse_args();
+    let matches = app().get_matches();
+    run_with::<P>(&matches)
+}
+
+/// Runs protocol `P` given the arguments in `matches` (which should have been
+/// parsed by `app()`, possibly extended with extra arguments).
+pub fn run_with<P>(matches: &ArgMatches<'_>) -> Result<(), Report>
+where
+    P: Protocol + Send + 'static,
+{
+    let (args, _guard) = parse_args(matches);
     let (
         process_id,
         shard_id,
@@ -71,9 +108,10 @@ where
         ip,
         port,
         client_port,
+        control_port,
         addresses,
         config,
-        tcp_nodelay,
+        tcp_options,
         tcp_buffer_size,
         tcp_flush_interval,
         process_channel_buffer_size,
@@ -82,12 +120,84 @@ where
         executors,
         multiplexing,
         execution_log,
+        execution_order_log,
         ping_interval,
         metrics_file,
+        graph_snapshot_dir,
+        epoch_file,
+        canary,
+        canary_mode,
         stack_size,
         cpus,
+        deterministic,
     ) = args;
 
+    if canary_mode {
+        let canary = fantoch::run::canary::<P>(
+            process_id,
+            shard_id,
+            ip,
+            port,
+            config,
+            tcp_options,
+            tcp_buffer_size,
+            executors,
+        );
+        return super::tokio_runtime(stack_size, cpus, deterministic.is_some())
+            .block_on(canary);
+    }
+
+    // other processes (tenants) to be run in this OS process
+    let tenants = parse_tenants(
+        matches.value_of("tenants"),
+        matches.value_of("directory"),
+    );
+    let tenant_sorted_processes = |process_id, shard_id| {
+        parse_sorted_processes(
+            process_id,
+            shard_id,
+            None,
+            matches.value_of("process_regions"),
+            matches.value_of("planet_dir"),
+        )
+    };
+    // files of tenants are suffixed with their process id
+    let tenant_file = |file: &Option<String>, process_id| {
+        file.as_ref().map(|file| format!("{}_{}", file, process_id))
+    };
+    let tenants: Vec<_> = tenants
+        .into_iter()
+        .map(|(process_id, shard_id, port, client_port, addresses)| {
+            fantoch::run::process::<P, String>(
+                process_id,
+                shard_id,
+                tenant_sorted_processes(process_id, shard_id),
+                ip,
+                port,
+                client_port,
+                addresses,
+                config,
+                tcp_options,
+                tcp_buffer_size,
+                tcp_flush_interval,
+                process_channel_buffer_size,
+                client_channel_buffer_size,
+                workers,
+                executors,
+                multiplexing,
+                tenant_file(&execution_log, process_id),
+                tenant_file(&execution_order_log, process_id),
+                ping_interval,
+                tenant_file(&metrics_file, process_id),
+                tenant_file(&graph_snapshot_dir, process_id),
+                deterministic,
+                tenant_file(&epoch_file, process_id),
+                None,
+                None,
+            )
+        })
+        .collect();
+
     let process = fantoch::run::process::<P, String>(
         process_id,
         shard_id,
@@ -97,7 +207,7 @@ where
         client_port,
         addresses,
         config,
-        tcp_nodelay,
+        tcp_options,
         tcp_buffer_size,
         tcp_flush_interval,
         process_channel_buffer_size,
@@ -106,15 +216,29 @@ where
         executors,
         multiplexing,
         execution_log,
+        execution_order_log,
         ping_interval,
         metrics_file,
+        graph_snapshot_dir,
+        deterministic,
+        epoch_file,
+        canary,
+        control_port,
     );
 
-    super::tokio_runtime(stack_size, cpus).block_on(process)
+    let runtime =
+        super::tokio_runtime(stack_size, cpus, deterministic.is_some());
+    if tenants.is_empty() {
+        runtime.block_on(process)
+    } else {
+        let processes = std::iter::once(process).chain(tenants).collect();
+        runtime.block_on(fantoch::run::processes(processes))
+    }
 }
 
-fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
-    let matches = App::new("process")
+/// Returns the arguments accepted by protocol binaries.
+pub fn app() -> App<'static, 'static> {
+    App::new("process")
         .version("0.1")
         .author("Vitor Enes <vitorenesduarte@gmail.com>")
         .about("Runs an instance of some protocol.")
@@ -141,6 +265,22 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("comma-separated list of 'ID-SHARD_ID', where ID is the process id and SHARD-ID the identifier of the shard it belongs to, sorted by distance; if not set, processes will ping each other and try to figure out this list from ping latency; for this, 'ping_interval' should be set")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("process_regions")
+                .long("process_regions")
+                .value_name("PROCESS_REGIONS")
+                .help("comma-separated list of 'ID-SHARD_ID-REGION', where REGION is the region of the process with identifier ID in shard SHARD_ID; if set, the sorted processes (see --sorted) are derived from the distances between these regions in the planet (see --planet_dir)")
+                .conflicts_with("sorted_processes")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("planet_dir")
+                .long("planet_dir")
+                .value_name("PLANET_DIR")
+                .help("directory with the latencies used to derive the sorted processes from --process_regions; by default, GCP latencies are used")
+                .requires("process_regions")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("ip")
                 .long("ip")
@@ -162,12 +302,27 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("client port to bind to; default: 4000")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("control_port")
+                .long("control_port")
+                .value_name("CONTROL_PORT")
+                .help("port to bind the control listener to; it answers queries sent as lines of text with a JSON line, e.g. 'state_sizes' returns the sizes of the internal state of each worker (dots tracked, clocks per key, etc); by default there's no control listener")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("addresses")
                 .long("addresses")
                 .value_name("ADDRESSES")
-                .help("comma-separated list of addresses to connect to; if a delay (in milliseconds) is to be injected, the address should be of the form IP:PORT-DELAY; for example, 127.0.0.1:3000-120 injects a delay of 120 milliseconds before sending a message to the process at the 127.0.0.1:3000 address")
-                .required(true)
+                .help("comma-separated list of addresses to connect to; if a delay (in milliseconds) is to be injected, the address should be of the form IP:PORT-DELAY; for example, 127.0.0.1:3000-120 injects a delay of 120 milliseconds before sending a message to the process at the 127.0.0.1:3000 address; either this or --directory should be set")
+                .required_unless("directory")
+                .conflicts_with("directory")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("directory")
+                .long("directory")
+                .value_name("DIRECTORY")
+                .help("HTTP endpoint (e.g. http://10.0.0.1:8000/directory) or file from which the addresses of all processes are fetched; each line should have the form 'PROCESS_ID SHARD_ID REGION ADDRESS CLIENT_ADDRESS'; if set, this process connects to all other processes in its shard and to the process in its region in each other shard")
                 .takes_value(true),
         )
         .arg(
@@ -208,6 +363,111 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("executor cleanup interval (in milliseconds); default: 5")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("executor_adaptive_cleanup")
+                .long("executor_adaptive_cleanup")
+                .value_name("EXECUTOR_ADAPTIVE_CLEANUP")
+                .help("bool indicating whether executors should adapt the cleanup interval to their load (ignoring the executor cleanup interval); default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_ordered_delivery")
+                .long("executor_ordered_delivery")
+                .value_name("EXECUTOR_ORDERED_DELIVERY")
+                .help("bool indicating whether command results should be delivered to each client in submission order (buffering the ones that complete out of order); default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_latency_budget")
+                .long("executor_latency_budget")
+                .value_name("EXECUTOR_LATENCY_BUDGET")
+                .help("executor latency budget (in milliseconds); commands whose commit-to-execution delay exceeds this budget are shed (only supported by protocols using the table executor, i.e. Tempo); if no value is set, commands are never shed")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_scc_budget")
+                .long("executor_scc_budget")
+                .value_name("EXECUTOR_SCC_BUDGET")
+                .help("maximum number of vertices visited by each search for SCCs (only used by protocols using the graph executor, i.e. Atlas and EPaxos); searches that exceed it are resumed in the next executor cleanup; if no value is set, searches are never interrupted")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_tenant_count")
+                .long("executor_tenant_count")
+                .value_name("EXECUTOR_TENANT_COUNT")
+                .help("number of tenants among which keys are partitioned; if set, clients are only authorized to access keys of their own tenant, and the ops on other keys fail; if no value is set, there's no access control")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_write_batching")
+                .long("executor_write_batching")
+                .value_name("EXECUTOR_WRITE_BATCHING")
+                .help("bool indicating whether consecutive writes on the same key executed within the same executor drain cycle should be batched, applying only the last one to the store; default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_state_machine")
+                .long("executor_state_machine")
+                .value_name("EXECUTOR_STATE_MACHINE")
+                .help("built-in state machine to which executors apply ops instead of the key-value store: 'lock' (puts acquire the lock on a key, deletes release it) or 'queue' (puts enqueue a value on a key, deletes dequeue it) or 'counter' (increments add to the counter of a key, deletes reset it); if no value is set, ops are applied to the key-value store")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_backlog_alarm_size")
+                .long("executor_backlog_alarm_size")
+                .value_name("EXECUTOR_BACKLOG_ALARM_SIZE")
+                .help("if set, an alarm is raised when the number of commands committed but not yet executed stays above this size for `executor_backlog_alarm_period` (which is checked every few seconds); if no value is set, there are no backlog alarms")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_backlog_alarm_period")
+                .long("executor_backlog_alarm_period")
+                .value_name("EXECUTOR_BACKLOG_ALARM_PERIOD")
+                .help("period (in milliseconds) during which the backlog must stay above `executor_backlog_alarm_size` for an alarm to be raised; default: 10000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_backlog_alarm_abort")
+                .long("executor_backlog_alarm_abort")
+                .value_name("EXECUTOR_BACKLOG_ALARM_ABORT")
+                .help("boolean indicating whether the process should abort once a backlog alarm is raised; the alarm is always logged; default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_throttle_rate")
+                .long("executor_throttle_rate")
+                .value_name("EXECUTOR_THROTTLE_RATE")
+                .help("if set, each executor produces at most this many command results per second (with bursts of up to `executor_throttle_burst` results), emulating a slow execution path; if no value is set, execution is not throttled")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executor_throttle_burst")
+                .long("executor_throttle_burst")
+                .value_name("EXECUTOR_THROTTLE_BURST")
+                .help("number of command results an executor can produce in a burst when execution is throttled by `executor_throttle_rate`; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("stale_reads")
+                .long("stale_reads")
+                .value_name("STALE_READS")
+                .help("boolean indicating whether single-shard read-only commands should be served from the local store, bypassing the protocol (the result may be stale); default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("trace_sample_rate")
+                .long("trace_sample_rate")
+                .value_name("TRACE_SAMPLE_RATE")
+                .help("if set with N, one in every N commands (sampled by dot) has the time of each of its phases traced, and the trace is sent back to the client; if no value is set, commands are not traced")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("preload_keys")
+                .long("preload_keys")
+                .value_name("PRELOAD_KEYS")
+                .help("if set with N, keys 1 to N (i.e. the keys of a Zipf workload) of this shard are created in the store and in the protocol's per-key clocks before the run starts; if no value is set, keys are only created once accessed")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("executor_monitor_pending_interval")
                 .long("executor_monitor_pending_interval")
@@ -222,6 +482,20 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("garbage collection interval (in milliseconds); if no value if set, stability doesn't run and commands are deleted at commit time")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("anti_entropy_interval")
+                .long("anti_entropy_interval")
+                .value_name("ANTI_ENTROPY_INTERVAL")
+                .help("anti-entropy interval (in milliseconds), in which committed commands missing at other processes are re-shipped to them; requires garbage collection; if no value if set, anti-entropy doesn't run")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("dependency_horizon")
+                .long("dependency_horizon")
+                .value_name("DEPENDENCY_HORIZON")
+                .help("maximum number of sequences a pending dependency can be behind the stable frontier in dependency-based protocols (i.e. Atlas and EPaxos); commands with older dependencies take the slow path; requires garbage collection; if no value is set, dependencies can be arbitrarily old")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("leader")
                 .long("leader")
@@ -236,6 +510,13 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("boolean indicating whether tempo's tiny quorums are enabled; default: false")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("tempo_per_command_tiny_quorums")
+                .long("tempo_per_command_tiny_quorums")
+                .value_name("TEMPO_PER_COMMAND_TINY_QUORUMS")
+                .help("boolean indicating whether tempo's tiny quorums are enabled only for single-key commands (with regular quorums used otherwise); default: false")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("tempo_clock_bump_interval")
                 .long("tempo_clock_bump_interval")
@@ -250,6 +531,27 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("number indicating the interval (in milliseconds) between mdetached messages are sent; default: 5")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("tempo_commit_clock_bump")
+                .long("tempo_commit_clock_bump")
+                .value_name("TEMPO_COMMIT_CLOCK_BUMP")
+                .help("boolean indicating whether tempo should bump the clocks of all keys upon receiving a commit with a higher clock; default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tempo_stability_threshold")
+                .long("tempo_stability_threshold")
+                .value_name("TEMPO_STABILITY_THRESHOLD")
+                .help("number overriding the stability threshold used by tempo's executor; it can't be lower than the safe one, which is used if no value is set")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tempo_multi_shard_reads")
+                .long("tempo_multi_shard_reads")
+                .value_name("TEMPO_MULTI_SHARD_READS")
+                .help("boolean indicating whether tempo should serve read-only commands accessing more than one shard with a single round of per-shard clock snapshots; default: false")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("skip_fast_ack")
                 .long("skip_fast_ack")
@@ -257,6 +559,13 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("boolean indicating whether protocols should try to enable the skip fast ack optimization; default: false")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("ack_coalescing_interval")
+                .long("ack_coalescing_interval")
+                .value_name("ACK_COALESCING_INTERVAL")
+                .help("number indicating the interval (in milliseconds) during which the acks sent to the same coordinator are coalesced into a single message; if this value is not set, then acks are sent right away")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("tcp_nodelay")
                 .long("tcp_nodelay")
@@ -264,6 +573,41 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("TCP_NODELAY; default: true")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("tcp_send_buffer_size")
+                .long("tcp_send_buffer_size")
+                .value_name("TCP_SEND_BUFFER_SIZE")
+                .help("size of the socket send buffer (SO_SNDBUF) of each connection; by default the OS default is used")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tcp_recv_buffer_size")
+                .long("tcp_recv_buffer_size")
+                .value_name("TCP_RECV_BUFFER_SIZE")
+                .help("size of the socket receive buffer (SO_RCVBUF) of each connection; by default the OS default is used")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tcp_keepalive")
+                .long("tcp_keepalive")
+                .value_name("TCP_KEEPALIVE")
+                .help("idle time (in milliseconds) after which TCP keepalive probes are sent in each connection; by default keepalive is not enabled")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tcp_max_segment_size")
+                .long("tcp_max_segment_size")
+                .value_name("TCP_MAX_SEGMENT_SIZE")
+                .help("maximum segment size (TCP_MAXSEG) of each connection, e.g. to make use of jumbo frames; by default the MSS is derived from the MTU")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("auth_token")
+                .long("auth_token")
+                .value_name("AUTH_TOKEN")
+                .help("shared secret (a number) presented on each connection to other processes; if set, connections from processes and clients that don't present it are dropped; by default connections are not authenticated")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("tcp_buffer_size")
                 .long("tcp_buffer_size")
@@ -296,6 +640,97 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 )
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("client_to_workers_buffer_size")
+                .long("client_to_workers_buffer_size")
+                .value_name("CLIENT_TO_WORKERS_BUFFER_SIZE")
+                .help("size of the buffer in each channel between clients and workers; default: the client channel buffer size")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("to_executors_buffer_size")
+                .long("to_executors_buffer_size")
+                .value_name("TO_EXECUTORS_BUFFER_SIZE")
+                .help("size of the buffer in each channel between workers and executors; default: the process channel buffer size")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("periodic_to_workers_buffer_size")
+                .long("periodic_to_workers_buffer_size")
+                .value_name("PERIODIC_TO_WORKERS_BUFFER_SIZE")
+                .help("size of the buffer in each channel between the periodic task and workers; default: the process channel buffer size")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("worker_assignment")
+                .long("worker_assignment")
+                .value_name("WORKER_ASSIGNMENT")
+                .help("how messages are assigned to workers given the sequence of the dot (or slot) they refer to; either 'contiguous' (consecutive sequences are assigned to consecutive workers) or 'hashed' (sequences are first hashed with a fixed hash function); default: contiguous")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("client_acceptors")
+                .long("client_acceptors")
+                .value_name("CLIENT_ACCEPTORS")
+                .help("number of tasks accepting client connections; if more than one, they share the client port with SO_REUSEPORT; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("client_results_flush_delay")
+                .long("client_results_flush_delay")
+                .value_name("CLIENT_RESULTS_FLUSH_DELAY")
+                .help("number indicating the interval (in microseconds) between flushes of the command results sent to each client, which are written in batches in the meantime; if this value is not set, then command results are flushed right away")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("client_pending_shards")
+                .long("client_pending_shards")
+                .value_name("CLIENT_PENDING_SHARDS")
+                .help("number of tasks tracking the progress of the commands submitted on each client connection; if more than one, each client multiplexed on the connection is assigned to one of them; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("server_delay_injection_rate")
+                .long("server_delay_injection_rate")
+                .value_name("SERVER_DELAY_INJECTION_RATE")
+                .help("if set, the processing of one in every `server_delay_injection_rate` commands (chosen at random) submitted to this process is delayed by `server_delay_injection_delay`, emulating server-side hiccups; if no value is set, no delay is injected")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("server_delay_injection_delay")
+                .long("server_delay_injection_delay")
+                .value_name("SERVER_DELAY_INJECTION_DELAY")
+                .help("delay (in milliseconds) injected in the processing of the commands selected by `server_delay_injection_rate`; default: 50")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("message_compression")
+                .long("message_compression")
+                .value_name("MESSAGE_COMPRESSION")
+                .help("if set, messages sent to other processes with a serialized size (in bytes) of at least this value are compressed; the threshold used is auto-tuned (but never below this value) given the compression ratios observed and the time spent compressing; if no value is set, messages are not compressed")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("delivery_window")
+                .long("delivery_window")
+                .value_name("DELIVERY_WINDOW")
+                .help("if set, messages sent to other processes are delivered at least once (and deduplicated by the receiver), with at most this many messages per connection awaiting acknowledgment; if no value is set, messages are delivered at most once")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("keep_alive_on_violation")
+                .long("keep_alive_on_violation")
+                .value_name("KEEP_ALIVE_ON_VIOLATION")
+                .help("boolean indicating whether the process should be kept alive when the protocol (or executor) reports a violation of one of its invariants; the violation is always logged; default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("farthest_first")
+                .long("farthest_first")
+                .value_name("FARTHEST_FIRST")
+                .help("boolean indicating whether messages should be sent to the farthest processes first, so that the slowest quorum members receive them (e.g. commits) as early as possible; default: false")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("workers")
                 .long("workers")
@@ -324,6 +759,13 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("log file in which execution info should be written to; by default this information is not logged")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("execution_order_log")
+                .long("execution_order_log")
+                .value_name("EXECUTION_ORDER_LOG")
+                .help("log file in which the order commands are executed on each key should be written to, which can be audited with `execution_order_audit`; by default this information is not logged")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("ping_interval")
                 .long("ping_interval")
@@ -338,6 +780,43 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("file in which metrics are (periodically, every 5s) written to; by default metrics are not logged")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("graph_snapshot_dir")
+                .long("graph_snapshot_dir")
+                .value_name("GRAPH_SNAPSHOT_DIR")
+                .help("directory in which snapshots of the dependency graph of executors (if any) are written to, in DOT and in GraphML; snapshots are taken on violations, on backlog alarms and when SIGUSR1 is received; by default no snapshots are taken")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("epoch_file")
+                .long("epoch_file")
+                .value_name("EPOCH_FILE")
+                .help("file in which the epoch of the process is persisted; the epoch is incremented each time the process starts, so that peers can tell a restarted process apart from its previous incarnation; by default the epoch is always 0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("canary")
+                .long("canary")
+                .value_name("CANARY")
+                .help("address of a canary process (see 'canary_mode') to which executors mirror the execution info they handle and the results they produce; by default there's no canary")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("canary_mode")
+                .long("canary_mode")
+                .value_name("CANARY_MODE")
+                .help("boolean indicating whether to run as the canary of the process with the same id, instead of as a regular process; the canary listens on 'port' for the process (that should have the same 'executors'), doesn't participate in quorums, executes the execution info mirrored and logs the results that diverge from the ones of the process; default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tenants")
+                .long("tenants")
+                .value_name("TENANTS")
+                .help("comma-separated list of 'ID-SHARD_ID' of other processes (e.g. of other shards) to be run in this OS process, sharing its runtime; their ports and the addresses they connect to are taken from the directory (see --directory), and their execution log, metrics file, graph snapshot directory and epoch file are the ones of this process suffixed with '_ID'; by default no other process is run")
+                .requires("directory")
+                .conflicts_with_all(&["sorted_processes", "canary", "canary_mode"])
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("stack_size")
                 .long("stack_size")
@@ -359,23 +838,52 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
                 .help("file to which logs will be written to; if not set, logs will be redirect to the stdout")
                 .takes_value(true),
         )
-        .get_matches();
+        .arg(
+            Arg::with_name("log_json")
+                .long("log_json")
+                .value_name("LOG_JSON")
+                .help("boolean indicating whether logs should be written as JSON lines (one JSON object per event, with its fields at the top level); default: false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("deterministic")
+                .long("deterministic")
+                .value_name("SEED")
+                .help("if set, all tasks run in a single thread and protocol workers poll their incoming messages in an order determined by SEED; this is meant for debugging, as it makes runs easier to reproduce; by default, tasks run in all the cpus set and messages are polled in a random order")
+                .takes_value(true),
+        )
+}
 
+fn parse_args(
+    matches: &ArgMatches<'_>,
+) -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
     let tracing_directives = None;
+    let log_json = super::parse_log_json(matches.value_of("log_json"));
     let guard = fantoch::util::init_tracing_subscriber(
         matches.value_of("log_file"),
         tracing_directives,
+        log_json,
     );
 
     // parse arguments
     let process_id = parse_process_id(matches.value_of("id"));
     let shard_id = parse_shard_id(matches.value_of("shard_id"));
-    let sorted_processes =
-        parse_sorted_processes(matches.value_of("sorted_processes"));
+    let sorted_processes = parse_sorted_processes(
+        process_id,
+        shard_id,
+        matches.value_of("sorted_processes"),
+        matches.value_of("process_regions"),
+        matches.value_of("planet_dir"),
+    );
     let ip = parse_ip(matches.value_of("ip"));
     let port = parse_port(matches.value_of("port"));
     let client_port = parse_client_port(matches.value_of("client_port"));
-    let addresses = parse_addresses(matches.value_of("addresses"));
+    let control_port = parse_control_port(matches.value_of("control_port"));
+    let addresses = parse_addresses(
+        process_id,
+        matches.value_of("addresses"),
+        matches.value_of("directory"),
+    );
 
     // parse config
     let config = build_config(
@@ -386,22 +894,102 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
         parse_executor_cleanup_interval(
             matches.value_of("executor_cleanup_interval"),
         ),
+        parse_executor_adaptive_cleanup(
+            matches.value_of("executor_adaptive_cleanup"),
+        ),
+        parse_executor_ordered_delivery(
+            matches.value_of("executor_ordered_delivery"),
+        ),
+        parse_executor_latency_budget(
+            matches.value_of("executor_latency_budget"),
+        ),
+        parse_executor_scc_budget(matches.value_of("executor_scc_budget")),
+        parse_executor_tenant_count(matches.value_of("executor_tenant_count")),
+        parse_executor_write_batching(
+            matches.value_of("executor_write_batching"),
+        ),
+        parse_executor_state_machine(
+            matches.value_of("executor_state_machine"),
+        ),
+        parse_executor_backlog_alarm(
+            matches.value_of("executor_backlog_alarm_size"),
+            matches.value_of("executor_backlog_alarm_period"),
+        ),
+        parse_executor_backlog_alarm_abort(
+            matches.value_of("executor_backlog_alarm_abort"),
+        ),
+        parse_executor_throttle(
+            matches.value_of("executor_throttle_rate"),
+            matches.value_of("executor_throttle_burst"),
+        ),
+        parse_stale_reads(matches.value_of("stale_reads")),
+        parse_trace_sample_rate(matches.value_of("trace_sample_rate")),
+        parse_preload_keys(matches.value_of("preload_keys")),
         parse_executor_monitor_pending_interval(
             matches.value_of("executor_monitor_pending_interval"),
         ),
         parse_gc_interval(matches.value_of("gc_interval")),
+        parse_anti_entropy_interval(matches.value_of("anti_entropy_interval")),
+        parse_dependency_horizon(matches.value_of("dependency_horizon")),
         parse_leader(matches.value_of("leader")),
         parse_tempo_tiny_quorums(matches.value_of("tempo_tiny_quorums")),
+        parse_tempo_per_command_tiny_quorums(
+            matches.value_of("tempo_per_command_tiny_quorums"),
+        ),
         parse_tempo_clock_bump_interval(
             matches.value_of("tempo_clock_bump_interval"),
         ),
         parse_tempo_detached_send_interval(
             matches.value_of("tempo_detached_send_interval"),
         ),
+        parse_tempo_commit_clock_bump(
+            matches.value_of("tempo_commit_clock_bump"),
+        ),
+        parse_tempo_stability_threshold(
+            matches.value_of("tempo_stability_threshold"),
+        ),
+        parse_tempo_multi_shard_reads(
+            matches.value_of("tempo_multi_shard_reads"),
+        ),
         parse_skip_fast_ack(matches.value_of("skip_fast_ack")),
+        parse_ack_coalescing_interval(
+            matches.value_of("ack_coalescing_interval"),
+        ),
+        parse_client_to_workers_buffer_size(
+            matches.value_of("client_to_workers_buffer_size"),
+        ),
+        parse_to_executors_buffer_size(
+            matches.value_of("to_executors_buffer_size"),
+        ),
+        parse_periodic_to_workers_buffer_size(
+            matches.value_of("periodic_to_workers_buffer_size"),
+        ),
+        parse_worker_assignment(matches.value_of("worker_assignment")),
+        parse_client_acceptors(matches.value_of("client_acceptors")),
+        parse_client_results_flush_delay(
+            matches.value_of("client_results_flush_delay"),
+        ),
+        parse_client_pending_shards(matches.value_of("client_pending_shards")),
+        parse_server_delay_injection(
+            matches.value_of("server_delay_injection_rate"),
+            matches.value_of("server_delay_injection_delay"),
+        ),
+        parse_message_compression(matches.value_of("message_compression")),
+        parse_delivery_window(matches.value_of("delivery_window")),
+        parse_keep_alive_on_violation(
+            matches.value_of("keep_alive_on_violation"),
+        ),
+        parse_farthest_first(matches.value_of("farthest_first")),
     );
 
-    let tcp_nodelay = super::parse_tcp_nodelay(matches.value_of("tcp_nodelay"));
+    let tcp_options = super::parse_tcp_options(
+        matches.value_of("tcp_nodelay"),
+        matches.value_of("tcp_send_buffer_size"),
+        matches.value_of("tcp_recv_buffer_size"),
+        matches.value_of("tcp_keepalive"),
+        matches.value_of("tcp_max_segment_size"),
+        matches.value_of("auth_token"),
+    );
     let tcp_buffer_size =
         super::parse_tcp_buffer_size(matches.value_of("tcp_buffer_size"));
     let tcp_flush_interval =
@@ -417,19 +1005,28 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
     let executors = parse_executors(matches.value_of("executors"));
     let multiplexing = parse_multiplexing(matches.value_of("multiplexing"));
     let execution_log = parse_execution_log(matches.value_of("execution_log"));
+    let execution_order_log =
+        parse_execution_log(matches.value_of("execution_order_log"));
     let ping_interval = parse_ping_interval(matches.value_of("ping_interval"));
     let metrics_file = parse_metrics_file(matches.value_of("metrics_file"));
+    let graph_snapshot_dir =
+        parse_graph_snapshot_dir(matches.value_of("graph_snapshot_dir"));
+    let epoch_file = parse_epoch_file(matches.value_of("epoch_file"));
+    let canary = parse_canary(matches.value_of("canary"));
+    let canary_mode = parse_canary_mode(matches.value_of("canary_mode"));
     let stack_size = super::parse_stack_size(matches.value_of("stack_size"));
     let cpus = super::parse_cpus(matches.value_of("cpus"));
+    let deterministic = parse_deterministic(matches.value_of("deterministic"));
 
     info!("process id: {}", process_id);
     info!("sorted processes: {:?}", sorted_processes);
     info!("ip: {:?}", ip);
     info!("port: {}", port);
     info!("client port: {}", client_port);
+    info!("control port: {:?}", control_port);
     info!("addresses: {:?}", addresses);
     info!("config: {:?}", config);
-    info!("tcp_nodelay: {:?}", tcp_nodelay);
+    info!("tcp options: {:?}", tcp_options);
     info!("tcp buffer size: {:?}", tcp_buffer_size);
     info!("tcp flush interval: {:?}", tcp_flush_interval);
     info!(
@@ -444,9 +1041,15 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
     info!("executors: {:?}", executors);
     info!("multiplexing: {:?}", multiplexing);
     info!("execution log: {:?}", execution_log);
+    info!("execution order log: {:?}", execution_order_log);
     info!("ping_interval: {:?}", ping_interval);
     info!("metrics file: {:?}", metrics_file);
+    info!("graph snapshot dir: {:?}", graph_snapshot_dir);
+    info!("epoch file: {:?}", epoch_file);
+    info!("canary: {:?}", canary);
+    info!("canary mode: {:?}", canary_mode);
     info!("stack size: {:?}", stack_size);
+    info!("deterministic: {:?}", deterministic);
 
     let args = (
         process_id,
@@ -455,9 +1058,10 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
         ip,
         port,
         client_port,
+        control_port,
         addresses,
         config,
-        tcp_nodelay,
+        tcp_options,
         tcp_buffer_size,
         tcp_flush_interval,
         process_channel_buffer_size,
@@ -466,10 +1070,16 @@ fn parse_args() -> (ProtocolArgs, tracing_appender::non_blocking::WorkerGuard) {
         executors,
         multiplexing,
         execution_log,
+        execution_order_log,
         ping_interval,
         metrics_file,
+        graph_snapshot_dir,
+        epoch_file,
+        canary,
+        canary_mode,
         stack_size,
         cpus,
+        deterministic,
     );
     (args, guard)
 }
@@ -493,8 +1103,23 @@ where
 }
 
 fn parse_sorted_processes(
+    process_id: ProcessId,
+    shard_id: ShardId,
     ids: Option<&str>,
+    process_regions: Option<&str>,
+    planet_dir: Option<&str>,
 ) -> Option<Vec<(ProcessId, ShardId)>> {
+    if let Some(process_regions) = process_regions {
+        let planet = planet_dir.map(Planet::from).unwrap_or_else(Planet::new);
+        let processes = parse_process_regions(process_regions);
+        let sorted = fantoch::util::derive_sorted_processes(
+            process_id, shard_id, &planet, processes,
+        )
+        .unwrap_or_else(|e| {
+            panic!("sorted processes could not be derived: {}", e)
+        });
+        return Some(sorted);
+    }
     ids.map(|ids| {
         ids.split(LIST_SEP)
             .map(|entry| {
@@ -508,7 +1133,75 @@ fn parse_sorted_processes(
     })
 }
 
-fn parse_ip(ip: Option<&str>) -> IpAddr {
+fn parse_tenants(
+    tenants: Option<&str>,
+    directory: Option<&str>,
+) -> Vec<(
+    ProcessId,
+    ShardId,
+    u16,
+    u16,
+    Vec<(String, Option<Duration>)>,
+)> {
+    let tenants = if let Some(tenants) = tenants {
+        tenants
+    } else {
+        return Vec::new();
+    };
+    let directory =
+        Directory::fetch(directory.expect("directory should be set"))
+            .expect("directory should be fetched");
+    tenants
+        .split(LIST_SEP)
+        .map(|entry| {
+            let parts: Vec<_> = entry.split('-').collect();
+            assert_eq!(
+                parts.len(),
+                2,
+                "each tenant entry should have the form 'ID-SHARD_ID'"
+            );
+            let id = parse_id::<ProcessId>(parts[0]);
+            let shard_id = parse_id::<ShardId>(parts[1]);
+            let entry = directory.entry(id).unwrap_or_else(|| {
+                panic!("tenant {} should be in the directory", id)
+            });
+            assert_eq!(
+                entry.shard_id, shard_id,
+                "tenant {} should be in shard {} in the directory",
+                id, shard_id
+            );
+            let port = entry.port().expect("tenant port should be a number");
+            let client_port = entry
+                .client_port()
+                .expect("tenant client port should be a number");
+            let addresses = directory
+                .peers(id)
+                .expect("tenant peers should be in the directory")
+                .into_iter()
+                .map(|address| (address, None))
+                .collect();
+            (id, shard_id, port, client_port, addresses)
+        })
+        .collect()
+}
+
+fn parse_process_regions(
+    process_regions: &str,
+) -> Vec<(ProcessId, ShardId, Region)> {
+    process_regions
+        .split(LIST_SEP)
+        .map(|entry| {
+            // regions may contain '-', so only split the first two
+            let parts: Vec<_> = entry.splitn(3, '-').collect();
+            assert_eq!(parts.len(), 3, "each process region entry should have the form 'ID-SHARD_ID-REGION'");
+            let id = parse_id::<ProcessId>(parts[0]);
+            let shard_id = parse_id::<ShardId>(parts[1]);
+            (id, shard_id, Region::new(parts[2]))
+        })
+        .collect()
+}
+
+pub fn parse_ip(ip: Option<&str>) -> IpAddr {
     ip.unwrap_or(DEFAULT_IP)
         .parse::<IpAddr>()
         .expect("ip should be a valid ip address")
@@ -526,70 +1219,159 @@ fn parse_client_port(port: Option<&str>) -> u16 {
     .unwrap_or(DEFAULT_CLIENT_PORT)
 }
 
-fn parse_addresses(addresses: Option<&str>) -> Vec<(String, Option<Duration>)> {
+fn parse_control_port(port: Option<&str>) -> Option<u16> {
+    port.map(|port| {
+        port.parse::<u16>()
+            .expect("control port should be a number")
+    })
+}
+
+fn parse_addresses(
+    process_id: ProcessId,
+    addresses: Option<&str>,
+    directory: Option<&str>,
+) -> Vec<(String, Option<Duration>)> {
+    if let Some(directory) = directory {
+        // no delays are injected when addresses come from a directory
+        return Directory::fetch(directory)
+            .and_then(|directory| directory.peers(process_id))
+            .expect("directory should be fetched")
+            .into_iter()
+            .map(|address| (address, None))
+            .collect();
+    }
     addresses
         .expect("addresses should be set")
         .split(LIST_SEP)
-        .map(|address| {
-            let parts: Vec<_> = address.split('-').collect();
-            let address = parts[0].to_string();
-            match parts.len() {
-                1 => {
-                    // in this case, no delay was set
-                    (address, None)
-                }
-                2 => {
-                    let millis = parts[1]
-                        .parse::<u64>()
-                        .expect("address delay should be a number");
-                    let delay = Duration::from_millis(millis);
-                    (address, Some(delay))
-                }
-                _ => {
-                    panic!("invalid address: {:?}", address);
-                }
-            }
-        })
+        .map(parse_address)
         .collect()
 }
 
+// Parses an address of the form IP:PORT, or IP:PORT-DELAY if a delay (in
+// milliseconds) is to be injected.
+pub fn parse_address(address: &str) -> (String, Option<Duration>) {
+    let parts: Vec<_> = address.split('-').collect();
+    let address = parts[0].to_string();
+    match parts.len() {
+        1 => {
+            // in this case, no delay was set
+            (address, None)
+        }
+        2 => {
+            let millis = parts[1]
+                .parse::<u64>()
+                .expect("address delay should be a number");
+            let delay = Duration::from_millis(millis);
+            (address, Some(delay))
+        }
+        _ => {
+            panic!("invalid address: {:?}", address);
+        }
+    }
+}
+
 pub fn build_config(
     n: usize,
     f: usize,
     shard_count: usize,
     execute_at_commit: bool,
     executor_cleanup_interval: Duration,
+    executor_adaptive_cleanup: bool,
+    executor_ordered_delivery: bool,
+    executor_latency_budget: Option<Duration>,
+    executor_scc_budget: Option<usize>,
+    executor_tenant_count: Option<usize>,
+    executor_write_batching: bool,
+    executor_state_machine: Option<StateMachineKind>,
+    executor_backlog_alarm: Option<(usize, Duration)>,
+    executor_backlog_alarm_abort: bool,
+    executor_throttle: Option<(usize, usize)>,
+    stale_reads: bool,
+    trace_sample_rate: Option<usize>,
+    preload_keys: Option<usize>,
     executor_monitor_pending_interval: Option<Duration>,
     gc_interval: Option<Duration>,
+    anti_entropy_interval: Option<Duration>,
+    dependency_horizon: Option<u64>,
     leader: Option<ProcessId>,
     tempo_tiny_quorums: bool,
+    tempo_per_command_tiny_quorums: bool,
     tempo_clock_bump_interval: Option<Duration>,
     tempo_detached_send_interval: Duration,
+    tempo_commit_clock_bump: bool,
+    tempo_stability_threshold: Option<usize>,
+    tempo_multi_shard_reads: bool,
     skip_fast_ack: bool,
+    ack_coalescing_interval: Option<Duration>,
+    client_to_workers_buffer_size: Option<usize>,
+    to_executors_buffer_size: Option<usize>,
+    periodic_to_workers_buffer_size: Option<usize>,
+    worker_assignment: WorkerAssignment,
+    client_acceptors: Option<usize>,
+    client_results_flush_delay: Option<Duration>,
+    client_pending_shards: Option<usize>,
+    server_delay_injection: Option<(usize, Duration)>,
+    message_compression: Option<usize>,
+    delivery_window: Option<usize>,
+    keep_alive_on_violation: bool,
+    farthest_first: bool,
 ) -> Config {
     // create config
     let mut config = Config::new(n, f);
     config.set_shard_count(shard_count);
     config.set_execute_at_commit(execute_at_commit);
     config.set_executor_cleanup_interval(executor_cleanup_interval);
+    config.set_executor_adaptive_cleanup(executor_adaptive_cleanup);
+    config.set_executor_ordered_delivery(executor_ordered_delivery);
+    config.set_executor_latency_budget(executor_latency_budget);
+    config.set_executor_scc_budget(executor_scc_budget);
+    config.set_executor_tenant_count(executor_tenant_count);
+    config.set_executor_write_batching(executor_write_batching);
+    config.set_executor_state_machine(executor_state_machine);
+    config.set_executor_backlog_alarm(executor_backlog_alarm);
+    config.set_executor_backlog_alarm_abort(executor_backlog_alarm_abort);
+    config.set_executor_throttle(executor_throttle);
+    config.set_stale_reads(stale_reads);
+    config.set_trace_sample_rate(trace_sample_rate);
+    config.set_preload_keys(preload_keys);
     if let Some(interval) = executor_monitor_pending_interval {
         config.set_executor_monitor_pending_interval(interval);
     }
     if let Some(interval) = gc_interval {
         config.set_gc_interval(interval);
     }
+    config.set_anti_entropy_interval(anti_entropy_interval);
+    config.set_dependency_horizon(dependency_horizon);
     // set leader if we have one
     if let Some(leader) = leader {
         config.set_leader(leader);
     }
     // set tempo's config
     config.set_tempo_tiny_quorums(tempo_tiny_quorums);
+    config.set_tempo_per_command_tiny_quorums(tempo_per_command_tiny_quorums);
     if let Some(interval) = tempo_clock_bump_interval {
         config.set_tempo_clock_bump_interval(interval);
     }
     config.set_tempo_detached_send_interval(tempo_detached_send_interval);
+    config.set_tempo_commit_clock_bump(tempo_commit_clock_bump);
+    config.set_tempo_stability_threshold(tempo_stability_threshold);
+    config.set_tempo_multi_shard_reads(tempo_multi_shard_reads);
     // set protocol's config
     config.set_skip_fast_ack(skip_fast_ack);
+    config.set_ack_coalescing_interval(ack_coalescing_interval);
+    // set run layer's config
+    config.set_client_to_workers_buffer_size(client_to_workers_buffer_size);
+    config.set_to_executors_buffer_size(to_executors_buffer_size);
+    config.set_periodic_to_workers_buffer_size(periodic_to_workers_buffer_size);
+    config.set_worker_assignment(worker_assignment);
+    config.set_client_acceptors(client_acceptors);
+    config.set_client_results_flush_delay(client_results_flush_delay);
+    config.set_client_pending_shards(client_pending_shards);
+    config.set_server_delay_injection(server_delay_injection);
+    config.set_message_compression(message_compression);
+    config.set_delivery_window(delivery_window);
+    config.set_keep_alive_on_violation(keep_alive_on_violation);
+    config.set_farthest_first(farthest_first);
     config
 }
 
@@ -634,6 +1416,154 @@ pub fn parse_executor_cleanup_interval(interval: Option<&str>) -> Duration {
         .unwrap_or(DEFAULT_EXECUTOR_CLEANUP_INTERVAL)
 }
 
+pub fn parse_executor_adaptive_cleanup(adaptive_cleanup: Option<&str>) -> bool {
+    adaptive_cleanup
+        .map(|adaptive_cleanup| {
+            adaptive_cleanup
+                .parse::<bool>()
+                .expect("executor_adaptive_cleanup should be a bool")
+        })
+        .unwrap_or(DEFAULT_EXECUTOR_ADAPTIVE_CLEANUP)
+}
+
+pub fn parse_executor_ordered_delivery(ordered_delivery: Option<&str>) -> bool {
+    ordered_delivery
+        .map(|ordered_delivery| {
+            ordered_delivery
+                .parse::<bool>()
+                .expect("executor_ordered_delivery should be a bool")
+        })
+        .unwrap_or(DEFAULT_EXECUTOR_ORDERED_DELIVERY)
+}
+
+pub fn parse_executor_latency_budget(budget: Option<&str>) -> Option<Duration> {
+    budget.map(|budget| {
+        let ms = budget
+            .parse::<u64>()
+            .expect("executor_latency_budget should be a number");
+        Duration::from_millis(ms)
+    })
+}
+
+pub fn parse_executor_scc_budget(budget: Option<&str>) -> Option<usize> {
+    budget.map(|budget| {
+        budget
+            .parse::<usize>()
+            .expect("executor_scc_budget should be a number")
+    })
+}
+
+pub fn parse_executor_tenant_count(
+    tenant_count: Option<&str>,
+) -> Option<usize> {
+    tenant_count.map(|tenant_count| {
+        tenant_count
+            .parse::<usize>()
+            .expect("executor_tenant_count should be a number")
+    })
+}
+
+pub fn parse_executor_write_batching(write_batching: Option<&str>) -> bool {
+    write_batching
+        .map(|write_batching| {
+            write_batching
+                .parse::<bool>()
+                .expect("executor_write_batching should be a bool")
+        })
+        .unwrap_or(DEFAULT_EXECUTOR_WRITE_BATCHING)
+}
+
+pub fn parse_executor_state_machine(
+    state_machine: Option<&str>,
+) -> Option<StateMachineKind> {
+    state_machine.map(|state_machine| match state_machine {
+        "lock" => StateMachineKind::Lock,
+        "queue" => StateMachineKind::Queue,
+        "counter" => StateMachineKind::Counter,
+        state_machine => {
+            panic!("invalid executor state machine: {}", state_machine)
+        }
+    })
+}
+
+pub fn parse_executor_backlog_alarm(
+    size: Option<&str>,
+    period: Option<&str>,
+) -> Option<(usize, Duration)> {
+    size.map(|size| {
+        let size = size
+            .parse::<usize>()
+            .expect("executor_backlog_alarm_size should be a number");
+        let period = period
+            .map(|period| {
+                let ms = period
+                    .parse::<u64>()
+                    .expect("executor_backlog_alarm_period should be a number");
+                Duration::from_millis(ms)
+            })
+            .unwrap_or(DEFAULT_EXECUTOR_BACKLOG_ALARM_PERIOD);
+        (size, period)
+    })
+}
+
+pub fn parse_executor_backlog_alarm_abort(abort: Option<&str>) -> bool {
+    abort
+        .map(|abort| {
+            abort
+                .parse::<bool>()
+                .expect("executor_backlog_alarm_abort should be a bool")
+        })
+        .unwrap_or(DEFAULT_EXECUTOR_BACKLOG_ALARM_ABORT)
+}
+
+pub fn parse_executor_throttle(
+    rate: Option<&str>,
+    burst: Option<&str>,
+) -> Option<(usize, usize)> {
+    rate.map(|rate| {
+        let rate = rate
+            .parse::<usize>()
+            .expect("executor_throttle_rate should be a number");
+        assert!(rate > 0, "executor_throttle_rate should be positive");
+        let burst = burst
+            .map(|burst| {
+                burst
+                    .parse::<usize>()
+                    .expect("executor_throttle_burst should be a number")
+            })
+            .unwrap_or(DEFAULT_EXECUTOR_THROTTLE_BURST);
+        (rate, burst)
+    })
+}
+
+pub fn parse_stale_reads(stale_reads: Option<&str>) -> bool {
+    stale_reads
+        .map(|stale_reads| {
+            stale_reads
+                .parse::<bool>()
+                .expect("stale_reads should be a boolean")
+        })
+        .unwrap_or(DEFAULT_STALE_READS)
+}
+
+pub fn parse_trace_sample_rate(sample_rate: Option<&str>) -> Option<usize> {
+    sample_rate.map(|sample_rate| {
+        let sample_rate = sample_rate
+            .parse::<usize>()
+            .expect("trace_sample_rate should be a number");
+        assert!(sample_rate > 0, "trace_sample_rate should be non-zero");
+        sample_rate
+    })
+}
+
+pub fn parse_preload_keys(preload_keys: Option<&str>) -> Option<usize> {
+    preload_keys.map(|preload_keys| {
+        preload_keys
+            .parse::<usize>()
+            .expect("preload_keys should be a number")
+    })
+}
+
 pub fn parse_executor_monitor_pending_interval(
     interval: Option<&str>,
 ) -> Option<Duration> {
@@ -654,6 +1584,23 @@ pub fn parse_gc_interval(gc_interval: Option<&str>) -> Option<Duration> {
     })
 }
 
+fn parse_anti_entropy_interval(interval: Option<&str>) -> Option<Duration> {
+    interval.map(|interval| {
+        let ms = interval
+            .parse::<u64>()
+            .expect("anti_entropy_interval should be a number");
+        Duration::from_millis(ms)
+    })
+}
+
+fn parse_dependency_horizon(horizon: Option<&str>) -> Option<u64> {
+    horizon.map(|horizon| {
+        horizon
+            .parse::<u64>()
+            .expect("dependency_horizon should be a number")
+    })
+}
+
 fn parse_leader(leader: Option<&str>) -> Option<ProcessId> {
     leader.map(|leader| parse_id(leader))
 }
@@ -668,6 +1615,18 @@ fn parse_tempo_tiny_quorums(tempo_tiny_quorums: Option<&str>) -> bool {
         .unwrap_or(DEFAULT_TEMPO_TINY_QUORUMS)
 }
 
+fn parse_tempo_per_command_tiny_quorums(
+    tempo_per_command_tiny_quorums: Option<&str>,
+) -> bool {
+    tempo_per_command_tiny_quorums
+        .map(|tempo_per_command_tiny_quorums| {
+            tempo_per_command_tiny_quorums
+                .parse::<bool>()
+                .expect("tempo_per_command_tiny_quorums should be a bool")
+        })
+        .unwrap_or(DEFAULT_TEMPO_PER_COMMAND_TINY_QUORUMS)
+}
+
 fn parse_tempo_clock_bump_interval(interval: Option<&str>) -> Option<Duration> {
     interval.map(|interval| {
         let ms = interval
@@ -688,6 +1647,38 @@ fn parse_tempo_detached_send_interval(interval: Option<&str>) -> Duration {
         .unwrap_or(DEFAULT_TEMPO_DETACHED_SEND_INTERVAL)
 }
 
+fn parse_tempo_commit_clock_bump(
+    tempo_commit_clock_bump: Option<&str>,
+) -> bool {
+    tempo_commit_clock_bump
+        .map(|tempo_commit_clock_bump| {
+            tempo_commit_clock_bump
+                .parse::<bool>()
+                .expect("tempo_commit_clock_bump should be a bool")
+        })
+        .unwrap_or(DEFAULT_TEMPO_COMMIT_CLOCK_BUMP)
+}
+
+fn parse_tempo_stability_threshold(threshold: Option<&str>) -> Option<usize> {
+    threshold.map(|threshold| {
+        threshold
+            .parse::<usize>()
+            .expect("tempo_stability_threshold should be a number")
+    })
+}
+
+fn parse_tempo_multi_shard_reads(
+    tempo_multi_shard_reads: Option<&str>,
+) -> bool {
+    tempo_multi_shard_reads
+        .map(|tempo_multi_shard_reads| {
+            tempo_multi_shard_reads
+                .parse::<bool>()
+                .expect("tempo_multi_shard_reads should be a bool")
+        })
+        .unwrap_or(DEFAULT_TEMPO_MULTI_SHARD_READS)
+}
+
 pub fn parse_skip_fast_ack(skip_fast_ack: Option<&str>) -> bool {
     skip_fast_ack
         .map(|skip_fast_ack| {
@@ -698,6 +1689,137 @@ pub fn parse_skip_fast_ack(skip_fast_ack: Option<&str>) -> bool {
         .unwrap_or(DEFAULT_SKIP_FAST_ACK)
 }
 
+fn parse_ack_coalescing_interval(interval: Option<&str>) -> Option<Duration> {
+    interval.map(|interval| {
+        let ms = interval
+            .parse::<u64>()
+            .expect("ack_coalescing_interval should be a number");
+        Duration::from_millis(ms)
+    })
+}
+
+fn parse_client_to_workers_buffer_size(
+    buffer_size: Option<&str>,
+) -> Option<usize> {
+    buffer_size.map(|buffer_size| {
+        buffer_size
+            .parse::<usize>()
+            .expect("client_to_workers_buffer_size should be a number")
+    })
+}
+
+fn parse_to_executors_buffer_size(buffer_size: Option<&str>) -> Option<usize> {
+    buffer_size.map(|buffer_size| {
+        buffer_size
+            .parse::<usize>()
+            .expect("to_executors_buffer_size should be a number")
+    })
+}
+
+fn parse_periodic_to_workers_buffer_size(
+    buffer_size: Option<&str>,
+) -> Option<usize> {
+    buffer_size.map(|buffer_size| {
+        buffer_size
+            .parse::<usize>()
+            .expect("periodic_to_workers_buffer_size should be a number")
+    })
+}
+
+fn parse_worker_assignment(assignment: Option<&str>) -> WorkerAssignment {
+    assignment
+        .map(|assignment| match assignment {
+            "contiguous" => WorkerAssignment::Contiguous,
+            "hashed" => WorkerAssignment::Hashed,
+            assignment => panic!("invalid worker assignment: {}", assignment),
+        })
+        .unwrap_or_default()
+}
+
+fn parse_client_acceptors(acceptors: Option<&str>) -> Option<usize> {
+    acceptors.map(|acceptors| {
+        acceptors
+            .parse::<usize>()
+            .expect("client_acceptors should be a number")
+    })
+}
+
+fn parse_client_results_flush_delay(delay: Option<&str>) -> Option<Duration> {
+    delay.map(|delay| {
+        let micros = delay
+            .parse::<u64>()
+            .expect("client_results_flush_delay should be a number");
+        Duration::from_micros(micros)
+    })
+}
+
+fn parse_client_pending_shards(shards: Option<&str>) -> Option<usize> {
+    shards.map(|shards| {
+        shards
+            .parse::<usize>()
+            .expect("client_pending_shards should be a number")
+    })
+}
+
+fn parse_server_delay_injection(
+    rate: Option<&str>,
+    delay: Option<&str>,
+) -> Option<(usize, Duration)> {
+    rate.map(|rate| {
+        let rate = rate
+            .parse::<usize>()
+            .expect("server_delay_injection_rate should be a number");
+        assert!(rate > 0, "server_delay_injection_rate should be positive");
+        let delay = delay
+            .map(|delay| {
+                let ms = delay
+                    .parse::<u64>()
+                    .expect("server_delay_injection_delay should be a number");
+                Duration::from_millis(ms)
+            })
+            .unwrap_or(DEFAULT_SERVER_DELAY_INJECTION_DELAY);
+        (rate, delay)
+    })
+}
+
+fn parse_message_compression(threshold: Option<&str>) -> Option<usize> {
+    threshold.map(|threshold| {
+        threshold
+            .parse::<usize>()
+            .expect("message_compression should be a number")
+    })
+}
+
+fn parse_delivery_window(window: Option<&str>) -> Option<usize> {
+    window.map(|window| {
+        let window = window
+            .parse::<usize>()
+            .expect("delivery_window should be a number");
+        assert!(window > 0, "delivery_window should be positive");
+        window
+    })
+}
+
+fn parse_keep_alive_on_violation(keep_alive: Option<&str>) -> bool {
+    keep_alive
+        .map(|keep_alive| {
+            keep_alive
+                .parse::<bool>()
+                .expect("keep_alive_on_violation should be a boolean")
+        })
+        .unwrap_or(DEFAULT_KEEP_ALIVE_ON_VIOLATION)
+}
+
+fn parse_farthest_first(farthest_first: Option<&str>) -> bool {
+    farthest_first
+        .map(|farthest_first| {
+            farthest_first
+                .parse::<bool>()
+                .expect("farthest_first should be a boolean")
+        })
+        .unwrap_or(DEFAULT_FARTHEST_FIRST)
+}
+
 fn parse_workers(workers: Option<&str>) -> usize {
     workers
         .map(|workers| {
@@ -744,3 +1866,32 @@ fn parse_ping_interval(interval: Option<&str>) -> Option<Duration> {
 pub fn parse_metrics_file(metrics_file: Option<&str>) -> Option<String> {
     metrics_file.map(String::from)
 }
+
+fn parse_graph_snapshot_dir(dir: Option<&str>) -> Option<String> {
+    dir.map(String::from)
+}
+
+fn parse_epoch_file(epoch_file: Option<&str>) -> Option<String> {
+    epoch_file.map(String::from)
+}
+
+fn parse_canary(canary: Option<&str>) -> Option<String> {
+    canary.map(String::from)
+}
+
+fn parse_canary_mode(canary_mode: Option<&str>) -> bool {
+    canary_mode
+        .map(|canary_mode| {
+            canary_mode
+                .parse::<bool>()
+                .expect("canary_mode should be a boolean")
+        })
+        .unwrap_or(DEFAULT_CANARY_MODE)
+}
+
+fn parse_deterministic(seed: Option<&str>) -> Option<u64> {
+    seed.map(|seed| {
+        seed.parse::<u64>()
+            .expect("deterministic seed should be a number")
+    })
+}
@@ -0,0 +1,160 @@
+use clap::{App, Arg};
+use fantoch::executor::{ExecutionOrderMonitor, OrderDivergence};
+use fantoch::id::Rifl;
+use fantoch::kvs::Key;
+use fantoch::run::rw::Rw;
+use fantoch::HashSet;
+use tokio::fs::File;
+
+const BUFFER_SIZE: usize = 8 * 1024; // 8KB
+const DEFAULT_CONTEXT: usize = 5;
+
+// Audits the execution order logs written by processes with
+// `--execution_order_log`: all processes should have executed the commands on
+// each key in the same order (though some may have executed fewer, if the run
+// was stopped while they were behind). For each key on which they don't, the
+// first divergence is reported, along with the commands executed right before
+// it.
+#[tokio::main]
+async fn main() {
+    let (logs, context) = parse_args();
+
+    // load the execution order of each log
+    let mut monitors = Vec::with_capacity(logs.len());
+    for log in logs.iter() {
+        let (monitor, commands) = load(log).await;
+        println!(
+            "{}: {} commands executed on {} keys",
+            log,
+            commands,
+            monitor.len()
+        );
+        monitors.push(monitor);
+    }
+
+    // compare the order of each key in each log with the longest one
+    let keys: HashSet<_> =
+        monitors.iter().flat_map(|monitor| monitor.keys()).collect();
+    let mut keys: Vec<_> = keys.into_iter().collect();
+    keys.sort();
+    let mut divergences = Vec::new();
+    for key in keys.iter() {
+        let order_len = |monitor: &ExecutionOrderMonitor| {
+            monitor.get_order(key).map(Vec::len).unwrap_or_default()
+        };
+        let (reference, reference_monitor) = monitors
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, monitor)| order_len(monitor))
+            .expect("there should be at least one log");
+        let first_divergence = monitors
+            .iter()
+            .enumerate()
+            .filter_map(|(index, monitor)| {
+                reference_monitor
+                    .divergence(key, monitor)
+                    .map(|divergence| (index, divergence))
+            })
+            .min_by_key(|(_, divergence)| divergence.index);
+        if let Some((index, divergence)) = first_divergence {
+            divergences.push((reference, index, divergence));
+        }
+    }
+
+    println!(
+        "audited {} keys: {} with divergent execution orders",
+        keys.len(),
+        divergences.len()
+    );
+    for (reference, index, divergence) in divergences.iter() {
+        show(
+            &logs[*reference],
+            &monitors[*reference],
+            &logs[*index],
+            divergence,
+            context,
+        );
+    }
+    if !divergences.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+// Loads the execution order log, returning it along with the number of
+// commands in it.
+async fn load(log: &str) -> (ExecutionOrderMonitor, usize) {
+    let file = File::open(log)
+        .await
+        .unwrap_or_else(|e| panic!("couldn't open {}: {:?}", log, e));
+    let mut rw = Rw::from(BUFFER_SIZE, BUFFER_SIZE, file);
+    let mut monitor = ExecutionOrderMonitor::new();
+    let mut commands = 0;
+    while let Some((key, rifl)) = rw.recv::<(Key, Rifl)>().await {
+        monitor.add(&key, rifl);
+        commands += 1;
+    }
+    (monitor, commands)
+}
+
+fn show(
+    reference_log: &str,
+    reference: &ExecutionOrderMonitor,
+    log: &str,
+    divergence: &OrderDivergence,
+    context: usize,
+) {
+    let order = reference
+        .get_order(&divergence.key)
+        .expect("the divergent key should be in the reference log");
+    let start = divergence.index.saturating_sub(context);
+    println!(
+        "key {:?} diverges at position {}:",
+        divergence.key, divergence.index
+    );
+    for (position, rifl) in order[start..divergence.index].iter().enumerate() {
+        println!("  {:>8} {:?}", start + position, rifl);
+    }
+    println!(
+        "  {:>8} {:?} in {} | {:?} in {}",
+        divergence.index, divergence.left, reference_log, divergence.right, log
+    );
+}
+
+fn parse_args() -> (Vec<String>, usize) {
+    let matches = App::new("execution_order_audit")
+        .version("0.1")
+        .author("Vitor Enes <vitorenesduarte@gmail.com>")
+        .about("Checks that all processes executed conflicting commands in the same order.")
+        .arg(
+            Arg::with_name("logs")
+                .long("logs")
+                .value_name("LOGS")
+                .help("comma-separated list of the execution order logs of the processes to be compared (see `--execution_order_log`)")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("context")
+                .long("context")
+                .value_name("CONTEXT")
+                .help("number of commands shown before each divergence; default: 5")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let logs: Vec<_> = matches
+        .value_of("logs")
+        .expect("logs should be set")
+        .split(',')
+        .map(String::from)
+        .collect();
+    let context = matches
+        .value_of("context")
+        .map(|context| {
+            context
+                .parse::<usize>()
+                .expect("context should be a number")
+        })
+        .unwrap_or(DEFAULT_CONTEXT);
+    (logs, context)
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use clap::{App, Arg};
+use color_eyre::Report;
+use fantoch::id::ClientId;
+use fantoch::info;
+use fantoch::run::rw::TcpOptions;
+
+const DEFAULT_GATEWAY_ID: ClientId = 1;
+const DEFAULT_GATEWAY_PORT: u16 = 8080;
+
+type GatewayArgs = (
+    ClientId,
+    Vec<String>,
+    std::net::IpAddr,
+    u16,
+    Option<u16>,
+    TcpOptions,
+    usize,
+    usize,
+    Option<usize>,
+);
+
+fn main() -> Result<(), Report> {
+    let (args, _guard) = parse_args();
+    let (
+        id,
+        addresses,
+        ip,
+        port,
+        grpc_port,
+        tcp_options,
+        channel_buffer_size,
+        stack_size,
+        cpus,
+    ) = args;
+
+    common::tokio_runtime(stack_size, cpus, false).block_on(
+        fantoch::run::gateway(
+            id,
+            addresses,
+            ip,
+            port,
+            grpc_port,
+            tcp_options,
+            channel_buffer_size,
+        ),
+    )
+}
+
+fn parse_args() -> (GatewayArgs, tracing_appender::non_blocking::WorkerGuard) {
+    let matches = App::new("gateway")
+        .version("0.1")
+        .author("Vitor Enes <vitorenesduarte@gmail.com>")
+        .about("Runs an HTTP gateway that converts GET/PUT/DELETE requests on /KEY into commands submitted to some instance of a protocol.")
+        .arg(
+            Arg::with_name("id")
+                .long("id")
+                .value_name("ID")
+                .help("client identifier used by the gateway to submit commands; it should not be used by any other client; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("addresses")
+                .long("addresses")
+                .value_name("ADDRESSES")
+                .help("comma-separated list of addresses to connect to, one per shard (in the form IP:PORT e.g. 127.0.0.1:3000)")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ip")
+                .long("ip")
+                .value_name("IP")
+                .help("ip to bind the HTTP listener to; default: 127.0.0.1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("port")
+                .long("port")
+                .value_name("PORT")
+                .help("port to bind the HTTP listener to; default: 8080")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("grpc_port")
+                .long("grpc_port")
+                .value_name("GRPC_PORT")
+                .help("if set, the gateway also serves the gRPC client protocol (defined in fantoch/proto/fantoch.proto) on this port; requires the grpc feature; by default gRPC is not served")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tcp_nodelay")
+                .long("tcp_nodelay")
+                .value_name("TCP_NODELAY")
+                .help("set TCP_NODELAY; default: true")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("channel_buffer_size")
+                .long("channel_buffer_size")
+                .value_name("CHANNEL_BUFFER_SIZE")
+                .help("set the size of the buffer in each channel used for task communication; default: 10000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("stack_size")
+                .long("stack_size")
+                .value_name("STACK_SIZE")
+                .help("stack size (in bytes) of each tokio thread; default: 2 * 1024 * 1024 (bytes)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("cpus")
+                .long("cpus")
+                .value_name("CPUS")
+                .help("number of cpus to be used by tokio; by default all available cpus are used")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("log_file")
+                .long("log_file")
+                .value_name("LOG_FILE")
+                .help("file to which logs will be written to; if not set, logs will be redirect to the stdout")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("log_json")
+                .long("log_json")
+                .value_name("LOG_JSON")
+                .help("boolean indicating whether logs should be written as JSON lines (one JSON object per event, with its fields at the top level); default: false")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let tracing_directives = None;
+    let log_json = common::parse_log_json(matches.value_of("log_json"));
+    let guard = fantoch::util::init_tracing_subscriber(
+        matches.value_of("log_file"),
+        tracing_directives,
+        log_json,
+    );
+
+    // parse arguments
+    let id = parse_id(matches.value_of("id"));
+    let addresses = parse_addresses(matches.value_of("addresses"));
+    let ip = common::protocol::parse_ip(matches.value_of("ip"));
+    let port = parse_port(matches.value_of("port"));
+    let grpc_port = parse_grpc_port(matches.value_of("grpc_port"));
+    let tcp_options = TcpOptions::new(common::parse_tcp_nodelay(
+        matches.value_of("tcp_nodelay"),
+    ));
+    let channel_buffer_size = common::parse_channel_buffer_size(
+        matches.value_of("channel_buffer_size"),
+    );
+    let stack_size = common::parse_stack_size(matches.value_of("stack_size"));
+    let cpus = common::parse_cpus(matches.value_of("cpus"));
+
+    info!("id: {}", id);
+    info!("addresses: {:?}", addresses);
+    info!("ip: {:?}", ip);
+    info!("port: {}", port);
+    info!("grpc port: {:?}", grpc_port);
+    info!("tcp options: {:?}", tcp_options);
+    info!("channel buffer size: {:?}", channel_buffer_size);
+    info!("stack size: {:?}", stack_size);
+
+    let args = (
+        id,
+        addresses,
+        ip,
+        port,
+        grpc_port,
+        tcp_options,
+        channel_buffer_size,
+        stack_size,
+        cpus,
+    );
+    (args, guard)
+}
+
+fn parse_id(id: Option<&str>) -> ClientId {
+    id.map(|id| id.parse::<ClientId>().expect("id should be a number"))
+        .unwrap_or(DEFAULT_GATEWAY_ID)
+}
+
+fn parse_addresses(addresses: Option<&str>) -> Vec<String> {
+    addresses
+        .expect("addresses should be set")
+        .split(common::protocol::LIST_SEP)
+        .map(|address| address.to_string())
+        .collect()
+}
+
+fn parse_port(port: Option<&str>) -> u16 {
+    port.map(|port| port.parse::<u16>().expect("port should be a number"))
+        .unwrap_or(DEFAULT_GATEWAY_PORT)
+}
+
+fn parse_grpc_port(port: Option<&str>) -> Option<u16> {
+    port.map(|port| port.parse::<u16>().expect("grpc port should be a number"))
+}
@@ -0,0 +1,566 @@
+mod common;
+
+use clap::{App, Arg, ArgMatches};
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+use fantoch::client::{ClientData, KeyGen, Workload, WorkloadMix};
+use fantoch::config::Config;
+use fantoch::id::{ClientId, ProcessId};
+use fantoch::metrics::Histogram;
+use fantoch::protocol::{Protocol, ProtocolMetricsKind};
+use fantoch::run::task::server::metrics_logger::{
+    ProcessMetrics, METRICS_INTERVAL,
+};
+use fantoch::run::task::util;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+const DEFAULT_N: usize = 3;
+const DEFAULT_F: usize = 1;
+const DEFAULT_CLIENTS: usize = 100;
+const DEFAULT_COMMANDS_PER_CLIENT: usize = 1000;
+const DEFAULT_CONFLICT_RATE: usize = 10;
+const DEFAULT_PAYLOAD_SIZE: usize = 100;
+const DEFAULT_WORKERS: usize = 1;
+const DEFAULT_EXECUTORS: usize = 1;
+const DEFAULT_PORT: u16 = 3000;
+const DEFAULT_CLIENT_PORT: u16 = 4000;
+const DEFAULT_DIR: &str = "fantoch_local_cluster";
+
+// the local cluster always has process 1 as the leader of leader-based
+// protocols, and runs the periodic tasks needed by the protocols that have
+// them
+const LEADER: ProcessId = 1;
+const GC_INTERVAL: Duration = Duration::from_millis(50);
+const TEMPO_DETACHED_SEND_INTERVAL: Duration = Duration::from_millis(5);
+
+const MULTIPLEXING: usize = 1;
+const BATCH_MAX_SIZE: usize = 1;
+const BATCH_MAX_DELAY: Duration = Duration::from_millis(5);
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+const LATENCY_PERCENTILES: [f64; 3] = [0.5, 0.99, 0.999];
+
+type LocalClusterArgs = (
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    usize,
+    u16,
+    u16,
+    String,
+);
+
+// Generates, from the protocols in `fantoch::registered_protocols!`,
+// `PROTOCOLS`, the names of all the protocols that can be run, and
+// `run_protocol`, which runs a local cluster of the protocol with a given
+// name.
+macro_rules! local_cluster {
+    ($(
+        register_protocol!($variant:ident {
+            name: $name:literal,
+            protocol: $protocol:path,
+            plot_name: $plot_name:literal,
+            plot_color: $plot_color:literal,
+            capabilities: $capabilities:tt $(,)?
+        });
+    )*) => {
+        const PROTOCOLS: &[&str] = &[$($name),*];
+
+        fn run_protocol(
+            name: &str,
+            args: LocalClusterArgs,
+        ) -> Result<(), Report> {
+            match name {
+                $($name => run::<$protocol>(args),)*
+                _ => Err(eyre!("unknown protocol: {}", name)),
+            }
+        }
+    };
+}
+
+fantoch::registered_protocols!(local_cluster);
+
+fn main() -> Result<(), Report> {
+    let matches = app().get_matches();
+    let protocol = matches
+        .value_of("protocol")
+        .expect("protocol should be set");
+    let args = parse_args(&matches);
+
+    // protocol logs go to a file so that they don't hide the summary
+    let dir = &args.10;
+    std::fs::create_dir_all(dir)?;
+    let log_file = format!("{}/local_cluster.log", dir);
+    let tracing_directives = None;
+    let log_json = false;
+    let _guard = fantoch::util::init_tracing_subscriber(
+        Some(&log_file),
+        tracing_directives,
+        log_json,
+    );
+    println!("running {} locally", protocol);
+    println!("protocol logs will be written to {}", log_file);
+
+    run_protocol(protocol, args)
+}
+
+fn run<P>(args: LocalClusterArgs) -> Result<(), Report>
+where
+    P: Protocol + Send + 'static,
+{
+    let (
+        n,
+        f,
+        clients,
+        commands_per_client,
+        conflict_rate,
+        payload_size,
+        workers,
+        executors,
+        port,
+        client_port,
+        dir,
+    ) = args;
+
+    let mut config = Config::new(n, f);
+    if !P::leaderless() {
+        config.set_leader(LEADER);
+    }
+    config.set_gc_interval(GC_INTERVAL);
+    config.set_tempo_detached_send_interval(TEMPO_DETACHED_SEND_INTERVAL);
+
+    let shard_count = 1;
+    let key_gen = KeyGen::ConflictPool {
+        conflict_rate,
+        pool_size: 1,
+        pool_id: 0,
+    };
+    let keys_per_command = 1;
+    let workload = Workload::new(
+        shard_count,
+        key_gen,
+        keys_per_command,
+        commands_per_client,
+        payload_size,
+    );
+
+    let stack_size = common::parse_stack_size(None);
+    let cpus = None;
+    let single_threaded = false;
+    common::tokio_runtime(stack_size, cpus, single_threaded).block_on(
+        local_cluster::<P>(
+            config,
+            workload,
+            clients,
+            workers,
+            executors,
+            port,
+            client_port,
+            dir,
+        ),
+    )
+}
+
+async fn local_cluster<P>(
+    config: Config,
+    workload: Workload,
+    clients: usize,
+    workers: usize,
+    executors: usize,
+    port: u16,
+    client_port: u16,
+    dir: String,
+) -> Result<(), Report>
+where
+    P: Protocol + Send + 'static,
+{
+    let localhost = "127.0.0.1"
+        .parse::<IpAddr>()
+        .expect("127.0.0.1 should be a valid ip");
+    let tcp_options =
+        common::parse_tcp_options(None, None, None, None, None, None);
+    let tcp_buffer_size = common::parse_tcp_buffer_size(None);
+    let tcp_flush_interval = common::parse_tcp_flush_interval(None);
+    let channel_buffer_size = common::parse_channel_buffer_size(None);
+
+    let process_ids: Vec<_> = (1..=config.n() as ProcessId).collect();
+    let process_port = |process_id: ProcessId| port + process_id as u16 - 1;
+    let process_client_port =
+        |process_id: ProcessId| client_port + process_id as u16 - 1;
+
+    println!(
+        "starting {} processes with f = {} (ports {}-{})",
+        config.n(),
+        config.f(),
+        process_port(1),
+        process_port(config.n() as ProcessId),
+    );
+    let mut process_handles = Vec::with_capacity(process_ids.len());
+    for &process_id in process_ids.iter() {
+        // each process connects to all the others; processes are sorted by
+        // id, except self, which comes first
+        let sorted_processes = std::iter::once(process_id)
+            .chain(process_ids.iter().copied().filter(|id| *id != process_id))
+            .map(|id| (id, 0))
+            .collect();
+        let addresses = process_ids
+            .iter()
+            .filter(|id| **id != process_id)
+            .map(|id| (format!("localhost:{}", process_port(*id)), None))
+            .collect();
+        let execution_log = None;
+        let execution_order_log = None;
+        let ping_interval = None;
+        let graph_snapshot_dir = None;
+        let polling_seed = None;
+        let epoch_file = None;
+        let canary = None;
+        let control_port = None;
+        let handle = tokio::spawn(fantoch::run::process::<P, String>(
+            process_id,
+            0,
+            Some(sorted_processes),
+            localhost,
+            process_port(process_id),
+            process_client_port(process_id),
+            addresses,
+            config,
+            tcp_options,
+            tcp_buffer_size,
+            tcp_flush_interval,
+            channel_buffer_size,
+            channel_buffer_size,
+            workers,
+            executors,
+            MULTIPLEXING,
+            execution_log,
+            execution_order_log,
+            ping_interval,
+            Some(metrics_file(&dir, process_id)),
+            graph_snapshot_dir,
+            polling_seed,
+            epoch_file,
+            canary,
+            control_port,
+        ));
+        process_handles.push(handle);
+    }
+
+    // spread the clients evenly across processes
+    println!(
+        "starting {} closed-loop clients, each issuing {} commands",
+        clients,
+        workload.commands_per_client()
+    );
+    let client_ids: Vec<ClientId> = (1..=clients as ClientId).collect();
+    let client_handles: Vec<_> = process_ids
+        .iter()
+        .map(|&process_id| {
+            let ids = client_ids
+                .iter()
+                .copied()
+                .filter(|id| {
+                    (*id as usize - 1) % config.n() == process_id as usize - 1
+                })
+                .collect();
+            let address =
+                format!("localhost:{}", process_client_port(process_id));
+            let addresses = vec![(address, None)];
+            let detect_closest = false;
+            let interval = None;
+            let status_frequency = None;
+            let max_run_time = None;
+            let command_timeout = None;
+            let traces_file = None;
+            tokio::spawn(fantoch::run::client(
+                ids,
+                addresses,
+                detect_closest,
+                interval,
+                WorkloadMix::single(workload),
+                BATCH_MAX_SIZE,
+                BATCH_MAX_DELAY,
+                tcp_options,
+                channel_buffer_size,
+                status_frequency,
+                None,
+                max_run_time,
+                SHUTDOWN_TIMEOUT,
+                command_timeout,
+                Some(client_metrics_file(&dir, process_id)),
+                traces_file,
+            ))
+        })
+        .collect();
+
+    // print a summary of the metrics reported by processes while clients run
+    let start = Instant::now();
+    let summary_process_ids = process_ids.clone();
+    let summary_dir = dir.clone();
+    let summary = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(METRICS_INTERVAL);
+        // the first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            print_process_summary(
+                start.elapsed(),
+                &summary_process_ids,
+                &summary_dir,
+            );
+        }
+    });
+
+    // processes only end if something goes wrong; in that case, stop
+    // waiting for clients (as they may never end)
+    let clients = futures::future::join_all(client_handles);
+    let processes = futures::future::select_all(process_handles);
+    tokio::select! {
+        results = clients => {
+            for result in results {
+                result??;
+            }
+        }
+        (result, index, _) = processes => {
+            return Err(eyre!(
+                "process {} ended unexpectedly: {:?}",
+                process_ids[index],
+                result
+            ));
+        }
+    }
+    summary.abort();
+    println!("all clients ended after {:?}", start.elapsed());
+
+    // merge the data from all clients
+    let mut data = ClientData::new();
+    for process_id in process_ids {
+        let file = client_metrics_file(&dir, process_id);
+        let client_data: ClientData = util::decompress_and_deserialize(&file)?;
+        data.merge(&client_data);
+    }
+    print_client_summary(&data);
+    Ok(())
+}
+
+fn print_process_summary(
+    elapsed: Duration,
+    process_ids: &[ProcessId],
+    dir: &str,
+) {
+    println!("[{:>4}s]", elapsed.as_secs());
+    for &process_id in process_ids {
+        // metrics files may be missing (if not yet written) or incomplete (if
+        // being written); in that case, that process is skipped
+        let metrics: ProcessMetrics = match util::decompress_and_deserialize(
+            &metrics_file(dir, process_id),
+        ) {
+            Ok(metrics) => metrics,
+            Err(_) => continue,
+        };
+        let protocol_metrics = metrics.protocol_metrics();
+        let metric =
+            |kind| protocol_metrics.get_aggregated(kind).copied().unwrap_or(0);
+        let fast_paths = metric(ProtocolMetricsKind::FastPath);
+        let slow_paths = metric(ProtocolMetricsKind::SlowPath);
+        let stable = metric(ProtocolMetricsKind::Stable);
+        println!(
+            "  p{}: fast paths = {} | slow paths = {} | stable = {}",
+            process_id, fast_paths, slow_paths, stable
+        );
+    }
+}
+
+fn metrics_file(dir: &str, process_id: ProcessId) -> String {
+    format!("{}/process_{}.metrics", dir, process_id)
+}
+
+fn client_metrics_file(dir: &str, process_id: ProcessId) -> String {
+    format!("{}/client_{}.metrics", dir, process_id)
+}
+
+fn print_client_summary(data: &ClientData) {
+    let latency = Histogram::from(
+        data.latency_data()
+            .map(|latency| latency.as_micros() as u64),
+    );
+    let to_millis = |micros: f64| micros / 1000f64;
+    println!("commands completed: {}", latency.count());
+    println!("throughput: {:.1} ops/s", data.throughput());
+    println!("latency avg: {:.2}ms", to_millis(latency.mean().value()));
+    for percentile in LATENCY_PERCENTILES.iter() {
+        println!(
+            "latency p{}: {:.2}ms",
+            percentile * 100f64,
+            to_millis(latency.percentile(*percentile).value())
+        );
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("local_cluster")
+        .version("0.1")
+        .author("Vitor Enes <vitorenesduarte@gmail.com>")
+        .about("Runs a cluster of some protocol, along with its clients, on this machine.")
+        .arg(
+            Arg::with_name("protocol")
+                .long("protocol")
+                .value_name("PROTOCOL")
+                .help("protocol to be run")
+                .possible_values(PROTOCOLS)
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("n")
+                .long("n")
+                .value_name("PROCESS_NUMBER")
+                .help("number of processes; default: 3")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("f")
+                .long("f")
+                .value_name("FAULT_NUMBER")
+                .help("number of allowed faults; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("clients")
+                .long("clients")
+                .value_name("CLIENTS")
+                .help("number of closed-loop clients, spread evenly across processes; default: 100")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("commands_per_client")
+                .long("commands_per_client")
+                .value_name("COMMANDS_PER_CLIENT")
+                .help("number of commands to be issued by each client; default: 1000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("conflict_rate")
+                .long("conflict_rate")
+                .value_name("CONFLICT_RATE")
+                .help("percentage of commands that access the same key; default: 10")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("payload_size")
+                .long("payload_size")
+                .value_name("PAYLOAD_SIZE")
+                .help("size of the payload (in bytes) of each command; default: 100")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("workers")
+                .long("workers")
+                .value_name("WORKERS")
+                .help("number of protocol workers in each process; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("executors")
+                .long("executors")
+                .value_name("EXECUTORS")
+                .help("number of executors in each process; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("port")
+                .long("port")
+                .value_name("PORT")
+                .help("port used by process 1 to listen for other processes; process i uses this port + i - 1; default: 3000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("client_port")
+                .long("client_port")
+                .value_name("CLIENT_PORT")
+                .help("port used by process 1 to listen for clients; process i uses this port + i - 1; default: 4000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("dir")
+                .long("dir")
+                .value_name("DIR")
+                .help("directory where logs and metrics are written; default: 'fantoch_local_cluster' in the temporary directory")
+                .takes_value(true),
+        )
+}
+
+fn parse_args(matches: &ArgMatches<'_>) -> LocalClusterArgs {
+    let n = parse_number(matches.value_of("n"), "n", DEFAULT_N);
+    let f = parse_number(matches.value_of("f"), "f", DEFAULT_F);
+    let clients =
+        parse_number(matches.value_of("clients"), "clients", DEFAULT_CLIENTS);
+    let commands_per_client = parse_number(
+        matches.value_of("commands_per_client"),
+        "commands_per_client",
+        DEFAULT_COMMANDS_PER_CLIENT,
+    );
+    let conflict_rate = parse_number(
+        matches.value_of("conflict_rate"),
+        "conflict_rate",
+        DEFAULT_CONFLICT_RATE,
+    );
+    let payload_size = parse_number(
+        matches.value_of("payload_size"),
+        "payload_size",
+        DEFAULT_PAYLOAD_SIZE,
+    );
+    let workers =
+        parse_number(matches.value_of("workers"), "workers", DEFAULT_WORKERS);
+    let executors = parse_number(
+        matches.value_of("executors"),
+        "executors",
+        DEFAULT_EXECUTORS,
+    );
+    let port = parse_number(matches.value_of("port"), "port", DEFAULT_PORT);
+    let client_port = parse_number(
+        matches.value_of("client_port"),
+        "client_port",
+        DEFAULT_CLIENT_PORT,
+    );
+    let dir = parse_dir(matches.value_of("dir"));
+    (
+        n,
+        f,
+        clients,
+        commands_per_client,
+        conflict_rate,
+        payload_size,
+        workers,
+        executors,
+        port,
+        client_port,
+        dir,
+    )
+}
+
+fn parse_number<T>(number: Option<&str>, name: &str, default: T) -> T
+where
+    T: std::str::FromStr,
+{
+    number
+        .map(|number| {
+            number
+                .parse::<T>()
+                .unwrap_or_else(|_| panic!("{} should be a number", name))
+        })
+        .unwrap_or(default)
+}
+
+fn parse_dir(dir: Option<&str>) -> String {
+    dir.map(String::from).unwrap_or_else(|| {
+        std::env::temp_dir()
+            .join(DEFAULT_DIR)
+            .to_string_lossy()
+            .into_owned()
+    })
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use clap::Arg;
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+
+// Generates, from the protocols in `fantoch::registered_protocols!`,
+// `PROTOCOLS`, the names of all the protocols that can be run, and
+// `run_protocol`, which runs the protocol with a given name.
+macro_rules! server {
+    ($(
+        register_protocol!($variant:ident {
+            name: $name:literal,
+            protocol: $protocol:path,
+            plot_name: $plot_name:literal,
+            plot_color: $plot_color:literal,
+            capabilities: $capabilities:tt $(,)?
+        });
+    )*) => {
+        const PROTOCOLS: &[&str] = &[$($name),*];
+
+        fn run_protocol(
+            name: &str,
+            matches: &clap::ArgMatches<'_>,
+        ) -> Result<(), Report> {
+            match name {
+                $($name => common::protocol::run_with::<$protocol>(matches),)*
+                _ => Err(eyre!("unknown protocol: {}", name)),
+            }
+        }
+    };
+}
+
+fantoch::registered_protocols!(server);
+
+fn main() -> Result<(), Report> {
+    let matches = common::protocol::app()
+        .arg(
+            Arg::with_name("protocol")
+                .long("protocol")
+                .value_name("PROTOCOL")
+                .help("protocol to be run")
+                .possible_values(PROTOCOLS)
+                .required(true)
+                .takes_value(true),
+        )
+        .get_matches();
+    let protocol = matches
+        .value_of("protocol")
+        .expect("protocol should be set");
+    run_protocol(protocol, &matches)
+}
@@ -54,9 +54,11 @@ fn main() {
                     payload_size,
                 );
                 let mut rifl_gen = RiflGen::new(client_id);
-                let mut key_gen_state = workload
-                    .key_gen()
-                    .initial_state(workload.shard_count(), client_id);
+                let mut key_gen_state = workload.key_gen().initial_state(
+                    workload.shard_count(),
+                    client_id,
+                    workload.seed(),
+                );
                 while let Some((_target_shard, cmd)) =
                     workload.next_cmd(&mut rifl_gen, &mut key_gen_state)
                 {
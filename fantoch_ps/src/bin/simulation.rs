@@ -1,11 +1,13 @@
 use fantoch::client::{KeyGen, Workload};
 use fantoch::config::Config;
 use fantoch::executor::{ExecutorMetrics, ExecutorMetricsKind};
-use fantoch::id::ProcessId;
+use fantoch::id::{ProcessId, Rifl};
 use fantoch::metrics::Histogram;
-use fantoch::planet::{Planet, Region};
+use fantoch::planet::{LatencyPercentile, Planet, Region};
 use fantoch::protocol::{Protocol, ProtocolMetrics, ProtocolMetricsKind};
-use fantoch::sim::Runner;
+use fantoch::sim::{
+    open_loop_submissions, LatencyDifferences, Runner, Submission,
+};
 use fantoch::HashMap;
 use fantoch_ps::protocol::{
     AtlasSequential, CaesarLocked, EPaxosSequential, FPaxos, TempoSequential,
@@ -15,6 +17,8 @@ use std::time::Duration;
 
 // latency dir
 const LATENCY_AWS: &str = "../latency_aws/2020_06_05";
+// RTT statistic used as the latency between regions
+const LATENCY_PERCENTILE: LatencyPercentile = LatencyPercentile::Avg;
 
 const STACK_SIZE: usize = 64 * 1024 * 1024; // 64mb
 
@@ -59,10 +63,11 @@ fn main() {
     let aws = true;
     tempo(aws);
     // fairest_leader();
+    // compare_protocols(aws);
 }
 
 fn aws_planet() -> (Planet, Vec<Region>) {
-    let planet = Planet::from(LATENCY_AWS);
+    let planet = Planet::from_percentile(LATENCY_AWS, LATENCY_PERCENTILE);
     let regions = vec![
         Region::new("eu-west-1"),
         Region::new("us-west-1"),
@@ -229,6 +234,7 @@ fn tempo(aws: bool) {
                         let key_gen = KeyGen::ConflictPool {
                             conflict_rate,
                             pool_size,
+                            pool_id: 0,
                         };
                         let keys_per_command = 1;
                         let commands_per_client = 200;
@@ -317,6 +323,7 @@ fn fairest_leader() {
     let key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
     let keys_per_command = 1;
     let commands_per_client = 500;
@@ -375,6 +382,104 @@ fn fairest_leader() {
     }
 }
 
+#[allow(dead_code)]
+// Compares Atlas, EPaxos and Tempo command by command, by feeding all of them
+// the exact same submissions.
+fn compare_protocols(aws: bool) {
+    let (planet, regions) = if aws { aws_planet() } else { gcp_planet() };
+    let n = 5;
+    let f = 1;
+    let config = config!(n, f, false, None, false, false);
+
+    // clients workload
+    let shard_count = 1;
+    let key_gen = KeyGen::ConflictPool {
+        conflict_rate: 2,
+        pool_size: 1,
+        pool_id: 0,
+    };
+    let keys_per_command = 1;
+    let commands_per_client = 1000;
+    let payload_size = 0;
+    let mut workload = Workload::new(
+        shard_count,
+        key_gen,
+        keys_per_command,
+        commands_per_client,
+        payload_size,
+    );
+    workload.set_seed(Some(0));
+
+    // each client submits a command every 50ms
+    let clients_per_region = 32;
+    let interval = Duration::from_millis(50);
+    let submissions = open_loop_submissions(
+        workload,
+        clients_per_region * regions.len(),
+        interval,
+    );
+
+    let replay = |protocol_name| {
+        let latencies = match protocol_name {
+            "Atlas" => replay::<AtlasSequential>(
+                planet.clone(),
+                config,
+                workload,
+                clients_per_region,
+                regions.clone(),
+                submissions.clone(),
+            ),
+            "EPaxos" => replay::<EPaxosSequential>(
+                planet.clone(),
+                config,
+                workload,
+                clients_per_region,
+                regions.clone(),
+                submissions.clone(),
+            ),
+            "Tempo" => replay::<TempoSequential>(
+                planet.clone(),
+                config,
+                workload,
+                clients_per_region,
+                regions.clone(),
+                submissions.clone(),
+            ),
+            _ => panic!("unsupported protocol {:?}", protocol_name),
+        };
+        (protocol_name, latencies)
+    };
+    let (baseline_name, baseline) = replay("Atlas");
+    for (protocol_name, latencies) in vec![replay("EPaxos"), replay("Tempo")] {
+        println!(
+            "{} vs {}: {:?}",
+            protocol_name,
+            baseline_name,
+            LatencyDifferences::new(&baseline, &latencies)
+        );
+    }
+}
+
+#[allow(dead_code)]
+fn replay<P: Protocol>(
+    planet: Planet,
+    config: Config,
+    workload: Workload,
+    clients_per_region: usize,
+    regions: Vec<Region>,
+    submissions: Vec<Submission>,
+) -> HashMap<Rifl, Duration> {
+    let mut runner: Runner<P> = Runner::new(
+        planet,
+        config,
+        workload,
+        clients_per_region,
+        regions.clone(),
+        regions,
+    );
+    runner.replay(submissions, None)
+}
+
 #[allow(dead_code)]
 fn equidistant<P: Protocol>(protocol_name: &str) {
     // intra-region distance
@@ -391,6 +496,7 @@ fn equidistant<P: Protocol>(protocol_name: &str) {
     let key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
     let keys_per_command = 1;
     let total_commands = 500;
@@ -463,6 +569,7 @@ fn increasing_regions<P: Protocol>(protocol_name: &str) {
     let key_gen = KeyGen::ConflictPool {
         conflict_rate: 2,
         pool_size: 1,
+        pool_id: 0,
     };
     let keys_per_command = 1;
     let total_commands = 500;
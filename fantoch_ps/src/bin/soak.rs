@@ -0,0 +1,526 @@
+use clap::{App, Arg, ArgMatches};
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+use fantoch::client::{KeyGen, Workload};
+use fantoch::config::Config;
+use fantoch::executor::ExecutionOrderMonitor;
+use fantoch::id::ProcessId;
+use fantoch::planet::Planet;
+use fantoch::protocol::Protocol;
+use fantoch::sim::Runner;
+use fantoch::{HashMap, HashSet};
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+const DEFAULT_N: usize = 3;
+const DEFAULT_F: usize = 1;
+const DEFAULT_DURATION: Duration = Duration::from_secs(8 * 3600);
+const DEFAULT_CLIENTS_PER_PROCESS: usize = 10;
+const DEFAULT_COMMANDS_PER_CLIENT: usize = 100;
+const DEFAULT_MAX_MEMORY_GROWTH: usize = 50;
+const PAYLOAD_SIZE: usize = 100;
+
+// the soak always has process 1 as the leader of leader-based protocols, and
+// runs the periodic tasks needed by the protocols that have them
+const LEADER: ProcessId = 1;
+const GC_INTERVAL: Duration = Duration::from_millis(50);
+const TEMPO_DETACHED_SEND_INTERVAL: Duration = Duration::from_millis(5);
+const EXECUTED_NOTIFICATION_INTERVAL: Duration = Duration::from_millis(50);
+
+// how much longer each simulation runs after its clients end, so that
+// committed commands are garbage collected
+const EXTRA_SIM_TIME: Duration = Duration::from_secs(10);
+
+// The workloads the rounds cycle through: each is a key generator along with
+// the number of keys accessed by each command.
+fn workloads() -> Vec<(KeyGen, usize)> {
+    let conflict_pool = |conflict_rate| KeyGen::ConflictPool {
+        conflict_rate,
+        pool_size: 1,
+        pool_id: 0,
+    };
+    let zipf = |coefficient| KeyGen::Zipf {
+        coefficient,
+        total_keys_per_shard: 1000,
+    };
+    vec![
+        (conflict_pool(0), 1),
+        (conflict_pool(10), 1),
+        (conflict_pool(50), 2),
+        (conflict_pool(100), 1),
+        (zipf(0.7), 1),
+        (zipf(1.0), 2),
+    ]
+}
+
+type SoakArgs = (
+    Planet,
+    usize,
+    usize,
+    Duration,
+    usize,
+    usize,
+    usize,
+    Option<String>,
+);
+
+// Outcome of running a single protocol with a single workload.
+struct Round {
+    commands: usize,
+    expected_commands: usize,
+    same_order: bool,
+    // number of executions (of a command on a key) performed by some process
+    // but not yet by others
+    missing_executions: usize,
+    // number of entries left in the state of all processes, once the clients
+    // end and garbage collection runs
+    state_left: usize,
+}
+
+// Generates, from the protocols in `fantoch::registered_protocols!`,
+// `PROTOCOLS`, the names of all the protocols that can be run, and
+// `run_round`, which runs a round of the protocol with a given name.
+macro_rules! soak {
+    ($(
+        register_protocol!($variant:ident {
+            name: $name:literal,
+            protocol: $protocol:path,
+            plot_name: $plot_name:literal,
+            plot_color: $plot_color:literal,
+            capabilities: $capabilities:tt $(,)?
+        });
+    )*) => {
+        const PROTOCOLS: &[&str] = &[$($name),*];
+
+        fn run_round(
+            name: &str,
+            planet: Planet,
+            n: usize,
+            f: usize,
+            workload: Workload,
+            clients_per_process: usize,
+        ) -> Round {
+            match name {
+                $($name => round::<$protocol>(
+                    planet,
+                    n,
+                    f,
+                    workload,
+                    clients_per_process,
+                ),)*
+                _ => panic!("unknown protocol: {}", name),
+            }
+        }
+    };
+}
+
+fantoch::registered_protocols!(soak);
+
+fn main() -> Result<(), Report> {
+    let matches = app().get_matches();
+    let protocols = parse_protocols(matches.value_of("protocols"))?;
+    let args = parse_args(&matches);
+    soak(protocols, args)
+}
+
+fn soak(protocols: Vec<&str>, args: SoakArgs) -> Result<(), Report> {
+    let (
+        planet,
+        n,
+        f,
+        duration,
+        clients_per_process,
+        commands_per_client,
+        max_memory_growth,
+        report_file,
+    ) = args;
+
+    let workloads = workloads();
+    let mut report = String::new();
+    let mut failures = Vec::new();
+    // memory used once all protocols ran with all workloads (so that e.g. the
+    // allocator has warmed up); growth is measured against it
+    let mut baseline_rss = None;
+    let mut max_growth = 0f64;
+
+    let start = Instant::now();
+    let mut cycle = 0;
+    while start.elapsed() < duration && failures.is_empty() {
+        cycle += 1;
+        let (key_gen, keys_per_command) =
+            workloads[(cycle - 1) % workloads.len()];
+        let shard_count = 1;
+        let workload = Workload::new(
+            shard_count,
+            key_gen,
+            keys_per_command,
+            commands_per_client,
+            PAYLOAD_SIZE,
+        );
+        log(
+            &mut report,
+            format!(
+                "[{:>6}s] cycle {}: {:?} with {} key(s) per command",
+                start.elapsed().as_secs(),
+                cycle,
+                key_gen,
+                keys_per_command
+            ),
+        );
+
+        for protocol in protocols.iter() {
+            // violations of invariants make the simulation panic; catch them
+            // so that they're part of the report
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                run_round(
+                    protocol,
+                    planet.clone(),
+                    n,
+                    f,
+                    workload,
+                    clients_per_process,
+                )
+            }));
+            let round = match outcome {
+                Ok(round) => round,
+                Err(e) => {
+                    let failure = format!(
+                        "{} panicked in cycle {}: {}",
+                        protocol,
+                        cycle,
+                        panic_message(e)
+                    );
+                    failures.push(failure);
+                    continue;
+                }
+            };
+            log(
+                &mut report,
+                format!(
+                    "  {:<14} commands = {} | same order = {} | missing executions = {} | state left = {}",
+                    protocol,
+                    round.commands,
+                    round.same_order,
+                    round.missing_executions,
+                    round.state_left
+                ),
+            );
+            if round.commands != round.expected_commands {
+                failures.push(format!(
+                    "{} completed {} commands in cycle {} (expected {})",
+                    protocol, round.commands, cycle, round.expected_commands
+                ));
+            }
+            if !round.same_order {
+                failures.push(format!(
+                    "{} executed conflicting commands in different orders in cycle {}",
+                    protocol, cycle
+                ));
+            }
+        }
+
+        // check memory growth
+        let warmed_up = cycle >= workloads.len();
+        if let Some(rss) = rss_kb().filter(|_| warmed_up) {
+            let baseline = *baseline_rss.get_or_insert(rss);
+            let growth =
+                (rss as f64 - baseline as f64) * 100f64 / baseline as f64;
+            max_growth = max_growth.max(growth);
+            log(
+                &mut report,
+                format!("  rss = {}kB (growth = {:.1}%)", rss, growth),
+            );
+            if growth > max_memory_growth as f64 {
+                failures.push(format!(
+                    "memory grew {:.1}% (from {}kB to {}kB) by cycle {}",
+                    growth, baseline, rss, cycle
+                ));
+            }
+        }
+    }
+
+    log(
+        &mut report,
+        format!(
+            "soak ended after {} cycles in {:?} (max memory growth = {:.1}%)",
+            cycle,
+            start.elapsed(),
+            max_growth
+        ),
+    );
+    if failures.is_empty() {
+        log(&mut report, String::from("no failures"));
+    }
+    for failure in failures.iter() {
+        log(&mut report, format!("FAILURE: {}", failure));
+    }
+
+    if let Some(report_file) = report_file {
+        std::fs::write(&report_file, &report)?;
+        println!("report written to {}", report_file);
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(eyre!("soak found {} failure(s)", failures.len()))
+    }
+}
+
+fn round<P: Protocol>(
+    planet: Planet,
+    n: usize,
+    f: usize,
+    workload: Workload,
+    clients_per_process: usize,
+) -> Round {
+    let mut config = Config::new(n, f);
+    if !P::leaderless() {
+        config.set_leader(LEADER);
+    }
+    config.set_gc_interval(GC_INTERVAL);
+    config.set_tempo_detached_send_interval(TEMPO_DETACHED_SEND_INTERVAL);
+    config.set_executor_executed_notification_interval(
+        EXECUTED_NOTIFICATION_INTERVAL,
+    );
+    config.set_executor_monitor_execution_order(true);
+
+    // processes and clients are placed in the first `n` regions
+    let mut regions = planet.regions();
+    regions.truncate(n);
+    let mut runner: Runner<P> = Runner::new(
+        planet,
+        config,
+        workload,
+        clients_per_process,
+        regions.clone(),
+        regions,
+    );
+    runner.reorder_messages();
+    let (_metrics, monitors, latencies) = runner.run(Some(EXTRA_SIM_TIME));
+
+    let commands = latencies
+        .values()
+        .map(|(_issued, histogram)| histogram.count() as usize)
+        .sum();
+    let expected_commands =
+        n * clients_per_process * workload.commands_per_client();
+    let (same_order, missing_executions) = compare_orders(monitors);
+    let state_left = (1..=n as ProcessId)
+        .map(|process_id| {
+            let (process, _executor) = runner.process(process_id);
+            process.state_sizes().values().sum::<usize>()
+        })
+        .sum();
+    Round {
+        commands,
+        expected_commands,
+        same_order,
+        missing_executions,
+        state_left,
+    }
+}
+
+// Checks whether all processes executed conflicting commands in the same
+// order, i.e. whether the order of each key in each process is a prefix of the
+// longest one (as processes may not have executed the same commands when the
+// simulation ends). Also returns the number of executions missing in some
+// process. Executors that don't monitor execution orders (e.g. the ones of
+// protocols that don't order commands) are skipped.
+fn compare_orders(
+    monitors: HashMap<ProcessId, Option<ExecutionOrderMonitor>>,
+) -> (bool, usize) {
+    let monitors: Vec<_> = monitors
+        .into_iter()
+        .filter_map(|(_, monitor)| monitor)
+        .collect();
+    let keys: HashSet<_> =
+        monitors.iter().flat_map(|monitor| monitor.keys()).collect();
+    let no_order = Vec::new();
+
+    let mut same_order = true;
+    let mut missing_executions = 0;
+    for key in keys {
+        let orders: Vec<_> = monitors
+            .iter()
+            .map(|monitor| monitor.get_order(key).unwrap_or(&no_order))
+            .collect();
+        let longest = orders
+            .iter()
+            .max_by_key(|order| order.len())
+            .expect("there should be at least one order per key");
+        for order in orders.iter() {
+            same_order &= longest.starts_with(order);
+            missing_executions += longest.len() - order.len();
+        }
+    }
+    (same_order, missing_executions)
+}
+
+// Returns the resident set size (in kB) of this process, if available.
+fn rss_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find(|line| line.starts_with("VmRSS:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|rss| rss.parse().ok())
+}
+
+fn panic_message(e: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(msg) = e.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = e.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        String::from("unknown panic")
+    }
+}
+
+// Prints a line and appends it to the report.
+fn log(report: &mut String, line: String) {
+    println!("{}", line);
+    writeln!(report, "{}", line).expect("writing to a string should work");
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("soak")
+        .version("0.1")
+        .author("Vitor Enes <vitorenesduarte@gmail.com>")
+        .about("Continuously runs a mixed workload across protocols in the simulator, checking invariants and memory growth.")
+        .arg(
+            Arg::with_name("protocols")
+                .long("protocols")
+                .value_name("PROTOCOLS")
+                .help("comma-separated list of the protocols to be run; by default all protocols are run")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("planet_dir")
+                .long("planet_dir")
+                .value_name("PLANET_DIR")
+                .help("directory with the latencies simulated between processes; by default, GCP latencies are used")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("n")
+                .long("n")
+                .value_name("PROCESS_NUMBER")
+                .help("number of processes; default: 3")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("f")
+                .long("f")
+                .value_name("FAULT_NUMBER")
+                .help("number of allowed faults; default: 1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .value_name("DURATION")
+                .help("for how long (in seconds) new cycles (in which each protocol runs once) are started; default: 28800 (8 hours)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("clients_per_process")
+                .long("clients_per_process")
+                .value_name("CLIENTS_PER_PROCESS")
+                .help("number of closed-loop clients per process; default: 10")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("commands_per_client")
+                .long("commands_per_client")
+                .value_name("COMMANDS_PER_CLIENT")
+                .help("number of commands to be issued by each client in each cycle; default: 100")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max_memory_growth")
+                .long("max_memory_growth")
+                .value_name("MAX_MEMORY_GROWTH")
+                .help("percentage by which the memory used can grow (with respect to the one used once all the workloads ran) before the soak fails; default: 50")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .value_name("REPORT")
+                .help("file where the report is written to; by default it's only printed")
+                .takes_value(true),
+        )
+}
+
+fn parse_protocols(protocols: Option<&str>) -> Result<Vec<&str>, Report> {
+    match protocols {
+        Some(protocols) => protocols
+            .split(',')
+            .map(|protocol| {
+                PROTOCOLS
+                    .iter()
+                    .copied()
+                    .find(|name| *name == protocol)
+                    .ok_or_else(|| eyre!("unknown protocol: {}", protocol))
+            })
+            .collect(),
+        None => Ok(PROTOCOLS.to_vec()),
+    }
+}
+
+fn parse_args(matches: &ArgMatches<'_>) -> SoakArgs {
+    let planet = matches
+        .value_of("planet_dir")
+        .map(Planet::from)
+        .unwrap_or_else(Planet::new);
+    let n = parse_number(matches.value_of("n"), "n", DEFAULT_N);
+    let f = parse_number(matches.value_of("f"), "f", DEFAULT_F);
+    let duration = matches
+        .value_of("duration")
+        .map(|secs| {
+            Duration::from_secs(
+                secs.parse().expect("duration should be a number"),
+            )
+        })
+        .unwrap_or(DEFAULT_DURATION);
+    let clients_per_process = parse_number(
+        matches.value_of("clients_per_process"),
+        "clients_per_process",
+        DEFAULT_CLIENTS_PER_PROCESS,
+    );
+    let commands_per_client = parse_number(
+        matches.value_of("commands_per_client"),
+        "commands_per_client",
+        DEFAULT_COMMANDS_PER_CLIENT,
+    );
+    let max_memory_growth = parse_number(
+        matches.value_of("max_memory_growth"),
+        "max_memory_growth",
+        DEFAULT_MAX_MEMORY_GROWTH,
+    );
+    let report = matches.value_of("report").map(String::from);
+    (
+        planet,
+        n,
+        f,
+        duration,
+        clients_per_process,
+        commands_per_client,
+        max_memory_growth,
+        report,
+    )
+}
+
+fn parse_number<T>(number: Option<&str>, name: &str, default: T) -> T
+where
+    T: std::str::FromStr,
+{
+    number
+        .map(|number| {
+            number
+                .parse::<T>()
+                .unwrap_or_else(|_| panic!("{} should be a number", name))
+        })
+        .unwrap_or(default)
+}
@@ -0,0 +1,124 @@
+use clap::{App, Arg};
+use fantoch::client::ZipfSampler;
+use rand::distributions::Distribution;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::{Duration, Instant};
+use zipf::ZipfDistribution;
+
+const DEFAULT_KEYS: usize = 1_000_000;
+const DEFAULT_COEFFICIENT: f64 = 1.0;
+const DEFAULT_SAMPLES: usize = 10_000_000;
+
+fn main() {
+    let (keys, coefficient, samples) = parse_args();
+
+    // rejection-inversion sampler (the one used before the alias sampler)
+    let zipf = ZipfDistribution::new(keys, coefficient)
+        .expect("it should be possible to initialize the ZipfDistribution");
+    let (rejection, rejection_sum) = bench(samples, |rng| zipf.sample(rng));
+    report("rejection-inversion", samples, rejection);
+
+    // alias sampler
+    let start = Instant::now();
+    let sampler = ZipfSampler::new(keys, coefficient);
+    println!("alias table computed in {:?}", start.elapsed());
+    let (alias, alias_sum) = bench(samples, |rng| sampler.sample(rng));
+    report("alias", samples, alias);
+
+    println!(
+        "speedup: {:.2}x",
+        rejection.as_secs_f64() / alias.as_secs_f64()
+    );
+    // print the mean key sampled, so that both samplers can be compared and
+    // the samples are not optimized away
+    println!(
+        "mean key: {:.2} (rejection-inversion) vs {:.2} (alias)",
+        rejection_sum as f64 / samples as f64,
+        alias_sum as f64 / samples as f64
+    );
+}
+
+// Draws `samples` keys using `sample`, returning how long it took and the sum
+// of all keys.
+fn bench<F>(samples: usize, mut sample: F) -> (Duration, usize)
+where
+    F: FnMut(&mut StdRng) -> usize,
+{
+    let mut rng = StdRng::seed_from_u64(0);
+    let start = Instant::now();
+    let sum = (0..samples).map(|_| sample(&mut rng)).sum();
+    (start.elapsed(), sum)
+}
+
+fn report(name: &str, samples: usize, elapsed: Duration) {
+    println!(
+        "{}: {} samples in {:?} ({:.1}ns per sample)",
+        name,
+        samples,
+        elapsed,
+        elapsed.as_nanos() as f64 / samples as f64
+    );
+}
+
+fn parse_args() -> (usize, f64, usize) {
+    let matches = App::new("zipf_bench")
+        .version("0.1")
+        .author("Vitor Enes <vitorenesduarte@gmail.com>")
+        .about("Benchmark the zipf key generator")
+        .arg(
+            Arg::with_name("keys")
+                .long("keys")
+                .value_name("KEYS")
+                .help("total number of keys; default: 1000000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("coefficient")
+                .long("coefficient")
+                .value_name("COEFFICIENT")
+                .help("zipf coefficient; default: 1.0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("samples")
+                .long("samples")
+                .value_name("SAMPLES")
+                .help("number of keys to be sampled; default: 10000000")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    // parse arguments
+    let keys = parse_keys(matches.value_of("keys"));
+    let coefficient = parse_coefficient(matches.value_of("coefficient"));
+    let samples = parse_samples(matches.value_of("samples"));
+
+    println!("keys: {:?}", keys);
+    println!("coefficient: {:?}", coefficient);
+    println!("samples: {:?}", samples);
+
+    (keys, coefficient, samples)
+}
+
+fn parse_keys(keys: Option<&str>) -> usize {
+    parse_number(keys).unwrap_or(DEFAULT_KEYS)
+}
+
+fn parse_coefficient(coefficient: Option<&str>) -> f64 {
+    coefficient
+        .map(|coefficient| {
+            coefficient
+                .parse::<f64>()
+                .expect("coefficient should be a float")
+        })
+        .unwrap_or(DEFAULT_COEFFICIENT)
+}
+
+fn parse_samples(samples: Option<&str>) -> usize {
+    parse_number(samples).unwrap_or(DEFAULT_SAMPLES)
+}
+
+fn parse_number(number: Option<&str>) -> Option<usize> {
+    number.map(|number| number.parse::<usize>().expect("should be a number"))
+}
@@ -3,11 +3,12 @@ use crate::protocol::common::graph::Dependency;
 use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::executor::{
-    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
+    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorMetricsKind,
+    ExecutorResult, GraphSnapshot,
 };
 use fantoch::id::{Dot, ProcessId, ShardId};
-use fantoch::kvs::KVStore;
-use fantoch::protocol::MessageIndex;
+use fantoch::kvs::{KVStore, Key};
+use fantoch::protocol::{MessageIndex, Violation};
 use fantoch::time::SysTime;
 use fantoch::HashSet;
 use fantoch::{debug, trace};
@@ -15,6 +16,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fmt;
 use std::iter::FromIterator;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct GraphExecutor {
@@ -26,6 +28,7 @@ pub struct GraphExecutor {
     store: KVStore,
     to_clients: VecDeque<ExecutorResult>,
     to_executors: Vec<(ShardId, GraphExecutionInfo)>,
+    to_process_executors: Vec<(ProcessId, GraphExecutionInfo)>,
 }
 
 impl Executor for GraphExecutor {
@@ -35,9 +38,10 @@ impl Executor for GraphExecutor {
         // this value will be overwritten
         let executor_index = 0;
         let graph = DependencyGraph::new(process_id, shard_id, &config);
-        let store = KVStore::new(config.executor_monitor_execution_order());
+        let store = KVStore::from_config(&config, shard_id);
         let to_clients = Default::default();
         let to_executors = Default::default();
+        let to_process_executors = Default::default();
         Self {
             executor_index,
             process_id,
@@ -47,6 +51,7 @@ impl Executor for GraphExecutor {
             store,
             to_clients,
             to_executors,
+            to_process_executors,
         }
     }
 
@@ -56,16 +61,41 @@ impl Executor for GraphExecutor {
     }
 
     fn cleanup(&mut self, time: &dyn SysTime) {
-        if self.config.shard_count() > 1 {
+        // cleanups are only needed with partial replication (to retry
+        // requests) or if searches for SCCs can be interrupted (to resume
+        // them)
+        if self.config.shard_count() > 1
+            || self.config.executor_scc_budget().is_some()
+        {
             self.graph.cleanup(time);
             self.fetch_actions(time);
         }
     }
 
+    fn cleanup_interval(&mut self) -> Option<Duration> {
+        if self.config.executor_adaptive_cleanup() {
+            Some(self.graph.cleanup_interval())
+        } else {
+            None
+        }
+    }
+
     fn monitor_pending(&mut self, time: &dyn SysTime) {
         self.graph.monitor_pending(time);
     }
 
+    fn sample_memory(&mut self) {
+        self.graph.sample_memory();
+    }
+
+    fn backlog(&self) -> Option<usize> {
+        self.graph.backlog()
+    }
+
+    fn graph_snapshot(&self) -> Option<GraphSnapshot> {
+        self.graph.snapshot()
+    }
+
     fn handle(&mut self, info: GraphExecutionInfo, time: &dyn SysTime) {
         match info {
             GraphExecutionInfo::Add { dot, cmd, deps } => {
@@ -78,8 +108,12 @@ impl Executor for GraphExecutor {
                     self.fetch_actions(time);
                 }
             }
-            GraphExecutionInfo::Request { from, dots } => {
-                self.graph.handle_request(from, dots, time);
+            GraphExecutionInfo::Request {
+                from,
+                from_shard,
+                dots,
+            } => {
+                self.graph.handle_request(from, from_shard, dots, time);
                 self.fetch_actions(time);
             }
             GraphExecutionInfo::RequestReply { infos } => {
@@ -90,6 +124,21 @@ impl Executor for GraphExecutor {
                 self.graph.handle_executed(dots, time);
             }
         }
+
+        // record the writes elided
+        let elided = self.store.flush_writes();
+        if elided > 0 {
+            self.graph
+                .metrics_mut()
+                .aggregate(ExecutorMetricsKind::ElidedWrites, elided);
+        }
+        // record the values expired
+        let expired = self.store.take_expired();
+        if expired > 0 {
+            self.graph
+                .metrics_mut()
+                .aggregate(ExecutorMetricsKind::ExpiredValues, expired);
+        }
     }
 
     fn to_clients(&mut self) -> Option<ExecutorResult> {
@@ -100,6 +149,16 @@ impl Executor for GraphExecutor {
         self.to_executors.pop()
     }
 
+    fn to_process_executors(
+        &mut self,
+    ) -> Option<(ProcessId, GraphExecutionInfo)> {
+        self.to_process_executors.pop()
+    }
+
+    fn to_violations(&mut self) -> Option<Violation> {
+        self.graph.to_violations()
+    }
+
     fn parallel() -> bool {
         true
     }
@@ -111,6 +170,15 @@ impl Executor for GraphExecutor {
     fn monitor(&self) -> Option<ExecutionOrderMonitor> {
         self.store.monitor().cloned()
     }
+
+    fn store(&self) -> &KVStore {
+        &self.store
+    }
+
+    fn stale_read_index(_key: &Key) -> Option<(usize, usize)> {
+        // only the store of the main executor is up-to-date
+        Some((0, MAIN_INDEX))
+    }
 }
 
 impl GraphExecutor {
@@ -161,9 +229,25 @@ impl GraphExecutor {
                 dots,
                 _time.millis()
             );
-            let request = GraphExecutionInfo::request(self.shard_id, dots);
+            let request = self.request(dots);
             self.to_executors.push((to, request));
         }
+        for (to, dots) in self.graph.retries() {
+            trace!(
+                "p{}: @{} GraphExecutor::fetch_retries {:?} {:?} | time = {}",
+                self.process_id,
+                self.executor_index,
+                to,
+                dots,
+                _time.millis()
+            );
+            let request = self.request(dots);
+            self.to_process_executors.push((to, request));
+        }
+    }
+
+    fn request(&self, dots: HashSet<Dot>) -> GraphExecutionInfo {
+        GraphExecutionInfo::request(self.process_id, self.shard_id, dots)
     }
 
     fn fetch_request_replies(&mut self, _time: &dyn SysTime) {
@@ -177,7 +261,7 @@ impl GraphExecutor {
                 _time.millis()
             );
             let reply = GraphExecutionInfo::request_reply(infos);
-            self.to_executors.push((to, reply));
+            self.to_process_executors.push((to, reply));
         }
     }
 
@@ -202,7 +286,8 @@ pub enum GraphExecutionInfo {
         deps: HashSet<Dependency>,
     },
     Request {
-        from: ShardId,
+        from: ProcessId,
+        from_shard: ShardId,
         dots: HashSet<Dot>,
     },
     RequestReply {
@@ -218,8 +303,16 @@ impl GraphExecutionInfo {
         Self::Add { dot, cmd, deps }
     }
 
-    fn request(from: ShardId, dots: HashSet<Dot>) -> Self {
-        Self::Request { from, dots }
+    fn request(
+        from: ProcessId,
+        from_shard: ShardId,
+        dots: HashSet<Dot>,
+    ) -> Self {
+        Self::Request {
+            from,
+            from_shard,
+            dots,
+        }
     }
 
     fn request_reply(infos: Vec<super::RequestReply>) -> Self {
@@ -231,11 +324,13 @@ impl GraphExecutionInfo {
     }
 }
 
+// commands are only executed by the main executor, while the secondary one
+// handles requests from other shards
+const MAIN_INDEX: usize = 0;
+const SECONDARY_INDEX: usize = 1;
+
 impl MessageIndex for GraphExecutionInfo {
     fn index(&self) -> Option<(usize, usize)> {
-        const MAIN_INDEX: usize = 0;
-        const SECONDARY_INDEX: usize = 1;
-
         const fn main_executor() -> Option<(usize, usize)> {
             Some((0, MAIN_INDEX))
         }
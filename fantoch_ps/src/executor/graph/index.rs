@@ -1,6 +1,7 @@
 use super::tarjan::Vertex;
 use crate::protocol::common::graph::Dependency;
 use fantoch::config::Config;
+use fantoch::executor::GraphSnapshot;
 use fantoch::hash_map::{Entry, HashMap};
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::info;
@@ -9,9 +10,10 @@ use fantoch::time::SysTime;
 use fantoch::HashSet;
 use parking_lot::{RwLock, RwLockReadGuard};
 use std::collections::BTreeMap;
+use std::mem;
 use std::sync::Arc;
 use std::time::Duration;
-use threshold::AEClock;
+use threshold::{AEClock, EventSet};
 
 pub type VertexRef<'a> = SharedMapRef<'a, Dot, RwLock<Vertex>>;
 
@@ -41,15 +43,61 @@ impl VertexIndex {
         self.index.iter().map(|entry| *entry.key())
     }
 
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
     pub fn find(&self, dot: &Dot) -> Option<VertexRef<'_>> {
         self.index.get(dot)
     }
 
+    /// Returns the approximate memory (in bytes) used by the index. Only the
+    /// vertices and their dependencies are accounted for (and not, e.g., the
+    /// operations in each command).
+    pub fn memory(&self) -> usize {
+        self.index
+            .iter()
+            .map(|vertex_ref| {
+                let deps = vertex_ref.read().deps.len();
+                mem::size_of::<Dot>()
+                    + mem::size_of::<RwLock<Vertex>>()
+                    + deps * mem::size_of::<Dependency>()
+            })
+            .sum()
+    }
+
     /// Removes a vertex from the index.
     pub fn remove(&mut self, dot: &Dot) -> Option<Vertex> {
         self.index.remove(dot).map(|(_, cell)| cell.into_inner())
     }
 
+    /// Creates a snapshot of the pending vertices, including the
+    /// dependencies of each of them that haven't been executed.
+    pub fn snapshot(
+        &self,
+        executed_clock: &AEClock<ProcessId>,
+    ) -> GraphSnapshot {
+        let frontier = executed_clock
+            .iter()
+            .map(|(process_id, executed)| (*process_id, executed.frontier()));
+        let mut snapshot = GraphSnapshot::new(frontier);
+        self.index.iter().for_each(|vertex_ref| {
+            let vertex = vertex_ref.read();
+            let deps = vertex
+                .deps
+                .iter()
+                .map(|dep| dep.dot)
+                .filter(|dep_dot| {
+                    !executed_clock
+                        .contains(&dep_dot.source(), dep_dot.sequence())
+                })
+                // copy the dependencies so that the vertex is not kept locked
+                .collect::<Vec<_>>();
+            snapshot.add_pending(vertex.dot, deps);
+        });
+        snapshot
+    }
+
     pub fn monitor_pending(
         &self,
         executed_clock: &AEClock<ProcessId>,
@@ -208,4 +256,16 @@ impl PendingIndex {
     pub fn remove(&mut self, dep_dot: &Dot) -> Option<HashSet<Dot>> {
         self.index.remove(dep_dot)
     }
+
+    /// Returns the approximate memory (in bytes) used by the index.
+    pub fn memory(&self) -> usize {
+        self.index
+            .values()
+            .map(|children| {
+                mem::size_of::<Dot>()
+                    + mem::size_of::<HashSet<Dot>>()
+                    + children.len() * mem::size_of::<Dot>()
+            })
+            .sum()
+    }
 }
@@ -16,8 +16,12 @@ use self::tarjan::{FinderResult, TarjanSCCFinder, Vertex, SCC};
 use crate::protocol::common::graph::Dependency;
 use fantoch::command::Command;
 use fantoch::config::Config;
-use fantoch::executor::{ExecutorMetrics, ExecutorMetricsKind};
+use fantoch::executor::{
+    AdaptiveCleanupInterval, ExecutorMetrics, ExecutorMetricsKind,
+    GraphSnapshot,
+};
 use fantoch::id::{Dot, ProcessId, ShardId};
+use fantoch::protocol::Violation;
 use fantoch::time::SysTime;
 use fantoch::util;
 use fantoch::{debug, trace};
@@ -29,6 +33,9 @@ use std::time::Duration;
 use threshold::AEClock;
 
 const MONITOR_PENDING_THRESHOLD: Duration = Duration::from_secs(1);
+// time after which an unanswered request is sent to a different replica of the
+// target shard
+const REQUEST_RETRY_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RequestReply {
@@ -42,29 +49,66 @@ pub enum RequestReply {
     },
 }
 
+impl RequestReply {
+    fn dot(&self) -> Dot {
+        match self {
+            Self::Info { dot, .. } => *dot,
+            Self::Executed { dot } => *dot,
+        }
+    }
+}
+
+// Request sent to another shard that hasn't been answered yet.
+#[derive(Debug, Clone)]
+struct InFlightRequest {
+    target_shard: ShardId,
+    // number of times the request has been retried
+    retries: usize,
+    // time at which the request was first sent
+    first_sent: u64,
+    // time at which the request was last sent
+    last_sent: u64,
+}
+
 #[derive(Clone)]
 pub struct DependencyGraph {
     executor_index: usize,
     process_id: ProcessId,
     shard_id: ShardId,
+    n: usize,
     executed_clock: AEClock<ProcessId>,
     vertex_index: VertexIndex,
     pending_index: PendingIndex,
     finder: TarjanSCCFinder,
+    // maximum number of vertices visited by each search for SCCs (if any),
+    // and the dots whose search exceeded it, mapped to the budget with which
+    // the search will be resumed in the next cleanup
+    scc_budget: Option<usize>,
+    deferred: HashMap<Dot, usize>,
     metrics: ExecutorMetrics,
+    // adaptive cleanup interval and the number of missing dependencies found
+    // since the last time it was computed
+    cleanup_interval: AdaptiveCleanupInterval,
+    missing_deps_since_cleanup: usize,
     // worker 0 (handles commands):
     // - adds new commands `to_execute`
     // - `out_requests` dependencies to be able to order commands
+    // - retries requests that are `in_flight_requests` for too long by sending
+    //   them to another replica of the target shard in `out_retries`
     // - notifies remaining workers about what's been executed through
     //   `added_to_executed_clock`
     to_execute: VecDeque<Command>,
     out_requests: HashMap<ShardId, HashSet<Dot>>,
+    in_flight_requests: HashMap<Dot, InFlightRequest>,
+    out_retries: HashMap<ProcessId, HashSet<Dot>>,
     added_to_executed_clock: HashSet<Dot>,
     // auxiliary workers (handles requests):
     // - may have `buffered_in_requests` when doesn't have the command yet
     // - produces `out_request_replies` when it has the command
-    buffered_in_requests: HashMap<ShardId, HashSet<Dot>>,
-    out_request_replies: HashMap<ShardId, Vec<RequestReply>>,
+    buffered_in_requests: HashMap<(ProcessId, ShardId), HashSet<Dot>>,
+    out_request_replies: HashMap<ProcessId, Vec<RequestReply>>,
+    // violations of the graph's invariants (e.g. a command added twice)
+    violations: Vec<Violation>,
 }
 
 enum FinderInfo {
@@ -76,6 +120,9 @@ enum FinderInfo {
     MissingDependencies(Vec<Dot>, HashSet<Dot>, HashSet<Dependency>),
     // in case we try to find SCCs on dots that are no longer pending
     NotPending,
+    // set of dots in found SCCs (it's possible to find SCCs even though the
+    // search for another dot was interrupted)
+    BudgetExhausted(Vec<Dot>),
 }
 
 impl DependencyGraph {
@@ -98,29 +145,45 @@ impl DependencyGraph {
         let pending_index = PendingIndex::new(process_id, shard_id, *config);
         // create finder
         let finder = TarjanSCCFinder::new(process_id, shard_id, *config);
+        let scc_budget = config.executor_scc_budget();
+        let deferred = Default::default();
         let metrics = ExecutorMetrics::new();
+        // create adaptive cleanup interval
+        let cleanup_interval = AdaptiveCleanupInterval::default();
+        let missing_deps_since_cleanup = 0;
         // create to execute
         let to_execute = Default::default();
         // create requests and request replies
         let out_requests = Default::default();
+        let in_flight_requests = Default::default();
+        let out_retries = Default::default();
         // only track what's added to the executed clock if partial replication
         let added_to_executed_clock = HashSet::new();
         let buffered_in_requests = Default::default();
         let out_request_replies = Default::default();
+        let violations = Vec::new();
         DependencyGraph {
             executor_index,
             process_id,
             shard_id,
+            n: config.n(),
             executed_clock,
             vertex_index,
             pending_index,
             finder,
+            scc_budget,
+            deferred,
             metrics,
+            cleanup_interval,
+            missing_deps_since_cleanup,
             to_execute,
             out_requests,
+            in_flight_requests,
+            out_retries,
             added_to_executed_clock,
             buffered_in_requests,
             out_request_replies,
+            violations,
         }
     }
 
@@ -150,12 +213,24 @@ impl DependencyGraph {
         std::mem::take(&mut self.out_requests)
     }
 
+    /// Returns the requests to be retried at a specific process.
+    #[must_use]
+    pub fn retries(&mut self) -> HashMap<ProcessId, HashSet<Dot>> {
+        std::mem::take(&mut self.out_retries)
+    }
+
     /// Returns a set of request replies.
     #[must_use]
-    pub fn request_replies(&mut self) -> HashMap<ShardId, Vec<RequestReply>> {
+    pub fn request_replies(&mut self) -> HashMap<ProcessId, Vec<RequestReply>> {
         std::mem::take(&mut self.out_request_replies)
     }
 
+    /// Returns a violation of the graph's invariants (if any).
+    #[must_use]
+    pub fn to_violations(&mut self) -> Option<Violation> {
+        self.violations.pop()
+    }
+
     #[cfg(test)]
     fn commands_to_execute(&mut self) -> VecDeque<Command> {
         std::mem::take(&mut self.to_execute)
@@ -165,6 +240,10 @@ impl DependencyGraph {
         &self.metrics
     }
 
+    fn metrics_mut(&mut self) -> &mut ExecutorMetrics {
+        &mut self.metrics
+    }
+
     fn cleanup(&mut self, time: &dyn SysTime) {
         trace!(
             "p{}: @{} Graph::cleanup | time = {}",
@@ -175,6 +254,64 @@ impl DependencyGraph {
         if self.executor_index > 0 {
             // if not main executor, check pending remote requests
             self.check_pending_requests(time);
+        } else {
+            // if main executor, retry requests that are taking too long and
+            // resume the searches for SCCs that were interrupted
+            self.retry_requests(time);
+            self.resume_deferred(time);
+        }
+    }
+
+    fn cleanup_interval(&mut self) -> Duration {
+        // pending dots are the ones in the vertex index and the ones buffered
+        // in requests
+        let buffered = self
+            .buffered_in_requests
+            .values()
+            .map(|dots| dots.len())
+            .sum::<usize>();
+        let pending = self.vertex_index.len() + buffered;
+        let missing_deps = std::mem::take(&mut self.missing_deps_since_cleanup);
+        let interval = self.cleanup_interval.next(pending, missing_deps);
+        self.metrics.collect(
+            ExecutorMetricsKind::CleanupInterval,
+            interval.as_millis() as u64,
+        );
+        interval
+    }
+
+    fn sample_memory(&mut self) {
+        // the vertex index is shared by all executors, and thus it's only
+        // sampled by the main executor
+        if self.executor_index == 0 {
+            self.metrics.collect(
+                ExecutorMetricsKind::VertexIndexMemory,
+                self.vertex_index.memory() as u64,
+            );
+        }
+        self.metrics.collect(
+            ExecutorMetricsKind::PendingIndexMemory,
+            self.pending_index.memory() as u64,
+        );
+    }
+
+    fn backlog(&self) -> Option<usize> {
+        // the vertex index is shared by all executors, and thus only the main
+        // executor reports its size
+        if self.executor_index == 0 {
+            Some(self.vertex_index.len())
+        } else {
+            None
+        }
+    }
+
+    fn snapshot(&self) -> Option<GraphSnapshot> {
+        // the vertex index is shared by all executors, and thus only the main
+        // executor takes snapshots of it
+        if self.executor_index == 0 {
+            Some(self.vertex_index.snapshot(&self.executed_clock))
+        } else {
+            None
         }
     }
 
@@ -229,23 +366,48 @@ impl DependencyGraph {
             time.millis()
         );
 
-        // create new vertex for this command
-        let vertex = Vertex::new(dot, cmd, deps, time);
-
-        if self.vertex_index.index(vertex).is_some() {
-            panic!(
-                "p{}: @{} Graph::handle_add tried to index already indexed {:?}",
-                self.process_id, self.executor_index, dot
+        // commands can't be added twice: if that happens, the command is
+        // ignored and the violation is reported
+        if self.executed_clock.contains(&dot.source(), dot.sequence()) {
+            let reason = format!(
+                "@{} Graph::handle_add tried to add already executed {:?}",
+                self.executor_index, dot
+            );
+            let state = format!(
+                "executed clock entry of p{}: {:?}",
+                dot.source(),
+                self.executed_clock.get(&dot.source())
+            );
+            self.violation(reason, state);
+            return;
+        }
+        let indexed = self
+            .vertex_index
+            .find(&dot)
+            .map(|vertex| format!("{:?}", vertex.read()));
+        if let Some(indexed) = indexed {
+            let reason = format!(
+                "@{} Graph::handle_add tried to index already indexed {:?}",
+                self.executor_index, dot
             );
+            self.violation(reason, format!("indexed vertex: {}", indexed));
+            return;
         }
 
+        // create new vertex for this command
+        let vertex = Vertex::new(dot, cmd, deps, time);
+        let previous = self.vertex_index.index(vertex);
+        assert!(previous.is_none(), "the dot was not indexed");
+
         // get current command ready count and count newly ready commands
         let initial_ready = self.to_execute.len();
         let mut total_scc_count = 0;
 
         // try to find new SCCs
         let first_find = true;
-        match self.find_scc(first_find, dot, &mut total_scc_count, time) {
+        let budget = self.scc_budget;
+        match self.find_scc(first_find, dot, budget, &mut total_scc_count, time)
+        {
             FinderInfo::Found(dots) => {
                 // try to execute other commands if new SCCs were found
                 self.check_pending(dots, &mut total_scc_count, time);
@@ -259,6 +421,12 @@ impl DependencyGraph {
             FinderInfo::NotPending => {
                 panic!("just added dot must be pending");
             }
+            FinderInfo::BudgetExhausted(dots) => {
+                // resume the search in the next cleanup
+                self.defer(dot, budget);
+                // try to execute other commands if new SCCs were found
+                self.check_pending(dots, &mut total_scc_count, time);
+            }
         }
 
         // check that all newly ready commands have been incorporated
@@ -278,7 +446,8 @@ impl DependencyGraph {
 
     fn handle_request(
         &mut self,
-        from: ShardId,
+        from: ProcessId,
+        from_shard: ShardId,
         dots: HashSet<Dot>,
         time: &dyn SysTime,
     ) {
@@ -294,12 +463,13 @@ impl DependencyGraph {
         // save in requests metric
         self.metrics.aggregate(ExecutorMetricsKind::InRequests, 1);
         // try to process requests
-        self.process_requests(from, dots.into_iter(), time)
+        self.process_requests(from, from_shard, dots.into_iter(), time)
     }
 
     fn process_requests(
         &mut self,
-        from: ShardId,
+        from: ProcessId,
+        from_shard: ShardId,
         dots: impl Iterator<Item = Dot>,
         time: &dyn SysTime,
     ) {
@@ -309,13 +479,13 @@ impl DependencyGraph {
                 let vertex = vertex.read();
 
                 // panic if the shard that requested this vertex replicates it
-                if vertex.cmd.replicated_by(&from) {
+                if vertex.cmd.replicated_by(&from_shard) {
                     panic!(
                         "p{}: @{} Graph::process_requests {:?} is replicated by {:?} (WARN) | time = {}",
                         self.process_id,
                         self.executor_index,
                         dot,
-                        from,
+                        from_shard,
                         time.millis()
                     )
                 } else {
@@ -366,7 +536,7 @@ impl DependencyGraph {
                     );
                     // buffer request again
                     self.buffered_in_requests
-                        .entry(from)
+                        .entry((from, from_shard))
                         .or_default()
                         .insert(dot);
                 }
@@ -389,6 +559,12 @@ impl DependencyGraph {
                 time.millis()
             );
 
+            // ignore replies to requests that have already been answered
+            // (which can happen when requests are retried)
+            if !self.request_answered(info.dot(), time) {
+                continue;
+            }
+
             match info {
                 RequestReply::Info { dot, cmd, deps } => {
                     // add requested command to our graph
@@ -407,11 +583,18 @@ impl DependencyGraph {
         }
     }
 
+    fn violation(&mut self, reason: String, state: String) {
+        self.metrics.aggregate(ExecutorMetricsKind::Violations, 1);
+        self.violations
+            .push(Violation::new(self.process_id, reason, state));
+    }
+
     #[must_use]
     fn find_scc(
         &mut self,
         first_find: bool,
         dot: Dot,
+        budget: Option<usize>,
         total_scc_count: &mut usize,
         time: &dyn SysTime,
     ) -> FinderInfo {
@@ -426,6 +609,7 @@ impl DependencyGraph {
         // execute tarjan's algorithm
         let mut scc_count = 0;
         let mut missing_deps_count = 0;
+        self.finder.set_budget(budget);
         let finder_result = self.strong_connect(
             first_find,
             dot,
@@ -476,6 +660,11 @@ impl DependencyGraph {
                 )
             }
             FinderResult::NotPending => FinderInfo::NotPending,
+            FinderResult::BudgetExhausted => {
+                // the missing dependencies found so far (if any) are ignored,
+                // as they'll be found again once the search is resumed
+                FinderInfo::BudgetExhausted(dots)
+            }
             FinderResult::NotFound => {
                 // in this case, `missing_deps` must be non-empty
                 assert!(
@@ -515,9 +704,12 @@ impl DependencyGraph {
             // get command
             let (duration_ms, cmd) = vertex.into_command(time);
 
-            // save execution delay metric
-            self.metrics
-                .collect(ExecutorMetricsKind::ExecutionDelay, duration_ms);
+            // save execution delay metric (once per original command, if the
+            // command is a batch)
+            for _ in 0..cmd.batch_size() {
+                self.metrics
+                    .collect(ExecutorMetricsKind::ExecutionDelay, duration_ms);
+            }
 
             // add command to commands to be executed
             self.to_execute.push_back(cmd);
@@ -528,8 +720,24 @@ impl DependencyGraph {
         &mut self,
         dot: Dot,
         missing_deps: HashSet<Dependency>,
-        _time: &dyn SysTime,
+        time: &dyn SysTime,
     ) {
+        // check whether some of the missing dependencies is replicated by
+        // other shards (noops are replicated by all shards)
+        let is_remote = missing_deps.iter().any(|dep| {
+            dep.shards
+                .as_ref()
+                .map(|shards| !shards.contains(&self.shard_id))
+                .unwrap_or(false)
+        });
+        // save stall metrics
+        self.metrics
+            .aggregate(ExecutorMetricsKind::DependencyStalls, 1);
+        if is_remote {
+            self.metrics
+                .aggregate(ExecutorMetricsKind::RemoteDependencyStalls, 1);
+        }
+
         let mut requests = 0;
         for dep in missing_deps {
             if let Some((dep_dot, target_shard)) =
@@ -541,18 +749,27 @@ impl DependencyGraph {
                     self.executor_index,
                     dep_dot,
                     target_shard,
-                    _time.millis()
+                    time.millis()
                 );
                 requests += 1;
                 self.out_requests
                     .entry(target_shard)
                     .or_default()
                     .insert(dep_dot);
+                let now = time.millis();
+                let request = InFlightRequest {
+                    target_shard,
+                    retries: 0,
+                    first_sent: now,
+                    last_sent: now,
+                };
+                self.in_flight_requests.insert(dep_dot, request);
             }
         }
         // save out requests metric
         self.metrics
             .aggregate(ExecutorMetricsKind::OutRequests, requests);
+        self.missing_deps_since_cleanup += requests as usize;
     }
 
     fn check_pending(
@@ -599,11 +816,18 @@ impl DependencyGraph {
         // try to find new SCCs for each of those commands
         let mut visited = HashSet::new();
         let first_find = false;
+        let budget = self.scc_budget;
 
         for dot in pending {
             // only try to find new SCCs from non-visited commands
             if !visited.contains(&dot) {
-                match self.find_scc(first_find, dot, total_scc_count, time) {
+                match self.find_scc(
+                    first_find,
+                    dot,
+                    budget,
+                    total_scc_count,
+                    time,
+                ) {
                     FinderInfo::Found(new_dots) => {
                         // reset visited
                         visited.clear();
@@ -638,6 +862,72 @@ impl DependencyGraph {
                         // this happens if the pending dot is no longer
                         // pending
                     }
+                    FinderInfo::BudgetExhausted(new_dots) => {
+                        // resume the search in the next cleanup
+                        self.defer(dot, budget);
+
+                        // if new SCCs were found, reset visited, as now there
+                        // are more child dots to check
+                        if !new_dots.is_empty() {
+                            visited.clear();
+                        }
+                        dots.extend(new_dots);
+                    }
+                }
+            }
+        }
+    }
+
+    // Records that the search for SCCs starting at `dot` was interrupted. The
+    // search is resumed in the next cleanup with twice the budget, so that
+    // the SCC is eventually found no matter how large it is.
+    fn defer(&mut self, dot: Dot, budget: Option<usize>) {
+        let budget = budget.expect("only searches with a budget are deferred");
+        self.metrics
+            .aggregate(ExecutorMetricsKind::SccBudgetExhaustions, 1);
+        self.deferred.insert(dot, budget * 2);
+    }
+
+    fn resume_deferred(&mut self, time: &dyn SysTime) {
+        assert_eq!(self.executor_index, 0);
+        let deferred = std::mem::take(&mut self.deferred);
+        let mut total_scc_count = 0;
+        let first_find = false;
+        for (dot, budget) in deferred {
+            debug!(
+                "p{}: @{} Graph::resume_deferred {:?} with budget {} | time = {}",
+                self.process_id,
+                self.executor_index,
+                dot,
+                budget,
+                time.millis()
+            );
+            let budget = Some(budget);
+            match self.find_scc(
+                first_find,
+                dot,
+                budget,
+                &mut total_scc_count,
+                time,
+            ) {
+                FinderInfo::Found(dots) => {
+                    self.check_pending(dots, &mut total_scc_count, time);
+                }
+                FinderInfo::MissingDependencies(
+                    dots,
+                    _visited,
+                    missing_deps,
+                ) => {
+                    self.index_pending(dot, missing_deps, time);
+                    self.check_pending(dots, &mut total_scc_count, time);
+                }
+                FinderInfo::NotPending => {
+                    // this happens if the deferred dot was meanwhile found to
+                    // be part of some SCC
+                }
+                FinderInfo::BudgetExhausted(dots) => {
+                    self.defer(dot, budget);
+                    self.check_pending(dots, &mut total_scc_count, time);
                 }
             }
         }
@@ -672,10 +962,67 @@ impl DependencyGraph {
 
     fn check_pending_requests(&mut self, time: &dyn SysTime) {
         let buffered = std::mem::take(&mut self.buffered_in_requests);
-        for (from, dots) in buffered {
-            self.process_requests(from, dots.into_iter(), time);
+        for ((from, from_shard), dots) in buffered {
+            self.process_requests(from, from_shard, dots.into_iter(), time);
         }
     }
+
+    // Marks the request for `dot` as answered, returning whether it was still
+    // in flight.
+    fn request_answered(&mut self, dot: Dot, time: &dyn SysTime) -> bool {
+        if let Some(request) = self.in_flight_requests.remove(&dot) {
+            // save request rtt metric (since the request was first sent)
+            let rtt = time.millis().saturating_sub(request.first_sent);
+            self.metrics.collect(ExecutorMetricsKind::RequestRtt, rtt);
+            true
+        } else {
+            debug!(
+                "p{}: @{} Graph::request_answered {:?} already answered | time = {}",
+                self.process_id,
+                self.executor_index,
+                dot,
+                time.millis()
+            );
+            false
+        }
+    }
+
+    fn retry_requests(&mut self, time: &dyn SysTime) {
+        let now = time.millis();
+        let timeout = REQUEST_RETRY_TIMEOUT.as_millis() as u64;
+        let mut retries = 0;
+        for (dot, request) in self.in_flight_requests.iter_mut() {
+            if now.saturating_sub(request.last_sent) < timeout {
+                continue;
+            }
+            // escalate by asking a different replica of the target shard:
+            // - the first request is sent to the closest replica, which is
+            //   likely the one in the same region (i.e. the one with the same
+            //   index within its shard)
+            // - each retry moves on to the next replica
+            request.retries += 1;
+            request.last_sent = now;
+            let region_index = (self.process_id as usize - 1) % self.n;
+            let replica_index = (region_index + request.retries) % self.n;
+            let target = util::process_ids(request.target_shard, self.n)
+                .nth(replica_index)
+                .expect("replica index should be smaller than n");
+            debug!(
+                "p{}: @{} Graph::retry_requests will ask {:?} to {:?} (retry {}) | time = {}",
+                self.process_id,
+                self.executor_index,
+                dot,
+                target,
+                request.retries,
+                now
+            );
+            self.out_retries.entry(target).or_default().insert(*dot);
+            retries += 1;
+        }
+        // save request retries metric
+        self.metrics
+            .aggregate(ExecutorMetricsKind::RequestRetries, retries);
+    }
 }
 
 impl fmt::Debug for DependencyGraph {
@@ -695,7 +1042,7 @@ mod tests {
     use crate::util;
     use fantoch::id::{ClientId, Rifl, ShardId};
     use fantoch::kvs::{KVOp, Key};
-    use fantoch::time::RunTime;
+    use fantoch::time::{RunTime, SimTime};
     use fantoch::HashMap;
     use permutator::{Combination, Permutation};
     use rand::seq::SliceRandom;
@@ -747,10 +1094,222 @@ mod tests {
         // check commands ready to be executed
         assert!(queue.commands_to_execute().is_empty());
 
+        // check that cmd 0 is pending on cmd 1, which is missing
+        let snapshot = queue.snapshot().expect("snapshot should exist");
+        assert_eq!(snapshot.pending(), &BTreeSet::from_iter(vec![dot_0]));
+        assert_eq!(snapshot.missing(), &BTreeSet::from_iter(vec![dot_1]));
+        assert_eq!(
+            snapshot.edges(),
+            &BTreeSet::from_iter(vec![(dot_0, dot_1)])
+        );
+
         // add cmd 1
         queue.handle_add(dot_1, cmd_1.clone(), deps_1, &time);
         // check commands ready to be executed
         assert_eq!(queue.commands_to_execute(), vec![cmd_0, cmd_1]);
+
+        // check that nothing is pending and that the frontier moved
+        let snapshot = queue.snapshot().expect("snapshot should exist");
+        assert!(snapshot.pending().is_empty());
+        assert!(snapshot.edges().is_empty());
+        assert_eq!(
+            snapshot.frontier(),
+            &BTreeMap::from_iter(vec![(1, 1), (2, 1)])
+        );
+    }
+
+    #[test]
+    fn duplicate_add() {
+        // create queue
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 2;
+        let f = 1;
+        let config = Config::new(n, f);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        // create dots
+        let dot_0 = Dot::new(1, 1);
+        let dot_1 = Dot::new(1, 2);
+        let dot_2 = Dot::new(2, 1);
+
+        // cmd 0 has no dependencies, and cmd 1 depends on cmd 2 (which is
+        // never added)
+        let cmd = |rifl| {
+            Command::from(
+                rifl,
+                vec![(String::from("A"), KVOp::Put(String::new()))],
+            )
+        };
+        let cmd_0 = cmd(Rifl::new(1, 1));
+        let cmd_1 = cmd(Rifl::new(1, 2));
+
+        // add cmd 0, which is executed right away
+        queue.handle_add(dot_0, cmd_0.clone(), vec![], &time);
+        assert_eq!(queue.commands_to_execute(), vec![cmd_0.clone()]);
+        assert!(queue.to_violations().is_none());
+
+        // adding it again is a violation, and the command is ignored
+        queue.handle_add(dot_0, cmd_0, vec![], &time);
+        assert!(queue.commands_to_execute().is_empty());
+        let violation = queue.to_violations().expect("violation");
+        assert_eq!(violation.process_id, process_id);
+        assert!(violation.reason.contains("already executed"));
+
+        // the same happens with pending commands
+        let deps_1 = vec![dep(dot_2, shard_id)];
+        queue.handle_add(dot_1, cmd_1.clone(), deps_1.clone(), &time);
+        queue.handle_add(dot_1, cmd_1, deps_1, &time);
+        assert!(queue.commands_to_execute().is_empty());
+        let violation = queue.to_violations().expect("violation");
+        assert!(violation.reason.contains("already indexed"));
+        assert!(queue.to_violations().is_none());
+
+        // check that both violations were accounted
+        assert_eq!(
+            queue
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::Violations),
+            Some(&2)
+        );
+    }
+
+    #[test]
+    fn request_retries() {
+        // create queue in shard 0 of a deployment with 2 shards
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_shard_count(2);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let mut time = SimTime::new();
+        let timeout = REQUEST_RETRY_TIMEOUT.as_millis() as u64;
+
+        // create a command that depends on a command from shard 1
+        let dot = Dot::new(1, 1);
+        let remote_dot = Dot::new(4, 1);
+        let cmd = Command::from(
+            Rifl::new(1, 1),
+            vec![(String::from("A"), KVOp::Put(String::new()))],
+        );
+        let deps = vec![dep(remote_dot, 1)];
+        let remote_dots = HashSet::from_iter(vec![remote_dot]);
+
+        // add cmd: the remote dependency is requested from shard 1
+        queue.handle_add(dot, cmd.clone(), deps, &time);
+        assert_eq!(queue.requests().get(&1), Some(&remote_dots));
+
+        // no retries until the timeout
+        time.add_millis(timeout - 1);
+        queue.cleanup(&time);
+        assert!(queue.retries().is_empty());
+
+        // after the timeout, the next replica of shard 1 is asked
+        time.add_millis(1);
+        queue.cleanup(&time);
+        assert_eq!(queue.retries().get(&5), Some(&remote_dots));
+
+        // and then the one after that
+        time.add_millis(timeout);
+        queue.cleanup(&time);
+        assert_eq!(queue.retries().get(&6), Some(&remote_dots));
+
+        // once a reply is received, the command is executed
+        let reply = RequestReply::Executed { dot: remote_dot };
+        queue.handle_request_reply(vec![reply.clone()], &time);
+        assert_eq!(queue.commands_to_execute(), vec![cmd]);
+
+        // replies from slower replicas are ignored
+        queue.handle_request_reply(vec![reply], &time);
+        assert!(queue.commands_to_execute().is_empty());
+
+        // no more retries and a single request rtt was recorded
+        time.add_millis(timeout);
+        queue.cleanup(&time);
+        assert!(queue.retries().is_empty());
+        let rtts = queue
+            .metrics()
+            .get_collected(ExecutorMetricsKind::RequestRtt)
+            .expect("request rtt should have been recorded");
+        assert_eq!(rtts.count(), 1);
+        assert_eq!(
+            queue
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::RequestRetries),
+            Some(&2)
+        );
+
+        // a single stall, caused by a dependency from another shard
+        assert_eq!(
+            queue
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::DependencyStalls),
+            Some(&1)
+        );
+        assert_eq!(
+            queue
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::RemoteDependencyStalls),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn scc_budget() {
+        // create queue with a budget of a single vertex per search
+        let process_id = 1;
+        let shard_id = 0;
+        let n = 3;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_executor_scc_budget(1);
+        let mut queue = DependencyGraph::new(process_id, shard_id, &config);
+        let time = RunTime;
+
+        // create a cycle with 3 commands
+        let dot_1 = Dot::new(1, 1);
+        let dot_2 = Dot::new(2, 1);
+        let dot_3 = Dot::new(3, 1);
+        let cmd = |rifl| {
+            Command::from(
+                rifl,
+                vec![(String::from("A"), KVOp::Put(String::new()))],
+            )
+        };
+        let cmd_1 = cmd(Rifl::new(1, 1));
+        let cmd_2 = cmd(Rifl::new(2, 1));
+        let cmd_3 = cmd(Rifl::new(3, 1));
+        let budget_exhaustions = |queue: &DependencyGraph| {
+            queue
+                .metrics()
+                .get_aggregated(ExecutorMetricsKind::SccBudgetExhaustions)
+                .cloned()
+        };
+
+        // add all commands: the search started by cmd 1 stops at the missing
+        // cmd 3, while the searches started by cmd 2 and cmd 3 are
+        // interrupted, since they'd need to visit a second vertex
+        queue.handle_add(dot_1, cmd_1, vec![dep(dot_3, shard_id)], &time);
+        queue.handle_add(dot_2, cmd_2, vec![dep(dot_1, shard_id)], &time);
+        queue.handle_add(dot_3, cmd_3, vec![dep(dot_2, shard_id)], &time);
+        assert!(queue.commands_to_execute().is_empty());
+        assert_eq!(budget_exhaustions(&queue), Some(2));
+
+        // the first cleanup resumes both searches with a budget of 2
+        // vertices, which is still not enough
+        queue.cleanup(&time);
+        assert!(queue.commands_to_execute().is_empty());
+        assert_eq!(budget_exhaustions(&queue), Some(4));
+
+        // the second cleanup resumes both searches with a budget of 4
+        // vertices, and thus the SCC is finally found
+        queue.cleanup(&time);
+        assert_eq!(queue.commands_to_execute().len(), 3);
+        assert_eq!(budget_exhaustions(&queue), Some(4));
+        assert!(queue.deferred.is_empty());
     }
 
     /// We have 5 commands by the same process (process A) that access the same
@@ -1319,8 +1878,13 @@ mod tests {
         // create ready commands counter and try to find an SCC
         let first_find = true;
         let mut ready_commands = 0;
-        let finder_info =
-            queue.find_scc(first_find, root_dot, &mut ready_commands, &time);
+        let finder_info = queue.find_scc(
+            first_find,
+            root_dot,
+            None,
+            &mut ready_commands,
+            &time,
+        );
 
         if let FinderInfo::MissingDependencies(
             to_be_executed,
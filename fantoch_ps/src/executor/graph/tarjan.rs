@@ -20,6 +20,8 @@ pub enum FinderResult {
     MissingDependencies(HashSet<Dependency>),
     NotPending,
     NotFound,
+    // the search visited more vertices than the budget allows
+    BudgetExhausted,
 }
 
 #[derive(Clone)]
@@ -31,6 +33,10 @@ pub struct TarjanSCCFinder {
     stack: Vec<Dot>,
     sccs: Vec<SCC>,
     missing_deps: HashSet<Dependency>,
+    // maximum number of vertices visited by each search (if any), and the
+    // number of vertices visited by the current search
+    budget: Option<usize>,
+    visits: usize,
 }
 
 impl TarjanSCCFinder {
@@ -48,9 +54,16 @@ impl TarjanSCCFinder {
             stack: Vec::new(),
             sccs: Vec::new(),
             missing_deps: HashSet::new(),
+            budget: None,
+            visits: 0,
         }
     }
 
+    /// Sets the maximum number of vertices visited by the next searches.
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+    }
+
     /// Returns a list with the SCCs found.
     #[must_use]
     pub fn sccs(&mut self) -> Vec<SCC> {
@@ -65,8 +78,9 @@ impl TarjanSCCFinder {
         vertex_index: &VertexIndex,
     ) -> (HashSet<Dot>, HashSet<Dependency>) {
         let _process_id = self.process_id;
-        // reset id
+        // reset id and number of visits
         self.id = 0;
+        self.visits = 0;
         // reset the id of each dot in the stack, while computing the set of
         // visited dots
         let mut visited = HashSet::new();
@@ -107,6 +121,19 @@ impl TarjanSCCFinder {
         scc_count: &mut usize,
         missing_deps_count: &mut usize,
     ) -> FinderResult {
+        // give up if this search already visited as many vertices as the
+        // budget allows
+        if let Some(budget) = self.budget {
+            if self.visits == budget {
+                debug!(
+                    "p{}: Finder::strong_connect budget exhausted at {:?}",
+                    self.process_id, dot
+                );
+                return FinderResult::BudgetExhausted;
+            }
+            self.visits += 1;
+        }
+
         // update id
         self.id += 1;
 
@@ -201,9 +228,11 @@ impl TarjanSCCFinder {
                         // deps of our dep
                         *missing_deps_count += dep_missing_deps_count;
 
-                        // if missing dependency, give up
-                        if let FinderResult::MissingDependencies(_) = result {
-                            return result;
+                        // if missing dependency or out of budget, give up
+                        match result {
+                            FinderResult::MissingDependencies(_)
+                            | FinderResult::BudgetExhausted => return result,
+                            _ => {}
                         }
 
                         // get guards again
@@ -3,11 +3,12 @@ use crate::protocol::common::pred::{CaesarDeps, Clock};
 use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::executor::{
-    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
+    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorMetricsKind,
+    ExecutorResult,
 };
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::kvs::KVStore;
-use fantoch::protocol::{CommittedAndExecuted, MessageIndex};
+use fantoch::protocol::{CommittedAndExecuted, MessageIndex, Violation};
 use fantoch::time::SysTime;
 use fantoch::trace;
 use serde::{Deserialize, Serialize};
@@ -29,7 +30,7 @@ impl Executor for PredecessorsExecutor {
 
     fn new(process_id: ProcessId, shard_id: ShardId, config: Config) -> Self {
         let graph = PredecessorsGraph::new(process_id, &config);
-        let store = KVStore::new(config.executor_monitor_execution_order());
+        let store = KVStore::from_config(&config, shard_id);
         let to_clients = Default::default();
         Self {
             process_id,
@@ -56,6 +57,21 @@ impl Executor for PredecessorsExecutor {
             );
             self.execute(cmd);
         }
+
+        // record the writes elided
+        let elided = self.store.flush_writes();
+        if elided > 0 {
+            self.graph
+                .metrics_mut()
+                .aggregate(ExecutorMetricsKind::ElidedWrites, elided);
+        }
+        // record the values expired
+        let expired = self.store.take_expired();
+        if expired > 0 {
+            self.graph
+                .metrics_mut()
+                .aggregate(ExecutorMetricsKind::ExpiredValues, expired);
+        }
     }
 
     fn to_clients(&mut self) -> Option<ExecutorResult> {
@@ -76,10 +92,22 @@ impl Executor for PredecessorsExecutor {
         Some(committed_and_executed)
     }
 
+    fn to_violations(&mut self) -> Option<Violation> {
+        self.graph.to_violations()
+    }
+
     fn parallel() -> bool {
         false
     }
 
+    fn sample_memory(&mut self) {
+        self.graph.sample_memory();
+    }
+
+    fn backlog(&self) -> Option<usize> {
+        Some(self.graph.backlog())
+    }
+
     fn metrics(&self) -> &ExecutorMetrics {
         &self.graph.metrics()
     }
@@ -87,6 +115,10 @@ impl Executor for PredecessorsExecutor {
     fn monitor(&self) -> Option<ExecutionOrderMonitor> {
         self.store.monitor().cloned()
     }
+
+    fn store(&self) -> &KVStore {
+        &self.store
+    }
 }
 
 impl PredecessorsExecutor {
@@ -5,6 +5,7 @@ use fantoch::id::{Dot, ProcessId};
 use fantoch::time::SysTime;
 use fantoch::HashSet;
 use std::cell::RefCell;
+use std::mem;
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
@@ -96,6 +97,25 @@ impl VertexIndex {
     pub fn remove(&mut self, dot: &Dot) -> Option<Vertex> {
         self.index.remove(dot).map(|cell| cell.into_inner())
     }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns the approximate memory (in bytes) used by the index. Only the
+    /// vertices and their dependencies are accounted for (and not, e.g., the
+    /// operations in each command).
+    pub fn memory(&self) -> usize {
+        self.index
+            .values()
+            .map(|vertex| {
+                let deps = vertex.borrow().deps.len();
+                mem::size_of::<Dot>()
+                    + mem::size_of::<RefCell<Vertex>>()
+                    + deps * mem::size_of::<Dot>()
+            })
+            .sum()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -119,4 +139,16 @@ impl PendingIndex {
     pub fn remove(&mut self, dep_dot: &Dot) -> HashSet<Dot> {
         self.index.remove(dep_dot).unwrap_or_default()
     }
+
+    /// Returns the approximate memory (in bytes) used by the index.
+    pub fn memory(&self) -> usize {
+        self.index
+            .values()
+            .map(|pending| {
+                mem::size_of::<Dot>()
+                    + mem::size_of::<HashSet<Dot>>()
+                    + pending.len() * mem::size_of::<Dot>()
+            })
+            .sum()
+    }
 }
@@ -15,7 +15,7 @@ use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::executor::{ExecutorMetrics, ExecutorMetricsKind};
 use fantoch::id::{Dot, ProcessId};
-use fantoch::protocol::CommittedAndExecuted;
+use fantoch::protocol::{CommittedAndExecuted, Violation};
 use fantoch::time::SysTime;
 use fantoch::util;
 use fantoch::{debug, trace};
@@ -41,6 +41,8 @@ pub struct PredecessorsGraph {
     new_executed_dots: Vec<Dot>,
     to_execute: VecDeque<Command>,
     execute_at_commit: bool,
+    // violations of the graph's invariants (e.g. a command added twice)
+    violations: Vec<Violation>,
 }
 
 impl PredecessorsGraph {
@@ -63,6 +65,7 @@ impl PredecessorsGraph {
         // create to execute
         let to_execute = VecDeque::new();
         let execute_at_commit = config.execute_at_commit();
+        let violations = Vec::new();
         PredecessorsGraph {
             process_id,
             executed_clock,
@@ -75,6 +78,7 @@ impl PredecessorsGraph {
             new_executed_dots,
             to_execute,
             execute_at_commit,
+            violations,
         }
     }
 
@@ -84,6 +88,12 @@ impl PredecessorsGraph {
         self.to_execute.pop_front()
     }
 
+    /// Returns a violation of the graph's invariants (if any).
+    #[must_use]
+    pub fn to_violations(&mut self) -> Option<Violation> {
+        self.violations.pop()
+    }
+
     #[cfg(test)]
     fn commands_to_execute(&mut self) -> VecDeque<Command> {
         std::mem::take(&mut self.to_execute)
@@ -100,6 +110,28 @@ impl PredecessorsGraph {
         &self.metrics
     }
 
+    fn metrics_mut(&mut self) -> &mut ExecutorMetrics {
+        &mut self.metrics
+    }
+
+    fn sample_memory(&mut self) {
+        self.metrics.collect(
+            ExecutorMetricsKind::VertexIndexMemory,
+            self.vertex_index.memory() as u64,
+        );
+        let pending_index_memory = self.phase_one_pending_index.memory()
+            + self.phase_two_pending_index.memory();
+        self.metrics.collect(
+            ExecutorMetricsKind::PendingIndexMemory,
+            pending_index_memory as u64,
+        );
+    }
+
+    /// Returns the number of commands committed but not yet executed.
+    pub fn backlog(&self) -> usize {
+        self.vertex_index.len()
+    }
+
     /// Add a new command.
     pub fn add(
         &mut self,
@@ -118,9 +150,32 @@ impl PredecessorsGraph {
             time.millis()
         );
 
+        // commands can't be added twice: if that happens, the command is
+        // ignored and the violation is reported
+        if self.committed_clock.contains(&dot.source(), dot.sequence()) {
+            let reason = format!(
+                "Predecessors::add tried to add already added {:?}",
+                dot
+            );
+            let state = format!(
+                "committed clock entry of p{}: {:?} | executed clock entry of p{}: {:?}",
+                dot.source(),
+                self.committed_clock.get(&dot.source()),
+                dot.source(),
+                self.executed_clock.get(&dot.source())
+            );
+            self.metrics.aggregate(ExecutorMetricsKind::Violations, 1);
+            self.violations.push(Violation::new(
+                self.process_id,
+                reason,
+                state,
+            ));
+            return;
+        }
+
         // mark dot as committed
         self.new_committed_dots += 1;
-        assert!(self.committed_clock.add(&dot.source(), dot.sequence()));
+        self.committed_clock.add(&dot.source(), dot.sequence());
 
         // we assume that commands to not depend on themselves
         assert!(!deps.contains(&dot));
@@ -355,9 +410,12 @@ impl PredecessorsGraph {
         // get command
         let (duration_ms, cmd) = vertex.into_command(time);
 
-        // save execution delay metric
-        self.metrics
-            .collect(ExecutorMetricsKind::ExecutionDelay, duration_ms);
+        // save execution delay metric (once per original command, if the
+        // command is a batch)
+        for _ in 0..cmd.batch_size() {
+            self.metrics
+                .collect(ExecutorMetricsKind::ExecutionDelay, duration_ms);
+        }
 
         // mark dot as executed and add command to commands to be executed
         self.execute(dot, cmd, time);
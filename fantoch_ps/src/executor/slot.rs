@@ -1,7 +1,8 @@
 use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::executor::{
-    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
+    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorMetricsKind,
+    ExecutorResult,
 };
 use fantoch::id::{ProcessId, ShardId};
 use fantoch::kvs::KVStore;
@@ -29,7 +30,7 @@ impl Executor for SlotExecutor {
     type ExecutionInfo = SlotExecutionInfo;
 
     fn new(_process_id: ProcessId, shard_id: ShardId, config: Config) -> Self {
-        let store = KVStore::new(config.executor_monitor_execution_order());
+        let store = KVStore::from_config(&config, shard_id);
         // the next slot to be executed is 1
         let next_slot = 1;
         // there's nothing to execute in the beginning
@@ -66,6 +67,23 @@ impl Executor for SlotExecutor {
             assert!(res.is_none());
             self.try_next_slot();
         }
+
+        // record the writes elided
+        let elided = self.store.flush_writes();
+        if elided > 0 {
+            self.metrics
+                .aggregate(ExecutorMetricsKind::ElidedWrites, elided);
+        }
+        // record the values expired
+        let expired = self.store.take_expired();
+        if expired > 0 {
+            self.metrics
+                .aggregate(ExecutorMetricsKind::ExpiredValues, expired);
+        }
+    }
+
+    fn backlog(&self) -> Option<usize> {
+        Some(self.to_execute.len())
     }
 
     fn to_clients(&mut self) -> Option<ExecutorResult> {
@@ -83,6 +101,10 @@ impl Executor for SlotExecutor {
     fn monitor(&self) -> Option<ExecutionOrderMonitor> {
         self.store.monitor().cloned()
     }
+
+    fn store(&self) -> &KVStore {
+        &self.store
+    }
 }
 
 impl SlotExecutor {
@@ -1,12 +1,13 @@
 use crate::executor::table::MultiVotesTable;
 use crate::protocol::common::table::VoteRange;
+use fantoch::command::KeyOps;
 use fantoch::config::Config;
 use fantoch::executor::{
-    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorResult,
-    MessageKey,
+    ExecutionOrderMonitor, Executor, ExecutorMetrics, ExecutorMetricsKind,
+    ExecutorResult, MessageKey,
 };
 use fantoch::id::{Dot, ProcessId, Rifl, ShardId};
-use fantoch::kvs::{KVOp, KVStore, Key};
+use fantoch::kvs::{KVError, KVOpResult, KVStore, Key, KeyRange};
 use fantoch::shared::SharedMap;
 use fantoch::time::SysTime;
 use fantoch::trace;
@@ -14,7 +15,9 @@ use fantoch::HashMap;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use std::mem;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct TableExecutor {
@@ -24,7 +27,7 @@ pub struct TableExecutor {
     table: MultiVotesTable,
     store: KVStore,
     metrics: ExecutorMetrics,
-    to_clients: VecDeque<ExecutorResult>,
+    to_clients: ToClients,
     to_executors: Vec<(ShardId, TableExecutionInfo)>,
     pending: HashMap<Key, PendingPerKey>,
     rifl_to_stable_count: Arc<SharedMap<Rifl, Mutex<u64>>>,
@@ -36,6 +39,17 @@ struct PendingPerKey {
     stable_shards_buffered: HashMap<Rifl, usize>,
 }
 
+impl PendingPerKey {
+    // Returns the approximate memory (in bytes) used by the commands pending
+    // on some key (not accounting for their operations).
+    fn memory(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.pending.len() * mem::size_of::<Pending>()
+            + self.stable_shards_buffered.len()
+                * (mem::size_of::<Rifl>() + mem::size_of::<usize>())
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Pending {
     rifl: Rifl,
@@ -44,7 +58,11 @@ pub struct Pending {
     shard_key_count: u64,
     // number of shards the key is not stable at yet
     missing_stable_shards: usize,
-    ops: Arc<Vec<KVOp>>,
+    ops: KeyOps,
+    // time (in millis) at which the command was added to the votes table
+    start_time_ms: u64,
+    // time (in millis) at which the command was issued
+    timestamp: u64,
 }
 
 impl Pending {
@@ -52,22 +70,38 @@ impl Pending {
         shard_id: ShardId,
         rifl: Rifl,
         shard_to_keys: Arc<HashMap<ShardId, Vec<Key>>>,
-        ops: Arc<Vec<KVOp>>,
+        ops: KeyOps,
     ) -> Self {
         let shard_key_count = shard_to_keys
             .get(&shard_id)
             .expect("my shard should be accessed by this command")
             .len() as u64;
         let missing_stable_shards = shard_to_keys.len();
+        let start_time_ms = 0;
+        let timestamp = 0;
         Self {
             rifl,
             shard_to_keys,
             shard_key_count,
             missing_stable_shards,
             ops,
+            start_time_ms,
+            timestamp,
         }
     }
 
+    /// Sets the time (in millis) at which the command was added to the votes
+    /// table.
+    pub fn set_start_time(&mut self, start_time_ms: u64) {
+        self.start_time_ms = start_time_ms;
+    }
+
+    /// Sets the time (in millis) at which the command was issued (see
+    /// `Command::timestamp`).
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = timestamp;
+    }
+
     pub fn single_key_command(&self) -> bool {
         // the command is single key if it accesses a single shard and the
         // number of keys accessed in that shard is one
@@ -86,9 +120,9 @@ impl Executor for TableExecutor {
             config.n(),
             stability_threshold,
         );
-        let store = KVStore::new(config.executor_monitor_execution_order());
+        let store = KVStore::from_config(&config, shard_id);
         let metrics = ExecutorMetrics::new();
-        let to_clients = Default::default();
+        let to_clients = ToClients::new(config.executor_latency_budget());
         let to_executors = Default::default();
         let pending = Default::default();
         let rifl_to_stable_count = Arc::new(SharedMap::new());
@@ -107,7 +141,15 @@ impl Executor for TableExecutor {
         }
     }
 
-    fn handle(&mut self, info: Self::ExecutionInfo, _time: &dyn SysTime) {
+    fn set_key_range(&mut self, key_range: KeyRange) {
+        self.store.set_key_range(key_range);
+    }
+
+    fn handle(&mut self, info: Self::ExecutionInfo, time: &dyn SysTime) {
+        // commands executed while handling this info are checked against the
+        // latency budget at this time
+        self.to_clients.set_now(time.millis());
+
         // handle each new info by updating the votes table and execute ready
         // commands
         match info {
@@ -118,33 +160,56 @@ impl Executor for TableExecutor {
                 rifl,
                 shard_to_keys,
                 ops,
+                timestamp,
                 votes,
             } => {
-                let pending =
+                let mut pending =
                     Pending::new(self.shard_id, rifl, shard_to_keys, ops);
+                pending.set_start_time(time.millis());
+                pending.set_timestamp(timestamp);
                 if self.execute_at_commit {
                     self.execute(key, pending);
                 } else {
                     let to_execute = self
                         .table
                         .add_attached_votes(dot, clock, &key, pending, votes);
-                    self.send_stable_or_execute(key, to_execute);
+                    self.send_stable_or_execute(key, to_execute, time);
                 }
             }
             TableExecutionInfo::DetachedVotes { key, votes } => {
                 if !self.execute_at_commit {
                     let to_execute = self.table.add_detached_votes(&key, votes);
-                    self.send_stable_or_execute(key, to_execute);
+                    self.send_stable_or_execute(key, to_execute, time);
                 }
             }
             TableExecutionInfo::StableAtShard { key, rifl } => {
                 self.handle_stable_msg(key, rifl)
             }
         }
+
+        // record the commands shed
+        let shed = self.to_clients.take_shed();
+        if shed > 0 {
+            self.metrics
+                .aggregate(ExecutorMetricsKind::ShedCommands, shed);
+        }
+
+        // record the writes elided
+        let elided = self.store.flush_writes();
+        if elided > 0 {
+            self.metrics
+                .aggregate(ExecutorMetricsKind::ElidedWrites, elided);
+        }
+        // record the values expired
+        let expired = self.store.take_expired();
+        if expired > 0 {
+            self.metrics
+                .aggregate(ExecutorMetricsKind::ExpiredValues, expired);
+        }
     }
 
     fn to_clients(&mut self) -> Option<ExecutorResult> {
-        self.to_clients.pop_front()
+        self.to_clients.results.pop_front()
     }
 
     fn to_executors(&mut self) -> Option<(ShardId, TableExecutionInfo)> {
@@ -155,6 +220,35 @@ impl Executor for TableExecutor {
         true
     }
 
+    fn supports_latency_budget() -> bool {
+        true
+    }
+
+    fn sample_memory(&mut self) {
+        self.metrics.collect(
+            ExecutorMetricsKind::VotesTableMemory,
+            self.table.memory() as u64,
+        );
+        let pending_memory = self
+            .pending
+            .iter()
+            .map(|(key, pending)| key.len() + pending.memory())
+            .sum::<usize>();
+        self.metrics
+            .collect(ExecutorMetricsKind::PendingMemory, pending_memory as u64);
+    }
+
+    fn backlog(&self) -> Option<usize> {
+        // commands are either waiting to be stable or, if stable, for the
+        // other shards accessed to also be stable
+        let pending = self
+            .pending
+            .values()
+            .map(|pending| pending.pending.len())
+            .sum::<usize>();
+        Some(self.table.len() + pending)
+    }
+
     fn metrics(&self) -> &ExecutorMetrics {
         &self.metrics
     }
@@ -162,6 +256,10 @@ impl Executor for TableExecutor {
     fn monitor(&self) -> Option<ExecutionOrderMonitor> {
         self.store.monitor().cloned()
     }
+
+    fn store(&self) -> &KVStore {
+        &self.store
+    }
 }
 
 impl TableExecutor {
@@ -234,10 +332,23 @@ impl TableExecutor {
         }
     }
 
-    fn send_stable_or_execute<I>(&mut self, key: Key, mut to_execute: I)
-    where
+    fn send_stable_or_execute<I>(
+        &mut self,
+        key: Key,
+        to_execute: I,
+        time: &dyn SysTime,
+    ) where
         I: Iterator<Item = Pending>,
     {
+        // save the stability delay of each command that is now stable at this
+        // key, i.e. how long it waited in this key's votes table
+        let now = time.millis();
+        let metrics = &mut self.metrics;
+        let mut to_execute = to_execute.inspect(|pending| {
+            let delay = now.saturating_sub(pending.start_time_ms);
+            metrics.collect(ExecutorMetricsKind::StabilityDelay, delay);
+        });
+
         let pending_per_key = self.pending.entry(key.clone()).or_default();
         if !pending_per_key.pending.is_empty() {
             // if there's already commmands pending at this key, then no
@@ -281,7 +392,7 @@ impl TableExecutor {
         key: &Key,
         mut pending: Pending,
         store: &mut KVStore,
-        to_clients: &mut VecDeque<ExecutorResult>,
+        to_clients: &mut ToClients,
         to_executors: &mut Vec<(ShardId, TableExecutionInfo)>,
         stable_shards_buffered: &mut HashMap<Rifl, usize>,
         rifl_to_stable_count: &Arc<SharedMap<Rifl, Mutex<u64>>>,
@@ -366,17 +477,67 @@ impl TableExecutor {
         key: Key,
         stable: Pending,
         store: &mut KVStore,
-        to_clients: &mut VecDeque<ExecutorResult>,
+        to_clients: &mut ToClients,
     ) {
-        // take the ops inside the arc if we're the last with a reference to it
-        // (otherwise, clone them)
+        // take the ops (decoding them, if needed)
         let rifl = stable.rifl;
-        let ops = stable.ops;
-        let ops =
-            Arc::try_unwrap(ops).unwrap_or_else(|ops| ops.as_ref().clone());
+        let ops = stable.ops.into_ops();
         // execute ops in the `KVStore`
-        let partial_results = store.execute(&key, ops, rifl);
-        to_clients.push_back(ExecutorResult::new(rifl, key, partial_results));
+        let execution = store.execute(&key, ops, rifl, stable.timestamp);
+        to_clients.push(rifl, key, execution, stable.start_time_ms);
+    }
+}
+
+#[derive(Clone)]
+struct ToClients {
+    results: VecDeque<ExecutorResult>,
+    // commands whose commit-to-execution delay (in millis) exceeds this
+    // budget are shed
+    latency_budget_ms: Option<u64>,
+    // time (in millis) at which the current execution info is being handled
+    now_ms: u64,
+    // number of commands shed not yet recorded in the metrics
+    shed: u64,
+}
+
+impl ToClients {
+    fn new(latency_budget: Option<Duration>) -> Self {
+        Self {
+            results: VecDeque::new(),
+            latency_budget_ms: latency_budget
+                .map(|budget| budget.as_millis() as u64),
+            now_ms: 0,
+            shed: 0,
+        }
+    }
+
+    fn set_now(&mut self, now_ms: u64) {
+        self.now_ms = now_ms;
+    }
+
+    fn take_shed(&mut self) -> u64 {
+        std::mem::take(&mut self.shed)
+    }
+
+    fn push(
+        &mut self,
+        rifl: Rifl,
+        key: Key,
+        execution: Result<Vec<KVOpResult>, KVError>,
+        start_time_ms: u64,
+    ) {
+        // the command has been executed (so that replicas don't diverge), but
+        // if it took longer than the budget, its results are replaced by an
+        // error
+        let delay = self.now_ms.saturating_sub(start_time_ms);
+        let result = match self.latency_budget_ms {
+            Some(budget) if delay > budget => {
+                self.shed += 1;
+                ExecutorResult::shed(rifl, key)
+            }
+            _ => ExecutorResult::from_execution(rifl, key, execution),
+        };
+        self.results.push_back(result);
     }
 }
 
@@ -388,7 +549,8 @@ pub enum TableExecutionInfo {
         key: Key,
         rifl: Rifl,
         shard_to_keys: Arc<HashMap<ShardId, Vec<Key>>>,
-        ops: Arc<Vec<KVOp>>,
+        ops: KeyOps,
+        timestamp: u64,
         votes: Vec<VoteRange>,
     },
     DetachedVotes {
@@ -408,7 +570,8 @@ impl TableExecutionInfo {
         key: Key,
         rifl: Rifl,
         shard_to_keys: Arc<HashMap<ShardId, Vec<Key>>>,
-        ops: Arc<Vec<KVOp>>,
+        ops: KeyOps,
+        timestamp: u64,
         votes: Vec<VoteRange>,
     ) -> Self {
         Self::AttachedVotes {
@@ -418,6 +581,7 @@ impl TableExecutionInfo {
             rifl,
             shard_to_keys,
             ops,
+            timestamp,
             votes,
         }
     }
@@ -440,3 +604,40 @@ impl MessageKey for TableExecutionInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_clients_latency_budget() {
+        let key = String::from("A");
+        let rifl_a = Rifl::new(1, 1);
+        let rifl_b = Rifl::new(1, 2);
+
+        // without a budget, commands are never shed
+        let mut to_clients = ToClients::new(None);
+        to_clients.set_now(1000);
+        to_clients.push(rifl_a, key.clone(), Ok(vec![None]), 0);
+        assert_eq!(to_clients.take_shed(), 0);
+        assert!(!to_clients.results.pop_front().unwrap().shed);
+
+        // with a budget of 10ms, only commands that took longer are shed
+        let budget = Duration::from_millis(10);
+        let mut to_clients = ToClients::new(Some(budget));
+        to_clients.set_now(100);
+        to_clients.push(rifl_a, key.clone(), Ok(vec![None]), 90);
+        to_clients.push(rifl_b, key, Ok(vec![None]), 89);
+        assert_eq!(to_clients.take_shed(), 1);
+        assert_eq!(to_clients.take_shed(), 0);
+
+        let result = to_clients.results.pop_front().unwrap();
+        assert_eq!(result.rifl, rifl_a);
+        assert!(!result.shed);
+        assert_eq!(result.partial_results, vec![None]);
+        let result = to_clients.results.pop_front().unwrap();
+        assert_eq!(result.rifl, rifl_b);
+        assert!(result.shed);
+        assert!(result.partial_results.is_empty());
+    }
+}
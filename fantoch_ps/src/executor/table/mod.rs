@@ -75,6 +75,18 @@ impl MultiVotesTable {
         })
     }
 
+    /// Returns the number of commands not yet stable.
+    pub fn len(&self) -> usize {
+        self.tables.values().map(|table| table.ops.len()).sum()
+    }
+
+    /// Returns the approximate memory (in bytes) used by the votes tables.
+    /// The votes themselves and the operations of each command are not
+    /// accounted for.
+    pub fn memory(&self) -> usize {
+        self.tables.values().map(VotesTable::memory).sum()
+    }
+
     // Generic function to be used when updating some votes table.
     #[must_use]
     fn update_table<F, I>(&mut self, key: &Key, update: F) -> I
@@ -113,7 +125,16 @@ struct VotesTable {
     // this buffer saves us always allocating a vector when computing the
     // stable clock (see `stable_clock`)
     frontiers_buffer: Vec<u64>,
+    // stability frontier of this key: the highest clock that is stable at
+    // this key; it can only advance when the votes of some process at (or
+    // behind) it advance, and so it's only recomputed in that case (see
+    // `add_detached_votes`)
+    stable_clock: u64,
+    stable_clock_outdated: bool,
     ops: BTreeMap<SortId, Pending>,
+    // highest sort id that has been stable in this table
+    #[cfg(feature = "strict-invariants")]
+    last_stable: Option<SortId>,
 }
 
 impl VotesTable {
@@ -134,10 +155,22 @@ impl VotesTable {
             stability_threshold,
             votes_clock,
             frontiers_buffer,
+            stable_clock: 0,
+            stable_clock_outdated: false,
             ops: BTreeMap::new(),
+            #[cfg(feature = "strict-invariants")]
+            last_stable: None,
         }
     }
 
+    fn memory(&self) -> usize {
+        self.key.len()
+            + mem::size_of::<Self>()
+            + self.frontiers_buffer.capacity() * mem::size_of::<u64>()
+            + self.ops.len()
+                * (mem::size_of::<SortId>() + mem::size_of::<Pending>())
+    }
+
     fn add_attached_votes(
         &mut self,
         dot: Dot,
@@ -159,6 +192,18 @@ impl VotesTable {
             sort_id
         );
 
+        // check that the op is not ordered before ops already stable (and
+        // thus possibly executed)
+        #[cfg(feature = "strict-invariants")]
+        assert!(
+            self.last_stable < Some(sort_id),
+            "p{}: key={} Table::add {:?} is ordered before stable {:?}",
+            self.process_id,
+            self.key,
+            sort_id,
+            self.last_stable
+        );
+
         // add op to the sorted list of ops to be executed
         let res = self.ops.insert(sort_id, pending);
         // and check there was nothing there for this exact same position
@@ -176,14 +221,25 @@ impl VotesTable {
             votes
         );
         votes.into_iter().for_each(|vote_range| {
+            let voter = vote_range.voter();
+            let frontier_before = self.frontier(&voter);
             // assert there's at least one new vote
             assert!(self.votes_clock.add_range(
-                &vote_range.voter(),
+                &voter,
                 vote_range.start(),
                 vote_range.end()
             ));
             // assert that the clock size didn't change
             assert_eq!(self.votes_clock.len(), self.n);
+
+            // the stability frontier of this key only needs to be recomputed
+            // if the frontier of a process at (or behind) it advanced; votes
+            // from processes ahead of it don't change it
+            if frontier_before <= self.stable_clock
+                && self.frontier(&voter) > frontier_before
+            {
+                self.stable_clock_outdated = true;
+            }
         });
         trace!(
             "p{}: key={} Table::add_votes votes_clock: {:?}",
@@ -194,6 +250,22 @@ impl VotesTable {
     }
 
     fn stable_ops(&mut self) -> impl Iterator<Item = Pending> {
+        // stability is tracked per key: if there are no ops pending on this
+        // key, there's nothing to execute, and thus there's no need to compute
+        // the stable clock (votes were still recorded in `self.votes_clock`,
+        // so the stable clock will be computed once some op is added)
+        let stable = if self.ops.is_empty() {
+            BTreeMap::new()
+        } else {
+            self.split_stable_ops()
+        };
+
+        // return stable ops
+        stable.into_iter().map(|(_, pending)| pending)
+    }
+
+    // Removes from `self.ops` the ops that are stable, returning them.
+    fn split_stable_ops(&mut self) -> BTreeMap<SortId, Pending> {
         // compute *next* stable sort id:
         // - if clock 10 is stable, then we can execute all ops with an id
         //   smaller than `(11,0)`
@@ -235,12 +307,35 @@ impl VotesTable {
             stable.iter().map(|((_, dot), _)| *dot).collect::<Vec<_>>()
         );
 
-        // return stable ops
-        stable.into_iter().map(|(_, pending)| pending)
+        // check that stable ops are executed in increasing clock order
+        #[cfg(feature = "strict-invariants")]
+        if let Some(first) = stable.keys().next() {
+            assert!(self.last_stable < Some(*first));
+            self.last_stable = stable.keys().next_back().cloned();
+        }
+
+        stable
     }
 
-    // Computes the (potentially) new stable clock in this table.
+    // Returns the frontier of the votes by `voter` on this key.
+    fn frontier(&self, voter: &ProcessId) -> u64 {
+        self.votes_clock
+            .get(voter)
+            .expect("voter should exist in the votes clock")
+            .frontier()
+    }
+
+    // Returns the stability frontier of this key, recomputing it if needed.
     fn stable_clock(&mut self) -> u64 {
+        if self.stable_clock_outdated {
+            self.stable_clock = self.compute_stable_clock();
+            self.stable_clock_outdated = false;
+        }
+        self.stable_clock
+    }
+
+    // Computes the (potentially) new stable clock in this table.
+    fn compute_stable_clock(&mut self) -> u64 {
         // NOTE: we don't use `self.votes_clocks.frontier_threshold` function in
         // order to save us an allocation
         let clock_size = self.votes_clock.len();
@@ -269,12 +364,52 @@ impl VotesTable {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use fantoch::command::DEFAULT_SHARD_ID;
+    use fantoch::command::{KeyOps, DEFAULT_SHARD_ID};
     use fantoch::id::{ClientId, Rifl};
     use fantoch::kvs::KVOp;
     use permutator::Permutation;
     use std::sync::Arc;
 
+    #[test]
+    fn multi_votes_table_memory() {
+        let n = 3;
+        let stability_threshold = 2;
+        let mut table =
+            MultiVotesTable::new(1, DEFAULT_SHARD_ID, n, stability_threshold);
+        // there's nothing in the table
+        assert_eq!(table.memory(), 0);
+
+        let key = String::from("KEY");
+        let rifl = Rifl::new(1, 1);
+        let shard_to_keys = Arc::new(
+            vec![(DEFAULT_SHARD_ID, vec![key.clone()])]
+                .into_iter()
+                .collect(),
+        );
+        let ops = KeyOps::new(vec![KVOp::Put(String::from("A"))]);
+        let pending = Pending::new(DEFAULT_SHARD_ID, rifl, shard_to_keys, ops);
+
+        // add a command with clock 2 and the votes of a single process: the
+        // command is not stable
+        let dot = Dot::new(1, 1);
+        let votes = vec![VoteRange::new(1, 1, 2)];
+        let stable: Vec<_> = table
+            .add_attached_votes(dot, 2, &key, pending, votes)
+            .collect();
+        assert!(stable.is_empty());
+        let with_pending = table.memory();
+        assert!(with_pending > 0);
+
+        // once the command is stable, it's no longer accounted for
+        let votes = vec![VoteRange::new(2, 1, 2)];
+        let stable: Vec<_> = table.add_detached_votes(&key, votes).collect();
+        assert_eq!(stable.len(), 1);
+        assert_eq!(
+            with_pending - table.memory(),
+            mem::size_of::<SortId>() + mem::size_of::<Pending>()
+        );
+    }
+
     #[test]
     fn votes_table_majority_quorums() {
         // process ids
@@ -306,7 +441,7 @@ mod tests {
                     .into_iter()
                     .collect(),
             );
-            let ops = Arc::new(vec![KVOp::Put(String::from(value))]);
+            let ops = KeyOps::new(vec![KVOp::Put(String::from(value))]);
             Pending::new(DEFAULT_SHARD_ID, rifl, shard_to_keys, ops)
         };
 
@@ -501,7 +636,7 @@ mod tests {
                     .into_iter()
                     .collect(),
             );
-            let ops = Arc::new(vec![KVOp::Put(String::from(value))]);
+            let ops = KeyOps::new(vec![KVOp::Put(String::from(value))]);
             Pending::new(DEFAULT_SHARD_ID, rifl, shard_to_keys, ops)
         };
 
@@ -712,4 +847,96 @@ mod tests {
         assert_eq!(stable_clock(&mut table, &key_a), 1);
         assert_eq!(stable_clock(&mut table, &key_b), 1);
     }
+
+    #[test]
+    fn per_key_stability_frontiers() {
+        let shard_id = 0;
+
+        // create table
+        let process_id = 1;
+        let n = 5;
+        let stability_threshold = 3;
+        let mut table =
+            MultiVotesTable::new(process_id, shard_id, n, stability_threshold);
+
+        // create keys
+        let key_a = String::from("A");
+        let key_b = String::from("B");
+        let pending = |key: &Key, rifl: Rifl| -> Pending {
+            let shard_to_keys = Arc::new(
+                vec![(DEFAULT_SHARD_ID, vec![key.clone()])]
+                    .into_iter()
+                    .collect(),
+            );
+            let ops = KeyOps::new(vec![KVOp::Put(String::from("V"))]);
+            Pending::new(DEFAULT_SHARD_ID, rifl, shard_to_keys, ops)
+        };
+
+        // p3 is slow: its votes on key A are still in flight, but its votes on
+        // key B have already arrived
+        let stable = table
+            .add_detached_votes(&key_b, vec![VoteRange::new(3, 1, 2)])
+            .collect::<Vec<_>>();
+        assert!(stable.is_empty());
+
+        // a command on each key, both with clock 2 and voted by p1 and p2
+        let votes = || vec![VoteRange::new(1, 1, 2), VoteRange::new(2, 1, 2)];
+        let a_rifl = Rifl::new(1, 1);
+        let stable = table
+            .add_attached_votes(
+                Dot::new(1, 1),
+                2,
+                &key_a,
+                pending(&key_a, a_rifl),
+                votes(),
+            )
+            .collect::<Vec<_>>();
+        // the command on key A waits for the votes of p3
+        assert!(stable.is_empty());
+        let b_rifl = Rifl::new(2, 1);
+        let stable = table
+            .add_attached_votes(
+                Dot::new(2, 1),
+                2,
+                &key_b,
+                pending(&key_b, b_rifl),
+                votes(),
+            )
+            .collect::<Vec<_>>();
+        // while the one on key B is stable right away
+        assert_eq!(stable, vec![pending(&key_b, b_rifl)]);
+        assert_eq!(table.tables[&key_a].stable_clock, 0);
+        assert_eq!(table.tables[&key_b].stable_clock, 2);
+
+        // p1 and p2 move ahead of the frontier of key B, which stays the same
+        // as p3 is now behind them
+        let stable = table
+            .add_detached_votes(
+                &key_b,
+                vec![VoteRange::new(1, 3, 4), VoteRange::new(2, 3, 4)],
+            )
+            .collect::<Vec<_>>();
+        assert!(stable.is_empty());
+        // (since there are no ops on key B, its frontier is only recomputed
+        // when needed)
+        assert!(table.tables[&key_b].stable_clock_outdated);
+        let table_b = table.tables.get_mut(&key_b).unwrap();
+        assert_eq!(table_b.stable_clock(), 2);
+
+        // votes from processes ahead of the frontier of key B don't change it,
+        // and so it's not recomputed
+        let stable = table
+            .add_detached_votes(&key_b, vec![VoteRange::new(1, 5, 5)])
+            .collect::<Vec<_>>();
+        assert!(stable.is_empty());
+        assert!(!table.tables[&key_b].stable_clock_outdated);
+        assert_eq!(table.tables[&key_b].stable_clock, 2);
+
+        // once the votes of p3 on key A arrive, the command on key A is stable
+        let stable = table
+            .add_detached_votes(&key_a, vec![VoteRange::new(3, 1, 2)])
+            .collect::<Vec<_>>();
+        assert_eq!(stable, vec![pending(&key_a, a_rifl)]);
+        assert_eq!(table.tables[&key_a].stable_clock, 2);
+    }
 }
@@ -8,11 +8,12 @@ use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    AckCoalescer, Action, BaseProcess, Info, MessageIndex, MessagePriority,
+    Protocol, ProtocolMetrics, ProtocolMetricsKind, SequentialCommandsInfo,
+    StateSizes, VClockGCTrack, ANTI_ENTROPY_MAX_REPAIRS,
 };
 use fantoch::time::SysTime;
-use fantoch::{singleton, trace};
+use fantoch::{flow, singleton, trace};
 use fantoch::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -27,6 +28,7 @@ pub struct Atlas<KD: KeyDeps> {
     key_deps: KD,
     cmds: SequentialCommandsInfo<AtlasInfo>,
     gc_track: VClockGCTrack,
+    acks: AckCoalescer<Message>,
     to_processes: Vec<Action<Self>>,
     to_executors: Vec<GraphExecutionInfo>,
     // set of processes in my shard
@@ -68,6 +70,7 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
             write_quorum_size,
         );
         let gc_track = VClockGCTrack::new(process_id, shard_id, config.n());
+        let acks = AckCoalescer::new(process_id, &config);
         let to_processes = Vec::new();
         let to_executors = Vec::new();
         let shard_processes =
@@ -80,6 +83,7 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
             key_deps,
             cmds,
             gc_track,
+            acks,
             to_processes,
             to_executors,
             shard_processes,
@@ -87,12 +91,25 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
         };
 
         // create periodic events
-        let events = if let Some(interval) = config.gc_interval() {
+        let mut events = if let Some(interval) = config.gc_interval() {
             vec![(PeriodicEvent::GarbageCollection, interval)]
         } else {
             vec![]
         };
 
+        // maybe create flush acks periodic event
+        if let Some(interval) = config.ack_coalescing_interval() {
+            events.push((PeriodicEvent::FlushAcks, interval));
+        }
+
+        // maybe create anti-entropy periodic event (which relies on the
+        // committed clocks exchanged for garbage collection)
+        if let Some(interval) = config.anti_entropy_interval() {
+            if config.gc_interval().is_some() {
+                events.push((PeriodicEvent::AntiEntropy, interval));
+            }
+        }
+
         // return both
         (protocol, events)
     }
@@ -141,6 +158,11 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
             Message::MCollectAck { dot, deps } => {
                 self.handle_mcollectack(from, dot, deps, time)
             }
+            Message::MCollectAckBatch { acks } => {
+                for ack in acks {
+                    self.handle(from, from_shard_id, ack, time);
+                }
+            }
             Message::MCommit { dot, value } => {
                 self.handle_mcommit(from, dot, value, time)
             }
@@ -170,6 +192,13 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
             Message::MStable { stable } => {
                 self.handle_mstable(from, stable, time)
             }
+            // Anti-entropy messages
+            Message::MRepair { dot, targets } => {
+                self.handle_mrepair(from, dot, targets, time)
+            }
+            Message::MRepairCommit { dot, cmd, value } => {
+                self.handle_mrepair_commit(from, dot, cmd, value, time)
+            }
         }
     }
 
@@ -179,6 +208,8 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
             PeriodicEvent::GarbageCollection => {
                 self.handle_event_garbage_collection(time)
             }
+            PeriodicEvent::FlushAcks => self.handle_event_flush_acks(time),
+            PeriodicEvent::AntiEntropy => self.handle_event_anti_entropy(time),
         }
     }
 
@@ -200,9 +231,22 @@ impl<KD: KeyDeps> Protocol for Atlas<KD> {
         true
     }
 
+    fn message_fingerprint() -> u64 {
+        super::message_fingerprints::ATLAS
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
+
+    fn state_sizes(&self) -> StateSizes {
+        let mut sizes = StateSizes::new();
+        sizes.insert("dots_in_flight", self.cmds.hot_len());
+        sizes.insert("dots_committed", self.cmds.cold_len());
+        sizes.insert("key_deps", self.key_deps.key_count());
+        sizes.insert("buffered_commits", self.buffered_commits.len());
+        sizes
+    }
 }
 
 impl<KD: KeyDeps> Atlas<KD> {
@@ -230,6 +274,8 @@ impl<KD: KeyDeps> Atlas<KD> {
 
         // compute its deps
         let deps = self.key_deps.add_cmd(dot, &cmd, None);
+        self.key_deps.take_lock_stats().record(&mut self.bp);
+        self.key_deps.record_commuted_deps(&mut self.bp);
 
         // create `MCollect` and target
         let mcollect = Message::MCollect {
@@ -266,8 +312,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // discard message if no longer in START
         if info.status != Status::START {
@@ -300,7 +350,10 @@ impl<KD: KeyDeps> Atlas<KD> {
             remote_deps
         } else {
             // otherwise, compute deps with the remote deps as past
-            self.key_deps.add_cmd(dot, &cmd, Some(remote_deps))
+            let deps = self.key_deps.add_cmd(dot, &cmd, Some(remote_deps));
+            self.key_deps.take_lock_stats().record(&mut self.bp);
+            self.key_deps.record_commuted_deps(&mut self.bp);
+            deps
         };
 
         // update command info
@@ -311,15 +364,17 @@ impl<KD: KeyDeps> Atlas<KD> {
         let value = ConsensusValue::with(deps.clone());
         assert!(info.synod.set_if_not_accepted(|| value));
 
-        // create `MCollectAck` and target
+        // create `MCollectAck` and maybe coalesce it with other acks
         let mcollectack = Message::MCollectAck { dot, deps };
-        let target = singleton![from];
+        if let Some(mcollectack) = self.acks.coalesce(from, mcollectack) {
+            let target = singleton![from];
 
-        // save new action
-        self.to_processes.push(Action::ToSend {
-            target,
-            msg: mcollectack,
-        });
+            // save new action
+            self.to_processes.push(Action::ToSend {
+                target,
+                msg: mcollectack,
+            });
+        }
     }
 
     fn handle_mcollectack(
@@ -338,8 +393,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         if info.status != Status::COLLECT {
             // do nothing if we're no longer COLLECT
@@ -356,6 +415,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             let (all_deps, equal_to_union) =
                 info.quorum_deps.check_threshold_union(self.bp.config.f());
 
+            // commands with dependencies beyond the dependency horizon take
+            // the slow path, even if the fast path condition holds
+            let equal_to_union =
+                equal_to_union && !self.beyond_horizon(&all_deps);
+            let info = self.cmds.get(dot);
+
             // create consensus value
             let value = ConsensusValue::with(all_deps);
 
@@ -390,6 +455,25 @@ impl<KD: KeyDeps> Atlas<KD> {
         }
     }
 
+    // Checks whether any of the dependencies is beyond the dependency horizon
+    // (if one is set), recording it in the metrics if so.
+    fn beyond_horizon(&mut self, deps: &HashSet<Dependency>) -> bool {
+        let horizon = match self.bp.config.dependency_horizon() {
+            Some(horizon) => horizon,
+            None => return false,
+        };
+        let beyond_horizon = deps
+            .iter()
+            .any(|dep| self.cmds.beyond_horizon(&dep.dot, horizon));
+        if beyond_horizon {
+            self.bp.aggregate_metric(
+                ProtocolMetricsKind::DependencyHorizonSlowPath,
+                1,
+            );
+        }
+        beyond_horizon
+    }
+
     fn handle_mcommit(
         &mut self,
         from: ProcessId,
@@ -405,8 +489,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         if info.status == Status::START {
             // TODO we missed the `MCollect` message and should try to recover
@@ -423,6 +511,22 @@ impl<KD: KeyDeps> Atlas<KD> {
             return;
         }
 
+        // check that all processes commit the same dependencies
+        #[cfg(feature = "strict-invariants")]
+        fantoch::sim::invariants::committed(
+            self.bp.process_id,
+            self.bp.shard_id,
+            dot,
+            &value.deps,
+        );
+
+        flow!(
+            process = self.bp.process_id,
+            dot = ?dot,
+            phase = "commit",
+            time = _time.micros()
+        );
+
         // check it's not a noop
         assert_eq!(
             value.is_noop, false,
@@ -457,6 +561,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             self.to_processes.push(Action::ToForward {
                 msg: Message::MCommitDot { dot },
             });
+            // the command info is only needed after the commit to repair the
+            // command; if anti-entropy is not running, keep only what's needed
+            // to garbage collect the dot
+            if self.bp.config.anti_entropy_interval().is_none() {
+                self.cmds.commit(dot);
+            }
         } else {
             // not running gc, so remove the dot info now
             self.cmds.gc_single(dot);
@@ -480,8 +590,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // compute message: that can either be nothing, an ack or an mcommit
         let msg = match info
@@ -527,8 +641,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // compute message: that can either be nothing or an mcommit
         match info.synod.handle(from, SynodMessage::MAccepted(ballot)) {
@@ -563,8 +681,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         let shard_count = info.cmd.as_ref().unwrap().shard_count();
         let add_shards_commits_info =
@@ -606,8 +728,12 @@ impl<KD: KeyDeps> Atlas<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // nothing else to extract
         let extract_mcommit_extra_data = |_| ();
@@ -685,15 +811,22 @@ impl<KD: KeyDeps> Atlas<KD> {
         self.bp.stable(stable_count);
     }
 
-    fn handle_event_garbage_collection(&mut self, _time: &dyn SysTime) {
+    fn handle_event_garbage_collection(&mut self, time: &dyn SysTime) {
         trace!(
             "p{}: PeriodicEvent::GarbageCollection | time={}",
             self.id(),
-            _time.micros()
+            time.micros()
         );
 
-        // retrieve the committed clock
-        let committed = self.gc_track.clock().frontier();
+        // sample the stability lag
+        let lag = self.gc_track.stability_lag();
+        self.bp.stability_lag(lag, time);
+
+        // retrieve the entries of the committed clock that changed since the
+        // last garbage collection; this message is sent even if no entry
+        // changed, as receiving it is what triggers the computation of new
+        // stable dots
+        let committed = self.gc_track.committed_delta();
 
         // save new action
         self.to_processes.push(Action::ToSend {
@@ -702,6 +835,115 @@ impl<KD: KeyDeps> Atlas<KD> {
         });
     }
 
+    fn handle_mrepair(
+        &mut self,
+        from: ProcessId,
+        dot: Dot,
+        targets: HashSet<ProcessId>,
+        _time: &dyn SysTime,
+    ) {
+        trace!(
+            "p{}: MRepair({:?}, {:?}) from {} | time={}",
+            self.id(),
+            dot,
+            targets,
+            from,
+            _time.micros()
+        );
+        assert_eq!(from, self.bp.process_id);
+
+        // if the command info no longer exists, then the command is stable
+        // and there's nothing to repair
+        let info = if let Some(info) = self.cmds.find(&dot) {
+            info
+        } else {
+            return;
+        };
+        assert_eq!(
+            info.status,
+            Status::COMMIT,
+            "only committed commands can be repaired"
+        );
+
+        // re-ship the command along with its committed value
+        let cmd = info.cmd.clone().expect("there should be a command payload");
+        let value = info.synod.value().clone();
+        self.bp.aggregate_metric(
+            ProtocolMetricsKind::Repairs,
+            targets.len() as u64,
+        );
+        self.to_processes.push(Action::ToSend {
+            target: targets,
+            msg: Message::MRepairCommit { dot, cmd, value },
+        });
+    }
+
+    fn handle_mrepair_commit(
+        &mut self,
+        from: ProcessId,
+        dot: Dot,
+        cmd: Command,
+        value: ConsensusValue,
+        time: &dyn SysTime,
+    ) {
+        trace!(
+            "p{}: MRepairCommit({:?}, {:?}, {:?}) from {} | time={}",
+            self.id(),
+            dot,
+            cmd,
+            value.deps,
+            from,
+            time.micros()
+        );
+
+        // ignore the repair if the command is already stable (in which case
+        // its info has been garbage collected)
+        if self.cmds.is_stable(&dot) {
+            return;
+        }
+
+        // if we missed the `MCollect`, save the payload now; this makes any
+        // buffered commit notification unnecessary
+        let info = self.cmds.get(dot);
+        if info.status == Status::START {
+            info.status = Status::PAYLOAD;
+            info.cmd = Some(cmd);
+            self.buffered_commits.remove(&dot);
+        }
+
+        // handle the commit (which is a noop if we have already committed)
+        self.handle_mcommit(from, dot, value, time);
+    }
+
+    fn handle_event_anti_entropy(&mut self, _time: &dyn SysTime) {
+        trace!(
+            "p{}: PeriodicEvent::AntiEntropy | time={}",
+            self.id(),
+            _time.micros()
+        );
+
+        // compute the processes missing each committed command
+        let mut missing = HashMap::new();
+        for (process_id, dots) in
+            self.gc_track.missing(ANTI_ENTROPY_MAX_REPAIRS)
+        {
+            for dot in dots {
+                missing
+                    .entry(dot)
+                    .or_insert_with(HashSet::new)
+                    .insert(process_id);
+            }
+        }
+
+        // forward each command to the worker responsible for it, which will
+        // then re-ship it
+        for (dot, targets) in missing {
+            self.to_processes.push(Action::ToForward {
+                msg: Message::MRepair { dot, targets },
+            });
+        }
+    }
+
     fn mcommit_actions(
         bp: &BaseProcess,
         info: &mut AtlasInfo,
@@ -733,6 +975,27 @@ impl<KD: KeyDeps> Atlas<KD> {
         )
     }
 
+    fn handle_event_flush_acks(&mut self, _time: &dyn SysTime) {
+        trace!(
+            "p{}: PeriodicEvent::FlushAcks | time={}",
+            self.id(),
+            _time.micros()
+        );
+
+        for (target, mut acks) in self.acks.flush() {
+            // only create a batch if there's more than one ack
+            let msg = if acks.len() == 1 {
+                acks.pop().expect("there should be one ack")
+            } else {
+                Message::MCollectAckBatch { acks }
+            };
+            self.to_processes.push(Action::ToSend {
+                target: singleton![target],
+                msg,
+            });
+        }
+    }
+
     fn gc_running(&self) -> bool {
         self.bp.config.gc_interval().is_some()
     }
@@ -815,6 +1078,9 @@ pub enum Message {
         dot: Dot,
         deps: HashSet<Dependency>,
     },
+    MCollectAckBatch {
+        acks: Vec<Message>,
+    },
     MCommit {
         dot: Dot,
         value: ConsensusValue,
@@ -851,6 +1117,16 @@ pub enum Message {
     MStable {
         stable: Vec<(ProcessId, u64, u64)>,
     },
+    // Anti-entropy messages
+    MRepair {
+        dot: Dot,
+        targets: HashSet<ProcessId>,
+    },
+    MRepairCommit {
+        dot: Dot,
+        cmd: Command,
+        value: ConsensusValue,
+    },
 }
 
 impl MessageIndex for Message {
@@ -862,6 +1138,11 @@ impl MessageIndex for Message {
             // Protocol messages
             Self::MCollect { dot, .. } => worker_dot_index_shift(&dot),
             Self::MCollectAck { dot, .. } => worker_dot_index_shift(&dot),
+            // all acks in a batch were coalesced by the same worker, and thus
+            // they're all handled by the same worker
+            Self::MCollectAckBatch { acks } => {
+                acks.first().and_then(MessageIndex::index)
+            }
             Self::MCommit { dot, .. } => worker_dot_index_shift(&dot),
             Self::MConsensus { dot, .. } => worker_dot_index_shift(&dot),
             Self::MConsensusAck { dot, .. } => worker_dot_index_shift(&dot),
@@ -877,6 +1158,23 @@ impl MessageIndex for Message {
                 worker_index_no_shift(GC_WORKER_INDEX)
             }
             Self::MStable { .. } => None,
+            // Anti-entropy messages
+            Self::MRepair { dot, .. } => worker_dot_index_shift(&dot),
+            Self::MRepairCommit { dot, .. } => worker_dot_index_shift(&dot),
+        }
+    }
+}
+
+impl MessagePriority for Message {
+    fn high_priority(&self) -> bool {
+        use fantoch::protocol::high_priority_by_size;
+        match self {
+            // messages carrying command payloads are low priority
+            Self::MCollect { .. }
+            | Self::MForwardSubmit { .. }
+            | Self::MRepairCommit { .. } => false,
+            // all other messages are classified based on their size
+            _ => high_priority_by_size(self),
         }
     }
 }
@@ -884,6 +1182,8 @@ impl MessageIndex for Message {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodicEvent {
     GarbageCollection,
+    FlushAcks,
+    AntiEntropy,
 }
 
 impl MessageIndex for PeriodicEvent {
@@ -891,6 +1191,11 @@ impl MessageIndex for PeriodicEvent {
         use fantoch::load_balance::{worker_index_no_shift, GC_WORKER_INDEX};
         match self {
             Self::GarbageCollection => worker_index_no_shift(GC_WORKER_INDEX),
+            Self::FlushAcks => {
+                // should be sent to all workers
+                None
+            }
+            Self::AntiEntropy => worker_index_no_shift(GC_WORKER_INDEX),
         }
     }
 }
@@ -909,6 +1214,8 @@ mod tests {
     use super::*;
     use fantoch::client::{Client, KeyGen, Workload};
     use fantoch::executor::Executor;
+    use fantoch::id::Rifl;
+    use fantoch::kvs::KVOp;
     use fantoch::planet::{Planet, Region};
     use fantoch::sim::Simulation;
     use fantoch::time::SimTime;
@@ -998,6 +1305,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 100,
             pool_size: 1,
+            pool_id: 0,
         };
         let keys_per_command = 1;
         let commands_per_client = 10;
@@ -1110,6 +1418,7 @@ mod tests {
             ready.pop().expect("there should an executor result");
         let cmd_result = pending
             .add_executor_result(executor_result)
+            .pop()
             .expect("there should be a command result");
 
         // handle the previous command result
@@ -1129,4 +1438,151 @@ mod tests {
             matches!(mcollect, Action::ToSend {msg, ..} if check_msg(&msg))
         );
     }
+
+    #[test]
+    fn atlas_anti_entropy() {
+        let n = 3;
+        let f = 1;
+        let shard_id = 0;
+        let mut config = Config::new(n, f);
+        config.set_gc_interval(Duration::from_millis(100));
+        config.set_anti_entropy_interval(Duration::from_millis(1000));
+        let time = SimTime::new();
+
+        // create 3 atlas processes
+        let region = Region::new("europe-west2");
+        let processes: Vec<_> = (1..=n as ProcessId)
+            .map(|process_id| (process_id, shard_id, region.clone()))
+            .collect();
+        let mut atlas: HashMap<_, _> = processes
+            .iter()
+            .map(|(process_id, _, _)| {
+                let (mut atlas, events) =
+                    AtlasSequential::new(*process_id, shard_id, config);
+                assert!(events.iter().any(|(event, _)| {
+                    *event == PeriodicEvent::AntiEntropy
+                }));
+                let sorted = fantoch::util::sort_processes_by_distance(
+                    &region,
+                    &Planet::new(),
+                    processes.clone(),
+                );
+                atlas.discover(sorted);
+                (*process_id, atlas)
+            })
+            .collect();
+
+        // delivers all actions (and the ones they generate), dropping the
+        // messages sent to the processes in `isolated`
+        let deliver = |atlas: &mut HashMap<ProcessId, AtlasSequential>,
+                       mut actions: Vec<(
+            ProcessId,
+            Action<AtlasSequential>,
+        )>,
+                       isolated: Option<ProcessId>| {
+            while let Some((from, action)) = actions.pop() {
+                let (target, msg) = match action {
+                    Action::ToSend { target, msg } => (target, msg),
+                    Action::ToForward { msg } => (singleton![from], msg),
+                };
+                for to in target {
+                    if Some(to) == isolated {
+                        continue;
+                    }
+                    let process = atlas.get_mut(&to).unwrap();
+                    process.handle(from, shard_id, msg.clone(), &time);
+                    actions.extend(
+                        process.to_processes_iter().map(|action| (to, action)),
+                    );
+                }
+            }
+        };
+        let event = |atlas: &mut HashMap<ProcessId, AtlasSequential>,
+                     process_id: ProcessId,
+                     event: PeriodicEvent| {
+            let process = atlas.get_mut(&process_id).unwrap();
+            process.handle_event(event, &time);
+            process
+                .to_processes_iter()
+                .map(|action| (process_id, action))
+                .collect::<Vec<_>>()
+        };
+        let executed = |atlas: &mut HashMap<ProcessId, AtlasSequential>,
+                        process_id: ProcessId| {
+            atlas
+                .get_mut(&process_id)
+                .unwrap()
+                .to_executors_iter()
+                .count()
+        };
+
+        // process 1 submits a command, which is committed while process 3 is
+        // isolated
+        let rifl = Rifl::new(1, 1);
+        let cmd = Command::from(
+            rifl,
+            vec![(String::from("A"), KVOp::Put(String::from("a")))],
+        );
+        let process_1 = atlas.get_mut(&1).unwrap();
+        process_1.submit(None, cmd, &time);
+        let actions = process_1
+            .to_processes_iter()
+            .map(|action| (1, action))
+            .collect();
+        deliver(&mut atlas, actions, Some(3));
+        assert_eq!(executed(&mut atlas, 1), 1);
+        assert_eq!(executed(&mut atlas, 2), 1);
+        assert_eq!(executed(&mut atlas, 3), 0);
+
+        // once the network heals, processes exchange their committed clocks
+        for process_id in 1..=3 {
+            let actions =
+                event(&mut atlas, process_id, PeriodicEvent::GarbageCollection);
+            deliver(&mut atlas, actions, None);
+        }
+
+        // in the first anti-entropy round, the command was committed too
+        // recently to be repaired
+        let actions = event(&mut atlas, 1, PeriodicEvent::AntiEntropy);
+        assert!(actions.is_empty());
+
+        // in the second round, it's re-shipped to process 3 (only)
+        let mut actions = event(&mut atlas, 1, PeriodicEvent::AntiEntropy);
+        assert_eq!(actions.len(), 1);
+        let (_, mrepair) = actions.pop().unwrap();
+        let process_1 = atlas.get_mut(&1).unwrap();
+        let msg = match mrepair {
+            Action::ToForward { msg } => msg,
+            action => panic!("unexpected action: {:?}", action),
+        };
+        process_1.handle(1, shard_id, msg, &time);
+        let mut actions: Vec<_> = process_1.to_processes_iter().collect();
+        assert_eq!(actions.len(), 1);
+        let mrepair_commit = actions.pop().unwrap();
+        assert!(matches!(
+            &mrepair_commit,
+            Action::ToSend { target, msg: Message::MRepairCommit { .. } }
+                if target == &singleton![3]
+        ));
+        let repairs = process_1
+            .metrics()
+            .get_aggregated(ProtocolMetricsKind::Repairs)
+            .cloned();
+        assert_eq!(repairs, Some(1));
+
+        // process 3 finally commits the command
+        deliver(&mut atlas, vec![(1, mrepair_commit.clone())], None);
+        assert_eq!(executed(&mut atlas, 3), 1);
+
+        // once the command is stable, a duplicate repair is ignored
+        for process_id in 1..=3 {
+            let actions =
+                event(&mut atlas, process_id, PeriodicEvent::GarbageCollection);
+            deliver(&mut atlas, actions, None);
+        }
+        deliver(&mut atlas, vec![(1, mrepair_commit)], None);
+        assert_eq!(executed(&mut atlas, 3), 0);
+        let actions = event(&mut atlas, 1, PeriodicEvent::AntiEntropy);
+        assert!(actions.is_empty());
+    }
 }
@@ -7,11 +7,11 @@ use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
     Action, BaseProcess, BasicGCTrack, CommittedAndExecuted, Info,
-    LockedCommandsInfo, MessageIndex, Protocol, ProtocolMetrics,
-    ProtocolMetricsKind,
+    LockedCommandsInfo, MessageIndex, MessagePriority, Protocol,
+    ProtocolMetrics, ProtocolMetricsKind, StateSizes, Violation,
 };
 use fantoch::time::SysTime;
-use fantoch::{singleton, trace};
+use fantoch::{flow, singleton, trace};
 use fantoch::{HashMap, HashSet};
 use parking_lot::MutexGuard;
 use serde::{Deserialize, Deserializer, Serialize};
@@ -222,6 +222,11 @@ impl<KC: KeyClocks> Protocol for Caesar<KC> {
         self.to_executors.pop()
     }
 
+    /// Returns a new violation of the protocol's invariants.
+    fn to_violations(&mut self) -> Option<Violation> {
+        self.bp.to_violations()
+    }
+
     fn parallel() -> bool {
         KC::parallel()
     }
@@ -230,9 +235,23 @@ impl<KC: KeyClocks> Protocol for Caesar<KC> {
         true
     }
 
+    fn message_fingerprint() -> u64 {
+        super::message_fingerprints::CAESAR
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
+
+    fn state_sizes(&self) -> StateSizes {
+        let mut sizes = StateSizes::new();
+        sizes.insert("dots", self.cmds.len());
+        sizes.insert("key_clocks", self.key_clocks.key_count());
+        sizes.insert("buffered_retries", self.buffered_retries.len());
+        sizes.insert("buffered_commits", self.buffered_commits.len());
+        sizes.insert("try_to_unblock_again", self.try_to_unblock_again.len());
+        sizes
+    }
 }
 
 impl<KC: KeyClocks> Caesar<KC> {
@@ -547,10 +566,16 @@ impl<KC: KeyClocks> Caesar<KC> {
             return;
         }
         if info.quorum_clocks.all() {
-            panic!(
-                "p{}: {:?} already had all MProposeAck needed",
-                self.bp.process_id, dot
+            // the ack is ignored, as it was either duplicated or sent by a
+            // process not in the fast quorum
+            self.bp.violation(
+                format!("{:?} already had all MProposeAck needed", dot),
+                format!(
+                    "MProposeAck from {} | quorum clocks: {:?}",
+                    from, info.quorum_clocks
+                ),
             );
+            return;
         }
 
         // update quorum deps
@@ -641,6 +666,22 @@ impl<KC: KeyClocks> Caesar<KC> {
             return;
         }
 
+        // check that all processes commit the same clock and dependencies
+        #[cfg(feature = "strict-invariants")]
+        fantoch::sim::invariants::committed(
+            self.bp.process_id,
+            self.bp.shard_id,
+            dot,
+            &(clock, deps.clone()),
+        );
+
+        flow!(
+            process = self.bp.process_id,
+            dot = ?dot,
+            phase = "commit",
+            time = time.micros()
+        );
+
         // register commit time if we're the coordinator
         if dot.source() == from {
             let start_time_ms = info.start_time_ms.take().expect(
@@ -791,10 +832,16 @@ impl<KC: KeyClocks> Caesar<KC> {
             return;
         }
         if info.quorum_retries.all() {
-            panic!(
-                "p{}: {:?} already had all MRetryAck needed",
-                self.bp.process_id, dot
+            // the ack is ignored, as it was either duplicated or sent by a
+            // process not in the write quorum
+            self.bp.violation(
+                format!("{:?} already had all MRetryAck needed", dot),
+                format!(
+                    "MRetryAck from {} | quorum retries: {:?}",
+                    from, info.quorum_retries
+                ),
             );
+            return;
         }
 
         // update quorum retries
@@ -861,13 +908,17 @@ impl<KC: KeyClocks> Caesar<KC> {
         self.bp.stable(1);
     }
 
-    fn handle_event_garbage_collection(&mut self, _time: &dyn SysTime) {
+    fn handle_event_garbage_collection(&mut self, time: &dyn SysTime) {
         trace!(
             "p{}: PeriodicEvent::GarbageCollection | time={}",
             self.id(),
-            _time.micros()
+            time.micros()
         );
 
+        // sample the stability lag
+        let lag = self.gc_track.stability_lag();
+        self.bp.stability_lag(lag, time);
+
         trace!(
             "p{}: COMMITTED {:>20} EXECUTED {:>20} EXISTING {:>20}",
             self.id(),
@@ -1358,6 +1409,18 @@ impl MessageIndex for Message {
     }
 }
 
+impl MessagePriority for Message {
+    fn high_priority(&self) -> bool {
+        use fantoch::protocol::high_priority_by_size;
+        match self {
+            // messages carrying command payloads are low priority
+            Self::MPropose { .. } => false,
+            // all other messages are classified based on their size
+            _ => high_priority_by_size(self),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodicEvent {
     GarbageCollection,
@@ -1480,6 +1543,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 100,
             pool_size: 1,
+            pool_id: 0,
         };
         let keys_per_command = 1;
         let commands_per_client = 10;
@@ -1592,6 +1656,7 @@ mod tests {
             ready.pop().expect("there should an executor result");
         let cmd_result = pending
             .add_executor_result(executor_result)
+            .pop()
             .expect("there should be a command result");
 
         // handle the previous command result
@@ -1643,6 +1708,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 100,
             pool_size: 1,
+            pool_id: 0,
         };
         let keys_per_command = 1;
         let commands_per_client = 10;
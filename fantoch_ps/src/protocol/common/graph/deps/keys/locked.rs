@@ -1,4 +1,5 @@
-use super::{Dependency, KeyDeps};
+use super::{Commuting, Dependency, KeyDeps};
+use crate::protocol::common::locks::KeyLockStats;
 use fantoch::command::Command;
 use fantoch::id::{Dot, ShardId};
 use fantoch::kvs::Key;
@@ -12,6 +13,7 @@ type Latest = Option<Dependency>;
 struct LatestRW {
     read: Latest,
     write: Latest,
+    commuting: Commuting,
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +21,8 @@ pub struct LockedKeyDeps {
     shard_id: ShardId,
     latest: Arc<SharedMap<Key, RwLock<LatestRW>>>,
     latest_noop: Arc<RwLock<Latest>>,
+    lock_stats: KeyLockStats,
+    commuted_deps: u64,
 }
 
 impl KeyDeps for LockedKeyDeps {
@@ -28,6 +32,8 @@ impl KeyDeps for LockedKeyDeps {
             shard_id,
             latest: Arc::new(SharedMap::new()),
             latest_noop: Arc::new(RwLock::new(None)),
+            lock_stats: KeyLockStats::default(),
+            commuted_deps: 0,
         }
     }
 
@@ -67,6 +73,18 @@ impl KeyDeps for LockedKeyDeps {
         super::extract_dots(deps)
     }
 
+    fn take_lock_stats(&mut self) -> KeyLockStats {
+        std::mem::take(&mut self.lock_stats)
+    }
+
+    fn take_commuted_deps(&mut self) -> u64 {
+        std::mem::take(&mut self.commuted_deps)
+    }
+
+    fn key_count(&self) -> usize {
+        self.latest.len()
+    }
+
     fn parallel() -> bool {
         true
     }
@@ -81,7 +99,7 @@ impl LockedKeyDeps {
     }
 
     fn do_add_cmd(
-        &self,
+        &mut self,
         dot: Dot,
         cmd: &Command,
         mut deps: HashSet<Dependency>,
@@ -91,22 +109,38 @@ impl LockedKeyDeps {
 
         // iterate through all command keys, grab a write lock, get their
         // current latest and set ourselves to be the new latest
+        let latest = &self.latest;
+        let lock_stats = &mut self.lock_stats;
+        let commuted_deps = &mut self.commuted_deps;
         cmd.keys(self.shard_id).for_each(|key| {
             // get latest read and write on this key
-            let entry = self.latest.get_or(key, || RwLock::default());
+            let entry = latest.get_or(key, || RwLock::default());
             // grab a write lock
-            let mut guard = entry.write();
+            let mut guard = lock_stats.write(key, &entry);
 
             if cmd.read_only() {
                 // if a command is read-only, then it should depend on the
-                // latest write, and it should be added as the latest read
+                // latest write (and the commuting commands after it), and it
+                // should be added as the latest read
                 if let Some(wdep) = guard.write.as_ref() {
                     deps.insert(wdep.clone());
                 }
+                deps.extend(guard.commuting.iter().cloned());
                 guard.read = Some(cmd_dep.clone());
+            } else if let Some(commuted) = guard.commuting.add(cmd, &cmd_dep) {
+                // if a command commutes with the commands added after the
+                // latest write, then it should only depend on the latest read
+                // and latest write
+                if let Some(rdep) = guard.read.as_ref() {
+                    deps.insert(rdep.clone());
+                }
+                if let Some(wdep) = guard.write.as_ref() {
+                    deps.insert(wdep.clone());
+                }
+                *commuted_deps += commuted;
             } else {
-                // if a command is not read-only, then it should depend on the
-                // latest read and latest write, and it should be added as the
+                // otherwise, it should depend on the latest read, latest write
+                // and the commuting commands, and it should be added as the
                 // latest write
                 if let Some(rdep) = guard.read.as_ref() {
                     deps.insert(rdep.clone());
@@ -114,6 +148,7 @@ impl LockedKeyDeps {
                 if let Some(wdep) = guard.write.replace(cmd_dep.clone()) {
                     deps.insert(wdep);
                 }
+                deps.extend(guard.commuting.drain());
             }
         });
 
@@ -165,6 +200,7 @@ impl LockedKeyDeps {
             if let Some(wdep) = latest_rw.write.as_ref() {
                 deps.insert(wdep.clone());
             }
+            deps.extend(latest_rw.commuting.iter().cloned());
         });
     }
 
@@ -181,6 +217,9 @@ impl LockedKeyDeps {
             if let Some(wdep) = latest_rw.write.as_ref() {
                 deps.insert(wdep.clone());
             }
+            if !latest_rw.commuting.commutes(cmd) {
+                deps.extend(latest_rw.commuting.iter().cloned());
+            }
         });
     }
 }
@@ -8,8 +8,11 @@ mod locked;
 pub use locked::LockedKeyDeps;
 pub use sequential::SequentialKeyDeps;
 
+use crate::protocol::common::locks::KeyLockStats;
 use fantoch::command::Command;
 use fantoch::id::{Dot, ShardId};
+use fantoch::kvs::Commutative;
+use fantoch::protocol::{BaseProcess, ProtocolMetricsKind};
 use fantoch::HashSet;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
@@ -59,9 +62,84 @@ pub trait KeyDeps: Debug + Clone {
     #[cfg(test)]
     fn noop_deps(&self) -> HashSet<Dot>;
 
+    /// Returns the key-lock acquisitions since the last call (only recorded
+    /// by implementations that lock keys).
+    fn take_lock_stats(&mut self) -> KeyLockStats {
+        KeyLockStats::default()
+    }
+
+    /// Returns the number of dependencies not added since the last call
+    /// because commands commuted (see `Command::commutative`).
+    fn take_commuted_deps(&mut self) -> u64;
+
+    /// Records the result of `KeyDeps::take_commuted_deps` in the process
+    /// metrics (as `CommutedDeps`).
+    fn record_commuted_deps(&mut self, bp: &mut BaseProcess) {
+        let commuted = self.take_commuted_deps();
+        if commuted > 0 {
+            bp.aggregate_metric(ProtocolMetricsKind::CommutedDeps, commuted);
+        }
+    }
+
+    /// Returns the number of keys with a latest dependency.
+    fn key_count(&self) -> usize;
+
     fn parallel() -> bool;
 }
 
+// maximum number of commuting commands tracked on each key; once reached, the
+// next command is handled as if it didn't commute, which bounds the number of
+// dependencies of the commands that don't commute with them
+const MAX_COMMUTING: usize = 64;
+
+/// Commands added on some key since the latest command on that key that
+/// doesn't commute with them. These don't depend on each other, while
+/// commands that don't commute with them depend on all of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Commuting {
+    commutative: Option<Commutative>,
+    deps: Vec<Dependency>,
+}
+
+impl Commuting {
+    /// Adds `dep` (of command `cmd`) if the command commutes with the ones
+    /// already added. If so, returns the number of dependencies not added to
+    /// the command because of that.
+    fn add(&mut self, cmd: &Command, dep: &Dependency) -> Option<u64> {
+        if self.commutes(cmd) {
+            self.commutative = cmd.commutative();
+            let commuted = self.deps.len() as u64;
+            self.deps.push(dep.clone());
+            Some(commuted)
+        } else {
+            None
+        }
+    }
+
+    /// Checks whether `cmd` commutes with the commands already added (and
+    /// can be added without exceeding `MAX_COMMUTING`).
+    fn commutes(&self, cmd: &Command) -> bool {
+        match cmd.commutative() {
+            Some(commutative) => {
+                (self.deps.is_empty() || self.commutative == Some(commutative))
+                    && self.deps.len() < MAX_COMMUTING
+            }
+            None => false,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Dependency> {
+        self.deps.iter()
+    }
+
+    /// Removes all commands (e.g. because a command that doesn't commute with
+    /// them was added).
+    fn drain(&mut self) -> impl Iterator<Item = Dependency> + '_ {
+        self.commutative = None;
+        self.deps.drain(..)
+    }
+}
+
 #[cfg(test)]
 fn extract_dots(deps: HashSet<Dependency>) -> HashSet<Dot> {
     deps.into_iter().map(|dep| dep.dot).collect()
@@ -87,6 +165,82 @@ mod tests {
         key_deps_flow::<LockedKeyDeps>();
     }
 
+    #[test]
+    fn sequential_key_deps_commutative() {
+        key_deps_commutative_flow::<SequentialKeyDeps>();
+    }
+
+    #[test]
+    fn locked_key_deps_commutative() {
+        key_deps_commutative_flow::<LockedKeyDeps>();
+    }
+
+    fn key_deps_commutative_flow<KD: KeyDeps>() {
+        // create key deps
+        let shard_id = 0;
+        let mut key_deps = KD::new(shard_id);
+
+        // create dot gen
+        let process_id = 1;
+        let mut dot_gen = DotGen::new(process_id);
+
+        // commands on key A
+        let key = String::from("A");
+        let cmd =
+            |op| Command::from(Rifl::new(100, 1), vec![(key.clone(), op)]);
+        let put = cmd(KVOp::Put(String::new()));
+        let incr = cmd(KVOp::Increment(1));
+        let set_add = cmd(KVOp::SetAdd(String::from("x")));
+        let dots = |seqs: Vec<u64>| -> HashSet<Dot> {
+            seqs.into_iter().map(|seq| Dot::new(1, seq)).collect()
+        };
+
+        // add put with {1,1}
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &put, None);
+        assert_eq!(extract_dots(deps), dots(vec![]));
+
+        // increments with {1,2} and {1,3} only depend on the put
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &incr, None);
+        assert_eq!(extract_dots(deps), dots(vec![1]));
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &incr, None);
+        assert_eq!(extract_dots(deps), dots(vec![1]));
+        assert_eq!(key_deps.cmd_deps(&incr), dots(vec![1]));
+        assert_eq!(key_deps.cmd_deps(&put), dots(vec![1, 2, 3]));
+        assert_eq!(key_deps.cmd_deps(&set_add), dots(vec![1, 2, 3]));
+        assert_eq!(key_deps.noop_deps(), dots(vec![1, 2, 3]));
+
+        // the set-add with {1,4} doesn't commute with increments, so it
+        // depends on all of them
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &set_add, None);
+        assert_eq!(extract_dots(deps), dots(vec![1, 2, 3]));
+
+        // an increment with {1,5} only depends on the set-add
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &incr, None);
+        assert_eq!(extract_dots(deps), dots(vec![4]));
+        assert_eq!(key_deps.cmd_deps(&incr), dots(vec![4]));
+        assert_eq!(key_deps.cmd_deps(&put), dots(vec![4, 5]));
+        assert_eq!(key_deps.noop_deps(), dots(vec![4, 5]));
+
+        // a put with {1,6} depends on both
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &put, None);
+        assert_eq!(extract_dots(deps), dots(vec![4, 5]));
+
+        // only the dependency between the two first increments was avoided
+        assert_eq!(key_deps.take_commuted_deps(), 1);
+        assert_eq!(key_deps.take_commuted_deps(), 0);
+
+        // once `MAX_COMMUTING` increments are added, the next one no longer
+        // commutes with them
+        let max = MAX_COMMUTING as u64;
+        for _ in 0..max {
+            let deps = key_deps.add_cmd(dot_gen.next_id(), &incr, None);
+            assert_eq!(extract_dots(deps), dots(vec![6]));
+        }
+        assert_eq!(key_deps.take_commuted_deps(), max * (max - 1) / 2);
+        let deps = key_deps.add_cmd(dot_gen.next_id(), &incr, None);
+        assert_eq!(extract_dots(deps), dots((6..=6 + max).collect()));
+    }
+
     fn multi_put(rifl: Rifl, keys: Vec<String>, value: String) -> Command {
         Command::from(
             rifl,
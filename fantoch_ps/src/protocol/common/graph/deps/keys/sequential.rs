@@ -1,14 +1,21 @@
-use super::{Dependency, KeyDeps};
+use super::{Commuting, Dependency, KeyDeps};
 use fantoch::command::Command;
 use fantoch::id::{Dot, ShardId};
 use fantoch::kvs::Key;
 use fantoch::{HashMap, HashSet};
 
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Latest {
+    latest: Option<Dependency>,
+    commuting: Commuting,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SequentialKeyDeps {
     shard_id: ShardId,
-    latest_deps: HashMap<Key, Dependency>,
+    latest_deps: HashMap<Key, Latest>,
     noop_latest_dep: Option<Dependency>,
+    commuted_deps: u64,
 }
 
 impl KeyDeps for SequentialKeyDeps {
@@ -18,6 +25,7 @@ impl KeyDeps for SequentialKeyDeps {
             shard_id,
             latest_deps: HashMap::new(),
             noop_latest_dep: None,
+            commuted_deps: 0,
         }
     }
 
@@ -57,6 +65,14 @@ impl KeyDeps for SequentialKeyDeps {
         super::extract_dots(deps)
     }
 
+    fn take_commuted_deps(&mut self) -> u64 {
+        std::mem::take(&mut self.commuted_deps)
+    }
+
+    fn key_count(&self) -> usize {
+        self.latest_deps.len()
+    }
+
     fn parallel() -> bool {
         false
     }
@@ -78,22 +94,26 @@ impl SequentialKeyDeps {
         // iterate through all command keys, get their current latest and set
         // ourselves to be the new latest
         let new_dep = Dependency::from_cmd(dot, cmd);
+        let latest_deps = &mut self.latest_deps;
+        let commuted_deps = &mut self.commuted_deps;
         cmd.keys(self.shard_id).for_each(|key| {
             // get latest command on this key
-            match self.latest_deps.get_mut(key) {
-                Some(dep) => {
-                    // if there was a previous latest, then it's a dependency
+            let latest = latest_deps.entry(key.clone()).or_default();
+            if let Some(commuted) = latest.commuting.add(cmd, &new_dep) {
+                // if self commutes with the commands added after the latest,
+                // then only the latest is a dependency
+                if let Some(dep) = latest.latest.as_ref() {
                     deps.insert(dep.clone());
-                    // set self to be the new latest
-                    *dep = new_dep.clone();
                 }
-                None => {
-                    // set self to be the new latest
-                    self.latest_deps
-                        .entry(key.clone())
-                        .or_insert(new_dep.clone());
+                *commuted_deps += commuted;
+            } else {
+                // otherwise, the previous latest (if any) and the commuting
+                // commands are dependencies; set self to be the new latest
+                if let Some(dep) = latest.latest.replace(new_dep.clone()) {
+                    deps.insert(dep);
                 }
-            };
+                deps.extend(latest.commuting.drain());
+            }
         });
 
         // include latest noop, if any
@@ -125,9 +145,10 @@ impl SequentialKeyDeps {
     fn do_noop_deps(&self, deps: &mut HashSet<Dependency>) {
         // iterate through all keys, grab a read lock, and include their latest
         // in the final `deps`
-        self.latest_deps.values().for_each(|dep| {
-            // take the dot as a dependency
-            deps.insert(dep.clone());
+        self.latest_deps.values().for_each(|latest| {
+            // take the dots as a dependency
+            deps.extend(latest.latest.iter().cloned());
+            deps.extend(latest.commuting.iter().cloned());
         });
     }
 
@@ -135,9 +156,14 @@ impl SequentialKeyDeps {
     fn do_cmd_deps(&self, cmd: &Command, deps: &mut HashSet<Dependency>) {
         cmd.keys(self.shard_id).for_each(|key| {
             // get latest command on this key
-            if let Some(dep) = self.latest_deps.get(key) {
+            if let Some(latest) = self.latest_deps.get(key) {
                 // if there is a latest, then it's a dependency
-                deps.insert(dep.clone());
+                deps.extend(latest.latest.iter().cloned());
+                // and so are the commuting commands, unless we commute with
+                // them
+                if !latest.commuting.commutes(cmd) {
+                    deps.extend(latest.commuting.iter().cloned());
+                }
             }
         });
     }
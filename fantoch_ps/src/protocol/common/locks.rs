@@ -0,0 +1,146 @@
+use fantoch::kvs::Key;
+use fantoch::protocol::{BaseProcess, ProtocolMetricsKind};
+use fantoch::util;
+use parking_lot::{Mutex, MutexGuard, RwLock, RwLockWriteGuard};
+use std::time::Instant;
+
+/// Key-lock acquisitions recorded by the `Locked` variants of key clocks and
+/// key deps, so that they can be reported in the process metrics.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyLockStats {
+    // total number of key-lock acquisitions
+    acquisitions: u64,
+    // hash of the key and wait time (in micros) of each acquisition that had
+    // to wait for the lock
+    contended: Vec<(u64, u64)>,
+}
+
+impl KeyLockStats {
+    /// Acquires the lock of `key`, recording how long it waited for it (if
+    /// any).
+    pub fn lock<'a, T>(
+        &mut self,
+        key: &Key,
+        lock: &'a Mutex<T>,
+    ) -> MutexGuard<'a, T> {
+        self.acquisitions += 1;
+        if let Some(guard) = lock.try_lock() {
+            return guard;
+        }
+        let start = Instant::now();
+        let guard = lock.lock();
+        self.record_wait(key, start);
+        guard
+    }
+
+    /// Same as `KeyLockStats::lock` but for a write lock.
+    pub fn write<'a, T>(
+        &mut self,
+        key: &Key,
+        lock: &'a RwLock<T>,
+    ) -> RwLockWriteGuard<'a, T> {
+        self.acquisitions += 1;
+        if let Some(guard) = lock.try_write() {
+            return guard;
+        }
+        let start = Instant::now();
+        let guard = lock.write();
+        self.record_wait(key, start);
+        guard
+    }
+
+    /// Records the acquisitions in the process metrics:
+    /// - `KeyLockAcquisitions`: number of acquisitions
+    /// - `KeyLockWait`: wait time of the acquisitions that had to wait
+    /// - `KeyLockWaitOn(key_hash)`: total wait time on each key
+    pub fn record(self, bp: &mut BaseProcess) {
+        if self.acquisitions == 0 {
+            return;
+        }
+        bp.aggregate_metric(
+            ProtocolMetricsKind::KeyLockAcquisitions,
+            self.acquisitions,
+        );
+        for (key_hash, wait) in self.contended {
+            bp.collect_metric(ProtocolMetricsKind::KeyLockWait, wait);
+            bp.aggregate_metric(
+                ProtocolMetricsKind::KeyLockWaitOn(key_hash),
+                wait,
+            );
+        }
+    }
+
+    fn record_wait(&mut self, key: &Key, start: Instant) {
+        let wait = start.elapsed().as_micros() as u64;
+        self.contended.push((util::key_hash(key), wait));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fantoch::config::Config;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn key_lock_stats() {
+        let key = String::from("A");
+        let lock = Arc::new(Mutex::new(0));
+
+        // uncontended acquisition
+        let mut stats = KeyLockStats::default();
+        *stats.lock(&key, &lock) += 1;
+        assert_eq!(stats.acquisitions, 1);
+        assert!(stats.contended.is_empty());
+
+        // contended acquisition: hold the lock in another thread for a while
+        let guard = lock.lock();
+        let barrier = Arc::new(Barrier::new(2));
+        let handle = {
+            let key = key.clone();
+            let lock = lock.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let mut stats = KeyLockStats::default();
+                barrier.wait();
+                *stats.lock(&key, &lock) += 1;
+                stats
+            })
+        };
+        barrier.wait();
+        thread::sleep(Duration::from_millis(10));
+        drop(guard);
+        let other_stats = handle.join().unwrap();
+        assert_eq!(*lock.lock(), 2);
+        assert_eq!(other_stats.acquisitions, 1);
+        assert_eq!(other_stats.contended.len(), 1);
+        let (key_hash, wait) = other_stats.contended[0];
+        assert_eq!(key_hash, util::key_hash(&key));
+        assert!(wait > 0);
+
+        // record both in the process metrics
+        let config = Config::new(3, 1);
+        let mut bp = BaseProcess::new(1, 0, config, 2, 2);
+        stats.record(&mut bp);
+        other_stats.record(&mut bp);
+        let metrics = bp.metrics();
+        assert_eq!(
+            metrics.get_aggregated(ProtocolMetricsKind::KeyLockAcquisitions),
+            Some(&2)
+        );
+        assert_eq!(
+            metrics
+                .get_collected(ProtocolMetricsKind::KeyLockWait)
+                .map(|waits| waits.count()),
+            Some(1)
+        );
+        assert_eq!(
+            metrics.get_aggregated(ProtocolMetricsKind::KeyLockWaitOn(
+                util::key_hash(&key)
+            )),
+            Some(&wait)
+        );
+    }
+}
@@ -7,6 +7,9 @@ pub mod table;
 // This module contains definitions common to predecessors-based protocols.
 pub mod pred;
 
+// This module contains the definition of `KeyLockStats`.
+pub mod locks;
+
 // This module contains the implementation of Paxos single and multi-decree
 // Synod Protocols.
 pub mod synod;
@@ -129,6 +129,10 @@ impl KeyClocks for LockedKeyClocks {
         predecessors
     }
 
+    fn key_count(&self) -> usize {
+        self.clocks.len()
+    }
+
     fn parallel() -> bool {
         true
     }
@@ -41,5 +41,8 @@ pub trait KeyClocks: Debug + Clone {
         higher: Option<&mut HashSet<Dot>>,
     ) -> CaesarDeps;
 
+    /// Returns the number of keys with a clock.
+    fn key_count(&self) -> usize;
+
     fn parallel() -> bool;
 }
@@ -38,6 +38,14 @@ impl GCTrack {
         self.committed.frontier()
     }
 
+    /// Returns the number of slots committed locally (i.e. below the local
+    /// frontier) that are not yet known to be stable.
+    pub fn stability_lag(&self) -> u64 {
+        self.committed
+            .frontier()
+            .saturating_sub(self.previous_stable)
+    }
+
     /// Records that set of `committed` commands by process `from`.
     pub fn committed_by(&mut self, from: ProcessId, committed: u64) {
         self.all_but_me.insert(from, committed);
@@ -116,6 +116,17 @@ where
         self.acceptor.gc_single(slot)
     }
 
+    /// Returns the number of slots with an active commander.
+    pub fn commanders_len(&self) -> usize {
+        self.commanders.len()
+    }
+
+    /// Returns the number of slots accepted that have not yet been garbage
+    /// collected.
+    pub fn accepted_len(&self) -> usize {
+        self.acceptor.accepted.len()
+    }
+
     fn handle_spawn_commander(
         &mut self,
         ballot: Ballot,
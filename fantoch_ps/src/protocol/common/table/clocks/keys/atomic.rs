@@ -39,6 +39,12 @@ impl KeyClocks for AtomicKeyClocks {
         });
     }
 
+    fn init_keys(&mut self, keys: impl Iterator<Item = Key>) {
+        keys.for_each(|key| {
+            let _ = self.clocks.get_or(&key, AtomicU64::default);
+        });
+    }
+
     fn proposal(&mut self, cmd: &Command, min_clock: u64) -> (u64, Votes) {
         // first round of votes:
         // - vote on each key and compute the highest clock seen
@@ -102,6 +108,10 @@ impl KeyClocks for AtomicKeyClocks {
         });
     }
 
+    fn key_count(&self) -> usize {
+        self.clocks.len()
+    }
+
     fn parallel() -> bool {
         true
     }
@@ -1,4 +1,5 @@
 use super::KeyClocks;
+use crate::protocol::common::locks::KeyLockStats;
 use crate::protocol::common::table::{VoteRange, Votes};
 use fantoch::command::Command;
 use fantoch::id::{ProcessId, Rifl, ShardId};
@@ -25,6 +26,7 @@ pub struct LockedKeyClocks {
     process_id: ProcessId,
     shard_id: ShardId,
     clocks: Clocks,
+    lock_stats: KeyLockStats,
 }
 
 impl KeyClocks for LockedKeyClocks {
@@ -34,6 +36,7 @@ impl KeyClocks for LockedKeyClocks {
             process_id,
             shard_id,
             clocks: common::new(),
+            lock_stats: KeyLockStats::default(),
         }
     }
 
@@ -41,6 +44,12 @@ impl KeyClocks for LockedKeyClocks {
         common::init_clocks(self.shard_id, &self.clocks, cmd)
     }
 
+    fn init_keys(&mut self, keys: impl Iterator<Item = Key>) {
+        keys.for_each(|key| {
+            let _ = self.clocks.get_or(&key, || Mutex::default());
+        });
+    }
+
     fn proposal(&mut self, cmd: &Command, min_clock: u64) -> (u64, Votes) {
         // make sure locks will be acquired in some pre-determined order to
         // avoid deadlocks
@@ -63,8 +72,8 @@ impl KeyClocks for LockedKeyClocks {
             // value
             // TODO: add the read as pending
             let mut clock = min_clock;
-            for (_key, key_lock) in &locks {
-                let guard = key_lock.lock();
+            for (key, key_lock) in &locks {
+                let guard = self.lock_stats.lock(key, key_lock);
                 clock = cmp::max(clock, guard.clock);
             }
             (clock, Votes::new())
@@ -74,8 +83,8 @@ impl KeyClocks for LockedKeyClocks {
 
             // acquire the lock on all keys
             let mut guards = Vec::with_capacity(key_count);
-            for (_key, key_lock) in &locks {
-                let guard = key_lock.lock();
+            for (key, key_lock) in &locks {
+                let guard = self.lock_stats.lock(key, key_lock);
                 up_to = cmp::max(up_to, guard.clock + 1);
                 guards.push(guard);
             }
@@ -104,6 +113,7 @@ impl KeyClocks for LockedKeyClocks {
             self.process_id,
             self.shard_id,
             &self.clocks,
+            &mut self.lock_stats,
             cmd,
             up_to,
             votes,
@@ -114,6 +124,14 @@ impl KeyClocks for LockedKeyClocks {
         common::detached_all(self.process_id, &self.clocks, up_to, votes)
     }
 
+    fn take_lock_stats(&mut self) -> KeyLockStats {
+        std::mem::take(&mut self.lock_stats)
+    }
+
+    fn key_count(&self) -> usize {
+        self.clocks.len()
+    }
+
     fn parallel() -> bool {
         true
     }
@@ -145,13 +163,14 @@ mod common {
         id: ProcessId,
         shard_id: ShardId,
         clocks: &Clocks,
+        lock_stats: &mut KeyLockStats,
         cmd: &Command,
         up_to: u64,
         votes: &mut Votes,
     ) {
         for key in cmd.keys(shard_id) {
             let key_lock = clocks.get_or(key, || Mutex::default());
-            let mut guard = key_lock.lock();
+            let mut guard = lock_stats.lock(key, &key_lock);
             maybe_bump(id, key, &mut guard.clock, up_to, votes);
             // release the lock
             drop(guard);
@@ -12,9 +12,11 @@ pub use atomic::AtomicKeyClocks;
 pub use locked::LockedKeyClocks;
 pub use sequential::SequentialKeyClocks;
 
+use crate::protocol::common::locks::KeyLockStats;
 use crate::protocol::common::table::Votes;
 use fantoch::command::Command;
 use fantoch::id::{ProcessId, ShardId};
+use fantoch::kvs::Key;
 use std::fmt::Debug;
 
 pub trait KeyClocks: Debug + Clone {
@@ -24,6 +26,10 @@ pub trait KeyClocks: Debug + Clone {
     /// Makes sure there's a clock for each key in the command.
     fn init_clocks(&mut self, cmd: &Command);
 
+    /// Makes sure there's a clock for each key provided (see
+    /// `Config::preload_keys`).
+    fn init_keys(&mut self, keys: impl Iterator<Item = Key>);
+
     /// Bump clocks to at least `min_clock` and return the new clock (that might
     /// be `min_clock` in case it was higher than any of the local clocks). Also
     /// returns the consumed votes.
@@ -35,6 +41,15 @@ pub trait KeyClocks: Debug + Clone {
     /// Votes up to `clock` on all keys.
     fn detached_all(&mut self, clock: u64, votes: &mut Votes);
 
+    /// Returns the key-lock acquisitions since the last call (only recorded
+    /// by implementations that lock keys).
+    fn take_lock_stats(&mut self) -> KeyLockStats {
+        KeyLockStats::default()
+    }
+
+    /// Returns the number of keys with a clock.
+    fn key_count(&self) -> usize;
+
     fn parallel() -> bool;
 }
 
@@ -33,6 +33,12 @@ impl KeyClocks for SequentialKeyClocks {
         });
     }
 
+    fn init_keys(&mut self, keys: impl Iterator<Item = Key>) {
+        keys.for_each(|key| {
+            self.clocks.entry(key).or_insert(0);
+        });
+    }
+
     fn proposal(&mut self, cmd: &Command, min_clock: u64) -> (u64, Votes) {
         // bump to at least `min_clock`
         let clock = cmp::max(min_clock, self.clock(cmd) + 1);
@@ -67,6 +73,10 @@ impl KeyClocks for SequentialKeyClocks {
         });
     }
 
+    fn key_count(&self) -> usize {
+        self.clocks.len()
+    }
+
     fn parallel() -> bool {
         false
     }
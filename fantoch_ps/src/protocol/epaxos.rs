@@ -7,11 +7,12 @@ use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    AckCoalescer, Action, BaseProcess, Info, MessageIndex, MessagePriority,
+    Protocol, ProtocolMetrics, ProtocolMetricsKind, SequentialCommandsInfo,
+    StateSizes, VClockGCTrack, ANTI_ENTROPY_MAX_REPAIRS,
 };
 use fantoch::time::SysTime;
-use fantoch::{singleton, trace};
+use fantoch::{flow, singleton, trace};
 use fantoch::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -26,6 +27,7 @@ pub struct EPaxos<KD: KeyDeps> {
     key_deps: KD,
     cmds: SequentialCommandsInfo<EPaxosInfo>,
     gc_track: VClockGCTrack,
+    acks: AckCoalescer<Message>,
     to_processes: Vec<Action<Self>>,
     to_executors: Vec<GraphExecutionInfo>,
     // commit notifications that arrived before the initial `MCollect` message
@@ -67,6 +69,7 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
             write_quorum_size,
         );
         let gc_track = VClockGCTrack::new(process_id, shard_id, config.n());
+        let acks = AckCoalescer::new(process_id, &config);
         let to_processes = Vec::new();
         let to_executors = Vec::new();
         let buffered_commits = HashMap::new();
@@ -77,18 +80,32 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
             key_deps,
             cmds,
             gc_track,
+            acks,
             to_processes,
             to_executors,
             buffered_commits,
         };
 
         // create periodic events
-        let events = if let Some(interval) = config.gc_interval() {
+        let mut events = if let Some(interval) = config.gc_interval() {
             vec![(PeriodicEvent::GarbageCollection, interval)]
         } else {
             vec![]
         };
 
+        // maybe create flush acks periodic event
+        if let Some(interval) = config.ack_coalescing_interval() {
+            events.push((PeriodicEvent::FlushAcks, interval));
+        }
+
+        // maybe create anti-entropy periodic event (which relies on the
+        // committed clocks exchanged for garbage collection)
+        if let Some(interval) = config.anti_entropy_interval() {
+            if config.gc_interval().is_some() {
+                events.push((PeriodicEvent::AntiEntropy, interval));
+            }
+        }
+
         // return both
         (protocol, events)
     }
@@ -136,6 +153,11 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
             Message::MCollectAck { dot, deps } => {
                 self.handle_mcollectack(from, dot, deps, time)
             }
+            Message::MCollectAckBatch { acks } => {
+                for ack in acks {
+                    self.handle(from, _from_shard_id, ack, time);
+                }
+            }
             Message::MCommit { dot, value } => {
                 self.handle_mcommit(from, dot, value, time)
             }
@@ -154,6 +176,12 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
             Message::MStable { stable } => {
                 self.handle_mstable(from, stable, time)
             }
+            Message::MRepair { dot, targets } => {
+                self.handle_mrepair(from, dot, targets, time)
+            }
+            Message::MRepairCommit { dot, cmd, value } => {
+                self.handle_mrepair_commit(from, dot, cmd, value, time)
+            }
         }
     }
 
@@ -163,6 +191,8 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
             PeriodicEvent::GarbageCollection => {
                 self.handle_event_garbage_collection(time)
             }
+            PeriodicEvent::FlushAcks => self.handle_event_flush_acks(time),
+            PeriodicEvent::AntiEntropy => self.handle_event_anti_entropy(time),
         }
     }
 
@@ -184,9 +214,22 @@ impl<KD: KeyDeps> Protocol for EPaxos<KD> {
         true
     }
 
+    fn message_fingerprint() -> u64 {
+        super::message_fingerprints::EPAXOS
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
+
+    fn state_sizes(&self) -> StateSizes {
+        let mut sizes = StateSizes::new();
+        sizes.insert("dots_in_flight", self.cmds.hot_len());
+        sizes.insert("dots_committed", self.cmds.cold_len());
+        sizes.insert("key_deps", self.key_deps.key_count());
+        sizes.insert("buffered_commits", self.buffered_commits.len());
+        sizes
+    }
 }
 
 impl<KD: KeyDeps> EPaxos<KD> {
@@ -202,6 +245,8 @@ impl<KD: KeyDeps> EPaxos<KD> {
 
         // compute its deps
         let deps = self.key_deps.add_cmd(dot, &cmd, None);
+        self.key_deps.take_lock_stats().record(&mut self.bp);
+        self.key_deps.record_commuted_deps(&mut self.bp);
 
         // create `MCollect` and target
         let mcollect = Message::MCollect {
@@ -238,8 +283,12 @@ impl<KD: KeyDeps> EPaxos<KD> {
             time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // discard message if no longer in START
         if info.status != Status::START {
@@ -272,7 +321,10 @@ impl<KD: KeyDeps> EPaxos<KD> {
             remote_deps
         } else {
             // otherwise, compute deps with the remote deps as past
-            self.key_deps.add_cmd(dot, &cmd, Some(remote_deps))
+            let deps = self.key_deps.add_cmd(dot, &cmd, Some(remote_deps));
+            self.key_deps.take_lock_stats().record(&mut self.bp);
+            self.key_deps.record_commuted_deps(&mut self.bp);
+            deps
         };
 
         // update command info
@@ -283,16 +335,19 @@ impl<KD: KeyDeps> EPaxos<KD> {
         let value = ConsensusValue::with(deps.clone());
         assert!(info.synod.set_if_not_accepted(|| value));
 
-        // create `MCollectAck` and target (only if not message from self)
+        // create `MCollectAck` and target (only if not message from self), and
+        // maybe coalesce it with other acks
         if !message_from_self {
             let mcollectack = Message::MCollectAck { dot, deps };
-            let target = singleton![from];
+            if let Some(mcollectack) = self.acks.coalesce(from, mcollectack) {
+                let target = singleton![from];
 
-            // save new action
-            self.to_processes.push(Action::ToSend {
-                target,
-                msg: mcollectack,
-            });
+                // save new action
+                self.to_processes.push(Action::ToSend {
+                    target,
+                    msg: mcollectack,
+                });
+            }
         }
     }
 
@@ -315,8 +370,12 @@ impl<KD: KeyDeps> EPaxos<KD> {
         // it can't be a ack from self (see the `MCollect` handler)
         assert_ne!(from, self.bp.process_id);
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // do nothing if we're no longer COLLECT
         if info.status != Status::COLLECT {
@@ -332,6 +391,11 @@ impl<KD: KeyDeps> EPaxos<KD> {
             // equal
             let (final_deps, all_equal) = info.quorum_deps.check_union();
 
+            // commands with dependencies beyond the dependency horizon take
+            // the slow path, even if all reported deps were equal
+            let all_equal = all_equal && !self.beyond_horizon(&final_deps);
+            let info = self.cmds.get(dot);
+
             // create consensus value
             let value = ConsensusValue::with(final_deps);
 
@@ -362,6 +426,25 @@ impl<KD: KeyDeps> EPaxos<KD> {
         }
     }
 
+    // Checks whether any of the dependencies is beyond the dependency horizon
+    // (if one is set), recording it in the metrics if so.
+    fn beyond_horizon(&mut self, deps: &HashSet<Dependency>) -> bool {
+        let horizon = match self.bp.config.dependency_horizon() {
+            Some(horizon) => horizon,
+            None => return false,
+        };
+        let beyond_horizon = deps
+            .iter()
+            .any(|dep| self.cmds.beyond_horizon(&dep.dot, horizon));
+        if beyond_horizon {
+            self.bp.aggregate_metric(
+                ProtocolMetricsKind::DependencyHorizonSlowPath,
+                1,
+            );
+        }
+        beyond_horizon
+    }
+
     fn handle_mcommit(
         &mut self,
         from: ProcessId,
@@ -377,8 +460,12 @@ impl<KD: KeyDeps> EPaxos<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         if info.status == Status::START {
             // save this notification just in case we've received the `MCollect`
@@ -392,6 +479,22 @@ impl<KD: KeyDeps> EPaxos<KD> {
             return;
         }
 
+        // check that all processes commit the same dependencies
+        #[cfg(feature = "strict-invariants")]
+        fantoch::sim::invariants::committed(
+            self.bp.process_id,
+            self.bp.shard_id,
+            dot,
+            &value.deps,
+        );
+
+        flow!(
+            process = self.bp.process_id,
+            dot = ?dot,
+            phase = "commit",
+            time = _time.micros()
+        );
+
         // check it's not a noop
         assert_eq!(
             value.is_noop, false,
@@ -416,6 +519,12 @@ impl<KD: KeyDeps> EPaxos<KD> {
             self.to_processes.push(Action::ToForward {
                 msg: Message::MCommitDot { dot },
             });
+            // the command info is only needed after the commit to repair the
+            // command; if anti-entropy is not running, keep only what's needed
+            // to garbage collect the dot
+            if self.bp.config.anti_entropy_interval().is_none() {
+                self.cmds.commit(dot);
+            }
         } else {
             // if we're not running gc, remove the dot info now
             self.cmds.gc_single(dot);
@@ -439,8 +548,12 @@ impl<KD: KeyDeps> EPaxos<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // compute message: that can either be nothing, an ack or an mcommit
         let msg = match info
@@ -486,8 +599,12 @@ impl<KD: KeyDeps> EPaxos<KD> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // compute message: that can either be nothing or an mcommit
         match info.synod.handle(from, SynodMessage::MAccepted(ballot)) {
@@ -569,15 +686,22 @@ impl<KD: KeyDeps> EPaxos<KD> {
         self.bp.stable(stable_count);
     }
 
-    fn handle_event_garbage_collection(&mut self, _time: &dyn SysTime) {
+    fn handle_event_garbage_collection(&mut self, time: &dyn SysTime) {
         trace!(
             "p{}: PeriodicEvent::GarbageCollection | time={}",
             self.id(),
-            _time.micros()
+            time.micros()
         );
 
-        // retrieve the committed clock
-        let committed = self.gc_track.clock().frontier();
+        // sample the stability lag
+        let lag = self.gc_track.stability_lag();
+        self.bp.stability_lag(lag, time);
+
+        // retrieve the entries of the committed clock that changed since the
+        // last garbage collection; this message is sent even if no entry
+        // changed, as receiving it is what triggers the computation of new
+        // stable dots
+        let committed = self.gc_track.committed_delta();
 
         // save new action
         self.to_processes.push(Action::ToSend {
@@ -586,6 +710,136 @@ impl<KD: KeyDeps> EPaxos<KD> {
         });
     }
 
+    fn handle_event_flush_acks(&mut self, _time: &dyn SysTime) {
+        trace!(
+            "p{}: PeriodicEvent::FlushAcks | time={}",
+            self.id(),
+            _time.micros()
+        );
+
+        for (target, mut acks) in self.acks.flush() {
+            // only create a batch if there's more than one ack
+            let msg = if acks.len() == 1 {
+                acks.pop().expect("there should be one ack")
+            } else {
+                Message::MCollectAckBatch { acks }
+            };
+            self.to_processes.push(Action::ToSend {
+                target: singleton![target],
+                msg,
+            });
+        }
+    }
+
+    fn handle_mrepair(
+        &mut self,
+        from: ProcessId,
+        dot: Dot,
+        targets: HashSet<ProcessId>,
+        _time: &dyn SysTime,
+    ) {
+        trace!(
+            "p{}: MRepair({:?}, {:?}) from {} | time={}",
+            self.id(),
+            dot,
+            targets,
+            from,
+            _time.micros()
+        );
+        assert_eq!(from, self.bp.process_id);
+
+        // if the command info no longer exists, then the command is stable
+        // and there's nothing to repair
+        let info = if let Some(info) = self.cmds.find(&dot) {
+            info
+        } else {
+            return;
+        };
+        assert_eq!(
+            info.status,
+            Status::COMMIT,
+            "only committed commands can be repaired"
+        );
+
+        // re-ship the command along with its committed value
+        let cmd = info.cmd.clone().expect("there should be a command payload");
+        let value = info.synod.value().clone();
+        self.bp.aggregate_metric(
+            ProtocolMetricsKind::Repairs,
+            targets.len() as u64,
+        );
+        self.to_processes.push(Action::ToSend {
+            target: targets,
+            msg: Message::MRepairCommit { dot, cmd, value },
+        });
+    }
+
+    fn handle_mrepair_commit(
+        &mut self,
+        from: ProcessId,
+        dot: Dot,
+        cmd: Command,
+        value: ConsensusValue,
+        time: &dyn SysTime,
+    ) {
+        trace!(
+            "p{}: MRepairCommit({:?}, {:?}, {:?}) from {} | time={}",
+            self.id(),
+            dot,
+            cmd,
+            value.deps,
+            from,
+            time.micros()
+        );
+
+        // ignore the repair if the command is already stable (in which case
+        // its info has been garbage collected)
+        if self.cmds.is_stable(&dot) {
+            return;
+        }
+
+        // if we missed the `MCollect`, save the payload now; this makes any
+        // buffered commit notification unnecessary
+        let info = self.cmds.get(dot);
+        if info.status == Status::START {
+            info.status = Status::PAYLOAD;
+            info.cmd = Some(cmd);
+            self.buffered_commits.remove(&dot);
+        }
+
+        // handle the commit (which is a noop if we have already committed)
+        self.handle_mcommit(from, dot, value, time);
+    }
+
+    fn handle_event_anti_entropy(&mut self, _time: &dyn SysTime) {
+        trace!(
+            "p{}: PeriodicEvent::AntiEntropy | time={}",
+            self.id(),
+            _time.micros()
+        );
+
+        // compute the processes missing each committed command
+        let mut missing = HashMap::new();
+        for (process_id, dots) in
+            self.gc_track.missing(ANTI_ENTROPY_MAX_REPAIRS)
+        {
+            for dot in dots {
+                missing
+                    .entry(dot)
+                    .or_insert_with(HashSet::new)
+                    .insert(process_id);
+            }
+        }
+
+        // forward each command to the worker responsible for it, which will
+        // then re-ship it
+        for (dot, targets) in missing {
+            self.to_processes.push(Action::ToForward {
+                msg: Message::MRepair { dot, targets },
+            });
+        }
+    }
+
     fn gc_running(&self) -> bool {
         self.bp.config.gc_interval().is_some()
     }
@@ -671,6 +925,9 @@ pub enum Message {
         dot: Dot,
         deps: HashSet<Dependency>,
     },
+    MCollectAckBatch {
+        acks: Vec<Message>,
+    },
     MCommit {
         dot: Dot,
         value: ConsensusValue,
@@ -693,6 +950,15 @@ pub enum Message {
     MStable {
         stable: Vec<(ProcessId, u64, u64)>,
     },
+    MRepair {
+        dot: Dot,
+        targets: HashSet<ProcessId>,
+    },
+    MRepairCommit {
+        dot: Dot,
+        cmd: Command,
+        value: ConsensusValue,
+    },
 }
 
 impl MessageIndex for Message {
@@ -704,6 +970,11 @@ impl MessageIndex for Message {
             // Protocol messages
             Self::MCollect { dot, .. } => worker_dot_index_shift(&dot),
             Self::MCollectAck { dot, .. } => worker_dot_index_shift(&dot),
+            // all acks in a batch were coalesced by the same worker, and thus
+            // they're all handled by the same worker
+            Self::MCollectAckBatch { acks } => {
+                acks.first().and_then(MessageIndex::index)
+            }
             Self::MCommit { dot, .. } => worker_dot_index_shift(&dot),
             Self::MConsensus { dot, .. } => worker_dot_index_shift(&dot),
             Self::MConsensusAck { dot, .. } => worker_dot_index_shift(&dot),
@@ -713,6 +984,21 @@ impl MessageIndex for Message {
                 worker_index_no_shift(GC_WORKER_INDEX)
             }
             Self::MStable { .. } => None,
+            // anti-entropy messages
+            Self::MRepair { dot, .. } => worker_dot_index_shift(&dot),
+            Self::MRepairCommit { dot, .. } => worker_dot_index_shift(&dot),
+        }
+    }
+}
+
+impl MessagePriority for Message {
+    fn high_priority(&self) -> bool {
+        use fantoch::protocol::high_priority_by_size;
+        match self {
+            // messages carrying command payloads are low priority
+            Self::MCollect { .. } | Self::MRepairCommit { .. } => false,
+            // all other messages are classified based on their size
+            _ => high_priority_by_size(self),
         }
     }
 }
@@ -720,13 +1006,21 @@ impl MessageIndex for Message {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodicEvent {
     GarbageCollection,
+    FlushAcks,
+    AntiEntropy,
 }
 
 impl MessageIndex for PeriodicEvent {
     fn index(&self) -> Option<(usize, usize)> {
         use fantoch::load_balance::{worker_index_no_shift, GC_WORKER_INDEX};
         match self {
-            Self::GarbageCollection => worker_index_no_shift(GC_WORKER_INDEX),
+            Self::GarbageCollection | Self::AntiEntropy => {
+                worker_index_no_shift(GC_WORKER_INDEX)
+            }
+            Self::FlushAcks => {
+                // should be sent to all workers
+                None
+            }
         }
     }
 }
@@ -838,6 +1132,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 100,
             pool_size: 1,
+            pool_id: 0,
         };
         let keys_per_command = 1;
         let commands_per_client = 10;
@@ -942,6 +1237,7 @@ mod tests {
             ready.pop().expect("there should an executor result");
         let cmd_result = pending
             .add_executor_result(executor_result)
+            .pop()
             .expect("there should be a command result");
 
         // handle the previous command result
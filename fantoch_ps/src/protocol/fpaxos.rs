@@ -4,7 +4,8 @@ use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, MessageIndex, Protocol, ProtocolMetrics,
+    Action, BaseProcess, MessageIndex, MessagePriority, Protocol,
+    ProtocolMetrics, StateSizes,
 };
 use fantoch::time::SysTime;
 use fantoch::{singleton, trace};
@@ -157,9 +158,20 @@ impl Protocol for FPaxos {
         false
     }
 
+    fn message_fingerprint() -> u64 {
+        super::message_fingerprints::FPAXOS
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
+
+    fn state_sizes(&self) -> StateSizes {
+        let mut sizes = StateSizes::new();
+        sizes.insert("commanders", self.multi_synod.commanders_len());
+        sizes.insert("accepted_slots", self.multi_synod.accepted_len());
+        sizes
+    }
 }
 
 impl FPaxos {
@@ -299,6 +311,8 @@ impl FPaxos {
         {
             match msg {
                 MultiSynodMessage::MChosen(slot, cmd) => {
+                    self.bp.committed();
+
                     // create `MChosen`
                     let mcommit = Message::MChosen { slot, cmd };
                     let target = self.bp.all();
@@ -360,13 +374,17 @@ impl FPaxos {
         self.bp.stable(stable_count);
     }
 
-    fn handle_event_garbage_collection(&mut self, _time: &dyn SysTime) {
+    fn handle_event_garbage_collection(&mut self, time: &dyn SysTime) {
         trace!(
             "p{}: PeriodicEvent::GarbageCollection | time={}",
             self.id(),
-            _time.micros()
+            time.micros()
         );
 
+        // sample the stability lag
+        let lag = self.gc_track.stability_lag();
+        self.bp.stability_lag(lag, time);
+
         // retrieve the committed slot
         let committed = self.gc_track.committed();
 
@@ -450,6 +468,20 @@ impl MessageIndex for Message {
     }
 }
 
+impl MessagePriority for Message {
+    fn high_priority(&self) -> bool {
+        use fantoch::protocol::high_priority_by_size;
+        match self {
+            // messages carrying command payloads are low priority
+            Self::MForwardSubmit { .. }
+            | Self::MAccept { .. }
+            | Self::MChosen { .. } => false,
+            // all other messages are classified based on their size
+            _ => high_priority_by_size(self),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodicEvent {
     GarbageCollection,
@@ -555,6 +587,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 100,
             pool_size: 1,
+            pool_id: 0,
         };
         let keys_per_command = 1;
         let commands_per_client = 10;
@@ -674,6 +707,7 @@ mod tests {
             ready.pop().expect("there should an executor result");
         let cmd_result = pending
             .add_executor_result(executor_result)
+            .pop()
             .expect("there should be a command result");
 
         // handle the previous command result
@@ -19,6 +19,12 @@ mod caesar;
 // This module contains common functionality for partial replication.
 mod partial;
 
+// This module contains the fingerprints of the protocol messages, generated
+// at build time (see `fantoch/build/message_fingerprint.rs`).
+mod message_fingerprints {
+    include!(concat!(env!("OUT_DIR"), "/message_fingerprints.rs"));
+}
+
 // Re-exports.
 pub use atlas::{AtlasLocked, AtlasSequential};
 pub use caesar::CaesarLocked;
@@ -31,14 +37,17 @@ mod tests {
     use super::*;
     use fantoch::client::{KeyGen, Workload};
     use fantoch::config::Config;
+    use fantoch::embed::Cluster;
     use fantoch::executor::ExecutionOrderMonitor;
     use fantoch::id::{ProcessId, Rifl};
-    use fantoch::kvs::Key;
+    use fantoch::kvs::{KVOp, Key};
     use fantoch::planet::Planet;
-    use fantoch::protocol::{Protocol, ProtocolMetrics, ProtocolMetricsKind};
+    use fantoch::protocol::{
+        Protocol, ProtocolMetrics, ProtocolMetricsKind, Shadow,
+    };
     use fantoch::run::tests::{run_test_with_inspect_fun, tokio_test_runtime};
     use fantoch::sim::Runner;
-    use fantoch::HashMap;
+    use fantoch::{HashMap, HashSet};
     use std::time::Duration;
 
     // global test config
@@ -47,6 +56,7 @@ mod tests {
     const KEY_GEN: KeyGen = KeyGen::ConflictPool {
         conflict_rate: 50,
         pool_size: 1,
+        pool_id: 0,
     };
     const CLIENTS_PER_PROCESS: usize = 10;
 
@@ -112,6 +122,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn message_fingerprints() {
+        let fingerprints = vec![
+            TempoSequential::message_fingerprint(),
+            AtlasSequential::message_fingerprint(),
+            EPaxosSequential::message_fingerprint(),
+            FPaxos::message_fingerprint(),
+            CaesarLocked::message_fingerprint(),
+            fantoch::protocol::Basic::message_fingerprint(),
+        ];
+        // protocols with different messages have different fingerprints
+        let distinct: HashSet<_> = fingerprints.iter().collect();
+        assert_eq!(distinct.len(), fingerprints.len());
+
+        // the fingerprint doesn't depend on how keys are tracked
+        assert_eq!(
+            TempoSequential::message_fingerprint(),
+            TempoLocked::message_fingerprint()
+        );
+        assert_eq!(
+            AtlasSequential::message_fingerprint(),
+            AtlasLocked::message_fingerprint()
+        );
+    }
+
     // ---- tempo tests ---- //
     #[test]
     fn sim_tempo_3_1_test() {
@@ -123,6 +158,42 @@ mod tests {
         assert_eq!(slow_paths, 0);
     }
 
+    #[test]
+    fn sim_tempo_3_1_commit_clock_bump_test() {
+        let mut config = tempo_config!(3, 1);
+        config.set_tempo_commit_clock_bump(true);
+        let slow_paths = sim_test::<TempoSequential>(
+            config,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(slow_paths, 0);
+    }
+
+    #[test]
+    fn sim_tempo_3_1_preload_keys_test() {
+        let mut config = tempo_config!(3, 1);
+        config.set_preload_keys(1000);
+        let slow_paths = sim_test::<TempoSequential>(
+            config,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(slow_paths, 0);
+    }
+
+    #[test]
+    fn sim_tempo_3_1_ack_coalescing_test() {
+        let mut config = tempo_config!(3, 1);
+        config.set_ack_coalescing_interval(Duration::from_millis(1));
+        let slow_paths = sim_test::<TempoSequential>(
+            config,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(slow_paths, 0);
+    }
+
     #[test]
     fn sim_real_time_tempo_3_1_test() {
         // NOTE: with n = 3 we don't really need real time clocks to get the
@@ -167,6 +238,20 @@ mod tests {
         assert_eq!(slow_paths, 0);
     }
 
+    #[test]
+    fn embedded_tempo_3_1_test() {
+        let mut config = tempo_config!(3, 1);
+        config.set_gc_interval(Duration::from_millis(100));
+        embedded_test::<TempoSequential>(config);
+    }
+
+    #[test]
+    fn embedded_epaxos_3_1_test() {
+        let mut config = Config::new(3, 1);
+        config.set_gc_interval(Duration::from_millis(100));
+        embedded_test::<EPaxosSequential>(config);
+    }
+
     #[test]
     fn run_tempo_3_1_atomic_test() {
         // tempo atomic can handle as many workers as we want but we may want to
@@ -281,6 +366,28 @@ mod tests {
         assert_eq!(slow_paths, 0);
     }
 
+    #[test]
+    fn run_tempo_3_1_atomic_partial_replication_two_shards_multi_shard_reads_test(
+    ) {
+        let mut config = tempo_config!(3, 1);
+        config.set_tempo_multi_shard_reads(true);
+        let shard_count = 2;
+        let workers = 2;
+        let executors = 2;
+        let read_only_percentage = 50;
+        let (commands_per_client, clients_per_process) = small_load_in_ci();
+        let slow_paths = run_test_with_reads::<TempoAtomic>(
+            config,
+            shard_count,
+            workers,
+            executors,
+            commands_per_client,
+            clients_per_process,
+            read_only_percentage,
+        );
+        assert_eq!(slow_paths, 0);
+    }
+
     #[test]
     fn run_tempo_5_2_atomic_partial_replication_two_shards_test() {
         let shard_count = 2;
@@ -309,6 +416,18 @@ mod tests {
         assert_eq!(slow_paths, 0);
     }
 
+    #[test]
+    fn sim_atlas_3_1_ack_coalescing_test() {
+        let mut config = config!(3, 1);
+        config.set_ack_coalescing_interval(Duration::from_millis(1));
+        let slow_paths = sim_test::<AtlasSequential>(
+            config,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(slow_paths, 0);
+    }
+
     #[test]
     fn sim_atlas_5_1_test() {
         let slow_paths = sim_test::<AtlasSequential>(
@@ -409,6 +528,18 @@ mod tests {
         assert_eq!(slow_paths, 0);
     }
 
+    #[test]
+    fn sim_epaxos_3_1_ack_coalescing_test() {
+        let mut config = config!(3, 1);
+        config.set_ack_coalescing_interval(Duration::from_millis(1));
+        let slow_paths = sim_test::<EPaxosSequential>(
+            config,
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(slow_paths, 0);
+    }
+
     #[test]
     fn sim_epaxos_5_2_test() {
         let slow_paths = sim_test::<EPaxosSequential>(
@@ -502,6 +633,18 @@ mod tests {
         );
     }
 
+    // ---- shadow tests ---- //
+    #[test]
+    fn sim_shadow_tempo_atlas_3_1_test() {
+        // run tempo with atlas as its shadow
+        let slow_paths = sim_test::<Shadow<TempoSequential, AtlasSequential>>(
+            tempo_config!(3, 1),
+            COMMANDS_PER_CLIENT,
+            CLIENTS_PER_PROCESS,
+        );
+        assert_eq!(slow_paths, 0);
+    }
+
     // ---- fpaxos tests ---- //
     #[test]
     fn sim_fpaxos_3_1_test() {
@@ -576,13 +719,66 @@ mod tests {
         (fast_paths, slow_paths, stable_count)
     }
 
+    fn embedded_test<P>(config: Config)
+    where
+        P: Protocol + Send + 'static,
+    {
+        tokio_test_runtime().block_on(async move {
+            let cluster = Cluster::<P>::start(config).expect("cluster starts");
+            let mut client_a = cluster.client();
+            let mut client_b = cluster.client();
+            assert_ne!(client_a.process_id(), client_b.process_id());
+
+            // a write by one client is read by the other (as commands are
+            // ordered by the protocol)
+            let key = String::from("A");
+            for value in vec!["x", "y"] {
+                let value = String::from(value);
+                let result = client_a
+                    .execute(key.clone(), KVOp::Put(value.clone()))
+                    .await
+                    .expect("put should succeed");
+                assert_eq!(result, None);
+                let result = client_b
+                    .execute(key.clone(), KVOp::Get)
+                    .await
+                    .expect("get should succeed");
+                assert_eq!(result, Some(value));
+            }
+        });
+    }
+
     fn run_test<P>(
+        config: Config,
+        shard_count: usize,
+        workers: usize,
+        executors: usize,
+        commands_per_client: usize,
+        clients_per_process: usize,
+    ) -> usize
+    where
+        P: Protocol + Send + 'static,
+    {
+        let read_only_percentage = 0;
+        run_test_with_reads::<P>(
+            config,
+            shard_count,
+            workers,
+            executors,
+            commands_per_client,
+            clients_per_process,
+            read_only_percentage,
+        )
+    }
+
+    fn run_test_with_reads<P>(
         mut config: Config,
         shard_count: usize,
         workers: usize,
         executors: usize,
         commands_per_client: usize,
         clients_per_process: usize,
+        read_only_percentage: usize,
     ) -> usize
     where
         P: Protocol + Send + 'static,
@@ -592,13 +788,14 @@ mod tests {
         // create workload
         let keys_per_command = 2;
         let payload_size = 1;
-        let workload = Workload::new(
+        let mut workload = Workload::new(
             shard_count,
             KEY_GEN,
             keys_per_command,
             commands_per_client,
             payload_size,
         );
+        workload.set_read_only_percentage(read_only_percentage);
 
         // run until the clients end + another 10 seconds
         let extra_run_time = Some(Duration::from_secs(10));
@@ -611,6 +808,7 @@ mod tests {
                 executors,
                 Some(metrics_inspect),
                 extra_run_time,
+                None,
             ))
             .expect("run should complete successfully")
             .into_iter()
@@ -9,12 +9,13 @@ use fantoch::command::Command;
 use fantoch::config::Config;
 use fantoch::id::{Dot, ProcessId, ShardId};
 use fantoch::protocol::{
-    Action, BaseProcess, Info, MessageIndex, Protocol, ProtocolMetrics,
-    SequentialCommandsInfo, VClockGCTrack,
+    AckCoalescer, Action, BaseProcess, Info, MessageIndex, MessagePriority,
+    Protocol, ProtocolMetrics, ProtocolMetricsKind, SequentialCommandsInfo,
+    StateSizes, VClockGCTrack,
 };
 use fantoch::time::SysTime;
 use fantoch::util;
-use fantoch::{singleton, trace};
+use fantoch::{flow, singleton, trace};
 use fantoch::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use std::mem;
@@ -31,6 +32,7 @@ pub struct Tempo<KC: KeyClocks> {
     key_clocks: KC,
     cmds: SequentialCommandsInfo<TempoInfo>,
     gc_track: VClockGCTrack,
+    acks: AckCoalescer<Message>,
     to_processes: Vec<Action<Self>>,
     to_executors: Vec<TableExecutionInfo>,
     // set of detached votes
@@ -47,6 +49,9 @@ pub struct Tempo<KC: KeyClocks> {
     // use this value as the minimum value to bump to
     max_commit_clock: u64,
     skip_fast_ack: bool,
+    // fast quorum used by single-key commands if per-command tiny quorums are
+    // enabled
+    tiny_fast_quorum: Option<HashSet<ProcessId>>,
 }
 
 impl<KC: KeyClocks> Protocol for Tempo<KC> {
@@ -72,7 +77,8 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
             fast_quorum_size,
             write_quorum_size,
         );
-        let key_clocks = KC::new(process_id, shard_id);
+        let mut key_clocks = KC::new(process_id, shard_id);
+        key_clocks.init_keys(util::preloaded_keys(&config, shard_id));
         let cmds = SequentialCommandsInfo::new(
             process_id,
             shard_id,
@@ -82,6 +88,7 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
             write_quorum_size,
         );
         let gc_track = VClockGCTrack::new(process_id, shard_id, config.n());
+        let acks = AckCoalescer::new(process_id, &config);
         let to_processes = Vec::new();
         let to_executors = Vec::new();
         let detached = Votes::new();
@@ -91,6 +98,8 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
         // enable skip fast ack if configured like that and the fast quorum size
         // is 2
         let skip_fast_ack = config.skip_fast_ack() && fast_quorum_size == 2;
+        // the tiny fast quorum is only known once processes are discovered
+        let tiny_fast_quorum = None;
 
         // create `Tempo`
         let protocol = Self {
@@ -98,6 +107,7 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
             key_clocks,
             cmds,
             gc_track,
+            acks,
             to_processes,
             to_executors,
             detached,
@@ -105,6 +115,7 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
             buffered_mbumps,
             max_commit_clock,
             skip_fast_ack,
+            tiny_fast_quorum,
         };
 
         // maybe create garbage collection periodic event
@@ -126,6 +137,12 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
             events.push((PeriodicEvent::SendDetached, interval));
         }
 
+        // maybe create flush acks periodic event
+        if let Some(interval) = config.ack_coalescing_interval() {
+            events.reserve_exact(1);
+            events.push((PeriodicEvent::FlushAcks, interval));
+        }
+
         // return both
         (protocol, events)
     }
@@ -146,6 +163,19 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
         &mut self,
         processes: Vec<(ProcessId, ShardId)>,
     ) -> (bool, HashMap<ShardId, ProcessId>) {
+        // if per-command tiny quorums are enabled, the tiny fast quorum is
+        // formed by the closest processes from my shard (as the regular fast
+        // quorum is larger, there are always enough processes if `connect_ok`)
+        if Self::per_command_tiny_quorums(&self.bp.config) {
+            let shard_id = self.bp.shard_id;
+            let tiny_fast_quorum = processes
+                .iter()
+                .filter(|(_, process_shard_id)| *process_shard_id == shard_id)
+                .map(|(process_id, _)| *process_id)
+                .take(self.bp.config.tempo_tiny_fast_quorum_size())
+                .collect();
+            self.tiny_fast_quorum = Some(tiny_fast_quorum);
+        }
         let connect_ok = self.bp.discover(processes);
         (connect_ok, self.bp.closest_shard_process().clone())
     }
@@ -185,6 +215,11 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
                 clock,
                 process_votes,
             } => self.handle_mcollectack(from, dot, clock, process_votes, time),
+            Message::MCollectAckBatch { acks } => {
+                for ack in acks {
+                    self.handle(from, from_shard_id, ack, time);
+                }
+            }
             Message::MCommit { dot, clock, votes } => {
                 self.handle_mcommit(from, dot, clock, votes, time)
             }
@@ -213,6 +248,9 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
             Message::MShardAggregatedCommit { dot, clock } => {
                 self.handle_mshard_aggregated_commit(dot, clock, time)
             }
+            Message::MReadRetry { dot, clock } => {
+                self.handle_mread_retry(dot, clock, time)
+            }
             // GC messages
             Message::MCommitDot { dot } => {
                 self.handle_mcommit_dot(from, dot, time)
@@ -236,6 +274,7 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
             PeriodicEvent::SendDetached => {
                 self.handle_event_send_detached(time)
             }
+            PeriodicEvent::FlushAcks => self.handle_event_flush_acks(time),
         }
     }
 
@@ -257,12 +296,49 @@ impl<KC: KeyClocks> Protocol for Tempo<KC> {
         true
     }
 
+    fn message_fingerprint() -> u64 {
+        super::message_fingerprints::TEMPO
+    }
+
     fn metrics(&self) -> &ProtocolMetrics {
         self.bp.metrics()
     }
+
+    fn state_sizes(&self) -> StateSizes {
+        let mut sizes = StateSizes::new();
+        sizes.insert("dots_in_flight", self.cmds.hot_len());
+        sizes.insert("dots_committed", self.cmds.cold_len());
+        sizes.insert("key_clocks", self.key_clocks.key_count());
+        sizes.insert("detached_votes", self.detached.len());
+        sizes.insert("buffered_mcommits", self.buffered_mcommits.len());
+        sizes.insert("buffered_mbumps", self.buffered_mbumps.len());
+        sizes
+    }
 }
 
 impl<KC: KeyClocks> Tempo<KC> {
+    /// Checks whether tiny quorums are chosen per command (which only happens
+    /// if tiny quorums are not used by all commands).
+    fn per_command_tiny_quorums(config: &Config) -> bool {
+        config.tempo_per_command_tiny_quorums() && !config.tempo_tiny_quorums()
+    }
+
+    /// Checks whether `cmd` uses a tiny quorum when per-command tiny quorums
+    /// are enabled: single-key commands use tiny quorums, while all other
+    /// commands use regular quorums.
+    fn tiny_quorum(config: &Config, cmd: &Command) -> bool {
+        Self::per_command_tiny_quorums(config) && cmd.total_key_count() == 1
+    }
+
+    /// Checks whether `cmd` is served by multi-shard reads, i.e. whether these
+    /// are enabled and `cmd` is a read-only command accessing more than one
+    /// shard.
+    fn multi_shard_read(config: &Config, cmd: &Command) -> bool {
+        config.tempo_multi_shard_reads()
+            && cmd.read_only()
+            && cmd.shard_count() > 1
+    }
+
     /// Handles a submit operation by a client.
     fn handle_submit(
         &mut self,
@@ -275,7 +351,7 @@ impl<KC: KeyClocks> Tempo<KC> {
 
         // record command size
         self.bp.collect_metric(
-            fantoch::protocol::ProtocolMetricsKind::CommandKeyCount,
+            ProtocolMetricsKind::CommandKeyCount,
             cmd.total_key_count() as u64,
         );
 
@@ -296,6 +372,9 @@ impl<KC: KeyClocks> Tempo<KC> {
         // - for that reason, we'll store these votes locally and not recompute
         //   them once we receive the `MCollect` from self
         let (clock, process_votes) = self.key_clocks.proposal(&cmd, 0);
+        // record the key-lock acquisitions since the last proposal (which
+        // include the ones when voting)
+        self.key_clocks.take_lock_stats().record(&mut self.bp);
         trace!(
             "p{}: bump_and_vote: {:?} | clock: {} | votes: {:?}",
             self.id(),
@@ -320,6 +399,15 @@ impl<KC: KeyClocks> Tempo<KC> {
             Votes::new()
         };
 
+        // choose the fast quorum of the command
+        let quorum = if Self::tiny_quorum(&self.bp.config, &cmd) {
+            self.tiny_fast_quorum
+                .clone()
+                .expect("the tiny fast quorum should be known")
+        } else {
+            self.bp.fast_quorum()
+        };
+
         // create `MCollect` and target
         // TODO maybe just don't send to self with `self.bp.all_but_me()`
         let mcollect = Message::MCollect {
@@ -327,7 +415,7 @@ impl<KC: KeyClocks> Tempo<KC> {
             cmd,
             clock,
             coordinator_votes,
-            quorum: self.bp.fast_quorum(),
+            quorum,
         };
         let target = self.bp.all();
 
@@ -360,8 +448,12 @@ impl<KC: KeyClocks> Tempo<KC> {
             time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // discard message if no longer in START
         if info.status != Status::START {
@@ -406,6 +498,7 @@ impl<KC: KeyClocks> Tempo<KC> {
             // minimum value
             let (clock, process_votes) =
                 self.key_clocks.proposal(&cmd, remote_clock);
+            self.key_clocks.take_lock_stats().record(&mut self.bp);
             trace!(
                 "p{}: bump_and_vote: {:?} | clock: {} | votes: {:?}",
                 self.bp.process_id,
@@ -430,6 +523,12 @@ impl<KC: KeyClocks> Tempo<KC> {
         // get shard count
         let shard_count = cmd.shard_count();
 
+        // with per-command tiny quorums, the coordinator should wait for as
+        // many clocks as the size of the fast quorum chosen for the command
+        if message_from_self && Self::tiny_quorum(&self.bp.config, &cmd) {
+            info.quorum_clocks = QuorumClocks::new(quorum.len());
+        }
+
         // update command info
         info.status = Status::COLLECT;
         info.cmd = Some(cmd);
@@ -476,8 +575,12 @@ impl<KC: KeyClocks> Tempo<KC> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         if info.status != Status::COLLECT {
             // do nothing if we're no longer COLLECT
@@ -517,8 +620,20 @@ impl<KC: KeyClocks> Tempo<KC> {
         if info.quorum_clocks.all() {
             // fast path condition:
             // - if `max_clock` was reported by at least f processes
-            if max_count >= self.bp.config.f() {
+            // - or if the command is a multi-shard read: since reads don't
+            //   change the state, there's no need to make sure that a clock
+            //   can be recovered, and so the clock snapshot is simply reported
+            //   to the process aggregating the snapshots from all shards
+            let tiny_quorum = Self::tiny_quorum(&self.bp.config, cmd);
+            let multi_shard_read = Self::multi_shard_read(&self.bp.config, cmd);
+            if max_count >= self.bp.config.f() || multi_shard_read {
                 self.bp.fast_path();
+                if tiny_quorum {
+                    self.bp.aggregate_metric(
+                        ProtocolMetricsKind::TinyQuorumFastPath,
+                        1,
+                    );
+                }
                 // reset local votes as we're going to receive them right away;
                 // this also prevents a `info.votes.clone()`
                 let votes = Self::reset_votes(&mut info.votes);
@@ -536,6 +651,12 @@ impl<KC: KeyClocks> Tempo<KC> {
                 )
             } else {
                 self.bp.slow_path();
+                if tiny_quorum {
+                    self.bp.aggregate_metric(
+                        ProtocolMetricsKind::TinyQuorumSlowPath,
+                        1,
+                    );
+                }
                 // slow path: create `MConsensus`
                 let ballot = info.synod.skip_prepare();
                 let mconsensus = Message::MConsensus {
@@ -571,8 +692,12 @@ impl<KC: KeyClocks> Tempo<KC> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         if info.status == Status::START {
             // save this notification just in case we've received the `MCollect`
@@ -586,12 +711,29 @@ impl<KC: KeyClocks> Tempo<KC> {
             return;
         }
 
+        // check that all processes commit the same clock
+        #[cfg(feature = "strict-invariants")]
+        fantoch::sim::invariants::committed(
+            self.bp.process_id,
+            self.bp.shard_id,
+            dot,
+            &clock,
+        );
+
+        flow!(
+            process = self.bp.process_id,
+            dot = ?dot,
+            phase = "commit",
+            time = _time.micros()
+        );
+
         // create execution info
         let cmd = info
             .cmd
             .as_ref()
             .expect("there should be a command payload");
         let rifl = cmd.rifl();
+        let timestamp = cmd.timestamp();
         let execution_info = cmd.iter(self.bp.shard_id).map(|(key, ops)| {
             // find votes on this key
             let key_votes = votes.remove(&key).unwrap_or_default();
@@ -611,6 +753,7 @@ impl<KC: KeyClocks> Tempo<KC> {
                 rifl,
                 shard_to_keys,
                 ops.clone(),
+                timestamp,
                 key_votes,
             )
         });
@@ -631,6 +774,17 @@ impl<KC: KeyClocks> Tempo<KC> {
             self.to_processes.push(Action::ToForward {
                 msg: Message::MCommitClock { clock },
             });
+        } else if self.bp.config.tempo_commit_clock_bump()
+            && clock > self.max_commit_clock
+        {
+            // if this is the highest commit clock so far, generate detached
+            // votes for all keys (and not only the ones accessed by this
+            // command); this prevents keys that have not been accessed
+            // recently from later proposing clocks lower than `clock`
+            self.max_commit_clock = clock;
+            self.key_clocks.detached_all(clock, &mut self.detached);
+            self.bp
+                .aggregate_metric(ProtocolMetricsKind::CommitClockBump, 1);
         } else {
             // try to generate detached votes
             self.key_clocks.detached(cmd, clock, &mut self.detached);
@@ -647,6 +801,8 @@ impl<KC: KeyClocks> Tempo<KC> {
             self.to_processes.push(Action::ToForward {
                 msg: Message::MCommitDot { dot },
             });
+            // keep only what's needed to garbage collect the dot
+            self.cmds.commit(dot);
         } else {
             // not running gc, so remove the dot info now
             self.cmds.gc_single(dot);
@@ -680,8 +836,12 @@ impl<KC: KeyClocks> Tempo<KC> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // maybe bump up to `clock`
         if let Some(cmd) = info.cmd.as_ref() {
@@ -732,8 +892,12 @@ impl<KC: KeyClocks> Tempo<KC> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // maybe bump up to `clock`
         if let Some(cmd) = info.cmd.as_ref() {
@@ -787,8 +951,12 @@ impl<KC: KeyClocks> Tempo<KC> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         // compute message: that can either be nothing or an mcommit
         match info.synod.handle(from, SynodMessage::MAccepted(ballot)) {
@@ -828,10 +996,27 @@ impl<KC: KeyClocks> Tempo<KC> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
+
+        let cmd = info.cmd.as_ref().unwrap();
+        let shard_count = cmd.shard_count();
+        if Self::multi_shard_read(&self.bp.config, cmd) {
+            return Self::read_snapshot_actions(
+                &mut self.bp,
+                info,
+                shard_count,
+                from,
+                dot,
+                clock,
+                &mut self.to_processes,
+            );
+        }
 
-        let shard_count = info.cmd.as_ref().unwrap().shard_count();
         let add_shards_commits_info =
             |shards_commit_info: &mut ShardsCommitsInfo, clock| {
                 shards_commit_info.add(clock)
@@ -871,8 +1056,12 @@ impl<KC: KeyClocks> Tempo<KC> {
             _time.micros()
         );
 
-        // get cmd info
-        let info = self.cmds.get(dot);
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
 
         let extract_mcommit_extra_data =
             |shards_commit_info: ShardsCommitsInfo| {
@@ -894,6 +1083,51 @@ impl<KC: KeyClocks> Tempo<KC> {
         )
     }
 
+    fn handle_mread_retry(
+        &mut self,
+        dot: Dot,
+        clock: u64,
+        _time: &dyn SysTime,
+    ) {
+        trace!(
+            "p{}: MReadRetry({:?}, {}) | time={}",
+            self.id(),
+            dot,
+            clock,
+            _time.micros()
+        );
+
+        // get cmd info, dropping the message if the command is no longer
+        // in-flight
+        let info = match self.cmds.get_in_flight(dot) {
+            Some(info) => info,
+            None => return self.bp.stale_message(),
+        };
+
+        // vote up to the clock of the read on the keys of my shard; these
+        // votes are attached to the ones collected for the read, and thus
+        // they'll be sent in its `MCommit`
+        let cmd = info.cmd.as_ref().unwrap();
+        let mut votes = Votes::new();
+        self.key_clocks.detached(cmd, clock, &mut votes);
+        info.shards_commits
+            .as_mut()
+            .expect("shards commit info should be set for multi-shard reads")
+            .update(|shards_commits_info| {
+                shards_commits_info
+                    .votes
+                    .as_mut()
+                    .expect("votes in shard commit info should be set")
+                    .merge(votes)
+            });
+
+        // report the new clock snapshot
+        self.to_processes.push(Action::ToSend {
+            target: singleton![dot.source()],
+            msg: Message::MShardCommit { dot, clock },
+        });
+    }
+
     fn handle_mcommit_dot(
         &mut self,
         from: ProcessId,
@@ -952,15 +1186,22 @@ impl<KC: KeyClocks> Tempo<KC> {
         self.bp.stable(stable_count);
     }
 
-    fn handle_event_garbage_collection(&mut self, _time: &dyn SysTime) {
+    fn handle_event_garbage_collection(&mut self, time: &dyn SysTime) {
         trace!(
             "p{}: PeriodicEvent::GarbageCollection | time={}",
             self.id(),
-            _time.micros()
+            time.micros()
         );
 
-        // retrieve the committed clock
-        let committed = self.gc_track.clock().frontier();
+        // sample the stability lag
+        let lag = self.gc_track.stability_lag();
+        self.bp.stability_lag(lag, time);
+
+        // retrieve the entries of the committed clock that changed since the
+        // last garbage collection; this message is sent even if no entry
+        // changed, as receiving it is what triggers the computation of new
+        // stable dots
+        let committed = self.gc_track.committed_delta();
 
         // save new action
         self.to_processes.push(Action::ToSend {
@@ -1018,17 +1259,19 @@ impl<KC: KeyClocks> Tempo<KC> {
         process_votes: Votes,
         shard_count: usize,
     ) {
-        // create `MCollectAck`
+        // create `MCollectAck` and maybe coalesce it with other acks
         let mcollectack = Message::MCollectAck {
             dot,
             clock,
             process_votes,
         };
-        let target = singleton![from];
-        self.to_processes.push(Action::ToSend {
-            msg: mcollectack,
-            target,
-        });
+        if let Some(mcollectack) = self.acks.coalesce(from, mcollectack) {
+            let target = singleton![from];
+            self.to_processes.push(Action::ToSend {
+                msg: mcollectack,
+                target,
+            });
+        }
 
         if shard_count > 1 {
             // get cmd info
@@ -1080,12 +1323,96 @@ impl<KC: KeyClocks> Tempo<KC> {
         )
     }
 
+    // Records the clock snapshot of a multi-shard read reported by the shard
+    // of `from`. Once all shards have reported their snapshot:
+    // - if they're all the same, the read is committed with that clock
+    // - otherwise, the shards that reported a lower snapshot are asked to take
+    //   it again at the highest one; this makes them vote up to the clock of
+    //   the read, which is needed for it to become stable
+    fn read_snapshot_actions(
+        bp: &mut BaseProcess,
+        info: &mut TempoInfo,
+        shard_count: usize,
+        from: ProcessId,
+        dot: Dot,
+        clock: u64,
+        to_processes: &mut Vec<Action<Self>>,
+    ) {
+        info.read_snapshots.insert(from, clock);
+        if info.read_snapshots.len() < shard_count {
+            // not all shards have reported their snapshot yet
+            return;
+        }
+
+        let max_clock = info
+            .read_snapshots
+            .values()
+            .copied()
+            .max()
+            .expect("there should be a snapshot from each shard");
+        let behind: HashSet<_> = info
+            .read_snapshots
+            .iter()
+            .filter(|(_, clock)| **clock < max_clock)
+            .map(|(process_id, _)| *process_id)
+            .collect();
+
+        if behind.is_empty() {
+            bp.aggregate_metric(ProtocolMetricsKind::MultiShardReads, 1);
+            let target = info
+                .read_snapshots
+                .drain()
+                .map(|(process_id, _)| process_id)
+                .collect();
+            to_processes.push(Action::ToSend {
+                target,
+                msg: Message::MShardAggregatedCommit {
+                    dot,
+                    clock: max_clock,
+                },
+            });
+        } else {
+            bp.aggregate_metric(
+                ProtocolMetricsKind::MultiShardReadRetries,
+                behind.len() as u64,
+            );
+            to_processes.push(Action::ToSend {
+                target: behind,
+                msg: Message::MReadRetry {
+                    dot,
+                    clock: max_clock,
+                },
+            });
+        }
+    }
+
     // Replaces the value `local_votes` with empty votes, returning the previous
     // votes.
     fn reset_votes(local_votes: &mut Votes) -> Votes {
         mem::take(local_votes)
     }
 
+    fn handle_event_flush_acks(&mut self, _time: &dyn SysTime) {
+        trace!(
+            "p{}: PeriodicEvent::FlushAcks | time={}",
+            self.id(),
+            _time.micros()
+        );
+
+        for (target, mut acks) in self.acks.flush() {
+            // only create a batch if there's more than one ack
+            let msg = if acks.len() == 1 {
+                acks.pop().expect("there should be one ack")
+            } else {
+                Message::MCollectAckBatch { acks }
+            };
+            self.to_processes.push(Action::ToSend {
+                target: singleton![target],
+                msg,
+            });
+        }
+    }
+
     fn gc_running(&self) -> bool {
         self.bp.config.gc_interval().is_some()
     }
@@ -1116,6 +1443,9 @@ struct TempoInfo {
     quorum_clocks: QuorumClocks,
     // `shard_commits` is only used when commands accessed more than one shard
     shards_commits: Option<ShardsCommits<ShardsCommitsInfo>>,
+    // `read_snapshots` is used by the coordinator of multi-shard reads to
+    // track the clock snapshot reported by each shard
+    read_snapshots: HashMap<ProcessId, u64>,
 }
 
 impl Info for TempoInfo {
@@ -1136,6 +1466,7 @@ impl Info for TempoInfo {
             votes: Votes::new(),
             quorum_clocks: QuorumClocks::new(fast_quorum_size),
             shards_commits: None,
+            read_snapshots: HashMap::new(),
         }
     }
 }
@@ -1172,6 +1503,9 @@ pub enum Message {
         clock: u64,
         process_votes: Votes,
     },
+    MCollectAckBatch {
+        acks: Vec<Message>,
+    },
     MCommit {
         dot: Dot,
         clock: u64,
@@ -1209,6 +1543,10 @@ pub enum Message {
         dot: Dot,
         clock: u64,
     },
+    MReadRetry {
+        dot: Dot,
+        clock: u64,
+    },
     // GC messages
     MCommitDot {
         dot: Dot,
@@ -1236,6 +1574,11 @@ impl MessageIndex for Message {
             // Protocol messages
             Self::MCollect { dot, .. } => worker_dot_index_shift(&dot),
             Self::MCollectAck { dot, .. } => worker_dot_index_shift(&dot),
+            // all acks in a batch were coalesced by the same worker, and thus
+            // they're all handled by the same worker
+            Self::MCollectAckBatch { acks } => {
+                acks.first().and_then(MessageIndex::index)
+            }
             Self::MCommit { dot, .. } => worker_dot_index_shift(&dot),
             Self::MCommitClock { .. } => {
                 worker_index_no_shift(CLOCK_BUMP_WORKER_INDEX)
@@ -1252,6 +1595,7 @@ impl MessageIndex for Message {
             Self::MShardAggregatedCommit { dot, .. } => {
                 worker_dot_index_shift(&dot)
             }
+            Self::MReadRetry { dot, .. } => worker_dot_index_shift(&dot),
             // GC messages
             Self::MCommitDot { .. } => worker_index_no_shift(GC_WORKER_INDEX),
             Self::MGarbageCollection { .. } => {
@@ -1262,11 +1606,24 @@ impl MessageIndex for Message {
     }
 }
 
+impl MessagePriority for Message {
+    fn high_priority(&self) -> bool {
+        use fantoch::protocol::high_priority_by_size;
+        match self {
+            // messages carrying command payloads are low priority
+            Self::MCollect { .. } | Self::MForwardSubmit { .. } => false,
+            // all other messages are classified based on their size
+            _ => high_priority_by_size(self),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodicEvent {
     GarbageCollection,
     ClockBump,
     SendDetached,
+    FlushAcks,
 }
 
 impl MessageIndex for PeriodicEvent {
@@ -1277,7 +1634,7 @@ impl MessageIndex for PeriodicEvent {
         match self {
             Self::GarbageCollection => worker_index_no_shift(GC_WORKER_INDEX),
             Self::ClockBump => worker_index_no_shift(CLOCK_BUMP_WORKER_INDEX),
-            Self::SendDetached => {
+            Self::SendDetached | Self::FlushAcks => {
                 // should be sent to all workers
                 None
             }
@@ -1299,6 +1656,8 @@ mod tests {
     use super::*;
     use fantoch::client::{Client, KeyGen, Workload};
     use fantoch::executor::Executor;
+    use fantoch::id::Rifl;
+    use fantoch::kvs::KVOp;
     use fantoch::planet::{Planet, Region};
     use fantoch::sim::Simulation;
     use fantoch::time::SimTime;
@@ -1313,6 +1672,50 @@ mod tests {
         tempo_flow::<AtomicKeyClocks>();
     }
 
+    #[test]
+    fn per_command_tiny_quorums() {
+        // n and f
+        let n = 5;
+        let f = 1;
+        let mut config = Config::new(n, f);
+        config.set_tempo_per_command_tiny_quorums(true);
+
+        // tempo 1, with processes sorted by distance
+        let process_id = 1;
+        let shard_id = 0;
+        let (mut tempo, _) = TempoSequential::new(process_id, shard_id, config);
+        let processes = (1..=n as ProcessId)
+            .map(|process_id| (process_id, shard_id))
+            .collect();
+        let (connect_ok, _) = tempo.discover(processes);
+        assert!(connect_ok);
+
+        // returns the fast quorum used by `cmd`
+        let mut submit = |cmd: Command| {
+            tempo.submit(None, cmd, &SimTime::new());
+            let mut actions: Vec<_> = tempo.to_processes_iter().collect();
+            assert_eq!(actions.len(), 1);
+            match actions.pop().unwrap() {
+                Action::ToSend {
+                    msg: Message::MCollect { quorum, .. },
+                    ..
+                } => quorum,
+                action => panic!("unexpected action: {:?}", action),
+            }
+        };
+
+        // single-key commands use a tiny quorum (of size 2f)
+        let put = |key: &str| (key.to_string(), KVOp::Put(String::new()));
+        let cmd = Command::from(Rifl::new(1, 1), vec![put("A")]);
+        let expected: HashSet<_> = vec![1, 2].into_iter().collect();
+        assert_eq!(submit(cmd), expected);
+
+        // all other commands use a regular quorum (of size n/2 + f)
+        let cmd = Command::from(Rifl::new(1, 2), vec![put("A"), put("B")]);
+        let expected: HashSet<_> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(submit(cmd), expected);
+    }
+
     fn tempo_flow<KC: KeyClocks>() {
         // create simulation
         let mut simulation = Simulation::new();
@@ -1394,6 +1797,7 @@ mod tests {
         let key_gen = KeyGen::ConflictPool {
             conflict_rate: 100,
             pool_size: 1,
+            pool_id: 0,
         };
         let keys_per_command = 1;
         let commands_per_client = 10;
@@ -1507,6 +1911,7 @@ mod tests {
             ready.pop().expect("there should an executor result");
         let cmd_result = pending
             .add_executor_result(executor_result)
+            .pop()
             .expect("there should be a command result");
 
         // handle the previous command result
@@ -21,6 +21,18 @@ file() {
     echo "log-${zone}.dat"
 }
 
+# computes the p50, p95 and p99 of all RTTs in a ping log
+percentiles() {
+    local file=$1
+    grep -Eo "time=[0-9\.]+" ${file} | cut -d= -f2 | sort -n |
+        awk '{ rtts[NR] = $1 } END {
+            p50 = rtts[int((NR - 1) * 0.50) + 1]
+            p95 = rtts[int((NR - 1) * 0.95) + 1]
+            p99 = rtts[int((NR - 1) * 0.99) + 1]
+            printf "%s/%s/%s", p50, p95, p99
+        }'
+}
+
 rm -f *.dat
 
 for host in $(cat ${HOSTS}); do
@@ -35,5 +47,6 @@ for host in $(cat ${HOSTS}); do
     ZONE=$(zone ${host})
     FILE=$(file ${host})
     STATS=$(grep min/avg/max ${FILE} | grep -Eo "([0-9\.]+/?){4}")
-    echo ${STATS}:${ZONE}
+    PERCENTILES=$(percentiles ${FILE})
+    echo ${STATS}/${PERCENTILES}:${ZONE}
 done | sort -n >${OUTPUT}